@@ -226,6 +226,19 @@ fn main() -> Result<()> {
                     eprintln!("Please provide a URL for simple rendering test.");
                 }
             }
+            "render_png" => {
+                if args.len() == 4 {
+                    let url = &args[2];
+                    let out = &args[3];
+                    println!("Rendering {} to {}", url, out);
+
+                    let mut browser = BrowserApp::default();
+                    let png = browser.render_page_to_png(url.parse()?, (800, 600))?;
+                    std::fs::write(out, png)?;
+                } else {
+                    eprintln!("Please provide a URL and an output path for headless rendering.");
+                }
+            }
             _ => {
                 eprintln!("Unknown argument: {}", args[1]);
                 let commands: Vec<&str> = get_commands().keys().copied().collect();
@@ -313,6 +326,14 @@ fn get_commands<'a>() -> HashMap<&'a str, (&'a str, &'a str, &'a str)> {
             "",
         ),
     );
+    map.insert(
+        "render_png",
+        (
+            "Render a URL headlessly (without a window) and write the result as a PNG.",
+            "URL OUT",
+            "The page is laid out at 800x600. No window is opened, so this works in CI.",
+        ),
+    );
 
     map
 }
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use orinium_browser::engine::fuzz::fuzz_parse_html;
+
+fuzz_target!(|data: &[u8]| {
+    fuzz_parse_html(data);
+});
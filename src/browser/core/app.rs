@@ -1,20 +1,131 @@
 use anyhow::Result;
-use std::collections::{HashMap, hash_map::DefaultHasher};
+use image::RgbaImage;
+use std::collections::{HashMap, HashSet, hash_map::DefaultHasher};
 use std::env;
 use std::hash::{Hash, Hasher};
+use std::io::Cursor;
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::sync::{Arc, mpsc};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use url::Url;
-use winit::event::WindowEvent;
+use winit::event::{ElementState, Ime, Touch, TouchPhase, WindowEvent};
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+use winit::window::ResizeDirection;
 
-use super::tab::{FetchKind, Tab, TabTask};
+use super::autofill::{AUTOFILL_FILE_NAME, AutofillField, AutofillStore};
+use super::browsing_history::{BrowsingHistory, HISTORY_FILE_NAME};
+use super::cdp::{CdpServer, RemoteTarget, TargetInfo};
+use super::content_blocking::{
+    CONTENT_BLOCKING_EXCEPTIONS_FILE_NAME, ContentBlocker, FILTER_LISTS_DIR_NAME,
+};
+use super::csp::ContentSecurityPolicy;
+use super::devtools::{Console, StyleInspection};
+use super::downloads::{DOWNLOADS_FILE_NAME, DownloadManager};
+use super::extensions::{
+    EXTENSIONS_DIR_NAME, Extension, ExtensionRegistry, InterceptedRequest, RequestAction,
+    ResourceType,
+};
+use super::fetch_policy::{self, Initiator, PolicyError, RequestMode};
+use super::frame_scheduler::{FrameScheduler, Invalidation};
+use super::internal_pages::{self, InternalPageContext};
+use super::mime::{self, Presentation};
+use super::passwords::{
+    Credential, PASSWORD_EXCEPTIONS_FILE_NAME, PASSWORD_FILE_NAME, PasswordManager, SubmittedLogin,
+};
+use super::permissions::{
+    PERMISSIONS_FILE_NAME, Permission, PermissionRequest, PermissionState, PermissionStore,
+};
+use super::reader::ReaderTheme;
+use super::scheduler::Scheduler;
+use super::session::{SESSION_FILE_NAME, Session};
+use super::tab::{FetchKind, NewTabLink, Tab, TabTask};
+use super::ui::{
+    ChromeTheme, ContextMenu, MenuItem, SearchEngine, Suggestion, TAB_STRIP_HEIGHT, TabStripHit,
+    TabStripItem, URL_BAR_HEIGHT, UrlBar, WindowControl, inspect_overlay, progress_bar, tab_strip,
+    url_bar, window_controls,
+};
+use super::visited_links::{SharedVisitedLinks, VisitedLinks};
 // use super::ui::init_browser_ui;
-use super::{BrowserCommand, resource_loader::BrowserResourceLoader};
-use crate::engine::layouter;
-use crate::engine::renderer_model::{self, DrawCommand};
-use crate::platform::network::NetworkCore;
-use crate::platform::renderer::gpu::GpuRenderer;
-use crate::system::App;
+use super::{
+    BrowserCommand, BrowserEvent, CommandSender, EventSink, WindowAction,
+    resource_loader::{BrowserNetworkError, BrowserResourceLoader},
+};
+use crate::browser::settings::Settings;
+use crate::engine::accessibility::{AccessAction, AccessTree};
+use crate::engine::bridge::text::{FallbackTextMeasurer, TextMeasurer};
+use crate::engine::css::media::ColorScheme;
+use crate::engine::html::HtmlNodeType;
+use crate::engine::html::preload::{PreloadDestination, ResourceHint};
+use crate::engine::input::gesture::{Gesture, TouchTracker};
+use crate::engine::input::spellcheck::SpellChecker;
+use crate::engine::input::text_edit::TextEdit;
+use crate::engine::layouter::{self, dump::LayoutDump, types::TextStyle};
+use crate::engine::renderer_model::{
+    self, DrawCommand,
+    paginate::{self, PaperSize},
+};
+use crate::engine::script::storage::{SharedStorage, WebStorage};
+use crate::engine::script::{FetchResponse, ScriptValue, TimerRequest};
+use crate::engine::tree::NodeRef;
+use crate::platform::clipboard;
+use crate::platform::decode::{DecodePool, Decoded};
+use crate::platform::geolocation::{self, Geolocation, LocationProvider, PositionError};
+use crate::platform::io;
+use crate::platform::keychain::{self, MemorySecretStore};
+use crate::platform::logging;
+use crate::platform::memory::{self, MemoryPressure, MemoryUsage, Subsystem, TrackedMemory};
+use crate::platform::network::{ConnectionHint, NetworkConfig, NetworkCore, StoragePartition};
+use crate::platform::notifications::{
+    self, NotificationBackend, NotificationCenter, NotificationCommand, NotificationEvent,
+};
+use crate::platform::renderer::compositor::Compositor;
+use crate::platform::renderer::headless;
+use crate::platform::renderer::pdf;
+use crate::platform::renderer::scroll_bar::{ScrollBar, ScrollBarFade};
+use crate::platform::renderer::text_measurer::PlatformTextMeasurer;
+use crate::system::{App, ThreadedApp};
+
+/// Maximum time to wait for a page to finish loading in headless rendering.
+const HEADLESS_LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Distance scrolled by the arrow keys, in CSS pixels.
+const LINE_SCROLL_AMOUNT: f32 = 40.0;
+
+/// Fraction of the viewport height scrolled by Page Up / Page Down.
+const PAGE_SCROLL_RATIO: f32 = 0.875;
+
+/// How often the open tabs are saved while browsing, so a crash loses little.
+const SESSION_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// File in the profile directory that holds `localStorage`.
+const LOCAL_STORAGE_FILE_NAME: &str = "local_storage";
+
+/// File in the profile directory that holds cookies with an expiry date.
+const COOKIE_FILE_NAME: &str = "cookies";
+
+/// File in the profile directory that holds the hosts that must use HTTPS.
+const HSTS_FILE_NAME: &str = "hsts";
+
+/// Directory in the profile directory that holds the HTTP disk cache.
+const CACHE_DIR_NAME: &str = "cache";
+
+/// Maximum number of history suggestions shown below the URL bar.
+const MAX_URL_SUGGESTIONS: usize = 6;
+
+/// How often the settings file is checked for changes made outside the browser.
+const SETTINGS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Timers of background tabs run together at most once per this interval.
+pub const BACKGROUND_TIMER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long a tab stays in the background before its layout is discarded.
+pub const BACKGROUND_DISCARD_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// How often memory usage is checked against the budget and the system's memory pressure.
+const MEMORY_CHECK_INTERVAL: Duration = Duration::from_secs(2);
 
 /// Stores rendering-related state for the browser window.
 pub struct RenderState {
@@ -24,6 +135,18 @@ pub struct RenderState {
     pub window_size: (u32, u32),
     /// Current scale factor (for HiDPI displays).
     pub scale_factor: f64,
+    /// Whether a smooth-scroll animation is still converging.
+    pub animating: bool,
+    /// Appearance of the page scrollbars.
+    pub scroll_bar: ScrollBar,
+    /// Fade in/out state of the overlay scrollbars.
+    pub scroll_bar_fade: ScrollBarFade,
+    /// Page scroll offset at the previous frame, used to detect scroll activity.
+    pub last_page_scroll: Option<(f32, f32)>,
+    /// Whether the browser UI (URL bar) is drawn above the page.
+    pub chrome_visible: bool,
+    /// Whether the renderer draws the FPS and frame time overlay (Ctrl+Shift+F).
+    pub show_frame_stats: bool,
 }
 
 /// Stores input-related state for the browser window.
@@ -31,6 +154,141 @@ pub struct RenderState {
 pub struct InputState {
     /// Current mouse position in window coordinates.
     pub mouse_position: (f64, f64),
+    /// Currently held modifier keys.
+    pub modifiers: ModifiersState,
+    /// Whether the left mouse button is held down (text selection drag).
+    pub mouse_pressed: bool,
+    /// Active touches and kinetic scrolling state.
+    pub touch: TouchTracker,
+    /// Scrollbar thumb being dragged and the last mouse position along its axis.
+    pub scroll_bar_drag: Option<(ScrollAxis, f32)>,
+}
+
+/// Direction of a scrollbar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollAxis {
+    Vertical,
+    Horizontal,
+}
+
+/// Work queued on the browser's [`Scheduler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Task {
+    /// A `setTimeout` / `setInterval` callback of the document `document`.
+    ScriptTimer { document: u64, timer: u32 },
+    /// A `<meta http-equiv="refresh">` of the document `document` (`None` reloads it).
+    Refresh { document: u64, url: Option<Url> },
+}
+
+/// Suggestions offered for a misspelled word in the focused text field.
+struct SpellingMenu {
+    menu: ContextMenu,
+    /// Byte range of the word in the field's value.
+    range: Range<usize>,
+    /// Replacement for each menu item (empty when there are no suggestions).
+    suggestions: Vec<String>,
+    /// Whether the right button that opened the menu has been released, after
+    /// which a release outside the suggestions closes it.
+    armed: bool,
+}
+
+/// Values offered for the focused text field from the autofill profiles, or the
+/// saved logins offered for the focused field of a login form.
+struct AutofillMenu {
+    menu: ContextMenu,
+    /// Kind of the field (`None` for a login form) and the typed value (the
+    /// username for a login form) when the menu was built; the menu is rebuilt
+    /// when either changes.
+    field: Option<AutofillField>,
+    typed: String,
+    /// Value of each menu item.
+    values: Vec<AutofillValue>,
+    /// Whether Escape hid the menu until the value changes.
+    hidden: bool,
+    /// Whether the left button was pressed on the menu, so its release picks an item.
+    pressed: bool,
+}
+
+enum AutofillValue {
+    /// Fills the focused field with the text.
+    Text(String),
+    /// Fills both the username and the password of the login form.
+    Login(Credential),
+}
+
+impl AutofillValue {
+    fn label(&self) -> &str {
+        match self {
+            AutofillValue::Text(text) => text,
+            AutofillValue::Login(credential) if credential.username.is_empty() => "(no username)",
+            AutofillValue::Login(credential) => &credential.username,
+        }
+    }
+}
+
+/// Asks whether to save the password of a submitted login form.
+struct PasswordPrompt {
+    menu: ContextMenu,
+    login: SubmittedLogin,
+    /// Whether the left button was pressed on the prompt, so its release picks an item.
+    pressed: bool,
+}
+
+/// Items of the password prompt after the question.
+const PROMPT_SAVE: usize = 1;
+const PROMPT_NEVER: usize = 2;
+
+impl PasswordPrompt {
+    /// Shows the prompt at the top right corner of the page.
+    fn new(position: (f32, f32), login: SubmittedLogin) -> Self {
+        let items = vec![
+            MenuItem::disabled(format!("Save password for {}?", login.origin)),
+            MenuItem::new("Save"),
+            MenuItem::new("Never for this site"),
+            MenuItem::new("Not now"),
+        ];
+        Self {
+            menu: ContextMenu::new(position, items),
+            login,
+            pressed: false,
+        }
+    }
+}
+
+/// Asks whether a site may use a capability that needs consent.
+struct PermissionPrompt {
+    menu: ContextMenu,
+    request: PermissionRequest,
+    /// Whether a private tab asked, in which case the answer is not saved.
+    private: bool,
+    /// Whether the left button was pressed on the prompt, so its release picks an item.
+    pressed: bool,
+}
+
+/// Items of the permission prompt after the question.
+const PROMPT_ALLOW: usize = 1;
+const PROMPT_BLOCK: usize = 2;
+
+impl PermissionPrompt {
+    /// Shows the prompt at the top right corner of the page.
+    fn new(position: (f32, f32), request: PermissionRequest, private: bool) -> Self {
+        let items = vec![
+            MenuItem::disabled(format!(
+                "{} wants to {}",
+                request.origin,
+                request.permission.description()
+            )),
+            MenuItem::new("Allow"),
+            MenuItem::new("Block"),
+            MenuItem::new("Not now"),
+        ];
+        Self {
+            menu: ContextMenu::new(position, items),
+            request,
+            private,
+            pressed: false,
+        }
+    }
 }
 
 pub struct PendingFetches {
@@ -58,6 +316,12 @@ impl PendingFetches {
         dbg!(id)
     }
 
+    /// どの fetch にも結び付けない一意 ID を生成する（ダウンロード用）
+    pub fn reserve_id(&mut self, url: &Url) -> usize {
+        self.counter += 1;
+        self.generate_id(url)
+    }
+
     fn generate_id(&self, url: &Url) -> usize {
         // URLをハッシュ化
         let mut hasher = DefaultHasher::new();
@@ -74,9 +338,37 @@ impl PendingFetches {
         now ^ self.counter ^ url_hash
     }
 
+    /// Returns the tab that issued the fetch `id`.
+    pub fn tab_of(&self, id: usize) -> Option<usize> {
+        self.map.get(&id).map(|(tab_id, _, _)| *tab_id)
+    }
+
     pub fn remove(&mut self, id: usize) -> Option<(usize, FetchKind, Url)> {
         self.map.remove(&id)
     }
+
+    /// tab_id のタブが閉じられたので、後ろのタブの ID を詰める
+    pub fn tab_removed(&mut self, tab_id: usize) {
+        for (tab, _, _) in self.map.values_mut() {
+            if *tab > tab_id {
+                *tab -= 1;
+            }
+        }
+    }
+
+    /// tab_id の fetch をすべて登録から外し、その ID を返す
+    pub fn remove_tab(&mut self, tab_id: usize) -> Vec<usize> {
+        let ids: Vec<usize> = self
+            .map
+            .iter()
+            .filter(|(_, (tab, _, _))| *tab == tab_id)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            self.map.remove(id);
+        }
+        ids
+    }
 }
 
 /// Main browser application struct.
@@ -85,10 +377,109 @@ pub struct BrowserApp {
     tabs: Vec<Tab>,
     active_tab: usize,
     render: RenderState,
-    window_title: String,
+    /// Application name shown in the window title after the page title.
+    app_name: String,
+    /// Window title last reported to the platform, to detect changes.
+    last_window_title: String,
     input: InputState,
     network: BrowserResourceLoader,
     pending_fetches: PendingFetches,
+    url_bar: UrlBar,
+    settings: Settings,
+    /// File the settings are loaded from and saved to. `None` keeps them in memory.
+    settings_path: Option<PathBuf>,
+    /// Modification time of the settings file when it was last read or written.
+    settings_modified: Option<SystemTime>,
+    /// When the settings file was last checked for changes.
+    settings_checked_at: Instant,
+    /// Color scheme of the operating system, used unless the settings override it.
+    system_color_scheme: ColorScheme,
+    /// Directory for persistent data (session, history). `None` disables persistence.
+    profile_dir: Option<PathBuf>,
+    /// `User-Agent` header sent instead of the default one.
+    user_agent: Option<String>,
+    /// The last session written to disk and when, to skip redundant periodic saves.
+    saved_session: Option<(Instant, String)>,
+    /// Every page visited successfully, shared by all tabs.
+    browsing_history: BrowsingHistory,
+    /// 閲覧履歴の URL のハッシュ。ページの :visited に使う
+    visited_links: SharedVisitedLinks,
+    /// プライベートタブで訪れたリンク（最後のプライベートタブを閉じると消す）
+    private_visited_links: SharedVisitedLinks,
+    /// Files being downloaded; unfinished ones are resumed from the profile directory.
+    downloads: DownloadManager,
+    /// Timers and tasks posted by scripts and browser subsystems.
+    scheduler: Scheduler<Task>,
+    /// `localStorage` of normal tabs, saved to the profile directory when changed.
+    local_storage: SharedStorage,
+    /// `localStorage` of private tabs, kept in memory until the last one closes.
+    private_local_storage: SharedStorage,
+    /// Whether the next click on the page picks an element to inspect (Ctrl+Shift+C).
+    /// Meanwhile the element under the pointer is highlighted.
+    inspecting: bool,
+    /// Styles of the element picked last, shown by `orinium://inspect`.
+    inspection: Option<StyleInspection>,
+    /// Console of the tab `orinium://console` was opened from (Ctrl+Shift+J).
+    console: Option<Console>,
+    /// Chrome DevTools Protocol server, when remote debugging is enabled.
+    remote_debugging: Option<CdpServer>,
+    /// Whether to print the load metrics of each page to stdout (`--metrics`).
+    print_metrics: bool,
+    /// Dictionary used to check the spelling in text fields, `None` when disabled.
+    spell_checker: Option<Arc<SpellChecker>>,
+    /// Corrections shown after right-clicking a misspelled word.
+    spelling_menu: Option<SpellingMenu>,
+    /// Names, emails and addresses from submitted forms, offered in matching fields.
+    autofill: AutofillStore,
+    /// Suggestions shown below the focused text field.
+    autofill_menu: Option<AutofillMenu>,
+    /// Saved logins, in the OS keychain or an encrypted file in the profile.
+    passwords: PasswordManager,
+    /// Shown after submitting a login form whose password is not saved yet.
+    password_prompt: Option<PasswordPrompt>,
+    /// Allowed and blocked capabilities of each site.
+    permissions: PermissionStore,
+    /// Permission requests waiting for an answer; the first one is shown.
+    permission_prompts: Vec<PermissionPrompt>,
+    /// Asks the OS for the current position, started on the first request.
+    geolocation: Option<Geolocation>,
+    /// Shows the notifications of pages, started on the first one.
+    notifications: Option<NotificationCenter>,
+    /// Extensions that inject content into pages, intercept their requests and
+    /// add toolbar buttons. Private tabs do not run them.
+    extensions: Arc<ExtensionRegistry>,
+    /// Blocks ads and trackers with the filter lists in the profile. It is also
+    /// registered as an extension, which does the blocking.
+    content_blocker: Option<Arc<ContentBlocker>>,
+    /// Commands sent through [`CommandSender`]s, executed by `process_commands`.
+    command_tx: mpsc::Sender<BrowserCommand>,
+    command_rx: mpsc::Receiver<BrowserCommand>,
+    /// Receives the events of the browser (the `EventLoopProxy` of the window).
+    event_sink: Option<Box<dyn EventSink>>,
+    /// Window title, load progress and decorations last sent to the event sink.
+    reported_title: String,
+    reported_progress: Option<f32>,
+    reported_decorations: Option<bool>,
+    /// Collects what needs redrawing and asks for at most one frame per display refresh.
+    frames: FrameScheduler,
+    /// Timers of background tabs (document, timer) waiting for the next background wake-up.
+    throttled_timers: Vec<(u64, u32)>,
+    /// When the timers of background tabs last ran.
+    background_wakeup: Instant,
+    /// How long a tab stays in the background before its layout is discarded.
+    background_discard_delay: Duration,
+    /// Approximate size of the DOMs of all tabs, counted by the memory registry.
+    dom_memory: Arc<TrackedMemory>,
+    /// When memory usage was last checked.
+    memory_checked_at: Instant,
+    /// Worker threads decoding images off the engine thread.
+    decoder: DecodePool,
+    /// Images decoded so far, by storage partition and URL.
+    ///
+    /// Private tabs never see images fetched for normal tabs and vice versa.
+    decoded_images: HashMap<(StoragePartition, Url), Arc<RgbaImage>>,
+    /// Size of `decoded_images`, counted by the memory registry.
+    image_memory: Arc<TrackedMemory>,
 }
 
 impl Default for BrowserApp {
@@ -103,9 +494,36 @@ impl BrowserApp {
         run_with_winit_backend(self)
     }
 
+    /// Starts the event loop with the page pipeline on an engine thread.
+    ///
+    /// `build` runs on the engine thread (the browser cannot move between threads),
+    /// and the main thread only keeps the window and the GPU.
+    pub fn run_on_engine_thread<F>(build: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<BrowserApp> + Send + 'static,
+    {
+        configure_winit_backend();
+        let event_loop = winit::event_loop::EventLoop::<BrowserEvent>::with_user_event().build()?;
+        event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
+        let (engine, ready) =
+            super::engine_thread::EngineThread::spawn(build, Box::new(event_loop.create_proxy()))?;
+        let mut app = ThreadedApp::new(engine, ready);
+        event_loop.run_app(&mut app)?;
+        Ok(())
+    }
+
     /// Creates a new browser instance with the given window size and title.
     pub fn new(window_size: (u32, u32), window_title: String) -> Self {
         let network = BrowserResourceLoader::new(Some(Rc::new(NetworkCore::new())));
+        network.set_network_config(NetworkConfig::default().with_env_proxies());
+        let (command_tx, command_rx) = mpsc::channel();
+        let dom_memory = TrackedMemory::new(Subsystem::Dom);
+        memory::registry().register(&dom_memory);
+        let image_memory = TrackedMemory::evictable(Subsystem::DecodedImages);
+        memory::registry().register(&image_memory);
+        // デコードし終えたものはコマンドとして届き、次の process_commands で受け取る
+        let decoded = CommandSender(command_tx.clone());
+        let decoder = DecodePool::new(move |d| decoded.send(BrowserCommand::Decoded(d)));
 
         Self {
             tabs: vec![],
@@ -114,304 +532,3933 @@ impl BrowserApp {
                 draw_commands: vec![],
                 window_size,
                 scale_factor: 1.0,
+                animating: false,
+                scroll_bar: ScrollBar::new(),
+                scroll_bar_fade: ScrollBarFade::new(),
+                last_page_scroll: None,
+                chrome_visible: true,
+                show_frame_stats: false,
             },
-            window_title,
+            last_window_title: window_title.clone(),
+            reported_title: window_title.clone(),
+            app_name: window_title,
             input: InputState::default(),
             network,
             pending_fetches: PendingFetches::new(),
+            url_bar: UrlBar::new(),
+            settings: Settings::default(),
+            settings_path: None,
+            settings_modified: None,
+            settings_checked_at: Instant::now(),
+            system_color_scheme: ColorScheme::Light,
+            profile_dir: None,
+            user_agent: None,
+            saved_session: None,
+            browsing_history: BrowsingHistory::new(),
+            visited_links: VisitedLinks::new().shared(),
+            private_visited_links: VisitedLinks::new().shared(),
+            downloads: DownloadManager::new(),
+            scheduler: Scheduler::new(),
+            local_storage: WebStorage::new().shared(),
+            private_local_storage: WebStorage::new().shared(),
+            inspecting: false,
+            inspection: None,
+            console: None,
+            remote_debugging: None,
+            print_metrics: false,
+            spell_checker: None,
+            spelling_menu: None,
+            autofill: AutofillStore::new(),
+            autofill_menu: None,
+            passwords: PasswordManager::new(Box::new(MemorySecretStore::new())),
+            password_prompt: None,
+            permissions: PermissionStore::new(),
+            permission_prompts: Vec::new(),
+            geolocation: None,
+            notifications: None,
+            extensions: Arc::new(ExtensionRegistry::new()),
+            content_blocker: None,
+            command_tx,
+            command_rx,
+            event_sink: None,
+            reported_progress: None,
+            reported_decorations: None,
+            frames: FrameScheduler::new(),
+            throttled_timers: Vec::new(),
+            background_wakeup: Instant::now(),
+            background_discard_delay: BACKGROUND_DISCARD_DELAY,
+            dom_memory,
+            memory_checked_at: Instant::now(),
+            decoder,
+            decoded_images: HashMap::new(),
+            image_memory,
         }
     }
 
-    pub fn tick(&mut self) -> BrowserCommand {
-        let tab_id = self.active_tab;
+    /// Returns the browser settings.
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
 
-        self.handle_network_messages();
+    /// Replaces the browser settings and applies them to the open tabs.
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+        self.apply_settings();
+    }
 
-        let Some(tab) = self.tabs.get_mut(tab_id) else {
-            return BrowserCommand::None;
-        };
+    /// Prints the load metrics of each page to stdout once it has loaded and
+    /// been painted for the first time.
+    pub fn set_print_metrics(&mut self, enabled: bool) {
+        self.print_metrics = enabled;
+    }
 
-        for task in tab.tick() {
-            match task {
-                TabTask::Fetch { url, kind } => {
-                    log::info!("Fetch requested in App: url={}", url);
-                    let id = self.pending_fetches.insert(tab_id, kind, url.clone());
-                    self.network.fetch_async(url, id);
-                }
-                TabTask::NeedsRedraw => {
-                    return BrowserCommand::RequestRedraw;
-                }
+    /// Loads the settings from `path` and keeps them in sync with the file.
+    ///
+    /// A missing file is created with the default settings so it can be edited.
+    /// The file is checked for changes while the browser runs, and changes made on
+    /// `orinium://settings` are written back to it.
+    pub fn set_settings_path(&mut self, path: PathBuf) {
+        if path.exists() {
+            match Settings::load(&path) {
+                Ok(settings) => self.set_settings(settings),
+                Err(e) => log::error!("Failed to load settings: {:#}", e),
             }
+        } else if let Err(e) = self.settings.save(&path) {
+            log::error!("Failed to save settings: {:#}", e);
         }
-
-        BrowserCommand::None
+        self.settings_modified = modified_time(&path);
+        self.settings_path = Some(path);
     }
 
-    fn handle_network_messages(&mut self) {
-        let messages = self.network.try_receive();
-
-        for msg in messages {
-            log::info!("Network message received in App for fetch_id={}", msg.id);
-
-            // pending_fetches から fetch 情報を取得
-            let Some((tab_id, kind, url)) = self.pending_fetches.remove(msg.id) else {
-                log::warn!("No pending fetch found for fetch_id={}", msg.id);
-                continue;
-            };
+    /// Writes the settings to the settings file, if one is set.
+    fn save_settings(&mut self) {
+        let Some(path) = self.settings_path.as_ref() else {
+            return;
+        };
+        match self.settings.save(path) {
+            Ok(()) => self.settings_modified = modified_time(path),
+            Err(e) => log::error!("Failed to save settings: {:#}", e),
+        }
+    }
 
-            // Tab を取得
-            let Some(tab) = self.tabs.get_mut(tab_id) else {
-                log::warn!("There is no Tab called id={}", tab_id);
-                continue;
-            };
+    /// Reloads the settings file if it was changed outside the browser.
+    fn reload_settings_if_changed(&mut self) {
+        if self.settings_checked_at.elapsed() < SETTINGS_CHECK_INTERVAL {
+            return;
+        }
+        self.settings_checked_at = Instant::now();
 
-            match msg.response {
-                Ok(resp) => {
-                    log::info!("Fetch Done in App for tab_id={}", tab_id);
+        let Some(path) = self.settings_path.as_ref() else {
+            return;
+        };
+        let modified = modified_time(path);
+        if modified.is_none() || modified == self.settings_modified {
+            return;
+        }
+        self.settings_modified = modified;
 
-                    match kind {
-                        FetchKind::Html => {
-                            let html = String::from_utf8_lossy(&resp.body).to_string();
-                            tab.on_fetch_succeeded_html(html);
-                        }
-                        FetchKind::Css => {
-                            let css = String::from_utf8_lossy(&resp.body).to_string();
-                            tab.on_fetch_succeeded_css(css);
-                        }
-                    }
-                }
-                Err(err) => {
-                    log::error!("NetworkError: {}", err);
-                    tab.on_fetch_failed(err, url);
-                }
+        match Settings::load(path) {
+            Ok(settings) if settings != self.settings => {
+                log::info!("Settings reloaded from {:?}", path);
+                self.set_settings(settings);
             }
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to reload settings: {:#}", e),
         }
     }
 
-    /// Returns a mutable reference to the currently active tab, if any.
-    fn active_tab_mut(&mut self) -> Option<&mut Tab> {
-        self.tabs.get_mut(self.active_tab)
+    /// Sets the color scheme reported by the operating system.
+    ///
+    /// It is used for the browser UI and `prefers-color-scheme` unless the settings
+    /// choose a scheme explicitly.
+    pub fn set_system_color_scheme(&mut self, scheme: ColorScheme) {
+        if scheme != self.system_color_scheme {
+            self.system_color_scheme = scheme;
+            self.apply_settings();
+        }
     }
 
-    /// Rebuilds the render tree for the active tab and generates draw commands.
-    fn rebuild_render_tree(&mut self) {
-        let (title, draw_commands) = {
-            let sf = self.render.scale_factor as f32;
-            let viewport = (
-                self.render.window_size.0 as f32 / sf,
-                self.render.window_size.1 as f32 / sf,
-            );
-
-            let Some(tab) = self.active_tab_mut() else {
-                return;
-            };
-
-            tab.relayout(viewport);
-
-            let Some((layout, info)) = tab.layout_and_info() else {
-                log::debug!("No layout/info available for active tab");
-                return;
-            };
-
-            let title = tab.title();
-            let draw_commands = renderer_model::generate_draw_commands(layout, info);
-
-            (title, draw_commands)
-        };
-
-        self.render.draw_commands = draw_commands;
+    /// Returns the color scheme in effect (the settings override, or the system's).
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.settings.color_scheme.resolve(self.system_color_scheme)
+    }
 
-        if let Some(title) = title {
-            self.window_title = title;
+    /// Applies the page defaults (font, zoom and color scheme) from the settings to
+    /// every tab.
+    fn apply_settings(&mut self) {
+        self.render.show_frame_stats = self.settings.show_frame_stats;
+        memory::registry().set_budget(self.settings.memory_budget());
+        if let Err(e) = logging::apply_levels(&self.settings.log_levels) {
+            log::warn!("Ignoring log levels from the settings: {:#}", e);
+        }
+        if let Err(e) = logging::logger().set_file(self.settings.log_file.as_deref()) {
+            log::warn!("{:#}", e);
         }
+        if let Some(blocker) = &self.content_blocker {
+            blocker.set_enabled(self.settings.content_blocking);
+        }
+        let defaults = self.settings.page_defaults(self.system_color_scheme);
+        for tab in &mut self.tabs {
+            tab.set_page_defaults(defaults.clone());
+        }
+        self.update_spell_checker();
     }
 
-    /// Handles a `winit` window event and returns a `BrowserCommand`.
-    pub fn handle_window_event(
-        &mut self,
-        event: WindowEvent,
-        gpu: &mut GpuRenderer,
-    ) -> BrowserCommand {
-        let browser_cmd = match event {
-            WindowEvent::CloseRequested => BrowserCommand::Exit,
-
-            WindowEvent::RedrawRequested => {
-                self.redraw(gpu);
-                BrowserCommand::RenameWindowTitle
+    /// Loads the spell checking dictionary for the language in the settings, or
+    /// drops it when spell checking is disabled, and hands it to every tab.
+    fn update_spell_checker(&mut self) {
+        let language = self
+            .settings
+            .spell_check
+            .then_some(self.settings.spell_check_language.as_str());
+        let current = self.spell_checker.as_deref().map(SpellChecker::language);
+        if language == current {
+            return;
+        }
+        self.spell_checker = language.and_then(|language| match io::load_dictionary(language) {
+            Ok((aff, dic)) => Some(Arc::new(SpellChecker::from_bytes(language, &aff, &dic))),
+            Err(e) => {
+                log::warn!("Spell checking is unavailable: {:#}", e);
+                None
             }
+        });
+        for tab in &mut self.tabs {
+            tab.set_spell_checker(self.spell_checker.clone());
+        }
+    }
 
-            WindowEvent::Resized(size) => {
-                self.render.window_size = (size.width, size.height);
-                gpu.resize(size);
-                self.redraw(gpu);
-                BrowserCommand::RequestRedraw
+    /// Sets the directory where the session and other persistent data are stored,
+    /// and loads the browsing history and `localStorage` saved there.
+    pub fn set_profile_dir(&mut self, dir: PathBuf) {
+        match BrowsingHistory::load(&dir.join(HISTORY_FILE_NAME)) {
+            Ok(history) => {
+                // タブが同じ記録を持っているので、中身だけを入れ替える
+                *self.visited_links.borrow_mut() = VisitedLinks::from_history(&history);
+                self.browsing_history = history;
             }
-
-            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
-                gpu.set_scale_factor(scale_factor);
-                self.render.scale_factor = scale_factor;
-                self.redraw(gpu);
-                BrowserCommand::RequestRedraw
+            Err(e) => log::error!("Failed to load browsing history: {:#}", e),
+        }
+        match AutofillStore::load(&dir.join(AUTOFILL_FILE_NAME)) {
+            Ok(autofill) => self.autofill = autofill,
+            Err(e) => log::error!("Failed to load autofill profiles: {:#}", e),
+        }
+        match PermissionStore::load(&dir.join(PERMISSIONS_FILE_NAME)) {
+            Ok(permissions) => self.permissions = permissions,
+            Err(e) => log::error!("Failed to load site permissions: {:#}", e),
+        }
+        match keychain::open_default(&dir, PASSWORD_FILE_NAME) {
+            Ok(store) => {
+                log::info!("Saving passwords in {}", store.name());
+                self.passwords = PasswordManager::new(store);
             }
-
-            WindowEvent::MouseWheel { delta, .. } => {
-                self.handle_scroll(delta);
-                BrowserCommand::RequestRedraw
+            Err(e) => log::error!("Failed to open the password store: {:#}", e),
+        }
+        if let Err(e) = self
+            .passwords
+            .load_exceptions(&dir.join(PASSWORD_EXCEPTIONS_FILE_NAME))
+        {
+            log::error!("Failed to load password exceptions: {:#}", e);
+        }
+        let path = dir.join(LOCAL_STORAGE_FILE_NAME);
+        if path.exists() {
+            match std::fs::read_to_string(&path) {
+                // タブが持っている参照もそのまま使えるように中身だけ入れ替える
+                Ok(text) => *self.local_storage.borrow_mut() = WebStorage::parse(&text),
+                Err(e) => log::error!("Failed to load local storage: {:#}", e),
             }
-
-            WindowEvent::CursorMoved { position, .. } => {
-                self.input.mouse_position = (position.x, position.y);
-                BrowserCommand::None
+        }
+        self.network
+            .set_network_config(self.network_config(Some(&dir)));
+        match Arc::make_mut(&mut self.extensions).load_dir(&dir.join(EXTENSIONS_DIR_NAME)) {
+            Ok(0) => {}
+            Ok(count) => {
+                log::info!("Loaded {} extensions", count);
+                self.update_extensions();
+            }
+            Err(e) => log::error!("Failed to load extensions: {:#}", e),
+        }
+        self.load_content_blocker(&dir);
+        let unfinished = match DownloadManager::load_unfinished(&dir.join(DOWNLOADS_FILE_NAME)) {
+            Ok(unfinished) => unfinished,
+            Err(e) => {
+                log::error!("Failed to load downloads: {:#}", e);
+                Vec::new()
             }
+        };
+        self.profile_dir = Some(dir);
+        for (url, path) in unfinished {
+            log::info!("Resuming the download of {} to {:?}", url, path);
+            self.resume_download(url, path);
+        }
+    }
 
-            WindowEvent::MouseInput { button, .. } => self.handle_mouse_input(button),
+    /// Sends `user_agent` as the `User-Agent` header instead of the default one.
+    pub fn set_user_agent(&mut self, user_agent: String) {
+        self.user_agent = Some(user_agent);
+        self.network
+            .set_network_config(self.network_config(self.profile_dir.as_deref()));
+    }
 
-            _ => BrowserCommand::None,
-        };
-        let cmd_from_tick = self.tick();
-        match browser_cmd {
-            BrowserCommand::None => {
-                if matches!(cmd_from_tick, BrowserCommand::RequestRedraw) {
-                    self.redraw(gpu);
-                }
-                cmd_from_tick
-            }
-            _ => browser_cmd,
+    /// Returns the network configuration, keeping the cache, cookies and HSTS
+    /// list in `profile_dir` if given.
+    fn network_config(&self, profile_dir: Option<&Path>) -> NetworkConfig {
+        let mut config = NetworkConfig::default().with_env_proxies();
+        if let Some(dir) = profile_dir {
+            config.cache_dir = Some(dir.join(CACHE_DIR_NAME));
+            config.cookie_file = Some(dir.join(COOKIE_FILE_NAME));
+            config.hsts_file = Some(dir.join(HSTS_FILE_NAME));
         }
+        if let Some(user_agent) = &self.user_agent {
+            config.user_agent = user_agent.clone();
+        }
+        config
     }
 
-    /// Handles mouse input events, mainly left-clicks for the active tab.
-    fn handle_mouse_input(&mut self, button: winit::event::MouseButton) -> BrowserCommand {
-        if button != winit::event::MouseButton::Left {
-            return BrowserCommand::None;
+    /// Writes `localStorage` to the profile directory if scripts changed it.
+    fn save_local_storage_if_modified(&mut self) {
+        let Some(dir) = self.profile_dir.as_ref() else {
+            return;
+        };
+        let mut storage = self.local_storage.borrow_mut();
+        if !storage.take_modified() {
+            return;
         }
+        let path = dir.join(LOCAL_STORAGE_FILE_NAME);
+        if let Err(e) = io::write_atomic(&path, storage.serialize().as_bytes()) {
+            log::error!("Failed to save local storage: {:#}", e);
+        }
+    }
 
-        let (x, y) = self.input.mouse_position;
-        let sf = self.render.scale_factor;
-        if let Some(tab) = self.active_tab_mut() {
-            Self::handle_mouse_click(tab, (x / sf) as f32, (y / sf) as f32);
-            BrowserCommand::RequestRedraw
-        } else {
-            BrowserCommand::None
+    /// Returns the record of visited pages.
+    pub fn browsing_history(&self) -> &BrowsingHistory {
+        &self.browsing_history
+    }
+
+    /// Writes the browsing history to the profile directory, if one is set.
+    fn save_browsing_history(&self) {
+        let Some(dir) = self.profile_dir.as_ref() else {
+            return;
+        };
+        if let Err(e) = self.browsing_history.save(&dir.join(HISTORY_FILE_NAME)) {
+            log::error!("Failed to save browsing history: {:#}", e);
         }
     }
 
-    /// Handles scrolling for the active tab, updating its layout container offsets.
+    /// Forgets the browsing history, cookies, cache and `localStorage` of the
+    /// normal tabs, both in memory and in the profile directory.
     ///
-    /// Currently a stub.
-    fn handle_scroll(&mut self, delta: winit::event::MouseScrollDelta) {
-        let scroll_amount = match delta {
-            winit::event::MouseScrollDelta::LineDelta(_, y) => -y * 60.0,
-            winit::event::MouseScrollDelta::PixelDelta(pos) => -pos.y as f32,
-        };
+    /// Private tabs keep their own data until the last one is closed.
+    pub fn clear_browsing_data(&mut self) {
+        self.browsing_history.clear();
+        self.visited_links.borrow_mut().clear();
+        self.save_browsing_history();
+        self.local_storage.borrow_mut().clear_all();
+        self.save_local_storage_if_modified();
+        self.network.clear_partition(StoragePartition::Default);
+        log::info!("Browsing data cleared");
+    }
 
-        let window_size = self.window_size();
-        let sf = self.render.scale_factor as f32;
+    /// Returns the names, emails and addresses remembered for autofill.
+    pub fn autofill(&self) -> &AutofillStore {
+        &self.autofill
+    }
 
-        if let Some(tab) = self.tabs.get_mut(self.active_tab)
-            && let Some((layout, info)) = tab.layout_and_info_mut()
-            && let layouter::types::NodeKind::Container {
-                scroll_offset_y, ..
-            } = &mut info.kind
-        {
-            *scroll_offset_y = (*scroll_offset_y + scroll_amount).clamp(
-                0.0,
-                (layout
-                    .layout_boxes
-                    .iter()
-                    .map(|l| l.children_box.height)
-                    .sum::<f32>()
-                    - (window_size.1 / sf))
-                    .max(0.0),
-            );
+    /// Writes the autofill profiles to the profile directory, if one is set.
+    fn save_autofill(&self) {
+        let Some(dir) = self.profile_dir.as_ref() else {
+            return;
+        };
+        if let Err(e) = self.autofill.save(&dir.join(AUTOFILL_FILE_NAME)) {
+            log::error!("Failed to save autofill profiles: {:#}", e);
         }
     }
 
-    /// Handles a mouse click in the given tab at the specified coordinates.
-    pub fn handle_mouse_click(tab: &mut Tab, x: f32, y: f32) {
-        let hit_path = match tab.layout_and_info() {
-            Some((layout, info)) => crate::engine::input::hit_test(layout, info, x, y),
-            None => return,
-        };
-
-        let href_opt = {
-            if let Some(hit) = hit_path.iter().find(|e| {
-                matches!(
-                    e.info.kind,
-                    layouter::types::NodeKind::Container { ref role, .. }
-                        if matches!(role, layouter::types::ContainerRole::Link { .. })
-                )
-            }) {
-                if let layouter::types::NodeKind::Container { role, .. } = &hit.info.kind
-                    && let layouter::types::ContainerRole::Link { href } = role
-                {
-                    Some(href.clone())
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        };
+    /// Returns the saved logins and the sites they are never saved for.
+    pub fn passwords(&self) -> &PasswordManager {
+        &self.passwords
+    }
 
-        if let Some(href) = href_opt {
-            tab.move_to(&href)
-        }
+    /// Returns the capabilities allowed or blocked for each site.
+    pub fn permissions(&self) -> &PermissionStore {
+        &self.permissions
     }
 
-    /// Rebuilds the render tree and sends draw commands to the GPU.
-    pub fn redraw(&mut self, gpu: &mut GpuRenderer) {
-        self.rebuild_render_tree();
-        self.apply_draw_commands(gpu);
-        if let Err(e) = gpu.render() {
-            log::error!(target: "BrowserApp::redraw", "Render error occurred: {}", e);
+    /// Allows, blocks or resets (`Ask`) `permission` for the site `origin`, saves
+    /// the decision and applies it to the open pages of the site.
+    pub fn set_site_permission(
+        &mut self,
+        origin: &str,
+        permission: Permission,
+        state: PermissionState,
+    ) {
+        if !self.permissions.set(origin, permission, state) {
+            return;
+        }
+        self.save_permissions();
+        if state == PermissionState::Ask {
+            return;
+        }
+        for tab in self.tabs.iter_mut().filter(|tab| !tab.is_private()) {
+            tab.set_permission(origin, permission, state == PermissionState::Allow);
         }
     }
 
-    /// Applies the current draw commands to the GPU renderer.
-    pub fn apply_draw_commands(&self, gpu: &mut GpuRenderer) {
-        gpu.parse_draw_commands(&self.render.draw_commands);
+    /// Answers the position requests of pages with `provider` instead of the
+    /// location service of the OS.
+    pub fn set_location_provider(&mut self, provider: Box<dyn LocationProvider>) {
+        self.geolocation = Some(Geolocation::new(provider));
     }
 
-    /// Adds a new tab to the browser.
-    pub fn add_tab(&mut self, tab: Tab) {
-        self.tabs.push(tab);
+    /// Passes the position requests that pages were allowed to make to the
+    /// location service, and delivers the answers that arrived.
+    fn update_geolocation(&mut self, requests: Vec<u64>) {
+        if !requests.is_empty() && self.geolocation.is_none() {
+            self.geolocation = geolocation::default_provider().map(Geolocation::new);
+        }
+        let mut results = Vec::new();
+        match &self.geolocation {
+            Some(service) => {
+                for id in requests {
+                    service.request(id, geolocation::DEFAULT_TIMEOUT);
+                }
+                results = service.poll();
+            }
+            None => {
+                for id in requests {
+                    let error = "No location service is available".to_string();
+                    results.push((id, Err(PositionError::PositionUnavailable(error))));
+                }
+            }
+        }
+        for (id, result) in results {
+            if !self
+                .tabs
+                .iter_mut()
+                .any(|tab| tab.set_position(id, result.clone()))
+            {
+                log::debug!("Dropping the position for closed request {}", id);
+            }
+        }
     }
 
-    /// Returns the current window size as `(width, height)` in floating-point pixels.
-    pub fn window_size(&self) -> (f32, f32) {
-        (
-            self.render.window_size.0 as f32,
-            self.render.window_size.1 as f32,
-        )
+    /// Adds `extension` to the browser. Pages opened from now on get its content.
+    pub fn register_extension(&mut self, extension: Box<dyn Extension>) {
+        Arc::make_mut(&mut self.extensions).register(extension);
+        self.update_extensions();
     }
 
-    /// Returns the current window title.
-    pub fn window_title(&self) -> String {
-        self.window_title.clone()
+    /// Returns the registered extensions.
+    pub fn extensions(&self) -> &ExtensionRegistry {
+        &self.extensions
     }
 
-    /// Sets the current scale factor for rendering.
-    pub fn set_scale_factor(&mut self, sf: f64) {
-        self.render.scale_factor = sf;
+    /// Hands the extensions to the tabs, shows their toolbar buttons and lets
+    /// the loader read their schemes.
+    fn update_extensions(&mut self) {
+        let labels = self
+            .extensions
+            .toolbar_actions()
+            .into_iter()
+            .map(|action| action.label.clone())
+            .collect();
+        self.url_bar.set_action_buttons(labels);
+        for tab in self.tabs.iter_mut().filter(|tab| !tab.is_private()) {
+            tab.set_extensions(Some(self.extensions.clone()));
+        }
+        for (scheme, handler) in self.extensions.scheme_handlers() {
+            if let Err(e) = self.network.register_scheme(&scheme, handler) {
+                log::warn!("Ignoring extension scheme {}: {:#}", scheme, e);
+            }
+        }
     }
-}
 
-fn run_with_winit_backend(app: BrowserApp) -> Result<()> {
-    configure_winit_backend_for_wslg();
-    if env::var_os("ORINIUM_FORCE_X11").is_some() {
-        configure_winit_backend_forced_x11();
+    /// Loads the filter lists in the profile directory and starts blocking the
+    /// requests they match.
+    fn load_content_blocker(&mut self, dir: &Path) {
+        let mut blocker = ContentBlocker::new();
+        match blocker.load_dir(&dir.join(FILTER_LISTS_DIR_NAME)) {
+            Ok(0) => return,
+            Ok(count) => log::info!("Loaded {} content blocking filters", count),
+            Err(e) => {
+                log::error!("Failed to load filter lists: {:#}", e);
+                return;
+            }
+        }
+        if let Err(e) = blocker.load_exceptions(&dir.join(CONTENT_BLOCKING_EXCEPTIONS_FILE_NAME)) {
+            log::error!("Failed to load content blocking exceptions: {:#}", e);
+        }
+        blocker.set_enabled(self.settings.content_blocking);
+        let blocker = Arc::new(blocker);
+        self.content_blocker = Some(blocker.clone());
+        self.register_extension(Box::new(blocker));
     }
 
-    run_event_loop(app)
+    /// Returns the ad and tracker blocker, if filter lists were loaded.
+    pub fn content_blocker(&self) -> Option<&ContentBlocker> {
+        self.content_blocker.as_deref()
+    }
+
+    /// Turns content blocking on or off for the site of the active tab, saves
+    /// the choice and reloads the page.
+    fn toggle_content_blocking(&mut self) -> BrowserCommand {
+        let Some(blocker) = &self.content_blocker else {
+            return BrowserCommand::None;
+        };
+        let Some(host) = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(Tab::document_url)
+            .and_then(|url| url.host_str().map(str::to_string))
+        else {
+            return BrowserCommand::None;
+        };
+        blocker.set_enabled_for(&host, !blocker.is_enabled_for(&host));
+        if let Some(dir) = self.profile_dir.as_ref()
+            && let Err(e) =
+                blocker.save_exceptions(&dir.join(CONTENT_BLOCKING_EXCEPTIONS_FILE_NAME))
+        {
+            log::error!("Failed to save content blocking exceptions: {:#}", e);
+        }
+        BrowserCommand::Reload {
+            bypass_cache: false,
+        }
+    }
+
+    /// Returns what the content blocking button shows for `tab`: whether its site
+    /// is blocked and how many requests were blocked, or `None` to hide it.
+    fn content_blocking_badge(&self, tab: &Tab) -> Option<(bool, usize)> {
+        let blocker = self.content_blocker.as_ref()?;
+        if !blocker.is_enabled() || tab.is_private() {
+            return None;
+        }
+        let url = tab.document_url()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        let host = url.host_str()?;
+        Some((blocker.is_enabled_for(host), tab.blocked_requests()))
+    }
+
+    /// Runs the script of the `index`-th toolbar button in the active tab.
+    fn run_toolbar_action(&mut self, index: usize) -> BrowserCommand {
+        let Some(script) = self
+            .extensions
+            .toolbar_actions()
+            .get(index)
+            .and_then(|action| action.script.clone())
+        else {
+            return BrowserCommand::None;
+        };
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return BrowserCommand::None;
+        };
+        if tab.is_private() {
+            return BrowserCommand::None;
+        }
+        if let Err(e) = tab.evaluate_script(&script) {
+            log::warn!("Toolbar action failed: {:#}", e);
+        }
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Shows the notifications of pages with `backend` instead of the
+    /// notification center of the OS.
+    pub fn set_notification_backend(&mut self, backend: Box<dyn NotificationBackend>) {
+        self.notifications = Some(NotificationCenter::new(backend));
+    }
+
+    /// Passes the notifications that pages were allowed to show to the
+    /// notification center, and tells the pages what happened to them.
+    fn update_notifications(&mut self, commands: Vec<NotificationCommand>) {
+        let shows = commands
+            .iter()
+            .any(|c| matches!(c, NotificationCommand::Show(..)));
+        if shows && self.notifications.is_none() {
+            self.notifications = notifications::default_backend().map(NotificationCenter::new);
+        }
+        let mut events = Vec::new();
+        match &self.notifications {
+            Some(center) => {
+                for command in commands {
+                    center.send(command);
+                }
+                events = center.poll();
+            }
+            None => {
+                for command in commands {
+                    if let NotificationCommand::Show(id, _) = command {
+                        let error = "No notification service is available".to_string();
+                        events.push((id, NotificationEvent::Error(error)));
+                    }
+                }
+            }
+        }
+        for (id, event) in events {
+            if !self
+                .tabs
+                .iter_mut()
+                .any(|tab| tab.notification_event(id, &event))
+            {
+                log::debug!("Dropping the event of closed notification {}", id);
+            }
+        }
+    }
+
+    /// Writes the site permissions to the profile directory, if one is set.
+    fn save_permissions(&self) {
+        let Some(dir) = self.profile_dir.as_ref() else {
+            return;
+        };
+        if let Err(e) = self.permissions.save(&dir.join(PERMISSIONS_FILE_NAME)) {
+            log::error!("Failed to save site permissions: {:#}", e);
+        }
+    }
+
+    /// Writes the sites whose passwords are never saved to the profile directory.
+    fn save_password_exceptions(&self) {
+        let Some(dir) = self.profile_dir.as_ref() else {
+            return;
+        };
+        let path = dir.join(PASSWORD_EXCEPTIONS_FILE_NAME);
+        if let Err(e) = self.passwords.save_exceptions(&path) {
+            log::error!("Failed to save password exceptions: {:#}", e);
+        }
+    }
+
+    /// Downloads `url` into the downloads directory and returns where it will be saved.
+    ///
+    /// An interrupted transfer continues from where it stopped, and downloads still
+    /// unfinished when the browser exits are resumed the next time the profile loads.
+    pub fn start_download(&mut self, url: Url) -> Result<PathBuf> {
+        let name = mime::download_file_name(&[], &url);
+        // 空のファイルで名前を押さえておき、受信し終えたら置き換える
+        let path = io::save_download(&io::downloads_dir()?, &name, &[])?;
+        self.resume_download(url, path.clone());
+        Ok(path)
+    }
+
+    /// Downloads `url` to `path`, continuing from `<path>.part` if it exists.
+    fn resume_download(&mut self, url: Url, path: PathBuf) {
+        let id = self.pending_fetches.reserve_id(&url);
+        self.network
+            .download(url.clone(), path.clone(), id, StoragePartition::Default);
+        self.downloads.start(id, url, path);
+        self.save_downloads_if_modified();
+    }
+
+    /// Returns the downloads started in this session.
+    pub fn downloads(&self) -> &DownloadManager {
+        &self.downloads
+    }
+
+    /// Writes the unfinished downloads to the profile directory if they changed.
+    fn save_downloads_if_modified(&mut self) {
+        let Some(dir) = self.profile_dir.as_ref() else {
+            return;
+        };
+        if !self.downloads.take_modified() {
+            return;
+        }
+        if let Err(e) = self.downloads.save(&dir.join(DOWNLOADS_FILE_NAME)) {
+            log::error!("Failed to save downloads: {:#}", e);
+        }
+    }
+
+    /// Returns the open tabs as a session that can be saved.
+    pub fn current_session(&self) -> Session {
+        let mut session = Session::default();
+        for (i, tab) in self.tabs.iter().enumerate() {
+            let Some(saved) = tab.session_tab() else {
+                continue;
+            };
+            if i == self.active_tab {
+                session.active = session.tabs.len();
+            }
+            session.tabs.push(saved);
+        }
+        session
+    }
+
+    /// Writes the open tabs to the profile directory, if one is set.
+    pub fn save_session(&mut self) {
+        let Some(path) = self.profile_dir.as_ref().map(|d| d.join(SESSION_FILE_NAME)) else {
+            return;
+        };
+
+        let session = self.current_session();
+        let serialized = session.serialize();
+        match session.save(&path) {
+            Ok(()) => self.saved_session = Some((Instant::now(), serialized)),
+            Err(e) => log::error!("Failed to save session: {:#}", e),
+        }
+    }
+
+    /// Saves the session if it changed and [`SESSION_SAVE_INTERVAL`] has passed.
+    fn save_session_periodically(&mut self) {
+        if self.profile_dir.is_none() {
+            return;
+        }
+        if let Some((saved_at, saved)) = &self.saved_session
+            && (saved_at.elapsed() < SESSION_SAVE_INTERVAL
+                || *saved == self.current_session().serialize())
+        {
+            return;
+        }
+
+        self.save_session();
+    }
+
+    /// Reopens the tabs saved in the profile directory ("continue where you left off").
+    ///
+    /// Returns `false` if restoring is disabled in the settings or there is nothing
+    /// to restore.
+    pub fn restore_session(&mut self) -> bool {
+        if !self.settings.restore_session {
+            return false;
+        }
+        let Some(path) = self.profile_dir.as_ref().map(|d| d.join(SESSION_FILE_NAME)) else {
+            return false;
+        };
+
+        let session = match Session::load(&path) {
+            Ok(Some(session)) if !session.is_empty() => session,
+            Ok(_) => return false,
+            Err(e) => {
+                log::error!("Failed to load session: {:#}", e);
+                return false;
+            }
+        };
+
+        for saved in &session.tabs {
+            let mut tab = Tab::new();
+            tab.restore(saved);
+            self.add_tab(tab);
+        }
+        self.active_tab = self.tabs.len() - session.tabs.len() + session.active;
+        log::info!(
+            "Restored {} tab(s) from the last session",
+            session.tabs.len()
+        );
+        true
+    }
+
+    /// Sets the search engine used for URL bar input that is not a URL.
+    pub fn set_search_engine(&mut self, search_engine: SearchEngine) {
+        self.settings.search_engine = search_engine;
+    }
+
+    pub fn tick(&mut self) -> BrowserCommand {
+        self.handle_network_messages();
+        self.save_session_periodically();
+        self.save_local_storage_if_modified();
+        self.reload_settings_if_changed();
+        self.update_background_tabs(Instant::now());
+        self.check_memory();
+
+        // 裏のタブも読み込みを進める
+        let mut cmd = BrowserCommand::None;
+        let mut settings_changed = false;
+        let mut autofill_changed = false;
+        let mut submitted_login = None;
+        let mut permission_requests = Vec::new();
+        let mut position_requests = Vec::new();
+        let mut notification_commands = Vec::new();
+        for tab_id in 0..self.tabs.len() {
+            let tab = &mut self.tabs[tab_id];
+            // 決めてあればすぐに答え、決めていなければ尋ねる
+            for request in tab.take_permission_requests() {
+                match self.permissions.state(&request.origin, request.permission) {
+                    PermissionState::Allow => {
+                        tab.set_permission(&request.origin, request.permission, true)
+                    }
+                    PermissionState::Deny => {
+                        tab.set_permission(&request.origin, request.permission, false)
+                    }
+                    PermissionState::Ask => permission_requests.push((request, tab.is_private())),
+                }
+            }
+            position_requests.extend(tab.take_position_requests());
+            notification_commands.extend(tab.take_notification_commands());
+            // プライベートタブで送信したフォームは覚えない
+            if let Some(profile) = tab.take_submitted_profile()
+                && self.settings.autofill
+                && !tab.is_private()
+            {
+                autofill_changed |= self.autofill.add_profile(profile);
+            }
+            if let Some(login) = tab.take_submitted_login()
+                && self.settings.passwords
+                && !tab.is_private()
+                && self.passwords.should_offer_to_save(&login)
+            {
+                submitted_login = Some(login);
+            }
+            // ページの処理でパニックしたらそのタブだけをクラッシュさせる
+            let tasks = tab.isolate(Tab::tick);
+            for task in tasks {
+                match task {
+                    TabTask::Fetch {
+                        url,
+                        kind,
+                        bypass_cache,
+                        site_for_cookies,
+                    } => {
+                        settings_changed |=
+                            self.start_fetch(tab_id, url, kind, bypass_cache, site_for_cookies);
+                    }
+                    TabTask::Hint(hint) => self.send_hint(tab_id, hint),
+                    TabTask::NeedsRedraw if tab_id == self.active_tab => {
+                        cmd = BrowserCommand::RequestRedraw;
+                    }
+                    TabTask::NeedsRedraw => {}
+                }
+            }
+        }
+
+        if settings_changed {
+            self.apply_settings();
+            self.save_settings();
+        }
+        if autofill_changed {
+            self.save_autofill();
+        }
+        if let Some(login) = submitted_login {
+            let position = (self.logical_width(), self.chrome_height());
+            self.password_prompt = Some(PasswordPrompt::new(position, login));
+            cmd = BrowserCommand::RequestRedraw;
+        }
+        self.update_geolocation(position_requests);
+        self.update_notifications(notification_commands);
+        for (request, private) in permission_requests {
+            // 同じものを尋ねている途中なら答えをまとめて返す
+            if self
+                .permission_prompts
+                .iter()
+                .any(|p| p.request == request && p.private == private)
+            {
+                continue;
+            }
+            let position = (self.logical_width(), self.chrome_height());
+            self.permission_prompts
+                .push(PermissionPrompt::new(position, request, private));
+            cmd = BrowserCommand::RequestRedraw;
+        }
+
+        if matches!(self.run_scheduled_tasks(), BrowserCommand::RequestRedraw) {
+            cmd = BrowserCommand::RequestRedraw;
+        }
+
+        // タイトルの変化（読み込みの開始と完了を含む）をウィンドウに反映させる
+        let title = self.window_title();
+        if title != self.last_window_title {
+            self.last_window_title = title;
+            if matches!(cmd, BrowserCommand::None) {
+                cmd = BrowserCommand::RenameWindowTitle;
+            }
+        }
+
+        cmd
+    }
+
+    /// Runs every task and timer that is due, in order.
+    ///
+    /// Called from [`tick`](Self::tick) and whenever the event loop wakes up, so
+    /// timers fire even while no window events arrive. Returns `RequestRedraw` if
+    /// a task changed the active page.
+    pub fn run_scheduled_tasks(&mut self) -> BrowserCommand {
+        self.collect_timer_requests();
+
+        let now = Instant::now();
+        let mut redraw = false;
+        while let Some(task) = self.scheduler.next_task(now) {
+            match task {
+                Task::ScriptTimer { document, timer } => {
+                    let Some(index) = self.tabs.iter().position(|tab| tab.has_document(document))
+                    else {
+                        // The document is gone (navigated away or tab closed).
+                        self.scheduler.cancel_where(
+                            |t| matches!(t, Task::ScriptTimer { document: d, .. } if *d == document),
+                        );
+                        continue;
+                    };
+                    // 裏のタブのタイマーはまとめて後で実行する
+                    if index != self.active_tab {
+                        if !self.throttled_timers.contains(&(document, timer)) {
+                            self.throttled_timers.push((document, timer));
+                        }
+                        continue;
+                    }
+                    let tab = &mut self.tabs[index];
+                    tab.fire_timer(document, timer);
+                    redraw |= tab.needs_redraw();
+                }
+                Task::Refresh { document, url } => {
+                    if let Some(index) = self.tabs.iter().position(|tab| tab.has_document(document))
+                    {
+                        self.cancel_fetches(index);
+                        self.tabs[index].follow_refresh(document, url);
+                        redraw |= index == self.active_tab;
+                    }
+                }
+            }
+            // The task may have set or cleared timers.
+            self.collect_timer_requests();
+        }
+        redraw |= self.run_throttled_timers(now);
+
+        if redraw {
+            BrowserCommand::RequestRedraw
+        } else {
+            BrowserCommand::None
+        }
+    }
+
+    /// Runs the timers of background tabs that came due, once per
+    /// [`BACKGROUND_TIMER_INTERVAL`].
+    ///
+    /// A repeating timer that came due several times since the last wake-up runs once.
+    /// Timers of a tab that came to the front meanwhile run right away. Returns
+    /// whether they changed the active page.
+    fn run_throttled_timers(&mut self, now: Instant) -> bool {
+        let wake_up = now.duration_since(self.background_wakeup) >= BACKGROUND_TIMER_INTERVAL;
+        let active = self.tabs.get(self.active_tab);
+        let timers: Vec<_> = if wake_up {
+            self.background_wakeup = now;
+            std::mem::take(&mut self.throttled_timers)
+        } else {
+            let (front, rest) = self
+                .throttled_timers
+                .drain(..)
+                .partition(|(document, _)| active.is_some_and(|tab| tab.has_document(*document)));
+            self.throttled_timers = rest;
+            front
+        };
+        if timers.is_empty() {
+            return false;
+        }
+        for (document, timer) in timers {
+            if let Some(tab) = self.tabs.iter_mut().find(|tab| tab.has_document(document)) {
+                tab.fire_timer(document, timer);
+            }
+        }
+        self.collect_timer_requests();
+        self.tabs
+            .get(self.active_tab)
+            .is_some_and(Tab::needs_redraw)
+    }
+
+    /// Keeps track of how long each tab has been in the background and discards the
+    /// layout of the ones left there for longer than the discard delay.
+    fn update_background_tabs(&mut self, now: Instant) {
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            let hidden = index != self.active_tab;
+            tab.set_hidden(hidden, now);
+            if let Some(since) = tab.hidden_since()
+                && now.duration_since(since) >= self.background_discard_delay
+                && tab.discard_render_data()
+            {
+                log::debug!("Discarded the layout of background tab {}", tab.id());
+            }
+        }
+    }
+
+    /// Returns the approximate memory usage of each subsystem (decoded images,
+    /// glyphs, the HTTP cache and the DOMs of the open tabs).
+    pub fn memory_usage(&mut self) -> MemoryUsage {
+        self.update_dom_memory();
+        memory::registry().usage()
+    }
+
+    fn update_dom_memory(&self) {
+        self.dom_memory
+            .set(self.tabs.iter().map(Tab::memory_usage).sum());
+    }
+
+    /// Evicts caches when memory usage exceeds the budget in the settings, or when
+    /// the system is running out of memory.
+    ///
+    /// Under critical pressure the layouts of all background tabs are discarded
+    /// as well, without waiting for [`BACKGROUND_DISCARD_DELAY`].
+    fn check_memory(&mut self) {
+        if self.memory_checked_at.elapsed() < MEMORY_CHECK_INTERVAL {
+            return;
+        }
+        self.memory_checked_at = Instant::now();
+
+        self.update_dom_memory();
+        // 画像はデコードし直せるので、頼まれたらすべて捨てる
+        if self.image_memory.take_eviction_request().is_some() {
+            self.decoded_images.clear();
+            self.image_memory.set(0);
+        }
+        let registry = memory::registry();
+        registry.enforce_budget();
+        let Some(pressure) = memory::system_pressure() else {
+            return;
+        };
+        registry.relieve(pressure);
+        if pressure == MemoryPressure::Critical {
+            for (index, tab) in self.tabs.iter_mut().enumerate() {
+                if index != self.active_tab && tab.discard_render_data() {
+                    log::debug!("Discarded the layout of background tab {}", tab.id());
+                }
+            }
+        }
+    }
+
+    /// Sets how long a tab stays in the background before its layout is discarded
+    /// ([`BACKGROUND_DISCARD_DELAY`] by default). The layout is rebuilt when the tab
+    /// comes back to the front.
+    pub fn set_background_discard_delay(&mut self, delay: Duration) {
+        self.background_discard_delay = delay;
+    }
+
+    /// Moves the timers scripts asked for since the last call onto the scheduler.
+    ///
+    /// `<meta http-equiv="refresh">` directives of newly parsed pages are scheduled
+    /// here too, unless automatic refreshes are turned off in the settings.
+    fn collect_timer_requests(&mut self) {
+        let now = Instant::now();
+        for tab in &mut self.tabs {
+            if let Some((document, delay, url)) = tab.take_refresh() {
+                if self.settings.meta_refresh {
+                    let task = Task::Refresh { document, url };
+                    self.scheduler.set_timer(now, delay, false, task);
+                } else {
+                    log::info!("Ignoring <meta http-equiv=\"refresh\"> (disabled in settings)");
+                }
+            }
+            for (document, request) in tab.take_timer_requests() {
+                match request {
+                    TimerRequest::Set { id, delay, repeat } => {
+                        let task = Task::ScriptTimer {
+                            document,
+                            timer: id,
+                        };
+                        self.scheduler.set_timer(now, delay, repeat, task);
+                    }
+                    TimerRequest::Clear(id) => {
+                        let task = Task::ScriptTimer {
+                            document,
+                            timer: id,
+                        };
+                        self.scheduler.cancel_where(|t| *t == task);
+                        self.throttled_timers.retain(|t| *t != (document, id));
+                    }
+                }
+            }
+        }
+    }
+
+    /// タブ tab_id が要求した fetch を拡張機能とポリシーに通して送る
+    ///
+    /// 内部ページの読み込みで設定を変えたら true を返す。
+    fn start_fetch(
+        &mut self,
+        tab_id: usize,
+        url: Url,
+        kind: FetchKind,
+        bypass_cache: bool,
+        site_for_cookies: Option<Url>,
+    ) -> bool {
+        let tab = &mut self.tabs[tab_id];
+        let mut settings_changed = false;
+        log::info!("Fetch requested in App: url={}", url);
+        let mode = RequestMode::for_fetch(&kind);
+        let initiator = tab.initiator(&kind);
+        // ページの移動はリンク元、サブリソースは読み込む文書が Referer
+        let referrer = match kind {
+            FetchKind::Html => tab.referrer().cloned(),
+            _ => initiator.as_ref().map(|document| document.url.clone()),
+        };
+        // 拡張機能はプライベートタブと内部ページのリクエストには触らない
+        let action = if tab.is_private() || internal_pages::is_internal(&url) {
+            RequestAction::Continue
+        } else {
+            self.extensions.intercept_request(&InterceptedRequest {
+                url: &url,
+                document: initiator.as_ref().map(|document| &document.url),
+                resource: ResourceType::of(&kind),
+            })
+        };
+        // 先読みと画像の応答は文書が見つけた URL に結び付けるので、転送前の URL で待つ
+        let pending_url = match kind {
+            FetchKind::Preload(_) | FetchKind::Image => url.clone(),
+            _ => match action {
+                RequestAction::Redirect(ref to) => to.clone(),
+                _ => url.clone(),
+            },
+        };
+        let url = match action {
+            RequestAction::Redirect(ref to) => {
+                log::info!("Extension redirected {} to {}", url, to);
+                to.clone()
+            }
+            _ => url,
+        };
+        let id = self.pending_fetches.insert(tab_id, kind, pending_url);
+        if action == RequestAction::Block {
+            log::info!("Extension blocked {}", url);
+            tab.record_blocked_request();
+            let error = anyhow::anyhow!("Blocked by an extension");
+            self.network.respond(id, url, Err(error));
+        } else if tab.is_private() && self.extensions.handles_scheme(url.scheme()) {
+            // 拡張機能のスキームもプライベートタブでは読まない
+            let error = anyhow::anyhow!("Extensions are disabled in private tabs");
+            self.network.respond(id, url, Err(error));
+        } else if let Err(e) = fetch_policy::check_request(initiator.as_ref(), &url, "GET", mode) {
+            log::warn!("{}", e);
+            self.network.respond(id, url, Err(e.into()));
+        } else if internal_pages::is_internal(&url) {
+            // about: / orinium:// はネットワークに出さずにここで作る
+            if internal_pages::update_settings(&url, &mut self.settings) {
+                settings_changed = true;
+            }
+            let ctx = InternalPageContext {
+                history: &self.browsing_history,
+                settings: &self.settings,
+                inspection: self.inspection.as_ref(),
+                console: self.console.as_ref(),
+            };
+            let html = internal_pages::load(&url, &ctx);
+            self.network.respond(id, url, html.map(String::into_bytes));
+        } else {
+            // 別のオリジンへのスクリプトの要求には Origin を付ける
+            let mut request_headers = match (&initiator, mode) {
+                (Some(document), RequestMode::Cors) => {
+                    fetch_policy::request_headers(document, &url)
+                }
+                _ => Vec::new(),
+            };
+            if let Some(value) = referrer
+                .as_ref()
+                .and_then(|referrer| fetch_policy::referrer_header(referrer, &url))
+            {
+                request_headers.push(("Referer", value));
+            }
+            self.network.fetch_with_headers(
+                url,
+                id,
+                bypass_cache,
+                tab.storage_partition(),
+                site_for_cookies,
+                request_headers,
+            );
+        }
+        settings_changed
+    }
+
+    /// タブ tab_id の文書が知らせてきたオリジンへの接続を先に用意する
+    fn send_hint(&self, tab_id: usize, hint: ResourceHint) {
+        let (origin, hint) = match hint {
+            ResourceHint::DnsPrefetch(origin) => (origin, ConnectionHint::DnsPrefetch),
+            ResourceHint::Preconnect(origin) => (origin, ConnectionHint::Preconnect),
+        };
+        log::info!("Connection hint in App: {:?} {}", hint, origin);
+        self.network
+            .hint(&origin, hint, self.tabs[tab_id].storage_partition());
+    }
+
+    fn handle_network_messages(&mut self) {
+        for progress in self.network.try_receive_progress() {
+            if self.downloads.contains(progress.msg_id) {
+                self.downloads
+                    .on_progress(progress.msg_id, progress.received, progress.total);
+                continue;
+            }
+            if let Some(tab) = self
+                .pending_fetches
+                .tab_of(progress.msg_id)
+                .and_then(|tab_id| self.tabs.get_mut(tab_id))
+            {
+                tab.on_fetch_progress(progress.msg_id, progress.received, progress.total);
+            }
+        }
+
+        let messages = self.network.try_receive();
+        let mut visited = false;
+
+        for msg in messages {
+            log::info!("Network message received in App for fetch_id={}", msg.id);
+
+            if self.downloads.contains(msg.id) {
+                let result = match msg.response {
+                    Ok(resp) if resp.status.is_success() => Ok(()),
+                    Ok(resp) => Err(format!("HTTP {}", resp.status)),
+                    Err(e) => Err(e.to_string()),
+                };
+                match &result {
+                    Ok(()) => log::info!("Download finished for fetch_id={}", msg.id),
+                    Err(e) => log::error!("Download failed for fetch_id={}: {}", msg.id, e),
+                }
+                self.downloads.on_finished(msg.id, result);
+                continue;
+            }
+
+            // pending_fetches から fetch 情報を取得
+            let Some((tab_id, kind, url)) = self.pending_fetches.remove(msg.id) else {
+                log::warn!("No pending fetch found for fetch_id={}", msg.id);
+                continue;
+            };
+
+            // Tab を取得
+            let Some(tab) = self.tabs.get_mut(tab_id) else {
+                log::warn!("There is no Tab called id={}", tab_id);
+                continue;
+            };
+
+            tab.on_fetch_finished(msg.id);
+            // <iframe> の文書のための fetch は frame の文書に渡す
+            let (frame, kind) = kind.split_frame();
+            match msg.response {
+                // 本文のないエラー応答は空白のページではなくエラーページにする
+                Ok(resp)
+                    if matches!(kind, FetchKind::Html)
+                        && (resp.status.is_client_error() || resp.status.is_server_error())
+                        && resp.body.iter().all(u8::is_ascii_whitespace) =>
+                {
+                    log::error!("HTTP error {} for {}", resp.status, url);
+                    let err = BrowserNetworkError::HttpStatus(resp.status);
+                    match frame {
+                        Some(frame) => tab.on_frame_fetch_failed(frame, err, url),
+                        None => tab.on_fetch_failed(err, url),
+                    }
+                }
+                Ok(resp) => {
+                    log::info!("Fetch Done in App for tab_id={}", tab_id);
+
+                    match kind {
+                        FetchKind::Html => {
+                            let html = document_html(&url, &resp.headers, &resp.body);
+                            let csp = ContentSecurityPolicy::from_headers(&resp.headers);
+                            if let Some(frame) = frame {
+                                tab.isolate(|tab| tab.on_frame_html_fetched(frame, html, csp));
+                                continue;
+                            }
+
+                            tab.set_tls_info(resp.tls.clone());
+                            tab.set_content_security_policy(csp);
+                            // DOM を組み立てる前に、文書が読むものを取りに行く
+                            for task in tab.start_preloads(&html) {
+                                match task {
+                                    TabTask::Fetch {
+                                        url,
+                                        kind,
+                                        bypass_cache,
+                                        site_for_cookies,
+                                    } => {
+                                        self.start_fetch(
+                                            tab_id,
+                                            url,
+                                            kind,
+                                            bypass_cache,
+                                            site_for_cookies,
+                                        );
+                                    }
+                                    TabTask::Hint(hint) => self.send_hint(tab_id, hint),
+                                    TabTask::NeedsRedraw => {}
+                                }
+                            }
+                            let tab = &mut self.tabs[tab_id];
+                            tab.isolate(|tab| tab.on_fetch_succeeded_html(html));
+
+                            // 内部ページ、エラーページ、プライベートタブは閲覧履歴に残さない
+                            if !tab.is_error_page()
+                                && !tab.is_private()
+                                && !internal_pages::is_internal(&url)
+                            {
+                                let now = SystemTime::now()
+                                    .duration_since(UNIX_EPOCH)
+                                    .map(|d| d.as_secs())
+                                    .unwrap_or(0);
+                                self.visited_links.borrow_mut().add(&url);
+                                self.browsing_history.record_visit(url, tab.title(), now);
+                                visited = true;
+                            } else if tab.is_private() && !tab.is_error_page() {
+                                self.private_visited_links.borrow_mut().add(&url);
+                            }
+                        }
+                        FetchKind::Css => {
+                            let css = mime::decode_response(&resp.headers, &resp.body);
+                            tab.isolate(|tab| tab.on_fetch_succeeded_css(frame, css));
+                        }
+                        FetchKind::Script => {
+                            let js = mime::decode_response(&resp.headers, &resp.body);
+                            tab.isolate(|tab| tab.on_fetch_succeeded_script(frame, url, js));
+                        }
+                        // フォントは HTTP キャッシュに入れておくだけ
+                        FetchKind::Preload(
+                            PreloadDestination::Style | PreloadDestination::Script,
+                        ) => {
+                            let body = mime::decode_response(&resp.headers, &resp.body);
+                            tab.on_preload_fetched(&url, body);
+                        }
+                        // 画像はデコードのスレッドでデコードし、済んだら on_decoded で渡す
+                        FetchKind::Preload(PreloadDestination::Image) | FetchKind::Image => {
+                            let partition = tab.storage_partition();
+                            match self.decoded_images.get(&(partition, url.clone())) {
+                                Some(image) => {
+                                    tab.isolate(|tab| tab.on_image_decoded(&url, image));
+                                }
+                                None => self.decoder.decode_image(url, partition, resp.body),
+                            }
+                        }
+                        FetchKind::Preload(_) => {}
+                        FetchKind::ScriptRequest { document, request } => {
+                            if let Err(e) = check_cors_response(
+                                tab.initiator_of(document),
+                                &resp.url,
+                                &resp.headers,
+                            ) {
+                                log::warn!("{}", e);
+                                tab.on_script_request_done(document, request, Err(e.to_string()));
+                                continue;
+                            }
+                            let response = FetchResponse {
+                                status: resp.status.as_u16(),
+                                status_text: resp
+                                    .status
+                                    .canonical_reason()
+                                    .unwrap_or_default()
+                                    .to_string(),
+                                url: resp.url,
+                                body: mime::decode_response(&resp.headers, &resp.body),
+                            };
+                            tab.on_script_request_done(document, request, Ok(response));
+                        }
+                        // split_frame で外してある
+                        FetchKind::Frame { .. } => {}
+                    }
+                }
+                Err(err) => {
+                    log::error!("NetworkError: {}", err);
+                    match kind {
+                        FetchKind::Html => match frame {
+                            Some(frame) => tab.on_frame_fetch_failed(frame, err, url),
+                            None => tab.on_fetch_failed(err, url),
+                        },
+                        FetchKind::Css => tab.on_fetch_failed_css(frame, err, url),
+                        FetchKind::Script => tab.on_fetch_failed_script(frame, err, url),
+                        FetchKind::Preload(_) => {
+                            log::warn!("Failed to preload {}: {}", url, err);
+                            tab.on_preload_fetched(&url, String::new());
+                        }
+                        FetchKind::ScriptRequest { document, request } => {
+                            tab.on_script_request_done(document, request, Err(err.to_string()))
+                        }
+                        FetchKind::Image => log::warn!("Failed to load the image {}: {}", url, err),
+                        FetchKind::Frame { .. } => {}
+                    }
+                }
+            }
+        }
+
+        if visited {
+            self.save_browsing_history();
+        }
+        self.save_downloads_if_modified();
+    }
+
+    /// Returns the image decoded from `url` for tabs of `partition`, if it has
+    /// been fetched and decoded.
+    pub fn decoded_image(&self, partition: StoragePartition, url: &Url) -> Option<&Arc<RgbaImage>> {
+        self.decoded_images.get(&(partition, url.clone()))
+    }
+
+    /// Keeps an image decoded on a worker thread and hands it to the tabs of its
+    /// storage partition, whose pages lay themselves out again if they show it.
+    ///
+    /// The image is dropped if every tab of that partition has closed meanwhile.
+    fn on_decoded(&mut self, decoded: Decoded) -> BrowserCommand {
+        match decoded {
+            Decoded::Image {
+                url,
+                partition,
+                image: Ok(image),
+            } => {
+                let mut tabs = self
+                    .tabs
+                    .iter_mut()
+                    .filter(|tab| tab.storage_partition() == partition)
+                    .peekable();
+                if tabs.peek().is_none() {
+                    return BrowserCommand::None;
+                }
+                for tab in tabs {
+                    tab.isolate(|tab| tab.on_image_decoded(&url, &image));
+                }
+                self.decoded_images.insert((partition, url), image);
+                self.update_image_memory();
+                BrowserCommand::RequestRedraw
+            }
+            Decoded::Image {
+                url, image: Err(e), ..
+            } => {
+                log::warn!("Failed to decode the image {}: {}", url, e);
+                BrowserCommand::None
+            }
+        }
+    }
+
+    /// Counts the decoded images for the memory registry.
+    fn update_image_memory(&self) {
+        let bytes = self
+            .decoded_images
+            .values()
+            .map(|image| image.as_raw().len())
+            .sum();
+        self.image_memory.set(bytes);
+    }
+
+    /// Returns the tab at `index`, if any.
+    pub fn tab(&self, index: usize) -> Option<&Tab> {
+        self.tabs.get(index)
+    }
+
+    /// Returns the currently active tab, if any.
+    pub fn active_tab(&self) -> Option<&Tab> {
+        self.tabs.get(self.active_tab)
+    }
+
+    /// Returns a mutable reference to the currently active tab, if any.
+    fn active_tab_mut(&mut self) -> Option<&mut Tab> {
+        self.tabs.get_mut(self.active_tab)
+    }
+
+    /// Rebuilds the render tree for the active tab and generates draw commands.
+    fn rebuild_render_tree(&mut self) {
+        let now = Instant::now();
+        let viewport = self.viewport_css();
+
+        let content_blocking = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(|tab| self.content_blocking_badge(tab));
+        self.url_bar.set_content_blocking(content_blocking);
+        let (page_commands, animating) = match self.tabs.get_mut(self.active_tab) {
+            Some(tab) => {
+                // 裏にいる間に捨てたレイアウトは表に戻ったときに作り直す
+                tab.set_hidden(false, now);
+                tab.isolate(|tab| {
+                    tab.restore_render_data();
+                    tab.relayout(viewport);
+                });
+
+                let fling = self.input.touch.step_fling(now);
+                if let Some((dx, dy)) = fling {
+                    let (x, y) = self.input.touch.last_position();
+                    tab.scroll_at(x, y, dx, dy, viewport);
+                }
+                let animating = tab.animate_scroll(now) || self.input.touch.is_flinging();
+
+                // スクロールしたらスクロールバーを表示する
+                let page_scroll = tab.page_scroll().map(|(offset, _)| offset);
+                if page_scroll != self.render.last_page_scroll {
+                    if self.render.last_page_scroll.is_some() {
+                        self.render.scroll_bar_fade.activity(now);
+                    }
+                    self.render.last_page_scroll = page_scroll;
+                }
+
+                self.url_bar
+                    .show_url(tab.history().current().map(|entry| &entry.url));
+                self.url_bar.set_reader_mode(tab.is_reader_mode());
+                self.url_bar.set_security(tab.security());
+
+                let draw_commands = tab.isolate(|tab| tab.draw_commands());
+                if draw_commands.is_empty() {
+                    log::debug!("No layout/info available for active tab");
+                } else {
+                    tab.mark_painted();
+                }
+                if self.print_metrics
+                    && let Some(metrics) = tab.take_finished_metrics()
+                {
+                    let url = tab.document_url().map(|url| url.to_string());
+                    println!("{}\n{}", url.as_deref().unwrap_or("about:blank"), metrics);
+                }
+
+                (draw_commands, animating)
+            }
+            None => (Vec::new(), false),
+        };
+
+        let fading = self.render.scroll_bar_fade.step(now);
+        // ページの下にキャンバスの色を敷く（暗い配色に対応したページでは暗くなる）
+        let canvas = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(Tab::canvas_color)
+            .unwrap_or(ChromeTheme::for_scheme(self.color_scheme()).page_background);
+        let mut page_commands = page_commands;
+        page_commands.insert(
+            0,
+            DrawCommand::DrawRect {
+                x: 0.0,
+                y: 0.0,
+                width: viewport.0,
+                height: viewport.1,
+                color: canvas,
+            },
+        );
+        page_commands.extend(self.inspect_overlay_commands());
+        page_commands.extend(self.scroll_bar_commands());
+        self.update_autofill_menu();
+        self.render.draw_commands = self.compose_frame(page_commands);
+        // 読み込み中はタブのスピナーを回し続ける
+        let spinning = self.render.chrome_visible && self.tabs.iter().any(Tab::is_loading);
+        self.render.animating = animating || fading || spinning;
+    }
+
+    /// Places the page below the browser UI and draws the UI on top of it.
+    ///
+    /// Draw commands are scaled by the page zoom on the GPU, so the UI is drawn at
+    /// `1 / zoom` to keep its size independent of the zoom.
+    fn compose_frame(&self, page_commands: Vec<DrawCommand>) -> Vec<DrawCommand> {
+        if !self.render.chrome_visible {
+            return page_commands;
+        }
+
+        let zoom = self.zoom();
+        let (vw, vh) = self.viewport_css();
+        let mut commands = Vec::with_capacity(page_commands.len() + 16);
+        commands.push(DrawCommand::PushTransform {
+            dx: 0.0,
+            dy: self.chrome_height() / zoom,
+        });
+        commands.push(DrawCommand::PushClip {
+            x: 0.0,
+            y: 0.0,
+            width: vw,
+            height: vh,
+        });
+        commands.extend(page_commands);
+        commands.push(DrawCommand::PopClip);
+        commands.push(DrawCommand::PopTransform);
+
+        let width = self.logical_width();
+        let items: Vec<TabStripItem> = self
+            .tabs
+            .iter()
+            .map(|tab| TabStripItem {
+                title: tab.display_title(),
+                loading: tab.is_loading(),
+                private: tab.is_private(),
+            })
+            .collect();
+        let spinner_phase = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.subsec_millis() as f32 / 1000.0);
+        let platform_measurer = PlatformTextMeasurer::new().ok();
+        let measurer: &dyn TextMeasurer<TextStyle> = match platform_measurer.as_ref() {
+            Some(m) => m,
+            None => &FallbackTextMeasurer,
+        };
+
+        let theme = ChromeTheme::for_scheme(self.color_scheme());
+        let mut chrome = tab_strip::draw_commands(
+            self.tab_strip_width(),
+            &items,
+            self.active_tab,
+            spinner_phase,
+            theme,
+            measurer,
+        );
+        if self.settings.custom_titlebar {
+            chrome.extend(window_controls::draw_commands(width, theme));
+        }
+        chrome.push(DrawCommand::PushTransform {
+            dx: 0.0,
+            dy: TAB_STRIP_HEIGHT,
+        });
+        chrome.extend(self.url_bar.draw_commands(width, theme, measurer));
+        chrome.push(DrawCommand::PopTransform);
+        if let Some(tab) = self.tabs.get(self.active_tab)
+            && tab.is_loading()
+        {
+            chrome.extend(progress_bar::draw_commands(
+                width,
+                tab.progress().fraction(),
+            ));
+        }
+        if let Some(prompt) = &self.password_prompt {
+            chrome.extend(prompt.menu.draw_commands(self.logical_window_size(), theme));
+        } else if let Some(prompt) = self.permission_prompts.first() {
+            chrome.extend(prompt.menu.draw_commands(self.logical_window_size(), theme));
+        }
+        if let Some(spelling) = &self.spelling_menu {
+            chrome.extend(
+                spelling
+                    .menu
+                    .draw_commands(self.logical_window_size(), theme),
+            );
+        } else if let Some(autofill) = self.autofill_menu.as_ref().filter(|m| !m.hidden) {
+            chrome.extend(
+                autofill
+                    .menu
+                    .draw_commands(self.logical_window_size(), theme),
+            );
+        }
+        commands.extend(chrome.into_iter().map(|c| c.scaled(1.0 / zoom)));
+
+        commands
+    }
+
+    /// Returns the window width in logical pixels.
+    fn logical_width(&self) -> f32 {
+        self.window_size().0 / self.render.scale_factor as f32
+    }
+
+    /// Returns the width of the tab strip in logical pixels. When the browser
+    /// draws its own titlebar, the window buttons take the right end.
+    fn tab_strip_width(&self) -> f32 {
+        if self.settings.custom_titlebar {
+            (self.logical_width() - window_controls::WINDOW_CONTROLS_WIDTH).max(0.0)
+        } else {
+            self.logical_width()
+        }
+    }
+
+    /// Returns the window size in logical pixels.
+    fn logical_window_size(&self) -> (f32, f32) {
+        let (width, height) = self.window_size();
+        let sf = self.render.scale_factor as f32;
+        (width / sf, height / sf)
+    }
+
+    /// Handles a `winit` window event and returns a `BrowserCommand`.
+    pub fn handle_window_event(
+        &mut self,
+        event: WindowEvent,
+        gpu: &mut dyn Compositor,
+    ) -> BrowserCommand {
+        let browser_cmd = match event {
+            WindowEvent::CloseRequested => BrowserCommand::Exit,
+
+            WindowEvent::RedrawRequested => {
+                self.frames.begin_frame(Instant::now());
+                self.redraw(gpu);
+                // スクロールが収束するまで次のフレームを要求し続ける
+                if self.render.animating {
+                    self.frames.invalidate(Invalidation::Animation);
+                }
+                BrowserCommand::RenameWindowTitle
+            }
+
+            // レイアウトし直すのはコマンドを実行するとき
+            WindowEvent::Resized(size) => {
+                if size.width > 0 && size.height > 0 {
+                    gpu.resize(size);
+                }
+                BrowserCommand::Resize {
+                    width: size.width,
+                    height: size.height,
+                }
+            }
+
+            WindowEvent::ThemeChanged(theme) => {
+                self.set_system_color_scheme(match theme {
+                    winit::window::Theme::Dark => ColorScheme::Dark,
+                    winit::window::Theme::Light => ColorScheme::Light,
+                });
+                self.frames.invalidate(Invalidation::Style);
+                BrowserCommand::None
+            }
+
+            // 別の倍率のモニターに移った。新しい大きさは続く Resized で届くので、
+            // レイアウトは次のフレームで新しい CSS ピクセルの大きさに合わせてやり直す
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                self.set_scale_factor(scale_factor);
+                gpu.set_scale_factor(self.page_scale());
+                BrowserCommand::None
+            }
+
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.handle_scroll(delta);
+                self.frames.invalidate(Invalidation::Scroll);
+                BrowserCommand::None
+            }
+
+            WindowEvent::Touch(touch) => self.handle_touch(touch),
+
+            WindowEvent::PinchGesture { delta, .. } => {
+                self.zoom_active_tab(1.0 + delta as f32);
+                self.frames.invalidate(Invalidation::Style);
+                BrowserCommand::None
+            }
+
+            WindowEvent::CursorMoved { position, .. } => {
+                self.input.mouse_position = (position.x, position.y);
+                let (x, y) = self.mouse_position_logical();
+                let window = self.logical_window_size();
+                // メニューが開いている間はページにポインタの動きを届けない
+                if let Some(spelling) = &mut self.spelling_menu {
+                    if spelling.menu.hover_at(window, x, y) {
+                        BrowserCommand::RequestRedraw
+                    } else {
+                        BrowserCommand::None
+                    }
+                } else if let Some(prompt) = &mut self.password_prompt
+                    && prompt.menu.hover_at(window, x, y)
+                {
+                    BrowserCommand::RequestRedraw
+                } else if self.password_prompt.is_none()
+                    && let Some(prompt) = self.permission_prompts.first_mut()
+                    && prompt.menu.hover_at(window, x, y)
+                {
+                    BrowserCommand::RequestRedraw
+                } else if let Some(autofill) = self.autofill_menu.as_mut().filter(|m| !m.hidden)
+                    && autofill.menu.hover_at(window, x, y)
+                {
+                    BrowserCommand::RequestRedraw
+                } else {
+                    match self.handle_mouse_drag() {
+                        BrowserCommand::None => match self.handle_scroll_bar_hover() {
+                            // 調べるモードではポインタの下の要素の箱を描き直す
+                            BrowserCommand::None if self.inspecting => {
+                                BrowserCommand::RequestRedraw
+                            }
+                            BrowserCommand::None => self.handle_link_hover(),
+                            cmd => cmd,
+                        },
+                        cmd => cmd,
+                    }
+                }
+            }
+
+            WindowEvent::MouseInput { state, button, .. } => self.handle_mouse_input(state, button),
+
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.input.modifiers = modifiers.state();
+                BrowserCommand::None
+            }
+
+            WindowEvent::Ime(ime) => self.handle_ime(ime),
+
+            WindowEvent::KeyboardInput { event, .. } => {
+                // 変換中のキーは IME が使う
+                if event.state != ElementState::Pressed || self.is_composing() {
+                    BrowserCommand::None
+                } else if let Some(cmd) = self.handle_spelling_menu_key(&event.logical_key) {
+                    cmd
+                } else if let Some(cmd) = self.handle_autofill_menu_key(&event.logical_key) {
+                    cmd
+                } else if self.url_bar.is_focused() {
+                    self.handle_url_bar_key(&event.logical_key, event.text.as_deref(), gpu)
+                } else if !self.dispatch_key_down(&event.logical_key) {
+                    // ページが keydown の既定の動作を止めた
+                    BrowserCommand::None
+                } else if self
+                    .tabs
+                    .get(self.active_tab)
+                    .is_some_and(Tab::has_focused_input)
+                {
+                    self.handle_input_key(&event.logical_key, event.text.as_deref(), gpu)
+                } else {
+                    self.handle_key_pressed(&event.logical_key, gpu)
+                }
+            }
+
+            _ => BrowserCommand::None,
+        };
+        let cmd_from_tick = self.tick();
+        // 読み込みで変わった分は次のフレームでまとめて描く
+        if matches!(cmd_from_tick, BrowserCommand::RequestRedraw) {
+            self.frames.invalidate(Invalidation::Content);
+        }
+        match browser_cmd {
+            BrowserCommand::None => cmd_from_tick,
+            _ => browser_cmd,
+        }
+    }
+
+    /// Handles keyboard shortcuts that are not consumed by the page.
+    fn handle_key_pressed(&mut self, key: &Key, gpu: &mut dyn Compositor) -> BrowserCommand {
+        let mods = self.input.modifiers;
+
+        match key {
+            // Ctrl+L / Alt+D / F6: focus the URL bar
+            Key::Character(c)
+                if (mods.control_key() && c.eq_ignore_ascii_case("l"))
+                    || (mods.alt_key() && c.eq_ignore_ascii_case("d")) =>
+            {
+                self.focus_url_bar();
+                BrowserCommand::RequestRedraw
+            }
+            Key::Named(NamedKey::F6) => {
+                self.focus_url_bar();
+                BrowserCommand::RequestRedraw
+            }
+            // Ctrl+Shift+S: save screenshot
+            Key::Character(c)
+                if mods.control_key() && mods.shift_key() && c.eq_ignore_ascii_case("s") =>
+            {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let path = format!("orinium-screenshot-{now}.png");
+                match self.save_screenshot(gpu, &path) {
+                    Ok(()) => log::info!("Screenshot saved to {}", path),
+                    Err(e) => log::error!("Failed to save screenshot: {}", e),
+                }
+                BrowserCommand::None
+            }
+            // Ctrl+P: save the page as PDF
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("p") => {
+                match self.save_page_as_pdf() {
+                    Ok(path) => log::info!("Page saved to {}", path.display()),
+                    Err(e) => log::error!("Failed to print the page: {:#}", e),
+                }
+                BrowserCommand::None
+            }
+            // Ctrl+Shift+C: pick an element to inspect
+            Key::Character(c)
+                if mods.control_key() && mods.shift_key() && c.eq_ignore_ascii_case("c") =>
+            {
+                self.inspecting = !self.inspecting;
+                BrowserCommand::RequestRedraw
+            }
+            // Ctrl+Shift+F: show or hide the frame statistics
+            Key::Character(c)
+                if mods.control_key() && mods.shift_key() && c.eq_ignore_ascii_case("f") =>
+            {
+                self.render.show_frame_stats = !self.render.show_frame_stats;
+                BrowserCommand::RequestRedraw
+            }
+            // Ctrl+Shift+J: show the console of the page
+            Key::Character(c)
+                if mods.control_key() && mods.shift_key() && c.eq_ignore_ascii_case("j") =>
+            {
+                self.show_console()
+            }
+            // Ctrl+plus / Ctrl+minus / Ctrl+0: page zoom
+            Key::Character(c)
+                if mods.control_key() && matches!(c.as_str(), "+" | "=" | "-" | "0") =>
+            {
+                let Some(tab) = self.active_tab_mut() else {
+                    return BrowserCommand::None;
+                };
+                match c.as_str() {
+                    "-" => tab.zoom_out(),
+                    "0" => tab.reset_zoom(),
+                    _ => tab.zoom_in(),
+                }
+                log::info!("Zoom: {:.0}%", tab.zoom() * 100.0);
+                self.frames.invalidate(Invalidation::Style);
+                BrowserCommand::None
+            }
+            // Ctrl+Alt+R / F9: reader mode
+            Key::Character(c)
+                if mods.control_key() && mods.alt_key() && c.eq_ignore_ascii_case("r") =>
+            {
+                BrowserCommand::ToggleReaderMode
+            }
+            Key::Named(NamedKey::F9) => BrowserCommand::ToggleReaderMode,
+            // Ctrl+Shift+Delete: clear the browsing data
+            Key::Named(NamedKey::Delete) if mods.control_key() && mods.shift_key() => {
+                BrowserCommand::ClearBrowsingData
+            }
+            // Ctrl+Shift+N: open a private tab
+            Key::Character(c)
+                if mods.control_key() && mods.shift_key() && c.eq_ignore_ascii_case("n") =>
+            {
+                BrowserCommand::NewPrivateTab
+            }
+            // Ctrl+T / Ctrl+W: open / close a tab
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("t") => {
+                BrowserCommand::NewTab
+            }
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("w") => {
+                BrowserCommand::CloseTab
+            }
+            // Ctrl+Tab / Ctrl+Shift+Tab, Ctrl+PageDown / Ctrl+PageUp: cycle tabs
+            Key::Named(NamedKey::Tab) if mods.control_key() => {
+                if mods.shift_key() {
+                    BrowserCommand::PreviousTab
+                } else {
+                    BrowserCommand::NextTab
+                }
+            }
+            // Tab / Shift+Tab: move the keyboard focus within the page
+            Key::Named(NamedKey::Tab) if !mods.alt_key() => {
+                if let Some(tab) = self.active_tab_mut() {
+                    tab.focus_next(mods.shift_key());
+                }
+                BrowserCommand::RequestRedraw
+            }
+            // Enter: activate the focused link or button
+            Key::Named(NamedKey::Enter) => {
+                match self.active_tab_mut().and_then(|tab| tab.activate_focused()) {
+                    Some(link) => Self::open_tab_command(link, false),
+                    None => BrowserCommand::RequestRedraw,
+                }
+            }
+            Key::Named(NamedKey::PageDown) if mods.control_key() => BrowserCommand::NextTab,
+            Key::Named(NamedKey::PageUp) if mods.control_key() => BrowserCommand::PreviousTab,
+            // Ctrl+1..8: jump to a tab, Ctrl+9: the last tab
+            Key::Character(c)
+                if mods.control_key()
+                    && matches!(
+                        c.as_str(),
+                        "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9"
+                    ) =>
+            {
+                match c.as_str() {
+                    "9" => BrowserCommand::SelectLastTab,
+                    digit => BrowserCommand::SelectTab(digit.parse::<usize>().unwrap_or(1) - 1),
+                }
+            }
+            // F5 / Ctrl+R: reload, Shift+F5 / Ctrl+Shift+R: reload bypassing the cache
+            Key::Named(NamedKey::F5) => BrowserCommand::Reload {
+                bypass_cache: mods.shift_key() || mods.control_key(),
+            },
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("r") => {
+                BrowserCommand::Reload {
+                    bypass_cache: mods.shift_key(),
+                }
+            }
+            // Escape: leave inspect mode, otherwise stop loading
+            Key::Named(NamedKey::Escape) if self.inspecting => {
+                self.inspecting = false;
+                BrowserCommand::RequestRedraw
+            }
+            Key::Named(NamedKey::Escape) => BrowserCommand::StopLoading,
+            // Alt+Home: home page
+            Key::Named(NamedKey::Home) if mods.alt_key() => {
+                self.navigate_active_tab(self.settings.homepage.clone());
+                BrowserCommand::RequestRedraw
+            }
+            // Alt+Left / Alt+Right: history back / forward
+            Key::Named(NamedKey::ArrowLeft) if mods.alt_key() => self.go_back(),
+            Key::Named(NamedKey::ArrowRight) if mods.alt_key() => self.go_forward(),
+            // Arrow / Page / Home / End / Space keys: scroll
+            Key::Named(
+                named @ (NamedKey::ArrowUp
+                | NamedKey::ArrowDown
+                | NamedKey::PageUp
+                | NamedKey::PageDown
+                | NamedKey::Home
+                | NamedKey::End
+                | NamedKey::Space),
+            ) => {
+                let page = self.viewport_css().1 * PAGE_SCROLL_RATIO;
+                let line = LINE_SCROLL_AMOUNT * self.settings.scroll_speed;
+                let dy = match named {
+                    NamedKey::ArrowUp => -line,
+                    NamedKey::ArrowDown => line,
+                    NamedKey::PageUp => -page,
+                    NamedKey::Space if mods.shift_key() => -page,
+                    // 範囲外はスクロール時に端へ丸められる
+                    NamedKey::Home => f32::NEG_INFINITY,
+                    NamedKey::End => f32::INFINITY,
+                    _ => page,
+                };
+                self.scroll_active_tab(0.0, dy);
+                BrowserCommand::RequestRedraw
+            }
+            // Ctrl+C: copy selected text
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("c") => {
+                if let Some(text) = self.copy_selection()
+                    && let Err(e) = clipboard::write_text(&text)
+                {
+                    log::error!("Failed to copy selection: {}", e);
+                }
+                BrowserCommand::None
+            }
+            _ => BrowserCommand::None,
+        }
+    }
+
+    /// Navigates the active tab one entry back in its history.
+    ///
+    /// The scroll position of the page being left is remembered and restored when
+    /// the user returns to it.
+    pub fn go_back(&mut self) -> BrowserCommand {
+        self.cancel_fetches(self.active_tab);
+        match self.active_tab_mut() {
+            Some(tab) if tab.go_back() => BrowserCommand::RequestRedraw,
+            _ => BrowserCommand::None,
+        }
+    }
+
+    /// Navigates the active tab one entry forward in its history.
+    pub fn go_forward(&mut self) -> BrowserCommand {
+        self.cancel_fetches(self.active_tab);
+        match self.active_tab_mut() {
+            Some(tab) if tab.go_forward() => BrowserCommand::RequestRedraw,
+            _ => BrowserCommand::None,
+        }
+    }
+
+    /// Executes a command that acts on the browser itself (tabs, loading) and
+    /// returns what the platform should do next.
+    ///
+    /// Commands addressed to the platform (redraw, exit, title) are returned as is.
+    pub fn execute(&mut self, cmd: BrowserCommand) -> BrowserCommand {
+        let result = self.execute_command(cmd);
+        // 終了する前に開いているタブを保存する
+        if matches!(result, BrowserCommand::Exit) {
+            self.save_session();
+        }
+        result
+    }
+
+    /// Returns a sender that queues commands for [`process_commands`](Self::process_commands).
+    pub fn command_sender(&self) -> CommandSender {
+        CommandSender(self.command_tx.clone())
+    }
+
+    /// Sets where the browser reports redraws, title changes, load progress and
+    /// exit (the `EventLoopProxy` of the window).
+    pub fn set_event_sink(&mut self, sink: Box<dyn EventSink>) {
+        self.event_sink = Some(sink);
+    }
+
+    /// Executes the queued commands and reports what changed to the event sink.
+    pub fn process_commands(&mut self) {
+        let commands: Vec<_> = self.command_rx.try_iter().collect();
+        let mut exit = false;
+        for cmd in commands {
+            let reason = match cmd {
+                BrowserCommand::Scroll { .. } => Invalidation::Scroll,
+                BrowserCommand::Resize { .. } => Invalidation::Resize,
+                _ => Invalidation::Content,
+            };
+            match self.execute(cmd) {
+                BrowserCommand::None | BrowserCommand::RenameWindowTitle => {}
+                BrowserCommand::RequestRedraw => self.frames.invalidate(reason),
+                BrowserCommand::Exit => exit = true,
+                // 続けて実行するコマンドは次の回に回す
+                next => {
+                    let _ = self.command_tx.send(next);
+                }
+            }
+        }
+
+        // 何度 invalidate されても、頼むフレームはリフレッシュごとに 1 つ
+        if self.frames.poll(Instant::now()) {
+            self.emit(BrowserEvent::NeedsRedraw);
+        }
+
+        let title = self.window_title();
+        if title != self.reported_title {
+            self.reported_title = title.clone();
+            self.emit(BrowserEvent::TitleChanged(title));
+        }
+        let progress = self
+            .tabs
+            .get(self.active_tab)
+            .filter(|tab| tab.is_loading())
+            .map(|tab| tab.progress().fraction());
+        if progress != self.reported_progress {
+            self.reported_progress = progress;
+            self.emit(BrowserEvent::LoadProgress(progress));
+            // 進み具合はツールバーの下に描く
+            self.frames.invalidate(Invalidation::Content);
+        }
+        let decorations = !self.settings.custom_titlebar;
+        if self.reported_decorations != Some(decorations) {
+            self.reported_decorations = Some(decorations);
+            self.emit(BrowserEvent::Decorations(decorations));
+        }
+        if exit {
+            self.emit(BrowserEvent::Exit);
+        }
+    }
+
+    /// Sets the refresh rate of the display the window is on, in millihertz.
+    ///
+    /// Frames are requested at most once per refresh; 0 (unknown) keeps 60 Hz.
+    pub fn set_refresh_rate(&mut self, millihertz: u32) {
+        self.frames.set_refresh_rate(millihertz);
+    }
+
+    fn emit(&self, event: BrowserEvent) {
+        if let Some(sink) = &self.event_sink {
+            sink.send(event);
+        }
+    }
+
+    fn execute_command(&mut self, cmd: BrowserCommand) -> BrowserCommand {
+        match cmd {
+            BrowserCommand::Navigate(url) => {
+                self.navigate_active_tab(url);
+                BrowserCommand::RequestRedraw
+            }
+            BrowserCommand::Decoded(decoded) => self.on_decoded(decoded),
+            BrowserCommand::Scroll { dx, dy } => {
+                self.scroll_active_tab(dx, dy);
+                BrowserCommand::RequestRedraw
+            }
+            BrowserCommand::Click { x, y } => {
+                let Some(tab) = self.active_tab_mut() else {
+                    return BrowserCommand::None;
+                };
+                match Self::handle_mouse_click(tab, x, y, false) {
+                    Some(link) => self.execute_command(Self::open_tab_command(link, false)),
+                    None => BrowserCommand::RequestRedraw,
+                }
+            }
+            // 最小化されたときは大きさが 0 になるので、レイアウトし直さない
+            BrowserCommand::Resize { width, height } if width == 0 || height == 0 => {
+                BrowserCommand::None
+            }
+            BrowserCommand::Resize { width, height } => {
+                self.set_window_size((width, height));
+                BrowserCommand::RequestRedraw
+            }
+            BrowserCommand::Reload { bypass_cache } => {
+                self.reload(bypass_cache);
+                BrowserCommand::RequestRedraw
+            }
+            BrowserCommand::StopLoading => {
+                self.stop_loading();
+                BrowserCommand::RequestRedraw
+            }
+            BrowserCommand::NewTab => self.new_tab(),
+            BrowserCommand::OpenTab {
+                url,
+                background,
+                referrer,
+                opener,
+            } => self.open_tab(
+                NewTabLink {
+                    url,
+                    referrer,
+                    opener,
+                },
+                background,
+            ),
+            BrowserCommand::NewPrivateTab => self.new_private_tab(),
+            BrowserCommand::CloseTab => self.close_tab(self.active_tab),
+            BrowserCommand::NextTab => {
+                let count = self.tabs.len().max(1);
+                self.switch_tab((self.active_tab + 1) % count)
+            }
+            BrowserCommand::PreviousTab => {
+                let count = self.tabs.len().max(1);
+                self.switch_tab((self.active_tab + count - 1) % count)
+            }
+            BrowserCommand::SelectTab(index) => self.switch_tab(index),
+            BrowserCommand::SelectLastTab => self.switch_tab(self.tabs.len().saturating_sub(1)),
+            BrowserCommand::ToggleReaderMode => self.toggle_reader_mode(),
+            BrowserCommand::ClearBrowsingData => {
+                self.clear_browsing_data();
+                BrowserCommand::RequestRedraw
+            }
+            BrowserCommand::None
+            | BrowserCommand::Exit
+            | BrowserCommand::RequestRedraw
+            | BrowserCommand::RenameWindowTitle => cmd,
+        }
+    }
+
+    /// Switches the active tab between the page and its extracted article.
+    pub fn toggle_reader_mode(&mut self) -> BrowserCommand {
+        let mut options = self.settings.reader;
+        // 暗い配色では明るいリーダーの代わりに暗いリーダーを使う
+        if self.color_scheme() == ColorScheme::Dark && options.theme == ReaderTheme::Light {
+            options.theme = ReaderTheme::Dark;
+        }
+        match self.tabs.get_mut(self.active_tab) {
+            Some(tab) if tab.toggle_reader_mode(&options) => {
+                self.render.last_page_scroll = None;
+                BrowserCommand::RequestRedraw
+            }
+            _ => BrowserCommand::None,
+        }
+    }
+
+    /// Reloads the page shown in the active tab, keeping its scroll position.
+    ///
+    /// With `bypass_cache`, every request of the reloaded page asks intermediate
+    /// caches to revalidate (`Cache-Control: no-cache`).
+    pub fn reload(&mut self, bypass_cache: bool) {
+        self.cancel_fetches(self.active_tab);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.reload(bypass_cache);
+        }
+    }
+
+    /// Cancels the in-flight requests of the active tab and shows what has loaded so far.
+    pub fn stop_loading(&mut self) {
+        self.cancel_fetches(self.active_tab);
+        if let Some(tab) = self.active_tab_mut() {
+            tab.stop_loading();
+        }
+    }
+
+    /// Cancels every pending network request issued for `tab_id`.
+    fn cancel_fetches(&mut self, tab_id: usize) {
+        for id in self.pending_fetches.remove_tab(tab_id) {
+            self.network.cancel(id);
+        }
+    }
+
+    /// Edits the focused URL bar. Keys it does not use fall through to the
+    /// browser shortcuts.
+    fn handle_url_bar_key(
+        &mut self,
+        key: &Key,
+        text: Option<&str>,
+        gpu: &mut dyn Compositor,
+    ) -> BrowserCommand {
+        let mods = self.input.modifiers;
+        let text_before = self.url_bar.text().to_string();
+        let bar = &mut self.url_bar;
+
+        match key {
+            Key::Named(NamedKey::Enter) => self.submit_url_bar(),
+            Key::Named(NamedKey::ArrowDown) => bar.select_next_suggestion(),
+            Key::Named(NamedKey::ArrowUp) => bar.select_previous_suggestion(),
+            Key::Named(NamedKey::Escape) => bar.blur(),
+            Key::Named(NamedKey::Backspace) => bar.backspace(),
+            Key::Named(NamedKey::Delete) => bar.delete(),
+            Key::Named(NamedKey::ArrowLeft) => bar.move_left(),
+            Key::Named(NamedKey::ArrowRight) => bar.move_right(),
+            Key::Named(NamedKey::Home) => bar.move_home(),
+            Key::Named(NamedKey::End) => bar.move_end(),
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("a") => {
+                bar.select_all()
+            }
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("c") => {
+                if let Err(e) = clipboard::write_text(bar.text()) {
+                    log::error!("Failed to copy URL: {}", e);
+                }
+                return BrowserCommand::None;
+            }
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("v") => {
+                match clipboard::read_text() {
+                    Ok(pasted) => bar.insert_str(&pasted),
+                    Err(e) => log::error!("Failed to paste: {}", e),
+                }
+            }
+            _ if mods.control_key() || mods.alt_key() => return self.handle_key_pressed(key, gpu),
+            _ => match text.filter(|t| !t.chars().all(char::is_control)) {
+                Some(text) => bar.insert_str(text),
+                None => return self.handle_key_pressed(key, gpu),
+            },
+        }
+
+        if self.url_bar.text() != text_before {
+            self.update_url_suggestions();
+        }
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Focuses the URL bar, taking the focus away from the text field of the page.
+    fn focus_url_bar(&mut self) {
+        if let Some(tab) = self.active_tab_mut() {
+            tab.blur_input();
+        }
+        self.url_bar.focus();
+    }
+
+    /// Edits the focused text field of the active page. Keys it does not use
+    /// fall through to the browser shortcuts.
+    fn handle_input_key(
+        &mut self,
+        key: &Key,
+        text: Option<&str>,
+        gpu: &mut dyn Compositor,
+    ) -> BrowserCommand {
+        let mods = self.input.modifiers;
+        // Shift で選択を広げ、Ctrl で単語ごとに動かす
+        let extend = mods.shift_key();
+        let by_word = mods.control_key();
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return BrowserCommand::None;
+        };
+
+        match key {
+            Key::Named(NamedKey::Escape) => tab.blur_input(),
+            Key::Named(NamedKey::Enter) => tab.submit_focused_input(),
+            Key::Named(NamedKey::Backspace) => {
+                tab.edit_input(TextEdit::backspace);
+            }
+            Key::Named(NamedKey::Delete) => {
+                tab.edit_input(TextEdit::delete);
+            }
+            Key::Named(NamedKey::ArrowLeft) if by_word => {
+                tab.edit_input(|e| e.move_word_left(extend));
+            }
+            Key::Named(NamedKey::ArrowRight) if by_word => {
+                tab.edit_input(|e| e.move_word_right(extend));
+            }
+            Key::Named(NamedKey::ArrowLeft) => {
+                tab.edit_input(|e| e.move_left(extend));
+            }
+            Key::Named(NamedKey::ArrowRight) => {
+                tab.edit_input(|e| e.move_right(extend));
+            }
+            Key::Named(NamedKey::Home) => {
+                tab.edit_input(|e| e.move_home(extend));
+            }
+            Key::Named(NamedKey::End) => {
+                tab.edit_input(|e| e.move_end(extend));
+            }
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("a") => {
+                tab.edit_input(TextEdit::select_all);
+            }
+            // Ctrl+C / Ctrl+X: copy / cut the selected text
+            Key::Character(c)
+                if mods.control_key()
+                    && (c.eq_ignore_ascii_case("c") || c.eq_ignore_ascii_case("x")) =>
+            {
+                let Some(selected) = tab.input_selected_text() else {
+                    return BrowserCommand::None;
+                };
+                if let Err(e) = clipboard::write_text(&selected) {
+                    log::error!("Failed to copy selection: {}", e);
+                    return BrowserCommand::None;
+                }
+                if c.eq_ignore_ascii_case("x") {
+                    tab.edit_input(|e| {
+                        e.delete_selection();
+                    });
+                }
+            }
+            Key::Character(c) if mods.control_key() && c.eq_ignore_ascii_case("v") => {
+                match clipboard::read_text() {
+                    Ok(pasted) => {
+                        tab.edit_input(|e| e.insert(&pasted));
+                    }
+                    Err(e) => log::error!("Failed to paste: {}", e),
+                }
+            }
+            _ if mods.control_key() || mods.alt_key() => return self.handle_key_pressed(key, gpu),
+            _ => match text.filter(|t| !t.chars().all(char::is_control)) {
+                Some(text) => {
+                    tab.edit_input(|e| e.insert(text));
+                }
+                None => return self.handle_key_pressed(key, gpu),
+            },
+        }
+
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Handles IME composition for the URL bar or the focused text field of
+    /// the page. Uncommitted text is shown at the caret until it is committed.
+    fn handle_ime(&mut self, ime: Ime) -> BrowserCommand {
+        let (preedit, cursor, commit) = match ime {
+            Ime::Preedit(text, cursor) => (text, cursor.map(|(start, _)| start), None),
+            Ime::Commit(text) => (String::new(), None, Some(text)),
+            Ime::Enabled | Ime::Disabled => (String::new(), None, None),
+        };
+
+        if self.url_bar.is_focused() {
+            let text_before = self.url_bar.text().to_string();
+            match commit {
+                Some(text) => self.url_bar.commit_preedit(&text),
+                None => self.url_bar.set_preedit(&preedit, cursor),
+            }
+            if self.url_bar.text() != text_before {
+                self.update_url_suggestions();
+            }
+            return BrowserCommand::RequestRedraw;
+        }
+
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return BrowserCommand::None;
+        };
+        let edited = match commit {
+            Some(text) => tab.edit_input(|e| e.commit(&text)),
+            None => tab.edit_input(|e| e.set_preedit(&preedit, cursor)),
+        };
+        if edited {
+            BrowserCommand::RequestRedraw
+        } else {
+            BrowserCommand::None
+        }
+    }
+
+    /// Sends a `keydown` event to the focused element of the active page.
+    /// Returns `false` when the page prevented the default action.
+    fn dispatch_key_down(&mut self, key: &Key) -> bool {
+        let name = match key {
+            Key::Character(c) => c.to_string(),
+            Key::Named(NamedKey::Space) => " ".to_string(),
+            Key::Named(named) => format!("{named:?}"),
+            _ => "Unidentified".to_string(),
+        };
+        self.active_tab_mut().is_none_or(|tab| tab.key_down(&name))
+    }
+
+    /// Returns whether IME composition is in progress.
+    fn is_composing(&self) -> bool {
+        if self.url_bar.is_focused() {
+            return self.url_bar.preedit().is_some();
+        }
+        self.tabs
+            .get(self.active_tab)
+            .is_some_and(Tab::is_composing)
+    }
+
+    /// Fills the URL bar dropdown with visited pages matching the typed text.
+    fn update_url_suggestions(&mut self) {
+        let suggestions = if self.url_bar.is_focused() && !self.url_bar.is_all_selected() {
+            self.browsing_history
+                .suggest(self.url_bar.text(), MAX_URL_SUGGESTIONS)
+                .into_iter()
+                .map(|visit| Suggestion {
+                    url: visit.url.clone(),
+                    title: visit.title.clone(),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        self.url_bar.set_suggestions(suggestions);
+    }
+
+    /// Navigates the active tab to the URL bar input.
+    ///
+    /// Host names without a scheme get `https://`; anything that is not a URL is
+    /// sent to the search engine.
+    fn submit_url_bar(&mut self) {
+        let search_engine = &self.settings.search_engine;
+        let url = match self.url_bar.selected_suggestion() {
+            Some(suggestion) => suggestion.url.clone(),
+            None => match url_bar::resolve_input(self.url_bar.text(), search_engine) {
+                Some(url) => url,
+                None => return,
+            },
+        };
+        self.navigate_active_tab(url);
+    }
+
+    /// Navigates the active tab (opening one if there is none) to `url`.
+    fn navigate_active_tab(&mut self, url: Url) {
+        self.url_bar.blur();
+
+        if self.tabs.is_empty() {
+            self.add_tab(Tab::new());
+            self.active_tab = 0;
+        }
+        self.cancel_fetches(self.active_tab);
+        if let Some(tab) = self.active_tab_mut() {
+            log::info!("Navigating to {}", url);
+            tab.navigate(url);
+        }
+    }
+
+    /// Returns the text currently selected in the active tab, if any.
+    pub fn copy_selection(&self) -> Option<String> {
+        self.tabs.get(self.active_tab)?.selected_text()
+    }
+
+    /// Captures the current frame and writes it to `path` as a PNG image.
+    ///
+    /// # Errors
+    /// Returns an error if the GPU read-back fails or the file cannot be written.
+    pub fn save_screenshot(&mut self, gpu: &mut dyn Compositor, path: &str) -> Result<()> {
+        self.apply_draw_commands(gpu);
+        let image = gpu.capture_frame()?;
+        image.save(path)?;
+        Ok(())
+    }
+
+    /// Lays out the active tab on `paper` with `@media print` styles and returns
+    /// it as a PDF document, one PDF page per printed page.
+    ///
+    /// # Errors
+    /// Returns an error if no page is open in the active tab.
+    pub fn print_to_pdf(&self, paper: PaperSize) -> Result<Vec<u8>> {
+        let tab = self
+            .tabs
+            .get(self.active_tab)
+            .ok_or_else(|| anyhow::anyhow!("No tab is open"))?;
+        let page = paper.content_size();
+        let (layout, info) = tab
+            .print_layout(page)
+            .ok_or_else(|| anyhow::anyhow!("No page is open"))?;
+        let commands = renderer_model::generate_draw_commands(&layout, &info);
+        let pages = paginate::paginate(&layout, &info, &commands, page);
+        Ok(pdf::write_pdf(&pages, paper, &tab.display_title()))
+    }
+
+    /// Prints the active tab to an A4 PDF in the downloads directory and returns its path.
+    ///
+    /// The file is named after the page title.
+    pub fn save_page_as_pdf(&self) -> Result<PathBuf> {
+        let pdf = self.print_to_pdf(PaperSize::A4)?;
+        let title = self
+            .tabs
+            .get(self.active_tab)
+            .map(Tab::display_title)
+            .unwrap_or_default();
+        io::save_download(&io::downloads_dir()?, &pdf_file_name(&title), &pdf)
+    }
+
+    /// Handles a click while the spelling menu is open, or a right click that opens
+    /// it on a misspelled word. Returns `None` to handle the click as usual.
+    ///
+    /// While the menu is open no click reaches the page. Releasing a button over a
+    /// suggestion replaces the word with it; releasing it elsewhere closes the menu,
+    /// except for the release of the right click that opened it.
+    fn handle_spelling_menu_click(
+        &mut self,
+        state: ElementState,
+        button: winit::event::MouseButton,
+    ) -> Option<BrowserCommand> {
+        let (x, y) = self.mouse_position_logical();
+        let window = self.logical_window_size();
+        if let Some(spelling) = &mut self.spelling_menu {
+            if state == ElementState::Pressed {
+                return Some(BrowserCommand::None);
+            }
+            let hit = spelling.menu.hit_test(window, x, y);
+            if hit.is_none() && !spelling.armed {
+                spelling.armed = true;
+                return Some(BrowserCommand::None);
+            }
+            let spelling = self.spelling_menu.take()?;
+            if let Some(i) = hit
+                && let Some(tab) = self.active_tab_mut()
+            {
+                tab.replace_input_range(spelling.range, &spelling.suggestions[i]);
+            }
+            return Some(BrowserCommand::RequestRedraw);
+        }
+        if state != ElementState::Pressed
+            || button != winit::event::MouseButton::Right
+            || y < self.chrome_height()
+        {
+            return None;
+        }
+
+        let (page_x, page_y) = self.mouse_position_css();
+        let misspelling = self
+            .tabs
+            .get(self.active_tab)?
+            .misspelling_at(page_x, page_y)?;
+        let items = if misspelling.suggestions.is_empty() {
+            vec![MenuItem::disabled("No suggestions")]
+        } else {
+            misspelling.suggestions.iter().map(MenuItem::new).collect()
+        };
+        self.spelling_menu = Some(SpellingMenu {
+            menu: ContextMenu::new((x, y), items),
+            range: misspelling.range,
+            suggestions: misspelling.suggestions,
+            armed: false,
+        });
+        Some(BrowserCommand::RequestRedraw)
+    }
+
+    /// Handles a key press while the spelling menu is open. Returns `None` to
+    /// handle the key as usual.
+    ///
+    /// The arrow keys move the highlight, Enter picks the highlighted suggestion
+    /// and Escape closes the menu. Other keys close it and reach the page.
+    fn handle_spelling_menu_key(&mut self, key: &Key) -> Option<BrowserCommand> {
+        let spelling = self.spelling_menu.as_mut()?;
+        match key {
+            Key::Named(NamedKey::ArrowDown) => spelling.menu.highlight_next(),
+            Key::Named(NamedKey::ArrowUp) => spelling.menu.highlight_previous(),
+            Key::Named(NamedKey::Enter) => {
+                let spelling = self.spelling_menu.take()?;
+                if let Some(i) = spelling.menu.highlighted()
+                    && let Some(tab) = self.active_tab_mut()
+                {
+                    tab.replace_input_range(spelling.range, &spelling.suggestions[i]);
+                }
+            }
+            Key::Named(NamedKey::Escape) => self.spelling_menu = None,
+            _ => {
+                self.spelling_menu = None;
+                return None;
+            }
+        }
+        Some(BrowserCommand::RequestRedraw)
+    }
+
+    /// Shows the autofill suggestions for the focused text field below it, or
+    /// hides them when the field has no matching values.
+    ///
+    /// In a login form the usernames saved for the site are offered instead, and
+    /// picking one fills in its password as well.
+    ///
+    /// The highlighted item and a menu hidden with Escape are kept while the
+    /// field and its value stay the same.
+    fn update_autofill_menu(&mut self) {
+        let Some(tab) = self
+            .tabs
+            .get(self.active_tab)
+            .filter(|_| !self.url_bar.is_focused())
+        else {
+            self.autofill_menu = None;
+            return;
+        };
+        let login = tab.login_field().filter(|_| self.settings.passwords);
+        let (field, typed, origin) = match login {
+            Some((origin, username)) => (None, username, Some(origin)),
+            None => match tab.autofill_field().filter(|_| self.settings.autofill) {
+                Some((field, typed)) => (Some(field), typed, None),
+                None => {
+                    self.autofill_menu = None;
+                    return;
+                }
+            },
+        };
+        let Some((x, y, _, height)) = tab.focused_input_rect() else {
+            self.autofill_menu = None;
+            return;
+        };
+        let (_, viewport_height) = self.viewport_css();
+        let bottom = y + height;
+        if bottom < 0.0 || y > viewport_height {
+            self.autofill_menu = None;
+            return;
+        }
+
+        let zoom = self.zoom();
+        let position = (x * zoom, bottom * zoom + self.chrome_height());
+        if let Some(autofill) = &mut self.autofill_menu
+            && autofill.field == field
+            && autofill.typed == typed
+        {
+            // スクロールで入力欄が動いたら付いていく
+            autofill.menu.set_position(position);
+            return;
+        }
+
+        let values: Vec<AutofillValue> = match (field, origin) {
+            (Some(field), _) => self
+                .autofill
+                .suggestions(field, &typed)
+                .into_iter()
+                .map(AutofillValue::Text)
+                .collect(),
+            (None, Some(origin)) => {
+                let prefix = typed.trim().to_lowercase();
+                self.passwords
+                    .credentials(&origin)
+                    .iter()
+                    .filter(|c| {
+                        c.username != typed && c.username.to_lowercase().starts_with(&prefix)
+                    })
+                    .cloned()
+                    .map(AutofillValue::Login)
+                    .collect()
+            }
+            (None, None) => Vec::new(),
+        };
+        self.autofill_menu = (!values.is_empty()).then(|| AutofillMenu {
+            menu: ContextMenu::new(
+                position,
+                values.iter().map(|v| MenuItem::new(v.label())).collect(),
+            ),
+            field,
+            typed,
+            values,
+            hidden: false,
+            pressed: false,
+        });
+    }
+
+    /// Fills the focused text field with the autofill value `i` and closes the menu.
+    fn pick_autofill_value(&mut self, i: usize) {
+        let Some(autofill) = self.autofill_menu.take() else {
+            return;
+        };
+        let (Some(value), Some(tab)) = (autofill.values.get(i), self.active_tab_mut()) else {
+            return;
+        };
+        match value {
+            AutofillValue::Text(text) => tab.set_input_value(text),
+            AutofillValue::Login(credential) => tab.fill_login(credential),
+        };
+    }
+
+    /// Handles a click on the password prompt. Returns `None` for clicks elsewhere,
+    /// which are handled as usual and leave the prompt open.
+    fn handle_password_prompt_click(
+        &mut self,
+        state: ElementState,
+        button: winit::event::MouseButton,
+    ) -> Option<BrowserCommand> {
+        let (x, y) = self.mouse_position_logical();
+        let window = self.logical_window_size();
+        let prompt = self.password_prompt.as_mut()?;
+        if button != winit::event::MouseButton::Left {
+            return None;
+        }
+        match state {
+            ElementState::Pressed => {
+                prompt.pressed = prompt.menu.contains(window, x, y);
+                prompt.pressed.then_some(BrowserCommand::None)
+            }
+            ElementState::Released => {
+                if !std::mem::take(&mut prompt.pressed) {
+                    return None;
+                }
+                if let Some(i) = prompt.menu.hit_test(window, x, y) {
+                    self.answer_password_prompt(i);
+                }
+                Some(BrowserCommand::RequestRedraw)
+            }
+        }
+    }
+
+    /// Handles a click on the permission prompt shown when no password prompt is.
+    /// Returns `None` for clicks elsewhere, which are handled as usual and leave
+    /// the prompt open.
+    fn handle_permission_prompt_click(
+        &mut self,
+        state: ElementState,
+        button: winit::event::MouseButton,
+    ) -> Option<BrowserCommand> {
+        let (x, y) = self.mouse_position_logical();
+        let window = self.logical_window_size();
+        if self.password_prompt.is_some() || button != winit::event::MouseButton::Left {
+            return None;
+        }
+        let prompt = self.permission_prompts.first_mut()?;
+        match state {
+            ElementState::Pressed => {
+                prompt.pressed = prompt.menu.contains(window, x, y);
+                prompt.pressed.then_some(BrowserCommand::None)
+            }
+            ElementState::Released => {
+                if !std::mem::take(&mut prompt.pressed) {
+                    return None;
+                }
+                if let Some(i) = prompt.menu.hit_test(window, x, y) {
+                    self.answer_permission_prompt(i);
+                }
+                Some(BrowserCommand::RequestRedraw)
+            }
+        }
+    }
+
+    /// Allows or blocks the capability for the site, remembering the answer unless
+    /// a private tab asked, or refuses it only this time, depending on the item `i`
+    /// picked.
+    fn answer_permission_prompt(&mut self, i: usize) {
+        if self.permission_prompts.is_empty() {
+            return;
+        }
+        let prompt = self.permission_prompts.remove(0);
+        let PermissionRequest { origin, permission } = prompt.request;
+        let granted = i == PROMPT_ALLOW;
+        if !prompt.private && matches!(i, PROMPT_ALLOW | PROMPT_BLOCK) {
+            let state = if granted {
+                PermissionState::Allow
+            } else {
+                PermissionState::Deny
+            };
+            if self.permissions.set(&origin, permission, state) {
+                self.save_permissions();
+            }
+        }
+        for tab in self
+            .tabs
+            .iter_mut()
+            .filter(|tab| tab.is_private() == prompt.private)
+        {
+            tab.set_permission(&origin, permission, granted);
+        }
+    }
+
+    /// Saves the submitted password, never asks again for its site, or just
+    /// closes the prompt, depending on the item `i` picked.
+    fn answer_password_prompt(&mut self, i: usize) {
+        let Some(prompt) = self.password_prompt.take() else {
+            return;
+        };
+        let SubmittedLogin { origin, credential } = prompt.login;
+        match i {
+            PROMPT_SAVE => {
+                if let Err(e) = self.passwords.save(&origin, credential) {
+                    log::error!("Failed to save the password for {}: {:#}", origin, e);
+                }
+            }
+            PROMPT_NEVER => {
+                self.passwords.never_save(&origin);
+                self.save_password_exceptions();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles a click on the autofill menu. Returns `None` for clicks elsewhere,
+    /// which are handled as usual.
+    ///
+    /// Releasing the left button over a value that it was pressed on fills the
+    /// field with it.
+    fn handle_autofill_menu_click(
+        &mut self,
+        state: ElementState,
+        button: winit::event::MouseButton,
+    ) -> Option<BrowserCommand> {
+        let (x, y) = self.mouse_position_logical();
+        let window = self.logical_window_size();
+        let autofill = self.autofill_menu.as_mut().filter(|m| !m.hidden)?;
+        if button != winit::event::MouseButton::Left {
+            return None;
+        }
+        match state {
+            ElementState::Pressed => {
+                autofill.pressed = autofill.menu.contains(window, x, y);
+                autofill.pressed.then_some(BrowserCommand::None)
+            }
+            ElementState::Released => {
+                if !std::mem::take(&mut autofill.pressed) {
+                    return None;
+                }
+                if let Some(i) = autofill.menu.hit_test(window, x, y) {
+                    self.pick_autofill_value(i);
+                }
+                Some(BrowserCommand::RequestRedraw)
+            }
+        }
+    }
+
+    /// Handles a key press while the autofill menu is shown. Returns `None` to
+    /// handle the key as usual.
+    ///
+    /// The arrow keys move the highlight, Enter fills the field with the
+    /// highlighted value and Escape hides the menu until the value changes.
+    fn handle_autofill_menu_key(&mut self, key: &Key) -> Option<BrowserCommand> {
+        let autofill = self.autofill_menu.as_mut().filter(|m| !m.hidden)?;
+        match key {
+            Key::Named(NamedKey::ArrowDown) => autofill.menu.highlight_next(),
+            Key::Named(NamedKey::ArrowUp) => autofill.menu.highlight_previous(),
+            Key::Named(NamedKey::Enter) => {
+                // 選んでいなければフォームの送信に回す
+                let i = autofill.menu.highlighted()?;
+                self.pick_autofill_value(i);
+            }
+            Key::Named(NamedKey::Escape) => autofill.hidden = true,
+            _ => return None,
+        }
+        Some(BrowserCommand::RequestRedraw)
+    }
+
+    /// Handles mouse input events for the active tab.
+    ///
+    /// Pressing the left button on a button shows it pressed and releasing it
+    /// over the same button activates it. Elsewhere, pressing starts a text
+    /// selection and releasing without having selected anything is a click.
+    fn handle_mouse_input(
+        &mut self,
+        state: ElementState,
+        button: winit::event::MouseButton,
+    ) -> BrowserCommand {
+        if let Some(cmd) = self.handle_spelling_menu_click(state, button) {
+            return cmd;
+        }
+        if let Some(cmd) = self.handle_password_prompt_click(state, button) {
+            return cmd;
+        }
+        if let Some(cmd) = self.handle_permission_prompt_click(state, button) {
+            return cmd;
+        }
+        if let Some(cmd) = self.handle_autofill_menu_click(state, button) {
+            return cmd;
+        }
+        match (button, state) {
+            (winit::event::MouseButton::Left, _) => {}
+            (winit::event::MouseButton::Back, ElementState::Pressed) => return self.go_back(),
+            (winit::event::MouseButton::Forward, ElementState::Pressed) => {
+                return self.go_forward();
+            }
+            _ => return BrowserCommand::None,
+        }
+
+        // 自前のタイトルバーのときは、ウィンドウの縁で大きさを変える
+        if state == ElementState::Pressed
+            && let Some(direction) = self.resize_edge()
+        {
+            self.emit(BrowserEvent::WindowAction(WindowAction::DragResize(
+                direction,
+            )));
+            return BrowserCommand::None;
+        }
+
+        // ブラウザ UI の上のクリック
+        let (x, y) = self.mouse_position_logical();
+        if state == ElementState::Pressed
+            && self.url_bar.is_focused()
+            && let Some(i) =
+                self.url_bar
+                    .suggestion_hit_test(self.logical_width(), x, y - TAB_STRIP_HEIGHT)
+        {
+            let url = self.url_bar.suggestions()[i].url.clone();
+            self.navigate_active_tab(url);
+            return BrowserCommand::RequestRedraw;
+        }
+        if y < self.chrome_height() {
+            if state == ElementState::Pressed {
+                return self.handle_chrome_click(x, y);
+            }
+            return BrowserCommand::None;
+        }
+        if state == ElementState::Pressed {
+            self.url_bar.blur();
+        }
+
+        let (x, y) = self.mouse_position_css();
+        self.input.mouse_pressed = state == ElementState::Pressed;
+
+        // スクロールバーのサムをつかむ
+        match state {
+            ElementState::Pressed => {
+                if let Some(axis) = self.scroll_bar_at(x, y) {
+                    let pos = match axis {
+                        ScrollAxis::Vertical => y,
+                        ScrollAxis::Horizontal => x,
+                    };
+                    self.input.scroll_bar_drag = Some((axis, pos));
+                    return BrowserCommand::None;
+                }
+            }
+            ElementState::Released => {
+                if self.input.scroll_bar_drag.take().is_some() {
+                    return BrowserCommand::None;
+                }
+            }
+        }
+
+        // 調べるモードのクリックはページに届けない
+        if self.inspecting {
+            if state == ElementState::Released {
+                self.inspecting = false;
+                return self.inspect_at(x, y);
+            }
+            return BrowserCommand::None;
+        }
+
+        // Ctrl+クリックしたリンクは裏のタブで開く
+        let background = self.input.modifiers.control_key();
+        let Some(tab) = self.active_tab_mut() else {
+            return BrowserCommand::None;
+        };
+
+        match state {
+            // ボタンなら押した状態にし、入力欄ならフォーカスを移し、それ以外なら
+            // ページの文字列の選択を始める
+            ElementState::Pressed => {
+                if !tab.press_button_at(x, y) && !tab.focus_input_at(x, y) {
+                    tab.begin_selection(x, y);
+                }
+            }
+            ElementState::Released => {
+                if tab.is_pressing_button() {
+                    tab.release_button_at(x, y);
+                } else if tab.selection().is_none() {
+                    tab.clear_selection();
+                    if let Some(link) = Self::handle_mouse_click(tab, x, y, background) {
+                        return Self::open_tab_command(link, background);
+                    }
+                }
+            }
+        }
+
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Inspects the element at (x, y) in the active tab (CSS pixels) and shows
+    /// its styles in the `orinium://inspect` tab, opening one if needed.
+    fn inspect_at(&mut self, x: f32, y: f32) -> BrowserCommand {
+        let Some(inspection) = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(|tab| tab.inspect_at(x, y))
+        else {
+            return BrowserCommand::None;
+        };
+        self.inspection = Some(inspection);
+        self.show_internal_page("orinium://inspect")
+    }
+
+    /// Copies the console of the active tab and shows it in the `orinium://console`
+    /// tab. On that tab itself, the page is only reloaded.
+    fn show_console(&mut self) -> BrowserCommand {
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return BrowserCommand::None;
+        };
+        let is_console = tab.document_url().is_some_and(|url| {
+            url.scheme() == internal_pages::INTERNAL_SCHEME && url.host_str() == Some("console")
+        });
+        if !is_console {
+            self.console = Some(tab.console());
+        }
+        self.show_internal_page("orinium://console")
+    }
+
+    /// Switches to the tab showing the internal page `url` and reloads it, or
+    /// opens the page in a new tab.
+    fn show_internal_page(&mut self, url: &str) -> BrowserCommand {
+        let url = Url::parse(url).expect("valid internal URL");
+        let existing = self.tabs.iter().position(|tab| {
+            tab.document_url().is_some_and(|current| {
+                current.scheme() == url.scheme() && current.host_str() == url.host_str()
+            })
+        });
+        match existing {
+            Some(i) => {
+                self.switch_tab(i);
+                self.reload(false);
+                BrowserCommand::RequestRedraw
+            }
+            None => self.open_in_new_tab(url),
+        }
+    }
+
+    /// Handles a click on the tab strip or the URL bar (logical pixels).
+    fn handle_chrome_click(&mut self, x: f32, y: f32) -> BrowserCommand {
+        let width = self.logical_width();
+
+        if y < TAB_STRIP_HEIGHT {
+            let custom_titlebar = self.settings.custom_titlebar;
+            if custom_titlebar && let Some(control) = window_controls::hit_test(width, x, y) {
+                let action = match control {
+                    WindowControl::Minimize => WindowAction::Minimize,
+                    WindowControl::Maximize => WindowAction::ToggleMaximize,
+                    WindowControl::Close => return BrowserCommand::Exit,
+                };
+                self.emit(BrowserEvent::WindowAction(action));
+                return BrowserCommand::None;
+            }
+            return match tab_strip::hit_test(self.tab_strip_width(), self.tabs.len(), x, y) {
+                Some(TabStripHit::Tab(i)) => self.switch_tab(i),
+                Some(TabStripHit::Close(i)) => self.close_tab(i),
+                Some(TabStripHit::NewTab) => self.new_tab(),
+                // タブのないところはウィンドウを動かす取っ手
+                None if custom_titlebar => {
+                    self.emit(BrowserEvent::WindowAction(WindowAction::Drag));
+                    BrowserCommand::None
+                }
+                None => BrowserCommand::None,
+            };
+        }
+
+        if self
+            .url_bar
+            .reader_button_hit_test(width, x, y - TAB_STRIP_HEIGHT)
+        {
+            return BrowserCommand::ToggleReaderMode;
+        }
+        if self
+            .url_bar
+            .content_blocking_button_hit_test(width, x, y - TAB_STRIP_HEIGHT)
+        {
+            return self.toggle_content_blocking();
+        }
+        if let Some(i) = self
+            .url_bar
+            .action_button_hit_test(width, x, y - TAB_STRIP_HEIGHT)
+        {
+            return self.run_toolbar_action(i);
+        }
+        if self
+            .url_bar
+            .security_badge_hit_test(width, x, y - TAB_STRIP_HEIGHT)
+        {
+            self.url_bar.toggle_security_popup();
+            return BrowserCommand::RequestRedraw;
+        }
+        if self.url_bar.hit_test(width, x, y - TAB_STRIP_HEIGHT) {
+            if !self.url_bar.is_focused() {
+                self.focus_url_bar();
+            }
+        } else {
+            self.url_bar.blur();
+        }
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Opens an empty tab, makes it active and focuses the URL bar.
+    pub fn new_tab(&mut self) -> BrowserCommand {
+        self.add_tab(Tab::new());
+        self.switch_tab(self.tabs.len() - 1);
+        self.url_bar.focus();
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Opens `url` in a new tab and makes it active.
+    pub fn open_in_new_tab(&mut self, url: Url) -> BrowserCommand {
+        let link = NewTabLink {
+            url,
+            referrer: None,
+            opener: None,
+        };
+        self.open_tab(link, false)
+    }
+
+    /// Opens a link in a new tab, private if the active tab is private.
+    ///
+    /// The new tab sends the link's referrer with its first request and keeps the
+    /// opener document only when the link asked for it. With `background` the
+    /// active tab stays selected.
+    pub fn open_tab(&mut self, link: NewTabLink, background: bool) -> BrowserCommand {
+        let private = self
+            .tabs
+            .get(self.active_tab)
+            .is_some_and(|tab| tab.is_private());
+        let mut tab = if private {
+            Tab::new_private()
+        } else {
+            Tab::new()
+        };
+        tab.set_opener(link.opener);
+        tab.navigate_from(link.url, link.referrer);
+        self.add_tab(tab);
+        let index = self.tabs.len() - 1;
+        if background {
+            BrowserCommand::RequestRedraw
+        } else {
+            self.switch_tab(index)
+        }
+    }
+
+    /// Opens an empty private tab, makes it active and focuses the URL bar.
+    ///
+    /// Private tabs are kept out of the browsing history and the saved session, and
+    /// their cookies and cache live in memory until the last private tab is closed.
+    pub fn new_private_tab(&mut self) -> BrowserCommand {
+        self.add_tab(Tab::new_private());
+        self.switch_tab(self.tabs.len() - 1);
+        self.url_bar.focus();
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Makes the tab at `index` the active one.
+    pub fn switch_tab(&mut self, index: usize) -> BrowserCommand {
+        if index >= self.tabs.len() {
+            return BrowserCommand::None;
+        }
+
+        self.active_tab = index;
+        self.url_bar.blur();
+        self.input.scroll_bar_drag = None;
+        self.render.last_page_scroll = None;
+        BrowserCommand::RequestRedraw
+    }
+
+    /// Closes the tab at `index`, cancelling its requests.
+    ///
+    /// Closing the last private tab discards the private cookies and cache, and
+    /// closing the last tab exits the browser.
+    pub fn close_tab(&mut self, index: usize) -> BrowserCommand {
+        if index >= self.tabs.len() {
+            return BrowserCommand::None;
+        }
+
+        self.cancel_fetches(index);
+        let mut closed = self.tabs.remove(index);
+        closed.shutdown_scripts();
+        self.pending_fetches.tab_removed(index);
+
+        if closed.is_private() && !self.tabs.iter().any(Tab::is_private) {
+            self.network.clear_partition(StoragePartition::Private);
+            self.private_local_storage.borrow_mut().clear_all();
+            self.private_visited_links.borrow_mut().clear();
+        }
+
+        // 開いているタブが表示していない画像（閉じたタブだけが表示していたもの）を捨てる
+        let shown: HashSet<(StoragePartition, Url)> = self
+            .tabs
+            .iter()
+            .flat_map(|tab| {
+                let partition = tab.storage_partition();
+                tab.image_urls()
+                    .into_iter()
+                    .map(move |url| (partition, url))
+            })
+            .collect();
+        self.decoded_images.retain(|key, _| shown.contains(key));
+        self.update_image_memory();
+
+        if self.tabs.is_empty() {
+            return BrowserCommand::Exit;
+        }
+
+        let active = if index < self.active_tab || self.active_tab >= self.tabs.len() {
+            self.active_tab.saturating_sub(1)
+        } else {
+            self.active_tab
+        };
+        self.switch_tab(active)
+    }
+
+    /// Extends the text selection (or drags a scrollbar thumb) while the left
+    /// button is held down.
+    fn handle_mouse_drag(&mut self) -> BrowserCommand {
+        if !self.input.mouse_pressed {
+            return BrowserCommand::None;
+        }
+
+        let (x, y) = self.mouse_position_css();
+
+        if let Some((axis, last)) = self.input.scroll_bar_drag {
+            let viewport = self.viewport_css();
+            let Some((_, content)) = self.tabs.get(self.active_tab).and_then(Tab::page_scroll)
+            else {
+                return BrowserCommand::None;
+            };
+            let bar = self.render.scroll_bar;
+
+            let (pos, delta) = match axis {
+                ScrollAxis::Vertical => (
+                    y,
+                    (
+                        0.0,
+                        bar.thumb_delta_to_scroll(viewport.1, content.1, y - last),
+                    ),
+                ),
+                ScrollAxis::Horizontal => (
+                    x,
+                    (
+                        bar.thumb_delta_to_scroll(viewport.0, content.0, x - last),
+                        0.0,
+                    ),
+                ),
+            };
+            self.input.scroll_bar_drag = Some((axis, pos));
+            self.scroll_active_tab(delta.0, delta.1);
+            return BrowserCommand::RequestRedraw;
+        }
+
+        let Some(tab) = self.active_tab_mut() else {
+            return BrowserCommand::None;
+        };
+
+        tab.extend_selection(x, y);
+        if tab.needs_redraw() {
+            BrowserCommand::RequestRedraw
+        } else {
+            BrowserCommand::None
+        }
+    }
+
+    /// Returns the mouse position in page CSS pixels.
+    fn mouse_position_css(&self) -> (f32, f32) {
+        self.logical_to_page(self.mouse_position_logical())
+    }
+
+    /// Returns the mouse position in logical window pixels.
+    fn mouse_position_logical(&self) -> (f32, f32) {
+        let (x, y) = self.input.mouse_position;
+        let sf = self.render.scale_factor;
+        ((x / sf) as f32, (y / sf) as f32)
+    }
+
+    /// Converts a position in logical window pixels to page CSS pixels.
+    fn logical_to_page(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let zoom = self.zoom();
+        (x / zoom, (y - self.chrome_height()) / zoom)
+    }
+
+    /// Returns the height of the browser UI above the page, in logical pixels.
+    fn chrome_height(&self) -> f32 {
+        if self.render.chrome_visible {
+            TAB_STRIP_HEIGHT + URL_BAR_HEIGHT
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the zoom factor of the active tab.
+    fn zoom(&self) -> f32 {
+        self.tabs.get(self.active_tab).map(Tab::zoom).unwrap_or(1.0)
+    }
+
+    /// Returns the number of physical pixels per CSS pixel for the active tab
+    /// (the window scale factor multiplied by the page zoom).
+    fn page_scale(&self) -> f64 {
+        self.render.scale_factor * self.zoom() as f64
+    }
+
+    /// Handles mouse wheel scrolling for the active tab.
+    ///
+    /// The innermost scrollable container under the cursor is scrolled first.
+    fn handle_scroll(&mut self, delta: winit::event::MouseScrollDelta) {
+        let (dx, dy) = match delta {
+            winit::event::MouseScrollDelta::LineDelta(x, y) => (-x * 60.0, -y * 60.0),
+            winit::event::MouseScrollDelta::PixelDelta(pos) => (-pos.x as f32, -pos.y as f32),
+        };
+        let speed = self.settings.scroll_speed;
+        let (dx, dy) = (dx * speed, dy * speed);
+        // Shift+ホイールは横スクロール
+        let (dx, dy) = if self.input.modifiers.shift_key() && dx == 0.0 {
+            (dy, 0.0)
+        } else {
+            (dx, dy)
+        };
+
+        let (x, y) = self.mouse_position_css();
+        let viewport = self.viewport_css();
+        if let Some(tab) = self.active_tab_mut() {
+            tab.scroll_at(x, y, dx, dy, viewport);
+        }
+    }
+
+    /// Shows the overlay scrollbars while the mouse is over their gutter.
+    fn handle_scroll_bar_hover(&mut self) -> BrowserCommand {
+        let (x, y) = self.mouse_position_css();
+        let (vw, vh) = self.viewport_css();
+        if !self.render.scroll_bar.is_in_gutter(vw, vh, x, y) {
+            return BrowserCommand::None;
+        }
+
+        let was_visible = self.render.scroll_bar_fade.is_visible();
+        self.render.scroll_bar_fade.activity(Instant::now());
+        if was_visible {
+            BrowserCommand::None
+        } else {
+            BrowserCommand::RequestRedraw
+        }
+    }
+
+    /// Applies `:hover` to the link under the mouse cursor and repaints if it changed.
+    fn handle_link_hover(&mut self) -> BrowserCommand {
+        let (x, y) = self.mouse_position_css();
+        let Some(tab) = self.active_tab_mut() else {
+            return BrowserCommand::None;
+        };
+
+        if tab.hover_at(x, y) {
+            BrowserCommand::RequestRedraw
+        } else {
+            BrowserCommand::None
+        }
+    }
+
+    /// Returns which page scrollbar thumb, if any, is at `(x, y)` (CSS pixels).
+    ///
+    /// Hidden scrollbars cannot be grabbed.
+    fn scroll_bar_at(&self, x: f32, y: f32) -> Option<ScrollAxis> {
+        if !self.render.scroll_bar_fade.is_visible() {
+            return None;
+        }
+
+        let ((scroll_x, scroll_y), (content_w, content_h)) =
+            self.tabs.get(self.active_tab)?.page_scroll()?;
+        let (vw, vh) = self.viewport_css();
+        let bar = &self.render.scroll_bar;
+
+        if bar.hit_test_thumb(vw, vh, content_h, scroll_y, x, y) {
+            Some(ScrollAxis::Vertical)
+        } else if bar.hit_test_horizontal_thumb(vw, vh, content_w, scroll_x, x, y) {
+            Some(ScrollAxis::Horizontal)
+        } else {
+            None
+        }
+    }
+
+    /// Draw commands highlighting the box model of the element under the
+    /// pointer while inspecting, in viewport coordinates.
+    fn inspect_overlay_commands(&self) -> Vec<DrawCommand> {
+        let (x, y) = self.mouse_position_css();
+        if !self.inspecting || x < 0.0 || y < 0.0 {
+            return Vec::new();
+        }
+        let Some(model) = self
+            .tabs
+            .get(self.active_tab)
+            .and_then(|tab| tab.box_model_at(x, y))
+        else {
+            return Vec::new();
+        };
+
+        let platform_measurer = PlatformTextMeasurer::new().ok();
+        let measurer: &dyn TextMeasurer<TextStyle> = match platform_measurer.as_ref() {
+            Some(m) => m,
+            None => &FallbackTextMeasurer,
+        };
+        inspect_overlay::draw_commands(&model, self.viewport_css(), measurer)
+    }
+
+    /// Draw commands for the page scrollbar thumbs, in viewport coordinates.
+    ///
+    /// The offset is clamped to [`Tab::max_scroll`] so the thumbs never run past
+    /// the end of their tracks while the page is still settling.
+    fn scroll_bar_commands(&self) -> Vec<DrawCommand> {
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return Vec::new();
+        };
+        let (Some(((scroll_x, scroll_y), (content_w, content_h))), Some((max_x, max_y))) =
+            (tab.page_scroll(), tab.max_scroll())
+        else {
+            return Vec::new();
+        };
+        let (scroll_x, scroll_y) = (scroll_x.clamp(0.0, max_x), scroll_y.clamp(0.0, max_y));
+        let (vw, vh) = self.viewport_css();
+        let bar = &self.render.scroll_bar;
+        let opacity = self.render.scroll_bar_fade.opacity();
+        if opacity <= 0.0 {
+            return Vec::new();
+        }
+        let [r, g, b, a] = bar.color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let a = (a as f32 * opacity).round() as u8;
+
+        [
+            bar.thumb_rect(vw, vh, content_h, scroll_y),
+            bar.horizontal_thumb_rect(vw, vh, content_w, scroll_x),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|(x1, y1, x2, y2)| DrawCommand::DrawRect {
+            x: x1,
+            y: y1,
+            width: x2 - x1,
+            height: y2 - y1,
+            color: layouter::types::Color(r, g, b, a),
+        })
+        .collect()
+    }
+
+    /// Handles touchscreen input: one-finger drags scroll (with momentum after
+    /// release) and two-finger pinches zoom the page.
+    fn handle_touch(&mut self, touch: Touch) -> BrowserCommand {
+        let sf = self.render.scale_factor;
+        let pos = self.logical_to_page((
+            (touch.location.x / sf) as f32,
+            (touch.location.y / sf) as f32,
+        ));
+        let now = Instant::now();
+
+        let gesture = match touch.phase {
+            TouchPhase::Started => {
+                self.input.touch.touch_started(touch.id, pos, now);
+                None
+            }
+            TouchPhase::Moved => self.input.touch.touch_moved(touch.id, pos, now),
+            TouchPhase::Ended => {
+                self.input.touch.touch_ended(touch.id, now);
+                None
+            }
+            TouchPhase::Cancelled => {
+                self.input.touch.touch_cancelled(touch.id);
+                None
+            }
+        };
+
+        match gesture {
+            Some(Gesture::Scroll { dx, dy }) => {
+                let viewport = self.viewport_css();
+                if let Some(tab) = self.active_tab_mut() {
+                    tab.scroll_at(pos.0, pos.1, dx, dy, viewport);
+                }
+                BrowserCommand::RequestRedraw
+            }
+            Some(Gesture::Zoom { scale }) => {
+                self.zoom_active_tab(scale);
+                BrowserCommand::RequestRedraw
+            }
+            // 慣性スクロールの開始
+            None if self.input.touch.is_flinging() => BrowserCommand::RequestRedraw,
+            None => BrowserCommand::None,
+        }
+    }
+
+    /// Multiplies the active tab's zoom factor by `scale`.
+    fn zoom_active_tab(&mut self, scale: f32) {
+        if let Some(tab) = self.active_tab_mut() {
+            tab.zoom_by(scale);
+        }
+    }
+
+    /// Smoothly scrolls the active tab by `(dx, dy)` CSS pixels.
+    fn scroll_active_tab(&mut self, dx: f32, dy: f32) {
+        let viewport = self.viewport_css();
+        if let Some(tab) = self.active_tab_mut() {
+            tab.scroll_by(dx, dy, viewport);
+        }
+    }
+
+    /// Returns the viewport size in CSS pixels (the window below the browser UI).
+    fn viewport_css(&self) -> (f32, f32) {
+        let (width, height) = self.window_size();
+        let sf = self.render.scale_factor as f32;
+        let zoom = self.zoom();
+        (
+            width / sf / zoom,
+            (height / sf - self.chrome_height()).max(0.0) / zoom,
+        )
+    }
+
+    /// Handles a mouse click in the given tab at the specified coordinates.
+    ///
+    /// The page receives a `click` event first; following a link is its
+    /// default action. Links that open in a new tab (`target="_blank"`, or every
+    /// link when `new_tab` is set) are not followed; they are returned instead.
+    pub fn handle_mouse_click(tab: &mut Tab, x: f32, y: f32, new_tab: bool) -> Option<NewTabLink> {
+        tab.click_at(x, y, new_tab)
+    }
+
+    fn open_tab_command(link: NewTabLink, background: bool) -> BrowserCommand {
+        BrowserCommand::OpenTab {
+            url: link.url,
+            background,
+            referrer: link.referrer,
+            opener: link.opener,
+        }
+    }
+
+    /// Rebuilds the render tree and sends draw commands to the GPU.
+    pub fn redraw(&mut self, gpu: &mut dyn Compositor) {
+        self.rebuild_render_tree();
+        self.apply_draw_commands(gpu);
+        if let Err(e) = gpu.render() {
+            log::error!(target: "BrowserApp::redraw", "Render error occurred: {}", e);
+        }
+    }
+
+    /// Applies the current draw commands to the GPU renderer.
+    pub fn apply_draw_commands(&self, gpu: &mut dyn Compositor) {
+        gpu.set_scale_factor(self.page_scale());
+        gpu.set_debug_overlay(self.render.show_frame_stats);
+        gpu.parse_draw_commands(&self.render.draw_commands);
+    }
+
+    /// Loads `url` in a temporary tab without opening a window and returns the rendered page as PNG bytes.
+    ///
+    /// The page is laid out at `size` (CSS pixels, scale factor 1.0). Loading is driven
+    /// synchronously by ticking the browser until the page and its stylesheets are
+    /// ready, or until [`HEADLESS_LOAD_TIMEOUT`] elapses, in which case whatever has
+    /// been laid out so far is rendered.
+    ///
+    /// # Errors
+    /// Returns an error if no GPU adapter is available or PNG encoding fails.
+    pub fn render_page_to_png(&mut self, url: Url, size: (u32, u32)) -> Result<Vec<u8>> {
+        self.with_headless_page(url, size, |browser| {
+            browser.rebuild_render_tree();
+            browser.encode_frame_png()
+        })
+    }
+
+    /// Loads `url` in a temporary tab without opening a window and returns it printed on
+    /// `paper` as PDF bytes (see [`Self::print_to_pdf`]).
+    ///
+    /// # Errors
+    /// Returns an error if the page has not been parsed before the load timed out.
+    pub fn print_page_to_pdf(&mut self, url: Url, paper: PaperSize) -> Result<Vec<u8>> {
+        let (width, height) = paper.content_size();
+        self.with_headless_page(url, (width as u32, height as u32), |browser| {
+            browser.print_to_pdf(paper)
+        })
+    }
+
+    /// Loads `url` in a temporary tab without opening a window and returns its layout
+    /// tree as text (see [`LayoutDump`]).
+    ///
+    /// The page is loaded and laid out as by [`Self::render_page_to_png`], but
+    /// nothing is rendered, so no GPU adapter is needed.
+    ///
+    /// # Errors
+    /// Returns an error if the page has not been laid out before the load timed out.
+    pub fn dump_layout(&mut self, url: Url, size: (u32, u32)) -> Result<String> {
+        self.with_headless_page(url.clone(), size, |browser| {
+            let viewport = browser.viewport_css();
+            let tab = browser
+                .tabs
+                .get_mut(browser.active_tab)
+                .ok_or_else(|| anyhow::anyhow!("No tab is open"))?;
+            tab.relayout(viewport);
+            let (layout, info) = tab
+                .layout_and_info()
+                .ok_or_else(|| anyhow::anyhow!("No layout available for {url}"))?;
+            Ok(LayoutDump::new(layout, info).to_string())
+        })
+    }
+
+    /// Loads `url` with [`Self::load_headless`], runs `f` on the loaded page and
+    /// then closes its tab and puts back the window size, scale factor, browser
+    /// UI and active tab, so that a browser showing a window is left as it was.
+    fn with_headless_page<T>(
+        &mut self,
+        url: Url,
+        size: (u32, u32),
+        f: impl FnOnce(&mut Self) -> T,
+    ) -> T {
+        let window_size = self.render.window_size;
+        let scale_factor = self.render.scale_factor;
+        let chrome_visible = self.render.chrome_visible;
+        let active_tab = self.active_tab;
+
+        self.load_headless(url, size);
+        let result = f(self);
+
+        // 閉じるのが最後のタブでもブラウザは終わらせない（Exit は捨てる）
+        self.close_tab(self.tabs.len() - 1);
+        self.render.window_size = window_size;
+        self.render.scale_factor = scale_factor;
+        self.render.chrome_visible = chrome_visible;
+        if active_tab < self.tabs.len() {
+            self.switch_tab(active_tab);
+        } else {
+            self.active_tab = active_tab;
+        }
+        self.frames.invalidate(Invalidation::Resize);
+        result
+    }
+
+    /// Opens `url` in a new active tab laid out at `size` and ticks the browser
+    /// until the page has loaded or [`HEADLESS_LOAD_TIMEOUT`] elapses.
+    ///
+    /// Use [`Self::with_headless_page`], which closes the tab again and undoes
+    /// the changes to the window.
+    fn load_headless(&mut self, url: Url, size: (u32, u32)) {
+        let mut tab = Tab::new();
+        tab.navigate(url);
+        self.add_tab(tab);
+        self.active_tab = self.tabs.len() - 1;
+        self.render.window_size = size;
+        self.render.scale_factor = 1.0;
+        self.render.chrome_visible = false;
+
+        let deadline = Instant::now() + HEADLESS_LOAD_TIMEOUT;
+        loop {
+            self.tick();
+
+            if self.tabs.get(self.active_tab).is_some_and(Tab::is_loaded) {
+                break;
+            }
+            if Instant::now() >= deadline {
+                log::warn!("Headless load timed out; rendering partial page");
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Renders the current draw commands offscreen and encodes them as PNG.
+    fn encode_frame_png(&self) -> Result<Vec<u8>> {
+        let image = headless::render_offscreen(
+            self.render.window_size,
+            self.page_scale(),
+            &self.render.draw_commands,
+        )?;
+
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)?;
+        Ok(png)
+    }
+
+    /// Starts the Chrome DevTools Protocol server on `127.0.0.1:port` (0 picks a
+    /// free port) and returns the address it listens on.
+    ///
+    /// WebSocket clients sending an `Origin` header are only accepted from
+    /// `allowed_origins` (`*` allows any), so that web pages open in other
+    /// browsers cannot take control of this one.
+    ///
+    /// The server is driven by [`Self::poll_remote_debugging`].
+    pub fn listen_remote_debugging(
+        &mut self,
+        port: u16,
+        allowed_origins: Vec<String>,
+    ) -> Result<SocketAddr> {
+        let mut server = CdpServer::bind(("127.0.0.1", port))?;
+        server.set_allowed_origins(allowed_origins);
+        let addr = server.local_addr()?;
+        self.remote_debugging = Some(server);
+        Ok(addr)
+    }
+
+    /// Accepts remote debugging clients and handles their messages. Returns
+    /// `RequestRedraw` if a message may have changed the page.
+    pub fn poll_remote_debugging(&mut self) -> BrowserCommand {
+        let Some(mut server) = self.remote_debugging.take() else {
+            return BrowserCommand::None;
+        };
+        let handled = server.poll(self);
+        self.remote_debugging = Some(server);
+        if handled {
+            BrowserCommand::RequestRedraw
+        } else {
+            BrowserCommand::None
+        }
+    }
+
+    /// Runs the browser without a window, loading pages and serving remote
+    /// debugging clients until the process is terminated.
+    pub fn run_headless(mut self) -> Result<()> {
+        self.render.chrome_visible = false;
+        loop {
+            let commands = [
+                self.tick(),
+                self.run_scheduled_tasks(),
+                self.poll_remote_debugging(),
+            ];
+            // 窓がなくても描画コマンドは作る（first paint の計測のため）
+            if commands
+                .iter()
+                .any(|command| matches!(command, BrowserCommand::RequestRedraw))
+            {
+                self.rebuild_render_tree();
+                if let Some(tab) = self.tabs.get_mut(self.active_tab) {
+                    tab.clear_redraw_flag();
+                }
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Returns the index of the tab with id `target`, or of the active tab.
+    fn remote_tab_index(&self, target: Option<u64>) -> Result<usize> {
+        match target {
+            Some(id) => self
+                .tabs
+                .iter()
+                .position(|tab| tab.id() == id)
+                .ok_or_else(|| anyhow::anyhow!("No target with given id found")),
+            None if self.active_tab < self.tabs.len() => Ok(self.active_tab),
+            None => anyhow::bail!("No tab is open"),
+        }
+    }
+
+    /// Adds a new tab to the browser.
+    pub fn add_tab(&mut self, mut tab: Tab) {
+        tab.set_page_defaults(self.settings.page_defaults(self.system_color_scheme));
+        let local_storage = if tab.is_private() {
+            &self.private_local_storage
+        } else {
+            &self.local_storage
+        };
+        tab.set_local_storage(local_storage.clone());
+        let visited_links = if tab.is_private() {
+            &self.private_visited_links
+        } else {
+            &self.visited_links
+        };
+        tab.set_visited_links(visited_links.clone());
+        tab.set_spell_checker(self.spell_checker.clone());
+        if !tab.is_private() {
+            tab.set_extensions(Some(self.extensions.clone()));
+        }
+        self.tabs.push(tab);
+    }
+
+    /// Returns the current window size as `(width, height)` in floating-point pixels.
+    pub fn window_size(&self) -> (f32, f32) {
+        (
+            self.render.window_size.0 as f32,
+            self.render.window_size.1 as f32,
+        )
+    }
+
+    /// Returns the accessibility tree of the active tab, placed where the page is
+    /// drawn in the window (logical pixels).
+    pub fn accessibility_tree(&self) -> Option<AccessTree> {
+        let mut tree = self.tabs.get(self.active_tab)?.accessibility_tree()?;
+        tree.offset = (0.0, self.chrome_height());
+        tree.scale = self.zoom();
+        Some(tree)
+    }
+
+    /// Performs an action requested by an assistive technology (e.g. a screen
+    /// reader) on a node of the active tab's accessibility tree.
+    ///
+    /// Clicking a node focuses it and activates it like the Enter key.
+    pub fn perform_accessibility_action(&mut self, action: AccessAction) -> BrowserCommand {
+        let Some(tab) = self.active_tab_mut() else {
+            return BrowserCommand::None;
+        };
+        if !tab.focus_accessible(action.target()) {
+            return BrowserCommand::None;
+        }
+        match action {
+            AccessAction::Focus(_) => BrowserCommand::RequestRedraw,
+            AccessAction::Click(_) => match tab.activate_focused() {
+                Some(link) => Self::open_tab_command(link, false),
+                None => BrowserCommand::RequestRedraw,
+            },
+        }
+    }
+
+    /// Returns whether the mouse cursor is over a link (the platform should show a pointer).
+    /// Returns where the IME candidate window should be placed: the caret of
+    /// the focused text field, as (x, y, width, height) in logical pixels.
+    /// `None` means no text field accepts IME input.
+    pub fn ime_cursor_area(&self) -> Option<(f32, f32, f32, f32)> {
+        if self.url_bar.is_focused() {
+            let measurer = PlatformTextMeasurer::new().ok();
+            let measurer: &dyn TextMeasurer<TextStyle> = match measurer.as_ref() {
+                Some(m) => m,
+                None => &FallbackTextMeasurer,
+            };
+            let (x, y, width, height) = self.url_bar.caret_rect(self.logical_width(), measurer);
+            return Some((x, y + TAB_STRIP_HEIGHT, width, height));
+        }
+
+        let (x, y, width, height) = self.tabs.get(self.active_tab)?.ime_cursor_area()?;
+        let zoom = self.zoom();
+        Some((
+            x * zoom,
+            y * zoom + self.chrome_height(),
+            width * zoom,
+            height * zoom,
+        ))
+    }
+
+    pub fn is_over_link(&self) -> bool {
+        self.tabs
+            .get(self.active_tab)
+            .is_some_and(Tab::is_over_link)
+    }
+
+    /// Returns the direction to resize the window in when the browser draws its
+    /// own titlebar and the pointer is on the edge of the window.
+    pub fn resize_edge(&self) -> Option<ResizeDirection> {
+        if !self.settings.custom_titlebar {
+            return None;
+        }
+        let (x, y) = self.mouse_position_logical();
+        window_controls::resize_edge(self.logical_window_size(), x, y)
+    }
+
+    /// Returns the window title: the active tab's title followed by the
+    /// application name, marked "(loading…)" while the page is loading and
+    /// "(Private)" for private tabs.
+    pub fn window_title(&self) -> String {
+        let Some(tab) = self.tabs.get(self.active_tab) else {
+            return self.app_name.clone();
+        };
+        let loading = if tab.is_loading() {
+            " (loading…)"
+        } else {
+            ""
+        };
+        let private = if tab.is_private() { " (Private)" } else { "" };
+        format!(
+            "{}{loading} - {}{private}",
+            tab.display_title(),
+            self.app_name
+        )
+    }
+
+    /// Sets the window's inner size in physical pixels.
+    ///
+    /// Pages are laid out at this size divided by the scale factor and the zoom
+    /// (see `viewport_css`) the next time they are drawn.
+    pub fn set_window_size(&mut self, size: (u32, u32)) {
+        self.render.window_size = size;
+    }
+
+    /// Sets the current scale factor for rendering and requests a new frame.
+    ///
+    /// Like the window size, pages are laid out at the new CSS pixel size the next
+    /// time they are drawn. The mouse position is kept in the same logical pixels
+    /// until the pointer moves again.
+    pub fn set_scale_factor(&mut self, sf: f64) {
+        let old = self.render.scale_factor;
+        if old > 0.0 && sf > 0.0 {
+            let (x, y) = self.input.mouse_position;
+            self.input.mouse_position = (x / old * sf, y / old * sf);
+        }
+        self.render.scale_factor = sf;
+        self.frames.invalidate(Invalidation::Resize);
+    }
+
+    /// Shows or hides the browser UI (tab strip and URL bar) above the page.
+    ///
+    /// While hidden, the page fills the whole window, as in an embedded
+    /// [`OriniumView`](crate::embed::OriniumView).
+    pub fn set_chrome_visible(&mut self, visible: bool) {
+        self.render.chrome_visible = visible;
+        self.frames.invalidate(Invalidation::Resize);
+    }
+}
+
+/// File name for a page printed to PDF: the title without path separators.
+fn pdf_file_name(title: &str) -> String {
+    let name: String = title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '\0' => '_',
+            c => c,
+        })
+        .collect();
+    match name.trim().trim_matches('.') {
+        "" => "page.pdf".to_string(),
+        name => format!("{name}.pdf"),
+    }
+}
+
+/// Returns when the file at `path` was last modified, or `None` if it cannot be read.
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl RemoteTarget for BrowserApp {
+    fn targets(&self) -> Vec<TargetInfo> {
+        self.tabs
+            .iter()
+            .map(|tab| TargetInfo {
+                id: tab.id(),
+                url: tab
+                    .document_url()
+                    .map(|url| url.to_string())
+                    .unwrap_or_default(),
+                title: tab.display_title(),
+            })
+            .collect()
+    }
+
+    fn navigate(&mut self, target: Option<u64>, url: Url) -> Result<u64> {
+        if target.is_none() && self.tabs.is_empty() {
+            self.add_tab(Tab::new());
+            self.active_tab = 0;
+        }
+        let index = self.remote_tab_index(target)?;
+        self.cancel_fetches(index);
+        let tab = &mut self.tabs[index];
+        tab.navigate(url);
+        Ok(tab.id())
+    }
+
+    fn document(&self, target: Option<u64>) -> Result<Option<NodeRef<HtmlNodeType>>> {
+        Ok(self.tabs[self.remote_tab_index(target)?].dom_root())
+    }
+
+    fn is_loaded(&self, target: Option<u64>) -> bool {
+        self.remote_tab_index(target)
+            .is_ok_and(|index| self.tabs[index].is_loaded())
+    }
+
+    /// Renders the page of the tab without the browser UI, at the window size.
+    fn capture_screenshot(&mut self, target: Option<u64>) -> Result<Vec<u8>> {
+        let index = self.remote_tab_index(target)?;
+        let (active_tab, chrome_visible) = (self.active_tab, self.render.chrome_visible);
+        self.active_tab = index;
+        self.render.chrome_visible = false;
+        self.rebuild_render_tree();
+        let png = self.encode_frame_png();
+
+        self.active_tab = active_tab;
+        self.render.chrome_visible = chrome_visible;
+        self.rebuild_render_tree();
+        png
+    }
+
+    fn evaluate(&mut self, target: Option<u64>, expression: &str) -> Result<ScriptValue> {
+        let index = self.remote_tab_index(target)?;
+        self.tabs[index].evaluate_script(expression)
+    }
+}
+
+fn run_with_winit_backend(app: BrowserApp) -> Result<()> {
+    configure_winit_backend();
+    run_event_loop(app)
+}
+
+fn configure_winit_backend() {
+    configure_winit_backend_for_wslg();
+    if env::var_os("ORINIUM_FORCE_X11").is_some() {
+        configure_winit_backend_forced_x11();
+    }
 }
 
 fn run_event_loop(app: BrowserApp) -> Result<()> {
-    let event_loop =
-        winit::event_loop::EventLoop::<crate::platform::system::State>::with_user_event()
-            .build()?;
+    let event_loop = winit::event_loop::EventLoop::<BrowserEvent>::with_user_event().build()?;
     event_loop.set_control_flow(winit::event_loop::ControlFlow::Poll);
-    let mut app = App::new(app);
+    let mut app = App::new(app, event_loop.create_proxy());
     event_loop.run_app(&mut app)?;
     Ok(())
 }
@@ -451,3 +4498,42 @@ fn configure_winit_backend_for_wslg() {
         log::info!("WSLg detected: defaulting to X11 backend for stability");
     }
 }
+
+/// Checks that the script of the `document` that sent a request may read a
+/// response from `response_url` (same origin, or allowed by CORS).
+///
+/// Script requests always carry cookies, so CORS has to allow credentials.
+fn check_cors_response(
+    document: Option<Initiator>,
+    response_url: &str,
+    headers: &[(String, String)],
+) -> Result<(), PolicyError> {
+    let (Some(document), Ok(response_url)) = (document, Url::parse(response_url)) else {
+        return Ok(());
+    };
+    fetch_policy::check_response(&document, &response_url, headers, true)
+}
+
+/// Turns a top-level response into the HTML shown in the tab.
+///
+/// HTML is decoded with its charset, text and images get a page that shows them,
+/// and anything else is saved to the downloads directory.
+fn document_html(url: &Url, headers: &[(String, String)], body: &[u8]) -> String {
+    let mime = mime::content_type(headers, url, body);
+    match mime::presentation(headers, &mime) {
+        Presentation::Html => mime::decode_text(body, &mime),
+        Presentation::PlainText => {
+            internal_pages::text_document(url, &mime::decode_text(body, &mime))
+        }
+        Presentation::Image => internal_pages::image_document(url, &mime.essence, body),
+        Presentation::Download => {
+            let name = mime::download_file_name(headers, url);
+            let saved = io::downloads_dir().and_then(|dir| io::save_download(&dir, &name, body));
+            match &saved {
+                Ok(path) => log::info!("Saved {} to {:?}", url, path),
+                Err(e) => log::error!("Failed to save {}: {:#}", url, e),
+            }
+            internal_pages::download_page(url, &saved)
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! フォームの自動入力
+//!
+//! 名前、メールアドレス、住所などの組（プロファイル）を覚えておき、入力欄の
+//! `autocomplete` 属性か name / id から種類が分かれば、その種類の値を候補に出す。
+//! プロファイルは送信されたフォームから作り、プロファイルの `autofill` ファイルに保存する。
+//!
+//! ```text
+//! profile
+//! name	Taro Yamada
+//! email	taro@example.com
+//! postal-code	100-0001
+//! ```
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::engine::html::HtmlNodeType;
+use crate::platform::io;
+
+/// プロファイル内の自動入力ファイル名
+pub const AUTOFILL_FILE_NAME: &str = "autofill";
+
+/// 覚えておくプロファイルの数（古いものから消す）
+const MAX_PROFILES: usize = 20;
+
+/// 自動入力できる欄の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutofillField {
+    Name,
+    GivenName,
+    FamilyName,
+    Email,
+    Tel,
+    Organization,
+    StreetAddress,
+    City,
+    Region,
+    PostalCode,
+    Country,
+}
+
+impl AutofillField {
+    const ALL: [Self; 11] = [
+        Self::Name,
+        Self::GivenName,
+        Self::FamilyName,
+        Self::Email,
+        Self::Tel,
+        Self::Organization,
+        Self::StreetAddress,
+        Self::City,
+        Self::Region,
+        Self::PostalCode,
+        Self::Country,
+    ];
+
+    /// `autocomplete` 属性での名前（ファイルの鍵にも使う）
+    pub fn token(&self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::GivenName => "given-name",
+            Self::FamilyName => "family-name",
+            Self::Email => "email",
+            Self::Tel => "tel",
+            Self::Organization => "organization",
+            Self::StreetAddress => "street-address",
+            Self::City => "address-level2",
+            Self::Region => "address-level1",
+            Self::PostalCode => "postal-code",
+            Self::Country => "country-name",
+        }
+    }
+
+    pub fn from_token(token: &str) -> Option<Self> {
+        let token = token.trim().to_ascii_lowercase();
+        match token.as_str() {
+            "address-line1" => return Some(Self::StreetAddress),
+            "country" => return Some(Self::Country),
+            _ => {}
+        }
+        Self::ALL.into_iter().find(|field| field.token() == token)
+    }
+
+    /// 入力欄 node の種類（分からない欄や自動入力しない欄なら None）
+    ///
+    /// `autocomplete` 属性の最後の語を先に見る（`shipping street-address` など）。
+    /// `autocomplete="off"` の欄とテキスト以外の欄には出さない。属性がなければ
+    /// type（email / tel）と name / id に含まれる語から推し量る。
+    pub fn for_input(node: &HtmlNodeType) -> Option<Self> {
+        if node.tag_name() != Some("input") {
+            return None;
+        }
+        let input_type = node
+            .get_attr("type")
+            .map(|t| t.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        if !matches!(
+            input_type.as_str(),
+            "" | "text" | "email" | "tel" | "search"
+        ) {
+            return None;
+        }
+        if let Some(autocomplete) = node.get_attr("autocomplete") {
+            let token = autocomplete.split_whitespace().last().unwrap_or_default();
+            if token.eq_ignore_ascii_case("off") {
+                return None;
+            }
+            if let Some(field) = Self::from_token(token) {
+                return Some(field);
+            }
+        }
+        match input_type.as_str() {
+            "email" => return Some(Self::Email),
+            "tel" => return Some(Self::Tel),
+            _ => {}
+        }
+        let names = [node.get_attr("name"), node.get_attr("id")];
+        names
+            .into_iter()
+            .flatten()
+            .find_map(|name| Self::guess(&name.to_ascii_lowercase()))
+    }
+
+    /// name や id に含まれる語から種類を推し量る
+    fn guess(name: &str) -> Option<Self> {
+        let has = |words: &[&str]| words.iter().any(|w| name.contains(w));
+        let field = if has(&["mail"]) {
+            Self::Email
+        } else if has(&["phone", "tel"]) {
+            Self::Tel
+        } else if has(&["zip", "postal", "postcode"]) {
+            Self::PostalCode
+        } else if has(&["city", "town"]) {
+            Self::City
+        } else if has(&["state", "region", "prefecture", "province"]) {
+            Self::Region
+        } else if has(&["country"]) {
+            Self::Country
+        } else if has(&["address", "street"]) {
+            Self::StreetAddress
+        } else if has(&["company", "organization", "organisation"]) {
+            Self::Organization
+        } else if has(&["first", "given", "fname"]) {
+            Self::GivenName
+        } else if has(&["last", "family", "surname", "lname"]) {
+            Self::FamilyName
+        } else if has(&["name"]) && !has(&["user", "login", "nick"]) {
+            Self::Name
+        } else {
+            return None;
+        };
+        Some(field)
+    }
+}
+
+/// 1 人分の値の組
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutofillProfile {
+    values: Vec<(AutofillField, String)>,
+}
+
+impl AutofillProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, field: AutofillField) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(f, _)| *f == field)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// field の値を value にする（前後の空白を除いて空なら何もしない）
+    pub fn set(&mut self, field: AutofillField, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        match self.values.iter_mut().find(|(f, _)| *f == field) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.values.push((field, value.to_string())),
+        }
+    }
+
+    /// other の値がすべて同じ値でこのプロファイルにあるか
+    fn contains(&self, other: &Self) -> bool {
+        other
+            .values
+            .iter()
+            .all(|(field, value)| self.get(*field) == Some(value.as_str()))
+    }
+}
+
+/// 覚えているプロファイル
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AutofillStore {
+    /// 古い順
+    profiles: Vec<AutofillProfile>,
+}
+
+impl AutofillStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn profiles(&self) -> &[AutofillProfile] {
+        &self.profiles
+    }
+
+    /// 送信されたフォームのプロファイルを覚える。増えたら true
+    ///
+    /// 覚えているものに含まれるなら増やさず、逆に覚えているものを含むなら置き換える。
+    pub fn add_profile(&mut self, profile: AutofillProfile) -> bool {
+        if profile.is_empty() || self.profiles.iter().any(|p| p.contains(&profile)) {
+            return false;
+        }
+        self.profiles.retain(|p| !profile.contains(p));
+        self.profiles.push(profile);
+        if self.profiles.len() > MAX_PROFILES {
+            self.profiles.remove(0);
+        }
+        true
+    }
+
+    /// field の欄に typed まで入力したときの候補（新しいプロファイルの値から、重複なし）
+    ///
+    /// 大文字と小文字を区別せずに typed で始まる値を返す。入力済みの値と同じものは出さない。
+    pub fn suggestions(&self, field: AutofillField, typed: &str) -> Vec<String> {
+        let typed = typed.trim().to_lowercase();
+        let mut suggestions: Vec<String> = Vec::new();
+        for value in self.profiles.iter().rev().filter_map(|p| p.get(field)) {
+            let lower = value.to_lowercase();
+            if lower.starts_with(&typed)
+                && lower != typed
+                && !suggestions.iter().any(|s| s.to_lowercase() == lower)
+            {
+                suggestions.push(value.to_string());
+            }
+        }
+        suggestions
+    }
+
+    pub fn clear(&mut self) {
+        self.profiles.clear();
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for profile in &self.profiles {
+            out.push_str("profile\n");
+            for (field, value) in &profile.values {
+                // 値の中の改行は行の区切りと紛れるので空白にする
+                let value = value.replace(['\t', '\n', '\r'], " ");
+                out.push_str(&format!("{}\t{}\n", field.token(), value));
+            }
+        }
+        out
+    }
+
+    /// 読めない行と、`profile` の行より前の値は飛ばす
+    pub fn parse(text: &str) -> Self {
+        let mut store = Self::default();
+        let mut current: Option<AutofillProfile> = None;
+
+        for line in text.lines() {
+            if line == "profile" {
+                store
+                    .profiles
+                    .extend(current.take().filter(|p| !p.is_empty()));
+                current = Some(AutofillProfile::new());
+                continue;
+            }
+            let Some(profile) = current.as_mut() else {
+                continue;
+            };
+            let Some((field, value)) = line
+                .split_once('\t')
+                .and_then(|(token, value)| Some((AutofillField::from_token(token)?, value)))
+            else {
+                log::warn!("Skipping invalid autofill entry: {:?}", line);
+                continue;
+            };
+            profile.set(field, value);
+        }
+        store.profiles.extend(current.filter(|p| !p.is_empty()));
+
+        store
+    }
+
+    /// path から読み込む。ファイルがなければ空
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        io::write_atomic(path, self.serialize().as_bytes())
+    }
+}
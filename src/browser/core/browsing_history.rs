@@ -0,0 +1,199 @@
+//! 閲覧履歴
+//!
+//! 読み込みに成功したページを URL ごとに 1 件（タイトル, 最終訪問時刻, 訪問回数）
+//! として記録する。タブの戻る / 進む用の [`super::history::History`] とは別に、
+//! ブラウザ全体で共有してプロファイルの `history` ファイルに保存する。
+//!
+//! ```text
+//! visit	1760000000	3	https://example.com/	Example Domain
+//! ```
+
+use std::path::Path;
+
+use anyhow::Result;
+use url::Url;
+
+use crate::platform::io;
+
+/// プロファイル内の閲覧履歴ファイル名
+pub const HISTORY_FILE_NAME: &str = "history";
+
+/// 1 つの URL の訪問記録
+#[derive(Debug, Clone, PartialEq)]
+pub struct Visit {
+    pub url: Url,
+    pub title: Option<String>,
+    /// 最後に訪れた時刻（UNIX 時間の秒）
+    pub last_visit: u64,
+    pub visit_count: u32,
+}
+
+impl Visit {
+    /// 補完で入力と比べる形（スキームと `www.` を除いた URL）
+    fn url_without_scheme(&self) -> &str {
+        let s = self.url.as_str();
+        let s = s.split_once("://").map_or(s, |(_, rest)| rest);
+        s.strip_prefix("www.").unwrap_or(s)
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        self.url.as_str().to_lowercase().contains(query)
+            || self
+                .title
+                .as_ref()
+                .is_some_and(|t| t.to_lowercase().contains(query))
+    }
+}
+
+/// 閲覧履歴
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BrowsingHistory {
+    visits: Vec<Visit>,
+}
+
+impl BrowsingHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.visits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.visits.is_empty()
+    }
+
+    /// 記録した順の訪問
+    pub fn iter(&self) -> impl Iterator<Item = &Visit> {
+        self.visits.iter()
+    }
+
+    pub fn get(&self, url: &Url) -> Option<&Visit> {
+        self.visits.iter().find(|v| v.url == *url)
+    }
+
+    /// url への訪問を記録する。title が None なら前のタイトルを残す
+    pub fn record_visit(&mut self, url: Url, title: Option<String>, timestamp: u64) {
+        let title = title.filter(|t| !t.trim().is_empty());
+
+        if let Some(visit) = self.visits.iter_mut().find(|v| v.url == url) {
+            visit.visit_count += 1;
+            visit.last_visit = visit.last_visit.max(timestamp);
+            if title.is_some() {
+                visit.title = title;
+            }
+            return;
+        }
+
+        self.visits.push(Visit {
+            url,
+            title,
+            last_visit: timestamp,
+            visit_count: 1,
+        });
+    }
+
+    /// URL かタイトルに query を含む訪問を新しい順に返す（空なら全件）
+    pub fn search(&self, query: &str) -> Vec<&Visit> {
+        let query = query.trim().to_lowercase();
+        let mut found: Vec<&Visit> = self.visits.iter().filter(|v| v.matches(&query)).collect();
+        found.sort_by(|a, b| b.last_visit.cmp(&a.last_visit));
+        found
+    }
+
+    /// URL バーの入力 input の補完候補を最大 limit 件返す
+    ///
+    /// URL が入力で始まるものを先に、次に URL かタイトルに入力を含むものを並べる。
+    /// それぞれの中では訪問回数が多く、新しいものほど前に来る。
+    pub fn suggest(&self, input: &str, limit: usize) -> Vec<&Visit> {
+        let input = input.trim().to_lowercase();
+        if input.is_empty() {
+            return Vec::new();
+        }
+        let input_without_scheme = input.split_once("://").map_or(input.as_str(), |(_, r)| r);
+
+        let mut found: Vec<(bool, &Visit)> = self
+            .visits
+            .iter()
+            .filter_map(|v| {
+                let prefix = v
+                    .url_without_scheme()
+                    .to_lowercase()
+                    .starts_with(input_without_scheme)
+                    || v.url.as_str().to_lowercase().starts_with(&input);
+                (prefix || v.matches(&input)).then_some((prefix, v))
+            })
+            .collect();
+        found.sort_by(|(a_prefix, a), (b_prefix, b)| {
+            b_prefix
+                .cmp(a_prefix)
+                .then(b.visit_count.cmp(&a.visit_count))
+                .then(b.last_visit.cmp(&a.last_visit))
+        });
+
+        found.into_iter().take(limit).map(|(_, v)| v).collect()
+    }
+
+    pub fn clear(&mut self) {
+        self.visits.clear();
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for visit in &self.visits {
+            // タイトルの中のタブ文字と改行は区切りと紛れるので空白にする
+            let title = visit
+                .title
+                .as_deref()
+                .unwrap_or("")
+                .replace(['\t', '\n', '\r'], " ");
+            out.push_str(&format!(
+                "visit\t{}\t{}\t{}\t{}\n",
+                visit.last_visit, visit.visit_count, visit.url, title
+            ));
+        }
+        out
+    }
+
+    /// 読めない行は飛ばす
+    pub fn parse(text: &str) -> Self {
+        let mut history = Self::default();
+
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            if fields.next() != Some("visit") {
+                continue;
+            }
+            let last_visit = fields.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+            let visit_count = fields.next().and_then(|s| s.parse().ok()).unwrap_or(1);
+            let Some(url) = fields.next().and_then(|s| Url::parse(s).ok()) else {
+                log::warn!("Skipping invalid history entry: {:?}", line);
+                continue;
+            };
+            let title = fields.next().filter(|t| !t.is_empty()).map(str::to_string);
+
+            history.visits.push(Visit {
+                url,
+                title,
+                last_visit,
+                visit_count,
+            });
+        }
+
+        history
+    }
+
+    /// path から読み込む。ファイルがなければ空の履歴
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        io::write_atomic(path, self.serialize().as_bytes())
+    }
+}
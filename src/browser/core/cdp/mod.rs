@@ -0,0 +1,747 @@
+//! Chrome DevTools Protocol（CDP）の一部を話すリモートデバッグのサーバー
+//!
+//! `--remote-debugging-port` で有効にすると、外部のツールや結合テストから WebSocket で
+//! ブラウザを操作できる。対応するのは次のメソッドだけ。
+//!
+//! - `Browser.getVersion`、`Target.getTargets`
+//! - `Page.enable` / `Page.disable`（有効な間は読み込みが終わると `Page.loadEventFired`）
+//! - `Page.navigate`、`Page.captureScreenshot`（PNG のみ）
+//! - `DOM.getDocument`
+//! - `Runtime.evaluate`（`js` フィーチャーを有効にしたビルドのみ）
+//!
+//! HTTP の `/json/version` と `/json/list` でターゲット（タブ）の一覧と接続先を返す。
+//! `/devtools/page/<id>` に繋ぐとそのタブを、`/devtools/browser` に繋ぐとそのときの
+//! アクティブなタブを操作する。
+//!
+//! サーバーはノンブロッキングのソケットを持ち、ブラウザのイベントループから
+//! [`CdpServer::poll`] で回す。
+//!
+//! 手元のほかのブラウザで開いたページからも `ws://127.0.0.1:<port>` には繋げるので、
+//! `Origin` ヘッダーの付いた WebSocket の接続は [`CdpServer::set_allowed_origins`] で
+//! 許したオリジンからしか受けない（Chrome の `--remote-allow-origins` と同じ）。
+//! DNS リバインディングを防ぐため、`Host` が `localhost` か IP アドレスでない
+//! リクエストも断る。
+
+pub mod json;
+pub mod websocket;
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use url::Url;
+
+use crate::engine::html::HtmlNodeType;
+use crate::engine::script::ScriptValue;
+use crate::engine::tree::NodeRef;
+use crate::platform::base64;
+use json::Json;
+use websocket::{Frame, HttpRequest};
+
+/// 話せるプロトコルの版
+pub const PROTOCOL_VERSION: &str = "1.3";
+
+/// 同時に繋げるクライアントの数
+const MAX_CONNECTIONS: usize = 16;
+
+/// CDP のエラーコード（JSON-RPC と同じ）
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// リモートデバッグで操作されるブラウザ
+///
+/// target はタブの id。None ならそのときのアクティブなタブ。
+pub trait RemoteTarget {
+    /// 開いているタブ
+    fn targets(&self) -> Vec<TargetInfo>;
+    /// タブ target を url に移動させ、そのタブの id を返す（タブがなければ開く）
+    fn navigate(&mut self, target: Option<u64>, url: Url) -> Result<u64>;
+    /// タブ target に表示している文書の DOM のルート
+    fn document(&self, target: Option<u64>) -> Result<Option<NodeRef<HtmlNodeType>>>;
+    /// タブ target の読み込みが終わっているか
+    fn is_loaded(&self, target: Option<u64>) -> bool;
+    /// タブ target のページを PNG にする
+    fn capture_screenshot(&mut self, target: Option<u64>) -> Result<Vec<u8>>;
+    /// タブ target の文書で式を評価する
+    fn evaluate(&mut self, target: Option<u64>, expression: &str) -> Result<ScriptValue>;
+}
+
+/// `/json/list` と `Target.getTargets` で返すタブの情報
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetInfo {
+    pub id: u64,
+    pub url: String,
+    pub title: String,
+}
+
+/// CDP のエラー
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProtocolError {
+    code: i64,
+    message: String,
+}
+
+impl ProtocolError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn server(e: anyhow::Error) -> Self {
+        Self::new(SERVER_ERROR, format!("{e:#}"))
+    }
+}
+
+/// 1 つの WebSocket 接続での CDP のやりとり
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// 操作するタブの id（None ならアクティブなタブ）
+    target: Option<u64>,
+    /// Page.enable されているか
+    page_events: bool,
+    /// 前に調べたときにタブの読み込みが終わっていたか
+    loaded: bool,
+}
+
+impl Session {
+    pub fn new(target: Option<u64>) -> Self {
+        Self {
+            target,
+            ..Self::default()
+        }
+    }
+
+    /// クライアントからのメッセージ text に応え、返すメッセージを返す
+    pub fn handle_message(&mut self, host: &mut dyn RemoteTarget, text: &str) -> Vec<String> {
+        let message = match Json::parse(text) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = ProtocolError::new(PARSE_ERROR, format!("Message must be JSON: {e}"));
+                return vec![error_response(Json::Number(0.0), &error)];
+            }
+        };
+        let id = message.get("id").cloned().unwrap_or(Json::Null);
+        let Some(method) = message.get("method").and_then(Json::as_str) else {
+            let error = ProtocolError::new(INVALID_PARAMS, "Message must have a method");
+            return vec![error_response(id, &error)];
+        };
+        let params = message
+            .get("params")
+            .cloned()
+            .unwrap_or(Json::Object(Vec::new()));
+
+        let mut out = vec![match self.call(host, method, &params) {
+            Ok(result) => Json::object([("id", id), ("result", result)]).to_string(),
+            Err(error) => error_response(id, &error),
+        }];
+        out.extend(self.poll_events(host));
+        out
+    }
+
+    /// 前に調べてから起きたイベント
+    pub fn poll_events(&mut self, host: &dyn RemoteTarget) -> Vec<String> {
+        let loaded = host.is_loaded(self.target);
+        let fired = loaded && !self.loaded;
+        self.loaded = loaded;
+        if !(fired && self.page_events) {
+            return Vec::new();
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_secs_f64());
+        vec![
+            Json::object([
+                ("method", "Page.loadEventFired".into()),
+                ("params", Json::object([("timestamp", timestamp.into())])),
+            ])
+            .to_string(),
+        ]
+    }
+
+    fn call(
+        &mut self,
+        host: &mut dyn RemoteTarget,
+        method: &str,
+        params: &Json,
+    ) -> Result<Json, ProtocolError> {
+        let empty = || Json::Object(Vec::new());
+        match method {
+            "Browser.getVersion" => Ok(Json::object([
+                ("protocolVersion", PROTOCOL_VERSION.into()),
+                ("product", product().into()),
+                ("revision", "".into()),
+                ("userAgent", user_agent().into()),
+                ("jsVersion", "".into()),
+            ])),
+            "Target.getTargets" => Ok(Json::object([(
+                "targetInfos",
+                Json::Array(
+                    host.targets()
+                        .iter()
+                        .map(|t| {
+                            Json::object([
+                                ("targetId", t.id.to_string().into()),
+                                ("type", "page".into()),
+                                ("title", t.title.as_str().into()),
+                                ("url", t.url.as_str().into()),
+                                ("attached", false.into()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            )])),
+            "Page.enable" => {
+                self.page_events = true;
+                Ok(empty())
+            }
+            "Page.disable" => {
+                self.page_events = false;
+                Ok(empty())
+            }
+            // 何も送らないドメインは有効にするだけ
+            "DOM.enable" | "DOM.disable" | "Runtime.enable" | "Runtime.disable" => Ok(empty()),
+            "Page.navigate" => {
+                let url = params
+                    .get("url")
+                    .and_then(Json::as_str)
+                    .ok_or_else(|| ProtocolError::new(INVALID_PARAMS, "url must be a string"))?;
+                let url = Url::parse(url).map_err(|e| {
+                    ProtocolError::new(INVALID_PARAMS, format!("Invalid url {url:?}: {e}"))
+                })?;
+                let id = host
+                    .navigate(self.target, url)
+                    .map_err(ProtocolError::server)?;
+                // 新しい読み込みの終わりで loadEventFired を送る
+                self.loaded = false;
+                Ok(Json::object([
+                    ("frameId", id.to_string().into()),
+                    ("loaderId", id.to_string().into()),
+                ]))
+            }
+            "Page.captureScreenshot" => {
+                let format = params.get("format").and_then(Json::as_str).unwrap_or("png");
+                if format != "png" {
+                    return Err(ProtocolError::new(
+                        INVALID_PARAMS,
+                        format!("Unsupported screenshot format: {format}"),
+                    ));
+                }
+                let png = host
+                    .capture_screenshot(self.target)
+                    .map_err(ProtocolError::server)?;
+                Ok(Json::object([("data", base64::encode(&png).into())]))
+            }
+            "DOM.getDocument" => {
+                // depth は省略なら 1、-1 ならすべて
+                let depth = params.get("depth").and_then(Json::as_i64).unwrap_or(1);
+                let depth = usize::try_from(depth).unwrap_or(usize::MAX);
+                let root = host
+                    .document(self.target)
+                    .map_err(ProtocolError::server)?
+                    .ok_or_else(|| ProtocolError::new(SERVER_ERROR, "Document is not loaded"))?;
+                let mut next_id = 1;
+                Ok(Json::object([(
+                    "root",
+                    dom_node(&root, depth, &mut next_id),
+                )]))
+            }
+            "Runtime.evaluate" => {
+                let expression =
+                    params
+                        .get("expression")
+                        .and_then(Json::as_str)
+                        .ok_or_else(|| {
+                            ProtocolError::new(INVALID_PARAMS, "expression must be a string")
+                        })?;
+                Ok(match host.evaluate(self.target, expression) {
+                    Ok(value) => Json::object([("result", remote_object(&value))]),
+                    // 例外は結果として返す
+                    Err(e) => Json::object([
+                        (
+                            "result",
+                            Json::object([
+                                ("type", "object".into()),
+                                ("subtype", "error".into()),
+                                ("description", format!("{e:#}").into()),
+                            ]),
+                        ),
+                        (
+                            "exceptionDetails",
+                            Json::object([
+                                ("exceptionId", 1u64.into()),
+                                ("text", format!("{e:#}").into()),
+                                ("lineNumber", 0u64.into()),
+                                ("columnNumber", 0u64.into()),
+                            ]),
+                        ),
+                    ]),
+                })
+            }
+            _ => Err(ProtocolError::new(
+                METHOD_NOT_FOUND,
+                format!("'{method}' wasn't found"),
+            )),
+        }
+    }
+}
+
+fn error_response(id: Json, error: &ProtocolError) -> String {
+    Json::object([
+        ("id", id),
+        (
+            "error",
+            Json::object([
+                ("code", error.code.into()),
+                ("message", error.message.as_str().into()),
+            ]),
+        ),
+    ])
+    .to_string()
+}
+
+fn product() -> String {
+    format!("Orinium/{}", env!("CARGO_PKG_VERSION"))
+}
+
+fn user_agent() -> String {
+    crate::platform::network::config::NetworkConfig::default().user_agent
+}
+
+/// DOM の node を CDP の Node にする。depth 段下の子まで入れる
+///
+/// nodeId は呼ぶたびに振り直す（文書順の番号）。
+fn dom_node(node: &NodeRef<HtmlNodeType>, depth: usize, next_id: &mut u64) -> Json {
+    let id = *next_id;
+    *next_id += 1;
+
+    let node_ref = node.borrow();
+    let (node_type, node_name, local_name, node_value): (u64, String, String, String) =
+        match &node_ref.value {
+            HtmlNodeType::Document => (9, "#document".into(), String::new(), String::new()),
+            HtmlNodeType::Element { tag_name, .. } => (
+                1,
+                tag_name.to_ascii_uppercase(),
+                tag_name.clone(),
+                String::new(),
+            ),
+            HtmlNodeType::Text(text) => (3, "#text".into(), String::new(), text.clone()),
+            HtmlNodeType::Comment(text) => (8, "#comment".into(), String::new(), text.clone()),
+            HtmlNodeType::Doctype { name, .. } => (
+                10,
+                name.clone().unwrap_or_else(|| "html".to_string()),
+                String::new(),
+                String::new(),
+            ),
+            HtmlNodeType::InvalidNode(..) => (8, "#comment".into(), String::new(), String::new()),
+        };
+    let children = node_ref.children();
+
+    let mut members: Vec<(&str, Json)> = vec![
+        ("nodeId", id.into()),
+        ("backendNodeId", id.into()),
+        ("nodeType", node_type.into()),
+        ("nodeName", node_name.into()),
+        ("localName", local_name.into()),
+        ("nodeValue", node_value.into()),
+        ("childNodeCount", children.len().into()),
+    ];
+    if let HtmlNodeType::Element { attributes, .. } = &node_ref.value {
+        let flat: Vec<Json> = attributes
+            .iter()
+            .flat_map(|a| [a.name.as_str().into(), a.value.as_str().into()])
+            .collect();
+        members.push(("attributes", Json::Array(flat)));
+    }
+    if depth > 0 && !children.is_empty() {
+        let children = children
+            .iter()
+            .map(|child| dom_node(child, depth - 1, next_id))
+            .collect();
+        members.push(("children", Json::Array(children)));
+    }
+    Json::object(members)
+}
+
+/// 評価した値を CDP の RemoteObject にする
+fn remote_object(value: &ScriptValue) -> Json {
+    match value {
+        ScriptValue::Undefined => Json::object([("type", "undefined".into())]),
+        ScriptValue::Null => Json::object([
+            ("type", "object".into()),
+            ("subtype", "null".into()),
+            ("value", Json::Null),
+        ]),
+        ScriptValue::Bool(b) => Json::object([("type", "boolean".into()), ("value", (*b).into())]),
+        ScriptValue::Number(n) => Json::object([
+            ("type", "number".into()),
+            ("value", (*n).into()),
+            ("description", n.to_string().into()),
+        ]),
+        ScriptValue::String(s) => {
+            Json::object([("type", "string".into()), ("value", s.as_str().into())])
+        }
+        ScriptValue::Object(description) => Json::object([
+            ("type", "object".into()),
+            ("description", description.as_str().into()),
+        ]),
+    }
+}
+
+/// 接続の状態
+enum Protocol {
+    /// HTTP のリクエストを待っている
+    Http,
+    WebSocket {
+        session: Session,
+        /// 続きのフレームを待っているメッセージ
+        fragments: Vec<u8>,
+    },
+}
+
+struct Connection {
+    stream: TcpStream,
+    protocol: Protocol,
+    /// 受け取ってまだ処理していないバイト
+    input: Vec<u8>,
+    /// まだ送れていないバイト
+    output: Vec<u8>,
+    /// output を送り終えたら閉じる
+    closing: bool,
+}
+
+/// 断るときの応答
+const FORBIDDEN_RESPONSE: &str =
+    "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// リモートデバッグのサーバー
+pub struct CdpServer {
+    listener: TcpListener,
+    connections: Vec<Connection>,
+    /// Origin ヘッダー付きの WebSocket の接続を受けるオリジン（`*` ならすべて）
+    allowed_origins: Vec<String>,
+}
+
+impl CdpServer {
+    /// addr で待ち受ける（ポート 0 なら空いているポート）
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            connections: Vec::new(),
+            allowed_origins: Vec::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// `Origin` ヘッダーの付いた WebSocket の接続を受けるオリジン
+    ///
+    /// `https://example.com` のように書き、`*` ならすべてのオリジンを許す。
+    /// 空なら（既定）ページからの接続はすべて断り、`Origin` を送らない
+    /// 自動化ツールからの接続だけを受ける。
+    pub fn set_allowed_origins(&mut self, origins: Vec<String>) {
+        self.allowed_origins = origins;
+    }
+
+    /// 新しい接続を受け付け、届いたメッセージを処理し、返事を送る
+    ///
+    /// ブラウザの状態を変えるメッセージを処理したら true。
+    pub fn poll(&mut self, host: &mut dyn RemoteTarget) -> bool {
+        self.accept();
+
+        let addr = self.local_addr().ok();
+        let allowed_origins = &self.allowed_origins;
+        let mut handled = false;
+        for connection in &mut self.connections {
+            match connection.read() {
+                Ok(()) => handled |= connection.process(host, addr, allowed_origins),
+                Err(e) => {
+                    log::debug!(target: "cdp", "Connection closed: {e:#}");
+                    connection.closing = true;
+                    connection.output.clear();
+                }
+            }
+            if let Protocol::WebSocket { session, .. } = &mut connection.protocol {
+                for event in session.poll_events(host) {
+                    connection.send_text(&event);
+                }
+            }
+            if let Err(e) = connection.flush() {
+                log::debug!(target: "cdp", "Failed to send: {e:#}");
+                connection.closing = true;
+                connection.output.clear();
+            }
+        }
+        self.connections
+            .retain(|c| !(c.closing && c.output.is_empty()));
+        handled
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    if self.connections.len() >= MAX_CONNECTIONS {
+                        log::warn!(target: "cdp", "Too many remote debugging clients; refused {peer}");
+                        continue;
+                    }
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        log::warn!(target: "cdp", "Failed to accept {peer}: {e}");
+                        continue;
+                    }
+                    log::info!(target: "cdp", "Remote debugging client connected from {peer}");
+                    self.connections.push(Connection {
+                        stream,
+                        protocol: Protocol::Http,
+                        input: Vec::new(),
+                        output: Vec::new(),
+                        closing: false,
+                    });
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log::warn!(target: "cdp", "Failed to accept a connection: {e}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Connection {
+    /// 届いているバイトを input に足す。相手が閉じていればエラー
+    fn read(&mut self) -> Result<()> {
+        let mut buf = [0u8; 16 * 1024];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => anyhow::bail!("closed by peer"),
+                Ok(n) => self.input.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// output を送れるだけ送る
+    fn flush(&mut self) -> Result<()> {
+        while !self.output.is_empty() {
+            match self.stream.write(&self.output) {
+                Ok(0) => anyhow::bail!("connection closed"),
+                Ok(n) => {
+                    self.output.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(())
+    }
+
+    fn send_text(&mut self, text: &str) {
+        self.output
+            .extend(Frame::encode(websocket::OPCODE_TEXT, text.as_bytes()));
+    }
+
+    /// input にある完全なリクエストやフレームを処理する
+    fn process(
+        &mut self,
+        host: &mut dyn RemoteTarget,
+        addr: Option<SocketAddr>,
+        allowed_origins: &[String],
+    ) -> bool {
+        let mut handled = false;
+        while !self.closing {
+            let result = match self.protocol {
+                Protocol::Http => self.process_http(host, addr, allowed_origins),
+                Protocol::WebSocket { .. } => self.process_frame(host),
+            };
+            match result {
+                Ok(Some(changed)) => handled |= changed,
+                Ok(None) => break,
+                Err(e) => {
+                    log::debug!(target: "cdp", "Protocol error: {e:#}");
+                    self.closing = true;
+                }
+            }
+        }
+        handled
+    }
+
+    fn process_http(
+        &mut self,
+        host: &mut dyn RemoteTarget,
+        addr: Option<SocketAddr>,
+        allowed_origins: &[String],
+    ) -> Result<Option<bool>> {
+        let Some((request, len)) = HttpRequest::parse(&self.input)? else {
+            return Ok(None);
+        };
+        self.input.drain(..len);
+
+        // 別の名前で解決させた（DNS リバインディングの）ページからのリクエスト
+        if let Some(name) = request.header("host")
+            && !is_local_host(name)
+        {
+            log::warn!(target: "cdp", "Refused a request for host {name:?}");
+            return Ok(Some(self.forbid()));
+        }
+
+        let path = request.path.split('?').next().unwrap_or_default();
+        if let Some(key) = request.websocket_key() {
+            // 許していないオリジンのページからの接続
+            if let Some(origin) = request.header("origin")
+                && !is_allowed_origin(origin, allowed_origins)
+            {
+                log::warn!(
+                    target: "cdp",
+                    "Refused a WebSocket connection from origin {origin:?}; allow it with --remote-allow-origins"
+                );
+                return Ok(Some(self.forbid()));
+            }
+
+            let target = match path {
+                "/devtools/browser" => Some(None),
+                _ => path
+                    .strip_prefix("/devtools/page/")
+                    .and_then(|id| id.parse().ok())
+                    .map(Some),
+            };
+            if let Some(target) = target {
+                self.output
+                    .extend(websocket::handshake_response(key).into_bytes());
+                let mut session = Session::new(target);
+                // 繋いだときに読み込みが終わっているタブではイベントを出さない
+                session.loaded = host.is_loaded(target);
+                self.protocol = Protocol::WebSocket {
+                    session,
+                    fragments: Vec::new(),
+                };
+                return Ok(Some(false));
+            }
+        }
+
+        let host_port = addr.map_or_else(|| "127.0.0.1".to_string(), |a| a.to_string());
+        let ws_url = |path: String| format!("ws://{host_port}{path}");
+        let body = match path {
+            "/json/version" => Some(Json::object([
+                ("Browser", product().into()),
+                ("Protocol-Version", PROTOCOL_VERSION.into()),
+                ("User-Agent", user_agent().into()),
+                (
+                    "webSocketDebuggerUrl",
+                    ws_url("/devtools/browser".to_string()).into(),
+                ),
+            ])),
+            "/json" | "/json/list" => Some(Json::Array(
+                host.targets()
+                    .iter()
+                    .map(|t| {
+                        Json::object([
+                            ("id", t.id.to_string().into()),
+                            ("type", "page".into()),
+                            ("title", t.title.as_str().into()),
+                            ("url", t.url.as_str().into()),
+                            (
+                                "webSocketDebuggerUrl",
+                                ws_url(format!("/devtools/page/{}", t.id)).into(),
+                            ),
+                        ])
+                    })
+                    .collect(),
+            )),
+            _ => None,
+        };
+        let response = match body {
+            Some(body) => {
+                let body = body.to_string();
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=UTF-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+            }
+            None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string(),
+        };
+        self.output.extend(response.into_bytes());
+        self.closing = true;
+        Ok(Some(false))
+    }
+
+    /// 403 を返して閉じる
+    fn forbid(&mut self) -> bool {
+        self.output.extend(FORBIDDEN_RESPONSE.as_bytes());
+        self.closing = true;
+        false
+    }
+
+    fn process_frame(&mut self, host: &mut dyn RemoteTarget) -> Result<Option<bool>> {
+        let Some((frame, len)) = Frame::decode(&self.input)? else {
+            return Ok(None);
+        };
+        self.input.drain(..len);
+        let Protocol::WebSocket { session, fragments } = &mut self.protocol else {
+            return Ok(None);
+        };
+
+        match frame.opcode {
+            websocket::OPCODE_PING => {
+                self.output
+                    .extend(Frame::encode(websocket::OPCODE_PONG, &frame.payload));
+                Ok(Some(false))
+            }
+            websocket::OPCODE_PONG => Ok(Some(false)),
+            websocket::OPCODE_CLOSE => {
+                self.output
+                    .extend(Frame::encode(websocket::OPCODE_CLOSE, &frame.payload));
+                self.closing = true;
+                Ok(Some(false))
+            }
+            opcode => {
+                if opcode != websocket::OPCODE_CONTINUATION {
+                    fragments.clear();
+                }
+                fragments.extend_from_slice(&frame.payload);
+                if fragments.len() > websocket::MAX_MESSAGE_SIZE {
+                    anyhow::bail!("WebSocket message is too large");
+                }
+                if !frame.fin {
+                    return Ok(Some(false));
+                }
+                let text = String::from_utf8(std::mem::take(fragments))?;
+                let replies = session.handle_message(host, &text);
+                for reply in replies {
+                    self.send_text(&reply);
+                }
+                Ok(Some(true))
+            }
+        }
+    }
+}
+
+/// Host ヘッダーの値（ポート付きでもよい）が `localhost` か IP アドレスか
+fn is_local_host(value: &str) -> bool {
+    let name = match value.strip_prefix('[') {
+        // [::1]:9222
+        Some(rest) => rest.split(']').next().unwrap_or_default(),
+        None => value.rsplit_once(':').map_or(value, |(name, _)| name),
+    };
+    name.eq_ignore_ascii_case("localhost") || name.parse::<IpAddr>().is_ok()
+}
+
+/// origin からの WebSocket の接続を受けてよいか
+fn is_allowed_origin(origin: &str, allowed: &[String]) -> bool {
+    allowed
+        .iter()
+        .any(|a| a == "*" || a.trim_end_matches('/').eq_ignore_ascii_case(origin))
+}
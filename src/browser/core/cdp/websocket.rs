@@ -0,0 +1,201 @@
+//! WebSocket（RFC 6455）のハンドシェイクとフレーム
+//!
+//! CDP のクライアントと話すのに要る部分だけを扱う。拡張（permessage-deflate など）は
+//! 受け付けず、サーバーから送るフレームはマスクしない。
+
+use anyhow::{Result, bail};
+use ring::digest;
+
+use crate::platform::base64;
+
+/// 受け付けるメッセージの大きさの上限
+pub const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Sec-WebSocket-Accept を作るときに鍵に付ける文字列
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub const OPCODE_CONTINUATION: u8 = 0x0;
+pub const OPCODE_TEXT: u8 = 0x1;
+pub const OPCODE_BINARY: u8 = 0x2;
+pub const OPCODE_CLOSE: u8 = 0x8;
+pub const OPCODE_PING: u8 = 0x9;
+pub const OPCODE_PONG: u8 = 0xA;
+
+/// HTTP のリクエスト（ハンドシェイクと `/json/*` の問い合わせ）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    /// buf の先頭のリクエストを読む。ヘッダーがまだ揃っていなければ Ok(None)
+    ///
+    /// 読めたら、リクエストの長さ（バイト）も返す。
+    pub fn parse(buf: &[u8]) -> Result<Option<(Self, usize)>> {
+        let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if buf.len() > 16 * 1024 {
+                bail!("HTTP request header is too large");
+            }
+            return Ok(None);
+        };
+        let head = String::from_utf8_lossy(&buf[..end]);
+        let mut lines = head.split("\r\n");
+        let request_line = lines.next().unwrap_or_default();
+        let mut parts = request_line.split(' ');
+        let (Some(method), Some(path), Some(_version)) = (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("Invalid HTTP request line: {:?}", request_line);
+        };
+        let headers = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        Ok(Some((
+            Self {
+                method: method.to_string(),
+                path: path.to_string(),
+                headers,
+            },
+            end + 4,
+        )))
+    }
+
+    /// ヘッダー name の値（name は小文字で渡す）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// WebSocket へのアップグレードを求めていれば、その Sec-WebSocket-Key
+    pub fn websocket_key(&self) -> Option<&str> {
+        let upgrade = self.header("upgrade")?;
+        if !upgrade.eq_ignore_ascii_case("websocket") {
+            return None;
+        }
+        self.header("sec-websocket-key")
+    }
+}
+
+/// key に対する Sec-WebSocket-Accept
+pub fn accept_key(key: &str) -> String {
+    // SHA-1 はこの鍵の計算で決められているので、ここでだけ使う
+    let hash = digest::digest(
+        &digest::SHA1_FOR_LEGACY_USE_ONLY,
+        format!("{}{}", key.trim(), ACCEPT_GUID).as_bytes(),
+    );
+    base64::encode(hash.as_ref())
+}
+
+/// ハンドシェイクの応答
+pub fn handshake_response(key: &str) -> String {
+    format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(key)
+    )
+}
+
+/// 1 つのフレーム
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// メッセージの最後のフレームか
+    pub fin: bool,
+    pub opcode: u8,
+    /// マスクを外したペイロード
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// buf の先頭のフレームを読む。まだ揃っていなければ Ok(None)
+    ///
+    /// 読めたら、フレームの長さ（バイト）も返す。
+    pub fn decode(buf: &[u8]) -> Result<Option<(Self, usize)>> {
+        if buf.len() < 2 {
+            return Ok(None);
+        }
+        let fin = buf[0] & 0x80 != 0;
+        if buf[0] & 0x70 != 0 {
+            bail!("WebSocket extensions are not supported");
+        }
+        let opcode = buf[0] & 0x0F;
+        let masked = buf[1] & 0x80 != 0;
+
+        let (len, mut pos) = match buf[1] & 0x7F {
+            126 => {
+                let Some(bytes) = buf.get(2..4) else {
+                    return Ok(None);
+                };
+                (u16::from_be_bytes([bytes[0], bytes[1]]) as u64, 4)
+            }
+            127 => {
+                let Some(bytes) = buf.get(2..10) else {
+                    return Ok(None);
+                };
+                let mut len = [0u8; 8];
+                len.copy_from_slice(bytes);
+                (u64::from_be_bytes(len), 10)
+            }
+            len => (len as u64, 2),
+        };
+        if len > MAX_MESSAGE_SIZE as u64 {
+            bail!("WebSocket frame is too large: {} bytes", len);
+        }
+        let len = len as usize;
+
+        let mask = if masked {
+            let Some(mask) = buf.get(pos..pos + 4) else {
+                return Ok(None);
+            };
+            pos += 4;
+            Some([mask[0], mask[1], mask[2], mask[3]])
+        } else {
+            None
+        };
+        let Some(payload) = buf.get(pos..pos + len) else {
+            return Ok(None);
+        };
+        let payload = match mask {
+            Some(mask) => payload
+                .iter()
+                .enumerate()
+                .map(|(i, b)| b ^ mask[i % 4])
+                .collect(),
+            None => payload.to_vec(),
+        };
+
+        Ok(Some((
+            Self {
+                fin,
+                opcode,
+                payload,
+            },
+            pos + len,
+        )))
+    }
+
+    /// マスクしない 1 つのフレームにする（サーバーから送る形）
+    pub fn encode(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(payload.len() + 10);
+        out.push(0x80 | opcode);
+        match payload.len() {
+            len @ 0..=125 => out.push(len as u8),
+            len @ 126..=0xFFFF => {
+                out.push(126);
+                out.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                out.push(127);
+                out.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        out.extend_from_slice(payload);
+        out
+    }
+}
@@ -1,7 +1,158 @@
-#[derive(Debug, Clone, Copy)]
+//! UI スレッドとブラウザの間のコマンドとイベント
+//!
+//! winit の [`App`](crate::platform::system::App) は入力から作った [`BrowserCommand`] を
+//! [`CommandSender`] でブラウザに送るだけで、その場では実行しない。ブラウザは
+//! [`BrowserApp::process_commands`](super::BrowserApp::process_commands) でまとめて実行し、
+//! 描き直しやタイトルの変化を [`BrowserEvent`] として [`EventSink`]
+//! （winit の `EventLoopProxy`）に知らせる。
+//!
+//! ページの処理をエンジンのスレッドで動かすとき（[`engine_thread`](super::engine_thread)）は、
+//! 描いたフレームとページの状態もこのイベントで UI スレッドに送る。
+
+use std::sync::{Arc, mpsc};
+
+use url::Url;
+use winit::event_loop::EventLoopProxy;
+use winit::window::ResizeDirection;
+
+use crate::engine::accessibility::AccessTree;
+use crate::platform::decode::Decoded;
+use crate::platform::renderer::compositor::Frame;
+
+#[derive(Debug, Clone)]
 pub enum BrowserCommand {
     None,
     Exit,
     RequestRedraw,
     RenameWindowTitle,
+    /// 現在のページを読み込み直す（bypass_cache: キャッシュを使わない）
+    Reload {
+        bypass_cache: bool,
+    },
+    /// 読み込み中のリクエストを止める
+    StopLoading,
+    /// 空のタブを開く
+    NewTab,
+    /// url を新しいタブで開く（background なら今のタブのまま）
+    ///
+    /// referrer は最初の文書の Referer、opener はそのタブの window.opener にする文書の番号。
+    OpenTab {
+        url: Url,
+        background: bool,
+        referrer: Option<Url>,
+        opener: Option<u64>,
+    },
+    /// 空のプライベートタブを開く
+    NewPrivateTab,
+    /// アクティブなタブを閉じる
+    CloseTab,
+    /// 右隣のタブに移る（末尾からは先頭へ）
+    NextTab,
+    /// 左隣のタブに移る（先頭からは末尾へ）
+    PreviousTab,
+    /// index 番目のタブに移る
+    SelectTab(usize),
+    /// 最後のタブに移る
+    SelectLastTab,
+    /// リーダーモードを切り替える
+    ToggleReaderMode,
+    /// 閲覧履歴、Cookie、キャッシュ、localStorage を消す
+    ClearBrowsingData,
+    /// アクティブなタブで url を開く
+    Navigate(Url),
+    /// アクティブなタブを (dx, dy) CSS ピクセルだけスクロールする
+    Scroll {
+        dx: f32,
+        dy: f32,
+    },
+    /// アクティブなタブのページの (x, y)（CSS ピクセル）をクリックする
+    Click {
+        x: f32,
+        y: f32,
+    },
+    /// ウィンドウの大きさが変わった（物理ピクセル）
+    Resize {
+        width: u32,
+        height: u32,
+    },
+    /// デコードのスレッドで画像などのデコードが終わった
+    Decoded(Decoded),
+}
+
+/// ブラウザから UI スレッドに知らせること
+#[derive(Debug, Clone, PartialEq)]
+pub enum BrowserEvent {
+    /// 描き直す
+    NeedsRedraw,
+    /// ウィンドウのタイトルが変わった
+    TitleChanged(String),
+    /// アクティブなタブの読み込みの進み具合（0.0 から 1.0。読み込み中でなければ None）
+    LoadProgress(Option<f32>),
+    /// 終了する（開いているタブは保存済み）
+    Exit,
+    /// 描いたフレーム（エンジンのスレッドで動かすとき。UI スレッドが GPU に渡す）
+    Frame(Arc<Frame>),
+    /// ウィンドウに反映するページの状態（エンジンのスレッドで動かすとき）
+    WindowState(Box<WindowState>),
+    /// OS の枠（タイトルバー）を付けるか。付けないときはブラウザがタイトルバーを描く
+    Decorations(bool),
+    /// ブラウザが描いたタイトルバーで頼まれたウィンドウの操作
+    WindowAction(WindowAction),
+}
+
+/// ブラウザが描いたタイトルバーとウィンドウの縁からの操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowAction {
+    /// 押しているボタンでウィンドウを動かし始める
+    Drag,
+    /// 押しているボタンで、向きの辺か角から大きさを変え始める
+    DragResize(ResizeDirection),
+    Minimize,
+    /// 最大化と元の大きさを切り替える
+    ToggleMaximize,
+}
+
+/// ポインタや IME、支援技術に反映するページの状態
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WindowState {
+    /// ポインタがリンクの上にあるか
+    pub over_link: bool,
+    /// IME の変換候補を出す位置（None なら IME は無効）
+    pub ime_area: Option<(f32, f32, f32, f32)>,
+    /// アクティブなタブのアクセシビリティツリー
+    pub access_tree: Option<AccessTree>,
+    /// ポインタが大きさを変えられるウィンドウの縁にあれば、その向き
+    pub resize_edge: Option<ResizeDirection>,
+}
+
+/// [`BrowserEvent`] を受け取るもの
+pub trait EventSink: Send {
+    fn send(&self, event: BrowserEvent);
+}
+
+impl EventSink for EventLoopProxy<BrowserEvent> {
+    fn send(&self, event: BrowserEvent) {
+        // イベントループが終わっていれば届け先はない
+        let _ = self.send_event(event);
+    }
+}
+
+impl EventSink for mpsc::Sender<BrowserEvent> {
+    fn send(&self, event: BrowserEvent) {
+        let _ = mpsc::Sender::send(self, event);
+    }
+}
+
+/// ブラウザにコマンドを送るもの（複製して別のスレッドからも送れる）
+#[derive(Debug, Clone)]
+pub struct CommandSender(pub(super) mpsc::Sender<BrowserCommand>);
+
+impl CommandSender {
+    /// cmd を送る。次の [`BrowserApp::process_commands`](super::BrowserApp::process_commands)
+    /// で実行される
+    pub fn send(&self, cmd: BrowserCommand) {
+        if !matches!(cmd, BrowserCommand::None) {
+            let _ = self.0.send(cmd);
+        }
+    }
 }
@@ -0,0 +1,595 @@
+//! 広告とトラッカーのブロック
+//!
+//! EasyList などの Adblock Plus 形式のフィルターリストのうち、リクエストを止める規則
+//! （ネットワークフィルター）を読む。要素を隠す規則（`##`）と、正規表現やこのブラウザが
+//! 扱わないオプションを使う規則は読み飛ばす。
+//!
+//! ```text
+//! ! コメント
+//! ||ads.example.com^
+//! /banner/*/ad.js$script,third-party
+//! @@||example.com/ads/allowed.js
+//! ||tracker.example.net^$domain=news.example|~sports.news.example
+//! ```
+//!
+//! 規則は URL の中の単語（英数字の並び）で索引を作り、リクエストの URL に含まれる単語の
+//! 規則だけを調べる。[`ContentBlocker`] は拡張機能として登録し、タブで開く文書以外の
+//! リクエストを止める。サイトごとに止めないようにでき、その一覧はプロファイルの
+//! `content-blocking-exceptions` ファイルに 1 行 1 ホストで保存する。
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use url::Url;
+
+use crate::browser::core::extensions::{
+    Extension, InterceptedRequest, RequestAction, ResourceType,
+};
+use crate::platform::io;
+use crate::platform::network::site;
+
+/// プロファイル内のフィルターリストのディレクトリ名（中の `*.txt` を読む）
+pub const FILTER_LISTS_DIR_NAME: &str = "filter-lists";
+
+/// プロファイル内の、ブロックしないサイトのファイル名
+pub const CONTENT_BLOCKING_EXCEPTIONS_FILE_NAME: &str = "content-blocking-exceptions";
+
+/// 規則が当てはまるリソースの種類（[`ResourceType`] ごとのビット）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ResourceMask(u8);
+
+impl ResourceMask {
+    /// 種類の指定がない規則は文書以外のすべてに当てはまる
+    const DEFAULT: Self = Self(!Self::bit(ResourceType::Document));
+
+    const fn bit(resource: ResourceType) -> u8 {
+        match resource {
+            ResourceType::Document => 1,
+            ResourceType::Subdocument => 2,
+            ResourceType::Stylesheet => 4,
+            ResourceType::Script => 8,
+            ResourceType::XmlHttpRequest => 16,
+            ResourceType::Image => 32,
+            ResourceType::Font => 64,
+        }
+    }
+
+    fn contains(self, resource: ResourceType) -> bool {
+        self.0 & Self::bit(resource) != 0
+    }
+}
+
+/// オプションの種類の名前。このブラウザが読まない種類（media など）は None
+fn resource_type(name: &str) -> Option<Option<ResourceType>> {
+    let resource = match name {
+        "document" => Some(ResourceType::Document),
+        "subdocument" => Some(ResourceType::Subdocument),
+        "stylesheet" | "css" => Some(ResourceType::Stylesheet),
+        "script" => Some(ResourceType::Script),
+        "xmlhttprequest" | "xhr" => Some(ResourceType::XmlHttpRequest),
+        "image" => Some(ResourceType::Image),
+        "font" => Some(ResourceType::Font),
+        "media" | "object" | "ping" | "websocket" | "other" => None,
+        _ => return None,
+    };
+    Some(resource)
+}
+
+/// 1 つのネットワークフィルター
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NetworkFilter {
+    /// `@@` で始まる例外の規則
+    exception: bool,
+    /// `||`：ホスト名の先頭（かそのドットの後）から当てはめる
+    host_anchor: bool,
+    /// `|`：URL の先頭から当てはめる
+    start_anchor: bool,
+    /// 末尾の `|`：URL の最後まで当てはまらなければならない
+    end_anchor: bool,
+    /// `*` と `^` を含む本体（match-case でなければ小文字）
+    pattern: String,
+    match_case: bool,
+    resources: ResourceMask,
+    /// Some(true) は別サイトへのリクエストだけ、Some(false) は同じサイトへのものだけ
+    third_party: Option<bool>,
+    /// 当てはめる文書のドメイン（空ならすべて）
+    domains: Vec<String>,
+    /// 当てはめない文書のドメイン
+    excluded_domains: Vec<String>,
+}
+
+impl NetworkFilter {
+    /// 1 行を読む。ネットワークフィルターでないか、扱えない規則なら None
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty()
+            || line.starts_with('!')
+            || line.starts_with('[')
+            || line.contains("##")
+            || line.contains("#@#")
+            || line.contains("#?#")
+        {
+            return None;
+        }
+
+        let (exception, line) = match line.strip_prefix("@@") {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (pattern, options) = match line.rfind('$') {
+            Some(i) => (&line[..i], Some(&line[i + 1..])),
+            None => (line, None),
+        };
+        // 正規表現の規則は扱わない
+        if pattern.len() > 1 && pattern.starts_with('/') && pattern.ends_with('/') {
+            return None;
+        }
+
+        let mut filter = Self {
+            exception,
+            host_anchor: false,
+            start_anchor: false,
+            end_anchor: false,
+            pattern: String::new(),
+            match_case: false,
+            resources: ResourceMask::DEFAULT,
+            third_party: None,
+            domains: Vec::new(),
+            excluded_domains: Vec::new(),
+        };
+        if let Some(options) = options {
+            filter.parse_options(options)?;
+        }
+
+        let mut pattern = pattern;
+        if let Some(rest) = pattern.strip_prefix("||") {
+            filter.host_anchor = true;
+            pattern = rest;
+        } else if let Some(rest) = pattern.strip_prefix('|') {
+            filter.start_anchor = true;
+            pattern = rest;
+        }
+        if let Some(rest) = pattern.strip_suffix('|') {
+            filter.end_anchor = true;
+            pattern = rest;
+        }
+        // 前後の `*` は何もしない
+        let pattern = pattern.trim_start_matches('*');
+        let pattern = if filter.end_anchor {
+            pattern
+        } else {
+            pattern.trim_end_matches('*')
+        };
+        // 何にでも当てはまる規則は、ドメインを絞っていなければ読まない
+        if pattern.is_empty() && !filter.host_anchor && filter.domains.is_empty() {
+            return None;
+        }
+        filter.pattern = if filter.match_case {
+            pattern.to_string()
+        } else {
+            pattern.to_ascii_lowercase()
+        };
+        Some(filter)
+    }
+
+    /// `$` の後のオプションを読む。扱えないオプションがあれば None
+    fn parse_options(&mut self, options: &str) -> Option<()> {
+        let mut included = 0u8;
+        let mut excluded = 0u8;
+        let mut only_unknown_types = false;
+
+        for option in options.split(',') {
+            let option = option.trim().to_ascii_lowercase();
+            let (negated, name) = match option.strip_prefix('~') {
+                Some(name) => (true, name),
+                None => (false, option.as_str()),
+            };
+            if let Some(resource) = resource_type(name) {
+                match (resource, negated) {
+                    (Some(resource), false) => included |= ResourceMask::bit(resource),
+                    (Some(resource), true) => excluded |= ResourceMask::bit(resource),
+                    (None, false) => only_unknown_types = true,
+                    (None, true) => {}
+                }
+                continue;
+            }
+            match name {
+                "third-party" | "3p" => self.third_party = Some(!negated),
+                "first-party" | "1p" => self.third_party = Some(negated),
+                "match-case" => self.match_case = true,
+                _ if name.starts_with("domain=") => {
+                    for domain in name["domain=".len()..].split('|') {
+                        match domain.strip_prefix('~') {
+                            Some(domain) => self.excluded_domains.push(domain.to_string()),
+                            None => self.domains.push(domain.to_string()),
+                        }
+                    }
+                }
+                _ => return None,
+            }
+        }
+
+        if included != 0 {
+            self.resources = ResourceMask(included);
+        } else if only_unknown_types {
+            // media などだけの規則はこのブラウザのリクエストには当てはまらない
+            return None;
+        }
+        self.resources = ResourceMask(self.resources.0 & !excluded);
+        Some(())
+    }
+
+    /// 索引に使う単語（規則の本体のうち、必ず URL に含まれる一番長い英数字の並び）
+    fn token(&self) -> Option<String> {
+        let bytes = self.pattern.as_bytes();
+        let mut best: Option<(usize, usize)> = None;
+        let mut i = 0;
+        while i < bytes.len() {
+            if !bytes[i].is_ascii_alphanumeric() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < bytes.len() && bytes[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            // `*` に接している単語は URL の中ではもっと長い単語の一部かもしれない
+            let starts_word = start > 0 || self.host_anchor || self.start_anchor;
+            let before_ok = start == 0 || bytes[start - 1] != b'*';
+            let after_ok = i == bytes.len() || bytes[i] != b'*';
+            let ends_word = i < bytes.len() || self.end_anchor;
+            if starts_word
+                && before_ok
+                && after_ok
+                && ends_word
+                && i - start >= 2
+                && best.is_none_or(|(s, e)| i - start > e - s)
+            {
+                best = Some((start, i));
+            }
+        }
+        best.map(|(s, e)| self.pattern[s..e].to_ascii_lowercase())
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        if !self.resources.contains(request.resource) {
+            return false;
+        }
+        if let Some(third_party) = self.third_party
+            && third_party != request.third_party
+        {
+            return false;
+        }
+        if !self.domains.is_empty() || !self.excluded_domains.is_empty() {
+            let Some(host) = request.document_host.as_deref() else {
+                return false;
+            };
+            if self.excluded_domains.iter().any(|d| is_subdomain(host, d)) {
+                return false;
+            }
+            if !self.domains.is_empty() && !self.domains.iter().any(|d| is_subdomain(host, d)) {
+                return false;
+            }
+        }
+
+        let url = if self.match_case {
+            request.url.as_str()
+        } else {
+            request.url_lower.as_str()
+        };
+        let pattern = self.pattern.as_bytes();
+        let text = url.as_bytes();
+        if self.host_anchor {
+            // ホスト名の先頭か、ホスト名の中のドットの後から
+            let (start, end) = request.host_range;
+            (start..end)
+                .filter(|&i| i == start || text[i - 1] == b'.')
+                .any(|i| match_pattern(pattern, &text[i..], false, self.end_anchor))
+        } else {
+            match_pattern(pattern, text, !self.start_anchor, self.end_anchor)
+        }
+    }
+}
+
+/// host が domain かそのサブドメインか
+fn is_subdomain(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|sub| sub.ends_with('.'))
+}
+
+/// `^` に当てはまる区切り文字か（英数字と `_-.%` 以外）
+fn is_separator(c: u8) -> bool {
+    !(c.is_ascii_alphanumeric() || matches!(c, b'_' | b'-' | b'.' | b'%'))
+}
+
+/// text に pattern が当てはまるか
+///
+/// anywhere なら text のどこからでも、そうでなければ先頭から当てはめる（to_end なら
+/// text の最後まで）。当てはまらなければ最後の `*` まで戻ってテキストを 1 文字ずらすだけなので、
+/// `*` がいくつあっても URL の長さとパターンの長さの積の時間で終わる。
+fn match_pattern(pattern: &[u8], text: &[u8], anywhere: bool, to_end: bool) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // 最後の `*` の次のパターンの位置と、その `*` の後に当てはめ始めたテキストの位置
+    let mut star = anywhere.then_some((0, 0));
+    loop {
+        if p == pattern.len() && (!to_end || t == text.len()) {
+            return true;
+        }
+        if p < pattern.len() && t < text.len() {
+            match pattern[p] {
+                b'*' => {
+                    p += 1;
+                    star = Some((p, t));
+                    continue;
+                }
+                b'^' if is_separator(text[t]) => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                c if c != b'^' && c == text[t] => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        } else if p < pattern.len() && matches!(pattern[p], b'*' | b'^') {
+            // テキストの終わりでは `*` は空に、`^` は URL の終わりに当てはまる
+            p += 1;
+            continue;
+        }
+        match star {
+            Some((after_star, start)) if start < text.len() => {
+                star = Some((after_star, start + 1));
+                p = after_star;
+                t = start + 1;
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// 規則に当てはめるリクエスト
+struct Request<'a> {
+    url: &'a Url,
+    url_lower: String,
+    /// URL の文字列の中のホスト名の範囲
+    host_range: (usize, usize),
+    document_host: Option<String>,
+    resource: ResourceType,
+    third_party: bool,
+}
+
+impl<'a> Request<'a> {
+    fn new(request: &InterceptedRequest<'a>) -> Self {
+        let url = request.url;
+        let host = url.host_str().unwrap_or("");
+        let host_start = url.as_str().find(host).unwrap_or(0);
+        let document_host = request
+            .document
+            .and_then(|d| d.host_str())
+            .map(str::to_ascii_lowercase);
+        let third_party = match &document_host {
+            Some(document) => site::registrable_domain(document) != site::registrable_domain(host),
+            None => false,
+        };
+        Self {
+            url,
+            url_lower: url.as_str().to_ascii_lowercase(),
+            host_range: (host_start, host_start + host.len()),
+            document_host,
+            resource: request.resource,
+            third_party,
+        }
+    }
+
+    /// URL の中の単語
+    fn tokens(&self) -> HashSet<&str> {
+        self.url_lower
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|t| t.len() >= 2)
+            .collect()
+    }
+}
+
+/// 単語で索引を付けたフィルター
+#[derive(Debug, Default)]
+struct FilterIndex {
+    filters: Vec<NetworkFilter>,
+    by_token: HashMap<String, Vec<usize>>,
+    /// 単語を取れない規則（いつも調べる）
+    untokenized: Vec<usize>,
+}
+
+impl FilterIndex {
+    fn add(&mut self, filter: NetworkFilter) {
+        let i = self.filters.len();
+        match filter.token() {
+            Some(token) => self.by_token.entry(token).or_default().push(i),
+            None => self.untokenized.push(i),
+        }
+        self.filters.push(filter);
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        let tokenized = request
+            .tokens()
+            .into_iter()
+            .filter_map(|t| self.by_token.get(t))
+            .flatten();
+        tokenized
+            .chain(&self.untokenized)
+            .any(|&i| self.filters[i].matches(request))
+    }
+}
+
+/// フィルターリストで広告とトラッカーのリクエストを止める
+#[derive(Debug)]
+pub struct ContentBlocker {
+    blocking: FilterIndex,
+    exceptions: FilterIndex,
+    /// ブロックしないサイト（文書のホスト名）
+    allowed_sites: RwLock<BTreeSet<String>>,
+    enabled: AtomicBool,
+}
+
+impl Default for ContentBlocker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentBlocker {
+    pub fn new() -> Self {
+        Self {
+            blocking: FilterIndex::default(),
+            exceptions: FilterIndex::default(),
+            allowed_sites: RwLock::new(BTreeSet::new()),
+            enabled: AtomicBool::new(true),
+        }
+    }
+
+    /// フィルターリストの text を足し、読めた規則の数を返す
+    pub fn add_filter_list(&mut self, text: &str) -> usize {
+        let mut count = 0;
+        for filter in text.lines().filter_map(NetworkFilter::parse) {
+            if filter.exception {
+                self.exceptions.add(filter);
+            } else {
+                self.blocking.add(filter);
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// dir の `*.txt` をフィルターリストとして読み、読めた規則の数を返す
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize> {
+        if !dir.exists() {
+            return Ok(0);
+        }
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        paths.sort();
+
+        let mut count = 0;
+        for path in paths {
+            match std::fs::read_to_string(&path) {
+                Ok(text) => count += self.add_filter_list(&text),
+                Err(e) => log::warn!("Failed to read filter list {}: {}", path.display(), e),
+            }
+        }
+        Ok(count)
+    }
+
+    /// 規則の数（例外の規則も含む）
+    pub fn len(&self) -> usize {
+        self.blocking.filters.len() + self.exceptions.filters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// ブロックするかどうかを切り替える（設定）
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// host のページでブロックするか
+    pub fn is_enabled_for(&self, host: &str) -> bool {
+        self.is_enabled()
+            && !self
+                .allowed_sites
+                .read()
+                .is_ok_and(|sites| sites.contains(&host.to_ascii_lowercase()))
+    }
+
+    /// host のページでブロックするかどうかを決める。変わったら true
+    pub fn set_enabled_for(&self, host: &str, enabled: bool) -> bool {
+        let Ok(mut sites) = self.allowed_sites.write() else {
+            return false;
+        };
+        let host = host.to_ascii_lowercase();
+        if enabled {
+            sites.remove(&host)
+        } else {
+            sites.insert(host)
+        }
+    }
+
+    /// ブロックしないサイト
+    pub fn allowed_sites(&self) -> Vec<String> {
+        self.allowed_sites
+            .read()
+            .map(|sites| sites.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// request を止めるか（サイトごとの設定は見ない）
+    pub fn should_block(&self, request: &InterceptedRequest) -> bool {
+        if request.resource == ResourceType::Document
+            || !matches!(request.url.scheme(), "http" | "https")
+        {
+            return false;
+        }
+        let request = Request::new(request);
+        self.blocking.matches(&request) && !self.exceptions.matches(&request)
+    }
+
+    /// ブロックしないサイトの一覧を読む。ファイルがなければ何もしない
+    pub fn load_exceptions(&self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let text = std::fs::read_to_string(path)?;
+        if let Ok(mut sites) = self.allowed_sites.write() {
+            sites.extend(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_ascii_lowercase),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn save_exceptions(&self, path: &Path) -> Result<()> {
+        let mut text = String::new();
+        for site in self.allowed_sites() {
+            text.push_str(&site);
+            text.push('\n');
+        }
+        io::write_atomic(path, text.as_bytes())
+    }
+}
+
+impl Extension for ContentBlocker {
+    fn name(&self) -> &str {
+        "Content blocking"
+    }
+
+    fn intercept_request(&self, request: &InterceptedRequest) -> RequestAction {
+        let site_enabled = request
+            .document
+            .and_then(Url::host_str)
+            .is_none_or(|host| self.is_enabled_for(host));
+        if self.is_enabled() && site_enabled && self.should_block(request) {
+            RequestAction::Block
+        } else {
+            RequestAction::Continue
+        }
+    }
+}
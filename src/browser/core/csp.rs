@@ -0,0 +1,320 @@
+//! Content Security Policy
+//!
+//! `Content-Security-Policy` ヘッダーと `<meta http-equiv="Content-Security-Policy">` を
+//! 読み、WebView がサブリソースを取りに行く前とスクリプトを実行する前に確かめる。
+//! ポリシーが複数あるときは、すべてが許したものだけを読み込む。
+//!
+//! 扱うディレクティブは `default-src`、`script-src`、`style-src`、`img-src`、
+//! `frame-src`（と `child-src`）。レポートは送らない（`-Report-Only` のヘッダーは読まない）。
+//! 要素の style 属性には掛けない。
+
+use std::fmt;
+
+use url::Url;
+
+/// 確かめるディレクティブ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Directive {
+    Script,
+    Style,
+    Image,
+    Font,
+    Frame,
+}
+
+impl Directive {
+    /// 順に探すディレクティブ名（見つからなければ次のものを使う）
+    fn fallbacks(self) -> &'static [&'static str] {
+        match self {
+            Self::Script => &["script-src", "default-src"],
+            Self::Style => &["style-src", "default-src"],
+            Self::Image => &["img-src", "default-src"],
+            Self::Font => &["font-src", "default-src"],
+            Self::Frame => &["frame-src", "child-src", "default-src"],
+        }
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.fallbacks()[0])
+    }
+}
+
+/// ソース式
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Source {
+    None,
+    SelfOrigin,
+    UnsafeInline,
+    /// `'nonce-...'`
+    Nonce(String),
+    /// `'sha256-...'` など（ハッシュは計算しないので一致しない）
+    Hash,
+    /// `*`
+    Any,
+    /// `https:` など
+    Scheme(String),
+    /// `https://*.example.com:8080/path/`
+    Host {
+        scheme: Option<String>,
+        /// `*.` で始まるならそのサブドメイン
+        host: String,
+        /// None なら既定のポート、`Some(None)` なら `*`
+        port: Option<Option<u16>>,
+        path: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Policy {
+    /// (ディレクティブ名, ソースの並び)。同じ名前は最初のものだけ
+    directives: Vec<(String, Vec<Source>)>,
+}
+
+/// 文書に掛かるポリシーの集まり
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentSecurityPolicy {
+    policies: Vec<Policy>,
+}
+
+impl ContentSecurityPolicy {
+    /// ポリシーのない（何でも許す）もの
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 応答のヘッダーの `Content-Security-Policy` をすべて読む
+    pub fn from_headers(headers: &[(String, String)]) -> Self {
+        let mut csp = Self::new();
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("content-security-policy") {
+                csp.add(value);
+            }
+        }
+        csp
+    }
+
+    /// ヘッダーか meta 要素の値を足す（`,` で区切った複数のポリシーを含んでよい）
+    pub fn add(&mut self, value: &str) {
+        for policy in value.split(',') {
+            let policy = parse_policy(policy);
+            if !policy.directives.is_empty() {
+                self.policies.push(policy);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+
+    /// document の文書が directive の対象として url を読み込んでよいか
+    pub fn allows_url(&self, directive: Directive, url: &Url, document: &Url) -> bool {
+        self.policies
+            .iter()
+            .all(|policy| match policy.sources(directive) {
+                Some(sources) => sources.iter().any(|s| s.matches_url(url, document)),
+                None => true,
+            })
+    }
+
+    /// インラインのスクリプトやスタイル（nonce 属性の値）を実行してよいか
+    pub fn allows_inline(&self, directive: Directive, nonce: Option<&str>) -> bool {
+        self.policies
+            .iter()
+            .all(|policy| match policy.sources(directive) {
+                Some(sources) => allows_inline(sources, nonce),
+                None => true,
+            })
+    }
+
+    /// `<script src>` や `<link rel=stylesheet>` を読み込んでよいか
+    ///
+    /// nonce が一致すれば URL によらず許す。
+    pub fn allows_element(
+        &self,
+        directive: Directive,
+        url: &Url,
+        nonce: Option<&str>,
+        document: &Url,
+    ) -> bool {
+        self.policies
+            .iter()
+            .all(|policy| match policy.sources(directive) {
+                Some(sources) => {
+                    sources.iter().any(|s| s.matches_url(url, document))
+                        || nonce.is_some_and(|n| sources.contains(&Source::Nonce(n.to_string())))
+                }
+                None => true,
+            })
+    }
+}
+
+impl Policy {
+    fn sources(&self, directive: Directive) -> Option<&[Source]> {
+        directive.fallbacks().iter().find_map(|name| {
+            self.directives
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, sources)| sources.as_slice())
+        })
+    }
+}
+
+fn allows_inline(sources: &[Source], nonce: Option<&str>) -> bool {
+    if let Some(nonce) = nonce
+        && sources.contains(&Source::Nonce(nonce.to_string()))
+    {
+        return true;
+    }
+    // nonce かハッシュがあれば 'unsafe-inline' は無視する
+    let has_nonce_or_hash = sources
+        .iter()
+        .any(|s| matches!(s, Source::Nonce(_) | Source::Hash));
+    !has_nonce_or_hash && sources.contains(&Source::UnsafeInline)
+}
+
+fn parse_policy(text: &str) -> Policy {
+    let mut policy = Policy::default();
+    for directive in text.split(';') {
+        let mut tokens = directive.split_ascii_whitespace();
+        let Some(name) = tokens.next() else {
+            continue;
+        };
+        let name = name.to_ascii_lowercase();
+        if policy.directives.iter().any(|(n, _)| *n == name) {
+            continue;
+        }
+        let sources = tokens.filter_map(parse_source).collect();
+        policy.directives.push((name, sources));
+    }
+    policy
+}
+
+fn parse_source(token: &str) -> Option<Source> {
+    let lower = token.to_ascii_lowercase();
+    let source = match lower.as_str() {
+        "'none'" => Source::None,
+        "'self'" => Source::SelfOrigin,
+        "'unsafe-inline'" => Source::UnsafeInline,
+        "*" => Source::Any,
+        _ if lower.starts_with("'nonce-") && lower.ends_with('\'') => {
+            // nonce は大文字と小文字を区別する
+            Source::Nonce(token[7..token.len() - 1].to_string())
+        }
+        _ if ["'sha256-", "'sha384-", "'sha512-"]
+            .iter()
+            .any(|p| lower.starts_with(p)) =>
+        {
+            Source::Hash
+        }
+        // 'unsafe-eval'、'strict-dynamic' などは URL に一致しない
+        _ if lower.starts_with('\'') => return None,
+        _ if lower.ends_with(':') && !lower.contains('/') => {
+            Source::Scheme(lower.trim_end_matches(':').to_string())
+        }
+        _ => parse_host_source(token)?,
+    };
+    Some(source)
+}
+
+/// `[scheme://]host[:port][/path]`（スキームとホストは小文字にする）
+fn parse_host_source(text: &str) -> Option<Source> {
+    let (scheme, rest) = match text.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme.to_ascii_lowercase()), rest),
+        None => (None, text),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], Some(rest[i..].to_string())),
+        None => (rest, None),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, "*")) => (host, Some(None)),
+        Some((host, port)) => (host, Some(Some(port.parse().ok()?))),
+        None => (authority, None),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    Some(Source::Host {
+        scheme,
+        host: host.to_ascii_lowercase(),
+        port,
+        path,
+    })
+}
+
+impl Source {
+    fn matches_url(&self, url: &Url, document: &Url) -> bool {
+        match self {
+            Self::None | Self::UnsafeInline | Self::Nonce(_) | Self::Hash => false,
+            // `*` は data: や blob: には一致しない
+            Self::Any => {
+                matches!(url.scheme(), "http" | "https" | "ws" | "wss")
+                    || url.scheme() == document.scheme()
+            }
+            Self::Scheme(scheme) => scheme_matches(scheme, url.scheme()),
+            Self::SelfOrigin => {
+                // file: や data: の文書でも一致するように、opaque なオリジンではなく
+                // スキーム、ホスト、ポートの組で比べる
+                tuple_origin(document) == tuple_origin(url)
+                    // http の文書の 'self' は同じホストの https も許す
+                    || (document.scheme() == "http"
+                        && url.scheme() == "https"
+                        && document.host_str() == url.host_str())
+            }
+            Self::Host {
+                scheme,
+                host,
+                port,
+                path,
+            } => {
+                let scheme_ok = match scheme {
+                    Some(scheme) => scheme_matches(scheme, url.scheme()),
+                    None => scheme_matches(document.scheme(), url.scheme()),
+                };
+                let Some(url_host) = url.host_str() else {
+                    return false;
+                };
+                let url_host = url_host.to_ascii_lowercase();
+                let host_ok = match host.strip_prefix("*.") {
+                    Some(suffix) => url_host.ends_with(&format!(".{suffix}")),
+                    None => host == "*" || *host == url_host,
+                };
+                let port_ok = match port {
+                    Some(None) => true,
+                    Some(Some(port)) => url.port_or_known_default() == Some(*port),
+                    None => url.port().is_none(),
+                };
+                let path_ok = match path.as_deref() {
+                    None | Some("/") => true,
+                    Some(path) if path.ends_with('/') => url.path().starts_with(path),
+                    Some(path) => url.path() == path,
+                };
+                scheme_ok && host_ok && port_ok && path_ok
+            }
+        }
+    }
+}
+
+/// url のスキーム、ホスト（小文字）、ポート（既定のポートも明示した値）
+///
+/// blob: の URL は中の URL のもの。
+fn tuple_origin(url: &Url) -> (String, Option<String>, Option<u16>) {
+    if url.scheme() == "blob"
+        && let Ok(inner) = Url::parse(url.path())
+    {
+        return tuple_origin(&inner);
+    }
+    (
+        url.scheme().to_string(),
+        url.host_str().map(str::to_ascii_lowercase),
+        url.port_or_known_default(),
+    )
+}
+
+/// source の scheme が url の scheme に一致するか（http は https も許す）
+fn scheme_matches(source: &str, url: &str) -> bool {
+    source == url || (source == "http" && url == "https") || (source == "ws" && url == "wss")
+}
@@ -0,0 +1,176 @@
+//! 開発者ツール
+//!
+//! - Ctrl+Shift+C で調べるモードに入り、次にクリックした要素に当たった CSS の規則
+//!   （セレクタ、由来、詳細度、上書きされたか）と最終的な値を `orinium://inspect` に出す。
+//! - 調べるモードの間は、ポインタの下の要素の margin / border / padding / content を
+//!   色付きの半透明の矩形で重ね、タグと大きさと位置を添える。
+//! - Ctrl+Shift+J でタブのコンソール（エンジンの警告とスクリプトの console.*）を
+//!   `orinium://console` に出す。
+
+use std::collections::VecDeque;
+
+use url::Url;
+
+use crate::engine::css::matcher::{ElementChain, ElementInfo};
+use crate::engine::css::values::CssValue;
+use crate::engine::diagnostics::{Level, Message};
+use crate::engine::layouter::cascade::{self, MatchedDeclaration};
+use crate::engine::layouter::css_resolver::ResolvedStyles;
+
+/// 調べた要素のスタイル
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleInspection {
+    /// 要素の表記（`div#main.note` の形）
+    pub element: String,
+    /// 要素のある文書の URL
+    pub document_url: Option<Url>,
+    /// 要素に当たった宣言（優先度の高い順）
+    pub matched: Vec<MatchedDeclaration>,
+    /// 最終的な値（プロパティ名の順）
+    pub computed: Vec<(String, CssValue)>,
+}
+
+impl StyleInspection {
+    /// chain の先頭の要素に resolved_styles を当てた結果をまとめる
+    pub fn new(
+        chain: &ElementChain,
+        resolved_styles: &ResolvedStyles,
+        document_url: Option<Url>,
+    ) -> Option<Self> {
+        let element = element_label(chain.first()?);
+        let matched = cascade::matched_declarations(resolved_styles, chain);
+        let computed = cascade::computed_values(&matched);
+        Some(Self {
+            element,
+            document_url,
+            matched,
+            computed,
+        })
+    }
+}
+
+/// 矩形 (x, y, width, height)（ビューポート座標、CSS px）
+pub type Rect = (f32, f32, f32, f32);
+
+/// ポインタの下の要素の箱（外側から順に）
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxModel {
+    /// 要素の表記（`div#main.note` の形）
+    pub element: String,
+    pub margin: Rect,
+    pub border: Rect,
+    pub padding: Rect,
+    pub content: Rect,
+}
+
+impl BoxModel {
+    /// border box と 4 辺の margin（上、右、下、左）から margin box を決めて組み立てる
+    pub fn new(
+        element: String,
+        border: Rect,
+        padding: Rect,
+        content: Rect,
+        margin: [f32; 4],
+    ) -> Self {
+        let [top, right, bottom, left] = margin;
+        let (x, y, width, height) = border;
+        Self {
+            element,
+            margin: (
+                x - left,
+                y - top,
+                (width + left + right).max(0.0),
+                (height + top + bottom).max(0.0),
+            ),
+            border,
+            padding,
+            content,
+        }
+    }
+}
+
+/// 要素をセレクタの形で表す（`div#main.note`）
+pub fn element_label(element: &ElementInfo) -> String {
+    let mut label = element.tag_name.clone();
+    if let Some(id) = &element.id {
+        label.push('#');
+        label.push_str(id);
+    }
+    for class in &element.classes {
+        label.push('.');
+        label.push_str(class);
+    }
+    label
+}
+
+/// タブのコンソールに残すメッセージの数（古いものから捨てる）
+pub const MAX_CONSOLE_ENTRIES: usize = 500;
+
+/// コンソールの 1 行（同じメッセージはまとめて数える）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsoleEntry {
+    pub message: Message,
+    pub count: usize,
+}
+
+/// 文書ごとのコンソール
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Console {
+    entries: VecDeque<ConsoleEntry>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// message を足す。同じメッセージが既にあれば数を増やす
+    ///
+    /// レイアウトし直すたびに出る警告で埋まらないようにまとめる。
+    pub fn push(&mut self, message: Message) {
+        self.add(message, 1);
+    }
+
+    pub fn extend(&mut self, messages: impl IntoIterator<Item = Message>) {
+        for message in messages {
+            self.push(message);
+        }
+    }
+
+    /// other の行を後ろに足す（`<iframe>` の中の文書のコンソールをまとめるとき）
+    pub fn append(&mut self, other: &Console) {
+        for entry in &other.entries {
+            self.add(entry.message.clone(), entry.count);
+        }
+    }
+
+    fn add(&mut self, message: Message, count: usize) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.message == message) {
+            entry.count += count;
+            return;
+        }
+        if self.entries.len() == MAX_CONSOLE_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(ConsoleEntry { message, count });
+    }
+
+    /// level 以上の行を出した順に返す
+    pub fn entries(&self, level: Level) -> impl Iterator<Item = &ConsoleEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.message.level >= level)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
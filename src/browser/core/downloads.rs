@@ -0,0 +1,154 @@
+//! ダウンロードの一覧
+//!
+//! ファイルは保存先の隣の `<保存先>.part` に受信し、受信し終えたら保存先に移す
+//! （[`crate::platform::network::download`]）。終わっていないダウンロードは
+//! プロファイルの `downloads` ファイルに記録し、次に起動したときに続きから受け取る。
+//!
+//! ```text
+//! download	https://example.com/file.zip	/home/user/Downloads/file.zip
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use url::Url;
+
+use crate::platform::io;
+
+/// プロファイル内のダウンロード一覧のファイル名
+pub const DOWNLOADS_FILE_NAME: &str = "downloads";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadState {
+    InProgress,
+    Completed,
+    /// 失敗した理由
+    Failed(String),
+}
+
+/// 1 つのダウンロード
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Download {
+    pub url: Url,
+    pub path: PathBuf,
+    /// 受信したバイト数（前回までに受信した分を含む）
+    pub received: u64,
+    /// ファイル全体の長さ（わからなければ None）
+    pub total: Option<u64>,
+    pub state: DownloadState,
+}
+
+/// ダウンロードの一覧。ネットワークのリクエスト ID で引く
+#[derive(Debug, Clone, Default)]
+pub struct DownloadManager {
+    downloads: BTreeMap<usize, Download>,
+    modified: bool,
+}
+
+impl DownloadManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// id のリクエストで url を path に受信し始めた
+    pub fn start(&mut self, id: usize, url: Url, path: PathBuf) {
+        self.downloads.insert(
+            id,
+            Download {
+                url,
+                path,
+                received: 0,
+                total: None,
+                state: DownloadState::InProgress,
+            },
+        );
+        self.modified = true;
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.downloads.contains_key(&id)
+    }
+
+    pub fn get(&self, id: usize) -> Option<&Download> {
+        self.downloads.get(&id)
+    }
+
+    /// 始めた順に並べた一覧
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Download)> {
+        self.downloads.iter().map(|(id, download)| (*id, download))
+    }
+
+    pub fn on_progress(&mut self, id: usize, received: u64, total: Option<u64>) {
+        if let Some(download) = self.downloads.get_mut(&id) {
+            download.received = received;
+            download.total = total;
+        }
+    }
+
+    /// id のダウンロードが終わった。Err なら失敗した理由
+    pub fn on_finished(&mut self, id: usize, result: Result<(), String>) {
+        let Some(download) = self.downloads.get_mut(&id) else {
+            return;
+        };
+        download.state = match result {
+            Ok(()) => {
+                download.total = download.total.or(Some(download.received));
+                DownloadState::Completed
+            }
+            Err(reason) => DownloadState::Failed(reason),
+        };
+        self.modified = true;
+    }
+
+    /// 受信中の一覧が変わっていれば true を返し、フラグを下ろす
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::take(&mut self.modified)
+    }
+
+    /// 受信中のものだけを書く（終わったものは次に起動したときには残さない）
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for download in self.downloads.values() {
+            if download.state != DownloadState::InProgress {
+                continue;
+            }
+            let Some(path) = download.path.to_str() else {
+                continue;
+            };
+            out.push_str(&format!("download\t{}\t{}\n", download.url, path));
+        }
+        out
+    }
+
+    /// 前回終わらなかったダウンロード（url, 保存先）を読む。読めない行は飛ばす
+    pub fn parse_unfinished(text: &str) -> Vec<(Url, PathBuf)> {
+        text.lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                if fields.next() != Some("download") {
+                    return None;
+                }
+                let url = fields.next().and_then(|s| Url::parse(s).ok());
+                let path = fields.next().filter(|s| !s.is_empty()).map(PathBuf::from);
+                if url.is_none() || path.is_none() {
+                    log::warn!("Skipping invalid download entry: {:?}", line);
+                }
+                Some((url?, path?))
+            })
+            .collect()
+    }
+
+    /// path から読む。ファイルがなければ空
+    pub fn load_unfinished(path: &Path) -> Result<Vec<(Url, PathBuf)>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse_unfinished(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        io::write_atomic(path, self.serialize().as_bytes())
+    }
+}
@@ -0,0 +1,191 @@
+//! エンジンのスレッド
+//!
+//! ページのパース、スタイル、レイアウトと描画命令づくりを UI スレッドとは別のスレッドで行う。
+//! UI スレッドはウィンドウのイベントを [`EngineMessage`] で送るだけで、描いたフレームと
+//! ページの状態は [`BrowserEvent`] で受け取って GPU とウィンドウに反映する。重いページを
+//! 読んでいる間も、UI スレッドはウィンドウのイベントや大きさの変更を処理し続けられる。
+//!
+//! [`BrowserApp`] はスレッドをまたげない（DOM が `Rc`）ので、エンジンのスレッドの中で作る。
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use winit::event::WindowEvent;
+
+use super::{BrowserApp, BrowserEvent, EventSink, WindowState};
+use crate::engine::accessibility::AccessAction;
+use crate::engine::css::media::ColorScheme;
+use crate::platform::renderer::compositor::{Compositor, FrameRecorder};
+
+/// メッセージがなくても、この間隔でタイマーとネットワークを進める
+const ENGINE_POLL_INTERVAL: Duration = Duration::from_millis(8);
+
+/// UI スレッドからエンジンのスレッドに送るもの
+#[derive(Debug)]
+pub enum EngineMessage {
+    /// ウィンドウを開いた（実際の大きさは頼んだものと違うことがある）
+    WindowOpened {
+        size: (u32, u32),
+        scale_factor: f64,
+        color_scheme: ColorScheme,
+        refresh_rate_millihertz: Option<u32>,
+    },
+    /// ウィンドウのイベント（RedrawRequested は送らない）
+    Window(WindowEvent),
+    /// 支援技術から頼まれた操作
+    Accessibility(AccessAction),
+}
+
+/// エンジンのスレッドで作った BrowserApp が最初に知らせるもの（ウィンドウを開くのに使う）
+#[derive(Debug, Clone)]
+pub struct EngineReady {
+    pub window_size: (u32, u32),
+    pub window_title: String,
+}
+
+/// BrowserApp を動かしているエンジンのスレッド。捨てるとスレッドの終わりを待つ
+pub struct EngineThread {
+    messages: Option<Sender<EngineMessage>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl EngineThread {
+    /// build で作った BrowserApp をエンジンのスレッドで動かし、そのイベントを events に送る
+    ///
+    /// build が失敗したらそのエラーを返す。
+    pub fn spawn<F>(build: F, events: Box<dyn EventSink>) -> Result<(Self, EngineReady)>
+    where
+        F: FnOnce() -> Result<BrowserApp> + Send + 'static,
+    {
+        let (messages, receiver) = mpsc::channel();
+        let (ready_tx, ready_rx) = mpsc::sync_channel(1);
+        let handle = std::thread::Builder::new()
+            .name("orinium-engine".to_string())
+            .spawn(move || {
+                let app = match build() {
+                    Ok(app) => app,
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                        return;
+                    }
+                };
+                let (width, height) = app.window_size();
+                let _ = ready_tx.send(Ok(EngineReady {
+                    window_size: (width as u32, height as u32),
+                    window_title: app.window_title(),
+                }));
+                run(app, receiver, events);
+            })?;
+        let ready = ready_rx
+            .recv()
+            .map_err(|_| anyhow!("The engine thread stopped before the browser started"))??;
+        let engine = Self {
+            messages: Some(messages),
+            handle: Some(handle),
+        };
+        Ok((engine, ready))
+    }
+
+    pub fn send(&self, message: EngineMessage) {
+        if let Some(messages) = &self.messages {
+            let _ = messages.send(message);
+        }
+    }
+}
+
+impl Drop for EngineThread {
+    fn drop(&mut self) {
+        // 送り口を閉じるとエンジンのスレッドのループが終わる
+        self.messages.take();
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            log::error!("The engine thread panicked");
+        }
+    }
+}
+
+/// エンジンのスレッドのループ。UI スレッドが送り口を閉じるまで続ける
+fn run(mut app: BrowserApp, messages: Receiver<EngineMessage>, events: Box<dyn EventSink>) {
+    // ブラウザのイベントは一度ここで受け、描き直しはこのスレッドで済ませる
+    let (sink, browser_events) = mpsc::channel();
+    app.set_event_sink(Box::new(sink));
+    let commands = app.command_sender();
+    let (width, height) = app.window_size();
+    let mut compositor = FrameRecorder::new((width as u32, height as u32));
+    let mut window_state = WindowState::default();
+    let mut redraw = true;
+
+    loop {
+        let first = match messages.recv_timeout(ENGINE_POLL_INTERVAL) {
+            Ok(message) => Some(message),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let mut handled = false;
+        for message in first.into_iter().chain(messages.try_iter()) {
+            handled = true;
+            let cmd = match message {
+                EngineMessage::WindowOpened {
+                    size,
+                    scale_factor,
+                    color_scheme,
+                    refresh_rate_millihertz,
+                } => {
+                    app.set_window_size(size);
+                    app.set_scale_factor(scale_factor);
+                    app.set_system_color_scheme(color_scheme);
+                    if let Some(millihertz) = refresh_rate_millihertz {
+                        app.set_refresh_rate(millihertz);
+                    }
+                    compositor.resize(winit::dpi::PhysicalSize::new(size.0, size.1));
+                    redraw = true;
+                    continue;
+                }
+                EngineMessage::Window(event) => app.handle_window_event(event, &mut compositor),
+                EngineMessage::Accessibility(action) => app.perform_accessibility_action(action),
+            };
+            commands.send(cmd);
+        }
+        commands.send(app.run_scheduled_tasks());
+        commands.send(app.poll_remote_debugging());
+        app.process_commands();
+
+        for event in browser_events.try_iter() {
+            match event {
+                BrowserEvent::NeedsRedraw => redraw = true,
+                event => events.send(event),
+            }
+        }
+        if redraw {
+            redraw = false;
+            // アニメーション中は次のフレームも頼まれる
+            commands.send(app.handle_window_event(WindowEvent::RedrawRequested, &mut compositor));
+        }
+
+        let frame = compositor.take_rendered();
+        if !handled && frame.is_none() {
+            continue;
+        }
+        let state = WindowState {
+            over_link: app.is_over_link(),
+            ime_area: app.ime_cursor_area(),
+            // ツリーは描き直したときだけ作り直す
+            access_tree: if frame.is_some() {
+                app.accessibility_tree()
+            } else {
+                window_state.access_tree.clone()
+            },
+            resize_edge: app.resize_edge(),
+        };
+        if let Some(frame) = frame {
+            events.send(BrowserEvent::Frame(frame));
+        }
+        if state != window_state {
+            window_state = state;
+            events.send(BrowserEvent::WindowState(Box::new(window_state.clone())));
+        }
+    }
+}
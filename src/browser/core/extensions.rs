@@ -0,0 +1,560 @@
+//! 拡張機能
+//!
+//! 拡張機能は 4 つのことができる。
+//!
+//! - URL パターンに合うページに CSS とスクリプトを差し込む（[`ContentScript`]）
+//! - ページのリクエストを止めるか、別の URL に向ける（[`Extension::intercept_request`]）
+//! - ツールバーにボタンを足し、押されたら表示中のページでスクリプトを実行する（[`ToolbarAction`]）
+//! - 自分のスキームの URL を読めるようにする（[`Extension::scheme_handlers`]。コードで登録した拡張機能だけ）
+//!
+//! [`Extension`] を実装したものを [`ExtensionRegistry::register`] で登録するか、
+//! プロファイルの `extensions` ディレクトリに置いたものを起動時に読み込む
+//! （[`ExtensionRegistry::load_dir`]）。ディレクトリの拡張機能は 1 つごとのディレクトリに
+//! `manifest.toml` を置き、ファイルはそのディレクトリからの相対パスで書く。
+//!
+//! ```toml
+//! name = "Quiet reading"
+//!
+//! [[content]]
+//! matches = "https://*.example.com/* https://example.org/*"
+//! css = "content.css"
+//! js = "content.js"
+//!
+//! [[block]]
+//! matches = "*://ads.example.net/*"
+//!
+//! [action]
+//! label = "Q"
+//! title = "Toggle quiet reading"
+//! js = "action.js"
+//! ```
+//!
+//! 差し込むスクリプトはページと同じ realm で、ページのスクリプトの後に実行する。
+//! `<iframe>` の中の文書には差し込まない。プライベートタブでは拡張機能は動かない。
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use url::Url;
+
+use super::schemes::SchemeHandler;
+use crate::browser::core::webview::FetchKind;
+use crate::browser::settings::{is_blank_or_comment, parse_value};
+use crate::engine::html::preload::PreloadDestination;
+
+/// プロファイル内の拡張機能のディレクトリ名
+pub const EXTENSIONS_DIR_NAME: &str = "extensions";
+
+/// 拡張機能のディレクトリの中の設定ファイル名
+pub const MANIFEST_FILE_NAME: &str = "manifest.toml";
+
+/// どの URL に当てはまるか（WebExtensions のマッチパターン）
+///
+/// `<scheme>://<host>/<path>` の形で、scheme の `*` は http と https、host の `*` は
+/// どのホストにも、`*.example.com` は example.com とそのサブドメインに当てはまる。
+/// path（クエリも含む）の `*` は何文字にでも当てはまる。`<all_urls>` は http、https、
+/// file のすべての URL に当てはまる。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchPattern {
+    scheme: SchemePattern,
+    host: HostPattern,
+    path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SchemePattern {
+    /// http と https
+    Web,
+    /// `<all_urls>`（http、https、file）
+    All,
+    Exact(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostPattern {
+    Any,
+    /// このホストとそのサブドメイン
+    Domain(String),
+    Exact(String),
+}
+
+impl MatchPattern {
+    pub fn parse(pattern: &str) -> Result<Self> {
+        if pattern == "<all_urls>" {
+            return Ok(Self {
+                scheme: SchemePattern::All,
+                host: HostPattern::Any,
+                path: "*".to_string(),
+            });
+        }
+
+        let (scheme, rest) = pattern
+            .split_once("://")
+            .ok_or_else(|| anyhow!("Missing scheme in match pattern {:?}", pattern))?;
+        let scheme = match scheme {
+            "*" => SchemePattern::Web,
+            "http" | "https" | "file" => SchemePattern::Exact(scheme.to_string()),
+            _ => bail!("Unsupported scheme in match pattern {:?}", pattern),
+        };
+        let (host, path) = match rest.find('/') {
+            Some(i) => rest.split_at(i),
+            None => bail!("Missing path in match pattern {:?}", pattern),
+        };
+        let host = match host {
+            "*" => HostPattern::Any,
+            "" if scheme == SchemePattern::Exact("file".to_string()) => HostPattern::Any,
+            "" => bail!("Missing host in match pattern {:?}", pattern),
+            host => match host.strip_prefix("*.") {
+                Some(domain) => HostPattern::Domain(domain.to_ascii_lowercase()),
+                None if host.contains('*') => {
+                    bail!("Invalid host in match pattern {:?}", pattern)
+                }
+                None => HostPattern::Exact(host.to_ascii_lowercase()),
+            },
+        };
+        Ok(Self {
+            scheme,
+            host,
+            path: path.to_string(),
+        })
+    }
+
+    pub fn matches(&self, url: &Url) -> bool {
+        let scheme_matches = match &self.scheme {
+            SchemePattern::Web => matches!(url.scheme(), "http" | "https"),
+            SchemePattern::All => matches!(url.scheme(), "http" | "https" | "file"),
+            SchemePattern::Exact(scheme) => url.scheme() == scheme,
+        };
+        if !scheme_matches {
+            return false;
+        }
+
+        let host = url.host_str().unwrap_or("").to_ascii_lowercase();
+        let host_matches = match &self.host {
+            HostPattern::Any => true,
+            HostPattern::Domain(domain) => {
+                host == *domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|sub| sub.ends_with('.'))
+            }
+            HostPattern::Exact(exact) => host == *exact,
+        };
+        if !host_matches {
+            return false;
+        }
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        glob_matches(&self.path, &path)
+    }
+}
+
+/// `*` だけを特別に扱う glob で text 全体が pattern に当てはまるか
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // `*` がなければ全体が一致していなければならない
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// URL パターンに合うページに差し込む CSS とスクリプト
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentScript {
+    pub matches: Vec<MatchPattern>,
+    pub css: Option<String>,
+    pub js: Option<String>,
+}
+
+impl ContentScript {
+    pub fn matches(&self, url: &Url) -> bool {
+        self.matches.iter().any(|p| p.matches(url))
+    }
+}
+
+/// リクエストで読むものの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceType {
+    /// タブで開く文書
+    Document,
+    /// `<iframe>` の文書
+    Subdocument,
+    Stylesheet,
+    Script,
+    /// スクリプトの fetch() と XMLHttpRequest
+    XmlHttpRequest,
+    Image,
+    Font,
+}
+
+impl ResourceType {
+    pub fn of(kind: &FetchKind) -> Self {
+        match kind {
+            FetchKind::Html => Self::Document,
+            FetchKind::Css => Self::Stylesheet,
+            FetchKind::Script => Self::Script,
+            FetchKind::ScriptRequest { .. } => Self::XmlHttpRequest,
+            FetchKind::Image => Self::Image,
+            FetchKind::Preload(destination) => match destination {
+                PreloadDestination::Style => Self::Stylesheet,
+                PreloadDestination::Script => Self::Script,
+                PreloadDestination::Image => Self::Image,
+                PreloadDestination::Font => Self::Font,
+            },
+            FetchKind::Frame { kind, .. } => match Self::of(kind) {
+                Self::Document => Self::Subdocument,
+                resource => resource,
+            },
+        }
+    }
+}
+
+/// 拡張機能に見せるリクエスト
+#[derive(Debug, Clone, Copy)]
+pub struct InterceptedRequest<'a> {
+    pub url: &'a Url,
+    /// リクエストを出した文書（タブで開く文書なら None）
+    pub document: Option<&'a Url>,
+    pub resource: ResourceType,
+}
+
+/// リクエストをどうするか
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestAction {
+    Continue,
+    /// 送らずに失敗させる
+    Block,
+    /// 代わりにこの URL に送る
+    Redirect(Url),
+}
+
+/// ツールバーのボタン
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolbarAction {
+    /// ボタンに描く短い文字列
+    pub label: String,
+    pub title: String,
+    /// 押されたら表示中のページで実行するスクリプト
+    pub script: Option<String>,
+}
+
+/// 拡張機能
+pub trait Extension: Send + Sync {
+    /// 名前（ログとエラーの表示用）
+    fn name(&self) -> &str;
+
+    /// ページに差し込む CSS とスクリプト
+    fn content_scripts(&self) -> &[ContentScript] {
+        &[]
+    }
+
+    /// ページのリクエスト（文書、CSS、スクリプト、fetch）をどうするか
+    fn intercept_request(&self, _request: &InterceptedRequest) -> RequestAction {
+        RequestAction::Continue
+    }
+
+    /// ツールバーに足すボタン
+    fn toolbar_action(&self) -> Option<&ToolbarAction> {
+        None
+    }
+
+    /// 読めるようにするスキームと、その URL を読むもの
+    fn scheme_handlers(&self) -> Vec<(String, Arc<dyn SchemeHandler>)> {
+        Vec::new()
+    }
+}
+
+/// 状態を持つ拡張機能を、ブラウザからも触れるように Arc で共有したまま登録する
+impl<T: Extension + ?Sized> Extension for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn content_scripts(&self) -> &[ContentScript] {
+        (**self).content_scripts()
+    }
+
+    fn intercept_request(&self, request: &InterceptedRequest) -> RequestAction {
+        (**self).intercept_request(request)
+    }
+
+    fn toolbar_action(&self) -> Option<&ToolbarAction> {
+        (**self).toolbar_action()
+    }
+
+    fn scheme_handlers(&self) -> Vec<(String, Arc<dyn SchemeHandler>)> {
+        (**self).scheme_handlers()
+    }
+}
+
+/// ディレクトリの `manifest.toml` から読んだ拡張機能
+#[derive(Debug, Clone, Default)]
+pub struct ManifestExtension {
+    name: String,
+    content_scripts: Vec<ContentScript>,
+    blocked: Vec<MatchPattern>,
+    action: Option<ToolbarAction>,
+}
+
+/// manifest.toml で今読んでいる表
+enum Section {
+    Top,
+    Content,
+    Block,
+    Action,
+}
+
+impl ManifestExtension {
+    /// dir の manifest.toml と、そこに書かれたファイルを読む
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE_NAME);
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&text, |file| {
+            let path = dir.join(file);
+            // ディレクトリの外のファイルは読まない
+            if Path::new(file).is_absolute() || file.split(['/', '\\']).any(|c| c == "..") {
+                bail!("{} is outside the extension directory", file);
+            }
+            std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))
+        })
+    }
+
+    /// manifest.toml の中身 text を読む。ファイルの中身は read_file で読む
+    pub fn parse(text: &str, read_file: impl Fn(&str) -> Result<String>) -> Result<Self> {
+        let mut extension = Self::default();
+        let mut section = Section::Top;
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                section = match line.split_once(']') {
+                    Some(("[[content", "]")) => {
+                        extension.content_scripts.push(ContentScript {
+                            matches: Vec::new(),
+                            css: None,
+                            js: None,
+                        });
+                        Section::Content
+                    }
+                    Some(("[[block", "]")) => Section::Block,
+                    Some(("[action", after)) if is_blank_or_comment(after) => {
+                        extension.action = Some(ToolbarAction {
+                            label: String::new(),
+                            title: String::new(),
+                            script: None,
+                        });
+                        Section::Action
+                    }
+                    _ => bail!("manifest.toml:{}: unknown table {}", i + 1, line),
+                };
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .and_then(|(key, value)| Some((key.trim(), parse_value(value)?)))
+                .ok_or_else(|| anyhow!("manifest.toml:{}: expected `key = value`", i + 1))?;
+            let patterns = || -> Result<Vec<MatchPattern>> {
+                value.split_whitespace().map(MatchPattern::parse).collect()
+            };
+
+            match (&section, key) {
+                (Section::Top, "name") => extension.name = value,
+                (Section::Top, "version" | "description") => {}
+                (Section::Content, "matches") => {
+                    if let Some(content) = extension.content_scripts.last_mut() {
+                        content.matches = patterns()?;
+                    }
+                }
+                (Section::Content, "css" | "js") => {
+                    let source = read_file(&value)?;
+                    if let Some(content) = extension.content_scripts.last_mut() {
+                        if key == "css" {
+                            content.css = Some(source);
+                        } else {
+                            content.js = Some(source);
+                        }
+                    }
+                }
+                (Section::Block, "matches") => extension.blocked.extend(patterns()?),
+                (Section::Action, "label" | "title" | "js") => {
+                    let Some(action) = extension.action.as_mut() else {
+                        continue;
+                    };
+                    match key {
+                        "label" => action.label = value,
+                        "title" => action.title = value,
+                        _ => action.script = Some(read_file(&value)?),
+                    }
+                }
+                _ => log::warn!("manifest.toml:{}: unknown key {}", i + 1, key),
+            }
+        }
+
+        if extension.name.is_empty() {
+            bail!("manifest.toml has no name");
+        }
+        if let Some(action) = extension.action.as_mut()
+            && action.label.is_empty()
+        {
+            action.label = extension.name.chars().take(1).collect();
+        }
+        Ok(extension)
+    }
+}
+
+impl Extension for ManifestExtension {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn content_scripts(&self) -> &[ContentScript] {
+        &self.content_scripts
+    }
+
+    fn intercept_request(&self, request: &InterceptedRequest) -> RequestAction {
+        if self.blocked.iter().any(|p| p.matches(request.url)) {
+            RequestAction::Block
+        } else {
+            RequestAction::Continue
+        }
+    }
+
+    fn toolbar_action(&self) -> Option<&ToolbarAction> {
+        self.action.as_ref()
+    }
+}
+
+/// ページに差し込むもの
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InjectedContent {
+    pub css: Vec<String>,
+    /// 拡張機能の名前とスクリプト
+    pub scripts: Vec<(String, String)>,
+}
+
+/// 登録した拡張機能（登録した順に当てはめる）
+#[derive(Clone, Default)]
+pub struct ExtensionRegistry {
+    extensions: Vec<Arc<dyn Extension>>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, extension: Box<dyn Extension>) {
+        log::info!("Registered extension {}", extension.name());
+        self.extensions.push(Arc::from(extension));
+    }
+
+    /// dir の下のディレクトリから拡張機能を読み込み、読めた数を返す
+    ///
+    /// 読めない拡張機能は警告を出して飛ばす。dir がなければ何もしない。
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize> {
+        if !dir.exists() {
+            return Ok(0);
+        }
+        let mut dirs: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.join(MANIFEST_FILE_NAME).is_file())
+            .collect();
+        dirs.sort();
+
+        let mut loaded = 0;
+        for path in dirs {
+            match ManifestExtension::load(&path) {
+                Ok(extension) => {
+                    self.register(Box::new(extension));
+                    loaded += 1;
+                }
+                Err(e) => log::warn!("Failed to load extension {}: {:#}", path.display(), e),
+            }
+        }
+        Ok(loaded)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.extensions.iter().map(|e| e.name()).collect()
+    }
+
+    /// url の文書に差し込む CSS とスクリプト
+    pub fn content_for(&self, url: &Url) -> InjectedContent {
+        let mut content = InjectedContent::default();
+        for extension in &self.extensions {
+            for script in extension.content_scripts() {
+                if !script.matches(url) {
+                    continue;
+                }
+                content.css.extend(script.css.clone());
+                if let Some(js) = &script.js {
+                    content
+                        .scripts
+                        .push((extension.name().to_string(), js.clone()));
+                }
+            }
+        }
+        content
+    }
+
+    /// request をどうするか（最初に Continue 以外を返した拡張機能に従う）
+    pub fn intercept_request(&self, request: &InterceptedRequest) -> RequestAction {
+        self.extensions
+            .iter()
+            .map(|e| e.intercept_request(request))
+            .find(|action| *action != RequestAction::Continue)
+            .unwrap_or(RequestAction::Continue)
+    }
+
+    /// ツールバーのボタン（拡張機能を登録した順）
+    pub fn toolbar_actions(&self) -> Vec<&ToolbarAction> {
+        self.extensions
+            .iter()
+            .filter_map(|e| e.toolbar_action())
+            .collect()
+    }
+
+    /// 拡張機能が読めるようにするスキーム（拡張機能を登録した順）
+    pub fn scheme_handlers(&self) -> Vec<(String, Arc<dyn SchemeHandler>)> {
+        self.extensions
+            .iter()
+            .flat_map(|e| e.scheme_handlers())
+            .collect()
+    }
+
+    /// scheme を拡張機能が読むか
+    pub fn handles_scheme(&self, scheme: &str) -> bool {
+        self.extensions.iter().any(|e| {
+            e.scheme_handlers()
+                .iter()
+                .any(|(s, _)| s.eq_ignore_ascii_case(scheme))
+        })
+    }
+}
@@ -0,0 +1,231 @@
+//! 同一オリジンポリシー
+//!
+//! BrowserApp が fetch を送る前と、スクリプトの要求の応答を渡す前に確かめる。
+//!
+//! - ページの移動（[`RequestMode::Navigate`]）と CSS、外部スクリプト、画像
+//!   （[`RequestMode::NoCors`]）はどのオリジンにも送れる。ただし file: を読めるのは
+//!   file: の文書だけ
+//! - スクリプトの fetch() や XMLHttpRequest（[`RequestMode::Cors`]）は、別のオリジンには
+//!   http(s) にだけ `Origin` ヘッダーを付けて送り、応答の
+//!   `Access-Control-Allow-Origin` が文書のオリジンを許していなければ読ませない。
+//!   Cookie を付けた要求では `*` を認めず、文書のオリジンそのものと
+//!   `Access-Control-Allow-Credentials: true` が要る
+//! - `Referer` は strict-origin-when-cross-origin で送る（[`referrer_header`]）
+//! - `allow-same-origin` のない `<iframe sandbox>` の文書は opaque なオリジンとして
+//!   扱う（[`Initiator`]）
+//!
+//! TODO:
+//! - credentials mode を持たないので、別のオリジンへのスクリプトの要求には常に Cookie が
+//!   付き、`Access-Control-Allow-Origin: *` の応答は読めない
+//! - プリフライト（OPTIONS）は送らないので、GET 以外は別のオリジンに送らない
+
+use std::fmt;
+
+use url::Url;
+
+use super::origin::Origin;
+use super::webview::FetchKind;
+
+/// 要求の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestMode {
+    /// ページそのものの読み込み
+    Navigate,
+    /// 応答をスクリプトに見せないサブリソース（CSS、外部スクリプト）
+    NoCors,
+    /// 応答をスクリプトが読む要求
+    Cors,
+}
+
+impl RequestMode {
+    pub fn for_fetch(kind: &FetchKind) -> Self {
+        match kind {
+            FetchKind::Html => Self::Navigate,
+            FetchKind::Css | FetchKind::Script | FetchKind::Preload(_) | FetchKind::Image => {
+                Self::NoCors
+            }
+            FetchKind::ScriptRequest { .. } => Self::Cors,
+            FetchKind::Frame { kind, .. } => Self::for_fetch(kind),
+        }
+    }
+}
+
+/// 要求を送る文書
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Initiator {
+    pub url: Url,
+    /// 同一オリジンポリシーで使うオリジン（`allow-same-origin` のない
+    /// `<iframe sandbox>` の文書なら opaque なもの）
+    pub origin: Origin,
+}
+
+impl Initiator {
+    /// url の文書（オリジンは url のもの）
+    pub fn new(url: Url) -> Self {
+        Self {
+            origin: Origin::of(&url),
+            url,
+        }
+    }
+
+    fn is_same_origin(&self, url: &Url) -> bool {
+        self.origin.is_same_origin(&Origin::of(url))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyError {
+    /// ローカルのファイルをウェブのページから読もうとした
+    LocalResource(Url),
+    /// 別のオリジンのスクリプトの要求で、送れない URL かメソッド
+    CrossOrigin { url: Url, origin: String },
+    /// CORS で許されなかった応答（理由）
+    CorsRejected { url: Url, reason: String },
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LocalResource(url) => write!(f, "Not allowed to load local resource: {url}"),
+            Self::CrossOrigin { url, origin } => {
+                write!(f, "Cross-origin request to {url} from {origin} was blocked")
+            }
+            Self::CorsRejected { url, reason } => {
+                write!(f, "Cross-origin response from {url} was blocked: {reason}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolicyError {}
+
+/// document（ブラウザの UI からなら None）の要求として method で url に送ってよいか
+pub fn check_request(
+    document: Option<&Initiator>,
+    url: &Url,
+    method: &str,
+    mode: RequestMode,
+) -> Result<(), PolicyError> {
+    let Some(document) = document else {
+        return Ok(());
+    };
+
+    if url.scheme() == "file" && document.url.scheme() != "file" {
+        return Err(PolicyError::LocalResource(url.clone()));
+    }
+    if mode != RequestMode::Cors || document.is_same_origin(url) {
+        return Ok(());
+    }
+
+    // data: の応答は誰でも読める
+    let allowed = match url.scheme() {
+        "http" | "https" => method == "GET",
+        "data" => true,
+        _ => false,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(PolicyError::CrossOrigin {
+            url: url.clone(),
+            origin: document.origin.ascii_serialization(),
+        })
+    }
+}
+
+/// CORS の要求に付けるヘッダー（同じオリジンへの要求には付けない）
+pub fn request_headers(document: &Initiator, url: &Url) -> Vec<(&'static str, String)> {
+    if document.is_same_origin(url) || !matches!(url.scheme(), "http" | "https") {
+        return Vec::new();
+    }
+    vec![("Origin", document.origin.ascii_serialization())]
+}
+
+/// referrer の文書から url への要求に付ける `Referer` の値
+///
+/// 同じオリジンへは URL（フラグメントとユーザー情報を除く）、別のオリジンへは
+/// オリジンだけを送る。https から http へは送らない。
+pub fn referrer_header(referrer: &Url, url: &Url) -> Option<String> {
+    if !matches!(referrer.scheme(), "http" | "https") || !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    if referrer.scheme() == "https" && url.scheme() == "http" {
+        return None;
+    }
+    if !is_same_origin(referrer, url) {
+        return Some(format!("{}/", Origin::of(referrer).ascii_serialization()));
+    }
+
+    let mut stripped = referrer.clone();
+    stripped.set_fragment(None);
+    let _ = stripped.set_username("");
+    let _ = stripped.set_password(None);
+    Some(stripped.to_string())
+}
+
+/// document のスクリプトに response_url からの応答（headers）を見せてよいか
+///
+/// credentials は要求に Cookie を付けたかどうか。リダイレクトした応答は最後の URL で
+/// 判断する。
+pub fn check_response(
+    document: &Initiator,
+    response_url: &Url,
+    headers: &[(String, String)],
+    credentials: bool,
+) -> Result<(), PolicyError> {
+    if document.is_same_origin(response_url) || response_url.scheme() == "data" {
+        return Ok(());
+    }
+
+    let reject = |reason: String| PolicyError::CorsRejected {
+        url: response_url.clone(),
+        reason,
+    };
+    let mut allow_origin = headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("access-control-allow-origin"))
+        .map(|(_, value)| value.trim());
+    let value = match (allow_origin.next(), allow_origin.next()) {
+        (Some(value), None) => value,
+        (None, _) => {
+            return Err(reject(
+                "no Access-Control-Allow-Origin header is present".to_string(),
+            ));
+        }
+        (Some(_), Some(_)) => {
+            return Err(reject(
+                "multiple Access-Control-Allow-Origin headers".to_string(),
+            ));
+        }
+    };
+
+    let origin = document.origin.ascii_serialization();
+    if !credentials && value == "*" {
+        return Ok(());
+    }
+    if value != origin {
+        return Err(reject(format!(
+            "Access-Control-Allow-Origin {value:?} does not match {origin}"
+        )));
+    }
+    if !credentials {
+        return Ok(());
+    }
+    let allow_credentials = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("access-control-allow-credentials"))
+        .map(|(_, value)| value.trim());
+    if allow_credentials == Some("true") {
+        Ok(())
+    } else {
+        Err(reject(
+            "Access-Control-Allow-Credentials is not \"true\" for a request with cookies"
+                .to_string(),
+        ))
+    }
+}
+
+/// a と b が同じオリジンか（どちらかが opaque なら別のもの）
+pub fn is_same_origin(a: &Url, b: &Url) -> bool {
+    Origin::of(a).is_same_origin(&Origin::of(b))
+}
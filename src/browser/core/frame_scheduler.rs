@@ -0,0 +1,123 @@
+//! フレームのスケジューラ
+//!
+//! スクロール、アニメーション、スタイルの変更などで画面が古くなったこと（invalidation）を
+//! ここに集め、ディスプレイのリフレッシュごとに多くても 1 回だけレイアウトと描画を頼む。
+//! requestAnimationFrame と同じく、続けて何度 invalidate してもフレームは 1 つにまとまる。
+
+use std::time::{Duration, Instant};
+
+/// リフレッシュレートがわからないときの値（60Hz）
+pub const DEFAULT_REFRESH_RATE_MILLIHERTZ: u32 = 60_000;
+
+/// 画面が古くなった理由
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Invalidation {
+    /// ページやスクロールバーの位置が変わった
+    Scroll,
+    /// スムーズスクロールやフェードが続いている
+    Animation,
+    /// スタイル（テーマ、ズーム、ホバーなど）が変わった
+    Style,
+    /// ウィンドウの大きさが変わった
+    Resize,
+    /// ページの中身やブラウザの UI が変わった
+    Content,
+}
+
+impl Invalidation {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// 次のフレームまでに溜まった invalidation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Invalidations(u8);
+
+impl Invalidations {
+    pub fn contains(self, reason: Invalidation) -> bool {
+        self.0 & reason.bit() != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    fn insert(&mut self, reason: Invalidation) {
+        self.0 |= reason.bit();
+    }
+}
+
+pub struct FrameScheduler {
+    /// ディスプレイのリフレッシュの間隔
+    interval: Duration,
+    pending: Invalidations,
+    /// フレームを頼んで、まだ始まっていない
+    requested: bool,
+    last_frame: Option<Instant>,
+}
+
+impl Default for FrameScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameScheduler {
+    pub fn new() -> Self {
+        let mut scheduler = Self {
+            interval: Duration::ZERO,
+            pending: Invalidations::default(),
+            requested: false,
+            last_frame: None,
+        };
+        scheduler.set_refresh_rate(DEFAULT_REFRESH_RATE_MILLIHERTZ);
+        scheduler
+    }
+
+    /// ディスプレイのリフレッシュレート（winit と同じミリヘルツ）を設定する。0 は無視する
+    pub fn set_refresh_rate(&mut self, millihertz: u32) {
+        if millihertz > 0 {
+            self.interval = Duration::from_secs_f64(1000.0 / millihertz as f64);
+        }
+    }
+
+    pub fn frame_interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// 画面が古くなったことを記録する（フレームを頼むのは poll のとき）
+    pub fn invalidate(&mut self, reason: Invalidation) {
+        self.pending.insert(reason);
+    }
+
+    pub fn pending(&self) -> Invalidations {
+        self.pending
+    }
+
+    /// 今フレームを頼むべきなら true を返す
+    ///
+    /// 前のフレームから 1 リフレッシュ経っていなければ待つ。頼んだフレームが始まるまでは
+    /// 何度呼んでも false を返す。
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if self.pending.is_empty() || self.requested {
+            return false;
+        }
+        if let Some(last) = self.last_frame
+            && now < last + self.interval
+        {
+            return false;
+        }
+        self.requested = true;
+        true
+    }
+
+    /// フレームを始める。溜まっていた invalidation を返して空にする
+    ///
+    /// 頼んでいないフレーム（ウィンドウが隠れていたのが見えたときなど）でも呼ぶ。
+    pub fn begin_frame(&mut self, now: Instant) -> Invalidations {
+        self.requested = false;
+        self.last_frame = Some(now);
+        std::mem::take(&mut self.pending)
+    }
+}
@@ -0,0 +1,97 @@
+//! タブごとのセッション履歴（戻る / 進む）
+
+use url::Url;
+
+use super::webview::form::FormState;
+
+/// 履歴の 1 項目
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub url: Url,
+    pub title: Option<String>,
+    /// ページを離れたときのスクロール位置 (x, y)
+    pub scroll: (f32, f32),
+    /// ページを離れたときの入力欄の値
+    pub form: FormState,
+}
+
+impl HistoryEntry {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            title: None,
+            scroll: (0.0, 0.0),
+            form: FormState::default(),
+        }
+    }
+}
+
+/// 訪れたページのスタック
+///
+/// `current` より後ろの項目が「進む」で戻れるページになる。新しいページに
+/// 移動するとそれらは捨てられる。
+#[derive(Debug, Default)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    current: Option<usize>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 新しいページに移動したときに呼ぶ
+    pub fn push(&mut self, url: Url) {
+        let next = self.current.map_or(0, |i| i + 1);
+        self.entries.truncate(next);
+        self.entries.push(HistoryEntry::new(url));
+        self.current = Some(next);
+    }
+
+    pub fn current(&self) -> Option<&HistoryEntry> {
+        self.entries.get(self.current?)
+    }
+
+    pub fn current_mut(&mut self) -> Option<&mut HistoryEntry> {
+        self.entries.get_mut(self.current?)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.current.is_some_and(|i| i > 0)
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.current.is_some_and(|i| i + 1 < self.entries.len())
+    }
+
+    /// 1 つ前の項目に移り、それを返す
+    pub fn back(&mut self) -> Option<&HistoryEntry> {
+        if !self.can_go_back() {
+            return None;
+        }
+        self.current = self.current.map(|i| i - 1);
+        self.current()
+    }
+
+    /// 1 つ後の項目に移り、それを返す
+    pub fn forward(&mut self) -> Option<&HistoryEntry> {
+        if !self.can_go_forward() {
+            return None;
+        }
+        self.current = self.current.map(|i| i + 1);
+        self.current()
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
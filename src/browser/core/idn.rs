@@ -0,0 +1,232 @@
+//! 国際化ドメイン名（IDN）の表示
+//!
+//! URL は url クレートが IDNA で punycode（`xn--`）にするので、ネットワークには
+//! ASCII のホスト名しか流れない。URL バーに出すときだけ Unicode に戻す。
+//! ただし、ラテン文字とキリル文字を混ぜたもののように、別のサイトに見間違え
+//! られるホスト名は punycode のまま出す。
+
+use url::{Position, Url};
+
+/// URL バーに出す URL の文字列
+///
+/// - 見間違えのおそれのないホスト名は Unicode で出す
+/// - パスとクエリの UTF-8 のパーセントエンコードを戻す（ASCII の `%20` などは戻さない）
+pub fn display_url(url: &Url) -> String {
+    let Some(host) = url.host_str() else {
+        return url.to_string();
+    };
+    format!(
+        "{}{}{}",
+        &url[..Position::BeforeHost],
+        display_host(host),
+        decode_non_ascii(&url[Position::AfterHost..])
+    )
+}
+
+/// ホスト名を表示用にする。ラベルが 1 つでも危なければ全体を punycode のまま返す
+pub fn display_host(host: &str) -> String {
+    let mut labels = Vec::new();
+    for label in host.split('.') {
+        let Some(encoded) = strip_ace_prefix(label) else {
+            labels.push(label.to_string());
+            continue;
+        };
+        match decode_punycode(encoded) {
+            Some(decoded) if is_safe_label(&decoded) => labels.push(decoded),
+            _ => return host.to_string(),
+        }
+    }
+    labels.join(".")
+}
+
+/// `xn--` で始まるラベルの残り
+fn strip_ace_prefix(label: &str) -> Option<&str> {
+    let prefix = label.get(..4)?;
+    prefix.eq_ignore_ascii_case("xn--").then(|| &label[4..])
+}
+
+/// punycode（RFC 3492）を Unicode に戻す。`xn--` は除いて渡す
+pub fn decode_punycode(input: &str) -> Option<String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+
+    let mut n: u32 = 0x80;
+    let mut i: u32 = 0;
+    let mut bias: u32 = 72;
+    let mut digits = extended.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next()? {
+                b @ b'a'..=b'z' => (b - b'a') as u32,
+                b @ b'A'..=b'Z' => (b - b'A') as u32,
+                b @ b'0'..=b'9' => (b - b'0') as u32 + 26,
+                _ => return None,
+            };
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = k.saturating_sub(bias).clamp(T_MIN, T_MAX);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let len = output.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len)?;
+        i %= len;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output.into_iter().collect())
+}
+
+fn adapt(delta: u32, num_points: u32, first_time: bool) -> u32 {
+    let mut delta = if first_time { delta / 700 } else { delta / 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((36 - 1) * 26) / 2 {
+        delta /= 36 - 1;
+        k += 36;
+    }
+    k + (36 * delta) / (delta + 38)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Thai,
+    Hangul,
+    Hiragana,
+    Katakana,
+    Bopomofo,
+    Han,
+}
+
+/// 文字の用字。数字やハイフンなどどの用字とも混ぜてよいものは Some(None)、
+/// ここで扱わない文字は None
+fn script(c: char) -> Option<Option<Script>> {
+    use Script::*;
+    let script = match c {
+        '0'..='9' | '-' | '\u{30fb}' | '\u{30fc}' => return Some(None),
+        'a'..='z' | 'A'..='Z' => Latin,
+        '\u{00c0}'..='\u{024f}' | '\u{1e00}'..='\u{1eff}' if c.is_alphabetic() => Latin,
+        '\u{0370}'..='\u{03ff}' => Greek,
+        '\u{0400}'..='\u{052f}' => Cyrillic,
+        '\u{0531}'..='\u{058f}' => Armenian,
+        '\u{0591}'..='\u{05ff}' => Hebrew,
+        '\u{0600}'..='\u{06ff}' => Arabic,
+        '\u{0900}'..='\u{097f}' => Devanagari,
+        '\u{0e00}'..='\u{0e7f}' => Thai,
+        '\u{1100}'..='\u{11ff}' | '\u{3130}'..='\u{318f}' | '\u{ac00}'..='\u{d7af}' => Hangul,
+        '\u{3040}'..='\u{309f}' => Hiragana,
+        '\u{30a0}'..='\u{30ff}' | '\u{31f0}'..='\u{31ff}' => Katakana,
+        '\u{3100}'..='\u{312f}' | '\u{31a0}'..='\u{31bf}' => Bopomofo,
+        '\u{3005}' | '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' => Han,
+        _ => return None,
+    };
+    Some(Some(script))
+}
+
+/// 形がラテン文字と同じキリル文字（`аррӏе` で apple に見える）
+const CYRILLIC_LOOKALIKES: &str = "аеорсухіјѕԁһӏԛԝѡүъьпгѵ";
+
+/// 用字を混ぜてよい組み合わせ（日本語、中国語、韓国語の表記）
+const ALLOWED_MIXES: &[&[Script]] = &[
+    &[
+        Script::Latin,
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+    ],
+    &[Script::Latin, Script::Han, Script::Bopomofo],
+    &[Script::Latin, Script::Han, Script::Hangul],
+];
+
+/// ラベルを Unicode で出してよいか
+fn is_safe_label(label: &str) -> bool {
+    let mut scripts: Vec<Script> = Vec::new();
+    for c in label.chars() {
+        match script(c) {
+            Some(Some(s)) if !scripts.contains(&s) => scripts.push(s),
+            Some(_) => {}
+            None => return false,
+        }
+    }
+
+    match scripts.as_slice() {
+        [] => true,
+        // キリル文字だけでラテン文字に見えるもの
+        [Script::Cyrillic] => !label
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .all(|c| CYRILLIC_LOOKALIKES.contains(c)),
+        [_] => true,
+        _ => ALLOWED_MIXES
+            .iter()
+            .any(|mix| scripts.iter().all(|s| mix.contains(s))),
+    }
+}
+
+/// UTF-8 の非 ASCII 文字を表すパーセントエンコードだけを戻す
+///
+/// 戻すと URL の意味が変わる ASCII（`%2F` など）や、空白・制御文字・双方向の
+/// 制御文字は戻さない。
+fn decode_non_ascii(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // 0x80 以上の %XX の並び
+        let mut decoded = Vec::new();
+        let mut j = i;
+        while let Some(byte) = percent_byte(bytes, j).filter(|b| *b >= 0x80) {
+            decoded.push(byte);
+            j += 3;
+        }
+        if decoded.is_empty() {
+            let c = s[i..].chars().next().unwrap_or_default();
+            out.push(c);
+            i += c.len_utf8().max(1);
+            continue;
+        }
+        match String::from_utf8(decoded) {
+            Ok(text) if text.chars().all(is_displayable) => out.push_str(&text),
+            _ => out.push_str(&s[i..j]),
+        }
+        i = j;
+    }
+    out
+}
+
+fn percent_byte(bytes: &[u8], i: usize) -> Option<u8> {
+    if bytes.get(i) != Some(&b'%') {
+        return None;
+    }
+    let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+    u8::from_str_radix(hex, 16).ok()
+}
+
+fn is_displayable(c: char) -> bool {
+    !c.is_whitespace()
+        && !c.is_control()
+        && !matches!(c, '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2066}'..='\u{2069}')
+}
@@ -0,0 +1,1055 @@
+//! ブラウザ内部ページ
+//!
+//! `about:` / `orinium://` のページと読み込み失敗時のエラーページ。
+//! ネットワークから読まずに、ブラウザの状態から HTML を生成する。
+//!
+//! - `about:blank`: 空のページ
+//! - `orinium://history`: 閲覧履歴（`?q=` で絞り込み）
+//! - `orinium://version`: バージョンとビルド情報
+//! - `orinium://flags`: 設定と環境変数で切り替えられる機能
+//! - `orinium://settings`: 設定の表示と変更（`?font.size=18` のようなクエリで変える）
+//! - `orinium://inspect`: 最後に調べた要素の CSS（Ctrl+Shift+C で要素を選ぶ）
+//! - `orinium://console`: タブのコンソール（Ctrl+Shift+J で開く。`?level=warning` で絞り込み）
+//! - `orinium://logs`: ブラウザのログ（`?level=debug&subsystem=platform::network` で絞り込み、
+//!   `?log.levels=...` でサブシステムごとのレベルを変える）
+//!
+//! `about:history` のように `about:` の後に名前を書いても同じページを開ける。
+
+use anyhow::{Result, anyhow};
+use url::Url;
+
+use super::browsing_history::BrowsingHistory;
+use super::devtools::{Console, StyleInspection};
+use super::reader::ReaderTheme;
+use super::resource_loader::BrowserNetworkError;
+use crate::browser::settings::{ColorSchemePreference, CookiePolicy, SETTINGS_FILE_NAME, Settings};
+use crate::engine::diagnostics::Level;
+use crate::engine::layouter::css_resolver::StyleOrigin;
+use crate::network::NetworkError;
+use crate::platform::io;
+use crate::platform::logging::{self, LogRecord};
+
+pub const INTERNAL_SCHEME: &str = "orinium";
+pub const ABOUT_SCHEME: &str = "about";
+
+/// 内部ページを作るのに使うブラウザの状態
+pub struct InternalPageContext<'a> {
+    pub history: &'a BrowsingHistory,
+    pub settings: &'a Settings,
+    /// 最後に調べた要素のスタイル
+    pub inspection: Option<&'a StyleInspection>,
+    /// Ctrl+Shift+J で開いたタブのコンソール
+    pub console: Option<&'a Console>,
+}
+
+/// url が内部ページ（ネットワークに出さない URL）か
+pub fn is_internal(url: &Url) -> bool {
+    matches!(url.scheme(), INTERNAL_SCHEME | ABOUT_SCHEME)
+}
+
+/// 内部ページの HTML を生成する
+pub fn load(url: &Url, ctx: &InternalPageContext) -> Result<String> {
+    let name = match url.scheme() {
+        INTERNAL_SCHEME => url.host_str().unwrap_or(""),
+        ABOUT_SCHEME => url.path(),
+        _ => return Err(anyhow!("Not an internal page: {}", url)),
+    };
+
+    match name {
+        "blank" => {
+            Ok("<!DOCTYPE html><html><head><title></title></head><body></body></html>".to_string())
+        }
+        "history" => {
+            let query = url
+                .query_pairs()
+                .find(|(key, _)| key == "q")
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default();
+            Ok(history_page(ctx.history, &query))
+        }
+        "version" => Ok(version_page()),
+        "flags" => Ok(flags_page(ctx.settings)),
+        "settings" => Ok(settings_page(ctx.settings)),
+        "inspect" => Ok(inspect_page(ctx.inspection)),
+        "console" => {
+            let level = url
+                .query_pairs()
+                .find(|(key, _)| key == "level")
+                .and_then(|(_, value)| Level::parse(&value))
+                .unwrap_or(Level::Info);
+            Ok(console_page(ctx.console, level))
+        }
+        "logs" => {
+            let query = |name: &str| {
+                url.query_pairs()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value.into_owned())
+            };
+            let level = query("level")
+                .and_then(|level| level.parse().ok())
+                .unwrap_or(log::LevelFilter::Trace);
+            let subsystem = query("subsystem").unwrap_or_default();
+            Ok(logs_page(
+                &logging::logger().records(),
+                ctx.settings,
+                level,
+                &subsystem,
+            ))
+        }
+        _ => Err(anyhow!("Unknown internal page: {}", url)),
+    }
+}
+
+/// url が `orinium://settings?key=value` なら、クエリの設定を settings に書き込む
+///
+/// `orinium://logs` ではログの設定（`log.` で始まるもの）だけを書き込む。
+/// 設定を変えたら true。読めない値は警告を出して飛ばす。
+pub fn update_settings(url: &Url, settings: &mut Settings) -> bool {
+    let logs = is_page(url, "logs");
+    if !is_settings_page(url) && !logs {
+        return false;
+    }
+
+    let before = settings.clone();
+    for (key, value) in url.query_pairs() {
+        // ログのページの絞り込みは設定ではない
+        if logs && !key.starts_with("log.") {
+            continue;
+        }
+        if let Err(e) = settings.set(&key, &value) {
+            log::warn!("Ignoring setting from {}: {:#}", url, e);
+        }
+    }
+    *settings != before
+}
+
+fn is_settings_page(url: &Url) -> bool {
+    is_page(url, "settings")
+}
+
+/// url が内部ページ name か
+fn is_page(url: &Url, name: &str) -> bool {
+    match url.scheme() {
+        INTERNAL_SCHEME => url.host_str() == Some(name),
+        ABOUT_SCHEME => url.path() == name,
+        _ => false,
+    }
+}
+
+/// 内部ページ共通の枠
+fn page(title: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <meta name="color-scheme" content="light dark">
+    <title>{title}</title>
+    <style>
+        body {{ font-family: sans-serif; margin: 24px 40px; }}
+        .summary {{ color: #6b7280; }}
+        ul {{ list-style: none; padding: 0; }}
+        li {{ margin: 12px 0; }}
+        .meta {{ color: #6b7280; font-size: 13px; }}
+        th {{ text-align: left; padding-right: 24px; }}
+        td {{ padding: 4px 24px 4px 0; }}
+        code {{ font-family: monospace; }}
+        .choice {{ margin-right: 12px; }}
+        .overridden {{ text-decoration: line-through; color: #9aa0a6; }}
+        @media (prefers-color-scheme: dark) {{
+            a {{ color: #8ab4f8; }}
+            .summary, .meta {{ color: #9aa0a6; }}
+        }}
+    </style>
+</head>
+<body>
+    <h1>{title}</h1>
+{body}</body>
+</html>
+"#
+    )
+}
+
+/// orinium://history（?q= で絞り込み）
+fn history_page(history: &BrowsingHistory, query: &str) -> String {
+    let visits = history.search(query);
+
+    let mut items = String::new();
+    for visit in &visits {
+        let url = escape_html(visit.url.as_str());
+        let title = escape_html(visit.title.as_deref().unwrap_or(visit.url.as_str()));
+        items.push_str(&format!(
+            "<li><a href=\"{url}\">{title}</a><div class=\"meta\">{url} · {} · {} visit{}</div></li>\n",
+            format_timestamp(visit.last_visit),
+            visit.visit_count,
+            if visit.visit_count == 1 { "" } else { "s" },
+        ));
+    }
+
+    let summary = match (query.trim().is_empty(), visits.is_empty()) {
+        (true, true) => "No pages have been visited yet.".to_string(),
+        (true, false) => format!("{} pages", visits.len()),
+        (false, true) => format!("No pages match \"{}\".", escape_html(query)),
+        (false, false) => format!("{} pages match \"{}\"", visits.len(), escape_html(query)),
+    };
+
+    page(
+        "History",
+        &format!("    <p class=\"summary\">{summary}</p>\n    <ul>\n{items}    </ul>\n"),
+    )
+}
+
+/// orinium://version
+fn version_page() -> String {
+    let profile = if cfg!(debug_assertions) {
+        "debug"
+    } else {
+        "release"
+    };
+    let rows = [
+        ("Version", env!("CARGO_PKG_VERSION").to_string()),
+        (
+            "Platform",
+            format!("{} ({})", std::env::consts::OS, std::env::consts::ARCH),
+        ),
+        ("Build", profile.to_string()),
+        ("Renderer", "wgpu".to_string()),
+        ("Network", "HTTP/1.1, TLS (rustls)".to_string()),
+        ("License", env!("CARGO_PKG_LICENSE").to_string()),
+    ];
+
+    page("Orinium Browser", &table(&rows))
+}
+
+/// 環境変数で切り替えられる機能（名前, 説明）
+const ENV_FLAGS: &[(&str, &str)] = &[
+    (
+        "ORINIUM_PROFILE_DIR",
+        "Directory for the session and history",
+    ),
+    ("ORINIUM_CONFIG_DIR", "Directory for settings.toml"),
+    ("ORINIUM_FONT", "Font file used for text"),
+    (
+        "ORINIUM_WGPU_BACKEND",
+        "Graphics backend (vulkan, metal, dx12, gl)",
+    ),
+    (
+        "ORINIUM_SOFTWARE_RENDER",
+        "Draw on the CPU instead of the GPU",
+    ),
+    (
+        "ORINIUM_TEXT_CULL",
+        "Skip drawing text outside the viewport",
+    ),
+    ("ORINIUM_FORCE_X11", "Use X11 instead of Wayland"),
+    ("ORINIUM_PREFER_WAYLAND", "Prefer Wayland under WSLg"),
+];
+
+/// orinium://flags
+fn flags_page(settings: &Settings) -> String {
+    let on_off = |b: bool| if b { "on" } else { "off" }.to_string();
+    let setting_rows = [
+        (
+            "Continue where you left off",
+            on_off(settings.restore_session),
+        ),
+        (
+            "Frame statistics overlay",
+            on_off(settings.show_frame_stats),
+        ),
+        (
+            "Memory budget",
+            format!("{} MiB", settings.memory_budget_mb),
+        ),
+    ];
+
+    let env_rows: Vec<(&str, String)> = ENV_FLAGS
+        .iter()
+        .map(|(name, description)| {
+            let value = std::env::var(name).unwrap_or_else(|_| "(not set)".to_string());
+            (
+                *description,
+                format!("<code>{name}</code> = {}", escape_html(&value)),
+            )
+        })
+        .collect();
+
+    page(
+        "Flags",
+        &format!(
+            "    <h2>Settings</h2>\n{}    <h2>Environment</h2>\n{}",
+            table(&setting_rows),
+            table_raw(&env_rows),
+        ),
+    )
+}
+
+/// orinium://inspect
+///
+/// 当たった宣言を優先度の高い順に並べ、上書きされたものには打ち消し線を引く。
+fn inspect_page(inspection: Option<&StyleInspection>) -> String {
+    let Some(inspection) = inspection else {
+        return page(
+            "Inspect",
+            "    <p class=\"summary\">No element selected. Press Ctrl+Shift+C and click an element.</p>\n",
+        );
+    };
+
+    let mut rules = String::new();
+    for decl in &inspection.matched {
+        let origin = match decl.origin {
+            StyleOrigin::UserAgent => "user agent",
+            StyleOrigin::Author => "author",
+        };
+        let (a, b, c) = decl.specificity;
+        rules.push_str(&format!(
+            "        <tr{}><td><code>{}</code></td><td><code>{}: {}{}</code></td><td>{origin}</td><td>{a},{b},{c}</td></tr>\n",
+            if decl.overridden {
+                " class=\"overridden\""
+            } else {
+                ""
+            },
+            escape_html(&decl.selector),
+            escape_html(&decl.name),
+            escape_html(&decl.value.to_string()),
+            if decl.important { " !important" } else { "" },
+        ));
+    }
+
+    let computed: Vec<(&str, String)> = inspection
+        .computed
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.as_str(),
+                format!("<code>{}</code>", escape_html(&value.to_string())),
+            )
+        })
+        .collect();
+
+    let document = inspection
+        .document_url
+        .as_ref()
+        .map(|url| format!(" in {}", escape_html(url.as_str())))
+        .unwrap_or_default();
+
+    let mut body = format!(
+        "    <p class=\"summary\"><code>{}</code>{document}</p>\n",
+        escape_html(&inspection.element)
+    );
+    body.push_str("    <h2>Matched rules</h2>\n    <table>\n");
+    body.push_str(
+        "        <tr><th>Selector</th><th>Declaration</th><th>Origin</th><th>Specificity</th></tr>\n",
+    );
+    body.push_str(&rules);
+    body.push_str("    </table>\n    <h2>Computed</h2>\n");
+    body.push_str(&table_raw(&computed));
+
+    page("Inspect", &body)
+}
+
+/// orinium://console（level 以上のメッセージだけ出す）
+fn console_page(console: Option<&Console>, level: Level) -> String {
+    let filters = [
+        (Level::Info, "All"),
+        (Level::Warning, "Warnings"),
+        (Level::Error, "Errors"),
+    ]
+    .iter()
+    .map(|(option, label)| {
+        if *option == level {
+            format!("<strong class=\"choice\">{label}</strong>")
+        } else {
+            format!("<a class=\"choice\" href=\"orinium://console?level={option}\">{label}</a>")
+        }
+    })
+    .collect::<String>();
+
+    let Some(console) = console else {
+        return page(
+            "Console",
+            "    <p class=\"summary\">No console opened. Press Ctrl+Shift+J on a page.</p>\n",
+        );
+    };
+
+    let mut items = String::new();
+    for entry in console.entries(level) {
+        let message = &entry.message;
+        let location = message
+            .location
+            .as_deref()
+            .map(|location| format!(" · {}", escape_html(location)))
+            .unwrap_or_default();
+        let count = if entry.count > 1 {
+            format!(" ×{}", entry.count)
+        } else {
+            String::new()
+        };
+        items.push_str(&format!(
+            "<li><code>{}</code>{count}<div class=\"meta\">{} · {}{location}</div></li>\n",
+            escape_html(&message.text),
+            message.level,
+            message.source,
+        ));
+    }
+
+    let shown = console.entries(level).count();
+    let summary = match shown {
+        0 => "No messages.".to_string(),
+        1 => "1 message".to_string(),
+        n => format!("{n} messages"),
+    };
+
+    page(
+        "Console",
+        &format!(
+            "    <p>{filters}</p>\n    <p class=\"summary\">{summary}</p>\n    <ul>\n{items}    </ul>\n"
+        ),
+    )
+}
+
+/// orinium://logs（level 以下で、subsystem から始まるサブシステムのメッセージを新しい順に出す）
+fn logs_page(
+    records: &[LogRecord],
+    settings: &Settings,
+    level: log::LevelFilter,
+    subsystem: &str,
+) -> String {
+    let logs_url = |level: log::LevelFilter| {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("level", &level.as_str().to_ascii_lowercase());
+        if !subsystem.is_empty() {
+            query.append_pair("subsystem", subsystem);
+        }
+        format!("{INTERNAL_SCHEME}://logs?{}", query.finish())
+    };
+    let filters = [
+        (log::LevelFilter::Trace, "All"),
+        (log::LevelFilter::Debug, "Debug"),
+        (log::LevelFilter::Info, "Info"),
+        (log::LevelFilter::Warn, "Warnings"),
+        (log::LevelFilter::Error, "Errors"),
+    ]
+    .iter()
+    .map(|(option, label)| {
+        if *option == level {
+            format!("<strong class=\"choice\">{label}</strong>")
+        } else {
+            format!(
+                "<a class=\"choice\" href=\"{}\">{label}</a>",
+                escape_html(&logs_url(*option))
+            )
+        }
+    })
+    .collect::<String>();
+
+    let shown: Vec<&LogRecord> = records
+        .iter()
+        .rev()
+        .filter(|record| record.level <= level && record.subsystem.starts_with(subsystem))
+        .collect();
+    let mut items = String::new();
+    for record in &shown {
+        items.push_str(&format!(
+            "<li><code>{}</code><div class=\"meta\">{} · {} · {}</div></li>\n",
+            escape_html(&record.message),
+            logging::format_time(record.time),
+            record.level,
+            escape_html(&record.subsystem),
+        ));
+    }
+    let summary = match shown.len() {
+        0 => "No messages.".to_string(),
+        1 => "1 message".to_string(),
+        n => format!("{n} messages"),
+    };
+
+    let file = settings
+        .log_file
+        .as_deref()
+        .map(|path| path.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let form = |key: &str, value: &str| {
+        format!(
+            "<form action=\"{INTERNAL_SCHEME}://logs\" method=\"get\"><input type=\"text\" name=\"{}\" value=\"{}\" size=\"48\"> <button type=\"submit\">Save</button></form>",
+            escape_html(key),
+            escape_html(value)
+        )
+    };
+    let levels = [
+        ("Levels", form("log.levels", &settings.log_levels)),
+        ("Log file", form("log.file", &file)),
+    ];
+
+    page(
+        "Logs",
+        &format!(
+            "{}    <p>{filters}</p>\n    <p class=\"summary\">{summary}</p>\n    <ul>\n{items}    </ul>\n",
+            table_raw(&levels),
+        ),
+    )
+}
+
+/// 設定ページで選べる検索エンジン（名前, テンプレート）
+const SEARCH_ENGINES: &[(&str, &str)] = &[
+    ("DuckDuckGo", "https://duckduckgo.com/?q={query}"),
+    ("Google", "https://www.google.com/search?q={query}"),
+    ("Bing", "https://www.bing.com/search?q={query}"),
+    (
+        "Wikipedia",
+        "https://en.wikipedia.org/w/index.php?search={query}",
+    ),
+];
+
+/// orinium://settings
+///
+/// 選択肢はそれぞれ `orinium://settings?key=value` へのリンクにする。
+/// 自由に入力する項目はフォームで同じ URL に送る。
+fn settings_page(settings: &Settings) -> String {
+    let font_sizes = [12.0, 14.0, 16.0, 18.0, 20.0, 24.0];
+    let size_choices = |key: &str, current: f32| {
+        let options: Vec<(String, String)> = font_sizes
+            .iter()
+            .map(|&size| (format!("{size}px"), size.to_string()))
+            .collect();
+        number_choices(key, &options, current)
+    };
+
+    let zooms: Vec<(String, String)> = [0.75, 0.9, 1.0, 1.1, 1.25, 1.5, 2.0]
+        .iter()
+        .map(|&zoom: &f32| (format!("{:.0}%", zoom * 100.0), zoom.to_string()))
+        .collect();
+    let scroll_speeds: Vec<(String, String)> = [0.5, 1.0, 1.5, 2.0, 3.0]
+        .iter()
+        .map(|&speed: &f32| (format!("{speed}×"), speed.to_string()))
+        .collect();
+    let search_engines: Vec<(String, String)> = SEARCH_ENGINES
+        .iter()
+        .map(|(name, template)| (name.to_string(), template.to_string()))
+        .collect();
+    let cookie_policies: Vec<(String, String)> = CookiePolicy::ALL
+        .iter()
+        .map(|p| (cookie_policy_label(*p).to_string(), p.name().to_string()))
+        .collect();
+    let color_schemes: Vec<(String, String)> = ColorSchemePreference::ALL
+        .iter()
+        .map(|s| (color_scheme_label(*s).to_string(), s.name().to_string()))
+        .collect();
+    let reader_themes: Vec<(String, String)> = ReaderTheme::ALL
+        .iter()
+        .map(|t| (reader_theme_label(*t).to_string(), t.name().to_string()))
+        .collect();
+    let on_off = [
+        ("On".to_string(), "true".to_string()),
+        ("Off".to_string(), "false".to_string()),
+    ];
+
+    let general = [
+        (
+            "Home page",
+            text_field("homepage", settings.homepage.as_str()),
+        ),
+        (
+            "Search engine",
+            format!(
+                "{}<br>{}",
+                choices(
+                    "search_engine",
+                    &search_engines,
+                    &settings.search_engine.template
+                ),
+                text_field("search_engine", &settings.search_engine.template),
+            ),
+        ),
+        (
+            "Continue where you left off",
+            choices(
+                "restore_session",
+                &on_off,
+                &settings.restore_session.to_string(),
+            ),
+        ),
+    ];
+    let appearance = [
+        (
+            "Color scheme",
+            choices("color_scheme", &color_schemes, settings.color_scheme.name()),
+        ),
+        (
+            "Font",
+            text_field("font.family", settings.font_family.as_deref().unwrap_or("")),
+        ),
+        ("Font size", size_choices("font.size", settings.font_size)),
+        (
+            "Default zoom",
+            number_choices("default_zoom", &zooms, settings.default_zoom),
+        ),
+        (
+            "Scroll speed",
+            number_choices("scroll_speed", &scroll_speeds, settings.scroll_speed),
+        ),
+        (
+            "Draw the titlebar in the tab strip",
+            choices(
+                "window.custom_titlebar",
+                &on_off,
+                &settings.custom_titlebar.to_string(),
+            ),
+        ),
+    ];
+    let privacy = [
+        (
+            "Cookies",
+            choices(
+                "cookie_policy",
+                &cookie_policies,
+                settings.cookie_policy.name(),
+            ),
+        ),
+        (
+            "Automatic page refresh",
+            choices("meta_refresh", &on_off, &settings.meta_refresh.to_string()),
+        ),
+        (
+            "Autofill forms",
+            choices("autofill", &on_off, &settings.autofill.to_string()),
+        ),
+        (
+            "Offer to save passwords",
+            choices("passwords", &on_off, &settings.passwords.to_string()),
+        ),
+        (
+            "Block ads and trackers",
+            choices(
+                "content_blocking",
+                &on_off,
+                &settings.content_blocking.to_string(),
+            ),
+        ),
+    ];
+    let reader = [
+        (
+            "Font size",
+            size_choices("reader.font_size", settings.reader.font_size),
+        ),
+        (
+            "Theme",
+            choices("reader.theme", &reader_themes, settings.reader.theme.name()),
+        ),
+    ];
+    let spell_check = [
+        (
+            "Check spelling",
+            choices(
+                "spell_check.enabled",
+                &on_off,
+                &settings.spell_check.to_string(),
+            ),
+        ),
+        (
+            "Dictionary",
+            text_field("spell_check.language", &settings.spell_check_language),
+        ),
+    ];
+    let developer = [
+        (
+            "Frame statistics",
+            choices(
+                "debug.frame_stats",
+                &on_off,
+                &settings.show_frame_stats.to_string(),
+            ),
+        ),
+        (
+            "Log levels",
+            format!(
+                "{}<br><a href=\"{INTERNAL_SCHEME}://logs\">View logs</a>",
+                text_field("log.levels", &settings.log_levels)
+            ),
+        ),
+    ];
+
+    let location = match io::config_dir() {
+        Ok(dir) => format!(
+            "    <p class=\"summary\">Saved to <code>{}</code>. Changes made to the file are applied while the browser is running.</p>\n",
+            escape_html(&dir.join(SETTINGS_FILE_NAME).to_string_lossy())
+        ),
+        Err(_) => String::new(),
+    };
+
+    page(
+        "Settings",
+        &format!(
+            "{location}    <h2>General</h2>\n{}    <h2>Appearance</h2>\n{}    <h2>Privacy</h2>\n{}    <h2>Reader mode</h2>\n{}    <h2>Spell check</h2>\n{}    <h2>Developer</h2>\n{}",
+            table_raw(&general),
+            table_raw(&appearance),
+            table_raw(&privacy),
+            table_raw(&reader),
+            table_raw(&spell_check),
+            table_raw(&developer),
+        ),
+    )
+}
+
+fn cookie_policy_label(policy: CookiePolicy) -> &'static str {
+    match policy {
+        CookiePolicy::AllowAll => "Allow all",
+        CookiePolicy::BlockThirdParty => "Block third-party",
+        CookiePolicy::BlockAll => "Block all",
+    }
+}
+
+fn color_scheme_label(scheme: ColorSchemePreference) -> &'static str {
+    match scheme {
+        ColorSchemePreference::System => "Use system setting",
+        ColorSchemePreference::Light => "Light",
+        ColorSchemePreference::Dark => "Dark",
+    }
+}
+
+fn reader_theme_label(theme: ReaderTheme) -> &'static str {
+    match theme {
+        ReaderTheme::Light => "Light",
+        ReaderTheme::Sepia => "Sepia",
+        ReaderTheme::Dark => "Dark",
+    }
+}
+
+/// 設定を key=value に変える URL
+fn settings_url(key: &str, value: &str) -> String {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair(key, value)
+        .finish();
+    format!("{INTERNAL_SCHEME}://settings?{query}")
+}
+
+/// 選択肢（表示名, 値）を並べたリンク。今の値は太字にしてリンクにしない
+fn choices(key: &str, options: &[(String, String)], current: &str) -> String {
+    options
+        .iter()
+        .map(|(label, value)| {
+            if value == current {
+                format!("<strong class=\"choice\">{}</strong>", escape_html(label))
+            } else {
+                format!(
+                    "<a class=\"choice\" href=\"{}\">{}</a>",
+                    escape_html(&settings_url(key, value)),
+                    escape_html(label)
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 数値の選択肢。今の値と等しい選択肢を太字にする
+fn number_choices(key: &str, options: &[(String, String)], current: f32) -> String {
+    let current = options
+        .iter()
+        .find(|(_, value)| {
+            value
+                .parse::<f32>()
+                .is_ok_and(|v| (v - current).abs() < 0.001)
+        })
+        .map(|(_, value)| value.as_str())
+        .unwrap_or("");
+    choices(key, options, current)
+}
+
+/// 自由に入力する設定のフォーム
+fn text_field(key: &str, value: &str) -> String {
+    format!(
+        "<form action=\"{INTERNAL_SCHEME}://settings\" method=\"get\"><input type=\"text\" name=\"{}\" value=\"{}\" size=\"48\"> <button type=\"submit\">Save</button></form>",
+        escape_html(key),
+        escape_html(value)
+    )
+}
+
+/// 2 列の表（値はエスケープする）
+fn table(rows: &[(&str, String)]) -> String {
+    let escaped: Vec<(&str, String)> = rows
+        .iter()
+        .map(|(name, value)| (*name, escape_html(value)))
+        .collect();
+    table_raw(&escaped)
+}
+
+/// 2 列の表（値は HTML のまま埋め込む）
+fn table_raw(rows: &[(&str, String)]) -> String {
+    let mut out = String::from("    <table>\n");
+    for (name, value) in rows {
+        out.push_str(&format!(
+            "        <tr><th>{}</th><td>{value}</td></tr>\n",
+            escape_html(name)
+        ));
+    }
+    out.push_str("    </table>\n");
+    out
+}
+
+/// file:// でディレクトリを開いたときの一覧ページ
+///
+/// url は `/` で終わる（ディレクトリの）URL。
+pub fn directory_listing(url: &Url, entries: &[io::DirEntry]) -> String {
+    let path = url
+        .to_file_path()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| url.path().to_string());
+
+    let mut items = String::new();
+    if let Ok(parent) = url.join("..")
+        && parent != *url
+    {
+        items.push_str(&format!(
+            "<li><a href=\"{}\">../</a></li>\n",
+            escape_html(parent.as_str())
+        ));
+    }
+    for entry in entries {
+        let name = if entry.is_dir {
+            format!("{}/", entry.name)
+        } else {
+            entry.name.clone()
+        };
+        // 名前に # や ? が含まれていても、そのままパスとして繋ぐ
+        let mut href = url.clone();
+        if let Ok(mut segments) = href.path_segments_mut() {
+            segments.pop_if_empty().push(&entry.name);
+            if entry.is_dir {
+                segments.push("");
+            }
+        }
+        let meta = if entry.is_dir {
+            String::new()
+        } else {
+            format!("<div class=\"meta\">{} bytes</div>", entry.size)
+        };
+        items.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>{meta}</li>\n",
+            escape_html(href.as_str()),
+            escape_html(&name)
+        ));
+    }
+
+    page(
+        &format!("Index of {}", escape_html(&path)),
+        &format!("    <ul>\n{items}    </ul>\n"),
+    )
+}
+
+/// テキストのレスポンスをそのまま見せるページ
+pub fn text_document(url: &Url, text: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n    <meta charset=\"UTF-8\">\n    \
+         <meta name=\"color-scheme\" content=\"light dark\">\n    <title>{}</title>\n</head>\n\
+         <body>\n<pre>{}</pre>\n</body>\n</html>\n",
+        escape_html(&document_name(url)),
+        escape_html(text)
+    )
+}
+
+/// 画像のレスポンスを見せるページ。タイトルに大きさを出す
+pub fn image_document(url: &Url, mime: &str, body: &[u8]) -> String {
+    let name = document_name(url);
+    let kind = mime
+        .strip_prefix("image/")
+        .unwrap_or(mime)
+        .trim_start_matches("x-")
+        .to_ascii_uppercase();
+    let size = image::ImageReader::new(std::io::Cursor::new(body))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+    let (title, summary) = match size {
+        Some((width, height)) => (
+            format!("{name} ({width}×{height})"),
+            format!("{kind} image, {width} × {height} pixels"),
+        ),
+        None => (name.clone(), format!("{kind} image")),
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n    <meta charset=\"UTF-8\">\n    \
+         <meta name=\"color-scheme\" content=\"light dark\">\n    <title>{}</title>\n</head>\n\
+         <body style=\"margin: 0; text-align: center\">\n<img src=\"{}\" alt=\"{}\">\n\
+         <p style=\"color: #6b7280\">{}</p>\n</body>\n</html>\n",
+        escape_html(&title),
+        escape_html(url.as_str()),
+        escape_html(&name),
+        escape_html(&summary)
+    )
+}
+
+/// 表示できないレスポンスを保存したことを知らせるページ
+pub fn download_page(url: &Url, saved: &Result<std::path::PathBuf>) -> String {
+    let body = match saved {
+        Ok(path) => format!(
+            "    <p>The file could not be displayed, so it was saved to:</p>\n    <p><code>{}</code></p>\n",
+            escape_html(&path.display().to_string())
+        ),
+        Err(e) => format!(
+            "    <p>The file could not be displayed or saved.</p>\n    <p class=\"meta\">{}</p>\n",
+            escape_html(&format!("{e:#}"))
+        ),
+    };
+    page(
+        &format!("Download {}", escape_html(&document_name(url))),
+        &format!(
+            "{body}    <p class=\"meta\">{}</p>\n",
+            escape_html(url.as_str())
+        ),
+    )
+}
+
+/// URL の最後の部分（なければ URL 全体）
+fn document_name(url: &Url) -> String {
+    url.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .map_or_else(|| url.to_string(), str::to_string)
+}
+
+/// url の読み込みに失敗したときに表示するページ
+///
+/// `resource/error.html` の `{{TITLE}}` などを埋めて作る。Retry ボタンは
+/// 失敗した URL へのリンク。
+pub fn error_page(url: &Url, err: &BrowserNetworkError) -> String {
+    let (title, description) = describe_error(err);
+    fill_error_template(url, title, description, &err.to_string(), "Retry")
+}
+
+/// url のページの処理がパニックしてタブがクラッシュしたときに表示するページ
+///
+/// エラーページと同じテンプレートを使い、Reload ボタンで url を読み直す。
+pub fn crash_page(url: &Url, message: &str) -> String {
+    fill_error_template(
+        url,
+        "This tab crashed",
+        "Something went wrong while displaying this page. \
+         Other tabs are not affected. Reload the page to try again.",
+        message,
+        "Reload",
+    )
+}
+
+fn fill_error_template(
+    url: &Url,
+    title: &str,
+    description: &str,
+    message: &str,
+    action: &str,
+) -> String {
+    let template = io::load_resource("error.html")
+        .map(|data| String::from_utf8_lossy(&data).into_owned())
+        .unwrap_or_else(|e| {
+            log::error!("Failed to load the error page template: {:#}", e);
+            "<!doctype html><html><head><title>{{TITLE}}</title></head><body>\
+             <h1>{{TITLE}}</h1><p>{{DESCRIPTION}}</p><pre>{{ERROR_MESSAGE}}</pre>\
+             <a href=\"{{URL}}\">{{ACTION}}</a></body></html>"
+                .to_string()
+        });
+
+    template
+        .replace("{{TITLE}}", &escape_html(title))
+        .replace("{{DESCRIPTION}}", &escape_html(description))
+        .replace("{{URL}}", &escape_html(url.as_str()))
+        .replace("{{ERROR_MESSAGE}}", &escape_html(message))
+        .replace("{{ACTION}}", &escape_html(action))
+}
+
+/// エラーの種類ごとの (見出し, 説明)
+fn describe_error(err: &BrowserNetworkError) -> (&'static str, &'static str) {
+    match err {
+        BrowserNetworkError::NetworkError(e) => match e {
+            NetworkError::InvalidUri | NetworkError::MissingHost | NetworkError::InvalidDnsName => {
+                (
+                    "This address is not valid",
+                    "Check the address for typing errors.",
+                )
+            }
+            NetworkError::ConnectionFailed => (
+                "This site can't be reached",
+                "The server could not be found or refused the connection.",
+            ),
+            NetworkError::TlsFailed => (
+                "Secure connection failed",
+                "A secure (TLS) connection to the server could not be established. \
+                 The certificate may be invalid or the server may not support HTTPS.",
+            ),
+            NetworkError::Timeout => (
+                "The connection timed out",
+                "The server took too long to respond.",
+            ),
+            NetworkError::ProxyFailed => (
+                "The proxy server is refusing connections",
+                "Check the proxy settings, or ask your network administrator \
+                 whether the proxy is working.",
+            ),
+            NetworkError::TooManyRedirects => (
+                "This page isn't redirecting properly",
+                "The server redirected the request too many times.",
+            ),
+            NetworkError::HttpHandshakeFailed
+            | NetworkError::HttpRequestFailed
+            | NetworkError::HttpResponseFailed
+            | NetworkError::UnsupportedHttpVersion
+            | NetworkError::ContentDecodingFailed => (
+                "The page could not be loaded",
+                "The server sent a response the browser could not read.",
+            ),
+            NetworkError::FileWriteFailed => (
+                "The file could not be saved",
+                "Check that there is enough free disk space.",
+            ),
+            NetworkError::Disconnected => (
+                "The page could not be loaded",
+                "The network is not available.",
+            ),
+        },
+        BrowserNetworkError::HttpStatus(status) if status.is_client_error() => (
+            "The page could not be loaded",
+            "The server could not find or refused to serve this page.",
+        ),
+        BrowserNetworkError::HttpStatus(_) => (
+            "The server had a problem",
+            "The server failed to respond to the request. Try again later.",
+        ),
+        BrowserNetworkError::AnyhowError(_) => (
+            "The page could not be loaded",
+            "The file could not be read.",
+        ),
+    }
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// UNIX 時間を `YYYY-MM-DD HH:MM` (UTC) にする
+fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = ((secs % 86_400) / 3600, (secs % 3600) / 60);
+
+    // 1970-01-01 からの日数を年月日にする（Howard Hinnant の civil_from_days）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}")
+}
@@ -0,0 +1,401 @@
+//! レスポンスの種類の判定と文字コードの変換
+//!
+//! 種類は `Content-Type`、なければ URL の拡張子、それでもわからなければ中身の
+//! 先頭のバイト列（MIME Sniffing Standard のシグネチャ）で決める。決めた種類から、
+//! HTML として描くか、テキストとして見せるか、画像として見せるか、ダウンロード
+//! するかを選ぶ。
+//!
+//! テキストの文字コードは BOM、`charset` パラメーター、HTML なら先頭の
+//! `<meta charset>` の順に決め、どれもなければ UTF-8 として読む。
+
+use std::fmt;
+
+use encoding_rs::{Encoding, UTF_8};
+use url::Url;
+
+use super::resource_loader::percent_decode;
+
+/// `<meta charset>` を探す範囲
+const META_PRESCAN_BYTES: usize = 1024;
+
+/// 判定に使う先頭の長さ
+const SNIFF_BYTES: usize = 1445;
+
+/// Content-Type の値
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeType {
+    /// `type/subtype`（小文字）
+    pub essence: String,
+    pub charset: Option<String>,
+}
+
+/// レスポンスの見せ方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presentation {
+    Html,
+    PlainText,
+    Image,
+    Download,
+}
+
+impl MimeType {
+    pub fn new(essence: &str) -> Self {
+        Self {
+            essence: essence.to_string(),
+            charset: None,
+        }
+    }
+
+    /// `text/html; charset=Shift_JIS` のような値を読む
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut params = value.split(';');
+        let essence = params.next()?.trim().to_ascii_lowercase();
+        let (ty, subtype) = essence.split_once('/')?;
+        if ty.is_empty() || subtype.is_empty() || essence.contains(char::is_whitespace) {
+            return None;
+        }
+        let charset = params.find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches('"').to_string())
+        });
+        Some(Self { essence, charset })
+    }
+
+    pub fn is_html(&self) -> bool {
+        matches!(self.essence.as_str(), "text/html" | "application/xhtml+xml")
+    }
+
+    /// 画面にテキストとして見せられる種類か
+    pub fn is_text(&self) -> bool {
+        self.essence.starts_with("text/")
+            || self.essence.ends_with("+json")
+            || self.essence.ends_with("+xml")
+            || matches!(
+                self.essence.as_str(),
+                "application/json" | "application/javascript" | "application/xml"
+            )
+    }
+
+    /// 表示できる画像か
+    pub fn is_image(&self) -> bool {
+        matches!(
+            self.essence.as_str(),
+            "image/png"
+                | "image/jpeg"
+                | "image/gif"
+                | "image/webp"
+                | "image/bmp"
+                | "image/x-icon"
+                | "image/vnd.microsoft.icon"
+        )
+    }
+
+    pub fn presentation(&self) -> Presentation {
+        if self.is_html() {
+            Presentation::Html
+        } else if self.is_image() {
+            Presentation::Image
+        } else if self.is_text() {
+            Presentation::PlainText
+        } else {
+            Presentation::Download
+        }
+    }
+}
+
+impl fmt::Display for MimeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.charset {
+            Some(charset) => write!(f, "{};charset={}", self.essence, charset),
+            None => f.write_str(&self.essence),
+        }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// レスポンスの種類を決める
+pub fn content_type(headers: &[(String, String)], url: &Url, body: &[u8]) -> MimeType {
+    let declared = header(headers, "content-type").and_then(MimeType::parse);
+    let nosniff = header(headers, "x-content-type-options")
+        .is_some_and(|v| v.trim().eq_ignore_ascii_case("nosniff"));
+
+    match declared {
+        Some(mime) if nosniff => mime,
+        // 種類がわからないと言っているものは中身で決める
+        Some(mime)
+            if !matches!(
+                mime.essence.as_str(),
+                "unknown/unknown" | "application/unknown" | "*/*"
+            ) =>
+        {
+            // text/plain と言いながらバイナリを送ってくるサーバーがある
+            if mime.essence == "text/plain" && is_binary(body) {
+                return MimeType::new("application/octet-stream");
+            }
+            // 画像の種類の誤りは中身に合わせる
+            if mime.essence.starts_with("image/")
+                && let Some(sniffed) = sniff_image(body)
+            {
+                return MimeType::new(sniffed);
+            }
+            mime
+        }
+        _ => {
+            let from_extension = url
+                .path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .and_then(|name| name.rsplit_once('.'))
+                .and_then(|(_, extension)| mime_for_extension(extension));
+            MimeType::new(from_extension.unwrap_or_else(|| sniff(body)))
+        }
+    }
+}
+
+/// ダウンロードするか（Content-Disposition: attachment を含む）
+pub fn presentation(headers: &[(String, String)], mime: &MimeType) -> Presentation {
+    let attachment = header(headers, "content-disposition").is_some_and(|v| {
+        v.split(';')
+            .next()
+            .is_some_and(|kind| kind.trim().eq_ignore_ascii_case("attachment"))
+    });
+    if attachment {
+        Presentation::Download
+    } else {
+        mime.presentation()
+    }
+}
+
+/// Content-Type の charset（なければ UTF-8）で本文を文字列にする（CSS やスクリプト用）
+pub fn decode_response(headers: &[(String, String)], body: &[u8]) -> String {
+    let mime = header(headers, "content-type")
+        .and_then(MimeType::parse)
+        .unwrap_or_else(|| MimeType::new("text/plain"));
+    decode_text(body, &mime)
+}
+
+/// ダウンロードしたファイルの名前
+///
+/// Content-Disposition の filename、なければ URL の最後の部分を使う。
+pub fn download_file_name(headers: &[(String, String)], url: &Url) -> String {
+    let from_header = header(headers, "content-disposition").and_then(|value| {
+        value.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("filename")
+                .then(|| value.trim().trim_matches('"').to_string())
+        })
+    });
+    let from_url = || {
+        url.path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .map(|name| String::from_utf8_lossy(&percent_decode(name.as_bytes())).into_owned())
+    };
+
+    let name = from_header.or_else(from_url).unwrap_or_default();
+    // パスの区切りは使わせない
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if matches!(c, '/' | '\\' | '\0') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    match name.trim_matches('.') {
+        "" => "download".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// 拡張子から MIME タイプを決める
+pub fn mime_for_extension(extension: &str) -> Option<&'static str> {
+    let mime = match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html",
+        "xhtml" => "application/xhtml+xml",
+        "css" => "text/css",
+        "js" | "mjs" => "text/javascript",
+        "txt" | "text" | "md" => "text/plain",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// 中身の先頭から種類を決める（MIME Sniffing Standard の unknown type の判定）
+pub fn sniff(body: &[u8]) -> &'static str {
+    let head = &body[..body.len().min(SNIFF_BYTES)];
+
+    // BOM があればテキスト
+    if head.starts_with(&[0xFE, 0xFF])
+        || head.starts_with(&[0xFF, 0xFE])
+        || head.starts_with(&[0xEF, 0xBB, 0xBF])
+    {
+        return "text/plain";
+    }
+
+    let text = trim_leading_whitespace(head);
+    if is_html_signature(text) {
+        return "text/html";
+    }
+    if text.starts_with(b"<?xml") {
+        return "text/xml";
+    }
+    if head.starts_with(b"%PDF-") {
+        return "application/pdf";
+    }
+    if let Some(image) = sniff_image(head) {
+        return image;
+    }
+
+    match head {
+        [b'I', b'D', b'3', ..] => "audio/mpeg",
+        [b'O', b'g', b'g', b'S', 0, ..] => "application/ogg",
+        [b'f', b'L', b'a', b'C', ..] => "audio/flac",
+        _ if is_riff(head, b"WAVE") => "audio/wave",
+        [0x1F, 0x8B, 0x08, ..] => "application/x-gzip",
+        [b'P', b'K', 0x03, 0x04, ..] => "application/zip",
+        _ if is_binary(head) => "application/octet-stream",
+        _ => "text/plain",
+    }
+}
+
+fn sniff_image(body: &[u8]) -> Option<&'static str> {
+    let mime = match body {
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        _ if is_riff(body, b"WEBP") && body[12..].starts_with(b"VP") => "image/webp",
+        [b'B', b'M', ..] => "image/bmp",
+        [0, 0, 1 | 2, 0, ..] => "image/x-icon",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// RIFF コンテナ（WAVE や WEBP）か
+fn is_riff(body: &[u8], kind: &[u8; 4]) -> bool {
+    body.len() >= 12 && body.starts_with(b"RIFF") && &body[8..12] == kind
+}
+
+/// `<!DOCTYPE HTML` や `<p` などで始まり、その後に空白か `>` が続くか
+fn is_html_signature(text: &[u8]) -> bool {
+    const TAGS: &[&[u8]] = &[
+        b"<!DOCTYPE HTML",
+        b"<HTML",
+        b"<HEAD",
+        b"<SCRIPT",
+        b"<IFRAME",
+        b"<H1",
+        b"<DIV",
+        b"<FONT",
+        b"<TABLE",
+        b"<A",
+        b"<STYLE",
+        b"<TITLE",
+        b"<B",
+        b"<BODY",
+        b"<BR",
+        b"<P",
+        b"<!--",
+    ];
+    TAGS.iter().any(|tag| {
+        text.len() > tag.len()
+            && text[..tag.len()].eq_ignore_ascii_case(tag)
+            && matches!(text[tag.len()], b' ' | b'>')
+    })
+}
+
+fn trim_leading_whitespace(bytes: &[u8]) -> &[u8] {
+    let start = bytes
+        .iter()
+        .position(|b| !matches!(b, b'\t' | b'\n' | b'\x0C' | b'\r' | b' '))
+        .unwrap_or(bytes.len());
+    &bytes[start..]
+}
+
+/// テキストには出てこない制御文字を含むか
+fn is_binary(body: &[u8]) -> bool {
+    body[..body.len().min(SNIFF_BYTES)]
+        .iter()
+        .any(|&b| matches!(b, 0x00..=0x08 | 0x0B | 0x0E..=0x1A | 0x1C..=0x1F))
+}
+
+/// テキストのレスポンスを文字列にする
+pub fn decode_text(body: &[u8], mime: &MimeType) -> String {
+    let encoding = mime
+        .charset
+        .as_deref()
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()))
+        .or_else(|| {
+            if mime.is_html() {
+                prescan_meta_charset(body)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(UTF_8);
+    // BOM があればそれに従う
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+/// HTML の先頭から `<meta charset="...">` か
+/// `<meta http-equiv="Content-Type" content="...; charset=...">` を探す
+fn prescan_meta_charset(body: &[u8]) -> Option<&'static Encoding> {
+    let head = &body[..body.len().min(META_PRESCAN_BYTES)];
+    let head = String::from_utf8_lossy(head).to_ascii_lowercase();
+
+    let mut rest = head.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag = &rest[start..];
+        let end = tag.find('>').unwrap_or(tag.len());
+        let attributes = &tag[..end];
+        rest = &tag[end..];
+
+        let Some(position) = attributes.find("charset") else {
+            continue;
+        };
+        let value = attributes[position + "charset".len()..].trim_start();
+        let Some(value) = value.strip_prefix('=') else {
+            continue;
+        };
+        let label: String = value
+            .trim_start()
+            .trim_start_matches(['"', '\''])
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+            .collect();
+        if let Some(encoding) = Encoding::for_label(label.as_bytes()) {
+            // UTF-16 と書いてあっても、ここまで ASCII で読めているなら UTF-8
+            return Some(encoding.output_encoding());
+        }
+    }
+    None
+}
@@ -1,10 +1,38 @@
 mod app;
+pub mod autofill;
+pub mod browsing_history;
+pub mod cdp;
 mod command;
+pub mod content_blocking;
+pub mod csp;
+pub mod devtools;
+pub mod downloads;
+pub mod engine_thread;
+pub mod extensions;
+pub mod fetch_policy;
+pub mod frame_scheduler;
+pub mod history;
+pub mod idn;
+pub mod internal_pages;
+pub mod mime;
+pub mod origin;
+pub mod passwords;
+pub mod permissions;
+pub mod progress;
+pub mod reader;
+pub mod reftest;
 pub mod resource_loader;
+pub mod scheduler;
+pub mod schemes;
+pub mod security;
+pub mod session;
 pub mod tab;
 pub mod ui;
+pub mod visited_links;
 pub mod webview;
 
 pub use app::BrowserApp;
-pub use command::BrowserCommand;
+pub use command::{
+    BrowserCommand, BrowserEvent, CommandSender, EventSink, WindowAction, WindowState,
+};
 pub use tab::Tab;
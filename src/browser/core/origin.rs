@@ -0,0 +1,86 @@
+//! オリジン（スキーム、ホスト、ポートの組）
+//!
+//! 同一オリジンポリシーの判定（[`super::fetch_policy`]）に使う。file: や data: の
+//! 文書は opaque なオリジンになり、自分以外のどのオリジンとも一致しない。
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use url::Url;
+
+/// 次に作る opaque なオリジンの番号
+static NEXT_OPAQUE_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Origin {
+    /// 作るたびに別のものになるオリジン（file:、data:、about: など）
+    Opaque(u64),
+    Tuple {
+        scheme: String,
+        host: String,
+        /// 既定のポートも明示した値
+        port: u16,
+    },
+}
+
+impl Origin {
+    /// url のオリジン
+    pub fn of(url: &Url) -> Self {
+        match url.scheme() {
+            "http" | "https" | "ws" | "wss" => {
+                match (url.host_str(), url.port_or_known_default()) {
+                    (Some(host), Some(port)) => Self::Tuple {
+                        scheme: url.scheme().to_string(),
+                        host: host.to_ascii_lowercase(),
+                        port,
+                    },
+                    _ => Self::new_opaque(),
+                }
+            }
+            // blob:https://example.com/... は中の URL のオリジン
+            "blob" => match Url::parse(url.path()) {
+                Ok(inner) if matches!(inner.scheme(), "http" | "https") => Self::of(&inner),
+                _ => Self::new_opaque(),
+            },
+            _ => Self::new_opaque(),
+        }
+    }
+
+    pub fn new_opaque() -> Self {
+        Self::Opaque(NEXT_OPAQUE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, Self::Opaque(_))
+    }
+
+    pub fn is_same_origin(&self, other: &Origin) -> bool {
+        self == other
+    }
+
+    /// `Origin` ヘッダーや `Access-Control-Allow-Origin` と比べる形
+    /// （`https://example.com`、既定のポートは書かない。opaque なら `null`）
+    pub fn ascii_serialization(&self) -> String {
+        match self {
+            Self::Opaque(_) => "null".to_string(),
+            Self::Tuple { scheme, host, port } => {
+                let default_port = match scheme.as_str() {
+                    "http" | "ws" => 80,
+                    "https" | "wss" => 443,
+                    _ => 0,
+                };
+                if *port == default_port {
+                    format!("{scheme}://{host}")
+                } else {
+                    format!("{scheme}://{host}:{port}")
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.ascii_serialization())
+    }
+}
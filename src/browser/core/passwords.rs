@@ -0,0 +1,173 @@
+//! パスワードの保存
+//!
+//! ログインフォーム（パスワードの欄があるフォーム）を送信すると、そのオリジンに保存するかを
+//! 利用者に尋ね、許されたものだけを [`SecretStore`]（OS のキーチェーンか暗号化したファイル）に
+//! 保存する。同じオリジンのログインフォームにフォーカスすると、保存したユーザー名を候補に出す。
+//! 「このサイトでは保存しない」を選んだオリジンはプロファイルの `password_exceptions` に残す。
+//!
+//! キーチェーンにはオリジンごとに 1 項目、`ユーザー名=パスワード&...`（URL エンコード）を置く。
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::Result;
+use url::{Url, form_urlencoded};
+
+use crate::platform::io;
+use crate::platform::keychain::SecretStore;
+
+/// プロファイル内のパスワードを暗号化して保存するファイル名（OS のキーチェーンがないとき）
+pub const PASSWORD_FILE_NAME: &str = "passwords";
+
+/// プロファイル内の、パスワードを保存しないオリジンのファイル名
+pub const PASSWORD_EXCEPTIONS_FILE_NAME: &str = "password_exceptions";
+
+/// ユーザー名とパスワード
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credential {
+    pub username: String,
+    pub password: String,
+}
+
+/// 送信されたログインフォームの中身
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubmittedLogin {
+    /// フォームのあった文書のオリジン（`https://example.com` の形）
+    pub origin: String,
+    pub credential: Credential,
+}
+
+/// パスワードを保存する単位のオリジン（http と https だけ）
+pub fn login_origin(url: &Url) -> Option<String> {
+    matches!(url.scheme(), "http" | "https").then(|| url.origin().ascii_serialization())
+}
+
+/// キーチェーンの 1 項目の中身にする
+pub fn encode_credentials(credentials: &[Credential]) -> String {
+    form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(
+            credentials
+                .iter()
+                .map(|c| (c.username.as_str(), c.password.as_str())),
+        )
+        .finish()
+}
+
+pub fn decode_credentials(secret: &str) -> Vec<Credential> {
+    form_urlencoded::parse(secret.as_bytes())
+        .map(|(username, password)| Credential {
+            username: username.into_owned(),
+            password: password.into_owned(),
+        })
+        .collect()
+}
+
+/// 保存したパスワードと、保存しないオリジン
+pub struct PasswordManager {
+    store: Box<dyn SecretStore>,
+    /// キーチェーンから読んだオリジンごとの中身（読むたびにキーチェーンを開かないように）
+    cache: HashMap<String, Vec<Credential>>,
+    /// 保存しないオリジン
+    never_save: Vec<String>,
+}
+
+impl PasswordManager {
+    pub fn new(store: Box<dyn SecretStore>) -> Self {
+        Self {
+            store,
+            cache: HashMap::new(),
+            never_save: Vec::new(),
+        }
+    }
+
+    /// 保存先の名前（ログ用）
+    pub fn store_name(&self) -> &'static str {
+        self.store.name()
+    }
+
+    /// origin に保存したもの（読めなければ空）
+    pub fn credentials(&mut self, origin: &str) -> &[Credential] {
+        if !self.cache.contains_key(origin) {
+            let credentials = match self.store.get(origin) {
+                Ok(secret) => secret
+                    .as_deref()
+                    .map(decode_credentials)
+                    .unwrap_or_default(),
+                Err(e) => {
+                    log::warn!("Failed to read saved passwords for {}: {:#}", origin, e);
+                    Vec::new()
+                }
+            };
+            self.cache.insert(origin.to_string(), credentials);
+        }
+        &self.cache[origin]
+    }
+
+    /// 送信された login を保存するか尋ねるべきか
+    ///
+    /// 保存しないオリジンと、同じユーザー名とパスワードを保存済みのものは尋ねない。
+    pub fn should_offer_to_save(&mut self, login: &SubmittedLogin) -> bool {
+        !login.credential.password.is_empty()
+            && !self.is_never_saved(&login.origin)
+            && !self.credentials(&login.origin).contains(&login.credential)
+    }
+
+    /// origin に credential を保存する（同じユーザー名のものは置き換える）
+    pub fn save(&mut self, origin: &str, credential: Credential) -> Result<()> {
+        let mut credentials = self.credentials(origin).to_vec();
+        credentials.retain(|c| c.username != credential.username);
+        credentials.push(credential);
+        self.store.set(origin, &encode_credentials(&credentials))?;
+        self.cache.insert(origin.to_string(), credentials);
+        Ok(())
+    }
+
+    /// origin に保存した username のものを消す
+    pub fn remove(&mut self, origin: &str, username: &str) -> Result<()> {
+        let mut credentials = self.credentials(origin).to_vec();
+        credentials.retain(|c| c.username != username);
+        if credentials.is_empty() {
+            self.store.delete(origin)?;
+        } else {
+            self.store.set(origin, &encode_credentials(&credentials))?;
+        }
+        self.cache.insert(origin.to_string(), credentials);
+        Ok(())
+    }
+
+    pub fn is_never_saved(&self, origin: &str) -> bool {
+        self.never_save.iter().any(|o| o == origin)
+    }
+
+    /// origin ではもう保存するか尋ねない
+    pub fn never_save(&mut self, origin: &str) {
+        if !self.is_never_saved(origin) {
+            self.never_save.push(origin.to_string());
+        }
+    }
+
+    pub fn never_saved_origins(&self) -> &[String] {
+        &self.never_save
+    }
+
+    /// 保存しないオリジンを path（1 行に 1 つ）から読み込む。ファイルがなければ何もしない
+    pub fn load_exceptions(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let text = std::fs::read_to_string(path)?;
+        for origin in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            self.never_save(origin);
+        }
+        Ok(())
+    }
+
+    pub fn save_exceptions(&self, path: &Path) -> Result<()> {
+        let mut text = String::new();
+        for origin in &self.never_save {
+            text.push_str(origin);
+            text.push('\n');
+        }
+        io::write_atomic(path, text.as_bytes())
+    }
+}
@@ -0,0 +1,208 @@
+//! サイトごとの権限
+//!
+//! 通知や位置情報のように、ページが使う前に利用者の同意が要る機能をまとめて扱う。
+//! ページが権限を求めると [`super::webview::WebView::request_permission`] が要求を溜め、
+//! アプリが保存済みの決定を返すか、利用者に尋ねて決める。決まったものはオリジンと
+//! 権限の組ごとにプロファイルの `permissions` ファイルに保存する。
+//!
+//! ```text
+//! allow	notifications	https://example.com
+//! deny	geolocation	https://example.org
+//! ```
+
+use std::path::Path;
+
+use anyhow::Result;
+use url::Url;
+
+use crate::platform::io;
+
+/// プロファイル内のサイトごとの権限のファイル名
+pub const PERMISSIONS_FILE_NAME: &str = "permissions";
+
+/// 同意が要る機能
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    Notifications,
+    Geolocation,
+    Autoplay,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 3] = [
+        Permission::Notifications,
+        Permission::Geolocation,
+        Permission::Autoplay,
+    ];
+
+    /// ファイルに書く名前（Permissions API の名前）
+    pub fn name(self) -> &'static str {
+        match self {
+            Permission::Notifications => "notifications",
+            Permission::Geolocation => "geolocation",
+            Permission::Autoplay => "autoplay",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// 尋ねるときの文（「example.com wants to ...」に続く）
+    pub fn description(self) -> &'static str {
+        match self {
+            Permission::Notifications => "show notifications",
+            Permission::Geolocation => "know your location",
+            Permission::Autoplay => "play audio automatically",
+        }
+    }
+}
+
+/// 権限の状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PermissionState {
+    /// まだ決めていない（求められたら尋ねる）
+    #[default]
+    Ask,
+    Allow,
+    Deny,
+}
+
+impl PermissionState {
+    pub fn name(self) -> &'static str {
+        match self {
+            PermissionState::Ask => "ask",
+            PermissionState::Allow => "allow",
+            PermissionState::Deny => "deny",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ask" => Some(PermissionState::Ask),
+            "allow" => Some(PermissionState::Allow),
+            "deny" => Some(PermissionState::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// 権限を決める単位のオリジン（http と https だけ。ほかの文書には権限を与えない）
+pub fn permission_origin(url: &Url) -> Option<String> {
+    matches!(url.scheme(), "http" | "https").then(|| url.origin().ascii_serialization())
+}
+
+/// ページが求めた権限
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionRequest {
+    /// 求めた文書のオリジン
+    pub origin: String,
+    pub permission: Permission,
+}
+
+/// 1 つのオリジンの 1 つの権限についての決定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionGrant {
+    pub origin: String,
+    pub permission: Permission,
+    pub state: PermissionState,
+}
+
+/// サイトごとの権限の決定
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PermissionStore {
+    grants: Vec<PermissionGrant>,
+}
+
+impl PermissionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn grants(&self) -> &[PermissionGrant] {
+        &self.grants
+    }
+
+    /// origin の permission の状態（決めていなければ Ask）
+    pub fn state(&self, origin: &str, permission: Permission) -> PermissionState {
+        self.grants
+            .iter()
+            .find(|g| g.origin == origin && g.permission == permission)
+            .map(|g| g.state)
+            .unwrap_or_default()
+    }
+
+    /// origin の permission を state にする（Ask なら決定を消す）。変わったら true
+    pub fn set(&mut self, origin: &str, permission: Permission, state: PermissionState) -> bool {
+        if self.state(origin, permission) == state {
+            return false;
+        }
+        self.grants
+            .retain(|g| g.origin != origin || g.permission != permission);
+        if state != PermissionState::Ask {
+            self.grants.push(PermissionGrant {
+                origin: origin.to_string(),
+                permission,
+                state,
+            });
+        }
+        true
+    }
+
+    /// origin の決定をすべて消す。消したものがあれば true
+    pub fn reset(&mut self, origin: &str) -> bool {
+        let len = self.grants.len();
+        self.grants.retain(|g| g.origin != origin);
+        self.grants.len() != len
+    }
+
+    pub fn clear(&mut self) {
+        self.grants.clear();
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for grant in &self.grants {
+            out.push_str(&format!(
+                "{}\t{}\t{}\n",
+                grant.state.name(),
+                grant.permission.name(),
+                grant.origin
+            ));
+        }
+        out
+    }
+
+    /// 読めない行は飛ばす
+    pub fn parse(text: &str) -> Self {
+        let mut store = Self::default();
+
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            let (Some(state), Some(permission), Some(origin)) = (
+                fields.next().and_then(PermissionState::from_name),
+                fields.next().and_then(Permission::from_name),
+                fields.next().filter(|o| !o.is_empty()),
+            ) else {
+                log::warn!("Skipping invalid permission entry: {:?}", line);
+                continue;
+            };
+            store.set(origin, permission, state);
+        }
+
+        store
+    }
+
+    /// path から読み込む。ファイルがなければ何も決めていない状態
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        io::write_atomic(path, self.serialize().as_bytes())
+    }
+}
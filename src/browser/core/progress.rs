@@ -0,0 +1,72 @@
+//! ページ読み込みの進み具合
+//!
+//! タブが要求したリソース（HTML と CSS）の数と、受信中のリソースのバイト数から
+//! 0.0〜1.0 の進捗を求める。CSS は HTML を読むまで数がわからないので、
+//! 完了するまではもう 1 つリソースが残っているものとして扱い、表示する値は
+//! 戻らないようにする。
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadProgress {
+    /// 要求したリソースの数
+    requested: usize,
+    /// 読み込み終わった（失敗を含む）リソースの数
+    finished: usize,
+    /// 受信中のリソースごとの (受信したバイト数, Content-Length)
+    receiving: HashMap<usize, (u64, Option<u64>)>,
+    /// これまでに表示した最大の進捗
+    fraction: f32,
+}
+
+impl LoadProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 新しいページの読み込みを始める
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn request_started(&mut self) {
+        self.requested += 1;
+        self.update();
+    }
+
+    /// fetch id のリソースを received バイトまで受信した
+    pub fn bytes_received(&mut self, id: usize, received: u64, total: Option<u64>) {
+        self.receiving.insert(id, (received, total));
+        self.update();
+    }
+
+    /// fetch id のリソースの読み込みが終わった
+    pub fn request_finished(&mut self, id: usize) {
+        self.receiving.remove(&id);
+        self.finished = (self.finished + 1).min(self.requested);
+        self.update();
+    }
+
+    /// (読み込み終わったリソースの数, 要求したリソースの数)
+    pub fn resource_counts(&self) -> (usize, usize) {
+        (self.finished, self.requested)
+    }
+
+    /// 0.0〜1.0 の進捗（読み込み中は 1.0 にならない）
+    pub fn fraction(&self) -> f32 {
+        self.fraction
+    }
+
+    fn update(&mut self) {
+        let partial: f32 = self
+            .receiving
+            .values()
+            .map(|&(received, total)| match total {
+                Some(total) if total > 0 => (received as f32 / total as f32).min(1.0),
+                _ => 0.0,
+            })
+            .sum();
+        let raw = (self.finished as f32 + partial) / (self.requested + 1) as f32;
+        self.fraction = self.fraction.max(raw.clamp(0.0, 1.0));
+    }
+}
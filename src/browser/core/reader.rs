@@ -0,0 +1,353 @@
+//! リーダーモード（本文の抽出）
+//!
+//! Readability と同じ考え方で DOM から記事の本文らしい要素を選び、見出し・段落・
+//! リスト・画像などだけを残した HTML にする。レイアウトエンジンがまだ扱えない
+//! ページでも、抽出した本文を簡素なスタイルで読めるようにするためのもの。
+//!
+//! 本文の選び方:
+//! 1. 25 文字以上のテキストを持つ段落（p, pre, td, blockquote）を探す
+//! 2. 段落のテキストの長さと読点の数から点数を付け、親に全部、祖父母に半分を足す
+//! 3. class / id に article や content を含む要素は加点、nav や sidebar は減点する
+//! 4. リンクのテキストが多い要素ほど点数を下げ、最高点の要素を本文とする
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use url::Url;
+
+use super::internal_pages::escape_html;
+use crate::engine::html::HtmlNodeType;
+use crate::engine::html::parser::DomTree;
+use crate::engine::tree::NodeRef;
+
+/// 本文とみなす最低限のテキストの長さ
+const MIN_ARTICLE_LENGTH: usize = 200;
+/// 点数を付ける段落の最低限のテキストの長さ
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+
+const POSITIVE_HINTS: &[&str] = &[
+    "article", "body", "content", "entry", "main", "page", "post", "story", "text",
+];
+const NEGATIVE_HINTS: &[&str] = &[
+    "ad-", "banner", "comment", "footer", "header", "menu", "meta", "nav", "related", "share",
+    "sidebar", "social", "sponsor", "widget",
+];
+
+/// 本文に入れない要素（中身ごと捨てる）
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "nav", "aside", "footer", "form", "button", "input", "select",
+    "textarea", "iframe", "svg", "canvas", "object", "embed", "head", "template",
+];
+
+/// 本文にそのまま残す要素。ほかの要素は中身だけ残す
+const KEPT_TAGS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "pre",
+    "code",
+    "blockquote",
+    "em",
+    "strong",
+    "b",
+    "i",
+    "a",
+    "img",
+    "br",
+    "hr",
+    "figure",
+    "figcaption",
+    "table",
+    "thead",
+    "tbody",
+    "tr",
+    "td",
+    "th",
+    "sub",
+    "sup",
+];
+
+/// 抽出した記事
+#[derive(Debug, Clone, PartialEq)]
+pub struct Article {
+    pub title: String,
+    /// 整理した本文の HTML
+    pub content: String,
+    /// 本文のテキストの長さ（文字数）
+    pub text_length: usize,
+}
+
+/// リーダーモードの配色
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReaderTheme {
+    #[default]
+    Light,
+    Sepia,
+    Dark,
+}
+
+impl ReaderTheme {
+    pub const ALL: [Self; 3] = [Self::Light, Self::Sepia, Self::Dark];
+
+    /// 設定ファイルでの名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Sepia => "sepia",
+            Self::Dark => "dark",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.name() == name)
+    }
+
+    /// (背景色, 文字色, リンクの色)
+    fn colors(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Self::Light => ("#ffffff", "#1f2328", "#0b57d0"),
+            Self::Sepia => ("#f4ecd8", "#5b4636", "#8a4b16"),
+            Self::Dark => ("#1c1b22", "#e8e6e3", "#8ab4f8"),
+        }
+    }
+}
+
+/// リーダーモードの表示設定
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReaderOptions {
+    /// 本文の文字の大きさ（px）
+    pub font_size: f32,
+    pub theme: ReaderTheme,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        Self {
+            font_size: 18.0,
+            theme: ReaderTheme::Light,
+        }
+    }
+}
+
+/// dom から本文を取り出す。本文らしいものがなければ None
+///
+/// リンクと画像の URL は base_url を基準に絶対 URL にする。
+pub fn extract(dom: &DomTree, title: &str, base_url: &Url) -> Option<Article> {
+    let mut scores: HashMap<*const (), (NodeRef<HtmlNodeType>, f32)> = HashMap::new();
+
+    for paragraph in
+        dom.find_all(|n| matches!(n.tag_name(), Some("p" | "pre" | "td" | "blockquote")))
+    {
+        let text = DomTree::inner_text(&paragraph);
+        let text = text.trim();
+        let length = text.chars().count();
+        if length < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+
+        // 読点と長さで段落の点数を付ける
+        let commas = text.matches([',', '、', '，']).count();
+        let score = 1.0 + commas as f32 + (length as f32 / 100.0).min(3.0);
+
+        let parent = paragraph.borrow().parent();
+        let grandparent = parent.as_ref().and_then(|p| p.borrow().parent());
+        for (ancestor, share) in [(parent, 1.0), (grandparent, 0.5)] {
+            let Some(ancestor) = ancestor else {
+                continue;
+            };
+            let key = Rc::as_ptr(&ancestor) as *const ();
+            let entry = scores
+                .entry(key)
+                .or_insert_with(|| (ancestor.clone(), initial_score(&ancestor)));
+            entry.1 += score * share;
+        }
+    }
+
+    let (best, _) = scores
+        .into_values()
+        .map(|(node, score)| {
+            let adjusted = score * (1.0 - link_density(&node));
+            (node, adjusted)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+    let mut content = String::new();
+    for child in best.borrow().children() {
+        write_clean(child, base_url, &mut content);
+    }
+    let text_length = DomTree::inner_text(&best)
+        .split_whitespace()
+        .map(|word| word.chars().count())
+        .sum();
+    if text_length < MIN_ARTICLE_LENGTH {
+        return None;
+    }
+
+    Some(Article {
+        title: title.trim().to_string(),
+        content,
+        text_length,
+    })
+}
+
+/// 記事をリーダーモードのページにする
+pub fn render(article: &Article, options: &ReaderOptions) -> String {
+    let (background, text, link) = options.theme.colors();
+    let font_size = options.font_size;
+    let title = escape_html(&article.title);
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>{title}</title>
+    <style>
+        html, body {{ background: {background}; color: {text}; }}
+        body {{ font-family: serif; font-size: {font_size}px; line-height: 1.6; }}
+        main {{ max-width: 40em; margin: 32px auto; padding: 0 24px; }}
+        h1 {{ font-family: sans-serif; line-height: 1.25; }}
+        a {{ color: {link}; }}
+        img {{ max-width: 100%; }}
+        pre {{ white-space: pre-wrap; font-size: 0.85em; }}
+        blockquote {{ margin-left: 0; padding-left: 1em; border-left: 3px solid {text}; }}
+    </style>
+</head>
+<body>
+    <main>
+        <h1>{title}</h1>
+{content}
+    </main>
+</body>
+</html>
+"#,
+        content = article.content,
+    )
+}
+
+/// タグと class / id からの初期点
+fn initial_score(node: &NodeRef<HtmlNodeType>) -> f32 {
+    let n = node.borrow();
+    let tag_score = match n.value.tag_name() {
+        Some("article" | "main") => 10.0,
+        Some("div") => 5.0,
+        Some("section" | "pre" | "td" | "blockquote") => 3.0,
+        Some("form" | "ul" | "ol" | "dl") => -3.0,
+        Some("th" | "header" | "footer" | "nav" | "aside") => -5.0,
+        _ => 0.0,
+    };
+
+    let hints = format!(
+        "{} {}",
+        n.value.get_attr("class").unwrap_or(""),
+        n.value.get_attr("id").unwrap_or("")
+    )
+    .to_lowercase();
+    let mut hint_score = 0.0;
+    if POSITIVE_HINTS.iter().any(|h| hints.contains(h)) {
+        hint_score += 25.0;
+    }
+    if NEGATIVE_HINTS.iter().any(|h| hints.contains(h)) {
+        hint_score -= 25.0;
+    }
+
+    tag_score + hint_score
+}
+
+/// 要素のテキストのうちリンクの中にあるものの割合
+fn link_density(node: &NodeRef<HtmlNodeType>) -> f32 {
+    let total = DomTree::inner_text(node).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let mut linked = 0;
+    let mut stack = vec![node.clone()];
+    while let Some(current) = stack.pop() {
+        let n = current.borrow();
+        if n.value.tag_name() == Some("a") {
+            linked += DomTree::inner_text(&current).chars().count();
+            continue;
+        }
+        stack.extend(n.children().iter().cloned());
+    }
+
+    linked as f32 / total as f32
+}
+
+/// node を本文用に整理した HTML を out に書く
+fn write_clean(node: &NodeRef<HtmlNodeType>, base_url: &Url, out: &mut String) {
+    let n = node.borrow();
+    let (tag, hints) = match &n.value {
+        HtmlNodeType::Text(text) => {
+            out.push_str(&escape_html(text));
+            return;
+        }
+        HtmlNodeType::Element { tag_name, .. } => (
+            tag_name.to_ascii_lowercase(),
+            format!(
+                "{} {}",
+                n.value.get_attr("class").unwrap_or(""),
+                n.value.get_attr("id").unwrap_or("")
+            )
+            .to_lowercase(),
+        ),
+        _ => return,
+    };
+
+    if SKIPPED_TAGS.contains(&tag.as_str()) {
+        return;
+    }
+    // 本文の中に紛れ込んだ共有ボタンや関連記事などを落とす
+    if NEGATIVE_HINTS.iter().any(|h| hints.contains(h))
+        && !POSITIVE_HINTS.iter().any(|h| hints.contains(h))
+        && link_density(node) > 0.5
+    {
+        return;
+    }
+
+    if !KEPT_TAGS.contains(&tag.as_str()) {
+        for child in n.children() {
+            write_clean(child, base_url, out);
+        }
+        return;
+    }
+
+    out.push('<');
+    out.push_str(&tag);
+    match tag.as_str() {
+        "a" => {
+            if let Some(href) = n.value.get_attr("href").and_then(|h| base_url.join(h).ok()) {
+                out.push_str(&format!(" href=\"{}\"", escape_html(href.as_str())));
+            }
+        }
+        "img" => {
+            if let Some(src) = n.value.get_attr("src").and_then(|s| base_url.join(s).ok()) {
+                out.push_str(&format!(" src=\"{}\"", escape_html(src.as_str())));
+            }
+            if let Some(alt) = n.value.get_attr("alt") {
+                out.push_str(&format!(" alt=\"{}\"", escape_html(alt)));
+            }
+        }
+        _ => {}
+    }
+    out.push('>');
+
+    if matches!(tag.as_str(), "img" | "br" | "hr") {
+        return;
+    }
+    for child in n.children() {
+        write_clean(child, base_url, out);
+    }
+    out.push_str(&format!("</{tag}>"));
+}
@@ -0,0 +1,196 @@
+//! 参照画像と比べるレイアウトのテスト（reftest）
+//!
+//! HTML（と、そこから読み込む CSS）のフィクスチャをウィンドウなしで読み込み、
+//! [`BrowserApp::render_page_to_png`] で画像にして、フィクスチャと同じ名前の PNG と
+//! 画素ごとに比べる。フォントのラスタライズや GPU の違いで端の画素が少しずれるので、
+//! 色の差と、差のある画素の数に許容範囲を持たせる。
+//!
+//! 参照画像がなければ描いた画像をそのまま参照画像として書き出す。
+//! 環境変数 [`UPDATE_ENV`] を設定すると、すべての参照画像を描き直す。
+//! 書き出した画像は目で確かめてからリポジトリに入れる。
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use image::{Rgba, RgbaImage};
+use url::Url;
+
+use super::BrowserApp;
+
+/// フィクスチャをレイアウトする大きさ（CSS px、スケール 1.0）
+pub const REFTEST_SIZE: (u32, u32) = (400, 300);
+
+/// 設定すると参照画像を描き直す環境変数
+pub const UPDATE_ENV: &str = "ORINIUM_UPDATE_REFTESTS";
+
+/// 比べるときの許容範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tolerance {
+    /// RGBA の各チャンネルで同じとみなす差
+    pub channel: u8,
+    /// channel を超えて違ってもよい画素の数
+    pub max_differing_pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            channel: 16,
+            max_differing_pixels: 32,
+        }
+    }
+}
+
+/// 2 つの画像の違い
+#[derive(Debug, Clone)]
+pub struct ImageDiff {
+    /// 大きさが違えば (描いた画像, 参照画像) の大きさ
+    pub size_mismatch: Option<((u32, u32), (u32, u32))>,
+    /// 許容範囲を超えて違う画素の数
+    pub differing_pixels: usize,
+    /// いちばん大きいチャンネルの差
+    pub max_channel_delta: u8,
+    /// 違う画素を赤、同じ画素を薄くした参照画像
+    pub image: RgbaImage,
+}
+
+impl ImageDiff {
+    pub fn passes(&self, tolerance: &Tolerance) -> bool {
+        self.size_mismatch.is_none() && self.differing_pixels <= tolerance.max_differing_pixels
+    }
+}
+
+/// actual を expected と画素ごとに比べる
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: &Tolerance) -> ImageDiff {
+    if actual.dimensions() != expected.dimensions() {
+        return ImageDiff {
+            size_mismatch: Some((actual.dimensions(), expected.dimensions())),
+            differing_pixels: 0,
+            max_channel_delta: 0,
+            image: expected.clone(),
+        };
+    }
+
+    let mut differing_pixels = 0;
+    let mut max_channel_delta = 0;
+    let mut image = RgbaImage::new(expected.width(), expected.height());
+    for ((a, e), out) in actual
+        .pixels()
+        .zip(expected.pixels())
+        .zip(image.pixels_mut())
+    {
+        let delta =
+            a.0.iter()
+                .zip(e.0)
+                .map(|(a, e)| a.abs_diff(e))
+                .max()
+                .unwrap_or(0);
+        max_channel_delta = max_channel_delta.max(delta);
+        *out = if delta > tolerance.channel {
+            differing_pixels += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            // 位置がわかるように参照画像を薄く残す
+            let [r, g, b, _] = e.0;
+            Rgba([r / 4 + 191, g / 4 + 191, b / 4 + 191, 255])
+        };
+    }
+
+    ImageDiff {
+        size_mismatch: None,
+        differing_pixels,
+        max_channel_delta,
+        image,
+    }
+}
+
+/// 1 つのフィクスチャ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fixture {
+    /// ファイル名から拡張子を除いたもの
+    pub name: String,
+    pub html: PathBuf,
+    /// 参照画像（HTML と同じ場所の `<name>.png`）
+    pub reference: PathBuf,
+}
+
+/// dir の `*.html` を名前順に集める
+pub fn fixtures(dir: &Path) -> Result<Vec<Fixture>> {
+    let mut fixtures = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {dir:?}"))? {
+        let html = entry?.path();
+        if html.extension().is_none_or(|ext| ext != "html") {
+            continue;
+        }
+        let Some(name) = html.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        fixtures.push(Fixture {
+            name: name.to_string(),
+            reference: html.with_extension("png"),
+            html,
+        });
+    }
+    fixtures.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(fixtures)
+}
+
+/// 1 つのフィクスチャを走らせた結果
+#[derive(Debug)]
+pub enum Outcome {
+    Passed,
+    /// 参照画像がなかった（か、描き直すよう指定された）ので書き出した
+    Written,
+    /// 許容範囲を超えて違った。描いた画像と差分の画像を書き出した場所も返す
+    Failed {
+        diff: ImageDiff,
+        actual_path: PathBuf,
+        diff_path: PathBuf,
+    },
+}
+
+/// fixture を描いて参照画像と比べる
+///
+/// 違ったときは、描いた画像を `<out_dir>/<name>-actual.png` に、差分を
+/// `<out_dir>/<name>-diff.png` に書き出す。
+///
+/// # Errors
+/// GPU のアダプターがない、ファイルを読み書きできないなど、比べられなかった場合
+pub fn run(fixture: &Fixture, tolerance: &Tolerance, out_dir: &Path) -> Result<Outcome> {
+    let path = fixture
+        .html
+        .canonicalize()
+        .with_context(|| format!("Fixture not found: {:?}", fixture.html))?;
+    let url = Url::from_file_path(&path)
+        .map_err(|_| anyhow::anyhow!("Fixture path is not absolute: {path:?}"))?;
+
+    let mut browser = BrowserApp::default();
+    let png = browser.render_page_to_png(url, REFTEST_SIZE)?;
+
+    let update = std::env::var_os(UPDATE_ENV).is_some();
+    if update || !fixture.reference.exists() {
+        std::fs::write(&fixture.reference, &png)
+            .with_context(|| format!("Failed to write {:?}", fixture.reference))?;
+        return Ok(Outcome::Written);
+    }
+
+    let actual = image::load_from_memory(&png)?.to_rgba8();
+    let expected = image::open(&fixture.reference)
+        .with_context(|| format!("Failed to read {:?}", fixture.reference))?
+        .to_rgba8();
+    let diff = compare(&actual, &expected, tolerance);
+    if diff.passes(tolerance) {
+        return Ok(Outcome::Passed);
+    }
+
+    std::fs::create_dir_all(out_dir)?;
+    let actual_path = out_dir.join(format!("{}-actual.png", fixture.name));
+    let diff_path = out_dir.join(format!("{}-diff.png", fixture.name));
+    std::fs::write(&actual_path, &png)?;
+    diff.image.save(&diff_path)?;
+    Ok(Outcome::Failed {
+        diff,
+        actual_path,
+        diff_path,
+    })
+}
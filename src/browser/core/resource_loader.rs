@@ -1,12 +1,20 @@
-use crate::network::{NetworkCore, NetworkError};
-use anyhow::{Result, anyhow};
+use super::schemes::{SchemeHandler, SchemeRegistry};
+use super::{internal_pages, mime};
+use crate::network::{
+    ConnectionHint, NetworkConfig, NetworkCore, NetworkError, NetworkProgress, StoragePartition,
+    TlsInfo,
+};
+use crate::platform::{base64, io};
+use anyhow::{Context, Result, anyhow};
 use hyper::StatusCode;
-use std::{fmt, rc::Rc};
+use std::{fmt, path::PathBuf, rc::Rc, sync::Arc};
 use url::Url;
 
-/// Unified resource loader for `resource:///` and HTTP/HTTPS URLs
+/// Unified resource loader for HTTP/HTTPS URLs and the schemes in a [`SchemeRegistry`]
+/// (`resource:///`, `file://`, `data:` and those added by extensions)
 pub struct BrowserResourceLoader {
     network: Option<Rc<NetworkCore>>,
+    schemes: SchemeRegistry,
     immediate_pool: Vec<BrowserNetworkMessage>,
 }
 
@@ -14,40 +22,161 @@ impl BrowserResourceLoader {
     pub fn new(network: Option<Rc<NetworkCore>>) -> Self {
         Self {
             network,
+            schemes: SchemeRegistry::new(),
             immediate_pool: vec![],
         }
     }
 
+    /// ネットワークに出さずに読むスキーム
+    pub fn schemes(&self) -> &SchemeRegistry {
+        &self.schemes
+    }
+
+    /// scheme の URL を handler で読むようにする（[`SchemeRegistry::register`]）
+    pub fn register_scheme(&mut self, scheme: &str, handler: Arc<dyn SchemeHandler>) -> Result<()> {
+        self.schemes.register(scheme, handler)
+    }
+
     /// 非同期 fetch: URL と ID を送信するだけ
-    pub fn fetch_async(&mut self, url: Url, id: usize) {
-        if url.scheme() == ("resource") {
-            let data = ResourceURI::load(url.as_ref());
+    ///
+    /// partition は Cookie とキャッシュの保存先（プライベートタブなら Private）。
+    /// site_for_cookies はサブリソースならそれを読み込む文書の URL。
+    pub fn fetch_async(
+        &mut self,
+        url: Url,
+        id: usize,
+        bypass_cache: bool,
+        partition: StoragePartition,
+        site_for_cookies: Option<Url>,
+    ) {
+        if let Some(response) = self.load_local(&url) {
             let msg = BrowserNetworkMessage {
                 id,
-                response: data
-                    .map(|data| BrowserResponse {
-                        url: url.to_string(),
-                        status: StatusCode::OK,
-                        body: data,
-                        headers: vec![],
-                    })
-                    .map_err(BrowserNetworkError::AnyhowError),
+                response: response.map_err(BrowserNetworkError::AnyhowError),
             };
             self.immediate_pool.push(msg);
         } else if let Some(net) = &self.network {
-            net.fetch_async(url.to_string(), id);
+            net.fetch_async(
+                url.to_string(),
+                id,
+                bypass_cache,
+                partition,
+                site_for_cookies,
+            );
+        }
+    }
+
+    /// fetch_async に request_headers を付けたもの（スクリプトの CORS の要求など）
+    ///
+    /// ローカルの URL にはヘッダーは使わない。
+    pub fn fetch_with_headers(
+        &mut self,
+        url: Url,
+        id: usize,
+        bypass_cache: bool,
+        partition: StoragePartition,
+        site_for_cookies: Option<Url>,
+        request_headers: Vec<(&'static str, String)>,
+    ) {
+        if self.schemes.handles(&url) || request_headers.is_empty() {
+            return self.fetch_async(url, id, bypass_cache, partition, site_for_cookies);
+        }
+        if let Some(net) = &self.network {
+            net.fetch_with_headers(
+                url.to_string(),
+                id,
+                bypass_cache,
+                partition,
+                site_for_cookies,
+                request_headers,
+            );
+        }
+    }
+
+    /// url を path にダウンロードする。結果（body は空）は id で try_receive に届く
+    ///
+    /// HTTP/HTTPS なら途切れても続きから取り直す（進み具合も try_receive_progress に届く）。
+    pub fn download(&mut self, url: Url, path: PathBuf, id: usize, partition: StoragePartition) {
+        if let Some(response) = self.load_local(&url) {
+            // 手元にあるものはそのまま書く
+            let response = response.and_then(|mut resp| {
+                std::fs::write(&path, &resp.body)
+                    .with_context(|| format!("Failed to write {:?}", path))?;
+                resp.body.clear();
+                Ok(resp)
+            });
+            self.immediate_pool.push(BrowserNetworkMessage {
+                id,
+                response: response.map_err(BrowserNetworkError::AnyhowError),
+            });
+        } else if let Some(net) = &self.network {
+            net.download(url.to_string(), path, id, partition);
+        }
+    }
+
+    /// ネットワークに出さずにここで読む URL（[`SchemeRegistry`] のスキーム）なら読んで返す
+    fn load_local(&self, url: &Url) -> Option<Result<BrowserResponse>> {
+        let response = self.schemes.load(url)?.map(|response| BrowserResponse {
+            url: url.to_string(),
+            status: StatusCode::OK,
+            body: response.body,
+            headers: response
+                .mime
+                .map(|mime| vec![("content-type".to_string(), mime)])
+                .unwrap_or_default(),
+            tls: None,
+        });
+        Some(response)
+    }
+
+    /// ネットワークを通さずに生成した内容を id の結果として返す（内部ページ用）
+    pub fn respond(&mut self, id: usize, url: Url, body: Result<Vec<u8>>) {
+        self.immediate_pool.push(BrowserNetworkMessage {
+            id,
+            response: body
+                .map(|body| BrowserResponse {
+                    url: url.to_string(),
+                    status: StatusCode::OK,
+                    body,
+                    headers: vec![("content-type".to_string(), "text/html".to_string())],
+                    tls: None,
+                })
+                .map_err(BrowserNetworkError::AnyhowError),
+        });
+    }
+
+    /// ネットワーク層の設定を差し替える
+    pub fn set_network_config(&self, config: NetworkConfig) {
+        if let Some(net) = &self.network {
+            net.set_network_config(config);
+        }
+    }
+
+    /// origin への名前解決や接続を裏で済ませておく（結果は届かない）
+    pub fn hint(&self, origin: &Url, hint: ConnectionHint, partition: StoragePartition) {
+        if let Some(net) = &self.network {
+            net.hint(origin.to_string(), hint, partition);
+        }
+    }
+
+    /// partition の Cookie とキャッシュを捨てる
+    pub fn clear_partition(&self, partition: StoragePartition) {
+        if let Some(net) = &self.network {
+            net.clear_partition(partition);
+        }
+    }
+
+    /// 完了していない fetch を取り消す。取り消した fetch の結果は届かない
+    pub fn cancel(&mut self, id: usize) {
+        self.immediate_pool.retain(|msg| msg.id != id);
+        if let Some(net) = &self.network {
+            net.cancel(id);
         }
     }
 
     pub fn fetch_blocking(&self, url: Url) -> Result<BrowserResponse> {
-        if url.scheme() == ("resource") {
-            let data = ResourceURI::load(url.as_ref());
-            data.map(|data| BrowserResponse {
-                url: url.to_string(),
-                status: StatusCode::OK,
-                body: data,
-                headers: vec![],
-            })
+        if let Some(response) = self.load_local(&url) {
+            response
         } else if let Some(net) = &self.network {
             net.fetch_blocking(url.as_str())
                 .map(|resp| BrowserResponse {
@@ -55,6 +184,7 @@ impl BrowserResourceLoader {
                     status: resp.status,
                     body: resp.body,
                     headers: resp.headers,
+                    tls: resp.tls,
                 })
                 .map_err(|e| anyhow!("NetworkError: {}", e))
         } else {
@@ -62,6 +192,14 @@ impl BrowserResourceLoader {
         }
     }
 
+    /// UIスレッドから呼ぶ: 受信中のリクエストの進み具合を取り込む
+    pub fn try_receive_progress(&mut self) -> Vec<NetworkProgress> {
+        self.network
+            .as_ref()
+            .map(|net| net.try_receive_progress())
+            .unwrap_or_default()
+    }
+
     /// UIスレッドから呼ぶ: 受信済みネットワーク結果を取り込む
     pub fn try_receive(&mut self) -> Vec<BrowserNetworkMessage> {
         let mut msgs = if let Some(net) = &self.network {
@@ -76,6 +214,7 @@ impl BrowserResourceLoader {
                             status: resp.status,
                             body: resp.body,
                             headers: resp.headers,
+                            tls: resp.tls,
                         })
                         .map_err(BrowserNetworkError::NetworkError),
                 })
@@ -95,6 +234,8 @@ pub struct BrowserResponse {
     pub status: StatusCode,
     pub body: Vec<u8>,
     pub headers: Vec<(String, String)>,
+    /// https で受け取ったときの TLS の接続
+    pub tls: Option<Arc<TlsInfo>>,
 }
 
 /// ネットワーク結果を UI スレッドで受け取るためのラッパー
@@ -106,6 +247,8 @@ pub struct BrowserNetworkMessage {
 #[derive(Debug)]
 pub enum BrowserNetworkError {
     NetworkError(NetworkError),
+    /// サーバーが中身のないエラー（4xx / 5xx）を返した
+    HttpStatus(StatusCode),
     AnyhowError(anyhow::Error),
 }
 
@@ -113,6 +256,7 @@ impl fmt::Display for BrowserNetworkError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::NetworkError(ne) => write!(f, "{ne}"),
+            Self::HttpStatus(status) => write!(f, "HTTP {status}"),
             Self::AnyhowError(ae) => write!(f, "{ae}"),
         }
     }
@@ -123,7 +267,6 @@ pub struct ResourceURI;
 
 impl ResourceURI {
     pub fn load(url: &str) -> Result<Vec<u8>, anyhow::Error> {
-        use crate::platform::io;
         if let Some(path) = url.strip_prefix("resource:///") {
             io::load_resource(path)
         } else {
@@ -131,3 +274,96 @@ impl ResourceURI {
         }
     }
 }
+
+/// file:// 専用
+pub struct FileURI;
+
+impl FileURI {
+    /// ファイルを読み、(MIME タイプ, 中身) を返す
+    ///
+    /// ディレクトリなら一覧の HTML を返す。
+    pub fn load(url: &Url) -> Result<(String, Vec<u8>), anyhow::Error> {
+        let path = url
+            .to_file_path()
+            .map_err(|_| anyhow!("Not a local file: {}", url))?;
+        match io::read_local(&path)? {
+            io::LocalEntry::File(data) => {
+                let mime = path
+                    .extension()
+                    .and_then(|extension| mime::mime_for_extension(&extension.to_string_lossy()))
+                    .unwrap_or_else(|| mime::sniff(&data));
+                Ok((mime.to_string(), data))
+            }
+            io::LocalEntry::Directory(entries) => {
+                // 一覧のリンクはディレクトリの中を指すようにする
+                let mut dir = url.clone();
+                if !dir.path().ends_with('/') {
+                    dir.set_path(&format!("{}/", dir.path()));
+                }
+                let html = internal_pages::directory_listing(&dir, &entries);
+                Ok(("text/html".to_string(), html.into_bytes()))
+            }
+        }
+    }
+}
+
+/// data: URL（RFC 2397）専用
+pub struct DataURI;
+
+impl DataURI {
+    /// data: URL を読み、(MIME タイプ, 中身) を返す
+    ///
+    /// MIME タイプを省略したものは `text/plain;charset=US-ASCII` として扱う。
+    pub fn load(url: &str) -> Result<(String, Vec<u8>), anyhow::Error> {
+        let Some(rest) = url.strip_prefix("data:") else {
+            return Err(anyhow!("Unsupported scheme: {}", url));
+        };
+        // フラグメントは中身に含めない
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (meta, data) = rest
+            .split_once(',')
+            .ok_or_else(|| anyhow!("Malformed data: URL"))?;
+
+        let mut meta = meta.trim().to_string();
+        let mut is_base64 = false;
+        if let Some((head, last)) = meta.rsplit_once(';')
+            && last.trim().eq_ignore_ascii_case("base64")
+        {
+            meta.truncate(head.len());
+            is_base64 = true;
+        }
+        let mime = match meta.split_once(';') {
+            _ if meta.is_empty() => "text/plain;charset=US-ASCII".to_string(),
+            Some(("", params)) => format!("text/plain;{params}"),
+            Some((essence, params)) => format!("{};{params}", essence.to_ascii_lowercase()),
+            None => meta.to_ascii_lowercase(),
+        };
+
+        let data = percent_decode(data.as_bytes());
+        let data = if is_base64 {
+            base64::decode(&data).ok_or_else(|| anyhow!("Invalid base64 in data: URL"))?
+        } else {
+            data
+        };
+        Ok((mime, data))
+    }
+}
+
+pub(crate) fn percent_decode(input: &[u8]) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'%'
+            && let Some(&[hi, lo]) = input.get(i + 1..i + 3)
+            && let (Some(hi), Some(lo)) = (hex(hi), hex(lo))
+        {
+            out.push((hi << 4) | lo);
+            i += 3;
+        } else {
+            out.push(input[i]);
+            i += 1;
+        }
+    }
+    out
+}
@@ -0,0 +1,132 @@
+//! タイマーとタスクのキュー
+//!
+//! BrowserApp が 1 つ持ち、スクリプトのタイマーやブラウザ内部の処理がタスクを積む。
+//! タスク（macrotask）は 1 つずつ取り出し、その間に積まれた microtask は次のタスクより
+//! 先にすべて取り出す。期限の来たタイマーは期限の順（同じなら登録の順）にタスクになる。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// タイマーの最短の遅延
+///
+/// 遅延 0 のタイマーを積み続けるスクリプトで取り出しが終わらなくならないようにする。
+pub const MIN_TIMER_DELAY: Duration = Duration::from_millis(1);
+
+pub type TimerId = u64;
+
+struct Timer<T> {
+    id: TimerId,
+    due: Instant,
+    /// 繰り返しの間隔（setInterval）
+    interval: Option<Duration>,
+    task: T,
+}
+
+pub struct Scheduler<T> {
+    tasks: VecDeque<T>,
+    microtasks: VecDeque<T>,
+    timers: Vec<Timer<T>>,
+    next_id: TimerId,
+}
+
+impl<T: Clone> Default for Scheduler<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Scheduler<T> {
+    pub fn new() -> Self {
+        Self {
+            tasks: VecDeque::new(),
+            microtasks: VecDeque::new(),
+            timers: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn post_task(&mut self, task: T) {
+        self.tasks.push_back(task);
+    }
+
+    pub fn post_microtask(&mut self, task: T) {
+        self.microtasks.push_back(task);
+    }
+
+    /// now から delay 後に task を積む。repeat なら以後 delay ごとに積む
+    pub fn set_timer(&mut self, now: Instant, delay: Duration, repeat: bool, task: T) -> TimerId {
+        let delay = delay.max(MIN_TIMER_DELAY);
+        let id = self.next_id;
+        self.next_id += 1;
+        self.timers.push(Timer {
+            id,
+            due: now + delay,
+            interval: repeat.then_some(delay),
+            task,
+        });
+        id
+    }
+
+    /// タイマーを止める。なければ false
+    pub fn clear_timer(&mut self, id: TimerId) -> bool {
+        let len = self.timers.len();
+        self.timers.retain(|t| t.id != id);
+        self.timers.len() != len
+    }
+
+    /// タスクが f に当たるタイマーと、まだ実行していないタスクをすべて捨てる
+    pub fn cancel_where(&mut self, f: impl Fn(&T) -> bool) {
+        self.timers.retain(|t| !f(&t.task));
+        self.tasks.retain(|t| !f(t));
+        self.microtasks.retain(|t| !f(t));
+    }
+
+    /// 次に実行するタスク（now までに期限の来たタイマーを含む）
+    ///
+    /// 実行中に積まれた microtask はこのタスクの次に取り出される。
+    pub fn next_task(&mut self, now: Instant) -> Option<T> {
+        if let Some(task) = self.microtasks.pop_front() {
+            return Some(task);
+        }
+        self.enqueue_due_timers(now);
+        self.tasks.pop_front()
+    }
+
+    fn enqueue_due_timers(&mut self, now: Instant) {
+        let mut due: Vec<(Instant, TimerId)> = self
+            .timers
+            .iter()
+            .filter(|t| t.due <= now)
+            .map(|t| (t.due, t.id))
+            .collect();
+        // id は登録の順に増える
+        due.sort();
+
+        for (_, id) in due {
+            let Some(index) = self.timers.iter().position(|t| t.id == id) else {
+                continue;
+            };
+            let timer = &mut self.timers[index];
+            self.tasks.push_back(timer.task.clone());
+            match timer.interval {
+                // 遅れても溜まった分をまとめて実行はしない
+                Some(interval) => timer.due = now + interval,
+                None => {
+                    self.timers.remove(index);
+                }
+            }
+        }
+    }
+
+    /// 次に起きるべき時刻（すぐ実行するタスクがあれば now、何もなければ None）
+    pub fn next_wakeup(&self, now: Instant) -> Option<Instant> {
+        if !self.tasks.is_empty() || !self.microtasks.is_empty() {
+            return Some(now);
+        }
+        self.timers.iter().map(|t| t.due).min()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.microtasks.is_empty() && self.timers.is_empty()
+    }
+}
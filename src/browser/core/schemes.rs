@@ -0,0 +1,158 @@
+//! URL のスキームごとの読み込み
+//!
+//! ネットワークに出さずに読む URL は、スキームごとの [`SchemeHandler`] を
+//! [`SchemeRegistry`] に登録して読む。タブの読み込み、音声、画像はどれもここを通すので、
+//! 同じ URL はどこから読んでも同じものになる。
+//!
+//! - `resource:///`: ブラウザに同梱したファイル
+//! - `file://`: 手元のファイル（ディレクトリなら一覧の HTML）
+//! - `data:`: URL に埋め込んだ中身
+//! - `about:` / `orinium://`: 内部ページ。ブラウザの状態から作るので、ブラウザが直接答える
+//! - `http://` / `https://`: ネットワーク
+//!
+//! 拡張機能は自分のスキームを足せる（[`Extension::scheme_handlers`]）が、上のスキームは
+//! 置き換えられない。
+//!
+//! [`Extension::scheme_handlers`]: super::extensions::Extension::scheme_handlers
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use url::Url;
+
+use super::internal_pages::{ABOUT_SCHEME, INTERNAL_SCHEME};
+use super::resource_loader::{DataURI, FileURI, ResourceURI};
+
+/// ブラウザが決めているスキーム
+pub const BUILTIN_SCHEMES: [&str; 7] = [
+    "http",
+    "https",
+    "resource",
+    "file",
+    "data",
+    ABOUT_SCHEME,
+    INTERNAL_SCHEME,
+];
+
+/// スキームの URL を読んだ結果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemeResponse {
+    /// 分からなければ None（読んだ側が中身から推測する）
+    pub mime: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// 1 つのスキームの URL を読むもの
+pub trait SchemeHandler: Send + Sync {
+    fn load(&self, url: &Url) -> Result<SchemeResponse>;
+}
+
+impl<F> SchemeHandler for F
+where
+    F: Fn(&Url) -> Result<SchemeResponse> + Send + Sync,
+{
+    fn load(&self, url: &Url) -> Result<SchemeResponse> {
+        self(url)
+    }
+}
+
+/// スキームと、その URL を読むもの
+#[derive(Clone)]
+pub struct SchemeRegistry {
+    handlers: HashMap<String, Arc<dyn SchemeHandler>>,
+}
+
+impl Default for SchemeRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SchemeRegistry {
+    /// `resource:`、`file:` と `data:` を読めるもの
+    pub fn new() -> Self {
+        let mut handlers: HashMap<String, Arc<dyn SchemeHandler>> = HashMap::new();
+        handlers.insert(
+            "resource".to_string(),
+            Arc::new(|url: &Url| -> Result<SchemeResponse> {
+                Ok(SchemeResponse {
+                    mime: None,
+                    body: ResourceURI::load(url.as_str())?,
+                })
+            }),
+        );
+        handlers.insert(
+            "file".to_string(),
+            Arc::new(|url: &Url| -> Result<SchemeResponse> {
+                let (mime, body) = FileURI::load(url)?;
+                Ok(SchemeResponse {
+                    mime: Some(mime),
+                    body,
+                })
+            }),
+        );
+        handlers.insert(
+            "data".to_string(),
+            Arc::new(|url: &Url| -> Result<SchemeResponse> {
+                let (mime, body) = DataURI::load(url.as_str())?;
+                Ok(SchemeResponse {
+                    mime: Some(mime),
+                    body,
+                })
+            }),
+        );
+        Self { handlers }
+    }
+
+    /// scheme の URL を handler で読むようにする
+    ///
+    /// ブラウザが決めているスキーム（[`BUILTIN_SCHEMES`]）は置き換えられない。
+    pub fn register(&mut self, scheme: &str, handler: Arc<dyn SchemeHandler>) -> Result<()> {
+        let scheme = scheme.to_ascii_lowercase();
+        if BUILTIN_SCHEMES.contains(&scheme.as_str()) {
+            bail!("The {}: scheme is built into the browser", scheme);
+        }
+        // URL のスキームに使える文字だけ（先頭は英字）
+        if !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            || !scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        {
+            bail!("Invalid scheme name {:?}", scheme);
+        }
+        log::info!("Registered handler for the {}: scheme", scheme);
+        self.handlers.insert(scheme, handler);
+        Ok(())
+    }
+
+    /// url をここで読むか（ネットワークにも内部ページにも出さない）
+    pub fn handles(&self, url: &Url) -> bool {
+        self.handlers.contains_key(url.scheme())
+    }
+
+    /// url を読む。ここで読むスキームでなければ None
+    pub fn load(&self, url: &Url) -> Option<Result<SchemeResponse>> {
+        self.handlers
+            .get(url.scheme())
+            .map(|handler| handler.load(url))
+    }
+
+    /// URI か手元のファイルのパスを読む（音声や画像のように URL でもパスでも受け取るもの用）
+    ///
+    /// スキームのないものと `C:\` のようなドライブ文字で始まるものはパスとして扱う。
+    pub fn load_uri(&self, uri: &str) -> Result<Vec<u8>> {
+        let url = match Url::parse(uri) {
+            Ok(url) if url.scheme().len() > 1 => url,
+            _ => {
+                let path =
+                    std::path::absolute(uri).with_context(|| format!("Invalid path {}", uri))?;
+                Url::from_file_path(&path).map_err(|_| anyhow!("Invalid path {}", uri))?
+            }
+        };
+        match self.load(&url) {
+            Some(response) => Ok(response?.body),
+            None => Err(anyhow!("Unsupported scheme: {}", url.scheme())),
+        }
+    }
+}
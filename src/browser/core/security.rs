@@ -0,0 +1,87 @@
+//! URL バーに出す接続の安全性
+//!
+//! https で受け取った文書には鍵を、http で受け取った文書には「保護されていない」を
+//! 出す。鍵を押すと TLS のバージョン、暗号スイートと証明書の詳細を出す。
+
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use url::Url;
+
+use crate::platform::network::http_date;
+use crate::platform::network::tls_info::TlsInfo;
+
+/// 表示中の文書をどう受け取ったか
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Security {
+    /// https で受け取った（キャッシュから読んだときは TLS の情報がない）
+    Secure(Option<Arc<TlsInfo>>),
+    /// http で受け取った
+    NotSecure,
+    /// ネットワークを通していない（内部ページ、file:、data: など）か、まだ何もない
+    #[default]
+    Local,
+}
+
+impl Security {
+    /// url の文書を tls の接続で受け取った
+    pub fn for_document(url: &Url, tls: Option<Arc<TlsInfo>>) -> Self {
+        match url.scheme() {
+            "https" => Self::Secure(tls),
+            "http" => Self::NotSecure,
+            _ => Self::Local,
+        }
+    }
+
+    /// 詳細のポップアップに出す行
+    pub fn details(&self, now: SystemTime) -> Vec<String> {
+        let tls = match self {
+            Self::Secure(Some(tls)) => tls,
+            Self::Secure(None) => {
+                return vec![
+                    "Connection is secure".to_string(),
+                    "Loaded from the cache; connection details are unavailable".to_string(),
+                ];
+            }
+            Self::NotSecure => {
+                return vec![
+                    "Connection is not secure".to_string(),
+                    "Information sent to this site could be read or changed by others".to_string(),
+                ];
+            }
+            Self::Local => return vec!["This page was not loaded over the network".to_string()],
+        };
+
+        let mut lines = vec!["Connection is secure".to_string()];
+        let mut protocol = tls.version.clone();
+        if let Some(cipher) = &tls.cipher_suite {
+            protocol.push_str(&format!(", {cipher}"));
+        }
+        if let Some(alpn) = &tls.alpn {
+            protocol.push_str(&format!(" ({alpn})"));
+        }
+        lines.push(protocol);
+
+        if let Some(leaf) = tls.leaf() {
+            lines.push(format!("Issued to: {}", leaf.common_name()));
+            lines.push(format!("Issued by: {}", leaf.issuer_name()));
+            if let (Some(from), Some(until)) = (leaf.not_before, leaf.not_after) {
+                let expired = if now > until { " (expired)" } else { "" };
+                lines.push(format!(
+                    "Valid from {} to {}{}",
+                    http_date::format(from),
+                    http_date::format(until),
+                    expired
+                ));
+            }
+            if !leaf.dns_names.is_empty() {
+                lines.push(format!("Names: {}", leaf.dns_names.join(", ")));
+            }
+        }
+        // 中間証明書からルートまで
+        for certificate in tls.certificates.iter().skip(1) {
+            lines.push(format!("Chain: {}", certificate.common_name()));
+        }
+        lines
+    }
+}
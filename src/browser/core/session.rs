@@ -0,0 +1,103 @@
+//! セッションの保存と復元
+//!
+//! 開いているタブ（URL, タイトル, スクロール位置）とアクティブなタブを
+//! プロファイルの `session` ファイルに 1 行 1 項目のテキストで保存する。
+//!
+//! ```text
+//! active	1
+//! tab	0	320	https://example.com/	Example Domain
+//! ```
+
+use std::path::Path;
+
+use anyhow::Result;
+use url::Url;
+
+use crate::platform::io;
+
+/// プロファイル内のセッションファイル名
+pub const SESSION_FILE_NAME: &str = "session";
+
+/// 保存された 1 つのタブ
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionTab {
+    pub url: Url,
+    pub title: Option<String>,
+    pub scroll: (f32, f32),
+}
+
+/// 保存されたタブの一覧
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub tabs: Vec<SessionTab>,
+    pub active: usize,
+}
+
+impl Session {
+    pub fn is_empty(&self) -> bool {
+        self.tabs.is_empty()
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = format!("active\t{}\n", self.active);
+        for tab in &self.tabs {
+            // タイトルの中のタブ文字と改行は区切りと紛れるので空白にする
+            let title = tab
+                .title
+                .as_deref()
+                .unwrap_or("")
+                .replace(['\t', '\n', '\r'], " ");
+            out.push_str(&format!(
+                "tab\t{}\t{}\t{}\t{}\n",
+                tab.scroll.0, tab.scroll.1, tab.url, title
+            ));
+        }
+        out
+    }
+
+    /// 読めない行は飛ばす
+    pub fn parse(text: &str) -> Self {
+        let mut session = Self::default();
+
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            match fields.next() {
+                Some("active") => {
+                    session.active = fields.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+                }
+                Some("tab") => {
+                    let scroll_x = fields.next().and_then(|s| s.parse().ok()).unwrap_or(0.0);
+                    let scroll_y = fields.next().and_then(|s| s.parse().ok()).unwrap_or(0.0);
+                    let Some(url) = fields.next().and_then(|s| Url::parse(s).ok()) else {
+                        log::warn!("Skipping invalid session entry: {:?}", line);
+                        continue;
+                    };
+                    let title = fields.next().filter(|t| !t.is_empty()).map(str::to_string);
+
+                    session.tabs.push(SessionTab {
+                        url,
+                        title,
+                        scroll: (scroll_x, scroll_y),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        session.active = session.active.min(session.tabs.len().saturating_sub(1));
+        session
+    }
+
+    /// path から読み込む。ファイルがなければ None
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(path)?;
+        Ok(Some(Self::parse(&text)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        io::write_atomic(path, self.serialize().as_bytes())
+    }
+}
@@ -1,19 +1,104 @@
 use crate::{
-    browser::core::resource_loader::BrowserNetworkError,
-    engine::{html::HtmlNodeType, layouter::types::InfoNode, tree::TreeNode},
+    browser::core::{
+        csp::ContentSecurityPolicy,
+        devtools::{BoxModel, Console, StyleInspection},
+        extensions::ExtensionRegistry,
+        fetch_policy::Initiator,
+        history::{History, HistoryEntry},
+        internal_pages,
+        progress::LoadProgress,
+        reader::{self, ReaderOptions},
+        resource_loader::BrowserNetworkError,
+        security::Security,
+        session::SessionTab,
+    },
+    engine::{
+        accessibility::AccessTree,
+        css::media::ColorScheme,
+        html::{HtmlNodeType, preload::ResourceHint},
+        input::{selection::Selection, spellcheck::SpellChecker, text_edit::TextEdit},
+        layouter::types::{Color, InfoNode},
+        renderer_model::DrawCommand,
+        script::{
+            FetchResponse, ScriptValue, TimerRequest,
+            storage::{SharedStorage, WebStorage},
+        },
+        tree::NodeRef,
+    },
+    network::{StoragePartition, TlsInfo},
+    platform::geolocation::{Position, PositionError},
+    platform::notifications::{NotificationCommand, NotificationEvent},
 };
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use ui_layout::LayoutNode;
 use url::Url;
 
-pub use super::webview::{FetchKind, WebView, WebViewTask};
+use super::autofill::{AutofillField, AutofillProfile};
+use super::passwords::{Credential, SubmittedLogin};
+use super::permissions::{Permission, PermissionRequest};
+use super::visited_links::SharedVisitedLinks;
+use super::webview::metrics::PageLoadMetrics;
+pub use super::webview::{FetchKind, Misspelling, WebView, WebViewTask};
+use super::webview::{LinkNavigation, LinkTarget};
+
+/// タブで開くページの既定値（ユーザー設定の既定フォント、ズーム、配色）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageDefaults {
+    /// 既定のフォント名（None ならシステムの既定フォント）
+    pub font_family: Option<String>,
+    /// 既定の文字の大きさ（CSS px）
+    pub font_size: f32,
+    /// 新しいタブのズーム倍率（Ctrl+0 でもこの倍率に戻る）
+    pub zoom: f32,
+    /// prefers-color-scheme に使う配色
+    pub color_scheme: ColorScheme,
+}
+
+impl Default for PageDefaults {
+    fn default() -> Self {
+        Self {
+            font_family: None,
+            font_size: super::webview::DEFAULT_FONT_SIZE,
+            zoom: 1.0,
+            color_scheme: ColorScheme::Light,
+        }
+    }
+}
 
 pub enum TabTask {
-    Fetch { url: Url, kind: FetchKind },
+    Fetch {
+        url: Url,
+        kind: FetchKind,
+        bypass_cache: bool,
+        /// サブリソースなら表示している文書の URL（SameSite Cookie の判定に使う）
+        site_for_cookies: Option<Url>,
+    },
+    /// オリジンへの名前解決や接続を先に済ませておく
+    Hint(ResourceHint),
     NeedsRedraw,
 }
 
-enum TabError {
+/// 新しいタブで開くリンク
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewTabLink {
+    pub url: Url,
+    /// Referer にするリンク元の文書の URL（`rel="noreferrer"` なら None）
+    pub referrer: Option<Url>,
+    /// 新しいタブから window.opener で見える文書の番号（`rel="opener"` のときだけ）
+    pub opener: Option<u64>,
+}
+
+/// タブがエラーページを表示している理由
+pub enum TabError {
     NetworkError(BrowserNetworkError),
+    /// ページの解析・レイアウト・描画の途中でパニックした（パニックのメッセージ）
+    Crashed(String),
 }
 
 enum TabState {
@@ -27,6 +112,7 @@ enum TabState {
 /// 主な責務:
 /// - 現在表示しているページのタイトルの保持
 /// - ページ内容を扱う WebView の保持
+/// - 戻る / 進むのための履歴の管理
 ///
 /// WebView が「ページそのもの」の状態を管理するのに対し、
 /// Tab は UI 上のタブとしてのメタ情報（タイトルなど）を管理します。
@@ -34,13 +120,51 @@ enum TabState {
 /// TODO:
 /// - ページの状態（Error、loading）の管理を追加
 pub struct Tab {
+    /// このタブを表す、プロセスの中で一意な番号（リモートデバッグのターゲット ID）
+    id: u64,
     title: Option<String>,
     base_url: Option<Url>,
     docment_url: Option<Url>,
+    /// 文書を受け取った TLS の接続
+    tls: Option<Arc<TlsInfo>>,
+    /// 文書の応答のヘッダーの Content Security Policy（WebView に渡すまで持つ）
+    csp: ContentSecurityPolicy,
     webview: Option<WebView>,
+    history: History,
     state: TabState,
+    /// 強制再読み込み中（このページの fetch はキャッシュを使わない）
+    bypass_cache: bool,
+    progress: LoadProgress,
+    /// ネットワークを通さずに WebView に渡す HTML（エラーページ、リーダーモード）
+    inline_html: Option<String>,
+    /// リーダーモード中は元のページの WebView をここに取っておく
+    reader_original: Option<WebView>,
+    defaults: PageDefaults,
+    /// プライベートタブ（閲覧履歴とセッションに残さず、Cookie とキャッシュを分ける）
+    private: bool,
+    /// ページのスクリプトの localStorage（ブラウザ全体で共有する）
+    local_storage: Option<SharedStorage>,
+    /// ページのスクリプトの sessionStorage（タブごとにメモリに持つ）
+    session_storage: SharedStorage,
+    /// 入力欄の綴りを調べる辞書（ブラウザ全体で共有する）
+    spell_checker: Option<Arc<SpellChecker>>,
+    /// :visited に当てる訪れたリンクの記録（プライベートタブはプライベートタブどうしで共有する）
+    visited_links: Option<SharedVisitedLinks>,
+    /// ページに CSS とスクリプトを差し込む拡張機能（ブラウザ全体で共有する）
+    extensions: Option<Arc<ExtensionRegistry>>,
+    /// 今の文書を開いてから拡張機能が止めたリクエストの数
+    blocked_requests: usize,
+    /// 今の文書の Referer にするリンク元の文書の URL
+    referrer: Option<Url>,
+    /// このタブを開いたリンクのある文書の番号（window.opener）
+    opener: Option<u64>,
+    /// 裏に回った時刻（表のタブは None）
+    hidden_since: Option<Instant>,
 }
 
+/// 次に作る Tab の id
+static NEXT_TAB_ID: AtomicU64 = AtomicU64::new(1);
+
 impl Default for Tab {
     fn default() -> Self {
         Self::new()
@@ -50,12 +174,188 @@ impl Default for Tab {
 impl Tab {
     pub fn new() -> Self {
         Self {
+            id: NEXT_TAB_ID.fetch_add(1, Ordering::Relaxed),
             title: None,
             base_url: None,
             docment_url: None,
+            tls: None,
+            csp: ContentSecurityPolicy::new(),
             webview: None,
+            history: History::new(),
             state: TabState::Loading,
+            bypass_cache: false,
+            progress: LoadProgress::new(),
+            inline_html: None,
+            reader_original: None,
+            defaults: PageDefaults::default(),
+            private: false,
+            local_storage: None,
+            session_storage: WebStorage::new().shared(),
+            spell_checker: None,
+            visited_links: None,
+            extensions: None,
+            blocked_requests: 0,
+            referrer: None,
+            opener: None,
+            hidden_since: None,
+        }
+    }
+
+    /// プライベートタブを作る
+    pub fn new_private() -> Self {
+        Self {
+            private: true,
+            ..Self::new()
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// 表か裏かを記録する。裏に回った時刻は、表に戻るまで最初のものを残す
+    pub fn set_hidden(&mut self, hidden: bool, now: Instant) {
+        if !hidden {
+            self.hidden_since = None;
+        } else if self.hidden_since.is_none() {
+            self.hidden_since = Some(now);
+        }
+    }
+
+    pub fn hidden_since(&self) -> Option<Instant> {
+        self.hidden_since
+    }
+
+    /// 描くためのレイアウトを捨てる。捨てたら true
+    ///
+    /// スクロール位置は履歴項目にも記録するので、捨てている間もセッションに残る。
+    pub fn discard_render_data(&mut self) -> bool {
+        self.save_page_state();
+        self.webview
+            .as_mut()
+            .is_some_and(WebView::discard_render_data)
+    }
+
+    /// 捨てたレイアウトを作り直す。作り直したら true
+    pub fn restore_render_data(&mut self) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(WebView::restore_render_data)
+    }
+
+    /// これから開くページのスクリプトが使う localStorage を設定する
+    pub fn set_local_storage(&mut self, storage: SharedStorage) {
+        self.local_storage = Some(storage);
+        // 読み込み中のページも HTML が届く前ならこれを使う
+        if let Some(webview) = self.webview.as_mut() {
+            webview.set_storage(
+                self.local_storage.clone(),
+                Some(self.session_storage.clone()),
+            );
+        }
+    }
+
+    /// :visited に当てる訪れたリンクの記録を設定する
+    pub fn set_visited_links(&mut self, visited_links: SharedVisitedLinks) {
+        for wv in self
+            .webview
+            .iter_mut()
+            .chain(self.reader_original.iter_mut())
+        {
+            wv.set_visited_links(Some(visited_links.clone()));
+        }
+        self.visited_links = Some(visited_links);
+    }
+
+    /// 入力欄の綴りを調べる辞書を設定する（None ならスペルチェックしない）
+    pub fn set_spell_checker(&mut self, spell_checker: Option<Arc<SpellChecker>>) {
+        for wv in self
+            .webview
+            .iter_mut()
+            .chain(self.reader_original.iter_mut())
+        {
+            wv.set_spell_checker(spell_checker.clone());
         }
+        self.spell_checker = spell_checker;
+    }
+
+    /// これから開くページに CSS とスクリプトを差し込む拡張機能を設定する
+    pub fn set_extensions(&mut self, extensions: Option<Arc<ExtensionRegistry>>) {
+        for wv in self
+            .webview
+            .iter_mut()
+            .chain(self.reader_original.iter_mut())
+        {
+            wv.set_extensions(extensions.clone());
+        }
+        self.extensions = extensions;
+    }
+
+    /// 拡張機能がこのページのリクエストを止めたことを記録する
+    pub fn record_blocked_request(&mut self) {
+        self.blocked_requests += 1;
+    }
+
+    /// 今の文書を開いてから拡張機能が止めたリクエストの数
+    pub fn blocked_requests(&self) -> usize {
+        self.blocked_requests
+    }
+
+    /// このタブのリクエストが使う Cookie とキャッシュの保存先
+    pub fn storage_partition(&self) -> StoragePartition {
+        if self.private {
+            StoragePartition::Private
+        } else {
+            StoragePartition::Default
+        }
+    }
+
+    /// ページの既定のフォント、ズーム、配色を設定する
+    ///
+    /// フォントと配色は開いているページにもすぐ反映する。ズームは利用者が変えていない
+    /// （前の既定値のままの）ページだけ新しい既定値にする。
+    pub fn set_page_defaults(&mut self, defaults: PageDefaults) {
+        let old_zoom = self.defaults.zoom;
+        for wv in self
+            .webview
+            .iter_mut()
+            .chain(self.reader_original.iter_mut())
+        {
+            wv.set_default_font(defaults.font_family.as_deref(), defaults.font_size);
+            wv.set_color_scheme(defaults.color_scheme);
+            if (wv.user_zoom() - old_zoom).abs() < f32::EPSILON {
+                wv.set_zoom(defaults.zoom);
+            }
+        }
+        self.defaults = defaults;
+    }
+
+    /// 既定のフォント、ズーム、配色を設定した WebView を作る
+    fn new_webview(&self) -> WebView {
+        let mut webview = WebView::new();
+        webview.set_default_font(
+            self.defaults.font_family.as_deref(),
+            self.defaults.font_size,
+        );
+        webview.set_zoom(self.defaults.zoom);
+        webview.set_color_scheme(self.defaults.color_scheme);
+        webview.set_storage(
+            self.local_storage.clone(),
+            Some(self.session_storage.clone()),
+        );
+        webview.set_spell_checker(self.spell_checker.clone());
+        webview.set_visited_links(self.visited_links.clone());
+        webview.set_extensions(self.extensions.clone());
+        webview
+    }
+
+    /// ページの背景の色（ページを開いていなければ None）
+    pub fn canvas_color(&self) -> Option<Color> {
+        self.webview.as_ref().map(WebView::canvas_color)
     }
 
     /// Tab 内の状態を 1 ステップ進める
@@ -64,6 +364,7 @@ impl Tab {
     /// - 発生した Task を BrowserApp に返す
     pub fn tick(&mut self) -> Vec<TabTask> {
         let mut tasks = Vec::new();
+        let mut inline_html = None;
         let Some(wv) = self.webview.as_mut() else {
             return tasks;
         };
@@ -72,14 +373,34 @@ impl Tab {
             match task {
                 WebViewTask::Fetch { url, kind } => {
                     log::info!("Fetch requested in Tab: url={}", url);
-                    tasks.push(TabTask::Fetch { url, kind });
+                    self.progress.request_started();
+                    tasks.push(TabTask::Fetch {
+                        url,
+                        kind,
+                        bypass_cache: self.bypass_cache,
+                        site_for_cookies: self.docment_url.clone(),
+                    });
                 }
                 WebViewTask::AskTabHtml => {
+                    // エラーページは用意した HTML をそのまま渡す
+                    if let Some(html) = self.inline_html.take() {
+                        inline_html = Some(html);
+                        continue;
+                    }
+                    let Some(url) = self.docment_url.clone() else {
+                        log::warn!("WebView asked for HTML without a document URL");
+                        continue;
+                    };
+                    self.progress.request_started();
+                    self.blocked_requests = 0;
                     tasks.push(TabTask::Fetch {
-                        url: self.docment_url.as_ref().unwrap().clone(),
+                        url,
                         kind: FetchKind::Html,
+                        bypass_cache: self.bypass_cache,
+                        site_for_cookies: None,
                     });
                 }
+                WebViewTask::Hint(hint) => tasks.push(TabTask::Hint(hint)),
             }
         }
 
@@ -87,9 +408,30 @@ impl Tab {
             tasks.push(TabTask::NeedsRedraw);
         }
 
+        if let Some(html) = inline_html {
+            self.on_fetch_succeeded_html(html);
+        }
+
+        // スクリプトなどで書き換えられたタイトルを取り込む
+        self.sync_title();
+
         tasks
     }
 
+    /// BrowserApp から fetch の受信状況を通知
+    pub fn on_fetch_progress(&mut self, fetch_id: usize, received: u64, total: Option<u64>) {
+        self.progress.bytes_received(fetch_id, received, total);
+    }
+
+    /// BrowserApp から fetch の完了（成功・失敗とも）を通知
+    pub fn on_fetch_finished(&mut self, fetch_id: usize) {
+        self.progress.request_finished(fetch_id);
+    }
+
+    pub fn progress(&self) -> &LoadProgress {
+        &self.progress
+    }
+
     /// BrowserApp から CSS fetch 完了を通知
     pub fn on_css_fetched(&mut self, css: String) {
         log::info!("CSS fetched in Tab");
@@ -100,71 +442,574 @@ impl Tab {
 
     /// BrowserApp からの HTML fetch 完了を通知
     pub fn on_fetch_succeeded_html(&mut self, html: String) {
-        let Some(wv) = self.webview.as_mut() else {
+        let (Some(wv), Some(url)) = (self.webview.as_mut(), self.docment_url.clone()) else {
             return;
         };
 
-        wv.on_html_fetched(html, self.docment_url.as_ref().unwrap().clone());
-        self.title = wv.title().cloned();
-        let base_url = wv.base_url().unwrap().clone();
-        log::info!("HTML fetched, base_url={}", base_url);
-        self.base_url = Some(base_url);
-
-        if let TabState::Error(TabError::NetworkError(err), url_opt) = &self.state {
-            let error_message = match url_opt {
-                Some(url) => format!("Failed to load {}: {}", url, err),
-                None => format!("Failed to load page: {}", err),
-            };
-
-            let error_message_element = wv
-                .document_info()
-                .unwrap()
-                .dom
-                .get_elements_by_class_name("error-message");
-            let error_message_element = error_message_element.first().unwrap();
-            let new_child = TreeNode::new(HtmlNodeType::Text(error_message));
-            TreeNode::replace_child(error_message_element, 0, new_child);
-
-            // Update page to show error message
-            // This is a stub implementation for now as you can see in WebView.update_page().
-            wv.update_page();
-        } else {
+        wv.set_content_security_policy(std::mem::take(&mut self.csp));
+        wv.on_html_fetched(html, url);
+        self.base_url = wv.base_url().cloned();
+        log::info!("HTML fetched, base_url={:?}", self.base_url);
+
+        if !matches!(self.state, TabState::Error(..)) {
             self.state = TabState::Loaded;
         }
+        self.sync_title();
     }
 
-    pub fn on_fetch_succeeded_css(&mut self, css: String) {
-        let Some(wv) = self.webview.as_mut() else {
+    /// 届いた HTML を組み立てる前に、中に見つけたものを先読みする
+    ///
+    /// on_fetch_succeeded_html の前に呼び、返した fetch はすぐに要求する。
+    pub fn start_preloads(&mut self, html: &str) -> Vec<TabTask> {
+        let (Some(wv), Some(url)) = (self.webview.as_mut(), self.docment_url.clone()) else {
+            return Vec::new();
+        };
+
+        wv.set_content_security_policy(self.csp.clone());
+        let mut tasks = Vec::new();
+        for task in wv.start_preloads(html, &url) {
+            match task {
+                WebViewTask::Fetch {
+                    url: preload_url,
+                    kind,
+                } => {
+                    log::info!("Preload requested in Tab: url={}", preload_url);
+                    self.progress.request_started();
+                    tasks.push(TabTask::Fetch {
+                        url: preload_url,
+                        kind,
+                        bypass_cache: self.bypass_cache,
+                        site_for_cookies: Some(url.clone()),
+                    });
+                }
+                WebViewTask::Hint(hint) => tasks.push(TabTask::Hint(hint)),
+                WebViewTask::AskTabHtml => {}
+            }
+        }
+        tasks
+    }
+
+    /// 先読みしたものが届いた（読めなかったときは body が空）
+    pub fn on_preload_fetched(&mut self, url: &Url, body: String) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.on_preload_fetched(url, body);
+        }
+    }
+
+    /// デコードが済んだ画像を文書に渡す。文書か `<iframe>` の文書が使っていれば true を返す
+    pub fn on_image_decoded(&mut self, url: &Url, image: &Arc<RgbaImage>) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.on_image_decoded(url, image))
+    }
+
+    /// 文書と `<iframe>` の文書が表示している画像の URL
+    pub fn image_urls(&self) -> Vec<Url> {
+        self.webview
+            .as_ref()
+            .map(WebView::image_urls)
+            .unwrap_or_default()
+    }
+
+    /// frame は `<iframe>` の文書の番号（None ならタブの文書）
+    pub fn on_fetch_succeeded_css(&mut self, frame: Option<u64>, css: String) {
+        let Some(wv) = self.webview_mut(frame) else {
             return;
         };
 
         wv.on_css_fetched(css);
     }
 
+    /// frame（None ならタブの文書）の WebView
+    fn webview_mut(&mut self, frame: Option<u64>) -> Option<&mut WebView> {
+        let wv = self.webview.as_mut()?;
+        match frame {
+            Some(frame) => wv.frame_mut(frame),
+            None => Some(wv),
+        }
+    }
+
+    /// `<iframe>` の文書 frame の HTML が届いた
+    pub fn on_frame_html_fetched(&mut self, frame: u64, html: String, csp: ContentSecurityPolicy) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.on_frame_html_fetched(frame, html, csp);
+        }
+    }
+
+    /// `<iframe>` の文書 frame が読めなかった。iframe の中にエラーページを表示する
+    pub fn on_frame_fetch_failed(&mut self, frame: u64, err: BrowserNetworkError, url: Url) {
+        log::warn!("Failed to load frame {}: {}", url, err);
+        let html = internal_pages::error_page(&url, &err);
+        self.on_frame_html_fetched(frame, html, ContentSecurityPolicy::new());
+    }
+
     /// Display error page on fetch failure
+    ///
+    /// エラーページは失敗した URL の文書として表示する。履歴には積まないので、
+    /// 戻ったり再読み込みしたりすると失敗した URL を読み直す。
     pub fn on_fetch_failed(&mut self, err: BrowserNetworkError, failed_url: Url) {
-        self.navigate("resource:///error.html".parse().unwrap());
+        let html = internal_pages::error_page(&failed_url, &err);
+        self.load(failed_url.clone());
+        self.inline_html = Some(html);
         self.state = TabState::Error(TabError::NetworkError(err), Some(failed_url));
     }
 
+    /// f を実行する。パニックしたらこのタブだけをクラッシュしたことにし、R::default() を返す
+    ///
+    /// ページの解析・レイアウト・描画でパニックしても、ほかのタブとブラウザは動き続ける。
+    /// クラッシュしたタブには再読み込みのボタンのあるページを表示する。
+    pub fn isolate<R: Default>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+            Ok(result) => result,
+            Err(payload) => {
+                self.crash(panic_message(payload.as_ref()));
+                R::default()
+            }
+        }
+    }
+
+    /// クラッシュしたページを捨て、代わりにクラッシュしたことを伝えるページを表示する
+    ///
+    /// エラーページと同じく、クラッシュしたページの URL の文書として表示し、履歴には積まない。
+    fn crash(&mut self, message: String) {
+        log::error!("Tab {} crashed: {}", self.id, message);
+        // 壊れたページのスクリプトを止める。止めるところでまたパニックしても捨てるだけ
+        if panic::catch_unwind(AssertUnwindSafe(|| self.shutdown_scripts())).is_err() {
+            log::warn!("Failed to shut down the scripts of crashed tab {}", self.id);
+        }
+        self.reader_original = None;
+
+        let url = self
+            .docment_url
+            .clone()
+            .or_else(|| self.history.current().map(|entry| entry.url.clone()));
+        // クラッシュを伝えるページでまたクラッシュしたら、何も表示しない
+        let crashed_again = matches!(self.state, TabState::Error(TabError::Crashed(_), _));
+        let Some(url) = url.filter(|_| !crashed_again) else {
+            self.webview = None;
+            self.state = TabState::Error(TabError::Crashed(message), None);
+            return;
+        };
+
+        let html = internal_pages::crash_page(&url, &message);
+        self.load(url.clone());
+        self.inline_html = Some(html);
+        self.state = TabState::Error(TabError::Crashed(message), Some(url));
+    }
+
+    /// CSS が読めなかった。その CSS なしでページを表示する
+    pub fn on_fetch_failed_css(&mut self, frame: Option<u64>, err: BrowserNetworkError, url: Url) {
+        log::warn!("Failed to load stylesheet {}: {}", url, err);
+        self.on_fetch_succeeded_css(frame, String::new());
+    }
+
+    /// 外部スクリプトが届いた
+    pub fn on_fetch_succeeded_script(&mut self, frame: Option<u64>, url: Url, js: String) {
+        let Some(wv) = self.webview_mut(frame) else {
+            return;
+        };
+
+        wv.on_script_fetched(&url, js);
+    }
+
+    /// スクリプトが読めなかった。そのスクリプトは飛ばして残りを実行する
+    pub fn on_fetch_failed_script(
+        &mut self,
+        frame: Option<u64>,
+        err: BrowserNetworkError,
+        url: Url,
+    ) {
+        log::warn!("Failed to load script {}: {}", url, err);
+        self.on_fetch_succeeded_script(frame, url, String::new());
+    }
+
+    /// スクリプトの fetch / XMLHttpRequest の応答が届いた
+    ///
+    /// 要求した文書をもう表示していなければ捨てる。
+    pub fn on_script_request_done(
+        &mut self,
+        document: u64,
+        request: u32,
+        result: Result<FetchResponse, String>,
+    ) {
+        if let Some(wv) = self.webview_mut(Some(document)) {
+            wv.on_script_request_done(request, result);
+        }
+    }
+
+    /// 表示している文書の番号（スクリプトのタイマーの宛先）
+    pub fn document_id(&self) -> Option<u64> {
+        self.webview.as_ref().map(WebView::document_id)
+    }
+
+    /// 表示している文書か、その `<iframe>` の中の文書の番号が document か
+    pub fn has_document(&self, document: u64) -> bool {
+        self.webview
+            .as_ref()
+            .is_some_and(|wv| wv.frame(document).is_some())
+    }
+
+    /// document の文書（`<iframe>` の中の文書も探す）。sandbox で opaque になった
+    /// オリジンはそのまま
+    pub fn initiator_of(&self, document: u64) -> Option<Initiator> {
+        self.webview.as_ref()?.frame(document)?.initiator()
+    }
+
+    /// kind の fetch を要求した文書（同一オリジンポリシーの判定に使う）
+    ///
+    /// タブの文書そのものの読み込みなら None。`<iframe>` の文書の読み込みはタブの
+    /// 文書が要求したものとして扱う。
+    pub fn initiator(&self, kind: &FetchKind) -> Option<Initiator> {
+        match kind {
+            FetchKind::Html => None,
+            FetchKind::ScriptRequest { document, .. } => self.initiator_of(*document),
+            FetchKind::Frame { kind, .. } if matches!(**kind, FetchKind::Html) => {
+                self.docment_url.clone().map(Initiator::new)
+            }
+            FetchKind::Frame { frame, kind } => match **kind {
+                FetchKind::ScriptRequest { document, .. } => self.initiator_of(document),
+                _ => self.initiator_of(*frame),
+            },
+            FetchKind::Css | FetchKind::Script | FetchKind::Preload(_) | FetchKind::Image => {
+                self.docment_url.clone().map(Initiator::new)
+            }
+        }
+    }
+
+    /// スクリプトのタイマーの操作を、宛先の文書の番号と一緒に受け取る
+    pub fn take_timer_requests(&mut self) -> Vec<(u64, TimerRequest)> {
+        self.webview
+            .as_mut()
+            .map(WebView::take_all_timer_requests)
+            .unwrap_or_default()
+    }
+
+    /// `<meta http-equiv="refresh">` の待ち時間と移動先を、文書の番号と一緒に受け取る
+    pub fn take_refresh(&mut self) -> Option<(u64, Duration, Option<Url>)> {
+        let webview = self.webview.as_mut()?;
+        let (delay, url) = webview.take_refresh()?;
+        Some((webview.document_id(), delay, url))
+    }
+
+    /// document がまだ表示中なら、`<meta http-equiv="refresh">` の移動先
+    /// （None なら同じページ）を読み込む
+    pub fn follow_refresh(&mut self, document: u64, url: Option<Url>) {
+        if self.document_id() != Some(document) {
+            return;
+        }
+        match url {
+            Some(url) => {
+                log::info!("Refreshing to {}", url);
+                self.move_to(url.as_str());
+            }
+            None => self.reload(false),
+        }
+    }
+
+    pub fn fire_timer(&mut self, document: u64, id: u32) {
+        if let Some(wv) = self.webview_mut(Some(document)) {
+            wv.fire_timer(id);
+        }
+    }
+
+    /// ページのスクリプトを止めて realm を捨てる（タブを閉じる前にも呼ぶ）
+    pub fn shutdown_scripts(&mut self) {
+        for wv in self
+            .webview
+            .iter_mut()
+            .chain(self.reader_original.iter_mut())
+        {
+            wv.shutdown_scripts();
+        }
+    }
+
+    /// リーダーモードを切り替える。本文を抽出できなければ何もせず false
+    ///
+    /// リーダーモードのページは同じ URL の文書として表示し、履歴には積まない。
+    /// 元のページは取っておき、戻すときは読み直さない。
+    pub fn toggle_reader_mode(&mut self, options: &ReaderOptions) -> bool {
+        if let Some(original) = self.reader_original.take() {
+            self.webview = Some(original);
+            return true;
+        }
+
+        let Some(wv) = self.webview.as_ref() else {
+            return false;
+        };
+        let (Some(info), Some(base_url)) = (wv.document_info(), wv.base_url()) else {
+            return false;
+        };
+        let title = wv.title().cloned().unwrap_or_default();
+        let Some(article) = reader::extract(&info.dom, &title, base_url) else {
+            log::info!("No article found for reader mode");
+            return false;
+        };
+
+        let mut reader_view = self.new_webview();
+        reader_view.set_zoom(wv.user_zoom());
+        reader_view.navigate();
+        self.reader_original = self.webview.replace(reader_view);
+        self.inline_html = Some(reader::render(&article, options));
+        true
+    }
+
+    pub fn is_reader_mode(&self) -> bool {
+        self.reader_original.is_some()
+    }
+
+    /// エラーページを表示している場合の (エラー, 失敗した URL)
+    pub fn load_error(&self) -> Option<(&TabError, Option<&Url>)> {
+        match &self.state {
+            TabState::Error(err, url) => Some((err, url.as_ref())),
+            _ => None,
+        }
+    }
+
+    /// url に移動し、履歴に積む
     pub fn navigate(&mut self, url: Url) {
+        self.navigate_from(url, None);
+    }
+
+    /// referrer の文書のリンクから url に移動し、履歴に積む
+    pub fn navigate_from(&mut self, url: Url, referrer: Option<Url>) {
+        self.save_page_state();
+        self.history.push(url.clone());
+        self.referrer = referrer;
+        self.load(url);
+    }
+
+    /// 今の文書の Referer にするリンク元の文書の URL
+    ///
+    /// 履歴には残さないので、戻る / 進むで開いた文書では None。
+    pub fn referrer(&self) -> Option<&Url> {
+        self.referrer.as_ref()
+    }
+
+    /// このタブを開いたリンクのある文書の番号（window.opener）
+    pub fn opener(&self) -> Option<u64> {
+        self.opener
+    }
+
+    pub fn set_opener(&mut self, opener: Option<u64>) {
+        self.opener = opener;
+    }
+
+    /// 履歴を 1 つ戻る。戻れなければ false
+    pub fn go_back(&mut self) -> bool {
+        self.save_page_state();
+        let Some(entry) = self.history.back().cloned() else {
+            return false;
+        };
+        self.load_history_entry(entry);
+        true
+    }
+
+    /// 履歴を 1 つ進む。進めなければ false
+    pub fn go_forward(&mut self) -> bool {
+        self.save_page_state();
+        let Some(entry) = self.history.forward().cloned() else {
+            return false;
+        };
+        self.load_history_entry(entry);
+        true
+    }
+
+    /// 保存されたセッションからタブを開き直す
+    pub fn restore(&mut self, saved: &SessionTab) {
+        self.history.push(saved.url.clone());
+        if let Some(entry) = self.history.current_mut() {
+            entry.title = saved.title.clone();
+            entry.scroll = saved.scroll;
+        }
+        // 読み込みが終わるまではタブバーに保存されたタイトルを出す
+        self.title = saved.title.clone();
+        self.load_with_scroll(saved.url.clone(), saved.scroll);
+    }
+
+    /// セッションに保存する内容（何も開いていないか、プライベートタブなら None）
+    pub fn session_tab(&self) -> Option<SessionTab> {
+        if self.private {
+            return None;
+        }
+        let entry = self.history.current()?;
+        let scroll = self
+            .page_scroll()
+            .map(|(offset, _)| offset)
+            .unwrap_or(entry.scroll);
+
+        Some(SessionTab {
+            url: entry.url.clone(),
+            title: self.title.clone().or_else(|| entry.title.clone()),
+            scroll,
+        })
+    }
+
+    /// 現在の URL を読み込み直す。スクロール位置は保つ
+    pub fn reload(&mut self, bypass_cache: bool) {
+        let Some(url) = self.history.current().map(|e| e.url.clone()) else {
+            return;
+        };
+        self.save_page_state();
+        let scroll = self.history.current().map_or((0.0, 0.0), |e| e.scroll);
+
+        self.load_with_scroll(url, scroll);
+        self.bypass_cache = bypass_cache;
+    }
+
+    /// 読み込みを止め、それまでに届いた内容で表示する
+    pub fn stop_loading(&mut self) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.stop_loading();
+        }
+        if matches!(self.state, TabState::Loading) {
+            self.state = TabState::Loaded;
+        }
+    }
+
+    /// 読み込みに失敗してエラーページを表示しているか
+    pub fn is_error_page(&self) -> bool {
+        matches!(self.state, TabState::Error(..))
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        self.history.can_go_back()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        self.history.can_go_forward()
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// WebView の <title> を取り込む。変わったら true
+    fn sync_title(&mut self) -> bool {
+        let Some(title) = self.webview.as_ref().and_then(|wv| wv.title()) else {
+            return false;
+        };
+        if self.title.as_ref() == Some(title) {
+            return false;
+        }
+
+        self.title = Some(title.clone());
+        if !matches!(self.state, TabState::Error(..))
+            && let Some(entry) = self.history.current_mut()
+        {
+            entry.title = self.title.clone();
+        }
+        true
+    }
+
+    /// 今のスクロール位置と入力欄の値を現在の履歴項目に記録する
+    fn save_page_state(&mut self) {
+        let scroll = self.page_scroll().map(|(offset, _)| offset);
+        let form = self.webview.as_ref().map(WebView::form_state);
+        let Some(entry) = self.history.current_mut() else {
+            return;
+        };
+        if let Some(scroll) = scroll {
+            entry.scroll = scroll;
+        }
+        if let Some(form) = form {
+            entry.form = form;
+        }
+    }
+
+    fn load_with_scroll(&mut self, url: Url, scroll: (f32, f32)) {
+        self.load(url);
+        if let Some(wv) = self.webview.as_mut() {
+            wv.restore_scroll(scroll);
+        }
+    }
+
+    /// 戻る / 進むで移った履歴項目を開き、離れたときのスクロール位置と入力欄の値に戻す
+    fn load_history_entry(&mut self, entry: HistoryEntry) {
+        self.referrer = None;
+        self.load_with_scroll(entry.url, entry.scroll);
+        if let Some(wv) = self.webview.as_mut() {
+            wv.restore_form_state(entry.form);
+        }
+    }
+
+    /// 履歴には触れずに url を読み込む
+    fn load(&mut self, url: Url) {
         self.docment_url = Some(url.clone());
-        let mut webview = WebView::new();
+        self.tls = None;
+        self.csp = ContentSecurityPolicy::new();
+        let mut webview = self.new_webview();
+        // ズームはページを移動しても引き継ぐ
+        if let Some(old) = self.webview.as_ref() {
+            webview.set_zoom(old.user_zoom());
+        }
+        // 前のページのスクリプトはもう動かさない
+        self.shutdown_scripts();
         webview.navigate();
         self.webview = Some(webview);
         self.state = TabState::Loading;
+        self.bypass_cache = false;
+        self.progress.reset();
+        self.inline_html = None;
+        self.reader_original = None;
     }
 
+    /// リンクなどの href を現在のページ基準で解決して移動する
     pub fn move_to(&mut self, href: &str) {
-        let base_url = match self.base_url.as_ref() {
-            Some(u) => u,
-            None => return,
+        self.move_to_from(href, self.docment_url.clone());
+    }
+
+    /// move_to と同じく移動する。Referer には referrer を使う
+    fn move_to_from(&mut self, href: &str, referrer: Option<Url>) {
+        let Some(url) = self.resolve_link(href) else {
+            return;
         };
 
-        let url = super::webview::resolve_url(base_url, href).unwrap();
+        // エラーページの Retry は履歴に積まずに読み直す
+        if self.is_error_page() && self.history.current().is_some_and(|e| e.url == url) {
+            self.reload(false);
+            return;
+        }
+
+        // 同じ文書内のフラグメントへのリンクは読み込み直さない
+        if url.fragment().is_some()
+            && let Some(current) = self.docment_url.as_ref()
+            && url.as_str().split('#').next() == current.as_str().split('#').next()
+        {
+            return;
+        }
 
+        log::info!("Navigating to {}", url);
         // navigate と同じ扱い
-        self.navigate(url)
+        self.navigate_from(url, referrer)
+    }
+
+    /// リンクの href を現在のページ基準で解決する。移動できない URL なら None
+    fn resolve_link(&self, href: &str) -> Option<Url> {
+        let base_url = self.base_url.as_ref().or(self.docment_url.as_ref())?;
+
+        let url = match super::webview::resolve_url(base_url, href) {
+            Ok(url) => url,
+            Err(e) => {
+                log::warn!("Invalid link href {:?}: {}", href, e);
+                return None;
+            }
+        };
+
+        // ローカルのファイルへはローカルのページからしか移動しない
+        let from_file = self
+            .docment_url
+            .as_ref()
+            .is_some_and(|current| current.scheme() == "file");
+        let allowed = match url.scheme() {
+            "http" | "https" | "resource" | "orinium" | "about" => true,
+            "file" => from_file,
+            _ => false,
+        };
+        if !allowed {
+            log::info!("Ignoring link with unsupported scheme: {}", url);
+            return None;
+        }
+        Some(url)
     }
 
     pub fn relayout(&mut self, viewport: (f32, f32)) {
@@ -186,15 +1031,475 @@ impl Tab {
         self.title.clone()
     }
 
+    /// タブバーに表示する名前（タイトル、なければ URL）
+    pub fn display_title(&self) -> String {
+        if let Some(title) = self.title.as_ref().filter(|t| !t.trim().is_empty()) {
+            return title.trim().to_string();
+        }
+
+        match self.history.current() {
+            Some(entry) => entry.url.to_string(),
+            None => "New Tab".to_string(),
+        }
+    }
+
     /// Returns document url
     pub fn document_url(&self) -> Option<Url> {
         self.docment_url.clone()
     }
 
+    /// 文書を受け取った TLS の接続を記録する（BrowserApp が HTML の応答から渡す）
+    pub fn set_tls_info(&mut self, tls: Option<Arc<TlsInfo>>) {
+        self.tls = tls;
+    }
+
+    /// 文書の応答のヘッダーの Content Security Policy を記録する（BrowserApp が渡す）
+    pub fn set_content_security_policy(&mut self, csp: ContentSecurityPolicy) {
+        self.csp = csp;
+    }
+
+    /// URL バーに出す接続の安全性
+    pub fn security(&self) -> Security {
+        match self.docment_url.as_ref() {
+            // エラーページはブラウザが作ったもの
+            Some(url) if !self.is_error_page() => Security::for_document(url, self.tls.clone()),
+            _ => Security::Local,
+        }
+    }
+
+    /// ページの描画コマンド（`<iframe>` の中の文書を含む）
+    pub fn draw_commands(&self) -> Vec<DrawCommand> {
+        self.webview
+            .as_ref()
+            .map(WebView::draw_commands)
+            .unwrap_or_default()
+    }
+
     pub fn layout_and_info(&self) -> Option<(&LayoutNode, &InfoNode)> {
         self.webview.as_ref().and_then(|wv| wv.layout_and_info())
     }
 
+    /// 印刷用のレイアウト（ページを開いていなければ None）
+    pub fn print_layout(&self, page: (f32, f32)) -> Option<(LayoutNode, InfoNode)> {
+        self.webview.as_ref()?.print_layout(page)
+    }
+
+    /// 今の文書の読み込みにかかった時間（ページを開いていなければ None）
+    pub fn metrics(&self) -> Option<&PageLoadMetrics> {
+        self.webview.as_ref().map(WebView::metrics)
+    }
+
+    /// ページを描画コマンドにしたことを記録する
+    pub fn mark_painted(&mut self) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.mark_painted();
+        }
+    }
+
+    /// 読み込みと最初の描画を終えた計測値（文書ごとに 1 度だけ）
+    pub fn take_finished_metrics(&mut self) -> Option<PageLoadMetrics> {
+        self.webview.as_mut()?.take_finished_metrics()
+    }
+
+    /// テキスト選択を開始する
+    pub fn begin_selection(&mut self, x: f32, y: f32) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.begin_selection(x, y);
+        }
+    }
+
+    /// テキスト選択の終点を動かす
+    pub fn extend_selection(&mut self, x: f32, y: f32) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.extend_selection(x, y);
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.clear_selection();
+        }
+    }
+
+    pub fn selection(&self) -> Option<&Selection> {
+        self.webview.as_ref().and_then(|wv| wv.selection())
+    }
+
+    /// 選択されている文字列
+    pub fn selected_text(&self) -> Option<String> {
+        self.webview.as_ref().and_then(|wv| wv.selected_text())
+    }
+
+    /// (x, y) の入力欄にフォーカスを移す。入力欄がなければ false
+    pub fn focus_input_at(&mut self, x: f32, y: f32) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.focus_input_at(x, y))
+    }
+
+    pub fn blur_input(&mut self) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.blur_input();
+        }
+    }
+
+    pub fn has_focused_input(&self) -> bool {
+        self.webview
+            .as_ref()
+            .is_some_and(|wv| wv.has_focused_input())
+    }
+
+    pub fn is_composing(&self) -> bool {
+        self.webview.as_ref().is_some_and(|wv| wv.is_composing())
+    }
+
+    /// フォーカスのある入力欄を編集する。入力欄がなければ false
+    pub fn edit_input(&mut self, edit: impl FnOnce(&mut TextEdit)) -> bool {
+        self.webview.as_mut().is_some_and(|wv| wv.edit_input(edit))
+    }
+
+    /// 送信したフォームの自動入力できる値（1 度だけ）
+    pub fn take_submitted_profile(&mut self) -> Option<AutofillProfile> {
+        self.webview.as_mut()?.take_submitted_profile()
+    }
+
+    /// フォーカスのある入力欄の自動入力の種類と今の値
+    pub fn autofill_field(&self) -> Option<(AutofillField, String)> {
+        self.webview.as_ref()?.autofill_field()
+    }
+
+    /// フォーカスのある入力欄の border box (x, y, width, height)（ビューポート座標）
+    pub fn focused_input_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        self.webview.as_ref()?.focused_input_rect()
+    }
+
+    /// フォーカスのある入力欄の値を value に置き換える
+    pub fn set_input_value(&mut self, value: &str) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.set_input_value(value))
+    }
+
+    /// 送信したログインフォームの内容（1 度だけ）
+    pub fn take_submitted_login(&mut self) -> Option<SubmittedLogin> {
+        self.webview.as_mut()?.take_submitted_login()
+    }
+
+    /// フォーカスのある入力欄がログインフォームの欄なら、オリジンとユーザー名の欄の値
+    pub fn login_field(&self) -> Option<(String, String)> {
+        self.webview.as_ref()?.login_field()
+    }
+
+    /// フォーカスのあるログインフォームに credential を入れる
+    pub fn fill_login(&mut self, credential: &Credential) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.fill_login(credential))
+    }
+
+    /// 求められてまだ決まっていない権限（1 度だけ）
+    pub fn take_permission_requests(&mut self) -> Vec<PermissionRequest> {
+        self.webview
+            .as_mut()
+            .map(WebView::take_permission_requests)
+            .unwrap_or_default()
+    }
+
+    /// origin の文書に permission を許すか拒む
+    pub fn set_permission(&mut self, origin: &str, permission: Permission, granted: bool) {
+        if let Some(webview) = self.webview.as_mut() {
+            webview.set_permission(origin, permission, granted);
+        }
+    }
+
+    /// 許されて、OS に問い合わせる現在地の番号（1 度だけ）
+    pub fn take_position_requests(&mut self) -> Vec<u64> {
+        self.webview
+            .as_mut()
+            .map(WebView::take_position_requests)
+            .unwrap_or_default()
+    }
+
+    /// 番号 id の現在地の問い合わせの答えを届ける。このタブのものでなければ false
+    pub fn set_position(&mut self, id: u64, result: Result<Position, PositionError>) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.set_position(id, result))
+    }
+
+    /// 通知センターに頼むこと（1 度だけ）
+    pub fn take_notification_commands(&mut self) -> Vec<NotificationCommand> {
+        self.webview
+            .as_mut()
+            .map(WebView::take_notification_commands)
+            .unwrap_or_default()
+    }
+
+    /// 通知 id に起きたことを届ける。このタブのものでなければ false
+    pub fn notification_event(&mut self, id: u64, event: &NotificationEvent) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.notification_event(id, event))
+    }
+
+    /// (x, y) にあるフォーカスのある入力欄の、綴りの誤っている単語と直す候補
+    pub fn misspelling_at(&self, x: f32, y: f32) -> Option<Misspelling> {
+        self.webview.as_ref()?.misspelling_at(x, y)
+    }
+
+    /// フォーカスのある入力欄の値の range を replacement で置き換える
+    pub fn replace_input_range(&mut self, range: Range<usize>, replacement: &str) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.replace_input_range(range, replacement))
+    }
+
+    /// フォーカスのある入力欄で選択されている文字列
+    pub fn input_selected_text(&self) -> Option<String> {
+        self.webview
+            .as_ref()
+            .and_then(|wv| wv.input_selected_text())
+    }
+
+    /// IME の変換候補を出す位置（ビューポートの CSS px）
+    pub fn ime_cursor_area(&self) -> Option<(f32, f32, f32, f32)> {
+        self.webview.as_ref().and_then(|wv| wv.ime_cursor_area())
+    }
+
+    /// (x, y) をクリックする。リンクなら移動する
+    ///
+    /// 新しいタブで開くリンク（`target="_blank"`、new_tab なら全部）はここでは開かず、
+    /// それを返す。
+    pub fn click_at(&mut self, x: f32, y: f32, new_tab: bool) -> Option<NewTabLink> {
+        let navigation = self
+            .webview
+            .as_mut()
+            .and_then(|wv| wv.capture(|wv| wv.click_at(x, y)))?;
+        self.follow_link(navigation, new_tab)
+    }
+
+    fn follow_link(&mut self, navigation: LinkNavigation, new_tab: bool) -> Option<NewTabLink> {
+        if new_tab || navigation.target == LinkTarget::NewTab {
+            return Some(NewTabLink {
+                url: self.resolve_link(&navigation.href)?,
+                referrer: navigation.referrer,
+                opener: navigation.opener,
+            });
+        }
+        self.move_to_from(&navigation.href, navigation.referrer);
+        None
+    }
+
+    /// 表示している文書のコンソール（`<iframe>` の中の文書を含む）
+    pub fn console(&self) -> Console {
+        self.webview
+            .as_ref()
+            .map(WebView::console)
+            .unwrap_or_default()
+    }
+
+    /// (x, y) にある要素のスタイルを調べる
+    pub fn inspect_at(&self, x: f32, y: f32) -> Option<StyleInspection> {
+        self.webview.as_ref()?.inspect_at(x, y)
+    }
+
+    /// 表示している文書の DOM のルート
+    pub fn dom_root(&self) -> Option<NodeRef<HtmlNodeType>> {
+        self.webview.as_ref()?.dom_root()
+    }
+
+    /// 表示している文書の DOM のおおよその大きさ（バイト）
+    pub fn memory_usage(&self) -> usize {
+        self.webview.as_ref().map_or(0, WebView::memory_usage)
+    }
+
+    /// 表示している文書で式 expression を評価する
+    pub fn evaluate_script(&mut self, expression: &str) -> Result<ScriptValue> {
+        self.webview
+            .as_mut()
+            .ok_or_else(|| anyhow!("No document is loaded"))?
+            .evaluate_script(expression)
+    }
+
+    /// (x, y) にある要素の箱
+    pub fn box_model_at(&self, x: f32, y: f32) -> Option<BoxModel> {
+        self.webview.as_ref()?.box_model_at(x, y)
+    }
+
+    /// ページに keydown を届ける。既定の動作をしてよければ true
+    pub fn key_down(&mut self, key: &str) -> bool {
+        self.webview.as_mut().is_none_or(|wv| wv.key_down(key))
+    }
+
+    /// Tab キーで次（backward なら前）の要素にフォーカスを移す
+    pub fn focus_next(&mut self, backward: bool) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.focus_next(backward);
+        }
+    }
+
+    /// フォーカスのある要素を実行する（Enter キー）。リンクなら移動する
+    ///
+    /// 新しいタブで開くリンクなら、それを返す。
+    pub fn activate_focused(&mut self) -> Option<NewTabLink> {
+        let navigation = self
+            .webview
+            .as_mut()
+            .and_then(|wv| wv.capture(WebView::activate_focused))?;
+        self.follow_link(navigation, false)
+    }
+
+    /// 今の文書のアクセシビリティツリー
+    pub fn accessibility_tree(&self) -> Option<AccessTree> {
+        self.webview.as_ref()?.accessibility_tree()
+    }
+
+    /// 支援技術から頼まれて、ID が id のノードにフォーカスを移す。なければ false
+    pub fn focus_accessible(&mut self, id: u64) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.focus_accessible(id))
+    }
+
+    pub fn focus_ring(&self) -> Option<&[usize]> {
+        self.webview.as_ref().and_then(|wv| wv.focus_ring())
+    }
+
+    /// (x, y) のボタンを押す。ボタンがなければ false
+    pub fn press_button_at(&mut self, x: f32, y: f32) -> bool {
+        self.webview
+            .as_mut()
+            .is_some_and(|wv| wv.press_button_at(x, y))
+    }
+
+    pub fn is_pressing_button(&self) -> bool {
+        self.webview
+            .as_ref()
+            .is_some_and(|wv| wv.is_pressing_button())
+    }
+
+    /// 押していたボタンを離す。フォームを送信するなら移動する
+    pub fn release_button_at(&mut self, x: f32, y: f32) {
+        if let Some(url) = self
+            .webview
+            .as_mut()
+            .and_then(|wv| wv.capture(|wv| wv.release_button_at(x, y)))
+        {
+            self.move_to(url.as_str());
+        }
+    }
+
+    /// フォーカスのある入力欄のフォームを送信する（Enter キー）
+    pub fn submit_focused_input(&mut self) {
+        if let Some(url) = self
+            .webview
+            .as_mut()
+            .and_then(|wv| wv.capture(WebView::submit_focused_input))
+        {
+            self.move_to(url.as_str());
+        }
+    }
+
+    /// ページをスクロールする（スムーススクロール）
+    pub fn scroll_by(&mut self, dx: f32, dy: f32, viewport: (f32, f32)) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.scroll_by(dx, dy, viewport);
+        }
+    }
+
+    /// ページのスクロール位置とコンテンツの大きさ ((x, y), (width, height))
+    pub fn page_scroll(&self) -> Option<((f32, f32), (f32, f32))> {
+        self.webview.as_ref().and_then(|wv| wv.page_scroll())
+    }
+
+    /// ページをスクロールできる最大の位置 (max_x, max_y)
+    pub fn max_scroll(&self) -> Option<(f32, f32)> {
+        self.webview.as_ref().and_then(|wv| wv.max_scroll())
+    }
+
+    /// (x, y) の下にあるリンクを :hover にする。:hover の対象が変わったら true
+    pub fn hover_at(&mut self, x: f32, y: f32) -> bool {
+        self.webview
+            .as_mut()
+            .map(|wv| wv.hover_at(x, y))
+            .unwrap_or(false)
+    }
+
+    pub fn is_over_link(&self) -> bool {
+        self.webview.as_ref().is_some_and(|wv| wv.is_over_link())
+    }
+
+    /// (x, y) の下にあるスクロールコンテナをスクロールする
+    pub fn scroll_at(&mut self, x: f32, y: f32, dx: f32, dy: f32, viewport: (f32, f32)) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.scroll_at(x, y, dx, dy, viewport);
+        }
+    }
+
+    /// スクロールのアニメーションを進める。まだ動いていれば true
+    pub fn animate_scroll(&mut self, now: Instant) -> bool {
+        self.webview
+            .as_mut()
+            .map(|wv| wv.animate_scroll(now))
+            .unwrap_or(false)
+    }
+
+    /// ページのズーム倍率（WebView がなければ既定の倍率）
+    pub fn zoom(&self) -> f32 {
+        self.webview
+            .as_ref()
+            .map(|wv| wv.zoom())
+            .unwrap_or(self.defaults.zoom)
+    }
+
+    pub fn zoom_in(&mut self) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.zoom_in();
+        }
+    }
+
+    pub fn zoom_out(&mut self) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.zoom_out();
+        }
+    }
+
+    /// ズーム倍率を scale 倍にする（ピンチ操作用）
+    pub fn zoom_by(&mut self, scale: f32) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.set_zoom(wv.user_zoom() * scale);
+        }
+    }
+
+    /// 既定の倍率に戻す
+    pub fn reset_zoom(&mut self) {
+        if let Some(wv) = self.webview.as_mut() {
+            wv.set_zoom(self.defaults.zoom);
+        }
+    }
+
+    /// ページを読み込んでいる途中か（HTML か CSS を待っている）
+    ///
+    /// 読み込みを止めた場合やエラーページは読み込み中としない。
+    pub fn is_loading(&self) -> bool {
+        let Some(wv) = self.webview.as_ref() else {
+            return false;
+        };
+        match self.state {
+            TabState::Loading => true,
+            TabState::Loaded => wv.document_info().is_some() && !wv.is_loaded(),
+            TabState::Error(..) => false,
+        }
+    }
+
+    /// ページの読み込みが完了しているか
+    pub fn is_loaded(&self) -> bool {
+        self.webview
+            .as_ref()
+            .map(|wv| wv.is_loaded())
+            .unwrap_or(false)
+    }
+
     pub fn needs_redraw(&self) -> bool {
         self.webview
             .as_ref()
@@ -208,3 +1513,12 @@ impl Tab {
         }
     }
 }
+
+/// パニックのメッセージ（panic! に渡した文字列）
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
@@ -0,0 +1,209 @@
+//! 右クリックのメニュー
+//!
+//! 項目を縦に並べた小さなメニューをポインタの位置に出す。窓からはみ出すときは
+//! 左や上にずらす。座標はウィンドウの論理ピクセルで、URL バーと同じく DrawCommand で描く。
+
+use crate::engine::layouter::types::TextStyle;
+use crate::engine::renderer_model::DrawCommand;
+
+use super::theme::ChromeTheme;
+
+const ITEM_HEIGHT: f32 = 26.0;
+const PADDING_X: f32 = 12.0;
+const PADDING_Y: f32 = 4.0;
+const FONT_SIZE: f32 = 14.0;
+const MIN_WIDTH: f32 = 160.0;
+/// 幅を決めるときの 1 文字の幅（font-size に対する割合）
+const CHAR_WIDTH_RATIO: f32 = 0.6;
+
+/// メニューの項目
+#[derive(Debug, Clone, PartialEq)]
+pub struct MenuItem {
+    pub label: String,
+    /// 選べるか（「候補なし」のような説明だけの項目は false）
+    pub enabled: bool,
+}
+
+impl MenuItem {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            enabled: true,
+        }
+    }
+
+    pub fn disabled(label: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            enabled: false,
+        }
+    }
+}
+
+/// 開いているメニュー
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextMenu {
+    /// 開いた位置（メニューの左上にしたい点）
+    position: (f32, f32),
+    items: Vec<MenuItem>,
+    /// ポインタやキーで選んでいる項目
+    highlighted: Option<usize>,
+}
+
+impl ContextMenu {
+    pub fn new(position: (f32, f32), items: Vec<MenuItem>) -> Self {
+        Self {
+            position,
+            items,
+            highlighted: None,
+        }
+    }
+
+    /// 開く位置を変える（選んでいる項目はそのまま）
+    pub fn set_position(&mut self, position: (f32, f32)) {
+        self.position = position;
+    }
+
+    pub fn items(&self) -> &[MenuItem] {
+        &self.items
+    }
+
+    pub fn highlighted(&self) -> Option<usize> {
+        self.highlighted
+    }
+
+    /// 次の選べる項目に移る（末尾で止まる）
+    pub fn highlight_next(&mut self) {
+        let start = self.highlighted.map_or(0, |i| i + 1);
+        if let Some(i) = (start..self.items.len()).find(|&i| self.items[i].enabled) {
+            self.highlighted = Some(i);
+        }
+    }
+
+    /// 前の選べる項目に移る（先頭で止まる）
+    pub fn highlight_previous(&mut self) {
+        let end = self.highlighted.unwrap_or(self.items.len());
+        if let Some(i) = (0..end).rev().find(|&i| self.items[i].enabled) {
+            self.highlighted = Some(i);
+        }
+    }
+
+    /// ポインタの下の項目を選んでいる状態にする。変わったら true
+    pub fn hover_at(&mut self, window: (f32, f32), x: f32, y: f32) -> bool {
+        let hovered = self.hit_test(window, x, y);
+        let changed = hovered != self.highlighted;
+        self.highlighted = hovered;
+        changed
+    }
+
+    /// (x, y) にある選べる項目の番号
+    pub fn hit_test(&self, window: (f32, f32), x: f32, y: f32) -> Option<usize> {
+        (0..self.items.len()).find(|&i| {
+            let (ix, iy, iw, ih) = self.item_rect(window, i);
+            self.items[i].enabled && x >= ix && x < ix + iw && y >= iy && y < iy + ih
+        })
+    }
+
+    /// (x, y) がメニューの上か
+    pub fn contains(&self, window: (f32, f32), x: f32, y: f32) -> bool {
+        let (mx, my, mw, mh) = self.rect(window);
+        x >= mx && x < mx + mw && y >= my && y < my + mh
+    }
+
+    /// 窓（幅, 高さ）に収めたメニューの矩形 (x, y, width, height)
+    fn rect(&self, (window_width, window_height): (f32, f32)) -> (f32, f32, f32, f32) {
+        let longest = self
+            .items
+            .iter()
+            .map(|item| item.label.chars().count())
+            .max()
+            .unwrap_or(0);
+        let width = (longest as f32 * FONT_SIZE * CHAR_WIDTH_RATIO + PADDING_X * 2.0)
+            .max(MIN_WIDTH)
+            .min(window_width);
+        let height = ITEM_HEIGHT * self.items.len() as f32 + PADDING_Y * 2.0;
+
+        let (x, y) = self.position;
+        // 右や下にはみ出すなら、ポインタの左や上に開く
+        let x = if x + width > window_width {
+            (x - width).max(0.0)
+        } else {
+            x
+        };
+        let y = if y + height > window_height {
+            (y - height).max(0.0)
+        } else {
+            y
+        };
+        (x, y, width, height)
+    }
+
+    fn item_rect(&self, window: (f32, f32), i: usize) -> (f32, f32, f32, f32) {
+        let (x, y, width, _) = self.rect(window);
+        (
+            x,
+            y + PADDING_Y + ITEM_HEIGHT * i as f32,
+            width,
+            ITEM_HEIGHT,
+        )
+    }
+
+    /// 窓（幅, 高さ）の中にメニューを描く DrawCommand
+    pub fn draw_commands(&self, window: (f32, f32), theme: &ChromeTheme) -> Vec<DrawCommand> {
+        let (x, y, width, height) = self.rect(window);
+        let mut commands = vec![
+            DrawCommand::DrawRect {
+                x,
+                y,
+                width,
+                height,
+                color: theme.field_border,
+            },
+            DrawCommand::DrawRect {
+                x: x + 1.0,
+                y: y + 1.0,
+                width: (width - 2.0).max(0.0),
+                height: (height - 2.0).max(0.0),
+                color: theme.field_background,
+            },
+            DrawCommand::PushClip {
+                x,
+                y,
+                width,
+                height,
+            },
+        ];
+
+        for (i, item) in self.items.iter().enumerate() {
+            let (ix, iy, iw, ih) = self.item_rect(window, i);
+            if self.highlighted == Some(i) {
+                commands.push(DrawCommand::DrawRect {
+                    x: ix + 1.0,
+                    y: iy,
+                    width: (iw - 2.0).max(0.0),
+                    height: ih,
+                    color: theme.suggestion_selected,
+                });
+            }
+            let style = TextStyle {
+                font_size: FONT_SIZE,
+                color: if item.enabled {
+                    theme.text
+                } else {
+                    theme.button
+                },
+                ..Default::default()
+            };
+            commands.push(DrawCommand::DrawText {
+                x: ix + PADDING_X,
+                y: iy + (ih - FONT_SIZE * 1.2) / 2.0,
+                text: item.label.clone(),
+                style,
+                max_width: (iw - PADDING_X * 2.0).max(0.0),
+            });
+        }
+
+        commands.push(DrawCommand::PopClip);
+        commands
+    }
+}
@@ -0,0 +1,126 @@
+//! 調べるモードの箱の重ね表示
+//!
+//! ポインタの下の要素の margin / border / padding / content を半透明の色で塗り分け、
+//! 要素のタグと大きさと位置を小さな札で添える。座標はページと同じ CSS px。
+
+use crate::browser::core::devtools::{BoxModel, Rect};
+use crate::engine::bridge::text::{TextMeasureRequest, TextMeasurer};
+use crate::engine::layouter::types::{Color, TextStyle};
+use crate::engine::renderer_model::DrawCommand;
+
+const MARGIN_COLOR: Color = Color(246, 178, 107, 120);
+const BORDER_COLOR: Color = Color(255, 229, 153, 120);
+const PADDING_COLOR: Color = Color(147, 196, 125, 120);
+const CONTENT_COLOR: Color = Color(111, 168, 220, 120);
+
+const TOOLTIP_BACKGROUND: Color = Color(36, 36, 40, 235);
+const TOOLTIP_TEXT: Color = Color(240, 240, 240, 255);
+const FONT_SIZE: f32 = 12.0;
+const TOOLTIP_PADDING: f32 = 4.0;
+/// 札と要素の間
+const TOOLTIP_GAP: f32 = 4.0;
+
+/// 札に出す文字列（`div#main.note  320 × 48  at (8, 120)`）
+pub fn tooltip_text(model: &BoxModel) -> String {
+    let (x, y, width, height) = model.border;
+    format!(
+        "{}  {} × {}  at ({}, {})",
+        model.element,
+        width.round(),
+        height.round(),
+        x.round(),
+        y.round()
+    )
+}
+
+/// model を大きさ viewport のページに重ねる DrawCommand
+pub fn draw_commands(
+    model: &BoxModel,
+    viewport: (f32, f32),
+    measurer: &dyn TextMeasurer<TextStyle>,
+) -> Vec<DrawCommand> {
+    let mut commands = Vec::new();
+    ring(&mut commands, model.margin, model.border, MARGIN_COLOR);
+    ring(&mut commands, model.border, model.padding, BORDER_COLOR);
+    ring(&mut commands, model.padding, model.content, PADDING_COLOR);
+    commands.push(rect(model.content, CONTENT_COLOR));
+
+    let style = TextStyle {
+        font_size: FONT_SIZE,
+        color: TOOLTIP_TEXT,
+        ..Default::default()
+    };
+    let text = tooltip_text(model);
+    let metrics = measurer
+        .measure(&TextMeasureRequest {
+            text: text.clone(),
+            style,
+            max_width: None,
+            wrap: false,
+        })
+        .ok();
+    let text_width = metrics.as_ref().map_or(0.0, |m| m.width);
+    let line_height = metrics
+        .map(|m| m.line_height())
+        .filter(|h| *h > 0.0)
+        .unwrap_or(FONT_SIZE * 1.2);
+
+    let width = text_width + TOOLTIP_PADDING * 2.0;
+    let height = line_height + TOOLTIP_PADDING * 2.0;
+    // margin box の下に置き、ビューポートからはみ出すなら上に置く
+    let (mx, my, _, mh) = model.margin;
+    let below = my + mh + TOOLTIP_GAP;
+    let y = if below + height <= viewport.1 {
+        below
+    } else {
+        my - TOOLTIP_GAP - height
+    };
+    let y = y.clamp(0.0, (viewport.1 - height).max(0.0));
+    let x = mx.clamp(0.0, (viewport.0 - width).max(0.0));
+
+    commands.push(DrawCommand::DrawRect {
+        x,
+        y,
+        width,
+        height,
+        color: TOOLTIP_BACKGROUND,
+    });
+    commands.push(DrawCommand::DrawText {
+        x: x + TOOLTIP_PADDING,
+        y: y + TOOLTIP_PADDING,
+        text,
+        style,
+        max_width: text_width + FONT_SIZE,
+    });
+    commands
+}
+
+fn rect((x, y, width, height): Rect, color: Color) -> DrawCommand {
+    DrawCommand::DrawRect {
+        x,
+        y,
+        width,
+        height,
+        color,
+    }
+}
+
+/// outer のうち inner の外側の部分を上下左右の 4 つの矩形で塗る
+///
+/// 幅のない辺は描かない。
+fn ring(commands: &mut Vec<DrawCommand>, outer: Rect, inner: Rect, color: Color) {
+    let (ox, oy, ow, oh) = outer;
+    let (ix, iy, iw, ih) = inner;
+    let bands = [
+        (ox, oy, ow, iy - oy),
+        (ox, iy + ih, ow, oy + oh - (iy + ih)),
+        (ox, iy, ix - ox, ih),
+        (ix + iw, iy, ox + ow - (ix + iw), ih),
+    ];
+    commands.extend(
+        bands
+            .into_iter()
+            .filter(|&(_, _, w, h)| w > 0.0 && h > 0.0)
+            .map(|band| rect(band, color)),
+    );
+}
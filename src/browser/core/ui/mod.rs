@@ -1,3 +1,17 @@
+pub mod context_menu;
+pub mod inspect_overlay;
+pub mod progress_bar;
+pub mod tab_strip;
+pub mod theme;
+pub mod url_bar;
+pub mod window_controls;
+
+pub use context_menu::{ContextMenu, MenuItem};
+pub use tab_strip::{TAB_STRIP_HEIGHT, TabStripHit, TabStripItem};
+pub use theme::ChromeTheme;
+pub use url_bar::{SearchEngine, Suggestion, URL_BAR_HEIGHT, UrlBar};
+pub use window_controls::WindowControl;
+
 /*
 use crate::renderer::{DrawCommand, RenderTree, RenderNode};
 
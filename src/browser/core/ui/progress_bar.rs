@@ -0,0 +1,22 @@
+//! 読み込みの進捗バー
+//!
+//! ブラウザ UI の最上部に細い帯で読み込みの進み具合を表示する。
+
+use crate::engine::layouter::types::Color;
+use crate::engine::renderer_model::DrawCommand;
+
+/// 進捗バーの太さ
+pub const PROGRESS_BAR_HEIGHT: f32 = 2.0;
+
+const BAR_COLOR: Color = Color(66, 133, 244, 255);
+
+/// 幅 width のうち fraction（0.0〜1.0）の割合を塗る DrawCommand
+pub fn draw_commands(width: f32, fraction: f32) -> Vec<DrawCommand> {
+    vec![DrawCommand::DrawRect {
+        x: 0.0,
+        y: 0.0,
+        width: width * fraction.clamp(0.0, 1.0),
+        height: PROGRESS_BAR_HEIGHT,
+        color: BAR_COLOR,
+    }]
+}
@@ -0,0 +1,251 @@
+//! タブバー
+//!
+//! ウィンドウ最上部に開いているタブを並べる。座標は URL バーと同じく
+//! ウィンドウの論理ピクセル。
+
+use crate::engine::bridge::text::{TextMeasureRequest, TextMeasurer};
+use crate::engine::layouter::types::{Color, TextStyle};
+use crate::engine::renderer_model::DrawCommand;
+
+use super::theme::ChromeTheme;
+
+/// タブバーの高さ
+pub const TAB_STRIP_HEIGHT: f32 = 34.0;
+
+const TAB_MAX_WIDTH: f32 = 220.0;
+const TAB_MIN_WIDTH: f32 = 64.0;
+const TAB_MARGIN_TOP: f32 = 6.0;
+const TAB_PADDING: f32 = 10.0;
+const TAB_GAP: f32 = 1.0;
+const CLOSE_BUTTON_SIZE: f32 = 16.0;
+const NEW_TAB_BUTTON_SIZE: f32 = 24.0;
+const FONT_SIZE: f32 = 13.0;
+const SPINNER_SIZE: f32 = 12.0;
+const SPINNER_DOTS: usize = 8;
+const PRIVATE_MARK_HEIGHT: f32 = 3.0;
+
+/// タブバーに並べる 1 つのタブ
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabStripItem {
+    pub title: String,
+    /// 読み込み中ならタイトルの前にスピナーを出す
+    pub loading: bool,
+    /// プライベートタブなら上端に印を付ける
+    pub private: bool,
+}
+
+/// タブバー上のクリック対象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabStripHit {
+    /// i 番目のタブ
+    Tab(usize),
+    /// i 番目のタブの閉じるボタン
+    Close(usize),
+    /// 新しいタブのボタン
+    NewTab,
+}
+
+/// 幅 width のタブバーに tab_count 個のタブを並べたときの 1 つあたりの幅
+fn tab_width(width: f32, tab_count: usize) -> f32 {
+    let available = width - NEW_TAB_BUTTON_SIZE - TAB_PADDING * 2.0;
+    (available / tab_count.max(1) as f32).clamp(TAB_MIN_WIDTH, TAB_MAX_WIDTH)
+}
+
+/// i 番目のタブの矩形 (x, y, width, height)
+fn tab_rect(width: f32, tab_count: usize, i: usize) -> (f32, f32, f32, f32) {
+    let w = tab_width(width, tab_count);
+    (
+        TAB_PADDING + (w + TAB_GAP) * i as f32,
+        TAB_MARGIN_TOP,
+        w,
+        TAB_STRIP_HEIGHT - TAB_MARGIN_TOP,
+    )
+}
+
+/// タブの矩形の中の閉じるボタンの矩形
+fn close_rect((x, y, w, h): (f32, f32, f32, f32)) -> (f32, f32, f32, f32) {
+    (
+        x + w - TAB_PADDING / 2.0 - CLOSE_BUTTON_SIZE,
+        y + (h - CLOSE_BUTTON_SIZE) / 2.0,
+        CLOSE_BUTTON_SIZE,
+        CLOSE_BUTTON_SIZE,
+    )
+}
+
+fn new_tab_rect(width: f32, tab_count: usize) -> (f32, f32, f32, f32) {
+    let (x, y, _, h) = tab_rect(width, tab_count, tab_count);
+    (
+        x,
+        y + (h - NEW_TAB_BUTTON_SIZE) / 2.0,
+        NEW_TAB_BUTTON_SIZE,
+        NEW_TAB_BUTTON_SIZE,
+    )
+}
+
+fn contains((rx, ry, rw, rh): (f32, f32, f32, f32), x: f32, y: f32) -> bool {
+    x >= rx && x <= rx + rw && y >= ry && y <= ry + rh
+}
+
+/// (x, y) にあるタブバーの要素を返す
+pub fn hit_test(width: f32, tab_count: usize, x: f32, y: f32) -> Option<TabStripHit> {
+    for i in 0..tab_count {
+        let rect = tab_rect(width, tab_count, i);
+        if contains(rect, x, y) {
+            return Some(if contains(close_rect(rect), x, y) {
+                TabStripHit::Close(i)
+            } else {
+                TabStripHit::Tab(i)
+            });
+        }
+    }
+
+    contains(new_tab_rect(width, tab_count), x, y).then_some(TabStripHit::NewTab)
+}
+
+/// タブバーを描く DrawCommand
+///
+/// spinner_phase（0.0〜1.0）は読み込み中のスピナーの回転位置。
+pub fn draw_commands(
+    width: f32,
+    tabs: &[TabStripItem],
+    active: usize,
+    spinner_phase: f32,
+    theme: &ChromeTheme,
+    measurer: &dyn TextMeasurer<TextStyle>,
+) -> Vec<DrawCommand> {
+    let style = TextStyle {
+        font_size: FONT_SIZE,
+        color: theme.text,
+        ..Default::default()
+    };
+    let button_style = TextStyle {
+        color: theme.button,
+        ..style
+    };
+
+    let mut commands = vec![DrawCommand::DrawRect {
+        x: 0.0,
+        y: 0.0,
+        width,
+        height: TAB_STRIP_HEIGHT,
+        color: theme.strip_background,
+    }];
+
+    for (i, tab) in tabs.iter().enumerate() {
+        let rect @ (x, y, w, h) = tab_rect(width, tabs.len(), i);
+        let (cx, cy, cw, ch) = close_rect(rect);
+
+        commands.push(DrawCommand::DrawRect {
+            x,
+            y,
+            width: w,
+            height: h,
+            color: if i == active {
+                theme.active_tab_background
+            } else {
+                theme.tab_background
+            },
+        });
+        if tab.private {
+            commands.push(DrawCommand::DrawRect {
+                x,
+                y,
+                width: w,
+                height: PRIVATE_MARK_HEIGHT,
+                color: theme.private_accent,
+            });
+        }
+
+        let mut title_x = x + TAB_PADDING;
+        if tab.loading {
+            commands.extend(spinner_commands(
+                (title_x + SPINNER_SIZE / 2.0, y + h / 2.0),
+                spinner_phase,
+                theme.accent,
+            ));
+            title_x += SPINNER_SIZE + TAB_PADDING / 2.0;
+        }
+
+        // タイトルは閉じるボタンの手前で切る
+        let title_width = (cx - title_x).max(0.0);
+        let (text_width, line_height) = measure(measurer, &tab.title, style);
+        commands.push(DrawCommand::PushClip {
+            x: title_x,
+            y,
+            width: title_width,
+            height: h,
+        });
+        commands.push(DrawCommand::DrawText {
+            x: title_x,
+            y: y + (h - line_height) / 2.0,
+            text: tab.title.clone(),
+            style,
+            // 折り返さずにクリップで切る
+            max_width: text_width + FONT_SIZE,
+        });
+        commands.push(DrawCommand::PopClip);
+
+        commands.push(DrawCommand::DrawText {
+            x: cx + (cw - FONT_SIZE * 0.6) / 2.0,
+            y: cy + (ch - line_height) / 2.0,
+            text: "×".to_string(),
+            style: button_style,
+            max_width: cw,
+        });
+    }
+
+    let (nx, ny, nw, nh) = new_tab_rect(width, tabs.len());
+    let (_, line_height) = measure(measurer, "+", button_style);
+    commands.push(DrawCommand::DrawText {
+        x: nx + (nw - FONT_SIZE * 0.6) / 2.0,
+        y: ny + (nh - line_height) / 2.0,
+        text: "+".to_string(),
+        style: button_style,
+        max_width: nw,
+    });
+
+    commands
+}
+
+/// center を中心に点を円形に並べたスピナー。phase の位置の点が一番濃い
+fn spinner_commands(center: (f32, f32), phase: f32, color: Color) -> Vec<DrawCommand> {
+    let radius = SPINNER_SIZE / 2.0 - 1.5;
+    let head = phase.rem_euclid(1.0) * SPINNER_DOTS as f32;
+
+    (0..SPINNER_DOTS)
+        .map(|i| {
+            let angle = i as f32 / SPINNER_DOTS as f32 * std::f32::consts::TAU;
+            // 先頭の点から遅れるほど薄くする
+            let behind = (head - i as f32).rem_euclid(SPINNER_DOTS as f32);
+            let alpha = 255.0 * (1.0 - behind / SPINNER_DOTS as f32);
+            DrawCommand::DrawEllipse {
+                center: (
+                    center.0 + radius * angle.sin(),
+                    center.1 - radius * angle.cos(),
+                ),
+                radius_x: 1.5,
+                radius_y: 1.5,
+                color: Color(color.0, color.1, color.2, alpha.max(40.0) as u8),
+            }
+        })
+        .collect()
+}
+
+/// 1 行で描いたときの (幅, 行の高さ)
+fn measure(measurer: &dyn TextMeasurer<TextStyle>, text: &str, style: TextStyle) -> (f32, f32) {
+    let metrics = measurer
+        .measure(&TextMeasureRequest {
+            text: text.to_string(),
+            style,
+            max_width: None,
+            wrap: false,
+        })
+        .ok();
+    let width = metrics.as_ref().map_or(0.0, |m| m.width);
+    let line_height = metrics
+        .map(|m| m.line_height())
+        .filter(|h| *h > 0.0)
+        .unwrap_or(FONT_SIZE * 1.2);
+
+    (width, line_height)
+}
@@ -0,0 +1,79 @@
+//! ブラウザ UI の配色
+//!
+//! タブバーと URL バーの色を明るい配色と暗い配色で切り替える。
+
+use crate::engine::css::media::ColorScheme;
+use crate::engine::layouter::types::Color;
+
+/// タブバーと URL バーの色
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChromeTheme {
+    pub strip_background: Color,
+    pub tab_background: Color,
+    /// URL バーの背景と同じ色にしてつながって見せる
+    pub active_tab_background: Color,
+    pub text: Color,
+    pub button: Color,
+    /// スピナーとフォーカス中の入力欄の枠
+    pub accent: Color,
+    pub bar_background: Color,
+    pub bar_border: Color,
+    pub field_background: Color,
+    pub field_border: Color,
+    pub suggestion_selected: Color,
+    pub suggestion_url: Color,
+    pub reader_button_active: Color,
+    /// http のページに出す「保護されていない」の印
+    pub not_secure: Color,
+    /// プライベートタブの印
+    pub private_accent: Color,
+    /// 何も表示していないときのページ領域の色
+    pub page_background: Color,
+}
+
+pub const LIGHT: ChromeTheme = ChromeTheme {
+    strip_background: Color(214, 214, 214, 255),
+    tab_background: Color(228, 228, 228, 255),
+    active_tab_background: Color(240, 240, 240, 255),
+    text: Color(32, 32, 32, 255),
+    button: Color(96, 96, 96, 255),
+    accent: Color(66, 133, 244, 255),
+    bar_background: Color(240, 240, 240, 255),
+    bar_border: Color(200, 200, 200, 255),
+    field_background: Color(255, 255, 255, 255),
+    field_border: Color(180, 180, 180, 255),
+    suggestion_selected: Color(225, 235, 252, 255),
+    suggestion_url: Color(26, 115, 232, 255),
+    reader_button_active: Color(210, 227, 252, 255),
+    not_secure: Color(197, 34, 31, 255),
+    private_accent: Color(124, 77, 255, 255),
+    page_background: Color(255, 255, 255, 255),
+};
+
+pub const DARK: ChromeTheme = ChromeTheme {
+    strip_background: Color(24, 24, 28, 255),
+    tab_background: Color(36, 36, 42, 255),
+    active_tab_background: Color(50, 50, 58, 255),
+    text: Color(232, 232, 236, 255),
+    button: Color(170, 170, 180, 255),
+    accent: Color(138, 180, 248, 255),
+    bar_background: Color(50, 50, 58, 255),
+    bar_border: Color(24, 24, 28, 255),
+    field_background: Color(32, 32, 38, 255),
+    field_border: Color(80, 80, 92, 255),
+    suggestion_selected: Color(48, 64, 96, 255),
+    suggestion_url: Color(138, 180, 248, 255),
+    reader_button_active: Color(48, 64, 96, 255),
+    not_secure: Color(242, 139, 130, 255),
+    private_accent: Color(179, 157, 219, 255),
+    page_background: Color(28, 27, 34, 255),
+};
+
+impl ChromeTheme {
+    pub fn for_scheme(scheme: ColorScheme) -> &'static ChromeTheme {
+        match scheme {
+            ColorScheme::Light => &LIGHT,
+            ColorScheme::Dark => &DARK,
+        }
+    }
+}
@@ -0,0 +1,1003 @@
+//! URL バー（オムニボックス）
+//!
+//! ページの上に描くブラウザ UI の入力欄。座標はすべてウィンドウの論理ピクセル
+//! （ページのズームに影響されない）で、描画はページと同じ DrawCommand で行う。
+
+use std::ops::Range;
+use std::path::Path;
+use std::time::SystemTime;
+
+use url::Url;
+
+use crate::browser::core::idn;
+use crate::browser::core::schemes::BUILTIN_SCHEMES;
+use crate::browser::core::security::Security;
+use crate::engine::bridge::text::{TextMeasureRequest, TextMeasurer};
+use crate::engine::input::selection::SELECTION_COLOR;
+use crate::engine::input::text_edit::Preedit;
+use crate::engine::layouter::types::TextStyle;
+use crate::engine::renderer_model::DrawCommand;
+
+use super::theme::ChromeTheme;
+
+/// URL バー全体の高さ
+pub const URL_BAR_HEIGHT: f32 = 40.0;
+
+const FIELD_MARGIN_X: f32 = 8.0;
+const FIELD_MARGIN_Y: f32 = 6.0;
+const FIELD_PADDING: f32 = 8.0;
+const FONT_SIZE: f32 = 14.0;
+const SUGGESTION_HEIGHT: f32 = 30.0;
+const READER_BUTTON_WIDTH: f32 = 32.0;
+const ACTION_BUTTON_WIDTH: f32 = 32.0;
+/// 止めたリクエストの数を出すボタンの幅
+const CONTENT_BLOCKING_BUTTON_WIDTH: f32 = 40.0;
+/// 入力欄の左端の鍵の印の幅
+const SECURITY_BADGE_WIDTH: f32 = 28.0;
+const SECURITY_POPUP_WIDTH: f32 = 420.0;
+const SECURITY_POPUP_LINE_HEIGHT: f32 = 22.0;
+
+/// URL として解釈できない入力を渡す検索エンジン
+///
+/// `template` の `{query}` が URL エンコードした入力に置き換えられる。
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchEngine {
+    pub template: String,
+}
+
+impl Default for SearchEngine {
+    fn default() -> Self {
+        Self::new("https://duckduckgo.com/?q={query}")
+    }
+}
+
+impl SearchEngine {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// query を検索する URL（テンプレートが URL にならなければ None）
+    pub fn search_url(&self, query: &str) -> Option<Url> {
+        let encoded: String = url::form_urlencoded::byte_serialize(query.as_bytes()).collect();
+        self.template.replace("{query}", &encoded).parse().ok()
+    }
+}
+
+/// URL バーに入力された文字列を移動先の URL にする
+///
+/// URL とみなせるものは [`fixup_input`] で直して使い、それ以外は検索エンジンに渡す。
+pub fn resolve_input(input: &str, search_engine: &SearchEngine) -> Option<Url> {
+    let input = input.trim();
+    if input.is_empty() {
+        return None;
+    }
+    fixup_input(input).or_else(|| search_engine.search_url(input))
+}
+
+/// URL バーに入力された文字列を URL として読む（URL とみなせなければ None）
+///
+/// - 貼り付けた URL に混じった改行やタブは除く
+/// - `%` の後ろが 16 進数 2 桁でなければ `%25` にする
+/// - スキーム付きの URL はそのまま使う
+/// - `example.com/path` のようなスキームのないホスト名には `https://` を付ける
+///   （`例え。jp` の全角のドットも区切りとして扱う）
+/// - パスの空白などはパーセントエンコードし、IDN は punycode にする（url クレートが行う）
+pub fn fixup_input(input: &str) -> Option<Url> {
+    let input: String = input.chars().filter(|c| !c.is_control()).collect();
+    let input = fix_percent_escapes(input.trim());
+    if input.is_empty() {
+        return None;
+    }
+
+    if let Ok(url) = Url::parse(&input)
+        && BUILTIN_SCHEMES.contains(&url.scheme())
+    {
+        return Some(url);
+    }
+
+    let end = input.find(['/', '?', '#']).unwrap_or(input.len());
+    let (host, rest) = input.split_at(end);
+    let host = host.replace(['。', '．', '｡'], ".");
+    if !looks_like_host(&host) {
+        return None;
+    }
+    match Url::parse(&format!("https://{host}{rest}")) {
+        Ok(url) => Some(url),
+        Err(e) => {
+            log::info!("Not a valid URL {:?}: {}", input, e);
+            None
+        }
+    }
+}
+
+/// 後ろが 16 進数 2 桁でない `%` を `%25` にする
+fn fix_percent_escapes(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity(input.len());
+    for (i, c) in input.char_indices() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+        if c == '%' && !escaped {
+            out.push_str("%25");
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// コマンドラインで渡された URL かファイルのパスを開く URL にする
+///
+/// `orinium ./page.html` のような相対パスは cwd から辿る。
+pub fn resolve_command_line(arg: &str, cwd: &Path) -> Option<Url> {
+    // `C:\page.html` のドライブ文字はスキームとみなさない
+    if let Ok(url) = Url::parse(arg)
+        && url.scheme().len() > 1
+    {
+        return Some(url);
+    }
+
+    let path = cwd.join(arg);
+    let path = path.canonicalize().unwrap_or(path);
+    Url::from_file_path(path).ok()
+}
+
+/// 空白を含まず、`localhost` か IP アドレスかドットを含むホスト名なら URL とみなす
+///
+/// `3.14` のように最後のラベルが数字だけのものは IPv4 アドレスでなければ URL としない。
+fn looks_like_host(host: &str) -> bool {
+    if host.is_empty() || host.contains(char::is_whitespace) {
+        return false;
+    }
+    // [::1]:8080
+    if host.starts_with('[') {
+        return true;
+    }
+
+    let host = host.rsplit_once(':').map_or(host, |(h, port)| {
+        if port.chars().all(|c| c.is_ascii_digit()) {
+            h
+        } else {
+            host
+        }
+    });
+    if host == "localhost" {
+        return true;
+    }
+    if !host.contains('.') || host.starts_with('.') || host.ends_with('.') {
+        return false;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let numeric = |label: &&str| label.chars().all(|c| c.is_ascii_digit());
+    !labels.last().is_some_and(numeric) || (labels.len() == 4 && labels.iter().all(numeric))
+}
+
+/// 入力欄の下に出す補完候補
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub url: Url,
+    pub title: Option<String>,
+}
+
+/// URL バーの編集状態
+#[derive(Debug, Default)]
+pub struct UrlBar {
+    text: String,
+    /// キャレットの位置（バイトオフセット）
+    cursor: usize,
+    focused: bool,
+    /// 全選択されているか（次の入力で置き換える）
+    all_selected: bool,
+    suggestions: Vec<Suggestion>,
+    /// 矢印キーで選んでいる候補
+    selected_suggestion: Option<usize>,
+    /// 表示中のタブがリーダーモードか（ボタンを押された状態で描く）
+    reader_mode: bool,
+    /// IME で変換中の文字列
+    preedit: Option<Preedit>,
+    /// 表示中のページの接続の安全性（入力欄の左端に印を出す）
+    security: Security,
+    /// 接続の詳細を出しているか
+    security_popup: bool,
+    /// 拡張機能のボタンに描く文字列（リーダーモードのボタンの右に並べる）
+    action_buttons: Vec<String>,
+    /// 表示中のページで広告をブロックしているかと止めたリクエストの数
+    /// （None ならボタンを出さない）
+    content_blocking: Option<(bool, usize)>,
+}
+
+/// 入力欄に表示する文字列の位置（URL バーの座標）
+struct FieldText {
+    text: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    line_height: f32,
+    /// 入力欄の内側の幅
+    inner_width: f32,
+    caret_x: f32,
+    /// IME で変換中の部分の左右の x 座標
+    preedit_x: Option<(f32, f32)>,
+}
+
+impl UrlBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    /// 入力を全選択しているか（フォーカスした直後など）
+    pub fn is_all_selected(&self) -> bool {
+        self.all_selected
+    }
+
+    /// 表示中のページの URL を表示する（編集中は変えない）
+    ///
+    /// IDN のホスト名などは読める形で出す（[`idn::display_url`]）。
+    pub fn show_url(&mut self, url: Option<&Url>) {
+        if self.focused {
+            return;
+        }
+        self.text = url.map(idn::display_url).unwrap_or_default();
+        self.cursor = self.text.len();
+    }
+
+    pub fn set_reader_mode(&mut self, reader_mode: bool) {
+        self.reader_mode = reader_mode;
+    }
+
+    /// 拡張機能のボタンを labels にする
+    pub fn set_action_buttons(&mut self, labels: Vec<String>) {
+        self.action_buttons = labels;
+    }
+
+    /// 表示中のページの接続の安全性を設定する。変わったら詳細は閉じる
+    /// 入力欄の右に出す広告ブロックのボタン（None なら出さない）
+    pub fn set_content_blocking(&mut self, content_blocking: Option<(bool, usize)>) {
+        self.content_blocking = content_blocking;
+    }
+
+    pub fn set_security(&mut self, security: Security) {
+        if self.security != security {
+            self.security = security;
+            self.security_popup = false;
+        }
+    }
+
+    pub fn security(&self) -> &Security {
+        &self.security
+    }
+
+    /// 接続の詳細を開くか閉じる（入力欄のフォーカスは外す）
+    pub fn toggle_security_popup(&mut self) {
+        let open = !self.security_popup;
+        self.blur();
+        self.security_popup = open && self.security != Security::Local;
+    }
+
+    pub fn is_security_popup_open(&self) -> bool {
+        self.security_popup
+    }
+
+    /// フォーカスして全選択する
+    pub fn focus(&mut self) {
+        self.focused = true;
+        self.all_selected = true;
+        self.cursor = self.text.len();
+        self.preedit = None;
+    }
+
+    /// フォーカスを外す（接続の詳細も閉じる）。編集内容は次の show_url で捨てられる
+    pub fn blur(&mut self) {
+        self.focused = false;
+        self.all_selected = false;
+        self.preedit = None;
+        self.security_popup = false;
+        self.set_suggestions(Vec::new());
+    }
+
+    /// IME で変換中の文字列を設定する。空なら変換をやめる
+    ///
+    /// 確定するまではキャレットの位置（全選択なら全体の代わり）に表示だけする。
+    pub fn set_preedit(&mut self, text: &str, cursor: Option<usize>) {
+        self.preedit = Preedit::new(text, cursor);
+    }
+
+    pub fn preedit(&self) -> Option<&Preedit> {
+        self.preedit.as_ref()
+    }
+
+    /// IME で確定した文字列を入れる
+    pub fn commit_preedit(&mut self, s: &str) {
+        self.preedit = None;
+        self.insert_str(s);
+    }
+
+    /// 補完候補を入れ替える。候補の選択は解除される
+    pub fn set_suggestions(&mut self, suggestions: Vec<Suggestion>) {
+        self.suggestions = suggestions;
+        self.selected_suggestion = None;
+    }
+
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    pub fn selected_suggestion(&self) -> Option<&Suggestion> {
+        self.suggestions.get(self.selected_suggestion?)
+    }
+
+    /// 次の候補を選ぶ（最後の候補で止まる）
+    pub fn select_next_suggestion(&mut self) {
+        if self.suggestions.is_empty() {
+            return;
+        }
+        self.selected_suggestion = Some(match self.selected_suggestion {
+            Some(i) => (i + 1).min(self.suggestions.len() - 1),
+            None => 0,
+        });
+    }
+
+    /// 前の候補を選ぶ。先頭より前に戻ると入力した文字列に戻る
+    pub fn select_previous_suggestion(&mut self) {
+        self.selected_suggestion = match self.selected_suggestion {
+            Some(0) | None => None,
+            Some(i) => Some(i - 1),
+        };
+    }
+
+    pub fn select_all(&mut self) {
+        self.all_selected = true;
+        self.cursor = self.text.len();
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        // 改行は URL に入れない
+        let s: String = s.chars().filter(|c| !c.is_control()).collect();
+        if self.all_selected {
+            self.text.clear();
+            self.cursor = 0;
+            self.all_selected = false;
+        }
+        self.text.insert_str(self.cursor, &s);
+        self.cursor += s.len();
+    }
+
+    /// キャレットの前の 1 文字（全選択なら全体）を消す
+    pub fn backspace(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        if let Some(prev) = self.prev_boundary() {
+            self.text.replace_range(prev..self.cursor, "");
+            self.cursor = prev;
+        }
+    }
+
+    /// キャレットの後ろの 1 文字（全選択なら全体）を消す
+    pub fn delete(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        if let Some(next) = self.next_boundary() {
+            self.text.replace_range(self.cursor..next, "");
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        self.all_selected = false;
+        if let Some(prev) = self.prev_boundary() {
+            self.cursor = prev;
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        self.all_selected = false;
+        if let Some(next) = self.next_boundary() {
+            self.cursor = next;
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.all_selected = false;
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.all_selected = false;
+        self.cursor = self.text.len();
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        if !self.all_selected {
+            return false;
+        }
+        self.text.clear();
+        self.cursor = 0;
+        self.all_selected = false;
+        true
+    }
+
+    fn prev_boundary(&self) -> Option<usize> {
+        self.text[..self.cursor]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self) -> Option<usize> {
+        self.text[self.cursor..]
+            .chars()
+            .next()
+            .map(|c| self.cursor + c.len_utf8())
+    }
+
+    /// 入力欄の矩形 (x, y, width, height)
+    ///
+    /// 右端には広告ブロックのボタン、リーダーモードのボタン、拡張機能のボタンを置く。
+    fn field_rect(&self, width: f32) -> (f32, f32, f32, f32) {
+        let actions = self.action_buttons.len() as f32 * (ACTION_BUTTON_WIDTH + FIELD_MARGIN_X);
+        (
+            FIELD_MARGIN_X,
+            FIELD_MARGIN_Y,
+            (width
+                - FIELD_MARGIN_X * 3.0
+                - self.content_blocking_button_width()
+                - READER_BUTTON_WIDTH
+                - actions)
+                .max(0.0),
+            URL_BAR_HEIGHT - FIELD_MARGIN_Y * 2.0,
+        )
+    }
+
+    /// 広告ブロックのボタンとその右の余白の幅（ボタンを出さなければ 0）
+    fn content_blocking_button_width(&self) -> f32 {
+        match self.content_blocking {
+            Some(_) => CONTENT_BLOCKING_BUTTON_WIDTH + FIELD_MARGIN_X,
+            None => 0.0,
+        }
+    }
+
+    /// 広告ブロックのボタンの矩形
+    fn content_blocking_button_rect(&self, width: f32) -> (f32, f32, f32, f32) {
+        let (fx, fy, fw, fh) = self.field_rect(width);
+        (
+            fx + fw + FIELD_MARGIN_X,
+            fy,
+            CONTENT_BLOCKING_BUTTON_WIDTH,
+            fh,
+        )
+    }
+
+    /// (x, y) が広告ブロックのボタンの上にあるか
+    pub fn content_blocking_button_hit_test(&self, width: f32, x: f32, y: f32) -> bool {
+        if self.content_blocking.is_none() {
+            return false;
+        }
+        let (bx, by, bw, bh) = self.content_blocking_button_rect(width);
+        x >= bx && x <= bx + bw && y >= by && y <= by + bh
+    }
+
+    /// リーダーモードのボタンの矩形
+    fn reader_button_rect(&self, width: f32) -> (f32, f32, f32, f32) {
+        let (fx, fy, fw, fh) = self.field_rect(width);
+        (
+            fx + fw + FIELD_MARGIN_X + self.content_blocking_button_width(),
+            fy,
+            READER_BUTTON_WIDTH,
+            fh,
+        )
+    }
+
+    /// (x, y) がリーダーモードのボタンの上にあるか
+    pub fn reader_button_hit_test(&self, width: f32, x: f32, y: f32) -> bool {
+        let (bx, by, bw, bh) = self.reader_button_rect(width);
+        x >= bx && x <= bx + bw && y >= by && y <= by + bh
+    }
+
+    /// i 番目の拡張機能のボタンの矩形
+    fn action_button_rect(&self, width: f32, i: usize) -> (f32, f32, f32, f32) {
+        let (rx, ry, rw, rh) = self.reader_button_rect(width);
+        let x = rx + rw + FIELD_MARGIN_X + (ACTION_BUTTON_WIDTH + FIELD_MARGIN_X) * i as f32;
+        (x, ry, ACTION_BUTTON_WIDTH, rh)
+    }
+
+    /// (x, y) にある拡張機能のボタンの番号
+    pub fn action_button_hit_test(&self, width: f32, x: f32, y: f32) -> Option<usize> {
+        (0..self.action_buttons.len()).find(|&i| {
+            let (bx, by, bw, bh) = self.action_button_rect(width, i);
+            x >= bx && x <= bx + bw && y >= by && y <= by + bh
+        })
+    }
+
+    /// 入力欄の左端の鍵の印の幅（印を出さなければ 0）
+    fn security_badge_width(&self) -> f32 {
+        match self.security {
+            Security::Local => 0.0,
+            _ => SECURITY_BADGE_WIDTH,
+        }
+    }
+
+    /// (x, y) が鍵の印の上にあるか
+    pub fn security_badge_hit_test(&self, width: f32, x: f32, y: f32) -> bool {
+        let (fx, fy, _, fh) = self.field_rect(width);
+        x >= fx && x <= fx + self.security_badge_width() && y >= fy && y <= fy + fh
+    }
+
+    /// (x, y) が入力欄の上にあるか
+    pub fn hit_test(&self, width: f32, x: f32, y: f32) -> bool {
+        let (fx, fy, fw, fh) = self.field_rect(width);
+        x >= fx && x <= fx + fw && y >= fy && y <= fy + fh
+    }
+
+    /// i 番目の補完候補の矩形。候補は URL バーの下、入力欄と同じ幅に並ぶ
+    fn suggestion_rect(&self, width: f32, i: usize) -> (f32, f32, f32, f32) {
+        let (fx, _, fw, _) = self.field_rect(width);
+        (
+            fx,
+            URL_BAR_HEIGHT + SUGGESTION_HEIGHT * i as f32,
+            fw,
+            SUGGESTION_HEIGHT,
+        )
+    }
+
+    /// (x, y) にある補完候補の番号（y は URL バーの上端から）
+    pub fn suggestion_hit_test(&self, width: f32, x: f32, y: f32) -> Option<usize> {
+        (0..self.suggestions.len()).find(|&i| {
+            let (sx, sy, sw, sh) = self.suggestion_rect(width, i);
+            x >= sx && x <= sx + sw && y >= sy && y < sy + sh
+        })
+    }
+
+    /// IME の変換候補を出す位置（キャレットの矩形 (x, y, width, height)）
+    pub fn caret_rect(
+        &self,
+        width: f32,
+        measurer: &dyn TextMeasurer<TextStyle>,
+    ) -> (f32, f32, f32, f32) {
+        let style = TextStyle {
+            font_size: FONT_SIZE,
+            ..Default::default()
+        };
+        let text = self.field_text(width, style, measurer);
+        (text.x + text.caret_x, text.y, 1.0, text.line_height)
+    }
+
+    /// 表示する文字列（変換中の文字列を差し込んだもの）とキャレット、変換中の範囲
+    fn composed_text(&self) -> (String, usize, Option<Range<usize>>) {
+        let Some(preedit) = self.preedit.as_ref() else {
+            return (self.text.clone(), self.cursor, None);
+        };
+        let range = if self.all_selected {
+            0..self.text.len()
+        } else {
+            self.cursor..self.cursor
+        };
+        let composition = preedit.compose(&self.text, range);
+        (
+            composition.text,
+            composition.caret,
+            Some(composition.preedit),
+        )
+    }
+
+    fn field_text(
+        &self,
+        width: f32,
+        style: TextStyle,
+        measurer: &dyn TextMeasurer<TextStyle>,
+    ) -> FieldText {
+        let (text, caret, preedit) = self.composed_text();
+        let metrics = measurer
+            .measure(&TextMeasureRequest {
+                text: text.clone(),
+                style,
+                max_width: None,
+                wrap: false,
+            })
+            .ok();
+        let line_height = metrics
+            .as_ref()
+            .map(|m| m.line_height())
+            .filter(|h| *h > 0.0)
+            .unwrap_or(FONT_SIZE * 1.2);
+        let x_at = |offset| metrics.as_ref().map_or(0.0, |m| m.caret_position(offset).0);
+        let caret_x = x_at(caret);
+
+        let (fx, fy, fw, fh) = self.field_rect(width);
+        let badge_width = self.security_badge_width();
+        let inner_width = (fw - FIELD_PADDING * 2.0 - badge_width).max(0.0);
+        // キャレットが見えるように横にずらす
+        let text_scroll = (caret_x - inner_width).max(0.0);
+
+        FieldText {
+            x: fx + badge_width + FIELD_PADDING - text_scroll,
+            y: fy + (fh - line_height) / 2.0,
+            width: metrics.as_ref().map_or(0.0, |m| m.width),
+            line_height,
+            inner_width,
+            caret_x,
+            preedit_x: preedit.map(|r| (x_at(r.start), x_at(r.end))),
+            text,
+        }
+    }
+
+    /// 幅 width の URL バーを描く DrawCommand
+    pub fn draw_commands(
+        &self,
+        width: f32,
+        theme: &ChromeTheme,
+        measurer: &dyn TextMeasurer<TextStyle>,
+    ) -> Vec<DrawCommand> {
+        let style = TextStyle {
+            font_size: FONT_SIZE,
+            color: theme.text,
+            ..Default::default()
+        };
+        let field_text = self.field_text(width, style, measurer);
+        let FieldText {
+            x: text_x,
+            y: text_y,
+            width: text_width,
+            line_height,
+            inner_width,
+            caret_x,
+            ..
+        } = field_text;
+
+        let (fx, fy, fw, fh) = self.field_rect(width);
+        let border = if self.focused {
+            theme.accent
+        } else {
+            theme.field_border
+        };
+
+        let mut commands = vec![
+            DrawCommand::DrawRect {
+                x: 0.0,
+                y: 0.0,
+                width,
+                height: URL_BAR_HEIGHT,
+                color: theme.bar_background,
+            },
+            DrawCommand::DrawRect {
+                x: 0.0,
+                y: URL_BAR_HEIGHT - 1.0,
+                width,
+                height: 1.0,
+                color: theme.bar_border,
+            },
+            DrawCommand::DrawRect {
+                x: fx,
+                y: fy,
+                width: fw,
+                height: fh,
+                color: border,
+            },
+            DrawCommand::DrawRect {
+                x: fx + 1.0,
+                y: fy + 1.0,
+                width: (fw - 2.0).max(0.0),
+                height: (fh - 2.0).max(0.0),
+                color: theme.field_background,
+            },
+        ];
+        commands.extend(self.security_badge_commands(fx, fy, fh, theme));
+        commands.push(DrawCommand::PushClip {
+            x: fx + self.security_badge_width() + FIELD_PADDING,
+            y: fy,
+            width: inner_width,
+            height: fh,
+        });
+
+        if self.focused && self.all_selected && self.preedit.is_none() && !self.text.is_empty() {
+            commands.push(DrawCommand::DrawRect {
+                x: text_x,
+                y: text_y,
+                width: text_width,
+                height: line_height,
+                color: SELECTION_COLOR,
+            });
+        }
+
+        commands.push(DrawCommand::DrawText {
+            x: text_x,
+            y: text_y,
+            text: field_text.text,
+            style,
+            // 折り返さないように少し余裕を持たせる
+            max_width: text_width + FONT_SIZE,
+        });
+
+        // IME で変換中の部分には下線を引く
+        if let Some((start, end)) = field_text.preedit_x {
+            commands.push(DrawCommand::DrawRect {
+                x: text_x + start,
+                y: text_y + line_height - 1.0,
+                width: end - start,
+                height: 1.0,
+                color: theme.text,
+            });
+        }
+
+        if self.focused && (!self.all_selected || self.preedit.is_some()) {
+            commands.push(DrawCommand::DrawRect {
+                x: text_x + caret_x,
+                y: text_y,
+                width: 1.0,
+                height: line_height,
+                color: theme.text,
+            });
+        }
+
+        commands.push(DrawCommand::PopClip);
+
+        if let Some((enabled, blocked)) = self.content_blocking {
+            let (bx, by, bw, bh) = self.content_blocking_button_rect(width);
+            // 止めたものがあれば押された状態で描き、このサイトでブロックしないなら "off" と出す
+            if enabled && blocked > 0 {
+                commands.push(DrawCommand::DrawRect {
+                    x: bx,
+                    y: by,
+                    width: bw,
+                    height: bh,
+                    color: theme.reader_button_active,
+                });
+            }
+            let label = if enabled {
+                blocked.to_string()
+            } else {
+                "off".to_string()
+            };
+            let label_width = measurer
+                .measure(&TextMeasureRequest {
+                    text: label.clone(),
+                    style,
+                    max_width: None,
+                    wrap: false,
+                })
+                .map_or(FONT_SIZE, |m| m.width);
+            commands.push(DrawCommand::DrawText {
+                x: bx + (bw - label_width).max(0.0) / 2.0,
+                y: by + (bh - line_height) / 2.0,
+                text: label,
+                style,
+                max_width: bw,
+            });
+        }
+
+        let (bx, by, bw, bh) = self.reader_button_rect(width);
+        if self.reader_mode {
+            commands.push(DrawCommand::DrawRect {
+                x: bx,
+                y: by,
+                width: bw,
+                height: bh,
+                color: theme.reader_button_active,
+            });
+        }
+        commands.push(DrawCommand::DrawText {
+            x: bx + (bw - FONT_SIZE * 1.2) / 2.0,
+            y: by + (bh - line_height) / 2.0,
+            text: "Aa".to_string(),
+            style,
+            max_width: bw,
+        });
+        for (i, label) in self.action_buttons.iter().enumerate() {
+            let (bx, by, bw, bh) = self.action_button_rect(width, i);
+            let label_width = measurer
+                .measure(&TextMeasureRequest {
+                    text: label.clone(),
+                    style,
+                    max_width: None,
+                    wrap: false,
+                })
+                .map_or(FONT_SIZE, |m| m.width);
+            commands.push(DrawCommand::DrawText {
+                x: bx + (bw - label_width).max(0.0) / 2.0,
+                y: by + (bh - line_height) / 2.0,
+                text: label.clone(),
+                style,
+                max_width: bw,
+            });
+        }
+
+        if self.focused {
+            commands.extend(self.suggestion_commands(width, style, line_height, theme, measurer));
+        }
+        if self.security_popup {
+            commands.extend(self.security_popup_commands(width, style, line_height, theme));
+        }
+        commands
+    }
+
+    /// 入力欄の左端の印（https なら鍵、http なら感嘆符）
+    fn security_badge_commands(
+        &self,
+        fx: f32,
+        fy: f32,
+        fh: f32,
+        theme: &ChromeTheme,
+    ) -> Vec<DrawCommand> {
+        let cx = fx + FIELD_PADDING / 2.0 + SECURITY_BADGE_WIDTH / 2.0;
+        let cy = fy + fh / 2.0;
+        let rect = |x: f32, y: f32, width: f32, height: f32, color| DrawCommand::DrawRect {
+            x,
+            y,
+            width,
+            height,
+            color,
+        };
+        match self.security {
+            Security::Local => Vec::new(),
+            Security::Secure(_) => vec![
+                // つる
+                rect(cx - 4.0, cy - 7.0, 8.0, 2.0, theme.text),
+                rect(cx - 4.0, cy - 7.0, 2.0, 6.0, theme.text),
+                rect(cx + 2.0, cy - 7.0, 2.0, 6.0, theme.text),
+                // 本体
+                rect(cx - 6.0, cy - 1.0, 12.0, 8.0, theme.text),
+            ],
+            Security::NotSecure => vec![
+                rect(cx - 1.0, cy - 7.0, 2.0, 10.0, theme.not_secure),
+                rect(cx - 1.0, cy + 5.0, 2.0, 2.0, theme.not_secure),
+            ],
+        }
+    }
+
+    /// 鍵の印の下に出す接続の詳細
+    fn security_popup_commands(
+        &self,
+        width: f32,
+        style: TextStyle,
+        line_height: f32,
+        theme: &ChromeTheme,
+    ) -> Vec<DrawCommand> {
+        let lines = self.security.details(SystemTime::now());
+        let (x, _, fw, _) = self.field_rect(width);
+        let w = SECURITY_POPUP_WIDTH.min(fw);
+        let height = SECURITY_POPUP_LINE_HEIGHT * lines.len() as f32 + FIELD_PADDING * 2.0;
+
+        let mut commands = vec![
+            DrawCommand::DrawRect {
+                x,
+                y: URL_BAR_HEIGHT,
+                width: w,
+                height: height + 1.0,
+                color: theme.field_border,
+            },
+            DrawCommand::DrawRect {
+                x: x + 1.0,
+                y: URL_BAR_HEIGHT,
+                width: (w - 2.0).max(0.0),
+                height,
+                color: theme.field_background,
+            },
+            DrawCommand::PushClip {
+                x: x + FIELD_PADDING,
+                y: URL_BAR_HEIGHT,
+                width: (w - FIELD_PADDING * 2.0).max(0.0),
+                height,
+            },
+        ];
+        for (i, line) in lines.into_iter().enumerate() {
+            let y = URL_BAR_HEIGHT + FIELD_PADDING + SECURITY_POPUP_LINE_HEIGHT * i as f32;
+            // 1 行目（安全かどうか）だけ色を変える
+            let color = match (i, &self.security) {
+                (0, Security::NotSecure) => theme.not_secure,
+                _ => theme.text,
+            };
+            commands.push(DrawCommand::DrawText {
+                x: x + FIELD_PADDING,
+                y: y + (SECURITY_POPUP_LINE_HEIGHT - line_height) / 2.0,
+                text: line,
+                style: TextStyle { color, ..style },
+                max_width: w,
+            });
+        }
+        commands.push(DrawCommand::PopClip);
+        commands
+    }
+
+    /// 補完候補のドロップダウン（タイトルと URL を 1 行に並べる）
+    fn suggestion_commands(
+        &self,
+        width: f32,
+        style: TextStyle,
+        line_height: f32,
+        theme: &ChromeTheme,
+        measurer: &dyn TextMeasurer<TextStyle>,
+    ) -> Vec<DrawCommand> {
+        let mut commands = Vec::new();
+        if self.suggestions.is_empty() {
+            return commands;
+        }
+
+        let (x, y, w, _) = self.suggestion_rect(width, 0);
+        let height = SUGGESTION_HEIGHT * self.suggestions.len() as f32;
+        commands.push(DrawCommand::DrawRect {
+            x,
+            y,
+            width: w,
+            height: height + 1.0,
+            color: theme.field_border,
+        });
+        commands.push(DrawCommand::DrawRect {
+            x: x + 1.0,
+            y,
+            width: (w - 2.0).max(0.0),
+            height,
+            color: theme.field_background,
+        });
+
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            let (sx, sy, sw, sh) = self.suggestion_rect(width, i);
+            if self.selected_suggestion == Some(i) {
+                commands.push(DrawCommand::DrawRect {
+                    x: sx + 1.0,
+                    y: sy,
+                    width: (sw - 2.0).max(0.0),
+                    height: sh,
+                    color: theme.suggestion_selected,
+                });
+            }
+
+            let url = suggestion.url.to_string();
+            let line = match suggestion.title.as_deref() {
+                Some(title) => format!("{title} — "),
+                None => String::new(),
+            };
+            let text_y = sy + (sh - line_height) / 2.0;
+            commands.push(DrawCommand::PushClip {
+                x: sx + FIELD_PADDING,
+                y: sy,
+                width: (sw - FIELD_PADDING * 2.0).max(0.0),
+                height: sh,
+            });
+            // タイトルと URL は色を変えるため別々に描く
+            let measure = |text: &str| {
+                measurer
+                    .measure(&TextMeasureRequest {
+                        text: text.to_string(),
+                        style,
+                        max_width: None,
+                        wrap: false,
+                    })
+                    .map_or(0.0, |m| m.width)
+            };
+            let title_width = measure(&line);
+            let url_width = measure(&url);
+            commands.push(DrawCommand::DrawText {
+                x: sx + FIELD_PADDING,
+                y: text_y,
+                text: line,
+                style,
+                max_width: title_width + FONT_SIZE,
+            });
+            commands.push(DrawCommand::DrawText {
+                x: sx + FIELD_PADDING + title_width,
+                y: text_y,
+                max_width: url_width + FONT_SIZE,
+                text: url,
+                style: TextStyle {
+                    color: theme.suggestion_url,
+                    ..style
+                },
+            });
+            commands.push(DrawCommand::PopClip);
+        }
+
+        commands
+    }
+}
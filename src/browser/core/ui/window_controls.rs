@@ -0,0 +1,165 @@
+//! ブラウザが描くタイトルバーのウィンドウボタン
+//!
+//! OS の枠を消したとき（設定の `window.custom_titlebar`）に、タブバーの右端に
+//! 最小化・最大化・閉じるのボタンを並べる。タブバーの空いているところはウィンドウを
+//! 動かす取っ手に、ウィンドウの縁は大きさを変える取っ手になる。座標はタブバーと同じく
+//! ウィンドウの論理ピクセル。
+
+use winit::window::ResizeDirection;
+
+use crate::engine::layouter::types::Color;
+use crate::engine::renderer_model::DrawCommand;
+
+use super::tab_strip::TAB_STRIP_HEIGHT;
+use super::theme::ChromeTheme;
+
+/// ボタン 1 つの幅
+const BUTTON_WIDTH: f32 = 46.0;
+/// ボタンの記号の大きさ
+const GLYPH_SIZE: f32 = 10.0;
+/// 記号の線の太さ
+const STROKE: f32 = 1.0;
+
+/// ボタンを並べる幅（タブはこの手前までに並べる）
+pub const WINDOW_CONTROLS_WIDTH: f32 = BUTTON_WIDTH * 3.0;
+
+/// ドラッグで大きさを変えられるウィンドウの縁の幅
+pub const RESIZE_BORDER: f32 = 5.0;
+
+/// ウィンドウボタン
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowControl {
+    Minimize,
+    /// 最大化と元の大きさを切り替える
+    Maximize,
+    Close,
+}
+
+impl WindowControl {
+    /// 左から並べる順
+    const ALL: [WindowControl; 3] = [
+        WindowControl::Minimize,
+        WindowControl::Maximize,
+        WindowControl::Close,
+    ];
+}
+
+/// 幅 width のウィンドウでの control の矩形 (x, y, width, height)
+fn button_rect(width: f32, control: WindowControl) -> (f32, f32, f32, f32) {
+    let index = WindowControl::ALL
+        .iter()
+        .position(|c| *c == control)
+        .unwrap_or(0);
+    (
+        width - WINDOW_CONTROLS_WIDTH + BUTTON_WIDTH * index as f32,
+        0.0,
+        BUTTON_WIDTH,
+        TAB_STRIP_HEIGHT,
+    )
+}
+
+/// (x, y) にあるウィンドウボタン
+pub fn hit_test(width: f32, x: f32, y: f32) -> Option<WindowControl> {
+    WindowControl::ALL.into_iter().find(|control| {
+        let (bx, by, bw, bh) = button_rect(width, *control);
+        x >= bx && x < bx + bw && y >= by && y < by + bh
+    })
+}
+
+/// (x, y) が大きさ (width, height) のウィンドウの縁なら、大きさを変える向き
+pub fn resize_edge((width, height): (f32, f32), x: f32, y: f32) -> Option<ResizeDirection> {
+    let west = x < RESIZE_BORDER;
+    let east = x >= width - RESIZE_BORDER;
+    let north = y < RESIZE_BORDER;
+    let south = y >= height - RESIZE_BORDER;
+    match (north, south, west, east) {
+        (true, _, true, _) => Some(ResizeDirection::NorthWest),
+        (true, _, _, true) => Some(ResizeDirection::NorthEast),
+        (_, true, true, _) => Some(ResizeDirection::SouthWest),
+        (_, true, _, true) => Some(ResizeDirection::SouthEast),
+        (true, ..) => Some(ResizeDirection::North),
+        (_, true, ..) => Some(ResizeDirection::South),
+        (_, _, true, _) => Some(ResizeDirection::West),
+        (.., true) => Some(ResizeDirection::East),
+        _ => None,
+    }
+}
+
+/// 幅 width のウィンドウの右上にウィンドウボタンを描く DrawCommand
+pub fn draw_commands(width: f32, theme: &ChromeTheme) -> Vec<DrawCommand> {
+    let mut commands = vec![DrawCommand::DrawRect {
+        x: width - WINDOW_CONTROLS_WIDTH,
+        y: 0.0,
+        width: WINDOW_CONTROLS_WIDTH,
+        height: TAB_STRIP_HEIGHT,
+        color: theme.strip_background,
+    }];
+
+    for control in WindowControl::ALL {
+        let (x, y, w, h) = button_rect(width, control);
+        let left = x + (w - GLYPH_SIZE) / 2.0;
+        let top = y + (h - GLYPH_SIZE) / 2.0;
+        match control {
+            WindowControl::Minimize => commands.push(line_rect(
+                (left, top + GLYPH_SIZE / 2.0),
+                (GLYPH_SIZE, STROKE),
+                theme.button,
+            )),
+            WindowControl::Maximize => {
+                // 枠だけの四角
+                commands.extend([
+                    line_rect((left, top), (GLYPH_SIZE, STROKE), theme.button),
+                    line_rect(
+                        (left, top + GLYPH_SIZE - STROKE),
+                        (GLYPH_SIZE, STROKE),
+                        theme.button,
+                    ),
+                    line_rect((left, top), (STROKE, GLYPH_SIZE), theme.button),
+                    line_rect(
+                        (left + GLYPH_SIZE - STROKE, top),
+                        (STROKE, GLYPH_SIZE),
+                        theme.button,
+                    ),
+                ]);
+            }
+            WindowControl::Close => {
+                // 斜めの線 2 本
+                let right = left + GLYPH_SIZE;
+                let bottom = top + GLYPH_SIZE;
+                commands.extend([
+                    diagonal((left, top), (right, bottom), theme.button),
+                    diagonal((right, top), (left, bottom), theme.button),
+                ]);
+            }
+        }
+    }
+
+    commands
+}
+
+fn line_rect((x, y): (f32, f32), (width, height): (f32, f32), color: Color) -> DrawCommand {
+    DrawCommand::DrawRect {
+        x,
+        y,
+        width,
+        height,
+        color,
+    }
+}
+
+/// from から to への太さ STROKE の線
+fn diagonal(from: (f32, f32), to: (f32, f32), color: Color) -> DrawCommand {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = dx.hypot(dy).max(f32::EPSILON);
+    // 線に垂直な向きに太さの半分ずつ広げる
+    let (nx, ny) = (-dy / length * STROKE / 2.0, dx / length * STROKE / 2.0);
+    DrawCommand::DrawPolygon {
+        points: vec![
+            (from.0 + nx, from.1 + ny),
+            (to.0 + nx, to.1 + ny),
+            (to.0 - nx, to.1 - ny),
+            (from.0 - nx, from.1 - ny),
+        ],
+        color,
+    }
+}
@@ -0,0 +1,70 @@
+//! 訪れたリンクの記録（`:visited` の判定に使う）
+//!
+//! 閲覧履歴と違って URL そのものは持たず、起動ごとに作る鍵で求めたハッシュだけを持つ。
+//! ページを描く側（WebView）に渡すのはこちらなので、ページのレイアウトに使う情報から
+//! 訪れた URL の一覧を取り出すことはできない。フラグメントの違いは同じページとみなす。
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, RandomState};
+use std::rc::Rc;
+
+use url::Url;
+
+use super::browsing_history::BrowsingHistory;
+
+/// タブと WebView で共有する訪れたリンクの記録
+pub type SharedVisitedLinks = Rc<RefCell<VisitedLinks>>;
+
+/// 訪れた URL のハッシュの集合
+#[derive(Debug, Clone, Default)]
+pub struct VisitedLinks {
+    keys: RandomState,
+    hashes: HashSet<u64>,
+}
+
+impl VisitedLinks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 閲覧履歴の URL をすべて訪れたものとして作る
+    pub fn from_history(history: &BrowsingHistory) -> Self {
+        let mut links = Self::new();
+        for visit in history.iter() {
+            links.add(&visit.url);
+        }
+        links
+    }
+
+    pub fn shared(self) -> SharedVisitedLinks {
+        Rc::new(RefCell::new(self))
+    }
+
+    pub fn add(&mut self, url: &Url) {
+        let hash = self.fingerprint(url);
+        self.hashes.insert(hash);
+    }
+
+    pub fn contains(&self, url: &Url) -> bool {
+        self.hashes.contains(&self.fingerprint(url))
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.hashes.clear();
+    }
+
+    fn fingerprint(&self, url: &Url) -> u64 {
+        let mut url = url.clone();
+        url.set_fragment(None);
+        self.keys.hash_one(url.as_str())
+    }
+}
@@ -0,0 +1,367 @@
+//! フォームの送信と入力内容の保存
+//!
+//! 送信ボタンのクリックや入力欄での Enter で、フォームの入力内容をクエリ文字列に
+//! して action の URL に移動する。今のところ method="get" だけに対応する。
+//!
+//! 履歴で戻る / 進むときは、ページを離れる前に入力欄の値を [`FormState`] に取っておき、
+//! 読み込み直した文書に入れ直す。送信したフォームの名前や住所は自動入力の
+//! プロファイルとして覚える。
+//!
+//! 自動入力した値はページのスクリプトから読めないよう、`value` 属性には書かずに
+//! [`FieldValues`] に持つ。
+
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+use url::{Url, form_urlencoded};
+
+use crate::browser::core::autofill::{AutofillField, AutofillProfile};
+use crate::engine::html::HtmlNodeType;
+use crate::engine::html::parser::DomTree;
+use crate::engine::layouter::is_text_input_type;
+use crate::engine::tree::{NodeRef, TreeNode};
+
+/// 文書の入力欄に入っている値
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormState {
+    /// (入力欄のパス, 値) を文書順に
+    pub fields: Vec<(Vec<usize>, String)>,
+}
+
+impl FormState {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+/// root の下のテキスト入力欄の値を集める
+///
+/// パスワード欄の値は残さない。
+pub fn save_form_state(root: &NodeRef<HtmlNodeType>) -> FormState {
+    let mut state = FormState::default();
+    let mut path = Vec::new();
+    collect_fields(root, &mut path, &mut state.fields);
+    state
+}
+
+fn collect_fields(
+    node: &NodeRef<HtmlNodeType>,
+    path: &mut Vec<usize>,
+    out: &mut Vec<(Vec<usize>, String)>,
+) {
+    let n = node.borrow();
+    if is_restorable_field(&n.value) {
+        let value = n.value.get_attr("value").unwrap_or_default();
+        out.push((path.clone(), value.to_string()));
+        return;
+    }
+    for (i, child) in n.children().iter().enumerate() {
+        path.push(i);
+        collect_fields(child, path, out);
+        path.pop();
+    }
+}
+
+/// 保存した値を root の下の入力欄に入れ直す
+///
+/// 文書が変わってパスの先がテキスト入力欄でなくなっていれば、その値は捨てる。
+pub fn restore_form_state(root: &NodeRef<HtmlNodeType>, state: &FormState) {
+    for (path, value) in &state.fields {
+        let Some(node) = path.iter().try_fold(root.clone(), |node, &i| {
+            node.borrow().children().get(i).cloned()
+        }) else {
+            continue;
+        };
+        let mut n = node.borrow_mut();
+        if is_restorable_field(&n.value) {
+            n.value.set_attr("value", value.clone());
+        }
+    }
+}
+
+/// 値を取っておくテキスト入力欄か
+fn is_restorable_field(node: &HtmlNodeType) -> bool {
+    let input_type = node.get_attr("type");
+    node.tag_name() == Some("input")
+        && is_text_input_type(input_type)
+        && !input_type.is_some_and(|t| t.trim().eq_ignore_ascii_case("password"))
+}
+
+/// ボタンを押したときにフォームを送信するか
+///
+/// `<button>` の type の既定値は submit。
+pub fn submits_form(button: &HtmlNodeType) -> bool {
+    let input_type = button
+        .get_attr("type")
+        .map(|t| t.trim().to_ascii_lowercase());
+    match button.tag_name() {
+        Some("button") => !matches!(input_type.as_deref(), Some("button" | "reset")),
+        Some("input") => input_type.as_deref() == Some("submit"),
+        _ => false,
+    }
+}
+
+/// node が属する form 要素（祖先の中で最も近いもの）
+pub fn form_owner(node: &NodeRef<HtmlNodeType>) -> Option<NodeRef<HtmlNodeType>> {
+    let mut current = node.borrow().parent();
+    while let Some(n) = current {
+        if n.borrow().value.tag_name() == Some("form") {
+            return Some(n);
+        }
+        current = n.borrow().parent();
+    }
+    None
+}
+
+/// フォームが送信する (名前, 値) を文書順に集める
+///
+/// submitter は送信に使ったボタンで、名前があればその値も送る。
+/// values に値を持っている欄は、`value` 属性の代わりにその値を送る。
+pub fn form_data(
+    form: &NodeRef<HtmlNodeType>,
+    submitter: Option<&NodeRef<HtmlNodeType>>,
+    values: &FieldValues,
+) -> Vec<(String, String)> {
+    let mut data = Vec::new();
+    let mut stack: Vec<NodeRef<HtmlNodeType>> =
+        form.borrow().children().iter().rev().cloned().collect();
+
+    while let Some(node) = stack.pop() {
+        let n = node.borrow();
+        stack.extend(n.children().iter().rev().cloned());
+
+        let Some(name) = n.value.get_attr("name").filter(|name| !name.is_empty()) else {
+            continue;
+        };
+        if n.value.has_attr("disabled") {
+            continue;
+        }
+        let is_submitter = submitter.is_some_and(|s| Rc::ptr_eq(s, &node));
+        let value = values.value_of(&node);
+
+        let value = match n.value.tag_name() {
+            Some("input") => {
+                let input_type = n
+                    .value
+                    .get_attr("type")
+                    .unwrap_or("text")
+                    .trim()
+                    .to_ascii_lowercase();
+                match input_type.as_str() {
+                    "checkbox" | "radio" if n.value.has_attr("checked") => {
+                        if n.value.has_attr("value") {
+                            value
+                        } else {
+                            "on".to_string()
+                        }
+                    }
+                    "checkbox" | "radio" | "file" => continue,
+                    "submit" | "reset" | "button" | "image" if !is_submitter => continue,
+                    _ => value,
+                }
+            }
+            Some("button") if is_submitter => value,
+            Some("textarea") => DomTree::inner_text(&node),
+            Some("select") => match selected_option(&node) {
+                Some(option) => option_value(&option),
+                None => continue,
+            },
+            _ => continue,
+        };
+
+        data.push((name.to_string(), value));
+    }
+
+    data
+}
+
+/// 送信するフォームの、自動入力できる欄の値
+pub fn autofill_profile(form: &NodeRef<HtmlNodeType>, values: &FieldValues) -> AutofillProfile {
+    let mut profile = AutofillProfile::new();
+    let mut stack: Vec<NodeRef<HtmlNodeType>> =
+        form.borrow().children().iter().rev().cloned().collect();
+
+    while let Some(node) = stack.pop() {
+        let n = node.borrow();
+        stack.extend(n.children().iter().rev().cloned());
+        if n.value.has_attr("disabled") {
+            continue;
+        }
+        if let Some(field) = AutofillField::for_input(&n.value) {
+            profile.set(field, &values.value_of(&node));
+        }
+    }
+
+    profile
+}
+
+/// DOM の `value` 属性の代わりに持っている入力欄の値
+///
+/// 属性に書くと outerHTML や getAttribute で読め、要素を複製しても残るので、
+/// 自動入力した値はここに持つ。一度ここに入れた欄は、その後の編集もここに書く。
+#[derive(Debug, Default)]
+pub struct FieldValues {
+    values: Vec<(Weak<RefCell<TreeNode<HtmlNodeType>>>, String)>,
+}
+
+impl FieldValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// node の値をここに持っていれば、その値
+    pub fn get(&self, node: &NodeRef<HtmlNodeType>) -> Option<&str> {
+        let node = Rc::downgrade(node);
+        self.values
+            .iter()
+            .find(|(n, _)| n.ptr_eq(&node))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// node の値（ここになければ `value` 属性、それもなければ空）
+    pub fn value_of(&self, node: &NodeRef<HtmlNodeType>) -> String {
+        match self.get(node) {
+            Some(value) => value.to_string(),
+            None => node
+                .borrow()
+                .value
+                .get_attr("value")
+                .unwrap_or_default()
+                .to_string(),
+        }
+    }
+
+    /// node の値を value にする（文書から外れた欄の値は捨てる）
+    pub fn set(&mut self, node: &NodeRef<HtmlNodeType>, value: String) {
+        self.values.retain(|(n, _)| n.strong_count() > 0);
+        let node = Rc::downgrade(node);
+        match self.values.iter_mut().find(|(n, _)| n.ptr_eq(&node)) {
+            Some((_, v)) => *v = value,
+            None => self.values.push((node, value)),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+}
+
+/// ログインフォームのユーザー名の欄とパスワードの欄
+#[derive(Debug, Clone)]
+pub struct LoginFields {
+    pub username: Option<NodeRef<HtmlNodeType>>,
+    pub password: NodeRef<HtmlNodeType>,
+}
+
+/// form の最初のパスワード欄と、その前にある最後のテキストかメールの欄
+///
+/// パスワード欄がなければログインフォームではないので None。
+pub fn login_fields(form: &NodeRef<HtmlNodeType>) -> Option<LoginFields> {
+    let mut username = None;
+    let mut stack: Vec<NodeRef<HtmlNodeType>> =
+        form.borrow().children().iter().rev().cloned().collect();
+
+    while let Some(node) = stack.pop() {
+        let n = node.borrow();
+        stack.extend(n.children().iter().rev().cloned());
+        if n.value.tag_name() != Some("input") || n.value.has_attr("disabled") {
+            continue;
+        }
+        let input_type = n
+            .value
+            .get_attr("type")
+            .map(|t| t.trim().to_ascii_lowercase())
+            .unwrap_or_default();
+        match input_type.as_str() {
+            "password" => {
+                drop(n);
+                return Some(LoginFields {
+                    username,
+                    password: node,
+                });
+            }
+            "" | "text" | "email" | "tel" => username = Some(node.clone()),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// フォームを送信するときに移動する URL（送信できなければ None）
+///
+/// action が空なら document_url に送る。
+pub fn submission_url(
+    form: &NodeRef<HtmlNodeType>,
+    submitter: Option<&NodeRef<HtmlNodeType>>,
+    values: &FieldValues,
+    document_url: &Url,
+    base_url: &Url,
+) -> Option<Url> {
+    let (action, method) = {
+        let f = form.borrow();
+        (
+            f.value
+                .get_attr("action")
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+            f.value
+                .get_attr("method")
+                .unwrap_or("get")
+                .trim()
+                .to_ascii_lowercase(),
+        )
+    };
+
+    if method == "post" {
+        // TODO: リクエストボディを送れるようになったら対応する
+        log::warn!("POST form submission is not supported yet");
+        return None;
+    }
+
+    let mut url = if action.is_empty() {
+        document_url.clone()
+    } else {
+        match super::resolve_url(base_url, &action) {
+            Ok(url) => url,
+            Err(e) => {
+                log::warn!("Invalid form action {:?}: {}", action, e);
+                return None;
+            }
+        }
+    };
+
+    let query = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(form_data(form, submitter, values))
+        .finish();
+    url.set_query(Some(&query));
+    url.set_fragment(None);
+
+    Some(url)
+}
+
+/// select の選ばれている option（selected がなければ最初のもの）
+fn selected_option(select: &NodeRef<HtmlNodeType>) -> Option<NodeRef<HtmlNodeType>> {
+    let mut options = Vec::new();
+    let mut stack = vec![select.clone()];
+    while let Some(node) = stack.pop() {
+        let n = node.borrow();
+        if n.value.tag_name() == Some("option") {
+            options.push(node.clone());
+        }
+        stack.extend(n.children().iter().rev().cloned());
+    }
+
+    options
+        .iter()
+        .find(|o| o.borrow().value.has_attr("selected"))
+        .or(options.first())
+        .cloned()
+}
+
+fn option_value(option: &NodeRef<HtmlNodeType>) -> String {
+    match option.borrow().value.get_attr("value") {
+        Some(value) => value.to_string(),
+        None => DomTree::inner_text(option).trim().to_string(),
+    }
+}
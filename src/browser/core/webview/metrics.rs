@@ -0,0 +1,110 @@
+//! 文書の読み込みにかかった時間
+//!
+//! WebView が移動を始めてから、段階ごとにかかった時間を記録する。スタイルとレイアウトは
+//! 何度も計算し直すので合計を取る。読み込みが終わり最初の描画を終えたら、それ以降の
+//! 再計算（:hover やスクロール）は数えない。
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// 1 回の移動での読み込みの計測値
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageLoadMetrics {
+    /// 移動を始めた時刻
+    start: Instant,
+    /// 移動を始めてから HTML が届くまで
+    pub fetch: Option<Duration>,
+    /// HTML のパース
+    pub parse: Duration,
+    /// スタイルの解決（CSS のパースとカスケード）の合計
+    pub style: Duration,
+    /// レイアウトツリーの構築とレイアウトの合計
+    pub layout: Duration,
+    /// 移動を始めてから、ページの中身のある描画コマンドを初めて作るまで
+    pub first_paint: Option<Duration>,
+    /// 移動を始めてから、HTML と外部 CSS（`<iframe>` の中の文書を含む）が揃うまで
+    pub load: Option<Duration>,
+}
+
+impl PageLoadMetrics {
+    /// start に移動を始めた文書の計測を始める
+    pub fn new(start: Instant) -> Self {
+        Self {
+            start,
+            fetch: None,
+            parse: Duration::ZERO,
+            style: Duration::ZERO,
+            layout: Duration::ZERO,
+            first_paint: None,
+            load: None,
+        }
+    }
+
+    /// 読み込みが終わり、最初の描画も済んだか（それ以降は何も記録しない）
+    pub fn is_complete(&self) -> bool {
+        self.load.is_some() && self.first_paint.is_some()
+    }
+
+    /// at に HTML が届いた
+    pub fn record_fetch(&mut self, at: Instant) {
+        if self.fetch.is_none() {
+            self.fetch = Some(at.saturating_duration_since(self.start));
+        }
+    }
+
+    pub fn add_parse(&mut self, time: Duration) {
+        if !self.is_complete() {
+            self.parse += time;
+        }
+    }
+
+    pub fn add_style(&mut self, time: Duration) {
+        if !self.is_complete() {
+            self.style += time;
+        }
+    }
+
+    pub fn add_layout(&mut self, time: Duration) {
+        if !self.is_complete() {
+            self.layout += time;
+        }
+    }
+
+    /// at に最初の描画コマンドを作った
+    pub fn record_first_paint(&mut self, at: Instant) {
+        if self.first_paint.is_none() {
+            self.first_paint = Some(at.saturating_duration_since(self.start));
+        }
+    }
+
+    /// at に読み込みが終わった
+    pub fn record_load(&mut self, at: Instant) {
+        if self.load.is_none() {
+            self.load = Some(at.saturating_duration_since(self.start));
+        }
+    }
+}
+
+impl fmt::Display for PageLoadMetrics {
+    /// 1 行に 1 つずつ、ミリ秒で書く（まだ記録していないものは `-`）
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = [
+            ("fetch", self.fetch),
+            ("parse", Some(self.parse)),
+            ("style", Some(self.style)),
+            ("layout", Some(self.layout)),
+            ("first paint", self.first_paint),
+            ("load", self.load),
+        ];
+        for (i, (name, time)) in rows.into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            match time {
+                Some(time) => write!(f, "{:<12}{:>9.1} ms", name, time.as_secs_f64() * 1000.0)?,
+                None => write!(f, "{:<12}{:>9} ms", name, "-")?,
+            }
+        }
+        Ok(())
+    }
+}
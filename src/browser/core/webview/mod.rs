@@ -1,30 +1,133 @@
+pub mod form;
+pub mod metrics;
+pub mod refresh;
+pub mod sandbox;
+pub mod viewport;
+
+use crate::browser::core::autofill::{AutofillField, AutofillProfile};
+use crate::browser::core::csp::{ContentSecurityPolicy, Directive};
+use crate::browser::core::devtools::{self, BoxModel, Console, StyleInspection};
+use crate::browser::core::extensions::ExtensionRegistry;
+use crate::browser::core::fetch_policy::{self, Initiator, RequestMode};
+use crate::browser::core::origin::Origin;
+use crate::browser::core::passwords::{self, Credential, SubmittedLogin};
+use crate::browser::core::permissions::{self, Permission, PermissionRequest};
+use crate::browser::core::visited_links::SharedVisitedLinks;
 use crate::engine::{
-    css::parser::Parser as CssParser,
-    html::parser::{DomTree, Parser as HtmlParser},
+    accessibility::{self, AccessTree},
+    css::{
+        media::{ColorScheme, MediaContext, MediaType},
+        parser::Parser as CssParser,
+        values::CssValue,
+    },
+    diagnostics::{self, Level, Source},
+    events::{self, Event, EventListeners, EventType},
+    html::{
+        HtmlNodeType,
+        parser::{self as html_parser, DomTree, Parser as HtmlParser},
+        preload::{self, PreloadDestination, ResourceHint},
+    },
+    input::{
+        self, focus,
+        scroll::{self, SmoothScroller},
+        selection::{self, Selection},
+        spellcheck::SpellChecker,
+        text_edit::{self, Composition, TextEdit},
+    },
     layouter::{
-        self,
-        types::{InfoNode, TextStyle},
+        self, cascade,
+        types::{Color, ContainerRole, FontFamilyList, InfoNode, InputCaret, NodeKind, TextStyle},
+    },
+    renderer_model::{self, DrawCommand, paginate},
+    script::{
+        self, FetchResponse, NotificationRequest, ScriptRuntime, ScriptSource, ScriptValue,
+        TimerRequest,
+        storage::{self, SharedStorage},
     },
+    tree::NodeRef,
 };
+use crate::platform::geolocation::{Position, PositionError, PositionResult};
+use crate::platform::notifications::{Notification, NotificationCommand, NotificationEvent};
 use crate::platform::renderer::text_measurer::PlatformTextMeasurer;
-use ui_layout::LayoutNode;
+use image::RgbaImage;
+use metrics::PageLoadMetrics;
+use refresh::MetaRefresh;
+use sandbox::SandboxFlags;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use ui_layout::{LayoutNode, Length};
 use url::Url;
+use viewport::ViewportMeta;
+
+/// 設定で変えていないときの文字の大きさ（CSS px）
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// 暗い配色のときのキャンバスの背景色と既定の文字色
+const DARK_CANVAS: Color = Color(28, 27, 34, 255);
+const DARK_TEXT: Color = Color(232, 230, 227, 255);
+const LIGHT_CANVAS: Color = Color(255, 255, 255, 255);
+
+/// ズーム倍率の範囲
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 5.0;
+
+/// ズーム倍率の段階（Ctrl+plus / Ctrl+minus で隣の段階に移る）
+/// Tab キーでフォーカスした要素をスクロールで見せるときの余白（CSS px）
+const FOCUS_SCROLL_MARGIN: f32 = 16.0;
 
-const USER_AGENT_CSS: &str = include_str!("../../../../resource/user-agent.css");
+/// `<iframe>` を入れ子にできる深さ（自分自身を読み込むページで止まるように）
+const MAX_FRAME_DEPTH: usize = 3;
+
+const ZOOM_LEVELS: &[f32] = &[
+    0.25, 0.33, 0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0, 4.0, 5.0,
+];
 
 pub enum WebViewTask {
     AskTabHtml,
-    Fetch { url: Url, kind: FetchKind },
+    Fetch {
+        url: Url,
+        kind: FetchKind,
+    },
+    /// オリジンへの名前解決や接続を先に済ませておく
+    Hint(ResourceHint),
 }
 
 /// TODO:
 /// - Root Document fetch
-/// - Image fetch
-/// - JS fetch
 /// - その他リソース fetch
 pub enum FetchKind {
     Html,
     Css,
+    /// 外部スクリプト（`js` フィーチャーが有効なときだけ要求する）
+    Script,
+    /// 文書 document のスクリプトが fetch() や XMLHttpRequest で送った要求
+    ScriptRequest {
+        document: u64,
+        request: u32,
+    },
+    /// `<iframe>` の文書 frame（WebView の document_id）のための kind の fetch
+    Frame {
+        frame: u64,
+        kind: Box<FetchKind>,
+    },
+    /// 先読みスキャナーが HTML の中に見つけたもの（DOM を組み立てる前に要求する）
+    Preload(PreloadDestination),
+    /// 文書の `<img>` と CSS の背景画像
+    Image,
+}
+
+impl FetchKind {
+    /// `<iframe>` の文書のための fetch なら、その文書の番号と中身の種類に分ける
+    pub fn split_frame(self) -> (Option<u64>, FetchKind) {
+        match self {
+            Self::Frame { frame, kind } => (Some(frame), *kind),
+            kind => (None, kind),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -36,30 +139,307 @@ enum PagePhase {
     CssApplied,
 }
 
+/// 次に作る WebView の document_id
+static NEXT_DOCUMENT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 次の現在地の問い合わせの番号（WebView をまたいで一意）
+static NEXT_POSITION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 次の通知の番号（WebView をまたいで一意）
+static NEXT_NOTIFICATION_ID: AtomicU64 = AtomicU64::new(1);
+
 pub struct WebView {
     phase: PagePhase,
 
+    /// この WebView を表す、プロセスの中で一意な番号（タイマーの宛先に使う）
+    document_id: u64,
+
     docment_info: Option<DocumentInfo>,
 
+    /// 文書に掛かる Content Security Policy（ヘッダーと meta 要素）
+    csp: ContentSecurityPolicy,
+
     pending_css_urls: Vec<Url>,
     loaded_css: Vec<String>,
+    /// 先読みしたスタイルシートとスクリプトの応答（届くまで None）
+    preloads: HashMap<Url, Option<String>>,
+    /// 要求した（または Content Security Policy で止めた）`<img>` と CSS の背景画像の URL
+    requested_images: HashSet<Url>,
+    /// まだ BrowserApp に渡していない画像の要求
+    image_requests: Vec<Url>,
+    /// デコードが済んで届いた画像（レイアウトのたびに `<img>` と背景に付ける）
+    images: HashMap<Url, Arc<RgbaImage>>,
+    /// <style> 要素の CSS
+    inline_css: Vec<String>,
+    /// 拡張機能が差し込む CSS（ページの CSS の後に当てる）
+    injected_css: Vec<String>,
+    /// 拡張機能が差し込むスクリプト（拡張機能の名前と中身、ページのスクリプトの後に実行する）
+    injected_scripts: Vec<(String, String)>,
+    /// @media の評価に使う環境（利用者の配色）
+    media: MediaContext,
+    /// ページが暗い配色に対応しているか（color-scheme に dark を含む）
+    supports_dark: bool,
 
     resolved_styles: layouter::css_resolver::ResolvedStyles,
     layout_and_info: Option<(LayoutNode, InfoNode)>,
 
+    /// この文書の処理中に出た警告とスクリプトの console.*
+    console: Console,
+
+    /// テキスト選択範囲
+    selection: Option<Selection>,
+
+    /// スクロールのアニメーション
+    scroller: SmoothScroller,
+
+    /// マウスカーソルの下にあるリンクのパス（:hover の対象）
+    hover_path: Option<Vec<usize>>,
+
+    /// フォーカスのある入力欄
+    focused_input: Option<FocusedInput>,
+    /// `value` 属性に書かずに持っている入力欄の値（自動入力した値）
+    field_values: form::FieldValues,
+
+    /// マウスのボタンで押されているボタンのパス（:active の対象）
+    active_path: Option<Vec<usize>>,
+
+    /// キーボードフォーカスのある要素のパス（:focus の対象）
+    focus_path: Option<Vec<usize>>,
+    /// フォーカスリングを描くか（キーボードでフォーカスを移したとき）
+    focus_visible: bool,
+
+    /// 要素に登録されたイベントリスナー（ページを移ると捨てる）
+    events: EventListeners,
+
+    /// 文書順のスクリプトと本文（外部スクリプトは届くまで None）
+    scripts: Vec<(ScriptSource, Option<String>)>,
+    /// スクリプトを実行し終えたか
+    scripts_executed: bool,
+    /// この WebView の realm
+    script_runtime: ScriptRuntime,
+    /// スクリプトが送り、応答を待っている fetch / XMLHttpRequest の要求
+    script_requests: Vec<u32>,
+    /// スクリプトに公開する localStorage と sessionStorage（オリジンごとに分かれる）
+    local_storage: Option<SharedStorage>,
+    session_storage: Option<SharedStorage>,
+
+    /// 最後にレイアウトしたビューポートの大きさ
+    viewport: Option<(f32, f32)>,
+    /// 最後にレイアウトしたページのコンテンツの大きさ (width, height)
+    content_size: (f32, f32),
+
+    /// `<iframe>` の中の文書
+    frames: Vec<ChildFrame>,
+    /// `<iframe>` の入れ子の深さ（タブの文書は 0）
+    frame_depth: usize,
+    /// `<iframe sandbox>` で掛かる制限（親の iframe の分を含む）
+    sandbox: SandboxFlags,
+    /// sandbox で opaque になったときのこの文書のオリジン（文書を読むたびに作り直す）
+    opaque_origin: Origin,
+    /// まだ BrowserApp に渡していない `<meta http-equiv="refresh">` の指定
+    refresh: Option<MetaRefresh>,
+
+    /// 読み込み完了後に戻すページのスクロール位置（履歴で戻ったとき）
+    pending_scroll: Option<(f32, f32)>,
+    /// 文書を読んだら入れ直す入力欄の値（履歴で戻ったとき）
+    pending_form_state: Option<form::FormState>,
+
+    /// 利用者が選んだズーム倍率
+    zoom: f32,
+    /// `<meta name="viewport">` の指定（`<iframe>` の中の文書では見ない）
+    viewport_meta: Option<ViewportMeta>,
+    /// `<meta name="viewport">` から決めた倍率（ズーム倍率に掛ける）
+    page_scale: f32,
+
+    /// ルート要素に継承させる既定のフォント（設定の既定フォントと文字の大きさ）
+    default_text: TextStyle,
+    /// 入力欄の綴りを調べる辞書（None ならスペルチェックしない）
+    spell_checker: Option<Arc<SpellChecker>>,
+    /// :visited に当てる訪れたリンクの記録（None ならどのリンクも :link）
+    visited_links: Option<SharedVisitedLinks>,
+    /// ページに CSS とスクリプトを差し込む拡張機能
+    extensions: Option<Arc<ExtensionRegistry>>,
+    /// 送信したフォームの自動入力できる値（take_submitted_profile で渡すまで）
+    submitted_profile: Option<AutofillProfile>,
+    /// 送信したログインフォームのユーザー名とパスワード（take_submitted_login で渡すまで）
+    submitted_login: Option<SubmittedLogin>,
+    /// 求められて、まだアプリが決めていない権限（take_permission_requests で渡すまで）
+    permission_requests: Vec<PermissionRequest>,
+    /// 今の文書に許した（true）か拒んだ（false）権限
+    permissions: Vec<(Permission, bool)>,
+    /// 位置情報の権限が決まるのを待っている現在地の問い合わせ
+    positions_waiting: Vec<u64>,
+    /// 許されて、アプリに渡す前の現在地の問い合わせ（take_position_requests で渡すまで）
+    position_requests: Vec<u64>,
+    /// アプリに渡して答えを待っている現在地の問い合わせ
+    positions_pending: Vec<u64>,
+    /// 届いた現在地（take_positions で渡すまで）
+    positions: Vec<PositionResult>,
+    /// 出した通知の番号と、スクリプトの中での番号
+    notifications: Vec<(u64, u32)>,
+    /// 通知センターに頼むこと（take_notification_commands で渡すまで）
+    notification_commands: Vec<NotificationCommand>,
+
+    /// 今の文書の読み込みにかかった時間
+    metrics: PageLoadMetrics,
+    /// 読み終えた計測値を take_finished_metrics で渡したか
+    metrics_reported: bool,
+
     needs_redraw: bool,
 }
 
+/// 入力欄の綴りの誤っている単語
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Misspelling {
+    /// 入力欄の値の中の範囲（バイト）
+    pub range: Range<usize>,
+    pub word: String,
+    /// 直す候補（よさそうな順）
+    pub suggestions: Vec<String>,
+}
+
+/// リンクをクリックしたときの移動先
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkNavigation {
+    /// 移動先（Here なら解決前の href、それ以外は絶対 URL）
+    pub href: String,
+    pub target: LinkTarget,
+    /// Referer にするリンク元の文書の URL（`rel="noreferrer"` なら None）
+    pub referrer: Option<Url>,
+    /// 新しいタブの文書から window.opener で見えるリンク元の文書の番号
+    /// （`rel="opener"` のときだけ）
+    pub opener: Option<u64>,
+}
+
+/// リンクを開く場所
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// クリックした文書で開く
+    Here,
+    /// タブの文書で開く（`target="_top"`）
+    Top,
+    /// 新しいタブで開く（`target="_blank"`）
+    NewTab,
+}
+
+/// `<iframe>` の中の文書
+struct ChildFrame {
+    /// iframe 要素へのパス（DOM と InfoNode で共通の子インデックス）
+    path: Vec<usize>,
+    /// 読み込む文書の URL（srcdoc なら about:srcdoc）
+    url: Url,
+    /// srcdoc 属性の値（取りに行かずにこれを文書にする）
+    srcdoc: Option<String>,
+    /// sandbox 属性の制限
+    sandbox: SandboxFlags,
+    webview: WebView,
+}
+
+/// フォーカスのある入力欄と編集中の値
+struct FocusedInput {
+    /// 入力欄へのパス（DOM と InfoNode で共通の子インデックス）
+    path: Vec<usize>,
+    edit: TextEdit,
+    password: bool,
+    /// 綴りを調べるか（1 行のテキストと検索の欄で、`spellcheck="false"` でないもの）
+    spellcheck: bool,
+}
+
+impl FocusedInput {
+    /// 値が value の path の入力欄 node にフォーカスする（キャレットは末尾）
+    fn new(path: Vec<usize>, node: &HtmlNodeType, value: String) -> Self {
+        Self {
+            path,
+            edit: TextEdit::new(value),
+            password: node
+                .get_attr("type")
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case("password")),
+            spellcheck: node.get_attr("type").is_none_or(|t| {
+                t.trim().eq_ignore_ascii_case("text") || t.trim().eq_ignore_ascii_case("search")
+            }) && !node
+                .get_attr("spellcheck")
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case("false")),
+        }
+    }
+
+    /// 値のオフセットを、入力欄に表示している文字列でのオフセットに直す
+    fn display_offset(&self, offset: usize) -> usize {
+        let value = self.edit.value();
+        if value.is_empty() {
+            // プレースホルダーの先頭
+            0
+        } else if self.password {
+            text_edit::masked_offset(value, offset)
+        } else {
+            offset
+        }
+    }
+
+    fn value_offset(&self, display_offset: usize) -> usize {
+        let value = self.edit.value();
+        if self.password {
+            text_edit::unmasked_offset(value, display_offset)
+        } else {
+            display_offset.min(value.len())
+        }
+    }
+
+    /// IME で変換中の文字列を差し込んだ表示（パスワード欄では IME を使わない）
+    fn composition(&self) -> Option<Composition> {
+        if self.password {
+            return None;
+        }
+        self.edit.composition()
+    }
+
+    fn caret(&self, spell_checker: Option<&SpellChecker>) -> InputCaret {
+        if let Some(composition) = self.composition() {
+            return InputCaret {
+                offset: composition.caret,
+                selection: None,
+                preedit: Some(composition.preedit),
+                misspelled: Vec::new(),
+            };
+        }
+        InputCaret {
+            offset: self.display_offset(self.edit.caret()),
+            selection: self
+                .edit
+                .selection()
+                .map(|r| self.display_offset(r.start)..self.display_offset(r.end)),
+            preedit: None,
+            misspelled: self.misspelled_words(spell_checker),
+        }
+    }
+
+    /// 綴りの誤っている単語（値のバイト範囲）
+    ///
+    /// キャレットが末尾にある単語は入力の途中なので印を付けない。
+    fn misspelled_words(&self, spell_checker: Option<&SpellChecker>) -> Vec<Range<usize>> {
+        let Some(checker) = spell_checker.filter(|_| self.spellcheck && !self.password) else {
+            return Vec::new();
+        };
+        let caret = self.edit.caret();
+        checker
+            .misspelled_words(self.edit.value())
+            .into_iter()
+            .filter(|range| range.end != caret)
+            .collect()
+    }
+}
+
 /// DocumentInfo holds basic information about the HTML document.
 /// It includes the document URL, base URL, title, and DOM tree.
 ///
 /// - document_url: The URL of the document.
 /// - base_url: The base URL for resolving relative URLs.
+/// - base_target: The default target of links (`<base target>`).
 /// - title: The title of the document.
 /// - dom: The DOM tree of the document.
 pub struct DocumentInfo {
     document_url: Url,
     base_url: Url,
+    base_target: Option<String>,
     title: String,
     pub dom: DomTree,
 }
@@ -69,17 +449,21 @@ pub struct DocumentInfo {
 ///
 /// - document_url: The URL of the document.
 /// - base_url: The base URL for resolving relative URLs.
+/// - base_target: The default target of links (`<base target>`).
 /// - dom: The DOM tree of the document.
 /// - title: The title of the document.
 /// - style_links: A list of URLs for linked stylesheets.
 /// - inline_styles: A list of inline CSS styles.
+/// - scripts: Classic scripts in document order (empty when JavaScript is disabled).
 struct ParsedDocument {
     document_url: Url,
     base_url: Url,
+    base_target: Option<String>,
     dom: DomTree,
     title: String,
     style_links: Vec<Url>,
     inline_styles: Vec<String>,
+    scripts: Vec<ScriptSource>,
 }
 
 impl Default for WebView {
@@ -93,14 +477,84 @@ impl WebView {
         Self {
             phase: PagePhase::Init,
 
+            document_id: NEXT_DOCUMENT_ID.fetch_add(1, Ordering::Relaxed),
+
             docment_info: None,
 
+            csp: ContentSecurityPolicy::new(),
             pending_css_urls: Vec::new(),
             loaded_css: Vec::new(),
+            preloads: HashMap::new(),
+            requested_images: HashSet::new(),
+            image_requests: Vec::new(),
+            images: HashMap::new(),
+            inline_css: Vec::new(),
+            injected_css: Vec::new(),
+            injected_scripts: Vec::new(),
+            media: MediaContext::default(),
+            supports_dark: false,
 
             resolved_styles: layouter::css_resolver::ResolvedStyles::default(),
             layout_and_info: None,
 
+            console: Console::new(),
+
+            selection: None,
+
+            scroller: SmoothScroller::new(),
+
+            hover_path: None,
+            focused_input: None,
+            field_values: form::FieldValues::new(),
+            active_path: None,
+            focus_path: None,
+            focus_visible: false,
+            events: EventListeners::new(),
+
+            scripts: Vec::new(),
+            scripts_executed: false,
+            script_runtime: ScriptRuntime::new(),
+            script_requests: Vec::new(),
+            local_storage: None,
+            session_storage: None,
+
+            viewport: None,
+            content_size: (0.0, 0.0),
+
+            frames: Vec::new(),
+            frame_depth: 0,
+            sandbox: SandboxFlags::none(),
+            opaque_origin: Origin::new_opaque(),
+            refresh: None,
+
+            pending_scroll: None,
+            pending_form_state: None,
+
+            zoom: 1.0,
+            viewport_meta: None,
+            page_scale: 1.0,
+
+            default_text: TextStyle {
+                font_size: DEFAULT_FONT_SIZE,
+                ..Default::default()
+            },
+            spell_checker: None,
+            visited_links: None,
+            extensions: None,
+            submitted_profile: None,
+            submitted_login: None,
+            permission_requests: Vec::new(),
+            permissions: Vec::new(),
+            positions_waiting: Vec::new(),
+            position_requests: Vec::new(),
+            positions_pending: Vec::new(),
+            positions: Vec::new(),
+            notifications: Vec::new(),
+            notification_commands: Vec::new(),
+
+            metrics: PageLoadMetrics::new(Instant::now()),
+            metrics_reported: false,
+
             needs_redraw: false,
         }
     }
@@ -110,10 +564,7 @@ impl WebView {
 
         match self.phase {
             PagePhase::Init => {
-                self.resolved_styles
-                    .extend(layouter::css_resolver::CssResolver::resolve(
-                        &CssParser::new(USER_AGENT_CSS).parse().unwrap(),
-                    ));
+                self.resolve_styles();
 
                 tasks.push(WebViewTask::AskTabHtml);
 
@@ -128,16 +579,44 @@ impl WebView {
 
                 self.update_layout_and_info(measurer);
 
-                // CSS fetch を要求
+                // CSS fetch を要求（先読みしたものは要求しない）
+                let mut preloaded_css = Vec::new();
                 for url in &self.pending_css_urls {
-                    log::info!("Fetch requested in WebView: url={}", url);
-                    tasks.push(WebViewTask::Fetch {
-                        url: url.clone(),
-                        kind: FetchKind::Css,
-                    });
+                    match self.preloads.get(url) {
+                        Some(Some(css)) => preloaded_css.push(css.clone()),
+                        Some(None) => {}
+                        None => {
+                            log::info!("Fetch requested in WebView: url={}", url);
+                            tasks.push(WebViewTask::Fetch {
+                                url: url.clone(),
+                                kind: FetchKind::Css,
+                            });
+                        }
+                    }
+                }
+
+                // 外部スクリプトの fetch を要求
+                for (source, text) in &self.scripts {
+                    if let (ScriptSource::External(url), None) = (source, text)
+                        && !self.preloads.contains_key(url)
+                    {
+                        log::info!("Script fetch requested in WebView: url={}", url);
+                        tasks.push(WebViewTask::Fetch {
+                            url: url.clone(),
+                            kind: FetchKind::Script,
+                        });
+                    }
                 }
 
-                self.phase = PagePhase::CssPending;
+                // 外部 CSS がなければ待つものはない
+                self.phase = if self.pending_css_urls.is_empty() {
+                    PagePhase::CssApplied
+                } else {
+                    PagePhase::CssPending
+                };
+                for css in preloaded_css {
+                    self.on_css_fetched(css);
+                }
             }
 
             PagePhase::CssPending => {
@@ -149,27 +628,236 @@ impl WebView {
             }
         }
 
+        if self.metrics.load.is_none() && self.is_loaded() {
+            self.metrics.record_load(Instant::now());
+        }
+
+        // <img> と CSS の背景画像
+        for url in std::mem::take(&mut self.image_requests) {
+            log::info!("Image fetch requested in WebView: url={}", url);
+            tasks.push(WebViewTask::Fetch {
+                url,
+                kind: FetchKind::Image,
+            });
+        }
+
+        // スクリプトの fetch / XMLHttpRequest
+        for (request, url) in self.take_script_requests() {
+            log::info!("Script request in WebView: url={}", url);
+            tasks.push(WebViewTask::Fetch {
+                url,
+                kind: FetchKind::ScriptRequest {
+                    document: self.document_id,
+                    request,
+                },
+            });
+        }
+
+        self.handle_notification_requests();
+
+        // <iframe> の文書の fetch は、どの文書のためのものかを付けて Tab に渡す
+        let base_url = self.base_url().cloned();
+        for frame in &mut self.frames {
+            let document = frame.webview.document_id;
+            for task in frame.webview.tick() {
+                let (url, kind) = match task {
+                    WebViewTask::AskTabHtml => {
+                        // srcdoc の相対 URL はこの文書の base URL で解決する
+                        if let Some(html) = frame.srcdoc.clone() {
+                            let url = frame.url.clone();
+                            frame.webview.load_html(html, url, base_url.clone());
+                            continue;
+                        }
+                        (frame.url.clone(), FetchKind::Html)
+                    }
+                    WebViewTask::Fetch { url, kind } => (url, kind),
+                    WebViewTask::Hint(hint) => {
+                        tasks.push(WebViewTask::Hint(hint));
+                        continue;
+                    }
+                };
+                // 孫の文書の fetch は既に包まれている
+                let kind = match kind {
+                    FetchKind::Frame { .. } => kind,
+                    kind => FetchKind::Frame {
+                        frame: document,
+                        kind: Box::new(kind),
+                    },
+                };
+                tasks.push(WebViewTask::Fetch { url, kind });
+            }
+        }
+
         tasks
     }
 
+    /// スクリプトの localStorage と sessionStorage の保存先を設定する
+    ///
+    /// 文書を読み込む前に呼ぶ。opaque なオリジン（data: など）の文書には公開しない。
+    pub fn set_storage(&mut self, local: Option<SharedStorage>, session: Option<SharedStorage>) {
+        self.local_storage = local;
+        self.session_storage = session;
+    }
+
+    /// 応答のヘッダーの Content Security Policy を設定する。on_html_fetched の前に呼ぶ
+    pub fn set_content_security_policy(&mut self, csp: ContentSecurityPolicy) {
+        self.csp = csp;
+    }
+
     pub fn on_html_fetched(&mut self, html: String, document_url: Url) {
+        self.load_html(html, document_url, None);
+    }
+
+    /// 文書 document_url の html を組み立てる前に先読みするものと接続のヒントを選ぶ
+    ///
+    /// Content Security Policy で許されないものは選ばない。スタイルシートとスクリプトは
+    /// 応答を取っておき、HTML を読んだ後に同じ URL を要求し直さない。
+    /// set_content_security_policy の後、on_html_fetched の前に呼ぶ。
+    pub fn start_preloads(&mut self, html: &str, document_url: &Url) -> Vec<WebViewTask> {
+        let scan = preload::scan(html, document_url);
+        let mut tasks: Vec<WebViewTask> = scan.hints.into_iter().map(WebViewTask::Hint).collect();
+        for request in scan.requests {
+            let directive = match request.destination {
+                PreloadDestination::Script if !script::ENABLED || self.sandbox.scripts => {
+                    continue;
+                }
+                PreloadDestination::Script => Directive::Script,
+                PreloadDestination::Style => Directive::Style,
+                PreloadDestination::Image => Directive::Image,
+                PreloadDestination::Font => Directive::Font,
+            };
+            if !self.csp.allows_element(
+                directive,
+                &request.url,
+                request.nonce.as_deref(),
+                document_url,
+            ) {
+                continue;
+            }
+            if matches!(
+                request.destination,
+                PreloadDestination::Style | PreloadDestination::Script
+            ) {
+                self.preloads.insert(request.url.clone(), None);
+            }
+            tasks.push(WebViewTask::Fetch {
+                url: request.url,
+                kind: FetchKind::Preload(request.destination),
+            });
+        }
+        tasks
+    }
+
+    /// 先読みしたものが届いた（読めなかったときは空文字列）
+    ///
+    /// 文書がもう待っていれば、そのスタイルシートやスクリプトとして渡す。
+    pub fn on_preload_fetched(&mut self, url: &Url, body: String) {
+        let Some(slot) = self.preloads.get_mut(url) else {
+            return;
+        };
+        *slot = Some(body.clone());
+
+        // HtmlParsed のうちは tick が取っておいた応答を使う
+        if self.phase == PagePhase::CssPending {
+            let count = self.pending_css_urls.iter().filter(|u| *u == url).count();
+            for _ in 0..count {
+                self.on_css_fetched(body.clone());
+            }
+        }
+        if self.docment_info.is_some() {
+            // 同じスクリプトを何度読み込んでいても応答は 1 つ
+            for (source, text) in &mut self.scripts {
+                if text.is_none() && matches!(source, ScriptSource::External(u) if u == url) {
+                    *text = Some(body.clone());
+                }
+            }
+            self.run_scripts_if_ready();
+        }
+    }
+
+    /// html を document_url の文書として読む
+    ///
+    /// base_url は `<base>` がないときに相対 URL を解決する URL（None なら document_url）。
+    fn load_html(&mut self, html: String, document_url: Url, base_url: Option<Url>) {
         log::info!("Fetched HTML: {}", document_url);
-        let parsed = parse_html(&html, document_url);
+        self.opaque_origin = Origin::new_opaque();
+        let started = Instant::now();
+        self.metrics.record_fetch(started);
+        // 拡張機能は `<iframe>` の中の文書には差し込まない
+        let injected = self
+            .extensions
+            .as_ref()
+            .filter(|_| self.frame_depth == 0)
+            .map(|extensions| extensions.content_for(&document_url))
+            .unwrap_or_default();
+        self.injected_css = injected.css;
+        self.injected_scripts = injected.scripts;
+        let mut parsed = self.capture(|wv| parse_html(&html, document_url, base_url, &mut wv.csp));
+        self.metrics.add_parse(started.elapsed());
+        if self.sandbox.scripts && !parsed.scripts.is_empty() {
+            self.report(
+                Level::Warning,
+                Source::Security,
+                "Blocked scripts in a sandboxed frame",
+            );
+            parsed.scripts.clear();
+        }
 
         self.pending_css_urls = parsed.style_links;
+        self.inline_css = parsed.inline_styles;
+        self.scripts = parsed
+            .scripts
+            .into_iter()
+            .map(|source| {
+                let text = match &source {
+                    ScriptSource::Inline(text) => Some(text.clone()),
+                    ScriptSource::External(url) => self.preloads.get(url).cloned().flatten(),
+                };
+                (source, text)
+            })
+            .collect();
+        self.scripts_executed = false;
+
+        // <iframe> の中の文書の指定は見ない
+        self.refresh = if self.frame_depth == 0 {
+            refresh::find(&parsed.dom)
+        } else {
+            None
+        };
+        self.viewport_meta = if self.frame_depth == 0 {
+            viewport::find(&parsed.dom)
+        } else {
+            None
+        };
 
         let docment_info = DocumentInfo {
             document_url: parsed.document_url,
             base_url: parsed.base_url,
+            base_target: parsed.base_target,
             dom: parsed.dom,
             title: parsed.title,
         };
+        self.script_runtime.attach_document(&docment_info.dom);
+        if let Some(origin) = storage::storage_origin(&docment_info.document_url) {
+            self.script_runtime.set_storage(
+                &origin,
+                self.local_storage.clone(),
+                self.session_storage.clone(),
+            );
+        }
+        // スクリプトより前に入れ直し、ページのスクリプトからは入力済みの値に見せる
+        if let Some(state) = self.pending_form_state.take() {
+            form::restore_form_state(&docment_info.dom.root, &state);
+        }
         self.docment_info = Some(docment_info);
+        self.capture(Self::create_frames);
 
-        self.resolved_styles
-            .extend(resolve_all_css(&parsed.inline_styles));
+        self.resolve_styles();
 
         self.phase = PagePhase::HtmlParsed;
+
+        // 外部スクリプトがなければここで実行する
+        self.run_scripts_if_ready();
     }
 
     pub fn on_css_fetched(&mut self, css: String) {
@@ -183,180 +871,2718 @@ impl WebView {
         }
     }
 
-    /// Update page (e.g. DOM changed)
+    /// 外部スクリプトが届いた（読めなかったときは空文字列）
+    pub fn on_script_fetched(&mut self, url: &Url, source: String) {
+        let pending = self
+            .scripts
+            .iter_mut()
+            .find(|(s, text)| text.is_none() && matches!(s, ScriptSource::External(u) if u == url));
+        if let Some((_, text)) = pending {
+            *text = Some(source);
+        }
+        self.run_scripts_if_ready();
+    }
+
+    /// スクリプトがすべて揃っていれば文書順に実行する
     ///
-    /// This is a stub method for now.
-    pub fn update_page(&mut self) {
-        let measurer = PlatformTextMeasurer::new().unwrap();
+    /// 外部スクリプトは届いた順ではなく、文書の中の順に実行する。
+    fn run_scripts_if_ready(&mut self) {
+        if self.scripts_executed || self.scripts.iter().any(|(_, text)| text.is_none()) {
+            return;
+        }
+        self.scripts_executed = true;
+        if self.scripts.is_empty() && self.injected_scripts.is_empty() {
+            return;
+        }
 
-        self.update_layout_and_info(measurer);
+        let document_url = self
+            .docment_info
+            .as_ref()
+            .map(|info| info.document_url.to_string())
+            .unwrap_or_default();
+        let ((), messages) = diagnostics::capture(|| {
+            for (i, (source, text)) in self.scripts.iter().enumerate() {
+                let name = match source {
+                    ScriptSource::Inline(_) => format!("{document_url} (inline script #{i})"),
+                    ScriptSource::External(url) => url.to_string(),
+                };
+                let text = text.as_deref().unwrap_or_default();
+                report_uncaught(self.script_runtime.execute(text, &name));
+            }
+            for (extension, text) in &self.injected_scripts {
+                let name = format!("{extension} (extension content script)");
+                report_uncaught(self.script_runtime.execute(text, &name));
+            }
+        });
+        self.console.extend(messages);
+
+        self.apply_script_mutations();
     }
 
-    fn apply_css_and_relayout(&mut self) {
-        self.resolved_styles
-            .extend(resolve_all_css(&self.loaded_css));
+    /// 式 expression を文書の realm で評価する（リモートデバッグの Runtime.evaluate）
+    ///
+    /// 出したメッセージはこの文書のコンソールに入り、DOM を書き換えていれば反映する。
+    pub fn evaluate_script(&mut self, expression: &str) -> anyhow::Result<ScriptValue> {
+        let result = self.capture(|wv| wv.script_runtime.evaluate(expression, "<remote>"));
+        self.apply_script_mutations();
+        result
+    }
 
-        let measurer = PlatformTextMeasurer::new().unwrap();
+    /// 文書の DOM のルート
+    pub fn dom_root(&self) -> Option<NodeRef<HtmlNodeType>> {
+        Some(self.docment_info.as_ref()?.dom.root.clone())
+    }
 
-        self.update_layout_and_info(measurer);
+    /// この文書と `<iframe>` の中の文書の DOM のおおよその大きさ（バイト）
+    pub fn memory_usage(&self) -> usize {
+        let dom = self
+            .docment_info
+            .as_ref()
+            .map_or(0, |info| html_parser::dom_memory_usage(&info.dom));
+        dom + self
+            .frames
+            .iter()
+            .map(|frame| frame.webview.memory_usage())
+            .sum::<usize>()
     }
 
-    fn update_layout_and_info(&mut self, measurer: PlatformTextMeasurer) {
-        self.layout_and_info = Some(layouter::build_layout_and_info(
-            &self.docment_info.as_ref().unwrap().dom.root,
-            &self.resolved_styles,
-            &measurer,
-            TextStyle {
-                font_size: 16.0,
-                ..Default::default()
-            },
-            Vec::new(),
-        ));
-        self.needs_redraw = true;
+    /// スクリプトが DOM を書き換えていれば反映する
+    ///
+    /// まだレイアウトしていなければ、最初のレイアウトで書き換えた DOM が使われる。
+    fn apply_script_mutations(&mut self) {
+        if !self.script_runtime.take_dom_mutated() {
+            return;
+        }
+        if self.layout_and_info.is_some() {
+            self.update_page();
+        } else {
+            self.refresh_title();
+        }
     }
 
-    pub fn navigate(&mut self) {
-        self.reset_for_navigation();
+    pub fn document_id(&self) -> u64 {
+        self.document_id
     }
 
-    fn reset_for_navigation(&mut self) {
-        if self.phase != PagePhase::Init {
-            self.phase = PagePhase::BeforeHtmlParsing;
+    /// この文書と `<iframe>` の中の文書のコンソール
+    pub fn console(&self) -> Console {
+        let mut console = self.console.clone();
+        for frame in &self.frames {
+            console.append(&frame.webview.console());
         }
+        console
+    }
 
-        self.docment_info = None;
-        self.pending_css_urls.clear();
-        self.loaded_css.clear();
-        self.resolved_styles.clear();
-        self.layout_and_info = None;
-
-        self.needs_redraw = false;
+    /// f の間に出たメッセージをこの文書のコンソールに集める
+    pub fn capture<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let (result, messages) = diagnostics::capture(|| f(self));
+        self.console.extend(messages);
+        result
     }
 
-    pub fn title(&self) -> Option<&String> {
-        self.docment_info.as_ref().map(|d| &d.title)
+    /// メッセージをログとこの文書のコンソールに出す
+    fn report(&mut self, level: Level, source: Source, text: impl Into<String>) {
+        self.capture(|_| diagnostics::report(level, source, text));
     }
 
-    pub fn relayout(&mut self, viewport: (f32, f32)) {
-        let Some((layout, _info)) = self.layout_and_info.as_mut() else {
+    /// `<iframe>` ごとに中の文書の WebView を作る
+    ///
+    /// srcdoc 属性があれば src より優先し、その値を文書にする（Content Security Policy
+    /// はこの文書のものを引き継ぐ）。frame-src で許されない URL と、入れ子が深すぎる
+    /// iframe は空のままにする。
+    fn create_frames(&mut self) {
+        self.shutdown_frames();
+        let Some(info) = self.docment_info.as_ref() else {
             return;
         };
+        if self.frame_depth >= MAX_FRAME_DEPTH {
+            return;
+        }
 
-        ui_layout::LayoutEngine::layout(layout, viewport.0, viewport.1);
-    }
+        let mut found = Vec::new();
+        collect_frames(&info.dom.root, &mut Vec::new(), &mut found);
+        let mut frames = Vec::new();
+        for (path, node) in found {
+            let element = node.borrow();
+            let node = &element.value;
+            let sandbox = node
+                .get_attr("sandbox")
+                .map(SandboxFlags::parse)
+                .unwrap_or_default();
+
+            if let Some(srcdoc) = node.get_attr("srcdoc") {
+                let mut webview = self.new_frame_webview(sandbox);
+                webview.set_content_security_policy(self.csp.clone());
+                frames.push(ChildFrame {
+                    path,
+                    url: Url::parse("about:srcdoc").expect("valid URL"),
+                    srcdoc: Some(srcdoc.to_string()),
+                    sandbox,
+                    webview,
+                });
+                continue;
+            }
 
-    /// 現在描画可能な Layout / Info を返す（なければ None）
-    pub fn layout_and_info(&self) -> Option<(&LayoutNode, &InfoNode)> {
-        self.layout_and_info.as_ref().map(|(l, i)| (l, i))
+            let Some(Ok(url)) = node
+                .get_attr("src")
+                .map(|src| resolve_url(&info.base_url, src.trim()))
+            else {
+                continue;
+            };
+            if !self
+                .csp
+                .allows_url(Directive::Frame, &url, &info.document_url)
+            {
+                diagnostics::report(
+                    Level::Error,
+                    Source::Security,
+                    format!("Refused to frame {url} (Content Security Policy)"),
+                );
+                continue;
+            }
+            frames.push(ChildFrame {
+                path,
+                url,
+                srcdoc: None,
+                sandbox,
+                webview: self.new_frame_webview(sandbox),
+            });
+        }
+        self.frames = frames;
     }
 
-    pub fn layout_and_info_mut(&mut self) -> Option<(&LayoutNode, &mut InfoNode)> {
-        self.layout_and_info.as_mut().map(|(l, i)| (&*l, i))
+    /// この文書の既定のフォント、配色、保存先を引き継いだ `<iframe>` 用の WebView
+    ///
+    /// sandbox は iframe の sandbox 属性の制限で、この文書の制限に足して掛ける。
+    fn new_frame_webview(&self, sandbox: SandboxFlags) -> WebView {
+        let mut webview = WebView::new();
+        webview.frame_depth = self.frame_depth + 1;
+        webview.sandbox = self.sandbox.union(sandbox);
+        webview.default_text = self.default_text;
+        webview.media = self.media;
+        webview.spell_checker = self.spell_checker.clone();
+        webview.visited_links = self.visited_links.clone();
+        if !webview.sandbox.origin {
+            webview.set_storage(self.local_storage.clone(), self.session_storage.clone());
+        }
+        webview
     }
 
-    /// Returns document info
-    pub fn document_info(&self) -> Option<&DocumentInfo> {
-        self.docment_info.as_ref()
+    /// `<iframe sandbox>` で掛かっている制限
+    pub fn sandbox(&self) -> SandboxFlags {
+        self.sandbox
     }
 
-    pub fn document_url(&self) -> Option<&Url> {
-        self.docment_info.as_ref().map(|info| &info.document_url)
+    fn shutdown_frames(&mut self) {
+        for mut frame in self.frames.drain(..) {
+            frame.webview.shutdown_scripts();
+            frame.webview.close_notifications();
+            self.notification_commands
+                .extend(frame.webview.take_notification_commands());
+        }
     }
 
-    pub fn base_url(&self) -> Option<&Url> {
-        self.docment_info.as_ref().map(|info| &info.base_url)
+    /// 中の文書の document_id が document の `<iframe>`（孫の文書も探す）
+    fn child_frame_mut(&mut self, document: u64) -> Option<&mut ChildFrame> {
+        let index = self
+            .frames
+            .iter()
+            .position(|f| f.webview.document_id == document);
+        match index {
+            Some(i) => self.frames.get_mut(i),
+            None => self
+                .frames
+                .iter_mut()
+                .find_map(|f| f.webview.child_frame_mut(document)),
+        }
     }
 
-    pub fn needs_redraw(&self) -> bool {
-        self.needs_redraw
+    /// この文書か `<iframe>` の中の文書のうち、document_id が document のもの
+    pub fn frame_mut(&mut self, document: u64) -> Option<&mut WebView> {
+        if self.document_id == document {
+            return Some(self);
+        }
+        self.child_frame_mut(document).map(|f| &mut f.webview)
     }
 
-    pub fn clear_redraw_flag(&mut self) {
-        self.needs_redraw = false;
+    pub fn frame(&self, document: u64) -> Option<&WebView> {
+        if self.document_id == document {
+            return Some(self);
+        }
+        self.frames.iter().find_map(|f| f.webview.frame(document))
     }
-}
 
-fn parse_html(html: &str, document_url: Url) -> ParsedDocument {
-    // --- DOM パース ---
-    let mut parser = HtmlParser::new(html);
-    let dom = parser.parse();
+    /// `<iframe>` の文書 frame の HTML が届いた（csp は応答のヘッダーのもの）
+    pub fn on_frame_html_fetched(&mut self, frame: u64, html: String, csp: ContentSecurityPolicy) {
+        let Some(frame) = self.child_frame_mut(frame) else {
+            return;
+        };
+        let url = frame.url.clone();
+        frame.webview.set_content_security_policy(csp);
+        frame.webview.on_html_fetched(html, url);
+        self.needs_redraw = true;
+    }
 
-    // --- base_url ---
-    let base_url = dom
-        .find_all(|n| n.tag_name() == Some("base"))
-        .iter()
-        .filter_map(|node_ref| {
-            let html_node = &node_ref.borrow().value;
-            let href = html_node.get_attr("href")?;
-            document_url.join(href).ok()
-        })
-        .next()
-        .unwrap_or_else(|| document_url.clone());
+    /// `<iframe>` の i 番目の content box (x, y, width, height)（ビューポート座標）
+    fn frame_rect(&self, i: usize) -> Option<(f32, f32, f32, f32)> {
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        let ((x, y), node, _) = node_with_origin(layout, info, &self.frames.get(i)?.path)?;
+        let content = node.layout_boxes.first()?.content_box;
+        Some((x + content.x, y + content.y, content.width, content.height))
+    }
 
-    // --- title 抽出 ---
-    let title = dom
-        .collect_text_by_tag("title")
-        .first()
-        .cloned()
-        .unwrap_or("".into());
+    /// (x, y) にある `<iframe>` の番号と、その中の文書での座標
+    fn frame_at(&self, x: f32, y: f32) -> Option<(usize, f32, f32)> {
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        let hits = input::hit_test(layout, info, x, y);
+        let path = input::node_path(&hits, input::find_frame(&hits)?);
+        let i = self.frames.iter().position(|f| f.path == path)?;
+        let (frame_x, frame_y, ..) = self.frame_rect(i)?;
+        Some((i, x - frame_x, y - frame_y))
+    }
 
-    // --- Style links ---
-    // <link rel="stylesheet" href="...">
-    let link_nodes = dom.find_all(|n| n.tag_name() == Some("link"));
-    let mut style_links = Vec::new();
+    /// `<iframe>` の i 番目の中で href のリンクをたどる（その iframe の中の文書だけが移動する）
+    fn navigate_frame(&mut self, i: usize, href: &str) {
+        let Some(frame) = self.frames.get(i) else {
+            return;
+        };
+        let base_url = frame.webview.base_url().unwrap_or(&frame.url);
+        let url = match resolve_url(base_url, href) {
+            Ok(url) => url,
+            Err(e) => {
+                diagnostics::report(
+                    Level::Warning,
+                    Source::Html,
+                    format!("Invalid link href {:?}: {}", href, e),
+                );
+                return;
+            }
+        };
+        let allowed = match url.scheme() {
+            "http" | "https" | "about" => true,
+            "file" => frame.url.scheme() == "file",
+            _ => false,
+        };
+        if !allowed {
+            log::info!("Ignoring link in a frame with unsupported scheme: {}", url);
+            return;
+        }
+        // 同じ文書内のフラグメントへのリンクは読み込み直さない
+        if url.fragment().is_some()
+            && url.as_str().split('#').next() == frame.url.as_str().split('#').next()
+        {
+            return;
+        }
 
-    for node in link_nodes {
-        let (rel, href) = {
-            let node_ref = node.borrow();
-            let html_node = &node_ref.value;
+        log::info!("Navigating a frame to {}", url);
+        let webview = self.new_frame_webview(frame.sandbox);
+        let frame = &mut self.frames[i];
+        frame.webview.shutdown_scripts();
+        frame.webview = webview;
+        frame.url = url;
+        frame.srcdoc = None;
+        self.needs_redraw = true;
+    }
 
-            let rel = html_node.get_attr("rel").map(|s| s.to_string());
-            let href = html_node.get_attr("href").map(|s| s.to_string());
-            (rel, href)
+    /// `<meta http-equiv="refresh">` の待ち時間と移動先を受け取る（一度だけ）
+    ///
+    /// 移動先は base URL で解決する。None なら同じページを読み込み直す。
+    pub fn take_refresh(&mut self) -> Option<(Duration, Option<Url>)> {
+        let refresh = self.refresh.take()?;
+        let url = match refresh.url {
+            Some(href) => {
+                let base_url = self.base_url().or(self.document_url())?;
+                match resolve_url(base_url, &href) {
+                    Ok(url) => Some(url),
+                    Err(e) => {
+                        self.report(
+                            Level::Warning,
+                            Source::Html,
+                            format!("Invalid refresh URL {:?}: {}", href, e),
+                        );
+                        return None;
+                    }
+                }
+            }
+            None => None,
         };
+        Some((refresh.delay, url))
+    }
 
-        if let (Some(rel), Some(href)) = (rel, href)
-            && rel == "stylesheet"
-        {
-            let css_url = match resolve_url(&base_url, &href) {
-                Ok(url) => url,
-                Err(_) => continue,
-            };
-            style_links.push(css_url);
-        }
+    /// スクリプトが setTimeout などで頼んだタイマーの操作を受け取る
+    pub fn take_timer_requests(&mut self) -> Vec<TimerRequest> {
+        self.script_runtime.take_timer_requests()
     }
 
-    // --- Inline styles ---
-    let inline_styles = dom.collect_text_by_tag("style");
+    /// take_timer_requests を `<iframe>` の中の文書の分も含めて、文書の番号と一緒に受け取る
+    pub fn take_all_timer_requests(&mut self) -> Vec<(u64, TimerRequest)> {
+        let mut requests: Vec<_> = self
+            .take_timer_requests()
+            .into_iter()
+            .map(|request| (self.document_id, request))
+            .collect();
+        for frame in &mut self.frames {
+            requests.extend(frame.webview.take_all_timer_requests());
+        }
+        requests
+    }
+
+    /// 期限の来たタイマーのコールバックを実行し、DOM の書き換えを反映する
+    pub fn fire_timer(&mut self, id: u32) {
+        if self.script_runtime.is_shut_down() {
+            return;
+        }
+        self.capture(|wv| report_uncaught(wv.script_runtime.fire_timer(id)));
+        self.apply_script_mutations();
+    }
+
+    /// スクリプトの要求のうち送ってよいものを URL を解決して返す
+    ///
+    /// GET 以外と、同一オリジンポリシーで送れない要求（[`fetch_policy::check_request`]）は
+    /// ここで失敗させる。別のオリジンへの要求の応答は BrowserApp が CORS で確かめる。
+    fn take_script_requests(&mut self) -> Vec<(u32, Url)> {
+        let mut allowed = Vec::new();
+        for request in self.script_runtime.take_fetch_requests() {
+            let url = self
+                .base_url()
+                .ok_or_else(|| "No document".to_string())
+                .and_then(|base| {
+                    base.join(&request.url)
+                        .map_err(|e| format!("Invalid URL {}: {e}", request.url))
+                });
+            let checked = url.and_then(|url| {
+                if request.method != "GET" {
+                    return Err(format!("Method {} is not supported", request.method));
+                }
+                fetch_policy::check_request(
+                    self.initiator().as_ref(),
+                    &url,
+                    &request.method,
+                    RequestMode::Cors,
+                )
+                .map_err(|e| e.to_string())?;
+                Ok(url)
+            });
+
+            match checked {
+                Ok(url) => {
+                    self.script_requests.push(request.id);
+                    allowed.push((request.id, url));
+                }
+                Err(message) => {
+                    self.report(
+                        Level::Error,
+                        Source::Script,
+                        format!("Script request failed: {message}"),
+                    );
+                    self.complete_script_request(request.id, Err(message));
+                }
+            }
+        }
+        allowed
+    }
+
+    /// スクリプトの要求 request の応答が届いた（失敗なら理由）
+    pub fn on_script_request_done(&mut self, request: u32, result: Result<FetchResponse, String>) {
+        let Some(index) = self.script_requests.iter().position(|&r| r == request) else {
+            return;
+        };
+        self.script_requests.remove(index);
+        self.complete_script_request(request, result);
+    }
+
+    fn complete_script_request(&mut self, request: u32, result: Result<FetchResponse, String>) {
+        self.capture(|wv| report_uncaught(wv.script_runtime.complete_fetch(request, result)));
+        self.apply_script_mutations();
+    }
+
+    /// スクリプトの realm を捨てる。タブを閉じたときやページを移るときに呼ぶ
+    ///
+    /// 以後この WebView ではスクリプトを実行しない。
+    pub fn shutdown_scripts(&mut self) {
+        self.scripts.clear();
+        self.script_requests.clear();
+        self.scripts_executed = true;
+        self.script_runtime.shutdown();
+        for frame in &mut self.frames {
+            frame.webview.shutdown_scripts();
+        }
+    }
+
+    /// Update page (e.g. DOM changed)
+    ///
+    /// This is a stub method for now.
+    pub fn update_page(&mut self) {
+        // <title> も書き換えられているかもしれない
+        self.refresh_title();
+        let measurer = PlatformTextMeasurer::new().unwrap();
+
+        self.update_layout_and_info(measurer);
+    }
+
+    fn refresh_title(&mut self) {
+        if let Some(info) = self.docment_info.as_mut() {
+            info.title = info
+                .dom
+                .collect_text_by_tag("title")
+                .first()
+                .cloned()
+                .unwrap_or_default();
+        }
+    }
+
+    fn apply_css_and_relayout(&mut self) {
+        self.resolve_styles();
+
+        let measurer = PlatformTextMeasurer::new().unwrap();
+
+        self.update_layout_and_info(measurer);
+    }
+
+    /// UA の CSS、<style>、読み込んだ CSS、拡張機能の CSS の順にスタイルを解決し直す
+    fn resolve_styles(&mut self) {
+        let started = Instant::now();
+        let mut styles = layouter::ua::user_agent_styles(&self.media);
+        self.capture(|wv| {
+            styles.extend(resolve_all_css(&wv.inline_css, &wv.media));
+            styles.extend(resolve_all_css(&wv.loaded_css, &wv.media));
+            styles.extend(resolve_all_css(&wv.injected_css, &wv.media));
+        });
+        self.resolved_styles = styles;
+
+        self.supports_dark = self
+            .docment_info
+            .as_ref()
+            .is_some_and(|info| meta_supports_dark(&info.dom))
+            || css_supports_dark(&self.resolved_styles);
+        self.metrics.add_style(started.elapsed());
+    }
+
+    fn update_layout_and_info(&mut self, measurer: PlatformTextMeasurer) {
+        let started = Instant::now();
+        self.layout_and_info = Some(self.capture(|wv| wv.build_layout_and_info(&measurer)));
+        self.metrics.add_layout(started.elapsed());
+        self.queue_image_requests();
+        // ツリーが作り直されたので選択位置やスクロール対象のパスは使えない
+        self.selection = None;
+        self.scroller.cancel();
+        self.hover_path = None;
+        self.focused_input = None;
+        self.active_path = None;
+        self.focus_path = None;
+        self.focus_visible = false;
+        self.needs_redraw = true;
+    }
+
+    fn build_layout_and_info(&self, measurer: &PlatformTextMeasurer) -> (LayoutNode, InfoNode) {
+        let composition = self
+            .focused_input
+            .as_ref()
+            .and_then(|f| Some((f.path.as_slice(), f.composition()?.text)));
+
+        let (mut layout, mut info) = layouter::build_layout_and_info_with_visited(
+            &self.docment_info.as_ref().unwrap().dom.root,
+            &self.resolved_styles,
+            measurer,
+            TextStyle {
+                color: if self.uses_dark_colors() {
+                    DARK_TEXT
+                } else {
+                    self.default_text.color
+                },
+                ..self.default_text
+            },
+            Vec::new(),
+            self.hover_path.as_deref(),
+            self.active_path.as_deref(),
+            self.focus_path.as_deref(),
+            composition
+                .as_ref()
+                .map(|(path, text)| (*path, text.as_str())),
+            &|href| self.is_visited_href(href),
+            &|node| self.field_values.get(node).map(str::to_string),
+        );
+        self.attach_images(&mut layout, &mut info);
+        (layout, info)
+    }
+
+    /// 届いた画像を `<img>` と CSS の背景に付ける（src と `url()` は文書の base URL で解決する）
+    fn attach_images(&self, layout: &mut LayoutNode, info: &mut InfoNode) {
+        let Some(document) = self.docment_info.as_ref() else {
+            return;
+        };
+        if self.images.is_empty() {
+            return;
+        }
+        layouter::attach_images(layout, info, &|src| {
+            let url = resolve_url(&document.base_url, src).ok()?;
+            self.images.get(&url).cloned()
+        });
+    }
+
+    /// 文書と `<iframe>` の文書が表示している画像の URL
+    pub fn image_urls(&self) -> Vec<Url> {
+        let mut urls: Vec<Url> = self.images.keys().cloned().collect();
+        for frame in &self.frames {
+            urls.extend(frame.webview.image_urls());
+        }
+        urls
+    }
+
+    /// デコードが済んだ画像を受け取る。この文書か `<iframe>` の文書が要求したものなら
+    /// レイアウトし直して true を返す
+    pub fn on_image_decoded(&mut self, url: &Url, image: &Arc<RgbaImage>) -> bool {
+        let mut used = false;
+        for frame in &mut self.frames {
+            used |= frame.webview.on_image_decoded(url, image);
+        }
+
+        if !self.requested_images.contains(url) || self.images.contains_key(url) {
+            return used;
+        }
+        self.images.insert(url.clone(), Arc::clone(image));
+        self.restyle();
+        true
+    }
+
+    /// レイアウトした `<img>` と CSS の背景画像のうち、まだ要求していないものを要求する
+    ///
+    /// img-src（なければ default-src）で許されない画像は要求しない。CSS の `url()` も
+    /// 文書の base URL で解決する。
+    fn queue_image_requests(&mut self) {
+        let (Some((_, info)), Some(document)) =
+            (self.layout_and_info.as_ref(), self.docment_info.as_ref())
+        else {
+            return;
+        };
+        let mut sources = Vec::new();
+        collect_image_sources(info, &mut sources);
+
+        let mut refused = Vec::new();
+        for src in sources {
+            let Ok(url) = resolve_url(&document.base_url, &src) else {
+                continue;
+            };
+            if !self.requested_images.insert(url.clone()) {
+                continue;
+            }
+            if self
+                .csp
+                .allows_url(Directive::Image, &url, &document.document_url)
+            {
+                self.image_requests.push(url);
+            } else {
+                refused.push(url);
+            }
+        }
+        for url in refused {
+            self.report(
+                Level::Error,
+                Source::Security,
+                format!("Refused to load the image {url} (Content Security Policy)"),
+            );
+        }
+    }
+
+    /// DOM は変えずにスタイルだけを計算し直す（:hover の変化など）
+    ///
+    /// ツリーの形は変わらないので、スクロール位置と選択範囲は引き継ぐ。
+    fn restyle(&mut self) {
+        if self.docment_info.is_none() {
+            return;
+        }
+        let Ok(measurer) = PlatformTextMeasurer::new() else {
+            return;
+        };
+
+        let started = Instant::now();
+        let (layout, mut info) = self.build_layout_and_info(&measurer);
+        self.metrics.add_layout(started.elapsed());
+        if let Some((_, old_info)) = self.layout_and_info.as_ref() {
+            scroll::copy_scroll_offsets(old_info, &mut info);
+        }
+        if let Some(focused) = self.focused_input.as_ref() {
+            set_input_caret(
+                &mut info,
+                &focused.path,
+                Some(focused.caret(self.spell_checker.as_deref())),
+            );
+        }
+        self.layout_and_info = Some((layout, info));
+        self.queue_image_requests();
+        self.needs_redraw = true;
+
+        // 次の描画を待たずにヒットテストできるようにする
+        if let Some(viewport) = self.viewport {
+            self.relayout(viewport);
+        }
+    }
+
+    /// (x, y) の下にあるリンクを :hover にする。:hover の対象が変わったら true を返す
+    ///
+    /// :hover の状態は今のところリンクについてだけ追跡する。
+    pub fn hover_at(&mut self, x: f32, y: f32) -> bool {
+        // <iframe> の外にカーソルが出たら中の :hover を外す
+        let in_frame = self.frame_at(x, y);
+        let mut frame_changed = false;
+        for (i, frame) in self.frames.iter_mut().enumerate() {
+            let (frame_x, frame_y) = match in_frame {
+                Some((j, frame_x, frame_y)) if j == i => (frame_x, frame_y),
+                _ => (f32::NEG_INFINITY, f32::NEG_INFINITY),
+            };
+            frame_changed |= frame.webview.hover_at(frame_x, frame_y);
+        }
+
+        let Some((layout, info)) = self.layout_and_info.as_ref() else {
+            return frame_changed;
+        };
+
+        let hits = input::hit_test(layout, info, x, y);
+        let link_path = input::find_link(&hits).map(|(i, _)| input::node_path(&hits, i));
+
+        if link_path == self.hover_path {
+            return frame_changed;
+        }
+
+        self.hover_path = link_path;
+        self.restyle();
+        true
+    }
+
+    /// マウスカーソルがリンクの上にあるか
+    pub fn is_over_link(&self) -> bool {
+        self.hover_path.is_some() || self.frames.iter().any(|f| f.webview.is_over_link())
+    }
+
+    pub fn navigate(&mut self) {
+        self.reset_for_navigation();
+    }
+
+    fn reset_for_navigation(&mut self) {
+        if self.phase != PagePhase::Init {
+            self.phase = PagePhase::BeforeHtmlParsing;
+        }
+
+        self.docment_info = None;
+        self.pending_css_urls.clear();
+        self.loaded_css.clear();
+        self.preloads.clear();
+        self.requested_images.clear();
+        self.image_requests.clear();
+        self.images.clear();
+        self.resolved_styles.clear();
+        self.layout_and_info = None;
+        self.selection = None;
+        self.scroller.cancel();
+        self.hover_path = None;
+        self.focused_input = None;
+        self.field_values.clear();
+        self.active_path = None;
+        self.focus_path = None;
+        self.focus_visible = false;
+        self.events.clear();
+        self.scripts.clear();
+        self.scripts_executed = false;
+        self.refresh = None;
+        self.viewport_meta = None;
+        self.page_scale = 1.0;
+        self.content_size = (0.0, 0.0);
+        self.console.clear();
+        self.shutdown_frames();
+        self.metrics = PageLoadMetrics::new(Instant::now());
+        self.metrics_reported = false;
+        // 権限は文書ごとに決め直す
+        self.permission_requests.clear();
+        self.permissions.clear();
+        self.positions_waiting.clear();
+        self.position_requests.clear();
+        self.positions_pending.clear();
+        self.positions.clear();
+        self.close_notifications();
+
+        self.needs_redraw = false;
+    }
+
+    /// 読み込みを止める
+    ///
+    /// 外部 CSS を待っている途中なら、届いた分だけでスタイルを確定する。
+    pub fn stop_loading(&mut self) {
+        if self.phase == PagePhase::CssPending {
+            self.pending_css_urls.truncate(self.loaded_css.len());
+            self.apply_css_and_relayout();
+            self.phase = PagePhase::CssApplied;
+        }
+
+        // 取り消したスクリプトの要求は失敗として返す
+        for request in std::mem::take(&mut self.script_requests) {
+            self.complete_script_request(request, Err("The request was aborted".to_string()));
+        }
+
+        for frame in &mut self.frames {
+            frame.webview.stop_loading();
+        }
+    }
+
+    pub fn title(&self) -> Option<&String> {
+        self.docment_info.as_ref().map(|d| &d.title)
+    }
+
+    pub fn relayout(&mut self, viewport: (f32, f32)) {
+        if self.layout_and_info.is_none() {
+            self.viewport = Some(viewport);
+            return;
+        }
+        let viewport = self.apply_viewport_meta(viewport);
+        self.viewport = Some(viewport);
+        let Some((layout, info)) = self.layout_and_info.as_mut() else {
+            return;
+        };
+
+        let started = Instant::now();
+        ui_layout::LayoutEngine::layout(layout, viewport.0, viewport.1);
+
+        // 折り返しでテキストの大きさが変わったらもう一度レイアウトする
+        if let Ok(measurer) = PlatformTextMeasurer::new()
+            && layouter::wrap_text(layout, info, &measurer)
+        {
+            ui_layout::LayoutEngine::layout(layout, viewport.0, viewport.1);
+        }
+        self.metrics.add_layout(started.elapsed());
+        self.content_size = scroll::root_content_size(layout);
+
+        // 外部 CSS まで揃ってからでないと高さが足りず、途中で丸められてしまう
+        if self.phase == PagePhase::CssApplied
+            && let Some((x, y)) = self.pending_scroll.take()
+            && let NodeKind::Container {
+                scroll_offset_x,
+                scroll_offset_y,
+                ..
+            } = &mut info.kind
+        {
+            *scroll_offset_x = x;
+            *scroll_offset_y = y;
+        }
+
+        // コンテンツやビューポートが縮んだら空白までスクロールしたままにしない
+        scroll::clamp_scroll_offsets(layout, info, viewport);
+        self.scroller.clamp_targets(layout, info, viewport);
+
+        if let Some(focused) = self.focused_input.as_ref() {
+            scroll_input_to_caret(layout, info, &focused.path);
+        }
+
+        // <iframe> の中の文書は iframe の content box をビューポートにする
+        for i in 0..self.frames.len() {
+            if let Some((_, _, width, height)) = self.frame_rect(i) {
+                self.frames[i].webview.relayout((width, height));
+            }
+        }
+    }
+
+    /// `<meta name="viewport">` の倍率を窓の幅に合わせて決め直し、その倍率での
+    /// ビューポートを返す
+    ///
+    /// viewport は今の倍率でのビューポート。窓の大きさが変わるたびに呼ばれるので、
+    /// `width=400` のようなページは窓の幅に合わせて拡大し直す。
+    fn apply_viewport_meta(&mut self, viewport: (f32, f32)) -> (f32, f32) {
+        let Some(meta) = self.viewport_meta else {
+            return viewport;
+        };
+        let old_zoom = self.zoom();
+        let window_width = viewport.0 * old_zoom;
+        let page_scale = meta.initial_scale(window_width);
+        if page_scale == self.page_scale {
+            return viewport;
+        }
+        self.page_scale = page_scale;
+        self.needs_redraw = true;
+        let ratio = old_zoom / self.zoom();
+        (viewport.0 * ratio, viewport.1 * ratio)
+    }
+
+    /// 描画コマンド（選択範囲、フォーカスリング、`<iframe>` の中の文書を含む）
+    pub fn draw_commands(&self) -> Vec<DrawCommand> {
+        let Some((layout, info)) = self.layout_and_info() else {
+            return Vec::new();
+        };
+        renderer_model::generate_draw_commands_with_frames(
+            layout,
+            info,
+            self.selection(),
+            self.focus_ring(),
+            &|path| {
+                self.frames
+                    .iter()
+                    .find(|f| f.path == path)
+                    .map(|f| f.webview.draw_commands())
+            },
+        )
+    }
+
+    /// 印刷用のレイアウト（`@media print` を当て、幅 page.0 の紙に合わせる）
+    ///
+    /// 高さは文書の全体が入るまで伸ばす（ページ分けは [`paginate`] で行う）。
+    /// 画面のレイアウトは変えない。:hover やフォーカスは反映せず、利用者が暗い配色を
+    /// 選んでいても明るい配色で作る。`<iframe>` の中の文書は印刷しない。
+    pub fn print_layout(&self, page: (f32, f32)) -> Option<(LayoutNode, InfoNode)> {
+        let document = self.docment_info.as_ref()?;
+        let media = MediaContext {
+            media_type: MediaType::Print,
+            color_scheme: ColorScheme::Light,
+        };
+        let mut styles = layouter::ua::user_agent_styles(&media);
+        styles.extend(resolve_all_css(&self.inline_css, &media));
+        styles.extend(resolve_all_css(&self.loaded_css, &media));
+        styles.extend(resolve_all_css(&self.injected_css, &media));
+
+        let measurer = PlatformTextMeasurer::new().ok()?;
+        let (mut layout, mut info) = layouter::build_layout_and_info(
+            &document.dom.root,
+            &styles,
+            &measurer,
+            self.default_text,
+            Vec::new(),
+            None,
+            None,
+            None,
+            None,
+        );
+        self.attach_images(&mut layout, &mut info);
+        ui_layout::LayoutEngine::layout(&mut layout, page.0, page.1);
+        if layouter::wrap_text(&mut layout, &mut info, &measurer) {
+            ui_layout::LayoutEngine::layout(&mut layout, page.0, page.1);
+        }
+        // ルートの箱で切り抜かれないよう、文書の高さでレイアウトし直す
+        let height = paginate::content_height(&layout, &info);
+        if height > page.1 {
+            ui_layout::LayoutEngine::layout(&mut layout, page.0, height);
+        }
+        Some((layout, info))
+    }
+
+    /// 今の文書の読み込みにかかった時間
+    pub fn metrics(&self) -> &PageLoadMetrics {
+        &self.metrics
+    }
+
+    /// 文書の中身を描画コマンドにしたことを記録する（最初の 1 回が first paint）
+    pub fn mark_painted(&mut self) {
+        if self.layout_and_info.is_some() {
+            self.metrics.record_first_paint(Instant::now());
+        }
+    }
+
+    /// 読み込みと最初の描画を終えた計測値を、文書ごとに 1 度だけ返す
+    pub fn take_finished_metrics(&mut self) -> Option<PageLoadMetrics> {
+        if self.metrics_reported || !self.metrics.is_complete() {
+            return None;
+        }
+        self.metrics_reported = true;
+        Some(self.metrics.clone())
+    }
+
+    /// 現在描画可能な Layout / Info を返す（なければ None）
+    pub fn layout_and_info(&self) -> Option<(&LayoutNode, &InfoNode)> {
+        self.layout_and_info.as_ref().map(|(l, i)| (l, i))
+    }
+
+    pub fn layout_and_info_mut(&mut self) -> Option<(&LayoutNode, &mut InfoNode)> {
+        self.layout_and_info.as_mut().map(|(l, i)| (&*l, i))
+    }
+
+    /// 選択を開始する（x, y: グローバル座標）
+    ///
+    /// テキスト以外の場所なら選択を解除する。
+    pub fn begin_selection(&mut self, x: f32, y: f32) {
+        self.selection = self
+            .layout_and_info()
+            .and_then(|(layout, info)| selection::text_position_at(layout, info, x, y))
+            .map(Selection::collapsed);
+        self.needs_redraw = true;
+    }
+
+    /// 選択の終点を (x, y) に動かす
+    ///
+    /// 入力欄にフォーカスがあれば入力欄の中の選択を動かす。
+    pub fn extend_selection(&mut self, x: f32, y: f32) {
+        if self.focused_input.is_some() {
+            self.extend_input_selection(x, y);
+            return;
+        }
+        let Some((layout, info)) = self.layout_and_info.as_ref() else {
+            return;
+        };
+        let Some(focus) = selection::text_position_at(layout, info, x, y) else {
+            return;
+        };
+
+        if let Some(sel) = self.selection.as_mut()
+            && sel.focus != focus
+        {
+            sel.focus = focus;
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn clear_selection(&mut self) {
+        if self.selection.take().is_some() {
+            self.needs_redraw = true;
+        }
+    }
+
+    /// 現在の選択範囲（幅 0 の選択は含まない）
+    pub fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref().filter(|s| !s.is_collapsed())
+    }
+
+    /// 選択されている文字列
+    pub fn selected_text(&self) -> Option<String> {
+        let sel = self.selection()?;
+        let (layout, info) = self.layout_and_info()?;
+
+        Some(selection::selected_text(layout, info, sel))
+    }
+
+    /// (x, y) にある入力欄にフォーカスを移し、キャレットをその位置に置く
+    ///
+    /// 入力欄がなければフォーカスを外して false を返す。
+    pub fn focus_input_at(&mut self, x: f32, y: f32) -> bool {
+        let Some((layout, info)) = self.layout_and_info.as_ref() else {
+            return false;
+        };
+
+        let hits = input::hit_test(layout, info, x, y);
+        let Some(path) = input::find_text_input(&hits).map(|i| input::node_path(&hits, i)) else {
+            self.blur_input();
+            self.set_focus(None, false);
+            return false;
+        };
+        let display_offset = self.input_offset_at(&path, x, y);
+
+        let Some(node) = self.dom_node_at(&path) else {
+            return false;
+        };
+
+        if self.focused_input.as_ref().map(|f| &f.path) != Some(&path) {
+            self.blur_input();
+        }
+        let value = self.field_values.value_of(&node);
+        let focused = self
+            .focused_input
+            .get_or_insert_with(|| FocusedInput::new(path, &node.borrow().value, value));
+        if let Some(display_offset) = display_offset {
+            let offset = focused.value_offset(display_offset);
+            focused.edit.set_caret(offset, false);
+        }
+        let path = focused.path.clone();
+
+        self.selection = None;
+        self.set_focus(Some(path), false);
+        self.update_input_caret();
+        true
+    }
+
+    /// path の要素にイベントリスナーを登録する
+    pub fn add_event_listener(
+        &mut self,
+        path: Vec<usize>,
+        event_type: EventType,
+        capture: bool,
+        listener: impl FnMut(&mut Event) + 'static,
+    ) {
+        self.events.add(path, event_type, capture, listener);
+    }
+
+    /// event をリスナーに届ける。既定の動作をしてよければ true
+    pub fn dispatch_event(&mut self, event: &mut Event) -> bool {
+        if self.events.is_empty() {
+            return true;
+        }
+        events::dispatch(&mut self.events, event)
+    }
+
+    /// (x, y) にある最も内側の要素（イベントの対象）のパス
+    pub fn event_target_at(&self, x: f32, y: f32) -> Option<Vec<usize>> {
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        events::target_from_hits(&input::hit_test(layout, info, x, y))
+    }
+
+    /// (x, y) をクリックする。リンクなら移動先を返す
+    ///
+    /// click イベントを届け、preventDefault されなければ既定の動作をする。
+    pub fn click_at(&mut self, x: f32, y: f32) -> Option<LinkNavigation> {
+        // <iframe> の中のクリックは中の文書に届け、リンクは iframe の中で開く
+        if let Some((i, frame_x, frame_y)) = self.frame_at(x, y) {
+            let navigation = self.frames[i].webview.click_at(frame_x, frame_y)?;
+            if navigation.target != LinkTarget::Here {
+                return Some(navigation);
+            }
+            self.navigate_frame(i, &navigation.href);
+            return None;
+        }
+
+        let target = self.event_target_at(x, y)?;
+        if !self.dispatch_event(&mut Event::new(EventType::Click, target)) {
+            return None;
+        }
+
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        let hits = input::hit_test(layout, info, x, y);
+        let link = input::find_link(&hits);
+
+        // <summary> の中のリンクはリンクとして開く
+        if let Some(i) = input::find_summary(&hits)
+            && link.is_none_or(|(j, _)| j > i)
+        {
+            let path = input::node_path(&hits, i);
+            self.toggle_details(&path);
+            return None;
+        }
+
+        let (i, href) = link?;
+        let href = href.to_string();
+        let link = self.dom_node_at(&input::node_path(&hits, i));
+        self.link_navigation(link.as_ref(), href)
+    }
+
+    /// summary_path の `<summary>` を持つ `<details>` を開いたり閉じたりする
+    ///
+    /// DOM の `open` 属性を書き換えてレイアウトし直す。
+    fn toggle_details(&mut self, summary_path: &[usize]) {
+        let Some((_, details_path)) = summary_path.split_last() else {
+            return;
+        };
+        let Some(details) = self.dom_node_at(details_path) else {
+            return;
+        };
+        {
+            let mut details = details.borrow_mut();
+            if details.value.remove_attr("open").is_none() {
+                details.value.set_attr("open", String::new());
+            }
+        }
+        self.restyle();
+    }
+
+    /// (x, y) にある要素のスタイルを調べる（`<iframe>` の中なら中の文書の要素）
+    ///
+    /// :hover などの状態は考えず、要素に当たる規則をすべて集める。
+    pub fn inspect_at(&self, x: f32, y: f32) -> Option<StyleInspection> {
+        if let Some((i, frame_x, frame_y)) = self.frame_at(x, y) {
+            return self.frames[i].webview.inspect_at(frame_x, frame_y);
+        }
+
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        let hits = input::hit_test(layout, info, x, y);
+        let root = &self.docment_info.as_ref()?.dom.root;
+        let chain = cascade::element_chain(root, &input::node_path(&hits, 0))?;
+        StyleInspection::new(&chain, &self.resolved_styles, self.document_url().cloned())
+    }
+
+    /// (x, y) にある要素の margin / border / padding / content の矩形（ビューポート座標）
+    ///
+    /// テキストの上なら、それを囲む要素の箱を返す。`<iframe>` の中なら中の文書の要素。
+    pub fn box_model_at(&self, x: f32, y: f32) -> Option<BoxModel> {
+        if let Some((i, frame_x, frame_y)) = self.frame_at(x, y) {
+            let (offset_x, offset_y, ..) = self.frame_rect(i)?;
+            let mut model = self.frames[i].webview.box_model_at(frame_x, frame_y)?;
+            for rect in [
+                &mut model.margin,
+                &mut model.border,
+                &mut model.padding,
+                &mut model.content,
+            ] {
+                rect.0 += offset_x;
+                rect.1 += offset_y;
+            }
+            return Some(model);
+        }
+
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        let hits = input::hit_test(layout, info, x, y);
+        let mut path = input::node_path(&hits, 0);
+        while !matches!(
+            self.dom_node_at(&path)?.borrow().value,
+            HtmlNodeType::Element { .. }
+        ) {
+            path.pop()?;
+        }
+
+        let root = &self.docment_info.as_ref()?.dom.root;
+        let chain = cascade::element_chain(root, &path)?;
+        let ((origin_x, origin_y), node, _) = node_with_origin(layout, info, &path)?;
+        let boxes = node.layout_boxes.first()?;
+        let [border, padding, content] = [boxes.border_box, boxes.padding_box, boxes.content_box]
+            .map(|r| (origin_x + r.x, origin_y + r.y, r.width, r.height));
+
+        // % の margin は親の content box の幅に対する割合
+        let parent_width = path
+            .split_last()
+            .and_then(|(_, parent)| node_with_origin(layout, info, parent))
+            .and_then(|(_, parent, _)| parent.layout_boxes.first())
+            .map_or(0.0, |b| b.content_box.width);
+        let viewport = self.viewport.unwrap_or((0.0, 0.0));
+        let spacing = &node.style.spacing;
+        let margin = [
+            &spacing.margin_top,
+            &spacing.margin_right,
+            &spacing.margin_bottom,
+            &spacing.margin_left,
+        ]
+        .map(|len| resolve_margin(len, parent_width, viewport));
+
+        Some(BoxModel::new(
+            devtools::element_label(chain.first()?),
+            border,
+            padding,
+            content,
+            margin,
+        ))
+    }
+
+    /// link の target 属性（なければ `<base target>`）から、href をどこで開くかを決める
+    ///
+    /// この文書の外で開くリンクの href は、この文書の base URL で解決しておく。
+    /// rel 属性の noreferrer は Referer を送らず、新しいタブの文書にリンク元の
+    /// 文書を見せるのは rel="opener" のときだけ（noopener と noreferrer が優先）。
+    fn link_navigation(
+        &self,
+        link: Option<&NodeRef<HtmlNodeType>>,
+        href: String,
+    ) -> Option<LinkNavigation> {
+        let attr = |name: &str| {
+            link.and_then(|node| node.borrow().value.get_attr(name).map(str::to_string))
+        };
+        let target = attr("target")
+            .or_else(|| self.docment_info.as_ref()?.base_target.clone())
+            .map(|target| target.trim().to_ascii_lowercase());
+        let rel = attr("rel").unwrap_or_default().to_ascii_lowercase();
+        let has_rel = |token: &str| rel.split_ascii_whitespace().any(|t| t == token);
+        let noreferrer = has_rel("noreferrer");
+
+        let (target, href) = match target.as_deref() {
+            Some("_blank") => {
+                if self.sandbox.popups {
+                    diagnostics::report(
+                        Level::Warning,
+                        Source::Security,
+                        "Blocked opening a new tab from a sandboxed frame",
+                    );
+                    return None;
+                }
+                (LinkTarget::NewTab, self.absolute_href(&href)?)
+            }
+            Some("_top") if self.frame_depth > 0 => {
+                if self.sandbox.top_navigation {
+                    diagnostics::report(
+                        Level::Warning,
+                        Source::Security,
+                        "Blocked top-level navigation from a sandboxed frame",
+                    );
+                    return None;
+                }
+                (LinkTarget::Top, self.absolute_href(&href)?)
+            }
+            _ => (LinkTarget::Here, href),
+        };
+        let opener = (target == LinkTarget::NewTab
+            && has_rel("opener")
+            && !has_rel("noopener")
+            && !noreferrer)
+            .then_some(self.document_id);
+
+        Some(LinkNavigation {
+            href,
+            target,
+            referrer: self.document_url().filter(|_| !noreferrer).cloned(),
+            opener,
+        })
+    }
+
+    fn absolute_href(&self, href: &str) -> Option<String> {
+        let base_url = self.base_url().or(self.document_url())?;
+        match resolve_url(base_url, href) {
+            Ok(url) => Some(url.to_string()),
+            Err(e) => {
+                diagnostics::report(
+                    Level::Warning,
+                    Source::Html,
+                    format!("Invalid link href {:?}: {}", href, e),
+                );
+                None
+            }
+        }
+    }
+
+    /// フォーカスのある要素（なければ文書）に keydown を届ける。既定の動作をしてよければ true
+    pub fn key_down(&mut self, key: &str) -> bool {
+        let target = self.focus_path.clone().unwrap_or_default();
+        self.dispatch_event(&mut Event::key_down(target, key))
+    }
+
+    /// Tab キーで次（backward なら前）の要素にフォーカスを移す
+    ///
+    /// 最後の要素の次はページの外（どこにもフォーカスがない状態）になる。
+    pub fn focus_next(&mut self, backward: bool) {
+        let next = match (self.docment_info.as_ref(), self.layout_and_info.as_ref()) {
+            (Some(doc), Some((layout, _))) => {
+                let order = focus::focus_order(&doc.dom.root, layout);
+                focus::next_focus(&order, self.focus_path.as_deref(), backward)
+            }
+            _ => None,
+        };
+        self.move_focus(next);
+    }
+
+    /// キーボードで移したのと同じように、path の要素（None ならページの外）にフォーカスを移す
+    fn move_focus(&mut self, next: Option<Vec<usize>>) {
+        if self.focused_input.as_ref().map(|f| &f.path) != next.as_ref() {
+            self.blur_input();
+        }
+        if let Some(path) = next.as_ref()
+            && self.focused_input.is_none()
+            && self.is_text_input_at(path)
+            && let Some(node) = self.dom_node_at(path)
+        {
+            let value = self.field_values.value_of(&node);
+            self.focused_input = Some(FocusedInput::new(path.clone(), &node.borrow().value, value));
+        }
+
+        self.selection = None;
+        self.set_focus(next.clone(), true);
+        if let Some(path) = next {
+            self.scroll_into_view(&path);
+        }
+    }
+
+    /// 文書のアクセシビリティツリー（レイアウトがまだなければ None）
+    pub fn accessibility_tree(&self) -> Option<AccessTree> {
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        let root = &self.docment_info.as_ref()?.dom.root;
+        Some(accessibility::build_tree(
+            root,
+            layout,
+            info,
+            self.focus_path.as_deref(),
+        ))
+    }
+
+    /// 支援技術から頼まれて、ID が id のノードの要素にフォーカスを移す。なければ false
+    pub fn focus_accessible(&mut self, id: u64) -> bool {
+        let Some(path) = self
+            .accessibility_tree()
+            .and_then(|tree| tree.root.find(id).map(|node| node.path.clone()))
+        else {
+            return false;
+        };
+        self.move_focus(Some(path));
+        true
+    }
+
+    /// フォーカスのある要素を Enter キーで実行したときの移動先
+    ///
+    /// リンクなら href、フォームを送信するボタンなら送信先を返す。
+    pub fn activate_focused(&mut self) -> Option<LinkNavigation> {
+        let path = self.focus_path.clone()?;
+        if !self.dispatch_event(&mut Event::new(EventType::Click, path.clone())) {
+            return None;
+        }
+        let node = self.dom_node_at(&path)?;
+        let value = node.borrow().value.clone();
+
+        match value.tag_name() {
+            Some("a" | "area") => {
+                let href = value.get_attr("href")?.to_string();
+                self.link_navigation(Some(&node), href)
+            }
+            _ if form::submits_form(&value) => {
+                let url = self.submission_url(&node, Some(&node))?;
+                Some(LinkNavigation {
+                    href: url.to_string(),
+                    target: LinkTarget::Here,
+                    referrer: self.document_url().cloned(),
+                    opener: None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn is_text_input_at(&self, path: &[usize]) -> bool {
+        self.layout_and_info
+            .as_ref()
+            .and_then(|(_, info)| path.iter().try_fold(info, |n, &i| n.children.get(i)))
+            .is_some_and(|n| {
+                matches!(
+                    n.kind,
+                    NodeKind::Container {
+                        role: ContainerRole::TextInput { .. },
+                        ..
+                    }
+                )
+            })
+    }
+
+    /// フォーカスリングを描く要素のパス
+    pub fn focus_ring(&self) -> Option<&[usize]> {
+        self.focus_path.as_deref().filter(|_| self.focus_visible)
+    }
+
+    /// path の要素にフォーカスを移す（None ならフォーカスを外す）
+    fn set_focus(&mut self, path: Option<Vec<usize>>, visible: bool) {
+        self.focus_visible = visible && path.is_some();
+        self.needs_redraw = true;
+        if path != self.focus_path {
+            self.focus_path = path;
+            self.restyle();
+        }
+    }
+
+    /// path の要素が見えるようにページを縦にスクロールする
+    fn scroll_into_view(&mut self, path: &[usize]) {
+        let (Some((layout, info)), Some(viewport)) = (self.layout_and_info.as_ref(), self.viewport)
+        else {
+            return;
+        };
+        let Some(((_, origin_y), node, _)) = node_with_origin(layout, info, path) else {
+            return;
+        };
+        let Some(rect) = node.layout_boxes.first().map(|b| b.border_box) else {
+            return;
+        };
+
+        let top = origin_y + rect.y - FOCUS_SCROLL_MARGIN;
+        let bottom = origin_y + rect.y + rect.height + FOCUS_SCROLL_MARGIN;
+        if top < 0.0 {
+            self.scroll_by(0.0, top, viewport);
+        } else if bottom > viewport.1 {
+            self.scroll_by(0.0, (bottom - viewport.1).min(top), viewport);
+        }
+    }
+
+    /// (x, y) にあるボタンを押した状態（:active）にする。ボタンがなければ false
+    pub fn press_button_at(&mut self, x: f32, y: f32) -> bool {
+        if let Some((i, frame_x, frame_y)) = self.frame_at(x, y) {
+            return self.frames[i].webview.press_button_at(frame_x, frame_y);
+        }
+        let Some(path) = self.button_path_at(x, y) else {
+            return false;
+        };
+
+        self.blur_input();
+        self.selection = None;
+        self.active_path = Some(path.clone());
+        self.focus_path = Some(path);
+        self.focus_visible = false;
+        self.restyle();
+        true
+    }
+
+    /// ボタンを押しているか
+    pub fn is_pressing_button(&self) -> bool {
+        self.active_path.is_some() || self.frames.iter().any(|f| f.webview.is_pressing_button())
+    }
+
+    /// 押していたボタンを (x, y) で離す
+    ///
+    /// 押したボタンの上で離したらボタンを実行し、フォームを送信するなら移動先の
+    /// URL を返す。
+    pub fn release_button_at(&mut self, x: f32, y: f32) -> Option<Url> {
+        // <iframe> の中のボタンなら、送信先は iframe の中で開く
+        if let Some(i) = self
+            .frames
+            .iter()
+            .position(|f| f.webview.is_pressing_button())
+        {
+            let (frame_x, frame_y, ..) = self.frame_rect(i).unwrap_or_default();
+            let url = self.frames[i]
+                .webview
+                .release_button_at(x - frame_x, y - frame_y)?;
+            self.navigate_frame(i, url.as_str());
+            return None;
+        }
+
+        let path = self.active_path.take()?;
+        let clicked = self.button_path_at(x, y).as_ref() == Some(&path);
+        self.restyle();
+
+        if !clicked || !self.dispatch_event(&mut Event::new(EventType::Click, path.clone())) {
+            return None;
+        }
+        let button = self.dom_node_at(&path)?;
+        if !form::submits_form(&button.borrow().value) {
+            // TODO: type="reset"
+            return None;
+        }
+        self.submission_url(&button, Some(&button))
+    }
+
+    /// フォーカスのある入力欄のフォームを送信するときの移動先（Enter キー）
+    pub fn submit_focused_input(&mut self) -> Option<Url> {
+        let path = self.focused_input.as_ref()?.path.clone();
+        let input = self.dom_node_at(&path)?;
+        self.submission_url(&input, None)
+    }
+
+    /// node が属するフォームの送信先
+    ///
+    /// 送信できるなら、フォームの自動入力できる値とログインの内容も取っておく。
+    fn submission_url(
+        &mut self,
+        node: &NodeRef<HtmlNodeType>,
+        submitter: Option<&NodeRef<HtmlNodeType>>,
+    ) -> Option<Url> {
+        if self.sandbox.forms {
+            diagnostics::report(
+                Level::Warning,
+                Source::Security,
+                "Blocked form submission from a sandboxed frame",
+            );
+            return None;
+        }
+        let form = form::form_owner(node)?;
+        let document_url = self.document_url()?;
+        let base_url = self.base_url().unwrap_or(document_url);
+        let origin = passwords::login_origin(document_url);
+
+        let url =
+            form::submission_url(&form, submitter, &self.field_values, document_url, base_url)?;
+        self.submitted_profile = Some(form::autofill_profile(&form, &self.field_values));
+        self.submitted_login =
+            origin.and_then(|origin| submitted_login(&form, &self.field_values, origin));
+        Some(url)
+    }
+
+    /// 送信したログインフォームの内容（`<iframe>` の中のものも含め、1 度だけ）
+    pub fn take_submitted_login(&mut self) -> Option<SubmittedLogin> {
+        self.submitted_login.take().or_else(|| {
+            self.frames
+                .iter_mut()
+                .find_map(|frame| frame.webview.take_submitted_login())
+        })
+    }
+
+    /// 今の文書が permission を使えるか（まだ決まっていなければ None）
+    pub fn permission(&self, permission: Permission) -> Option<bool> {
+        self.permissions
+            .iter()
+            .find(|(p, _)| *p == permission)
+            .map(|(_, granted)| *granted)
+    }
+
+    /// 今の文書が permission を使えるかを調べ、決まっていなければアプリに尋ねる
+    ///
+    /// 尋ねた結果は set_permission で届く。http と https 以外の文書には許さない。
+    pub fn request_permission(&mut self, permission: Permission) -> Option<bool> {
+        if let Some(granted) = self.permission(permission) {
+            return Some(granted);
+        }
+        let Some(origin) = self.document_url().and_then(permissions::permission_origin) else {
+            return Some(false);
+        };
+        let request = PermissionRequest { origin, permission };
+        if !self.permission_requests.contains(&request) {
+            self.permission_requests.push(request);
+        }
+        None
+    }
+
+    /// 求められてまだ決まっていない権限（`<iframe>` の中のものも含め、1 度だけ）
+    pub fn take_permission_requests(&mut self) -> Vec<PermissionRequest> {
+        let mut requests = std::mem::take(&mut self.permission_requests);
+        for frame in &mut self.frames {
+            requests.extend(frame.webview.take_permission_requests());
+        }
+        requests
+    }
+
+    /// origin の文書（`<iframe>` の中のものも含む）に permission を許すか拒む
+    pub fn set_permission(&mut self, origin: &str, permission: Permission, granted: bool) {
+        for frame in &mut self.frames {
+            frame.webview.set_permission(origin, permission, granted);
+        }
+        if self
+            .document_url()
+            .and_then(permissions::permission_origin)
+            .as_deref()
+            != Some(origin)
+        {
+            return;
+        }
+        self.permissions.retain(|(p, _)| *p != permission);
+        self.permissions.push((permission, granted));
+
+        match permission {
+            Permission::Geolocation => {
+                for id in std::mem::take(&mut self.positions_waiting) {
+                    self.send_position_request(id, granted);
+                }
+            }
+            Permission::Notifications => {
+                self.set_notification_permission(granted);
+            }
+            Permission::Autoplay => {}
+        }
+    }
+
+    /// 現在地を問い合わせ、その番号を返す。答えは take_positions で受け取る
+    ///
+    /// 位置情報の権限が決まっていなければ、決まるまで待ってからアプリに渡す。
+    pub fn get_current_position(&mut self) -> u64 {
+        let id = NEXT_POSITION_ID.fetch_add(1, Ordering::Relaxed);
+        match self.request_permission(Permission::Geolocation) {
+            Some(granted) => self.send_position_request(id, granted),
+            None => self.positions_waiting.push(id),
+        }
+        id
+    }
+
+    fn send_position_request(&mut self, id: u64, granted: bool) {
+        if granted {
+            self.position_requests.push(id);
+        } else {
+            self.positions
+                .push((id, Err(PositionError::PermissionDenied)));
+        }
+    }
+
+    /// 許されて、アプリが OS に問い合わせる現在地の番号（`<iframe>` の中のものも含め、1 度だけ）
+    pub fn take_position_requests(&mut self) -> Vec<u64> {
+        let mut ids = std::mem::take(&mut self.position_requests);
+        self.positions_pending.extend(&ids);
+        for frame in &mut self.frames {
+            ids.extend(frame.webview.take_position_requests());
+        }
+        ids
+    }
+
+    /// 番号 id の問い合わせの答えを届ける。この WebView（か `<iframe>`）のものでなければ false
+    pub fn set_position(&mut self, id: u64, result: Result<Position, PositionError>) -> bool {
+        if let Some(i) = self.positions_pending.iter().position(|p| *p == id) {
+            self.positions_pending.remove(i);
+            self.positions.push((id, result));
+            return true;
+        }
+        self.frames
+            .iter_mut()
+            .any(|frame| frame.webview.set_position(id, result.clone()))
+    }
+
+    /// 届いた現在地の答え（この文書のものだけ）
+    pub fn take_positions(&mut self) -> Vec<PositionResult> {
+        std::mem::take(&mut self.positions)
+    }
+
+    /// スクリプトが Notification で頼んだことを、通知の権限に照らして通知センターへの頼みにする
+    fn handle_notification_requests(&mut self) {
+        for request in self.script_runtime.take_notification_requests() {
+            match request {
+                NotificationRequest::RequestPermission => {
+                    // 決まっていなければ set_permission で答える
+                    if let Some(granted) = self.request_permission(Permission::Notifications) {
+                        self.set_notification_permission(granted);
+                    }
+                }
+                NotificationRequest::Show {
+                    id,
+                    title,
+                    body,
+                    tag,
+                } => {
+                    let origin = self
+                        .document_url()
+                        .and_then(permissions::permission_origin)
+                        .filter(|_| self.permission(Permission::Notifications) == Some(true));
+                    let Some(origin) = origin else {
+                        self.dispatch_notification_event(id, "error");
+                        continue;
+                    };
+                    let notification_id = NEXT_NOTIFICATION_ID.fetch_add(1, Ordering::Relaxed);
+                    self.notifications.push((notification_id, id));
+                    self.notification_commands.push(NotificationCommand::Show(
+                        notification_id,
+                        Notification {
+                            title,
+                            body,
+                            tag,
+                            origin,
+                        },
+                    ));
+                }
+                NotificationRequest::Close(id) => {
+                    if let Some((notification_id, _)) =
+                        self.notifications.iter().find(|(_, n)| *n == id)
+                    {
+                        self.notification_commands
+                            .push(NotificationCommand::Close(*notification_id));
+                    }
+                }
+            }
+        }
+    }
+
+    /// 出した通知を閉じる（文書を移るとき）
+    fn close_notifications(&mut self) {
+        for (id, _) in std::mem::take(&mut self.notifications) {
+            self.notification_commands
+                .push(NotificationCommand::Close(id));
+        }
+    }
+
+    fn set_notification_permission(&mut self, granted: bool) {
+        if self.script_runtime.is_shut_down() {
+            return;
+        }
+        self.capture(|wv| {
+            report_uncaught(wv.script_runtime.set_notification_permission(Some(granted)))
+        });
+        self.apply_script_mutations();
+    }
+
+    fn dispatch_notification_event(&mut self, id: u32, event: &str) {
+        if self.script_runtime.is_shut_down() {
+            return;
+        }
+        self.capture(|wv| {
+            report_uncaught(wv.script_runtime.dispatch_notification_event(id, event))
+        });
+        self.apply_script_mutations();
+    }
+
+    /// 通知センターに頼むこと（`<iframe>` の中のものも含め、1 度だけ）
+    pub fn take_notification_commands(&mut self) -> Vec<NotificationCommand> {
+        let mut commands = std::mem::take(&mut self.notification_commands);
+        for frame in &mut self.frames {
+            commands.extend(frame.webview.take_notification_commands());
+        }
+        commands
+    }
+
+    /// 通知 id に起きたことをスクリプトに知らせる。この WebView（か `<iframe>`）のものでなければ false
+    pub fn notification_event(&mut self, id: u64, event: &NotificationEvent) -> bool {
+        let Some(i) = self.notifications.iter().position(|(n, _)| *n == id) else {
+            return self
+                .frames
+                .iter_mut()
+                .any(|frame| frame.webview.notification_event(id, event));
+        };
+        let script_id = self.notifications[i].1;
+        let name = match event {
+            NotificationEvent::Shown => "show",
+            NotificationEvent::Closed => "close",
+            NotificationEvent::Error(message) => {
+                self.report(
+                    Level::Error,
+                    Source::Script,
+                    format!("Failed to show a notification: {message}"),
+                );
+                "error"
+            }
+        };
+        if *event != NotificationEvent::Shown {
+            self.notifications.remove(i);
+        }
+        self.dispatch_notification_event(script_id, name);
+        true
+    }
+
+    /// フォーカスのある入力欄がログインフォームの欄なら、文書のオリジンとユーザー名の欄の値
+    pub fn login_field(&self) -> Option<(String, String)> {
+        let focused = self.focused_input.as_ref()?;
+        let node = self.dom_node_at(&focused.path)?;
+        let fields = form::login_fields(&form::form_owner(&node)?)?;
+        let is_username = fields
+            .username
+            .as_ref()
+            .is_some_and(|u| Rc::ptr_eq(u, &node));
+        if !is_username && !Rc::ptr_eq(&fields.password, &node) {
+            return None;
+        }
+
+        let origin = passwords::login_origin(self.document_url()?)?;
+        let username = match fields.username {
+            Some(_) if is_username => focused.edit.value().to_string(),
+            Some(username) => self.field_values.value_of(&username),
+            None => String::new(),
+        };
+        Some((origin, username))
+    }
+
+    /// フォーカスのあるログインフォームに credential を入れる
+    ///
+    /// ページのスクリプトから読めないよう、値は `value` 属性に書かずに field_values に持つ。
+    /// フォーカスのある欄は入力と同じように編集して書き換える。
+    pub fn fill_login(&mut self, credential: &Credential) -> bool {
+        let Some(path) = self.focused_input.as_ref().map(|f| f.path.clone()) else {
+            return false;
+        };
+        let Some(node) = self.dom_node_at(&path) else {
+            return false;
+        };
+        let Some(fields) = form::form_owner(&node).and_then(|f| form::login_fields(&f)) else {
+            return false;
+        };
+
+        let focused_value = if Rc::ptr_eq(&fields.password, &node) {
+            if let Some(username) = fields.username.as_ref() {
+                self.field_values.set(username, credential.username.clone());
+            }
+            &credential.password
+        } else if fields
+            .username
+            .as_ref()
+            .is_some_and(|u| Rc::ptr_eq(u, &node))
+        {
+            self.field_values
+                .set(&fields.password, credential.password.clone());
+            &credential.username
+        } else {
+            return false;
+        };
+        // 編集した値も属性ではなく field_values に書くようにする
+        let value = self.field_values.value_of(&node);
+        self.field_values.set(&node, value);
+        self.restyle();
+        self.set_input_value(focused_value)
+    }
+
+    /// 送信したフォームの自動入力できる値（`<iframe>` の中のものも含め、1 度だけ）
+    pub fn take_submitted_profile(&mut self) -> Option<AutofillProfile> {
+        self.submitted_profile.take().or_else(|| {
+            self.frames
+                .iter_mut()
+                .find_map(|frame| frame.webview.take_submitted_profile())
+        })
+    }
+
+    /// フォーカスのある入力欄の自動入力の種類と今の値
+    pub fn autofill_field(&self) -> Option<(AutofillField, String)> {
+        let focused = self.focused_input.as_ref()?;
+        let node = self.dom_node_at(&focused.path)?;
+        let field = AutofillField::for_input(&node.borrow().value)?;
+        Some((field, focused.edit.value().to_string()))
+    }
+
+    /// フォーカスのある入力欄の border box (x, y, width, height)（ビューポート座標）
+    pub fn focused_input_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let focused = self.focused_input.as_ref()?;
+        let (layout, info) = self.layout_and_info.as_ref()?;
+
+        let ((x, y), layout, _) = node_with_origin(layout, info, &focused.path)?;
+        let rect = layout.layout_boxes.first()?.border_box;
+        Some((x + rect.x, y + rect.y, rect.width, rect.height))
+    }
+
+    /// フォーカスのある入力欄の値を value に置き換える（キャレットは末尾）
+    pub fn set_input_value(&mut self, value: &str) -> bool {
+        self.edit_input(|edit| {
+            edit.select_all();
+            edit.insert(value);
+        })
+    }
+
+    fn button_path_at(&self, x: f32, y: f32) -> Option<Vec<usize>> {
+        let (layout, info) = self.layout_and_info.as_ref()?;
+        let hits = input::hit_test(layout, info, x, y);
+
+        input::find_button(&hits).map(|i| input::node_path(&hits, i))
+    }
+
+    /// フォーカスのある入力欄の選択の終点を (x, y) に動かす
+    fn extend_input_selection(&mut self, x: f32, y: f32) {
+        let Some(path) = self.focused_input.as_ref().map(|f| f.path.clone()) else {
+            return;
+        };
+        let Some(display_offset) = self.input_offset_at(&path, x, y) else {
+            return;
+        };
+        let Some(focused) = self.focused_input.as_mut() else {
+            return;
+        };
+
+        let offset = focused.value_offset(display_offset);
+        if offset != focused.edit.caret() {
+            focused.edit.set_caret(offset, true);
+            self.update_input_caret();
+        }
+    }
+
+    /// (x, y) が path の入力欄に表示している文字列のどこを指しているか
+    fn input_offset_at(&self, path: &[usize], x: f32, y: f32) -> Option<usize> {
+        let (layout, info) = self.layout_and_info.as_ref()?;
+
+        selection::text_position_at(layout, info, x, y)
+            .filter(|pos| pos.path.len() == path.len() + 1 && pos.path.starts_with(path))
+            .map(|pos| pos.offset)
+    }
+
+    /// 入力欄からフォーカスを外す
+    pub fn blur_input(&mut self) {
+        let Some(focused) = self.focused_input.take() else {
+            return;
+        };
+        if let Some((_, info)) = self.layout_and_info.as_mut() {
+            set_input_caret(info, &focused.path, None);
+        }
+        if self.focus_path.as_ref() == Some(&focused.path) {
+            self.set_focus(None, false);
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn has_focused_input(&self) -> bool {
+        self.focused_input.is_some()
+    }
+
+    /// フォーカスのある入力欄で IME の変換中か
+    pub fn is_composing(&self) -> bool {
+        self.focused_input
+            .as_ref()
+            .is_some_and(|f| f.composition().is_some())
+    }
+
+    /// フォーカスのある入力欄の値を edit で編集する。入力欄がなければ false
+    ///
+    /// 値は DOM の `value` 属性に書き戻す（field_values に持っている欄ならそちらに書く）。
+    pub fn edit_input(&mut self, edit: impl FnOnce(&mut TextEdit)) -> bool {
+        let Some(focused) = self.focused_input.as_mut() else {
+            return false;
+        };
+        let before = focused.edit.clone();
+        edit(&mut focused.edit);
+
+        if focused.edit.value() == before.value() && focused.edit.preedit() == before.preedit() {
+            // キャレットが動いただけなら作り直さなくてよい
+            self.update_input_caret();
+            return true;
+        }
+
+        let value_changed = focused.edit.value() != before.value();
+        let path = focused.path.clone();
+        if value_changed {
+            let value = focused.edit.value().to_string();
+            if let Some(node) = self.dom_node_at(&path) {
+                if self.field_values.get(&node).is_some() {
+                    self.field_values.set(&node, value);
+                } else {
+                    node.borrow_mut().value.set_attr("value", value);
+                }
+            }
+        }
+        self.restyle();
+
+        if value_changed {
+            self.dispatch_event(&mut Event::new(EventType::Input, path));
+        }
+        true
+    }
+
+    /// :visited に当てる訪れたリンクの記録を設定する
+    pub fn set_visited_links(&mut self, visited_links: Option<SharedVisitedLinks>) {
+        for frame in &mut self.frames {
+            frame.webview.set_visited_links(visited_links.clone());
+        }
+        self.visited_links = visited_links;
+    }
+
+    /// この文書の href が訪れたことのあるページを指しているか
+    fn is_visited_href(&self, href: &str) -> bool {
+        let (Some(visited), Some(base_url)) = (
+            self.visited_links.as_ref(),
+            self.base_url().or(self.document_url()),
+        ) else {
+            return false;
+        };
+        resolve_url(base_url, href).is_ok_and(|url| visited.borrow().contains(&url))
+    }
+
+    /// 入力欄の綴りを調べる辞書を設定する（None ならスペルチェックしない）
+    pub fn set_spell_checker(&mut self, spell_checker: Option<Arc<SpellChecker>>) {
+        for frame in &mut self.frames {
+            frame.webview.set_spell_checker(spell_checker.clone());
+        }
+        self.spell_checker = spell_checker;
+        self.update_input_caret();
+    }
+
+    /// これから読む文書に CSS とスクリプトを差し込む拡張機能を設定する
+    pub fn set_extensions(&mut self, extensions: Option<Arc<ExtensionRegistry>>) {
+        self.extensions = extensions;
+    }
+
+    /// (x, y) にあるフォーカスのある入力欄の、綴りの誤っている単語と直す候補
+    pub fn misspelling_at(&self, x: f32, y: f32) -> Option<Misspelling> {
+        let focused = self.focused_input.as_ref()?;
+        let checker = self.spell_checker.as_deref()?;
+        let offset = focused.value_offset(self.input_offset_at(&focused.path, x, y)?);
+        let range = focused
+            .misspelled_words(Some(checker))
+            .into_iter()
+            .find(|range| range.start <= offset && offset <= range.end)?;
+        let word = focused.edit.value()[range.clone()].to_string();
+        Some(Misspelling {
+            suggestions: checker.suggest(&word),
+            range,
+            word,
+        })
+    }
+
+    /// フォーカスのある入力欄の値の range を replacement で置き換える
+    pub fn replace_input_range(&mut self, range: Range<usize>, replacement: &str) -> bool {
+        self.edit_input(|edit| {
+            edit.set_caret(range.start, false);
+            edit.set_caret(range.end, true);
+            edit.insert(replacement);
+        })
+    }
+
+    /// フォーカスのある入力欄で選択されている文字列
+    pub fn input_selected_text(&self) -> Option<String> {
+        let focused = self.focused_input.as_ref()?;
+        // パスワードはコピーさせない
+        if focused.password {
+            return None;
+        }
+        focused.edit.selected_text().map(str::to_string)
+    }
+
+    /// IME の変換候補を出す位置 (x, y, width, height)（ビューポート座標）
+    ///
+    /// フォーカスのある入力欄のキャレットの矩形。入力欄がないか、IME を使わない
+    /// パスワード欄なら None。
+    pub fn ime_cursor_area(&self) -> Option<(f32, f32, f32, f32)> {
+        let focused = self.focused_input.as_ref()?;
+        if focused.password {
+            return None;
+        }
+        let (layout, info) = self.layout_and_info.as_ref()?;
+
+        let ((x, y), layout, info) = node_with_origin(layout, info, &focused.path)?;
+        let (dx, dy) = content_origin(layout, info)?;
+        let content = layout.layout_boxes.first()?.content_box;
+
+        Some((
+            x + dx + input_caret_x(layout, info)?,
+            y + dy,
+            1.0,
+            content.height,
+        ))
+    }
+
+    /// 入力欄のキャレットを InfoNode に反映する
+    fn update_input_caret(&mut self) {
+        let (Some(focused), Some((layout, info))) =
+            (self.focused_input.as_ref(), self.layout_and_info.as_mut())
+        else {
+            return;
+        };
+        set_input_caret(
+            info,
+            &focused.path,
+            Some(focused.caret(self.spell_checker.as_deref())),
+        );
+        scroll_input_to_caret(layout, info, &focused.path);
+        self.needs_redraw = true;
+    }
+
+    fn dom_node_at(&self, path: &[usize]) -> Option<NodeRef<HtmlNodeType>> {
+        let root = self.docment_info.as_ref()?.dom.root.clone();
+        path.iter()
+            .try_fold(root, |node, &i| node.borrow().children().get(i).cloned())
+    }
+
+    /// ページ（ルートコンテナ）を (dx, dy) だけスクロールする
+    ///
+    /// 目標位置だけを動かし、実際の位置は [`Self::animate_scroll`] で近づける。
+    /// アニメーション中に続けて呼ばれた場合は目標位置に加算する。
+    pub fn scroll_by(&mut self, dx: f32, dy: f32, viewport: (f32, f32)) {
+        let Some((layout, info)) = self.layout_and_info.as_ref() else {
+            return;
+        };
+        let Some(current) = scroll_offset(info) else {
+            return;
+        };
+
+        let chain = vec![(
+            Vec::new(),
+            current,
+            scroll::root_scroll_range(layout, viewport),
+        )];
+        self.scroll_chain(chain, dx, dy);
+    }
+
+    /// (x, y) の下にある最も内側のスクロールコンテナを (dx, dy) だけスクロールする
+    ///
+    /// 端に達して使い切れなかった分は外側のコンテナ、最後はページに渡す。
+    pub fn scroll_at(&mut self, x: f32, y: f32, dx: f32, dy: f32, viewport: (f32, f32)) {
+        // <iframe> の上なら中の文書をスクロールする（端に達しても外には渡さない）
+        if let Some((i, frame_x, frame_y)) = self.frame_at(x, y)
+            && let Some((_, _, width, height)) = self.frame_rect(i)
+        {
+            self.frames[i]
+                .webview
+                .scroll_at(frame_x, frame_y, dx, dy, (width, height));
+            return;
+        }
+
+        let Some((layout, info)) = self.layout_and_info.as_ref() else {
+            return;
+        };
+
+        let hits = input::hit_test(layout, info, x, y);
+        let last = hits.len().saturating_sub(1);
+        let mut chain = Vec::new();
+        for (i, item) in hits.iter().enumerate() {
+            let Some(current) = scroll_offset(item.info) else {
+                continue;
+            };
+            // ルートはビューポートでスクロールする
+            let range = if i == last {
+                scroll::root_scroll_range(item.layout, viewport)
+            } else {
+                scroll::container_scroll_range(item.layout, item.info)
+            };
+            if i == last || range != (0.0, 0.0) {
+                chain.push((input::node_path(&hits, i), current, range));
+            }
+        }
+
+        // 何にも当たらなければページをスクロールする
+        if chain.is_empty() {
+            self.scroll_by(dx, dy, viewport);
+        } else {
+            self.scroll_chain(chain, dx, dy);
+        }
+    }
+
+    /// 内側から順に (パス, 現在位置, 最大位置) のコンテナへ移動量を配る
+    fn scroll_chain(&mut self, chain: Vec<(Vec<usize>, (f32, f32), (f32, f32))>, dx: f32, dy: f32) {
+        let (mut rest_x, mut rest_y) = (dx, dy);
+
+        for (path, current, (max_x, max_y)) in chain {
+            let (base_x, base_y) = self.scroller.target(&path).unwrap_or(current);
+            let target_x = (base_x + rest_x).clamp(0.0, max_x);
+            let target_y = (base_y + rest_y).clamp(0.0, max_y);
+
+            rest_x -= target_x - base_x;
+            rest_y -= target_y - base_y;
+
+            if (target_x, target_y) != (base_x, base_y) {
+                self.scroller.scroll_to(&path, (target_x, target_y));
+                self.needs_redraw = true;
+                self.dispatch_event(&mut Event::new(EventType::Scroll, path.clone()));
+            }
+
+            if rest_x == 0.0 && rest_y == 0.0 {
+                break;
+            }
+        }
+    }
+
+    /// ページのスクロール位置とコンテンツの大きさ ((x, y), (width, height))
+    pub fn page_scroll(&self) -> Option<((f32, f32), (f32, f32))> {
+        let (_, info) = self.layout_and_info.as_ref()?;
+        Some((scroll_offset(info)?, self.content_size))
+    }
+
+    /// ページをスクロールできる最大の位置 (max_x, max_y)
+    ///
+    /// 最後にレイアウトしたときのコンテンツとビューポートの大きさから決まる。
+    pub fn max_scroll(&self) -> Option<(f32, f32)> {
+        self.layout_and_info.as_ref()?;
+        let (width, height) = self.viewport?;
+        Some((
+            (self.content_size.0 - width).max(0.0),
+            (self.content_size.1 - height).max(0.0),
+        ))
+    }
+
+    /// スクロールのアニメーションを 1 フレーム進める。まだ動いていれば true
+    pub fn animate_scroll(&mut self, now: Instant) -> bool {
+        let mut animating = false;
+        for frame in &mut self.frames {
+            animating |= frame.webview.animate_scroll(now);
+        }
+        let Some((_, info)) = self.layout_and_info.as_mut() else {
+            return animating;
+        };
+
+        self.scroller.step(info, now) || animating
+    }
+
+    /// 読み込みが終わったらページを (x, y) までスクロールする
+    pub fn restore_scroll(&mut self, scroll: (f32, f32)) {
+        self.pending_scroll = Some(scroll);
+    }
+
+    /// 描くためのレイアウトを捨てる（裏のタブのメモリを空ける）。捨てたら true
+    ///
+    /// 読み込みを終えたページだけが対象で、DOM とスタイルは残す。スクロール位置は
+    /// [`restore_render_data`](Self::restore_render_data) で作り直したときに戻す。
+    pub fn discard_render_data(&mut self) -> bool {
+        if self.phase != PagePhase::CssApplied {
+            return false;
+        }
+        let Some(((x, y), _)) = self.page_scroll() else {
+            return false;
+        };
+        self.pending_scroll = Some((x, y));
+        self.layout_and_info = None;
+        self.selection = None;
+        self.scroller.cancel();
+        self.hover_path = None;
+        self.active_path = None;
+        for frame in &mut self.frames {
+            frame.webview.discard_render_data();
+        }
+        true
+    }
+
+    /// 捨てたレイアウトを作り直す。作り直したら true
+    pub fn restore_render_data(&mut self) -> bool {
+        let mut restored = false;
+        for frame in &mut self.frames {
+            restored |= frame.webview.restore_render_data();
+        }
+        if self.phase != PagePhase::CssApplied || self.layout_and_info.is_some() {
+            return restored;
+        }
+        self.restyle();
+        true
+    }
+
+    /// 入力欄の今の値
+    pub fn form_state(&self) -> form::FormState {
+        self.docment_info
+            .as_ref()
+            .map(|info| form::save_form_state(&info.dom.root))
+            .unwrap_or_default()
+    }
+
+    /// 文書を読んだら入力欄に state の値を入れ直す
+    pub fn restore_form_state(&mut self, state: form::FormState) {
+        self.pending_form_state = (!state.is_empty()).then_some(state);
+    }
+
+    /// ページの表示倍率（CSS px 1 つあたりのデバイス非依存ピクセル数）
+    ///
+    /// 利用者のズーム倍率に `<meta name="viewport">` の倍率を掛けたもの。
+    pub fn zoom(&self) -> f32 {
+        (self.zoom * self.page_scale).clamp(MIN_ZOOM, MAX_ZOOM)
+    }
+
+    /// 利用者が選んだズーム倍率（ページを移っても引き継ぐ）
+    pub fn user_zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// 利用者のズーム倍率を設定する
+    ///
+    /// レイアウトに渡すビューポートが `1 / zoom` 倍になるので、次の relayout で
+    /// テキストは新しい幅で折り返される。
+    pub fn set_zoom(&mut self, zoom: f32) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        if zoom != self.zoom {
+            self.zoom = zoom;
+            self.needs_redraw = true;
+        }
+    }
+
+    /// 既定のフォントを設定する。family が None なら既定のフォントを使う
+    ///
+    /// CSS で指定されていない要素の文字に使われる。変わったらスタイルを計算し直す。
+    pub fn set_default_font(&mut self, family: Option<&str>, size: f32) {
+        for frame in &mut self.frames {
+            frame.webview.set_default_font(family, size);
+        }
+        let families: Vec<String> = family.into_iter().map(str::to_string).collect();
+        let text = TextStyle {
+            font_size: size,
+            font_family: FontFamilyList::intern(&families),
+            ..self.default_text
+        };
+        if text != self.default_text {
+            self.default_text = text;
+            self.restyle();
+        }
+    }
+
+    /// 利用者の配色を設定する。変わったら @media を評価し直す
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        for frame in &mut self.frames {
+            frame.webview.set_color_scheme(color_scheme);
+        }
+        if color_scheme == self.media.color_scheme {
+            return;
+        }
+        self.media.color_scheme = color_scheme;
+        if self.phase != PagePhase::Init {
+            self.resolve_styles();
+            self.restyle();
+        }
+    }
+
+    /// 暗い配色で表示するか（利用者が暗い配色を選び、ページも対応している）
+    ///
+    /// 対応していないページは文字色だけを指定していることがあるので、
+    /// 既定の色は明るい配色のままにする。
+    pub fn uses_dark_colors(&self) -> bool {
+        self.media.color_scheme == ColorScheme::Dark && self.supports_dark
+    }
+
+    /// ページの背景（キャンバス）の色
+    pub fn canvas_color(&self) -> Color {
+        if self.uses_dark_colors() {
+            DARK_CANVAS
+        } else {
+            LIGHT_CANVAS
+        }
+    }
+
+    /// 1 段階拡大する
+    pub fn zoom_in(&mut self) {
+        if let Some(&next) = ZOOM_LEVELS.iter().find(|&&z| z > self.zoom + f32::EPSILON) {
+            self.set_zoom(next);
+        }
+    }
+
+    /// 1 段階縮小する
+    pub fn zoom_out(&mut self) {
+        if let Some(&prev) = ZOOM_LEVELS
+            .iter()
+            .rev()
+            .find(|&&z| z < self.zoom - f32::EPSILON)
+        {
+            self.set_zoom(prev);
+        }
+    }
+
+    /// Returns document info
+    pub fn document_info(&self) -> Option<&DocumentInfo> {
+        self.docment_info.as_ref()
+    }
+
+    pub fn document_url(&self) -> Option<&Url> {
+        self.docment_info.as_ref().map(|info| &info.document_url)
+    }
+
+    /// 同一オリジンポリシーで見たこの文書
+    ///
+    /// `allow-same-origin` のない `<iframe sandbox>` の文書は、URL によらず opaque な
+    /// オリジンになる。
+    pub fn initiator(&self) -> Option<Initiator> {
+        let url = self.document_url()?.clone();
+        Some(if self.sandbox.origin {
+            Initiator {
+                url,
+                origin: self.opaque_origin.clone(),
+            }
+        } else {
+            Initiator::new(url)
+        })
+    }
+
+    pub fn base_url(&self) -> Option<&Url> {
+        self.docment_info.as_ref().map(|info| &info.base_url)
+    }
+
+    /// HTML と外部 CSS の読み込みが `<iframe>` の中の文書も含めてすべて完了しているか
+    pub fn is_loaded(&self) -> bool {
+        self.phase == PagePhase::CssApplied && self.frames.iter().all(|f| f.webview.is_loaded())
+    }
+
+    pub fn needs_redraw(&self) -> bool {
+        self.needs_redraw || self.frames.iter().any(|f| f.webview.needs_redraw())
+    }
+
+    pub fn clear_redraw_flag(&mut self) {
+        self.needs_redraw = false;
+        for frame in &mut self.frames {
+            frame.webview.clear_redraw_flag();
+        }
+    }
+}
+
+/// HTML を読み、読み込むサブリソースを集める
+///
+/// meta 要素の Content Security Policy は csp に足し、csp で許されない CSS と
+/// スクリプトは除く。
+fn parse_html(
+    html: &str,
+    document_url: Url,
+    fallback_base_url: Option<Url>,
+    csp: &mut ContentSecurityPolicy,
+) -> ParsedDocument {
+    // --- DOM パース ---
+    let mut parser = HtmlParser::new(html);
+    let dom = parser.parse();
+
+    // --- <meta http-equiv="Content-Security-Policy"> ---
+    for node in dom.find_all(|n| n.tag_name() == Some("meta")) {
+        let node = node.borrow();
+        if node
+            .value
+            .get_attr("http-equiv")
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case("content-security-policy"))
+            && let Some(content) = node.value.get_attr("content")
+        {
+            csp.add(content);
+        }
+    }
+
+    // --- base_url ---
+    let fallback_base_url = fallback_base_url.unwrap_or_else(|| document_url.clone());
+    let (base_url, base_target) = document_base(&dom, &fallback_base_url);
+
+    // --- title 抽出 ---
+    let title = dom
+        .collect_text_by_tag("title")
+        .first()
+        .cloned()
+        .unwrap_or("".into());
+
+    // --- Style links ---
+    // <link rel="stylesheet" href="...">
+    let link_nodes = dom.find_all(|n| n.tag_name() == Some("link"));
+    let mut style_links = Vec::new();
+
+    for node in link_nodes {
+        let (rel, href, nonce) = {
+            let node_ref = node.borrow();
+            let html_node = &node_ref.value;
+
+            let rel = html_node.get_attr("rel").map(|s| s.to_string());
+            let href = html_node.get_attr("href").map(|s| s.to_string());
+            let nonce = html_node.get_attr("nonce").map(|s| s.to_string());
+            (rel, href, nonce)
+        };
+
+        if let (Some(rel), Some(href)) = (rel, href)
+            && rel == "stylesheet"
+        {
+            let css_url = match resolve_url(&base_url, &href) {
+                Ok(url) => url,
+                Err(_) => continue,
+            };
+            if !csp.allows_element(Directive::Style, &css_url, nonce.as_deref(), &document_url) {
+                diagnostics::report(
+                    Level::Error,
+                    Source::Security,
+                    format!("Refused to load the stylesheet {css_url} (Content Security Policy)"),
+                );
+                continue;
+            }
+            style_links.push(css_url);
+        }
+    }
+
+    // --- Inline styles ---
+    let inline_styles = dom
+        .find_all(|n| n.tag_name() == Some("style"))
+        .iter()
+        .filter(|node| {
+            let nonce = node.borrow().value.get_attr("nonce").map(str::to_string);
+            let allowed = csp.allows_inline(Directive::Style, nonce.as_deref());
+            if !allowed {
+                diagnostics::report(
+                    Level::Error,
+                    Source::Security,
+                    "Refused to apply an inline style (Content Security Policy)",
+                );
+            }
+            allowed
+        })
+        .map(DomTree::inner_text)
+        .collect();
+
+    // --- Scripts ---
+    // 実行できないビルドでは外部スクリプトを取りに行かない
+    let scripts = if script::ENABLED {
+        script::collect_scripts_where(&dom, &base_url, |node, source| {
+            let nonce = node.get_attr("nonce");
+            let allowed = match source {
+                ScriptSource::Inline(_) => csp.allows_inline(Directive::Script, nonce),
+                ScriptSource::External(url) => {
+                    csp.allows_element(Directive::Script, url, nonce, &document_url)
+                }
+            };
+            if !allowed {
+                diagnostics::report(
+                    Level::Error,
+                    Source::Security,
+                    "Refused to run a script (Content Security Policy)",
+                );
+            }
+            allowed
+        })
+    } else {
+        Vec::new()
+    };
 
     ParsedDocument {
         document_url,
         base_url,
+        base_target,
         dom,
         title,
         style_links,
         inline_styles,
+        scripts,
+    }
+}
+
+/// `<img>` の src と CSS の背景画像の URL（解決前）を文書順に集める
+fn collect_image_sources(info: &InfoNode, found: &mut Vec<String>) {
+    if let NodeKind::Container { style, role, .. } = &info.kind {
+        if let Some(url) = &style.background_image {
+            found.push(url.clone());
+        }
+        if let ContainerRole::Image { src, .. } = role {
+            found.push(src.clone());
+        }
+    }
+    for child in &info.children {
+        collect_image_sources(child, found);
+    }
+}
+
+/// `<iframe>` 要素とそのパスを文書順に集める
+fn collect_frames(
+    node: &NodeRef<HtmlNodeType>,
+    path: &mut Vec<usize>,
+    found: &mut Vec<(Vec<usize>, NodeRef<HtmlNodeType>)>,
+) {
+    if node.borrow().value.tag_name() == Some("iframe") {
+        found.push((path.clone(), node.clone()));
+        return;
+    }
+    for (i, child) in node.borrow().children().iter().enumerate() {
+        path.push(i);
+        collect_frames(child, path, found);
+        path.pop();
     }
 }
 
-fn resolve_all_css(css_sources: &[String]) -> layouter::css_resolver::ResolvedStyles {
+fn resolve_all_css(
+    css_sources: &[String],
+    media: &MediaContext,
+) -> layouter::css_resolver::ResolvedStyles {
     let mut resolved = layouter::css_resolver::ResolvedStyles::default();
 
     for css in css_sources {
         let sheet = match CssParser::new(css).parse() {
             Ok(sheet) => sheet,
             Err(err) => {
-                log::error!("Failed to parse CSS: {}", err);
+                diagnostics::report(
+                    Level::Error,
+                    Source::Css,
+                    format!("Failed to parse CSS: {}", err),
+                );
                 continue;
             }
         };
 
-        resolved.extend(layouter::css_resolver::CssResolver::resolve(&sheet));
+        resolved.extend(layouter::css_resolver::CssResolver::resolve_with_media(
+            &sheet, media,
+        ));
     }
 
     resolved
 }
 
+/// スクリプトで捕まえられなかった例外をコンソールに出す
+fn report_uncaught(result: anyhow::Result<()>) {
+    if let Err(e) = result {
+        diagnostics::report(
+            Level::Error,
+            Source::Script,
+            format!("Uncaught script error: {e}"),
+        );
+    }
+}
+
+/// `<meta name="color-scheme" content="light dark">` で暗い配色に対応しているか
+fn meta_supports_dark(dom: &DomTree) -> bool {
+    dom.find_all(|n| {
+        n.tag_name() == Some("meta")
+            && n.get_attr("name")
+                .is_some_and(|name| name.eq_ignore_ascii_case("color-scheme"))
+    })
+    .iter()
+    .any(|meta| {
+        meta.borrow()
+            .value
+            .get_attr("content")
+            .is_some_and(|content| content.split_whitespace().any(|v| v == "dark"))
+    })
+}
+
+/// `:root` か `html` の `color-scheme` に dark があるか
+fn css_supports_dark(styles: &layouter::css_resolver::ResolvedStyles) -> bool {
+    styles.iter().any(|decl| {
+        let is_root = decl.selector.parts.first().is_some_and(|part| {
+            part.selector.tag.as_deref() == Some("html")
+                || part.selector.pseudo_class.as_deref() == Some("root")
+        });
+        let has_dark = match &decl.value {
+            CssValue::Keyword(v) => v == "dark",
+            CssValue::List(values) => values
+                .iter()
+                .any(|v| matches!(v, CssValue::Keyword(k) if k == "dark")),
+            _ => false,
+        };
+        decl.name == "color-scheme" && is_root && has_dark
+    })
+}
+
+/// コンテナの現在のスクロール位置（Text なら None）
+fn scroll_offset(info: &InfoNode) -> Option<(f32, f32)> {
+    match info.kind {
+        NodeKind::Container {
+            scroll_offset_x,
+            scroll_offset_y,
+            ..
+        } => Some((scroll_offset_x, scroll_offset_y)),
+        NodeKind::Text { .. } => None,
+    }
+}
+
+/// path の入力欄にキャレットを設定する（None なら消す）
+fn set_input_caret(info: &mut InfoNode, path: &[usize], caret: Option<InputCaret>) {
+    if let Some(InfoNode {
+        kind:
+            NodeKind::Container {
+                role: ContainerRole::TextInput { caret: current },
+                ..
+            },
+        ..
+    }) = scroll::node_at_mut(info, path)
+    {
+        *current = caret;
+    }
+}
+
+/// path のノードと、その座標の原点（ビューポート座標）
+fn node_with_origin<'a>(
+    layout: &'a LayoutNode,
+    info: &'a InfoNode,
+    path: &[usize],
+) -> Option<((f32, f32), &'a LayoutNode, &'a InfoNode)> {
+    // 各祖先コンテナの content 座標の原点を足していく
+    let (mut x, mut y) = (0.0, 0.0);
+    let (mut layout, mut info) = (layout, info);
+    for &i in path {
+        let (dx, dy) = content_origin(layout, info)?;
+        x += dx;
+        y += dy;
+        layout = layout.children.get(i)?;
+        info = info.children.get(i)?;
+    }
+    Some(((x, y), layout, info))
+}
+
+/// margin の長さを px にする（auto や calc() は 0 とみなす）
+fn resolve_margin(len: &Length, parent_width: f32, viewport: (f32, f32)) -> f32 {
+    match len {
+        Length::Px(v) => *v,
+        Length::Percent(v) => parent_width * v / 100.0,
+        Length::Vw(v) => viewport.0 * v / 100.0,
+        Length::Vh(v) => viewport.1 * v / 100.0,
+        _ => 0.0,
+    }
+}
+
+/// 子の座標の原点（content box の左上からスクロール位置を引いたもの）
+fn content_origin(layout: &LayoutNode, info: &InfoNode) -> Option<(f32, f32)> {
+    let content = layout.layout_boxes.first()?.content_box;
+    let (scroll_x, scroll_y) = scroll_offset(info).unwrap_or((0.0, 0.0));
+
+    Some((content.x - scroll_x, content.y - scroll_y))
+}
+
+/// 入力欄のキャレットの x 座標（入力欄の content 座標、スクロール前）
+fn input_caret_x(layout: &LayoutNode, info: &InfoNode) -> Option<f32> {
+    let text_box = layout.children.first()?.layout_boxes.first()?.padding_box;
+    match (&info.kind, info.children.first().map(|c| &c.kind)) {
+        (
+            NodeKind::Container {
+                role: ContainerRole::TextInput { caret: Some(caret) },
+                ..
+            },
+            Some(NodeKind::Text {
+                measured: Some(measured),
+                ..
+            }),
+        ) => Some(
+            text_box.x
+                + measured
+                    .lines
+                    .first()
+                    .map(|l| l.x_at(caret.offset))
+                    .unwrap_or(0.0),
+        ),
+        _ => None,
+    }
+}
+
+/// path の入力欄を横にスクロールしてキャレットを見えるようにする
+fn scroll_input_to_caret(layout: &LayoutNode, info: &mut InfoNode, path: &[usize]) {
+    let Some(layout) = path
+        .iter()
+        .try_fold(layout, |node, &i| node.children.get(i))
+    else {
+        return;
+    };
+    let Some(info) = scroll::node_at_mut(info, path) else {
+        return;
+    };
+    let (Some(content), Some(caret_x)) = (
+        layout.layout_boxes.first().map(|b| b.content_box),
+        input_caret_x(layout, info),
+    ) else {
+        return;
+    };
+
+    if let NodeKind::Container {
+        scroll_offset_x, ..
+    } = &mut info.kind
+    {
+        // キャレットの幅の分だけ右に余裕を持たせる
+        if caret_x + 1.0 > *scroll_offset_x + content.width {
+            *scroll_offset_x = caret_x + 1.0 - content.width;
+        } else if caret_x < *scroll_offset_x {
+            *scroll_offset_x = caret_x;
+        }
+        *scroll_offset_x = scroll_offset_x.max(0.0);
+    }
+}
+
+/// 文書の base URL と、リンクの既定の target（`<base target>`）
+///
+/// どちらも、その属性を持つ最初の `<base>` のものを使う。href がない、読めない、
+/// data: や javascript: のときは fallback_base_url（ふつうは文書の URL）。
+pub fn document_base(dom: &DomTree, fallback_base_url: &Url) -> (Url, Option<String>) {
+    let bases = dom.find_all(|n| n.tag_name() == Some("base"));
+    let attr = |name: &str| {
+        bases
+            .iter()
+            .find_map(|node| node.borrow().value.get_attr(name).map(str::to_string))
+    };
+
+    let base_url = match attr("href").map(|href| resolve_url(fallback_base_url, href.trim())) {
+        Some(Ok(url)) if !matches!(url.scheme(), "data" | "javascript") => url,
+        Some(_) => {
+            diagnostics::report(Level::Warning, Source::Html, "Ignoring invalid <base href>");
+            fallback_base_url.clone()
+        }
+        None => fallback_base_url.clone(),
+    };
+    let base_target = attr("target")
+        .map(|target| target.trim().to_string())
+        .filter(|target| !target.is_empty());
+    (base_url, base_target)
+}
+
 pub fn resolve_url(base_url: &Url, path: &str) -> Result<Url, url::ParseError> {
     // absolute URL（scheme を持つ）
     if let Ok(url) = Url::parse(path) {
@@ -366,3 +3592,25 @@ pub fn resolve_url(base_url: &Url, path: &str) -> Result<Url, url::ParseError> {
     // relative URL
     base_url.join(path)
 }
+
+/// form を送信したときのログインの内容（パスワードの欄がないか空なら None）
+fn submitted_login(
+    form: &NodeRef<HtmlNodeType>,
+    values: &form::FieldValues,
+    origin: String,
+) -> Option<SubmittedLogin> {
+    let fields = form::login_fields(form)?;
+    let password = values.value_of(&fields.password);
+    if password.is_empty() {
+        return None;
+    }
+    let username = fields
+        .username
+        .as_ref()
+        .map(|username| values.value_of(username))
+        .unwrap_or_default();
+    Some(SubmittedLogin {
+        origin,
+        credential: Credential { username, password },
+    })
+}
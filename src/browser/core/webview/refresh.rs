@@ -0,0 +1,97 @@
+//! `<meta http-equiv="refresh">`
+//!
+//! content 属性の `秒数[; url=移動先]` を読み、その秒数の後にページを読み込み直すか
+//! 移動先へ移る。タイマーは BrowserApp のスケジューラに積む。
+
+use std::time::Duration;
+
+use crate::engine::html::parser::DomTree;
+
+/// 読み込み直し（移動先）の指定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetaRefresh {
+    pub delay: Duration,
+    /// 移動先の URL（解決前）。None なら同じページを読み込み直す
+    pub url: Option<String>,
+}
+
+/// 文書で最初の `<meta http-equiv="refresh">` の指定（読めないものは飛ばす）
+pub fn find(dom: &DomTree) -> Option<MetaRefresh> {
+    dom.find_all(|n| n.tag_name() == Some("meta"))
+        .iter()
+        .find_map(|node| {
+            let node = &node.borrow().value;
+            if !node
+                .get_attr("http-equiv")
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case("refresh"))
+            {
+                return None;
+            }
+            parse(node.get_attr("content")?)
+        })
+}
+
+/// content 属性の値を読む
+///
+/// `5`、`0; url=/next`、`3, URL='page.html'`、`1.5 next.html` のような形を受け付ける
+/// （秒数の小数部は切り捨てる）。
+pub fn parse(content: &str) -> Option<MetaRefresh> {
+    let content = content.trim_start_matches(is_space);
+
+    let digits = content.bytes().take_while(u8::is_ascii_digit).count();
+    let rest = &content[digits..];
+    let fraction = rest
+        .bytes()
+        .take_while(|b| b.is_ascii_digit() || *b == b'.')
+        .count();
+    if digits == 0 && fraction == 0 {
+        return None;
+    }
+    let seconds = content[..digits].parse::<u64>().unwrap_or(0);
+    let delay = Duration::from_secs(seconds);
+
+    // 秒数の後は空白か ; か , で区切る
+    let rest = &rest[fraction..];
+    if !rest.is_empty() && !rest.starts_with(|c: char| is_space(c) || c == ';' || c == ',') {
+        return None;
+    }
+    let rest = rest.trim_start_matches(is_space);
+    let rest = rest
+        .strip_prefix([';', ','])
+        .unwrap_or(rest)
+        .trim_start_matches(is_space);
+    if rest.is_empty() {
+        return Some(MetaRefresh { delay, url: None });
+    }
+
+    // url= は省略できる
+    let url = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            let after = rest[3..].trim_start_matches(is_space);
+            match after.strip_prefix('=') {
+                Some(after) => after.trim_start_matches(is_space),
+                None => rest,
+            }
+        }
+        _ => rest,
+    };
+
+    // 引用符で囲まれていれば閉じる引用符まで
+    let url = match url.chars().next() {
+        Some(quote @ ('"' | '\'')) => {
+            let inner = &url[1..];
+            inner.split(quote).next().unwrap_or(inner)
+        }
+        _ => url,
+    };
+    let url = url.trim_matches(is_space);
+
+    Some(MetaRefresh {
+        delay,
+        url: (!url.is_empty()).then(|| url.to_string()),
+    })
+}
+
+fn is_space(c: char) -> bool {
+    c.is_ascii_whitespace()
+}
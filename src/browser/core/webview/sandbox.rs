@@ -0,0 +1,64 @@
+//! `<iframe sandbox>`
+//!
+//! sandbox 属性のある iframe の中の文書には制限を掛け、属性の値の `allow-*` で
+//! 一部を外す。制限は入れ子の iframe にも引き継がれる。
+
+/// 文書に掛かる制限（true なら禁止）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SandboxFlags {
+    /// スクリプトを実行しない（`allow-scripts` で外す）
+    pub scripts: bool,
+    /// フォームを送信しない（`allow-forms` で外す）
+    pub forms: bool,
+    /// `target="_top"` のリンクでタブの文書を移動させない（`allow-top-navigation` で外す）
+    pub top_navigation: bool,
+    /// `target="_blank"` のリンクで新しいタブを開かない（`allow-popups` で外す）
+    pub popups: bool,
+    /// 親と別の opaque なオリジンとして扱い、localStorage などを見せず、要求は
+    /// 別のオリジンからのものとして確かめる（`allow-same-origin` で外す）
+    pub origin: bool,
+}
+
+impl SandboxFlags {
+    /// 制限のない文書
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// sandbox 属性の値から作る（値が空ならすべて禁止）
+    pub fn parse(value: &str) -> Self {
+        let mut flags = Self {
+            scripts: true,
+            forms: true,
+            top_navigation: true,
+            popups: true,
+            origin: true,
+        };
+        for token in value.split_ascii_whitespace() {
+            match token.to_ascii_lowercase().as_str() {
+                "allow-scripts" => flags.scripts = false,
+                "allow-forms" => flags.forms = false,
+                "allow-top-navigation" => flags.top_navigation = false,
+                "allow-popups" => flags.popups = false,
+                "allow-same-origin" => flags.origin = false,
+                _ => {}
+            }
+        }
+        flags
+    }
+
+    /// 親の文書の制限 self に、iframe の制限 other を足したもの
+    pub fn union(self, other: Self) -> Self {
+        Self {
+            scripts: self.scripts || other.scripts,
+            forms: self.forms || other.forms,
+            top_navigation: self.top_navigation || other.top_navigation,
+            popups: self.popups || other.popups,
+            origin: self.origin || other.origin,
+        }
+    }
+
+    pub fn is_sandboxed(&self) -> bool {
+        *self != Self::none()
+    }
+}
@@ -0,0 +1,112 @@
+//! `<meta name="viewport">`
+//!
+//! content 属性の `width`、`initial-scale`、`minimum-scale`、`maximum-scale` を読み、
+//! ページを最初に表示する倍率を決める。ページは「窓の幅 ÷ 倍率」の幅でレイアウトされるので、
+//! `width=device-width` なら窓の幅、`width=400` なら 400px でレイアウトして窓に合わせて拡大する。
+
+use crate::engine::html::parser::DomTree;
+
+/// 倍率の範囲
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 10.0;
+
+/// `width` の値
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewportWidth {
+    /// `device-width`（窓の幅）
+    DeviceWidth,
+    /// CSS px
+    Px(f32),
+}
+
+/// viewport の指定
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ViewportMeta {
+    pub width: Option<ViewportWidth>,
+    pub initial_scale: Option<f32>,
+    pub minimum_scale: Option<f32>,
+    pub maximum_scale: Option<f32>,
+}
+
+impl ViewportMeta {
+    /// 窓の幅が window_width（CSS px、ズームなし）のときの最初の倍率
+    ///
+    /// initial-scale があればそれを、なければ width の幅が窓にちょうど収まる倍率を使い、
+    /// minimum-scale から maximum-scale の範囲に収める。
+    pub fn initial_scale(&self, window_width: f32) -> f32 {
+        let scale = match (self.initial_scale, self.width) {
+            (Some(scale), _) => scale,
+            (None, Some(ViewportWidth::Px(width))) => window_width / width,
+            (None, _) => 1.0,
+        };
+        let min = self.minimum_scale.unwrap_or(MIN_SCALE);
+        let max = self.maximum_scale.unwrap_or(MAX_SCALE).max(min);
+        scale.clamp(min, max)
+    }
+}
+
+/// 文書の `<meta name="viewport">` の指定（いくつもあれば最後のもの）
+pub fn find(dom: &DomTree) -> Option<ViewportMeta> {
+    dom.find_all(|n| n.tag_name() == Some("meta"))
+        .iter()
+        .rev()
+        .find_map(|node| {
+            let node = &node.borrow().value;
+            if !node
+                .get_attr("name")
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case("viewport"))
+            {
+                return None;
+            }
+            Some(parse(node.get_attr("content")?))
+        })
+}
+
+/// content 属性の値を読む
+///
+/// `width=device-width, initial-scale=1` のように `,` か `;` で区切った `名前=値` の並び。
+/// 知らない名前と読めない値は飛ばす。
+pub fn parse(content: &str) -> ViewportMeta {
+    let mut meta = ViewportMeta::default();
+    for item in content.split([',', ';']) {
+        let Some((name, value)) = item.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "width" => meta.width = parse_width(value),
+            "initial-scale" => meta.initial_scale = parse_scale(value),
+            "minimum-scale" => meta.minimum_scale = parse_scale(value),
+            "maximum-scale" => meta.maximum_scale = parse_scale(value),
+            _ => {}
+        }
+    }
+    meta
+}
+
+fn parse_width(value: &str) -> Option<ViewportWidth> {
+    if value.eq_ignore_ascii_case("device-width") || value.eq_ignore_ascii_case("device-height") {
+        return Some(ViewportWidth::DeviceWidth);
+    }
+    let width = leading_number(value)?;
+    (width > 0.0).then(|| ViewportWidth::Px(width.clamp(1.0, 10000.0)))
+}
+
+fn parse_scale(value: &str) -> Option<f32> {
+    let scale = match value.to_ascii_lowercase().as_str() {
+        "yes" => 1.0,
+        "no" => MIN_SCALE,
+        "device-width" | "device-height" => MAX_SCALE,
+        value => leading_number(value)?,
+    };
+    (scale > 0.0).then(|| scale.clamp(MIN_SCALE, MAX_SCALE))
+}
+
+/// `1.5px` のような値の先頭の数
+fn leading_number(value: &str) -> Option<f32> {
+    let len = value
+        .bytes()
+        .take_while(|b| b.is_ascii_digit() || *b == b'.')
+        .count();
+    value[..len].parse().ok()
+}
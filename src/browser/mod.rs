@@ -1,7 +1,10 @@
 //! TODO: コアモジュール以外にwebviewなどの外部アプリ向けのモジュールを公開
 
 pub mod core;
+pub mod settings;
 
 pub use core::BrowserApp;
 pub use core::BrowserCommand;
+pub use core::BrowserEvent;
 pub use core::Tab;
+pub use settings::Settings;
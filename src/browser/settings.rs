@@ -0,0 +1,497 @@
+//! ブラウザの設定
+//!
+//! 設定ディレクトリ（[`crate::platform::io::config_dir`]）の `settings.toml` に保存する。
+//! 扱うのは TOML のうち設定に要る部分（`key = value`、`[table]`、文字列・数値・
+//! 真偽値、コメント）だけ。読めない行や範囲外の値は警告を出して既定値のままにする。
+//!
+//! ```toml
+//! homepage = "https://example.com/"
+//! search_engine = "https://duckduckgo.com/?q={query}"
+//! default_zoom = 1.25
+//!
+//! [font]
+//! family = "Noto Serif"
+//! size = 18.0
+//!
+//! [spell_check]
+//! enabled = true
+//! language = "en_US"
+//!
+//! [window]
+//! custom_titlebar = true
+//!
+//! [memory]
+//! budget_mb = 1024
+//!
+//! [log]
+//! levels = "warn,platform::network=debug"
+//! file = "/tmp/orinium.log"
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use url::Url;
+
+use crate::browser::core::reader::{ReaderOptions, ReaderTheme};
+use crate::browser::core::tab::PageDefaults;
+use crate::browser::core::ui::SearchEngine;
+use crate::browser::core::webview::{DEFAULT_FONT_SIZE, MAX_ZOOM, MIN_ZOOM};
+use crate::engine::css::media::ColorScheme;
+use crate::platform::logging::{self, LevelFilters};
+use crate::platform::{io, memory};
+
+/// 設定ディレクトリ内の設定ファイル名
+pub const SETTINGS_FILE_NAME: &str = "settings.toml";
+
+/// 文字の大きさとして受け付ける範囲（CSS px）
+const FONT_SIZE_RANGE: (f32, f32) = (6.0, 72.0);
+/// スクロール量の倍率として受け付ける範囲
+const SCROLL_SPEED_RANGE: (f32, f32) = (0.1, 10.0);
+/// キャッシュなどに使うメモリーの予算として受け付ける範囲（MiB）
+const MEMORY_BUDGET_RANGE: (f32, f32) = (64.0, 65536.0);
+
+/// Cookie を受け入れる範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CookiePolicy {
+    AllowAll,
+    /// 表示しているページと別のサイトの Cookie は受け入れない
+    #[default]
+    BlockThirdParty,
+    BlockAll,
+}
+
+impl CookiePolicy {
+    pub const ALL: [Self; 3] = [Self::AllowAll, Self::BlockThirdParty, Self::BlockAll];
+
+    /// 設定ファイルでの名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::AllowAll => "allow-all",
+            Self::BlockThirdParty => "block-third-party",
+            Self::BlockAll => "block-all",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+}
+
+/// 明るい配色と暗い配色のどちらを使うか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSchemePreference {
+    /// OS の設定に合わせる
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl ColorSchemePreference {
+    pub const ALL: [Self; 3] = [Self::System, Self::Light, Self::Dark];
+
+    /// 設定ファイルでの名前
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::System => "system",
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.name() == name)
+    }
+
+    /// 使う配色（System なら OS の配色 system）
+    pub fn resolve(self, system: ColorScheme) -> ColorScheme {
+        match self {
+            Self::System => system,
+            Self::Light => ColorScheme::Light,
+            Self::Dark => ColorScheme::Dark,
+        }
+    }
+}
+
+/// ユーザーが変更できる設定
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// 起動時（前回のタブを開き直さないとき）と Alt+Home で開くページ
+    pub homepage: Url,
+    /// URL バーの入力が URL でないときに使う検索エンジン
+    pub search_engine: SearchEngine,
+    /// CSS でフォントが指定されていない文字のフォント（None ならシステムの既定）
+    pub font_family: Option<String>,
+    /// CSS で大きさが指定されていない文字の大きさ（CSS px）
+    pub font_size: f32,
+    /// 新しいタブのズーム倍率
+    pub default_zoom: f32,
+    /// ホイールと矢印キーでのスクロール量の倍率
+    pub scroll_speed: f32,
+    /// ブラウザ UI と prefers-color-scheme の配色
+    pub color_scheme: ColorSchemePreference,
+    pub cookie_policy: CookiePolicy,
+    /// `<meta http-equiv="refresh">` でページを自動で読み込み直す（移動する）
+    pub meta_refresh: bool,
+    /// 送信したフォームの名前や住所を覚えて、入力欄に候補を出す
+    pub autofill: bool,
+    /// ログインフォームを送信したとき、パスワードを保存するか尋ねる
+    pub passwords: bool,
+    /// フィルターリストに当てはまる広告とトラッカーのリクエストを止める
+    pub content_blocking: bool,
+    /// 起動時に前回開いていたタブを開き直す（前回の続きから）
+    pub restore_session: bool,
+    /// リーダーモードの文字の大きさと配色
+    pub reader: ReaderOptions,
+    /// 入力欄の綴りを調べる
+    pub spell_check: bool,
+    /// スペルチェックの辞書の言語（`en_US` のような Hunspell の辞書の名前）
+    pub spell_check_language: String,
+    /// OS のタイトルバーを消し、タブバーにウィンドウボタンを並べてタイトルバーにする
+    pub custom_titlebar: bool,
+    /// FPS とフレーム時間を画面の右上に出す（Ctrl+Shift+F でも切り替えられる）
+    pub show_frame_stats: bool,
+    /// 画像・グリフ・HTTP キャッシュ・DOM に使うメモリーの予算（MiB）。超えたらキャッシュを捨てる
+    pub memory_budget_mb: u32,
+    /// サブシステムごとのログのレベル（`warn,platform::network=debug` の形）
+    pub log_levels: String,
+    /// ログを書き足すファイル（None なら書かない）
+    pub log_file: Option<PathBuf>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            homepage: Url::parse("resource:///test/compatibility_test.html")
+                .expect("default homepage is a valid URL"),
+            search_engine: SearchEngine::default(),
+            font_family: None,
+            font_size: DEFAULT_FONT_SIZE,
+            default_zoom: 1.0,
+            scroll_speed: 1.0,
+            color_scheme: ColorSchemePreference::default(),
+            cookie_policy: CookiePolicy::default(),
+            meta_refresh: true,
+            autofill: true,
+            passwords: true,
+            content_blocking: true,
+            restore_session: true,
+            reader: ReaderOptions::default(),
+            spell_check: true,
+            spell_check_language: "en_US".to_string(),
+            custom_titlebar: false,
+            show_frame_stats: false,
+            memory_budget_mb: (memory::DEFAULT_MEMORY_BUDGET / (1024 * 1024)) as u32,
+            log_levels: logging::DEFAULT_LOG_LEVELS.to_string(),
+            log_file: None,
+        }
+    }
+}
+
+impl Settings {
+    /// タブで開くページの既定値（system は OS の配色）
+    pub fn page_defaults(&self, system: ColorScheme) -> PageDefaults {
+        PageDefaults {
+            font_family: self.font_family.clone(),
+            font_size: self.font_size,
+            zoom: self.default_zoom,
+            color_scheme: self.color_scheme.resolve(system),
+        }
+    }
+
+    /// メモリーの予算（バイト）
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget_mb as usize * 1024 * 1024
+    }
+
+    /// key（`font.size` のように表の名前を付けたもの）の設定を value にする
+    ///
+    /// 値は文字列で受け取り、設定ごとの型として読めなければエラーを返す。
+    /// 設定ファイルの読み込みと `orinium://settings` での変更の両方で使う。
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        match key {
+            "homepage" => {
+                self.homepage = Url::parse(value.trim())
+                    .map_err(|e| anyhow!("Invalid homepage {:?}: {}", value, e))?;
+            }
+            "search_engine" => {
+                let engine = SearchEngine::new(value.trim());
+                if !engine.template.contains("{query}") || engine.search_url("test").is_none() {
+                    bail!(
+                        "Search engine must be a URL containing {{query}}: {:?}",
+                        value
+                    );
+                }
+                self.search_engine = engine;
+            }
+            "restore_session" => self.restore_session = parse_bool(key, value)?,
+            "meta_refresh" => self.meta_refresh = parse_bool(key, value)?,
+            "autofill" => self.autofill = parse_bool(key, value)?,
+            "passwords" => self.passwords = parse_bool(key, value)?,
+            "content_blocking" => self.content_blocking = parse_bool(key, value)?,
+            "default_zoom" => self.default_zoom = parse_number(key, value, (MIN_ZOOM, MAX_ZOOM))?,
+            "scroll_speed" => self.scroll_speed = parse_number(key, value, SCROLL_SPEED_RANGE)?,
+            "color_scheme" => {
+                self.color_scheme = ColorSchemePreference::from_name(value.trim())
+                    .ok_or_else(|| anyhow!("Unknown color scheme: {:?}", value))?;
+            }
+            "cookie_policy" => {
+                self.cookie_policy = CookiePolicy::from_name(value.trim())
+                    .ok_or_else(|| anyhow!("Unknown cookie policy: {:?}", value))?;
+            }
+            "font.family" => {
+                let family = value.trim();
+                self.font_family = (!family.is_empty()).then(|| family.to_string());
+            }
+            "font.size" => self.font_size = parse_number(key, value, FONT_SIZE_RANGE)?,
+            "reader.font_size" => {
+                self.reader.font_size = parse_number(key, value, FONT_SIZE_RANGE)?;
+            }
+            "reader.theme" => {
+                self.reader.theme = ReaderTheme::from_name(value.trim())
+                    .ok_or_else(|| anyhow!("Unknown reader theme: {:?}", value))?;
+            }
+            "spell_check.enabled" => self.spell_check = parse_bool(key, value)?,
+            "spell_check.language" => {
+                let language = value.trim();
+                if language.is_empty()
+                    || !language
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                {
+                    bail!("Invalid spell check language: {:?}", value);
+                }
+                self.spell_check_language = language.to_string();
+            }
+            "window.custom_titlebar" => self.custom_titlebar = parse_bool(key, value)?,
+            "debug.frame_stats" => self.show_frame_stats = parse_bool(key, value)?,
+            "memory.budget_mb" => {
+                self.memory_budget_mb = parse_number(key, value, MEMORY_BUDGET_RANGE)? as u32;
+            }
+            "log.levels" => {
+                self.log_levels = LevelFilters::parse(value)?.to_string();
+            }
+            "log.file" => {
+                let path = value.trim();
+                self.log_file = (!path.is_empty()).then(|| PathBuf::from(path));
+            }
+            _ => bail!("Unknown setting: {}", key),
+        }
+        Ok(())
+    }
+
+    pub fn serialize(&self) -> String {
+        format!(
+            "# Orinium browser settings\n\
+             \n\
+             homepage = {}\n\
+             search_engine = {}\n\
+             restore_session = {}\n\
+             default_zoom = {:?}\n\
+             scroll_speed = {:?}\n\
+             color_scheme = {}\n\
+             cookie_policy = {}\n\
+             meta_refresh = {}\n\
+             autofill = {}\n\
+             passwords = {}\n\
+             content_blocking = {}\n\
+             \n\
+             [font]\n\
+             family = {}\n\
+             size = {:?}\n\
+             \n\
+             [reader]\n\
+             font_size = {:?}\n\
+             theme = {}\n\
+             \n\
+             [spell_check]\n\
+             enabled = {}\n\
+             language = {}\n\
+             \n\
+             [window]\n\
+             custom_titlebar = {}\n\
+             \n\
+             [debug]\n\
+             frame_stats = {}\n\
+             \n\
+             [memory]\n\
+             budget_mb = {}\n\
+             \n\
+             [log]\n\
+             levels = {}\n\
+             file = {}\n",
+            quote(self.homepage.as_str()),
+            quote(&self.search_engine.template),
+            self.restore_session,
+            self.default_zoom,
+            self.scroll_speed,
+            quote(self.color_scheme.name()),
+            quote(self.cookie_policy.name()),
+            self.meta_refresh,
+            self.autofill,
+            self.passwords,
+            self.content_blocking,
+            quote(self.font_family.as_deref().unwrap_or("")),
+            self.font_size,
+            self.reader.font_size,
+            quote(self.reader.theme.name()),
+            self.spell_check,
+            quote(&self.spell_check_language),
+            self.custom_titlebar,
+            self.show_frame_stats,
+            self.memory_budget_mb,
+            quote(&self.log_levels),
+            quote(
+                &self
+                    .log_file
+                    .as_deref()
+                    .map(|path| path.to_string_lossy())
+                    .unwrap_or_default()
+            ),
+        )
+    }
+
+    /// 読めない行と値は飛ばす
+    pub fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        let mut table = String::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(rest) = line.strip_prefix('[') {
+                match rest.split_once(']') {
+                    Some((name, after)) if is_blank_or_comment(after) => {
+                        table = name.trim().to_string();
+                    }
+                    _ => log::warn!("settings.toml:{}: invalid table header", i + 1),
+                }
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                log::warn!("settings.toml:{}: expected `key = value`", i + 1);
+                continue;
+            };
+            let key = key.trim().trim_matches('"');
+            let key = if table.is_empty() {
+                key.to_string()
+            } else {
+                format!("{table}.{key}")
+            };
+            let Some(value) = parse_value(value) else {
+                log::warn!("settings.toml:{}: invalid value for {}", i + 1, key);
+                continue;
+            };
+
+            if let Err(e) = settings.set(&key, &value) {
+                log::warn!("settings.toml:{}: {:#}", i + 1, e);
+            }
+        }
+
+        settings
+    }
+
+    /// path から読み込む。ファイルがなければ既定の設定
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::parse(&text))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        io::write_atomic(path, self.serialize().as_bytes())
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value.trim() {
+        "true" | "on" => Ok(true),
+        "false" | "off" => Ok(false),
+        _ => bail!("{} must be true or false: {:?}", key, value),
+    }
+}
+
+/// 数値として読み、(min, max) の範囲に入っていなければエラー
+fn parse_number(key: &str, value: &str, (min, max): (f32, f32)) -> Result<f32> {
+    let number: f32 = value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("{} must be a number: {:?}", key, value))?;
+    if !(min..=max).contains(&number) {
+        bail!("{} must be between {} and {}: {}", key, min, max, number);
+    }
+    Ok(number)
+}
+
+/// `=` の右側を読む。文字列は中身、それ以外（数値と真偽値）はそのままの文字列にする
+pub(crate) fn parse_value(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+
+    if let Some(rest) = raw.strip_prefix('\'') {
+        // リテラル文字列（エスケープなし）
+        let (value, after) = rest.split_once('\'')?;
+        return is_blank_or_comment(after).then(|| value.to_string());
+    }
+
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return is_blank_or_comment(&rest[i + 1..]).then_some(value),
+                '\\' => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    '"' => value.push('"'),
+                    '\\' => value.push('\\'),
+                    'u' => {
+                        let hex: String = (0..4)
+                            .filter_map(|_| chars.next())
+                            .map(|(_, c)| c)
+                            .collect();
+                        value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    _ => return None,
+                },
+                c => value.push(c),
+            }
+        }
+        // 閉じる引用符がない
+        return None;
+    }
+
+    let value = raw.split('#').next().unwrap_or("").trim();
+    (!value.is_empty()).then(|| value.replace('_', ""))
+}
+
+pub(crate) fn is_blank_or_comment(s: &str) -> bool {
+    let s = s.trim();
+    s.is_empty() || s.starts_with('#')
+}
+
+/// TOML の基本文字列にする
+fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,234 @@
+//! [`OriniumView`] はタブバーや URL バーを持たない、ページ 1 枚のビュー。
+//! winit のウィンドウか、呼び出し側で作った wgpu のデバイスとサーフェスに描く。
+//! 入力は winit の `WindowEvent` をそのまま渡し、描き直しやタイトルの変化は
+//! [`OriniumView::update`] が返す [`ViewEvent`] で知る。
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use orinium_browser::embed::{OriniumView, ViewEvent};
+//! use winit::event::WindowEvent;
+//! use winit::window::Window;
+//!
+//! fn embed(window: Arc<Window>, events: Vec<WindowEvent>) -> anyhow::Result<()> {
+//!     let mut view = OriniumView::with_window(window.clone())?;
+//!     view.navigate("https://example.com/".parse()?);
+//!
+//!     // ウィンドウのイベントを渡し（RedrawRequested で描く）……
+//!     for event in events {
+//!         view.handle_window_event(event);
+//!     }
+//!     // ……イベントループが待つ前に読み込みとタイマーを進める
+//!     for event in view.update() {
+//!         match event {
+//!             ViewEvent::NeedsRedraw => window.request_redraw(),
+//!             ViewEvent::TitleChanged(title) => window.set_title(&title),
+//!             _ => {}
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+
+use anyhow::Result;
+use url::Url;
+use winit::dpi::PhysicalSize;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+use crate::browser::core::CommandSender;
+use crate::browser::{BrowserApp, BrowserCommand, BrowserEvent};
+use crate::platform::renderer::compositor::Compositor;
+use crate::platform::renderer::gpu::GpuRenderer;
+use crate::platform::system::app::open_renderer;
+
+/// ビューから組み込む側に知らせること
+#[derive(Debug, Clone, PartialEq)]
+pub enum ViewEvent {
+    /// 描き直す（ウィンドウの `request_redraw` などで RedrawRequested を送る）
+    NeedsRedraw,
+    /// ページのタイトルが変わった
+    TitleChanged(String),
+    /// 読み込みの進み具合（0.0 から 1.0。読み込み中でなければ None）
+    LoadProgress(Option<f32>),
+    /// ページが閉じた（最後のタブを閉じるショートカットなど）
+    Closed,
+}
+
+/// ブラウザの UI を持たないページのビュー
+///
+/// 中身は UI を隠した [`BrowserApp`] なので、ネットワーク、スクリプト、キーボードの
+/// ショートカットなどはブラウザと同じように動く。
+pub struct OriniumView {
+    browser: BrowserApp,
+    compositor: Box<dyn Compositor>,
+    commands: CommandSender,
+    events: Receiver<BrowserEvent>,
+}
+
+impl OriniumView {
+    /// window に描くビューを作る。使える GPU がなければ CPU で描く
+    ///
+    /// CPU で描くためのサーフェスも作れなければエラー。
+    pub fn with_window(window: Arc<Window>) -> Result<Self> {
+        let size = window.inner_size();
+        let scale_factor = window.scale_factor();
+        Ok(Self::with_compositor(
+            (size.width, size.height),
+            scale_factor,
+            open_renderer(window)?,
+        ))
+    }
+
+    /// 呼び出し側で作った wgpu のデバイスとサーフェスに描くビューを作る
+    ///
+    /// 大きさは config の幅と高さ（物理ピクセル）にする。
+    pub fn with_surface(
+        instance: wgpu::Instance,
+        surface: wgpu::Surface<'static>,
+        (device, queue): (wgpu::Device, wgpu::Queue),
+        config: wgpu::SurfaceConfiguration,
+        scale_factor: f64,
+    ) -> Result<Self> {
+        let size = (config.width, config.height);
+        let gpu =
+            GpuRenderer::with_surface(instance, surface, (device, queue), config, scale_factor)?;
+        Ok(Self::with_compositor(size, scale_factor, Box::new(gpu)))
+    }
+
+    /// compositor に描くビューを作る
+    ///
+    /// 自分で描くときは [`Compositor`] を実装して渡すと、フレームごとに描画命令が届く。
+    pub fn with_compositor(
+        size: (u32, u32),
+        scale_factor: f64,
+        mut compositor: Box<dyn Compositor>,
+    ) -> Self {
+        let mut browser = BrowserApp::new(size, String::new());
+        browser.set_chrome_visible(false);
+        browser.set_scale_factor(scale_factor);
+        let (sink, events) = mpsc::channel();
+        browser.set_event_sink(Box::new(sink));
+        compositor.resize(PhysicalSize::new(size.0, size.1));
+        compositor.set_scale_factor(scale_factor);
+        Self {
+            commands: browser.command_sender(),
+            browser,
+            compositor,
+            events,
+        }
+    }
+
+    /// url を開く
+    pub fn navigate(&mut self, url: Url) {
+        self.commands.send(BrowserCommand::Navigate(url));
+    }
+
+    pub fn go_back(&mut self) {
+        let cmd = self.browser.go_back();
+        self.commands.send(cmd);
+    }
+
+    pub fn go_forward(&mut self) {
+        let cmd = self.browser.go_forward();
+        self.commands.send(cmd);
+    }
+
+    pub fn reload(&mut self) {
+        self.commands.send(BrowserCommand::Reload {
+            bypass_cache: false,
+        });
+    }
+
+    /// 表示している文書の URL
+    pub fn url(&self) -> Option<Url> {
+        self.browser.active_tab()?.document_url()
+    }
+
+    /// ページのタイトル（なければ URL）
+    pub fn title(&self) -> String {
+        self.browser
+            .active_tab()
+            .map(|tab| tab.display_title())
+            .unwrap_or_default()
+    }
+
+    pub fn is_loading(&self) -> bool {
+        self.browser
+            .active_tab()
+            .is_some_and(|tab| tab.is_loading())
+    }
+
+    /// ウィンドウのイベントを渡す
+    ///
+    /// RedrawRequested で描いて出す。CloseRequested はビューではなく
+    /// 組み込む側が扱うので無視する。
+    pub fn handle_window_event(&mut self, event: WindowEvent) {
+        if matches!(event, WindowEvent::CloseRequested | WindowEvent::Destroyed) {
+            return;
+        }
+        let cmd = self
+            .browser
+            .handle_window_event(event, self.compositor.as_mut());
+        self.commands.send(cmd);
+    }
+
+    /// 大きさ（物理ピクセル）が変わった
+    pub fn resize(&mut self, size: PhysicalSize<u32>) {
+        self.handle_window_event(WindowEvent::Resized(size));
+    }
+
+    /// 倍率が変わった（winit のウィンドウなら ScaleFactorChanged を渡せばよい）
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.browser.set_scale_factor(scale_factor);
+    }
+
+    /// 今のページを描いて出す（RedrawRequested と同じ）
+    pub fn render(&mut self) {
+        self.handle_window_event(WindowEvent::RedrawRequested);
+    }
+
+    /// 最後に描いたフレームを画像にする
+    pub fn capture_frame(&mut self) -> Result<image::RgbaImage> {
+        self.compositor.capture_frame()
+    }
+
+    /// 読み込み、タイマー、溜まったコマンドを進め、その間に起きたことを返す
+    ///
+    /// イベントループが待つ前（winit なら `about_to_wait`）に毎回呼ぶ。
+    pub fn update(&mut self) -> Vec<ViewEvent> {
+        let tick = self.browser.tick();
+        self.commands.send(tick);
+        let tasks = self.browser.run_scheduled_tasks();
+        self.commands.send(tasks);
+        self.browser.process_commands();
+
+        self.events
+            .try_iter()
+            .filter_map(|event| match event {
+                BrowserEvent::NeedsRedraw => Some(ViewEvent::NeedsRedraw),
+                // ウィンドウのタイトルにはアプリの名前が付くので、ページのものを渡す
+                BrowserEvent::TitleChanged(_) => Some(ViewEvent::TitleChanged(self.title())),
+                BrowserEvent::LoadProgress(progress) => Some(ViewEvent::LoadProgress(progress)),
+                BrowserEvent::Exit => Some(ViewEvent::Closed),
+                // タイトルバーやエンジンのスレッドはビューにはない
+                BrowserEvent::Frame(_)
+                | BrowserEvent::WindowState(_)
+                | BrowserEvent::Decorations(_)
+                | BrowserEvent::WindowAction(_) => None,
+            })
+            .collect()
+    }
+
+    /// 中のブラウザ（設定やプロファイルの置き場所を変えるときなど）
+    pub fn browser(&self) -> &BrowserApp {
+        &self.browser
+    }
+
+    pub fn browser_mut(&mut self) -> &mut BrowserApp {
+        &mut self.browser
+    }
+}
@@ -0,0 +1,120 @@
+//! 要素の役割と ARIA 属性
+//!
+//! `role` 属性があればそれを、なければ要素の暗黙の役割（HTML-AAM）を使う。
+
+use crate::engine::html::HtmlNodeType;
+use crate::engine::input::focus;
+
+use super::Role;
+
+/// `role` 属性の 1 つのトークンの意味
+enum Explicit {
+    Role(Role),
+    /// `none` / `presentation` / `generic`: 役割を持たない
+    Presentation,
+    /// 知らない役割（次のトークンを見る）
+    Unknown,
+}
+
+/// 要素の役割（役割がなければ None）
+///
+/// `role` 属性はトークンを前から見て、最初に知っている役割を使う。`presentation` は
+/// フォーカスできる要素では無視する（WAI-ARIA の「役割の衝突の解決」）。
+pub fn role_of(node: &HtmlNodeType) -> Option<Role> {
+    if let Some(roles) = node.get_attr("role") {
+        for token in roles.split_ascii_whitespace() {
+            match explicit_role(&token.to_ascii_lowercase(), node) {
+                Explicit::Role(role) => return Some(role),
+                Explicit::Presentation if !focus::is_focusable(node) => return None,
+                _ => {}
+            }
+        }
+    }
+    native_role(node)
+}
+
+/// `aria-hidden="true"` か `hidden` 属性で支援技術から隠された要素か
+pub fn is_hidden(node: &HtmlNodeType) -> bool {
+    node.has_attr("hidden")
+        || node
+            .get_attr("aria-hidden")
+            .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"))
+}
+
+fn explicit_role(token: &str, node: &HtmlNodeType) -> Explicit {
+    let role = match token {
+        "none" | "presentation" | "generic" => return Explicit::Presentation,
+        "heading" => Role::Heading {
+            level: heading_level(node, 2),
+        },
+        "paragraph" => Role::Paragraph,
+        "link" => Role::Link,
+        "button" => Role::Button,
+        "textbox" | "searchbox" => Role::TextInput,
+        "checkbox" | "switch" => Role::CheckBox,
+        "radio" => Role::RadioButton,
+        "img" | "image" => Role::Image,
+        "list" => Role::List,
+        "listitem" => Role::ListItem,
+        "table" | "grid" => Role::Table,
+        "row" => Role::Row,
+        "cell" | "gridcell" | "columnheader" | "rowheader" => Role::Cell,
+        "navigation" => Role::Navigation,
+        "main" => Role::Main,
+        "banner" => Role::Banner,
+        "contentinfo" => Role::ContentInfo,
+        "article" => Role::Article,
+        "region" => Role::Section,
+        "form" => Role::Form,
+        _ => return Explicit::Unknown,
+    };
+    Explicit::Role(role)
+}
+
+fn native_role(node: &HtmlNodeType) -> Option<Role> {
+    let role = match node.tag_name()? {
+        tag @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => Role::Heading {
+            level: heading_level(node, tag[1..].parse().ok()?),
+        },
+        "p" => Role::Paragraph,
+        "a" | "area" if node.has_attr("href") => Role::Link,
+        "button" => Role::Button,
+        "input" => {
+            let kind = node.get_attr("type").unwrap_or("text").trim();
+            match kind.to_ascii_lowercase().as_str() {
+                "hidden" => return None,
+                "checkbox" => Role::CheckBox,
+                "radio" => Role::RadioButton,
+                "button" | "submit" | "reset" | "image" => Role::Button,
+                _ => Role::TextInput,
+            }
+        }
+        "textarea" => Role::TextInput,
+        // alt="" の画像は飾り
+        "img" if node.get_attr("alt") == Some("") => return None,
+        "img" => Role::Image,
+        "ul" | "ol" | "menu" => Role::List,
+        "li" => Role::ListItem,
+        "table" => Role::Table,
+        "tr" => Role::Row,
+        "td" | "th" => Role::Cell,
+        "nav" => Role::Navigation,
+        "main" => Role::Main,
+        "header" => Role::Banner,
+        "footer" => Role::ContentInfo,
+        "article" => Role::Article,
+        "section" => Role::Section,
+        "form" => Role::Form,
+        "iframe" => Role::Frame,
+        _ => return None,
+    };
+    Some(role)
+}
+
+/// `aria-level`（なければ default）
+fn heading_level(node: &HtmlNodeType, default: u8) -> u8 {
+    node.get_attr("aria-level")
+        .and_then(|level| level.trim().parse::<u8>().ok())
+        .filter(|&level| level > 0)
+        .unwrap_or(default)
+}
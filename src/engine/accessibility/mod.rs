@@ -0,0 +1,413 @@
+//! アクセシビリティツリー
+//!
+//! DOM とレイアウトから、スクリーンリーダーなどの支援技術に渡す木を作る。
+//! 各ノードは役割（見出し、リンク、ボタンなど）、名前、ページ上の位置、状態を持つ。
+//! 役割のない要素（div や span など）は木に出さず、子を親に繋ぎ替える。
+//! 役割は `role` 属性（なければ要素の暗黙の役割）から、名前は AccName の手順で決める
+//! （aria、name モジュール）。`aria-hidden="true"` の要素は子も含めて木に出さない。
+//!
+//! プラットフォームの API（AccessKit）への変換は platform::system::accessibility で行う。
+
+mod aria;
+mod name;
+
+use ui_layout::LayoutNode;
+
+use crate::engine::html::HtmlNodeType;
+use crate::engine::input::{focus, text_edit};
+use crate::engine::layouter::types::{InfoNode, NodeKind};
+use crate::engine::tree::{NodeRef, TreeNode};
+
+use name::NameComputer;
+
+/// 文書のノードの ID（空のパスの ID）
+const DOCUMENT_ID: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// ノードの役割
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Document,
+    /// `<h1>`〜`<h6>`（level は 1〜6）
+    Heading {
+        level: u8,
+    },
+    Paragraph,
+    Link,
+    Button,
+    TextInput,
+    CheckBox,
+    RadioButton,
+    Image,
+    List,
+    ListItem,
+    Table,
+    Row,
+    Cell,
+    Navigation,
+    Main,
+    Banner,
+    ContentInfo,
+    Article,
+    Section,
+    Form,
+    Frame,
+    /// 文字列（テキストノード）
+    StaticText,
+}
+
+/// ノードの状態
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct States {
+    pub focusable: bool,
+    pub focused: bool,
+    pub disabled: bool,
+    /// チェックボックスとラジオボタンの選択（それ以外は None）
+    pub checked: Option<bool>,
+}
+
+/// ページ上の矩形（CSS px、ページの左上が原点）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// アクセシビリティツリーのノード
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessNode {
+    /// DOM のパスから決まる ID（木を作り直しても同じ要素なら同じ）
+    pub id: u64,
+    pub role: Role,
+    /// 読み上げる名前
+    pub name: String,
+    /// 入力欄の値
+    pub value: Option<String>,
+    /// リンク先
+    pub url: Option<String>,
+    /// border box（レイアウトの箱がなければ None）
+    pub bounds: Option<Bounds>,
+    pub states: States,
+    /// DOM のパス（ルートからの子インデックス）
+    pub path: Vec<usize>,
+    pub children: Vec<AccessNode>,
+}
+
+impl AccessNode {
+    /// id のノードを子孫から探す
+    pub fn find(&self, id: u64) -> Option<&AccessNode> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(id))
+    }
+
+    /// 自分と子孫を深さ優先でたどる
+    pub fn walk(&self, f: &mut impl FnMut(&AccessNode)) {
+        f(self);
+        for child in &self.children {
+            child.walk(f);
+        }
+    }
+}
+
+/// 1 つの文書のアクセシビリティツリー
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessTree {
+    pub root: AccessNode,
+    /// フォーカスのあるノード（なければ文書）の ID
+    pub focus: u64,
+    /// ウィンドウの中でページを描いている位置（論理 px）。BrowserApp が設定する
+    pub offset: (f32, f32),
+    /// ページの CSS px あたりの論理 px（ズーム）
+    pub scale: f32,
+}
+
+/// 支援技術から頼まれた操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessAction {
+    /// ノードにフォーカスを移す
+    Focus(u64),
+    /// ノードを実行する（リンクをたどる、ボタンを押す）
+    Click(u64),
+}
+
+impl AccessAction {
+    pub fn target(&self) -> u64 {
+        match self {
+            Self::Focus(id) | Self::Click(id) => *id,
+        }
+    }
+}
+
+/// path の要素のノードの ID
+///
+/// 要素が増減しない限り、木を作り直しても同じ ID になる。
+pub fn node_id(path: &[usize]) -> u64 {
+    // FNV-1a
+    path.iter().fold(DOCUMENT_ID, |hash, &i| {
+        (hash ^ (i as u64 + 1)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// DOM とレイアウトからアクセシビリティツリーを作る
+///
+/// layout と info は DOM と同じ形（子インデックスが共通）であること。
+/// focus_path はフォーカスのある要素のパス。
+pub fn build_tree(
+    root: &NodeRef<HtmlNodeType>,
+    layout: &LayoutNode,
+    info: &InfoNode,
+    focus_path: Option<&[usize]>,
+) -> AccessTree {
+    let builder = Builder {
+        names: NameComputer::new(root),
+        focus_path,
+    };
+    let mut children = Vec::new();
+    let mut path = Vec::new();
+    let origin = content_origin(layout, info, (0.0, 0.0));
+    builder.collect_children(root, layout, info, origin, &mut path, &mut children);
+
+    let document = AccessNode {
+        id: DOCUMENT_ID,
+        role: Role::Document,
+        name: document_title(root).unwrap_or_default(),
+        value: None,
+        url: None,
+        bounds: border_box(layout, (0.0, 0.0)),
+        states: States::default(),
+        path: Vec::new(),
+        children,
+    };
+    let focus = focus_path
+        .map(node_id)
+        .filter(|&id| document.find(id).is_some())
+        .unwrap_or(DOCUMENT_ID);
+    AccessTree {
+        root: document,
+        focus,
+        offset: (0.0, 0.0),
+        scale: 1.0,
+    }
+}
+
+struct Builder<'a> {
+    names: NameComputer,
+    focus_path: Option<&'a [usize]>,
+}
+
+impl Builder<'_> {
+    /// node の子を木にしたものを out に足す
+    fn collect_children(
+        &self,
+        node: &NodeRef<HtmlNodeType>,
+        layout: &LayoutNode,
+        info: &InfoNode,
+        origin: (f32, f32),
+        path: &mut Vec<usize>,
+        out: &mut Vec<AccessNode>,
+    ) {
+        for (i, child) in node.borrow().children().iter().enumerate() {
+            let (Some(child_layout), Some(child_info)) =
+                (layout.children.get(i), info.children.get(i))
+            else {
+                continue;
+            };
+            path.push(i);
+            self.collect(child, child_layout, child_info, origin, path, out);
+            path.pop();
+        }
+    }
+
+    /// node を木にしたものを out に足す
+    ///
+    /// 役割のない要素は自分の代わりに子を足す。origin は親の content box の原点（ページの座標）。
+    fn collect(
+        &self,
+        node_ref: &NodeRef<HtmlNodeType>,
+        layout: &LayoutNode,
+        info: &InfoNode,
+        origin: (f32, f32),
+        path: &mut Vec<usize>,
+        out: &mut Vec<AccessNode>,
+    ) {
+        // display: none
+        if layout.layout_boxes.is_empty() {
+            return;
+        }
+
+        let node = node_ref.borrow();
+        let value = &node.value;
+        match value {
+            HtmlNodeType::Text(text) => {
+                let name = normalize(text);
+                if !name.is_empty() {
+                    out.push(leaf(Role::StaticText, name, layout, origin, path));
+                }
+                return;
+            }
+            HtmlNodeType::Element { tag_name, .. }
+                if !is_hidden_tag(tag_name) && !aria::is_hidden(value) => {}
+            _ => return,
+        }
+
+        let mut children = Vec::new();
+        let child_origin = content_origin(layout, info, origin);
+        self.collect_children(node_ref, layout, info, child_origin, path, &mut children);
+
+        let Some(role) = aria::role_of(value) else {
+            out.append(&mut children);
+            return;
+        };
+
+        let states = States {
+            focusable: focus::is_focusable(value),
+            focused: self.focus_path == Some(path.as_slice()),
+            disabled: value.has_attr("disabled"),
+            checked: matches!(role, Role::CheckBox | Role::RadioButton)
+                .then(|| value.has_attr("checked")),
+        };
+        let input_value = (role == Role::TextInput).then(|| input_value(&node));
+        // 入力欄の文字列は値として、<input> のボタンの文字列は名前として持つ
+        if role == Role::TextInput || value.tag_name() == Some("input") {
+            children.clear();
+        }
+
+        out.push(AccessNode {
+            id: node_id(path),
+            role,
+            name: self.names.name(node_ref),
+            value: input_value,
+            url: value
+                .get_attr("href")
+                .filter(|_| role == Role::Link)
+                .map(str::to_string),
+            bounds: border_box(layout, origin),
+            states,
+            path: path.clone(),
+            children,
+        });
+    }
+}
+
+fn leaf(
+    role: Role,
+    name: String,
+    layout: &LayoutNode,
+    origin: (f32, f32),
+    path: &[usize],
+) -> AccessNode {
+    AccessNode {
+        id: node_id(path),
+        role,
+        name,
+        value: None,
+        url: None,
+        bounds: border_box(layout, origin),
+        states: States::default(),
+        path: path.to_vec(),
+        children: Vec::new(),
+    }
+}
+
+/// 木に出さない要素（中身を表示しないもの）
+fn is_hidden_tag(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "head" | "title" | "meta" | "link" | "script" | "style" | "template" | "noscript"
+    )
+}
+
+/// node の子孫のテキストをつなげる
+fn text_content(node: &NodeRef<HtmlNodeType>, out: &mut String) {
+    let node = node.borrow();
+    match &node.value {
+        HtmlNodeType::Text(text) => out.push_str(text),
+        HtmlNodeType::Element { tag_name, .. } if is_hidden_tag(tag_name) => {}
+        HtmlNodeType::Element { .. } => {
+            if node.value.tag_name() == Some("img") {
+                out.push_str(node.value.get_attr("alt").unwrap_or_default());
+            }
+            for child in node.children() {
+                text_content(child, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 入力欄の値（パスワードは伏せ字にする）
+fn input_value(node: &TreeNode<HtmlNodeType>) -> String {
+    if node.value.tag_name() == Some("textarea") {
+        let mut text = String::new();
+        for child in node.children() {
+            text_content(child, &mut text);
+        }
+        return text;
+    }
+
+    let value = node.value.get_attr("value").unwrap_or_default();
+    let is_password = node
+        .value
+        .get_attr("type")
+        .is_some_and(|t| t.trim().eq_ignore_ascii_case("password"));
+    if is_password {
+        text_edit::PASSWORD_MASK
+            .to_string()
+            .repeat(value.chars().count())
+    } else {
+        value.to_string()
+    }
+}
+
+/// `<title>` の文字列
+fn document_title(root: &NodeRef<HtmlNodeType>) -> Option<String> {
+    let node = root.borrow();
+    if node.value.tag_name() == Some("title") {
+        let mut text = String::new();
+        for child in node.children() {
+            text_content(child, &mut text);
+        }
+        return Some(normalize(&text));
+    }
+    if matches!(&node.value, HtmlNodeType::Element { tag_name, .. } if tag_name == "body") {
+        return None;
+    }
+    node.children().iter().find_map(document_title)
+}
+
+/// 空白をまとめて前後を取り除く
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn border_box(layout: &LayoutNode, origin: (f32, f32)) -> Option<Bounds> {
+    let rect = layout.layout_boxes.first()?.border_box;
+    Some(Bounds {
+        x: origin.0 + rect.x,
+        y: origin.1 + rect.y,
+        width: rect.width,
+        height: rect.height,
+    })
+}
+
+/// 子の座標の原点（content box の左上からスクロール位置を引いたもの）
+fn content_origin(layout: &LayoutNode, info: &InfoNode, origin: (f32, f32)) -> (f32, f32) {
+    let Some(content) = layout.layout_boxes.first().map(|b| b.content_box) else {
+        return origin;
+    };
+    let (scroll_x, scroll_y) = match &info.kind {
+        NodeKind::Container {
+            scroll_offset_x,
+            scroll_offset_y,
+            ..
+        } => (*scroll_offset_x, *scroll_offset_y),
+        _ => (0.0, 0.0),
+    };
+    (
+        origin.0 + content.x - scroll_x,
+        origin.1 + content.y - scroll_y,
+    )
+}
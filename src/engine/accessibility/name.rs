@@ -0,0 +1,329 @@
+//! アクセシブルな名前の計算
+//!
+//! [Accessible Name and Description Computation 1.2](https://www.w3.org/TR/accname-1.2/)
+//! の手順に沿って、`aria-labelledby`、`aria-label`、要素ごとの名前（`alt`、`<label>` など）、
+//! 中身の文字列、`title` 属性の順に名前を決める。CSS の生成内容（`::before` など）は扱わない。
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::engine::html::HtmlNodeType;
+use crate::engine::tree::{NodeRef, TreeNode};
+
+use super::{Role, aria, input_value, is_hidden_tag, normalize};
+
+/// 計算の途中の状態
+#[derive(Clone, Copy, Default)]
+struct Step {
+    /// aria-labelledby でたどっている（その先の aria-labelledby はたどらない）
+    labelledby: bool,
+    /// aria-labelledby で直接参照された要素（隠れていても数える）
+    referenced: bool,
+    /// 中身や `<label>` をたどっている
+    content: bool,
+}
+
+/// 文書の要素の名前を求める
+///
+/// id と `<label for>` を先に集めておく。
+pub struct NameComputer {
+    /// id 属性の値 → 要素（同じ id があれば最初のもの）
+    ids: HashMap<String, NodeRef<HtmlNodeType>>,
+    /// `<label>` の for 属性の値 → label 要素
+    labels: HashMap<String, Vec<NodeRef<HtmlNodeType>>>,
+}
+
+impl NameComputer {
+    pub fn new(root: &NodeRef<HtmlNodeType>) -> Self {
+        let mut computer = Self {
+            ids: HashMap::new(),
+            labels: HashMap::new(),
+        };
+        computer.index(root);
+        computer
+    }
+
+    fn index(&mut self, node: &NodeRef<HtmlNodeType>) {
+        let n = node.borrow();
+        if let Some(id) = n.value.get_attr("id") {
+            self.ids
+                .entry(id.to_string())
+                .or_insert_with(|| Rc::clone(node));
+        }
+        if n.value.tag_name() == Some("label")
+            && let Some(target) = n.value.get_attr("for")
+        {
+            self.labels
+                .entry(target.to_string())
+                .or_default()
+                .push(Rc::clone(node));
+        }
+        for child in n.children() {
+            self.index(child);
+        }
+    }
+
+    /// node のアクセシブルな名前（空白はまとめる）
+    pub fn name(&self, node: &NodeRef<HtmlNodeType>) -> String {
+        normalize(&self.compute(node, Step::default(), &mut HashSet::new()))
+    }
+
+    fn compute(
+        &self,
+        node: &NodeRef<HtmlNodeType>,
+        step: Step,
+        visited: &mut HashSet<*const ()>,
+    ) -> String {
+        let n = node.borrow();
+        let value = &n.value;
+        let tag_name = match value {
+            HtmlNodeType::Element { tag_name, .. } => tag_name.as_str(),
+            // 2G: テキストノード
+            HtmlNodeType::Text(text) if step.content => return text.clone(),
+            _ => return String::new(),
+        };
+        // <label> の中の入力欄が同じ label を指すなど、同じ要素に戻ってきたら数えない
+        if !visited.insert(Rc::as_ptr(node).cast()) && !step.referenced {
+            return String::new();
+        }
+
+        // 2A: 隠れた要素
+        if (aria::is_hidden(value) || is_hidden_tag(tag_name)) && !step.referenced {
+            return String::new();
+        }
+
+        // 2B: aria-labelledby
+        if !step.labelledby
+            && let Some(ids) = value.get_attr("aria-labelledby")
+        {
+            let mut names = Vec::new();
+            for element in ids
+                .split_ascii_whitespace()
+                .filter_map(|id| self.ids.get(id))
+            {
+                let step = Step {
+                    labelledby: true,
+                    referenced: true,
+                    content: true,
+                };
+                names.push(self.compute(element, step, visited));
+            }
+            let name = names.join(" ");
+            if !name.trim().is_empty() {
+                return name;
+            }
+        }
+
+        let role = aria::role_of(value);
+
+        // 2E: 名前の途中に埋め込まれた入力欄は値
+        if step.content && role == Some(Role::TextInput) {
+            return input_value(&n);
+        }
+
+        // 2C: aria-label
+        if let Some(label) = value.get_attr("aria-label")
+            && !label.trim().is_empty()
+        {
+            return label.to_string();
+        }
+
+        // 2D: 要素ごとの名前
+        let native = self.native_name(&n, step, visited);
+        if !native.trim().is_empty() {
+            return native;
+        }
+
+        // 2F: 中身から
+        if step.content || role.is_some_and(name_from_content) {
+            let content = self.content_name(&n, step, visited);
+            if !content.trim().is_empty() {
+                return content;
+            }
+        }
+
+        // 2I: ツールチップ（入力欄ならプレースホルダーも）
+        if let Some(title) = value.get_attr("title")
+            && !title.trim().is_empty()
+        {
+            return title.to_string();
+        }
+        if !step.content && role == Some(Role::TextInput) {
+            return value
+                .get_attr("placeholder")
+                .unwrap_or_default()
+                .to_string();
+        }
+        String::new()
+    }
+
+    /// HTML-AAM で要素ごとに決まっている名前
+    fn native_name(
+        &self,
+        n: &TreeNode<HtmlNodeType>,
+        step: Step,
+        visited: &mut HashSet<*const ()>,
+    ) -> String {
+        let value = &n.value;
+        let attr = |name: &str| value.get_attr(name).unwrap_or_default().to_string();
+        match value.tag_name() {
+            Some("img" | "area") => attr("alt"),
+            Some("input") => {
+                let kind = value.get_attr("type").unwrap_or("text").trim();
+                match kind.to_ascii_lowercase().as_str() {
+                    "submit" => value.get_attr("value").unwrap_or("Submit").to_string(),
+                    "reset" => value.get_attr("value").unwrap_or("Reset").to_string(),
+                    "button" => attr("value"),
+                    "image" => [attr("alt"), attr("value")]
+                        .into_iter()
+                        .find(|name| !name.trim().is_empty())
+                        .unwrap_or_else(|| "Submit".to_string()),
+                    _ => self.label_name(n, step, visited),
+                }
+            }
+            Some("textarea" | "select" | "button") => self.label_name(n, step, visited),
+            Some("fieldset") => self.child_name(n, "legend", step, visited),
+            Some("table") => self.child_name(n, "caption", step, visited),
+            Some("figure") => self.child_name(n, "figcaption", step, visited),
+            _ => String::new(),
+        }
+    }
+
+    /// 入力欄などを指す `<label>`（for 属性か、入力欄を囲むもの）の名前
+    fn label_name(
+        &self,
+        n: &TreeNode<HtmlNodeType>,
+        step: Step,
+        visited: &mut HashSet<*const ()>,
+    ) -> String {
+        let mut labels: Vec<NodeRef<HtmlNodeType>> = n
+            .value
+            .get_attr("id")
+            .and_then(|id| self.labels.get(id))
+            .cloned()
+            .unwrap_or_default();
+        let mut ancestor = n.parent();
+        while let Some(node) = ancestor {
+            if node.borrow().value.tag_name() == Some("label")
+                && !labels.iter().any(|label| Rc::ptr_eq(label, &node))
+            {
+                labels.push(Rc::clone(&node));
+            }
+            ancestor = node.borrow().parent();
+        }
+
+        let step = Step {
+            referenced: false,
+            content: true,
+            ..step
+        };
+        labels
+            .iter()
+            .map(|label| self.compute(label, step, visited))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// 最初の tag_name の子（`<legend>` など）の名前
+    fn child_name(
+        &self,
+        n: &TreeNode<HtmlNodeType>,
+        tag_name: &str,
+        step: Step,
+        visited: &mut HashSet<*const ()>,
+    ) -> String {
+        let Some(child) = n
+            .children()
+            .iter()
+            .find(|child| child.borrow().value.tag_name() == Some(tag_name))
+        else {
+            return String::new();
+        };
+        let step = Step {
+            referenced: false,
+            content: true,
+            ..step
+        };
+        self.compute(child, step, visited)
+    }
+
+    /// 子の名前をつなげたもの
+    fn content_name(
+        &self,
+        n: &TreeNode<HtmlNodeType>,
+        step: Step,
+        visited: &mut HashSet<*const ()>,
+    ) -> String {
+        let step = Step {
+            referenced: false,
+            content: true,
+            ..step
+        };
+        let mut name = String::new();
+        for child in n.children() {
+            let child_name = self.compute(child, step, visited);
+            // ブロックの要素は前後の文字列とつながらない
+            if child.borrow().value.tag_name().is_some_and(is_block) {
+                name.push(' ');
+                name.push_str(&child_name);
+                name.push(' ');
+            } else {
+                name.push_str(&child_name);
+            }
+        }
+        name
+    }
+}
+
+/// 中身の文字列を名前にする役割
+fn name_from_content(role: Role) -> bool {
+    matches!(
+        role,
+        Role::Heading { .. }
+            | Role::Link
+            | Role::Button
+            | Role::CheckBox
+            | Role::RadioButton
+            | Role::Cell
+            | Role::Row
+    )
+}
+
+fn is_block(tag_name: &str) -> bool {
+    matches!(
+        tag_name,
+        "address"
+            | "article"
+            | "blockquote"
+            | "br"
+            | "dd"
+            | "div"
+            | "dl"
+            | "dt"
+            | "fieldset"
+            | "figcaption"
+            | "figure"
+            | "footer"
+            | "form"
+            | "h1"
+            | "h2"
+            | "h3"
+            | "h4"
+            | "h5"
+            | "h6"
+            | "header"
+            | "hr"
+            | "li"
+            | "main"
+            | "nav"
+            | "ol"
+            | "p"
+            | "pre"
+            | "section"
+            | "table"
+            | "td"
+            | "th"
+            | "tr"
+            | "ul"
+    )
+}
@@ -1,10 +1,16 @@
-use super::{TextMeasureError, TextMeasureRequest, TextMeasurer, TextMetrics};
+use super::{
+    ClusterMetrics, LineFragment, TextMeasureError, TextMeasureRequest, TextMeasurer, TextMetrics,
+};
 use crate::engine::layouter::types::TextStyle;
 
+use unicode_linebreak::{BreakOpportunity, linebreaks};
+
 /// Fallback text measurer.
 ///
 /// This implementation does not rely on any font engine.
 /// It uses a simple heuristic based on font size and character count.
+/// Line breaking follows UAX #14 break opportunities, so text without
+/// spaces (e.g. Japanese) still wraps.
 /// Intended for testing, bring-up, and environments without font support.
 #[derive(Debug, Default)]
 pub struct FallbackTextMeasurer;
@@ -20,37 +26,83 @@ impl TextMeasurer<TextStyle> for FallbackTextMeasurer {
         let char_width = font_size * 0.6;
         let line_height = font_size * 1.2;
 
-        let mut current_line_width = 0.0;
-        let mut max_line_width: f32 = 0.0;
-        let mut line_count = 1;
+        let text = request.text.as_str();
+        let segment_width =
+            |s: &str| s.chars().filter(|c| !matches!(c, '\n' | '\r')).count() as f32 * char_width;
+        let max_width = request.max_width.filter(|_| request.wrap);
 
-        for ch in request.text.chars() {
-            if ch == '\n' {
-                max_line_width = max_line_width.max(current_line_width);
-                current_line_width = 0.0;
-                line_count += 1;
-                continue;
-            }
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        let mut line_width = 0.0;
+        let mut prev = 0;
 
-            current_line_width += char_width;
+        // Greedily fill lines with the segments between break opportunities
+        for (idx, opportunity) in linebreaks(text) {
+            let segment_w = segment_width(&text[prev..idx]);
 
-            if request.wrap
-                && let Some(max_width) = request.max_width
-                && current_line_width > max_width
+            if let Some(max_width) = max_width
+                && line_width > 0.0
+                && line_width + segment_w > max_width
             {
-                max_line_width = max_line_width.max(current_line_width - char_width);
-                current_line_width = char_width;
-                line_count += 1;
+                lines.push(LineFragment {
+                    start: line_start,
+                    end: prev,
+                    width: line_width,
+                    clusters: Vec::new(),
+                });
+                line_start = prev;
+                line_width = 0.0;
+            }
+
+            line_width += segment_w;
+
+            if opportunity == BreakOpportunity::Mandatory && idx < text.len() {
+                lines.push(LineFragment {
+                    start: line_start,
+                    end: idx,
+                    width: line_width,
+                    clusters: Vec::new(),
+                });
+                line_start = idx;
+                line_width = 0.0;
+            }
+
+            prev = idx;
+        }
+
+        lines.push(LineFragment {
+            start: line_start,
+            end: text.len(),
+            width: line_width,
+            clusters: Vec::new(),
+        });
+
+        // 1 文字 = 1 クラスタとして位置を割り当てる
+        for line in &mut lines {
+            let mut x = 0.0;
+            for (i, ch) in text[line.start..line.end].char_indices() {
+                if matches!(ch, '\n' | '\r') {
+                    continue;
+                }
+                let start = line.start + i;
+                line.clusters.push(ClusterMetrics {
+                    start,
+                    end: start + ch.len_utf8(),
+                    x,
+                    width: char_width,
+                });
+                x += char_width;
             }
         }
 
-        max_line_width = max_line_width.max(current_line_width);
+        let width = lines.iter().map(|l| l.width).fold(0.0, f32::max);
 
         Ok(TextMetrics {
-            width: max_line_width,
-            height: line_height * line_count as f32,
+            width,
+            height: line_height * lines.len() as f32,
             baseline: font_size,
-            line_count,
+            line_count: lines.len(),
+            lines,
         })
     }
 }
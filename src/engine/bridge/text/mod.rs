@@ -65,6 +65,106 @@ pub struct TextMetrics {
 
     /// Number of layouted lines
     pub line_count: usize,
+
+    /// Layouted lines in order (one per line, at least one for non-empty text)
+    pub lines: Vec<LineFragment>,
+}
+
+/// A single layouted line of the measured text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineFragment {
+    /// Start byte offset into the request text
+    pub start: usize,
+
+    /// End byte offset into the request text (exclusive)
+    pub end: usize,
+
+    /// Logical width of the line
+    pub width: f32,
+
+    /// Clusters in visual order with their x-offsets from the line start
+    pub clusters: Vec<ClusterMetrics>,
+}
+
+/// Position of a grapheme cluster (the smallest unit a caret can sit between).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClusterMetrics {
+    /// Start byte offset into the request text
+    pub start: usize,
+
+    /// End byte offset into the request text (exclusive)
+    pub end: usize,
+
+    /// X offset from the start of the line
+    pub x: f32,
+
+    /// Advance width of the cluster
+    pub width: f32,
+}
+
+impl TextMetrics {
+    /// Height of a single line.
+    pub fn line_height(&self) -> f32 {
+        self.height / self.line_count.max(1) as f32
+    }
+
+    /// Returns the byte offset of the caret position closest to `(x, y)`,
+    /// given relative to the top-left of the text box.
+    pub fn offset_at(&self, x: f32, y: f32) -> usize {
+        let Some(last) = self.lines.len().checked_sub(1) else {
+            return 0;
+        };
+        let line_i = ((y / self.line_height()).floor().max(0.0) as usize).min(last);
+
+        self.lines[line_i].offset_at(x)
+    }
+
+    /// Returns the caret position `(x, y)` for a byte offset, relative to the
+    /// top-left of the text box. `y` is the top of the line.
+    pub fn caret_position(&self, offset: usize) -> (f32, f32) {
+        // 行の境界にあるオフセットは後ろの行の先頭として扱う
+        let Some(line_i) = self
+            .lines
+            .iter()
+            .position(|l| offset < l.end)
+            .or(self.lines.len().checked_sub(1))
+        else {
+            return (0.0, 0.0);
+        };
+        let y = self.line_height() * line_i as f32;
+
+        (self.lines[line_i].x_at(offset), y)
+    }
+}
+
+impl LineFragment {
+    /// Returns the byte offset of the caret position closest to `x` on this line.
+    pub fn offset_at(&self, x: f32) -> usize {
+        for cluster in &self.clusters {
+            // クラスタの左半分なら手前、右半分なら後ろにキャレットを置く
+            if x < cluster.x + cluster.width / 2.0 {
+                return cluster.start;
+            }
+        }
+
+        self.clusters.last().map(|c| c.end).unwrap_or(self.start)
+    }
+
+    /// Returns the x offset of the caret at a byte offset on this line.
+    pub fn x_at(&self, offset: usize) -> f32 {
+        self.clusters
+            .iter()
+            .find_map(|c| {
+                if (c.start..c.end).contains(&offset) {
+                    Some(c.x)
+                } else if c.end == offset {
+                    Some(c.x + c.width)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(if offset >= self.end { self.width } else { 0.0 })
+    }
 }
 
 /* ============================
@@ -1,18 +1,42 @@
-use super::parser::{Combinator, ComplexSelector, Selector};
+use super::parser::{AttributeOperator, AttributeSelector, Combinator, ComplexSelector, Selector};
 
 #[derive(Debug, Clone)]
 pub struct ElementInfo {
     pub tag_name: String,
     pub id: Option<String>,
     pub classes: Vec<String>,
+    /// 属性（名前は小文字）
+    pub attributes: Vec<(String, String)>,
+    /// マウスカーソルがこの要素（またはその子孫）の上にあるか
+    pub hovered: bool,
+    /// マウスのボタンでこの要素（またはその子孫）が押されているか
+    pub active: bool,
+    /// この要素にキーボードフォーカスがあるか
+    pub focused: bool,
+    /// この要素かその子孫にキーボードフォーカスがあるか
+    pub focus_within: bool,
+    /// 訪れたことのあるページへのリンクか（スクリプトからの照合では常に false）
+    pub visited: bool,
+}
+
+impl ElementInfo {
+    /// href を持つ `<a>` / `<area>`（`:link` か `:visited` になる要素）か
+    pub fn is_link(&self) -> bool {
+        matches!(self.tag_name.as_str(), "a" | "area")
+            && self.attributes.iter().any(|(name, _)| name == "href")
+    }
 }
 
 /// 右（自分）→ 左（祖先）
 pub type ElementChain = Vec<ElementInfo>;
 
 impl Selector {
-    /// Simple selector matcher (tag / class / id)
-    pub fn matches(&self, tag_name: &str, id: Option<&str>, class_list: &[String]) -> bool {
+    /// Simple selector matcher (tag / class / id / pseudo-class)
+    pub fn matches(&self, element: &ElementInfo) -> bool {
+        let tag_name = element.tag_name.as_str();
+        let id = element.id.as_deref();
+        let class_list = element.classes.as_slice();
+
         // tag
         if let Some(tag) = &self.tag
             && tag != tag_name
@@ -35,9 +59,33 @@ impl Selector {
             }
         }
 
-        if let Some(_pseudo) = &self.pseudo_class {
-            // TODO
-            return false;
+        // attribute
+        for attribute in &self.attributes {
+            let value = element
+                .attributes
+                .iter()
+                .find(|(name, _)| *name == attribute.name)
+                .map(|(_, v)| v.as_str());
+            if !value.is_some_and(|v| attribute.matches(v)) {
+                return false;
+            }
+        }
+
+        if let Some(pseudo) = &self.pseudo_class {
+            let matched = match pseudo.as_str() {
+                "hover" => element.hovered,
+                "active" => element.active,
+                "focus" => element.focused,
+                "focus-within" => element.focus_within,
+                "link" => element.is_link() && !element.visited,
+                "visited" => element.is_link() && element.visited,
+                "any-link" => element.is_link(),
+                // TODO: その他の擬似クラス
+                _ => false,
+            };
+            if !matched {
+                return false;
+            }
         }
 
         if let Some(_pseudo) = &self.pseudo_element {
@@ -49,6 +97,28 @@ impl Selector {
     }
 }
 
+impl AttributeSelector {
+    /// 属性値 value がこのセレクタに合うか
+    pub fn matches(&self, value: &str) -> bool {
+        let expected = self.value.as_str();
+        match self.operator {
+            AttributeOperator::Exists => true,
+            AttributeOperator::Equals => value == expected,
+            AttributeOperator::Includes => value.split_ascii_whitespace().any(|v| v == expected),
+            AttributeOperator::DashMatch => {
+                value == expected
+                    || value
+                        .strip_prefix(expected)
+                        .is_some_and(|rest| rest.starts_with('-'))
+            }
+            // 空の値に対する ^= $= *= は何にも当たらない
+            AttributeOperator::Prefix => !expected.is_empty() && value.starts_with(expected),
+            AttributeOperator::Suffix => !expected.is_empty() && value.ends_with(expected),
+            AttributeOperator::Substring => !expected.is_empty() && value.contains(expected),
+        }
+    }
+}
+
 impl ComplexSelector {
     pub fn matches(&self, chain: &[ElementInfo]) -> bool {
         if chain.is_empty() || self.parts.is_empty() {
@@ -61,10 +131,7 @@ impl ComplexSelector {
         let element = &chain[chain_index];
         let part = &self.parts[selector_index];
 
-        if !part
-            .selector
-            .matches(&element.tag_name, element.id.as_deref(), &element.classes)
-        {
+        if !part.selector.matches(element) {
             return false;
         }
 
@@ -98,9 +165,16 @@ impl ComplexSelector {
                 a += 1;
             }
             b += sel.classes.len() as u32;
+            b += sel.attributes.len() as u32;
+            if sel.pseudo_class.is_some() {
+                b += 1;
+            }
             if sel.tag.is_some() {
                 c += 1;
             }
+            if sel.pseudo_element.is_some() {
+                c += 1;
+            }
         }
 
         (a, b, c)
@@ -0,0 +1,142 @@
+//! Media query evaluation
+//!
+//! Decides whether the prelude of an `@media` rule matches the environment
+//! the page is rendered in.
+//!
+//! Supported:
+//! - Media types (`all` always matches; `screen` or `print` depending on
+//!   what the page is rendered for; others do not)
+//! - `not` / `only` / `and`, and comma-separated query lists
+//! - `prefers-color-scheme`
+//!
+//! Features that cannot be evaluated yet (e.g. `max-width`) are treated as
+//! matching, so such rules keep applying as they did before media queries
+//! were evaluated.
+
+use super::parser::AtQuery;
+use super::values::CssValue;
+
+/// Color scheme the page is rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl ColorScheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Light => "light",
+            Self::Dark => "dark",
+        }
+    }
+}
+
+/// What the page is rendered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MediaType {
+    #[default]
+    Screen,
+    /// Printing or exporting to PDF.
+    Print,
+}
+
+impl MediaType {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Screen => "screen",
+            Self::Print => "print",
+        }
+    }
+}
+
+/// Environment media queries are evaluated against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MediaContext {
+    pub media_type: MediaType,
+    pub color_scheme: ColorScheme,
+}
+
+impl MediaContext {
+    /// Returns whether the `@media` prelude `query` matches this environment.
+    ///
+    /// An empty prelude (`@media { ... }`) matches.
+    pub fn matches(&self, query: &AtQuery) -> bool {
+        let items = match query {
+            AtQuery::Group(items) => items.as_slice(),
+            other => std::slice::from_ref(other),
+        };
+        if items.is_empty() {
+            return true;
+        }
+
+        // カンマ区切りのどれかに一致すればよい
+        items
+            .split(|item| matches!(item, AtQuery::Comma))
+            .any(|query| self.matches_query(query))
+    }
+
+    /// `[not | only] <media-type> and (<feature>) and ...`
+    fn matches_query(&self, items: &[AtQuery]) -> bool {
+        let (negated, items) = match items.first() {
+            Some(AtQuery::Keyword(k)) if k.eq_ignore_ascii_case("not") => (true, &items[1..]),
+            Some(AtQuery::Keyword(k)) if k.eq_ignore_ascii_case("only") => (false, &items[1..]),
+            _ => (false, items),
+        };
+
+        let matched = self.matches_condition(items);
+        matched != negated
+    }
+
+    /// Items joined by `and` (or all by `or`).
+    fn matches_condition(&self, items: &[AtQuery]) -> bool {
+        let is_or = items
+            .iter()
+            .any(|item| matches!(item, AtQuery::Keyword(k) if k.eq_ignore_ascii_case("or")));
+
+        let mut results = items.iter().filter_map(|item| match item {
+            AtQuery::Keyword(k)
+                if k.eq_ignore_ascii_case("and") || k.eq_ignore_ascii_case("or") =>
+            {
+                None
+            }
+            AtQuery::Keyword(media_type) => Some(self.matches_media_type(media_type)),
+            AtQuery::Condition { name, value } => Some(self.matches_feature(name, value)),
+            AtQuery::Group(inner) => Some(self.matches_group(inner)),
+            AtQuery::Comma => None,
+        });
+
+        if is_or {
+            results.any(|matched| matched)
+        } else {
+            results.all(|matched| matched)
+        }
+    }
+
+    /// `( ... )`: a feature, a nested condition or `not ( ... )`.
+    fn matches_group(&self, items: &[AtQuery]) -> bool {
+        match items.first() {
+            Some(AtQuery::Keyword(k)) if k.eq_ignore_ascii_case("not") => {
+                !self.matches_condition(&items[1..])
+            }
+            // `(color)` のような値のない特性は評価できないので一致とみなす
+            Some(AtQuery::Keyword(_)) if items.len() == 1 => true,
+            _ => self.matches_condition(items),
+        }
+    }
+
+    fn matches_feature(&self, name: &str, value: &CssValue) -> bool {
+        match name.to_ascii_lowercase().as_str() {
+            "prefers-color-scheme" => {
+                matches!(value, CssValue::Keyword(v) if v.eq_ignore_ascii_case(self.color_scheme.name()))
+            }
+            _ => true,
+        }
+    }
+
+    fn matches_media_type(&self, media_type: &str) -> bool {
+        media_type.eq_ignore_ascii_case("all")
+            || media_type.eq_ignore_ascii_case(self.media_type.name())
+    }
+}
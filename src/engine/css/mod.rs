@@ -1,4 +1,5 @@
 pub mod matcher;
+pub mod media;
 pub mod parser;
 pub mod tokenizer;
 pub mod values;
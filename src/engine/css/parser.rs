@@ -61,6 +61,7 @@ pub enum AtQuery {
         value: CssValue, // 600px
     },
     Group(Vec<AtQuery>), // ( ... )
+    Comma,               // `,` (separates media queries)
 }
 
 /// Node in the CSS syntax tree.
@@ -85,7 +86,7 @@ impl CssNode {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Selector {
     /// Type selector (e.g. `div`)
     ///
@@ -99,6 +100,9 @@ pub struct Selector {
     /// Class selectors (e.g. `.container`)
     pub classes: Vec<String>,
 
+    /// Attribute selectors (e.g. `[type="text"]`)
+    pub attributes: Vec<AttributeSelector>,
+
     /// Pseudo-class (e.g. `:hover`)
     pub pseudo_class: Option<String>,
 
@@ -106,6 +110,37 @@ pub struct Selector {
     pub pseudo_element: Option<String>,
 }
 
+/// Attribute selector (e.g. `[type]`, `[lang|="en"]`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AttributeSelector {
+    /// Attribute name (lowercase)
+    pub name: String,
+
+    pub operator: AttributeOperator,
+
+    /// Expected value (empty for `AttributeOperator::Exists`)
+    pub value: String,
+}
+
+/// How an attribute selector compares the attribute value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttributeOperator {
+    /// `[attr]`
+    Exists,
+    /// `[attr=value]`
+    Equals,
+    /// `[attr~=value]` (whitespace-separated list contains value)
+    Includes,
+    /// `[attr|=value]` (equals value or starts with `value-`)
+    DashMatch,
+    /// `[attr^=value]`
+    Prefix,
+    /// `[attr$=value]`
+    Suffix,
+    /// `[attr*=value]`
+    Substring,
+}
+
 /// Combinator defining the relationship between selectors.
 ///
 /// Additional combinators (`>`, `+`, `~`) may be added later.
@@ -179,6 +214,9 @@ pub enum ParserErrorKind {
 
     /// Mismatched braces or parentheses
     MismatchedDelimiter { expected: char, found: char },
+
+    /// Blocks, parentheses or functions nested deeper than [`MAX_NESTING_DEPTH`]
+    NestingTooDeep,
 }
 
 impl fmt::Display for ParserErrorKind {
@@ -221,6 +259,18 @@ impl std::error::Error for ParserError {}
 /// Result type for parser functions
 pub type ParseResult<T> = Result<T, ParserError>;
 
+/// How deeply at-rule blocks, parenthesized queries and functions may nest.
+///
+/// Deeper input is rejected instead of recursing until the stack overflows.
+pub const MAX_NESTING_DEPTH: usize = 64;
+
+fn nesting_too_deep() -> ParserError {
+    ParserError {
+        kind: ParserErrorKind::NestingTooDeep,
+        context: vec![],
+    }
+}
+
 impl<'a> Parser<'a> {
     /// Create a new CSS parser from a source string.
     pub fn new(input: &'a str) -> Self {
@@ -343,6 +393,9 @@ impl<'a> Parser<'a> {
         let children = if self.peek_token() == &Token::Delim('{') {
             self.consume_token();
             self.brace_depth += 1;
+            if self.brace_depth > MAX_NESTING_DEPTH {
+                return Err(nesting_too_deep());
+            }
 
             let mut children = vec![];
             while self.peek_token() != &Token::Delim('}') {
@@ -430,11 +483,18 @@ impl<'a> Parser<'a> {
 
     fn parse_at_query(tokens: Vec<Token>) -> ParseResult<AtQuery> {
         let mut cursor = 0;
-        let items = Self::parse_at_query_list(&tokens, &mut cursor)?;
+        let items = Self::parse_at_query_list(&tokens, &mut cursor, 0)?;
         Ok(AtQuery::Group(items))
     }
 
-    fn parse_at_query_list(tokens: &[Token], cursor: &mut usize) -> ParseResult<Vec<AtQuery>> {
+    fn parse_at_query_list(
+        tokens: &[Token],
+        cursor: &mut usize,
+        depth: usize,
+    ) -> ParseResult<Vec<AtQuery>> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(nesting_too_deep());
+        }
         let mut items = Vec::new();
 
         while *cursor < tokens.len() {
@@ -445,7 +505,7 @@ impl<'a> Parser<'a> {
 
                 Token::Delim('(') => {
                     *cursor += 1;
-                    let group = Self::parse_at_query_list(tokens, cursor)?;
+                    let group = Self::parse_at_query_list(tokens, cursor, depth + 1)?;
                     items.push(AtQuery::Group(group));
                 }
 
@@ -458,6 +518,11 @@ impl<'a> Parser<'a> {
                     items.push(Self::parse_at_query_item(tokens, cursor)?);
                 }
 
+                Token::Delim(',') => {
+                    *cursor += 1;
+                    items.push(AtQuery::Comma);
+                }
+
                 _ => {
                     *cursor += 1;
                 }
@@ -468,9 +533,17 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_at_query_item(tokens: &[Token], cursor: &mut usize) -> ParseResult<AtQuery> {
-        let name = match &tokens[*cursor] {
-            Token::Ident(s) => s.clone(),
-            _ => unreachable!(),
+        let name = match tokens.get(*cursor) {
+            Some(Token::Ident(s)) => s.clone(),
+            token => {
+                return Err(ParserError {
+                    kind: ParserErrorKind::UnexpectedToken {
+                        expected: "ident",
+                        found: format!("{:?}", token),
+                    },
+                    context: vec![],
+                });
+            }
         };
         *cursor += 1;
 
@@ -583,13 +656,7 @@ impl<'a> Parser<'a> {
             let token = self.peek_token().clone();
             match token {
                 Token::Ident(name) => {
-                    let sel = current_selector.get_or_insert_with(|| Selector {
-                        tag: None,
-                        id: None,
-                        classes: vec![],
-                        pseudo_class: None,
-                        pseudo_element: None,
-                    });
+                    let sel = current_selector.get_or_insert_with(Selector::default);
 
                     if sel.tag.is_none() {
                         sel.tag = Some(name);
@@ -599,13 +666,7 @@ impl<'a> Parser<'a> {
                 }
 
                 Token::Hash(id) => {
-                    let sel = current_selector.get_or_insert_with(|| Selector {
-                        tag: None,
-                        id: None,
-                        classes: vec![],
-                        pseudo_class: None,
-                        pseudo_element: None,
-                    });
+                    let sel = current_selector.get_or_insert_with(Selector::default);
                     sel.id = Some(id);
                     self.consume_token();
                 }
@@ -613,13 +674,7 @@ impl<'a> Parser<'a> {
                 Token::Delim('.') => {
                     self.consume_token();
                     if let Token::Ident(class) = self.consume_token() {
-                        let sel = current_selector.get_or_insert_with(|| Selector {
-                            tag: None,
-                            id: None,
-                            classes: vec![],
-                            pseudo_class: None,
-                            pseudo_element: None,
-                        });
+                        let sel = current_selector.get_or_insert_with(Selector::default);
                         sel.classes.push(class);
                     }
                 }
@@ -630,27 +685,25 @@ impl<'a> Parser<'a> {
                         // pseudo-element
                         self.consume_token();
                         if let Token::Ident(name) = self.consume_token() {
-                            let sel = current_selector.get_or_insert_with(|| Selector {
-                                tag: None,
-                                id: None,
-                                classes: vec![],
-                                pseudo_class: None,
-                                pseudo_element: None,
-                            });
+                            let sel = current_selector.get_or_insert_with(Selector::default);
                             sel.pseudo_element = Some(name);
                         }
                     } else if let Token::Ident(name) = self.consume_token() {
-                        let sel = current_selector.get_or_insert_with(|| Selector {
-                            tag: None,
-                            id: None,
-                            classes: vec![],
-                            pseudo_class: None,
-                            pseudo_element: None,
-                        });
+                        let sel = current_selector.get_or_insert_with(Selector::default);
                         sel.pseudo_class = Some(name);
                     }
                 }
 
+                Token::Delim('[') => {
+                    self.consume_token();
+                    if let Some(attribute) = self.parse_attribute_selector() {
+                        current_selector
+                            .get_or_insert_with(Selector::default)
+                            .attributes
+                            .push(attribute);
+                    }
+                }
+
                 Token::Whitespace | Token::Comment(_) => {
                     // descendant combinator
                     if let Some(sel) = current_selector.take() {
@@ -706,6 +759,90 @@ impl<'a> Parser<'a> {
         selectors
     }
 
+    /// Parse an attribute selector after `[` up to and including `]`.
+    ///
+    /// Returns `None` for malformed selectors.
+    fn parse_attribute_selector(&mut self) -> Option<AttributeSelector> {
+        self.skip_whitespace();
+        let name = match self.consume_token() {
+            Token::Ident(name) => name.to_ascii_lowercase(),
+            _ => {
+                self.skip_until_bracket_end();
+                return None;
+            }
+        };
+        self.skip_whitespace();
+
+        let operator = match self.consume_token() {
+            Token::Delim(']') => {
+                return Some(AttributeSelector {
+                    name,
+                    operator: AttributeOperator::Exists,
+                    value: String::new(),
+                });
+            }
+            Token::Delim('=') => AttributeOperator::Equals,
+            Token::Delim(c @ ('~' | '|' | '^' | '$' | '*'))
+                if self.peek_token() == &Token::Delim('=') =>
+            {
+                self.consume_token();
+                match c {
+                    '~' => AttributeOperator::Includes,
+                    '|' => AttributeOperator::DashMatch,
+                    '^' => AttributeOperator::Prefix,
+                    '$' => AttributeOperator::Suffix,
+                    _ => AttributeOperator::Substring,
+                }
+            }
+            _ => {
+                self.skip_until_bracket_end();
+                return None;
+            }
+        };
+        self.skip_whitespace();
+
+        let value = match self.consume_token() {
+            Token::Ident(v) | Token::String(v) => v,
+            Token::Number(n) => n.to_string(),
+            _ => {
+                self.skip_until_bracket_end();
+                return None;
+            }
+        };
+        self.skip_whitespace();
+
+        // 大文字小文字を無視する `i` フラグなどは読み飛ばす
+        self.skip_until_bracket_end();
+
+        Some(AttributeSelector {
+            name,
+            operator,
+            value,
+        })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek_token(), Token::Whitespace | Token::Comment(_)) {
+            self.consume_token();
+        }
+    }
+
+    /// Skip tokens up to and including `]` (stops before `{` or EOF).
+    fn skip_until_bracket_end(&mut self) {
+        loop {
+            match self.peek_token() {
+                Token::Delim(']') => {
+                    self.consume_token();
+                    break;
+                }
+                Token::Delim('{') | Token::EOF => break,
+                _ => {
+                    self.consume_token();
+                }
+            }
+        }
+    }
+
     /// Parse declaration until `Token::Delim('}')`.
     fn parse_declaration_list(&mut self) -> ParseResult<Vec<CssNode>> {
         let mut declarations = vec![];
@@ -771,6 +908,14 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_tokens_to_css_value(tokens: Vec<Token>) -> ParseResult<CssValue> {
+        Self::parse_nested_css_value(tokens, 0)
+    }
+
+    /// Parses a value that is nested `depth` functions deep.
+    fn parse_nested_css_value(tokens: Vec<Token>, depth: usize) -> ParseResult<CssValue> {
+        if depth > MAX_NESTING_DEPTH {
+            return Err(nesting_too_deep());
+        }
         let mut values = vec![];
         let mut iter = tokens.into_iter().peekable();
 
@@ -835,7 +980,7 @@ impl<'a> Parser<'a> {
                         }
                     }
 
-                    let arg_value = Self::parse_tokens_to_css_value(func_tokens)
+                    let arg_value = Self::parse_nested_css_value(func_tokens, depth + 1)
                         .map_err(|e| e.with_context("parse function args"))?;
 
                     let args = match arg_value {
@@ -859,6 +1004,69 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Formats the selector as it would be written in a stylesheet.
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let is_universal = self.tag.is_none()
+            && self.id.is_none()
+            && self.classes.is_empty()
+            && self.attributes.is_empty()
+            && self.pseudo_class.is_none()
+            && self.pseudo_element.is_none();
+        if is_universal {
+            return f.write_str("*");
+        }
+
+        if let Some(tag) = &self.tag {
+            f.write_str(tag)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, "#{id}")?;
+        }
+        for class in &self.classes {
+            write!(f, ".{class}")?;
+        }
+        for attr in &self.attributes {
+            let op = match attr.operator {
+                AttributeOperator::Exists => {
+                    write!(f, "[{}]", attr.name)?;
+                    continue;
+                }
+                AttributeOperator::Equals => "=",
+                AttributeOperator::Includes => "~=",
+                AttributeOperator::DashMatch => "|=",
+                AttributeOperator::Prefix => "^=",
+                AttributeOperator::Suffix => "$=",
+                AttributeOperator::Substring => "*=",
+            };
+            write!(f, "[{}{}{:?}]", attr.name, op, attr.value)?;
+        }
+        if let Some(pseudo) = &self.pseudo_class {
+            write!(f, ":{pseudo}")?;
+        }
+        if let Some(pseudo) = &self.pseudo_element {
+            write!(f, "::{pseudo}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ComplexSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.parts.is_empty() {
+            return f.write_str("*");
+        }
+        // parts は右から左の順
+        for (i, part) in self.parts.iter().rev().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}", part.selector)?;
+        }
+        Ok(())
+    }
+}
+
 // ====================
 impl fmt::Display for CssNode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -83,6 +83,10 @@ pub struct Tokenizer<'a> {
 
     /// Current character under examination
     current: Option<char>,
+
+    /// Tokens already consumed but not yet returned, in reverse order
+    /// (the `(`, URL and `)` of an unquoted `url()`)
+    pending: Vec<Token>,
 }
 
 impl<'a> Tokenizer<'a> {
@@ -91,7 +95,11 @@ impl<'a> Tokenizer<'a> {
         let mut chars = input.chars();
         let current = chars.next();
 
-        Self { chars, current }
+        Self {
+            chars,
+            current,
+            pending: Vec::new(),
+        }
     }
 
     /// Advance to the next character.
@@ -115,6 +123,9 @@ impl<'a> Tokenizer<'a> {
     ///
     /// This is the main entry point used by the parser.
     pub fn next_token(&mut self) -> Token {
+        if let Some(token) = self.pending.pop() {
+            return token;
+        }
         let token = match self.peek() {
             Some(c) if c.is_whitespace() => self.consume_whitespace(),
             Some(c) if is_number_start(c, self.peek_next()) => self.consume_number_like(),
@@ -198,12 +209,53 @@ impl<'a> Tokenizer<'a> {
             }
         }
         if self.peek() == Some('(') {
+            if ident.eq_ignore_ascii_case("url") && !self.url_is_quoted() {
+                self.consume_unquoted_url();
+            }
             Token::Function(ident)
         } else {
             Token::Ident(ident)
         }
     }
 
+    /// Whether the argument of the `url(` at the current `(` starts with a quote.
+    fn url_is_quoted(&self) -> bool {
+        self.chars
+            .clone()
+            .find(|c| !c.is_whitespace())
+            .is_some_and(is_string_delimiter)
+    }
+
+    /// Consume the `(`, URL and `)` of an unquoted `url()`.
+    ///
+    /// The URL is queued as a `Token::String` between `Delim('(')` and
+    /// `Delim(')')`, so that `url(a.png)` reads like `url("a.png")`.
+    fn consume_unquoted_url(&mut self) {
+        self.bump(); // consume '('
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.bump();
+        }
+
+        let mut url = String::new();
+        while let Some(c) = self.peek() {
+            if c == ')' {
+                self.bump();
+                break;
+            }
+            if c == '\\' {
+                if let Some(escaped) = self.consume_escape() {
+                    url.push(escaped);
+                }
+                continue;
+            }
+            url.push(c);
+            self.bump();
+        }
+
+        let url = url.trim_end().to_string();
+        self.pending = vec![Token::Delim(')'), Token::String(url), Token::Delim('(')];
+    }
+
     fn consume_string_like(&mut self) -> Token {
         let quote = self.peek().unwrap(); // '"' or '\''
         self.bump(); // consume opening quote
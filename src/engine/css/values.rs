@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Unit {
     Px,
@@ -19,6 +21,51 @@ pub enum CssValue {
     List(Vec<CssValue>),             // e.g. 100px auto
 }
 
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Unit::Px => "px",
+            Unit::Em => "em",
+            Unit::Rem => "rem",
+            Unit::Percent => "%",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+        })
+    }
+}
+
+/// CSS の書き方で表示する（DevTools 用）
+impl fmt::Display for CssValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CssValue::Keyword(k) => f.write_str(k),
+            CssValue::Length(v, unit) => write!(f, "{v}{unit}"),
+            CssValue::Number(v) => write!(f, "{v}"),
+            CssValue::String(s) => write!(f, "{s:?}"),
+            CssValue::Color(s) => write!(f, "#{s}"),
+            CssValue::Function(name, args) => {
+                write!(f, "{name}(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                f.write_str(")")
+            }
+            CssValue::List(values) => {
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 impl CssValue {
     /// Colorの文字列からRGBAタプルを返す
     pub fn to_rgba_tuple(&self) -> Option<(u8, u8, u8, u8)> {
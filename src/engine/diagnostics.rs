@@ -0,0 +1,157 @@
+//! エンジンの警告とスクリプトの console 出力
+//!
+//! [`report`] で出したメッセージはログに流し、[`capture`] の中で出したものは
+//! 呼び出し元にも返す。WebView は文書を処理する間を capture で囲み、タブの
+//! コンソールに集める。
+
+use std::cell::RefCell;
+use std::fmt;
+
+/// メッセージの重さ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    /// `info` / `warning` / `error`（大文字小文字は区別しない）
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "info" | "log" => Some(Level::Info),
+            "warning" | "warn" => Some(Level::Warning),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    fn log_level(self) -> log::Level {
+        match self {
+            Level::Info => log::Level::Info,
+            Level::Warning => log::Level::Warn,
+            Level::Error => log::Level::Error,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        })
+    }
+}
+
+/// メッセージを出した処理
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+    Html,
+    Css,
+    /// スクリプトの console.* と捕まえられなかった例外
+    Script,
+    /// Content Security Policy や sandbox で止めたもの
+    Security,
+}
+
+impl Source {
+    /// ログの target にも使う名前
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Source::Html => "html",
+            Source::Css => "css",
+            Source::Script => "console",
+            Source::Security => "security",
+        }
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// コンソールに出す 1 件
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Message {
+    pub level: Level,
+    pub source: Source,
+    pub text: String,
+    /// 出したスクリプトやスタイルシートの名前（分かるときだけ）
+    pub location: Option<String>,
+}
+
+thread_local! {
+    /// capture の中で出されたメッセージ（capture の外なら None）
+    static CAPTURED: RefCell<Option<Vec<Message>>> = const { RefCell::new(None) };
+    /// with_location で指定された、今処理しているものの名前
+    static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// メッセージを出す
+pub fn report(level: Level, source: Source, text: impl Into<String>) {
+    let location = LOCATION.with(|location| location.borrow().clone());
+    report_message(Message {
+        level,
+        source,
+        text: text.into(),
+        location,
+    });
+}
+
+/// 出した場所を指定してメッセージを出す
+pub fn report_at(
+    level: Level,
+    source: Source,
+    location: impl Into<String>,
+    text: impl Into<String>,
+) {
+    report_message(Message {
+        level,
+        source,
+        text: text.into(),
+        location: Some(location.into()),
+    });
+}
+
+fn report_message(message: Message) {
+    match &message.location {
+        Some(location) => log::log!(
+            target: message.source.as_str(),
+            message.level.log_level(),
+            "{} ({})",
+            message.text,
+            location
+        ),
+        None => {
+            log::log!(target: message.source.as_str(), message.level.log_level(), "{}", message.text)
+        }
+    }
+
+    CAPTURED.with(|captured| {
+        if let Some(messages) = captured.borrow_mut().as_mut() {
+            messages.push(message);
+        }
+    });
+}
+
+/// f を実行し、その間に出されたメッセージを集めて返す
+///
+/// 入れ子にした capture のメッセージは内側だけが受け取る。
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<Message>) {
+    let outer = CAPTURED.with(|captured| captured.replace(Some(Vec::new())));
+    let result = f();
+    let messages = CAPTURED.with(|captured| captured.replace(outer));
+    (result, messages.unwrap_or_default())
+}
+
+/// f の間に出されたメッセージの場所を location にする（スクリプトの名前など）
+pub fn with_location<R>(location: &str, f: impl FnOnce() -> R) -> R {
+    let outer = LOCATION.with(|current| current.replace(Some(location.to_string())));
+    let result = f();
+    LOCATION.with(|current| current.replace(outer));
+    result
+}
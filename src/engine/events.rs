@@ -0,0 +1,198 @@
+//! DOM イベントの配送
+//!
+//! イベントはルートから対象へ向かう capture、対象での at-target、対象から
+//! ルートへ戻る bubble の順に、途中の要素に登録されたリスナーへ届ける。
+//! 要素はパス（DOM と InfoNode で共通の子インデックス）で表す。
+//!
+//! リンクの移動やフォームの送信などの既定の動作は呼び出し側が行い、
+//! [`dispatch`] が false を返した（preventDefault された）ときは行わない。
+
+use std::collections::HashMap;
+
+use super::input::{self, HitItem};
+use super::layouter::types::NodeKind;
+
+/// イベントの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventType {
+    Click,
+    Input,
+    KeyDown,
+    Scroll,
+}
+
+impl EventType {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Click => "click",
+            Self::Input => "input",
+            Self::KeyDown => "keydown",
+            Self::Scroll => "scroll",
+        }
+    }
+
+    /// 対象からルートへ bubble するか（要素の scroll は bubble しない）
+    pub fn bubbles(self) -> bool {
+        !matches!(self, Self::Scroll)
+    }
+
+    /// preventDefault で既定の動作を止められるか
+    pub fn cancelable(self) -> bool {
+        matches!(self, Self::Click | Self::KeyDown)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventPhase {
+    Capturing,
+    AtTarget,
+    Bubbling,
+}
+
+/// リスナーに渡すイベント
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub event_type: EventType,
+    /// イベントの対象のパス
+    pub target: Vec<usize>,
+    /// 今リスナーを呼んでいる要素のパス
+    pub current_target: Vec<usize>,
+    pub phase: EventPhase,
+    /// keydown のキー（KeyboardEvent.key と同じ名前）
+    pub key: Option<String>,
+    default_prevented: bool,
+    propagation_stopped: bool,
+}
+
+impl Event {
+    pub fn new(event_type: EventType, target: Vec<usize>) -> Self {
+        Self {
+            event_type,
+            current_target: target.clone(),
+            target,
+            phase: EventPhase::AtTarget,
+            key: None,
+            default_prevented: false,
+            propagation_stopped: false,
+        }
+    }
+
+    pub fn key_down(target: Vec<usize>, key: impl Into<String>) -> Self {
+        Self {
+            key: Some(key.into()),
+            ..Self::new(EventType::KeyDown, target)
+        }
+    }
+
+    /// 既定の動作を止める（cancelable でなければ何もしない）
+    pub fn prevent_default(&mut self) {
+        if self.event_type.cancelable() {
+            self.default_prevented = true;
+        }
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.default_prevented
+    }
+
+    /// ここから先の要素にはイベントを届けない
+    pub fn stop_propagation(&mut self) {
+        self.propagation_stopped = true;
+    }
+}
+
+pub type Listener = Box<dyn FnMut(&mut Event)>;
+
+/// 要素ごとのリスナー
+#[derive(Default)]
+pub struct EventListeners {
+    listeners: HashMap<(Vec<usize>, EventType), Vec<(bool, Listener)>>,
+}
+
+impl EventListeners {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// path の要素にリスナーを登録する（capture なら capture フェーズで呼ぶ）
+    pub fn add(
+        &mut self,
+        path: Vec<usize>,
+        event_type: EventType,
+        capture: bool,
+        listener: impl FnMut(&mut Event) + 'static,
+    ) {
+        self.listeners
+            .entry((path, event_type))
+            .or_default()
+            .push((capture, Box::new(listener)));
+    }
+
+    pub fn clear(&mut self) {
+        self.listeners.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    /// path の要素のリスナーのうち、phase で呼ぶものを登録順に呼ぶ
+    fn invoke(&mut self, path: &[usize], phase: EventPhase, event: &mut Event) {
+        let Some(listeners) = self.listeners.get_mut(&(path.to_vec(), event.event_type)) else {
+            return;
+        };
+
+        event.current_target = path.to_vec();
+        event.phase = phase;
+        for (capture, listener) in listeners {
+            let called = match phase {
+                EventPhase::Capturing => *capture,
+                EventPhase::AtTarget => true,
+                EventPhase::Bubbling => !*capture,
+            };
+            if called {
+                listener(event);
+            }
+        }
+    }
+}
+
+/// event をリスナーに届ける。既定の動作をしてよければ true
+pub fn dispatch(listeners: &mut EventListeners, event: &mut Event) -> bool {
+    let target = event.target.clone();
+    // ルートから対象の親まで
+    let ancestors: Vec<&[usize]> = (0..target.len()).map(|len| &target[..len]).collect();
+
+    for path in &ancestors {
+        if event.propagation_stopped {
+            break;
+        }
+        listeners.invoke(path, EventPhase::Capturing, event);
+    }
+
+    if !event.propagation_stopped {
+        listeners.invoke(&target, EventPhase::AtTarget, event);
+    }
+
+    if event.event_type.bubbles() {
+        for path in ancestors.iter().rev() {
+            if event.propagation_stopped {
+                break;
+            }
+            listeners.invoke(path, EventPhase::Bubbling, event);
+        }
+    }
+
+    event.current_target = target;
+    !event.default_prevented
+}
+
+/// ヒットパスからイベントの対象（最も内側の要素）のパスを求める
+///
+/// 文字列（Text ノード）に当たったときはそれを含む要素が対象になる。
+pub fn target_from_hits(hit_path: &[HitItem]) -> Option<Vec<usize>> {
+    hit_path
+        .iter()
+        .position(|item| matches!(item.info.kind, NodeKind::Container { .. }))
+        .map(|i| input::node_path(hit_path, i))
+}
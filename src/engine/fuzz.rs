@@ -0,0 +1,93 @@
+//! ファジング用の入口
+//!
+//! 任意のバイト列を HTML や CSS として、トークナイザー、パーサー、スタイルの解決、
+//! レイアウトツリーの構築まで通す。どんな入力でもパニックせずに戻ることを確かめるためのもので、
+//! `fuzz/` の cargo-fuzz のターゲットと、見つかった入力を残す回帰テストから呼ぶ。
+//!
+//! 結果は捨てる。文字の計測はフォントに頼らない [`FallbackTextMeasurer`] で行う。
+
+use crate::engine::bridge::text::FallbackTextMeasurer;
+use crate::engine::css::media::{ColorScheme, MediaContext};
+use crate::engine::css::parser::{CssNode, Parser as CssParser};
+use crate::engine::diagnostics;
+use crate::engine::html::parser::{DomTree, Parser as HtmlParser};
+use crate::engine::layouter::{
+    self,
+    css_resolver::{CssResolver, ResolvedStyles},
+    types::TextStyle,
+    ua,
+};
+
+/// CSS を当てる文書（fuzz_parse_css で使う）
+const STYLED_DOCUMENT: &str = r#"<!DOCTYPE html>
+<html lang="en"><head><title>t</title></head>
+<body class="page">
+<div id="main" class="a b" data-x="1"><p>text <a href="/">link</a> <span>span</span></p>
+<ul><li>one</li><li class="b">two</li></ul>
+<input type="text" value="v"><button>go</button></div>
+</body></html>"#;
+
+/// data を HTML として読み、`<style>` を当ててレイアウトツリーまで作る
+pub fn fuzz_parse_html(data: &[u8]) {
+    let html = String::from_utf8_lossy(data);
+    let ((), _messages) = diagnostics::capture(|| {
+        let dom = HtmlParser::new(&html).parse();
+        let _ = dom.to_string();
+
+        let mut styles = ua::user_agent_styles(&MediaContext::default());
+        for css in dom.collect_text_by_tag("style") {
+            if let Ok(sheet) = CssParser::new(&css).parse() {
+                styles.extend(CssResolver::resolve_with_media(
+                    &sheet,
+                    &MediaContext::default(),
+                ));
+            }
+        }
+        build_layout(&dom, &styles);
+    });
+}
+
+/// data を CSS として読み、明るい配色と暗い配色の両方で決まった文書に当てる
+pub fn fuzz_parse_css(data: &[u8]) {
+    let css = String::from_utf8_lossy(data);
+    let ((), _messages) = diagnostics::capture(|| {
+        let Ok(sheet) = CssParser::new(&css).parse() else {
+            return;
+        };
+        let _ = sheet.to_string();
+
+        let dom = HtmlParser::new(STYLED_DOCUMENT).parse();
+        for color_scheme in [ColorScheme::Light, ColorScheme::Dark] {
+            let mut styles = ua::user_agent_styles(&MediaContext::default());
+            styles.extend(resolve(
+                &sheet,
+                MediaContext {
+                    color_scheme,
+                    ..Default::default()
+                },
+            ));
+            build_layout(&dom, &styles);
+        }
+    });
+}
+
+fn resolve(sheet: &CssNode, media: MediaContext) -> ResolvedStyles {
+    CssResolver::resolve_with_media(sheet, &media)
+}
+
+fn build_layout(dom: &DomTree, styles: &ResolvedStyles) {
+    let _ = layouter::build_layout_and_info(
+        &dom.root,
+        styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+}
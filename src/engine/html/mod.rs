@@ -1,4 +1,5 @@
 pub mod parser;
+pub mod preload;
 pub mod tokenizer;
 pub mod util;
 
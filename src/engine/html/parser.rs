@@ -1,3 +1,4 @@
+use crate::engine::diagnostics::{self, Level, Source};
 use crate::engine::html::tokenizer::{Attribute, Token, Tokenizer};
 use crate::engine::html::util as html_util;
 use crate::engine::tree::*;
@@ -68,10 +69,49 @@ impl HtmlNodeType {
             _ => false,
         }
     }
+
+    /// 文字列などヒープに持っている分のおおよその大きさ（バイト）
+    pub fn heap_size(&self) -> usize {
+        match self {
+            HtmlNodeType::Element {
+                tag_name,
+                attributes,
+            } => {
+                tag_name.capacity()
+                    + attributes
+                        .iter()
+                        .map(|a| std::mem::size_of::<Attribute>() + a.name.len() + a.value.len())
+                        .sum::<usize>()
+            }
+            HtmlNodeType::Text(text) | HtmlNodeType::Comment(text) => text.capacity(),
+            HtmlNodeType::Doctype {
+                name,
+                public_id,
+                system_id,
+            } => [name, public_id, system_id]
+                .into_iter()
+                .flatten()
+                .map(String::capacity)
+                .sum(),
+            HtmlNodeType::InvalidNode(_, text) => text.capacity(),
+        }
+    }
 }
 
 pub type DomTree = Tree<HtmlNodeType>;
 
+/// DOM のおおよその大きさ（バイト）
+pub fn dom_memory_usage(dom: &DomTree) -> usize {
+    let mut bytes = 0;
+    dom.traverse(|node| {
+        let node = node.borrow();
+        bytes += std::mem::size_of::<TreeNode<HtmlNodeType>>()
+            + node.children().len() * std::mem::size_of::<NodeRef<HtmlNodeType>>()
+            + node.value.heap_size();
+    });
+    bytes
+}
+
 impl DomTree {
     /// Returns all elements with the given tag name
     pub fn get_elements_by_tag_name(&self, tag_name: &str) -> Vec<NodeRef<HtmlNodeType>> {
@@ -130,8 +170,12 @@ impl DomTree {
             HtmlNodeType::Element { .. } => {
                 // remove all children and add a single Text node
                 n.clear_children();
-                let text_node = TreeNode::new(HtmlNodeType::Text(new_text.to_string()));
-                TreeNode::add_child(node, text_node);
+                // add_child が node を借りるので先に手放す
+                drop(n);
+                if !new_text.is_empty() {
+                    let text_node = TreeNode::new(HtmlNodeType::Text(new_text.to_string()));
+                    TreeNode::add_child(node, text_node);
+                }
             }
             _ => { /* do nothing */ }
         }
@@ -167,6 +211,12 @@ impl DomTree {
     }
 }
 
+/// 要素を入れ子にできる深さ
+///
+/// これより深い要素は、この深さの要素の子として兄弟に並べる。深すぎる木で
+/// スタイルの計算やレイアウトの再帰がスタックを使い切らないようにする。
+pub const MAX_TREE_DEPTH: usize = 512;
+
 pub struct Parser<'a> {
     tokenizer: Tokenizer<'a>,
     tree: DomTree,
@@ -189,6 +239,22 @@ impl<'a> Parser<'a> {
     }
 
     pub fn parse(&mut self) -> DomTree {
+        self.process_tokens();
+        self.autofill_elements();
+
+        self.tree.clone()
+    }
+
+    /// innerHTML などの断片をパースし、最上位のノードを返す
+    ///
+    /// html, head, body の補完はしない。返したノードは呼び出し側で別の親に繋ぐ。
+    pub fn parse_fragment(&mut self) -> Vec<NodeRef<HtmlNodeType>> {
+        self.process_tokens();
+
+        self.tree.root.borrow().children().to_vec()
+    }
+
+    fn process_tokens(&mut self) {
         while let Some(token) = self.tokenizer.next_token() {
             log::debug!(target:"HtmlParser::Token" ,"Processing token: {token:?}");
             match token {
@@ -199,9 +265,6 @@ impl<'a> Parser<'a> {
                 Token::Text(_) => self.handle_text(token),
             }
         }
-        self.autofill_elements();
-
-        self.tree.clone()
     }
 
     fn handle_start_tag(&mut self, token: Token) {
@@ -211,14 +274,18 @@ impl<'a> Parser<'a> {
             self_closing,
         } = token
         {
-            let mut parent = Rc::clone(self.stack.last().unwrap());
             if self.special_text_mode.is_some() {
                 // TODO:
                 // attributes, self_closing
-                TreeNode::add_child_value(&parent, HtmlNodeType::Text(format!("<{}>", name)));
+                TreeNode::add_child_value(
+                    &self.insertion_parent(),
+                    HtmlNodeType::Text(format!("<{}>", name)),
+                );
                 return;
             }
 
+            let mut parent = self.current_node();
+
             while self.check_start_tag_with_invalid_nesting(&name, &parent) {
                 if let HtmlNodeType::Element { tag_name, .. } = &parent.borrow().value {
                     log::info!(target:"HtmlParser::AutoClosing" ,"Auto-closing tag: <{}> to allow <{}> inside it.", tag_name, name);
@@ -226,11 +293,11 @@ impl<'a> Parser<'a> {
                         name: tag_name.clone(),
                     });
                 }
-                parent = Rc::clone(self.stack.last().unwrap());
+                parent = self.current_node();
             }
 
             let new_node = TreeNode::add_child_value(
-                &parent,
+                &self.insertion_parent(),
                 HtmlNodeType::Element {
                     tag_name: name.clone(),
                     attributes: attributes.clone(),
@@ -259,14 +326,20 @@ impl<'a> Parser<'a> {
             }
 
             if self.special_text_mode.is_some() {
-                let parent = Rc::clone(self.stack.last().unwrap());
-                TreeNode::add_child_value(&parent, HtmlNodeType::Text(format!("</{}>", name)));
+                TreeNode::add_child_value(
+                    &self.insertion_parent(),
+                    HtmlNodeType::Text(format!("</{}>", name)),
+                );
                 return;
             }
 
             let name = name.clone();
             if self.tag_stack.contains(&name) {
-                while let Some(top) = self.stack.pop() {
+                // ルートの Document は取り除かない
+                while self.stack.len() > 1 {
+                    let Some(top) = self.stack.pop() else {
+                        break;
+                    };
                     if let HtmlNodeType::Element { tag_name, .. } = &top.borrow().value {
                         self.tag_stack.pop();
                         if tag_name == &name {
@@ -274,26 +347,34 @@ impl<'a> Parser<'a> {
                             break;
                         } else {
                             log::debug!(target:"HtmlParser::Stack" ,"Stack len: {}, Unmatched end tag: </{}>, Find <{}>", self.stack.len(), name, tag_name);
+                            diagnostics::report(
+                                Level::Info,
+                                Source::Html,
+                                format!("</{}> closed an unclosed <{}>", name, tag_name),
+                            );
                         }
                     }
                 }
             } else {
-                let parent = Rc::clone(self.stack.last().unwrap());
                 TreeNode::add_child_value(
-                    &parent,
+                    &self.insertion_parent(),
                     HtmlNodeType::InvalidNode(
                         token,
                         format!("No matching start tag for </{}>", name),
                     ),
                 );
-                log::debug!(target:"HtmlParser::Invalid" ,"Invalid end tag: </{}>", name);
+                diagnostics::report(
+                    Level::Warning,
+                    Source::Html,
+                    format!("Ignored </{}> without a matching start tag", name),
+                );
             }
         }
     }
 
     fn handle_text(&mut self, token: Token) {
         if let Token::Text(data) = token {
-            let parent = Rc::clone(self.stack.last().unwrap());
+            let parent = self.insertion_parent();
 
             // special mode 中はそのままテキスト追加
             if self.special_text_mode.is_some() {
@@ -322,8 +403,7 @@ impl<'a> Parser<'a> {
 
     fn handle_comment(&mut self, token: Token) {
         if let Token::Comment(data) = token {
-            let parent = Rc::clone(self.stack.last().unwrap());
-            TreeNode::add_child_value(&parent, HtmlNodeType::Comment(data));
+            TreeNode::add_child_value(&self.insertion_parent(), HtmlNodeType::Comment(data));
         }
     }
 
@@ -335,9 +415,8 @@ impl<'a> Parser<'a> {
             ..
         } = token
         {
-            let parent = Rc::clone(self.stack.last().unwrap());
             TreeNode::add_child_value(
-                &parent,
+                &self.insertion_parent(),
                 HtmlNodeType::Doctype {
                     name,
                     public_id,
@@ -347,6 +426,19 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// 開いている要素のうちいちばん内側のもの（なければ Document）
+    fn current_node(&self) -> Rc<RefCell<TreeNode<HtmlNodeType>>> {
+        Rc::clone(self.stack.last().unwrap_or(&self.tree.root))
+    }
+
+    /// 次のノードを子として足す先（[`MAX_TREE_DEPTH`] より深くはしない）
+    fn insertion_parent(&self) -> Rc<RefCell<TreeNode<HtmlNodeType>>> {
+        let depth = (self.stack.len().max(1) - 1).min(MAX_TREE_DEPTH - 1);
+        self.stack
+            .get(depth)
+            .map_or_else(|| Rc::clone(&self.tree.root), Rc::clone)
+    }
+
     fn check_start_tag_with_invalid_nesting(
         &self,
         name: &String,
@@ -396,7 +488,7 @@ impl<'a> Parser<'a> {
 
     /// DOCTYPE宣言、html, head, body 要素が存在しない場合に補完する
     fn autofill_elements(&mut self) {
-        let root = Rc::clone(&self.stack[0]);
+        let root = Rc::clone(&self.tree.root);
         let mut has_doctype = false;
         let mut has_html = false;
         let mut has_head = false;
@@ -0,0 +1,192 @@
+//! # 先読みスキャナー
+//!
+//! DOM を組み立てる前に HTML のトークン列をざっと眺め、文書が読むことになる
+//! スタイルシート、スクリプト、画像と `<link rel="preload">` を見つける。
+//! 見つけたものは DOM の構築やレイアウトを待たずに取りに行ける。
+//! `<link rel="dns-prefetch">` と `<link rel="preconnect">` のオリジンも集め、
+//! 名前解決や接続を先に済ませられるようにする。
+//!
+//! - `<script>` と `<style>` の中身はタグに見えても読まない
+//! - 最初の `<base href>` より後の URL はそれを基準に解決する
+//! - `<meta http-equiv="Content-Security-Policy">` より後は読まない（許されるか分からないため）
+//! - 取りこぼしや余分に見つけたものがあっても、パーサーが改めて要求するので結果は変わらない
+
+use url::Url;
+
+use super::tokenizer::{Token, Tokenizer};
+use crate::engine::diagnostics;
+use crate::engine::script;
+
+/// 先読みするものの種類（`<link rel="preload">` の as 属性）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PreloadDestination {
+    Style,
+    Script,
+    Image,
+    Font,
+}
+
+impl PreloadDestination {
+    /// as 属性の値。このブラウザが読まない種類は None
+    pub fn from_as(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "style" => Some(Self::Style),
+            "script" => Some(Self::Script),
+            "image" => Some(Self::Image),
+            "font" => Some(Self::Font),
+            _ => None,
+        }
+    }
+}
+
+/// 先読みする URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreloadRequest {
+    pub url: Url,
+    pub destination: PreloadDestination,
+    /// 要素の nonce 属性（Content Security Policy の判定に使う）
+    pub nonce: Option<String>,
+}
+
+/// 接続を先に用意するオリジン（URL はオリジンだけを残したもの）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceHint {
+    /// `<link rel="dns-prefetch">`
+    DnsPrefetch(Url),
+    /// `<link rel="preconnect">`
+    Preconnect(Url),
+}
+
+/// 先読みスキャナーが見つけたもの
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreloadScan {
+    /// 文書順の先読みする URL
+    pub requests: Vec<PreloadRequest>,
+    /// 文書順の接続のヒント
+    pub hints: Vec<ResourceHint>,
+}
+
+/// html の中で document_url の文書が読むものを文書順に集める
+///
+/// http と https の URL だけを返し、同じものは最初の 1 回だけにする。
+pub fn scan(html: &str, document_url: &Url) -> PreloadScan {
+    // 構文エラーはパーサーが改めて報告するのでここでは捨てる
+    let (found, _) = diagnostics::capture(|| scan_tokens(html, document_url));
+    found
+}
+
+fn scan_tokens(html: &str, document_url: &Url) -> PreloadScan {
+    let mut tokenizer = Tokenizer::new(html);
+    let mut base_url: Option<Url> = None;
+    let mut requests: Vec<PreloadRequest> = Vec::new();
+    let mut hints: Vec<ResourceHint> = Vec::new();
+    // 中身を読み飛ばしている <script> / <style> の名前
+    let mut raw_text: Option<String> = None;
+
+    while let Some(token) = tokenizer.next_token() {
+        let (name, attributes, self_closing) = match token {
+            Token::StartTag { .. } if raw_text.is_some() => continue,
+            Token::StartTag {
+                name,
+                attributes,
+                self_closing,
+            } => (name.to_ascii_lowercase(), attributes, self_closing),
+            Token::EndTag { name } => {
+                if raw_text
+                    .as_deref()
+                    .is_some_and(|raw| name.eq_ignore_ascii_case(raw))
+                {
+                    raw_text = None;
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        if matches!(name.as_str(), "script" | "style") && !self_closing {
+            raw_text = Some(name.clone());
+        }
+        let attr = |name: &str| {
+            attributes
+                .iter()
+                .find(|a| a.name.eq_ignore_ascii_case(name))
+                .map(|a| a.value.as_str())
+        };
+
+        let (src, destination) = match name.as_str() {
+            "base" => {
+                if base_url.is_none()
+                    && let Some(href) = attr("href")
+                {
+                    base_url = document_url.join(href.trim()).ok();
+                }
+                continue;
+            }
+            "meta" => {
+                if attr("http-equiv")
+                    .is_some_and(|v| v.trim().eq_ignore_ascii_case("content-security-policy"))
+                {
+                    break;
+                }
+                continue;
+            }
+            "link" => {
+                let rel = attr("rel").unwrap_or_default().to_ascii_lowercase();
+                let rel: Vec<&str> = rel.split_ascii_whitespace().collect();
+                if let Some(origin) = attr("href")
+                    .filter(|_| rel.contains(&"preconnect") || rel.contains(&"dns-prefetch"))
+                    .and_then(|href| resolve(base_url.as_ref(), document_url, href))
+                    .and_then(|url| Url::parse(&url.origin().ascii_serialization()).ok())
+                {
+                    let hint = if rel.contains(&"preconnect") {
+                        ResourceHint::Preconnect(origin)
+                    } else {
+                        ResourceHint::DnsPrefetch(origin)
+                    };
+                    if !hints.contains(&hint) {
+                        hints.push(hint);
+                    }
+                }
+                let destination = if rel.contains(&"stylesheet") && !rel.contains(&"alternate") {
+                    Some(PreloadDestination::Style)
+                } else if rel.contains(&"preload") {
+                    attr("as").and_then(PreloadDestination::from_as)
+                } else {
+                    None
+                };
+                match destination {
+                    Some(destination) => (attr("href"), destination),
+                    None => continue,
+                }
+            }
+            "script" if script::is_classic_script_type(attr("type")) => {
+                (attr("src"), PreloadDestination::Script)
+            }
+            "img" => (attr("src"), PreloadDestination::Image),
+            _ => continue,
+        };
+
+        let Some(url) = src.and_then(|src| resolve(base_url.as_ref(), document_url, src)) else {
+            continue;
+        };
+        if requests.iter().any(|r| r.url == url) {
+            continue;
+        }
+        requests.push(PreloadRequest {
+            url,
+            destination,
+            nonce: attr("nonce").map(str::to_string),
+        });
+    }
+
+    PreloadScan { requests, hints }
+}
+
+/// 属性の URL を解決する（http と https でなければ None）
+fn resolve(base_url: Option<&Url>, document_url: &Url, src: &str) -> Option<Url> {
+    let src = src.trim();
+    if src.is_empty() {
+        return None;
+    }
+    let url = base_url.unwrap_or(document_url).join(src).ok()?;
+    matches!(url.scheme(), "http" | "https").then_some(url)
+}
@@ -1,4 +1,5 @@
 use super::util::decode_entity;
+use crate::engine::diagnostics::{self, Level, Source};
 
 /// Represents a single HTML attribute
 #[derive(Debug, Clone, PartialEq)]
@@ -126,6 +127,15 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    /// Returns whether the unread input starts with `word` (ASCII, case-insensitive)
+    ///
+    /// Only the next `word.len()` bytes are compared, so this stays cheap on long input.
+    fn remaining_starts_with_ignore_case(&self, word: &str) -> bool {
+        self.input
+            .get(self.pos..self.pos + word.len())
+            .is_some_and(|s| s.eq_ignore_ascii_case(word))
+    }
+
     /// Emits the current token and clears the buffer
     fn commit_token(&mut self) {
         self.token = self.current_token.take();
@@ -201,7 +211,14 @@ impl<'a> Tokenizer<'a> {
                 TokenizerState::EndTagOpen => self.state_end_tag_open(c),
                 _ if self.state.is_comment() => self.state_comment(c),
                 _ => {
-                    log::error!(target:"HtmlTokenizer::State", "Unimplemented state: {:?}, returning to Data state", self.state);
+                    diagnostics::report(
+                        Level::Warning,
+                        Source::Html,
+                        format!(
+                            "Unimplemented tokenizer state {:?}, returning to Data state",
+                            self.state
+                        ),
+                    );
                     self.state = TokenizerState::Data;
                 }
             }
@@ -277,7 +294,7 @@ impl<'a> Tokenizer<'a> {
                 if self.input[self.pos..].starts_with('-') {
                     self.pos += 1;
                     self.state = TokenizerState::CommentStartDash;
-                } else if self.input[self.pos..].to_lowercase().starts_with("doctype") {
+                } else if self.remaining_starts_with_ignore_case("doctype") {
                     self.pos += 7;
                     self.state = TokenizerState::Doctype;
                     self.current_token = Some(Token::Doctype {
@@ -528,8 +545,8 @@ impl<'a> Tokenizer<'a> {
             c if c.is_whitespace() => match self.state {
                 TokenizerState::Doctype => self.state = TokenizerState::DoctypeName,
                 TokenizerState::DoctypeName => {
-                    if self.input[self.pos..].to_lowercase().starts_with("public")
-                        || self.input[self.pos..].to_lowercase().starts_with("system")
+                    if self.remaining_starts_with_ignore_case("public")
+                        || self.remaining_starts_with_ignore_case("system")
                     {
                         self.pos += 6;
                         self.state = TokenizerState::BeforeDoctypePublicId;
@@ -0,0 +1,88 @@
+//! キーボードフォーカスの移動
+//!
+//! Tab キーで順にたどる要素（リンク、フォームの部品、tabindex のある要素）を
+//! DOM から集める。パスは DOM と InfoNode で共通の子インデックス。
+
+use ui_layout::LayoutNode;
+
+use crate::engine::html::HtmlNodeType;
+use crate::engine::tree::NodeRef;
+
+/// tabindex 属性の値（数値でなければ None）
+pub fn tab_index(node: &HtmlNodeType) -> Option<i32> {
+    node.get_attr("tabindex")?.trim().parse().ok()
+}
+
+/// クリックや Tab キーでフォーカスできる要素か
+pub fn is_focusable(node: &HtmlNodeType) -> bool {
+    if tab_index(node).is_some() {
+        return true;
+    }
+    match node.tag_name() {
+        Some("a" | "area") => node.has_attr("href"),
+        Some("input") => {
+            !node.has_attr("disabled")
+                && !node
+                    .get_attr("type")
+                    .is_some_and(|t| t.trim().eq_ignore_ascii_case("hidden"))
+        }
+        Some("button" | "select" | "textarea") => !node.has_attr("disabled"),
+        _ => false,
+    }
+}
+
+/// Tab キーでたどる順のパス
+///
+/// 正の tabindex を持つ要素が値の小さい順に先に来て、残りは文書順に並ぶ。
+/// tabindex が負の要素と、表示されていない要素（レイアウトの箱がない）は含めない。
+pub fn focus_order(root: &NodeRef<HtmlNodeType>, layout: &LayoutNode) -> Vec<Vec<usize>> {
+    let mut found = Vec::new();
+    collect_focusable(root, layout, &mut Vec::new(), &mut found);
+
+    // sort_by_key は安定なので、同じ tabindex の中では文書順のまま
+    found.sort_by_key(|&(index, _)| if index > 0 { index } else { i32::MAX });
+    found.into_iter().map(|(_, path)| path).collect()
+}
+
+fn collect_focusable(
+    node: &NodeRef<HtmlNodeType>,
+    layout: &LayoutNode,
+    path: &mut Vec<usize>,
+    found: &mut Vec<(i32, Vec<usize>)>,
+) {
+    if layout.layout_boxes.is_empty() {
+        return;
+    }
+
+    let node = node.borrow();
+    if is_focusable(&node.value) {
+        let index = tab_index(&node.value).unwrap_or(0);
+        if index >= 0 {
+            found.push((index, path.clone()));
+        }
+    }
+
+    for (i, (child, child_layout)) in node.children().iter().zip(&layout.children).enumerate() {
+        path.push(i);
+        collect_focusable(child, child_layout, path, found);
+        path.pop();
+    }
+}
+
+/// current の次（backward なら前）にフォーカスする要素
+///
+/// 端を越えるとページの外に出る（None）。current が None なら先頭（末尾）から。
+pub fn next_focus(
+    order: &[Vec<usize>],
+    current: Option<&[usize]>,
+    backward: bool,
+) -> Option<Vec<usize>> {
+    let position = current.and_then(|c| order.iter().position(|p| p == c));
+    let next = match (position, backward) {
+        (None, false) => Some(0),
+        (None, true) => order.len().checked_sub(1),
+        (Some(i), false) => Some(i + 1),
+        (Some(i), true) => i.checked_sub(1),
+    };
+    next.and_then(|i| order.get(i)).cloned()
+}
@@ -0,0 +1,159 @@
+//! タッチ操作（ドラッグでのスクロール、慣性スクロール、ピンチズーム）
+//!
+//! 座標はすべて CSS px。タッチ ID ごとの位置を追い、1 本指の移動はスクロール量、
+//! 2 本指の距離の変化はズーム倍率に変換する。指を離したときの速度が十分なら
+//! 慣性スクロール（fling）を始め、フレームごとに減速させる。
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 慣性スクロールの減速率（1 秒あたり）
+const FLING_FRICTION: f32 = 4.0;
+
+/// これより遅ければ慣性スクロールを始めない / 止める（px/s）
+const MIN_FLING_VELOCITY: f32 = 50.0;
+
+/// 速度の推定に使う移動の重み（直近の移動ほど重くする）
+const VELOCITY_SMOOTHING: f32 = 0.6;
+
+/// 指を止めてからこれ以上経って離したら慣性スクロールしない
+const FLING_HOLD_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// タッチ操作から得られる入力
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// (dx, dy) だけスクロールする（正の値で下・右へ）
+    Scroll { dx: f32, dy: f32 },
+    /// ズーム倍率を scale 倍にする
+    Zoom { scale: f32 },
+}
+
+#[derive(Debug, Default)]
+pub struct TouchTracker {
+    /// タッチ ID → 現在位置
+    touches: HashMap<u64, (f32, f32)>,
+    /// 1 本指ドラッグの速度（スクロール方向, px/s）
+    velocity: (f32, f32),
+    last_move: Option<Instant>,
+    /// 慣性スクロールの速度（動いていなければ None）
+    fling: Option<(f32, f32)>,
+    last_fling_frame: Option<Instant>,
+    /// 最後に触れていた位置（慣性スクロールの対象を決める）
+    last_position: (f32, f32),
+}
+
+impl TouchTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn touch_started(&mut self, id: u64, pos: (f32, f32), now: Instant) {
+        // 触れたら慣性スクロールは止める
+        self.fling = None;
+        self.last_fling_frame = None;
+        self.velocity = (0.0, 0.0);
+        self.last_move = Some(now);
+        self.last_position = pos;
+        self.touches.insert(id, pos);
+    }
+
+    pub fn touch_moved(&mut self, id: u64, pos: (f32, f32), now: Instant) -> Option<Gesture> {
+        let prev = self.touches.insert(id, pos)?;
+
+        match self.touches.len() {
+            1 => {
+                // 指と逆向きにスクロールする
+                let dx = prev.0 - pos.0;
+                let dy = prev.1 - pos.1;
+
+                let dt = self
+                    .last_move
+                    .map(|last| now.saturating_duration_since(last).as_secs_f32())
+                    .unwrap_or(0.0);
+                if dt > 0.0 {
+                    let (vx, vy) = self.velocity;
+                    self.velocity = (
+                        vx + (dx / dt - vx) * VELOCITY_SMOOTHING,
+                        vy + (dy / dt - vy) * VELOCITY_SMOOTHING,
+                    );
+                }
+                self.last_move = Some(now);
+                self.last_position = pos;
+
+                Some(Gesture::Scroll { dx, dy })
+            }
+            2 => {
+                let other = self
+                    .touches
+                    .iter()
+                    .find(|(other_id, _)| **other_id != id)
+                    .map(|(_, p)| *p)?;
+
+                let before = distance(prev, other);
+                let after = distance(pos, other);
+                if before <= f32::EPSILON {
+                    return None;
+                }
+
+                Some(Gesture::Zoom {
+                    scale: after / before,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn touch_ended(&mut self, id: u64, now: Instant) {
+        let was_single = self.touches.len() == 1;
+        if self.touches.remove(&id).is_none() {
+            return;
+        }
+
+        // 1 本指で払うように離したときだけ慣性スクロールする
+        let held_still = self
+            .last_move
+            .is_some_and(|last| now.saturating_duration_since(last) > FLING_HOLD_TIMEOUT);
+        let (vx, vy) = self.velocity;
+        if was_single && !held_still && vx.hypot(vy) >= MIN_FLING_VELOCITY {
+            self.fling = Some(self.velocity);
+            self.last_fling_frame = Some(now);
+        }
+        self.velocity = (0.0, 0.0);
+    }
+
+    /// タッチがキャンセルされた（OS にジェスチャーを取られたなど）
+    pub fn touch_cancelled(&mut self, id: u64) {
+        self.touches.remove(&id);
+        self.velocity = (0.0, 0.0);
+    }
+
+    pub fn is_flinging(&self) -> bool {
+        self.fling.is_some()
+    }
+
+    /// 最後に触れていた位置
+    pub fn last_position(&self) -> (f32, f32) {
+        self.last_position
+    }
+
+    /// 慣性スクロールを 1 フレーム進め、このフレームのスクロール量を返す
+    pub fn step_fling(&mut self, now: Instant) -> Option<(f32, f32)> {
+        let (vx, vy) = self.fling?;
+
+        let dt = self
+            .last_fling_frame
+            .map(|last| now.saturating_duration_since(last).as_secs_f32())
+            .unwrap_or(0.0);
+        self.last_fling_frame = Some(now);
+
+        let decay = (-FLING_FRICTION * dt).exp();
+        let next = (vx * decay, vy * decay);
+        self.fling = (next.0.hypot(next.1) >= MIN_FLING_VELOCITY).then_some(next);
+
+        Some((vx * dt, vy * dt))
+    }
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
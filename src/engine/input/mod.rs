@@ -1,15 +1,103 @@
-use super::layouter::types::{InfoNode, NodeKind};
+pub mod focus;
+pub mod gesture;
+pub mod scroll;
+pub mod selection;
+pub mod spellcheck;
+pub mod text_edit;
+
+use super::layouter::types::{ContainerRole, InfoNode, NodeKind};
 use ui_layout::LayoutNode;
 
 /// ヒットしたノード情報
 pub struct HitItem<'a> {
     pub layout: &'a LayoutNode,
     pub info: &'a InfoNode,
+    /// 親の children 内での位置（ルートは 0）
+    pub index: usize,
 }
 
 /// ヒットパス（子→親の順）
 pub type HitPath<'a> = Vec<HitItem<'a>>;
 
+/// `hit_path[i]` のノードへのパス（ルートからの子インデックス）を返す
+pub fn node_path(hit_path: &[HitItem], i: usize) -> Vec<usize> {
+    // 末尾（ルート）は含めない
+    let end = hit_path.len().saturating_sub(1);
+    hit_path
+        .get(i..end)
+        .unwrap_or_default()
+        .iter()
+        .rev()
+        .map(|item| item.index)
+        .collect()
+}
+
+/// ヒットパスの中で最も内側のリンクの位置と href を返す
+pub fn find_link<'a>(hit_path: &[HitItem<'a>]) -> Option<(usize, &'a str)> {
+    hit_path
+        .iter()
+        .enumerate()
+        .find_map(|(i, item)| match &item.info.kind {
+            NodeKind::Container {
+                role: ContainerRole::Link { href },
+                ..
+            } => Some((i, href.as_str())),
+            _ => None,
+        })
+}
+
+/// ヒットパスの中で最も内側のテキスト入力欄の位置を返す
+pub fn find_text_input(hit_path: &[HitItem]) -> Option<usize> {
+    hit_path.iter().position(|item| {
+        matches!(
+            item.info.kind,
+            NodeKind::Container {
+                role: ContainerRole::TextInput { .. },
+                ..
+            }
+        )
+    })
+}
+
+/// ヒットパスの中で最も内側のボタンの位置を返す
+pub fn find_button(hit_path: &[HitItem]) -> Option<usize> {
+    hit_path.iter().position(|item| {
+        matches!(
+            item.info.kind,
+            NodeKind::Container {
+                role: ContainerRole::Button,
+                ..
+            }
+        )
+    })
+}
+
+/// ヒットパスの中で最も内側の `<details>` の `<summary>` の位置を返す
+pub fn find_summary(hit_path: &[HitItem]) -> Option<usize> {
+    hit_path.iter().position(|item| {
+        matches!(
+            item.info.kind,
+            NodeKind::Container {
+                role: ContainerRole::Summary { .. },
+                ..
+            }
+        )
+    })
+}
+
+/// ヒットパスの中で最も内側の `<iframe>` の位置を返す
+pub fn find_frame(hit_path: &[HitItem]) -> Option<usize> {
+    hit_path.iter().position(|item| {
+        matches!(
+            item.info.kind,
+            NodeKind::Container {
+                role: ContainerRole::Frame,
+                ..
+            }
+        )
+    })
+}
+
 /// x, y: グローバル座標
 pub fn hit_test<'a>(layout: &'a LayoutNode, info: &'a InfoNode, x: f32, y: f32) -> HitPath<'a> {
     // layout_boxes が空なら何もヒットしない
@@ -47,17 +135,28 @@ pub fn hit_test<'a>(layout: &'a LayoutNode, info: &'a InfoNode, x: f32, y: f32)
         }
 
         // 3. 子ノードを前面から探索
-        for (child_layout, child_info) in layout.children.iter().zip(&info.children).rev() {
+        for (i, (child_layout, child_info)) in
+            layout.children.iter().zip(&info.children).enumerate().rev()
+        {
             let mut path = hit_test(child_layout, child_info, local_x, local_y);
-            if !path.is_empty() {
+            if let Some(child) = path.last_mut() {
                 // 子がヒット → 自分を末尾に追加
-                path.push(HitItem { layout, info });
+                child.index = i;
+                path.push(HitItem {
+                    layout,
+                    info,
+                    index: 0,
+                });
                 return path;
             }
         }
 
         // 4. 子ノードに当たらなければこの box がヒット
-        return vec![HitItem { layout, info }];
+        return vec![HitItem {
+            layout,
+            info,
+            index: 0,
+        }];
     }
 
     // どの box にもヒットしなかった
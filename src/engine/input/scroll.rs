@@ -0,0 +1,237 @@
+//! スムーススクロール
+//!
+//! ホイールやキー入力では目標位置だけを更新し、実際のスクロール位置は
+//! フレームごとに目標へ指数平滑化で近づける。
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::engine::layouter::types::{InfoNode, NodeKind};
+use ui_layout::LayoutNode;
+
+/// 平滑化の速さ（1 秒あたりの減衰率）。大きいほど速く目標に着く
+pub const SMOOTHING_SPEED: f32 = 18.0;
+
+/// 目標との差がこれ未満になったら吸着して止める（px）
+const SNAP_DISTANCE: f32 = 0.5;
+
+/// 前フレームからの経過時間が分からないときに使う値
+const DEFAULT_FRAME_TIME: Duration = Duration::from_millis(16);
+
+/// スクロールコンテナごとの目標位置を持ち、フレームごとに近づける
+#[derive(Debug, Default)]
+pub struct SmoothScroller {
+    /// コンテナのパス（ルートからの子インデックス）→ 目標位置 (x, y)
+    targets: HashMap<Vec<usize>, (f32, f32)>,
+    last_frame: Option<Instant>,
+}
+
+impl SmoothScroller {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 目標位置（アニメーション中でなければ None）
+    pub fn target(&self, path: &[usize]) -> Option<(f32, f32)> {
+        self.targets.get(path).copied()
+    }
+
+    /// 目標位置を設定する
+    pub fn scroll_to(&mut self, path: &[usize], target: (f32, f32)) {
+        self.targets.insert(path.to_vec(), target);
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.targets.is_empty()
+    }
+
+    /// アニメーションを止める（現在位置はそのまま）
+    pub fn cancel(&mut self) {
+        self.targets.clear();
+        self.last_frame = None;
+    }
+
+    /// 目標位置をスクロール可能範囲に収める（レイアウトが変わったとき用）
+    pub fn clamp_targets(&mut self, layout: &LayoutNode, info: &InfoNode, viewport: (f32, f32)) {
+        self.targets.retain(|path, (target_x, target_y)| {
+            let Some((max_x, max_y)) = scroll_range_at(layout, info, path, viewport) else {
+                return false;
+            };
+            *target_x = target_x.clamp(0.0, max_x);
+            *target_y = target_y.clamp(0.0, max_y);
+            true
+        });
+    }
+
+    /// 1 フレーム進める。まだ目標に届いていないコンテナがあれば true を返す
+    pub fn step(&mut self, info: &mut InfoNode, now: Instant) -> bool {
+        if self.targets.is_empty() {
+            self.last_frame = None;
+            return false;
+        }
+
+        let dt = self
+            .last_frame
+            .map(|last| now.saturating_duration_since(last))
+            .unwrap_or(DEFAULT_FRAME_TIME)
+            .as_secs_f32();
+        self.last_frame = Some(now);
+
+        // 経過時間に依存しない指数平滑化: 残り距離が exp(-speed * dt) 倍になる
+        let keep = (-SMOOTHING_SPEED * dt).exp();
+
+        self.targets.retain(|path, &mut (target_x, target_y)| {
+            let Some(NodeKind::Container {
+                scroll_offset_x,
+                scroll_offset_y,
+                ..
+            }) = node_at_mut(info, path).map(|n| &mut n.kind)
+            else {
+                // コンテナが消えた
+                return false;
+            };
+
+            let mut done = true;
+            for (offset, target) in [(scroll_offset_x, target_x), (scroll_offset_y, target_y)] {
+                let next = target + (*offset - target) * keep;
+                if (next - target).abs() < SNAP_DISTANCE {
+                    *offset = target;
+                } else {
+                    *offset = next;
+                    done = false;
+                }
+            }
+
+            !done
+        });
+
+        if self.targets.is_empty() {
+            self.last_frame = None;
+        }
+
+        self.is_animating()
+    }
+}
+
+/// ページ全体のコンテンツの大きさ (width, height)
+pub fn root_content_size(layout: &LayoutNode) -> (f32, f32) {
+    let width = layout
+        .layout_boxes
+        .iter()
+        .map(|l| l.children_box.width)
+        .fold(0.0, f32::max);
+    let height = layout
+        .layout_boxes
+        .iter()
+        .map(|l| l.children_box.height)
+        .sum::<f32>();
+
+    (width, height)
+}
+
+/// ルート（ビューポート）のスクロール可能範囲 (max_x, max_y)
+pub fn root_scroll_range(layout: &LayoutNode, viewport: (f32, f32)) -> (f32, f32) {
+    let (content_width, content_height) = root_content_size(layout);
+
+    (
+        (content_width - viewport.0).max(0.0),
+        (content_height - viewport.1).max(0.0),
+    )
+}
+
+/// `overflow: auto | scroll` のコンテナのスクロール可能範囲 (max_x, max_y)
+///
+/// スクロールできない方向は 0 になる。
+pub fn container_scroll_range(layout: &LayoutNode, info: &InfoNode) -> (f32, f32) {
+    let NodeKind::Container {
+        scroll_x, scroll_y, ..
+    } = info.kind
+    else {
+        return (0.0, 0.0);
+    };
+    let Some(box_model) = layout.layout_boxes.first() else {
+        return (0.0, 0.0);
+    };
+
+    let max_x = (box_model.children_box.width - box_model.content_box.width).max(0.0);
+    let max_y = (box_model.children_box.height - box_model.content_box.height).max(0.0);
+
+    (
+        if scroll_x { max_x } else { 0.0 },
+        if scroll_y { max_y } else { 0.0 },
+    )
+}
+
+/// path のコンテナのスクロール可能範囲（ノードがなければ None）
+fn scroll_range_at(
+    layout: &LayoutNode,
+    info: &InfoNode,
+    path: &[usize],
+    viewport: (f32, f32),
+) -> Option<(f32, f32)> {
+    if path.is_empty() {
+        return Some(root_scroll_range(layout, viewport));
+    }
+
+    let mut layout = layout;
+    let mut info = info;
+    for &i in path {
+        layout = layout.children.get(i)?;
+        info = info.children.get(i)?;
+    }
+
+    Some(container_scroll_range(layout, info))
+}
+
+/// すべてのスクロール位置を `[0, コンテンツの大きさ - ビューポート]` に収める
+pub fn clamp_scroll_offsets(layout: &LayoutNode, info: &mut InfoNode, viewport: (f32, f32)) {
+    let range = root_scroll_range(layout, viewport);
+    clamp_offsets_in(layout, info, range);
+}
+
+fn clamp_offsets_in(layout: &LayoutNode, info: &mut InfoNode, (max_x, max_y): (f32, f32)) {
+    if let NodeKind::Container {
+        scroll_offset_x,
+        scroll_offset_y,
+        ..
+    } = &mut info.kind
+    {
+        *scroll_offset_x = scroll_offset_x.clamp(0.0, max_x);
+        *scroll_offset_y = scroll_offset_y.clamp(0.0, max_y);
+    }
+
+    for (child_layout, child_info) in layout.children.iter().zip(info.children.iter_mut()) {
+        let range = container_scroll_range(child_layout, child_info);
+        clamp_offsets_in(child_layout, child_info, range);
+    }
+}
+
+/// 同じ DOM から作り直したツリーにスクロール位置を引き継ぐ
+pub fn copy_scroll_offsets(from: &InfoNode, to: &mut InfoNode) {
+    if let (
+        NodeKind::Container {
+            scroll_offset_x: from_x,
+            scroll_offset_y: from_y,
+            ..
+        },
+        NodeKind::Container {
+            scroll_offset_x,
+            scroll_offset_y,
+            ..
+        },
+    ) = (&from.kind, &mut to.kind)
+    {
+        *scroll_offset_x = *from_x;
+        *scroll_offset_y = *from_y;
+    }
+
+    for (from_child, to_child) in from.children.iter().zip(to.children.iter_mut()) {
+        copy_scroll_offsets(from_child, to_child);
+    }
+}
+
+/// パス（ルートからの子インデックス）で InfoNode を辿る
+pub fn node_at_mut<'a>(info: &'a mut InfoNode, path: &[usize]) -> Option<&'a mut InfoNode> {
+    path.iter()
+        .try_fold(info, |node, &i| node.children.get_mut(i))
+}
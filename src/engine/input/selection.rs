@@ -0,0 +1,277 @@
+//! テキスト選択
+//!
+//! 選択範囲は InfoNode ツリー上の位置（Text ノードへの子インデックスのパス +
+//! バイトオフセット）で持つ。座標からの変換はヒットテストと計測済みの
+//! クラスタ位置（`MeasureCache.lines`）で行う。
+
+use std::ops::Range;
+
+use crate::engine::bridge::text::LineFragment;
+use crate::engine::layouter::types::{Color, InfoNode, NodeKind};
+use ui_layout::LayoutNode;
+
+/// 選択ハイライトの色
+pub const SELECTION_COLOR: Color = Color(179, 215, 255, 255);
+
+/// テキスト中の位置
+///
+/// `Ord` はパス → オフセットの辞書順で、文書順と一致する。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TextPosition {
+    /// ルートから Text ノードまでの子インデックス
+    pub path: Vec<usize>,
+    /// テキスト内のバイトオフセット
+    pub offset: usize,
+}
+
+/// 選択範囲（anchor: 選択開始位置, focus: 現在の位置）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub anchor: TextPosition,
+    pub focus: TextPosition,
+}
+
+impl Selection {
+    /// 幅 0 の選択を作る
+    pub fn collapsed(pos: TextPosition) -> Self {
+        Self {
+            anchor: pos.clone(),
+            focus: pos,
+        }
+    }
+
+    pub fn is_collapsed(&self) -> bool {
+        self.anchor == self.focus
+    }
+
+    /// 文書順に並べた (start, end)
+    pub fn ordered(&self) -> (&TextPosition, &TextPosition) {
+        if self.anchor <= self.focus {
+            (&self.anchor, &self.focus)
+        } else {
+            (&self.focus, &self.anchor)
+        }
+    }
+
+    /// `path` の Text ノードのうち選択されているバイト範囲（なければ None）
+    pub fn range_in(&self, path: &[usize], text_len: usize) -> Option<Range<usize>> {
+        let (start, end) = self.ordered();
+
+        if path < start.path.as_slice() || path > end.path.as_slice() {
+            return None;
+        }
+
+        let from = if start.path == path { start.offset } else { 0 };
+        let to = if end.path == path {
+            end.offset
+        } else {
+            text_len
+        };
+
+        (from < to).then_some(from..to.min(text_len))
+    }
+}
+
+/// x, y（グローバル座標）に最も近いテキスト位置を返す
+///
+/// テキストの外（行末の右や行間）を指している場合は、そのコンテナ内で
+/// 手前にある子に寄せて探す。
+pub fn text_position_at(
+    layout: &LayoutNode,
+    info: &InfoNode,
+    x: f32,
+    y: f32,
+) -> Option<TextPosition> {
+    let mut path = Vec::new();
+    let offset = position_in(layout, info, x, y, &mut path)?;
+
+    Some(TextPosition { path, offset })
+}
+
+fn position_in(
+    layout: &LayoutNode,
+    info: &InfoNode,
+    x: f32,
+    y: f32,
+    path: &mut Vec<usize>,
+) -> Option<usize> {
+    // 後ろの box が前面
+    for box_model in layout.layout_boxes.iter().rev() {
+        let rect = box_model.padding_box;
+
+        if x < rect.x || y < rect.y || x > rect.x + rect.width || y > rect.y + rect.height {
+            continue;
+        }
+
+        match &info.kind {
+            NodeKind::Text { text, measured, .. } => {
+                let local_x = x - rect.x;
+                let local_y = y - rect.y;
+
+                let Some(lines) = measured
+                    .as_ref()
+                    .map(|m| &m.lines)
+                    .filter(|l| !l.is_empty())
+                else {
+                    // 未計測なら前半/後半で決める
+                    return Some(if local_x < rect.width / 2.0 {
+                        0
+                    } else {
+                        text.len()
+                    });
+                };
+
+                let line_height = rect.height / lines.len() as f32;
+                let line_i =
+                    ((local_y / line_height).floor().max(0.0) as usize).min(lines.len() - 1);
+
+                return Some(lines[line_i].offset_at(local_x));
+            }
+
+            NodeKind::Container {
+                scroll_offset_x,
+                scroll_offset_y,
+                ..
+            } => {
+                let local_x = x - box_model.content_box.x + scroll_offset_x;
+                let local_y = y - box_model.content_box.y + scroll_offset_y;
+
+                // 子ノードを前面から探索
+                for (i, (child_layout, child_info)) in
+                    layout.children.iter().zip(&info.children).enumerate().rev()
+                {
+                    path.push(i);
+                    if let Some(offset) =
+                        position_in(child_layout, child_info, local_x, local_y, path)
+                    {
+                        return Some(offset);
+                    }
+                    path.pop();
+                }
+
+                // どの子にも当たらなければ、上端が手前にある最後の子に寄せる
+                for (i, (child_layout, child_info)) in
+                    layout.children.iter().zip(&info.children).enumerate().rev()
+                {
+                    let Some(child_rect) = child_layout.layout_boxes.last().map(|b| b.padding_box)
+                    else {
+                        continue;
+                    };
+                    if child_rect.y > local_y {
+                        continue;
+                    }
+
+                    let clamped_x = local_x.clamp(child_rect.x, child_rect.x + child_rect.width);
+                    let clamped_y = local_y.clamp(child_rect.y, child_rect.y + child_rect.height);
+
+                    path.push(i);
+                    if let Some(offset) =
+                        position_in(child_layout, child_info, clamped_x, clamped_y, path)
+                    {
+                        return Some(offset);
+                    }
+                    path.pop();
+                }
+
+                return None;
+            }
+        }
+    }
+
+    None
+}
+
+/// 選択されている文字列を返す
+///
+/// 別の行に置かれたテキスト同士の間には改行を入れる。
+pub fn selected_text(layout: &LayoutNode, info: &InfoNode, selection: &Selection) -> String {
+    let mut pieces = Vec::new();
+    collect_selected(layout, info, selection, 0.0, &mut Vec::new(), &mut pieces);
+
+    let mut out = String::new();
+    let mut prev_bottom: Option<f32> = None;
+    for (top, bottom, text) in pieces {
+        if prev_bottom.is_some_and(|b| top >= b - 0.5) {
+            out.push('\n');
+        }
+        out.push_str(text);
+        prev_bottom = Some(bottom);
+    }
+
+    out
+}
+
+/// 選択されたテキスト片を (上端, 下端, 文字列) として文書順に集める
+fn collect_selected<'a>(
+    layout: &LayoutNode,
+    info: &'a InfoNode,
+    selection: &Selection,
+    origin_y: f32,
+    path: &mut Vec<usize>,
+    out: &mut Vec<(f32, f32, &'a str)>,
+) {
+    match &info.kind {
+        NodeKind::Text { text, .. } => {
+            let Some(range) = selection.range_in(path, text.len()) else {
+                return;
+            };
+            let Some(selected) = text.get(range) else {
+                return;
+            };
+            let Some(rect) = layout.layout_boxes.first().map(|b| b.padding_box) else {
+                return;
+            };
+
+            let top = origin_y + rect.y;
+            out.push((top, top + rect.height, selected));
+        }
+
+        NodeKind::Container {
+            scroll_offset_y, ..
+        } => {
+            let Some(content_box) = layout.layout_boxes.first().map(|b| b.content_box) else {
+                return;
+            };
+            let child_origin_y = origin_y + content_box.y - scroll_offset_y;
+
+            for (i, (child_layout, child_info)) in
+                layout.children.iter().zip(&info.children).enumerate()
+            {
+                path.push(i);
+                collect_selected(
+                    child_layout,
+                    child_info,
+                    selection,
+                    child_origin_y,
+                    path,
+                    out,
+                );
+                path.pop();
+            }
+        }
+    }
+}
+
+/// 行ごとの選択ハイライト矩形 (x, y, width, height) をテキストボックス基準で返す
+pub fn highlight_rects(
+    lines: &[LineFragment],
+    line_height: f32,
+    range: &Range<usize>,
+) -> Vec<(f32, f32, f32, f32)> {
+    lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let start = range.start.max(line.start);
+            let end = range.end.min(line.end);
+            if start >= end {
+                return None;
+            }
+
+            let x0 = line.x_at(start);
+            let x1 = line.x_at(end);
+
+            Some((x0, line_height * i as f32, x1 - x0, line_height))
+        })
+        .collect()
+}
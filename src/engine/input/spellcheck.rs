@@ -0,0 +1,493 @@
+//! スペルチェック
+//!
+//! Hunspell 形式の辞書（`.aff` と `.dic`）を読み、入力欄の単語の綴りを調べる。
+//! 接辞は `PFX` と `SFX` の規則（条件付きの付け外し）と、接頭辞と接尾辞の組み合わせまで扱う。
+//! 複合語（`COMPOUNDFLAG` など）や形態素の情報は扱わない。
+//!
+//! 直す候補は `REP` の置き換え、1 文字の置換・入れ替え・削除・挿入、2 語への分割から探す。
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// 候補の数の上限
+const MAX_SUGGESTIONS: usize = 5;
+
+/// 辞書のフラグ（`FLAG` の書き方によらず数にしておく）
+type Flag = u64;
+
+/// フラグの書き方（`FLAG`）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FlagMode {
+    /// 1 文字で 1 つ（既定）
+    Char,
+    /// 2 文字で 1 つ（`FLAG long`）
+    Long,
+    /// `,` で区切った数（`FLAG num`）
+    Num,
+}
+
+impl FlagMode {
+    fn parse(self, flags: &str) -> Vec<Flag> {
+        match self {
+            Self::Char => flags.chars().map(|c| c as Flag).collect(),
+            Self::Long => flags
+                .chars()
+                .collect::<Vec<_>>()
+                .chunks(2)
+                .map(|pair| pair.iter().fold(0, |flag, &c| (flag << 21) | c as Flag))
+                .collect(),
+            Self::Num => flags
+                .split(',')
+                .filter_map(|f| f.trim().parse().ok())
+                .collect(),
+        }
+    }
+}
+
+/// 接辞の条件の 1 文字分
+#[derive(Debug, Clone, PartialEq)]
+enum CharClass {
+    /// `.`
+    Any,
+    Char(char),
+    /// `[abc]`
+    Set(Vec<char>),
+    /// `[^abc]`
+    NotSet(Vec<char>),
+}
+
+impl CharClass {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Char(expected) => c == *expected,
+            Self::Set(chars) => chars.contains(&c),
+            Self::NotSet(chars) => !chars.contains(&c),
+        }
+    }
+
+    /// `[^aeiou]y` のような条件を読む
+    fn parse_condition(condition: &str) -> Vec<Self> {
+        let mut classes = Vec::new();
+        let mut chars = condition.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => classes.push(Self::Any),
+                '[' => {
+                    let mut set = Vec::new();
+                    let mut negated = false;
+                    for c in chars.by_ref() {
+                        match c {
+                            ']' => break,
+                            '^' if set.is_empty() && !negated => negated = true,
+                            c => set.push(c),
+                        }
+                    }
+                    classes.push(if negated {
+                        Self::NotSet(set)
+                    } else {
+                        Self::Set(set)
+                    });
+                }
+                c => classes.push(Self::Char(c)),
+            }
+        }
+        // `.` だけなら条件なし
+        if classes == [Self::Any] {
+            classes.clear();
+        }
+        classes
+    }
+}
+
+/// `PFX` / `SFX` の規則の 1 行
+#[derive(Debug, Clone)]
+struct Affix {
+    flag: Flag,
+    /// 接頭辞と接尾辞の両方を付けられるか
+    cross: bool,
+    /// 語幹から取り除く文字列
+    strip: String,
+    /// 付け足す文字列
+    add: String,
+    /// 付けられる語幹（strip を取り除く前）の条件。接頭辞なら先頭、接尾辞なら末尾の文字
+    condition: Vec<CharClass>,
+}
+
+impl Affix {
+    fn matches_start(&self, stem: &str) -> bool {
+        let mut chars = stem.chars();
+        self.condition
+            .iter()
+            .all(|class| chars.next().is_some_and(|c| class.matches(c)))
+    }
+
+    fn matches_end(&self, stem: &str) -> bool {
+        let mut chars = stem.chars().rev();
+        self.condition
+            .iter()
+            .rev()
+            .all(|class| chars.next().is_some_and(|c| class.matches(c)))
+    }
+}
+
+/// 1 つの言語の辞書
+#[derive(Debug, Clone)]
+pub struct SpellChecker {
+    language: String,
+    /// 語 → 付けられる接辞のフラグ
+    words: HashMap<String, Vec<Flag>>,
+    prefixes: Vec<Affix>,
+    suffixes: Vec<Affix>,
+    /// 候補を作るときに試す文字（`TRY`、よく使う順）
+    try_chars: Vec<char>,
+    /// よくある綴り間違いの置き換え（`REP`）
+    replacements: Vec<(String, String)>,
+    /// 辞書の語に使われている文字（これ以外の文字を含む語は調べない）
+    alphabet: HashSet<char>,
+}
+
+impl SpellChecker {
+    /// `.aff` と `.dic` の中身から辞書を作る
+    ///
+    /// 文字コードは `.aff` の `SET` に従う（UTF-8 と ISO8859-1 を読める）。
+    pub fn from_bytes(language: &str, aff: &[u8], dic: &[u8]) -> Self {
+        let latin1 = aff
+            .split(|&b| b == b'\n')
+            .filter_map(|line| line.strip_prefix(b"SET"))
+            .any(|encoding| {
+                let encoding = String::from_utf8_lossy(encoding)
+                    .trim()
+                    .to_ascii_uppercase();
+                matches!(encoding.as_str(), "ISO8859-1" | "ISO-8859-1" | "LATIN1")
+            });
+        let decode = |bytes: &[u8]| {
+            if latin1 {
+                bytes.iter().map(|&b| b as char).collect::<String>()
+            } else {
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+        };
+        Self::parse(language, &decode(aff), &decode(dic))
+    }
+
+    /// `.aff` と `.dic` の文字列から辞書を作る。読めない行は飛ばす
+    pub fn parse(language: &str, aff: &str, dic: &str) -> Self {
+        let mut checker = Self {
+            language: language.to_string(),
+            words: HashMap::new(),
+            prefixes: Vec::new(),
+            suffixes: Vec::new(),
+            try_chars: Vec::new(),
+            replacements: Vec::new(),
+            alphabet: HashSet::new(),
+        };
+        let flag_mode = checker.parse_aff(aff);
+        checker.parse_dic(dic, flag_mode);
+
+        checker
+            .alphabet
+            .extend(checker.words.keys().flat_map(|w| w.chars()));
+        checker.alphabet.extend(checker.try_chars.iter().copied());
+        if checker.try_chars.is_empty() {
+            let mut chars: Vec<char> = checker
+                .alphabet
+                .iter()
+                .copied()
+                .filter(|c| c.is_lowercase())
+                .collect();
+            chars.sort_unstable();
+            checker.try_chars = chars;
+        }
+        checker
+    }
+
+    fn parse_aff(&mut self, aff: &str) -> FlagMode {
+        let mut flag_mode = FlagMode::Char;
+        // 規則の見出し行の cross product の指定（フラグごと）
+        let mut cross = HashMap::new();
+
+        for line in aff.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                ["FLAG", mode, ..] => {
+                    flag_mode = match *mode {
+                        "long" => FlagMode::Long,
+                        "num" => FlagMode::Num,
+                        _ => FlagMode::Char,
+                    };
+                }
+                ["TRY", chars, ..] => self.try_chars = chars.chars().collect(),
+                ["REP", from, to, ..] => {
+                    // `_` は空白
+                    self.replacements
+                        .push((from.replace('_', " "), to.replace('_', " ")));
+                }
+                [kind @ ("PFX" | "SFX"), flag, yes_no @ ("Y" | "N"), count]
+                    if count.parse::<usize>().is_ok() =>
+                {
+                    if let Some(&flag) = flag_mode.parse(flag).first() {
+                        cross.insert((*kind, flag), *yes_no == "Y");
+                    }
+                }
+                [kind @ ("PFX" | "SFX"), flag, strip, add, rest @ ..] => {
+                    let Some(&flag) = flag_mode.parse(flag).first() else {
+                        continue;
+                    };
+                    let empty = |s: &str| {
+                        if s == "0" {
+                            String::new()
+                        } else {
+                            s.to_string()
+                        }
+                    };
+                    // 付け足す文字列の後ろの `/フラグ`（さらに付く接辞）は扱わない
+                    let add = add.split('/').next().unwrap_or_default();
+                    let affix = Affix {
+                        flag,
+                        cross: cross.get(&(*kind, flag)).copied().unwrap_or(false),
+                        strip: empty(*strip),
+                        add: empty(add),
+                        condition: CharClass::parse_condition(rest.first().unwrap_or(&".")),
+                    };
+                    if *kind == "PFX" {
+                        self.prefixes.push(affix);
+                    } else {
+                        self.suffixes.push(affix);
+                    }
+                }
+                _ => {}
+            }
+        }
+        flag_mode
+    }
+
+    fn parse_dic(&mut self, dic: &str, flag_mode: FlagMode) {
+        let mut lines = dic.lines().peekable();
+        // 1 行目は語の数
+        if lines
+            .peek()
+            .is_some_and(|line| line.trim().parse::<usize>().is_ok())
+        {
+            lines.next();
+        }
+        for line in lines {
+            // 語のあとにタブで形態素の情報が続くことがある
+            let Some(entry) = line.split(['\t', ' ']).next().filter(|e| !e.is_empty()) else {
+                continue;
+            };
+            let (word, flags) = entry.split_once('/').unwrap_or((entry, ""));
+            self.words
+                .entry(word.to_string())
+                .or_default()
+                .extend(flag_mode.parse(flags));
+        }
+    }
+
+    /// 辞書の言語（`en_US` など）
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// word の綴りが正しいか
+    ///
+    /// 辞書の語が小文字なら、文頭の大文字や全部大文字で書いた語も正しいとする。
+    pub fn check(&self, word: &str) -> bool {
+        let word = word.replace('’', "'");
+        case_variants(&word)
+            .iter()
+            .any(|variant| self.check_exact(variant))
+    }
+
+    fn check_exact(&self, word: &str) -> bool {
+        self.words.contains_key(word)
+            || self.check_suffixed(word, None)
+            || self.check_prefixed(word)
+    }
+
+    /// 接尾辞を外した語幹が辞書にあるか
+    ///
+    /// prefix は先に外した接頭辞（そのときは cross product を許す規則だけを使う）。
+    fn check_suffixed(&self, word: &str, prefix: Option<&Affix>) -> bool {
+        self.suffixes.iter().any(|suffix| {
+            if prefix.is_some() && !suffix.cross {
+                return false;
+            }
+            let Some(rest) = word.strip_suffix(suffix.add.as_str()) else {
+                return false;
+            };
+            let stem = format!("{rest}{}", suffix.strip);
+            !rest.is_empty()
+                && suffix.matches_end(&stem)
+                && self.has_flags(&stem, suffix.flag, prefix.map(|p| p.flag))
+        })
+    }
+
+    fn check_prefixed(&self, word: &str) -> bool {
+        self.prefixes.iter().any(|prefix| {
+            let Some(rest) = word.strip_prefix(prefix.add.as_str()) else {
+                return false;
+            };
+            let stem = format!("{}{rest}", prefix.strip);
+            !rest.is_empty()
+                && prefix.matches_start(&stem)
+                && (self.has_flags(&stem, prefix.flag, None)
+                    || (prefix.cross && self.check_suffixed(&stem, Some(prefix))))
+        })
+    }
+
+    fn has_flags(&self, word: &str, flag: Flag, other: Option<Flag>) -> bool {
+        self.words
+            .get(word)
+            .is_some_and(|flags| flags.contains(&flag) && other.is_none_or(|f| flags.contains(&f)))
+    }
+
+    /// text の中の綴りの誤っている単語の範囲（バイト）
+    ///
+    /// 数字を含む語、1 文字の語、辞書にない文字（別の言語の文字）を含む語は調べない。
+    pub fn misspelled_words(&self, text: &str) -> Vec<Range<usize>> {
+        words(text)
+            .into_iter()
+            .filter(|range| {
+                let word = &text[range.clone()];
+                self.should_check(word) && !self.check(word)
+            })
+            .collect()
+    }
+
+    fn should_check(&self, word: &str) -> bool {
+        word.chars().filter(|c| !is_apostrophe(*c)).count() > 1
+            && word.chars().all(|c| {
+                is_apostrophe(c)
+                    || (!c.is_numeric()
+                        && (self.alphabet.contains(&c)
+                            || c.to_lowercase().all(|l| self.alphabet.contains(&l))))
+            })
+    }
+
+    /// word を直す候補（よさそうな順、最大 5 つ）
+    pub fn suggest(&self, word: &str) -> Vec<String> {
+        let word = word.replace('’', "'");
+        // 全部大文字の語は小文字で探して大文字に戻す
+        let all_upper = word.chars().count() > 1 && word.chars().all(|c| !c.is_lowercase());
+        let base = if all_upper {
+            word.to_lowercase()
+        } else {
+            word.clone()
+        };
+        let chars: Vec<char> = base.chars().collect();
+
+        let mut candidates = Vec::new();
+        for (from, to) in &self.replacements {
+            for (i, _) in base.match_indices(from.as_str()) {
+                candidates.push(format!("{}{to}{}", &base[..i], &base[i + from.len()..]));
+            }
+        }
+        for i in 0..chars.len() {
+            for &c in &self.try_chars {
+                if c != chars[i] {
+                    candidates.push(edit(&chars, i..i + 1, &[c]));
+                }
+            }
+        }
+        for i in 1..chars.len() {
+            if chars[i - 1] != chars[i] {
+                candidates.push(edit(&chars, i - 1..i + 1, &[chars[i], chars[i - 1]]));
+            }
+        }
+        for i in 0..chars.len() {
+            candidates.push(edit(&chars, i..i + 1, &[]));
+        }
+        for i in 0..=chars.len() {
+            for &c in &self.try_chars {
+                candidates.push(edit(&chars, i..i, &[c]));
+            }
+        }
+
+        let mut suggestions: Vec<String> = Vec::new();
+        for candidate in candidates {
+            if suggestions.len() >= MAX_SUGGESTIONS {
+                break;
+            }
+            let candidate = if all_upper {
+                candidate.to_uppercase()
+            } else {
+                candidate
+            };
+            if candidate != word && !suggestions.contains(&candidate) && self.check(&candidate) {
+                suggestions.push(candidate);
+            }
+        }
+        // 空白を入れ忘れた 2 語
+        for i in 1..chars.len() {
+            if suggestions.len() >= MAX_SUGGESTIONS {
+                break;
+            }
+            let (first, second): (String, String) =
+                (chars[..i].iter().collect(), chars[i..].iter().collect());
+            if first.chars().count() > 1
+                && second.chars().count() > 1
+                && self.check(&first)
+                && self.check(&second)
+            {
+                suggestions.push(format!("{first} {second}"));
+            }
+        }
+        suggestions
+    }
+}
+
+/// chars の range を replacement に置き換えた文字列
+fn edit(chars: &[char], range: Range<usize>, replacement: &[char]) -> String {
+    chars[..range.start]
+        .iter()
+        .chain(replacement)
+        .chain(&chars[range.end..])
+        .collect()
+}
+
+/// 辞書を引く綴り（そのまま、文頭だけ大文字なら小文字、全部大文字なら文頭だけ大文字と小文字）
+fn case_variants(word: &str) -> Vec<String> {
+    let mut variants = vec![word.to_string()];
+    let mut chars = word.chars();
+    let Some(first) = chars.next() else {
+        return variants;
+    };
+    let rest: String = chars.collect();
+    if !first.is_uppercase() {
+        return variants;
+    }
+    if rest.chars().all(|c| !c.is_lowercase()) {
+        variants.push(first.to_string() + &rest.to_lowercase());
+        variants.push(word.to_lowercase());
+    } else if rest.chars().all(|c| !c.is_uppercase()) {
+        variants.push(word.to_lowercase());
+    }
+    variants
+}
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '’'
+}
+
+/// text の単語の範囲（文字と、語の途中のアポストロフィーの並び）
+pub fn words(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut end = 0;
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            start.get_or_insert(i);
+            end = i + c.len_utf8();
+        } else if !(is_apostrophe(c) && start.is_some()) {
+            // 語の末尾のアポストロフィーは含めない
+            if let Some(start) = start.take() {
+                ranges.push(start..end);
+            }
+        }
+    }
+    if let Some(start) = start {
+        ranges.push(start..end);
+    }
+    ranges
+}
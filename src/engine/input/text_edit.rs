@@ -0,0 +1,269 @@
+//! 1 行のテキスト入力欄の編集
+//!
+//! 値とキャレット、選択範囲、IME で変換中の文字列だけを持つ。位置はすべて値の
+//! バイトオフセットで、常に文字の境界にある。描画やフォーカスの管理は呼び出し側
+//! （WebView）で行う。
+
+use std::ops::Range;
+
+/// パスワード欄で 1 文字の代わりに表示する文字
+pub const PASSWORD_MASK: char = '•';
+
+/// パスワード欄の値の offset を、伏せ字にした文字列でのオフセットに直す
+pub fn masked_offset(value: &str, offset: usize) -> usize {
+    value[..offset].chars().count() * PASSWORD_MASK.len_utf8()
+}
+
+/// 伏せ字にした文字列でのオフセットを、値のオフセットに直す
+pub fn unmasked_offset(value: &str, masked: usize) -> usize {
+    value
+        .char_indices()
+        .nth(masked / PASSWORD_MASK.len_utf8())
+        .map(|(i, _)| i)
+        .unwrap_or(value.len())
+}
+
+/// IME で変換中（未確定）の文字列
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Preedit {
+    pub text: String,
+    /// 変換中の文字列の中のカーソル位置（IME が示さなければ None）
+    pub cursor: Option<usize>,
+}
+
+impl Preedit {
+    /// 空の文字列なら None（変換の終了）
+    pub fn new(text: &str, cursor: Option<usize>) -> Option<Self> {
+        (!text.is_empty()).then(|| Self {
+            text: text.to_string(),
+            cursor: cursor.filter(|&c| text.is_char_boundary(c)),
+        })
+    }
+
+    /// value の range を変換中の文字列で置き換えて表示する文字列
+    pub fn compose(&self, value: &str, range: Range<usize>) -> Composition {
+        let mut text = value.to_string();
+        text.replace_range(range.clone(), &self.text);
+        let preedit = range.start..range.start + self.text.len();
+
+        Composition {
+            caret: self.cursor.map_or(preedit.end, |c| preedit.start + c),
+            text,
+            preedit,
+        }
+    }
+}
+
+/// 変換中の文字列を差し込んで表示する文字列
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Composition {
+    pub text: String,
+    /// text の中で変換中の部分（下線を引く）
+    pub preedit: Range<usize>,
+    pub caret: usize,
+}
+
+/// 入力欄の値と編集位置
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TextEdit {
+    value: String,
+    /// キャレットの位置
+    caret: usize,
+    /// 選択の開始位置（選択していなければキャレットと同じ）
+    anchor: usize,
+    preedit: Option<Preedit>,
+}
+
+impl TextEdit {
+    /// キャレットを末尾に置いた状態で作る
+    pub fn new(value: &str) -> Self {
+        let value = strip_line_breaks(value);
+        let caret = value.len();
+
+        Self {
+            value,
+            caret,
+            anchor: caret,
+            preedit: None,
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    /// 選択範囲（幅 0 なら None）
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let range = self.caret.min(self.anchor)..self.caret.max(self.anchor);
+        (!range.is_empty()).then_some(range)
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|r| &self.value[r])
+    }
+
+    /// キャレットを offset に置く（文字の途中なら手前の境界に寄せる）
+    ///
+    /// extend が true なら選択の開始位置は動かさない。
+    pub fn set_caret(&mut self, offset: usize, extend: bool) {
+        let mut offset = offset.min(self.value.len());
+        while !self.value.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        self.move_to(offset, extend);
+    }
+
+    pub fn preedit(&self) -> Option<&Preedit> {
+        self.preedit.as_ref()
+    }
+
+    /// IME で変換中の文字列を設定する。空なら変換をやめる
+    ///
+    /// 変換中の文字列はまだ値に入れず、確定するまで選択範囲の位置に表示だけする。
+    pub fn set_preedit(&mut self, text: &str, cursor: Option<usize>) {
+        self.preedit = Preedit::new(&strip_line_breaks(text), cursor);
+    }
+
+    /// 変換中の文字列を差し込んだ表示（変換中でなければ None）
+    pub fn composition(&self) -> Option<Composition> {
+        let range = self.selection().unwrap_or(self.caret..self.caret);
+        self.preedit
+            .as_ref()
+            .map(|preedit| preedit.compose(&self.value, range))
+    }
+
+    /// IME で確定した文字列を入れる
+    pub fn commit(&mut self, s: &str) {
+        self.preedit = None;
+        self.insert(s);
+    }
+
+    /// 選択範囲を s で置き換える。改行は取り除く
+    pub fn insert(&mut self, s: &str) {
+        let s = strip_line_breaks(s);
+        let range = self.selection().unwrap_or(self.caret..self.caret);
+
+        self.value.replace_range(range.clone(), &s);
+        self.caret = range.start + s.len();
+        self.anchor = self.caret;
+    }
+
+    /// 選択範囲、なければキャレットの前の 1 文字を消す
+    pub fn backspace(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        if let Some(prev) = self.prev_boundary(self.caret) {
+            self.value.replace_range(prev..self.caret, "");
+            self.caret = prev;
+            self.anchor = prev;
+        }
+    }
+
+    /// 選択範囲、なければキャレットの後ろの 1 文字を消す
+    pub fn delete(&mut self) {
+        if self.delete_selection() {
+            return;
+        }
+        if let Some(next) = self.next_boundary(self.caret) {
+            self.value.replace_range(self.caret..next, "");
+        }
+    }
+
+    /// 選択範囲を消す。選択していなければ false
+    pub fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            return false;
+        };
+        self.value.replace_range(range.clone(), "");
+        self.caret = range.start;
+        self.anchor = range.start;
+        true
+    }
+
+    pub fn move_left(&mut self, extend: bool) {
+        // 選択中に Shift なしで動かしたら選択の左端に寄せる
+        if !extend && let Some(range) = self.selection() {
+            self.move_to(range.start, false);
+            return;
+        }
+        let offset = self.prev_boundary(self.caret).unwrap_or(0);
+        self.move_to(offset, extend);
+    }
+
+    pub fn move_right(&mut self, extend: bool) {
+        if !extend && let Some(range) = self.selection() {
+            self.move_to(range.end, false);
+            return;
+        }
+        let offset = self.next_boundary(self.caret).unwrap_or(self.value.len());
+        self.move_to(offset, extend);
+    }
+
+    /// 前の単語の先頭に動かす
+    pub fn move_word_left(&mut self, extend: bool) {
+        let before = &self.value[..self.caret];
+        let trimmed = before.trim_end();
+        let offset = trimmed
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(0);
+        self.move_to(offset, extend);
+    }
+
+    /// 次の単語の末尾に動かす
+    pub fn move_word_right(&mut self, extend: bool) {
+        let after = &self.value[self.caret..];
+        let skipped = after.len() - after.trim_start().len();
+        let offset = after[skipped..]
+            .find(char::is_whitespace)
+            .map(|i| self.caret + skipped + i)
+            .unwrap_or(self.value.len());
+        self.move_to(offset, extend);
+    }
+
+    pub fn move_home(&mut self, extend: bool) {
+        self.move_to(0, extend);
+    }
+
+    pub fn move_end(&mut self, extend: bool) {
+        self.move_to(self.value.len(), extend);
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.caret = self.value.len();
+    }
+
+    fn move_to(&mut self, offset: usize, extend: bool) {
+        self.caret = offset;
+        if !extend {
+            self.anchor = offset;
+        }
+    }
+
+    fn prev_boundary(&self, offset: usize) -> Option<usize> {
+        self.value[..offset]
+            .char_indices()
+            .next_back()
+            .map(|(i, _)| i)
+    }
+
+    fn next_boundary(&self, offset: usize) -> Option<usize> {
+        self.value[offset..]
+            .chars()
+            .next()
+            .map(|c| offset + c.len_utf8())
+    }
+}
+
+/// 1 行の入力欄には改行を入れない
+fn strip_line_breaks(s: &str) -> String {
+    s.chars().filter(|c| !matches!(c, '\n' | '\r')).collect()
+}
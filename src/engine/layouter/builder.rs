@@ -3,6 +3,8 @@ use crate::engine::css::{
     matcher::{ElementChain, ElementInfo},
     values::{CssValue, Unit},
 };
+use crate::engine::diagnostics::{self, Level, Source};
+use crate::engine::input::text_edit;
 use crate::engine::tree::TreeNode;
 use crate::html::HtmlNodeType;
 
@@ -15,10 +17,12 @@ use ui_layout::{
     AlignItems, BoxSizing, Display, FlexDirection, JustifyContent, LayoutNode, Length, Style,
 };
 
+use super::cascade;
 use super::css_resolver::ResolvedStyles;
 use super::types::{
-    BorderStyle, Color, ContainerRole, ContainerStyle, FontStyle, FontWeight, InfoNode,
-    MeasureCache, NodeKind, TextAlign, TextDecoration, TextStyle,
+    BorderStyle, Color, ContainerRole, ContainerStyle, FontFamilyList, FontStyle, FontWeight,
+    InfoNode, MeasureCache, NodeKind, Overflow, TextAlign, TextDecoration, TextStyle,
+    VerticalAlign, WhiteSpace,
 };
 
 /// Builds a layout tree (`LayoutNode`) and a render info tree (`InfoNode`) from the DOM.
@@ -56,17 +60,80 @@ use super::types::{
 /// These values must be passed from the computed result of the parent when
 /// calling this function recursively.
 ///
+/// - `hover_path`
+///
+/// Path (child indices) from this node to the element under the mouse cursor.
+/// `Some` means this node is that element or one of its ancestors, and
+/// therefore matches `:hover`.
+///
+/// - `active_path`
+///
+/// Path from this node to the element being pressed (`:active`), in the same
+/// form as `hover_path`.
+///
+/// - `focus_path`
+///
+/// Path from this node to the element that has the keyboard focus (`:focus`),
+/// in the same form as `hover_path`.
+///
+/// - `composition`
+///
+/// Path to the focused text field and the text it shows while an IME
+/// composition is in progress (its value with the uncommitted text inserted).
+///
 /// # Returns
 ///
 /// A tuple of:
 /// - `LayoutNode`: used by the layout engine
 /// - `InfoNode`: used for rendering (text, color, font size)
 pub fn build_layout_and_info(
+    dom: &Rc<RefCell<TreeNode<HtmlNodeType>>>,
+    resolved_styles: &ResolvedStyles,
+    measurer: &dyn text::TextMeasurer<TextStyle>,
+    parent_text_style: TextStyle,
+    chain: ElementChain,
+    hover_path: Option<&[usize]>,
+    active_path: Option<&[usize]>,
+    focus_path: Option<&[usize]>,
+    composition: Option<(&[usize], &str)>,
+) -> (LayoutNode, InfoNode) {
+    build_layout_and_info_with_visited(
+        dom,
+        resolved_styles,
+        measurer,
+        parent_text_style,
+        chain,
+        hover_path,
+        active_path,
+        focus_path,
+        composition,
+        &|_| false,
+        &|_| None,
+    )
+}
+
+/// Like [`build_layout_and_info`], but links whose `href` attribute
+/// `is_visited` accepts match `:visited` instead of `:link`.
+///
+/// Visited links are only told apart for the properties in
+/// [`VISITED_PROPERTIES`]. For every other property all links match `:link`
+/// and none match `:visited`, so that a page cannot learn which links were
+/// visited from the size or position of what it lays out.
+///
+/// Text fields for which `field_value` returns a value show it instead of
+/// their `value` attribute (values the browser keeps out of the DOM).
+pub fn build_layout_and_info_with_visited(
     dom: &Rc<RefCell<TreeNode<HtmlNodeType>>>,
     resolved_styles: &ResolvedStyles,
     measurer: &dyn text::TextMeasurer<TextStyle>,
     parent_text_style: TextStyle,
     mut chain: ElementChain,
+    hover_path: Option<&[usize]>,
+    active_path: Option<&[usize]>,
+    focus_path: Option<&[usize]>,
+    composition: Option<(&[usize], &str)>,
+    is_visited: &dyn Fn(&str) -> bool,
+    field_value: &dyn Fn(&Rc<RefCell<TreeNode<HtmlNodeType>>>) -> Option<String>,
 ) -> (LayoutNode, InfoNode) {
     let html_node = dom.borrow().value.clone();
 
@@ -87,28 +154,16 @@ pub fn build_layout_and_info(
         ..
     } = &html_node
     {
-        let id = attributes
-            .iter()
-            .find(|a| a.name == "id")
-            .map(|a| a.value.clone());
-
-        let class_list: Vec<String> = attributes
-            .iter()
-            .find(|attr| attr.name == "class")
-            .map(|attr| {
-                attr.value
-                    .split_whitespace()
-                    .map(|s| s.to_string())
-                    .collect()
-            })
-            .unwrap_or_default();
-
         chain.insert(
             0,
             ElementInfo {
-                tag_name: tag_name.clone(),
-                id,
-                classes: class_list,
+                hovered: hover_path.is_some(),
+                active: active_path.is_some(),
+                focused: focus_path.is_some_and(|path| path.is_empty()),
+                focus_within: focus_path.is_some(),
+                visited: matches!(tag_name.as_str(), "a" | "area")
+                    && html_node.get_attr("href").is_some_and(is_visited),
+                ..cascade::element_info(tag_name, attributes)
             },
         );
 
@@ -129,7 +184,13 @@ pub fn build_layout_and_info(
     }
 
     let mut kind = if let HtmlNodeType::Text(t) = &html_node {
-        let t = normalize_whitespace(t);
+        let t = process_whitespace(t, text_style.white_space);
+
+        // 行の中の箱は上端で揃うので、下付きの文字はその分だけ下げる
+        // （上付きの文字は小さいので、上端に揃えるだけで周りより上に出る）
+        if text_style.vertical_align == VerticalAlign::Sub {
+            style.spacing.margin_top = Length::Px(text_style.font_size * SUB_SHIFT_EM);
+        }
 
         let mut kind = NodeKind::Text {
             text: t.clone(),
@@ -145,8 +206,8 @@ pub fn build_layout_and_info(
         && let Some(href) = html_node.get_attr("href")
     {
         NodeKind::Container {
-            scroll_x: false,
-            scroll_y: false,
+            scroll_x: container_style.overflow_x.is_scrollable(),
+            scroll_y: container_style.overflow_y.is_scrollable(),
             scroll_offset_x: 0.0,
             scroll_offset_y: 0.0,
             style: container_style,
@@ -154,13 +215,94 @@ pub fn build_layout_and_info(
                 href: href.to_string(),
             },
         }
-    } else {
+    } else if is_button(&html_node) {
+        NodeKind::Container {
+            scroll_x: container_style.overflow_x.is_scrollable(),
+            scroll_y: container_style.overflow_y.is_scrollable(),
+            scroll_offset_x: 0.0,
+            scroll_offset_y: 0.0,
+            style: container_style,
+            role: ContainerRole::Button,
+        }
+    } else if html_node.tag_name() == Some("input")
+        && is_text_input_type(html_node.get_attr("type"))
+    {
+        NodeKind::Container {
+            // 値が入りきらないときは横にスクロールしてキャレットを見せる
+            scroll_x: true,
+            scroll_y: false,
+            scroll_offset_x: 0.0,
+            scroll_offset_y: 0.0,
+            style: container_style,
+            role: ContainerRole::TextInput { caret: None },
+        }
+    } else if let Some(role) = gauge_role(&html_node) {
+        NodeKind::Container {
+            scroll_x: false,
+            scroll_y: false,
+            scroll_offset_x: 0.0,
+            scroll_offset_y: 0.0,
+            style: container_style,
+            role,
+        }
+    } else if html_node.tag_name() == Some("img")
+        && let Some(src) = html_node.get_attr("src")
+    {
+        // 指定のない辺は、デコードした画像を WebView が渡すときに画像の大きさにする
+        for (attr, length) in [
+            ("width", &mut style.size.width),
+            ("height", &mut style.size.height),
+        ] {
+            if matches!(length, Length::Auto)
+                && let Some(px) = html_node
+                    .get_attr(attr)
+                    .and_then(|v| v.trim().trim_end_matches("px").parse::<f32>().ok())
+                    .filter(|v| *v >= 0.0)
+            {
+                *length = Length::Px(px);
+            }
+        }
         NodeKind::Container {
             scroll_x: false,
             scroll_y: false,
             scroll_offset_x: 0.0,
             scroll_offset_y: 0.0,
             style: container_style,
+            role: ContainerRole::Image {
+                src: src.trim().to_string(),
+                image: None,
+            },
+        }
+    } else if html_node.tag_name() == Some("iframe") {
+        // 中の文書は WebView が別にレイアウトして描く
+        for (attr, length, default) in [
+            ("width", &mut style.size.width, DEFAULT_FRAME_WIDTH),
+            ("height", &mut style.size.height, DEFAULT_FRAME_HEIGHT),
+        ] {
+            if matches!(length, Length::Auto) {
+                let px = html_node
+                    .get_attr(attr)
+                    .and_then(|v| v.trim().trim_end_matches("px").parse::<f32>().ok())
+                    .filter(|v| *v >= 0.0)
+                    .unwrap_or(default);
+                *length = Length::Px(px);
+            }
+        }
+        NodeKind::Container {
+            scroll_x: false,
+            scroll_y: false,
+            scroll_offset_x: 0.0,
+            scroll_offset_y: 0.0,
+            style: container_style,
+            role: ContainerRole::Frame,
+        }
+    } else {
+        NodeKind::Container {
+            scroll_x: container_style.overflow_x.is_scrollable(),
+            scroll_y: container_style.overflow_y.is_scrollable(),
+            scroll_offset_x: 0.0,
+            scroll_offset_y: 0.0,
+            style: container_style,
             role: ContainerRole::Normal,
         }
     };
@@ -181,7 +323,15 @@ pub fn build_layout_and_info(
     let mut layout_children = Vec::new();
     let mut info_children = Vec::new();
 
-    if !matches!(style.display, Display::None) {
+    // <iframe>、<progress>、<meter> の子は表示できないときの代わりの内容なので作らない
+    let is_replaced = matches!(
+        kind,
+        NodeKind::Container {
+            role: ContainerRole::Frame | ContainerRole::Gauge { .. },
+            ..
+        }
+    );
+    if !matches!(style.display, Display::None) && !is_replaced {
         let mut has_text_child = false;
 
         for child_dom in dom.borrow().children() {
@@ -218,13 +368,29 @@ pub fn build_layout_and_info(
             _ => {}
         }
 
-        for child_dom in dom.borrow().children() {
-            let (child_layout, child_info) = build_layout_and_info(
+        // <details> の最初の <summary>。閉じていればそれ以外の子は隠す
+        let details_open =
+            (html_node.tag_name() == Some("details")).then(|| html_node.has_attr("open"));
+        let summary_index = details_open.and_then(|_| {
+            dom.borrow()
+                .children()
+                .iter()
+                .position(|c| c.borrow().value.tag_name() == Some("summary"))
+        });
+
+        for (i, child_dom) in dom.borrow().children().iter().enumerate() {
+            let (mut child_layout, mut child_info) = build_layout_and_info_with_visited(
                 child_dom,
                 resolved_styles,
                 measurer,
                 text_style,
                 chain.clone(),
+                child_path(hover_path, i),
+                child_path(active_path, i),
+                child_path(focus_path, i),
+                composition.and_then(|(path, text)| Some((child_path(Some(path), i)?, text))),
+                is_visited,
+                field_value,
             );
 
             if dom.borrow().value.tag_name() == Some("html")
@@ -243,9 +409,82 @@ pub fn build_layout_and_info(
                 style.background_color = background_color;
             }
 
+            if let Some(open) = details_open {
+                if summary_index == Some(i) {
+                    if let NodeKind::Container { role, .. } = &mut child_info.kind {
+                        *role = ContainerRole::Summary {
+                            open,
+                            marker_size: text_style.font_size * SUMMARY_MARKER_EM,
+                            marker_color: text_style.color,
+                        };
+                    }
+                } else if !open {
+                    // パスが DOM と揃うように、ノードは残して中身を作らない
+                    child_layout.style.display = Display::None;
+                    child_layout.children.clear();
+                    child_info.children.clear();
+                }
+            }
+
+            layout_children.push(child_layout);
+            info_children.push(child_info);
+        }
+
+        // 入力欄の中身（値かプレースホルダー）は DOM にないのでここで作る
+        if let NodeKind::Container {
+            role: ContainerRole::TextInput { .. },
+            ..
+        } = &kind
+        {
+            if matches!(style.size.width, Length::Auto) {
+                let size = html_node
+                    .get_attr("size")
+                    .and_then(|s| s.trim().parse::<u32>().ok())
+                    .filter(|&s| s > 0)
+                    .unwrap_or(DEFAULT_INPUT_SIZE);
+                style.size.width =
+                    Length::Px(size as f32 * text_style.font_size * INPUT_CHAR_WIDTH_EM);
+            }
+
+            let kept = field_value(dom);
+            let composed = composition
+                .filter(|(path, _)| path.is_empty())
+                .map(|(_, text)| text)
+                .or(kept.as_deref());
+            let (child_layout, child_info) =
+                build_text_input_content(&html_node, composed, text_style, measurer);
             layout_children.push(child_layout);
             info_children.push(child_info);
         }
+
+        if let NodeKind::Container {
+            role: ContainerRole::Button,
+            ..
+        } = &kind
+        {
+            // <input type="submit"> などのラベルは value 属性から作る
+            if html_node.tag_name() == Some("input") {
+                let (child_layout, child_info) =
+                    text_node(button_label(&html_node), text_style, measurer);
+                layout_children.push(child_layout);
+                info_children.push(child_info);
+            }
+
+            // inline-block は未実装なので、幅が指定されていなければ中身の幅に縮める
+            let child_widths: Option<Vec<f32>> = layout_children
+                .iter()
+                .map(|c| match c.style.size.width {
+                    Length::Px(w) => Some(w),
+                    _ => None,
+                })
+                .collect();
+            if matches!(style.size.width, Length::Auto)
+                && let Some(widths) = child_widths
+                && !widths.is_empty()
+            {
+                style.size.width = Length::Px(widths.iter().sum());
+            }
+        }
     }
 
     let layout = LayoutNode::with_children(style, layout_children);
@@ -258,6 +497,222 @@ pub fn build_layout_and_info(
     (layout, info)
 }
 
+/// `type` がこれ以外（未対応の種類を含む）の `<input>` は 1 行のテキスト入力欄として扱う
+const NON_TEXT_INPUT_TYPES: &[&str] = &[
+    "hidden", "checkbox", "radio", "submit", "reset", "button", "image", "file", "range", "color",
+];
+
+/// `size` 属性がないときの入力欄の幅（文字数）
+const DEFAULT_INPUT_SIZE: u32 = 20;
+
+/// 入力欄の幅を決めるときの 1 文字の幅（em）
+const INPUT_CHAR_WIDTH_EM: f32 = 0.5;
+
+/// width / height 属性がないときの `<iframe>` の大きさ（CSS px）
+const DEFAULT_FRAME_WIDTH: f32 = 300.0;
+const DEFAULT_FRAME_HEIGHT: f32 = 150.0;
+
+/// プレースホルダーの文字色
+const PLACEHOLDER_COLOR: Color = Color(117, 117, 117, 255);
+
+/// リンクを訪れたかどうかで値が変わってよいプロパティ（`:link` / `:visited` の規則）
+///
+/// どれも色なので、リンクを訪れたかどうかで箱の大きさや位置は変わらない。
+pub const VISITED_PROPERTIES: &[&str] = &[
+    "color",
+    "background-color",
+    "border-color",
+    "border-top-color",
+    "border-right-color",
+    "border-bottom-color",
+    "border-left-color",
+    "outline-color",
+    "text-decoration-color",
+    "column-rule-color",
+];
+
+/// 下付きの文字を下げる量（em）
+const SUB_SHIFT_EM: f32 = 0.45;
+
+/// `<summary>` の開閉の三角の大きさ（em）
+const SUMMARY_MARKER_EM: f32 = 0.5;
+
+/// `<progress>` の埋まった部分の色
+const PROGRESS_COLOR: Color = Color(0, 117, 255, 255);
+/// `<meter>` の値が最適な範囲、その隣の範囲、最も遠い範囲にあるときの色
+const METER_OPTIMUM_COLOR: Color = Color(16, 124, 16, 255);
+const METER_SUBOPTIMUM_COLOR: Color = Color(255, 193, 7, 255);
+const METER_EVEN_LESS_GOOD_COLOR: Color = Color(211, 47, 47, 255);
+
+/// `<progress>` と `<meter>` のバーの役割（それ以外の要素なら None）
+///
+/// value / max（meter は min、low、high、optimum も）の省略や範囲外の値は HTML の
+/// 仕様どおりに補う。value のない `<progress>`（進み具合が分からない）は空のバーにする。
+fn gauge_role(html_node: &HtmlNodeType) -> Option<ContainerRole> {
+    let number = |name: &str| {
+        html_node
+            .get_attr(name)
+            .and_then(|v| v.trim().parse::<f32>().ok())
+            .filter(|v| v.is_finite())
+    };
+
+    match html_node.tag_name()? {
+        "progress" => {
+            let max = number("max").filter(|m| *m > 0.0).unwrap_or(1.0);
+            let value = number("value").map_or(0.0, |v| v.clamp(0.0, max));
+            Some(ContainerRole::Gauge {
+                fraction: value / max,
+                color: PROGRESS_COLOR,
+            })
+        }
+        "meter" => {
+            let min = number("min").unwrap_or(0.0);
+            let max = number("max").unwrap_or(1.0).max(min);
+            let value = number("value").unwrap_or(0.0).clamp(min, max);
+            let low = number("low").unwrap_or(min).clamp(min, max);
+            let high = number("high").unwrap_or(max).clamp(low, max);
+            let optimum = number("optimum")
+                .unwrap_or((min + max) / 2.0)
+                .clamp(min, max);
+
+            let color = if optimum < low {
+                if value < low {
+                    METER_OPTIMUM_COLOR
+                } else if value <= high {
+                    METER_SUBOPTIMUM_COLOR
+                } else {
+                    METER_EVEN_LESS_GOOD_COLOR
+                }
+            } else if optimum > high {
+                if value > high {
+                    METER_OPTIMUM_COLOR
+                } else if value >= low {
+                    METER_SUBOPTIMUM_COLOR
+                } else {
+                    METER_EVEN_LESS_GOOD_COLOR
+                }
+            } else if (low..=high).contains(&value) {
+                METER_OPTIMUM_COLOR
+            } else {
+                METER_SUBOPTIMUM_COLOR
+            };
+
+            let fraction = if max > min {
+                (value - min) / (max - min)
+            } else {
+                0.0
+            };
+            Some(ContainerRole::Gauge { fraction, color })
+        }
+        _ => None,
+    }
+}
+
+/// `<button>` と `<input type="submit | reset | button">`
+fn is_button(html_node: &HtmlNodeType) -> bool {
+    match html_node.tag_name() {
+        Some("button") => true,
+        Some("input") => html_node.get_attr("type").is_some_and(|t| {
+            ["submit", "reset", "button"]
+                .iter()
+                .any(|b| b.eq_ignore_ascii_case(t.trim()))
+        }),
+        _ => false,
+    }
+}
+
+/// `<input>` のボタンに表示する文字列
+fn button_label(html_node: &HtmlNodeType) -> String {
+    if let Some(value) = html_node.get_attr("value") {
+        return normalize_whitespace(value);
+    }
+    let input_type = html_node.get_attr("type").unwrap_or_default().trim();
+    if input_type.eq_ignore_ascii_case("submit") {
+        "Submit".to_string()
+    } else if input_type.eq_ignore_ascii_case("reset") {
+        "Reset".to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// `<input>` の `type` 属性の値が 1 行のテキスト入力欄になるものか
+pub fn is_text_input_type(input_type: Option<&str>) -> bool {
+    input_type.is_none_or(|t| {
+        !NON_TEXT_INPUT_TYPES
+            .iter()
+            .any(|n| n.eq_ignore_ascii_case(t.trim()))
+    })
+}
+
+/// 入力欄に表示する Text ノードを作る
+///
+/// 値が空ならプレースホルダーを薄い色で、パスワード欄なら伏せ字を表示する。
+/// IME で変換中の文字列か、属性の外に持っている値があれば、値の代わりに composed を表示する。
+fn build_text_input_content(
+    html_node: &HtmlNodeType,
+    composed: Option<&str>,
+    text_style: TextStyle,
+    measurer: &dyn text::TextMeasurer<TextStyle>,
+) -> (LayoutNode, InfoNode) {
+    let value = composed.or(html_node.get_attr("value")).unwrap_or_default();
+    let is_password = html_node
+        .get_attr("type")
+        .is_some_and(|t| t.trim().eq_ignore_ascii_case("password"));
+
+    let (text, style) = if value.is_empty() {
+        let placeholder = html_node.get_attr("placeholder").unwrap_or_default();
+        (
+            normalize_whitespace(placeholder),
+            TextStyle {
+                color: PLACEHOLDER_COLOR,
+                text_decoration: TextDecoration::None,
+                ..text_style
+            },
+        )
+    } else if is_password {
+        (
+            text_edit::PASSWORD_MASK
+                .to_string()
+                .repeat(value.chars().count()),
+            text_style,
+        )
+    } else {
+        (value.to_string(), text_style)
+    };
+
+    text_node(text, style, measurer)
+}
+
+/// DOM にない文字列（入力欄の値やボタンのラベル）の Text ノードを作る
+fn text_node(
+    text: String,
+    style: TextStyle,
+    measurer: &dyn text::TextMeasurer<TextStyle>,
+) -> (LayoutNode, InfoNode) {
+    let mut layout_style = Style::default();
+    let mut kind = NodeKind::Text {
+        text,
+        style,
+        measured: None,
+    };
+    ensure_text_measured(&mut layout_style, &mut kind, measurer);
+
+    (
+        LayoutNode::with_children(layout_style, Vec::new()),
+        InfoNode {
+            kind,
+            children: Vec::new(),
+        },
+    )
+}
+
+/// path が i 番目の子を通るなら、その子から先のパスを返す
+fn child_path(path: Option<&[usize]>, i: usize) -> Option<&[usize]> {
+    path.and_then(|path| path.split_first())
+        .and_then(|(&first, rest)| (first == i).then_some(rest))
+}
+
 fn calc_text_measure_hash(text: &str, style: &TextStyle) -> u64 {
     use std::collections::hash_map::DefaultHasher;
 
@@ -267,6 +722,7 @@ fn calc_text_measure_hash(text: &str, style: &TextStyle) -> u64 {
     style.font_size.to_bits().hash(&mut hasher);
     style.font_weight.hash(&mut hasher);
     style.font_style.hash(&mut hasher);
+    style.font_family.hash(&mut hasher);
 
     hasher.finish()
 }
@@ -300,21 +756,106 @@ fn ensure_text_measured(
         wrap: false,
     };
 
-    let (width, height) = measurer
+    let (width, height, baseline, lines) = measurer
         .measure(&req)
-        .map(|m| (m.width, m.height))
-        .unwrap_or((800.0, style.font_size * 1.2));
+        .map(|m| (m.width, m.height, m.baseline, m.lines))
+        .unwrap_or((
+            800.0,
+            style.font_size * 1.2,
+            style.font_size * 0.8,
+            Vec::new(),
+        ));
 
     *measured = Some(MeasureCache {
         hash,
         width,
         height,
+        baseline,
+        wrap_width: None,
+        lines,
     });
 
     node_style.size.width = Length::Px(width);
     node_style.size.height = Length::Px(height);
 }
 
+fn parse_overflow(v: &str) -> Option<Overflow> {
+    match v {
+        "visible" => Some(Overflow::Visible),
+        // clip もスクロールできないので hidden と同じ扱い
+        "hidden" | "clip" => Some(Overflow::Hidden),
+        "scroll" => Some(Overflow::Scroll),
+        "auto" => Some(Overflow::Auto),
+        _ => None,
+    }
+}
+
+/// 値の中の最初の `url()` の URL（解決前の文字列）
+fn css_url(value: &CssValue) -> Option<&str> {
+    match value {
+        CssValue::Function(name, args) if name.eq_ignore_ascii_case("url") => match args.as_slice()
+        {
+            [CssValue::String(url)] => Some(url),
+            _ => None,
+        },
+        CssValue::List(values) => values.iter().find_map(css_url),
+        _ => None,
+    }
+}
+
+/// タブを次の止まりまでの空白にする幅
+const TAB_SIZE: usize = 8;
+
+/// white_space に従ってテキストノードの空白と改行を整える
+///
+/// 残した改行はテキストの計測と描画で段落の区切りになる。
+fn process_whitespace(text: &str, white_space: WhiteSpace) -> String {
+    if !white_space.preserves_newlines() {
+        return normalize_whitespace(text);
+    }
+
+    let mut result = String::new();
+    let mut lines = text.split('\n').peekable();
+    let mut first = true;
+    while let Some(line) = lines.next() {
+        if !first {
+            result.push('\n');
+        }
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if white_space.collapses_spaces() {
+            // 改行の前後の空白は消える
+            let mut line = normalize_whitespace(line);
+            if lines.peek().is_some() {
+                line.truncate(line.trim_end_matches(' ').len());
+            }
+            result.push_str(if first {
+                &line
+            } else {
+                line.trim_start_matches(' ')
+            });
+        } else {
+            expand_tabs(line, &mut result);
+        }
+        first = false;
+    }
+    result
+}
+
+/// line のタブを TAB_SIZE 文字ごとの止まりまでの空白にして out に足す
+fn expand_tabs(line: &str, out: &mut String) {
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let width = TAB_SIZE - column % TAB_SIZE;
+            out.extend(std::iter::repeat_n(' ', width));
+            column += width;
+        } else {
+            out.push(c);
+            column += 1;
+        }
+    }
+}
+
 fn normalize_whitespace(text: &str) -> String {
     let mut result = String::new();
     let mut prev_was_space = false;
@@ -340,14 +881,30 @@ fn collect_candidates(
 ) -> HashMap<String, (CssValue, (u32, u32, u32), usize)> {
     let mut candidates: HashMap<String, (CssValue, (u32, u32, u32), usize)> = HashMap::new();
 
+    // 色以外はどのリンクも訪れていないものとして当てはめる（:link はすべてのリンクに、
+    // :visited はどれにも当てはまる）。:link の規則からも訪れたかどうかが漏れないように
+    let unvisited: Option<ElementChain> = chain.iter().any(|e| e.visited).then(|| {
+        chain
+            .iter()
+            .map(|e| ElementInfo {
+                visited: false,
+                ..e.clone()
+            })
+            .collect()
+    });
+
     for decl in resolved_styles {
+        let chain = match &unvisited {
+            Some(unvisited) if !VISITED_PROPERTIES.contains(&decl.name.as_str()) => unvisited,
+            _ => chain,
+        };
         if decl.selector.matches(chain) {
             let entry = candidates.get(&decl.name);
 
             let should_replace = match entry {
                 None => true,
                 Some((_, spec, order)) => {
-                    decl.specificity > *spec || (decl.specificity == *spec && decl.order > *order)
+                    cascade::takes_precedence((decl.specificity, decl.order), (*spec, *order))
                 }
             };
 
@@ -504,6 +1061,33 @@ fn apply_declaration(
             };
         }
 
+        /* ======================
+         * Overflow
+         * ====================== */
+        ("overflow", _) => {
+            let (x, y) = match value {
+                CssValue::Keyword(v) => {
+                    let o = parse_overflow(v)?;
+                    (o, o)
+                }
+                CssValue::List(values) => match values.as_slice() {
+                    [CssValue::Keyword(x), CssValue::Keyword(y)] => {
+                        (parse_overflow(x)?, parse_overflow(y)?)
+                    }
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            container_style.overflow_x = x;
+            container_style.overflow_y = y;
+        }
+        ("overflow-x", CssValue::Keyword(v)) => {
+            container_style.overflow_x = parse_overflow(v)?;
+        }
+        ("overflow-y", CssValue::Keyword(v)) => {
+            container_style.overflow_y = parse_overflow(v)?;
+        }
+
         /* ======================
          * Color / Text
          * ====================== */
@@ -520,7 +1104,12 @@ fn apply_declaration(
             };
         }
 
+        ("background-image", _) => {
+            container_style.background_image = css_url(value).map(str::to_string);
+        }
+
         ("background", _) => {
+            container_style.background_image = css_url(value).map(str::to_string);
             container_style.background_color = match value {
                 CssValue::Keyword(kw) if kw.eq_ignore_ascii_case("inherit") => {
                     // inherit: use parent's text color
@@ -553,7 +1142,11 @@ fn apply_declaration(
                 Length::Px(v) => *v,
                 Length::Percent(v) => *v * text_style.font_size / 100.0,
                 _ => {
-                    log::error!(target: "Layouter", "Unknown size type for font-size: {:?}", len);
+                    diagnostics::report(
+                        Level::Warning,
+                        Source::Css,
+                        format!("Unknown size type for font-size: {:?}", len),
+                    );
                     return None;
                 }
             };
@@ -571,6 +1164,26 @@ fn apply_declaration(
             text_style.font_weight = FontWeight(*v as u16);
         }
 
+        ("font-family", _) => {
+            // カンマは tokenizer で落ちるので、Keyword / String をそのまま候補として並べる
+            let values = match value {
+                CssValue::List(list) => list.as_slice(),
+                other => std::slice::from_ref(other),
+            };
+            let names: Vec<String> = values
+                .iter()
+                .filter_map(|v| match v {
+                    CssValue::Keyword(s) | CssValue::String(s) if !s.is_empty() => Some(s.clone()),
+                    _ => None,
+                })
+                .collect();
+
+            if names.len() == 1 && names[0].eq_ignore_ascii_case("inherit") {
+                return Some(());
+            }
+            text_style.font_family = FontFamilyList::intern(&names);
+        }
+
         ("font-style", CssValue::Keyword(v)) => {
             text_style.font_style = match v.as_str() {
                 "normal" => FontStyle::Normal,
@@ -590,6 +1203,27 @@ fn apply_declaration(
             };
         }
 
+        ("white-space", CssValue::Keyword(v)) => {
+            text_style.white_space = match v.as_str() {
+                "normal" => WhiteSpace::Normal,
+                "nowrap" => WhiteSpace::Nowrap,
+                "pre" => WhiteSpace::Pre,
+                // break-spaces は pre-wrap と同じ扱い
+                "pre-wrap" | "break-spaces" => WhiteSpace::PreWrap,
+                "pre-line" => WhiteSpace::PreLine,
+                _ => text_style.white_space,
+            };
+        }
+
+        ("vertical-align", CssValue::Keyword(v)) => {
+            text_style.vertical_align = match v.as_str() {
+                "baseline" => VerticalAlign::Baseline,
+                "sub" => VerticalAlign::Sub,
+                "super" => VerticalAlign::Super,
+                _ => text_style.vertical_align,
+            };
+        }
+
         ("text-align", CssValue::Keyword(v)) if v == "left" => {
             text_style.text_align = TextAlign::Left;
         }
@@ -881,24 +1515,40 @@ fn resolve_css_len(css_len: &CssValue, text_style: &TextStyle) -> Option<Length>
                         if let CssValue::Number(factor) = val {
                             result = Length::Mul(Box::new(result), *factor);
                         } else {
-                            log::error!(target: "Layouter", "Invalid operand for multiplication in calc(): {:?}", val);
+                            diagnostics::report(
+                                Level::Warning,
+                                Source::Css,
+                                format!("Invalid operand for multiplication in calc(): {:?}", val),
+                            );
                             return None;
                         }
                     }
                     CssValue::Keyword(o) if o == "/" => {
                         if let CssValue::Number(factor) = val {
                             if *factor == 0.0 {
-                                log::error!(target: "Layouter", "Division by zero in calc()");
+                                diagnostics::report(
+                                    Level::Warning,
+                                    Source::Css,
+                                    "Division by zero in calc()",
+                                );
                                 return None;
                             }
                             result = Length::Div(Box::new(result), *factor);
                         } else {
-                            log::error!(target: "Layouter", "Invalid operand for division in calc(): {:?}", val);
+                            diagnostics::report(
+                                Level::Warning,
+                                Source::Css,
+                                format!("Invalid operand for division in calc(): {:?}", val),
+                            );
                             return None;
                         }
                     }
                     _ => {
-                        log::error!(target: "Layouter", "Unknown operator for calc function: {:?}", op);
+                        diagnostics::report(
+                            Level::Warning,
+                            Source::Css,
+                            format!("Unknown operator for calc function: {:?}", op),
+                        );
                         return None;
                     }
                 }
@@ -907,7 +1557,11 @@ fn resolve_css_len(css_len: &CssValue, text_style: &TextStyle) -> Option<Length>
             Some(result)
         }
         _ => {
-            log::error!(target: "Layouter", "Unknown CSS Length type: {:?}", css_len);
+            diagnostics::report(
+                Level::Warning,
+                Source::Css,
+                format!("Unknown CSS Length type: {:?}", css_len),
+            );
             None
         }
     }
@@ -964,7 +1618,11 @@ fn resolve_css_color(css_color: &CssValue) -> Option<Color> {
             "none" => Some(Color(0, 0, 0, 0)),
 
             _ => {
-                log::error!(target: "Layouter", "Unknown CSS color keyword: {}", keyword);
+                diagnostics::report(
+                    Level::Warning,
+                    Source::Css,
+                    format!("Unknown CSS color keyword: {}", keyword),
+                );
                 None
             }
         }
@@ -1080,10 +1738,13 @@ fn resolve_css_color(css_color: &CssValue) -> Option<Color> {
 
         // Any other value reaching here is a pipeline error
         _ => {
-            log::error!(
-                target: "Layouter",
-                "Unexpected CSS color value at layout stage: {:?}",
-                css_color
+            diagnostics::report(
+                Level::Warning,
+                Source::Css,
+                format!(
+                    "Unexpected CSS color value at layout stage: {:?}",
+                    css_color
+                ),
             );
             None
         }
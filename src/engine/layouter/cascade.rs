@@ -0,0 +1,171 @@
+//! Cascade inspection
+//!
+//! Exposes which declarations match an element, which of them win, and the
+//! resulting values. The layout builder applies the same precedence rules
+//! through [`takes_precedence`]; this module keeps the losers too so that
+//! developer tools can show why a value was chosen.
+
+use crate::engine::css::matcher::{ElementChain, ElementInfo};
+use crate::engine::css::values::CssValue;
+use crate::engine::html::tokenizer::Attribute;
+use crate::engine::tree::NodeRef;
+use crate::html::HtmlNodeType;
+
+use std::collections::HashMap;
+
+use super::css_resolver::{ResolvedStyles, StyleOrigin};
+
+/// A declaration that applies to the inspected element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchedDeclaration {
+    /// The selector of the rule, as written in a stylesheet.
+    pub selector: String,
+    pub origin: StyleOrigin,
+    pub specificity: (u32, u32, u32),
+    /// Source order within its stylesheet.
+    pub order: usize,
+    pub name: String,
+    pub value: CssValue,
+    pub important: bool,
+    /// Whether another matching declaration of the same property wins.
+    pub overridden: bool,
+}
+
+/// Returns whether a declaration with `(specificity, order)` replaces the
+/// current winner `current` for the same property.
+///
+/// Higher specificity wins; with equal specificity the later declaration wins.
+pub fn takes_precedence(
+    candidate: ((u32, u32, u32), usize),
+    current: ((u32, u32, u32), usize),
+) -> bool {
+    let ((specificity, order), (current_specificity, current_order)) = (candidate, current);
+    specificity > current_specificity
+        || (specificity == current_specificity && order > current_order)
+}
+
+/// Builds the selector-matching information of an element.
+///
+/// Dynamic state (`:hover`, `:active`, `:focus`, `:focus-within`) is left unset.
+pub fn element_info(tag_name: &str, attributes: &[Attribute]) -> ElementInfo {
+    let id = attributes
+        .iter()
+        .find(|a| a.name == "id")
+        .map(|a| a.value.clone());
+
+    let classes = attributes
+        .iter()
+        .find(|attr| attr.name == "class")
+        .map(|attr| {
+            attr.value
+                .split_whitespace()
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    ElementInfo {
+        tag_name: tag_name.to_string(),
+        id,
+        classes,
+        attributes: attributes
+            .iter()
+            .map(|a| (a.name.to_ascii_lowercase(), a.value.clone()))
+            .collect(),
+        hovered: false,
+        active: false,
+        focused: false,
+        focus_within: false,
+        visited: false,
+    }
+}
+
+/// Returns the element chain (element first, then its ancestors) of the node
+/// reached from `root` by following child indices in `path`.
+///
+/// If the node is not an element (e.g. a text node), the chain starts at its
+/// nearest element ancestor. Returns `None` if `path` does not exist or no
+/// element is on it.
+pub fn element_chain(root: &NodeRef<HtmlNodeType>, path: &[usize]) -> Option<ElementChain> {
+    let mut chain = ElementChain::new();
+    push_element(&mut chain, root);
+
+    let mut node = root.clone();
+    for &i in path {
+        let child = node.borrow().children().get(i).cloned()?;
+        push_element(&mut chain, &child);
+        node = child;
+    }
+
+    (!chain.is_empty()).then_some(chain)
+}
+
+fn push_element(chain: &mut ElementChain, node: &NodeRef<HtmlNodeType>) {
+    if let HtmlNodeType::Element {
+        tag_name,
+        attributes,
+    } = &node.borrow().value
+    {
+        chain.insert(0, element_info(tag_name, attributes));
+    }
+}
+
+/// Returns every declaration that matches `chain`, highest precedence first.
+///
+/// For each property exactly one declaration is not `overridden`: the one the
+/// layout builder applies.
+pub fn matched_declarations(
+    resolved_styles: &ResolvedStyles,
+    chain: &ElementChain,
+) -> Vec<MatchedDeclaration> {
+    let matching: Vec<_> = resolved_styles
+        .iter()
+        .filter(|decl| decl.selector.matches(chain))
+        .collect();
+
+    let mut winners: HashMap<&str, usize> = HashMap::new();
+    for (i, decl) in matching.iter().enumerate() {
+        let replace = winners.get(decl.name.as_str()).is_none_or(|&w| {
+            takes_precedence(
+                (decl.specificity, decl.order),
+                (matching[w].specificity, matching[w].order),
+            )
+        });
+        if replace {
+            winners.insert(&decl.name, i);
+        }
+    }
+
+    let mut matched: Vec<MatchedDeclaration> = matching
+        .iter()
+        .enumerate()
+        .map(|(i, decl)| MatchedDeclaration {
+            selector: decl.selector.to_string(),
+            origin: decl.origin,
+            specificity: decl.specificity,
+            order: decl.order,
+            name: decl.name.clone(),
+            value: decl.value.clone(),
+            important: decl.important,
+            overridden: winners.get(decl.name.as_str()) != Some(&i),
+        })
+        .collect();
+
+    // The sort is stable: on a tie the earlier (winning) declaration stays first.
+    matched.sort_by(|a, b| (b.specificity, b.order).cmp(&(a.specificity, a.order)));
+    matched
+}
+
+/// Returns the winning value of each property, sorted by property name.
+///
+/// Custom properties (`--*`) are left out; they are already substituted into
+/// the values that use them.
+pub fn computed_values(matched: &[MatchedDeclaration]) -> Vec<(String, CssValue)> {
+    let mut values: Vec<(String, CssValue)> = matched
+        .iter()
+        .filter(|decl| !decl.overridden && !decl.name.starts_with("--"))
+        .map(|decl| (decl.name.clone(), decl.value.clone()))
+        .collect();
+    values.sort_by(|a, b| a.0.cmp(&b.0));
+    values
+}
@@ -1,3 +1,4 @@
+use crate::engine::css::media::MediaContext;
 use crate::engine::css::parser::{ComplexSelector, CssNode, CssNodeType};
 use crate::engine::css::values::CssValue;
 
@@ -5,6 +6,15 @@ use std::collections::HashMap;
 
 type CustomProperties = HashMap<String, CssValue>;
 
+/// Where a stylesheet comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StyleOrigin {
+    /// The browser's built-in stylesheet.
+    UserAgent,
+    /// `<style>` elements and stylesheets loaded by the page.
+    Author,
+}
+
 /// A single CSS declaration after selector resolution and value processing.
 ///
 /// `ResolvedDeclaration` represents one property-value pair that has been
@@ -45,6 +55,9 @@ pub struct ResolvedDeclaration {
 
     /// Whether this declaration is marked as `!important`.
     pub important: bool,
+
+    /// The stylesheet this declaration comes from.
+    pub origin: StyleOrigin,
 }
 
 pub type ResolvedStyles = Vec<ResolvedDeclaration>;
@@ -52,14 +65,42 @@ pub type ResolvedStyles = Vec<ResolvedDeclaration>;
 pub struct CssResolver;
 
 impl CssResolver {
+    /// Resolves `stylesheet` for the default (light, screen) environment.
     pub fn resolve(stylesheet: &CssNode) -> ResolvedStyles {
+        Self::resolve_with_media(stylesheet, &MediaContext::default())
+    }
+
+    /// Resolves an author `stylesheet`, skipping `@media` rules that do not match `media`.
+    pub fn resolve_with_media(stylesheet: &CssNode, media: &MediaContext) -> ResolvedStyles {
+        Self::resolve_with_origin(stylesheet, media, StyleOrigin::Author)
+    }
+
+    /// Like [`Self::resolve_with_media`], tagging every declaration with `origin`.
+    pub fn resolve_with_origin(
+        stylesheet: &CssNode,
+        media: &MediaContext,
+        origin: StyleOrigin,
+    ) -> ResolvedStyles {
         let mut styles = Vec::new();
         let mut order = 0;
-        Self::walk(stylesheet, &mut styles, &mut order);
+        Self::walk(stylesheet, media, origin, &mut styles, &mut order);
         styles
     }
 
-    fn walk(node: &CssNode, styles: &mut ResolvedStyles, order: &mut usize) {
+    fn walk(
+        node: &CssNode,
+        media: &MediaContext,
+        origin: StyleOrigin,
+        styles: &mut ResolvedStyles,
+        order: &mut usize,
+    ) {
+        if let CssNodeType::AtRule { name, params } = &node.node()
+            && name.eq_ignore_ascii_case("media")
+            && !media.matches(params)
+        {
+            return;
+        }
+
         if let CssNodeType::Rule { selectors } = &node.node() {
             let declarations = Self::collect_declarations(node);
 
@@ -74,6 +115,7 @@ impl CssResolver {
                         specificity,
                         order: *order,
                         important: *important,
+                        origin,
                     });
                     *order += 1;
                 }
@@ -81,7 +123,7 @@ impl CssResolver {
         }
 
         for child in node.children() {
-            Self::walk(child, styles, order);
+            Self::walk(child, media, origin, styles, order);
         }
     }
 
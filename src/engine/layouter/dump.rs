@@ -0,0 +1,357 @@
+//! Text dumps of layout and style trees
+//!
+//! Prints the layout tree (boxes from [`LayoutNode`] paired with the render
+//! information of [`InfoNode`]) and the styles applied to each element as
+//! indented trees, in the same format as the [`CssNode`] printer. Layout bugs
+//! can then be reported and compared as text instead of screenshots.
+//!
+//! [`CssNode`]: crate::engine::css::parser::CssNode
+
+use std::fmt;
+
+use ui_layout::LayoutNode;
+
+use crate::engine::css::matcher::{ElementChain, ElementInfo};
+use crate::engine::tree::NodeRef;
+use crate::html::HtmlNodeType;
+
+use super::cascade;
+use super::css_resolver::ResolvedStyles;
+use super::types::{
+    BorderStyle, Color, ContainerRole, FontStyle, FontWeight, InfoNode, NodeKind, Overflow,
+    TextAlign, TextDecoration, VerticalAlign, WhiteSpace,
+};
+
+/// Text longer than this is cut off in the dump.
+const MAX_TEXT_CHARS: usize = 40;
+
+/// A layout tree and its render-info tree, printed one node per line.
+///
+/// Each line shows the kind of the node, its border boxes relative to the
+/// parent's content box, and the styles that differ from the initial values:
+///
+/// ```text
+/// box [0,0 800x56] bg=#ffffff
+/// └── link href="/" [8,8 34x18]
+///     └── text "Home" [0,0 34x18] 16px #0000ee underline
+/// ```
+pub struct LayoutDump<'a> {
+    layout: &'a LayoutNode,
+    info: &'a InfoNode,
+}
+
+impl<'a> LayoutDump<'a> {
+    pub fn new(layout: &'a LayoutNode, info: &'a InfoNode) -> Self {
+        Self { layout, info }
+    }
+}
+
+impl fmt::Display for LayoutDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_layout_node(Some(self.layout), self.info, f, &mut Vec::new())
+    }
+}
+
+/// Returns the layout tree as text (see [`LayoutDump`]).
+pub fn dump_layout(layout: &LayoutNode, info: &InfoNode) -> String {
+    LayoutDump::new(layout, info).to_string()
+}
+
+/// The DOM elements with the declarations that win for each of them.
+///
+/// Values come from [`cascade::computed_values`]: inherited values are not
+/// repeated on descendants, and dynamic state (`:hover` etc.) is not applied.
+///
+/// ```text
+/// #document
+/// └── html
+///     └── body { margin: 8px }
+///         └── p.note { color: #ff0000; font-size: 12px }
+/// ```
+pub struct StyleDump<'a> {
+    root: &'a NodeRef<HtmlNodeType>,
+    styles: &'a ResolvedStyles,
+}
+
+impl<'a> StyleDump<'a> {
+    pub fn new(root: &'a NodeRef<HtmlNodeType>, styles: &'a ResolvedStyles) -> Self {
+        Self { root, styles }
+    }
+}
+
+impl fmt::Display for StyleDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_style_node(
+            self.root,
+            self.styles,
+            &ElementChain::new(),
+            f,
+            &mut Vec::new(),
+        )
+    }
+}
+
+/// Returns the styles of the elements under `root` as text (see [`StyleDump`]).
+pub fn dump_styles(root: &NodeRef<HtmlNodeType>, styles: &ResolvedStyles) -> String {
+    StyleDump::new(root, styles).to_string()
+}
+
+/// Writes one line of a tree. `ancestors_last` tells, for the node and each of
+/// its ancestors below the root, whether it is the last child of its parent.
+fn write_tree_line(
+    f: &mut fmt::Formatter<'_>,
+    ancestors_last: &[bool],
+    line: &dyn fmt::Display,
+) -> fmt::Result {
+    let Some((&is_last, ancestors)) = ancestors_last.split_last() else {
+        return writeln!(f, "{line}");
+    };
+    for &ancestor_last in ancestors {
+        f.write_str(if ancestor_last { "    " } else { "│   " })?;
+    }
+    let connector = if is_last { "└── " } else { "├── " };
+    writeln!(f, "{connector}{line}")
+}
+
+fn fmt_layout_node(
+    layout: Option<&LayoutNode>,
+    info: &InfoNode,
+    f: &mut fmt::Formatter<'_>,
+    ancestors_last: &mut Vec<bool>,
+) -> fmt::Result {
+    let mut line = node_label(info);
+    match layout {
+        Some(layout) if !layout.layout_boxes.is_empty() => {
+            for box_model in &layout.layout_boxes {
+                let rect = box_model.border_box;
+                line.push_str(&format!(
+                    " [{},{} {}x{}]",
+                    num(rect.x),
+                    num(rect.y),
+                    num(rect.width),
+                    num(rect.height)
+                ));
+            }
+        }
+        _ => line.push_str(" [no box]"),
+    }
+    line.push_str(&style_summary(info));
+    write_tree_line(f, ancestors_last, &line)?;
+
+    let child_count = info.children.len();
+    for (i, child) in info.children.iter().enumerate() {
+        let child_layout = layout.and_then(|layout| layout.children.get(i));
+        ancestors_last.push(i == child_count - 1);
+        fmt_layout_node(child_layout, child, f, ancestors_last)?;
+        ancestors_last.pop();
+    }
+    Ok(())
+}
+
+fn node_label(info: &InfoNode) -> String {
+    match &info.kind {
+        NodeKind::Container { role, .. } => match role {
+            ContainerRole::Normal => "box".to_string(),
+            ContainerRole::Link { href } => format!("link href={href:?}"),
+            ContainerRole::TextInput { .. } => "text-input".to_string(),
+            ContainerRole::Button => "button".to_string(),
+            ContainerRole::Frame => "frame".to_string(),
+            ContainerRole::Image { src, .. } => format!("image src={src:?}"),
+            ContainerRole::Gauge { fraction, .. } => format!("gauge {}%", num(fraction * 100.0)),
+            ContainerRole::Summary { open: true, .. } => "summary open".to_string(),
+            ContainerRole::Summary { open: false, .. } => "summary".to_string(),
+        },
+        NodeKind::Text { text, .. } => {
+            let mut shown: String = text.chars().take(MAX_TEXT_CHARS).collect();
+            if shown.len() < text.len() {
+                shown.push('…');
+            }
+            format!("text {shown:?}")
+        }
+    }
+}
+
+/// The styles of the node that differ from the initial values.
+fn style_summary(info: &InfoNode) -> String {
+    let mut parts = Vec::new();
+    match &info.kind {
+        NodeKind::Container {
+            scroll_offset_x,
+            scroll_offset_y,
+            style,
+            ..
+        } => {
+            if style.background_color.3 != 0 {
+                parts.push(format!("bg={}", hex(style.background_color)));
+            }
+            let border = &style.border_style;
+            let styles = [border.top, border.right, border.bottom, border.left];
+            if styles.iter().any(|s| *s != BorderStyle::None) {
+                let color = &style.border_color;
+                let colors = [color.top, color.right, color.bottom, color.left];
+                parts.push(format!(
+                    "border={} {}",
+                    sides(styles.map(|s| format!("{s:?}").to_lowercase())),
+                    sides(colors.map(hex))
+                ));
+            }
+            if (style.overflow_x, style.overflow_y) != (Overflow::Visible, Overflow::Visible) {
+                let overflow =
+                    [style.overflow_x, style.overflow_y].map(|o| format!("{o:?}").to_lowercase());
+                if overflow[0] == overflow[1] {
+                    parts.push(format!("overflow={}", overflow[0]));
+                } else {
+                    parts.push(format!("overflow=({} {})", overflow[0], overflow[1]));
+                }
+            }
+            if *scroll_offset_x != 0.0 || *scroll_offset_y != 0.0 {
+                parts.push(format!(
+                    "scroll=({},{})",
+                    num(*scroll_offset_x),
+                    num(*scroll_offset_y)
+                ));
+            }
+        }
+        NodeKind::Text {
+            style, measured, ..
+        } => {
+            parts.push(format!("{}px", num(style.font_size)));
+            parts.push(hex(style.color));
+            let families = style.font_family.names();
+            if !families.is_empty() {
+                parts.push(format!("family={:?}", families.join(", ")));
+            }
+            if style.font_weight != FontWeight::NORMAL {
+                parts.push(match style.font_weight {
+                    FontWeight::BOLD => "bold".to_string(),
+                    FontWeight(weight) => format!("weight={weight}"),
+                });
+            }
+            match style.font_style {
+                FontStyle::Normal => {}
+                FontStyle::Italic => parts.push("italic".to_string()),
+                FontStyle::Oblique => parts.push("oblique".to_string()),
+            }
+            match style.text_decoration {
+                TextDecoration::None => {}
+                TextDecoration::Underline => parts.push("underline".to_string()),
+                TextDecoration::LineThrough => parts.push("line-through".to_string()),
+                TextDecoration::Overline => parts.push("overline".to_string()),
+            }
+            match style.text_align {
+                TextAlign::Left => {}
+                TextAlign::Center => parts.push("align=center".to_string()),
+                TextAlign::Right => parts.push("align=right".to_string()),
+            }
+            match style.white_space {
+                WhiteSpace::Normal => {}
+                WhiteSpace::Nowrap => parts.push("white-space=nowrap".to_string()),
+                WhiteSpace::Pre => parts.push("white-space=pre".to_string()),
+                WhiteSpace::PreWrap => parts.push("white-space=pre-wrap".to_string()),
+                WhiteSpace::PreLine => parts.push("white-space=pre-line".to_string()),
+            }
+            match style.vertical_align {
+                VerticalAlign::Baseline => {}
+                VerticalAlign::Sub => parts.push("sub".to_string()),
+                VerticalAlign::Super => parts.push("super".to_string()),
+            }
+            if let Some(measured) = measured
+                && measured.lines.len() > 1
+            {
+                parts.push(format!("lines={}", measured.lines.len()));
+            }
+        }
+    }
+
+    parts.iter().map(|part| format!(" {part}")).collect()
+}
+
+fn fmt_style_node(
+    node: &NodeRef<HtmlNodeType>,
+    styles: &ResolvedStyles,
+    parent_chain: &ElementChain,
+    f: &mut fmt::Formatter<'_>,
+    ancestors_last: &mut Vec<bool>,
+) -> fmt::Result {
+    let node = node.borrow();
+    let chain = match &node.value {
+        HtmlNodeType::Document => {
+            write_tree_line(f, ancestors_last, &"#document")?;
+            parent_chain.clone()
+        }
+        HtmlNodeType::Element {
+            tag_name,
+            attributes,
+        } => {
+            let mut chain = parent_chain.clone();
+            chain.insert(0, cascade::element_info(tag_name, attributes));
+
+            let mut line = element_label(&chain[0]);
+            let matched = cascade::matched_declarations(styles, &chain);
+            let values = cascade::computed_values(&matched);
+            if !values.is_empty() {
+                let declarations: Vec<String> = values
+                    .iter()
+                    .map(|(name, value)| format!("{name}: {value}"))
+                    .collect();
+                line.push_str(&format!(" {{ {} }}", declarations.join("; ")));
+            }
+            write_tree_line(f, ancestors_last, &line)?;
+            chain
+        }
+        // 文字や注釈にはスタイルを当てない
+        _ => return Ok(()),
+    };
+
+    let elements: Vec<_> = node
+        .children()
+        .iter()
+        .filter(|child| matches!(child.borrow().value, HtmlNodeType::Element { .. }))
+        .collect();
+    for (i, child) in elements.iter().enumerate() {
+        ancestors_last.push(i == elements.len() - 1);
+        fmt_style_node(child, styles, &chain, f, ancestors_last)?;
+        ancestors_last.pop();
+    }
+    Ok(())
+}
+
+/// The element as a selector (`div#main.note`).
+fn element_label(element: &ElementInfo) -> String {
+    let mut label = element.tag_name.clone();
+    if let Some(id) = &element.id {
+        label.push('#');
+        label.push_str(id);
+    }
+    for class in &element.classes {
+        label.push('.');
+        label.push_str(class);
+    }
+    label
+}
+
+/// One value if all four sides are the same, otherwise `(top right bottom left)`.
+fn sides(values: [String; 4]) -> String {
+    if values.iter().all(|v| *v == values[0]) {
+        values[0].clone()
+    } else {
+        format!("({})", values.join(" "))
+    }
+}
+
+/// `#rrggbb`, or `#rrggbbaa` if the color is not opaque.
+fn hex(color: Color) -> String {
+    let Color(r, g, b, a) = color;
+    if a == 255 {
+        format!("#{r:02x}{g:02x}{b:02x}")
+    } else {
+        format!("#{r:02x}{g:02x}{b:02x}{a:02x}")
+    }
+}
+
+/// Rounds to two decimal places so that dumps stay stable across platforms.
+fn num(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    // -0 と 0 を同じに書く
+    format!("{}", rounded + 0.0)
+}
@@ -0,0 +1,66 @@
+//! Decoded image pass
+//!
+//! The tree is built from the DOM alone, so `<img>` elements and CSS background
+//! images only carry their (unresolved) URLs. Once the WebView has decoded
+//! them, this pass attaches the images to the render-info tree and sizes
+//! `<img>` elements whose `width` or `height` was not given.
+//!
+//! The caller must run layout after [`attach_images`].
+
+use std::sync::Arc;
+
+use image::RgbaImage;
+use ui_layout::{LayoutNode, Length};
+
+use super::types::{ContainerRole, InfoNode, NodeKind};
+
+/// Attach the images `image` returns for each `<img>` src and background URL.
+///
+/// An `<img>` with neither dimension set takes the image's size; with one set,
+/// the other keeps the image's aspect ratio.
+pub fn attach_images(
+    layout: &mut LayoutNode,
+    info: &mut InfoNode,
+    image: &dyn Fn(&str) -> Option<Arc<RgbaImage>>,
+) {
+    if let NodeKind::Container { style, role, .. } = &mut info.kind {
+        style.decoded_background_image = style.background_image.as_deref().and_then(image);
+        if let ContainerRole::Image {
+            src,
+            image: decoded,
+        } = role
+        {
+            *decoded = image(src);
+            if let Some(decoded) = decoded {
+                size_to_image(layout, decoded.width() as f32, decoded.height() as f32);
+            }
+        }
+    }
+
+    for (child_layout, child_info) in layout.children.iter_mut().zip(&mut info.children) {
+        attach_images(child_layout, child_info, image);
+    }
+}
+
+fn size_to_image(layout: &mut LayoutNode, width: f32, height: f32) {
+    let size = &mut layout.style.size;
+    let px = |length: &Length| match length {
+        Length::Px(px) => Some(*px),
+        _ => None,
+    };
+    let auto = |length: &Length| matches!(length, Length::Auto);
+    if auto(&size.width) && auto(&size.height) {
+        size.width = Length::Px(width);
+        size.height = Length::Px(height);
+    } else if let Some(w) = px(&size.width)
+        && auto(&size.height)
+        && width > 0.0
+    {
+        size.height = Length::Px(w * height / width);
+    } else if let Some(h) = px(&size.height)
+        && auto(&size.width)
+        && height > 0.0
+    {
+        size.width = Length::Px(h * width / height);
+    }
+}
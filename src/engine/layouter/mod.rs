@@ -4,7 +4,7 @@
 //!
 //! Responsibilities:
 //! - Style inheritance and cascade
-//! - Text measurement
+//! - Text measurement and wrapping
 //! - Incremental (diff-based) update of layout/info trees
 //!
 //! Out of scope:
@@ -13,8 +13,18 @@
 //! - GPU / platform concerns
 
 mod builder;
+pub mod cascade;
 pub mod css_resolver;
 mod diff;
+pub mod dump;
+mod images;
 pub mod types;
+pub mod ua;
+mod wrap;
 
-pub use builder::build_layout_and_info;
+pub use builder::{
+    VISITED_PROPERTIES, build_layout_and_info, build_layout_and_info_with_visited,
+    is_text_input_type,
+};
+pub use images::attach_images;
+pub use wrap::wrap_text;
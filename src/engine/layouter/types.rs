@@ -1,3 +1,11 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use image::RgbaImage;
+
+use crate::engine::bridge::text::LineFragment;
+
 /// InfoNode represents a node in the layout tree.
 /// It can be either a Container or Text node, each with its own properties and styles.
 #[derive(Debug, Clone)]
@@ -10,10 +18,52 @@ pub struct InfoNode {
 ///
 /// - Normal: A standard container with no special role.
 /// - Link: A container that acts as a hyperlink, containing a URL.
+/// - TextInput: A single-line text field. Its only child is the text it shows.
+/// - Button: A `<button>` or a button-like `<input>` that can be activated by clicking.
+/// - Frame: An `<iframe>`. It has no children; the framed document is drawn into its content box.
+/// - Image: An `<img>` with a `src` attribute (not yet resolved against the base URL).
+///   Once the WebView has decoded it, `image` is drawn into its content box.
+/// - Gauge: A `<progress>` or `<meter>`. It has no children; `fraction` (0.0–1.0) of its content
+///   box is filled with `color`, and its background is the track.
+/// - Summary: The `<summary>` of a `<details>`. Clicking it opens or closes the details;
+///   a disclosure triangle of `marker_size` is drawn in its left padding.
 #[derive(Debug, Clone, PartialEq)]
 pub enum ContainerRole {
     Normal,
-    Link { href: String },
+    Link {
+        href: String,
+    },
+    TextInput {
+        caret: Option<InputCaret>,
+    },
+    Button,
+    Frame,
+    Image {
+        src: String,
+        image: Option<Arc<RgbaImage>>,
+    },
+    Gauge {
+        fraction: f32,
+        color: Color,
+    },
+    Summary {
+        open: bool,
+        marker_size: f32,
+        marker_color: Color,
+    },
+}
+
+/// Caret and selection of the focused text field.
+///
+/// Offsets are byte offsets into the text shown by the field's child.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputCaret {
+    pub offset: usize,
+    pub selection: Option<Range<usize>>,
+    /// Text being composed with an IME, drawn underlined.
+    pub preedit: Option<Range<usize>>,
+    /// Words the spell checker flagged, drawn with a wavy underline.
+    pub misspelled: Vec<Range<usize>>,
 }
 
 /// Node kind of InfoNode
@@ -94,6 +144,23 @@ pub enum BorderStyle {
     Dotted,
 }
 
+/// CSS `overflow`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    #[default]
+    Visible,
+    Hidden,
+    Scroll,
+    Auto,
+}
+
+impl Overflow {
+    /// ユーザーがスクロールできるか
+    pub fn is_scrollable(self) -> bool {
+        matches!(self, Self::Scroll | Self::Auto)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct BorderColor {
     pub top: Color,
@@ -125,16 +192,26 @@ pub struct BorderStyles {
 #[derive(Debug, Clone, PartialEq)]
 pub struct ContainerStyle {
     pub background_color: Color,
+    /// URL in the `url()` of `background-image` (not yet resolved)
+    pub background_image: Option<String>,
+    /// `background_image` once the WebView has decoded it
+    pub decoded_background_image: Option<Arc<RgbaImage>>,
     pub border_color: BorderColor,
     pub border_style: BorderStyles,
+    pub overflow_x: Overflow,
+    pub overflow_y: Overflow,
 }
 
 impl Default for ContainerStyle {
     fn default() -> Self {
         Self {
             background_color: Color(0, 0, 0, 0),
+            background_image: None,
+            decoded_background_image: None,
             border_color: BorderColor::default(),
             border_style: BorderStyles::default(),
+            overflow_x: Overflow::Visible,
+            overflow_y: Overflow::Visible,
         }
     }
 }
@@ -159,6 +236,12 @@ pub struct MeasureCache {
     pub hash: u64,
     pub width: f32,
     pub height: f32,
+    /// Baseline of the first line, from the top of the text box
+    pub baseline: f32,
+    /// Width the text is currently wrapped to (None = unwrapped)
+    pub wrap_width: Option<f32>,
+    /// Layouted lines (byte ranges into the text)
+    pub lines: Vec<LineFragment>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -169,6 +252,50 @@ pub enum TextAlign {
     Right,
 }
 
+/// `white-space` の値
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhiteSpace {
+    /// 空白と改行をまとめて 1 つの空白にし、折り返す
+    #[default]
+    Normal,
+    /// 空白をまとめるが折り返さない
+    Nowrap,
+    /// 空白も改行もそのまま残し、折り返さない
+    Pre,
+    /// 空白も改行もそのまま残し、折り返す
+    PreWrap,
+    /// 改行だけ残して空白はまとめ、折り返す
+    PreLine,
+}
+
+impl WhiteSpace {
+    /// 続く空白を 1 つにまとめるか
+    pub fn collapses_spaces(self) -> bool {
+        matches!(self, Self::Normal | Self::Nowrap | Self::PreLine)
+    }
+
+    /// 改行を行の区切りとして残すか
+    pub fn preserves_newlines(self) -> bool {
+        matches!(self, Self::Pre | Self::PreWrap | Self::PreLine)
+    }
+
+    /// 幅に収まらないときに折り返すか
+    pub fn wraps(self) -> bool {
+        matches!(self, Self::Normal | Self::PreWrap | Self::PreLine)
+    }
+}
+
+/// `vertical-align` の値（文字だけに効く）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerticalAlign {
+    #[default]
+    Baseline,
+    /// 下付き（`<sub>`）
+    Sub,
+    /// 上付き（`<sup>`）
+    Super,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum TextDecoration {
     #[default]
@@ -202,12 +329,81 @@ impl Default for FontWeight {
     }
 }
 
+/// Maximum number of distinct `font-family` lists kept by [`FontFamilyList`].
+///
+/// Ids are never reused (the font matcher and text caches are keyed by them),
+/// so once the table is full new lists fall back to the default font.
+pub const MAX_FONT_FAMILY_LISTS: usize = 4096;
+
+/// Interned CSS `font-family` list.
+///
+/// `TextStyle` is `Copy` and is copied for every text node, so the family
+/// names are stored once in a process-wide table and referenced by id.
+/// The default value is the empty list, meaning "use the default font".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FontFamilyList(u32);
+
+/// The interned lists, with an index from names to id.
+struct FontFamilyTable {
+    lists: Vec<Arc<[String]>>,
+    ids: HashMap<Arc<[String]>, u32>,
+}
+
+impl FontFamilyList {
+    fn table() -> &'static Mutex<FontFamilyTable> {
+        static TABLE: OnceLock<Mutex<FontFamilyTable>> = OnceLock::new();
+        TABLE.get_or_init(|| {
+            Mutex::new(FontFamilyTable {
+                lists: vec![Arc::from(Vec::new())],
+                ids: HashMap::new(),
+            })
+        })
+    }
+
+    /// Intern a list of family names (in priority order).
+    ///
+    /// Returns the default list when [`MAX_FONT_FAMILY_LISTS`] lists are
+    /// already interned.
+    pub fn intern(names: &[String]) -> Self {
+        if names.is_empty() {
+            return Self::default();
+        }
+
+        let mut table = Self::table().lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&id) = table.ids.get(names) {
+            return Self(id);
+        }
+        if table.lists.len() >= MAX_FONT_FAMILY_LISTS {
+            log::debug!("Too many font-family lists; using the default font for {names:?}");
+            return Self::default();
+        }
+        let id = table.lists.len() as u32;
+        let list: Arc<[String]> = Arc::from(names.to_vec());
+        table.lists.push(list.clone());
+        table.ids.insert(list, id);
+        Self(id)
+    }
+
+    /// Family names in priority order.
+    pub fn names(&self) -> Arc<[String]> {
+        let table = Self::table().lock().unwrap_or_else(|e| e.into_inner());
+        table
+            .lists
+            .get(self.0 as usize)
+            .cloned()
+            .unwrap_or_else(|| Arc::from(Vec::new()))
+    }
+}
+
 #[derive(Copy, Debug, Clone, Default, PartialEq)]
 pub struct TextStyle {
     pub font_size: f32,
+    pub font_family: FontFamilyList,
     pub text_align: TextAlign,
     pub text_decoration: TextDecoration,
     pub font_style: FontStyle,
     pub font_weight: FontWeight,
     pub color: Color,
+    pub white_space: WhiteSpace,
+    pub vertical_align: VerticalAlign,
 }
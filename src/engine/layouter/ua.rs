@@ -0,0 +1,43 @@
+//! User agent stylesheet
+//!
+//! The browser's default styles (block and inline elements, headings, lists,
+//! tables, form controls, ...) live in `resource/user-agent.css` and go through
+//! the same parser and cascade as author stylesheets, so they can be read and
+//! extended without touching the layout builder. The sheet is parsed once, the
+//! first time it is used.
+
+use std::sync::OnceLock;
+
+use crate::engine::css::media::MediaContext;
+use crate::engine::css::parser::{CssNode, Parser as CssParser};
+use crate::engine::diagnostics::{self, Level, Source};
+
+use super::css_resolver::{CssResolver, ResolvedStyles, StyleOrigin};
+
+/// The source of the user agent stylesheet.
+pub const USER_AGENT_CSS: &str = include_str!("../../../resource/user-agent.css");
+
+/// The parsed user agent stylesheet (`None` if it failed to parse).
+pub fn user_agent_sheet() -> Option<&'static CssNode> {
+    static SHEET: OnceLock<Option<CssNode>> = OnceLock::new();
+    SHEET
+        .get_or_init(|| match CssParser::new(USER_AGENT_CSS).parse() {
+            Ok(sheet) => Some(sheet),
+            Err(e) => {
+                diagnostics::report(
+                    Level::Error,
+                    Source::Css,
+                    format!("Failed to parse the user agent stylesheet: {e}"),
+                );
+                None
+            }
+        })
+        .as_ref()
+}
+
+/// The user agent declarations that apply under `media`.
+pub fn user_agent_styles(media: &MediaContext) -> ResolvedStyles {
+    user_agent_sheet()
+        .map(|sheet| CssResolver::resolve_with_origin(sheet, media, StyleOrigin::UserAgent))
+        .unwrap_or_default()
+}
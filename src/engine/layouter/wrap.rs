@@ -0,0 +1,97 @@
+//! Text wrapping pass
+//!
+//! Text nodes are measured unconstrained while the tree is built, because the
+//! width available to them is only known after layout. This pass re-measures
+//! text that overflows its containing block with wrapping enabled (UAX #14
+//! break opportunities, see [`text::TextMeasurer`]) and updates the node size.
+//!
+//! The caller must run layout again when [`wrap_text`] returns `true`.
+
+use ui_layout::{LayoutNode, Length};
+
+use crate::engine::bridge::text;
+
+use super::types::{ContainerRole, InfoNode, NodeKind, TextStyle};
+
+/// Tolerance for comparing measured widths against the available width
+const WRAP_EPSILON: f32 = 0.5;
+
+/// Wrap overflowing text nodes to the width of their containing block.
+///
+/// Text that fits again (e.g. after the viewport grew) is unwrapped.
+/// Returns `true` if any text node changed size.
+pub fn wrap_text(
+    layout: &mut LayoutNode,
+    info: &mut InfoNode,
+    measurer: &dyn text::TextMeasurer<TextStyle>,
+) -> bool {
+    wrap_node(layout, info, measurer, None)
+}
+
+/// `available` is the content width of the parent (child boxes are relative to it).
+fn wrap_node(
+    layout: &mut LayoutNode,
+    info: &mut InfoNode,
+    measurer: &dyn text::TextMeasurer<TextStyle>,
+    available: Option<f32>,
+) -> bool {
+    if let NodeKind::Text {
+        text,
+        style,
+        measured: Some(measured),
+    } = &mut info.kind
+    {
+        let (Some(available), Some(rect)) =
+            (available, layout.layout_boxes.first().map(|b| b.border_box))
+        else {
+            return false;
+        };
+
+        // 最低でも 1 文字分の幅は確保する
+        let line_width = (available - rect.x).max(style.font_size);
+        // white-space: pre や nowrap のテキストははみ出したままにする
+        let target = (style.white_space.wraps() && measured.width > line_width + WRAP_EPSILON)
+            .then_some(line_width);
+
+        if target == measured.wrap_width {
+            return false;
+        }
+
+        let req = text::TextMeasureRequest {
+            text: text.clone(),
+            style: *style,
+            max_width: target,
+            wrap: target.is_some(),
+        };
+        let Ok(metrics) = measurer.measure(&req) else {
+            return false;
+        };
+
+        // measured.width / height は折り返し前の大きさのまま残す
+        measured.wrap_width = target;
+        measured.baseline = metrics.baseline;
+        measured.lines = metrics.lines;
+
+        layout.style.size.width = Length::Px(metrics.width);
+        layout.style.size.height = Length::Px(metrics.height);
+
+        return true;
+    }
+
+    // 1 行の入力欄の中身は折り返さずに横にスクロールさせる
+    if let NodeKind::Container {
+        role: ContainerRole::TextInput { .. },
+        ..
+    } = &info.kind
+    {
+        return false;
+    }
+
+    let content_width = layout.layout_boxes.first().map(|b| b.content_box.width);
+
+    let mut changed = false;
+    for (child_layout, child_info) in layout.children.iter_mut().zip(info.children.iter_mut()) {
+        changed |= wrap_node(child_layout, child_info, measurer, content_width);
+    }
+    changed
+}
@@ -1,7 +1,12 @@
+pub mod accessibility;
 pub mod bridge;
 pub mod css;
+pub mod diagnostics;
+pub mod events;
+pub mod fuzz;
 pub mod html;
 pub mod input;
 pub mod layouter;
 pub mod renderer_model;
+pub mod script;
 pub mod tree;
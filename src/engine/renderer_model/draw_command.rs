@@ -1,7 +1,15 @@
-use crate::engine::layouter::types::{Color, InfoNode, NodeKind, TextDecoration, TextStyle};
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+use crate::engine::bridge::text::LineFragment;
+use crate::engine::input::selection::{self, Selection};
+use crate::engine::layouter::types::{
+    Color, ContainerRole, InfoNode, InputCaret, NodeKind, TextDecoration, TextStyle,
+};
 use ui_layout::LayoutNode;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DrawCommand {
     DrawText {
         x: f32,
@@ -27,6 +35,14 @@ pub enum DrawCommand {
         radius_y: f32,
         color: Color,
     },
+    /// image を (x, y) から width × height に伸縮して描く
+    DrawImage {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        image: Arc<RgbaImage>,
+    },
     PushClip {
         x: f32,
         y: f32,
@@ -41,46 +57,227 @@ pub enum DrawCommand {
     PopTransform,
 }
 
+impl DrawCommand {
+    /// 座標と大きさを factor 倍にする
+    pub fn scaled(self, factor: f32) -> Self {
+        match self {
+            Self::DrawText {
+                x,
+                y,
+                text,
+                mut style,
+                max_width,
+            } => {
+                style.font_size *= factor;
+                Self::DrawText {
+                    x: x * factor,
+                    y: y * factor,
+                    text,
+                    style,
+                    max_width: max_width * factor,
+                }
+            }
+            Self::DrawRect {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => Self::DrawRect {
+                x: x * factor,
+                y: y * factor,
+                width: width * factor,
+                height: height * factor,
+                color,
+            },
+            Self::DrawPolygon { points, color } => Self::DrawPolygon {
+                points: points
+                    .into_iter()
+                    .map(|(x, y)| (x * factor, y * factor))
+                    .collect(),
+                color,
+            },
+            Self::DrawEllipse {
+                center,
+                radius_x,
+                radius_y,
+                color,
+            } => Self::DrawEllipse {
+                center: (center.0 * factor, center.1 * factor),
+                radius_x: radius_x * factor,
+                radius_y: radius_y * factor,
+                color,
+            },
+            Self::DrawImage {
+                x,
+                y,
+                width,
+                height,
+                image,
+            } => Self::DrawImage {
+                x: x * factor,
+                y: y * factor,
+                width: width * factor,
+                height: height * factor,
+                image,
+            },
+            Self::PushClip {
+                x,
+                y,
+                width,
+                height,
+            } => Self::PushClip {
+                x: x * factor,
+                y: y * factor,
+                width: width * factor,
+                height: height * factor,
+            },
+            Self::PushTransform { dx, dy } => Self::PushTransform {
+                dx: dx * factor,
+                dy: dy * factor,
+            },
+            Self::PopClip => Self::PopClip,
+            Self::PopTransform => Self::PopTransform,
+        }
+    }
+}
+
 /// LayoutNode + InfoNode → DrawCommand
 pub fn generate_draw_commands(layout: &LayoutNode, info: &InfoNode) -> Vec<DrawCommand> {
+    generate_draw_commands_with_selection(layout, info, None, None)
+}
+
+/// LayoutNode + InfoNode → DrawCommand（選択範囲のハイライトとフォーカスリング付き）
+///
+/// focus_ring はフォーカスリングを描く要素のパス。
+pub fn generate_draw_commands_with_selection(
+    layout: &LayoutNode,
+    info: &InfoNode,
+    selection: Option<&Selection>,
+    focus_ring: Option<&[usize]>,
+) -> Vec<DrawCommand> {
+    generate_draw_commands_with_frames(layout, info, selection, focus_ring, &|_| None)
+}
+
+/// generate_draw_commands_with_selection に `<iframe>` の中の文書を加えたもの
+///
+/// frames は iframe 要素のパスを受け取り、中の文書の描画コマンド（iframe の
+/// content box の左上が原点）を返す。コマンドは content box で切り抜いて描く。
+pub fn generate_draw_commands_with_frames(
+    layout: &LayoutNode,
+    info: &InfoNode,
+    selection: Option<&Selection>,
+    focus_ring: Option<&[usize]>,
+    frames: &dyn Fn(&[usize]) -> Option<Vec<DrawCommand>>,
+) -> Vec<DrawCommand> {
     let mut commands = Vec::new();
+    push_draw_commands(
+        layout,
+        info,
+        selection,
+        focus_ring,
+        frames,
+        &mut Vec::new(),
+        &mut commands,
+    );
+    commands
+}
 
+fn push_draw_commands(
+    layout: &LayoutNode,
+    info: &InfoNode,
+    selection: Option<&Selection>,
+    focus_ring: Option<&[usize]>,
+    frames: &dyn Fn(&[usize]) -> Option<Vec<DrawCommand>>,
+    path: &mut Vec<usize>,
+    commands: &mut Vec<DrawCommand>,
+) {
     match &info.kind {
-        NodeKind::Text { text, style, .. } => {
+        NodeKind::Text {
+            text,
+            style,
+            measured,
+        } => {
             for box_model in &layout.layout_boxes {
                 let rect = box_model.padding_box;
 
                 let abs_x = rect.x;
                 let abs_y = rect.y;
 
-                // テキスト
-                commands.push(DrawCommand::DrawText {
-                    x: abs_x,
-                    y: abs_y,
-                    text: text.clone(),
-                    style: *style,
-                    max_width: rect.width,
-                });
+                // 折り返されたテキストは行ごとに描く
+                let lines: Vec<(&str, f32)> = match measured {
+                    Some(m) if m.lines.len() > 1 => m
+                        .lines
+                        .iter()
+                        .filter_map(|l| {
+                            text.get(l.start..l.end)
+                                .map(|s| (s.trim_end_matches(['\n', '\r']), l.width))
+                        })
+                        .collect(),
+                    _ => vec![(text.as_str(), rect.width)],
+                };
+                let line_height = rect.height / lines.len().max(1) as f32;
+
+                // 選択ハイライト（テキストより先に描く）
+                if let Some(range) = selection.and_then(|s| s.range_in(path, text.len())) {
+                    let rects = match measured {
+                        Some(m) if !m.lines.is_empty() => {
+                            selection::highlight_rects(&m.lines, line_height, &range)
+                        }
+                        _ => vec![(0.0, 0.0, rect.width, rect.height)],
+                    };
+
+                    for (x, y, width, height) in rects {
+                        commands.push(DrawCommand::DrawRect {
+                            x: abs_x + x,
+                            y: abs_y + y,
+                            width,
+                            height,
+                            color: selection::SELECTION_COLOR,
+                        });
+                    }
+                }
 
-                // テキストデコレーション
                 let font_size = style.font_size;
                 let line_thickness = (font_size * 0.08).max(1.0);
+                // シェーピング結果のベースライン（未計測なら概算）
+                let baseline = measured
+                    .as_ref()
+                    .map(|m| m.baseline)
+                    .unwrap_or(font_size * 0.8);
 
-                let (line_y, draw) = match style.text_decoration {
-                    TextDecoration::None => (0.0, false),
-                    TextDecoration::Underline => (abs_y + font_size, true),
-                    TextDecoration::LineThrough => (abs_y + font_size * 0.5, true),
-                    TextDecoration::Overline => (abs_y, true),
-                };
+                for (i, (line_text, line_width)) in lines.into_iter().enumerate() {
+                    let line_y = abs_y + line_height * i as f32;
 
-                if draw {
-                    commands.push(DrawCommand::DrawRect {
+                    // テキスト
+                    commands.push(DrawCommand::DrawText {
                         x: abs_x,
                         y: line_y,
-                        width: rect.width,
-                        height: line_thickness,
-                        color: style.color,
+                        text: line_text.to_string(),
+                        style: *style,
+                        max_width: rect.width,
                     });
+
+                    // テキストデコレーション
+                    let (deco_y, draw) = match style.text_decoration {
+                        TextDecoration::None => (0.0, false),
+                        TextDecoration::Underline => (line_y + baseline + line_thickness, true),
+                        TextDecoration::LineThrough => (line_y + baseline - font_size * 0.3, true),
+                        // ベースラインから 1em 上（ascent の概算）
+                        TextDecoration::Overline => {
+                            (line_y + (baseline - font_size).max(0.0), true)
+                        }
+                    };
+
+                    if draw {
+                        commands.push(DrawCommand::DrawRect {
+                            x: abs_x,
+                            y: deco_y,
+                            width: line_width,
+                            height: line_thickness,
+                            color: style.color,
+                        });
+                    }
                 }
             }
         }
@@ -89,6 +286,7 @@ pub fn generate_draw_commands(layout: &LayoutNode, info: &InfoNode) -> Vec<DrawC
             scroll_offset_x,
             scroll_offset_y,
             style,
+            role,
             ..
         } => {
             for box_model in &layout.layout_boxes {
@@ -148,6 +346,11 @@ pub fn generate_draw_commands(layout: &LayoutNode, info: &InfoNode) -> Vec<DrawC
                     color: bc.right,
                 });
 
+                // ===== focus ring (border box の外側) =====
+                if focus_ring == Some(path.as_slice()) {
+                    commands.extend(focus_ring_rects(border_box.width, border_box.height));
+                }
+
                 // ===== clip + background + content =====
                 commands.push(DrawCommand::PushClip {
                     x: padding_box.x - border_box.x,
@@ -164,6 +367,16 @@ pub fn generate_draw_commands(layout: &LayoutNode, info: &InfoNode) -> Vec<DrawC
                     height: padding_box.height,
                     color: style.background_color,
                 });
+                // NOTE: background-repeat と background-size は未実装。画像の大きさで 1 度だけ描く
+                if let Some(image) = &style.decoded_background_image {
+                    commands.push(DrawCommand::DrawImage {
+                        x: padding_box.x - border_box.x,
+                        y: padding_box.y - border_box.y,
+                        width: image.width() as f32,
+                        height: image.height() as f32,
+                        image: image.clone(),
+                    });
+                }
 
                 // content + scroll
                 commands.push(DrawCommand::PushTransform {
@@ -171,15 +384,128 @@ pub fn generate_draw_commands(layout: &LayoutNode, info: &InfoNode) -> Vec<DrawC
                     dy: content_box.y - border_box.y,
                 });
                 commands.push(DrawCommand::PushTransform {
-                    dx: *scroll_offset_x,
+                    dx: -*scroll_offset_x,
                     dy: -*scroll_offset_y,
                 });
             }
+
+            // <iframe> の中の文書
+            if *role == ContainerRole::Frame
+                && let Some(content) = layout.layout_boxes.first().map(|b| b.content_box)
+                && let Some(frame_commands) = frames(path)
+            {
+                commands.push(DrawCommand::PushClip {
+                    x: 0.0,
+                    y: 0.0,
+                    width: content.width,
+                    height: content.height,
+                });
+                commands.extend(frame_commands);
+                commands.push(DrawCommand::PopClip);
+            }
+
+            // <progress> と <meter> の埋まった部分（残りは背景色が見える）
+            if let ContainerRole::Gauge { fraction, color } = role
+                && let Some(content) = layout.layout_boxes.first().map(|b| b.content_box)
+            {
+                commands.push(DrawCommand::DrawRect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: content.width * fraction.clamp(0.0, 1.0),
+                    height: content.height,
+                    color: *color,
+                });
+            }
+
+            // <img> の画像（content box いっぱいに描く）
+            if let ContainerRole::Image {
+                image: Some(image), ..
+            } = role
+                && let Some(content) = layout.layout_boxes.first().map(|b| b.content_box)
+            {
+                commands.push(DrawCommand::DrawImage {
+                    x: 0.0,
+                    y: 0.0,
+                    width: content.width,
+                    height: content.height,
+                    image: image.clone(),
+                });
+            }
+
+            // <summary> の開閉の三角（左の padding に描く）
+            if let ContainerRole::Summary {
+                open,
+                marker_size,
+                marker_color,
+            } = role
+                && let Some(b) = layout.layout_boxes.first()
+            {
+                commands.push(disclosure_marker(
+                    b.padding_box.x - b.content_box.x,
+                    *open,
+                    *marker_size,
+                    *marker_color,
+                ));
+            }
+
+            // 入力欄の選択範囲（文字より先に描く）
+            if let Some((caret, text)) = focused_input(layout, info) {
+                if let Some(range) = &caret.selection {
+                    let start = text.x + text.x_at(range.start);
+                    let end = text.x + text.x_at(range.end);
+                    commands.push(DrawCommand::DrawRect {
+                        x: start,
+                        y: text.y,
+                        width: end - start,
+                        height: text.height,
+                        color: selection::SELECTION_COLOR,
+                    });
+                }
+            }
         }
     }
 
-    for (child_layout, child_info) in layout.children.iter().zip(&info.children) {
-        commands.extend(generate_draw_commands(child_layout, child_info));
+    for (i, (child_layout, child_info)) in layout.children.iter().zip(&info.children).enumerate() {
+        path.push(i);
+        push_draw_commands(
+            child_layout,
+            child_info,
+            selection,
+            focus_ring,
+            frames,
+            path,
+            commands,
+        );
+        path.pop();
+    }
+
+    // 入力欄の綴りの誤り、IME で変換中の文字列の下線とキャレット（文字の上に描く）
+    if let Some((caret, text)) = focused_input(layout, info) {
+        for range in &caret.misspelled {
+            commands.extend(squiggle(
+                text.x + text.x_at(range.start),
+                text.x + text.x_at(range.end),
+                text.y + text.height - SQUIGGLE_HEIGHT,
+            ));
+        }
+        if let Some(range) = &caret.preedit {
+            let start = text.x + text.x_at(range.start);
+            let end = text.x + text.x_at(range.end);
+            commands.push(DrawCommand::DrawRect {
+                x: start,
+                y: text.y + text.height - PREEDIT_UNDERLINE_WIDTH,
+                width: end - start,
+                height: PREEDIT_UNDERLINE_WIDTH,
+                color: text.color,
+            });
+        }
+        commands.push(DrawCommand::DrawRect {
+            x: text.x + text.x_at(caret.offset),
+            y: text.y,
+            width: CARET_WIDTH,
+            height: text.height,
+            color: text.color,
+        });
     }
 
     // Pop commands for containers
@@ -191,6 +517,156 @@ pub fn generate_draw_commands(layout: &LayoutNode, info: &InfoNode) -> Vec<DrawC
             commands.push(DrawCommand::PopTransform);
         }
     }
+}
 
+/// 入力欄のキャレットの幅
+const CARET_WIDTH: f32 = 1.0;
+/// IME で変換中の文字列の下線の太さ
+const PREEDIT_UNDERLINE_WIDTH: f32 = 1.0;
+
+/// 綴りの誤りの波線の色、山の高さ、山 1 つの幅
+const SQUIGGLE_COLOR: Color = Color(220, 38, 38, 255);
+const SQUIGGLE_HEIGHT: f32 = 2.0;
+const SQUIGGLE_PERIOD: f32 = 4.0;
+
+/// x0 から x1 までの、上端が y の波線（ジグザグの線分を細い四角形で描く）
+fn squiggle(x0: f32, x1: f32, y: f32) -> Vec<DrawCommand> {
+    let half = SQUIGGLE_PERIOD / 2.0;
+    let mut commands = Vec::new();
+    let mut x = x0;
+    let mut up = false;
+    while x < x1 {
+        let next = (x + half).min(x1);
+        let rise = (next - x) / half * SQUIGGLE_HEIGHT;
+        let (y_start, y_end) = if up {
+            (y + SQUIGGLE_HEIGHT, y + SQUIGGLE_HEIGHT - rise)
+        } else {
+            (y, y + rise)
+        };
+        commands.push(DrawCommand::DrawPolygon {
+            points: vec![
+                (x, y_start),
+                (next, y_end),
+                (next, y_end + 1.0),
+                (x, y_start + 1.0),
+            ],
+            color: SQUIGGLE_COLOR,
+        });
+        x = next;
+        up = !up;
+    }
     commands
 }
+
+/// キーボードでフォーカスした要素を囲む線
+const FOCUS_RING_COLOR: Color = Color(16, 103, 220, 255);
+const FOCUS_RING_WIDTH: f32 = 2.0;
+/// border box とフォーカスリングの間隔
+const FOCUS_RING_OFFSET: f32 = 1.0;
+
+/// 幅 width、高さ height の box（原点が左上）を囲むフォーカスリング
+fn focus_ring_rects(width: f32, height: f32) -> [DrawCommand; 4] {
+    let outset = FOCUS_RING_OFFSET + FOCUS_RING_WIDTH;
+    let (x, y) = (-outset, -outset);
+    let (outer_width, outer_height) = (width + outset * 2.0, height + outset * 2.0);
+    let rect = |x, y, width, height| DrawCommand::DrawRect {
+        x,
+        y,
+        width,
+        height,
+        color: FOCUS_RING_COLOR,
+    };
+
+    [
+        rect(x, y, outer_width, FOCUS_RING_WIDTH),
+        rect(
+            x,
+            y + outer_height - FOCUS_RING_WIDTH,
+            outer_width,
+            FOCUS_RING_WIDTH,
+        ),
+        rect(x, y, FOCUS_RING_WIDTH, outer_height),
+        rect(
+            x + outer_width - FOCUS_RING_WIDTH,
+            y,
+            FOCUS_RING_WIDTH,
+            outer_height,
+        ),
+    ]
+}
+
+/// 三角の大きさに対する 1 行目の真ん中の高さ（三角は 0.5em、行の高さは 1.2em）
+const SUMMARY_MARKER_LINE_CENTER: f32 = 1.2;
+
+/// `<summary>` の content 座標で、左の padding（幅 -left）の真ん中に描く開閉の三角
+///
+/// 閉じていれば右向き、開いていれば下向き。縦は 1 行目の真ん中に合わせる。
+fn disclosure_marker(left: f32, open: bool, size: f32, color: Color) -> DrawCommand {
+    let half = size / 2.0;
+    let (cx, cy) = (left / 2.0, size * SUMMARY_MARKER_LINE_CENTER);
+    let points = if open {
+        vec![
+            (cx - half, cy - half),
+            (cx + half, cy - half),
+            (cx, cy + half),
+        ]
+    } else {
+        vec![
+            (cx - half, cy - half),
+            (cx + half, cy),
+            (cx - half, cy + half),
+        ]
+    };
+    DrawCommand::DrawPolygon { points, color }
+}
+
+/// 入力欄に表示している文字列の位置（入力欄の content 座標）
+struct InputText<'a> {
+    x: f32,
+    y: f32,
+    height: f32,
+    color: Color,
+    line: Option<&'a LineFragment>,
+}
+
+impl InputText<'_> {
+    fn x_at(&self, offset: usize) -> f32 {
+        self.line.map(|l| l.x_at(offset)).unwrap_or(0.0)
+    }
+}
+
+/// フォーカスのある入力欄ならキャレットと表示している文字列を返す
+fn focused_input<'a>(
+    layout: &'a LayoutNode,
+    info: &'a InfoNode,
+) -> Option<(&'a InputCaret, InputText<'a>)> {
+    let NodeKind::Container {
+        role: ContainerRole::TextInput { caret: Some(caret) },
+        ..
+    } = &info.kind
+    else {
+        return None;
+    };
+    let content = layout.layout_boxes.first()?.content_box;
+
+    let (text_layout, text_info) = layout.children.first().zip(info.children.first())?;
+    let NodeKind::Text {
+        style, measured, ..
+    } = &text_info.kind
+    else {
+        return None;
+    };
+    let rect = text_layout.layout_boxes.first()?.padding_box;
+
+    Some((
+        caret,
+        InputText {
+            x: rect.x,
+            y: 0.0,
+            // 文字列が空でも入力欄の高さいっぱいに描く
+            height: content.height.max(rect.height),
+            color: style.color,
+            line: measured.as_ref().and_then(|m| m.lines.first()),
+        },
+    ))
+}
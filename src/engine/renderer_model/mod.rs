@@ -1,3 +1,7 @@
 mod draw_command;
+pub mod paginate;
 
-pub use draw_command::{DrawCommand, generate_draw_commands};
+pub use draw_command::{
+    DrawCommand, generate_draw_commands, generate_draw_commands_with_frames,
+    generate_draw_commands_with_selection,
+};
@@ -0,0 +1,230 @@
+//! 印刷のページ分け
+//!
+//! 紙の幅でレイアウトした文書を紙の高さごとに区切り、ページごとの描画コマンドにする。
+//! テキストの行や入力欄、ボタン、画像の途中ではなるべく区切らず、その上で次のページに送る。
+
+use ui_layout::LayoutNode;
+
+use super::DrawCommand;
+use crate::engine::layouter::types::{ContainerRole, InfoNode, NodeKind};
+
+/// 紙の大きさと余白（CSS px、1in = 96px）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaperSize {
+    pub width: f32,
+    pub height: f32,
+    /// 上下左右の余白
+    pub margin: f32,
+}
+
+impl PaperSize {
+    /// A4（210mm × 297mm）、余白 0.5in
+    pub const A4: Self = Self {
+        width: 793.7,
+        height: 1122.5,
+        margin: 48.0,
+    };
+    /// US Letter（8.5in × 11in）、余白 0.5in
+    pub const LETTER: Self = Self {
+        width: 816.0,
+        height: 1056.0,
+        margin: 48.0,
+    };
+
+    /// 余白を除いた、ページの中身を描く部分の大きさ
+    pub fn content_size(&self) -> (f32, f32) {
+        (
+            (self.width - self.margin * 2.0).max(1.0),
+            (self.height - self.margin * 2.0).max(1.0),
+        )
+    }
+}
+
+/// 区切り位置の誤差（これより小さくはみ出しただけならページを増やさない）
+const EPSILON: f32 = 0.5;
+
+/// 文書の一番下の y 座標（ページの座標）
+pub fn content_height(layout: &LayoutNode, info: &InfoNode) -> f32 {
+    Blocks::collect(layout, info).bottom
+}
+
+/// 各ページの始まりの y 座標（最初のページは 0）
+///
+/// 区切り位置にまたがる行などがあれば、その上端で区切る。ページより高いものは
+/// 次のページに送っても収まらないので、途中で区切る。
+pub fn page_breaks(layout: &LayoutNode, info: &InfoNode, page_height: f32) -> Vec<f32> {
+    let Blocks { spans, bottom } = Blocks::collect(layout, info);
+
+    let mut breaks = vec![0.0];
+    let mut start = 0.0;
+    while bottom - start > page_height + EPSILON {
+        let mut end = start + page_height;
+        // ずらした先でまた別のものにまたがることがあるので、またがらなくなるまで繰り返す
+        while let Some(top) = spans
+            .iter()
+            .filter(|&&(top, span_bottom)| {
+                top > start + EPSILON
+                    && top < end
+                    && span_bottom > end
+                    && span_bottom - top <= page_height
+            })
+            .map(|&(top, _)| top)
+            .reduce(f32::min)
+        {
+            end = top;
+        }
+        breaks.push(end);
+        start = end;
+    }
+    breaks
+}
+
+/// 描画コマンドをページごとに分ける
+///
+/// 各ページのコマンドはページの中身の左上が原点で、ページの範囲で切り抜く。
+/// ページに掛からない図形とテキストは省く。
+pub fn paginate(
+    layout: &LayoutNode,
+    info: &InfoNode,
+    commands: &[DrawCommand],
+    page: (f32, f32),
+) -> Vec<Vec<DrawCommand>> {
+    let breaks = page_breaks(layout, info, page.1);
+    breaks
+        .iter()
+        .enumerate()
+        .map(|(i, &top)| {
+            let bottom = breaks.get(i + 1).copied().unwrap_or(top + page.1);
+            page_commands(commands, top, bottom, page.0)
+        })
+        .collect()
+}
+
+/// y が top から bottom までの部分を 1 ページにする
+fn page_commands(commands: &[DrawCommand], top: f32, bottom: f32, width: f32) -> Vec<DrawCommand> {
+    let mut out = vec![
+        DrawCommand::PushClip {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height: bottom - top,
+        },
+        DrawCommand::PushTransform { dx: 0.0, dy: -top },
+    ];
+
+    // PushTransform で積まれた y のずれ
+    let mut offsets = vec![0.0];
+    for command in commands {
+        let dy = offsets.last().copied().unwrap_or(0.0);
+        let visible = match command {
+            DrawCommand::PushTransform { dy: d, .. } => {
+                offsets.push(dy + d);
+                true
+            }
+            DrawCommand::PopTransform => {
+                offsets.pop();
+                true
+            }
+            DrawCommand::PushClip { .. } | DrawCommand::PopClip => true,
+            command => {
+                vertical_extent(command).is_some_and(|(y0, y1)| dy + y1 > top && dy + y0 < bottom)
+            }
+        };
+        if visible {
+            out.push(command.clone());
+        }
+    }
+
+    out.push(DrawCommand::PopTransform);
+    out.push(DrawCommand::PopClip);
+    out
+}
+
+/// 図形とテキストの上端と下端
+fn vertical_extent(command: &DrawCommand) -> Option<(f32, f32)> {
+    match command {
+        // 行の高さは font-size の 1.2 倍程度
+        DrawCommand::DrawText { y, style, .. } => Some((*y, y + style.font_size * 1.2)),
+        DrawCommand::DrawRect { y, height, .. } | DrawCommand::DrawImage { y, height, .. } => {
+            Some((*y, y + height))
+        }
+        DrawCommand::DrawPolygon { points, .. } => {
+            let ys = points.iter().map(|&(_, y)| y);
+            Some((
+                ys.clone().fold(f32::INFINITY, f32::min),
+                ys.fold(f32::NEG_INFINITY, f32::max),
+            ))
+        }
+        DrawCommand::DrawEllipse {
+            center, radius_y, ..
+        } => Some((center.1 - radius_y, center.1 + radius_y)),
+        _ => None,
+    }
+}
+
+/// 文書の中の箱の縦の位置
+struct Blocks {
+    /// 途中で区切りたくないもの（テキストの行、入力欄、ボタン、`<iframe>`、`<img>`）の上端と下端
+    spans: Vec<(f32, f32)>,
+    /// 一番下の箱の下端
+    bottom: f32,
+}
+
+impl Blocks {
+    fn collect(layout: &LayoutNode, info: &InfoNode) -> Self {
+        let mut blocks = Self {
+            spans: Vec::new(),
+            bottom: 0.0,
+        };
+        blocks.visit(layout, info, 0.0);
+        blocks
+    }
+
+    /// origin_y は layout の座標の原点（親の content box の上端からスクロール位置を引いたもの）
+    fn visit(&mut self, layout: &LayoutNode, info: &InfoNode, origin_y: f32) {
+        for box_model in &layout.layout_boxes {
+            let rect = box_model.border_box;
+            self.bottom = self.bottom.max(origin_y + rect.y + rect.height);
+        }
+
+        match &info.kind {
+            NodeKind::Text { measured, .. } => {
+                for box_model in &layout.layout_boxes {
+                    let rect = box_model.padding_box;
+                    let lines = measured.as_ref().map_or(1, |m| m.lines.len().max(1));
+                    let line_height = rect.height / lines as f32;
+                    for i in 0..lines {
+                        let top = origin_y + rect.y + line_height * i as f32;
+                        self.spans.push((top, top + line_height));
+                    }
+                }
+            }
+            NodeKind::Container {
+                scroll_offset_y,
+                role,
+                ..
+            } => {
+                let Some(box_model) = layout.layout_boxes.first() else {
+                    return;
+                };
+                if matches!(
+                    role,
+                    ContainerRole::TextInput { .. }
+                        | ContainerRole::Button
+                        | ContainerRole::Frame
+                        | ContainerRole::Image { .. }
+                        | ContainerRole::Gauge { .. }
+                ) {
+                    let rect = box_model.border_box;
+                    self.spans
+                        .push((origin_y + rect.y, origin_y + rect.y + rect.height));
+                    return;
+                }
+                let child_origin = origin_y + box_model.content_box.y - scroll_offset_y;
+                for (child_layout, child_info) in layout.children.iter().zip(&info.children) {
+                    self.visit(child_layout, child_info, child_origin);
+                }
+            }
+        }
+    }
+}
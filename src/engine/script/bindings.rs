@@ -0,0 +1,592 @@
+//! boa に公開する DOM、console、タイマー、fetch、通知、Web Storage
+//!
+//! 要素は [`DomHandle`] を持つ JS オブジェクトで表す。DOM の読み書きは [`dom`] を通し、
+//! 書き換えたら mutated を立てる。WebView はそれを見てレイアウトし直す。
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+use boa_engine::{
+    Context, JsArgs, JsData, JsNativeError, JsResult, JsString, JsValue, NativeFunction, Source,
+    js_string,
+    object::{
+        FunctionObjectBuilder, ObjectInitializer,
+        builtins::{JsArray, JsFunction},
+    },
+    property::Attribute,
+};
+use boa_gc::{Finalize, Trace};
+
+use super::storage::SharedStorage;
+use super::{FetchRequest, FetchResponse, NotificationRequest, TimerRequest, dom};
+use crate::engine::css::parser::ComplexSelector;
+use crate::engine::diagnostics::{self, Level};
+use crate::engine::html::HtmlNodeType;
+use crate::engine::tree::NodeRef;
+
+type NativeFn = fn(&JsValue, &[JsValue], &mut Context) -> JsResult<JsValue>;
+
+/// ブラウザとのやりとりに使う隠しグローバルオブジェクトの名前
+const HOST_OBJECT: &str = "__orinium";
+
+/// setTimeout などを定義するスクリプト
+const TIMERS_JS: &str = include_str!("timers.js");
+
+/// fetch() と XMLHttpRequest を定義するスクリプト
+const FETCH_JS: &str = include_str!("fetch.js");
+
+/// Notification を定義するスクリプト
+const NOTIFICATIONS_JS: &str = include_str!("notifications.js");
+
+/// JS オブジェクトに持たせる DOM のノード
+#[derive(Clone, Trace, Finalize, JsData)]
+struct DomHandle {
+    #[unsafe_ignore_trace]
+    node: NodeRef<HtmlNodeType>,
+    /// 同じ文書のハンドルで共有する、DOM を書き換えたかの印
+    #[unsafe_ignore_trace]
+    mutated: Rc<Cell<bool>>,
+}
+
+impl DomHandle {
+    fn with_node(&self, node: NodeRef<HtmlNodeType>) -> Self {
+        Self {
+            node,
+            mutated: Rc::clone(&self.mutated),
+        }
+    }
+}
+
+/// console.log / warn / error をログとタブのコンソールに流す
+pub fn register_console(context: &mut Context) -> JsResult<()> {
+    let console = ObjectInitializer::new(context)
+        .function(
+            NativeFunction::from_fn_ptr(console_log),
+            js_string!("log"),
+            0,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(console_warn),
+            js_string!("warn"),
+            0,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(console_error),
+            js_string!("error"),
+            0,
+        )
+        .build();
+    context.register_global_property(js_string!("console"), console, Attribute::all())
+}
+
+/// スクリプトからブラウザへの頼みごと（スクリプトが積み、ブラウザが取り出す）
+#[derive(Clone, Default, Trace, Finalize)]
+pub struct HostQueues {
+    #[unsafe_ignore_trace]
+    pub timers: Rc<RefCell<Vec<TimerRequest>>>,
+    #[unsafe_ignore_trace]
+    pub fetches: Rc<RefCell<Vec<FetchRequest>>>,
+    #[unsafe_ignore_trace]
+    pub notifications: Rc<RefCell<Vec<NotificationRequest>>>,
+}
+
+/// ブラウザとやりとりする隠しオブジェクトと、それを使う setTimeout / setInterval /
+/// queueMicrotask / fetch / XMLHttpRequest / Notification を定義する
+pub fn register_host(context: &mut Context, queues: &HostQueues) -> JsResult<()> {
+    let set_timer = NativeFunction::from_copy_closure_with_captures(
+        |_, args, queues: &HostQueues, context| {
+            let id = args.get_or_undefined(0).to_u32(context)?;
+            let delay = args.get_or_undefined(1).to_number(context)?;
+            let repeat = args.get_or_undefined(2).to_boolean();
+            // 負や NaN の遅延は 0 として扱う
+            let delay = if delay.is_finite() && delay > 0.0 {
+                Duration::from_micros((delay * 1000.0) as u64)
+            } else {
+                Duration::ZERO
+            };
+            queues
+                .timers
+                .borrow_mut()
+                .push(TimerRequest::Set { id, delay, repeat });
+            Ok(JsValue::undefined())
+        },
+        queues.clone(),
+    );
+    let clear_timer = NativeFunction::from_copy_closure_with_captures(
+        |_, args, queues: &HostQueues, context| {
+            let id = args.get_or_undefined(0).to_u32(context)?;
+            queues.timers.borrow_mut().push(TimerRequest::Clear(id));
+            Ok(JsValue::undefined())
+        },
+        queues.clone(),
+    );
+    let fetch = NativeFunction::from_copy_closure_with_captures(
+        |_, args, queues: &HostQueues, context| {
+            let id = args.get_or_undefined(0).to_u32(context)?;
+            let method = string_arg(args, 1, context)?;
+            let url = string_arg(args, 2, context)?;
+            queues
+                .fetches
+                .borrow_mut()
+                .push(FetchRequest { id, method, url });
+            Ok(JsValue::undefined())
+        },
+        queues.clone(),
+    );
+    let request_permission = NativeFunction::from_copy_closure_with_captures(
+        |_, _, queues: &HostQueues, _| {
+            queues
+                .notifications
+                .borrow_mut()
+                .push(NotificationRequest::RequestPermission);
+            Ok(JsValue::undefined())
+        },
+        queues.clone(),
+    );
+    let show_notification = NativeFunction::from_copy_closure_with_captures(
+        |_, args, queues: &HostQueues, context| {
+            let id = args.get_or_undefined(0).to_u32(context)?;
+            let title = string_arg(args, 1, context)?;
+            let body = string_arg(args, 2, context)?;
+            let tag = string_arg(args, 3, context)?;
+            queues
+                .notifications
+                .borrow_mut()
+                .push(NotificationRequest::Show {
+                    id,
+                    title,
+                    body,
+                    tag,
+                });
+            Ok(JsValue::undefined())
+        },
+        queues.clone(),
+    );
+    let close_notification = NativeFunction::from_copy_closure_with_captures(
+        |_, args, queues: &HostQueues, context| {
+            let id = args.get_or_undefined(0).to_u32(context)?;
+            queues
+                .notifications
+                .borrow_mut()
+                .push(NotificationRequest::Close(id));
+            Ok(JsValue::undefined())
+        },
+        queues.clone(),
+    );
+
+    let host = ObjectInitializer::new(context)
+        .function(set_timer, js_string!("setTimer"), 3)
+        .function(clear_timer, js_string!("clearTimer"), 1)
+        .function(fetch, js_string!("fetch"), 3)
+        .function(
+            request_permission,
+            js_string!("requestNotificationPermission"),
+            0,
+        )
+        .function(show_notification, js_string!("showNotification"), 4)
+        .function(close_notification, js_string!("closeNotification"), 1)
+        .build();
+    context.register_global_property(JsString::from(HOST_OBJECT), host, Attribute::empty())?;
+    context.eval(Source::from_bytes(TIMERS_JS))?;
+    context.eval(Source::from_bytes(FETCH_JS))?;
+    context.eval(Source::from_bytes(NOTIFICATIONS_JS))?;
+    Ok(())
+}
+
+/// Notification.permission を state（許した / 拒んだ / まだ決めていない）にし、
+/// requestPermission() の答えを待っているものに返す
+pub fn set_notification_permission(context: &mut Context, state: Option<bool>) -> JsResult<()> {
+    let state = match state {
+        Some(true) => "granted",
+        Some(false) => "denied",
+        None => "default",
+    };
+    call_host(context, "setNotificationPermission", &[js_str(state)])
+}
+
+/// 通知 id に event（show / close / error）が起きたことを知らせる
+pub fn dispatch_notification_event(context: &mut Context, id: u32, event: &str) -> JsResult<()> {
+    call_host(context, "notificationEvent", &[id.into(), js_str(event)])
+}
+
+/// タイマー id のコールバックを呼ぶ
+pub fn fire_timer(context: &mut Context, id: u32) -> JsResult<()> {
+    call_host(context, "fireTimer", &[id.into()])
+}
+
+/// fetch / XMLHttpRequest の要求 id に応答（か失敗の理由）を返す
+pub fn complete_fetch(
+    context: &mut Context,
+    id: u32,
+    result: Result<FetchResponse, String>,
+) -> JsResult<()> {
+    match result {
+        Ok(response) => call_host(
+            context,
+            "fetchDone",
+            &[
+                id.into(),
+                u32::from(response.status).into(),
+                js_str(&response.status_text),
+                js_str(&response.url),
+                js_str(&response.body),
+            ],
+        ),
+        Err(message) => call_host(context, "fetchFailed", &[id.into(), js_str(&message)]),
+    }
+}
+
+/// 隠しオブジェクトの関数 name を呼ぶ
+fn call_host(context: &mut Context, name: &str, args: &[JsValue]) -> JsResult<()> {
+    let host = context
+        .global_object()
+        .get(JsString::from(HOST_OBJECT), context)?;
+    let function = match host.as_object() {
+        Some(object) => object.get(JsString::from(name), context)?,
+        None => JsValue::undefined(),
+    };
+    let Some(function) = function.as_callable() else {
+        return Err(JsNativeError::typ()
+            .with_message(format!("{HOST_OBJECT}.{name} is not a function"))
+            .into());
+    };
+    function.call(&host, args, context)?;
+    Ok(())
+}
+
+/// 文書のオリジンの localStorage か sessionStorage
+#[derive(Clone, Trace, Finalize)]
+pub struct StorageHandle {
+    #[unsafe_ignore_trace]
+    pub storage: SharedStorage,
+    #[unsafe_ignore_trace]
+    pub origin: String,
+}
+
+/// handle を name（localStorage / sessionStorage）として公開する
+pub fn register_storage(context: &mut Context, name: &str, handle: StorageHandle) -> JsResult<()> {
+    let get_item = NativeFunction::from_copy_closure_with_captures(
+        |_, args, handle: &StorageHandle, context| {
+            let key = string_arg(args, 0, context)?;
+            let storage = handle.storage.borrow();
+            Ok(storage
+                .get_item(&handle.origin, &key)
+                .map_or(JsValue::null(), js_str))
+        },
+        handle.clone(),
+    );
+    let set_item = NativeFunction::from_copy_closure_with_captures(
+        |_, args, handle: &StorageHandle, context| {
+            let key = string_arg(args, 0, context)?;
+            let value = string_arg(args, 1, context)?;
+            handle
+                .storage
+                .borrow_mut()
+                .set_item(&handle.origin, &key, &value)
+                .map_err(|e| {
+                    JsNativeError::error().with_message(format!("QuotaExceededError: {e}"))
+                })?;
+            Ok(JsValue::undefined())
+        },
+        handle.clone(),
+    );
+    let remove_item = NativeFunction::from_copy_closure_with_captures(
+        |_, args, handle: &StorageHandle, context| {
+            let key = string_arg(args, 0, context)?;
+            handle
+                .storage
+                .borrow_mut()
+                .remove_item(&handle.origin, &key);
+            Ok(JsValue::undefined())
+        },
+        handle.clone(),
+    );
+    let clear = NativeFunction::from_copy_closure_with_captures(
+        |_, _, handle: &StorageHandle, _| {
+            handle.storage.borrow_mut().clear(&handle.origin);
+            Ok(JsValue::undefined())
+        },
+        handle.clone(),
+    );
+    let key = NativeFunction::from_copy_closure_with_captures(
+        |_, args, handle: &StorageHandle, context| {
+            let index = args.get_or_undefined(0).to_number(context)?;
+            if !(index >= 0.0) {
+                return Ok(JsValue::null());
+            }
+            let storage = handle.storage.borrow();
+            Ok(storage
+                .key(&handle.origin, index as usize)
+                .map_or(JsValue::null(), js_str))
+        },
+        handle.clone(),
+    );
+    let length = FunctionObjectBuilder::new(
+        context.realm(),
+        NativeFunction::from_copy_closure_with_captures(
+            |_, _, handle: &StorageHandle, _| {
+                let len = handle.storage.borrow().len(&handle.origin);
+                Ok(JsValue::from(len as u32))
+            },
+            handle,
+        ),
+    )
+    .build();
+
+    let storage = ObjectInitializer::new(context)
+        .function(get_item, js_string!("getItem"), 1)
+        .function(set_item, js_string!("setItem"), 2)
+        .function(remove_item, js_string!("removeItem"), 1)
+        .function(clear, js_string!("clear"), 0)
+        .function(key, js_string!("key"), 1)
+        .accessor(
+            js_string!("length"),
+            Some(length),
+            None,
+            Attribute::CONFIGURABLE,
+        )
+        .build();
+    context.register_global_property(JsString::from(name), storage, Attribute::all())
+}
+
+/// root を document として公開する
+pub fn register_document(
+    context: &mut Context,
+    root: NodeRef<HtmlNodeType>,
+    mutated: Rc<Cell<bool>>,
+) -> JsResult<()> {
+    let body = accessor_function(context, document_body);
+    let document = ObjectInitializer::with_native_data(
+        DomHandle {
+            node: root,
+            mutated,
+        },
+        context,
+    )
+    .function(
+        NativeFunction::from_fn_ptr(get_element_by_id),
+        js_string!("getElementById"),
+        1,
+    )
+    .function(
+        NativeFunction::from_fn_ptr(query_selector),
+        js_string!("querySelector"),
+        1,
+    )
+    .function(
+        NativeFunction::from_fn_ptr(query_selector_all),
+        js_string!("querySelectorAll"),
+        1,
+    )
+    .accessor(
+        js_string!("body"),
+        Some(body),
+        None,
+        Attribute::CONFIGURABLE,
+    )
+    .build();
+    context.register_global_property(js_string!("document"), document, Attribute::all())
+}
+
+/// アクセサに使う関数オブジェクト
+fn accessor_function(context: &mut Context, f: NativeFn) -> JsFunction {
+    FunctionObjectBuilder::new(context.realm(), NativeFunction::from_fn_ptr(f)).build()
+}
+
+fn wrap_element(handle: DomHandle, context: &mut Context) -> JsValue {
+    let tag_name = handle
+        .node
+        .borrow()
+        .value
+        .tag_name()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    let text_get = accessor_function(context, text_content_get);
+    let text_set = accessor_function(context, text_content_set);
+    let html_get = accessor_function(context, inner_html_get);
+    let html_set = accessor_function(context, inner_html_set);
+
+    ObjectInitializer::with_native_data(handle, context)
+        .property(
+            js_string!("tagName"),
+            JsString::from(tag_name.as_str()),
+            Attribute::READONLY,
+        )
+        .accessor(
+            js_string!("textContent"),
+            Some(text_get),
+            Some(text_set),
+            Attribute::CONFIGURABLE,
+        )
+        .accessor(
+            js_string!("innerHTML"),
+            Some(html_get),
+            Some(html_set),
+            Attribute::CONFIGURABLE,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(set_attribute),
+            js_string!("setAttribute"),
+            2,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(get_attribute),
+            js_string!("getAttribute"),
+            1,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(query_selector),
+            js_string!("querySelector"),
+            1,
+        )
+        .function(
+            NativeFunction::from_fn_ptr(query_selector_all),
+            js_string!("querySelectorAll"),
+            1,
+        )
+        .build()
+        .into()
+}
+
+fn wrap_optional(
+    handle: &DomHandle,
+    node: Option<NodeRef<HtmlNodeType>>,
+    context: &mut Context,
+) -> JsValue {
+    match node {
+        Some(node) => wrap_element(handle.with_node(node), context),
+        None => JsValue::null(),
+    }
+}
+
+fn this_handle(this: &JsValue) -> JsResult<DomHandle> {
+    this.as_object()
+        .and_then(|o| o.downcast_ref::<DomHandle>().map(|h| DomHandle::clone(&h)))
+        .ok_or_else(|| {
+            JsNativeError::typ()
+                .with_message("Illegal invocation")
+                .into()
+        })
+}
+
+fn string_arg(args: &[JsValue], index: usize, context: &mut Context) -> JsResult<String> {
+    Ok(args
+        .get_or_undefined(index)
+        .to_string(context)?
+        .to_std_string_escaped())
+}
+
+fn js_str(s: &str) -> JsValue {
+    JsString::from(s).into()
+}
+
+fn get_element_by_id(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let id = string_arg(args, 0, context)?;
+    let found = dom::get_element_by_id(&handle.node, &id);
+    Ok(wrap_optional(&handle, found, context))
+}
+
+fn document_body(this: &JsValue, _: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let body = dom::parse_selectors("body").and_then(|s| dom::query_selector(&handle.node, &s));
+    Ok(wrap_optional(&handle, body, context))
+}
+
+fn selectors_arg(args: &[JsValue], context: &mut Context) -> JsResult<Vec<ComplexSelector>> {
+    let selectors = string_arg(args, 0, context)?;
+    dom::parse_selectors(&selectors).ok_or_else(|| {
+        JsNativeError::syntax()
+            .with_message(format!("'{selectors}' is not a valid selector"))
+            .into()
+    })
+}
+
+fn query_selector(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let selectors = selectors_arg(args, context)?;
+    let found = dom::query_selector(&handle.node, &selectors);
+    Ok(wrap_optional(&handle, found, context))
+}
+
+fn query_selector_all(
+    this: &JsValue,
+    args: &[JsValue],
+    context: &mut Context,
+) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let selectors = selectors_arg(args, context)?;
+    let elements: Vec<JsValue> = dom::query_selector_all(&handle.node, &selectors)
+        .into_iter()
+        .map(|node| wrap_element(handle.with_node(node), context))
+        .collect();
+    Ok(JsArray::from_iter(elements, context).into())
+}
+
+fn text_content_get(this: &JsValue, _: &[JsValue], _: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    Ok(js_str(&dom::text_content(&handle.node)))
+}
+
+fn text_content_set(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let text = string_arg(args, 0, context)?;
+    dom::set_text_content(&handle.node, &text);
+    handle.mutated.set(true);
+    Ok(JsValue::undefined())
+}
+
+fn inner_html_get(this: &JsValue, _: &[JsValue], _: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    Ok(js_str(&dom::inner_html(&handle.node)))
+}
+
+fn inner_html_set(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let html = string_arg(args, 0, context)?;
+    dom::set_inner_html(&handle.node, &html);
+    handle.mutated.set(true);
+    Ok(JsValue::undefined())
+}
+
+fn set_attribute(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let name = string_arg(args, 0, context)?;
+    let value = string_arg(args, 1, context)?;
+    dom::set_attribute(&handle.node, &name, &value);
+    handle.mutated.set(true);
+    Ok(JsValue::undefined())
+}
+
+fn get_attribute(this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let handle = this_handle(this)?;
+    let name = string_arg(args, 0, context)?;
+    Ok(dom::get_attribute(&handle.node, &name).map_or(JsValue::null(), |v| js_str(&v)))
+}
+
+fn console_message(args: &[JsValue], context: &mut Context) -> JsResult<String> {
+    let parts = args
+        .iter()
+        .map(|arg| Ok(arg.to_string(context)?.to_std_string_escaped()))
+        .collect::<JsResult<Vec<_>>>()?;
+    Ok(parts.join(" "))
+}
+
+fn console_log(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let message = console_message(args, context)?;
+    diagnostics::report(Level::Info, diagnostics::Source::Script, message);
+    Ok(JsValue::undefined())
+}
+
+fn console_warn(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let message = console_message(args, context)?;
+    diagnostics::report(Level::Warning, diagnostics::Source::Script, message);
+    Ok(JsValue::undefined())
+}
+
+fn console_error(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let message = console_message(args, context)?;
+    diagnostics::report(Level::Error, diagnostics::Source::Script, message);
+    Ok(JsValue::undefined())
+}
@@ -0,0 +1,239 @@
+//! boa によるスクリプトの実行
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use anyhow::{Result, anyhow};
+use boa_engine::{Context, JsResult, JsValue, Source};
+
+use super::bindings::{self, HostQueues, StorageHandle};
+use super::storage::SharedStorage;
+use super::{FetchRequest, FetchResponse, NotificationRequest, ScriptValue, TimerRequest};
+use crate::engine::diagnostics;
+use crate::engine::html::HtmlNodeType;
+use crate::engine::html::parser::DomTree;
+use crate::engine::tree::NodeRef;
+
+/// WebView ごとの realm
+///
+/// Context は最初のスクリプトを実行するときに作る。shutdown した後は何も実行しない。
+pub struct ScriptRuntime {
+    context: Option<Context>,
+    host: Host,
+    shut_down: bool,
+}
+
+/// バインディングとブラウザで共有するもの
+#[derive(Default)]
+struct Host {
+    /// document として公開する DOM のルート
+    document: Option<NodeRef<HtmlNodeType>>,
+    /// スクリプトが DOM を書き換えたか
+    dom_mutated: Rc<Cell<bool>>,
+    /// ブラウザがまだ受け取っていないタイマーの操作、fetch の要求と通知の要求
+    queues: HostQueues,
+    /// 文書のオリジンの localStorage と sessionStorage
+    local_storage: Option<StorageHandle>,
+    session_storage: Option<StorageHandle>,
+}
+
+impl Default for ScriptRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptRuntime {
+    pub fn new() -> Self {
+        Self {
+            context: None,
+            host: Host::default(),
+            shut_down: false,
+        }
+    }
+
+    /// dom を document としてスクリプトに公開する
+    pub fn attach_document(&mut self, dom: &DomTree) {
+        self.host.document = Some(Rc::clone(&dom.root));
+        if let Some(context) = self.context.as_mut()
+            && let Err(e) = bindings::register_document(
+                context,
+                Rc::clone(&dom.root),
+                Rc::clone(&self.host.dom_mutated),
+            )
+        {
+            log::warn!("Failed to expose document to scripts: {e}");
+        }
+    }
+
+    /// 文書のオリジン origin の localStorage と sessionStorage を公開する
+    pub fn set_storage(
+        &mut self,
+        origin: &str,
+        local: Option<SharedStorage>,
+        session: Option<SharedStorage>,
+    ) {
+        let handle = |storage| StorageHandle {
+            storage,
+            origin: origin.to_string(),
+        };
+        self.host.local_storage = local.map(handle);
+        self.host.session_storage = session.map(handle);
+        if let Some(context) = self.context.as_mut() {
+            register_storages(context, &self.host);
+        }
+    }
+
+    /// 前に呼んでからスクリプトが DOM を書き換えたか
+    pub fn take_dom_mutated(&self) -> bool {
+        self.host.dom_mutated.replace(false)
+    }
+
+    /// 前に呼んでからスクリプトが頼んだタイマーの操作
+    pub fn take_timer_requests(&mut self) -> Vec<TimerRequest> {
+        self.host.queues.timers.take()
+    }
+
+    /// 前に呼んでからスクリプトが送った fetch / XMLHttpRequest の要求
+    pub fn take_fetch_requests(&mut self) -> Vec<FetchRequest> {
+        self.host.queues.fetches.take()
+    }
+
+    /// 前に呼んでからスクリプトが Notification で頼んだこと
+    pub fn take_notification_requests(&mut self) -> Vec<NotificationRequest> {
+        self.host.queues.notifications.take()
+    }
+
+    /// Notification.permission を state にする（None はまだ決めていない）
+    pub fn set_notification_permission(&mut self, state: Option<bool>) -> Result<()> {
+        self.run("notification", |context| {
+            bindings::set_notification_permission(context, state)
+        })
+    }
+
+    /// 通知 id に event（show / close / error）が起きたことをスクリプトに知らせる
+    pub fn dispatch_notification_event(&mut self, id: u32, event: &str) -> Result<()> {
+        self.run("notification", |context| {
+            bindings::dispatch_notification_event(context, id, event)
+        })
+    }
+
+    /// source を実行し、それで積まれた Promise のジョブ（microtask）も済ませる
+    ///
+    /// name はエラーメッセージに出すスクリプトの名前（URL など）。
+    /// 実行中に console.* で出したメッセージの場所は name になる。
+    pub fn execute(&mut self, source: &str, name: &str) -> Result<()> {
+        diagnostics::with_location(name, || {
+            self.run(name, |context| {
+                context.eval(Source::from_bytes(source)).map(|_| ())
+            })
+        })
+    }
+
+    /// 式 source を評価して値を返す（`execute` と同じく microtask も済ませる）
+    pub fn evaluate(&mut self, source: &str, name: &str) -> Result<ScriptValue> {
+        let mut value = ScriptValue::Undefined;
+        diagnostics::with_location(name, || {
+            self.run(name, |context| {
+                let result = context.eval(Source::from_bytes(source))?;
+                value = script_value(&result);
+                Ok(())
+            })
+        })?;
+        Ok(value)
+    }
+
+    /// 期限の来たタイマー id のコールバックを呼ぶ
+    pub fn fire_timer(&mut self, id: u32) -> Result<()> {
+        self.run("timer", |context| bindings::fire_timer(context, id))
+    }
+
+    /// fetch / XMLHttpRequest の要求 id に応答（か失敗の理由）を返す
+    pub fn complete_fetch(
+        &mut self,
+        id: u32,
+        result: std::result::Result<FetchResponse, String>,
+    ) -> Result<()> {
+        self.run("fetch", |context| {
+            bindings::complete_fetch(context, id, result)
+        })
+    }
+
+    /// realm で f を実行し、積まれた microtask を済ませる
+    fn run(&mut self, name: &str, f: impl FnOnce(&mut Context) -> JsResult<()>) -> Result<()> {
+        if self.shut_down {
+            return Err(anyhow!("{name}: script runtime has been shut down"));
+        }
+
+        let context = self
+            .context
+            .get_or_insert_with(|| create_context(&self.host));
+        let result = f(context);
+        context.run_jobs();
+        result.map_err(|e| anyhow!("{name}: {e}"))
+    }
+
+    /// realm を捨てる。タブを閉じたときや別のページへ移るときに呼ぶ
+    pub fn shutdown(&mut self) {
+        self.context = None;
+        self.host.document = None;
+        self.host.queues.timers.borrow_mut().clear();
+        self.host.queues.fetches.borrow_mut().clear();
+        self.host.queues.notifications.borrow_mut().clear();
+        self.shut_down = true;
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+}
+
+/// JsValue を Rust の値にする（オブジェクトは表示用の文字列にする）
+fn script_value(value: &JsValue) -> ScriptValue {
+    if value.is_undefined() {
+        ScriptValue::Undefined
+    } else if value.is_null() {
+        ScriptValue::Null
+    } else if let Some(b) = value.as_boolean() {
+        ScriptValue::Bool(b)
+    } else if let Some(n) = value.as_number() {
+        ScriptValue::Number(n)
+    } else if let Some(s) = value.as_string() {
+        ScriptValue::String(s.to_std_string_escaped())
+    } else {
+        ScriptValue::Object(value.display().to_string())
+    }
+}
+
+/// console、タイマー、fetch、Web Storage、document を登録した Context を作る
+fn create_context(host: &Host) -> Context {
+    let mut context = Context::default();
+    if let Err(e) = bindings::register_console(&mut context) {
+        log::warn!("Failed to expose console to scripts: {e}");
+    }
+    if let Err(e) = bindings::register_host(&mut context, &host.queues) {
+        log::warn!("Failed to expose timers and fetch to scripts: {e}");
+    }
+    register_storages(&mut context, host);
+    if let Some(root) = &host.document
+        && let Err(e) =
+            bindings::register_document(&mut context, Rc::clone(root), Rc::clone(&host.dom_mutated))
+    {
+        log::warn!("Failed to expose document to scripts: {e}");
+    }
+    context
+}
+
+fn register_storages(context: &mut Context, host: &Host) {
+    let storages = [
+        ("localStorage", &host.local_storage),
+        ("sessionStorage", &host.session_storage),
+    ];
+    for (name, handle) in storages {
+        if let Some(handle) = handle
+            && let Err(e) = bindings::register_storage(context, name, handle.clone())
+        {
+            log::warn!("Failed to expose {name} to scripts: {e}");
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! `js` フィーチャーが無効なときの ScriptRuntime（何も実行しない）
+
+use anyhow::Result;
+
+use super::storage::SharedStorage;
+use super::{FetchRequest, FetchResponse, NotificationRequest, ScriptValue, TimerRequest};
+use crate::engine::html::parser::DomTree;
+
+#[derive(Default)]
+pub struct ScriptRuntime {
+    shut_down: bool,
+}
+
+impl ScriptRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn attach_document(&mut self, _dom: &DomTree) {}
+
+    pub fn set_storage(
+        &mut self,
+        _origin: &str,
+        _local: Option<SharedStorage>,
+        _session: Option<SharedStorage>,
+    ) {
+    }
+
+    pub fn take_dom_mutated(&self) -> bool {
+        false
+    }
+
+    pub fn execute(&mut self, _source: &str, name: &str) -> Result<()> {
+        log::debug!("JavaScript is disabled in this build; skipped {name}");
+        Ok(())
+    }
+
+    pub fn evaluate(&mut self, _source: &str, _name: &str) -> Result<ScriptValue> {
+        anyhow::bail!("JavaScript is disabled in this build")
+    }
+
+    pub fn take_timer_requests(&mut self) -> Vec<TimerRequest> {
+        Vec::new()
+    }
+
+    pub fn fire_timer(&mut self, _id: u32) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn take_fetch_requests(&mut self) -> Vec<FetchRequest> {
+        Vec::new()
+    }
+
+    pub fn complete_fetch(
+        &mut self,
+        _id: u32,
+        _result: std::result::Result<FetchResponse, String>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn take_notification_requests(&mut self) -> Vec<NotificationRequest> {
+        Vec::new()
+    }
+
+    pub fn set_notification_permission(&mut self, _state: Option<bool>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn dispatch_notification_event(&mut self, _id: u32, _event: &str) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn shutdown(&mut self) {
+        self.shut_down = true;
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down
+    }
+}
@@ -0,0 +1,202 @@
+//! スクリプトから DOM を読み書きするための操作
+//!
+//! JavaScript のバインディングはここを通して DOM に触る。JS エンジンに依存しないので
+//! `js` フィーチャーが無効でも使える。
+
+use std::rc::Rc;
+
+use crate::engine::css::matcher::ElementInfo;
+use crate::engine::css::parser::{ComplexSelector, CssNodeType, Parser as CssParser};
+use crate::engine::html::HtmlNodeType;
+use crate::engine::html::parser::{DomTree, Parser as HtmlParser};
+use crate::engine::tree::{NodeRef, TreeNode};
+
+/// 終了タグを書かない要素
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// querySelector に渡されたセレクタリストをパースする（不正なら None）
+pub fn parse_selectors(selectors: &str) -> Option<Vec<ComplexSelector>> {
+    if selectors.trim().is_empty() || selectors.contains(['{', '}']) {
+        return None;
+    }
+
+    // 空の規則としてパースし、そのセレクタを取り出す
+    let css = format!("{selectors} {{}}");
+    let stylesheet = CssParser::new(&css).parse().ok()?;
+    match stylesheet.children().first()?.node() {
+        CssNodeType::Rule { selectors } if !selectors.is_empty() => Some(selectors.clone()),
+        _ => None,
+    }
+}
+
+/// セレクタの照合に使う要素の情報（要素でなければ None）
+fn element_info(node: &HtmlNodeType) -> Option<ElementInfo> {
+    let HtmlNodeType::Element {
+        tag_name,
+        attributes,
+    } = node
+    else {
+        return None;
+    };
+    Some(ElementInfo {
+        tag_name: tag_name.clone(),
+        id: node.get_attr("id").map(str::to_string),
+        classes: node
+            .get_attr("class")
+            .map(|c| c.split_whitespace().map(str::to_string).collect())
+            .unwrap_or_default(),
+        attributes: attributes
+            .iter()
+            .map(|a| (a.name.to_ascii_lowercase(), a.value.clone()))
+            .collect(),
+        hovered: false,
+        active: false,
+        focused: false,
+        focus_within: false,
+        visited: false,
+    })
+}
+
+/// node がセレクタリストのどれかに当たるか
+pub fn matches(node: &NodeRef<HtmlNodeType>, selectors: &[ComplexSelector]) -> bool {
+    // 自分 → 祖先の順
+    let mut chain = Vec::new();
+    let mut current = Some(Rc::clone(node));
+    while let Some(n) = current {
+        let n = n.borrow();
+        let Some(info) = element_info(&n.value) else {
+            break;
+        };
+        chain.push(info);
+        current = n.parent();
+    }
+    !chain.is_empty() && selectors.iter().any(|s| s.matches(&chain))
+}
+
+/// root の子孫のうちセレクタに当たる要素を文書順に集める（root 自身は含めない）
+pub fn query_selector_all(
+    root: &NodeRef<HtmlNodeType>,
+    selectors: &[ComplexSelector],
+) -> Vec<NodeRef<HtmlNodeType>> {
+    fn visit(
+        node: &NodeRef<HtmlNodeType>,
+        selectors: &[ComplexSelector],
+        found: &mut Vec<NodeRef<HtmlNodeType>>,
+    ) {
+        for child in node.borrow().children() {
+            if matches(child, selectors) {
+                found.push(Rc::clone(child));
+            }
+            visit(child, selectors, found);
+        }
+    }
+
+    let mut found = Vec::new();
+    visit(root, selectors, &mut found);
+    found
+}
+
+pub fn query_selector(
+    root: &NodeRef<HtmlNodeType>,
+    selectors: &[ComplexSelector],
+) -> Option<NodeRef<HtmlNodeType>> {
+    query_selector_all(root, selectors).into_iter().next()
+}
+
+/// root 以下で id を持つ最初の要素
+pub fn get_element_by_id(root: &NodeRef<HtmlNodeType>, id: &str) -> Option<NodeRef<HtmlNodeType>> {
+    DomTree {
+        root: Rc::clone(root),
+    }
+    .get_element_by_id(id)
+}
+
+/// 属性を設定する（HTML の属性名は大文字小文字を区別しない）
+pub fn set_attribute(node: &NodeRef<HtmlNodeType>, name: &str, value: &str) {
+    node.borrow_mut()
+        .value
+        .set_attr(&name.to_ascii_lowercase(), value.to_string());
+}
+
+pub fn get_attribute(node: &NodeRef<HtmlNodeType>, name: &str) -> Option<String> {
+    node.borrow()
+        .value
+        .get_attr(&name.to_ascii_lowercase())
+        .map(str::to_string)
+}
+
+/// 子ノードを HTML に書き出す
+pub fn inner_html(node: &NodeRef<HtmlNodeType>) -> String {
+    let mut html = String::new();
+    let n = node.borrow();
+    let raw = matches!(n.value.tag_name(), Some("script" | "style"));
+    for child in n.children() {
+        serialize(child, raw, &mut html);
+    }
+    html
+}
+
+fn serialize(node: &NodeRef<HtmlNodeType>, raw_text: bool, out: &mut String) {
+    let n = node.borrow();
+    match &n.value {
+        HtmlNodeType::Element {
+            tag_name,
+            attributes,
+        } => {
+            out.push('<');
+            out.push_str(tag_name);
+            for attr in attributes {
+                out.push_str(&format!(" {}=\"{}\"", attr.name, escape(&attr.value, true)));
+            }
+            out.push('>');
+            if VOID_ELEMENTS.contains(&tag_name.as_str()) {
+                return;
+            }
+            let raw = matches!(tag_name.as_str(), "script" | "style");
+            for child in n.children() {
+                serialize(child, raw, out);
+            }
+            out.push_str(&format!("</{tag_name}>"));
+        }
+        HtmlNodeType::Text(text) if raw_text => out.push_str(text),
+        HtmlNodeType::Text(text) => out.push_str(&escape(text, false)),
+        HtmlNodeType::Comment(text) => out.push_str(&format!("<!--{text}-->")),
+        _ => {}
+    }
+}
+
+fn escape(text: &str, attribute: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '\u{a0}' => escaped.push_str("&nbsp;"),
+            '"' if attribute => escaped.push_str("&quot;"),
+            '<' if !attribute => escaped.push_str("&lt;"),
+            '>' if !attribute => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 子ノードを html をパースしたものに置き換える
+pub fn set_inner_html(node: &NodeRef<HtmlNodeType>, html: &str) {
+    let fragment = HtmlParser::new(html).parse_fragment();
+    node.borrow_mut().clear_children();
+    for child in fragment {
+        TreeNode::add_child(node, child);
+    }
+}
+
+/// 子ノードを 1 つの文字列に置き換える
+pub fn set_text_content(node: &NodeRef<HtmlNodeType>, text: &str) {
+    DomTree::set_text_content(node, text);
+}
+
+pub fn text_content(node: &NodeRef<HtmlNodeType>) -> String {
+    DomTree::inner_text(node)
+}
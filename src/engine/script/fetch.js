@@ -0,0 +1,125 @@
+// fetch() と XMLHttpRequest
+//
+// 要求はブラウザに id、メソッド、URL だけを渡し、応答は __orinium.fetchDone(id, ...)
+// か __orinium.fetchFailed(id, message) で受け取る。本文は文字列として扱う。
+(() => {
+    const host = globalThis.__orinium;
+    const pending = new Map();
+    let nextId = 1;
+
+    const request = (method, url) =>
+        new Promise((resolve, reject) => {
+            const id = nextId++;
+            pending.set(id, { resolve, reject });
+            host.fetch(id, String(method).toUpperCase(), String(url));
+        });
+
+    host.fetchDone = (id, status, statusText, url, body) => {
+        const request = pending.get(id);
+        if (request) {
+            pending.delete(id);
+            request.resolve({ status, statusText, url, body });
+        }
+    };
+    host.fetchFailed = (id, message) => {
+        const request = pending.get(id);
+        if (request) {
+            pending.delete(id);
+            request.reject(new TypeError(message));
+        }
+    };
+
+    class Response {
+        #body;
+
+        constructor(response) {
+            this.status = response.status;
+            this.statusText = response.statusText;
+            this.ok = response.status >= 200 && response.status < 300;
+            this.url = response.url;
+            this.bodyUsed = false;
+            this.#body = response.body;
+        }
+
+        text() {
+            if (this.bodyUsed) {
+                return Promise.reject(new TypeError("Body has already been consumed"));
+            }
+            this.bodyUsed = true;
+            return Promise.resolve(this.#body);
+        }
+
+        json() {
+            return this.text().then((text) => JSON.parse(text));
+        }
+    }
+
+    globalThis.Response = Response;
+    globalThis.fetch = (input, init = {}) =>
+        request(init.method || "GET", input).then((response) => new Response(response));
+
+    class XMLHttpRequest {
+        static UNSENT = 0;
+        static OPENED = 1;
+        static HEADERS_RECEIVED = 2;
+        static LOADING = 3;
+        static DONE = 4;
+
+        constructor() {
+            this.readyState = XMLHttpRequest.UNSENT;
+            this.status = 0;
+            this.statusText = "";
+            this.responseText = "";
+            this.responseURL = "";
+            this.onreadystatechange = null;
+            this.onload = null;
+            this.onerror = null;
+            this.onloadend = null;
+        }
+
+        get response() {
+            return this.responseText;
+        }
+
+        open(method, url) {
+            this.method = method;
+            this.url = url;
+            this.#setReadyState(XMLHttpRequest.OPENED);
+        }
+
+        send() {
+            if (this.readyState !== XMLHttpRequest.OPENED) {
+                throw new Error("InvalidStateError: open() has not been called");
+            }
+            request(this.method, this.url).then(
+                (response) => {
+                    this.status = response.status;
+                    this.statusText = response.statusText;
+                    this.responseURL = response.url;
+                    this.responseText = response.body;
+                    this.#setReadyState(XMLHttpRequest.DONE);
+                    this.#fire("onload");
+                    this.#fire("onloadend");
+                },
+                () => {
+                    this.#setReadyState(XMLHttpRequest.DONE);
+                    this.#fire("onerror");
+                    this.#fire("onloadend");
+                },
+            );
+        }
+
+        #setReadyState(state) {
+            this.readyState = state;
+            this.#fire("onreadystatechange");
+        }
+
+        #fire(handler) {
+            if (typeof this[handler] === "function") {
+                this[handler]();
+            }
+        }
+    }
+
+    globalThis.XMLHttpRequest = XMLHttpRequest;
+})();
@@ -0,0 +1,156 @@
+//! JavaScript の実行
+//!
+//! `js` フィーチャーを有効にしてビルドしたときだけ boa でスクリプトを実行する。
+//! 無効なときは [`ENABLED`] が false になり、WebView はスクリプトを集めも取得もしない。
+//!
+//! 対応するのは classic script だけで、`type="module"` などは実行しない。
+
+use std::time::Duration;
+
+use url::Url;
+
+use crate::engine::html::HtmlNodeType;
+use crate::engine::html::parser::DomTree;
+
+pub mod dom;
+pub mod storage;
+
+#[cfg(feature = "js")]
+mod bindings;
+#[cfg(feature = "js")]
+mod boa;
+#[cfg(not(feature = "js"))]
+mod disabled;
+
+#[cfg(feature = "js")]
+pub use boa::ScriptRuntime;
+#[cfg(not(feature = "js"))]
+pub use disabled::ScriptRuntime;
+
+/// このビルドでスクリプトを実行できるか
+pub const ENABLED: bool = cfg!(feature = "js");
+
+/// スクリプトがブラウザに頼むタイマーの操作
+///
+/// id は realm の中で setTimeout / setInterval が返したもの。期限が来たらブラウザが
+/// [`ScriptRuntime::fire_timer`] を呼ぶ。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimerRequest {
+    Set {
+        id: u32,
+        delay: Duration,
+        repeat: bool,
+    },
+    Clear(u32),
+}
+
+/// スクリプトが fetch() や XMLHttpRequest で送った要求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchRequest {
+    /// realm の中での要求の番号（応答を返すときに使う）
+    pub id: u32,
+    /// 大文字のメソッド名
+    pub method: String,
+    /// スクリプトが渡したままの URL（base URL での解決は呼び出し側が行う）
+    pub url: String,
+}
+
+/// スクリプトが Notification で頼んだこと
+///
+/// id は realm の中での通知の番号。出せたか・閉じたかは
+/// [`ScriptRuntime::dispatch_notification_event`] で返す。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationRequest {
+    /// Notification.requestPermission()（答えは [`ScriptRuntime::set_notification_permission`]）
+    RequestPermission,
+    Show {
+        id: u32,
+        title: String,
+        body: String,
+        tag: String,
+    },
+    Close(u32),
+}
+
+/// スクリプトに返す応答
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FetchResponse {
+    pub status: u16,
+    pub status_text: String,
+    pub url: String,
+    pub body: String,
+}
+
+/// 式を評価した結果（リモートデバッグの Runtime.evaluate で返す）
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptValue {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    /// オブジェクトと関数（表示用の文字列）
+    Object(String),
+}
+
+/// `<script>` の中身
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptSource {
+    Inline(String),
+    /// src 属性を base URL で解決したもの
+    External(Url),
+}
+
+/// classic script として実行する type 属性か（属性なし・空もこれに当たる）
+pub fn is_classic_script(node: &HtmlNodeType) -> bool {
+    is_classic_script_type(node.get_attr("type"))
+}
+
+/// type 属性の値 ty が classic script を表すか（None は属性なし）
+pub fn is_classic_script_type(ty: Option<&str>) -> bool {
+    let Some(ty) = ty else {
+        return true;
+    };
+    let ty = ty.trim().to_ascii_lowercase();
+    matches!(
+        ty.as_str(),
+        "" | "text/javascript"
+            | "application/javascript"
+            | "application/x-javascript"
+            | "application/ecmascript"
+            | "text/ecmascript"
+            | "text/jscript"
+    )
+}
+
+/// 実行するスクリプトを文書順に集める
+///
+/// src 属性があれば中身は無視して外部スクリプトとして扱う。解決できない src の
+/// スクリプトは実行しない。
+pub fn collect_scripts(dom: &DomTree, base_url: &Url) -> Vec<ScriptSource> {
+    collect_scripts_where(dom, base_url, |_, _| true)
+}
+
+/// collect_scripts のうち allow（要素とスクリプト）が true を返すものだけを集める
+///
+/// Content Security Policy で許されないスクリプトを除くのに使う。
+pub fn collect_scripts_where(
+    dom: &DomTree,
+    base_url: &Url,
+    allow: impl Fn(&HtmlNodeType, &ScriptSource) -> bool,
+) -> Vec<ScriptSource> {
+    dom.find_all(|n| n.tag_name() == Some("script"))
+        .iter()
+        .filter_map(|node_ref| {
+            let node = node_ref.borrow();
+            if !is_classic_script(&node.value) {
+                return None;
+            }
+            let source = match node.value.get_attr("src") {
+                Some(src) => ScriptSource::External(base_url.join(src.trim()).ok()?),
+                None => ScriptSource::Inline(DomTree::inner_text(node_ref)),
+            };
+            allow(&node.value, &source).then_some(source)
+        })
+        .collect()
+}
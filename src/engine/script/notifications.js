@@ -0,0 +1,83 @@
+// Notification
+//
+// 通知を出すかどうかと出した結果はブラウザが決める。requestPermission() の答えは
+// __orinium.setNotificationPermission(state) で、通知に起きたこと（show / close / error）は
+// __orinium.notificationEvent(id, type) で受け取る。
+(() => {
+    const host = globalThis.__orinium;
+    const notifications = new Map();
+    const waiting = [];
+    let permission = "default";
+    let nextId = 1;
+
+    host.setNotificationPermission = (state) => {
+        permission = state;
+        if (state === "default") {
+            return;
+        }
+        for (const resolve of waiting.splice(0)) {
+            resolve(state);
+        }
+    };
+    host.notificationEvent = (id, type) => {
+        const notification = notifications.get(id);
+        if (!notification) {
+            return;
+        }
+        if (type === "close" || type === "error") {
+            notifications.delete(id);
+        }
+        const handler = notification["on" + type];
+        if (typeof handler === "function") {
+            handler.call(notification, { type, target: notification });
+        }
+    };
+
+    class Notification {
+        #id;
+
+        static get permission() {
+            return permission;
+        }
+
+        static requestPermission(callback) {
+            const answer =
+                permission === "default"
+                    ? new Promise((resolve) => {
+                          waiting.push(resolve);
+                          host.requestNotificationPermission();
+                      })
+                    : Promise.resolve(permission);
+            if (typeof callback === "function") {
+                answer.then(callback);
+            }
+            return answer;
+        }
+
+        constructor(title, options = {}) {
+            if (arguments.length === 0) {
+                throw new TypeError("Notification requires a title");
+            }
+            this.title = String(title);
+            this.body = options.body === undefined ? "" : String(options.body);
+            this.tag = options.tag === undefined ? "" : String(options.tag);
+            this.data = options.data === undefined ? null : options.data;
+            this.onshow = null;
+            this.onclick = null;
+            this.onclose = null;
+            this.onerror = null;
+            this.#id = nextId++;
+            notifications.set(this.#id, this);
+            // 許されていなければブラウザが error を返す
+            host.showNotification(this.#id, this.title, this.body, this.tag);
+        }
+
+        close() {
+            if (notifications.has(this.#id)) {
+                host.closeNotification(this.#id);
+            }
+        }
+    }
+
+    globalThis.Notification = Notification;
+})();
@@ -0,0 +1,209 @@
+//! Web Storage（localStorage と sessionStorage）の中身
+//!
+//! オリジンごとのキーと値の組を持つ。localStorage はブラウザ全体で 1 つ共有して
+//! プロファイルに保存し、sessionStorage はタブごとにメモリーにだけ持つ。
+//! 1 つのオリジンが保存できるのは、キーと値の長さ（UTF-16 の符号単位）の合計で
+//! [`QUOTA`] まで。
+//!
+//! 保存するときの形式は 1 行に 1 項目で、タブ・改行・`\` はエスケープする。
+//!
+//! ```text
+//! item	https://example.com	theme	dark
+//! ```
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use url::Url;
+
+/// 1 つのオリジンが保存できる量（UTF-16 の符号単位）
+pub const QUOTA: usize = 5 * 1024 * 1024;
+
+/// WebView とバインディングで共有するストレージ
+pub type SharedStorage = Rc<RefCell<WebStorage>>;
+
+/// 保存すると QUOTA を超える
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuotaExceeded;
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "The quota of {QUOTA} characters has been exceeded")
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebStorage {
+    /// オリジン → (キー → 値)
+    areas: BTreeMap<String, BTreeMap<String, String>>,
+    /// 前に take_modified してから書き換えたか
+    modified: bool,
+}
+
+/// url のストレージのオリジン（data: などの不透明なオリジンでは使えないので None）
+pub fn storage_origin(url: &Url) -> Option<String> {
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+fn utf16_len(s: &str) -> usize {
+    s.encode_utf16().count()
+}
+
+impl WebStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn shared(self) -> SharedStorage {
+        Rc::new(RefCell::new(self))
+    }
+
+    pub fn len(&self, origin: &str) -> usize {
+        self.areas.get(origin).map_or(0, BTreeMap::len)
+    }
+
+    pub fn is_empty(&self, origin: &str) -> bool {
+        self.len(origin) == 0
+    }
+
+    /// index 番目のキー（キーの順）
+    pub fn key(&self, origin: &str, index: usize) -> Option<&str> {
+        self.areas
+            .get(origin)?
+            .keys()
+            .nth(index)
+            .map(String::as_str)
+    }
+
+    pub fn get_item(&self, origin: &str, key: &str) -> Option<&str> {
+        self.areas.get(origin)?.get(key).map(String::as_str)
+    }
+
+    /// key に value を保存する。オリジンの合計が QUOTA を超えるなら何もしない
+    pub fn set_item(&mut self, origin: &str, key: &str, value: &str) -> Result<(), QuotaExceeded> {
+        let old = self
+            .get_item(origin, key)
+            .map_or(0, |v| utf16_len(key) + utf16_len(v));
+        let usage = self.usage(origin) - old + utf16_len(key) + utf16_len(value);
+        if usage > QUOTA {
+            return Err(QuotaExceeded);
+        }
+
+        let area = self.areas.entry(origin.to_string()).or_default();
+        if area.get(key).map(String::as_str) != Some(value) {
+            area.insert(key.to_string(), value.to_string());
+            self.modified = true;
+        }
+        Ok(())
+    }
+
+    pub fn remove_item(&mut self, origin: &str, key: &str) {
+        let Some(area) = self.areas.get_mut(origin) else {
+            return;
+        };
+        if area.remove(key).is_some() {
+            self.modified = true;
+        }
+        if area.is_empty() {
+            self.areas.remove(origin);
+        }
+    }
+
+    /// origin の項目をすべて消す
+    pub fn clear(&mut self, origin: &str) {
+        if self.areas.remove(origin).is_some() {
+            self.modified = true;
+        }
+    }
+
+    /// すべてのオリジンの項目を消す
+    pub fn clear_all(&mut self) {
+        if !self.areas.is_empty() {
+            self.areas.clear();
+            self.modified = true;
+        }
+    }
+
+    /// origin が使っている量（キーと値の UTF-16 の長さの合計）
+    pub fn usage(&self, origin: &str) -> usize {
+        self.areas.get(origin).map_or(0, |area| {
+            area.iter().map(|(k, v)| utf16_len(k) + utf16_len(v)).sum()
+        })
+    }
+
+    /// 前に呼んでから書き換えられたか（保存するかどうかの判断に使う）
+    pub fn take_modified(&mut self) -> bool {
+        std::mem::take(&mut self.modified)
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut out = String::new();
+        for (origin, area) in &self.areas {
+            for (key, value) in area {
+                out.push_str(&format!(
+                    "item\t{}\t{}\t{}\n",
+                    escape(origin),
+                    escape(key),
+                    escape(value)
+                ));
+            }
+        }
+        out
+    }
+
+    /// 読めない行は飛ばす
+    pub fn parse(text: &str) -> Self {
+        let mut storage = Self::default();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let ["item", origin, key, value] = fields.as_slice() else {
+                log::warn!("Skipping invalid storage entry: {:?}", line);
+                continue;
+            };
+            storage
+                .areas
+                .entry(unescape(origin))
+                .or_default()
+                .insert(unescape(key), unescape(value));
+        }
+        storage
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
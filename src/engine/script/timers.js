@@ -0,0 +1,47 @@
+// setTimeout / setInterval と queueMicrotask
+//
+// コールバックは realm の中で持ち、ブラウザには id と遅延だけを渡す。期限が来ると
+// ブラウザが __orinium.fireTimer(id) を呼ぶ。
+(() => {
+    const host = globalThis.__orinium;
+    const timers = new Map();
+    let nextId = 1;
+
+    const schedule = (callback, delay, args, repeat) => {
+        const id = nextId++;
+        timers.set(id, { callback, args, repeat });
+        host.setTimer(id, Number(delay) || 0, repeat);
+        return id;
+    };
+    const clear = (id) => {
+        id = Number(id);
+        if (timers.delete(id)) {
+            host.clearTimer(id);
+        }
+    };
+
+    globalThis.setTimeout = (callback, delay = 0, ...args) => schedule(callback, delay, args, false);
+    globalThis.setInterval = (callback, delay = 0, ...args) => schedule(callback, delay, args, true);
+    globalThis.clearTimeout = clear;
+    globalThis.clearInterval = clear;
+    if (typeof globalThis.queueMicrotask !== "function") {
+        globalThis.queueMicrotask = (callback) => {
+            Promise.resolve().then(() => callback());
+        };
+    }
+
+    host.fireTimer = (id) => {
+        const timer = timers.get(id);
+        if (!timer) {
+            return;
+        }
+        if (!timer.repeat) {
+            timers.delete(id);
+        }
+        if (typeof timer.callback === "function") {
+            timer.callback(...timer.args);
+        } else {
+            (0, eval)(String(timer.callback));
+        }
+    };
+})();
@@ -6,6 +6,9 @@ pub mod browser;
 /// JavaScriptエンジンなどブラウザの中核となる機能が含まれます。
 pub mod engine;
 
+/// 他のアプリにページのビューとして組み込むためのモジュール
+pub mod embed;
+
 /// プラットフォーム依存の機能を提供するモジュール
 /// このモジュールには、ネットワーク処理、レンダリング、UI表示、
 /// ファイルI/Oなどプラットフォーム固有の実装が含まれます。
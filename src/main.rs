@@ -1,25 +1,179 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
+use clap::Parser;
+use orinium_browser::browser::core::ui::url_bar::resolve_command_line;
+use orinium_browser::browser::settings::SETTINGS_FILE_NAME;
 use orinium_browser::browser::{BrowserApp, Tab};
+use orinium_browser::engine::renderer_model::paginate::PaperSize;
 use std::env;
+use std::path::PathBuf;
+use url::Url;
+
+/// Orinium Browser
+#[derive(Debug, Parser)]
+#[command(name = "orinium", version)]
+struct Cli {
+    /// URL or file path to open
+    url: Option<String>,
+
+    /// Run without opening a window
+    #[arg(long)]
+    headless: bool,
+
+    /// Render the page to a PNG file and exit
+    #[arg(long, value_name = "PATH", requires = "headless")]
+    screenshot: Option<PathBuf>,
+
+    /// Print the layout tree of the page and exit
+    #[arg(long)]
+    dump_layout: bool,
+
+    /// Print the page to an A4 PDF file and exit
+    #[arg(long, value_name = "PATH")]
+    print_to_pdf: Option<PathBuf>,
+
+    /// Size of the window, or of the page for --screenshot and --dump-layout
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_window_size, default_value = "800x600")]
+    window_size: (u32, u32),
+
+    /// Directory for the session, history, cookies and cache
+    #[arg(long, value_name = "DIR")]
+    profile_dir: Option<PathBuf>,
+
+    /// User-Agent header to send instead of the default one
+    #[arg(long, value_name = "STRING")]
+    user_agent: Option<String>,
+
+    /// Print page load metrics to standard output
+    #[arg(long)]
+    metrics: bool,
+
+    /// Accept DevTools protocol clients on this port
+    #[arg(long, value_name = "PORT")]
+    remote_debugging_port: Option<u16>,
+
+    /// Accept DevTools WebSocket clients from these page origins (comma-separated, * for any)
+    #[arg(long, value_name = "ORIGINS", value_delimiter = ',')]
+    remote_allow_origins: Vec<String>,
+}
 
 fn main() -> Result<()> {
-    let args: Vec<String> = env::args().collect();
-    let _font_path = if args.len() > 1 {
-        Some(args[1].clone())
-    } else {
-        None
+    let cli = Cli::parse();
+    let startup_url = cli.url.as_deref().and_then(|arg| {
+        let cwd = env::current_dir().unwrap_or_default();
+        resolve_command_line(arg, &cwd)
+    });
+
+    orinium_browser::platform::logging::init();
+
+    // ページの処理はエンジンのスレッドで行い、このスレッドはウィンドウだけを受け持つ
+    if !cli.headless && !cli.dump_layout && cli.print_to_pdf.is_none() {
+        return BrowserApp::run_on_engine_thread(move || {
+            let mut browser = new_browser(&cli);
+            open_startup_pages(
+                &mut browser,
+                startup_url,
+                cli.remote_debugging_port,
+                &cli.remote_allow_origins,
+            )?;
+            Ok(browser)
+        });
+    }
+
+    let mut browser = new_browser(&cli);
+
+    // レイアウトツリーを標準出力に書いて終わる（セッションは復元しない）
+    if cli.dump_layout {
+        let Some(url) = startup_url else {
+            bail!("--dump-layout needs a URL or a file path");
+        };
+        print!("{}", browser.dump_layout(url, cli.window_size)?);
+        return Ok(());
+    }
+
+    // ページを A4 の PDF に書き出して終わる
+    if let Some(path) = &cli.print_to_pdf {
+        let Some(url) = startup_url else {
+            bail!("--print-to-pdf needs a URL or a file path");
+        };
+        std::fs::write(path, browser.print_page_to_pdf(url, PaperSize::A4)?)?;
+        return Ok(());
+    }
+
+    // ページをウィンドウの大きさで PNG に描いて終わる
+    if let Some(path) = &cli.screenshot {
+        let Some(url) = startup_url else {
+            bail!("--screenshot needs a URL or a file path");
+        };
+        std::fs::write(path, browser.render_page_to_png(url, cli.window_size)?)?;
+        return Ok(());
+    }
+
+    open_startup_pages(
+        &mut browser,
+        startup_url,
+        cli.remote_debugging_port,
+        &cli.remote_allow_origins,
+    )?;
+    browser.run_headless()
+}
+
+/// `1280x720` のようなウィンドウの大きさを読む（Chrome と同じ `1280,720` でもよい）
+fn parse_window_size(arg: &str) -> Result<(u32, u32)> {
+    let Some((width, height)) = arg.split_once(['x', ',']) else {
+        bail!("expected WIDTHxHEIGHT, e.g. 1280x720");
     };
+    let (width, height) = (width.trim().parse::<u32>()?, height.trim().parse::<u32>()?);
+    if width == 0 || height == 0 {
+        bail!("the window size must not be zero");
+    }
+    Ok((width, height))
+}
 
-    env_logger::init();
+/// 設定とプロファイルの置き場所を決めたブラウザを作る
+fn new_browser(cli: &Cli) -> BrowserApp {
+    let mut browser = BrowserApp::new(cli.window_size, "Orinium Browser".to_string());
+    browser.set_print_metrics(cli.metrics);
+    if let Some(user_agent) = &cli.user_agent {
+        browser.set_user_agent(user_agent.clone());
+    }
 
-    let mut browser = BrowserApp::default();
+    match orinium_browser::platform::io::config_dir() {
+        Ok(dir) => browser.set_settings_path(dir.join(SETTINGS_FILE_NAME)),
+        Err(e) => log::warn!("Settings will not be saved: {:#}", e),
+    }
 
-    let mut tab = Tab::new();
-    tab.navigate("resource:///test/compatibility_test.html".parse()?);
+    let profile_dir = match &cli.profile_dir {
+        Some(dir) => Ok(dir.clone()),
+        None => orinium_browser::platform::io::profile_dir(),
+    };
+    match profile_dir {
+        Ok(dir) => browser.set_profile_dir(dir),
+        Err(e) => log::warn!("Session will not be saved: {:#}", e),
+    }
+    browser
+}
 
-    browser.add_tab(tab);
+/// セッションを復元するか最初のページを開き、頼まれていればリモートデバッグを始める
+fn open_startup_pages(
+    browser: &mut BrowserApp,
+    startup_url: Option<Url>,
+    remote_debugging_port: Option<u16>,
+    remote_allow_origins: &[String],
+) -> Result<()> {
+    let restored = browser.restore_session();
+    if let Some(url) = startup_url {
+        browser.open_in_new_tab(url);
+    } else if !restored {
+        let mut tab = Tab::new();
+        tab.navigate(browser.settings().homepage.clone());
 
-    browser.run()?;
+        browser.add_tab(tab);
+    }
 
+    if let Some(port) = remote_debugging_port {
+        let addr = browser.listen_remote_debugging(port, remote_allow_origins.to_vec())?;
+        // 自動化ツールが読めるよう、Chrome と同じ形で標準エラーに出す
+        eprintln!("DevTools listening on ws://{addr}/devtools/browser");
+    }
     Ok(())
 }
@@ -1,3 +1,4 @@
+use crate::browser::core::schemes::SchemeRegistry;
 use crate::platform::io as platform_io;
 use anyhow::{Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
@@ -137,30 +138,15 @@ impl SoundManager {
         self.play_from_bytes(&data)
     }
 
-    /// URIから音声を再生する（resourceまたはfileスキームに対応）
+    /// URIから音声を再生する（`resource:///`、`file://`、`data:` とローカルファイルのパス）
     ///
+    /// URI は [`SchemeRegistry`] で読むので、タブが読む URL と同じように解決される。
     /// 通常の再生には `play_from_bytes` を使用してください。
     /// これはテスト用メソッドです
     pub fn play_from_local_uri(&mut self, uri: &str) -> Result<()> {
-        if uri.starts_with("resource:") {
-            let rel = uri
-                .trim_start_matches("resource:///")
-                .trim_start_matches("resource://")
-                .trim_start_matches("resource:/")
-                .trim_start_matches("resource:");
-            let rel = rel.trim_start_matches('/');
-            let data = platform_io::load_resource(rel)
-                .with_context(|| format!("Failed to load resource: {}", rel))?;
-            return self.play_from_bytes(&data);
-        }
-        if uri.starts_with("file://") {
-            let p = uri.trim_start_matches("file://");
-            let data = platform_io::load_local_file(p)
-                .with_context(|| format!("Failed to read local file from URI: {}", p))?;
-            return self.play_from_bytes(&data);
-        }
-        let data = platform_io::load_local_file(uri)
-            .with_context(|| format!("Failed to read local file: {}", uri))?;
+        let data = SchemeRegistry::new()
+            .load_uri(uri)
+            .with_context(|| format!("Failed to load audio from {}", uri))?;
         self.play_from_bytes(&data)
     }
 }
@@ -0,0 +1,40 @@
+//! システムクリップボードの Facade
+//!
+//! 実装は arboard に任せる。Linux では `Clipboard` を破棄すると書き込んだ内容が
+//! 失われることがあるため、スレッドごとに 1 つ作って使い回す。
+
+use anyhow::Result;
+use arboard::Clipboard;
+use std::cell::RefCell;
+
+thread_local! {
+    static CLIPBOARD: RefCell<Option<Clipboard>> = const { RefCell::new(None) };
+}
+
+fn with_clipboard<T>(f: impl FnOnce(&mut Clipboard) -> Result<T, arboard::Error>) -> Result<T> {
+    CLIPBOARD.with(|cell| {
+        let mut slot = cell.borrow_mut();
+        let clipboard = match slot.as_mut() {
+            Some(clipboard) => clipboard,
+            None => slot.insert(Clipboard::new()?),
+        };
+
+        Ok(f(clipboard)?)
+    })
+}
+
+/// クリップボードの文字列を読む
+///
+/// # Errors
+/// クリップボードが使えない、または文字列が入っていない場合はエラーを返す。
+pub fn read_text() -> Result<String> {
+    with_clipboard(|c| c.get_text())
+}
+
+/// クリップボードに文字列を書き込む
+///
+/// # Errors
+/// クリップボードが使えない場合はエラーを返す。
+pub fn write_text(text: &str) -> Result<()> {
+    with_clipboard(|c| c.set_text(text))
+}
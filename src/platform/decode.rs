@@ -0,0 +1,126 @@
+//! 画像を別のスレッドでデコードする
+//!
+//! 大きな JPEG のデコードにはフレーム何枚分もかかることがあるので、エンジンや UI の
+//! スレッドでは行わず、いくつかのワーカーのスレッドに任せる。デコードし終えたものは
+//! 作るときに渡した関数に [`Decoded`] として渡す（ブラウザはコマンドとして自分に送り、
+//! 次の `process_commands` で受け取って、その画像を使うタブに渡す）。
+//!
+//! フォントなど、ほかの重いデコードもここに足していく。
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use image::RgbaImage;
+use url::Url;
+
+use crate::platform::network::StoragePartition;
+
+/// ワーカーの数の上限（ページの処理に使う分のコアは残す）
+const MAX_WORKERS: usize = 4;
+
+/// デコードし終えたもの
+#[derive(Debug, Clone)]
+pub enum Decoded {
+    /// partition のタブが読んだ url の画像（デコードできなかったときはその理由）
+    Image {
+        url: Url,
+        partition: StoragePartition,
+        image: Result<Arc<RgbaImage>, String>,
+    },
+}
+
+enum Job {
+    Image {
+        url: Url,
+        partition: StoragePartition,
+        bytes: Vec<u8>,
+    },
+}
+
+/// デコードのワーカーのスレッド
+pub struct DecodePool {
+    /// 閉じるとワーカーが終わる
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl DecodePool {
+    /// コアの数に合わせた数のワーカーを立て、デコードし終えたものを done に渡す
+    pub fn new(done: impl Fn(Decoded) + Send + Sync + 'static) -> Self {
+        let workers = std::thread::available_parallelism()
+            .map_or(1, |n| n.get() / 2)
+            .clamp(1, MAX_WORKERS);
+        Self::with_workers(workers, done)
+    }
+
+    pub fn with_workers(workers: usize, done: impl Fn(Decoded) + Send + Sync + 'static) -> Self {
+        let (jobs, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let done: Arc<dyn Fn(Decoded) + Send + Sync> = Arc::new(done);
+        let workers = (0..workers.max(1))
+            .filter_map(|index| {
+                let receiver = receiver.clone();
+                let done = done.clone();
+                std::thread::Builder::new()
+                    .name(format!("orinium-decode-{index}"))
+                    .spawn(move || run(&receiver, done.as_ref()))
+                    .inspect_err(|e| log::error!("Failed to start a decode thread: {}", e))
+                    .ok()
+            })
+            .collect();
+        Self {
+            jobs: Some(jobs),
+            workers,
+        }
+    }
+
+    /// partition のタブが url から読んだ bytes を画像としてデコードする
+    pub fn decode_image(&self, url: Url, partition: StoragePartition, bytes: Vec<u8>) {
+        if let Some(jobs) = &self.jobs {
+            let _ = jobs.send(Job::Image {
+                url,
+                partition,
+                bytes,
+            });
+        }
+    }
+}
+
+impl Drop for DecodePool {
+    fn drop(&mut self) {
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            if worker.join().is_err() {
+                log::error!("A decode thread panicked");
+            }
+        }
+    }
+}
+
+/// ワーカーのループ。送り口が閉じるまで続ける
+fn run(jobs: &Mutex<Receiver<Job>>, done: &(dyn Fn(Decoded) + Send + Sync)) {
+    loop {
+        // 待つ間だけロックを持つ
+        let job = match jobs.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        match job {
+            Job::Image {
+                url,
+                partition,
+                bytes,
+            } => {
+                let image = image::load_from_memory(&bytes)
+                    .map(|image| Arc::new(image.to_rgba8()))
+                    .map_err(|e| e.to_string());
+                done(Decoded::Image {
+                    url,
+                    partition,
+                    image,
+                });
+            }
+        }
+    }
+}
@@ -1,7 +1,17 @@
-//! システムフォント取得の Facade
+//! システムフォント取得とフォント選択の Facade
 
 use anyhow::Result;
+use glyphon::{
+    Attrs, FontSystem,
+    cosmic_text::{CacheKeyFlags, Family},
+    fontdb,
+};
+use std::collections::{HashMap, HashSet};
+use std::env;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::engine::layouter::types::{FontFamilyList, FontStyle, FontWeight, TextStyle};
 
 #[cfg(target_os = "windows")]
 use crate::platform::os::windows;
@@ -28,3 +38,233 @@ pub fn system_font_candidates() -> Result<Vec<PathBuf>> {
 
     anyhow::bail!("system font is not supported on this OS yet");
 }
+
+/// 主フォントに無いグリフ（CJK・記号・絵文字）用のフォールバック候補
+#[allow(unreachable_code)]
+pub fn fallback_font_candidates() -> Result<Vec<PathBuf>> {
+    #[cfg(target_os = "windows")]
+    {
+        return windows::font::fallback_font_candidates();
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        return macos::font::fallback_font_candidates();
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return crate::platform::os::linux::font::fallback_font_candidates();
+    }
+
+    Ok(Vec::new())
+}
+
+/// 既定の主フォントを読む（`ORINIUM_FONT` → システムフォント候補の順）
+///
+/// 戻り値は (識別用のパス, フォントのバイト列)
+pub fn default_font() -> Result<(String, Vec<u8>)> {
+    if let Ok(p) = env::var("ORINIUM_FONT")
+        && let Ok(bytes) = std::fs::read(&p)
+    {
+        return Ok((p, bytes));
+    }
+
+    for p in system_font_candidates()? {
+        if let Ok(bytes) = std::fs::read(&p) {
+            return Ok((p.to_string_lossy().into_owned(), bytes));
+        }
+    }
+
+    anyhow::bail!("no system font found");
+}
+
+/// 既定フォントで作ったプロセス共有の `FontSystem`
+///
+/// システムフォントのスキャンは重いため一度だけ行い、
+/// 計測と描画が同じフォント ID でシェーピングできるよう共有する。
+pub fn shared_font_system() -> Result<Arc<Mutex<FontSystem>>> {
+    static SHARED: OnceLock<Arc<Mutex<FontSystem>>> = OnceLock::new();
+
+    if let Some(font_sys) = SHARED.get() {
+        return Ok(font_sys.clone());
+    }
+
+    let (_, bytes) = default_font()?;
+    Ok(SHARED
+        .get_or_init(|| Arc::new(Mutex::new(font_system_with_fallbacks(bytes))))
+        .clone())
+}
+
+/// 主フォントとフォールバックフォントを読み込んだ `FontSystem` を作る
+///
+/// generic family（sans-serif など）は主フォントに向ける。
+/// 主フォントに無い文字は cosmic-text のシェーピング時にフォールバックフォントへ
+/// run が分割されるため、計測（`PlatformTextMeasurer`）と描画（`TextRenderer`）の
+/// 両方で同じ関数を使って結果を一致させること。
+pub fn font_system_with_fallbacks(primary: Vec<u8>) -> FontSystem {
+    // FontSystem::new はシステムフォントも読み込む
+    let mut font_sys = FontSystem::new();
+    let db = font_sys.db_mut();
+
+    let ids = db.load_font_source(fontdb::Source::Binary(Arc::new(primary)));
+    let primary_family = ids
+        .first()
+        .and_then(|id| db.face(*id))
+        .and_then(|face| face.families.first())
+        .map(|(name, _)| name.clone());
+    if let Some(family) = primary_family {
+        db.set_sans_serif_family(family.clone());
+        db.set_serif_family(family.clone());
+        db.set_monospace_family(family);
+    }
+
+    // システムフォントのスキャンで既に読み込まれているファイルは飛ばす
+    let loaded: HashSet<PathBuf> = db
+        .faces()
+        .filter_map(|face| match &face.source {
+            fontdb::Source::File(path) => Some(path.clone()),
+            _ => None,
+        })
+        .collect();
+
+    match fallback_font_candidates() {
+        Ok(paths) => {
+            for path in paths.iter().filter(|p| p.exists() && !loaded.contains(*p)) {
+                if let Err(e) = db.load_font_file(path) {
+                    log::debug!(target: "PFont", "failed to load fallback font {:?}: {}", path, e);
+                }
+            }
+        }
+        Err(e) => log::debug!(target: "PFont", "no fallback fonts: {}", e),
+    }
+
+    font_sys
+}
+
+/// CSS の font-family / font-weight / font-style から選ばれた実フォント
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontMatch {
+    /// 実際に使うフェイスのファミリー名
+    pub family: String,
+    /// 要求より細いフェイスしか見つからなかった
+    pub synthetic_bold: bool,
+    /// イタリック / 斜体のフェイスが見つからなかった
+    pub synthetic_italic: bool,
+}
+
+/// `font-family` リストを実フォントに解決する
+///
+/// リストの先頭から順に、データベースにあるファミリーを探す。
+/// 解決結果は (family, weight, style) ごとにキャッシュする。
+#[derive(Debug, Default)]
+pub struct FontMatcher {
+    cache: HashMap<(FontFamilyList, FontWeight, FontStyle), Option<FontMatch>>,
+}
+
+impl FontMatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// スタイルに合うフェイスを探す。どのファミリーも見つからなければ `None`（デフォルトフォントを使う）
+    pub fn resolve(&mut self, db: &fontdb::Database, style: &TextStyle) -> Option<FontMatch> {
+        let key = (style.font_family, style.font_weight, style.font_style);
+        self.cache
+            .entry(key)
+            .or_insert_with(|| {
+                match_face(
+                    db,
+                    &style.font_family.names(),
+                    style.font_weight,
+                    style.font_style,
+                )
+            })
+            .clone()
+    }
+}
+
+/// マッチ結果を cosmic-text の属性に反映する
+///
+/// イタリックのフェイスが無い場合は斜体を合成する。
+/// 疑似ボールドは cosmic-text 側が未対応のため、最も近い太さのフェイスでシェーピングし、
+/// 描画時に少しずらして重ね描きする（[`synthetic_bold_offset`]）。
+pub fn apply_font_match<'a>(attrs: Attrs<'a>, font_match: &'a FontMatch) -> Attrs<'a> {
+    let mut attrs = attrs.family(Family::Name(&font_match.family));
+    if font_match.synthetic_italic {
+        attrs = attrs.cache_key_flags(CacheKeyFlags::FAKE_ITALIC);
+    }
+    attrs
+}
+
+/// 疑似ボールドで重ね描きするときの横のずらし幅（font-size に対する倍率）
+const SYNTHETIC_BOLD_EM: f32 = 1.0 / 24.0;
+
+/// style の文字を疑似ボールドにするなら、重ね描きする横のずらし幅（font_size と同じ単位）
+///
+/// 要求どおりの太さのフェイスがあれば `None`。
+pub fn synthetic_bold_offset(
+    font_matcher: &mut FontMatcher,
+    db: &fontdb::Database,
+    style: &TextStyle,
+) -> Option<f32> {
+    font_matcher
+        .resolve(db, style)
+        .filter(|font_match| font_match.synthetic_bold)
+        .map(|_| (style.font_size * SYNTHETIC_BOLD_EM).max(1.0))
+}
+
+/// CSS の generic family を fontdb の Family に変換する
+fn generic_family(name: &str) -> Option<fontdb::Family<'static>> {
+    match name.to_ascii_lowercase().as_str() {
+        "serif" => Some(fontdb::Family::Serif),
+        "sans-serif" | "system-ui" | "-apple-system" | "blinkmacsystemfont" => {
+            Some(fontdb::Family::SansSerif)
+        }
+        "monospace" | "ui-monospace" => Some(fontdb::Family::Monospace),
+        "cursive" => Some(fontdb::Family::Cursive),
+        "fantasy" => Some(fontdb::Family::Fantasy),
+        _ => None,
+    }
+}
+
+fn match_face(
+    db: &fontdb::Database,
+    names: &[String],
+    weight: FontWeight,
+    style: FontStyle,
+) -> Option<FontMatch> {
+    let query_style = match style {
+        FontStyle::Normal => fontdb::Style::Normal,
+        FontStyle::Italic => fontdb::Style::Italic,
+        FontStyle::Oblique => fontdb::Style::Oblique,
+    };
+
+    for name in names {
+        let family = match generic_family(name) {
+            Some(family) => family,
+            None => fontdb::Family::Name(name),
+        };
+        let query = fontdb::Query {
+            families: &[family],
+            weight: fontdb::Weight(weight.0),
+            stretch: fontdb::Stretch::Normal,
+            style: query_style,
+        };
+
+        let Some(face) = db.query(&query).and_then(|id| db.face(id)) else {
+            continue;
+        };
+        let Some((family, _)) = face.families.first() else {
+            continue;
+        };
+
+        return Some(FontMatch {
+            family: family.clone(),
+            synthetic_bold: weight >= FontWeight(600) && face.weight.0 < 600,
+            synthetic_italic: style != FontStyle::Normal && face.style == fontdb::Style::Normal,
+        });
+    }
+
+    None
+}
@@ -0,0 +1,202 @@
+//! 現在地の取得の Facade
+//!
+//! OS の位置情報サービス（macOS の CoreLocation、Windows の Windows.Devices.Geolocation、
+//! Linux の GeoClue）に問い合わせる。どれも時間がかかる（利用者の許可を待つこともある）ので、
+//! [`Geolocation`] が別スレッドで問い合わせ、結果を [`Geolocation::poll`] で返す。
+//!
+//! ページに現在地を渡してよいかは呼ぶ側（権限の仕組み）が決める。
+
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+
+/// 問い合わせの既定の待ち時間
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 現在地
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    /// 緯度（度）
+    pub latitude: f64,
+    /// 経度（度）
+    pub longitude: f64,
+    /// 誤差（メートル、95% の信頼度）
+    pub accuracy: f64,
+}
+
+/// 現在地を取れなかった理由（`GeolocationPositionError` の code に対応する）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionError {
+    /// 利用者かサイトの設定が許さなかった
+    PermissionDenied,
+    /// 位置情報サービスが使えないか、答えられなかった
+    PositionUnavailable(String),
+    /// 待ち時間のうちに答えがなかった
+    Timeout,
+}
+
+impl PositionError {
+    pub fn code(&self) -> u16 {
+        match self {
+            PositionError::PermissionDenied => 1,
+            PositionError::PositionUnavailable(_) => 2,
+            PositionError::Timeout => 3,
+        }
+    }
+}
+
+/// 現在地を答えるもの
+pub trait LocationProvider: Send {
+    /// 名前（ログ用）
+    fn name(&self) -> &'static str;
+
+    /// 今の位置を timeout まで待って返す（呼んだスレッドを止める）
+    fn current_position(&mut self, timeout: Duration) -> Result<Position, PositionError>;
+}
+
+/// 決まった位置を答える（位置を指定して起動したときとテスト用）
+#[derive(Debug, Clone, Copy)]
+pub struct FixedLocationProvider(pub Position);
+
+impl LocationProvider for FixedLocationProvider {
+    fn name(&self) -> &'static str {
+        "fixed"
+    }
+
+    fn current_position(&mut self, _timeout: Duration) -> Result<Position, PositionError> {
+        Ok(self.0)
+    }
+}
+
+/// この OS の位置情報サービス（使えなければ None）
+#[allow(unreachable_code)]
+pub fn default_provider() -> Option<Box<dyn LocationProvider>> {
+    #[cfg(target_os = "macos")]
+    {
+        return crate::platform::os::macos::geolocation::CoreLocationProvider::find()
+            .map(|p| Box::new(p) as Box<dyn LocationProvider>);
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        return Some(Box::new(
+            crate::platform::os::windows::geolocation::WindowsLocationProvider,
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return crate::platform::os::linux::geolocation::GeoClueProvider::find()
+            .map(|p| Box::new(p) as Box<dyn LocationProvider>);
+    }
+
+    None
+}
+
+/// `緯度 経度 誤差` の形の 1 行を読む（OS のコマンドの出力）
+pub fn parse_position(text: &str) -> Option<Position> {
+    let mut numbers = text.split_whitespace().map(|s| s.parse::<f64>().ok());
+    let position = Position {
+        latitude: numbers.next()??,
+        longitude: numbers.next()??,
+        accuracy: numbers.next()??,
+    };
+    let valid = (-90.0..=90.0).contains(&position.latitude)
+        && (-180.0..=180.0).contains(&position.longitude)
+        && position.accuracy >= 0.0;
+    valid.then_some(position)
+}
+
+/// command を実行し、timeout までに終われば標準出力を返す（終わらなければ止める）
+pub fn output_with_timeout(
+    command: &mut Command,
+    timeout: Duration,
+) -> Result<String, PositionError> {
+    let unavailable = |e: anyhow::Error| PositionError::PositionUnavailable(format!("{:#}", e));
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {:?}", command.get_program()))
+        .map_err(unavailable)?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(PositionError::Timeout);
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(unavailable(e.into())),
+        }
+    }
+    finish(child).map_err(unavailable)
+}
+
+fn finish(child: Child) -> Result<String> {
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        bail!(
+            "{}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// 問い合わせの番号と結果
+pub type PositionResult = (u64, Result<Position, PositionError>);
+
+/// 別スレッドで現在地を問い合わせる
+///
+/// 問い合わせは順に 1 つずつ行う。答えを待っている間に来た問い合わせは、
+/// 同じ答えでまとめて返す。
+pub struct Geolocation {
+    requests: Sender<(u64, Duration)>,
+    results: Receiver<PositionResult>,
+}
+
+impl Geolocation {
+    pub fn new(mut provider: Box<dyn LocationProvider>) -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<(u64, Duration)>();
+        let (result_tx, result_rx) = mpsc::channel();
+        log::info!("Using the {} location provider", provider.name());
+
+        thread::spawn(move || {
+            while let Ok((id, timeout)) = request_rx.recv() {
+                let mut ids = vec![id];
+                let result = provider.current_position(timeout);
+                ids.extend(request_rx.try_iter().map(|(id, _)| id));
+                for id in ids {
+                    if result_tx.send((id, result.clone())).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Self {
+            requests: request_tx,
+            results: result_rx,
+        }
+    }
+
+    /// 番号 id の問い合わせを始める。答えは poll で返す
+    pub fn request(&self, id: u64, timeout: Duration) {
+        if self.requests.send((id, timeout)).is_err() {
+            log::warn!("Geolocation worker has stopped");
+        }
+    }
+
+    /// 届いた答え
+    pub fn poll(&self) -> Vec<PositionResult> {
+        self.results.try_iter().collect()
+    }
+}
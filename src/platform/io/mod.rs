@@ -1,6 +1,214 @@
 use anyhow::{Context, Result};
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+/// プロファイル（セッションや履歴など）を保存するディレクトリ
+///
+/// `ORINIUM_PROFILE_DIR` があればそれを使い、なければ OS ごとのデータ置き場を使う。
+/// - Linux: `$XDG_DATA_HOME/orinium`（なければ `~/.local/share/orinium`）
+/// - macOS: `~/Library/Application Support/Orinium`
+/// - Windows: `%APPDATA%\Orinium`
+pub fn profile_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("ORINIUM_PROFILE_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+
+    let dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(|d| PathBuf::from(d).join("Orinium"))
+    } else if cfg!(target_os = "macos") {
+        home().map(|h| h.join("Library/Application Support/Orinium"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|h| h.join(".local/share")))
+            .map(|d| d.join("orinium"))
+    };
+
+    dir.context("Could not determine the profile directory")
+}
+
+/// 設定ファイルを置くディレクトリ
+///
+/// `ORINIUM_CONFIG_DIR` があればそれを使い、なければ OS ごとの設定置き場を使う。
+/// - Linux: `$XDG_CONFIG_HOME/orinium`（なければ `~/.config/orinium`）
+/// - macOS: `~/Library/Application Support/Orinium`
+/// - Windows: `%APPDATA%\Orinium`
+pub fn config_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("ORINIUM_CONFIG_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+
+    let dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(|d| PathBuf::from(d).join("Orinium"))
+    } else if cfg!(target_os = "macos") {
+        home().map(|h| h.join("Library/Application Support/Orinium"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|h| h.join(".config")))
+            .map(|d| d.join("orinium"))
+    };
+
+    dir.context("Could not determine the configuration directory")
+}
+
+/// ダウンロードしたファイルを置くディレクトリ
+///
+/// `ORINIUM_DOWNLOAD_DIR` があればそれを使い、なければ OS ごとの置き場を使う。
+/// - Linux: `$XDG_DOWNLOAD_DIR`（なければ `~/Downloads`）
+/// - macOS: `~/Downloads`
+/// - Windows: `%USERPROFILE%\Downloads`
+pub fn downloads_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("ORINIUM_DOWNLOAD_DIR") {
+        return Ok(PathBuf::from(dir));
+    }
+
+    let home = || {
+        std::env::var_os(if cfg!(target_os = "windows") {
+            "USERPROFILE"
+        } else {
+            "HOME"
+        })
+        .map(PathBuf::from)
+    };
+
+    let dir = if cfg!(any(target_os = "windows", target_os = "macos")) {
+        home().map(|h| h.join("Downloads"))
+    } else {
+        std::env::var_os("XDG_DOWNLOAD_DIR")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|h| h.join("Downloads")))
+    };
+
+    dir.context("Could not determine the downloads directory")
+}
+
+/// スペルチェックの辞書を探し、(`.aff` の中身, `.dic` の中身) を読む
+///
+/// language は `en_US` のような辞書の名前（`en-US` でもよい）。設定ディレクトリの
+/// `dictionaries` を先に見て、なければ OS の Hunspell の辞書の置き場を探す。
+pub fn load_dictionary(language: &str) -> Result<(Vec<u8>, Vec<u8>)> {
+    let name = language.replace('-', "_");
+    let mut dirs = Vec::new();
+    if let Ok(dir) = config_dir() {
+        dirs.push(dir.join("dictionaries"));
+    }
+    if cfg!(target_os = "macos") {
+        dirs.extend(
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Spelling")),
+        );
+    } else if cfg!(unix) {
+        dirs.extend(
+            [
+                "/usr/share/hunspell",
+                "/usr/share/myspell",
+                "/usr/share/myspell/dicts",
+            ]
+            .map(PathBuf::from),
+        );
+    }
+
+    for dir in dirs {
+        let aff = dir.join(format!("{name}.aff"));
+        let dic = dir.join(format!("{name}.dic"));
+        if aff.is_file() && dic.is_file() {
+            let read =
+                |path: &Path| fs::read(path).with_context(|| format!("Failed to read {:?}", path));
+            return Ok((read(&aff)?, read(&dic)?));
+        }
+    }
+    anyhow::bail!("No dictionary found for {}", language)
+}
+
+/// dir に name で data を保存する。同じ名前があれば `name (1).ext` のようにずらす
+pub fn save_download(dir: &Path, name: &str, data: &[u8]) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, Some(extension)),
+        _ => (name, None),
+    };
+    let mut n = 0;
+    loop {
+        let file_name = match (n, extension) {
+            (0, _) => name.to_string(),
+            (n, Some(extension)) => format!("{stem} ({n}).{extension}"),
+            (n, None) => format!("{stem} ({n})"),
+        };
+        let path = dir.join(file_name);
+        // 既にあるファイルは上書きしない
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                file.write_all(data)
+                    .with_context(|| format!("Failed to write {:?}", path))?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => n += 1,
+            Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", path)),
+        }
+    }
+}
+
+/// path に書き込む。一時ファイルに書いてから置き換えるので、途中で落ちても
+/// 元のファイルは壊れない
+pub fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data).with_context(|| format!("Failed to write {:?}", tmp))?;
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))
+}
+
+/// file:// で開いたパスの中身
+pub enum LocalEntry {
+    File(Vec<u8>),
+    /// ディレクトリ内の一覧（名前順）
+    Directory(Vec<DirEntry>),
+}
+
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// path がファイルなら中身を、ディレクトリなら一覧を読む
+pub fn read_local(path: &Path) -> Result<LocalEntry> {
+    let metadata = fs::metadata(path).with_context(|| format!("Failed to open {:?}", path))?;
+    if !metadata.is_dir() {
+        let data = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+        return Ok(LocalEntry::File(data));
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(path).with_context(|| format!("Failed to list {:?}", path))? {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        // シンボリックリンクはリンク先の種類で扱う
+        let Ok(metadata) = fs::metadata(entry.path()) else {
+            continue;
+        };
+        entries.push(DirEntry {
+            name: entry.file_name().to_string_lossy().into_owned(),
+            is_dir: metadata.is_dir(),
+            size: metadata.len(),
+        });
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(LocalEntry::Directory(entries))
+}
 
 #[allow(dead_code)]
 pub fn load_local_file(path: &str) -> Result<Vec<u8>> {
@@ -0,0 +1,219 @@
+//! パスワードなどの秘密の保存先の Facade
+//!
+//! OS のキーチェーン（macOS のログインキーチェーン、Linux の Secret Service）が
+//! 使えればそこに、使えなければプロファイルの暗号化したファイルに保存する。
+//! どれも「鍵 → 秘密の文字列」の対応を持つだけで、文字列の中身の形は使う側が決める。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use url::form_urlencoded;
+
+use crate::platform::io;
+
+/// 暗号化したファイルの鍵の長さ（ChaCha20-Poly1305）
+const KEY_LEN: usize = 32;
+
+/// 秘密の保存先
+pub trait SecretStore {
+    /// 保存先の名前（ログ用）
+    fn name(&self) -> &'static str;
+
+    /// key の秘密（なければ None）
+    fn get(&mut self, key: &str) -> Result<Option<String>>;
+
+    /// key の秘密を secret にする
+    fn set(&mut self, key: &str, secret: &str) -> Result<()>;
+
+    /// key の秘密を消す（なくてもエラーにしない）
+    fn delete(&mut self, key: &str) -> Result<()>;
+}
+
+/// メモリの中だけの保存先（プロファイルがないときとテスト用）
+#[derive(Debug, Default)]
+pub struct MemorySecretStore {
+    secrets: HashMap<String, String>,
+}
+
+impl MemorySecretStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SecretStore for MemorySecretStore {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn get(&mut self, key: &str) -> Result<Option<String>> {
+        Ok(self.secrets.get(key).cloned())
+    }
+
+    fn set(&mut self, key: &str, secret: &str) -> Result<()> {
+        self.secrets.insert(key.to_string(), secret.to_string());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        self.secrets.remove(key);
+        Ok(())
+    }
+}
+
+/// ChaCha20-Poly1305 で暗号化したファイル
+///
+/// 中身は `鍵=秘密&...`（URL エンコード）を暗号化し、先頭に nonce を付けたもの。
+/// 鍵は別のファイルに置き、Unix では所有者だけが読めるようにする。
+pub struct EncryptedFileStore {
+    path: PathBuf,
+    key: LessSafeKey,
+    rng: SystemRandom,
+    secrets: Vec<(String, String)>,
+}
+
+impl EncryptedFileStore {
+    /// path の暗号化したファイルを開く。鍵は key_path から読み、なければ作る
+    ///
+    /// # Errors
+    /// 鍵を読めない・作れない場合と、ファイルを復号できない場合はエラーを返す。
+    pub fn open(path: &Path, key_path: &Path) -> Result<Self> {
+        let rng = SystemRandom::new();
+        let key_bytes = if key_path.exists() {
+            std::fs::read(key_path).with_context(|| format!("Failed to read {:?}", key_path))?
+        } else {
+            let mut key = vec![0; KEY_LEN];
+            rng.fill(&mut key)
+                .map_err(|_| anyhow!("Failed to generate a key"))?;
+            write_private(key_path, &key)?;
+            key
+        };
+        let key = UnboundKey::new(&CHACHA20_POLY1305, &key_bytes)
+            .map_err(|_| anyhow!("Invalid key in {:?}", key_path))?;
+
+        let mut store = Self {
+            path: path.to_path_buf(),
+            key: LessSafeKey::new(key),
+            rng,
+            secrets: Vec::new(),
+        };
+        if path.exists() {
+            let data = std::fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+            let plain = store.decrypt(data)?;
+            store.secrets = form_urlencoded::parse(&plain).into_owned().collect();
+        }
+        Ok(store)
+    }
+
+    fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate a nonce"))?;
+        let mut data = plain.to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("Failed to encrypt secrets"))?;
+
+        let mut out = nonce.to_vec();
+        out.extend(data);
+        Ok(out)
+    }
+
+    fn decrypt(&self, mut data: Vec<u8>) -> Result<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            bail!("{:?} is too short", self.path);
+        }
+        let mut sealed = data.split_off(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(&data)
+            .map_err(|_| anyhow!("Invalid nonce in {:?}", self.path))?;
+        let plain = self
+            .key
+            .open_in_place(nonce, Aad::empty(), &mut sealed)
+            .map_err(|_| anyhow!("Failed to decrypt {:?}", self.path))?;
+        Ok(plain.to_vec())
+    }
+
+    fn save(&self) -> Result<()> {
+        let plain = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&self.secrets)
+            .finish();
+        io::write_atomic(&self.path, &self.encrypt(plain.as_bytes())?)
+    }
+}
+
+impl SecretStore for EncryptedFileStore {
+    fn name(&self) -> &'static str {
+        "encrypted file"
+    }
+
+    fn get(&mut self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .secrets
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, secret)| secret.clone()))
+    }
+
+    fn set(&mut self, key: &str, secret: &str) -> Result<()> {
+        match self.secrets.iter_mut().find(|(k, _)| k == key) {
+            Some((_, s)) => *s = secret.to_string(),
+            None => self.secrets.push((key.to_string(), secret.to_string())),
+        }
+        self.save()
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        let len = self.secrets.len();
+        self.secrets.retain(|(k, _)| k != key);
+        if self.secrets.len() == len {
+            return Ok(());
+        }
+        self.save()
+    }
+}
+
+/// 所有者だけが読めるファイルとして書く
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {:?}", path))?;
+    std::io::Write::write_all(&mut file, data)
+        .with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// OS のキーチェーンを開く。使えなければ profile_dir の暗号化したファイル
+/// （`file_name` と、鍵の `file_name.key`）を開く
+#[allow(unreachable_code)]
+pub fn open_default(profile_dir: &Path, file_name: &str) -> Result<Box<dyn SecretStore>> {
+    #[cfg(target_os = "macos")]
+    {
+        return Ok(Box::new(
+            crate::platform::os::macos::keychain::SecurityStore,
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    if crate::platform::os::linux::keychain::SecretToolStore::is_available() {
+        return Ok(Box::new(
+            crate::platform::os::linux::keychain::SecretToolStore,
+        ));
+    }
+
+    let path = profile_dir.join(file_name);
+    let key_path = profile_dir.join(format!("{}.key", file_name));
+    Ok(Box::new(EncryptedFileStore::open(&path, &key_path)?))
+}
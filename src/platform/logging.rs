@@ -0,0 +1,310 @@
+//! ブラウザのログ
+//!
+//! `log` クレートのメッセージを受け取り、サブシステムごとに決めたレベルで絞って
+//! 標準エラーに出す。最近のものはリングバッファに取っておき（`orinium://logs` で見る）、
+//! 頼まれていればファイルにも書き足す。
+//!
+//! サブシステムはメッセージの target から `orinium_browser::` を除いたもの
+//! （`platform::network::cache` など）。レベルは env_logger と同じ形の指定
+//! （`warn,platform::network=debug,console=info`）で決め、いちばん長く一致した
+//! 指定を使う。指定は設定（`log.levels`）か `orinium://logs` で変えられる。
+//! 起動時に `RUST_LOG` があれば、そちらを優先する。
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// 取っておくメッセージの数
+pub const LOG_BUFFER_CAPACITY: usize = 2000;
+
+/// 指定がないときのレベル
+pub const DEFAULT_LOG_LEVELS: &str = "warn";
+
+/// target から除く、このクレートのモジュールの接頭辞
+const CRATE_PREFIX: &str = "orinium_browser::";
+
+/// 取っておいた 1 件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub time: SystemTime,
+    pub level: Level,
+    pub subsystem: String,
+    pub message: String,
+}
+
+impl fmt::Display for LogRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<5} {}: {}",
+            format_time(self.time),
+            self.level,
+            self.subsystem,
+            self.message
+        )
+    }
+}
+
+/// サブシステムごとのレベル
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LevelFilters {
+    default: LevelFilter,
+    /// (サブシステム, レベル)。長いものから並べる
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LevelFilters {
+    fn default() -> Self {
+        Self::parse(DEFAULT_LOG_LEVELS).expect("default log levels are valid")
+    }
+}
+
+impl LevelFilters {
+    /// `warn,platform::network=debug` のような指定を読む
+    ///
+    /// サブシステムのない指定は既定のレベルにする。`orinium_browser::` から書いてもよい。
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut default = LevelFilter::Error;
+        let mut directives: Vec<(String, LevelFilter)> = Vec::new();
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let (subsystem, level) = match directive.split_once('=') {
+                Some((subsystem, level)) => (Some(subsystem.trim()), level.trim()),
+                None => match directive.parse::<LevelFilter>() {
+                    Ok(_) => (None, directive),
+                    // レベルのないサブシステムはすべて出す（env_logger と同じ）
+                    Err(_) => (Some(directive), "trace"),
+                },
+            };
+            let Ok(level) = level.parse::<LevelFilter>() else {
+                bail!("Unknown log level: {:?}", level);
+            };
+            match subsystem {
+                Some(subsystem) => {
+                    let subsystem = subsystem.strip_prefix(CRATE_PREFIX).unwrap_or(subsystem);
+                    if subsystem.is_empty() {
+                        bail!("Empty subsystem in log directive: {:?}", directive);
+                    }
+                    directives.retain(|(s, _)| s != subsystem);
+                    directives.push((subsystem.to_string(), level));
+                }
+                None => default = level,
+            }
+        }
+        directives.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        Ok(Self {
+            default,
+            directives,
+        })
+    }
+
+    /// subsystem のメッセージを出すレベル
+    pub fn level_for(&self, subsystem: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .find(|(prefix, _)| {
+                subsystem
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// どれかのサブシステムで出すいちばん細かいレベル
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, LevelFilter::max)
+    }
+}
+
+impl fmt::Display for LevelFilters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.default.as_str().to_ascii_lowercase())?;
+        // 短いものから書くと読みやすい
+        for (subsystem, level) in self.directives.iter().rev() {
+            write!(f, ",{}={}", subsystem, level.as_str().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// ログの受け口
+pub struct Logger {
+    filters: RwLock<LevelFilters>,
+    records: Mutex<VecDeque<LogRecord>>,
+    capacity: usize,
+    /// 書き足していくファイル
+    file: Mutex<Option<(PathBuf, File)>>,
+    /// 標準エラーにも出す
+    stderr: bool,
+}
+
+impl Logger {
+    /// 最近の capacity 件を取っておく。stderr なら標準エラーにも出す
+    pub fn new(capacity: usize, stderr: bool) -> Self {
+        Self {
+            filters: RwLock::new(LevelFilters::default()),
+            records: Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+            file: Mutex::new(None),
+            stderr,
+        }
+    }
+
+    pub fn filters(&self) -> LevelFilters {
+        self.filters
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// レベルを変える。グローバルのロガーなら `log` クレートの上限も合わせる
+    pub fn set_filters(&self, filters: LevelFilters) {
+        if std::ptr::eq(self, logger()) {
+            log::set_max_level(filters.max_level());
+        }
+        *self.filters.write().unwrap_or_else(|e| e.into_inner()) = filters;
+    }
+
+    /// 取っておいたメッセージ（古い順）
+    pub fn records(&self) -> Vec<LogRecord> {
+        let records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        records.iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    /// path にも書き足す（None なら書くのをやめる）。同じファイルなら開き直さない
+    pub fn set_file(&self, path: Option<&Path>) -> Result<()> {
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if file.as_ref().map(|(p, _)| p.as_path()) == path {
+            return Ok(());
+        }
+        *file = match path {
+            Some(path) => {
+                let opened = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open the log file {:?}", path))?;
+                Some((path.to_path_buf(), opened))
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// 書き足しているファイル
+    pub fn file(&self) -> Option<PathBuf> {
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.as_ref().map(|(path, _)| path.clone())
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let filters = self.filters.read().unwrap_or_else(|e| e.into_inner());
+        metadata.level() <= filters.level_for(subsystem_of(metadata.target()))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let record = LogRecord {
+            time: SystemTime::now(),
+            level: record.level(),
+            subsystem: subsystem_of(record.target()).to_string(),
+            message: record.args().to_string(),
+        };
+
+        if self.stderr {
+            eprintln!("[{} {}] {}", record.level, record.subsystem, record.message);
+        }
+        if let Some((_, file)) = self.file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            // 書けなくてもログのためにブラウザは止めない
+            let _ = writeln!(file, "{record}");
+        }
+
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    fn flush(&self) {
+        if let Some((_, file)) = self.file.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// プロセス全体のロガー
+pub fn logger() -> &'static Logger {
+    static LOGGER: OnceLock<Logger> = OnceLock::new();
+    LOGGER.get_or_init(|| Logger::new(LOG_BUFFER_CAPACITY, true))
+}
+
+/// [`logger`] を `log` クレートのロガーにする。`RUST_LOG` があればそのレベルを使う
+pub fn init() {
+    let logger = logger();
+    if let Some(spec) = env_levels() {
+        match LevelFilters::parse(&spec) {
+            Ok(filters) => logger.set_filters(filters),
+            Err(e) => eprintln!("Ignoring RUST_LOG: {:#}", e),
+        }
+    }
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(logger.filters().max_level());
+    }
+}
+
+/// 設定のレベル spec を使う。`RUST_LOG` で起動したときはそちらを優先する
+pub fn apply_levels(spec: &str) -> Result<()> {
+    if env_levels().is_some() {
+        return Ok(());
+    }
+    let filters = LevelFilters::parse(spec)?;
+    if filters != logger().filters() {
+        logger().set_filters(filters);
+    }
+    Ok(())
+}
+
+fn env_levels() -> Option<String> {
+    std::env::var("RUST_LOG")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+}
+
+/// target からサブシステムの名前を取り出す
+fn subsystem_of(target: &str) -> &str {
+    target.strip_prefix(CRATE_PREFIX).unwrap_or(target)
+}
+
+/// `HH:MM:SS.mmm`（UTC）
+pub fn format_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() % (24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        since_epoch.subsec_millis()
+    )
+}
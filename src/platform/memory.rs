@@ -0,0 +1,313 @@
+//! メモリーの使用量の集計と、足りないときのキャッシュの追い出し
+//!
+//! 画像、グリフ、HTTP キャッシュ、DOM などの持ち主が [`MemoryConsumer`] を
+//! [`MemoryRegistry`] に登録し、おおよその使用量（バイト）を答える。合計が予算を
+//! 超えたとき（[`MemoryRegistry::enforce_budget`]）や OS のメモリーが足りなくなったとき
+//! （[`system_pressure`]、[`MemoryRegistry::relieve`]）は、捨てられるキャッシュを捨てる。
+//!
+//! 登録は弱い参照で持つので、持ち主を捨てれば集計からも外れる。別のスレッドにあって
+//! すぐには捨てられないもの（GPU のグリフなど）は [`TrackedMemory`] を使い、
+//! 頼まれた追い出しを持ち主の都合のよいときに行う。
+
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+
+/// 予算の既定値
+pub const DEFAULT_MEMORY_BUDGET: usize = 512 * 1024 * 1024;
+
+/// メモリーを数える単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    /// デコードした画像（テクスチャ）
+    DecodedImages,
+    /// グリフのアトラスとシェーピング・計測の結果
+    Glyphs,
+    /// HTTP キャッシュのうちメモリーに置いている分
+    HttpCache,
+    /// 開いているページの DOM
+    Dom,
+}
+
+impl Subsystem {
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::DecodedImages,
+        Subsystem::Glyphs,
+        Subsystem::HttpCache,
+        Subsystem::Dom,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Subsystem::DecodedImages => "Decoded images",
+            Subsystem::Glyphs => "Glyphs",
+            Subsystem::HttpCache => "HTTP cache",
+            Subsystem::Dom => "DOM",
+        }
+    }
+}
+
+/// どれだけ強く追い出すか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MemoryPressure {
+    /// すぐにまた使いそうにないものを捨てる
+    Moderate,
+    /// 捨てられるものはすべて捨てる
+    Critical,
+}
+
+/// 使用量を答え、頼まれたらキャッシュを捨てるもの
+pub trait MemoryConsumer: Send + Sync {
+    fn subsystem(&self) -> Subsystem;
+
+    /// おおよその使用量（バイト）
+    fn memory_usage(&self) -> usize;
+
+    /// pressure に応じて捨て、空けた（空ける予定の）バイト数を返す。既定では何も捨てない
+    fn evict(&self, _pressure: MemoryPressure) -> usize {
+        0
+    }
+}
+
+/// 持ち主が使用量を書き込むだけのもの
+///
+/// evictable なら、追い出しを頼まれたことを覚えておき、持ち主が
+/// [`take_eviction_request`](Self::take_eviction_request) で受け取って捨てる。
+#[derive(Debug)]
+pub struct TrackedMemory {
+    subsystem: Subsystem,
+    bytes: AtomicUsize,
+    evictable: bool,
+    /// 頼まれた追い出し（0: なし、1: Moderate、2: Critical）
+    requested: AtomicU8,
+}
+
+impl TrackedMemory {
+    /// 数えるだけのもの（DOM など）
+    pub fn new(subsystem: Subsystem) -> Arc<Self> {
+        Self::with_eviction(subsystem, false)
+    }
+
+    /// 持ち主が後で捨てるもの
+    pub fn evictable(subsystem: Subsystem) -> Arc<Self> {
+        Self::with_eviction(subsystem, true)
+    }
+
+    fn with_eviction(subsystem: Subsystem, evictable: bool) -> Arc<Self> {
+        Arc::new(Self {
+            subsystem,
+            bytes: AtomicUsize::new(0),
+            evictable,
+            requested: AtomicU8::new(0),
+        })
+    }
+
+    pub fn set(&self, bytes: usize) {
+        self.bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// 頼まれている追い出しを受け取る（受け取ったら消える）
+    pub fn take_eviction_request(&self) -> Option<MemoryPressure> {
+        match self.requested.swap(0, Ordering::Relaxed) {
+            0 => None,
+            1 => Some(MemoryPressure::Moderate),
+            _ => Some(MemoryPressure::Critical),
+        }
+    }
+}
+
+impl MemoryConsumer for TrackedMemory {
+    fn subsystem(&self) -> Subsystem {
+        self.subsystem
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.get()
+    }
+
+    fn evict(&self, pressure: MemoryPressure) -> usize {
+        if !self.evictable {
+            return 0;
+        }
+        let level = match pressure {
+            MemoryPressure::Moderate => 1,
+            MemoryPressure::Critical => 2,
+        };
+        self.requested.fetch_max(level, Ordering::Relaxed);
+        match pressure {
+            MemoryPressure::Moderate => self.get() / 2,
+            MemoryPressure::Critical => self.get(),
+        }
+    }
+}
+
+/// 使用量の集計
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    by_subsystem: Vec<(Subsystem, usize)>,
+}
+
+impl MemoryUsage {
+    pub fn of(&self, subsystem: Subsystem) -> usize {
+        self.by_subsystem
+            .iter()
+            .find(|(s, _)| *s == subsystem)
+            .map_or(0, |(_, bytes)| *bytes)
+    }
+
+    pub fn total(&self) -> usize {
+        self.by_subsystem.iter().map(|(_, bytes)| bytes).sum()
+    }
+
+    /// 数える単位ごとの使用量（[`Subsystem::ALL`] の順）
+    pub fn iter(&self) -> impl Iterator<Item = (Subsystem, usize)> + '_ {
+        self.by_subsystem.iter().copied()
+    }
+}
+
+/// メモリーを使うものの登録先
+pub struct MemoryRegistry {
+    consumers: Mutex<Vec<Weak<dyn MemoryConsumer>>>,
+    budget: AtomicUsize,
+}
+
+impl Default for MemoryRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_BUDGET)
+    }
+}
+
+impl MemoryRegistry {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            consumers: Mutex::new(Vec::new()),
+            budget: AtomicUsize::new(budget),
+        }
+    }
+
+    /// consumer を数える。捨てられたら自然に外れる
+    pub fn register<C: MemoryConsumer + 'static>(&self, consumer: &Arc<C>) {
+        let consumer: Weak<dyn MemoryConsumer> = Arc::downgrade(consumer) as _;
+        let mut consumers = self.consumers.lock().unwrap_or_else(|e| e.into_inner());
+        consumers.retain(|c| c.strong_count() > 0);
+        consumers.push(consumer);
+    }
+
+    pub fn set_budget(&self, budget: usize) {
+        self.budget.store(budget, Ordering::Relaxed);
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget.load(Ordering::Relaxed)
+    }
+
+    fn live_consumers(&self) -> Vec<Arc<dyn MemoryConsumer>> {
+        let mut consumers = self.consumers.lock().unwrap_or_else(|e| e.into_inner());
+        consumers.retain(|c| c.strong_count() > 0);
+        consumers.iter().filter_map(Weak::upgrade).collect()
+    }
+
+    pub fn usage(&self) -> MemoryUsage {
+        let consumers = self.live_consumers();
+        MemoryUsage {
+            by_subsystem: Subsystem::ALL
+                .into_iter()
+                .map(|subsystem| {
+                    let bytes = consumers
+                        .iter()
+                        .filter(|c| c.subsystem() == subsystem)
+                        .map(|c| c.memory_usage())
+                        .sum();
+                    (subsystem, bytes)
+                })
+                .collect(),
+        }
+    }
+
+    /// 予算を超えていたら、大きいものから Moderate で捨て、それでも超えていれば
+    /// Critical で捨てる。空けたバイト数を返す
+    pub fn enforce_budget(&self) -> usize {
+        let budget = self.budget();
+        let mut consumers = self.live_consumers();
+        let mut total: usize = consumers.iter().map(|c| c.memory_usage()).sum();
+        if total <= budget {
+            return 0;
+        }
+        consumers.sort_by_key(|c| std::cmp::Reverse(c.memory_usage()));
+
+        let mut freed = 0;
+        for pressure in [MemoryPressure::Moderate, MemoryPressure::Critical] {
+            for consumer in &consumers {
+                if total <= budget {
+                    break;
+                }
+                let bytes = consumer.evict(pressure);
+                total = total.saturating_sub(bytes);
+                freed += bytes;
+            }
+        }
+        if freed > 0 {
+            log::info!(
+                "Memory over budget ({} bytes): freed {} bytes",
+                budget,
+                freed
+            );
+        }
+        freed
+    }
+
+    /// OS にメモリーが足りないと言われたとき、すべてのものに pressure で捨てさせる
+    pub fn relieve(&self, pressure: MemoryPressure) -> usize {
+        let freed = self
+            .live_consumers()
+            .iter()
+            .map(|c| c.evict(pressure))
+            .sum();
+        log::info!("Memory pressure ({:?}): freed {} bytes", pressure, freed);
+        freed
+    }
+}
+
+/// プロセス全体の登録先
+pub fn registry() -> &'static MemoryRegistry {
+    static REGISTRY: OnceLock<MemoryRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(MemoryRegistry::default)
+}
+
+/// OS のメモリーの逼迫の度合い（わからなければ None）
+#[allow(unreachable_code)]
+pub fn system_pressure() -> Option<MemoryPressure> {
+    #[cfg(target_os = "linux")]
+    {
+        return crate::platform::os::linux::memory::pressure();
+    }
+
+    None
+}
+
+/// `/proc/meminfo` の形の文字列から、空きの割合で逼迫の度合いを決める
+///
+/// MemAvailable が MemTotal の 5% 未満なら Critical、10% 未満なら Moderate。
+pub fn pressure_from_meminfo(meminfo: &str) -> Option<MemoryPressure> {
+    let field = |name: &str| -> Option<u64> {
+        meminfo.lines().find_map(|line| {
+            let rest = line.strip_prefix(name)?.strip_prefix(':')?;
+            rest.split_whitespace().next()?.parse().ok()
+        })
+    };
+    let total = field("MemTotal")?;
+    let available = field("MemAvailable")?;
+    if total == 0 {
+        return None;
+    }
+    if available * 20 < total {
+        Some(MemoryPressure::Critical)
+    } else if available * 10 < total {
+        Some(MemoryPressure::Moderate)
+    } else {
+        None
+    }
+}
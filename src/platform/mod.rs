@@ -4,6 +4,14 @@ pub mod renderer;
 pub mod system;
 
 pub mod audio;
+pub mod base64;
+pub mod clipboard;
+pub mod decode;
 
 pub mod font;
+pub mod geolocation;
+pub mod keychain;
+pub mod logging;
+pub mod memory;
+pub mod notifications;
 pub(crate) mod os;
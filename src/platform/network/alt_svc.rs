@@ -0,0 +1,157 @@
+//! Alt-Svc（RFC 7838）で知らされた HTTP/3 のエンドポイント
+//!
+//! HTTP/1・HTTP/2 の応答の `Alt-Svc: h3=":443"; ma=86400` を覚えておき、同じ
+//! オリジンへの次のリクエストから HTTP/3 を試す。HTTP/3 で繋がらなかった
+//! オリジンはしばらく TCP だけを使う。
+//!
+//! Alt-Svc はオリジンをまたいだ追跡に使えるので、Cookie と同じく保存先
+//! （[`super::StoragePartition`]）ごとに分けて持つ。
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// ma が省略されたときの有効期間（24 時間）
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// HTTP/3 で繋がらなかったオリジンに再び試すまでの時間
+const BROKEN_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Alt-Svc ヘッダーの値のうち HTTP/3 に関わるもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AltSvcValue {
+    /// `clear`: 覚えている代替サービスを忘れる
+    Clear,
+    /// HTTP/3 の代替サービス（host が None なら同じホスト）
+    Http3 {
+        host: Option<String>,
+        port: u16,
+        max_age: Duration,
+    },
+    /// HTTP/3 を含まない
+    Other,
+}
+
+/// Alt-Svc ヘッダーの値を読む
+///
+/// HTTP/3 の代替サービスが複数あれば最初のものを使う。draft 版（h3-29 など）は
+/// 扱わない。
+pub fn parse(value: &str) -> AltSvcValue {
+    if value.trim().eq_ignore_ascii_case("clear") {
+        return AltSvcValue::Clear;
+    }
+
+    for alternative in value.split(',') {
+        let mut params = alternative.split(';').map(str::trim);
+        let Some((protocol, authority)) = params.next().and_then(|p| p.split_once('=')) else {
+            continue;
+        };
+        if protocol.trim() != "h3" {
+            continue;
+        }
+        let authority = authority.trim().trim_matches('"');
+        let Some((host, port)) = authority.rsplit_once(':') else {
+            continue;
+        };
+        let Ok(port) = port.parse::<u16>() else {
+            continue;
+        };
+
+        let max_age = params
+            .filter_map(|p| p.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("ma"))
+            .and_then(|(_, v)| v.trim().trim_matches('"').parse::<u64>().ok())
+            .map_or(DEFAULT_MAX_AGE, Duration::from_secs);
+
+        return AltSvcValue::Http3 {
+            host: (!host.is_empty()).then(|| host.to_string()),
+            port,
+            max_age,
+        };
+    }
+
+    AltSvcValue::Other
+}
+
+#[derive(Debug, Clone)]
+struct Alternative {
+    host: Option<String>,
+    port: u16,
+    expires_at: Instant,
+}
+
+#[derive(Debug, Default)]
+struct AltSvcState {
+    /// (ホスト, ポート) → HTTP/3 のエンドポイント
+    alternatives: HashMap<(String, u16), Alternative>,
+    /// HTTP/3 で繋がらなかったオリジンと、再び試してよくなる時刻
+    broken_until: HashMap<(String, u16), Instant>,
+}
+
+/// オリジンごとの HTTP/3 のエンドポイント
+#[derive(Debug, Clone, Default)]
+pub struct AltSvcStore {
+    state: Arc<RwLock<AltSvcState>>,
+}
+
+impl AltSvcStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// host:port の応答に付いていた Alt-Svc を覚える
+    ///
+    /// 新しい Alt-Svc は前のものを置き換えるので、HTTP/3 を含まなければ忘れる。
+    pub fn record(&self, host: &str, port: u16, value: &str, now: Instant) {
+        let key = (host.to_ascii_lowercase(), port);
+        let mut state = self.state.write().unwrap();
+        match parse(value) {
+            AltSvcValue::Http3 {
+                host,
+                port,
+                max_age,
+            } => {
+                state.alternatives.insert(
+                    key,
+                    Alternative {
+                        host,
+                        port,
+                        expires_at: now + max_age,
+                    },
+                );
+            }
+            AltSvcValue::Clear | AltSvcValue::Other => {
+                state.alternatives.remove(&key);
+            }
+        }
+    }
+
+    /// host:port へのリクエストに使う HTTP/3 の (ホスト, ポート)
+    pub fn http3_endpoint(&self, host: &str, port: u16, now: Instant) -> Option<(String, u16)> {
+        let key = (host.to_ascii_lowercase(), port);
+        let state = self.state.read().unwrap();
+        if state
+            .broken_until
+            .get(&key)
+            .is_some_and(|until| now < *until)
+        {
+            return None;
+        }
+        let alternative = state.alternatives.get(&key)?;
+        if now >= alternative.expires_at {
+            return None;
+        }
+        let alt_host = alternative.host.clone().unwrap_or_else(|| key.0.clone());
+        Some((alt_host, alternative.port))
+    }
+
+    /// host:port に HTTP/3 で繋がらなかった。しばらく TCP だけを使う
+    pub fn mark_broken(&self, host: &str, port: u16, now: Instant) {
+        let key = (host.to_ascii_lowercase(), port);
+        self.state
+            .write()
+            .unwrap()
+            .broken_until
+            .insert(key, now + BROKEN_RETRY_AFTER);
+    }
+}
@@ -1,23 +1,136 @@
+//! HTTP キャッシュ（RFC 9111 のうちブラウザのプライベートキャッシュに要るもの）
+//!
+//! 新鮮さは Cache-Control の max-age、なければ Expires と Date、どちらもなければ
+//! Last-Modified からの経過時間の 1 割（ヒューリスティック）で決める。新鮮でなく
+//! なった応答は ETag / Last-Modified を使って取り直す前に確かめ
+//! （[`Cache::conditional_headers`]）、304 が返ってきたら保存していたボディを使う
+//! （[`Cache::freshen`]）。
+//!
+//! 応答はトップレベルのページのサイトごとに分けて持つ（[`CacheKey`]）。あるサイトで
+//! 読んだものを別のサイトのページが使えると、読み込みにかかる時間から閲覧履歴を
+//! 探られてしまうため。
+//!
+//! [`Cache::enable_disk`] でディスクにも書き、メモリーから消えた（再起動した）
+//! あとも使えるようにする。ディスクの使用量が予算を超えたら最近使っていない
+//! ものから捨てる。どのファイルをいつ使ったかは索引ファイルに書き
+//! （[`Cache::save_index_if_modified`]）、起動時に読む。索引やファイルが壊れていたら
+//! ディレクトリの中身から作り直し、読めないファイルは消す。
+//!
+//! メモリーに置いている分は [`memory::registry`] に数えさせ、メモリーが足りないときは
+//! 古くなったもの（ディスクにもあるならすべて）から捨てる。
+
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use url::Url;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+use super::{http_date, site};
+use crate::platform::io;
+use crate::platform::memory::{self, MemoryConsumer, MemoryPressure, Subsystem};
+
+/// ヒューリスティックな新鮮さの上限
+const MAX_HEURISTIC_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// ディスクに書くファイルの拡張子
+const ENTRY_EXTENSION: &str = "entry";
+
+/// ディスクのファイルの先頭行
+const ENTRY_MAGIC: &str = "ORINIUM-CACHE 2";
+
+/// ディスクのキャッシュのディレクトリ内の索引のファイル名
+pub const INDEX_FILE_NAME: &str = "index";
+
+/// 索引ファイルの先頭行
+const INDEX_MAGIC: &str = "ORINIUM-CACHE-INDEX 1";
+
+/// キャッシュのキー（トップレベルのページのサイトと URL の組）
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    site: String,
+    url: Url,
+}
+
+impl CacheKey {
+    /// top_level のページが読む url のキー（top_level が None なら url 自身をトップレベルで開く）
+    pub fn new(url: &Url, top_level: Option<&Url>) -> Self {
+        Self {
+            site: site::site_of(top_level.unwrap_or(url)),
+            url: url.clone(),
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// トップレベルのページのサイト
+    pub fn site(&self) -> &str {
+        &self.site
+    }
+
+    /// メモリーとディスクで使う文字列（URL には空白が入らない）
+    fn id(&self) -> String {
+        format!("{} {}", self.site, self.url)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct CachedResponse {
     pub body: Vec<u8>,
     pub headers: Vec<(String, String)>,
     pub cached_at: SystemTime,
-    pub expires_at: Option<SystemTime>,
+    /// これを過ぎたら使う前に確かめる
+    pub expires_at: SystemTime,
+}
+
+impl CachedResponse {
+    pub fn is_fresh(&self, now: SystemTime) -> bool {
+        now < self.expires_at
+    }
+
+    fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    /// 確かめるのに使えるもの（ETag か Last-Modified）を持っているか
+    pub fn has_validators(&self) -> bool {
+        self.header("etag").is_some() || self.header("last-modified").is_some()
+    }
+
+    /// おおよその大きさ（バイト）
+    fn size(&self) -> usize {
+        self.body.len()
+            + self
+                .headers
+                .iter()
+                .map(|(name, value)| name.len() + value.len())
+                .sum::<usize>()
+    }
+}
+
+/// キャッシュを引いた結果
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheLookup {
+    Miss,
+    /// そのまま使える
+    Fresh(CachedResponse),
+    /// 使う前にサーバーに確かめる
+    Stale(CachedResponse),
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    memory: HashMap<String, CachedResponse>,
+    disk: Option<DiskCache>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Cache {
-    store: Arc<RwLock<HashMap<String, CachedResponse>>>,
+    state: Arc<RwLock<CacheState>>,
 }
 
-#[allow(dead_code)]
 impl Default for Cache {
     fn default() -> Self {
         Self::new()
@@ -26,57 +139,535 @@ impl Default for Cache {
 
 impl Cache {
     pub fn new() -> Self {
-        Self {
-            store: Arc::new(RwLock::new(HashMap::new())),
+        let state = Arc::new(RwLock::new(CacheState::default()));
+        memory::registry().register(&state);
+        Self { state }
+    }
+
+    /// dir にもキャッシュを書く。ディスクの使用量は budget バイトまで
+    ///
+    /// dir にすでにある分は索引から使用量として数え、引かれたときに読み込む。
+    pub fn enable_disk(&self, dir: PathBuf, budget: u64) {
+        let mut state = self.state.write().unwrap();
+        let mut disk = DiskCache::open(dir, budget);
+        disk.evict();
+        state.disk = Some(disk);
+    }
+
+    pub fn lookup(&self, key: &CacheKey, now: SystemTime) -> CacheLookup {
+        let mut state = self.state.write().unwrap();
+        let key = key.id();
+
+        let entry = match state.memory.get(&key) {
+            Some(entry) => Some(entry.clone()),
+            None => state.disk.as_mut().and_then(|disk| disk.read(&key)),
+        };
+        let Some(entry) = entry else {
+            return CacheLookup::Miss;
+        };
+        if let Some(disk) = state.disk.as_mut() {
+            disk.touch(&key, now);
+        }
+        state.memory.insert(key, entry.clone());
+
+        if entry.is_fresh(now) {
+            CacheLookup::Fresh(entry)
+        } else {
+            CacheLookup::Stale(entry)
+        }
+    }
+
+    /// 新鮮でないときに送る If-None-Match / If-Modified-Since
+    pub fn conditional_headers(entry: &CachedResponse) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = entry.header("etag") {
+            headers.push(("If-None-Match", etag.to_string()));
+        }
+        if let Some(last_modified) = entry.header("last-modified") {
+            headers.push(("If-Modified-Since", last_modified.to_string()));
         }
+        headers
     }
 
-    pub fn get(&self, url: &Url) -> Option<CachedResponse> {
-        let store = self.store.read().ok()?;
-        let key = url.as_str();
+    /// 200 の応答を保存する。保存してはいけない応答なら何もせず false
+    pub fn store(
+        &self,
+        key: &CacheKey,
+        body: Vec<u8>,
+        headers: Vec<(String, String)>,
+        now: SystemTime,
+    ) -> bool {
+        let Some(expires_at) = expiry(&headers, now) else {
+            self.remove(key);
+            return false;
+        };
+        let entry = CachedResponse {
+            body,
+            headers,
+            cached_at: now,
+            expires_at,
+        };
+        // すぐ古くなり、確かめる手段もない応答は取っておいても使えない
+        if !entry.is_fresh(now) && !entry.has_validators() {
+            self.remove(key);
+            return false;
+        }
 
-        if let Some(entry) = store.get(key) {
-            if let Some(exp) = entry.expires_at
-                && SystemTime::now() > exp
-            {
-                return None;
+        let mut state = self.state.write().unwrap();
+        let key = key.id();
+        if let Some(disk) = state.disk.as_mut() {
+            disk.write(&key, &entry, now);
+        }
+        state.memory.insert(key, entry);
+        true
+    }
+
+    /// 304 Not Modified で確かめられた応答を新しいヘッダーで更新して返す
+    pub fn freshen(
+        &self,
+        key: &CacheKey,
+        headers: &[(String, String)],
+        now: SystemTime,
+    ) -> Option<CachedResponse> {
+        let mut state = self.state.write().unwrap();
+        let key = key.id();
+        let mut entry = state.memory.get(&key).cloned()?;
+
+        // 304 に付いてきたヘッダーで置き換える（ボディの長さは変わらない）
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("content-length") {
+                continue;
             }
-            return Some(entry.clone());
+            entry.headers.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+            entry.headers.push((name.clone(), value.clone()));
+        }
+        entry.cached_at = now;
+        entry.expires_at = expiry(&entry.headers, now).unwrap_or(now);
+
+        if let Some(disk) = state.disk.as_mut() {
+            disk.write(&key, &entry, now);
         }
-        None
+        state.memory.insert(key, entry.clone());
+        Some(entry)
     }
 
-    pub fn set(&self, url: &Url, body: Vec<u8>, headers: Vec<(String, String)>) {
-        let mut store = self.store.write().expect("RwLock poisoned");
-        let key = url.as_str().to_string();
+    pub fn remove(&self, key: &CacheKey) {
+        let mut state = self.state.write().unwrap();
+        let key = key.id();
+        state.memory.remove(&key);
+        if let Some(disk) = state.disk.as_mut() {
+            disk.remove(&key);
+        }
+    }
 
-        let mut expires = None;
-        if let Some((_, cc)) = headers
-            .iter()
-            .find(|(n, _)| n.eq_ignore_ascii_case("cache-control"))
-            && let Some(pos) = cc.find("max-age=")
-            && let Ok(max_age) = cc[pos + 8..]
-                .split(|c: char| !c.is_ascii_digit())
-                .next()
-                .unwrap_or("0")
-                .parse::<u64>()
+    /// メモリーとディスクの中身をすべて捨てる
+    pub fn clear(&self) {
+        let mut state = self.state.write().unwrap();
+        state.memory.clear();
+        if let Some(disk) = state.disk.as_mut() {
+            disk.clear();
+            disk.save_index();
+        }
+    }
+
+    /// ディスクのキャッシュの索引が変わっていたら書く
+    pub fn save_index_if_modified(&self) {
+        let mut state = self.state.write().unwrap();
+        if let Some(disk) = state.disk.as_mut()
+            && disk.index_modified
         {
-            expires = Some(SystemTime::now() + Duration::from_secs(max_age));
+            disk.save_index();
         }
+    }
+
+    /// ディスクに書いている量（バイト）
+    pub fn disk_usage(&self) -> u64 {
+        let state = self.state.read().unwrap();
+        state.disk.as_ref().map_or(0, |disk| disk.total)
+    }
+
+    /// メモリーに置いている量（バイト）
+    pub fn memory_usage(&self) -> usize {
+        self.state.memory_usage()
+    }
+}
+
+impl MemoryConsumer for RwLock<CacheState> {
+    fn subsystem(&self) -> Subsystem {
+        Subsystem::HttpCache
+    }
+
+    fn memory_usage(&self) -> usize {
+        let state = self.read().unwrap_or_else(|e| e.into_inner());
+        state
+            .memory
+            .iter()
+            .map(|(key, entry)| key.len() + entry.size())
+            .sum()
+    }
+
+    /// Moderate なら古くなったものを捨てる（ディスクにもあるならすべて捨て、次に引かれたときに
+    /// ディスクから読み直す）。Critical ならメモリーの分をすべて捨てる
+    fn evict(&self, pressure: MemoryPressure) -> usize {
+        let mut state = self.write().unwrap_or_else(|e| e.into_inner());
+        let keep_fresh = pressure == MemoryPressure::Moderate && state.disk.is_none();
+        let now = SystemTime::now();
+        let mut freed = 0;
+        state.memory.retain(|key, entry| {
+            let keep = keep_fresh && entry.is_fresh(now);
+            if !keep {
+                freed += key.len() + entry.size();
+            }
+            keep
+        });
+        freed
+    }
+}
 
-        store.insert(
-            key,
-            CachedResponse {
-                body,
-                headers,
-                cached_at: SystemTime::now(),
-                expires_at: expires,
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// 応答が新鮮でなくなる時刻。保存してはいけない応答なら None
+fn expiry(headers: &[(String, String)], now: SystemTime) -> Option<SystemTime> {
+    let mut max_age = None;
+    let mut no_cache = false;
+    for directive in header(headers, "cache-control")
+        .unwrap_or("")
+        .split(',')
+        .map(str::trim)
+    {
+        let directive = directive.to_ascii_lowercase();
+        match directive.as_str() {
+            "no-store" => return None,
+            "no-cache" => no_cache = true,
+            _ => {
+                if let Some(value) = directive.strip_prefix("max-age=") {
+                    // 読めない max-age は古くなっているものとして扱う
+                    max_age = Some(value.trim_matches('"').parse::<u64>().unwrap_or(0));
+                }
+            }
+        }
+    }
+    if header(headers, "vary").is_some_and(|v| v.trim() == "*") {
+        return None;
+    }
+
+    let date = header(headers, "date")
+        .and_then(http_date::parse)
+        .unwrap_or(now);
+    let lifetime = if no_cache {
+        Duration::ZERO
+    } else if let Some(max_age) = max_age {
+        Duration::from_secs(max_age)
+    } else if let Some(expires) = header(headers, "expires") {
+        // 読めない Expires は過去の日付として扱う
+        http_date::parse(expires)
+            .and_then(|expires| expires.duration_since(date).ok())
+            .unwrap_or_default()
+    } else if let Some(last_modified) = header(headers, "last-modified").and_then(http_date::parse)
+    {
+        (date.duration_since(last_modified).unwrap_or_default() / 10).min(MAX_HEURISTIC_LIFETIME)
+    } else {
+        Duration::ZERO
+    };
+
+    // 途中のキャッシュにいた時間を引く
+    let age = header(headers, "age")
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map_or(Duration::ZERO, Duration::from_secs);
+    Some(now + lifetime.saturating_sub(age))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DiskEntry {
+    size: u64,
+    last_used: SystemTime,
+}
+
+/// ディスク上のキャッシュ（1 つの応答を 1 つのファイルに書く）
+#[derive(Debug)]
+struct DiskCache {
+    dir: PathBuf,
+    budget: u64,
+    /// ファイル名 → 大きさと最後に使った時刻
+    entries: HashMap<String, DiskEntry>,
+    total: u64,
+    /// 索引ファイルに書いていない変更がある
+    index_modified: bool,
+}
+
+impl DiskCache {
+    /// 索引を読み、ディレクトリにあるファイルと突き合わせる
+    ///
+    /// 索引にないファイル（索引を書く前に終了した）はファイルの大きさと更新時刻で足し、
+    /// ファイルのない項目は捨てる。書いている途中で止まった一時ファイルは消す。
+    fn open(dir: PathBuf, budget: u64) -> Self {
+        let index_path = dir.join(INDEX_FILE_NAME);
+        let index = match fs::read_to_string(&index_path) {
+            Ok(text) => parse_index(&text).unwrap_or_else(|| {
+                log::warn!("The disk cache index is corrupted; rebuilding it");
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        let mut entries = HashMap::new();
+        let mut total = 0;
+        if let Ok(read_dir) = fs::read_dir(&dir) {
+            for file in read_dir.flatten() {
+                let path = file.path();
+                match path.extension().and_then(|e| e.to_str()) {
+                    Some(ENTRY_EXTENSION) => {}
+                    Some("tmp") => {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    _ => continue,
+                }
+                let (Some(name), Ok(metadata)) = (file_stem(&path), file.metadata()) else {
+                    continue;
+                };
+                let size = metadata.len();
+                let last_used = match index.get(&name) {
+                    Some(entry) if entry.size == size => entry.last_used,
+                    _ => metadata.modified().unwrap_or(UNIX_EPOCH),
+                };
+                total += size;
+                entries.insert(name, DiskEntry { size, last_used });
+            }
+        }
+        let index_modified = entries.len() != index.len();
+        Self {
+            dir,
+            budget,
+            entries,
+            total,
+            index_modified,
+        }
+    }
+
+    /// 索引ファイルを書く
+    fn save_index(&mut self) {
+        let mut names: Vec<(&String, &DiskEntry)> = self.entries.iter().collect();
+        names.sort_by_key(|(name, _)| *name);
+        let mut text = format!("{INDEX_MAGIC}\n");
+        for (name, entry) in names {
+            text.push_str(&format!(
+                "{name}\t{}\t{}\n",
+                entry.size,
+                unix_secs(entry.last_used)
+            ));
+        }
+        match io::write_atomic(&self.dir.join(INDEX_FILE_NAME), text.as_bytes()) {
+            Ok(()) => self.index_modified = false,
+            Err(e) => log::warn!("Failed to write the disk cache index: {:#}", e),
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.{ENTRY_EXTENSION}"))
+    }
+
+    fn read(&mut self, key: &str) -> Option<CachedResponse> {
+        let name = file_name(key);
+        if !self.entries.contains_key(&name) {
+            return None;
+        }
+        let data = fs::read(self.path(&name)).ok();
+        match data.as_deref().and_then(|data| decode_entry(data, key)) {
+            Some(entry) => Some(entry),
+            None => {
+                // 壊れているか、古い形式か、ファイル名の衝突した別のキー
+                log::debug!("Dropping the unreadable disk cache entry {}", name);
+                self.remove(key);
+                None
+            }
+        }
+    }
+
+    fn write(&mut self, key: &str, entry: &CachedResponse, now: SystemTime) {
+        let name = file_name(key);
+        let data = encode_entry(key, entry);
+        if let Err(e) = io::write_atomic(&self.path(&name), &data) {
+            log::warn!("Failed to write the disk cache: {:#}", e);
+            return;
+        }
+        let size = data.len() as u64;
+        if let Some(old) = self.entries.insert(
+            name,
+            DiskEntry {
+                size,
+                last_used: now,
             },
-        );
+        ) {
+            self.total -= old.size;
+        }
+        self.total += size;
+        self.index_modified = true;
+        self.evict();
     }
 
-    pub fn clear(&self) {
-        let mut store = self.store.write().expect("RwLock poisoned");
-        store.clear();
+    fn touch(&mut self, key: &str, now: SystemTime) {
+        // 引くたびに索引を書き直さないよう、使った時刻は次に索引を書くときに残す
+        if let Some(entry) = self.entries.get_mut(&file_name(key)) {
+            entry.last_used = now;
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.remove_file(&file_name(key));
+    }
+
+    fn remove_file(&mut self, name: &str) {
+        if let Some(entry) = self.entries.remove(name) {
+            self.total -= entry.size;
+            self.index_modified = true;
+            let _ = fs::remove_file(self.path(name));
+        }
+    }
+
+    /// 予算に収まるまで最近使っていないものから捨てる
+    fn evict(&mut self) {
+        if self.total <= self.budget {
+            return;
+        }
+        let mut by_age: Vec<(String, SystemTime)> = self
+            .entries
+            .iter()
+            .map(|(name, entry)| (name.clone(), entry.last_used))
+            .collect();
+        by_age.sort_by_key(|&(_, last_used)| last_used);
+        for (name, _) in by_age {
+            if self.total <= self.budget {
+                break;
+            }
+            self.remove_file(&name);
+        }
     }
+
+    fn clear(&mut self) {
+        let names: Vec<String> = self.entries.keys().cloned().collect();
+        for name in names {
+            self.remove_file(&name);
+        }
+    }
+}
+
+fn file_stem(path: &Path) -> Option<String> {
+    path.file_stem()?.to_str().map(str::to_string)
+}
+
+/// キーのファイル名（FNV-1a。Rust のバージョンが変わっても同じ名前になる）
+fn file_name(key: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{hash:016x}")
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// 索引ファイルを読む。形式が違えば None
+///
+/// ```text
+/// ORINIUM-CACHE-INDEX 1
+/// <ファイル名>	<大きさ>	<最後に使った時刻>
+/// ```
+fn parse_index(text: &str) -> Option<HashMap<String, DiskEntry>> {
+    let mut lines = text.lines();
+    if lines.next()? != INDEX_MAGIC {
+        return None;
+    }
+    let mut entries = HashMap::new();
+    for line in lines {
+        let mut fields = line.split('\t');
+        let name = fields.next()?;
+        let size = fields.next()?.parse().ok()?;
+        let last_used = UNIX_EPOCH + Duration::from_secs(fields.next()?.parse().ok()?);
+        entries.insert(name.to_string(), DiskEntry { size, last_used });
+    }
+    Some(entries)
+}
+
+/// ファイルの形式: ヘッダー部の行、空行、ボディ
+///
+/// ```text
+/// ORINIUM-CACHE 2
+/// key	https://example.com https://cdn.example.net/style.css
+/// cached_at	1700000000
+/// expires_at	1700003600
+/// body_length	5120
+/// header	content-type	text/css
+///
+/// <ボディ>
+/// ```
+fn encode_entry(key: &str, entry: &CachedResponse) -> Vec<u8> {
+    let mut head = format!(
+        "{ENTRY_MAGIC}\nkey\t{key}\ncached_at\t{}\nexpires_at\t{}\nbody_length\t{}\n",
+        unix_secs(entry.cached_at),
+        unix_secs(entry.expires_at),
+        entry.body.len()
+    );
+    for (name, value) in &entry.headers {
+        // ヘッダーの値に改行は入らないが、念のため壊れた行は書かない
+        if !name.contains(['\t', '\n']) && !value.contains('\n') {
+            head.push_str(&format!("header\t{name}\t{value}\n"));
+        }
+    }
+    head.push('\n');
+
+    let mut data = head.into_bytes();
+    data.extend_from_slice(&entry.body);
+    data
+}
+
+/// key のファイルを読む。壊れている（途中で切れている）か別のキーのものなら None
+fn decode_entry(data: &[u8], key: &str) -> Option<CachedResponse> {
+    let split = data.windows(2).position(|w| w == b"\n\n")?;
+    let head = std::str::from_utf8(&data[..split]).ok()?;
+    let body = data[split + 2..].to_vec();
+
+    let mut lines = head.lines();
+    if lines.next()? != ENTRY_MAGIC {
+        return None;
+    }
+    let mut stored_key = None;
+    let mut cached_at = None;
+    let mut expires_at = None;
+    let mut body_length = None;
+    let mut headers = Vec::new();
+    for line in lines {
+        let (kind, rest) = line.split_once('\t')?;
+        let secs = || {
+            rest.parse::<u64>()
+                .ok()
+                .map(|s| UNIX_EPOCH + Duration::from_secs(s))
+        };
+        match kind {
+            "key" => stored_key = Some(rest),
+            "cached_at" => cached_at = secs(),
+            "expires_at" => expires_at = secs(),
+            "body_length" => body_length = rest.parse::<usize>().ok(),
+            "header" => {
+                let (name, value) = rest.split_once('\t')?;
+                headers.push((name.to_string(), value.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    (stored_key? == key && body_length? == body.len()).then_some(CachedResponse {
+        body,
+        headers,
+        cached_at: cached_at?,
+        expires_at: expires_at?,
+    })
 }
@@ -1,65 +1,449 @@
+//! Cookie（RFC 6265bis）
+//!
+//! Set-Cookie の属性（Expires / Max-Age、Domain、Path、Secure、HttpOnly、
+//! SameSite）を読み、期限の切れた Cookie は送らずに捨てる。期限の付いた Cookie は
+//! [`CookieStore::serialize`] でプロファイルに書き、次に起動したときに
+//! [`CookieStore::load`] で読み戻す。期限のない Cookie（セッション Cookie）は
+//! メモリー上にだけ置く。
+//!
+//! 別のサイトの文書が読み込むサブリソースのリクエストには、SameSite=None の
+//! Cookie だけを送る（[`super::site`]）。
+
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use url::Url;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+use super::{http_date, site};
+
+/// 名前と値を合わせた長さの上限
+const MAX_COOKIE_SIZE: usize = 4096;
+
+/// 1 つのドメインに置ける Cookie の数。超えたら古いものから捨てる
+const MAX_COOKIES_PER_DOMAIN: usize = 180;
+
+/// 期限の上限（400 日）
+const MAX_LIFETIME: Duration = Duration::from_secs(400 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SameSite {
+    Strict,
+    /// 属性がないときもこれとして扱う
+    #[default]
+    Lax,
+    None,
+}
+
+impl SameSite {
+    fn as_str(self) -> &'static str {
+        match self {
+            SameSite::Strict => "strict",
+            SameSite::Lax => "lax",
+            SameSite::None => "none",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "strict" => Some(SameSite::Strict),
+            "lax" => Some(SameSite::Lax),
+            "none" => Some(SameSite::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Cookie {
     pub name: String,
     pub value: String,
+    /// 先頭の `.` を除いた小文字のドメイン
     pub domain: String,
     pub path: String,
     pub secure: bool,
+    /// スクリプトから読ませない
+    pub http_only: bool,
+    /// Domain 属性がなかった（domain と同じホストにだけ送る）
+    pub host_only: bool,
+    pub same_site: SameSite,
+    /// None ならセッション Cookie
+    pub expires: Option<SystemTime>,
+    pub created: SystemTime,
 }
 
-#[derive(Debug, Clone)]
-pub struct CookieStore {
-    store: Arc<RwLock<HashMap<String, Vec<Cookie>>>>, // domain -> cookies
-}
+impl Cookie {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
 
-impl Default for CookieStore {
-    fn default() -> Self {
-        Self::new()
+    fn matches(&self, host: &str, path: &str, secure: bool) -> bool {
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_match(host, &self.domain)
+        };
+        domain_ok && path_match(path, &self.path) && (secure || !self.secure)
     }
 }
 
+#[derive(Debug, Default)]
+struct CookieState {
+    /// ドメイン → Cookie
+    cookies: HashMap<String, Vec<Cookie>>,
+    /// 期限の付いた Cookie が変わったか
+    modified: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CookieStore {
+    state: Arc<RwLock<CookieState>>,
+}
+
 impl CookieStore {
     pub fn new() -> Self {
-        Self {
-            store: Arc::new(RwLock::new(HashMap::new())),
-        }
+        Self::default()
     }
 
-    pub fn set_cookies(&self, url: &Url, cookie_headers: &[String]) {
-        let mut store = self.store.write().expect("RwLock poisoned");
-        let domain = url.host_str().unwrap_or_default().to_string();
-        let entry = store.entry(domain.clone()).or_default();
-
-        for hdr in cookie_headers {
-            if let Some((name, value)) = hdr.split_once('=') {
-                entry.push(Cookie {
-                    name: name.trim().to_string(),
-                    value: value.split(';').next().unwrap_or("").trim().to_string(),
-                    domain: domain.clone(),
-                    path: "/".to_string(),
-                    secure: url.scheme() == "https",
-                });
+    /// url の応答の Set-Cookie ヘッダーを取り込む
+    ///
+    /// site_for_cookies はリクエストを出した文書の URL（トップレベルのページを開く
+    /// リクエストなら None）。別のサイトの文書のサブリソースの応答は
+    /// SameSite=None の Cookie しか置けない。
+    pub fn set_cookies(
+        &self,
+        url: &Url,
+        cookie_headers: &[String],
+        site_for_cookies: Option<&Url>,
+        now: SystemTime,
+    ) {
+        let cross_site = site_for_cookies.is_some_and(|site| !site::is_same_site(site, url));
+        let mut state = self.state.write().unwrap();
+
+        for header in cookie_headers {
+            let Some(cookie) = parse_set_cookie(url, header, now) else {
+                log::debug!("Ignoring Set-Cookie from {}: {:?}", url, header);
+                continue;
+            };
+            if cross_site && cookie.same_site != SameSite::None {
+                continue;
             }
+            state.insert(cookie, now);
         }
     }
 
-    pub fn get_cookie_header(&self, url: &Url) -> Option<String> {
-        let store = self.store.read().ok()?;
-        let domain = url.host_str().unwrap_or_default();
+    /// url へのリクエストに付ける Cookie ヘッダー
+    ///
+    /// site_for_cookies は [`set_cookies`](Self::set_cookies) と同じ。
+    pub fn get_cookie_header(
+        &self,
+        url: &Url,
+        site_for_cookies: Option<&Url>,
+        now: SystemTime,
+    ) -> Option<String> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let secure = is_secure_scheme(url);
+        let cross_site = site_for_cookies.is_some_and(|site| !site::is_same_site(site, url));
 
-        store.get(domain).and_then(|cookies| {
-            let s = cookies
+        let state = self.state.read().ok()?;
+        let mut cookies: Vec<&Cookie> = parent_domains(&host)
+            .filter_map(|domain| state.cookies.get(domain))
+            .flatten()
+            .filter(|c| !c.is_expired(now))
+            .filter(|c| c.matches(&host, url.path(), secure))
+            .filter(|c| !cross_site || c.same_site == SameSite::None)
+            .collect();
+        if cookies.is_empty() {
+            return None;
+        }
+
+        // パスの長いものから、同じ長さなら古いものから
+        cookies.sort_by(|a, b| {
+            b.path
+                .len()
+                .cmp(&a.path.len())
+                .then(a.created.cmp(&b.created))
+        });
+        Some(
+            cookies
                 .iter()
-                .map(|c| format!("{}={}", c.name, c.value))
+                .map(|c| {
+                    if c.name.is_empty() {
+                        c.value.clone()
+                    } else {
+                        format!("{}={}", c.name, c.value)
+                    }
+                })
                 .collect::<Vec<_>>()
-                .join("; ");
+                .join("; "),
+        )
+    }
+
+    /// 保存している Cookie の一覧（期限の切れたものを除く）
+    pub fn cookies(&self, now: SystemTime) -> Vec<Cookie> {
+        let state = self.state.read().unwrap();
+        state
+            .cookies
+            .values()
+            .flatten()
+            .filter(|c| !c.is_expired(now))
+            .cloned()
+            .collect()
+    }
+
+    /// すべての Cookie を捨てる
+    pub fn clear(&self) {
+        let mut state = self.state.write().unwrap();
+        state.cookies.clear();
+        state.modified = true;
+    }
+
+    /// 前に呼んでから期限の付いた Cookie が変わったか
+    pub fn take_modified(&self) -> bool {
+        std::mem::take(&mut self.state.write().unwrap().modified)
+    }
+
+    /// 期限の付いた Cookie を保存用の文字列にする
+    ///
+    /// 1 行に 1 つ、`cookie` ドメイン パス 期限 フラグ SameSite 作成時刻 名前 値 を
+    /// タブ区切りで書く。
+    pub fn serialize(&self, now: SystemTime) -> String {
+        let state = self.state.read().unwrap();
+        let mut out = String::new();
+        for cookie in state.cookies.values().flatten() {
+            let Some(expires) = cookie.expires.filter(|_| !cookie.is_expired(now)) else {
+                continue;
+            };
+            // タブ文字は区切りと紛れるので、含むものは残さない
+            if [&cookie.name, &cookie.value, &cookie.path]
+                .iter()
+                .any(|field| field.contains('\t'))
+            {
+                continue;
+            }
+            let flags = [
+                (cookie.secure, "secure"),
+                (cookie.http_only, "httponly"),
+                (cookie.host_only, "hostonly"),
+            ]
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>()
+            .join(",");
+            out.push_str(&format!(
+                "cookie\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                cookie.domain,
+                cookie.path,
+                unix_secs(expires),
+                if flags.is_empty() { "-" } else { &flags },
+                cookie.same_site.as_str(),
+                unix_secs(cookie.created),
+                cookie.name,
+                cookie.value
+            ));
+        }
+        out
+    }
 
-            if s.is_empty() { None } else { Some(s) }
-        })
+    /// serialize した文字列から Cookie を読み込む。期限の切れたものと読めない行は飛ばす
+    pub fn load(&self, text: &str, now: SystemTime) {
+        let mut state = self.state.write().unwrap();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [
+                "cookie",
+                domain,
+                path,
+                expires,
+                flags,
+                same_site,
+                created,
+                name,
+                value,
+            ] = fields[..]
+            else {
+                continue;
+            };
+            let (Ok(expires), Ok(created), Some(same_site)) = (
+                expires.parse::<u64>(),
+                created.parse::<u64>(),
+                SameSite::parse(same_site),
+            ) else {
+                log::warn!("Skipping invalid cookie entry: {:?}", line);
+                continue;
+            };
+            let flags: Vec<&str> = flags.split(',').collect();
+            let cookie = Cookie {
+                name: name.to_string(),
+                value: value.to_string(),
+                domain: domain.to_string(),
+                path: path.to_string(),
+                secure: flags.contains(&"secure"),
+                http_only: flags.contains(&"httponly"),
+                host_only: flags.contains(&"hostonly"),
+                same_site,
+                expires: Some(UNIX_EPOCH + Duration::from_secs(expires)),
+                created: UNIX_EPOCH + Duration::from_secs(created),
+            };
+            if !cookie.is_expired(now) {
+                state.insert(cookie, now);
+            }
+        }
+        // 読み込んだだけでは書き直さない
+        state.modified = false;
+    }
+}
+
+impl CookieState {
+    /// 同じ名前・ドメイン・パスの Cookie を置き換える。期限切れなら消すだけ
+    fn insert(&mut self, mut cookie: Cookie, now: SystemTime) {
+        let expired = cookie.is_expired(now);
+        let entry = self.cookies.entry(cookie.domain.clone()).or_default();
+
+        if let Some(pos) = entry.iter().position(|c| {
+            c.name == cookie.name && c.path == cookie.path && c.host_only == cookie.host_only
+        }) {
+            let old = entry.remove(pos);
+            self.modified |= old.expires.is_some();
+            cookie.created = old.created;
+        }
+        if expired {
+            return;
+        }
+
+        // 古い（期限切れを含む）ものから捨てる
+        entry.retain(|c| !c.is_expired(now));
+        if entry.len() >= MAX_COOKIES_PER_DOMAIN
+            && let Some(oldest) = entry
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, c)| c.created)
+                .map(|(i, _)| i)
+        {
+            let old = entry.remove(oldest);
+            self.modified |= old.expires.is_some();
+        }
+        self.modified |= cookie.expires.is_some();
+        entry.push(cookie);
+    }
+}
+
+/// Set-Cookie ヘッダーを読む。置いてはいけない Cookie なら None
+fn parse_set_cookie(url: &Url, header: &str, now: SystemTime) -> Option<Cookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut parts = header.split(';');
+    let name_value = parts.next()?;
+    // `=` のないものは名前が空の Cookie として扱う
+    let (name, value) = match name_value.split_once('=') {
+        Some((name, value)) => (name.trim(), value.trim()),
+        None => ("", name_value.trim()),
+    };
+    if (name.is_empty() && value.is_empty()) || name.len() + value.len() > MAX_COOKIE_SIZE {
+        return None;
+    }
+
+    let mut cookie = Cookie {
+        name: name.to_string(),
+        value: value.to_string(),
+        domain: host.clone(),
+        path: default_path(url),
+        secure: false,
+        http_only: false,
+        host_only: true,
+        same_site: SameSite::default(),
+        expires: None,
+        created: now,
+    };
+
+    let mut max_age = None;
+    let mut expires = None;
+    for attribute in parts {
+        let (key, value) = match attribute.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => (attribute.trim(), ""),
+        };
+        match key.to_ascii_lowercase().as_str() {
+            "expires" => expires = http_date::parse(value).or(expires),
+            "max-age" => {
+                // 数字以外が混じっていたら無視する
+                if let Ok(secs) = value.parse::<i64>() {
+                    max_age = Some(secs);
+                }
+            }
+            "domain" if !value.is_empty() => {
+                let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                // 自分より上のドメインにしか置けず、公開接尾辞には置けない
+                if !domain_match(&host, &domain) {
+                    return None;
+                }
+                if site::is_public_suffix(&domain) && domain != host {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+            "path" if value.starts_with('/') => cookie.path = value.to_string(),
+            "secure" => cookie.secure = true,
+            "httponly" => cookie.http_only = true,
+            "samesite" => {
+                // 知らない値は属性がないものとして扱う
+                cookie.same_site = SameSite::parse(value).unwrap_or_default();
+            }
+            _ => {}
+        }
+    }
+
+    // Max-Age は Expires より優先する
+    cookie.expires = match (max_age, expires) {
+        (Some(secs), _) if secs <= 0 => Some(UNIX_EPOCH),
+        (Some(secs), _) => Some(now + Duration::from_secs(secs as u64).min(MAX_LIFETIME)),
+        (None, Some(expires)) => Some(expires.min(now + MAX_LIFETIME)),
+        (None, None) => None,
+    };
+
+    // Secure の Cookie は安全な接続からしか置けず、SameSite=None には Secure が要る
+    if cookie.secure && !is_secure_scheme(url) {
+        return None;
     }
+    if cookie.same_site == SameSite::None && !cookie.secure {
+        return None;
+    }
+    Some(cookie)
+}
+
+/// Path 属性がないときのパス（リクエストのパスのディレクトリ）
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(pos) => path[..pos].to_string(),
+    }
+}
+
+/// host が domain かそのサブドメインか
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<std::net::IpAddr>().is_err())
+}
+
+fn path_match(request_path: &str, cookie_path: &str) -> bool {
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// host とその親のドメイン（`a.b.example.com` → `b.example.com` → …）
+fn parent_domains(host: &str) -> impl Iterator<Item = &str> {
+    std::iter::successors(Some(host), |d| d.split_once('.').map(|(_, rest)| rest))
+}
+
+fn is_secure_scheme(url: &Url) -> bool {
+    // localhost は http でも安全な文脈として扱う
+    url.scheme() == "https" || url.scheme() == "wss" || url.host_str() == Some("localhost")
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
 }
@@ -1,8 +1,22 @@
-use super::{HostKey, HttpSender, NetworkConfig, NetworkError, SenderPool};
+use super::cache::{CacheKey, CacheLookup, CachedResponse};
+use super::config::ProxyConfig;
+use super::decode::{self, ACCEPT_ENCODING, BodyDecoder};
+use super::dns::Resolver;
+use super::download::{self, PartialDownload, Resume};
+use super::hints::{ConnectionHint, HintBudget};
+use super::partition::{PartitionStores, PartitionedStores};
+use super::proxy::{self, BoxedIo};
+use super::sender_pool::{ConnectionLimiter, ConnectionPermit};
+use super::stream::{self, BodyStream};
+use super::tls_info::TlsInfo;
+use super::{
+    Cache, CancellationToken, HostKey, HttpSender, NetworkConfig, NetworkError, SenderPool,
+    StoragePartition,
+};
 
 use http_body_util::{BodyExt, Empty};
 use hyper::{
-    Method, Request, Uri,
+    HeaderMap, Method, Request, StatusCode, Uri,
     body::{Bytes, Incoming},
     client::conn,
     http::uri::Scheme,
@@ -10,14 +24,37 @@ use hyper::{
 use hyper_util::rt::TokioIo;
 use rustls::{ClientConfig, RootCertStore};
 use rustls_native_certs::load_native_certs;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::Arc;
-use tokio::{net::TcpStream, runtime::Runtime, task::LocalSet};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::{runtime::Runtime, task::LocalSet};
 use tokio_rustls::TlsConnector;
+use url::Url;
+
+use crate::platform::io;
+
+/// ボディを受信するたびに (受信したバイト数, Content-Length) で呼ばれる
+pub(super) type ProgressCallback<'a> = &'a dyn Fn(u64, Option<u64>);
+
+/// 受信しながらボディを渡す fetch で、ボディより先にヘッダーを渡すときに呼ばれる
+///
+/// 渡す Response の body は空で、ボディは [`Response::body_stream`] から読む。
+pub(super) type HeadersCallback<'a> = &'a dyn Fn(Response);
 
 pub(super) struct AsyncNetworkCore {
     local: LocalSet,
     rt: Runtime,
-    inner: NetworkInner,
+    /// 裏で進めているヒントのタスクも持つ
+    inner: Rc<NetworkInner>,
+    stores: PartitionedStores,
+    /// 期限の付いた Cookie を書くファイル
+    cookie_file: Option<PathBuf>,
+    /// HSTS のホストを書くファイル
+    hsts_file: Option<PathBuf>,
 }
 
 impl AsyncNetworkCore {
@@ -32,19 +69,257 @@ impl AsyncNetworkCore {
         Self {
             rt,
             local,
-            inner: NetworkInner::new(),
+            inner: Rc::new(NetworkInner::new()),
+            stores: PartitionedStores::new(),
+            cookie_file: None,
+            hsts_file: None,
         }
     }
 
     pub fn set_network_config(&mut self, config: NetworkConfig) {
-        self.inner.set_network_config(config)
+        // ディスクに書くのは通常の保存先だけ（プライベートタブの分は残さない）
+        if config.enable_cache
+            && let Some(dir) = &config.cache_dir
+        {
+            self.stores
+                .get(StoragePartition::Default)
+                .cache
+                .enable_disk(dir.clone(), config.disk_cache_size);
+        }
+        let default = self.stores.get(StoragePartition::Default);
+        if config.cookie_file != self.cookie_file {
+            self.cookie_file = config.cookie_file.clone();
+            if let Some(text) = read_store_file(self.cookie_file.as_deref(), "cookies") {
+                default.cookies.load(&text, SystemTime::now());
+            }
+        }
+        if config.hsts_file != self.hsts_file {
+            self.hsts_file = config.hsts_file.clone();
+            if let Some(text) = read_store_file(self.hsts_file.as_deref(), "HSTS hosts") {
+                default.hsts.load(&text, SystemTime::now());
+            }
+        }
+        self.finish_hints().set_network_config(config)
+    }
+
+    /// 進めているヒントが終わるのを待ち、設定を変えられるようにする
+    fn finish_hints(&mut self) -> &mut NetworkInner {
+        // ヒントは connect_timeout のうちに終わる
+        while Rc::get_mut(&mut self.inner).is_none() {
+            self.run_hints(HINT_POLL_INTERVAL);
+        }
+        Rc::get_mut(&mut self.inner).unwrap()
+    }
+
+    /// url のオリジンについてヒントを始める。枠が空いていなければ何もしない
+    pub fn start_hint(&self, url: &str, hint: ConnectionHint, partition: StoragePartition) {
+        let stores = self.stores.get(partition);
+        let key = url
+            .parse()
+            .map_err(|_| NetworkError::InvalidUri)
+            .and_then(|uri| hsts_upgrade(uri, stores))
+            .and_then(|uri| host_key(&uri));
+        let key = match key {
+            Ok(key) => key,
+            Err(e) => {
+                log::debug!("NetworkCore: ignoring the hint for {}: {}", url, e);
+                return;
+            }
+        };
+        let Some(permit) = self.inner.hints.try_start(&key) else {
+            log::debug!("NetworkCore: dropping the {:?} hint for {}", hint, key.host);
+            return;
+        };
+
+        let inner = self.inner.clone();
+        self.local.spawn_local(async move {
+            inner.warm(&key, hint).await;
+            drop(permit);
+        });
+    }
+
+    /// 進めているヒントがあるか
+    pub fn has_pending_hints(&self) -> bool {
+        self.inner.hints.in_flight() > 0
+    }
+
+    /// 進めているヒントを duration の間だけ進める
+    pub fn run_hints(&self, duration: Duration) {
+        self.local.block_on(&self.rt, tokio::time::sleep(duration));
+    }
+
+    /// 期限の付いた Cookie と HSTS のホスト、ディスクのキャッシュの索引が変わっていたら
+    /// ファイルに書く
+    pub fn save_stores_if_modified(&self) {
+        let default = self.stores.get(StoragePartition::Default);
+        let now = SystemTime::now();
+        default.cache.save_index_if_modified();
+        if let Some(path) = &self.cookie_file
+            && default.cookies.take_modified()
+            && let Err(e) = io::write_atomic(path, default.cookies.serialize(now).as_bytes())
+        {
+            log::error!("Failed to save cookies: {:#}", e);
+        }
+        if let Some(path) = &self.hsts_file
+            && default.hsts.take_modified()
+            && let Err(e) = io::write_atomic(path, default.hsts.serialize(now).as_bytes())
+        {
+            log::error!("Failed to save HSTS hosts: {:#}", e);
+        }
+    }
+
+    pub fn clear_partition(&mut self, partition: StoragePartition) {
+        self.stores.clear(partition)
     }
 
     /// UI スレッドなどから呼ばれる blocking API
-    pub fn fetch_blocking(&self, url: &str) -> Result<Response, NetworkError> {
+    ///
+    /// 完了前に token が取り消されたら None を返す。
+    pub fn fetch_blocking(
+        &self,
+        url: &str,
+        bypass_cache: bool,
+        request_headers: &[(&'static str, String)],
+        partition: StoragePartition,
+        site_for_cookies: Option<&Url>,
+        token: &CancellationToken,
+        on_progress: ProgressCallback<'_>,
+        on_headers: Option<HeadersCallback<'_>>,
+    ) -> Option<Result<Response, NetworkError>> {
+        let stores = self.stores.get(partition);
+        if token.is_cancelled() {
+            return None;
+        }
+
         // network スレッド内で完結させる
-        self.local
-            .block_on(&self.rt, async { self.inner.fetch_url(url).await })
+        self.local.block_on(&self.rt, async {
+            let streaming = on_headers.map(|f| Streaming {
+                on_headers: f,
+                token,
+            });
+            let fetch = self.inner.fetch_url(
+                url,
+                bypass_cache,
+                request_headers,
+                stores,
+                site_for_cookies,
+                on_progress,
+                streaming.as_ref(),
+            );
+            token
+                .run_until_cancelled(async {
+                    match self.inner.network_config.total_timeout {
+                        Some(limit) => tokio::time::timeout(limit, fetch)
+                            .await
+                            .unwrap_or(Err(NetworkError::Timeout)),
+                        None => fetch.await,
+                    }
+                })
+                .await
+        })
+    }
+
+    /// url を path にダウンロードする（blocking）
+    ///
+    /// 接続が切れたら、待ち時間を延ばしながら続きから取り直す。完了前に token が
+    /// 取り消されたら None を返す。受信した分は残すので、同じ path にもう一度
+    /// ダウンロードすると続きから受け取る。返す Response の body は空。
+    pub fn download_blocking(
+        &self,
+        url: &str,
+        path: &Path,
+        partition: StoragePartition,
+        token: &CancellationToken,
+        on_progress: ProgressCallback<'_>,
+    ) -> Option<Result<Response, NetworkError>> {
+        let stores = self.stores.get(partition);
+        if token.is_cancelled() {
+            return None;
+        }
+
+        self.local.block_on(&self.rt, async {
+            token
+                .run_until_cancelled(async {
+                    let mut delay = download::RETRY_DELAY;
+                    let mut attempt = 0;
+                    loop {
+                        match self
+                            .inner
+                            .download_once(url, path, stores, on_progress)
+                            .await
+                        {
+                            Err(e) if attempt < download::RETRIES && e.is_transient() => {
+                                log::warn!(
+                                    "Download of {} interrupted ({}), retrying in {:?}",
+                                    url,
+                                    e,
+                                    delay
+                                );
+                                tokio::time::sleep(delay).await;
+                                delay *= 2;
+                                attempt += 1;
+                            }
+                            result => return result,
+                        }
+                    }
+                })
+                .await
+        })
+    }
+}
+
+/// ダウンロードのボディを file に書く（file が None なら読み捨てる）
+///
+/// 書けなかったら stream を捨てて受信を取り消す。受信の失敗は fetch の結果で伝わる。
+fn write_body(mut stream: BodyStream, mut file: Option<std::fs::File>) -> std::io::Result<()> {
+    use futures_core::Stream;
+    use std::io::Write;
+
+    while let Some(chunk) = pollster::block_on(std::future::poll_fn(|cx| {
+        std::pin::Pin::new(&mut stream).poll_next(cx)
+    })) {
+        if let Some(file) = file.as_mut() {
+            file.write_all(&chunk)?;
+        }
+    }
+    match file {
+        Some(mut file) => file.flush(),
+        None => Ok(()),
+    }
+}
+
+/// トランスポート（HTTP/1・HTTP/2・HTTP/3）ごとのレスポンスボディの読み口
+pub(super) trait ResponseBody {
+    /// 次に届いたデータ。終わりなら None
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, NetworkError>>;
+}
+
+impl ResponseBody for Incoming {
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, NetworkError>> {
+        loop {
+            let frame = match self.frame().await? {
+                Ok(frame) => frame,
+                Err(_) => return Some(Err(NetworkError::HttpResponseFailed)),
+            };
+            // トレーラーは読み飛ばす
+            if let Ok(data) = frame.into_data() {
+                return Some(Ok(data));
+            }
+        }
+    }
+}
+
+/// 次のデータが limit のうちに届かなければ Timeout にする
+struct WithReadTimeout<'a, B> {
+    body: &'a mut B,
+    limit: Duration,
+}
+
+impl<B: ResponseBody> ResponseBody for WithReadTimeout<'_, B> {
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, NetworkError>> {
+        tokio::time::timeout(self.limit, self.body.next_chunk())
+            .await
+            .unwrap_or(Some(Err(NetworkError::Timeout)))
     }
 }
 
@@ -54,28 +329,96 @@ pub struct Response {
     pub status: hyper::StatusCode,
     pub reason_phrase: String,
     pub headers: Vec<(String, String)>,
+    /// 受信し終えたボディ（受信しながら読む応答では空）
     pub body: Vec<u8>,
+    /// 応答を受け取った TLS の接続（https 以外や、キャッシュから答えたときは None）
+    pub tls: Option<Arc<TlsInfo>>,
+    stream: Option<BodyStream>,
+}
+
+impl Response {
+    /// ボディをチャンクの列として読む
+    ///
+    /// [`super::NetworkCore::fetch_streaming`] の応答では受信中のボディを、それ以外では
+    /// 受信し終えた body を流す。2 回目以降は空の列になる。
+    pub fn body_stream(&mut self) -> BodyStream {
+        self.stream
+            .take()
+            .unwrap_or_else(|| BodyStream::from_bytes(std::mem::take(&mut self.body)))
+    }
+
+    /// ボディの代わりに stream を持つ写し
+    fn with_stream(&self, stream: BodyStream) -> Self {
+        Self {
+            url: self.url.clone(),
+            status: self.status,
+            reason_phrase: self.reason_phrase.clone(),
+            headers: self.headers.clone(),
+            body: Vec::new(),
+            tls: self.tls.clone(),
+            stream: Some(stream),
+        }
+    }
+}
+
+/// 受信しながらボディを渡す fetch の設定
+pub(super) struct Streaming<'a> {
+    on_headers: HeadersCallback<'a>,
+    /// BodyStream を捨てたときに取り消す
+    token: &'a CancellationToken,
 }
 
 pub(super) struct NetworkInner {
     sender_pool: Arc<std::sync::RwLock<SenderPool>>,
     tls_config: Arc<ClientConfig>,
     network_config: Arc<NetworkConfig>,
+    resolver: Resolver,
+    /// 開いている接続の数（同時接続数の上限を守るため）
+    limiter: ConnectionLimiter,
+    /// https のオリジンごとの、最後に張った TLS の接続の情報
+    tls_info: RefCell<HashMap<HostKey, Arc<TlsInfo>>>,
+    /// 進めている接続のヒントの枠
+    hints: HintBudget,
+    #[cfg(feature = "http3")]
+    http3: Option<super::http3::Http3Client>,
 }
 
 impl NetworkInner {
     pub fn new() -> Self {
+        let tls_config = Self::build_tls_config();
         Self {
             sender_pool: Arc::new(std::sync::RwLock::new(SenderPool::new())),
-            tls_config: Arc::new(Self::build_tls_config()),
+            #[cfg(feature = "http3")]
+            http3: super::http3::Http3Client::new(&tls_config),
+            tls_config: Arc::new(tls_config),
             network_config: Arc::new(NetworkConfig::default()),
+            resolver: Resolver::new(),
+            limiter: ConnectionLimiter::new(),
+            tls_info: RefCell::new(HashMap::new()),
+            hints: HintBudget::new(),
         }
     }
 
     pub fn set_network_config(&mut self, confing: NetworkConfig) {
+        // プロキシが変わったら、前の経路で張った接続は使わない
+        if confing.proxies != self.network_config.proxies
+            || confing.no_proxy != self.network_config.no_proxy
+        {
+            *self.sender_pool.write().unwrap() = SenderPool::new();
+        }
+        self.sender_pool
+            .write()
+            .unwrap()
+            .set_max_connections_per_host(confing.max_connections_per_host);
         self.network_config = Arc::new(confing)
     }
 
+    /// key へのリクエストに使うプロキシ
+    fn proxy_for(&self, key: &HostKey) -> Option<&ProxyConfig> {
+        self.network_config
+            .proxy_for(key.scheme.as_str(), &key.host, key.port)
+    }
+
     fn build_tls_config() -> ClientConfig {
         let mut roots = RootCertStore::empty();
         let result = load_native_certs();
@@ -84,17 +427,71 @@ impl NetworkInner {
             let _ = roots.add(cert);
         }
 
-        ClientConfig::builder()
+        let mut config = ClientConfig::builder()
             .with_root_certificates(roots)
-            .with_no_client_auth()
+            .with_no_client_auth();
+        // ALPN で HTTP/2 を優先して申し出る
+        config.alpn_protocols = vec![ALPN_H2.to_vec(), ALPN_HTTP1.to_vec()];
+        config
     }
 
-    pub async fn fetch_url(&self, url: &str) -> Result<Response, NetworkError> {
-        let mut current: Uri = url.parse().map_err(|_| NetworkError::InvalidUri)?;
+    pub async fn fetch_url(
+        &self,
+        url: &str,
+        bypass_cache: bool,
+        request_headers: &[(&'static str, String)],
+        stores: &PartitionStores,
+        site_for_cookies: Option<&Url>,
+        on_progress: ProgressCallback<'_>,
+        streaming: Option<&Streaming<'_>>,
+    ) -> Result<Response, NetworkError> {
+        let mut current = hsts_upgrade(url.parse().map_err(|_| NetworkError::InvalidUri)?, stores)?;
+        let url = &current.to_string();
         let mut redirects = 0usize;
 
+        // キャッシュはトップレベルのページのサイトごとに分ける
+        let cache_key = Url::parse(url)
+            .ok()
+            .filter(|_| self.network_config.enable_cache)
+            .map(|url| CacheKey::new(&url, site_for_cookies));
+        // 新鮮でないキャッシュは、変わっていないかを確かめるヘッダーを付けて取り直す
+        let mut conditional = Vec::new();
+        if !bypass_cache && let Some(key) = &cache_key {
+            match stores.cache.lookup(key, SystemTime::now()) {
+                CacheLookup::Fresh(cached) => {
+                    return Ok(cached_response(url, cached, on_progress, streaming));
+                }
+                CacheLookup::Stale(cached) => conditional = Cache::conditional_headers(&cached),
+                CacheLookup::Miss => {}
+            }
+        }
+
         loop {
-            let resp = self.send_request(&current).await?;
+            // 確かめるヘッダーはリダイレクト先には付けない
+            let mut extra_headers = request_headers.to_vec();
+            if redirects == 0 {
+                extra_headers.extend_from_slice(&conditional);
+            }
+            let resp = self
+                .send_with_retry(
+                    &current,
+                    bypass_cache,
+                    &extra_headers,
+                    stores,
+                    site_for_cookies,
+                    on_progress,
+                    streaming,
+                )
+                .await?;
+
+            if resp.status == StatusCode::NOT_MODIFIED
+                && redirects == 0
+                && !conditional.is_empty()
+                && let Some(key) = &cache_key
+                && let Some(cached) = stores.cache.freshen(key, &resp.headers, SystemTime::now())
+            {
+                return Ok(cached_response(url, cached, on_progress, streaming));
+            }
 
             if self.network_config.follow_redirects && resp.status.is_redirection() {
                 if redirects >= 10 {
@@ -107,89 +504,529 @@ impl NetworkInner {
                     .find(|(k, _)| k.eq_ignore_ascii_case("location"))
                     .map(|(_, v)| v)
                 {
-                    current = resolve_redirect(&current, loc)?;
+                    current = hsts_upgrade(resolve_redirect(&current, loc)?, stores)?;
                     redirects += 1;
                     continue;
                 }
             }
 
+            // リダイレクトした応答は元の URL で引くと URL が変わってしまうので入れない。
+            // 受信しながら渡したボディは手元に残らないので入れない
+            if redirects == 0
+                && streaming.is_none()
+                && resp.status == StatusCode::OK
+                && let Some(key) = &cache_key
+            {
+                stores.cache.store(
+                    key,
+                    resp.body.clone(),
+                    resp.headers.clone(),
+                    SystemTime::now(),
+                );
+            }
+
             return Ok(resp);
         }
     }
 
-    async fn send_request(&self, uri: &Uri) -> Result<Response, NetworkError> {
-        let host = uri.host().ok_or(NetworkError::MissingHost)?;
-        let scheme = uri.scheme().unwrap_or(&Scheme::HTTP);
-        let port = uri
-            .port_u16()
-            .unwrap_or(if scheme == &Scheme::HTTPS { 443 } else { 80 });
+    /// url を 1 回取りに行き、ボディを path の `.part` に書く
+    ///
+    /// 前回の続きがあれば Range で続きを頼む。書き終えたら path に移す。
+    pub async fn download_once(
+        &self,
+        url: &str,
+        path: &Path,
+        stores: &PartitionStores,
+        on_progress: ProgressCallback<'_>,
+    ) -> Result<Response, NetworkError> {
+        let partial = PartialDownload::load(path, url);
+        // BodyStream を捨てると取り消される（書けなくなったら受信をやめる）
+        let write_token = CancellationToken::new();
+        let offset = Cell::new(0);
+        let mode = Cell::new(None);
+        let open_failed = Cell::new(false);
+        let writer = RefCell::new(None);
 
-        let key = HostKey {
-            scheme: scheme.clone(),
-            host: host.to_string(),
-            port,
+        let on_headers = |mut response: Response| {
+            let stream = response.body_stream();
+            let file = match partial.resume_mode(response.status, &response.headers) {
+                Some(resume) => match download::open_part(path, url, resume, &response.headers) {
+                    Ok(file) => {
+                        mode.set(Some(resume));
+                        file
+                    }
+                    Err(e) => {
+                        log::error!("Failed to open {:?}: {}", download::part_path(path), e);
+                        open_failed.set(true);
+                        return;
+                    }
+                },
+                None => None,
+            };
+            if mode.get() == Some(Resume::Append) {
+                offset.set(partial.received);
+            }
+            // ネットワークスレッドを止めないよう、別のスレッドで書く
+            // （書かない応答のボディは読み捨てる）
+            *writer.borrow_mut() = Some(std::thread::spawn(move || write_body(stream, file)));
+        };
+        let on_body_progress = |received: u64, total: Option<u64>| {
+            let offset = offset.get();
+            on_progress(offset + received, total.map(|total| offset + total));
+        };
+        let streaming = Streaming {
+            on_headers: &on_headers,
+            token: &write_token,
         };
 
-        let mut sender = self.get_or_create_sender(&key).await?;
+        let fetched = write_token
+            .run_until_cancelled(self.fetch_url(
+                url,
+                true,
+                &partial.request_headers(),
+                stores,
+                None,
+                &on_body_progress,
+                Some(&streaming),
+            ))
+            .await;
+        let written = writer.take().map_or(Ok(()), |handle| {
+            handle
+                .join()
+                .unwrap_or_else(|_| Err(std::io::Error::other("the writer panicked")))
+        });
+        if open_failed.get() {
+            return Err(NetworkError::FileWriteFailed);
+        }
+        if let Err(e) = written {
+            log::error!("Failed to write {:?}: {}", download::part_path(path), e);
+            return Err(NetworkError::FileWriteFailed);
+        }
+        let mut response = fetched.ok_or(NetworkError::FileWriteFailed)??;
 
-        let req = Request::builder()
-            .method(Method::GET)
-            .uri(uri.path_and_query().map(|p| p.as_str()).unwrap_or("/"))
-            .header("Host", host)
-            .header("User-Agent", self.network_config.user_agent.as_str())
-            .body(Empty::<Bytes>::new())
-            .map_err(|_| NetworkError::HttpRequestFailed)?;
+        match mode.get() {
+            Some(resume) => {
+                download::finish(path).map_err(|e| {
+                    log::error!("Failed to move the download to {:?}: {}", path, e);
+                    NetworkError::FileWriteFailed
+                })?;
+                // 前回で受信し終えていた（416）ことは呼び出し側には関係ない
+                if resume == Resume::Complete {
+                    response.status = StatusCode::OK;
+                    response.reason_phrase = "OK".to_string();
+                }
+                Ok(response)
+            }
+            // 頼んだのと違う範囲が返ってきたら、受信した分を捨てて最初から取り直す
+            None if response.status == StatusCode::PARTIAL_CONTENT => {
+                download::discard(path);
+                Err(NetworkError::HttpResponseFailed)
+            }
+            // エラーの応答はそのまま返す（受信した分は次に続きから使えるよう残す）
+            None => Ok(response),
+        }
+    }
+
+    /// 応答を受け取る前に失敗したら、待ち時間を延ばしながら送り直す
+    ///
+    /// リクエストはいつも GET（冪等）なので送り直してよい。受信しながら渡す fetch で
+    /// ヘッダーを渡したあとは、呼び出し側が読み始めているので送り直さない。
+    async fn send_with_retry(
+        &self,
+        uri: &Uri,
+        bypass_cache: bool,
+        extra_headers: &[(&'static str, String)],
+        stores: &PartitionStores,
+        site_for_cookies: Option<&Url>,
+        on_progress: ProgressCallback<'_>,
+        streaming: Option<&Streaming<'_>>,
+    ) -> Result<Response, NetworkError> {
+        let headers_sent = Cell::new(false);
+        let on_headers = |response: Response| {
+            headers_sent.set(true);
+            if let Some(streaming) = streaming {
+                (streaming.on_headers)(response);
+            }
+        };
+        let streaming = streaming.map(|streaming| Streaming {
+            on_headers: &on_headers,
+            token: streaming.token,
+        });
 
-        let mut res = match &mut sender {
-            HttpSender::Http1(s) => s
-                .send_request(req)
+        let mut delay = self.network_config.retry_backoff;
+        let mut attempt = 0;
+        loop {
+            match self
+                .send_request(
+                    uri,
+                    bypass_cache,
+                    extra_headers,
+                    stores,
+                    site_for_cookies,
+                    on_progress,
+                    streaming.as_ref(),
+                )
                 .await
-                .map_err(|_| NetworkError::HttpRequestFailed)?,
-            _ => {
-                return Err(NetworkError::UnsupportedHttpVersion);
+            {
+                Err(e)
+                    if attempt < self.network_config.max_retries
+                        && !headers_sent.get()
+                        && e.is_transient() =>
+                {
+                    log::info!(
+                        "NetworkCore: request to {} failed ({}), retrying in {:?}",
+                        uri,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send_request(
+        &self,
+        uri: &Uri,
+        bypass_cache: bool,
+        extra_headers: &[(&'static str, String)],
+        stores: &PartitionStores,
+        site_for_cookies: Option<&Url>,
+        on_progress: ProgressCallback<'_>,
+        streaming: Option<&Streaming<'_>>,
+    ) -> Result<Response, NetworkError> {
+        let cookie_url = Url::parse(&uri.to_string())
+            .ok()
+            .filter(|_| self.network_config.enable_cookies);
+        let key = host_key(uri)?;
+
+        let mut headers = vec![("User-Agent", self.network_config.user_agent.clone())];
+        if !extra_headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("accept-encoding"))
+        {
+            headers.push(("Accept-Encoding", ACCEPT_ENCODING.to_string()));
+        }
+        // 強制再読み込みでは途中のキャッシュにも取り直させる
+        if bypass_cache {
+            headers.push(("Cache-Control", "no-cache".to_string()));
+            headers.push(("Pragma", "no-cache".to_string()));
+        }
+        headers.extend_from_slice(extra_headers);
+        if let Some(cookie) = cookie_url.as_ref().and_then(|u| {
+            stores
+                .cookies
+                .get_cookie_header(u, site_for_cookies, SystemTime::now())
+        }) {
+            headers.push(("Cookie", cookie));
+        }
+
+        let response = match self
+            .send_http3(uri, &key, &headers, stores, on_progress, streaming)
+            .await
+        {
+            Some(response) => response?,
+            None => {
+                self.send_tcp(uri, &key, &headers, on_progress, streaming)
+                    .await?
             }
         };
 
-        let response = Self::collect_response(uri.to_string(), &mut res).await?;
+        if let Some(url) = &cookie_url {
+            let set_cookies: Vec<String> = response
+                .headers
+                .iter()
+                .filter(|(k, _)| k.eq_ignore_ascii_case("set-cookie"))
+                .map(|(_, v)| v.clone())
+                .collect();
+            if !set_cookies.is_empty() {
+                stores
+                    .cookies
+                    .set_cookies(url, &set_cookies, site_for_cookies, SystemTime::now());
+            }
+        }
+        if key.scheme == Scheme::HTTPS
+            && let Some((_, sts)) = response
+                .headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("strict-transport-security"))
+        {
+            stores.hsts.record(&key.host, sts, SystemTime::now());
+        }
+        if key.scheme == Scheme::HTTPS
+            && let Some((_, alt_svc)) = response
+                .headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case("alt-svc"))
+        {
+            stores
+                .alt_svc
+                .record(&key.host, key.port, alt_svc, Instant::now());
+        }
+
+        Ok(response)
+    }
+
+    /// HTTP/1・HTTP/2 で送る
+    async fn send_tcp(
+        &self,
+        uri: &Uri,
+        key: &HostKey,
+        headers: &[(&str, String)],
+        on_progress: ProgressCallback<'_>,
+        streaming: Option<&Streaming<'_>>,
+    ) -> Result<Response, NetworkError> {
+        let mut sender = self.get_or_create_sender(key).await?;
+
+        // HTTP/2 は :authority と :scheme を URI から作るので絶対 URI を渡す。
+        // HTTP プロキシにそのまま送るリクエストも絶対 URI にする
+        let forward_proxy = self
+            .proxy_for(key)
+            .filter(|proxy| proxy::forwards_plain_http(proxy, key.scheme.as_str()));
+        let mut req = match &sender {
+            HttpSender::Http1(_) if forward_proxy.is_some() => Request::builder()
+                .uri(uri.clone())
+                .header("Host", key.host.as_str()),
+            HttpSender::Http1(_) => Request::builder()
+                .uri(uri.path_and_query().map(|p| p.as_str()).unwrap_or("/"))
+                .header("Host", key.host.as_str()),
+            HttpSender::Http2(_) => Request::builder().uri(uri.clone()),
+        };
+        if let Some(auth) = forward_proxy.and_then(proxy::authorization) {
+            req = req.header("Proxy-Authorization", auth);
+        }
+        req = req.method(Method::GET);
+        for (name, value) in headers {
+            req = req.header(*name, value.as_str());
+        }
+        let req = req
+            .body(Empty::<Bytes>::new())
+            .map_err(|_| NetworkError::HttpRequestFailed)?;
+
+        let res = tokio::time::timeout(self.network_config.read_timeout, async {
+            match &mut sender {
+                HttpSender::Http1(s) => s.send_request(req).await,
+                HttpSender::Http2(s) => s.send_request(req).await,
+            }
+        })
+        .await
+        .map_err(|_| NetworkError::Timeout)?
+        .map_err(|_| NetworkError::HttpRequestFailed)?;
+
+        let tls = self.tls_info.borrow().get(key).cloned();
+        let (parts, mut body) = res.into_parts();
+        let response = self
+            .collect_response(
+                uri.to_string(),
+                parts.status,
+                &parts.headers,
+                tls,
+                &mut body,
+                on_progress,
+                streaming,
+            )
+            .await?;
 
         self.sender_pool
             .write()
             .unwrap()
-            .add_connection(key, sender);
+            .add_connection(key.clone(), sender);
 
         Ok(response)
     }
 
+    /// Alt-Svc で HTTP/3 が知らされているオリジンなら HTTP/3 で送る
+    ///
+    /// HTTP/3 を使わなかったか、応答のヘッダーを受け取る前に失敗したら None
+    /// （HTTP/2・HTTP/1 でやり直す）。
+    #[cfg(feature = "http3")]
+    async fn send_http3(
+        &self,
+        uri: &Uri,
+        key: &HostKey,
+        headers: &[(&str, String)],
+        stores: &PartitionStores,
+        on_progress: ProgressCallback<'_>,
+        streaming: Option<&Streaming<'_>>,
+    ) -> Option<Result<Response, NetworkError>> {
+        // UDP はプロキシを通せない
+        if key.scheme != Scheme::HTTPS || self.proxy_for(key).is_some() {
+            return None;
+        }
+        let http3 = self.http3.as_ref()?;
+        let (alt_host, alt_port) =
+            stores
+                .alt_svc
+                .http3_endpoint(&key.host, key.port, Instant::now())?;
+
+        match http3.send(uri, (&alt_host, alt_port), headers).await {
+            Ok((status, response_headers, tls, mut body)) => Some(
+                self.collect_response(
+                    uri.to_string(),
+                    status,
+                    &response_headers,
+                    tls,
+                    &mut body,
+                    on_progress,
+                    streaming,
+                )
+                .await,
+            ),
+            Err(e) => {
+                log::info!(
+                    "NetworkCore: HTTP/3 to {} failed ({}), falling back to TCP",
+                    key.host,
+                    e
+                );
+                stores
+                    .alt_svc
+                    .mark_broken(&key.host, key.port, Instant::now());
+                None
+            }
+        }
+    }
+
+    #[cfg(not(feature = "http3"))]
+    async fn send_http3(
+        &self,
+        _uri: &Uri,
+        _key: &HostKey,
+        _headers: &[(&str, String)],
+        _stores: &PartitionStores,
+        _on_progress: ProgressCallback<'_>,
+        _streaming: Option<&Streaming<'_>>,
+    ) -> Option<Result<Response, NetworkError>> {
+        None
+    }
+
     async fn collect_response(
+        &self,
         url: String,
-        res: &mut hyper::Response<Incoming>,
+        status: StatusCode,
+        header_map: &HeaderMap,
+        tls: Option<Arc<TlsInfo>>,
+        body: &mut impl ResponseBody,
+        on_progress: ProgressCallback<'_>,
+        streaming: Option<&Streaming<'_>>,
     ) -> Result<Response, NetworkError> {
-        let status = res.status();
         let reason_phrase = status.canonical_reason().unwrap_or("").to_string();
 
-        let headers = res
-            .headers()
+        let mut headers = header_map
             .iter()
             .map(|(k, v)| (k.as_str().to_string(), v.to_str().unwrap_or("").to_string()))
             .collect();
 
-        let mut body = Vec::new();
-        while let Some(frame) = res.frame().await {
-            let frame = frame.map_err(|_| NetworkError::HttpResponseFailed)?;
-            if let Some(chunk) = frame.data_ref() {
-                body.extend_from_slice(chunk);
-            }
+        let content_length = header_map
+            .get(hyper::header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<u64>().ok());
+        on_progress(0, content_length);
+        let body = &mut WithReadTimeout {
+            body,
+            limit: self.network_config.read_timeout,
+        };
+
+        // 追いかけるリダイレクトのボディは呼び出し側に渡さない。
+        // 304 はキャッシュから答えるので同じく渡さない
+        let streaming = streaming.filter(|_| {
+            status != StatusCode::NOT_MODIFIED
+                && !(self.network_config.follow_redirects
+                    && status.is_redirection()
+                    && header_map.contains_key(hyper::header::LOCATION))
+        });
+        if let Some(streaming) = streaming {
+            return Self::stream_response(
+                Response {
+                    url,
+                    status,
+                    reason_phrase,
+                    headers,
+                    body: Vec::new(),
+                    tls,
+                    stream: None,
+                },
+                body,
+                content_length,
+                on_progress,
+                streaming,
+            )
+            .await;
+        }
+
+        let mut received = Vec::new();
+        while let Some(chunk) = body.next_chunk().await {
+            received.extend_from_slice(&chunk?);
+            on_progress(received.len() as u64, content_length);
         }
+        // 進み具合は圧縮されたままの長さで数え、展開は受信し終えてから行う
+        let received = decode::decode_body(&mut headers, received)?;
 
         Ok(Response {
             url,
             status,
             reason_phrase,
             headers,
-            body,
+            body: received,
+            tls,
+            stream: None,
         })
     }
 
+    /// ヘッダーを先に渡し、ボディは届いた分から BodyStream に流す
+    ///
+    /// ヘッダーを渡したあとのエラーは BodyStream に伝え、戻り値でも返す。
+    async fn stream_response(
+        mut response: Response,
+        body: &mut impl ResponseBody,
+        content_length: Option<u64>,
+        on_progress: ProgressCallback<'_>,
+        streaming: &Streaming<'_>,
+    ) -> Result<Response, NetworkError> {
+        let mut decoder = BodyDecoder::from_headers(&mut response.headers)?;
+
+        let (sender, body_stream) = stream::channel(Some(streaming.token.clone()));
+        (streaming.on_headers)(response.with_stream(body_stream));
+
+        let mut received = 0u64;
+        while let Some(chunk) = body.next_chunk().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    sender.fail(NetworkError::HttpResponseFailed);
+                    return Err(e);
+                }
+            };
+            received += chunk.len() as u64;
+            on_progress(received, content_length);
+
+            let chunk = match decoder.as_mut().map(|d| d.push(&chunk)) {
+                None => chunk,
+                Some(Ok(decoded)) => Bytes::from(decoded),
+                Some(Err(e)) => {
+                    sender.fail(NetworkError::ContentDecodingFailed);
+                    return Err(e);
+                }
+            };
+            if !chunk.is_empty() {
+                sender.send(chunk);
+            }
+        }
+
+        match decoder.map(BodyDecoder::finish) {
+            Some(Err(e)) => {
+                sender.fail(NetworkError::ContentDecodingFailed);
+                return Err(e);
+            }
+            Some(Ok(rest)) if !rest.is_empty() => sender.send(Bytes::from(rest)),
+            _ => {}
+        }
+        sender.finish();
+        Ok(response)
+    }
+
     async fn get_or_create_sender(&self, key: &HostKey) -> Result<HttpSender, NetworkError> {
         if let Some(s) = self.sender_pool.write().unwrap().get_connection(key) {
             return Ok(s);
@@ -198,11 +1035,54 @@ impl NetworkInner {
         self.create_connection(key).await
     }
 
+    /// key に新しく繋ぐ。connect_timeout のうちに繋がらなければ Timeout
     async fn create_connection(&self, key: &HostKey) -> Result<HttpSender, NetworkError> {
-        let addr = format!("{}:{}", key.host, key.port);
-        let stream = TcpStream::connect(addr)
-            .await
-            .map_err(|_| NetworkError::ConnectionFailed)?;
+        let permit = self.acquire_permit(key).await?;
+        tokio::time::timeout(
+            self.network_config.connect_timeout,
+            self.connect(key, permit),
+        )
+        .await
+        .map_err(|_| {
+            log::info!("NetworkCore: connecting to {} timed out", key.host);
+            NetworkError::Timeout
+        })?
+    }
+
+    /// 同時接続数の枠を取る。空かなければ connect_timeout まで待つ
+    async fn acquire_permit(&self, key: &HostKey) -> Result<ConnectionPermit, NetworkError> {
+        let config = &self.network_config;
+        let deadline = Instant::now() + config.connect_timeout;
+        loop {
+            if let Some(permit) = self.limiter.try_acquire(
+                key,
+                config.max_connections_per_host,
+                config.max_connections,
+            ) {
+                return Ok(permit);
+            }
+            // 使っていない別のホストへの接続を閉じて枠を空ける
+            self.sender_pool.write().unwrap().evict_idle(key);
+            if Instant::now() >= deadline {
+                log::info!("NetworkCore: too many connections to {}", key.host);
+                return Err(NetworkError::Timeout);
+            }
+            tokio::time::sleep(PERMIT_POLL_INTERVAL).await;
+        }
+    }
+
+    async fn connect(
+        &self,
+        key: &HostKey,
+        permit: ConnectionPermit,
+    ) -> Result<HttpSender, NetworkError> {
+        let stream: BoxedIo = match self.proxy_for(key) {
+            Some(proxy) => {
+                let tunnel = !proxy::forwards_plain_http(proxy, key.scheme.as_str());
+                proxy::connect(proxy, &self.tls_config, &key.host, key.port, tunnel).await?
+            }
+            None => Box::new(self.resolver.connect(&key.host, key.port).await?),
+        };
 
         if key.scheme == Scheme::HTTPS {
             let tls = TlsConnector::from(self.tls_config.clone());
@@ -214,39 +1094,199 @@ impl NetworkInner {
                 .connect(domain, stream)
                 .await
                 .map_err(|_| NetworkError::TlsFailed)?;
+            match TlsInfo::from_connection(stream.get_ref().1) {
+                Some(info) => {
+                    self.tls_info
+                        .borrow_mut()
+                        .insert(key.clone(), Arc::new(info));
+                }
+                None => {
+                    self.tls_info.borrow_mut().remove(&key);
+                }
+            }
+
+            // サーバーが ALPN で h2 を選んだら HTTP/2 で話す
+            if stream.get_ref().1.alpn_protocol() == Some(ALPN_H2) {
+                let (sender, conn) = conn::http2::handshake(LocalExecutor, TokioIo::new(stream))
+                    .await
+                    .map_err(|_| NetworkError::HttpHandshakeFailed)?;
+
+                log::info!("NetworkCore: HTTP/2 connection to {}", key.host);
+                self.spawn_connection_task(conn, key, permit);
+                return Ok(HttpSender::Http2(sender));
+            }
 
             let (sender, conn) = conn::http1::handshake(TokioIo::new(stream))
                 .await
                 .map_err(|_| NetworkError::HttpHandshakeFailed)?;
 
-            self.spawn_connection_task(conn, key);
+            self.spawn_connection_task(conn, key, permit);
             Ok(HttpSender::Http1(sender))
         } else {
             let (sender, conn) = conn::http1::handshake(TokioIo::new(stream))
                 .await
                 .map_err(|_| NetworkError::HttpHandshakeFailed)?;
 
-            self.spawn_connection_task(conn, key.clone());
+            self.spawn_connection_task(conn, key.clone(), permit);
             Ok(HttpSender::Http1(sender))
         }
     }
 
+    /// ヒントに従い、key の名前を引くか接続を張ってプールに入れておく
+    async fn warm(&self, key: &HostKey, hint: ConnectionHint) {
+        let result = match hint {
+            // プロキシを通すなら名前はプロキシが引く
+            ConnectionHint::DnsPrefetch if self.proxy_for(key).is_some() => Ok(()),
+            ConnectionHint::DnsPrefetch => tokio::time::timeout(
+                self.network_config.connect_timeout,
+                self.resolver.lookup(&key.host),
+            )
+            .await
+            .unwrap_or(Err(NetworkError::Timeout))
+            .map(|_| ()),
+            ConnectionHint::Preconnect => self.preconnect(key).await,
+        };
+        match result {
+            Ok(()) => log::debug!("NetworkCore: {:?} for {} done", hint, key.host),
+            Err(e) => log::info!("NetworkCore: {:?} for {} failed: {}", hint, key.host, e),
+        }
+    }
+
+    /// key への接続がプールになければ張って入れておく
+    ///
+    /// 同時接続数の枠が空いていなければ、ほかの接続を閉じてまでは張らない。
+    async fn preconnect(&self, key: &HostKey) -> Result<(), NetworkError> {
+        if self.sender_pool.read().unwrap().has_connection(key) {
+            return Ok(());
+        }
+        let config = &self.network_config;
+        let Some(permit) =
+            self.limiter
+                .try_acquire(key, config.max_connections_per_host, config.max_connections)
+        else {
+            return Ok(());
+        };
+        let sender = tokio::time::timeout(config.connect_timeout, self.connect(key, permit))
+            .await
+            .map_err(|_| NetworkError::Timeout)??;
+        self.sender_pool
+            .write()
+            .unwrap()
+            .add_connection(key.clone(), sender);
+        Ok(())
+    }
+
+    /// 接続を動かし、閉じたらプールから外して同時接続数の枠を返す
     fn spawn_connection_task(
         &self,
-        conn: conn::http1::Connection<
-            TokioIo<impl tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + 'static>,
-            Empty<Bytes>,
-        >,
+        conn: impl Future + 'static,
         key: HostKey,
+        permit: ConnectionPermit,
     ) {
         let pool = self.sender_pool.clone();
         tokio::task::spawn_local(async move {
             let _ = conn.await;
             pool.write().unwrap().remove_connection(&key);
+            drop(permit);
         });
     }
 }
 
+/// ヒントを進めながら、次のコマンドやヒントの終わりを確かめる間隔
+pub(super) const HINT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 同時接続数の枠が空いたかを確かめる間隔
+const PERMIT_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// ALPN のプロトコル名
+const ALPN_H2: &[u8] = b"h2";
+const ALPN_HTTP1: &[u8] = b"http/1.1";
+
+/// HTTP/2 の接続が使うタスクをネットワークスレッドの LocalSet で動かす
+#[derive(Clone, Copy)]
+struct LocalExecutor;
+
+impl<F> hyper::rt::Executor<F> for LocalExecutor
+where
+    F: Future + 'static,
+    F::Output: 'static,
+{
+    fn execute(&self, fut: F) {
+        tokio::task::spawn_local(fut);
+    }
+}
+
+/// キャッシュから答える（受信しながら読む fetch ならヘッダーを先に渡す）
+fn cached_response(
+    url: &str,
+    cached: CachedResponse,
+    on_progress: ProgressCallback<'_>,
+    streaming: Option<&Streaming<'_>>,
+) -> Response {
+    let length = cached.body.len() as u64;
+    on_progress(length, Some(length));
+    let mut resp = Response {
+        url: url.to_string(),
+        status: StatusCode::OK,
+        reason_phrase: "OK".to_string(),
+        headers: cached.headers,
+        body: cached.body,
+        tls: None,
+        stream: None,
+    };
+    if let Some(streaming) = streaming {
+        let stream = resp.body_stream();
+        (streaming.on_headers)(resp.with_stream(stream));
+    }
+    resp
+}
+
+/// 保存先のファイルを読む。ファイルがなければ None
+fn read_store_file(path: Option<&Path>, what: &str) -> Option<String> {
+    let path = path.filter(|path| path.exists())?;
+    match std::fs::read_to_string(path) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            log::error!("Failed to load {}: {:#}", what, e);
+            None
+        }
+    }
+}
+
+/// HSTS のホストへの http の URI を https に書き換える（80 番ポートの指定は外す）
+/// uri の接続先
+fn host_key(uri: &Uri) -> Result<HostKey, NetworkError> {
+    let host = uri.host().ok_or(NetworkError::MissingHost)?;
+    let scheme = uri.scheme().unwrap_or(&Scheme::HTTP);
+    let port = uri
+        .port_u16()
+        .unwrap_or(if scheme == &Scheme::HTTPS { 443 } else { 80 });
+    Ok(HostKey {
+        scheme: scheme.clone(),
+        host: host.to_string(),
+        port,
+    })
+}
+
+fn hsts_upgrade(uri: Uri, stores: &PartitionStores) -> Result<Uri, NetworkError> {
+    if uri.scheme() != Some(&Scheme::HTTP) {
+        return Ok(uri);
+    }
+    let host = uri.host().ok_or(NetworkError::MissingHost)?;
+    if !stores.hsts.should_upgrade(host, SystemTime::now()) {
+        return Ok(uri);
+    }
+    let port = uri
+        .port_u16()
+        .filter(|port| *port != 80)
+        .map(|port| format!(":{port}"))
+        .unwrap_or_default();
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+    format!("https://{host}{port}{path}")
+        .parse()
+        .map_err(|_| NetworkError::InvalidUri)
+}
+
 fn resolve_redirect(base: &Uri, location: &str) -> Result<Uri, NetworkError> {
     if location.starts_with("http://") || location.starts_with("https://") {
         return location.parse().map_err(|_| NetworkError::InvalidUri);
@@ -0,0 +1,226 @@
+//! Content-Encoding で圧縮されたレスポンスボディの展開
+//!
+//! リクエストには [`ACCEPT_ENCODING`] を付け、届いたボディは Content-Encoding に
+//! 書かれた順と逆に展開する。展開したあとは Content-Encoding と Content-Length を
+//! ヘッダーから取り除き、以降（キャッシュ、Cookie、呼び出し側）は展開済みの
+//! ボディとして扱う。
+//!
+//! 受信しながら読むボディ（[`super::stream::BodyStream`]）のために、届いた分から
+//! 少しずつ展開する [`BodyDecoder`] も用意する。
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use flate2::write::{DeflateDecoder, GzDecoder, ZlibDecoder};
+
+use super::NetworkError;
+
+/// リクエストの Accept-Encoding
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// brotli の展開に使うバッファの大きさ
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// headers の Content-Encoding に従って body を展開する
+///
+/// 知らない符号化が付いていたり展開に失敗したりしたら
+/// [`NetworkError::ContentDecodingFailed`]。
+pub fn decode_body(
+    headers: &mut Vec<(String, String)>,
+    body: Vec<u8>,
+) -> Result<Vec<u8>, NetworkError> {
+    // HEAD や 304 の応答はボディがなくても Content-Encoding が付いている
+    if body.is_empty() {
+        return Ok(body);
+    }
+    let Some(mut decoder) = BodyDecoder::from_headers(headers)? else {
+        return Ok(body);
+    };
+
+    let mut decoded = decoder.push(&body)?;
+    decoded.extend(decoder.finish()?);
+    Ok(decoded)
+}
+
+/// 届いた分から少しずつボディを展開する
+pub struct BodyDecoder {
+    /// 展開する順（Content-Encoding の逆順）
+    stages: Vec<Stage>,
+    /// まだ何も受け取っていない
+    empty: bool,
+}
+
+impl BodyDecoder {
+    /// headers の Content-Encoding の展開器。符号化されていなければ None
+    ///
+    /// 展開器を作ったときは Content-Encoding と Content-Length をヘッダーから取り除く。
+    pub fn from_headers(headers: &mut Vec<(String, String)>) -> Result<Option<Self>, NetworkError> {
+        let encodings: Vec<String> = headers
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case("content-encoding"))
+            .flat_map(|(_, v)| v.split(','))
+            .map(|e| e.trim().to_ascii_lowercase())
+            .filter(|e| !e.is_empty() && e != "identity")
+            .collect();
+        if encodings.is_empty() {
+            return Ok(None);
+        }
+
+        let stages: Vec<Stage> = encodings
+            .iter()
+            .rev()
+            .map(|encoding| Stage::new(encoding))
+            .collect::<Result<_, _>>()?;
+
+        headers.retain(|(k, _)| {
+            !k.eq_ignore_ascii_case("content-encoding") && !k.eq_ignore_ascii_case("content-length")
+        });
+        Ok(Some(Self {
+            stages,
+            empty: true,
+        }))
+    }
+
+    /// data を展開し、展開できた分を返す
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<u8>, NetworkError> {
+        if !data.is_empty() {
+            self.empty = false;
+        }
+        let mut data = data.to_vec();
+        for stage in &mut self.stages {
+            data = stage.push(&data)?;
+        }
+        Ok(data)
+    }
+
+    /// ボディの終わり。残りを返す（途中で切れていたらエラー）
+    pub fn finish(self) -> Result<Vec<u8>, NetworkError> {
+        if self.empty {
+            return Ok(Vec::new());
+        }
+        let mut data = Vec::new();
+        for stage in self.stages {
+            data = stage.push_and_finish(&data)?;
+        }
+        Ok(data)
+    }
+}
+
+/// 展開した結果を溜めておく書き込み先
+#[derive(Clone, Default)]
+struct Sink(Rc<RefCell<Vec<u8>>>);
+
+impl Sink {
+    fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut self.0.borrow_mut())
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Decoder {
+    Gzip(GzDecoder<Sink>),
+    Zlib(ZlibDecoder<Sink>),
+    Deflate(DeflateDecoder<Sink>),
+    Brotli(Box<brotli_decompressor::DecompressorWriter<Sink>>),
+    /// deflate が zlib 形式か生の deflate かを先頭の 2 バイトで決めるまで溜めておく
+    PendingDeflate(Vec<u8>),
+}
+
+/// 1 つの Content-Encoding の展開
+struct Stage {
+    encoding: String,
+    decoder: Decoder,
+    out: Sink,
+}
+
+impl Stage {
+    fn new(encoding: &str) -> Result<Self, NetworkError> {
+        let out = Sink::default();
+        let decoder = match encoding {
+            "gzip" | "x-gzip" => Decoder::Gzip(GzDecoder::new(out.clone())),
+            "deflate" => Decoder::PendingDeflate(Vec::new()),
+            "br" => Decoder::Brotli(Box::new(brotli_decompressor::DecompressorWriter::new(
+                out.clone(),
+                BROTLI_BUFFER_SIZE,
+            ))),
+            _ => {
+                log::warn!("Unsupported Content-Encoding: {}", encoding);
+                return Err(NetworkError::ContentDecodingFailed);
+            }
+        };
+        Ok(Self {
+            encoding: encoding.to_string(),
+            decoder,
+            out,
+        })
+    }
+
+    fn push(&mut self, data: &[u8]) -> Result<Vec<u8>, NetworkError> {
+        let result = match &mut self.decoder {
+            Decoder::Gzip(d) => d.write_all(data),
+            Decoder::Zlib(d) => d.write_all(data),
+            Decoder::Deflate(d) => d.write_all(data),
+            Decoder::Brotli(d) => d.write_all(data),
+            Decoder::PendingDeflate(pending) => {
+                pending.extend_from_slice(data);
+                if pending.len() < 2 {
+                    return Ok(Vec::new());
+                }
+                let pending = std::mem::take(pending);
+                // 本来は zlib 形式だが、生の deflate を送るサーバーもある
+                self.decoder = if is_zlib_header(pending[0], pending[1]) {
+                    Decoder::Zlib(ZlibDecoder::new(self.out.clone()))
+                } else {
+                    Decoder::Deflate(DeflateDecoder::new(self.out.clone()))
+                };
+                return self.push(&pending);
+            }
+        };
+        check(&self.encoding, result)?;
+        Ok(self.out.take())
+    }
+
+    fn push_and_finish(mut self, data: &[u8]) -> Result<Vec<u8>, NetworkError> {
+        let mut out = self.push(data)?;
+        let result = match self.decoder {
+            Decoder::Gzip(mut d) => d.try_finish(),
+            Decoder::Zlib(mut d) => d.try_finish(),
+            Decoder::Deflate(mut d) => d.try_finish(),
+            Decoder::Brotli(d) => (*d)
+                .into_inner()
+                .map(|_| ())
+                .map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated stream")),
+            // 2 バイトに満たない deflate は壊れている
+            Decoder::PendingDeflate(_) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated stream",
+            )),
+        };
+        check(&self.encoding, result)?;
+        out.extend(self.out.take());
+        Ok(out)
+    }
+}
+
+fn check(encoding: &str, result: io::Result<()>) -> Result<(), NetworkError> {
+    result.map_err(|e| {
+        log::warn!("Failed to decode {} response body: {}", encoding, e);
+        NetworkError::ContentDecodingFailed
+    })
+}
+
+/// RFC 1950 の zlib ヘッダー（圧縮方式が deflate で、チェックビットが合う）か
+fn is_zlib_header(cmf: u8, flg: u8) -> bool {
+    cmf & 0x0f == 8 && (u16::from(cmf) << 8 | u16::from(flg)) % 31 == 0
+}
@@ -0,0 +1,247 @@
+//! 名前解決と接続の競争（Happy Eyeballs）
+//!
+//! 名前解決は hickory-resolver で行い、結果はレコードの TTL（長くても
+//! [`MAX_TTL`]）の間キャッシュする。システムの設定が読めないときや hickory で
+//! 引けなかった名前（mDNS の `.local` など）は OS のリゾルバーに任せる。
+//!
+//! 接続は RFC 8305 にならい、IPv6 と IPv4 のアドレスを交互に並べて順に試す。
+//! 前の試行が [`CONNECTION_ATTEMPT_DELAY`] のうちに繋がらなければ次のアドレスにも
+//! 並行して繋ぎ始め、最初に繋がったものを使う。壊れた IPv6 の経路で長く
+//! 待たされないようにするため。
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use hickory_resolver::TokioAsyncResolver;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+
+use super::NetworkError;
+
+/// キャッシュする長さの上限
+pub const MAX_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// TTL のわからない（OS のリゾルバーで引いた）結果をキャッシュする長さ
+const FALLBACK_TTL: Duration = Duration::from_secs(60);
+
+/// キャッシュするホストの数
+const MAX_ENTRIES: usize = 1000;
+
+/// 次のアドレスに繋ぎ始めるまでの時間（RFC 8305 の推奨値）
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    addrs: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+/// ホスト名 → アドレスのキャッシュ
+#[derive(Debug, Clone, Default)]
+pub struct DnsCache {
+    entries: Arc<Mutex<HashMap<String, CacheEntry>>>,
+}
+
+impl DnsCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, host: &str, now: Instant) -> Option<Vec<IpAddr>> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(&host.to_ascii_lowercase())
+            .filter(|entry| now < entry.expires_at)
+            .map(|entry| entry.addrs.clone())
+    }
+
+    /// valid_until（レコードの TTL が切れる時刻）まで覚える。長くても MAX_TTL
+    pub fn insert(&self, host: &str, addrs: Vec<IpAddr>, valid_until: Instant, now: Instant) {
+        if addrs.is_empty() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, entry| now < entry.expires_at);
+            // それでも多ければいちばん早く切れるものを捨てる
+            if entries.len() >= MAX_ENTRIES
+                && let Some(oldest) = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires_at)
+                    .map(|(host, _)| host.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            host.to_ascii_lowercase(),
+            CacheEntry {
+                addrs,
+                expires_at: valid_until.min(now + MAX_TTL),
+            },
+        );
+    }
+
+    /// 繋がらなかったホストを忘れる（次は引き直す）
+    pub fn remove(&self, host: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&host.to_ascii_lowercase());
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// キャッシュ付きのリゾルバー
+pub(super) struct Resolver {
+    /// 最初に使うときに作る（システムの設定が読めなければ None のまま）
+    backend: RefCell<Option<Option<TokioAsyncResolver>>>,
+    cache: DnsCache,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            backend: RefCell::new(None),
+            cache: DnsCache::new(),
+        }
+    }
+
+    fn backend(&self) -> Option<TokioAsyncResolver> {
+        self.backend
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                match hickory_resolver::system_conf::read_system_conf() {
+                    Ok((config, mut options)) => {
+                        // キャッシュはこちらで持つ
+                        options.cache_size = 0;
+                        Some(TokioAsyncResolver::tokio(config, options))
+                    }
+                    Err(e) => {
+                        log::warn!("Using the system resolver: {}", e);
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+
+    /// host を引く。IP アドレスならそのまま返す
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, NetworkError> {
+        let host = host.trim_matches(['[', ']']);
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![ip]);
+        }
+        let now = Instant::now();
+        if let Some(addrs) = self.cache.get(host, now) {
+            return Ok(addrs);
+        }
+
+        if let Some(resolver) = self.backend() {
+            match resolver.lookup_ip(host).await {
+                Ok(lookup) => {
+                    let addrs: Vec<IpAddr> = lookup.iter().collect();
+                    self.cache
+                        .insert(host, addrs.clone(), lookup.valid_until(), now);
+                    if !addrs.is_empty() {
+                        return Ok(addrs);
+                    }
+                }
+                Err(e) => log::debug!("hickory could not resolve {}: {}", host, e),
+            }
+        }
+
+        let addrs: Vec<IpAddr> = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|_| NetworkError::ConnectionFailed)?
+            .map(|addr| addr.ip())
+            .collect();
+        if addrs.is_empty() {
+            return Err(NetworkError::ConnectionFailed);
+        }
+        self.cache
+            .insert(host, addrs.clone(), now + FALLBACK_TTL, now);
+        Ok(addrs)
+    }
+
+    /// host:port に TCP で繋ぐ
+    pub async fn connect(&self, host: &str, port: u16) -> Result<TcpStream, NetworkError> {
+        let addrs = self.lookup(host).await?;
+        let addrs = addrs
+            .into_iter()
+            .map(|ip| SocketAddr::new(ip, port))
+            .collect();
+        match happy_eyeballs(addrs, CONNECTION_ATTEMPT_DELAY).await {
+            Ok(stream) => Ok(stream),
+            Err(e) => {
+                log::info!("NetworkCore: could not connect to {}: {}", host, e);
+                // アドレスが変わったのかもしれないので次は引き直す
+                self.cache.remove(host);
+                Err(NetworkError::ConnectionFailed)
+            }
+        }
+    }
+
+    pub fn clear_cache(&self) {
+        self.cache.clear();
+    }
+}
+
+/// IPv6 と IPv4 を交互に並べる（最初の 1 つの種類から始める）
+pub fn interleave(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let first_is_v6 = addrs.first().is_some_and(SocketAddr::is_ipv6);
+    let (mut preferred, mut other): (VecDeque<_>, VecDeque<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_v6);
+
+    let mut sorted = Vec::with_capacity(preferred.len() + other.len());
+    loop {
+        match (preferred.pop_front(), other.pop_front()) {
+            (None, None) => return sorted,
+            (a, b) => sorted.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// addrs に順に繋ぎ、前の試行が delay のうちに終わらなければ次も並行して始める
+///
+/// 最初に繋がった接続を返し、残りの試行は取り消す。
+pub async fn happy_eyeballs(addrs: Vec<SocketAddr>, delay: Duration) -> io::Result<TcpStream> {
+    let mut pending: VecDeque<SocketAddr> = interleave(addrs).into();
+    let mut attempts = JoinSet::new();
+    let mut last_error = None;
+
+    loop {
+        if let Some(addr) = pending.pop_front() {
+            attempts.spawn(TcpStream::connect(addr));
+        }
+
+        let finished = if pending.is_empty() {
+            attempts.join_next().await
+        } else {
+            match tokio::time::timeout(delay, attempts.join_next()).await {
+                Ok(finished) => finished,
+                // まだ繋がらないので次のアドレスにも繋ぎ始める
+                Err(_) => continue,
+            }
+        };
+
+        match finished {
+            Some(Ok(Ok(stream))) => return Ok(stream),
+            Some(Ok(Err(e))) => last_error = Some(e),
+            Some(Err(e)) => last_error = Some(io::Error::other(e)),
+            None => {
+                return Err(last_error.unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+                }));
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! 途中から再開できるダウンロード
+//!
+//! 受信中の内容は `<保存先>.part` に書き、検証子（強い ETag、なければ
+//! Last-Modified）を `<保存先>.part.meta` に残す。接続が切れたら `Range` と
+//! `If-Range` を付けて続きを頼み、サーバーが同じ ETag の続きを 206 で返したときだけ
+//! 追記する。200 が返ったら（ファイルが変わったか、Range に対応していない）
+//! 最初から書き直す。受信し終えたら `.part` を保存先に移す。
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use hyper::StatusCode;
+
+/// 接続が切れたときに続きから取り直す回数
+pub const RETRIES: u32 = 5;
+
+/// 最初に取り直すまでの待ち時間（取り直すたびに倍にする）
+pub const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// 受信中の内容を書くファイル
+pub fn part_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".part")
+}
+
+fn meta_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".part.meta")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// 前回までに受信した分
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartialDownload {
+    pub received: u64,
+    /// If-Range に渡す検証子
+    pub validator: Option<String>,
+}
+
+/// レスポンスを受けてどう書くか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resume {
+    /// 最初から書き直す
+    Restart,
+    /// 受信した分の後ろに続ける
+    Append,
+    /// もう全部受信している
+    Complete,
+}
+
+impl PartialDownload {
+    /// path に url をダウンロードしかけていれば、その受信済みの長さと検証子
+    ///
+    /// 検証子がなければ続きが同じファイルか確かめられないので、最初から取り直す。
+    pub fn load(path: &Path, url: &str) -> Self {
+        let Ok(meta) = fs::read_to_string(meta_path(path)) else {
+            return Self::default();
+        };
+        let validator = meta.lines().find_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[..] {
+                ["download", meta_url, validator] if meta_url == url && !validator.is_empty() => {
+                    Some(validator.to_string())
+                }
+                _ => None,
+            }
+        });
+        let received = fs::metadata(part_path(path)).map_or(0, |m| m.len());
+        match validator {
+            Some(validator) if received > 0 => Self {
+                received,
+                validator: Some(validator),
+            },
+            _ => Self::default(),
+        }
+    }
+
+    /// リクエストに付けるヘッダー
+    ///
+    /// 範囲は圧縮前のバイト列で数えたいので、圧縮しないよう頼む。
+    pub fn request_headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![("Accept-Encoding", "identity".to_string())];
+        if let Some(validator) = &self.validator {
+            headers.push(("Range", format!("bytes={}-", self.received)));
+            headers.push(("If-Range", validator.clone()));
+        }
+        headers
+    }
+
+    /// レスポンスを受けて、受信済みの分に続けるかを決める
+    ///
+    /// ボディを書けない応答（エラーや、頼んだのと違う範囲）なら None。
+    pub fn resume_mode(&self, status: StatusCode, headers: &[(String, String)]) -> Option<Resume> {
+        match status {
+            StatusCode::OK => Some(Resume::Restart),
+            StatusCode::PARTIAL_CONTENT => {
+                // 頼んだ位置から始まり、ETag が変わっていないときだけ続ける
+                let validator = self.validator.as_deref()?;
+                let start = header(headers, "content-range").and_then(content_range_start);
+                let same_entity = match header(headers, "etag") {
+                    Some(etag) if is_etag(validator) => validator == etag.trim(),
+                    _ => true,
+                };
+                (start == Some(self.received) && same_entity).then_some(Resume::Append)
+            }
+            // 受信済みの長さがファイル全体の長さと同じ
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                let total = header(headers, "content-range")
+                    .and_then(|v| v.trim().strip_prefix("bytes */"))
+                    .and_then(|len| len.trim().parse::<u64>().ok());
+                (self.validator.is_some() && total == Some(self.received))
+                    .then_some(Resume::Complete)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// `bytes 100-199/200` の 100
+fn content_range_start(value: &str) -> Option<u64> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (start, _) = range.split_once('-')?;
+    start.trim().parse().ok()
+}
+
+fn is_etag(validator: &str) -> bool {
+    validator.starts_with('"')
+}
+
+/// 次に続きを頼むときに使う検証子（強い ETag、なければ Last-Modified）
+///
+/// 弱い ETag は If-Range に使えない。圧縮して送られたボディは範囲が合わないので
+/// 続きを頼まない。
+pub fn validator(headers: &[(String, String)]) -> Option<String> {
+    if header(headers, "content-encoding")
+        .is_some_and(|v| !v.trim().eq_ignore_ascii_case("identity"))
+    {
+        return None;
+    }
+    header(headers, "etag")
+        .map(str::trim)
+        .filter(|etag| is_etag(etag))
+        .or_else(|| header(headers, "last-modified").map(str::trim))
+        .map(str::to_string)
+}
+
+/// レスポンスのボディを書くファイルを開く。Complete なら None
+///
+/// 次に続きから頼めるよう、検証子も書いておく。
+pub fn open_part(
+    path: &Path,
+    url: &str,
+    mode: Resume,
+    headers: &[(String, String)],
+) -> std::io::Result<Option<File>> {
+    let part = part_path(path);
+    let file = match mode {
+        Resume::Complete => return Ok(None),
+        Resume::Append => OpenOptions::new().append(true).open(&part)?,
+        Resume::Restart => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            File::create(&part)?
+        }
+    };
+
+    if mode == Resume::Restart {
+        match validator(headers) {
+            Some(validator) => {
+                fs::write(meta_path(path), format!("download\t{url}\t{validator}\n"))?
+            }
+            // 続きを頼めないので、切れたら最初から
+            None => {
+                let _ = fs::remove_file(meta_path(path));
+            }
+        }
+    }
+    Ok(Some(file))
+}
+
+/// 受信し終えた `.part` を保存先に移す
+pub fn finish(path: &Path) -> std::io::Result<()> {
+    fs::rename(part_path(path), path)?;
+    let _ = fs::remove_file(meta_path(path));
+    Ok(())
+}
+
+/// 受信した分を捨てる（次は最初から取り直す）
+pub fn discard(path: &Path) {
+    let _ = fs::remove_file(part_path(path));
+    let _ = fs::remove_file(meta_path(path));
+}
@@ -9,6 +9,7 @@ pub enum NetworkError {
     ConnectionFailed,
     TlsFailed,
     Timeout,
+    ProxyFailed,
 
     // HTTP
     HttpHandshakeFailed,
@@ -16,6 +17,10 @@ pub enum NetworkError {
     HttpResponseFailed,
     TooManyRedirects,
     UnsupportedHttpVersion,
+    ContentDecodingFailed,
+
+    // Downloads
+    FileWriteFailed,
 
     // Infrastructure
     Disconnected,
@@ -32,12 +37,16 @@ impl std::fmt::Display for NetworkError {
             ConnectionFailed => "connection failed",
             TlsFailed => "TLS handshake failed",
             Timeout => "network timeout",
+            ProxyFailed => "proxy connection failed",
 
             HttpHandshakeFailed => "HTTP handshake failed",
             HttpRequestFailed => "HTTP request failed",
             HttpResponseFailed => "HTTP response failed",
             TooManyRedirects => "too many redirects",
             UnsupportedHttpVersion => "unsupported HTTP version",
+            ContentDecodingFailed => "failed to decode the response body",
+
+            FileWriteFailed => "failed to write the downloaded file",
 
             Disconnected => "network subsystem disconnected",
         };
@@ -46,3 +55,18 @@ impl std::fmt::Display for NetworkError {
 }
 
 impl std::error::Error for NetworkError {}
+
+impl NetworkError {
+    /// 同じリクエストを送り直せば直るかもしれないエラーか
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::ConnectionFailed
+                | Self::Timeout
+                | Self::HttpHandshakeFailed
+                | Self::HttpRequestFailed
+                | Self::HttpResponseFailed
+                | Self::Disconnected
+        )
+    }
+}
@@ -0,0 +1,69 @@
+//! 接続のヒント（`<link rel="dns-prefetch">` と `<link rel="preconnect">`）
+//!
+//! ページが近いうちに使うと知らせてきたオリジンについて、リクエストを出す前に
+//! 名前を引いたり接続を張ってプールに入れたりしておく。ヒントはネットワーク
+//! スレッドの裏で進め、同時に進めるのは [`MAX_HINTS_IN_FLIGHT`] 個まで。枠が
+//! 空いていなければそのヒントは捨てる（ヒントなので従わなくても結果は変わらない）。
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use super::HostKey;
+
+/// 同時に進めるヒントの数
+pub const MAX_HINTS_IN_FLIGHT: usize = 4;
+
+/// ヒントの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionHint {
+    /// 名前だけを引いておく
+    DnsPrefetch,
+    /// 名前を引き、TCP と TLS の接続まで張っておく
+    Preconnect,
+}
+
+/// 進めているヒントの数を数え、枠を超えて始めないようにする
+///
+/// ヒントを始めるときに [`HintPermit`] を取り、終わったら捨てる。
+#[derive(Debug, Clone, Default)]
+pub struct HintBudget {
+    in_flight: Arc<Mutex<HashSet<HostKey>>>,
+}
+
+/// 進めているヒント 1 つ分の枠。捨てると空く
+#[derive(Debug)]
+pub struct HintPermit {
+    budget: HintBudget,
+    key: HostKey,
+}
+
+impl HintBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// key へのヒントを始めてよければ枠を取る
+    ///
+    /// 同じオリジンのヒントを進めている途中か、枠が埋まっていれば None。
+    pub fn try_start(&self, key: &HostKey) -> Option<HintPermit> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.len() >= MAX_HINTS_IN_FLIGHT || !in_flight.insert(key.clone()) {
+            return None;
+        }
+        Some(HintPermit {
+            budget: self.clone(),
+            key: key.clone(),
+        })
+    }
+
+    /// 進めているヒントの数
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+}
+
+impl Drop for HintPermit {
+    fn drop(&mut self) {
+        self.budget.in_flight.lock().unwrap().remove(&self.key);
+    }
+}
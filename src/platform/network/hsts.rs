@@ -0,0 +1,189 @@
+//! HSTS（RFC 6797）
+//!
+//! https の応答の `Strict-Transport-Security` を覚えておき、そのホストへの http の
+//! リクエストを送る前に https に書き換える。覚えたホストは Cookie と同じく
+//! プロファイルに書き、次に起動したときに読み戻す。
+//!
+//! 一度も https で開いていないホストは守れないので、https しか使えない TLD
+//! （`.dev` など）は最初から登録しておく。
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 最初から https を使うドメイン（サブドメインを含む）
+const PRELOADED: &[&str] = &["app", "dev", "page", "new", "day", "foo", "mov", "zip"];
+
+/// 期限がこれ以上ずれたら保存し直す
+const REWRITE_THRESHOLD: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HstsEntry {
+    expires: SystemTime,
+    include_subdomains: bool,
+}
+
+#[derive(Debug, Default)]
+struct HstsState {
+    /// ホスト → 期限
+    hosts: HashMap<String, HstsEntry>,
+    modified: bool,
+}
+
+/// https で開くホストの一覧
+#[derive(Debug, Clone, Default)]
+pub struct HstsStore {
+    state: Arc<RwLock<HstsState>>,
+}
+
+/// Strict-Transport-Security の値を読む。(max-age, includeSubDomains)
+pub fn parse(value: &str) -> Option<(Duration, bool)> {
+    let mut max_age = None;
+    let mut include_subdomains = false;
+    for directive in value.split(';').map(str::trim) {
+        let (name, value) = match directive.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (directive, None),
+        };
+        if name.eq_ignore_ascii_case("max-age") {
+            // max-age が 2 つあるか読めなければ全体を無視する
+            if max_age.is_some() {
+                return None;
+            }
+            max_age = Some(Duration::from_secs(value?.parse().ok()?));
+        } else if name.eq_ignore_ascii_case("includesubdomains") {
+            include_subdomains = true;
+        }
+    }
+    Some((max_age?, include_subdomains))
+}
+
+impl HstsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// https の応答に付いていた Strict-Transport-Security を覚える
+    ///
+    /// max-age=0 なら忘れる。IP アドレスのホストは覚えない。
+    pub fn record(&self, host: &str, value: &str, now: SystemTime) {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if host.parse::<IpAddr>().is_ok() || host.starts_with('[') {
+            return;
+        }
+        let Some((max_age, include_subdomains)) = parse(value) else {
+            return;
+        };
+
+        let mut state = self.state.write().unwrap();
+        if max_age.is_zero() {
+            state.modified |= state.hosts.remove(&host).is_some();
+            return;
+        }
+        let entry = HstsEntry {
+            expires: now + max_age,
+            include_subdomains,
+        };
+        // 応答のたびに書き直さないよう、期限が少し延びただけなら書かない
+        let changed = state.hosts.get(&host).is_none_or(|old| {
+            let shift = entry
+                .expires
+                .duration_since(old.expires)
+                .or_else(|_| old.expires.duration_since(entry.expires))
+                .unwrap_or_default();
+            old.include_subdomains != include_subdomains || shift > REWRITE_THRESHOLD
+        });
+        state.modified |= changed;
+        state.hosts.insert(host, entry);
+    }
+
+    /// host への http のリクエストを https に書き換えるか
+    pub fn should_upgrade(&self, host: &str, now: SystemTime) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if host.parse::<IpAddr>().is_ok() || host.starts_with('[') {
+            return false;
+        }
+        let state = self.state.read().unwrap();
+
+        let mut domain = host.as_str();
+        let mut is_self = true;
+        loop {
+            if PRELOADED.contains(&domain) {
+                return true;
+            }
+            if let Some(entry) = state.hosts.get(domain)
+                && now < entry.expires
+                && (is_self || entry.include_subdomains)
+            {
+                return true;
+            }
+            let Some((_, parent)) = domain.split_once('.') else {
+                return false;
+            };
+            domain = parent;
+            is_self = false;
+        }
+    }
+
+    /// 覚えているホストをすべて忘れる
+    pub fn clear(&self) {
+        let mut state = self.state.write().unwrap();
+        state.hosts.clear();
+        state.modified = true;
+    }
+
+    /// 前に呼んでから覚えているホストが変わったか
+    pub fn take_modified(&self) -> bool {
+        std::mem::take(&mut self.state.write().unwrap().modified)
+    }
+
+    /// 保存用の文字列にする。1 行に 1 つ、`hsts` ホスト 期限 includeSubDomains
+    pub fn serialize(&self, now: SystemTime) -> String {
+        let state = self.state.read().unwrap();
+        let mut out = String::new();
+        for (host, entry) in &state.hosts {
+            if entry.expires <= now {
+                continue;
+            }
+            let expires = entry
+                .expires
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            out.push_str(&format!(
+                "hsts\t{}\t{}\t{}\n",
+                host,
+                expires,
+                if entry.include_subdomains { "1" } else { "0" }
+            ));
+        }
+        out
+    }
+
+    /// serialize した文字列から読み込む。期限の切れたものと読めない行は飛ばす
+    pub fn load(&self, text: &str, now: SystemTime) {
+        let mut state = self.state.write().unwrap();
+        for line in text.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            let ["hsts", host, expires, include_subdomains] = fields[..] else {
+                continue;
+            };
+            let Ok(expires) = expires.parse::<u64>() else {
+                log::warn!("Skipping invalid HSTS entry: {:?}", line);
+                continue;
+            };
+            let expires = UNIX_EPOCH + Duration::from_secs(expires);
+            if expires <= now {
+                continue;
+            }
+            state.hosts.insert(
+                host.to_string(),
+                HstsEntry {
+                    expires,
+                    include_subdomains: include_subdomains == "1",
+                },
+            );
+        }
+        state.modified = false;
+    }
+}
@@ -0,0 +1,184 @@
+//! HTTP/3（QUIC）のトランスポート（feature `http3`）
+//!
+//! quinn で QUIC の接続を張り、h3 でリクエストを送る。接続はオリジンごとに 1 本を
+//! 多重化して使い回す。どのオリジンに HTTP/3 を使うかは [`super::alt_svc`] が決め、
+//! ここで失敗したら呼び出し側が HTTP/2・HTTP/1 でやり直す。
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::future::poll_fn;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::body::{Buf, Bytes};
+use hyper::{HeaderMap, Method, Request, StatusCode, Uri};
+use rustls::ClientConfig;
+
+use super::NetworkError;
+use super::core::ResponseBody;
+use super::tls_info::{self, TlsInfo};
+
+/// QUIC の接続を張るのを諦めるまでの時間（UDP が塞がれていることがある）
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+const ALPN_H3: &[u8] = b"h3";
+
+type SendRequest = h3::client::SendRequest<h3_quinn::OpenStreams, Bytes>;
+type RequestStream = h3::client::RequestStream<h3_quinn::BidiStream<Bytes>, Bytes>;
+
+/// HTTP/3 の応答のボディ
+pub(super) struct Http3Body {
+    stream: RequestStream,
+}
+
+impl ResponseBody for Http3Body {
+    async fn next_chunk(&mut self) -> Option<Result<Bytes, NetworkError>> {
+        match self.stream.recv_data().await {
+            Ok(Some(mut data)) => Some(Ok(data.copy_to_bytes(data.remaining()))),
+            Ok(None) => None,
+            Err(_) => Some(Err(NetworkError::HttpResponseFailed)),
+        }
+    }
+}
+
+pub(super) struct Http3Client {
+    crypto: Arc<quinn::crypto::rustls::QuicClientConfig>,
+    /// 最初に使うときに作る（tokio のランタイムの中でしか作れない）
+    endpoint: RefCell<Option<quinn::Endpoint>>,
+    /// (ホスト, ポート) → 接続と、その TLS の情報
+    connections: RefCell<HashMap<(String, u16), (SendRequest, Option<Arc<TlsInfo>>)>>,
+}
+
+impl Http3Client {
+    /// tls は TCP の接続と同じルート証明書の設定（ALPN だけ h3 に差し替える）
+    pub fn new(tls: &ClientConfig) -> Option<Self> {
+        let mut tls = tls.clone();
+        tls.alpn_protocols = vec![ALPN_H3.to_vec()];
+        let crypto = match quinn::crypto::rustls::QuicClientConfig::try_from(tls) {
+            Ok(crypto) => crypto,
+            Err(e) => {
+                log::warn!("HTTP/3 is unavailable: {}", e);
+                return None;
+            }
+        };
+        Some(Self {
+            crypto: Arc::new(crypto),
+            endpoint: RefCell::new(None),
+            connections: RefCell::new(HashMap::new()),
+        })
+    }
+
+    /// uri へのリクエストを (host, port) の HTTP/3 のエンドポイントに送る
+    ///
+    /// 応答のヘッダーまで受け取れたら返す。ボディは返した Http3Body から読む。
+    pub async fn send(
+        &self,
+        uri: &Uri,
+        (host, port): (&str, u16),
+        headers: &[(&str, String)],
+    ) -> Result<(StatusCode, HeaderMap, Option<Arc<TlsInfo>>, Http3Body), NetworkError> {
+        let key = (host.to_string(), port);
+        let cached = self.connections.borrow().get(&key).cloned();
+        let (mut sender, tls) = match cached {
+            Some(connection) => connection,
+            None => self.connect(host, port).await?,
+        };
+
+        let mut req = Request::builder().method(Method::GET).uri(uri.clone());
+        for (name, value) in headers {
+            req = req.header(*name, value.as_str());
+        }
+        let req = req.body(()).map_err(|_| NetworkError::HttpRequestFailed)?;
+
+        let result = async {
+            let mut stream = sender.send_request(req).await.ok()?;
+            stream.finish().await.ok()?;
+            let response = stream.recv_response().await.ok()?;
+            Some((response, stream))
+        }
+        .await;
+        let Some((response, stream)) = result else {
+            // 閉じた接続は次から使わない
+            self.connections.borrow_mut().remove(&key);
+            return Err(NetworkError::HttpRequestFailed);
+        };
+
+        self.connections
+            .borrow_mut()
+            .insert(key, (sender, tls.clone()));
+        let (parts, ()) = response.into_parts();
+        Ok((parts.status, parts.headers, tls, Http3Body { stream }))
+    }
+
+    async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+    ) -> Result<(SendRequest, Option<Arc<TlsInfo>>), NetworkError> {
+        let addr: SocketAddr = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|_| NetworkError::ConnectionFailed)?
+            // エンドポイントは IPv4 で開いている
+            .find(SocketAddr::is_ipv4)
+            .ok_or(NetworkError::ConnectionFailed)?;
+
+        let endpoint = self.endpoint()?;
+        let config = quinn::ClientConfig::new(self.crypto.clone());
+        let connecting = endpoint
+            .connect_with(config, addr, host)
+            .map_err(|_| NetworkError::ConnectionFailed)?;
+        let connection = tokio::time::timeout(CONNECT_TIMEOUT, connecting)
+            .await
+            .map_err(|_| NetworkError::Timeout)?
+            .map_err(|_| NetworkError::TlsFailed)?;
+
+        // QUIC はいつも TLS 1.3。暗号スイートは quinn から取り出せない
+        let tls = connection
+            .peer_identity()
+            .and_then(|identity| {
+                identity
+                    .downcast::<Vec<rustls::pki_types::CertificateDer<'static>>>()
+                    .ok()
+            })
+            .map(|chain| {
+                Arc::new(TlsInfo {
+                    version: "TLS 1.3".to_string(),
+                    cipher_suite: None,
+                    alpn: Some("h3".to_string()),
+                    certificates: tls_info::certificates(&chain),
+                })
+            });
+
+        let (mut driver, sender) = h3::client::new(h3_quinn::Connection::new(connection))
+            .await
+            .map_err(|_| NetworkError::HttpHandshakeFailed)?;
+
+        let key = (host.to_string(), port);
+        log::info!("NetworkCore: HTTP/3 connection to {}:{}", host, port);
+        tokio::task::spawn_local(async move {
+            let _ = poll_fn(|cx| driver.poll_close(cx)).await;
+            log::info!(
+                "NetworkCore: HTTP/3 connection to {}:{} closed",
+                key.0,
+                key.1
+            );
+        });
+
+        self.connections
+            .borrow_mut()
+            .insert((host.to_string(), port), (sender.clone(), tls.clone()));
+        Ok((sender, tls))
+    }
+
+    fn endpoint(&self) -> Result<quinn::Endpoint, NetworkError> {
+        let mut endpoint = self.endpoint.borrow_mut();
+        if let Some(endpoint) = endpoint.as_ref() {
+            return Ok(endpoint.clone());
+        }
+        let created = quinn::Endpoint::client(SocketAddr::from(([0, 0, 0, 0], 0)))
+            .map_err(|_| NetworkError::ConnectionFailed)?;
+        *endpoint = Some(created.clone());
+        Ok(created)
+    }
+}
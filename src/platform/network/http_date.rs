@@ -0,0 +1,97 @@
+//! HTTP の日付（RFC 9110 の HTTP-date）
+//!
+//! 送るときは IMF-fixdate（`Sun, 06 Nov 1994 08:49:37 GMT`）で書き、読むときは
+//! 古い RFC 850 形式（`Sunday, 06-Nov-94 08:49:37 GMT`）と asctime 形式
+//! （`Sun Nov  6 08:49:37 1994`）も受け付ける。
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+
+/// HTTP-date を読む。読めなければ None
+pub fn parse(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    // 曜日は読み飛ばす
+    let (_, rest) = value.split_once([',', ' '])?;
+    let fields: Vec<&str> = rest.split([' ', '-']).filter(|f| !f.is_empty()).collect();
+
+    let (day, month, year, time) = match fields.as_slice() {
+        // IMF-fixdate / RFC 850: 06 Nov 1994 08:49:37 GMT
+        [day, month, year, time, zone] if zone.eq_ignore_ascii_case("GMT") => {
+            (*day, *month, *year, *time)
+        }
+        // asctime: Nov 6 08:49:37 1994
+        [month, day, time, year] => (*day, *month, *year, *time),
+        _ => return None,
+    };
+
+    let day: u32 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? as u32 + 1;
+    let mut year: i64 = year.parse().ok()?;
+    // RFC 850 の 2 桁の年（70 未満は 2000 年代とみなす）
+    if year < 100 {
+        year += if year < 70 { 2000 } else { 1900 };
+    }
+
+    let mut hms = time.split(':').map(|f| f.parse::<u64>().ok());
+    let (hour, minute, second) = (hms.next()??, hms.next()??, hms.next()??);
+    if hms.next().is_some() || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    let secs = days * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+    if secs < 0 {
+        return Some(UNIX_EPOCH);
+    }
+    Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+}
+
+/// IMF-fixdate で書く
+pub fn format(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[days.rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// 1970-01-01 からの日数（proleptic グレゴリオ暦）
+pub(super) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -1,28 +1,82 @@
+pub mod alt_svc;
 pub mod cache;
+pub mod cancel;
 pub mod config;
 pub mod cookie_store;
 mod core;
+pub mod decode;
+pub mod dns;
+pub mod download;
 pub mod error;
+pub mod hints;
+pub mod hsts;
+#[cfg(feature = "http3")]
+mod http3;
+pub mod http_date;
+pub mod partition;
+mod proxy;
 pub mod sender_pool;
+pub mod site;
+pub mod stream;
+pub mod tls_info;
 
 // 外部公開用
 pub use cache::Cache;
+pub use cancel::CancellationToken;
 pub use config::NetworkConfig;
-pub use cookie_store::CookieStore;
+pub use cookie_store::{Cookie, CookieStore, SameSite};
 pub use core::Response;
 pub use error::NetworkError;
+pub use hints::ConnectionHint;
 pub use hyper::http::{Request, StatusCode};
+pub use partition::StoragePartition;
 pub use sender_pool::HostKey;
 pub use sender_pool::{HttpSender, SenderPool};
+pub use stream::BodyStream;
+pub use tls_info::TlsInfo;
 
-use core::AsyncNetworkCore;
+use core::{AsyncNetworkCore, HINT_POLL_INTERVAL};
 
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::thread;
+use url::Url;
 
 pub enum NetworkCommand {
-    Fetch { url: String, msg_id: usize },
+    Fetch {
+        url: String,
+        msg_id: usize,
+        /// キャッシュを使わずに取り直す（強制再読み込み）
+        bypass_cache: bool,
+        /// Cookie とキャッシュの保存先
+        partition: StoragePartition,
+        /// リクエストを出した文書の URL（トップレベルのページを開くなら None）
+        site_for_cookies: Option<Url>,
+        /// 既定のものに加えて送るヘッダー（CORS の `Origin` など）
+        request_headers: Vec<(&'static str, String)>,
+        token: CancellationToken,
+        /// ヘッダーが届いた時点で結果を返し、ボディは BodyStream に流す
+        stream: bool,
+    },
+    /// url のボディを path に書く。途中まで受信していれば続きから受け取る
+    Download {
+        url: String,
+        path: PathBuf,
+        msg_id: usize,
+        partition: StoragePartition,
+        token: CancellationToken,
+    },
+    /// url のオリジンへの名前解決や接続を裏で済ませておく
+    Hint {
+        url: String,
+        hint: ConnectionHint,
+        partition: StoragePartition,
+    },
     SetConfig(NetworkConfig),
+    /// 保存先の Cookie とキャッシュを捨てる
+    ClearPartition(StoragePartition),
 }
 
 pub struct NetworkMessage {
@@ -30,9 +84,22 @@ pub struct NetworkMessage {
     pub response: Result<Response, NetworkError>,
 }
 
+/// 受信中のレスポンスの進み具合
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkProgress {
+    pub msg_id: usize,
+    /// 受信したボディのバイト数
+    pub received: u64,
+    /// Content-Length（わからなければ None）
+    pub total: Option<u64>,
+}
+
 pub struct NetworkCore {
     cmd_tx: Sender<NetworkCommand>,
     msg_rx: Receiver<NetworkMessage>, // UI スレッド用
+    progress_rx: Receiver<NetworkProgress>,
+    /// 完了していないリクエストの取り消しフラグ
+    tokens: RefCell<HashMap<usize, CancellationToken>>,
 }
 
 impl Default for NetworkCore {
@@ -45,10 +112,16 @@ impl NetworkCore {
     pub fn new() -> Self {
         let (cmd_tx, cmd_rx) = mpsc::channel();
         let (msg_tx, msg_rx) = mpsc::channel();
+        let (progress_tx, progress_rx) = mpsc::channel();
 
-        thread::spawn(move || spawn_network_thread(cmd_rx, msg_tx));
+        thread::spawn(move || spawn_network_thread(cmd_rx, msg_tx, progress_tx));
 
-        Self { cmd_tx, msg_rx }
+        Self {
+            cmd_tx,
+            msg_rx,
+            progress_rx,
+            tokens: RefCell::new(HashMap::new()),
+        }
     }
 
     pub fn set_network_config(&self, cfg: NetworkConfig) {
@@ -56,8 +129,138 @@ impl NetworkCore {
     }
 
     /// 非同期送信のみ。結果は try_receive で取得
-    pub fn fetch_async(&self, url: String, msg_id: usize) {
-        let _ = self.cmd_tx.send(NetworkCommand::Fetch { url, msg_id });
+    ///
+    /// site_for_cookies はサブリソースならそれを読み込む文書の URL、トップレベルの
+    /// ページを開くなら None。別のサイトへのサブリソースのリクエストには
+    /// SameSite=None の Cookie だけを付ける。
+    pub fn fetch_async(
+        &self,
+        url: String,
+        msg_id: usize,
+        bypass_cache: bool,
+        partition: StoragePartition,
+        site_for_cookies: Option<Url>,
+    ) {
+        self.send_fetch(
+            url,
+            msg_id,
+            bypass_cache,
+            partition,
+            site_for_cookies,
+            Vec::new(),
+            false,
+        );
+    }
+
+    /// request_headers を付けて送る fetch_async
+    pub fn fetch_with_headers(
+        &self,
+        url: String,
+        msg_id: usize,
+        bypass_cache: bool,
+        partition: StoragePartition,
+        site_for_cookies: Option<Url>,
+        request_headers: Vec<(&'static str, String)>,
+    ) {
+        self.send_fetch(
+            url,
+            msg_id,
+            bypass_cache,
+            partition,
+            site_for_cookies,
+            request_headers,
+            false,
+        );
+    }
+
+    /// ボディを受信しながら読む fetch
+    ///
+    /// ヘッダーが届いた時点で try_receive に結果が届き、ボディは
+    /// [`Response::body_stream`] から読む。chunked 転送のように長さがわからない
+    /// ボディや、大きなダウンロードに使う。ボディを読み終える前に取り消すには
+    /// BodyStream を捨てる。
+    pub fn fetch_streaming(
+        &self,
+        url: String,
+        msg_id: usize,
+        bypass_cache: bool,
+        partition: StoragePartition,
+        site_for_cookies: Option<Url>,
+    ) {
+        self.send_fetch(
+            url,
+            msg_id,
+            bypass_cache,
+            partition,
+            site_for_cookies,
+            Vec::new(),
+            true,
+        );
+    }
+
+    fn send_fetch(
+        &self,
+        url: String,
+        msg_id: usize,
+        bypass_cache: bool,
+        partition: StoragePartition,
+        site_for_cookies: Option<Url>,
+        request_headers: Vec<(&'static str, String)>,
+        stream: bool,
+    ) {
+        let token = CancellationToken::new();
+        self.tokens.borrow_mut().insert(msg_id, token.clone());
+        let _ = self.cmd_tx.send(NetworkCommand::Fetch {
+            url,
+            msg_id,
+            bypass_cache,
+            partition,
+            site_for_cookies,
+            request_headers,
+            token,
+            stream,
+        });
+    }
+
+    /// url を path にダウンロードする
+    ///
+    /// 終わったら try_receive に（body が空の）結果が届き、進み具合は
+    /// try_receive_progress で届く。接続が切れたら続きから取り直す。取り消しても
+    /// 受信した分は `<path>.part` に残り、同じ path にもう一度ダウンロードすると
+    /// 続きから受け取る。
+    pub fn download(&self, url: String, path: PathBuf, msg_id: usize, partition: StoragePartition) {
+        let token = CancellationToken::new();
+        self.tokens.borrow_mut().insert(msg_id, token.clone());
+        let _ = self.cmd_tx.send(NetworkCommand::Download {
+            url,
+            path,
+            msg_id,
+            partition,
+            token,
+        });
+    }
+
+    /// url のオリジンについて、リクエストを出す前に名前を引くか接続を張っておく
+    ///
+    /// 結果は届かない。同時に進めるヒントの数には上限があり、超えた分は捨てる。
+    pub fn hint(&self, url: String, hint: ConnectionHint, partition: StoragePartition) {
+        let _ = self.cmd_tx.send(NetworkCommand::Hint {
+            url,
+            hint,
+            partition,
+        });
+    }
+
+    /// partition の Cookie とキャッシュを捨てる（最後のプライベートタブを閉じたときなど）
+    pub fn clear_partition(&self, partition: StoragePartition) {
+        let _ = self.cmd_tx.send(NetworkCommand::ClearPartition(partition));
+    }
+
+    /// msg_id のリクエストを取り消す。取り消したリクエストの結果は届かない
+    pub fn cancel(&self, msg_id: usize) {
+        if let Some(token) = self.tokens.borrow_mut().remove(&msg_id) {
+            token.cancel();
+        }
     }
 
     /// UIスレッドから呼ぶ: 完了しているメッセージを取り込む
@@ -65,13 +268,35 @@ impl NetworkCore {
         let mut msgs = Vec::new();
         while let Ok(msg) = self.msg_rx.try_recv() {
             log::info!("NetworkCore: received message for msg_id={}", msg.msg_id);
+            // 送信済みの結果と取り消しがすれ違った場合は捨てる
+            if self.tokens.borrow_mut().remove(&msg.msg_id).is_none() {
+                continue;
+            }
             msgs.push(msg);
         }
         msgs
     }
 
+    /// UIスレッドから呼ぶ: 受信中のリクエストの進み具合を取り込む
+    ///
+    /// 同じリクエストについては最新のものだけを返す。
+    pub fn try_receive_progress(&self) -> Vec<NetworkProgress> {
+        let mut latest: Vec<NetworkProgress> = Vec::new();
+        let tokens = self.tokens.borrow();
+        while let Ok(progress) = self.progress_rx.try_recv() {
+            if !tokens.contains_key(&progress.msg_id) {
+                continue;
+            }
+            match latest.iter_mut().find(|p| p.msg_id == progress.msg_id) {
+                Some(p) => *p = progress,
+                None => latest.push(progress),
+            }
+        }
+        latest
+    }
+
     pub fn fetch_blocking(&self, url: &str) -> Result<Response, NetworkError> {
-        self.fetch_async(url.to_string(), 0);
+        self.fetch_async(url.to_string(), 0, false, StoragePartition::Default, None);
         loop {
             if let Some(v) = self.try_receive().into_iter().next() {
                 return v.response;
@@ -82,14 +307,115 @@ impl NetworkCore {
 }
 
 /// ネットワークスレッド
-fn spawn_network_thread(rx: Receiver<NetworkCommand>, tx: Sender<NetworkMessage>) {
+fn spawn_network_thread(
+    rx: Receiver<NetworkCommand>,
+    tx: Sender<NetworkMessage>,
+    progress_tx: Sender<NetworkProgress>,
+) {
     let mut core = AsyncNetworkCore::new();
 
-    for cmd in rx {
+    loop {
+        // 進めているヒントがあれば、次のコマンドを待つ間も進める
+        let cmd = if core.has_pending_hints() {
+            match rx.try_recv() {
+                Ok(cmd) => cmd,
+                Err(TryRecvError::Empty) => {
+                    core.run_hints(HINT_POLL_INTERVAL);
+                    continue;
+                }
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match rx.recv() {
+                Ok(cmd) => cmd,
+                Err(_) => break,
+            }
+        };
+
         match cmd {
             NetworkCommand::SetConfig(cfg) => core.set_network_config(cfg),
-            NetworkCommand::Fetch { url, msg_id } => {
-                let res = core.fetch_blocking(&url);
+            NetworkCommand::Hint {
+                url,
+                hint,
+                partition,
+            } => core.start_hint(&url, hint, partition),
+            NetworkCommand::ClearPartition(partition) => {
+                core.clear_partition(partition);
+                core.save_stores_if_modified();
+            }
+            NetworkCommand::Download {
+                url,
+                path,
+                msg_id,
+                partition,
+                token,
+            } => {
+                let on_progress = |received, total| {
+                    let _ = progress_tx.send(NetworkProgress {
+                        msg_id,
+                        received,
+                        total,
+                    });
+                };
+                let res = core.download_blocking(&url, &path, partition, &token, &on_progress);
+                core.save_stores_if_modified();
+                let Some(res) = res else {
+                    log::info!("NetworkCore: cancelled download msg_id={}", msg_id);
+                    continue;
+                };
+                log::info!("NetworkCore: downloaded {} to {:?}", url, path);
+                let _ = tx.send(NetworkMessage {
+                    msg_id,
+                    response: res,
+                });
+            }
+            NetworkCommand::Fetch {
+                url,
+                msg_id,
+                bypass_cache,
+                partition,
+                site_for_cookies,
+                request_headers,
+                token,
+                stream,
+            } => {
+                let on_progress = |received, total| {
+                    let _ = progress_tx.send(NetworkProgress {
+                        msg_id,
+                        received,
+                        total,
+                    });
+                };
+                // ヘッダーを渡したあとは、結果（エラーも）は BodyStream で伝わる
+                let headers_sent = Cell::new(false);
+                let on_headers = |response: Response| {
+                    headers_sent.set(true);
+                    let _ = tx.send(NetworkMessage {
+                        msg_id,
+                        response: Ok(response),
+                    });
+                };
+                let on_headers: Option<&dyn Fn(Response)> =
+                    if stream { Some(&on_headers) } else { None };
+                let res = core.fetch_blocking(
+                    &url,
+                    bypass_cache,
+                    &request_headers,
+                    partition,
+                    site_for_cookies.as_ref(),
+                    &token,
+                    &on_progress,
+                    on_headers,
+                );
+                core.save_stores_if_modified();
+                if headers_sent.get() {
+                    log::info!("NetworkCore: streamed body for msg_id={}", msg_id);
+                    continue;
+                }
+                let Some(res) = res else {
+                    log::info!("NetworkCore: cancelled msg_id={}", msg_id);
+                    continue;
+                };
                 log::info!("NetworkCore: fetched URL for msg_id={}", msg_id);
                 let _ = tx.send(NetworkMessage {
                     msg_id,
@@ -0,0 +1,65 @@
+//! Cookie とキャッシュ（と Alt-Svc）の保存先の切り分け
+//!
+//! プライベートタブのリクエストは通常のタブと別の Cookie / キャッシュを使う。
+//! プライベート用の保存先はメモリ上にだけ置き、最後のプライベートタブを閉じたら捨てる。
+
+use super::{Cache, CookieStore, alt_svc::AltSvcStore, hsts::HstsStore};
+
+/// リクエストが使う保存先
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StoragePartition {
+    /// 通常のタブ（プロファイルの保存先）
+    #[default]
+    Default,
+    /// プライベートタブ
+    Private,
+}
+
+/// 1 つの保存先が持つ Cookie とキャッシュ
+#[derive(Debug, Clone, Default)]
+pub struct PartitionStores {
+    pub cookies: CookieStore,
+    pub cache: Cache,
+    /// HTTP/3 を使えるオリジン
+    pub alt_svc: AltSvcStore,
+    /// https で開くホスト
+    pub hsts: HstsStore,
+}
+
+/// ネットワークスレッドが持つ保存先の一覧
+#[derive(Debug, Default)]
+pub struct PartitionedStores {
+    default: PartitionStores,
+    private: PartitionStores,
+}
+
+impl PartitionedStores {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, partition: StoragePartition) -> &PartitionStores {
+        match partition {
+            StoragePartition::Default => &self.default,
+            StoragePartition::Private => &self.private,
+        }
+    }
+
+    /// partition の Cookie とキャッシュを捨てる
+    pub fn clear(&mut self, partition: StoragePartition) {
+        let stores = match partition {
+            StoragePartition::Default => &mut self.default,
+            StoragePartition::Private => &mut self.private,
+        };
+        // 保存先のファイルに書くものは、設定を残したまま中身だけ捨てる
+        stores.cookies.clear();
+        stores.cache.clear();
+        stores.hsts.clear();
+        *stores = PartitionStores {
+            cookies: stores.cookies.clone(),
+            cache: stores.cache.clone(),
+            hsts: stores.hsts.clone(),
+            ..PartitionStores::default()
+        };
+    }
+}
@@ -0,0 +1,217 @@
+//! プロキシ経由の接続
+//!
+//! HTTP プロキシには、http のリクエストはそのまま（リクエストターゲットを絶対 URI に
+//! して）送り、https のリクエストは CONNECT でトンネルを張ってからその中で TLS を
+//! 話す。SOCKS5 プロキシ（RFC 1928）はいつもトンネルとして使い、名前解決も
+//! プロキシに任せる。
+
+use std::sync::Arc;
+
+use rustls::ClientConfig;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+use super::NetworkError;
+use super::config::{ProxyConfig, ProxyType};
+use crate::platform::base64;
+
+/// TCP・TLS・トンネルのどれでも同じように扱う接続
+pub(super) trait Io: AsyncRead + AsyncWrite + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Io for T {}
+
+pub(super) type BoxedIo = Box<dyn Io>;
+
+/// CONNECT の応答のヘッダーの長さの上限
+const MAX_CONNECT_RESPONSE: usize = 16 * 1024;
+
+/// proxy に繋ぐ
+///
+/// tunnel なら host:port へのトンネルを張ってから返す（https のリクエストと
+/// SOCKS5）。そうでなければプロキシとの接続をそのまま返す。
+pub(super) async fn connect(
+    proxy: &ProxyConfig,
+    tls: &Arc<ClientConfig>,
+    host: &str,
+    port: u16,
+    tunnel: bool,
+) -> Result<BoxedIo, NetworkError> {
+    let stream = TcpStream::connect((proxy.host.as_str(), proxy.port))
+        .await
+        .map_err(|_| NetworkError::ProxyFailed)?;
+
+    let mut io: BoxedIo = match proxy.proxy_type {
+        ProxyType::Http | ProxyType::Socks5 => Box::new(stream),
+        ProxyType::Https => {
+            // プロキシとは HTTP/1.1 で話す
+            let mut config = (**tls).clone();
+            config.alpn_protocols = vec![b"http/1.1".to_vec()];
+            let name = rustls::pki_types::ServerName::try_from(proxy.host.clone())
+                .map_err(|_| NetworkError::InvalidDnsName)?;
+            let stream = TlsConnector::from(Arc::new(config))
+                .connect(name, stream)
+                .await
+                .map_err(|_| NetworkError::ProxyFailed)?;
+            Box::new(stream)
+        }
+    };
+
+    match proxy.proxy_type {
+        ProxyType::Socks5 => socks5_connect(&mut io, proxy, host, port).await?,
+        ProxyType::Http | ProxyType::Https if tunnel => {
+            http_connect(&mut io, proxy, host, port).await?
+        }
+        _ => {}
+    }
+    Ok(io)
+}
+
+/// プロキシに HTTP のリクエストをそのまま送るか（トンネルを張らないか）
+pub(super) fn forwards_plain_http(proxy: &ProxyConfig, scheme: &str) -> bool {
+    proxy.proxy_type != ProxyType::Socks5 && scheme == "http"
+}
+
+/// Proxy-Authorization ヘッダーの値（Basic 認証）
+pub(super) fn authorization(proxy: &ProxyConfig) -> Option<String> {
+    let username = proxy.username.as_deref()?;
+    let password = proxy.password.as_deref().unwrap_or("");
+    Some(format!(
+        "Basic {}",
+        base64::encode(format!("{username}:{password}").as_bytes())
+    ))
+}
+
+async fn http_connect(
+    io: &mut BoxedIo,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), NetworkError> {
+    let authority = if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    };
+    let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+    if let Some(auth) = authorization(proxy) {
+        request.push_str(&format!("Proxy-Authorization: {auth}\r\n"));
+    }
+    request.push_str("\r\n");
+    io.write_all(request.as_bytes())
+        .await
+        .map_err(|_| NetworkError::ProxyFailed)?;
+
+    // トンネルの中身を読みすぎないよう、ヘッダーの終わりまで 1 バイトずつ読む
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err(NetworkError::ProxyFailed);
+        }
+        let byte = io.read_u8().await.map_err(|_| NetworkError::ProxyFailed)?;
+        response.push(byte);
+    }
+
+    let status_line = response.split(|&b| b == b'\r').next().unwrap_or_default();
+    let status = std::str::from_utf8(status_line)
+        .ok()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok());
+    match status {
+        Some(200..=299) => Ok(()),
+        _ => {
+            log::warn!(
+                "Proxy refused CONNECT to {}: {}",
+                authority,
+                String::from_utf8_lossy(status_line)
+            );
+            Err(NetworkError::ProxyFailed)
+        }
+    }
+}
+
+async fn socks5_connect(
+    io: &mut BoxedIo,
+    proxy: &ProxyConfig,
+    host: &str,
+    port: u16,
+) -> Result<(), NetworkError> {
+    let failed = |_| NetworkError::ProxyFailed;
+    let credentials = proxy
+        .username
+        .as_deref()
+        .map(|user| (user, proxy.password.as_deref().unwrap_or("")));
+
+    // 使える認証方式: 0x00 なし、0x02 ユーザー名とパスワード
+    let greeting: &[u8] = if credentials.is_some() {
+        &[5, 2, 0x00, 0x02]
+    } else {
+        &[5, 1, 0x00]
+    };
+    io.write_all(greeting).await.map_err(failed)?;
+    let mut choice = [0u8; 2];
+    io.read_exact(&mut choice).await.map_err(failed)?;
+
+    match (choice, credentials) {
+        ([5, 0x00], _) => {}
+        ([5, 0x02], Some((user, password))) => {
+            // RFC 1929
+            if user.len() > 255 || password.len() > 255 {
+                return Err(NetworkError::ProxyFailed);
+            }
+            let mut auth = vec![1, user.len() as u8];
+            auth.extend_from_slice(user.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend_from_slice(password.as_bytes());
+            io.write_all(&auth).await.map_err(failed)?;
+            let mut reply = [0u8; 2];
+            io.read_exact(&mut reply).await.map_err(failed)?;
+            if reply[1] != 0 {
+                log::warn!("SOCKS5 proxy rejected the credentials");
+                return Err(NetworkError::ProxyFailed);
+            }
+        }
+        _ => return Err(NetworkError::ProxyFailed),
+    }
+
+    // CONNECT（アドレスはドメイン名のまま渡す）
+    let host = host.trim_matches(['[', ']']);
+    let mut request = vec![5, 1, 0];
+    match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            if host.len() > 255 {
+                return Err(NetworkError::InvalidDnsName);
+            }
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    io.write_all(&request).await.map_err(failed)?;
+
+    let mut reply = [0u8; 4];
+    io.read_exact(&mut reply).await.map_err(failed)?;
+    if reply[0] != 5 || reply[1] != 0 {
+        log::warn!("SOCKS5 proxy refused the connection (reply {})", reply[1]);
+        return Err(NetworkError::ProxyFailed);
+    }
+    // プロキシ側のアドレスとポートは使わないので読み捨てる
+    let address_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => io.read_u8().await.map_err(failed)? as usize,
+        _ => return Err(NetworkError::ProxyFailed),
+    };
+    let mut rest = vec![0u8; address_len + 2];
+    io.read_exact(&mut rest).await.map_err(failed)?;
+    Ok(())
+}
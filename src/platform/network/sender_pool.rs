@@ -4,6 +4,7 @@ use hyper::{
     client::conn::{http1, http2},
 };
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 #[derive(Hash, Eq, PartialEq, Clone, Debug)]
 pub struct HostKey {
@@ -18,6 +19,16 @@ pub enum HttpSender {
     Http2(http2::SendRequest<Empty<Bytes>>),
 }
 
+impl HttpSender {
+    /// 接続が閉じられてもう使えないか
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Self::Http1(s) => s.is_closed(),
+            Self::Http2(s) => s.is_closed(),
+        }
+    }
+}
+
 pub struct SenderPool {
     pool: HashMap<HostKey, Vec<HttpSender>>,
     max_connections_per_host: usize,
@@ -37,27 +48,137 @@ impl SenderPool {
         }
     }
 
+    /// key への接続を取り出す
+    ///
+    /// HTTP/2 の接続は 1 本で複数のリクエストを多重化するので、プールに残したまま
+    /// 複製を返す。HTTP/1 の接続は使い終わるまでプールから外す。
     pub fn get_connection(&mut self, key: &HostKey) -> Option<HttpSender> {
-        self.pool.get_mut(key).and_then(|v| v.pop())
+        let conns = self.pool.get_mut(key)?;
+        conns.retain(|c| !c.is_closed());
+        for conn in conns.iter() {
+            if let HttpSender::Http2(s) = conn {
+                return Some(HttpSender::Http2(s.clone()));
+            }
+        }
+        conns.pop()
+    }
+
+    /// key への使える接続がプールにあるか
+    pub fn has_connection(&self, key: &HostKey) -> bool {
+        self.pool
+            .get(key)
+            .is_some_and(|conns| conns.iter().any(|c| !c.is_closed()))
+    }
+
+    /// プールに残すホストごとの接続の数
+    pub fn set_max_connections_per_host(&mut self, max: usize) {
+        self.max_connections_per_host = max;
     }
 
     pub fn add_connection(&mut self, key: HostKey, conn: HttpSender) {
         let entry = self.pool.entry(key).or_default();
+        // HTTP/2 はオリジンごとに 1 本だけ持つ（取り出した複製を戻したときは捨てる）
+        if matches!(conn, HttpSender::Http2(_))
+            && entry
+                .iter()
+                .any(|c| matches!(c, HttpSender::Http2(_)) && !c.is_closed())
+        {
+            return;
+        }
         if entry.len() < self.max_connections_per_host {
             entry.push(conn);
         }
     }
 
+    /// key の閉じた接続をプールから外す
     pub fn remove_connection(&mut self, key: &HostKey) {
         if let Some(conns) = self.pool.get_mut(key) {
-            conns.pop();
+            conns.retain(|c| !c.is_closed());
             if conns.is_empty() {
                 self.pool.remove(key);
             }
         }
     }
 
+    /// keep 以外のホストへの使っていない接続を 1 本閉じる。閉じたら true
+    ///
+    /// 同時接続数の上限に達したとき、別のホストに繋ぐ枠を空けるのに使う。
+    pub fn evict_idle(&mut self, keep: &HostKey) -> bool {
+        let Some(key) = self
+            .pool
+            .iter()
+            .find(|(key, conns)| *key != keep && !conns.is_empty())
+            .map(|(key, _)| key.clone())
+        else {
+            return false;
+        };
+        let conns = self.pool.get_mut(&key).unwrap();
+        conns.pop();
+        if conns.is_empty() {
+            self.pool.remove(&key);
+        }
+        true
+    }
+
     pub fn clear(&mut self) {
         self.pool.clear();
     }
 }
+
+/// 開いている接続の数を数え、上限を超えて接続しないようにする
+///
+/// 接続を開くときに [`ConnectionPermit`] を取り、接続が閉じたら捨てる。
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimiter {
+    open: Arc<Mutex<HashMap<HostKey, usize>>>,
+}
+
+/// 開いている接続 1 本分の枠。捨てると空く
+#[derive(Debug)]
+pub struct ConnectionPermit {
+    limiter: ConnectionLimiter,
+    key: HostKey,
+}
+
+impl ConnectionLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// key への接続が per_host 本未満で、全体でも total 本未満なら枠を取る
+    pub fn try_acquire(
+        &self,
+        key: &HostKey,
+        per_host: usize,
+        total: usize,
+    ) -> Option<ConnectionPermit> {
+        let mut open = self.open.lock().unwrap();
+        let host_count = open.get(key).copied().unwrap_or(0);
+        let total_count: usize = open.values().sum();
+        if host_count >= per_host || total_count >= total {
+            return None;
+        }
+        *open.entry(key.clone()).or_default() += 1;
+        Some(ConnectionPermit {
+            limiter: self.clone(),
+            key: key.clone(),
+        })
+    }
+
+    /// key への開いている接続の数
+    pub fn open_connections(&self, key: &HostKey) -> usize {
+        self.open.lock().unwrap().get(key).copied().unwrap_or(0)
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut open = self.limiter.open.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.key) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.key);
+            }
+        }
+    }
+}
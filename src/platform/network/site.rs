@@ -0,0 +1,54 @@
+//! サイト（スキームと登録可能ドメインの組）
+//!
+//! SameSite Cookie の判定などで「同じサイトか」を調べるのに使う。公開接尾辞リストは
+//! 持っていないので、登録可能ドメインはホスト名の末尾の 2 ラベル（よく使われる
+//! `co.jp` などの 2 段の接尾辞の下では 3 ラベル）で近似する。
+
+use std::net::IpAddr;
+use url::Url;
+
+/// 2 ラベルからなる公開接尾辞のうちよく使われるもの
+const SECOND_LEVEL_SUFFIXES: &[&str] = &[
+    "co.jp", "ne.jp", "or.jp", "ac.jp", "go.jp", "ed.jp", "lg.jp", "co.uk", "org.uk", "ac.uk",
+    "gov.uk", "com.au", "net.au", "org.au", "co.nz", "com.br", "com.cn", "com.tw", "co.kr",
+];
+
+/// host の登録可能ドメイン（IP アドレスならそのまま）
+pub fn registrable_domain(host: &str) -> String {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    if host.parse::<IpAddr>().is_ok() || host.starts_with('[') {
+        return host;
+    }
+
+    let labels: Vec<&str> = host.split('.').collect();
+    let take = if labels.len() >= 3 && is_public_suffix(&labels[labels.len() - 2..].join(".")) {
+        3
+    } else {
+        2
+    };
+    labels[labels.len().saturating_sub(take)..].join(".")
+}
+
+/// domain がそれ自体公開接尾辞か（`com` や `co.jp` には Cookie を置かせない）
+pub fn is_public_suffix(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    !domain.contains('.') || SECOND_LEVEL_SUFFIXES.contains(&domain.as_str())
+}
+
+/// url のサイト（`https://example.com` のようなスキームと登録可能ドメインの組）
+///
+/// ホスト名のない URL（file: など）はスキームだけで 1 つのサイトとみなす。
+pub fn site_of(url: &Url) -> String {
+    match url.host_str() {
+        Some(host) => format!("{}://{}", url.scheme(), registrable_domain(host)),
+        None => format!("{}:", url.scheme()),
+    }
+}
+
+/// a と b が同じサイトか（スキームも比べる）
+pub fn is_same_site(a: &Url, b: &Url) -> bool {
+    let (Some(host_a), Some(host_b)) = (a.host_str(), b.host_str()) else {
+        return false;
+    };
+    a.scheme() == b.scheme() && registrable_domain(host_a) == registrable_domain(host_b)
+}
@@ -0,0 +1,133 @@
+//! 受信しながら読めるレスポンスボディ
+//!
+//! [`super::NetworkCore::fetch_streaming`] の応答はヘッダーが届いた時点で返り、
+//! ボディは [`BodyStream`] からチャンク（chunked 転送のチャンクとは限らない）
+//! ごとに読む。Content-Encoding は展開済み。
+//!
+//! 非同期のコードでは `Stream` として、UI スレッドからは
+//! [`BodyStream::try_next_chunk`] でポーリングして使う。
+
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures_channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures_core::Stream;
+use hyper::body::Bytes;
+
+use super::{CancellationToken, NetworkError};
+
+/// ネットワークスレッドから BodyStream に送るもの
+enum BodyEvent {
+    Data(Bytes),
+    End,
+    Failed(NetworkError),
+}
+
+enum StreamState {
+    Open,
+    Finished,
+    Failed(NetworkError),
+}
+
+/// レスポンスボディのチャンクの列
+///
+/// 読み終える前に捨てるとリクエストを取り消す。
+pub struct BodyStream {
+    rx: UnboundedReceiver<BodyEvent>,
+    state: StreamState,
+    token: Option<CancellationToken>,
+}
+
+impl BodyStream {
+    /// 受信し終えたボディを 1 つのチャンクとして流す
+    pub fn from_bytes(body: Vec<u8>) -> Self {
+        let (tx, stream) = channel(None);
+        if !body.is_empty() {
+            tx.send(Bytes::from(body));
+        }
+        tx.finish();
+        stream
+    }
+
+    /// 次のチャンクを待たずに取り出す（UI スレッド用）
+    ///
+    /// まだ届いていなければ `Poll::Pending`、終わりに達したら `Poll::Ready(None)`。
+    pub fn try_next_chunk(&mut self) -> Poll<Option<Bytes>> {
+        Pin::new(self).poll_next(&mut Context::from_waker(Waker::noop()))
+    }
+
+    /// 最後まで受信したか
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, StreamState::Finished)
+    }
+
+    /// 途中で失敗したときのエラー（取り消されたときは Disconnected）
+    pub fn error(&self) -> Option<&NetworkError> {
+        match &self.state {
+            StreamState::Failed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Stream for BodyStream {
+    type Item = Bytes;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Bytes>> {
+        if !matches!(self.state, StreamState::Open) {
+            return Poll::Ready(None);
+        }
+        let event = match Pin::new(&mut self.rx).poll_next(cx) {
+            Poll::Ready(event) => event,
+            Poll::Pending => return Poll::Pending,
+        };
+        match event {
+            Some(BodyEvent::Data(chunk)) => return Poll::Ready(Some(chunk)),
+            Some(BodyEvent::End) => self.state = StreamState::Finished,
+            Some(BodyEvent::Failed(e)) => self.state = StreamState::Failed(e),
+            // 終わりを送る前にネットワークスレッドが手放した（取り消しなど）
+            None => self.state = StreamState::Failed(NetworkError::Disconnected),
+        }
+        Poll::Ready(None)
+    }
+}
+
+impl Drop for BodyStream {
+    fn drop(&mut self) {
+        if matches!(self.state, StreamState::Open)
+            && let Some(token) = &self.token
+        {
+            token.cancel();
+        }
+    }
+}
+
+/// ネットワークスレッド側の送り口
+pub(super) struct BodySender {
+    tx: UnboundedSender<BodyEvent>,
+}
+
+impl BodySender {
+    pub fn send(&self, chunk: Bytes) {
+        let _ = self.tx.unbounded_send(BodyEvent::Data(chunk));
+    }
+
+    pub fn finish(self) {
+        let _ = self.tx.unbounded_send(BodyEvent::End);
+    }
+
+    pub fn fail(self, error: NetworkError) {
+        let _ = self.tx.unbounded_send(BodyEvent::Failed(error));
+    }
+}
+
+/// token はストリームを捨てたときに取り消すリクエストのもの
+pub(super) fn channel(token: Option<CancellationToken>) -> (BodySender, BodyStream) {
+    let (tx, rx) = mpsc::unbounded();
+    let stream = BodyStream {
+        rx,
+        state: StreamState::Open,
+        token,
+    };
+    (BodySender { tx }, stream)
+}
@@ -0,0 +1,284 @@
+//! TLS の接続の情報（プロトコルのバージョン、暗号スイート、サーバー証明書）
+//!
+//! 証明書は画面に出すための項目（主体、発行者、有効期間、DNS 名）だけを DER から
+//! 読む。検証は rustls が済ませているので、ここでは何も確かめない。
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::http_date;
+
+/// 応答を受け取った TLS の接続
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// `TLS 1.3` など
+    pub version: String,
+    /// `TLS13_AES_128_GCM_SHA256` など（HTTP/3 ではわからないので None）
+    pub cipher_suite: Option<String>,
+    /// ALPN で決まったプロトコル（`h2` など）
+    pub alpn: Option<String>,
+    /// サーバーが送った証明書。サーバー自身のものが先頭
+    pub certificates: Vec<CertificateInfo>,
+}
+
+/// 証明書 1 枚
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    /// 主体（`CN=example.com, O=Example` の形）
+    pub subject: String,
+    pub issuer: String,
+    /// シリアル番号（16 進）
+    pub serial_number: String,
+    pub not_before: Option<SystemTime>,
+    pub not_after: Option<SystemTime>,
+    /// subjectAltName の DNS 名
+    pub dns_names: Vec<String>,
+    pub der: Vec<u8>,
+}
+
+impl TlsInfo {
+    /// ハンドシェイクを終えた接続から作る
+    pub fn from_connection(conn: &rustls::ClientConnection) -> Option<Self> {
+        let version = match conn.protocol_version()? {
+            rustls::ProtocolVersion::TLSv1_2 => "TLS 1.2".to_string(),
+            rustls::ProtocolVersion::TLSv1_3 => "TLS 1.3".to_string(),
+            other => format!("{other:?}"),
+        };
+        Some(Self {
+            version,
+            cipher_suite: conn
+                .negotiated_cipher_suite()
+                .map(|suite| format!("{:?}", suite.suite())),
+            alpn: conn
+                .alpn_protocol()
+                .map(|p| String::from_utf8_lossy(p).into_owned()),
+            certificates: certificates(conn.peer_certificates().unwrap_or_default()),
+        })
+    }
+
+    /// サーバー自身の証明書
+    pub fn leaf(&self) -> Option<&CertificateInfo> {
+        self.certificates.first()
+    }
+}
+
+/// DER の証明書の列を読む（読めないものは飛ばす）
+pub fn certificates(chain: &[rustls::pki_types::CertificateDer<'_>]) -> Vec<CertificateInfo> {
+    chain
+        .iter()
+        .filter_map(|der| CertificateInfo::parse(der))
+        .collect()
+}
+
+impl CertificateInfo {
+    /// DER の X.509 証明書を読む
+    pub fn parse(der: &[u8]) -> Option<Self> {
+        let certificate = Der(der).expect_value(SEQUENCE)?;
+        let mut tbs = Der(Der(certificate).expect_value(SEQUENCE)?);
+
+        // [0] version は省略できる
+        if tbs.peek() == Some(0xa0) {
+            tbs.read()?;
+        }
+        let serial = tbs.expect_value(INTEGER)?;
+        tbs.expect_value(SEQUENCE)?; // signature
+        let issuer = tbs.expect_value(SEQUENCE)?;
+        let validity = tbs.expect_value(SEQUENCE)?;
+        let subject = tbs.expect_value(SEQUENCE)?;
+        tbs.expect_value(SEQUENCE)?; // subjectPublicKeyInfo
+
+        let mut dns_names = Vec::new();
+        while let Some((tag, value)) = tbs.read() {
+            // [3] extensions
+            if tag == 0xa3 {
+                dns_names = subject_alt_names(value).unwrap_or_default();
+            }
+        }
+
+        let mut validity = Der(validity);
+        let not_before = validity.read().and_then(|(tag, v)| parse_time(tag, v));
+        let not_after = validity.read().and_then(|(tag, v)| parse_time(tag, v));
+
+        Some(Self {
+            subject: distinguished_name(subject),
+            issuer: distinguished_name(issuer),
+            serial_number: serial.iter().map(|b| format!("{b:02X}")).collect(),
+            not_before,
+            not_after,
+            dns_names,
+            der: der.to_vec(),
+        })
+    }
+
+    /// 主体の CN（なければ O、それもなければ主体全体）
+    pub fn common_name(&self) -> &str {
+        name_attribute(&self.subject, "CN")
+            .or_else(|| name_attribute(&self.subject, "O"))
+            .unwrap_or(&self.subject)
+    }
+
+    /// 発行者の O（なければ CN）
+    pub fn issuer_name(&self) -> &str {
+        name_attribute(&self.issuer, "O")
+            .or_else(|| name_attribute(&self.issuer, "CN"))
+            .unwrap_or(&self.issuer)
+    }
+}
+
+/// `CN=a, O=b` から key の値を取り出す
+fn name_attribute<'a>(name: &'a str, key: &str) -> Option<&'a str> {
+    name.split(", ").find_map(|part| {
+        part.split_once('=')
+            .filter(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    })
+}
+
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+
+/// subjectAltName（2.5.29.17）
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// DER の読み口
+struct Der<'a>(&'a [u8]);
+
+impl<'a> Der<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    /// 次の要素の (タグ, 中身)
+    fn read(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.0.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let n = (first & 0x7f) as usize;
+            if n == 0 || n > 4 || rest.len() < n {
+                return None;
+            }
+            let len = rest[..n]
+                .iter()
+                .fold(0usize, |len, b| (len << 8) | *b as usize);
+            (len, &rest[n..])
+        };
+        if rest.len() < len {
+            return None;
+        }
+        let (value, rest) = rest.split_at(len);
+        self.0 = rest;
+        Some((tag, value))
+    }
+
+    /// 次の要素が tag ならその中身
+    fn expect_value(&mut self, tag: u8) -> Option<&'a [u8]> {
+        let (found, value) = self.read()?;
+        (found == tag).then_some(value)
+    }
+}
+
+/// Name（RDN の列）を `CN=example.com, O=Example` の形にする（細かいものが先）
+fn distinguished_name(name: &[u8]) -> String {
+    let mut parts = Vec::new();
+    let mut rdns = Der(name);
+    while let Some((SET, rdn)) = rdns.read() {
+        let mut attributes = Der(rdn);
+        while let Some((SEQUENCE, attribute)) = attributes.read() {
+            let mut attribute = Der(attribute);
+            let (Some(oid), Some((tag, value))) = (attribute.expect_value(OID), attribute.read())
+            else {
+                continue;
+            };
+            let key = match oid {
+                [0x55, 0x04, 0x03] => "CN",
+                [0x55, 0x04, 0x06] => "C",
+                [0x55, 0x04, 0x07] => "L",
+                [0x55, 0x04, 0x08] => "ST",
+                [0x55, 0x04, 0x0a] => "O",
+                [0x55, 0x04, 0x0b] => "OU",
+                _ => continue,
+            };
+            parts.push(format!("{key}={}", decode_string(tag, value)));
+        }
+    }
+    parts.reverse();
+    parts.join(", ")
+}
+
+/// DirectoryString などの文字列
+fn decode_string(tag: u8, value: &[u8]) -> String {
+    match tag {
+        // BMPString
+        0x1e => {
+            let units: Vec<u16> = value
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => String::from_utf8_lossy(value).into_owned(),
+    }
+}
+
+/// extensions から subjectAltName の DNS 名を取り出す
+fn subject_alt_names(extensions: &[u8]) -> Option<Vec<String>> {
+    let mut extensions = Der(Der(extensions).expect_value(SEQUENCE)?);
+    while let Some((SEQUENCE, extension)) = extensions.read() {
+        let mut extension = Der(extension);
+        if extension.expect_value(OID)? != OID_SUBJECT_ALT_NAME {
+            continue;
+        }
+        // critical は省略できる
+        let mut value = extension.read()?;
+        if value.0 != OCTET_STRING {
+            value = extension.read()?;
+        }
+        let mut names = Der(Der(value.1).expect_value(SEQUENCE)?);
+        let mut dns_names = Vec::new();
+        while let Some((tag, name)) = names.read() {
+            // [2] dNSName
+            if tag == 0x82 {
+                dns_names.push(String::from_utf8_lossy(name).into_owned());
+            }
+        }
+        return Some(dns_names);
+    }
+    None
+}
+
+/// UTCTime（`YYMMDDHHMMSSZ`）か GeneralizedTime（`YYYYMMDDHHMMSSZ`）
+fn parse_time(tag: u8, value: &[u8]) -> Option<SystemTime> {
+    let text = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let (year, rest) = match tag {
+        UTC_TIME => {
+            let year: i64 = text.get(..2)?.parse().ok()?;
+            // RFC 5280: 50 以上は 1900 年代
+            (
+                if year >= 50 { 1900 + year } else { 2000 + year },
+                &text[2..],
+            )
+        }
+        GENERALIZED_TIME => (text.get(..4)?.parse().ok()?, &text[4..]),
+        _ => return None,
+    };
+    let field = |i: usize| rest.get(i..i + 2)?.parse::<u32>().ok();
+    let (month, day) = (field(0)?, field(2)?);
+    let (hour, minute, second) = (field(4)?, field(6)?, field(8).unwrap_or(0));
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let days = http_date::days_from_civil(year, month, day);
+    let secs = days * 86400 + (hour * 3600 + minute * 60 + second) as i64;
+    Some(if secs < 0 {
+        UNIX_EPOCH
+    } else {
+        UNIX_EPOCH + Duration::from_secs(secs as u64)
+    })
+}
@@ -0,0 +1,200 @@
+//! デスクトップ通知の Facade
+//!
+//! OS の通知センター（Linux の freedesktop 通知、macOS の通知センター、Windows のトースト）に
+//! 出す。どれも外部のコマンドを呼ぶので、[`NotificationCenter`] が別スレッドで出し、
+//! 出せたか・閉じたかを [`NotificationCenter::poll`] で返す。
+//!
+//! ページが通知を出してよいかは呼ぶ側（権限の仕組み）が決める。
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use anyhow::Result;
+
+/// 通知の中身
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    /// 同じタグの通知は前のものを置き換える（空なら置き換えない）
+    pub tag: String,
+    /// 通知を出した文書のオリジン（どのサイトからの通知か示す）
+    pub origin: String,
+}
+
+/// 通知センターに頼むこと（id はブラウザ全体で一意な通知の番号）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationCommand {
+    Show(u64, Notification),
+    Close(u64),
+}
+
+/// 通知に起きたこと
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotificationEvent {
+    Shown,
+    Closed,
+    /// 出せなかった（理由）
+    Error(String),
+}
+
+/// OS の通知センター
+pub trait NotificationBackend: Send {
+    /// 名前（ログ用）
+    fn name(&self) -> &'static str;
+
+    /// notification を出し、閉じるときに使う OS の番号を返す（閉じられなければ None）
+    fn show(&mut self, notification: &Notification) -> Result<Option<u32>>;
+
+    /// show が返した番号の通知を閉じる
+    fn close(&mut self, id: u32) -> Result<()>;
+}
+
+/// 出した通知を覚えておくだけの通知センター（テスト用）
+#[derive(Debug, Default)]
+pub struct MemoryNotificationBackend {
+    shown: Vec<(u32, Notification)>,
+    next_id: u32,
+}
+
+impl MemoryNotificationBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NotificationBackend for MemoryNotificationBackend {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    fn show(&mut self, notification: &Notification) -> Result<Option<u32>> {
+        self.next_id += 1;
+        self.shown.push((self.next_id, notification.clone()));
+        Ok(Some(self.next_id))
+    }
+
+    fn close(&mut self, id: u32) -> Result<()> {
+        self.shown.retain(|(shown, _)| *shown != id);
+        Ok(())
+    }
+}
+
+/// この OS の通知センター（使えなければ None）
+#[allow(unreachable_code)]
+pub fn default_backend() -> Option<Box<dyn NotificationBackend>> {
+    #[cfg(target_os = "macos")]
+    {
+        return Some(Box::new(
+            crate::platform::os::macos::notifications::NotificationCenterBackend,
+        ));
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        return Some(Box::new(
+            crate::platform::os::windows::notifications::ToastBackend,
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        return crate::platform::os::linux::notifications::FreedesktopBackend::find()
+            .map(|b| Box::new(b) as Box<dyn NotificationBackend>);
+    }
+
+    None
+}
+
+/// 通知の番号と、それに起きたこと
+pub type NotificationResult = (u64, NotificationEvent);
+
+/// 別スレッドで通知を出す
+///
+/// 同じオリジンの同じタグの通知を出すと、前の通知を閉じてから出す。
+pub struct NotificationCenter {
+    commands: Sender<NotificationCommand>,
+    events: Receiver<NotificationResult>,
+}
+
+impl NotificationCenter {
+    pub fn new(mut backend: Box<dyn NotificationBackend>) -> Self {
+        let (command_tx, command_rx) = mpsc::channel::<NotificationCommand>();
+        let (event_tx, event_rx) = mpsc::channel();
+        log::info!("Showing notifications with {}", backend.name());
+
+        thread::spawn(move || {
+            let mut shown = HashMap::new();
+            for command in command_rx {
+                for event in run_command(backend.as_mut(), &mut shown, command) {
+                    if event_tx.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Self {
+            commands: command_tx,
+            events: event_rx,
+        }
+    }
+
+    pub fn send(&self, command: NotificationCommand) {
+        if self.commands.send(command).is_err() {
+            log::warn!("Notification worker has stopped");
+        }
+    }
+
+    /// 届いた出来事
+    pub fn poll(&self) -> Vec<NotificationResult> {
+        self.events.try_iter().collect()
+    }
+}
+
+/// 出している通知の OS の番号と、オリジンとタグ
+type Shown = HashMap<u64, (Option<u32>, (String, String))>;
+
+fn run_command(
+    backend: &mut dyn NotificationBackend,
+    shown: &mut Shown,
+    command: NotificationCommand,
+) -> Vec<NotificationResult> {
+    let mut events = Vec::new();
+    match command {
+        NotificationCommand::Show(id, notification) => {
+            let key = (notification.origin.clone(), notification.tag.clone());
+            let replaced = shown
+                .iter()
+                .find_map(|(id, (_, k))| (*k == key).then_some(*id))
+                .filter(|_| !notification.tag.is_empty());
+            if let Some(replaced) = replaced {
+                events.extend(close(backend, shown, replaced));
+            }
+            match backend.show(&notification) {
+                Ok(os_id) => {
+                    shown.insert(id, (os_id, key));
+                    events.push((id, NotificationEvent::Shown));
+                }
+                Err(e) => events.push((id, NotificationEvent::Error(format!("{:#}", e)))),
+            }
+        }
+        NotificationCommand::Close(id) => events.extend(close(backend, shown, id)),
+    }
+    events
+}
+
+fn close(
+    backend: &mut dyn NotificationBackend,
+    shown: &mut Shown,
+    id: u64,
+) -> Option<NotificationResult> {
+    let (os_id, _) = shown.remove(&id)?;
+    if let Some(os_id) = os_id
+        && let Err(e) = backend.close(os_id)
+    {
+        log::warn!("Failed to close a notification: {:#}", e);
+    }
+    Some((id, NotificationEvent::Closed))
+}
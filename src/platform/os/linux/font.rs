@@ -17,3 +17,21 @@ pub fn system_font_candidates() -> Result<Vec<PathBuf>> {
         PathBuf::from("/usr/share/fonts/truetype/freefont/FreeSans.ttf"),
     ])
 }
+
+/// 主フォントに無いグリフ用のフォールバック候補（CJK・記号・絵文字）
+pub fn fallback_font_candidates() -> Result<Vec<PathBuf>> {
+    Ok(vec![
+        // CJK
+        PathBuf::from("/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc"),
+        PathBuf::from("/usr/share/fonts/truetype/noto/NotoSansCJK-Regular.ttc"),
+        PathBuf::from("/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc"),
+        PathBuf::from("/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf"),
+        // 記号
+        PathBuf::from("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf"),
+        PathBuf::from("/usr/share/fonts/truetype/noto/NotoSansSymbols-Regular.ttf"),
+        PathBuf::from("/usr/share/fonts/truetype/noto/NotoSansSymbols2-Regular.ttf"),
+        // 絵文字
+        PathBuf::from("/usr/share/fonts/truetype/noto/NotoColorEmoji.ttf"),
+        PathBuf::from("/usr/share/fonts/noto/NotoColorEmoji.ttf"),
+    ])
+}
@@ -0,0 +1,88 @@
+//! GeoClue で現在地を取る
+//!
+//! GeoClue に付いてくる `where-am-i` を呼び、最初に出した位置を使う。
+//! `where-am-i` は位置が変わるたびに出力し続けるので、読めたところで止める。
+
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::platform::geolocation::{LocationProvider, Position, PositionError};
+
+/// `where-am-i` の置き場所（ディストリビューションによって違う）
+const WHERE_AM_I: [&str; 3] = [
+    "/usr/libexec/geoclue-2.0/demos/where-am-i",
+    "/usr/lib/geoclue-2.0/demos/where-am-i",
+    "/usr/lib/geoclue/demos/where-am-i",
+];
+
+pub struct GeoClueProvider {
+    path: PathBuf,
+}
+
+impl GeoClueProvider {
+    /// `where-am-i` があれば使う
+    pub fn find() -> Option<Self> {
+        WHERE_AM_I
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+            .map(|path| Self { path })
+    }
+}
+
+impl LocationProvider for GeoClueProvider {
+    fn name(&self) -> &'static str {
+        "GeoClue"
+    }
+
+    fn current_position(&mut self, timeout: Duration) -> Result<Position, PositionError> {
+        let unavailable = |e: std::io::Error| PositionError::PositionUnavailable(e.to_string());
+        // -t の秒数が過ぎると何も出さずに終わる
+        let mut child = Command::new(&self.path)
+            .arg("-t")
+            .arg(timeout.as_secs().max(1).to_string())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(unavailable)?;
+        let Some(stdout) = child.stdout.take() else {
+            return Err(PositionError::PositionUnavailable(
+                "where-am-i has no output".to_string(),
+            ));
+        };
+
+        let (mut latitude, mut longitude) = (None, None);
+        let mut position = None;
+        for line in BufReader::new(stdout).lines() {
+            let line = line.map_err(unavailable)?;
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            // 「Latitude:    35.681236°」「Accuracy:    25.000000 meters」の形
+            let value = value.trim().trim_end_matches('°').split_whitespace().next();
+            let Some(value) = value.and_then(|v| v.parse::<f64>().ok()) else {
+                continue;
+            };
+            match key.trim() {
+                "Latitude" => latitude = Some(value),
+                "Longitude" => longitude = Some(value),
+                "Accuracy" => {
+                    if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+                        position = Some(Position {
+                            latitude,
+                            longitude,
+                            accuracy: value,
+                        });
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+        position.ok_or(PositionError::Timeout)
+    }
+}
@@ -0,0 +1,81 @@
+//! Secret Service（GNOME Keyring、KWallet など）に秘密を保存する
+//!
+//! libsecret の `secret-tool` を呼ぶ。秘密は標準入力で渡すので、コマンドラインには出ない。
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+
+use crate::platform::keychain::SecretStore;
+
+/// 項目に付ける属性（アプリの名前）
+const APPLICATION: &str = "orinium";
+
+pub struct SecretToolStore;
+
+impl SecretToolStore {
+    /// `secret-tool` が PATH にあるか
+    pub fn is_available() -> bool {
+        std::env::var_os("PATH").is_some_and(|path| {
+            std::env::split_paths(&path).any(|dir| dir.join("secret-tool").is_file())
+        })
+    }
+}
+
+impl SecretStore for SecretToolStore {
+    fn name(&self) -> &'static str {
+        "Secret Service"
+    }
+
+    fn get(&mut self, key: &str) -> Result<Option<String>> {
+        let output = Command::new("secret-tool")
+            .args(["lookup", "application", APPLICATION, "key", key])
+            .stderr(Stdio::null())
+            .output()
+            .context("Failed to run secret-tool")?;
+        // 見つからなければ何も出さずに失敗する
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let secret = String::from_utf8(output.stdout).context("Secret is not UTF-8")?;
+        Ok(Some(secret.trim_end_matches('\n').to_string()))
+    }
+
+    fn set(&mut self, key: &str, secret: &str) -> Result<()> {
+        let mut child = Command::new("secret-tool")
+            .args(["store", "--label"])
+            .arg(format!("Orinium: {}", key))
+            .args(["application", APPLICATION, "key", key])
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run secret-tool")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(secret.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            bail!(
+                "secret-tool store failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        // 項目がなくても成功する
+        let output = Command::new("secret-tool")
+            .args(["clear", "application", APPLICATION, "key", key])
+            .output()
+            .context("Failed to run secret-tool")?;
+        if !output.status.success() {
+            bail!(
+                "secret-tool clear failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,8 @@
+//! メモリーの逼迫の度合いを `/proc/meminfo` から読む
+
+use crate::platform::memory::{self, MemoryPressure};
+
+pub fn pressure() -> Option<MemoryPressure> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    memory::pressure_from_meminfo(&meminfo)
+}
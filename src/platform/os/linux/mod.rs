@@ -1,3 +1,7 @@
 //! Linux 固有実装
 
 pub mod font;
+pub mod geolocation;
+pub mod keychain;
+pub mod memory;
+pub mod notifications;
@@ -0,0 +1,75 @@
+//! freedesktop の通知（GNOME、KDE など）に出す
+//!
+//! libnotify の `notify-send` で出し、`gdbus` で org.freedesktop.Notifications の
+//! CloseNotification を呼んで閉じる。
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+use crate::platform::notifications::{Notification, NotificationBackend};
+
+pub struct FreedesktopBackend {
+    notify_send: PathBuf,
+}
+
+impl FreedesktopBackend {
+    /// `notify-send` が PATH にあれば使う
+    pub fn find() -> Option<Self> {
+        let path = std::env::var_os("PATH")?;
+        std::env::split_paths(&path)
+            .map(|dir| dir.join("notify-send"))
+            .find(|path| path.is_file())
+            .map(|notify_send| Self { notify_send })
+    }
+}
+
+impl NotificationBackend for FreedesktopBackend {
+    fn name(&self) -> &'static str {
+        "notify-send"
+    }
+
+    fn show(&mut self, notification: &Notification) -> Result<Option<u32>> {
+        // 本文の先頭にどのサイトからの通知かを出す
+        let body = format!("{}\n{}", notification.origin, notification.body);
+        let output = Command::new(&self.notify_send)
+            .args(["--app-name=Orinium", "--print-id", "--"])
+            .arg(&notification.title)
+            .arg(body.trim_end())
+            .output()
+            .context("Failed to run notify-send")?;
+        if !output.status.success() {
+            bail!(
+                "notify-send failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        // 古い notify-send は --print-id を知らずに番号を出さない
+        Ok(String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+    }
+
+    fn close(&mut self, id: u32) -> Result<()> {
+        let output = Command::new("gdbus")
+            .args([
+                "call",
+                "--session",
+                "--dest",
+                "org.freedesktop.Notifications",
+                "--object-path",
+                "/org/freedesktop/Notifications",
+                "--method",
+                "org.freedesktop.Notifications.CloseNotification",
+            ])
+            .arg(id.to_string())
+            .output()
+            .context("Failed to run gdbus")?;
+        if !output.status.success() {
+            bail!(
+                "CloseNotification failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
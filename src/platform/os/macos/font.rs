@@ -16,3 +16,17 @@ pub fn system_font_candidates() -> Result<Vec<PathBuf>> {
         PathBuf::from("/System/Library/Fonts/AppleSDGothicNeo.ttc"),
     ])
 }
+
+/// 主フォントに無いグリフ用のフォールバック候補（CJK・記号・絵文字）
+pub fn fallback_font_candidates() -> Result<Vec<PathBuf>> {
+    Ok(vec![
+        // CJK
+        PathBuf::from("/System/Library/Fonts/ヒラギノ角ゴシック W3.ttc"),
+        PathBuf::from("/System/Library/Fonts/PingFang.ttc"),
+        PathBuf::from("/System/Library/Fonts/AppleSDGothicNeo.ttc"),
+        // 記号
+        PathBuf::from("/System/Library/Fonts/Apple Symbols.ttf"),
+        // 絵文字
+        PathBuf::from("/System/Library/Fonts/Apple Color Emoji.ttc"),
+    ])
+}
@@ -0,0 +1,41 @@
+//! CoreLocation で現在地を取る
+//!
+//! CoreLocation はアプリのバンドルに許可を与える仕組みなので、許可済みの
+//! `CoreLocationCLI` を呼んで 1 度だけ位置を出させる。
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::platform::geolocation::{self, LocationProvider, Position, PositionError};
+
+pub struct CoreLocationProvider {
+    path: PathBuf,
+}
+
+impl CoreLocationProvider {
+    /// `CoreLocationCLI` が PATH にあれば使う
+    pub fn find() -> Option<Self> {
+        let path = std::env::var_os("PATH")?;
+        std::env::split_paths(&path)
+            .map(|dir| dir.join("CoreLocationCLI"))
+            .find(|path| path.is_file())
+            .map(|path| Self { path })
+    }
+}
+
+impl LocationProvider for CoreLocationProvider {
+    fn name(&self) -> &'static str {
+        "CoreLocation"
+    }
+
+    fn current_position(&mut self, timeout: Duration) -> Result<Position, PositionError> {
+        let output = geolocation::output_with_timeout(
+            Command::new(&self.path).args(["--format", "%latitude %longitude %h_accuracy"]),
+            timeout,
+        )?;
+        geolocation::parse_position(output.trim()).ok_or_else(|| {
+            PositionError::PositionUnavailable(format!("Unexpected output: {:?}", output))
+        })
+    }
+}
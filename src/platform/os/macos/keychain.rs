@@ -0,0 +1,108 @@
+//! ログインキーチェーンに秘密を保存する
+//!
+//! `security` コマンドの汎用パスワード（サービス名 `Orinium`、アカウント名が鍵）として持つ。
+
+use std::fmt::Write as _;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, bail};
+
+use crate::platform::keychain::SecretStore;
+
+/// 項目のサービス名
+const SERVICE: &str = "Orinium";
+
+/// `security` の「項目が見つからない」終了コード
+const ITEM_NOT_FOUND: i32 = 44;
+
+pub struct SecurityStore;
+
+impl SecretStore for SecurityStore {
+    fn name(&self) -> &'static str {
+        "Keychain"
+    }
+
+    fn get(&mut self, key: &str) -> Result<Option<String>> {
+        let output = Command::new("security")
+            .args(["find-generic-password", "-s", SERVICE, "-a", key, "-w"])
+            .stderr(Stdio::null())
+            .output()
+            .context("Failed to run security")?;
+        match output.status.code() {
+            Some(0) => {
+                let secret = String::from_utf8(output.stdout).context("Secret is not UTF-8")?;
+                Ok(Some(secret.trim_end_matches('\n').to_string()))
+            }
+            Some(ITEM_NOT_FOUND) => Ok(None),
+            _ => bail!("security find-generic-password failed: {}", output.status),
+        }
+    }
+
+    fn set(&mut self, key: &str, secret: &str) -> Result<()> {
+        // 改行があると次のコマンドとして読まれてしまう
+        if key.contains(['\n', '\r']) {
+            bail!("Keychain keys cannot contain line breaks");
+        }
+        // 引数に書くと実行中に ps から読めるので、`security -i` に標準入力でコマンドを渡す。
+        // 秘密は 16 進（-X）にして引用符の扱いを気にしなくて済むようにする（-U で上書き）
+        let script = format!(
+            "add-generic-password -U -s {SERVICE} -a {} -X {}\n",
+            quote(key),
+            hex(secret.as_bytes())
+        );
+        let mut child = Command::new("security")
+            .arg("-i")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run security")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(script.as_bytes())
+                .context("Failed to write to security")?;
+        }
+        let output = child.wait_with_output().context("Failed to run security")?;
+        // -i ではコマンドが失敗しても終了コードが 0 のことがあるので、エラー出力も見る
+        let errors = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !errors.trim().is_empty() {
+            bail!("security add-generic-password failed: {}", errors.trim());
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &str) -> Result<()> {
+        let output = Command::new("security")
+            .args(["delete-generic-password", "-s", SERVICE, "-a", key])
+            .output()
+            .context("Failed to run security")?;
+        match output.status.code() {
+            Some(0 | ITEM_NOT_FOUND) => Ok(()),
+            _ => bail!(
+                "security delete-generic-password failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        }
+    }
+}
+
+/// `security -i` の 1 語として読まれるよう、引用符で囲む
+fn quote(word: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in word.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, b| {
+        let _ = write!(out, "{b:02x}");
+        out
+    })
+}
@@ -1,3 +1,6 @@
 //! macOS 固有実装
 
 pub mod font;
+pub mod geolocation;
+pub mod keychain;
+pub mod notifications;
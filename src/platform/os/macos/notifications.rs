@@ -0,0 +1,51 @@
+//! 通知センターに出す
+//!
+//! AppleScript の `display notification` を使う。文字列は引数で渡すので、
+//! スクリプトの中でエスケープしなくてよい。こうして出した通知は閉じられない。
+
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+
+use crate::platform::notifications::{Notification, NotificationBackend};
+
+/// argv の 1 番目をタイトル、2 番目を本文、3 番目をサブタイトルにして出す
+const SCRIPT: [&str; 3] = [
+    "on run argv",
+    "display notification (item 2 of argv) with title (item 1 of argv) subtitle (item 3 of argv)",
+    "end run",
+];
+
+pub struct NotificationCenterBackend;
+
+impl NotificationBackend for NotificationCenterBackend {
+    fn name(&self) -> &'static str {
+        "Notification Center"
+    }
+
+    fn show(&mut self, notification: &Notification) -> Result<Option<u32>> {
+        let mut command = Command::new("osascript");
+        for line in SCRIPT {
+            command.args(["-e", line]);
+        }
+        let output = command
+            .args([
+                &notification.title,
+                &notification.body,
+                &notification.origin,
+            ])
+            .output()
+            .context("Failed to run osascript")?;
+        if !output.status.success() {
+            bail!(
+                "osascript failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(None)
+    }
+
+    fn close(&mut self, _id: u32) -> Result<()> {
+        Ok(())
+    }
+}
@@ -16,3 +16,18 @@ pub fn system_font_candidates() -> Result<Vec<PathBuf>> {
         PathBuf::from(r"C:\Windows\Fonts\arial.ttf"),
     ])
 }
+
+/// 主フォントに無いグリフ用のフォールバック候補（CJK・記号・絵文字）
+pub fn fallback_font_candidates() -> Result<Vec<PathBuf>> {
+    Ok(vec![
+        // CJK
+        PathBuf::from(r"C:\Windows\Fonts\YuGothR.ttc"),
+        PathBuf::from(r"C:\Windows\Fonts\msgothic.ttc"),
+        PathBuf::from(r"C:\Windows\Fonts\msyh.ttc"),
+        PathBuf::from(r"C:\Windows\Fonts\malgun.ttf"),
+        // 記号
+        PathBuf::from(r"C:\Windows\Fonts\seguisym.ttf"),
+        // 絵文字
+        PathBuf::from(r"C:\Windows\Fonts\seguiemj.ttf"),
+    ])
+}
@@ -0,0 +1,42 @@
+//! Windows.Devices.Geolocation で現在地を取る
+//!
+//! WinRT の `Geolocator` を PowerShell から呼び、`緯度 経度 誤差` を 1 行で出させる。
+//! 「設定 > プライバシー > 位置情報」で許可されていなければ失敗する。
+
+use std::process::Command;
+use std::time::Duration;
+
+use crate::platform::geolocation::{self, LocationProvider, Position, PositionError};
+
+/// GetGeopositionAsync の結果を待って出力するスクリプト
+const SCRIPT: &str = r#"
+Add-Type -AssemblyName System.Runtime.WindowsRuntime
+$asTask = [System.WindowsRuntimeSystemExtensions].GetMethods() | Where-Object {
+    $_.Name -eq 'AsTask' -and $_.GetParameters().Count -eq 1 -and
+    $_.GetParameters()[0].ParameterType.Name -eq 'IAsyncOperation`1'
+} | Select-Object -First 1
+$null = [Windows.Devices.Geolocation.Geolocator, Windows.Devices.Geolocation, ContentType = WindowsRuntime]
+$operation = (New-Object Windows.Devices.Geolocation.Geolocator).GetGeopositionAsync()
+$task = $asTask.MakeGenericMethod([Windows.Devices.Geolocation.Geoposition]).Invoke($null, @($operation))
+$coordinate = $task.GetAwaiter().GetResult().Coordinate
+$p = $coordinate.Point.Position
+[string]::Format([Globalization.CultureInfo]::InvariantCulture, '{0} {1} {2}', $p.Latitude, $p.Longitude, $coordinate.Accuracy)
+"#;
+
+pub struct WindowsLocationProvider;
+
+impl LocationProvider for WindowsLocationProvider {
+    fn name(&self) -> &'static str {
+        "Windows.Devices.Geolocation"
+    }
+
+    fn current_position(&mut self, timeout: Duration) -> Result<Position, PositionError> {
+        let output = geolocation::output_with_timeout(
+            Command::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", SCRIPT]),
+            timeout,
+        )?;
+        geolocation::parse_position(output.trim()).ok_or_else(|| {
+            PositionError::PositionUnavailable(format!("Unexpected output: {:?}", output))
+        })
+    }
+}
@@ -1,3 +1,5 @@
 //! Windows 固有実装
 
 pub mod font;
+pub mod geolocation;
+pub mod notifications;
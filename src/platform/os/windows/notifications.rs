@@ -0,0 +1,86 @@
+//! トースト通知を出す
+//!
+//! Windows.UI.Notifications を PowerShell から呼ぶ。文字列は環境変数で渡し、
+//! XML のテキストノードとして入れるのでエスケープしなくてよい。トーストのタグに
+//! 番号を付けておき、閉じるときは通知の履歴から消す。
+
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{Context, Result, bail};
+
+use crate::platform::notifications::{Notification, NotificationBackend};
+
+/// トーストを出すアプリ（登録済みの PowerShell の AppUserModelID を借りる）
+const APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// トーストのグループ名
+const GROUP: &str = "Orinium";
+
+const SHOW_SCRIPT: &str = r#"
+$null = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]
+$null = [Windows.Data.Xml.Dom.XmlDocument, Windows.Data.Xml.Dom, ContentType = WindowsRuntime]
+$xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent(
+    [Windows.UI.Notifications.ToastTemplateType]::ToastText04)
+$texts = $xml.GetElementsByTagName('text')
+$null = $texts.Item(0).AppendChild($xml.CreateTextNode($env:ORINIUM_TITLE))
+$null = $texts.Item(1).AppendChild($xml.CreateTextNode($env:ORINIUM_BODY))
+$null = $texts.Item(2).AppendChild($xml.CreateTextNode($env:ORINIUM_ORIGIN))
+$toast = [Windows.UI.Notifications.ToastNotification]::new($xml)
+$toast.Tag = $env:ORINIUM_TAG
+$toast.Group = $env:ORINIUM_GROUP
+[Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier($env:ORINIUM_APP_ID).Show($toast)
+"#;
+
+const CLOSE_SCRIPT: &str = r#"
+$null = [Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime]
+[Windows.UI.Notifications.ToastNotificationManager]::History.Remove(
+    $env:ORINIUM_TAG, $env:ORINIUM_GROUP, $env:ORINIUM_APP_ID)
+"#;
+
+/// 次に出すトーストのタグ
+static NEXT_TAG: AtomicU32 = AtomicU32::new(1);
+
+pub struct ToastBackend;
+
+fn run(script: &str, envs: &[(&str, &str)]) -> Result<()> {
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .envs(envs.iter().copied())
+        .env("ORINIUM_GROUP", GROUP)
+        .env("ORINIUM_APP_ID", APP_ID)
+        .output()
+        .context("Failed to run powershell")?;
+    if !output.status.success() {
+        bail!(
+            "powershell failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+impl NotificationBackend for ToastBackend {
+    fn name(&self) -> &'static str {
+        "Windows toast notifications"
+    }
+
+    fn show(&mut self, notification: &Notification) -> Result<Option<u32>> {
+        let tag = NEXT_TAG.fetch_add(1, Ordering::Relaxed);
+        run(
+            SHOW_SCRIPT,
+            &[
+                ("ORINIUM_TITLE", &notification.title),
+                ("ORINIUM_BODY", &notification.body),
+                ("ORINIUM_ORIGIN", &notification.origin),
+                ("ORINIUM_TAG", &tag.to_string()),
+            ],
+        )?;
+        Ok(Some(tag))
+    }
+
+    fn close(&mut self, id: u32) -> Result<()> {
+        run(CLOSE_SCRIPT, &[("ORINIUM_TAG", &id.to_string())])
+    }
+}
@@ -0,0 +1,133 @@
+//! 描いたものを画面に出す先
+//!
+//! ブラウザは描画命令を [`Compositor`] に渡す。UI スレッドで動かすときはウィンドウの
+//! [`GpuRenderer`]（GPU がなければ [`SoftwareRenderer`](super::software::SoftwareRenderer)）
+//! に直接渡し、エンジンのスレッドで動かすときは [`FrameRecorder`] に溜めた [`Frame`] を
+//! UI スレッドに送り、そこでウィンドウのレンダラーに渡す。
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use winit::dpi::PhysicalSize;
+
+use super::gpu::GpuRenderer;
+use super::headless;
+use crate::engine::renderer_model::DrawCommand;
+
+/// 描画命令を受け取って画面に出すもの
+pub trait Compositor {
+    /// 描く先の大きさ（物理ピクセル）が変わった
+    fn resize(&mut self, size: PhysicalSize<u32>);
+    fn set_scale_factor(&mut self, scale_factor: f64);
+    /// フレームの統計を重ねて描くか
+    fn set_debug_overlay(&mut self, enabled: bool);
+    fn parse_draw_commands(&mut self, commands: &[DrawCommand]);
+    /// 渡された描画命令で 1 フレームを出す
+    fn render(&mut self) -> Result<()>;
+    /// 今の描画命令を画像にする（スクリーンショット用）
+    fn capture_frame(&mut self) -> Result<image::RgbaImage>;
+}
+
+impl Compositor for GpuRenderer {
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        GpuRenderer::resize(self, size);
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        GpuRenderer::set_scale_factor(self, scale_factor);
+    }
+
+    fn set_debug_overlay(&mut self, enabled: bool) {
+        GpuRenderer::set_debug_overlay(self, enabled);
+    }
+
+    fn parse_draw_commands(&mut self, commands: &[DrawCommand]) {
+        GpuRenderer::parse_draw_commands(self, commands);
+    }
+
+    fn render(&mut self) -> Result<()> {
+        GpuRenderer::render(self)
+    }
+
+    fn capture_frame(&mut self) -> Result<image::RgbaImage> {
+        GpuRenderer::capture_frame(self)
+    }
+}
+
+/// 1 フレーム分の描画命令と、その描き方
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub draw_commands: Vec<DrawCommand>,
+    pub scale_factor: f64,
+    pub debug_overlay: bool,
+}
+
+impl Frame {
+    /// gpu に渡す（出すのは gpu.render() のとき）
+    pub fn apply(&self, gpu: &mut dyn Compositor) {
+        gpu.set_scale_factor(self.scale_factor);
+        gpu.set_debug_overlay(self.debug_overlay);
+        gpu.parse_draw_commands(&self.draw_commands);
+    }
+}
+
+/// 出したフレームを溜めておく Compositor（別のスレッドの GPU に送るため）
+pub struct FrameRecorder {
+    size: PhysicalSize<u32>,
+    frame: Frame,
+    /// render() で出して、まだ取り出していないフレーム
+    rendered: Option<Arc<Frame>>,
+}
+
+impl FrameRecorder {
+    pub fn new(size: (u32, u32)) -> Self {
+        Self {
+            size: PhysicalSize::new(size.0, size.1),
+            frame: Frame {
+                draw_commands: Vec::new(),
+                scale_factor: 1.0,
+                debug_overlay: false,
+            },
+            rendered: None,
+        }
+    }
+
+    /// 最後に出したフレームを取り出す
+    pub fn take_rendered(&mut self) -> Option<Arc<Frame>> {
+        self.rendered.take()
+    }
+}
+
+impl Compositor for FrameRecorder {
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        if size.width > 0 && size.height > 0 {
+            self.size = size;
+        }
+    }
+
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.frame.scale_factor = scale_factor;
+    }
+
+    fn set_debug_overlay(&mut self, enabled: bool) {
+        self.frame.debug_overlay = enabled;
+    }
+
+    fn parse_draw_commands(&mut self, commands: &[DrawCommand]) {
+        self.frame.draw_commands = commands.to_vec();
+    }
+
+    fn render(&mut self) -> Result<()> {
+        self.rendered = Some(Arc::new(self.frame.clone()));
+        Ok(())
+    }
+
+    /// ウィンドウの GPU はこのスレッドにないので、同じ大きさのオフスクリーンに描く
+    fn capture_frame(&mut self) -> Result<image::RgbaImage> {
+        headless::render_offscreen(
+            (self.size.width, self.size.height),
+            self.frame.scale_factor,
+            &self.frame.draw_commands,
+        )
+    }
+}
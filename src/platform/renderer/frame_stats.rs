@@ -0,0 +1,142 @@
+//! フレームの統計と、それを画面に出すデバッグ用の表示
+//!
+//! [`GpuRenderer`](super::gpu::GpuRenderer) が描いたフレームごとにかかった時間と、
+//! 描いたものの数を記録する。描かなかった（再描画のなかった）時間は数えない。
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::engine::layouter::types::{Color, TextStyle};
+use crate::engine::renderer_model::DrawCommand;
+
+/// 覚えておくフレームの数（百分位数はこの中で求める）
+pub const FRAME_HISTORY: usize = 240;
+
+const FONT_SIZE: f32 = 13.0;
+const LINE_HEIGHT: f32 = FONT_SIZE * 1.3;
+const PADDING: f32 = 6.0;
+const MARGIN: f32 = 8.0;
+const PANEL_WIDTH: f32 = 300.0;
+const BACKGROUND: Color = Color(0, 0, 0, 190);
+const TEXT_COLOR: Color = Color(120, 255, 140, 255);
+
+/// 1 フレームで描いたものの数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameCounts {
+    pub draw_commands: usize,
+    pub vertices: usize,
+    pub rect_instances: usize,
+    pub text_sections: usize,
+}
+
+/// 最近のフレームの記録
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    /// (描き終えた時刻, かかった時間)
+    frames: VecDeque<(Instant, Duration)>,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// at に描き終えたフレームを記録する。frame_time は描画コマンドを受け取ってから
+    /// 画面に出すまでの時間
+    pub fn record(&mut self, at: Instant, frame_time: Duration) {
+        if self.frames.len() == FRAME_HISTORY {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((at, frame_time));
+    }
+
+    /// now までの 1 秒間に描いたフレームの数
+    pub fn fps(&self, now: Instant) -> usize {
+        self.frames
+            .iter()
+            .rev()
+            .take_while(|(at, _)| now.saturating_duration_since(*at) <= Duration::from_secs(1))
+            .count()
+    }
+
+    /// フレームにかかった時間の p 百分位数（0.0〜100.0、nearest-rank 法）
+    pub fn percentile(&self, p: f32) -> Option<Duration> {
+        let mut times: Vec<Duration> = self.frames.iter().map(|(_, t)| *t).collect();
+        if times.is_empty() {
+            return None;
+        }
+        times.sort_unstable();
+        let rank = ((p.clamp(0.0, 100.0) / 100.0) * times.len() as f32).ceil() as usize;
+        Some(times[rank.clamp(1, times.len()) - 1])
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// 表示する行
+    pub fn lines(&self, counts: &FrameCounts, now: Instant) -> Vec<String> {
+        let ms = |p| {
+            self.percentile(p).map_or("-".to_string(), |t| {
+                format!("{:.1}", t.as_secs_f64() * 1000.0)
+            })
+        };
+        vec![
+            format!("{} fps", self.fps(now)),
+            format!(
+                "frame ms  p50 {}  p95 {}  p99 {}",
+                ms(50.0),
+                ms(95.0),
+                ms(99.0)
+            ),
+            format!("draw commands  {}", counts.draw_commands),
+            format!(
+                "vertices  {}  rects  {}",
+                counts.vertices, counts.rect_instances
+            ),
+            format!("text sections  {}", counts.text_sections),
+        ]
+    }
+}
+
+/// 幅 width（物理ピクセル）の画面の右上に統計を出す DrawCommand（物理ピクセル）
+pub fn overlay_commands(
+    stats: &FrameStats,
+    counts: &FrameCounts,
+    now: Instant,
+    width: f32,
+) -> Vec<DrawCommand> {
+    let lines = stats.lines(counts, now);
+    let x = (width - PANEL_WIDTH - MARGIN).max(0.0);
+    let y = MARGIN;
+    let style = TextStyle {
+        font_size: FONT_SIZE,
+        color: TEXT_COLOR,
+        ..Default::default()
+    };
+
+    let mut commands = vec![DrawCommand::DrawRect {
+        x,
+        y,
+        width: PANEL_WIDTH,
+        height: LINE_HEIGHT * lines.len() as f32 + PADDING * 2.0,
+        color: BACKGROUND,
+    }];
+    commands.extend(
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| DrawCommand::DrawText {
+                x: x + PADDING,
+                y: y + PADDING + LINE_HEIGHT * i as f32,
+                text,
+                style,
+                max_width: PANEL_WIDTH - PADDING * 2.0,
+            }),
+    );
+    commands
+}
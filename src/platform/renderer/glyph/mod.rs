@@ -1 +1,2 @@
+pub(crate) mod shaping;
 pub mod text;
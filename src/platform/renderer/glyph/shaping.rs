@@ -0,0 +1,55 @@
+//! 計測と描画で共通のシェーピング
+//!
+//! `PlatformTextMeasurer` と `TextRenderer` が同じ関数・同じ属性で
+//! cosmic-text（harfrust）のシェーピングを行うことで、計測幅と描画結果を一致させる。
+
+use glyphon::{
+    Attrs, Buffer, Color as GlyphColor, FontSystem, Metrics, Shaping, Style, Weight, Wrap,
+    cosmic_text::Align,
+};
+
+use crate::engine::layouter::types::TextStyle;
+use crate::platform::font::{self, FontMatcher};
+
+/// 行の高さ（font-size に対する倍率）
+pub(crate) const LINE_HEIGHT_FACTOR: f32 = 1.2;
+
+/// テキストをシェーピング・行レイアウトした `Buffer` を作る
+///
+/// `max_width` を渡すと UAX #14 の改行機会で折り返す（CJK は文字単位、
+/// 1 単語が収まらない場合はグリフ単位）。
+pub(crate) fn shape_text(
+    font_sys: &mut FontSystem,
+    font_matcher: &mut FontMatcher,
+    text: &str,
+    style: &TextStyle,
+    max_width: Option<f32>,
+) -> Buffer {
+    let font_size = style.font_size.max(1.0);
+    let metrics = Metrics::relative(font_size, LINE_HEIGHT_FACTOR);
+    let color = style.color;
+
+    let font_match = font_matcher.resolve(font_sys.db(), style);
+
+    let mut attrs = Attrs::new()
+        .metrics(metrics)
+        .color(GlyphColor::rgba(color.0, color.1, color.2, color.3))
+        .weight(Weight(style.font_weight.0))
+        .style(Style::from(style.font_style));
+    if let Some(font_match) = &font_match {
+        attrs = font::apply_font_match(attrs, font_match);
+    }
+
+    let mut buffer = Buffer::new(font_sys, metrics);
+    buffer.set_wrap(font_sys, Wrap::WordOrGlyph);
+    buffer.set_size(font_sys, max_width, None);
+    buffer.set_text(
+        font_sys,
+        text,
+        &attrs,
+        Shaping::Advanced,
+        Some(Align::from(style.text_align)),
+    );
+
+    buffer
+}
@@ -1,13 +1,21 @@
-use std::{env, sync::Arc};
+use std::sync::{Arc, Mutex};
 
 use crate::engine::layouter::types::{FontStyle, TextAlign, TextStyle};
 use glyphon::{
-    Attrs, Buffer, Cache, Color as GlyphColor, FontSystem, Metrics, PrepareError, Resolution,
-    Shaping, Style, SwashCache, TextArea, TextAtlas, TextBounds, TextRenderer as TextBrush,
-    Viewport, Weight, cosmic_text::Align, fontdb,
+    Buffer, Cache, Color as GlyphColor, FontSystem, PrepareError, Resolution, Style, SwashCache,
+    TextArea, TextAtlas, TextBounds, TextRenderer as TextBrush, Viewport, cosmic_text::Align,
 };
 
-use crate::platform::font;
+use crate::platform::font::{self, FontMatcher};
+use crate::platform::memory::{self, MemoryPressure, Subsystem, TrackedMemory};
+use crate::platform::renderer::glyph::shaping;
+use crate::platform::renderer::text_cache::{LruCache, TextCacheKey};
+
+/// シェーピング済み Buffer の LRU 上限（エントリ数）
+const BUFFER_CACHE_CAPACITY: usize = 2048;
+
+/// シェーピング済み Buffer 1 つのおおよその大きさ（メモリーの集計用）
+const BUFFER_ENTRY_SIZE: usize = 4 * 1024;
 
 /// テキストセクション位置・クリップ・描画するBufferをまとめた構造体
 pub struct TextSection {
@@ -17,10 +25,16 @@ pub struct TextSection {
     pub clip_origin: (f32, f32),
     /// クリップ領域の幅・高さ
     pub bounds: (f32, f32),
-    pub buffer: Buffer,
+    pub buffer: Arc<Buffer>,
+    /// 疑似ボールドなら、右にずらして重ね描きする幅（物理ピクセル）
+    pub synthetic_bold: Option<f32>,
 }
 
 /// glyphon使ったテキストレンダラー
+///
+/// カラー絵文字（CBDT / sbix / COLR）は swash がカラー画像としてラスタライズし、
+/// glyphon のカラーアトラスに載るため文字色の影響を受けない。
+/// 絵文字フォントは [`font::font_system_with_fallbacks`] でフォールバックとして読み込まれる。
 pub struct TextRenderer {
     /// glyphonのテキストブラシ
     brush: TextBrush,
@@ -30,38 +44,35 @@ pub struct TextRenderer {
     atlas: TextAtlas,
     /// rasterize 結果のキャッシュ
     swash_cache: SwashCache,
-    font_sys: FontSystem,
+    /// 計測側と共有することがあるためロック越しに使う
+    font_sys: Arc<Mutex<FontSystem>>,
+    /// CSS の font-family を実フォントに解決する
+    font_matcher: FontMatcher,
+    /// シェーピング済み Buffer のキャッシュ（計測側と同じキーを使う）
+    buffer_cache: LruCache<TextCacheKey, Arc<Buffer>>,
+    /// buffer_cache の使用量。追い出しを頼まれたら次の queue で捨てる
+    memory: Arc<TrackedMemory>,
 }
 
 impl TextRenderer {
     /// 情報を渡してシステムフォントから初期化する
+    ///
+    /// `FontSystem` は `PlatformTextMeasurer` と共有する
+    /// （`ORINIUM_FONT` が設定されていればそのフォントを使う）。
     pub fn new_from_device(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         format: wgpu::TextureFormat,
     ) -> anyhow::Result<Self> {
-        // 後々環境変数とかに設定しているときに使えるようにしてます
-        if let Ok(p) = env::var("ORINIUM_FONT")
-            && let Ok(bytes) = std::fs::read(&p)
-        {
-            return Self::new_from_bytes(device, queue, format, bytes);
-        }
-
-        for p in font::system_font_candidates()? {
-            if let Ok(bytes) = std::fs::read(p) {
-                // build brush from bytes
-                return Self::new_from_bytes(device, queue, format, bytes);
-            }
-        }
-
-        anyhow::bail!("no system font found");
+        let font_sys = font::shared_font_system()?;
+        Self::new_with_fontsys(device, queue, format, font_sys)
     }
 
     pub fn new_with_fontsys(
         device: &wgpu::Device,
         queue: &wgpu::Queue,
         format: wgpu::TextureFormat,
-        font_sys: FontSystem,
+        font_sys: Arc<Mutex<FontSystem>>,
     ) -> anyhow::Result<Self> {
         let cache = Cache::new(device);
         let mut atlas = TextAtlas::new(device, queue, &cache, format);
@@ -76,12 +87,18 @@ impl TextRenderer {
 
         let swash_cache = SwashCache::new();
 
+        let memory = TrackedMemory::evictable(Subsystem::Glyphs);
+        memory::registry().register(&memory);
+
         Ok(Self {
             brush,
             atlas,
             font_sys,
+            font_matcher: FontMatcher::new(),
+            buffer_cache: LruCache::new(BUFFER_CACHE_CAPACITY),
             viewport,
             swash_cache,
+            memory,
         })
     }
 
@@ -92,45 +109,69 @@ impl TextRenderer {
         format: wgpu::TextureFormat,
         font_bytes: Vec<u8>,
     ) -> anyhow::Result<Self> {
-        let font_source = Arc::new(font_bytes);
-        let font = fontdb::Source::Binary(font_source);
-        let font_sys = FontSystem::new_with_fonts(vec![font]);
-        Self::new_with_fontsys(device, queue, format, font_sys)
+        let font_sys = font::font_system_with_fallbacks(font_bytes);
+        Self::new_with_fontsys(device, queue, format, Arc::new(Mutex::new(font_sys)))
     }
 
     /// Create a cosmic-text `Buffer` for the given text using the internal `FontSystem`.
-    /// This encapsulates the required `Metrics` and calls `set_text`.
-    pub fn create_buffer_for_text(&mut self, text: &str, text_style: TextStyle) -> Buffer {
-        let font_size = text_style.font_size;
-        let color = text_style.color;
-        let color = GlyphColor::rgba(color.0, color.1, color.2, color.3);
-        let align = text_style.text_align;
-        let weight = text_style.font_weight;
-        let style = text_style.font_style;
-        // reasonable default line height (1.2x)
-        let metrics = Metrics::relative(font_size, 1.2);
-
-        let mut buffer = Buffer::new(&mut self.font_sys, metrics);
-
-        // build attributes (defaults + color + metrics)
-        let attrs = Attrs::new()
-            .metrics(metrics)
-            .color(color)
-            .weight(Weight(weight.0))
-            .style(Style::from(style));
-
-        // shape and layout
-        buffer.set_text(
-            &mut self.font_sys,
-            text,
-            &attrs,
-            Shaping::Advanced,
-            Some(Align::from(align)),
-        );
+    /// Shaping goes through [`shaping::shape_text`], the same path the measurer uses.
+    ///
+    /// 同じ文字列・スタイルの Buffer はキャッシュから返すため、毎フレームの再シェーピングを避けられる。
+    pub fn create_buffer_for_text(&mut self, text: &str, text_style: TextStyle) -> Arc<Buffer> {
+        // Buffer は FontSystem ごとなので font_key は使わない
+        let key = TextCacheKey::new(0, text, &text_style, None, false);
+        if let Some(buffer) = self.buffer_cache.get(&key) {
+            return buffer.clone();
+        }
+
+        let buffer = {
+            let mut font_sys = self.font_sys.lock().unwrap_or_else(|e| e.into_inner());
+            shaping::shape_text(
+                &mut font_sys,
+                &mut self.font_matcher,
+                text,
+                &text_style,
+                None,
+            )
+        };
 
+        let buffer = Arc::new(buffer);
+        self.buffer_cache.insert(key, buffer.clone());
+        self.memory.set(self.buffer_cache.len() * BUFFER_ENTRY_SIZE);
         buffer
     }
 
+    /// text_style の文字を疑似ボールドにするなら、重ね描きのずらし幅（物理ピクセル）
+    pub fn synthetic_bold_offset(&mut self, text_style: &TextStyle) -> Option<f32> {
+        let font_sys = self.font_sys.lock().unwrap_or_else(|e| e.into_inner());
+        font::synthetic_bold_offset(&mut self.font_matcher, font_sys.db(), text_style)
+    }
+
+    /// 頼まれていた追い出しを行う（GPU のアトラスを触るのでこのスレッドで行う）
+    fn evict_if_requested(&mut self) {
+        let Some(pressure) = self.memory.take_eviction_request() else {
+            return;
+        };
+        match pressure {
+            MemoryPressure::Moderate => self.buffer_cache.shrink_to(self.buffer_cache.len() / 2),
+            MemoryPressure::Critical => {
+                self.buffer_cache.clear();
+                // アトラスのグリフを使っていないものとし、次に場所が要るときに捨てさせる
+                self.atlas.trim();
+            }
+        }
+        self.memory.set(self.buffer_cache.len() * BUFFER_ENTRY_SIZE);
+    }
+
+    /// ラスタライズしたグリフを捨てる（倍率が変わって大きさが合わなくなったとき）
+    ///
+    /// アトラスのグリフは使っていないものとし、次に場所が要るときに捨てさせる。
+    /// シェーピング済みの Buffer は物理ピクセルの文字の大きさで引くので、そのまま残す。
+    pub fn discard_glyphs(&mut self) {
+        self.atlas.trim();
+        self.swash_cache = SwashCache::new();
+    }
+
     /// 指定されたセクション群をギリフォン用の TextArea に変換して Atlas に転送する
     pub fn queue<'a>(
         &mut self,
@@ -138,6 +179,8 @@ impl TextRenderer {
         queue: &wgpu::Queue,
         sections: &'a [TextSection],
     ) -> Result<(), PrepareError> {
+        self.evict_if_requested();
+
         // TextArea は Buffer を参照するライフタイムを持つため、一時的にベクタに詰めて渡す
         let mut text_areas: Vec<TextArea<'a>> = Vec::with_capacity(sections.len());
 
@@ -152,23 +195,26 @@ impl TextRenderer {
             // デフォルト色は Buffer 内の属性が優先されるため適当で良い
             let default_color = GlyphColor::rgba(0, 0, 0, 255);
 
-            let area = TextArea {
-                buffer: &s.buffer,
-                left: s.screen_position.0,
-                top: s.screen_position.1,
-                scale: 1.0,
-                bounds,
-                default_color,
-                custom_glyphs: &[],
-            };
-
-            text_areas.push(area);
+            // 疑似ボールドは少し右にずらしてもう一度描く
+            let offsets = [Some(0.0), s.synthetic_bold];
+            for offset in offsets.into_iter().flatten() {
+                text_areas.push(TextArea {
+                    buffer: &s.buffer,
+                    left: s.screen_position.0 + offset,
+                    top: s.screen_position.1,
+                    scale: 1.0,
+                    bounds,
+                    default_color,
+                    custom_glyphs: &[],
+                });
+            }
         }
 
+        let mut font_sys = self.font_sys.lock().unwrap_or_else(|e| e.into_inner());
         self.brush.prepare(
             device,
             queue,
-            &mut self.font_sys,
+            &mut font_sys,
             &mut self.atlas,
             &self.viewport,
             text_areas,
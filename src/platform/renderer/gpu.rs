@@ -1,20 +1,30 @@
 use crate::engine::renderer_model::DrawCommand;
 use anyhow::Result;
+use image::RgbaImage;
+use std::borrow::Cow;
 use std::env;
+use std::mem::offset_of;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
 use wgpu::util::DeviceExt;
 use winit::window::Window;
 
+use super::frame_stats::{self, FrameCounts, FrameStats};
 use super::glyph::text::{TextRenderer, TextSection};
 
 /// GPU描画コンテキスト
 pub struct GpuRenderer {
-    /// GPUの描画対象
-    surface: wgpu::Surface<'static>,
+    /// デバイスを作り直すときに使うwgpuインスタンス
+    instance: wgpu::Instance,
+    /// GPUの描画対象（ヘッドレス時は None）
+    surface: Option<wgpu::Surface<'static>>,
     /// GPUの論理デバイス
     device: wgpu::Device,
     /// コマンド送信用キュー
     queue: wgpu::Queue,
+    /// デバイスが失われたら立つ（次の描画の前に作り直す）
+    device_lost: Arc<AtomicBool>,
     /// サーフェス設定、解像度・フォーマットなどのフレームバッファ設定
     config: wgpu::SurfaceConfiguration,
     /// WindowSize
@@ -27,14 +37,45 @@ pub struct GpuRenderer {
     vertex_buffer: Option<wgpu::Buffer>,
     /// 頂点
     vertices: Vec<Vertex>,
-    /// 頂点数
-    num_vertices: u32,
+    /// 矩形インスタンス描画用パイプライン
+    rect_pipeline: wgpu::RenderPipeline,
+    /// 矩形インスタンスバッファ
+    instance_buffer: Option<wgpu::Buffer>,
+    /// 矩形インスタンス
+    rect_instances: Vec<RectInstance>,
+    /// 画像描画用パイプライン
+    image_pipeline: wgpu::RenderPipeline,
+    /// 画像のテクスチャとサンプラーのバインドグループレイアウト
+    image_bind_group_layout: wgpu::BindGroupLayout,
+    /// 画像用サンプラー
+    image_sampler: wgpu::Sampler,
+    /// 画像インスタンスバッファ
+    image_instance_buffer: Option<wgpu::Buffer>,
+    /// 画像インスタンス
+    image_instances: Vec<ImageInstance>,
+    /// 前のフレームで描いた画像のテクスチャ（同じ画像なら次のフレームでも使い回す）
+    image_textures: Vec<ImageTexture>,
+    /// 描画順を保ったバッチ列（矩形と多角形の前後関係を維持する）
+    batches: Vec<ShapeBatch>,
 
     /// テキスト描画用ラッパー
     text_renderer: Option<TextRenderer>,
+    /// テキストレンダラーを作り直すときに読むフォント
+    font_path: Option<String>,
+    /// 最後に受け取った描画コマンド（デバイスを作り直したらバッファを作り直す）
+    commands: Vec<DrawCommand>,
 
     /// テキストカリングを有効にする
     enable_text_culling: bool,
+
+    /// FPS と描いたものの数を右上に出す
+    debug_overlay: bool,
+    /// 最近のフレームにかかった時間
+    frame_stats: FrameStats,
+    /// 前のフレームで描いたものの数
+    frame_counts: FrameCounts,
+    /// 描画中のフレームの描画コマンドを受け取った時刻
+    frame_started: Option<Instant>,
 }
 
 #[repr(C)]
@@ -65,6 +106,119 @@ impl Vertex {
     }
 }
 
+/// 矩形 1 つ分のインスタンスデータ（座標は NDC）
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct RectInstance {
+    /// 左上
+    position: [f32; 2],
+    /// 幅・高さ（y は下向きなので負）
+    size: [f32; 2],
+    color: [f32; 4],
+}
+
+impl RectInstance {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<RectInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: offset_of!(RectInstance, size) as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: offset_of!(RectInstance, color) as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+            ],
+        }
+    }
+}
+
+/// 画像 1 枚分のインスタンスデータ（座標は NDC、テクスチャ座標は 0〜1）
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ImageInstance {
+    /// 左上
+    position: [f32; 2],
+    /// 幅・高さ（y は下向きなので負）
+    size: [f32; 2],
+    /// 描く部分のテクスチャ座標の左上
+    uv_position: [f32; 2],
+    /// 描く部分のテクスチャ座標の幅・高さ
+    uv_size: [f32; 2],
+}
+
+impl ImageInstance {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<ImageInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: offset_of!(ImageInstance, size) as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: offset_of!(ImageInstance, uv_position) as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: offset_of!(ImageInstance, uv_size) as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+            ],
+        }
+    }
+}
+
+/// GPU に送った画像 1 枚分のテクスチャ
+struct ImageTexture {
+    /// 元の画像（同じ `Arc` かどうかで使い回しを判定する）
+    image: Arc<RgbaImage>,
+    bind_group: wgpu::BindGroup,
+}
+
+/// 同じパイプラインで連続して描画できる図形の範囲
+#[derive(Clone, Debug, PartialEq)]
+enum ShapeBatch {
+    /// `rect_instances` の範囲
+    Rects(std::ops::Range<u32>),
+    /// `vertices` の範囲（三角形リスト）
+    Triangles(std::ops::Range<u32>),
+    /// `image_instances` の 1 つと、それを描く `image_textures` の番号
+    Image { instance: u32, texture: usize },
+}
+
+/// バッチ列の末尾が同じ種類なら範囲を伸ばし、違えば新しいバッチを積む
+fn push_batch(batches: &mut Vec<ShapeBatch>, batch: ShapeBatch) {
+    match (batches.last_mut(), &batch) {
+        (Some(ShapeBatch::Rects(last)), ShapeBatch::Rects(next))
+        | (Some(ShapeBatch::Triangles(last)), ShapeBatch::Triangles(next))
+            if last.end == next.start =>
+        {
+            last.end = next.end;
+        }
+        _ => batches.push(batch),
+    }
+}
+
 impl GpuRenderer {
     /// 新しいGPUレンダラーを作成
     pub async fn new(window: Arc<Window>, font_path: Option<&str>) -> Result<Self> {
@@ -91,121 +245,113 @@ impl GpuRenderer {
             })
             .await?;
 
-        // デバイスとキューの作成
-        let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor {
-                label: None,
-                required_features: wgpu::Features::empty(),
-                required_limits: wgpu::Limits::default(),
-                experimental_features: Default::default(),
-                memory_hints: wgpu::MemoryHints::default(),
-                trace: Default::default(),
-            })
-            .await?;
+        let (device, queue) = request_device(&adapter).await?;
 
         // サーフェス設定
         // フレームバッファ設定（解像度・フォーマットなど）
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_caps.formats[0]);
+        let config = surface_config(&surface, &adapter, size);
+        surface.configure(&device, &config);
+
+        Self::from_parts(
+            instance,
+            Some(surface),
+            (device, queue),
+            config,
+            size,
+            scale_factor,
+            font_path,
+        )
+    }
+
+    /// 呼び出し側で作ったデバイスとサーフェスに描くGPUレンダラーを作成
+    ///
+    /// 他のアプリに組み込むときに使う。サーフェスは config で設定し直し、大きさは
+    /// config の幅と高さにする。デバイスを失ったときは instance から作り直す。
+    pub fn with_surface(
+        instance: wgpu::Instance,
+        surface: wgpu::Surface<'static>,
+        (device, queue): (wgpu::Device, wgpu::Queue),
+        config: wgpu::SurfaceConfiguration,
+        scale_factor: f64,
+    ) -> Result<Self> {
+        surface.configure(&device, &config);
+        let size = winit::dpi::PhysicalSize::new(config.width, config.height);
+        Self::from_parts(
+            instance,
+            Some(surface),
+            (device, queue),
+            config,
+            size,
+            scale_factor,
+            None,
+        )
+    }
+
+    /// ウィンドウを持たないGPUレンダラーを作成
+    ///
+    /// サーフェスを作らず、描画結果は [`GpuRenderer::capture_frame`] で取得する。
+    /// CI やスクリプトからページを画像化する用途を想定している。
+    ///
+    /// # Errors
+    /// 利用可能なアダプターまたはデバイスが見つからない場合
+    pub async fn new_headless(
+        size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f64,
+        font_path: Option<&str>,
+    ) -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+            backends: select_wgpu_backends(),
+            ..Default::default()
+        });
 
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await?;
+
+        let (device, queue) = request_device(&adapter).await?;
+
+        // オフスクリーン描画用の設定（サーフェスには適用しない）
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
             view_formats: vec![],
             desired_maximum_frame_latency: 2,
         };
-        surface.configure(&device, &config);
 
-        // シェーダーの読み込み
-        // シェーダーモジュールの作成
-        // vertex/fragment for main pipeline
-        let main_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Main Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader/main.wgsl").into()),
-        });
-
-        // --- レンダーパイプライン（頂点→ピクセル変換のルール）の作成 ---
-        let render_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[],
-                immediate_size: 0,
-            });
+        Self::from_parts(
+            instance,
+            None,
+            (device, queue),
+            config,
+            size,
+            scale_factor,
+            font_path,
+        )
+    }
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            cache: None,
-            vertex: wgpu::VertexState {
-                module: &main_shader,
-                entry_point: Some("vs_main"),
-                buffers: &[Vertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &main_shader,
-                entry_point: Some("fs_main"),
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None, // 三角扇がカリングで消えちゃう...
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview_mask: None,
-        });
-        // --- レンダーパイプライン作成終了 ---
-
-        // テキスト描画用ラッパーの初期化。引数で渡されたフォントパスがあればそれを優先して読み込む。
-        let text_renderer = if let Some(p) = font_path {
-            match std::fs::read(p) {
-                Ok(bytes) => {
-                    match TextRenderer::new_from_bytes(&device, &queue, config.format, bytes) {
-                        Ok(t) => Some(t),
-                        Err(e) => {
-                            log::warn!(target:"PRender::gpu::font" ,"failed to init text renderer from provided font: {}", e);
-                            None
-                        }
-                    }
-                }
-                Err(e) => {
-                    log::warn!(target:"PRender::gpu::font" ,"failed to read font path '{}': {}", p, e);
-                    None
-                }
-            }
-        } else {
-            match TextRenderer::new_from_device(&device, &queue, config.format) {
-                Ok(t) => Some(t),
-                Err(e) => {
-                    log::warn!(target:"PRender::gpu::font" ,"no system font found for text renderer: {}", e);
-                    None
-                }
-            }
-        };
+    /// デバイス・設定からパイプラインとテキストレンダラーを構築する
+    fn from_parts(
+        instance: wgpu::Instance,
+        surface: Option<wgpu::Surface<'static>>,
+        (device, queue): (wgpu::Device, wgpu::Queue),
+        config: wgpu::SurfaceConfiguration,
+        size: winit::dpi::PhysicalSize<u32>,
+        scale_factor: f64,
+        font_path: Option<&str>,
+    ) -> Result<Self> {
+        let device_lost = watch_device_lost(&device);
+        let (render_pipeline, rect_pipeline) = create_pipelines(&device, config.format);
+        let (image_pipeline, image_bind_group_layout, image_sampler) =
+            create_image_pipeline(&device, config.format);
+        let text_renderer = create_text_renderer(&device, &queue, &config, font_path);
 
         // Enable text culling by default, allow override by env var
         let enable_text_culling = std::env::var("ORINIUM_TEXT_CULL")
@@ -213,18 +359,35 @@ impl GpuRenderer {
             .unwrap_or(true);
 
         Ok(Self {
+            instance,
             surface,
             device,
             queue,
+            device_lost,
             config,
             size,
             scale_factor,
             render_pipeline,
             vertex_buffer: None,
             vertices: vec![],
-            num_vertices: 0,
+            rect_pipeline,
+            instance_buffer: None,
+            rect_instances: vec![],
+            image_pipeline,
+            image_bind_group_layout,
+            image_sampler,
+            image_instance_buffer: None,
+            image_instances: vec![],
+            image_textures: vec![],
+            batches: vec![],
             text_renderer,
+            font_path: font_path.map(str::to_string),
+            commands: vec![],
             enable_text_culling,
+            debug_overlay: false,
+            frame_stats: FrameStats::new(),
+            frame_counts: FrameCounts::default(),
+            frame_started: None,
         })
     }
 
@@ -240,7 +403,9 @@ impl GpuRenderer {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
 
-            self.surface.configure(&self.device, &self.config);
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
 
             self.update_vertices(old_size, new_size);
 
@@ -259,8 +424,39 @@ impl GpuRenderer {
         let screen_width = self.size.width as f32;
         let screen_height = self.size.height as f32;
 
+        // デバイスを作り直したときに同じ内容を描けるよう取っておく
+        self.commands.clear();
+        self.commands.extend_from_slice(commands);
+
+        let now = Instant::now();
+        self.frame_started = Some(now);
+        let draw_commands = commands.len();
+        // デバッグ表示は物理ピクセルで作り、ページの倍率を打ち消して重ねる
+        let commands: Cow<[DrawCommand]> = if self.debug_overlay {
+            let factor = 1.0 / self.scale_factor as f32;
+            let overlay = frame_stats::overlay_commands(
+                &self.frame_stats,
+                &self.frame_counts,
+                now,
+                screen_width,
+            );
+            let mut all = commands.to_vec();
+            all.extend(overlay.into_iter().map(|c| c.scaled(factor)));
+            Cow::Owned(all)
+        } else {
+            Cow::Borrowed(commands)
+        };
+
         // --- 頂点データ ---
         let mut vertices = Vec::new();
+        // --- 矩形インスタンス ---
+        let mut rect_instances: Vec<RectInstance> = Vec::new();
+        // --- 画像 ---
+        let mut image_instances: Vec<ImageInstance> = Vec::new();
+        let mut image_textures: Vec<ImageTexture> = Vec::new();
+        let mut previous_textures = std::mem::take(&mut self.image_textures);
+        // --- 描画順 ---
+        let mut batches: Vec<ShapeBatch> = Vec::new();
         // --- Text ---
         let mut sections: Vec<TextSection> = Vec::new();
         // --- scale_factor ---
@@ -292,7 +488,7 @@ impl GpuRenderer {
         }];
         let current_clip = |stack: &Vec<ClipRect>| -> ClipRect { *stack.last().unwrap() };
 
-        for command in commands {
+        for command in commands.iter() {
             match command {
                 // Transform (Push / Pop)
                 DrawCommand::PushTransform { dx, dy } => {
@@ -382,18 +578,13 @@ impl GpuRenderer {
                     let px2 = ndc(x2, screen_width);
                     let py2 = -ndc(y2, screen_height);
 
-                    let color = color.to_linear_f32_array();
-
-                    #[rustfmt::skip]
-                    vertices.extend_from_slice(&[
-                        Vertex { position: [px1, py1, 0.0], color },
-                        Vertex { position: [px1, py2, 0.0], color },
-                        Vertex { position: [px2, py1, 0.0], color },
-
-                        Vertex { position: [px2, py1, 0.0], color },
-                        Vertex { position: [px1, py2, 0.0], color },
-                        Vertex { position: [px2, py2, 0.0], color },
-                    ]);
+                    let index = rect_instances.len() as u32;
+                    rect_instances.push(RectInstance {
+                        position: [px1, py1],
+                        size: [px2 - px1, py2 - py1],
+                        color: color.to_linear_f32_array(),
+                    });
+                    push_batch(&mut batches, ShapeBatch::Rects(index..index + 1));
                 }
 
                 // Text
@@ -467,6 +658,7 @@ impl GpuRenderer {
                             clip_origin: (clip.x * sf, clip.y * sf),
                             bounds: (tw * sf, th * sf),
                             buffer,
+                            synthetic_bold: tr.synthetic_bold_offset(&render_text_style),
                         }
                     } else {
                         // No text renderer available; skip
@@ -608,6 +800,7 @@ impl GpuRenderer {
 
                     let color_arr = color.to_linear_f32_array();
 
+                    let first_vertex = vertices.len() as u32;
                     let v0 = transformed_points[0];
                     for i in 1..(transformed_points.len() - 1) {
                         let tri = vec![v0, transformed_points[i], transformed_points[i + 1]];
@@ -657,6 +850,82 @@ impl GpuRenderer {
                             });
                         }
                     }
+                    let last_vertex = vertices.len() as u32;
+                    if last_vertex > first_vertex {
+                        push_batch(
+                            &mut batches,
+                            ShapeBatch::Triangles(first_vertex..last_vertex),
+                        );
+                    }
+                }
+
+                // Image
+                DrawCommand::DrawImage {
+                    x,
+                    y,
+                    width: w,
+                    height: h,
+                    image,
+                } => {
+                    // transform
+                    let (tdx, tdy) = current_transform(&transform_stack);
+                    let x1 = (x + tdx) * sf;
+                    let y1 = (y + tdy) * sf;
+                    let x2 = x1 + w * sf;
+                    let y2 = y1 + h * sf;
+
+                    // clip 取得
+                    let clip = current_clip(&clip_stack);
+                    let cx1 = x1.max(clip.x * sf);
+                    let cy1 = y1.max(clip.y * sf);
+                    let cx2 = x2.min((clip.x + clip.w) * sf);
+                    let cy2 = y2.min((clip.y + clip.h) * sf);
+
+                    // 完全に外・空の画像なら skip
+                    if cx2 <= cx1 || cy2 <= cy1 || image.width() == 0 || image.height() == 0 {
+                        continue;
+                    }
+
+                    // 切り抜いた分だけテクスチャ座標も縮める
+                    let u1 = (cx1 - x1) / (x2 - x1);
+                    let v1 = (cy1 - y1) / (y2 - y1);
+                    let u2 = (cx2 - x1) / (x2 - x1);
+                    let v2 = (cy2 - y1) / (y2 - y1);
+
+                    // NDC
+                    let ndc = |v, max| (v / max) * 2.0 - 1.0;
+
+                    let px1 = ndc(cx1, screen_width);
+                    let py1 = -ndc(cy1, screen_height);
+                    let px2 = ndc(cx2, screen_width);
+                    let py2 = -ndc(cy2, screen_height);
+
+                    let texture = match image_textures
+                        .iter()
+                        .position(|t| Arc::ptr_eq(&t.image, image))
+                    {
+                        Some(texture) => texture,
+                        None => {
+                            let texture = match previous_textures
+                                .iter()
+                                .position(|t| Arc::ptr_eq(&t.image, image))
+                            {
+                                Some(i) => previous_textures.swap_remove(i),
+                                None => self.create_image_texture(image),
+                            };
+                            image_textures.push(texture);
+                            image_textures.len() - 1
+                        }
+                    };
+
+                    let instance = image_instances.len() as u32;
+                    image_instances.push(ImageInstance {
+                        position: [px1, py1],
+                        size: [px2 - px1, py2 - py1],
+                        uv_position: [u1, v1],
+                        uv_size: [u2 - u1, v2 - v1],
+                    });
+                    push_batch(&mut batches, ShapeBatch::Image { instance, texture });
                 }
 
                 // Ellipse
@@ -680,7 +949,18 @@ impl GpuRenderer {
             }
         }
 
+        self.frame_counts = FrameCounts {
+            draw_commands,
+            vertices: vertices.len(),
+            rect_instances: rect_instances.len() + image_instances.len(),
+            text_sections: sections.len(),
+        };
         self.set_vertex_buffer(vertices);
+        self.set_instance_buffer(rect_instances);
+        self.set_image_instance_buffer(image_instances);
+        // このフレームで使わなかったテクスチャはここで捨てる
+        self.image_textures = image_textures;
+        self.batches = batches;
 
         // テキストセクションをキューに追加
         if let Some(tr) = &mut self.text_renderer {
@@ -689,9 +969,31 @@ impl GpuRenderer {
     }
 
     /// フレームを描画
+    ///
+    /// サーフェスが古くなった・失われたときは設定し直して取り直し、デバイスが失われていたら
+    /// 作り直してから描く。フレームバッファの取得が時間切れになったときはこのフレームを飛ばす。
     pub fn render(&mut self) -> Result<()> {
+        if self.device_lost.load(Ordering::Relaxed) {
+            self.recover_device()?;
+        }
+
         // 描画するフレームバッファを取得
-        let output = self.surface.get_current_texture()?;
+        let Some(surface) = &self.surface else {
+            anyhow::bail!("render() requires a surface; use capture_frame() in headless mode");
+        };
+        let output = match surface.get_current_texture() {
+            Ok(output) => output,
+            Err(wgpu::SurfaceError::Outdated | wgpu::SurfaceError::Lost) => {
+                log::info!(target:"PRender::gpu::surface", "Surface outdated or lost; reconfiguring");
+                surface.configure(&self.device, &self.config);
+                surface.get_current_texture()?
+            }
+            Err(wgpu::SurfaceError::Timeout) => {
+                log::debug!(target:"PRender::gpu::surface", "Timed out acquiring the next frame; skipping it");
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
         let view = output
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -706,12 +1008,185 @@ impl GpuRenderer {
                 label: Some("Render Encoder"),
             });
 
+        self.encode_passes(&mut encoder, &view);
+
+        // コマンドをGPUに送信
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // フレームを画面に表示
+        output.present();
+
+        if let Some(started) = self.frame_started.take() {
+            self.frame_stats.record(Instant::now(), started.elapsed());
+        }
+
+        Ok(())
+    }
+
+    /// 現在の描画内容をオフスクリーンテクスチャに描画し、RGBA ピクセルとして読み出す
+    ///
+    /// サーフェスには一切触れないため、表示中のフレームに影響を与えずに
+    /// スクリーンショットやレイアウトの回帰テストに利用できる。
+    ///
+    /// # Errors
+    /// - バッファのマップに失敗した場合
+    /// - 読み出したピクセル数がテクスチャサイズと一致しない場合
+    pub fn capture_frame(&mut self) -> Result<image::RgbaImage> {
+        if self.device_lost.load(Ordering::Relaxed) {
+            self.recover_device()?;
+        }
+
+        let width = self.config.width.max(1);
+        let height = self.config.height.max(1);
+        let format = self.config.format;
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Capture Texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // copy_texture_to_buffer は 1 行あたりのバイト数が 256 の倍数である必要がある
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let output_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Capture Encoder"),
+            });
+
+        self.encode_passes(&mut encoder, &view);
+
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &output_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // バッファをマップして GPU の完了を待つ
+        let slice = output_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| anyhow::anyhow!("failed to poll device: {e}"))?;
+        rx.recv()
+            .map_err(|e| anyhow::anyhow!("capture buffer callback dropped: {e}"))?
+            .map_err(|e| anyhow::anyhow!("failed to map capture buffer: {e}"))?;
+
+        let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+            }
+        }
+        output_buffer.unmap();
+
+        // BGRA 系のフォーマットは RGBA に並べ替える
+        if matches!(
+            format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for px in pixels.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+        }
+
+        image::RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("captured pixel buffer size mismatch"))
+    }
+
+    /// 失われたデバイスを作り直す
+    ///
+    /// アダプターから取り直し、パイプライン・テキストレンダラー・頂点バッファを作り直して
+    /// サーフェスを設定し直す。最後に受け取った描画コマンドを解析し直すので、
+    /// 呼び出し元から描画コマンドを送り直さなくても同じ内容が描ける。
+    fn recover_device(&mut self) -> Result<()> {
+        log::warn!(target:"PRender::gpu::device", "Recreating the GPU device");
+        let adapter =
+            pollster::block_on(self.instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: self.surface.as_ref(),
+                force_fallback_adapter: false,
+            }))?;
+        let (device, queue) = pollster::block_on(request_device(&adapter))?;
+
+        if let Some(surface) = &self.surface {
+            // 別のアダプターになったときはフォーマットが変わることがある
+            self.config = surface_config(surface, &adapter, self.size);
+            surface.configure(&device, &self.config);
+        }
+
+        (self.render_pipeline, self.rect_pipeline) = create_pipelines(&device, self.config.format);
+        (
+            self.image_pipeline,
+            self.image_bind_group_layout,
+            self.image_sampler,
+        ) = create_image_pipeline(&device, self.config.format);
+        self.text_renderer =
+            create_text_renderer(&device, &queue, &self.config, self.font_path.as_deref());
+        self.device_lost = watch_device_lost(&device);
+        self.device = device;
+        self.queue = queue;
+
+        // バッファは古いデバイスのものなので、描画コマンドから作り直す
+        self.vertex_buffer = None;
+        self.instance_buffer = None;
+        self.image_instance_buffer = None;
+        self.image_textures.clear();
+        let commands = std::mem::take(&mut self.commands);
+        self.parse_draw_commands(&commands);
+        Ok(())
+    }
+
+    /// 図形パスとテキストパスを指定されたビューに対してエンコードする
+    fn encode_passes(&mut self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
         // 描画パスの開始
         {
             let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         // 背景色をクリア
@@ -731,12 +1206,39 @@ impl GpuRenderer {
                 multiview_mask: None,
             });
 
-            // 使用するシェーダー・設定をセット
-            render_pass.set_pipeline(&self.render_pipeline);
-            // 頂点バッファをセットして描画
-            if let Some(ref vertex_buffer) = self.vertex_buffer {
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.draw(0..self.num_vertices, 0..1);
+            // 描画順を保ったままバッチごとにパイプラインを切り替えて描画
+            for batch in &self.batches {
+                match batch {
+                    ShapeBatch::Rects(range) => {
+                        let Some(instance_buffer) = &self.instance_buffer else {
+                            continue;
+                        };
+                        render_pass.set_pipeline(&self.rect_pipeline);
+                        render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                        // 1 インスタンスあたり 6 頂点（三角形 2 つ）
+                        render_pass.draw(0..6, range.clone());
+                    }
+                    ShapeBatch::Triangles(range) => {
+                        let Some(vertex_buffer) = &self.vertex_buffer else {
+                            continue;
+                        };
+                        render_pass.set_pipeline(&self.render_pipeline);
+                        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                        render_pass.draw(range.clone(), 0..1);
+                    }
+                    ShapeBatch::Image { instance, texture } => {
+                        let (Some(image_instance_buffer), Some(texture)) = (
+                            &self.image_instance_buffer,
+                            self.image_textures.get(*texture),
+                        ) else {
+                            continue;
+                        };
+                        render_pass.set_pipeline(&self.image_pipeline);
+                        render_pass.set_bind_group(0, &texture.bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, image_instance_buffer.slice(..));
+                        render_pass.draw(0..6, *instance..instance + 1);
+                    }
+                }
             }
         }
 
@@ -745,7 +1247,7 @@ impl GpuRenderer {
             let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Text Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
+                    view,
                     resolve_target: None,
                     ops: wgpu::Operations {
                         load: wgpu::LoadOp::Load,
@@ -760,14 +1262,6 @@ impl GpuRenderer {
             });
             tr.draw(&mut rpass);
         }
-
-        // コマンドをGPUに送信
-        self.queue.submit(std::iter::once(encoder.finish()));
-
-        // フレームを画面に表示
-        output.present();
-
-        Ok(())
     }
 
     fn update_vertices(
@@ -792,6 +1286,38 @@ impl GpuRenderer {
             vertex.position[1] = -((logical_y / new_h) * 2.0 - 1.0);
         }
         self.set_vertex_buffer(new_vertices);
+
+        let mut new_instances = self.rect_instances.clone();
+
+        for rect in new_instances.iter_mut() {
+            // サイズは NDC 上の長さなので原点を足さずにスケールだけ掛ける
+            let logical_x = (rect.position[0] + 1.0) / 2.0 * old_w;
+            let logical_y = -(rect.position[1] - 1.0) / 2.0 * old_h;
+            let logical_w = rect.size[0] / 2.0 * old_w;
+            let logical_h = -rect.size[1] / 2.0 * old_h;
+
+            rect.position[0] = (logical_x / new_w) * 2.0 - 1.0;
+            rect.position[1] = -((logical_y / new_h) * 2.0 - 1.0);
+            rect.size[0] = logical_w / new_w * 2.0;
+            rect.size[1] = -(logical_h / new_h * 2.0);
+        }
+        self.set_instance_buffer(new_instances);
+
+        let mut new_images = self.image_instances.clone();
+
+        for image in new_images.iter_mut() {
+            // テクスチャ座標はそのままで、位置とサイズだけ矩形と同じように直す
+            let logical_x = (image.position[0] + 1.0) / 2.0 * old_w;
+            let logical_y = -(image.position[1] - 1.0) / 2.0 * old_h;
+            let logical_w = image.size[0] / 2.0 * old_w;
+            let logical_h = -image.size[1] / 2.0 * old_h;
+
+            image.position[0] = (logical_x / new_w) * 2.0 - 1.0;
+            image.position[1] = -((logical_y / new_h) * 2.0 - 1.0);
+            image.size[0] = logical_w / new_w * 2.0;
+            image.size[1] = -(logical_h / new_h * 2.0);
+        }
+        self.set_image_instance_buffer(new_images);
     }
 
     fn set_vertex_buffer(&mut self, vertices: Vec<Vertex>) {
@@ -804,16 +1330,377 @@ impl GpuRenderer {
                     usage: wgpu::BufferUsages::VERTEX,
                 },
             ));
-            self.num_vertices = vertices.len() as u32;
         }
         self.vertices = vertices;
     }
 
+    fn set_instance_buffer(&mut self, instances: Vec<RectInstance>) {
+        // 矩形インスタンスバッファを登録
+        if !instances.is_empty() {
+            self.instance_buffer = Some(self.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Rect Instance Buffer"),
+                    contents: bytemuck::cast_slice(&instances),
+                    usage: wgpu::BufferUsages::VERTEX,
+                },
+            ));
+        }
+        self.rect_instances = instances;
+    }
+
+    fn set_image_instance_buffer(&mut self, instances: Vec<ImageInstance>) {
+        // 画像インスタンスバッファを登録
+        if !instances.is_empty() {
+            self.image_instance_buffer = Some(self.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Image Instance Buffer"),
+                    contents: bytemuck::cast_slice(&instances),
+                    usage: wgpu::BufferUsages::VERTEX,
+                },
+            ));
+        }
+        self.image_instances = instances;
+    }
+
+    /// 画像をテクスチャに書き込み、描画に使うバインドグループを作る
+    fn create_image_texture(&self, image: &Arc<RgbaImage>) -> ImageTexture {
+        let (width, height) = image.dimensions();
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            image.as_raw(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Image Bind Group"),
+            layout: &self.image_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.image_sampler),
+                },
+            ],
+        });
+        ImageTexture {
+            image: Arc::clone(image),
+            bind_group,
+        }
+    }
+
+    /// 物理ピクセルと CSS ピクセルの比（ウィンドウの倍率 × ズーム）を変える
+    ///
+    /// 変わったら前の倍率でラスタライズしたグリフをアトラスから捨てられるようにし、
+    /// 次のフレームで新しい大きさのグリフを作らせる。
     pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor == self.scale_factor {
+            return;
+        }
         self.scale_factor = scale_factor;
+        if let Some(tr) = &mut self.text_renderer {
+            tr.discard_glyphs();
+        }
+    }
+
+    /// FPS・フレーム時間の百分位数・描いたものの数の表示を出すかどうか
+    ///
+    /// 数は前のフレームのもの。描画コマンドの数はページとブラウザ UI の分だけで、
+    /// 頂点などの数には表示そのものの分も入る。
+    pub fn set_debug_overlay(&mut self, enabled: bool) {
+        self.debug_overlay = enabled;
+    }
+
+    pub fn debug_overlay(&self) -> bool {
+        self.debug_overlay
+    }
+
+    /// 最近のフレームにかかった時間
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// 前のフレームで描いたものの数
+    pub fn frame_counts(&self) -> FrameCounts {
+        self.frame_counts
     }
 }
 
+/// 図形描画用のレンダーパイプラインを作成する
+///
+/// 多角形（頂点単位）と矩形（インスタンス単位）で頂点レイアウトとシェーダーだけが異なる
+fn create_shape_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    buffer: wgpu::VertexBufferLayout<'static>,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        cache: None,
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: Some("vs_main"),
+            buffers: &[buffer],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None, // 三角扇がカリングで消えちゃう...
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview_mask: None,
+    })
+}
+
+/// 多角形用と矩形用のパイプラインを作成する
+fn create_pipelines(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    // シェーダーの読み込み
+    // シェーダーモジュールの作成
+    // vertex/fragment for main pipeline
+    let main_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Main Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shader/main.wgsl").into()),
+    });
+
+    // --- レンダーパイプライン（頂点→ピクセル変換のルール）の作成 ---
+    let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Render Pipeline Layout"),
+        bind_group_layouts: &[],
+        immediate_size: 0,
+    });
+
+    // 多角形用（三角形リスト）
+    let render_pipeline = create_shape_pipeline(
+        device,
+        "Render Pipeline",
+        &render_pipeline_layout,
+        &main_shader,
+        Vertex::desc(),
+        format,
+    );
+
+    // 矩形用（インスタンス描画）
+    let rect_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Rect Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shader/rect.wgsl").into()),
+    });
+    let rect_pipeline = create_shape_pipeline(
+        device,
+        "Rect Pipeline",
+        &render_pipeline_layout,
+        &rect_shader,
+        RectInstance::desc(),
+        format,
+    );
+    // --- レンダーパイプライン作成終了 ---
+
+    (render_pipeline, rect_pipeline)
+}
+
+/// 画像用のパイプラインと、テクスチャ・サンプラーのバインドグループレイアウト、サンプラーを作成する
+fn create_image_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> (wgpu::RenderPipeline, wgpu::BindGroupLayout, wgpu::Sampler) {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Image Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Image Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        immediate_size: 0,
+    });
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Image Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("shader/image.wgsl").into()),
+    });
+    let pipeline = create_shape_pipeline(
+        device,
+        "Image Pipeline",
+        &layout,
+        &shader,
+        ImageInstance::desc(),
+        format,
+    );
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("Image Sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+    (pipeline, bind_group_layout, sampler)
+}
+
+/// テキスト描画用ラッパーの初期化。フォントパスがあればそれを優先して読み込む。
+///
+/// フォントが見つからなければテキストは描かない（None）
+fn create_text_renderer(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    config: &wgpu::SurfaceConfiguration,
+    font_path: Option<&str>,
+) -> Option<TextRenderer> {
+    let mut text_renderer = if let Some(p) = font_path {
+        match std::fs::read(p) {
+            Ok(bytes) => match TextRenderer::new_from_bytes(device, queue, config.format, bytes) {
+                Ok(t) => Some(t),
+                Err(e) => {
+                    log::warn!(target:"PRender::gpu::font" ,"failed to init text renderer from provided font: {}", e);
+                    None
+                }
+            },
+            Err(e) => {
+                log::warn!(target:"PRender::gpu::font" ,"failed to read font path '{}': {}", p, e);
+                None
+            }
+        }
+    } else {
+        match TextRenderer::new_from_device(device, queue, config.format) {
+            Ok(t) => Some(t),
+            Err(e) => {
+                log::warn!(target:"PRender::gpu::font" ,"no system font found for text renderer: {}", e);
+                None
+            }
+        }
+    };
+
+    // 初回の Resized イベントを待たずに描画できるようビューポートを合わせておく
+    if let Some(tr) = &mut text_renderer {
+        tr.resize_view(config.width as f32, config.height as f32, queue);
+    }
+    text_renderer
+}
+
+/// アダプターが対応するフォーマットなどからサーフェス設定を作る
+fn surface_config(
+    surface: &wgpu::Surface,
+    adapter: &wgpu::Adapter,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> wgpu::SurfaceConfiguration {
+    let surface_caps = surface.get_capabilities(adapter);
+    let surface_format = surface_caps
+        .formats
+        .iter()
+        .copied()
+        .find(|f| f.is_srgb())
+        .unwrap_or(surface_caps.formats[0]);
+
+    wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: surface_format,
+        width: size.width,
+        height: size.height,
+        present_mode: surface_caps.present_modes[0],
+        alpha_mode: surface_caps.alpha_modes[0],
+        view_formats: vec![],
+        desired_maximum_frame_latency: 2,
+    }
+}
+
+/// デバイスが失われたら立つフラグを返す（こちらで捨てたときは立てない）
+fn watch_device_lost(device: &wgpu::Device) -> Arc<AtomicBool> {
+    let lost = Arc::new(AtomicBool::new(false));
+    let flag = lost.clone();
+    device.set_device_lost_callback(move |reason, message| {
+        if !matches!(reason, wgpu::DeviceLostReason::Destroyed) {
+            log::error!(target:"PRender::gpu::device", "GPU device lost: {}", message);
+            flag.store(true, Ordering::Relaxed);
+        }
+    });
+    lost
+}
+
+/// 論理デバイスとキューを作成する
+async fn request_device(adapter: &wgpu::Adapter) -> Result<(wgpu::Device, wgpu::Queue)> {
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features: wgpu::Features::empty(),
+            required_limits: wgpu::Limits::default(),
+            experimental_features: Default::default(),
+            memory_hints: wgpu::MemoryHints::default(),
+            trace: Default::default(),
+        })
+        .await?;
+    Ok((device, queue))
+}
+
 fn select_wgpu_backends() -> wgpu::Backends {
     if let Ok(value) = env::var("ORINIUM_WGPU_BACKEND") {
         match value.to_lowercase().as_str() {
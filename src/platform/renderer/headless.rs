@@ -0,0 +1,59 @@
+//! ウィンドウを持たないレンダリングターゲット
+//!
+//! winit のウィンドウやサーフェスを作らずに `DrawCommand` 列を画像化する。
+//! CI やスクリプトからページを PNG に書き出す用途で使う。
+
+use anyhow::Result;
+
+use super::compositor::Compositor;
+use super::gpu::GpuRenderer;
+use super::software::SoftwareRenderer;
+use crate::engine::renderer_model::DrawCommand;
+
+/// サーフェスを持たない GPU レンダラー
+pub struct HeadlessRenderer {
+    gpu: GpuRenderer,
+}
+
+impl HeadlessRenderer {
+    /// 指定サイズ（物理ピクセル）のヘッドレスレンダラーを作成する
+    ///
+    /// # Errors
+    /// 利用可能な GPU アダプターが見つからない場合
+    pub async fn new(size: (u32, u32), scale_factor: f64) -> Result<Self> {
+        let size = winit::dpi::PhysicalSize::new(size.0.max(1), size.1.max(1));
+        let gpu = GpuRenderer::new_headless(size, scale_factor, None).await?;
+        Ok(Self { gpu })
+    }
+
+    /// 描画命令をオフスクリーンに描画して RGBA 画像として返す
+    pub fn render(&mut self, commands: &[DrawCommand]) -> Result<image::RgbaImage> {
+        self.gpu.parse_draw_commands(commands);
+        self.gpu.capture_frame()
+    }
+
+    /// 内部の [`GpuRenderer`] への参照を返す
+    pub fn gpu(&mut self) -> &mut GpuRenderer {
+        &mut self.gpu
+    }
+}
+
+/// 描画命令を size（物理ピクセル）のオフスクリーンに描画して RGBA 画像として返す
+///
+/// GPU のアダプターが見つからなければ [`SoftwareRenderer`] で描く。
+pub fn render_offscreen(
+    size: (u32, u32),
+    scale_factor: f64,
+    commands: &[DrawCommand],
+) -> Result<image::RgbaImage> {
+    match pollster::block_on(HeadlessRenderer::new(size, scale_factor)) {
+        Ok(mut renderer) => renderer.render(commands),
+        Err(e) => {
+            log::info!("No usable GPU ({:#}); rendering in software", e);
+            let size = winit::dpi::PhysicalSize::new(size.0, size.1);
+            let mut renderer = SoftwareRenderer::new_headless(size, scale_factor);
+            renderer.parse_draw_commands(commands);
+            renderer.capture_frame()
+        }
+    }
+}
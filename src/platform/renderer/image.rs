@@ -2,9 +2,12 @@
 
 use anyhow::{Context, Result};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 
+use crate::browser::core::schemes::SchemeRegistry;
+use crate::platform::memory::{self, Subsystem, TrackedMemory};
+
 pub struct ImageHandle {
     /// テクスチャID
     pub id: u64,
@@ -23,6 +26,8 @@ pub struct ImageManager {
     counter: AtomicU64,
     /// 画像メタデータのマップ
     images: HashMap<u64, ImageMetadata>,
+    /// デコードしたテクスチャの使用量
+    memory: Arc<TrackedMemory>,
 }
 
 struct ImageMetadata {
@@ -38,13 +43,18 @@ struct ImageMetadata {
 
 impl ImageManager {
     pub fn new() -> Self {
+        let memory = TrackedMemory::new(Subsystem::DecodedImages);
+        memory::registry().register(&memory);
         Self {
             counter: AtomicU64::new(1),
             images: HashMap::new(),
+            memory,
         }
     }
 
     /// URIから画像を読み込み、テクスチャとして登録する
+    ///
+    /// URI は [`SchemeRegistry`] で読む（`resource:///`、`file://`、`data:` とパス）。
     pub fn load_from_uri(
         &mut self,
         device: &wgpu::Device,
@@ -52,16 +62,9 @@ impl ImageManager {
         uri: &str,
         label: Option<&str>,
     ) -> Result<ImageHandle> {
-        if !uri.starts_with("resource:///") {
-            anyhow::bail!("Only resource:/// URIs are supported by ImageManager");
-        }
-        // strip scheme
-        let rel = uri.trim_start_matches("resource:///");
-        let mut path = PathBuf::from("resource");
-        path.push(rel);
-
-        let bytes = std::fs::read(&path)
-            .with_context(|| format!("failed to read resource file: {}", path.display()))?;
+        let bytes = SchemeRegistry::new()
+            .load_uri(uri)
+            .with_context(|| format!("failed to load image: {}", uri))?;
         let img = image::load_from_memory(&bytes).context("failed to decode image")?;
         let rgba = img.to_rgba8();
         let (width, height) = rgba.dimensions();
@@ -121,6 +124,7 @@ impl ImageManager {
                 sampler: sampler.clone(),
             },
         );
+        self.update_memory();
 
         Ok(ImageHandle {
             id,
@@ -140,4 +144,20 @@ impl ImageManager {
     pub fn get_view_sampler(&self, id: u64) -> Option<(&wgpu::TextureView, &wgpu::Sampler)> {
         self.images.get(&id).map(|m| (&m.view, &m.sampler))
     }
+
+    /// 画像を捨てる
+    pub fn remove(&mut self, id: u64) {
+        self.images.remove(&id);
+        self.update_memory();
+    }
+
+    /// RGBA8 のテクスチャとして数える
+    fn update_memory(&self) {
+        let bytes = self
+            .images
+            .values()
+            .map(|m| m.width as usize * m.height as usize * 4)
+            .sum();
+        self.memory.set(bytes);
+    }
 }
@@ -1,5 +1,11 @@
+pub mod compositor;
+pub mod frame_stats;
 mod glyph;
 pub mod gpu;
+pub mod headless;
 mod image;
+pub mod pdf;
 pub(crate) mod scroll_bar;
+pub mod software;
+mod text_cache;
 pub mod text_measurer;
@@ -0,0 +1,312 @@
+//! 描画コマンドを PDF にする
+//!
+//! ページごとの `DrawCommand` 列を PDF のページの内容ストリームに変換する。
+//! 文字は PDF の標準フォント（Helvetica）で描くので、フォントは埋め込まない。
+//! WinAnsiEncoding にない文字（日本語など）は `?` になる。画像は RGB と透明度の
+//! マスクに分けて埋め込み、同じ画像は 1 度だけ書く。
+
+use std::io::Write;
+use std::sync::Arc;
+
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use image::RgbaImage;
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str, TextStr};
+
+use crate::engine::layouter::types::{Color, FontStyle, FontWeight, TextStyle};
+use crate::engine::renderer_model::{DrawCommand, paginate::PaperSize};
+
+/// CSS px あたりの PDF の pt（1in = 96px = 72pt）
+const PT_PER_PX: f32 = 0.75;
+
+/// テキストの行の上端からベースラインまで（font-size に対する割合）
+const BASELINE_RATIO: f32 = 0.8;
+
+/// 楕円を 4 本のベジェ曲線で近似するときの制御点の位置
+const KAPPA: f32 = 0.552_284_8;
+
+/// 標準フォントの名前（通常、太字、斜体、太字斜体）
+const FONTS: [(&[u8], &[u8]); 4] = [
+    (b"F1", b"Helvetica"),
+    (b"F2", b"Helvetica-Bold"),
+    (b"F3", b"Helvetica-Oblique"),
+    (b"F4", b"Helvetica-BoldOblique"),
+];
+
+/// pages（各ページの中身の左上が原点の描画コマンド）を paper の大きさの PDF にする
+pub fn write_pdf(pages: &[Vec<DrawCommand>], paper: PaperSize, title: &str) -> Vec<u8> {
+    let mut pdf = Pdf::new();
+    let mut next_id = Ref::new(1);
+    let mut alloc = || next_id.bump();
+
+    let catalog_id = alloc();
+    let page_tree_id = alloc();
+    let info_id = alloc();
+    let font_ids: Vec<Ref> = FONTS.iter().map(|_| alloc()).collect();
+    let page_ids: Vec<(Ref, Ref)> = pages.iter().map(|_| (alloc(), alloc())).collect();
+    let images = page_images(pages.iter().flatten());
+    // 画像と、その透明度のマスク
+    let image_ids: Vec<(Ref, Ref)> = images.iter().map(|_| (alloc(), alloc())).collect();
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().map(|&(page, _)| page))
+        .count(page_ids.len() as i32);
+    let mut info = pdf.document_info(info_id);
+    if !title.is_empty() {
+        info.title(TextStr(title));
+    }
+    info.producer(TextStr("Orinium Browser"));
+    info.finish();
+
+    for (&(_, base_font), &id) in FONTS.iter().zip(&font_ids) {
+        pdf.type1_font(id)
+            .base_font(Name(base_font))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+    for (image, &(id, mask_id)) in images.iter().zip(&image_ids) {
+        write_image(&mut pdf, image, id, mask_id);
+    }
+
+    let width = paper.width * PT_PER_PX;
+    let height = paper.height * PT_PER_PX;
+    for (commands, &(page_id, content_id)) in pages.iter().zip(&page_ids) {
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, width, height));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        let mut resources = page.resources();
+        let mut fonts = resources.fonts();
+        for (&(name, _), &id) in FONTS.iter().zip(&font_ids) {
+            fonts.pair(Name(name), id);
+        }
+        fonts.finish();
+        let used = page_images(commands);
+        if !used.is_empty() {
+            let mut x_objects = resources.x_objects();
+            for (i, &(id, _)) in image_ids.iter().enumerate() {
+                if used.iter().any(|image| Arc::ptr_eq(image, &images[i])) {
+                    x_objects.pair(Name(image_name(i).as_bytes()), id);
+                }
+            }
+            x_objects.finish();
+        }
+        resources.finish();
+        page.finish();
+
+        let content = page_content(commands, paper, &images);
+        pdf.stream(content_id, &content);
+    }
+    pdf.finish()
+}
+
+/// commands が描く画像（同じ画像は 1 つにまとめる）
+fn page_images<'a>(commands: impl IntoIterator<Item = &'a DrawCommand>) -> Vec<Arc<RgbaImage>> {
+    let mut images: Vec<Arc<RgbaImage>> = Vec::new();
+    for command in commands {
+        if let DrawCommand::DrawImage { image, .. } = command
+            && !images.iter().any(|i| Arc::ptr_eq(i, image))
+        {
+            images.push(image.clone());
+        }
+    }
+    images
+}
+
+/// 文書の i 番目の画像のリソース名
+fn image_name(i: usize) -> String {
+    format!("Im{i}")
+}
+
+/// image を id の画像 XObject として書く。透明度は mask_id のマスクにする
+fn write_image(pdf: &mut Pdf, image: &RgbaImage, id: Ref, mask_id: Ref) {
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    let mut rgb = Vec::with_capacity(image.as_raw().len() / 4 * 3);
+    let mut alpha = Vec::with_capacity(image.as_raw().len() / 4);
+    for pixel in image.pixels() {
+        rgb.extend_from_slice(&pixel.0[..3]);
+        alpha.push(pixel.0[3]);
+    }
+
+    let rgb = deflate(&rgb);
+    let mut xobject = pdf.image_xobject(id, &rgb);
+    xobject.filter(Filter::FlateDecode);
+    xobject.width(width);
+    xobject.height(height);
+    xobject.color_space().device_rgb();
+    xobject.bits_per_component(8);
+    xobject.s_mask(mask_id);
+    xobject.finish();
+
+    let alpha = deflate(&alpha);
+    let mut mask = pdf.image_xobject(mask_id, &alpha);
+    mask.filter(Filter::FlateDecode);
+    mask.width(width);
+    mask.height(height);
+    mask.color_space().device_gray();
+    mask.bits_per_component(8);
+    mask.finish();
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    // Vec への書き込みは失敗しない
+    let _ = encoder.write_all(data);
+    encoder.finish().unwrap_or_default()
+}
+
+/// 1 ページの内容ストリーム
+///
+/// images は文書の画像（リソース名の番号の順）。
+fn page_content(commands: &[DrawCommand], paper: PaperSize, images: &[Arc<RgbaImage>]) -> Vec<u8> {
+    let mut content = Content::new();
+    // y 軸を下向きにし、単位を CSS px にして、余白の分ずらす
+    content.transform([
+        PT_PER_PX,
+        0.0,
+        0.0,
+        -PT_PER_PX,
+        paper.margin * PT_PER_PX,
+        (paper.height - paper.margin) * PT_PER_PX,
+    ]);
+
+    for command in commands {
+        match command {
+            DrawCommand::DrawText {
+                x, y, text, style, ..
+            } => draw_text(&mut content, *x, *y, text, style),
+            DrawCommand::DrawRect {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => {
+                if set_fill(&mut content, *color) {
+                    content.rect(*x, *y, *width, *height);
+                    content.fill_nonzero();
+                }
+            }
+            DrawCommand::DrawPolygon { points, color } => {
+                let Some((&(x, y), rest)) = points.split_first() else {
+                    continue;
+                };
+                if set_fill(&mut content, *color) {
+                    content.move_to(x, y);
+                    for &(x, y) in rest {
+                        content.line_to(x, y);
+                    }
+                    content.close_path();
+                    content.fill_nonzero();
+                }
+            }
+            DrawCommand::DrawEllipse {
+                center,
+                radius_x,
+                radius_y,
+                color,
+            } => {
+                if set_fill(&mut content, *color) {
+                    ellipse(&mut content, *center, *radius_x, *radius_y);
+                    content.fill_nonzero();
+                }
+            }
+            DrawCommand::DrawImage {
+                x,
+                y,
+                width,
+                height,
+                image,
+            } => {
+                let Some(i) = images.iter().position(|i| Arc::ptr_eq(i, image)) else {
+                    continue;
+                };
+                content.save_state();
+                // 画像は単位正方形に下から上へ描かれるので、y を反転して (x, y) から下に描く
+                content.transform([*width, 0.0, 0.0, -*height, *x, y + height]);
+                content.x_object(Name(image_name(i).as_bytes()));
+                content.restore_state();
+            }
+            DrawCommand::PushClip {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                content.save_state();
+                content.rect(*x, *y, *width, *height);
+                content.clip_nonzero();
+                content.end_path();
+            }
+            DrawCommand::PushTransform { dx, dy } => {
+                content.save_state();
+                content.transform([1.0, 0.0, 0.0, 1.0, *dx, *dy]);
+            }
+            DrawCommand::PopClip | DrawCommand::PopTransform => {
+                content.restore_state();
+            }
+        }
+    }
+    content.finish()
+}
+
+/// 塗りの色を設定する。透明なら false（描かない）
+fn set_fill(content: &mut Content, color: Color) -> bool {
+    let Color(r, g, b, a) = color;
+    if a == 0 {
+        return false;
+    }
+    content.set_fill_rgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    true
+}
+
+fn draw_text(content: &mut Content, x: f32, y: f32, text: &str, style: &TextStyle) {
+    let encoded = win_ansi(text);
+    if encoded.iter().all(|&b| b == b' ') || !set_fill(content, style.color) {
+        return;
+    }
+    let bold = style.font_weight >= FontWeight(600);
+    let italic = style.font_style != FontStyle::Normal;
+    let font = FONTS[bold as usize + italic as usize * 2].0;
+
+    content.begin_text();
+    content.set_font(Name(font), style.font_size);
+    // ページの y 軸は下向きなので、文字が逆さにならないよう反転し直す
+    content.set_text_matrix([1.0, 0.0, 0.0, -1.0, x, y + style.font_size * BASELINE_RATIO]);
+    content.show(Str(&encoded));
+    content.end_text();
+}
+
+/// 中心 center、半径 (rx, ry) の楕円の経路
+fn ellipse(content: &mut Content, (cx, cy): (f32, f32), rx: f32, ry: f32) {
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    content.move_to(cx + rx, cy);
+    content.cubic_to(cx + rx, cy + ky, cx + kx, cy + ry, cx, cy + ry);
+    content.cubic_to(cx - kx, cy + ry, cx - rx, cy + ky, cx - rx, cy);
+    content.cubic_to(cx - rx, cy - ky, cx - kx, cy - ry, cx, cy - ry);
+    content.cubic_to(cx + kx, cy - ry, cx + rx, cy - ky, cx + rx, cy);
+    content.close_path();
+}
+
+/// 文字列を WinAnsiEncoding のバイト列にする（表せない文字は `?`）
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\t' | '\n' | '\r' => b' ',
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            '™' => 0x99,
+            _ => b'?',
+        })
+        .collect()
+}
@@ -1,4 +1,13 @@
 #![allow(unused)]
+use std::time::{Duration, Instant};
+
+/// 最後にスクロール・ホバーしてから隠し始めるまでの時間
+const IDLE_TIMEOUT: Duration = Duration::from_millis(1000);
+/// 表示にかかる時間（秒）
+const FADE_IN_SECS: f32 = 0.1;
+/// 非表示にかかる時間（秒）
+const FADE_OUT_SECS: f32 = 0.3;
+
 #[derive(Debug, Clone, Copy)]
 pub struct ScrollBar {
     /// スクロールバーのトラックの幅（ピクセル）
@@ -9,6 +18,8 @@ pub struct ScrollBar {
     pub min_thumb: f32,
     /// 色（RGBA）
     pub color: [f32; 4],
+    /// 当たり判定をサムの周りに広げる幅（ピクセル）
+    pub hit_padding: f32,
 }
 
 impl Default for ScrollBar {
@@ -18,6 +29,7 @@ impl Default for ScrollBar {
             margin: 4.0,
             min_thumb: 20.0,
             color: [0.18, 0.18, 0.18, 0.7],
+            hit_padding: 6.0,
         }
     }
 }
@@ -67,6 +79,69 @@ impl ScrollBar {
         Some((x1, y1, x2, y2))
     }
 
+    /// 点 (px,py) がスクロールバーの表示領域（右端・下端の帯）にあるか
+    pub fn is_in_gutter(
+        &self,
+        viewport_width: f32,
+        viewport_height: f32,
+        px: f32,
+        py: f32,
+    ) -> bool {
+        let band = self.width + self.margin * 2.0 + self.hit_padding;
+        px >= viewport_width - band || py >= viewport_height - band
+    }
+
+    /// 横スクロールバーのサムの矩形 (x1, y1, x2, y2)。ビューポートの下端に置く。
+    /// コンテンツの幅がビューポートに収まる場合は None
+    pub fn horizontal_thumb_rect(
+        &self,
+        viewport_width: f32,
+        viewport_height: f32,
+        content_width: f32,
+        scroll_x: f32,
+    ) -> Option<(f32, f32, f32, f32)> {
+        // 縦のサムを x と y を入れ替えて計算する
+        let (y1, x1, y2, x2) =
+            self.thumb_rect(viewport_height, viewport_width, content_width, scroll_x)?;
+        Some((x1, y1, x2, y2))
+    }
+
+    /// サムを delta ピクセル動かしたときのスクロール量
+    ///
+    /// `viewport` / `content` はスクロール方向の長さ。
+    pub fn thumb_delta_to_scroll(&self, viewport: f32, content: f32, delta: f32) -> f32 {
+        if content <= viewport || viewport <= 0.0 {
+            return 0.0;
+        }
+
+        let thumb = (viewport * (viewport / content))
+            .max(self.min_thumb)
+            .min(viewport - 2.0 * self.margin);
+        let track = (viewport - 2.0 * self.margin - thumb).max(1.0);
+
+        delta * (content - viewport) / track
+    }
+
+    /// 画面座標の点 (px,py) が横スクロールバーのサムに当たる場合にtrue
+    pub fn hit_test_horizontal_thumb(
+        &self,
+        viewport_width: f32,
+        viewport_height: f32,
+        content_width: f32,
+        scroll_x: f32,
+        px: f32,
+        py: f32,
+    ) -> bool {
+        if let Some((x1, y1, x2, y2)) =
+            self.horizontal_thumb_rect(viewport_width, viewport_height, content_width, scroll_x)
+        {
+            let pad = self.hit_padding;
+            px >= x1 - pad && px <= x2 + pad && py >= y1 - pad && py <= y2 + pad
+        } else {
+            false
+        }
+    }
+
     /// 画面座標の点 (px,py) が矩形に当たる場合にtrue
     pub fn hit_test_thumb(
         &self,
@@ -80,8 +155,66 @@ impl ScrollBar {
         if let Some((x1, y1, x2, y2)) =
             self.thumb_rect(viewport_width, viewport_height, content_height, scroll_y)
         {
-            px >= x1 && px <= x2 && py >= y1 && py <= y2
+            let pad = self.hit_padding;
+            px >= x1 - pad && px <= x2 + pad && py >= y1 - pad && py <= y2 + pad
+        } else {
+            false
+        }
+    }
+}
+
+/// オーバーレイ型スクロールバーの表示状態
+///
+/// スクロールやホバーがあるとフェードインし、しばらく操作がなければフェードアウトする。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrollBarFade {
+    opacity: f32,
+    last_activity: Option<Instant>,
+    last_frame: Option<Instant>,
+}
+
+impl ScrollBarFade {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// スクロールやホバーがあったことを記録する
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = Some(now);
+    }
+
+    /// 現在の不透明度（0.0 〜 1.0）
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.opacity > 0.0
+    }
+
+    /// 1 フレーム進める。表示中またはフェード中なら true（次のフレームが必要）
+    pub fn step(&mut self, now: Instant) -> bool {
+        let dt = self
+            .last_frame
+            .map(|last| now.saturating_duration_since(last).as_secs_f32())
+            .unwrap_or(0.0);
+
+        let active = self
+            .last_activity
+            .is_some_and(|last| now.saturating_duration_since(last) < IDLE_TIMEOUT);
+
+        self.opacity = if active {
+            // 最初のフレームから見えるように、少なくとも 1 フレーム分は進める
+            (self.opacity + dt.max(1.0 / 60.0) / FADE_IN_SECS).min(1.0)
+        } else {
+            (self.opacity - dt / FADE_OUT_SECS).max(0.0)
+        };
+
+        if self.opacity > 0.0 {
+            self.last_frame = Some(now);
+            true
         } else {
+            self.last_frame = None;
             false
         }
     }
@@ -0,0 +1,46 @@
+// 画像をインスタンス描画するシェーダー
+// 1 インスタンス = 1 枚、矩形と同じく vertex_index から四隅を生成する
+
+struct ImageInstance {
+    // 左上の NDC 座標
+    @location(0) position: vec2<f32>,
+    // NDC 上のサイズ（y は下向きなので負になる）
+    @location(1) size: vec2<f32>,
+    // 描く部分のテクスチャ座標の左上とサイズ（クリップで切り抜いた分だけ小さくなる）
+    @location(2) uv_position: vec2<f32>,
+    @location(3) uv_size: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@group(0) @binding(0)
+var image_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var image_sampler: sampler;
+
+// 2 つの三角形で矩形を構成する
+var<private> CORNERS: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: ImageInstance) -> VertexOutput {
+    var out: VertexOutput;
+    let corner = CORNERS[vertex_index];
+    out.clip_position = vec4<f32>(instance.position + corner * instance.size, 0.0, 1.0);
+    out.uv = instance.uv_position + corner * instance.uv_size;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(image_texture, image_sampler, in.uv);
+}
@@ -0,0 +1,39 @@
+// 矩形をインスタンス描画するシェーダー
+// 1 インスタンス = 1 矩形、vertex_index から四隅を生成する
+
+struct RectInstance {
+    // 左上の NDC 座標
+    @location(0) position: vec2<f32>,
+    // NDC 上のサイズ（y は下向きなので負になる）
+    @location(1) size: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+}
+
+// 2 つの三角形で矩形を構成する
+var<private> CORNERS: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, rect: RectInstance) -> VertexOutput {
+    var out: VertexOutput;
+    let corner = CORNERS[vertex_index];
+    out.clip_position = vec4<f32>(rect.position + corner * rect.size, 0.0, 1.0);
+    out.color = rect.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
@@ -0,0 +1,587 @@
+//! GPU を使わないレンダラー
+//!
+//! wgpu のアダプターが見つからない環境（仮想マシン、古い GPU、GPU のない CI）でも
+//! ブラウザが起動できるよう、描画命令を tiny-skia で CPU でラスタライズし、
+//! softbuffer でウィンドウに写す。[`GpuRenderer`](super::gpu::GpuRenderer) と同じ
+//! [`Compositor`] として使える。
+
+use std::borrow::Cow;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{Result, anyhow, bail};
+use glyphon::{Buffer, Color as GlyphColor, FontSystem, SwashCache};
+use tiny_skia::{
+    ColorU8, FillRule, FilterQuality, Mask, Paint, PathBuilder, Pattern, Pixmap,
+    PremultipliedColorU8, Rect, SpreadMode, Transform,
+};
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+use super::compositor::Compositor;
+use super::frame_stats::{self, FrameCounts, FrameStats};
+use super::glyph::shaping;
+use super::text_cache::{LruCache, TextCacheKey};
+use crate::engine::layouter::types::Color;
+use crate::engine::renderer_model::DrawCommand;
+use crate::platform::font::{self, FontMatcher};
+
+/// シェーピング済み Buffer の LRU 上限（エントリ数）
+const BUFFER_CACHE_CAPACITY: usize = 2048;
+
+type WindowSurface = softbuffer::Surface<Arc<Window>, Arc<Window>>;
+
+/// CPU で描くレンダラー
+pub struct SoftwareRenderer {
+    /// 描いたものを写すウィンドウのバッファ（ヘッドレス時は None）
+    surface: Option<WindowSurface>,
+    /// 描く先の大きさ（物理ピクセル）
+    size: PhysicalSize<u32>,
+    /// ディスプレイ倍率
+    scale_factor: f64,
+    /// 受け取った描画命令。ラスタライズは render() のときに行う
+    commands: Vec<DrawCommand>,
+    /// フォントが見つからなければ None（テキストは描かない）
+    text: Option<SoftwareText>,
+
+    /// FPS と描いたものの数を右上に出す
+    debug_overlay: bool,
+    /// 最近のフレームにかかった時間
+    frame_stats: FrameStats,
+    /// 前のフレームで描いたものの数
+    frame_counts: FrameCounts,
+    /// 描画中のフレームの描画命令を受け取った時刻
+    frame_started: Option<Instant>,
+}
+
+/// テキストのシェーピングとグリフのラスタライズ
+struct SoftwareText {
+    /// 計測側と共有する
+    font_sys: Arc<Mutex<FontSystem>>,
+    /// CSS の font-family を実フォントに解決する
+    font_matcher: FontMatcher,
+    /// rasterize 結果のキャッシュ
+    swash_cache: SwashCache,
+    /// シェーピング済み Buffer のキャッシュ（計測側と同じキーを使う）
+    buffer_cache: LruCache<TextCacheKey, Arc<Buffer>>,
+}
+
+impl SoftwareText {
+    fn new() -> Option<Self> {
+        match font::shared_font_system() {
+            Ok(font_sys) => Some(Self {
+                font_sys,
+                font_matcher: FontMatcher::new(),
+                swash_cache: SwashCache::new(),
+                buffer_cache: LruCache::new(BUFFER_CACHE_CAPACITY),
+            }),
+            Err(e) => {
+                log::warn!(target:"PRender::software::font", "no system font found for text rendering: {}", e);
+                None
+            }
+        }
+    }
+}
+
+/// 物理ピクセルのクリップ矩形
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Clip {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+}
+
+impl Clip {
+    fn intersect(self, other: Clip) -> Clip {
+        Clip {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        }
+    }
+
+    fn is_empty(self) -> bool {
+        self.right <= self.left || self.bottom <= self.top
+    }
+}
+
+impl SoftwareRenderer {
+    /// window に描くレンダラーを作成
+    ///
+    /// # Errors
+    /// ウィンドウシステムにバッファを作れない場合
+    pub fn new(window: Arc<Window>) -> Result<Self> {
+        let context = softbuffer::Context::new(window.clone())
+            .map_err(|e| anyhow!("failed to connect to the display: {e}"))?;
+        let surface = softbuffer::Surface::new(&context, window.clone())
+            .map_err(|e| anyhow!("failed to create a window buffer: {e}"))?;
+
+        let mut renderer = Self::with_surface(Some(surface), window.scale_factor());
+        renderer.resize(window.inner_size());
+        Ok(renderer)
+    }
+
+    /// ウィンドウを持たないレンダラーを作成
+    ///
+    /// 描いたものは [`Compositor::capture_frame`] で取得する。
+    pub fn new_headless(size: PhysicalSize<u32>, scale_factor: f64) -> Self {
+        let mut renderer = Self::with_surface(None, scale_factor);
+        renderer.resize(size);
+        renderer
+    }
+
+    fn with_surface(surface: Option<WindowSurface>, scale_factor: f64) -> Self {
+        Self {
+            surface,
+            size: PhysicalSize::new(1, 1),
+            scale_factor,
+            commands: Vec::new(),
+            text: SoftwareText::new(),
+            debug_overlay: false,
+            frame_stats: FrameStats::new(),
+            frame_counts: FrameCounts::default(),
+            frame_started: None,
+        }
+    }
+}
+
+impl Compositor for SoftwareRenderer {
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        let (Some(width), Some(height)) =
+            (NonZeroU32::new(size.width), NonZeroU32::new(size.height))
+        else {
+            return;
+        };
+        self.size = size;
+        if let Some(surface) = &mut self.surface
+            && let Err(e) = surface.resize(width, height)
+        {
+            log::error!(target:"PRender::software::resized", "failed to resize the window buffer: {}", e);
+        }
+    }
+
+    /// 倍率が変わったら、前の大きさでラスタライズしたグリフを捨てる
+    fn set_scale_factor(&mut self, scale_factor: f64) {
+        if scale_factor == self.scale_factor {
+            return;
+        }
+        self.scale_factor = scale_factor;
+        if let Some(text) = &mut self.text {
+            text.swash_cache = SwashCache::new();
+        }
+    }
+
+    fn set_debug_overlay(&mut self, enabled: bool) {
+        self.debug_overlay = enabled;
+    }
+
+    fn parse_draw_commands(&mut self, commands: &[DrawCommand]) {
+        let now = Instant::now();
+        self.frame_started = Some(now);
+        // デバッグ表示は物理ピクセルで作り、ページの倍率を打ち消して重ねる
+        let commands: Cow<[DrawCommand]> = if self.debug_overlay {
+            let factor = 1.0 / self.scale_factor as f32;
+            let overlay = frame_stats::overlay_commands(
+                &self.frame_stats,
+                &self.frame_counts,
+                now,
+                self.size.width as f32,
+            );
+            let mut all = commands.to_vec();
+            all.extend(overlay.into_iter().map(|c| c.scaled(factor)));
+            Cow::Owned(all)
+        } else {
+            Cow::Borrowed(commands)
+        };
+        self.commands.clear();
+        self.commands.extend_from_slice(&commands);
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let Some(surface) = &mut self.surface else {
+            bail!("render() requires a window; use capture_frame() in headless mode");
+        };
+        let (pixmap, counts) = rasterize(
+            &self.commands,
+            self.text.as_mut(),
+            self.size,
+            self.scale_factor as f32,
+        )?;
+        self.frame_counts = counts;
+
+        let mut buffer = surface
+            .buffer_mut()
+            .map_err(|e| anyhow!("failed to get the window buffer: {e}"))?;
+        // softbuffer のピクセルは 0RGB
+        for (dst, src) in buffer.iter_mut().zip(pixmap.pixels()) {
+            *dst = ((src.red() as u32) << 16) | ((src.green() as u32) << 8) | src.blue() as u32;
+        }
+        buffer
+            .present()
+            .map_err(|e| anyhow!("failed to present the window buffer: {e}"))?;
+
+        if let Some(started) = self.frame_started.take() {
+            self.frame_stats.record(Instant::now(), started.elapsed());
+        }
+        Ok(())
+    }
+
+    fn capture_frame(&mut self) -> Result<image::RgbaImage> {
+        let (pixmap, counts) = rasterize(
+            &self.commands,
+            self.text.as_mut(),
+            self.size,
+            self.scale_factor as f32,
+        )?;
+        self.frame_counts = counts;
+        let pixels = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|px| {
+                let px = px.demultiply();
+                [px.red(), px.green(), px.blue(), px.alpha()]
+            })
+            .collect();
+        image::RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixels)
+            .ok_or_else(|| anyhow!("captured pixel buffer size mismatch"))
+    }
+}
+
+/// 描画命令を size（物理ピクセル）の白い画像に描き、描いたものの数と一緒に返す
+///
+/// 座標は CSS ピクセルなので scale_factor 倍して描く。
+fn rasterize(
+    commands: &[DrawCommand],
+    mut text: Option<&mut SoftwareText>,
+    size: PhysicalSize<u32>,
+    scale_factor: f32,
+) -> Result<(Pixmap, FrameCounts)> {
+    let (width, height) = (size.width.max(1), size.height.max(1));
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("invalid canvas size {width}x{height}"))?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+
+    let sf = scale_factor;
+    let screen = Clip {
+        left: 0.0,
+        top: 0.0,
+        right: width as f32,
+        bottom: height as f32,
+    };
+    let mut clip_stack = vec![screen];
+    let mut transform_stack = vec![(0.0f32, 0.0f32)];
+    // 多角形と楕円はクリップをマスクで掛ける。クリップが変わるまで使い回す
+    let mut mask: Option<(Clip, Option<Mask>)> = None;
+
+    let mut counts = FrameCounts {
+        draw_commands: commands.len(),
+        ..FrameCounts::default()
+    };
+
+    for command in commands {
+        let (tdx, tdy) = *transform_stack.last().unwrap();
+        let clip = *clip_stack.last().unwrap();
+        match command {
+            DrawCommand::PushTransform { dx, dy } => {
+                transform_stack.push((tdx + dx, tdy + dy));
+            }
+            DrawCommand::PopTransform => {
+                if transform_stack.len() > 1 {
+                    transform_stack.pop();
+                }
+            }
+            DrawCommand::PushClip {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                let new_clip = Clip {
+                    left: (x + tdx) * sf,
+                    top: (y + tdy) * sf,
+                    right: (x + tdx + width) * sf,
+                    bottom: (y + tdy + height) * sf,
+                };
+                clip_stack.push(new_clip.intersect(clip));
+            }
+            DrawCommand::PopClip => {
+                if clip_stack.len() > 1 {
+                    clip_stack.pop();
+                }
+            }
+
+            DrawCommand::DrawRect {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => {
+                // 矩形はクリップと交わる部分だけを塗る
+                let rect = Clip {
+                    left: (x + tdx) * sf,
+                    top: (y + tdy) * sf,
+                    right: (x + tdx + width) * sf,
+                    bottom: (y + tdy + height) * sf,
+                }
+                .intersect(clip);
+                let Some(rect) = Rect::from_ltrb(rect.left, rect.top, rect.right, rect.bottom)
+                else {
+                    continue;
+                };
+                pixmap.fill_rect(rect, &paint(*color), Transform::identity(), None);
+                counts.rect_instances += 1;
+            }
+
+            DrawCommand::DrawPolygon { points, color } => {
+                if points.len() < 3 || clip.is_empty() {
+                    continue;
+                }
+                let mut builder = PathBuilder::new();
+                let mut points = points
+                    .iter()
+                    .map(|(px, py)| ((px + tdx) * sf, (py + tdy) * sf));
+                if let Some((px, py)) = points.next() {
+                    builder.move_to(px, py);
+                }
+                for (px, py) in points {
+                    builder.line_to(px, py);
+                }
+                builder.close();
+                let Some(path) = builder.finish() else {
+                    continue;
+                };
+                let mask = clip_mask(&mut mask, clip, screen, width, height);
+                pixmap.fill_path(
+                    &path,
+                    &paint(*color),
+                    FillRule::Winding,
+                    Transform::identity(),
+                    mask,
+                );
+                counts.vertices += path.points().len();
+            }
+
+            DrawCommand::DrawEllipse {
+                center,
+                radius_x,
+                radius_y,
+                color,
+            } => {
+                if clip.is_empty() {
+                    continue;
+                }
+                let Some(path) = Rect::from_xywh(
+                    (center.0 - radius_x + tdx) * sf,
+                    (center.1 - radius_y + tdy) * sf,
+                    radius_x * 2.0 * sf,
+                    radius_y * 2.0 * sf,
+                )
+                .and_then(PathBuilder::from_oval) else {
+                    continue;
+                };
+                let mask = clip_mask(&mut mask, clip, screen, width, height);
+                pixmap.fill_path(
+                    &path,
+                    &paint(*color),
+                    FillRule::Winding,
+                    Transform::identity(),
+                    mask,
+                );
+                counts.vertices += path.points().len();
+            }
+
+            DrawCommand::DrawImage {
+                x,
+                y,
+                width,
+                height,
+                image,
+            } => {
+                let rect = Clip {
+                    left: (x + tdx) * sf,
+                    top: (y + tdy) * sf,
+                    right: (x + tdx + width) * sf,
+                    bottom: (y + tdy + height) * sf,
+                };
+                // 矩形と同じく、クリップと交わる部分だけを塗る
+                let visible = rect.intersect(clip);
+                let (Some(visible), Some(source)) = (
+                    Rect::from_ltrb(visible.left, visible.top, visible.right, visible.bottom),
+                    image_pixmap(image),
+                ) else {
+                    continue;
+                };
+                // 画像を描く先の矩形いっぱいに伸縮する
+                let transform = Transform::from_row(
+                    (rect.right - rect.left) / source.width() as f32,
+                    0.0,
+                    0.0,
+                    (rect.bottom - rect.top) / source.height() as f32,
+                    rect.left,
+                    rect.top,
+                );
+                let paint = Paint {
+                    shader: Pattern::new(
+                        source.as_ref(),
+                        SpreadMode::Pad,
+                        FilterQuality::Bilinear,
+                        1.0,
+                        transform,
+                    ),
+                    ..Paint::default()
+                };
+                pixmap.fill_rect(visible, &paint, Transform::identity(), None);
+                counts.rect_instances += 1;
+            }
+
+            DrawCommand::DrawText {
+                x,
+                y,
+                text: content,
+                style,
+                max_width,
+            } => {
+                let Some(text) = text.as_deref_mut() else {
+                    continue;
+                };
+                // 折り返し幅より右には描かない（GPU の描画と同じ）
+                let bounds = Clip {
+                    right: clip.right.min((x + tdx + max_width) * sf),
+                    ..clip
+                };
+                if bounds.is_empty() {
+                    continue;
+                }
+
+                let mut render_style = *style;
+                render_style.font_size = style.font_size * sf;
+                let key = TextCacheKey::new(0, content, &render_style, None, false);
+                let buffer = match text.buffer_cache.get(&key) {
+                    Some(buffer) => buffer.clone(),
+                    None => {
+                        let mut font_sys = text.font_sys.lock().unwrap_or_else(|e| e.into_inner());
+                        let buffer = Arc::new(shaping::shape_text(
+                            &mut font_sys,
+                            &mut text.font_matcher,
+                            content,
+                            &render_style,
+                            None,
+                        ));
+                        text.buffer_cache.insert(key, buffer.clone());
+                        buffer
+                    }
+                };
+
+                let origin_x = ((x + tdx) * sf).round() as i32;
+                let origin_y = ((y + tdy) * sf).round() as i32;
+                let mut font_sys = text.font_sys.lock().unwrap_or_else(|e| e.into_inner());
+                // 疑似ボールドは少し右にずらしてもう一度描く（GPU の描画と同じ）
+                let synthetic_bold = font::synthetic_bold_offset(
+                    &mut text.font_matcher,
+                    font_sys.db(),
+                    &render_style,
+                )
+                .map(|offset| offset.round() as i32);
+                for offset in [Some(0), synthetic_bold].into_iter().flatten() {
+                    // 色は Buffer 内の属性が優先されるため既定色は適当で良い
+                    buffer.draw(
+                        &mut font_sys,
+                        &mut text.swash_cache,
+                        GlyphColor::rgba(0, 0, 0, 255),
+                        |gx, gy, w, h, color| {
+                            blend_rect(
+                                &mut pixmap,
+                                bounds,
+                                origin_x + offset + gx,
+                                origin_y + gy,
+                                w,
+                                h,
+                                color,
+                            );
+                        },
+                    );
+                }
+                counts.text_sections += 1;
+            }
+        }
+    }
+
+    Ok((pixmap, counts))
+}
+
+/// RGBA の画像を tiny-skia のピクスマップ（乗算済みアルファ）にする。空の画像なら None
+fn image_pixmap(image: &image::RgbaImage) -> Option<Pixmap> {
+    let mut pixmap = Pixmap::new(image.width(), image.height())?;
+    for (dst, src) in pixmap.pixels_mut().iter_mut().zip(image.pixels()) {
+        let [r, g, b, a] = src.0;
+        *dst = ColorU8::from_rgba(r, g, b, a).premultiply();
+    }
+    Some(pixmap)
+}
+
+fn paint(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(color.0, color.1, color.2, color.3);
+    paint
+}
+
+/// clip で切り抜くマスク。画面全体なら None（マスクなし）
+fn clip_mask(
+    cached: &mut Option<(Clip, Option<Mask>)>,
+    clip: Clip,
+    screen: Clip,
+    width: u32,
+    height: u32,
+) -> Option<&Mask> {
+    if cached.as_ref().is_none_or(|(c, _)| *c != clip) {
+        let mask = if clip.intersect(screen) == screen {
+            None
+        } else {
+            Rect::from_ltrb(clip.left, clip.top, clip.right, clip.bottom)
+                .zip(Mask::new(width, height))
+                .map(|(rect, mut mask)| {
+                    let path = PathBuilder::from_rect(rect);
+                    mask.fill_path(&path, FillRule::Winding, false, Transform::identity());
+                    mask
+                })
+        };
+        *cached = Some((clip, mask));
+    }
+    cached.as_ref().and_then(|(_, mask)| mask.as_ref())
+}
+
+/// (x, y) から w×h の範囲のうち clip の内側に color を重ねる（グリフのピクセル用）
+fn blend_rect(pixmap: &mut Pixmap, clip: Clip, x: i32, y: i32, w: u32, h: u32, color: GlyphColor) {
+    let alpha = color.a() as u32;
+    if alpha == 0 {
+        return;
+    }
+    let width = pixmap.width() as i32;
+    let left = x.max(clip.left.round() as i32).max(0);
+    let top = y.max(clip.top.round() as i32).max(0);
+    let right = (x + w as i32).min(clip.right.round() as i32).min(width);
+    let bottom = (y + h as i32)
+        .min(clip.bottom.round() as i32)
+        .min(pixmap.height() as i32);
+
+    // 乗算済みアルファで src over
+    let over = |src: u8, dst: u8| ((src as u32 * alpha + dst as u32 * (255 - alpha)) / 255) as u8;
+    let pixels = pixmap.pixels_mut();
+    for py in top..bottom {
+        for px in left..right {
+            let dst = &mut pixels[(py * width + px) as usize];
+            if let Some(blended) = PremultipliedColorU8::from_rgba(
+                over(color.r(), dst.red()),
+                over(color.g(), dst.green()),
+                over(color.b(), dst.blue()),
+                over(255, dst.alpha()),
+            ) {
+                *dst = blended;
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! テキスト計測・シェーピング結果のキャッシュ
+//!
+//! 計測（`PlatformTextMeasurer`）と描画（`TextRenderer`）で同じキーと LRU を使う。
+//! 計測結果はプロセス全体で共有し、シェーピング済みの `Buffer` は
+//! `FontSystem` ごとに持つ（フォント ID が `FontSystem` に紐づくため）。
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+
+use crate::engine::layouter::types::TextStyle;
+
+/// キャッシュのキー
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct TextCacheKey {
+    /// フォント構成の識別子（主フォントのパスなど）
+    pub font_key: u64,
+    pub text: String,
+    pub style_hash: u64,
+    /// 折り返し幅（`f32::to_bits`）
+    pub max_width: Option<u32>,
+    /// max_width で折り返すか（折り返さなくても計測の幅は max_width で切る）
+    pub wrap: bool,
+}
+
+impl TextCacheKey {
+    pub fn new(
+        font_key: u64,
+        text: &str,
+        style: &TextStyle,
+        max_width: Option<f32>,
+        wrap: bool,
+    ) -> Self {
+        Self {
+            font_key,
+            text: text.to_string(),
+            style_hash: style_hash(style),
+            max_width: max_width.map(f32::to_bits),
+            wrap,
+        }
+    }
+}
+
+/// 文字列からフォント構成の識別子を作る
+pub(crate) fn font_key(id: &str) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    id.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// シェーピング結果に影響する `TextStyle` のフィールドをハッシュする
+pub(crate) fn style_hash(style: &TextStyle) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+
+    style.font_size.to_bits().hash(&mut hasher);
+    style.font_family.hash(&mut hasher);
+    style.font_weight.hash(&mut hasher);
+    style.font_style.hash(&mut hasher);
+    (style.text_align as u8).hash(&mut hasher);
+    (style.text_decoration as u8).hash(&mut hasher);
+    // 色は Buffer の属性に焼き込まれる
+    (style.color.0, style.color.1, style.color.2, style.color.3).hash(&mut hasher);
+
+    hasher.finish()
+}
+
+/// エントリ数で上限を決める LRU キャッシュ
+#[derive(Debug)]
+pub(crate) struct LruCache<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    /// 最終利用時刻 → キー
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    /// 値を取得し、最近使ったものとして記録する
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.next_tick();
+        let entry = self.entries.get_mut(key)?;
+
+        self.order.remove(&entry.1);
+        entry.1 = tick;
+        self.order.insert(tick, key.clone());
+
+        Some(&entry.0)
+    }
+
+    /// 値を登録し、上限を超えたら最も古いものから捨てる
+    pub fn insert(&mut self, key: K, value: V) {
+        let tick = self.next_tick();
+
+        if let Some((_, old_tick)) = self.entries.insert(key.clone(), (value, tick)) {
+            self.order.remove(&old_tick);
+        }
+        self.order.insert(tick, key);
+
+        self.shrink_to(self.capacity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 最も古いものから捨て、len 個以下にする
+    pub fn shrink_to(&mut self, len: usize) {
+        while self.entries.len() > len {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+
+        // a を使ったので次に捨てられるのは b
+        assert_eq!(cache.get(&"a"), Some(&1));
+        cache.insert("c", 3);
+
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"a"), Some(&1));
+        assert_eq!(cache.get(&"c"), Some(&3));
+    }
+
+    #[test]
+    fn reinsert_updates_value_without_growing() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("a", 10);
+
+        assert_eq!(cache.get(&"a"), Some(&10));
+        assert_eq!(cache.entries.len(), 1);
+    }
+
+    #[test]
+    fn shrink_keeps_most_recently_used() {
+        let mut cache = LruCache::new(4);
+        for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+            cache.insert(key, i);
+        }
+        cache.get(&"a");
+
+        cache.shrink_to(2);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&"a"), Some(&0));
+        assert_eq!(cache.get(&"d"), Some(&3));
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn key_distinguishes_style_and_width() {
+        let style = TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        };
+        let bigger = TextStyle {
+            font_size: 20.0,
+            ..style
+        };
+
+        let base = TextCacheKey::new(0, "hello", &style, Some(100.0), true);
+        assert_eq!(
+            base,
+            TextCacheKey::new(0, "hello", &style, Some(100.0), true)
+        );
+        assert_ne!(
+            base,
+            TextCacheKey::new(0, "hello", &bigger, Some(100.0), true)
+        );
+        assert_ne!(
+            base,
+            TextCacheKey::new(0, "hello", &style, Some(50.0), true)
+        );
+        assert_ne!(
+            base,
+            TextCacheKey::new(1, "hello", &style, Some(100.0), true)
+        );
+        // 同じ幅でも折り返すかどうかで結果が違う
+        assert_ne!(
+            base,
+            TextCacheKey::new(0, "hello", &style, Some(100.0), false)
+        );
+    }
+}
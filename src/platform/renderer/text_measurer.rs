@@ -1,12 +1,68 @@
 use crate::engine::bridge::text::{
-    TextMeasureError, TextMeasureRequest, TextMeasurer, TextMetrics,
+    ClusterMetrics, LineFragment, TextMeasureError, TextMeasureRequest, TextMeasurer, TextMetrics,
 };
 use crate::engine::layouter::types::TextStyle;
+use crate::platform::font::{self, FontMatcher};
+use crate::platform::memory::{self, MemoryConsumer, MemoryPressure, Subsystem};
 
-use std::env;
-use std::sync::{Arc, Mutex};
+use super::glyph::shaping;
+use super::text_cache::{self, LruCache, TextCacheKey};
 
-use glyphon::{Attrs, Buffer, Color as GlyphColor, FontSystem, Metrics, Shaping, Style, Weight};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use glyphon::{FontSystem, cosmic_text::LayoutGlyph};
+
+/// Cache id of the process-wide font system from [`font::shared_font_system`].
+const SHARED_FONT_ID: &str = "<shared>";
+
+/// Maximum number of measurement results kept in the process-wide cache.
+const MEASURE_CACHE_CAPACITY: usize = 8192;
+
+/// Rough size of one measurement cache entry, for memory accounting.
+const MEASURE_ENTRY_SIZE: usize = 512;
+
+/// Process-wide measurement cache.
+///
+/// A new measurer is created for every layout pass, so the cache lives
+/// outside the measurer. Entries are keyed by the measurer's font
+/// configuration, so measurers built from different fonts never share results.
+fn measure_cache() -> &'static Mutex<LruCache<TextCacheKey, TextMetrics>> {
+    static CACHE: OnceLock<Arc<MeasureCache>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| {
+        let cache = Arc::new(MeasureCache(Mutex::new(LruCache::new(
+            MEASURE_CACHE_CAPACITY,
+        ))));
+        memory::registry().register(&cache);
+        cache
+    });
+    &cache.0
+}
+
+/// The measurement cache as seen by the memory registry.
+struct MeasureCache(Mutex<LruCache<TextCacheKey, TextMetrics>>);
+
+impl MemoryConsumer for MeasureCache {
+    fn subsystem(&self) -> Subsystem {
+        Subsystem::Glyphs
+    }
+
+    fn memory_usage(&self) -> usize {
+        let cache = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        cache.len() * MEASURE_ENTRY_SIZE
+    }
+
+    /// Moderate pressure keeps the most recently used half; critical pressure
+    /// drops everything. Results are measured again on the next layout.
+    fn evict(&self, pressure: MemoryPressure) -> usize {
+        let mut cache = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let before = cache.len();
+        match pressure {
+            MemoryPressure::Moderate => cache.shrink_to(before / 2),
+            MemoryPressure::Critical => cache.clear(),
+        }
+        (before - cache.len()) * MEASURE_ENTRY_SIZE
+    }
+}
 
 /// Platform-backed text measurer using glyphon / cosmic-text.
 ///
@@ -14,65 +70,47 @@ use glyphon::{Attrs, Buffer, Color as GlyphColor, FontSystem, Metrics, Shaping,
 /// and is intended for production use.
 pub struct PlatformTextMeasurer {
     /// Font system used for shaping and metrics
-    font_sys: Mutex<FontSystem>,
+    font_sys: Arc<Mutex<FontSystem>>,
+    /// Resolves CSS font-family lists to faces in `font_sys`
+    font_matcher: Mutex<FontMatcher>,
+    /// Identifies the font configuration in the measurement cache
+    font_key: u64,
 }
 
 impl PlatformTextMeasurer {
     /// Initialize using system fonts.
     ///
-    /// TODO:
-    /// - Share font system with PlatformTextRenderer
-    /// - Support font family / fallback selection
+    /// The font system is shared with the GPU text renderer, so both shape
+    /// text with the same faces and agree on advances and line breaks.
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let mut maybe_bytes: Option<Vec<u8>> = None;
-
-        if let Ok(p) = env::var("ORINIUM_FONT")
-            && let Ok(b) = std::fs::read(&p)
-        {
-            maybe_bytes = Some(b);
-        }
-
-        if maybe_bytes.is_none() {
-            for p in crate::platform::font::system_font_candidates()? {
-                if let Ok(b) = std::fs::read(p) {
-                    maybe_bytes = Some(b);
-                    break;
-                }
-            }
-        }
-
-        if let Some(bytes) = maybe_bytes {
-            let font_source = Arc::new(bytes);
-            let font = glyphon::fontdb::Source::Binary(font_source);
-            let font_sys = FontSystem::new_with_fonts(vec![font]);
-
-            return Ok(Self {
-                font_sys: Mutex::new(font_sys),
-            });
-        }
+        let font_sys = font::shared_font_system()?;
 
-        Err("no system font found".into())
+        Ok(Self {
+            font_sys,
+            font_matcher: Mutex::new(FontMatcher::new()),
+            font_key: text_cache::font_key(SHARED_FONT_ID),
+        })
     }
 
     /// Initialize from raw font bytes.
-    pub fn from_bytes(_id: &str, bytes: Vec<u8>) -> Result<Self, Box<dyn std::error::Error>> {
-        let font_source = Arc::new(bytes);
-        let font = glyphon::fontdb::Source::Binary(font_source);
-        let font_sys = FontSystem::new_with_fonts(vec![font]);
+    ///
+    /// Platform fallback fonts are loaded as well, so characters missing
+    /// from `bytes` are measured with the same font the renderer will use.
+    ///
+    /// `id` identifies the font in the shared measurement cache; measurers
+    /// created with the same `id` must use the same font bytes.
+    pub fn from_bytes(id: &str, bytes: Vec<u8>) -> Result<Self, Box<dyn std::error::Error>> {
+        let font_sys = font::font_system_with_fallbacks(bytes);
 
         Ok(Self {
-            font_sys: Mutex::new(font_sys),
+            font_sys: Arc::new(Mutex::new(font_sys)),
+            font_matcher: Mutex::new(FontMatcher::new()),
+            font_key: text_cache::font_key(id),
         })
     }
-}
 
-impl TextMeasurer<TextStyle> for PlatformTextMeasurer {
-    /// Measure text using real shaping and line layout.
-    ///
-    /// Notes:
-    /// - Baseline is currently approximated
-    /// - Decorations and alignment are handled at render time
-    fn measure(
+    /// Shape and lay out the text without consulting the cache.
+    fn measure_uncached(
         &self,
         req: &TextMeasureRequest<TextStyle>,
     ) -> Result<TextMetrics, TextMeasureError> {
@@ -82,55 +120,184 @@ impl TextMeasurer<TextStyle> for PlatformTextMeasurer {
             .font_sys
             .lock()
             .map_err(|e| TextMeasureError::Internal(format!("font_sys lock poisoned: {}", e)))?;
+        let mut font_matcher = self.font_matcher.lock().map_err(|e| {
+            TextMeasureError::Internal(format!("font_matcher lock poisoned: {}", e))
+        })?;
 
-        // glyphon metrics: font size + line height
-        let metrics = Metrics::relative(font_size, 1.2);
-        let mut buffer = Buffer::new(&mut fs, metrics);
-
-        // Attributes used only for shaping / layout
-        let attrs = Attrs::new()
-            .metrics(metrics)
-            .color(GlyphColor::rgba(0, 0, 0, 255))
-            .weight(Weight(req.style.font_weight.0))
-            .style(Style::from(req.style.font_style));
-
-        buffer.set_text(&mut fs, &req.text, &attrs, Shaping::Advanced, None);
-
-        let mut max_width: f32 = 0.0;
-        let mut line_count: usize = 0;
-
-        // Iterate over shaped lines
-        for para_i in 0..buffer.lines.len() {
-            if let Some(layout_lines) = buffer.line_layout(&mut fs, para_i) {
-                for line in layout_lines {
-                    max_width = max_width.max(line.w);
-                    line_count += 1;
-                }
-            }
-        }
+        // Same shaping path as the GPU text renderer
+        let max_width = req.max_width.filter(|_| req.wrap);
+        let buffer =
+            shaping::shape_text(&mut fs, &mut font_matcher, &req.text, &req.style, max_width);
+        let line_height = buffer.metrics().line_height;
 
-        if line_count == 0 {
+        // (paragraph index, end, width, baseline, clusters) per layouted line.
+        // Offsets are relative to the paragraph.
+        let runs: Vec<(usize, usize, f32, f32, Vec<ClusterMetrics>)> = buffer
+            .layout_runs()
+            .map(|run| {
+                let end = run.glyphs.iter().map(|g| g.end).max().unwrap_or(0);
+                (
+                    run.line_i,
+                    end,
+                    run.line_w,
+                    run.line_y,
+                    clusters_of(run.glyphs),
+                )
+            })
+            .collect();
+
+        if runs.is_empty() {
             // Empty text
             return Ok(TextMetrics {
                 width: 0.0,
-                height: metrics.line_height,
+                height: line_height,
                 baseline: font_size * 0.8,
                 line_count: 1,
+                lines: vec![LineFragment {
+                    start: 0,
+                    end: req.text.len(),
+                    width: 0.0,
+                    clusters: Vec::new(),
+                }],
+            });
+        }
+
+        let paragraphs = paragraph_ranges(&req.text);
+        let mut lines: Vec<LineFragment> = Vec::with_capacity(runs.len());
+
+        for (i, (para_i, end, width, _, clusters)) in runs.iter().enumerate() {
+            let (para_i, end, width) = (*para_i, *end, *width);
+            let (para_start, para_end) = paragraphs
+                .get(para_i)
+                .copied()
+                .unwrap_or((0, req.text.len()));
+
+            // Keep fragments contiguous so every byte belongs to exactly one line
+            let first_in_para = i == 0 || runs[i - 1].0 != para_i;
+            let last_in_para = runs.get(i + 1).is_none_or(|next| next.0 != para_i);
+
+            let start = match lines.last() {
+                Some(prev) if !first_in_para => prev.end,
+                _ => para_start,
+            };
+            let end = if last_in_para {
+                para_end
+            } else {
+                (para_start + end).min(para_end)
+            };
+
+            // クラスタのオフセットを段落相対から文字列全体の相対に直す
+            let clusters = clusters
+                .iter()
+                .map(|c| ClusterMetrics {
+                    start: para_start + c.start,
+                    end: para_start + c.end,
+                    ..c.clone()
+                })
+                .collect();
+
+            lines.push(LineFragment {
+                start,
+                end,
+                width,
+                clusters,
             });
         }
 
+        let mut max_width: f32 = lines.iter().map(|l| l.width).fold(0.0, f32::max);
+
         // Apply wrapping constraint
         if let Some(max_width_limit) = req.max_width {
             max_width = max_width.min(max_width_limit);
         }
 
-        let height = metrics.line_height * line_count as f32;
+        let line_count = lines.len();
+        let height = line_height * line_count as f32;
 
         Ok(TextMetrics {
             width: max_width,
             height,
-            baseline: font_size * 0.8, // TODO: precise baseline from font metrics
+            // Baseline of the first line, measured from the top of the text box
+            baseline: runs[0].3,
             line_count,
+            lines,
         })
     }
 }
+
+impl TextMeasurer<TextStyle> for PlatformTextMeasurer {
+    /// Measure text using real shaping and line layout.
+    ///
+    /// Results are cached process-wide by (font, text, style, max_width, wrap).
+    ///
+    /// Notes:
+    /// - Baseline is currently approximated
+    /// - Decorations and alignment are handled at render time
+    fn measure(
+        &self,
+        req: &TextMeasureRequest<TextStyle>,
+    ) -> Result<TextMetrics, TextMeasureError> {
+        let key = TextCacheKey::new(
+            self.font_key,
+            &req.text,
+            &req.style,
+            req.max_width,
+            req.wrap,
+        );
+
+        if let Ok(mut cache) = measure_cache().lock()
+            && let Some(metrics) = cache.get(&key)
+        {
+            return Ok(metrics.clone());
+        }
+
+        let metrics = self.measure_uncached(req)?;
+
+        if let Ok(mut cache) = measure_cache().lock() {
+            cache.insert(key, metrics.clone());
+        }
+
+        Ok(metrics)
+    }
+}
+
+/// Byte ranges of the paragraphs cosmic-text splits the text into
+/// (separated by `\n`, with a trailing `\r` excluded).
+fn paragraph_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+
+    for (i, _) in text.match_indices('\n') {
+        let end = if text[..i].ends_with('\r') { i - 1 } else { i };
+        ranges.push((start, end));
+        start = i + 1;
+    }
+    ranges.push((start, text.len()));
+
+    ranges
+}
+
+/// Group glyphs that belong to the same cluster (e.g. ligatures, combining marks).
+///
+/// Offsets are relative to the paragraph, as in [`LayoutGlyph`].
+fn clusters_of(glyphs: &[LayoutGlyph]) -> Vec<ClusterMetrics> {
+    let mut clusters: Vec<ClusterMetrics> = Vec::new();
+
+    for glyph in glyphs {
+        match clusters.last_mut() {
+            Some(last) if last.start == glyph.start && last.end == glyph.end => {
+                let right = (last.x + last.width).max(glyph.x + glyph.w);
+                last.x = last.x.min(glyph.x);
+                last.width = right - last.x;
+            }
+            _ => clusters.push(ClusterMetrics {
+                start: glyph.start,
+                end: glyph.end,
+                x: glyph.x,
+                width: glyph.w,
+            }),
+        }
+    }
+
+    clusters
+}
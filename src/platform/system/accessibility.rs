@@ -0,0 +1,244 @@
+//! AccessKit でアクセシビリティツリーを OS に渡す
+//!
+//! エンジンの [`AccessTree`] を AccessKit のノードにして、winit のウィンドウから
+//! スクリーンリーダーなどに公開する。ツリーは描き直すたびに作り、変わったときだけ送る。
+//! 支援技術からの操作（フォーカス、クリック）はチャネルで受け取り、イベントループで実行する。
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use accesskit::{
+    Action, ActionHandler, ActionRequest, ActivationHandler, Affine, DeactivationHandler, Node,
+    NodeId, Rect, Role as AkRole, Toggled, Tree, TreeUpdate, Vec2,
+};
+use accesskit_winit::Adapter;
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::Window;
+
+use crate::engine::accessibility::{AccessAction, AccessNode, AccessTree, Role, States, node_id};
+
+/// ウィンドウのノードの ID（ページのノードの ID は DOM のパスのハッシュ）
+const WINDOW_ID: NodeId = NodeId(0);
+
+pub struct Accessibility {
+    adapter: Adapter,
+    actions: Receiver<ActionRequest>,
+    /// 最後に送ったツリー。支援技術が後から有効になったときに渡す
+    latest: Arc<Mutex<Option<TreeUpdate>>>,
+    last_sent: Option<(AccessTree, String, f64)>,
+}
+
+impl Accessibility {
+    /// window に AccessKit のアダプターを付ける
+    ///
+    /// window は表示する前（`with_visible(false)` で作った直後）に渡すこと。
+    pub fn new(event_loop: &ActiveEventLoop, window: &Window) -> Self {
+        let (sender, actions) = mpsc::channel();
+        let latest = Arc::new(Mutex::new(None));
+        let adapter = Adapter::with_direct_handlers(
+            event_loop,
+            window,
+            InitialTree(latest.clone()),
+            ActionSender(sender),
+            NoDeactivation,
+        );
+        Self {
+            adapter,
+            actions,
+            latest,
+            last_sent: None,
+        }
+    }
+
+    /// ウィンドウのイベントを AccessKit に知らせる（フォーカスや大きさの変化）
+    pub fn process_event(&mut self, window: &Window, event: &WindowEvent) {
+        self.adapter.process_event(window, event);
+    }
+
+    /// ページのツリーを送る。前に送ったものと同じなら何もしない
+    pub fn update(&mut self, tree: Option<AccessTree>, title: &str, scale_factor: f64) {
+        let tree = tree.unwrap_or_else(empty_tree);
+        if self
+            .last_sent
+            .as_ref()
+            .is_some_and(|(t, s, f)| *t == tree && s == title && *f == scale_factor)
+        {
+            return;
+        }
+
+        let update = tree_update(&tree, title, scale_factor);
+        *self.latest.lock().unwrap_or_else(|e| e.into_inner()) = Some(update.clone());
+        self.adapter.update_if_active(|| update);
+        self.last_sent = Some((tree, title.to_string(), scale_factor));
+    }
+
+    /// 支援技術から頼まれた操作を取り出す
+    pub fn take_actions(&mut self) -> Vec<AccessAction> {
+        self.actions
+            .try_iter()
+            .filter_map(|request| {
+                let NodeId(id) = request.target;
+                match request.action {
+                    Action::Focus => Some(AccessAction::Focus(id)),
+                    Action::Click => Some(AccessAction::Click(id)),
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+struct InitialTree(Arc<Mutex<Option<TreeUpdate>>>);
+
+impl ActivationHandler for InitialTree {
+    fn request_initial_tree(&mut self) -> Option<TreeUpdate> {
+        // まだ送っていなければ、最初の描画のあとに update で送る
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+struct ActionSender(Sender<ActionRequest>);
+
+impl ActionHandler for ActionSender {
+    fn do_action(&mut self, request: ActionRequest) {
+        let _ = self.0.send(request);
+    }
+}
+
+struct NoDeactivation;
+
+impl DeactivationHandler for NoDeactivation {
+    fn deactivate_accessibility(&mut self) {}
+}
+
+/// ページを開いていないときのツリー（空の文書）
+fn empty_tree() -> AccessTree {
+    AccessTree {
+        root: AccessNode {
+            id: node_id(&[]),
+            role: Role::Document,
+            name: String::new(),
+            value: None,
+            url: None,
+            bounds: None,
+            states: States::default(),
+            path: Vec::new(),
+            children: Vec::new(),
+        },
+        focus: node_id(&[]),
+        offset: (0.0, 0.0),
+        scale: 1.0,
+    }
+}
+
+/// ツリー全体を AccessKit の更新にする
+///
+/// ウィンドウのノードで論理 px を物理 px に、文書のノードでページの位置とズームを
+/// 変換するので、各ノードの矩形はページの CSS px のまま渡す。
+fn tree_update(tree: &AccessTree, title: &str, scale_factor: f64) -> TreeUpdate {
+    let mut nodes = Vec::new();
+
+    let mut window = Node::new(AkRole::Window);
+    window.set_label(title);
+    window.set_transform(Affine::scale(scale_factor));
+    window.set_children(vec![NodeId(tree.root.id)]);
+    nodes.push((WINDOW_ID, window));
+
+    // 文書のノードは push_node で最初に入る
+    let document = nodes.len();
+    push_node(&tree.root, &mut nodes);
+    let (x, y) = tree.offset;
+    nodes[document].1.set_transform(
+        Affine::translate(Vec2::new(x as f64, y as f64)) * Affine::scale(tree.scale as f64),
+    );
+
+    TreeUpdate {
+        nodes,
+        tree: Some(Tree::new(WINDOW_ID)),
+        focus: NodeId(tree.focus),
+    }
+}
+
+fn push_node(node: &AccessNode, nodes: &mut Vec<(NodeId, Node)>) {
+    let mut ak = Node::new(role(node.role));
+    if !node.name.is_empty() {
+        ak.set_label(node.name.as_str());
+    }
+    if let Some(value) = &node.value {
+        ak.set_value(value.as_str());
+    }
+    if let Some(url) = &node.url {
+        ak.set_url(url.as_str());
+    }
+    if let Some(b) = node.bounds {
+        ak.set_bounds(Rect::new(
+            b.x as f64,
+            b.y as f64,
+            (b.x + b.width) as f64,
+            (b.y + b.height) as f64,
+        ));
+    }
+    if let Role::Heading { level } = node.role {
+        ak.set_level(level as usize);
+    }
+    if let Some(checked) = node.states.checked {
+        ak.set_toggled(if checked {
+            Toggled::True
+        } else {
+            Toggled::False
+        });
+    }
+    if node.states.disabled {
+        ak.set_disabled();
+    } else {
+        if node.states.focusable {
+            ak.add_action(Action::Focus);
+        }
+        if matches!(
+            node.role,
+            Role::Link | Role::Button | Role::CheckBox | Role::RadioButton
+        ) {
+            ak.add_action(Action::Click);
+        }
+    }
+    ak.set_children(
+        node.children
+            .iter()
+            .map(|child| NodeId(child.id))
+            .collect::<Vec<_>>(),
+    );
+    nodes.push((NodeId(node.id), ak));
+
+    for child in &node.children {
+        push_node(child, nodes);
+    }
+}
+
+fn role(role: Role) -> AkRole {
+    match role {
+        Role::Document => AkRole::Document,
+        Role::Heading { .. } => AkRole::Heading,
+        Role::Paragraph => AkRole::Paragraph,
+        Role::Link => AkRole::Link,
+        Role::Button => AkRole::Button,
+        Role::TextInput => AkRole::TextInput,
+        Role::CheckBox => AkRole::CheckBox,
+        Role::RadioButton => AkRole::RadioButton,
+        Role::Image => AkRole::Image,
+        Role::List => AkRole::List,
+        Role::ListItem => AkRole::ListItem,
+        Role::Table => AkRole::Table,
+        Role::Row => AkRole::Row,
+        Role::Cell => AkRole::Cell,
+        Role::Navigation => AkRole::Navigation,
+        Role::Main => AkRole::Main,
+        Role::Banner => AkRole::Banner,
+        Role::ContentInfo => AkRole::ContentInfo,
+        Role::Article => AkRole::Article,
+        Role::Section => AkRole::Section,
+        Role::Form => AkRole::Form,
+        Role::Frame => AkRole::Iframe,
+        Role::StaticText => AkRole::Label,
+    }
+}
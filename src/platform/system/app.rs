@@ -1,84 +1,267 @@
 use std::sync::Arc;
 use winit::application::ApplicationHandler;
+use winit::dpi::{LogicalPosition, LogicalSize};
 use winit::event::WindowEvent;
-use winit::event_loop::ActiveEventLoop;
-use winit::window::{Window, WindowId};
+use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
+use winit::window::{CursorIcon, ResizeDirection, Theme, Window, WindowId};
 
-use crate::browser::{BrowserApp, BrowserCommand};
+use super::accessibility::Accessibility;
+use crate::browser::core::{CommandSender, WindowAction};
+use crate::browser::{BrowserApp, BrowserEvent};
+use crate::engine::css::media::ColorScheme;
+use crate::platform::renderer::compositor::Compositor;
 use crate::platform::renderer::gpu::GpuRenderer;
+use crate::platform::renderer::software::SoftwareRenderer;
 
 pub struct State {
     pub window: Arc<Window>,
-    pub gpu_renderer: GpuRenderer,
+    /// GPU がなければ CPU で描く
+    pub renderer: Box<dyn Compositor>,
+    pub cursor: CursorIcon,
+    /// IME の変換候補を出している位置（None なら IME は無効）
+    pub ime_area: Option<(f32, f32, f32, f32)>,
+    pub accessibility: Accessibility,
 }
 
+impl State {
+    /// size（物理ピクセル）と title のウィンドウを開き、GPU とアクセシビリティを用意する
+    pub fn open(event_loop: &ActiveEventLoop, size: (u32, u32), title: String) -> Self {
+        let window = Arc::new(
+            event_loop
+                .create_window(
+                    Window::default_attributes()
+                        .with_inner_size(winit::dpi::PhysicalSize::new(size.0, size.1))
+                        .with_title(title)
+                        // AccessKit のアダプターは表示する前に付ける
+                        .with_visible(false),
+                )
+                .unwrap(),
+        );
+        let accessibility = Accessibility::new(event_loop, &window);
+        window.set_visible(true);
+        Self {
+            window: window.clone(),
+            renderer: open_renderer(window.clone()).expect("failed to create a renderer"),
+            cursor: CursorIcon::Default,
+            ime_area: None,
+            accessibility,
+        }
+    }
+
+    /// OS の配色
+    pub fn color_scheme(&self) -> ColorScheme {
+        match self.window.theme() {
+            Some(Theme::Dark) => ColorScheme::Dark,
+            _ => ColorScheme::Light,
+        }
+    }
+
+    /// ウィンドウのあるディスプレイのリフレッシュレート（ミリヘルツ）
+    pub fn refresh_rate_millihertz(&self) -> Option<u32> {
+        self.window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+    }
+
+    /// ウィンドウの縁では大きさを変える向きの矢印に、リンクの上ではポインタにする
+    pub fn set_pointer(&mut self, over_link: bool, resize_edge: Option<ResizeDirection>) {
+        let cursor = match resize_edge {
+            Some(direction) => resize_cursor(direction),
+            None if over_link => CursorIcon::Pointer,
+            None => CursorIcon::Default,
+        };
+        if cursor != self.cursor {
+            self.window.set_cursor(cursor);
+            self.cursor = cursor;
+        }
+    }
+
+    /// 入力欄にフォーカスがあるときだけ IME を有効にし、候補をキャレットに出す
+    pub fn set_ime_area(&mut self, ime_area: Option<(f32, f32, f32, f32)>) {
+        if ime_area == self.ime_area {
+            return;
+        }
+        if ime_area.is_some() != self.ime_area.is_some() {
+            self.window.set_ime_allowed(ime_area.is_some());
+        }
+        if let Some((x, y, width, height)) = ime_area {
+            self.window
+                .set_ime_cursor_area(LogicalPosition::new(x, y), LogicalSize::new(width, height));
+        }
+        self.ime_area = ime_area;
+    }
+
+    /// ブラウザが描いたタイトルバーで頼まれた操作をする
+    pub fn perform_window_action(&self, action: WindowAction) {
+        let result = match action {
+            WindowAction::Drag => self.window.drag_window(),
+            WindowAction::DragResize(direction) => self.window.drag_resize_window(direction),
+            WindowAction::Minimize => {
+                self.window.set_minimized(true);
+                Ok(())
+            }
+            WindowAction::ToggleMaximize => {
+                self.window.set_maximized(!self.window.is_maximized());
+                Ok(())
+            }
+        };
+        // ボタンを放した後に届いたときなどは動かせない
+        if let Err(e) = result {
+            log::warn!("Window action {:?} failed: {}", action, e);
+        }
+    }
+}
+
+fn resize_cursor(direction: ResizeDirection) -> CursorIcon {
+    match direction {
+        ResizeDirection::East => CursorIcon::EResize,
+        ResizeDirection::North => CursorIcon::NResize,
+        ResizeDirection::NorthEast => CursorIcon::NeResize,
+        ResizeDirection::NorthWest => CursorIcon::NwResize,
+        ResizeDirection::South => CursorIcon::SResize,
+        ResizeDirection::SouthEast => CursorIcon::SeResize,
+        ResizeDirection::SouthWest => CursorIcon::SwResize,
+        ResizeDirection::West => CursorIcon::WResize,
+    }
+}
+
+/// window に描くレンダラーを作る
+///
+/// 使える GPU のアダプターがない（仮想マシン、古い GPU など）か `ORINIUM_SOFTWARE_RENDER=1`
+/// なら CPU で描く [`SoftwareRenderer`] にする。それも作れなければエラー。
+pub(crate) fn open_renderer(window: Arc<Window>) -> anyhow::Result<Box<dyn Compositor>> {
+    let force_software = std::env::var("ORINIUM_SOFTWARE_RENDER").is_ok_and(|v| v != "0");
+    if !force_software {
+        match pollster::block_on(GpuRenderer::new(window.clone(), None)) {
+            Ok(gpu) => return Ok(Box::new(gpu)),
+            Err(e) => log::warn!(
+                "No usable GPU ({:#}); falling back to software rendering",
+                e
+            ),
+        }
+    }
+    Ok(Box::new(SoftwareRenderer::new(window)?))
+}
+
+/// winit のイベントループ
+///
+/// 入力から作ったコマンドはブラウザに送るだけで、実行するのはイベントを処理し終えてから
+/// （[`BrowserApp::process_commands`]）。ブラウザからは描き直しやタイトルの変化が
+/// [`BrowserEvent`] として `EventLoopProxy` 経由で届く。
 pub struct App {
     state: Option<State>,
     browser_app: BrowserApp,
+    commands: CommandSender,
 }
 
 impl App {
-    pub fn new(browser_app: BrowserApp) -> Self {
+    pub fn new(mut browser_app: BrowserApp, proxy: EventLoopProxy<BrowserEvent>) -> Self {
+        browser_app.set_event_sink(Box::new(proxy));
         Self {
             state: None,
+            commands: browser_app.command_sender(),
             browser_app,
         }
     }
 }
 
-impl ApplicationHandler<State> for App {
+impl ApplicationHandler<BrowserEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         // reqed = requested
         let reqed_window_size = self.browser_app.window_size();
-        let reqed_window_title = self.browser_app.window_title();
-        let window = Arc::new(
-            event_loop
-                .create_window(
-                    Window::default_attributes()
-                        .with_inner_size(winit::dpi::PhysicalSize::new(
-                            reqed_window_size.0,
-                            reqed_window_size.1,
-                        ))
-                        .with_title(reqed_window_title),
-                )
-                .unwrap(),
+        let state = State::open(
+            event_loop,
+            (reqed_window_size.0 as u32, reqed_window_size.1 as u32),
+            self.browser_app.window_title(),
         );
-        let state = State {
-            window: window.clone(),
-            gpu_renderer: pollster::block_on(GpuRenderer::new(window.clone(), None)).unwrap(),
-        };
         self.state = Some(state);
 
         // 初回描画
+        // 頼んだ大きさで開くとは限らない（タイル型のウィンドウマネージャーなど）ので、
+        // 実際の大きさでレイアウトする
         if let Some(state) = &mut self.state {
+            let size = state.window.inner_size();
+            self.browser_app.set_window_size((size.width, size.height));
             self.browser_app
                 .set_scale_factor(state.window.scale_factor());
             self.browser_app
-                .apply_draw_commands(&mut state.gpu_renderer);
+                .set_system_color_scheme(state.color_scheme());
+            if let Some(millihertz) = state.refresh_rate_millihertz() {
+                self.browser_app.set_refresh_rate(millihertz);
+            }
+            self.browser_app
+                .apply_draw_commands(state.renderer.as_mut());
             state.window.request_redraw();
         }
     }
 
+    /// イベントを処理し終えて待つ前に、期限の来たタイマーと送ったコマンドを実行する
+    ///
+    /// 入力のないページでも setTimeout などが動くように、ここでもスケジューラを回す。
+    /// イベントループは ControlFlow::Poll なので、タイマーの期限を過ぎればすぐここに来る。
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        self.commands.send(self.browser_app.run_scheduled_tasks());
+        self.commands.send(self.browser_app.poll_remote_debugging());
+
+        // スクリーンリーダーなどからの操作
+        for action in state.accessibility.take_actions() {
+            self.commands
+                .send(self.browser_app.perform_accessibility_action(action));
+        }
+
+        self.browser_app.process_commands();
+    }
+
+    /// ブラウザからのイベントをウィンドウに反映する
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: BrowserEvent) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        match event {
+            BrowserEvent::Exit => event_loop.exit(),
+            BrowserEvent::TitleChanged(title) => state.window.set_title(&title),
+            // 描き直しはフレームのスケジューラがリフレッシュごとに 1 回だけ頼む
+            BrowserEvent::NeedsRedraw => state.window.request_redraw(),
+            // 進み具合の描き直しも NeedsRedraw で頼まれる
+            BrowserEvent::LoadProgress(_) => {}
+            BrowserEvent::Decorations(decorated) => state.window.set_decorations(decorated),
+            BrowserEvent::WindowAction(action) => state.perform_window_action(action),
+            // エンジンのスレッドで動かすとき（ThreadedApp）だけ届く
+            BrowserEvent::Frame(_) | BrowserEvent::WindowState(_) => {}
+        }
+    }
+
     fn window_event(
         &mut self,
-        event_loop: &ActiveEventLoop,
+        _event_loop: &ActiveEventLoop,
         _window_id: WindowId,
         event: WindowEvent,
     ) {
         if let Some(state) = &mut self.state {
-            match self
+            state.accessibility.process_event(&state.window, &event);
+            let redrawn = matches!(event, WindowEvent::RedrawRequested);
+            let cmd = self
                 .browser_app
-                .handle_window_event(event, &mut state.gpu_renderer)
-            {
-                BrowserCommand::Exit => event_loop.exit(),
-                BrowserCommand::RequestRedraw => {
-                    state.window.request_redraw();
-                    state.window.set_title(&self.browser_app.window_title());
-                }
-                BrowserCommand::RenameWindowTitle => {
-                    state.window.set_title(&self.browser_app.window_title())
-                }
-                BrowserCommand::None => {}
+                .handle_window_event(event, state.renderer.as_mut());
+            // タブ操作などはブラウザ側で実行し、その結果はイベントで届く
+            self.commands.send(cmd);
+
+            state.set_pointer(
+                self.browser_app.is_over_link(),
+                self.browser_app.resize_edge(),
+            );
+            state.set_ime_area(self.browser_app.ime_cursor_area());
+
+            // 描き直したらアクセシビリティツリーも新しくする
+            if redrawn {
+                state.accessibility.update(
+                    self.browser_app.accessibility_tree(),
+                    &self.browser_app.window_title(),
+                    state.window.scale_factor(),
+                );
             }
         }
     }
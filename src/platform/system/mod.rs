@@ -1,4 +1,7 @@
+pub mod accessibility;
 pub mod app;
+pub mod threaded;
 
 pub use app::App;
 pub use app::State;
+pub use threaded::ThreadedApp;
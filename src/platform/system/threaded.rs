@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use winit::application::ApplicationHandler;
+use winit::event::WindowEvent;
+use winit::event_loop::ActiveEventLoop;
+use winit::window::WindowId;
+
+use super::app::State;
+use crate::browser::BrowserEvent;
+use crate::browser::core::WindowState;
+use crate::browser::core::engine_thread::{EngineMessage, EngineReady, EngineThread};
+use crate::platform::renderer::compositor::Frame;
+
+/// ページの処理をエンジンのスレッドに任せる winit のイベントループ
+///
+/// このスレッドはウィンドウと GPU だけを持ち、ウィンドウのイベントはエンジンに送る。
+/// エンジンが描いたフレームが届くまでは前のフレームを出し続けるので、重いページでも
+/// 大きさの変更やウィンドウの操作は止まらない。
+pub struct ThreadedApp {
+    state: Option<State>,
+    engine: EngineThread,
+    ready: EngineReady,
+    /// 最後に届いたフレーム
+    frame: Option<Arc<Frame>>,
+    /// 最後に届いたページの状態
+    window_state: WindowState,
+    title: String,
+}
+
+impl ThreadedApp {
+    pub fn new(engine: EngineThread, ready: EngineReady) -> Self {
+        Self {
+            state: None,
+            engine,
+            title: ready.window_title.clone(),
+            ready,
+            frame: None,
+            window_state: WindowState::default(),
+        }
+    }
+}
+
+impl ApplicationHandler<BrowserEvent> for ThreadedApp {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let state = State::open(event_loop, self.ready.window_size, self.title.clone());
+        // 頼んだ大きさで開くとは限らないので、実際の大きさを知らせる
+        let size = state.window.inner_size();
+        self.engine.send(EngineMessage::WindowOpened {
+            size: (size.width, size.height),
+            scale_factor: state.window.scale_factor(),
+            color_scheme: state.color_scheme(),
+            refresh_rate_millihertz: state.refresh_rate_millihertz(),
+        });
+        self.state = Some(state);
+    }
+
+    /// スクリーンリーダーなどからの操作をエンジンに送る
+    fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        for action in state.accessibility.take_actions() {
+            self.engine.send(EngineMessage::Accessibility(action));
+        }
+    }
+
+    /// エンジンからのフレームと状態をウィンドウに反映する
+    fn user_event(&mut self, event_loop: &ActiveEventLoop, event: BrowserEvent) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        match event {
+            BrowserEvent::Exit => event_loop.exit(),
+            BrowserEvent::TitleChanged(title) => {
+                state.window.set_title(&title);
+                self.title = title;
+            }
+            BrowserEvent::Frame(frame) => {
+                frame.apply(state.renderer.as_mut());
+                self.frame = Some(frame);
+                state.window.request_redraw();
+            }
+            BrowserEvent::WindowState(window_state) => {
+                state.set_pointer(window_state.over_link, window_state.resize_edge);
+                state.set_ime_area(window_state.ime_area);
+                self.window_state = *window_state;
+            }
+            BrowserEvent::Decorations(decorated) => state.window.set_decorations(decorated),
+            BrowserEvent::WindowAction(action) => state.perform_window_action(action),
+            // 進み具合もフレームに描かれて届く
+            BrowserEvent::NeedsRedraw | BrowserEvent::LoadProgress(_) => {}
+        }
+    }
+
+    fn window_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _window_id: WindowId,
+        event: WindowEvent,
+    ) {
+        let Some(state) = &mut self.state else {
+            return;
+        };
+        state.accessibility.process_event(&state.window, &event);
+        match event {
+            // 届いているフレームを出す。新しいフレームはエンジンが描き終えたら届く
+            WindowEvent::RedrawRequested => {
+                if self.frame.is_some()
+                    && let Err(e) = state.renderer.render()
+                {
+                    log::error!(target: "ThreadedApp::redraw", "Render error occurred: {}", e);
+                }
+                state.accessibility.update(
+                    self.window_state.access_tree.clone(),
+                    &self.title,
+                    state.window.scale_factor(),
+                );
+            }
+            // レイアウトし直したフレームが届くまでは前のフレームを引き伸ばして出す
+            WindowEvent::Resized(size) => {
+                state.renderer.resize(size);
+                state.window.request_redraw();
+                self.engine
+                    .send(EngineMessage::Window(WindowEvent::Resized(size)));
+            }
+            event => self.engine.send(EngineMessage::Window(event)),
+        }
+    }
+}
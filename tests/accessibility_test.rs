@@ -0,0 +1,121 @@
+use orinium_browser::engine::accessibility::{self, AccessNode, AccessTree, Role};
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter::css_resolver::CssResolver;
+use orinium_browser::engine::layouter::{self, types::TextStyle};
+
+const HTML: &str = r#"<html><head><title>Example  page</title></head><body>
+<div><h2>Getting <em>started</em></h2></div>
+<p>Read the <a href="/docs">docs</a>.</p>
+<div class="hidden"><button>Hidden</button></div>
+<button>Go</button>
+<input type="checkbox" checked>
+<input type="submit">
+<input value="abc" placeholder="Name">
+<img alt="Logo">
+</body></html>"#;
+
+fn tree(focus_path: Option<&[usize]>) -> AccessTree {
+    let dom = HtmlParser::new(HTML).parse();
+    let sheet = CssParser::new(".hidden { display: none }").parse().unwrap();
+    let styles = CssResolver::resolve_with_media(&sheet, &MediaContext::default());
+    let (mut layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+    accessibility::build_tree(&dom.root, &layout, &info, focus_path)
+}
+
+/// 役割がテキスト以外のノードを (役割, 名前) で文書順に並べる
+fn outline(tree: &AccessTree) -> Vec<(Role, String)> {
+    let mut nodes = Vec::new();
+    tree.root.walk(&mut |node| {
+        if node.role != Role::StaticText {
+            nodes.push((node.role, node.name.clone()));
+        }
+    });
+    nodes
+}
+
+fn find_by_role(tree: &AccessTree, role: Role) -> AccessNode {
+    let mut found = None;
+    tree.root.walk(&mut |node| {
+        if node.role == role && found.is_none() {
+            found = Some(node.clone());
+        }
+    });
+    found.expect("node with the role")
+}
+
+#[test]
+fn elements_without_a_role_are_flattened() {
+    let tree = tree(None);
+    assert_eq!(
+        outline(&tree),
+        vec![
+            (Role::Document, "Example page".to_string()),
+            (Role::Heading { level: 2 }, "Getting started".to_string()),
+            (Role::Paragraph, String::new()),
+            (Role::Link, "docs".to_string()),
+            (Role::Button, "Go".to_string()),
+            (Role::CheckBox, String::new()),
+            (Role::Button, "Submit".to_string()),
+            (Role::TextInput, "Name".to_string()),
+            (Role::Image, "Logo".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn nodes_carry_values_states_and_bounds() {
+    let tree = tree(None);
+
+    let link = find_by_role(&tree, Role::Link);
+    assert_eq!(link.url.as_deref(), Some("/docs"));
+    assert!(link.states.focusable);
+    assert_eq!(link.children.len(), 1);
+    assert_eq!(link.children[0].role, Role::StaticText);
+
+    assert_eq!(
+        find_by_role(&tree, Role::CheckBox).states.checked,
+        Some(true)
+    );
+    assert_eq!(
+        find_by_role(&tree, Role::TextInput).value.as_deref(),
+        Some("abc")
+    );
+
+    let heading = find_by_role(&tree, Role::Heading { level: 2 })
+        .bounds
+        .unwrap();
+    let paragraph = find_by_role(&tree, Role::Paragraph).bounds.unwrap();
+    assert!(heading.width > 0.0 && heading.height > 0.0);
+    assert_ne!(heading, paragraph);
+}
+
+#[test]
+fn ids_are_stable_and_follow_the_focus() {
+    let first = tree(None);
+    assert_eq!(first, tree(None));
+    assert_eq!(first.focus, first.root.id);
+
+    let button = find_by_role(&first, Role::Button);
+    assert_eq!(button.id, accessibility::node_id(&button.path));
+
+    let focused = tree(Some(&button.path));
+    assert_eq!(focused.focus, button.id);
+    assert!(find_by_role(&focused, Role::Button).states.focused);
+}
@@ -0,0 +1,119 @@
+use orinium_browser::engine::accessibility::{self, AccessTree, Role};
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter::css_resolver::CssResolver;
+use orinium_browser::engine::layouter::{self, types::TextStyle};
+
+fn tree(html: &str) -> AccessTree {
+    let dom = HtmlParser::new(html).parse();
+    let sheet = CssParser::new("").parse().unwrap();
+    let styles = CssResolver::resolve_with_media(&sheet, &MediaContext::default());
+    let (mut layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+    accessibility::build_tree(&dom.root, &layout, &info, None)
+}
+
+/// 文書とテキスト以外のノードを (役割, 名前) で文書順に並べる
+fn outline(html: &str) -> Vec<(Role, String)> {
+    let mut nodes = Vec::new();
+    tree(html).root.walk(&mut |node| {
+        if !matches!(node.role, Role::Document | Role::StaticText) {
+            nodes.push((node.role, node.name.clone()));
+        }
+    });
+    nodes
+}
+
+fn named(role: Role, name: &str) -> (Role, String) {
+    (role, name.to_string())
+}
+
+#[test]
+fn aria_label_and_labelledby_override_the_content() {
+    let html = r#"<html><body>
+<span id="a">Save</span><span id="b" hidden>draft</span>
+<button aria-label="Close">X</button>
+<button aria-labelledby="a b" aria-label="ignored">S</button>
+<a href="/x" aria-labelledby="missing">Fallback</a>
+</body></html>"#;
+    assert_eq!(
+        outline(html),
+        vec![
+            named(Role::Button, "Close"),
+            named(Role::Button, "Save draft"),
+            named(Role::Link, "Fallback"),
+        ]
+    );
+}
+
+#[test]
+fn role_attribute_overrides_the_element() {
+    let html = r#"<html><body>
+<div role="button">Play</div>
+<div role="heading" aria-level="3">Title</div>
+<h4 role="presentation">Plain</h4>
+<a href="/y" role="none">Kept</a>
+<div role="unknown link"><b>Next</b> page</div>
+</body></html>"#;
+    assert_eq!(
+        outline(html),
+        vec![
+            named(Role::Button, "Play"),
+            named(Role::Heading { level: 3 }, "Title"),
+            named(Role::Link, "Kept"),
+            named(Role::Link, "Next page"),
+        ]
+    );
+}
+
+#[test]
+fn aria_hidden_subtrees_are_left_out() {
+    let html = r#"<html><body>
+<div aria-hidden="true"><button>Hidden</button></div>
+<button><span aria-hidden="true">★</span> Star</button>
+<img alt="">
+</body></html>"#;
+    assert_eq!(outline(html), vec![named(Role::Button, "Star")]);
+}
+
+#[test]
+fn form_controls_are_named_by_their_labels() {
+    let html = r#"<html><body>
+<label for="email">E-mail</label><input id="email">
+<label>Remember me <input type="checkbox"></label>
+<input type="checkbox" aria-labelledby="l"><span id="l">Show <input value="10"> items</span>
+<input title="Search" placeholder="Query">
+<input placeholder="Query">
+<input type="submit" value="Send">
+<textarea aria-labelledby="c"></textarea><p id="c">Comment</p>
+</body></html>"#;
+    assert_eq!(
+        outline(html),
+        vec![
+            named(Role::TextInput, "E-mail"),
+            named(Role::CheckBox, "Remember me"),
+            named(Role::CheckBox, "Show 10 items"),
+            named(Role::TextInput, ""),
+            named(Role::TextInput, "Search"),
+            named(Role::TextInput, "Query"),
+            named(Role::Button, "Send"),
+            named(Role::TextInput, "Comment"),
+            named(Role::Paragraph, ""),
+        ]
+    );
+}
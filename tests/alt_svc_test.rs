@@ -0,0 +1,59 @@
+use orinium_browser::platform::network::alt_svc::{self, AltSvcStore, AltSvcValue};
+use std::time::{Duration, Instant};
+
+#[test]
+fn h3_alternative_is_parsed() {
+    assert_eq!(
+        alt_svc::parse(r#"h3=":443"; ma=3600, h3-29=":443""#),
+        AltSvcValue::Http3 {
+            host: None,
+            port: 443,
+            max_age: Duration::from_secs(3600),
+        }
+    );
+    assert_eq!(
+        alt_svc::parse(r#"h2="alt.example.com:8443", h3="quic.example.com:4433""#),
+        AltSvcValue::Http3 {
+            host: Some("quic.example.com".into()),
+            port: 4433,
+            max_age: Duration::from_secs(24 * 60 * 60),
+        }
+    );
+    assert_eq!(alt_svc::parse(r#"h3-29=":443""#), AltSvcValue::Other);
+    assert_eq!(alt_svc::parse(" clear "), AltSvcValue::Clear);
+}
+
+#[test]
+fn store_remembers_until_max_age() {
+    let store = AltSvcStore::new();
+    let now = Instant::now();
+    store.record("Example.com", 443, r#"h3=":443"; ma=60"#, now);
+
+    assert_eq!(
+        store.http3_endpoint("example.com", 443, now),
+        Some(("example.com".to_string(), 443))
+    );
+    assert_eq!(store.http3_endpoint("example.com", 8443, now), None);
+    assert_eq!(
+        store.http3_endpoint("example.com", 443, now + Duration::from_secs(61)),
+        None
+    );
+
+    store.record("example.com", 443, "clear", now);
+    assert_eq!(store.http3_endpoint("example.com", 443, now), None);
+}
+
+#[test]
+fn broken_origins_use_tcp_for_a_while() {
+    let store = AltSvcStore::new();
+    let now = Instant::now();
+    store.record("example.com", 443, r#"h3=":443""#, now);
+    store.mark_broken("example.com", 443, now);
+
+    assert_eq!(store.http3_endpoint("example.com", 443, now), None);
+    assert!(
+        store
+            .http3_endpoint("example.com", 443, now + Duration::from_secs(10 * 60))
+            .is_some()
+    );
+}
@@ -0,0 +1,113 @@
+use orinium_browser::browser::core::autofill::{AutofillField, AutofillProfile, AutofillStore};
+use orinium_browser::browser::core::webview::form::{self, FieldValues};
+use orinium_browser::engine::html::parser::Parser;
+
+const FORM: &str = r#"<html><body>
+<form action="/checkout">
+    <input name="full_name" value="Taro Yamada">
+    <input type="email" name="contact" value="taro@example.com">
+    <input name="addr" autocomplete="shipping street-address" value="1-1 Chiyoda">
+    <input name="zip" value="100-0001">
+    <input name="username" value="taro">
+    <input type="password" name="password" value="secret">
+    <input name="email_disabled" value="old@example.com" disabled>
+    <input name="nickname" autocomplete="off" value="tarochan">
+</form>
+</body></html>"#;
+
+fn profile(values: &[(AutofillField, &str)]) -> AutofillProfile {
+    let mut profile = AutofillProfile::new();
+    for (field, value) in values {
+        profile.set(*field, value);
+    }
+    profile
+}
+
+#[test]
+fn fields_are_recognized_from_autocomplete_type_and_name() {
+    let dom = Parser::new(FORM).parse();
+    let inputs = dom.get_elements_by_tag_name("input");
+    let fields: Vec<Option<AutofillField>> = inputs
+        .iter()
+        .map(|input| AutofillField::for_input(&input.borrow().value))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            Some(AutofillField::Name),
+            Some(AutofillField::Email),
+            Some(AutofillField::StreetAddress),
+            Some(AutofillField::PostalCode),
+            None,
+            None,
+            Some(AutofillField::Email),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn submitted_form_becomes_a_profile() {
+    let dom = Parser::new(FORM).parse();
+    let input = dom.get_elements_by_tag_name("input")[0].clone();
+    let owner = form::form_owner(&input).expect("form owner");
+
+    let profile = form::autofill_profile(&owner, &FieldValues::new());
+    assert_eq!(profile.get(AutofillField::Name), Some("Taro Yamada"));
+    assert_eq!(profile.get(AutofillField::Email), Some("taro@example.com"));
+    assert_eq!(
+        profile.get(AutofillField::StreetAddress),
+        Some("1-1 Chiyoda")
+    );
+    assert_eq!(profile.get(AutofillField::PostalCode), Some("100-0001"));
+}
+
+#[test]
+fn suggestions_match_the_typed_prefix_newest_first() {
+    let mut store = AutofillStore::new();
+    assert!(store.add_profile(profile(&[
+        (AutofillField::Name, "Taro Yamada"),
+        (AutofillField::Email, "taro@example.com"),
+    ])));
+    assert!(store.add_profile(profile(&[(AutofillField::Email, "tanaka@example.org")])));
+
+    assert_eq!(
+        store.suggestions(AutofillField::Email, "TA"),
+        ["tanaka@example.org", "taro@example.com"]
+    );
+    assert_eq!(
+        store.suggestions(AutofillField::Email, "taro"),
+        ["taro@example.com"]
+    );
+    // 入力済みの値そのものは出さない
+    assert!(
+        store
+            .suggestions(AutofillField::Email, "taro@example.com")
+            .is_empty()
+    );
+    assert!(store.suggestions(AutofillField::Tel, "").is_empty());
+}
+
+#[test]
+fn profiles_are_merged_and_saved() {
+    let mut store = AutofillStore::new();
+    store.add_profile(profile(&[(AutofillField::Name, "Taro Yamada")]));
+    // 覚えているものを含むプロファイルは置き換える
+    assert!(store.add_profile(profile(&[
+        (AutofillField::Name, "Taro Yamada"),
+        (AutofillField::City, "Tokyo"),
+    ])));
+    // 覚えているものに含まれるプロファイルは増やさない
+    assert!(!store.add_profile(profile(&[(AutofillField::City, "Tokyo")])));
+    assert!(!store.add_profile(AutofillProfile::new()));
+    assert_eq!(store.profiles().len(), 1);
+
+    let text = store.serialize();
+    assert_eq!(text, "profile\nname\tTaro Yamada\naddress-level2\tTokyo\n");
+    assert_eq!(AutofillStore::parse(&text), store);
+    assert!(
+        AutofillStore::parse("name\tstray\nprofile\nunknown\tx\n")
+            .profiles()
+            .is_empty()
+    );
+}
@@ -0,0 +1,74 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::browser::{BrowserApp, Tab};
+use orinium_browser::platform::renderer::compositor::FrameRecorder;
+
+fn page(text: &str) -> url::Url {
+    format!("data:text/html,<p>{text}</p>").parse().unwrap()
+}
+
+/// url を新しいタブで開き、読み込み終えたら描く
+fn open(browser: &mut BrowserApp, url: url::Url) {
+    browser.open_in_new_tab(url);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !browser.active_tab().is_some_and(Tab::is_loaded) && Instant::now() < deadline {
+        browser.tick();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    browser.redraw(&mut FrameRecorder::new((800, 600)));
+}
+
+#[test]
+fn hidden_time_is_kept_until_the_tab_comes_back() {
+    let mut tab = Tab::new();
+    let start = Instant::now();
+    assert_eq!(tab.hidden_since(), None);
+
+    tab.set_hidden(true, start);
+    tab.set_hidden(true, start + Duration::from_secs(3));
+    assert_eq!(tab.hidden_since(), Some(start));
+
+    tab.set_hidden(false, start + Duration::from_secs(4));
+    assert_eq!(tab.hidden_since(), None);
+    // 開いていないタブには捨てるものがない
+    assert!(!tab.discard_render_data());
+}
+
+#[test]
+fn background_layout_is_discarded_and_rebuilt_on_activation() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    open(&mut browser, page("first"));
+    open(&mut browser, page("second"));
+    assert!(browser.tab(0).unwrap().layout_and_info().is_some());
+
+    // 表のタブは捨てない
+    browser.set_background_discard_delay(Duration::ZERO);
+    browser.tick();
+    assert!(browser.tab(0).unwrap().hidden_since().is_some());
+    assert!(browser.tab(0).unwrap().layout_and_info().is_none());
+    assert!(browser.tab(1).unwrap().layout_and_info().is_some());
+
+    browser.switch_tab(0);
+    browser.redraw(&mut FrameRecorder::new((800, 600)));
+    let tab = browser.tab(0).unwrap();
+    assert!(tab.hidden_since().is_none());
+    assert!(tab.layout_and_info().is_some());
+}
+
+#[test]
+fn headless_loads_keep_the_window_state() {
+    let mut browser = BrowserApp::new((1024, 768), "Orinium Browser".to_string());
+    open(&mut browser, page("first"));
+    browser.set_chrome_visible(true);
+    browser.set_window_size((1280, 720));
+
+    browser.dump_layout(page("second"), (400, 300)).unwrap();
+
+    // 読み込みに使ったタブは閉じ、ウィンドウは前のまま
+    assert_eq!(browser.window_size(), (1280.0, 720.0));
+    assert_eq!(
+        browser.active_tab().unwrap().document_url(),
+        Some(page("first"))
+    );
+    assert!(browser.tab(1).is_none());
+}
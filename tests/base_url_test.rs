@@ -0,0 +1,35 @@
+use orinium_browser::browser::core::webview::document_base;
+use orinium_browser::engine::html::parser::Parser;
+use url::Url;
+
+fn base_of(html: &str) -> (String, Option<String>) {
+    let dom = Parser::new(html).parse();
+    let document = Url::parse("https://example.com/dir/page.html").unwrap();
+    let (url, target) = document_base(&dom, &document);
+    (url.to_string(), target)
+}
+
+#[test]
+fn first_base_with_each_attribute_wins() {
+    let (url, target) = base_of(
+        r#"<html><head>
+<base target=" _blank ">
+<base href="/static/">
+<base href="https://other.test/" target="_self">
+</head></html>"#,
+    );
+    assert_eq!(url, "https://example.com/static/");
+    assert_eq!(target.as_deref(), Some("_blank"));
+}
+
+#[test]
+fn document_url_is_used_without_a_usable_base() {
+    let (url, target) = base_of("<html><head><title>x</title></head></html>");
+    assert_eq!(url, "https://example.com/dir/page.html");
+    assert_eq!(target, None);
+
+    let (url, _) = base_of(r#"<html><head><base href="javascript:alert(1)"></head></html>"#);
+    assert_eq!(url, "https://example.com/dir/page.html");
+    let (url, _) = base_of(r#"<html><head><base href="http://[::1"></head></html>"#);
+    assert_eq!(url, "https://example.com/dir/page.html");
+}
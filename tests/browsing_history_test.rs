@@ -0,0 +1,80 @@
+use orinium_browser::browser::Settings;
+use orinium_browser::browser::core::browsing_history::BrowsingHistory;
+use orinium_browser::browser::core::internal_pages::{self, InternalPageContext};
+
+fn history() -> BrowsingHistory {
+    let mut history = BrowsingHistory::new();
+    history.record_visit(
+        "https://www.rust-lang.org/".parse().unwrap(),
+        Some("Rust Programming Language".to_string()),
+        100,
+    );
+    history.record_visit(
+        "https://example.com/".parse().unwrap(),
+        Some("Example Domain".to_string()),
+        200,
+    );
+    history.record_visit(
+        "https://docs.rs/rustls".parse().unwrap(),
+        Some("rustls - Rust".to_string()),
+        300,
+    );
+    history
+}
+
+#[test]
+fn revisits_update_the_existing_entry() {
+    let mut history = history();
+    let url = "https://example.com/".parse().unwrap();
+    history.record_visit(url, None, 400);
+
+    let url = "https://example.com/".parse().unwrap();
+    let visit = history.get(&url).unwrap();
+    assert_eq!(history.len(), 3);
+    assert_eq!(visit.visit_count, 2);
+    assert_eq!(visit.last_visit, 400);
+    // タイトルが取れなかった訪問では前のタイトルを残す
+    assert_eq!(visit.title.as_deref(), Some("Example Domain"));
+}
+
+#[test]
+fn suggestions_prefer_url_prefix_matches() {
+    let history = history();
+
+    let urls: Vec<String> = history
+        .suggest("rust", 5)
+        .iter()
+        .map(|v| v.url.to_string())
+        .collect();
+    // www. を除いた URL が入力で始まるものが先、タイトルに含むものが後
+    assert_eq!(
+        urls,
+        ["https://www.rust-lang.org/", "https://docs.rs/rustls"]
+    );
+
+    assert_eq!(history.suggest("EXAMPLE.com", 5).len(), 1);
+    assert!(history.suggest("  ", 5).is_empty());
+}
+
+#[test]
+fn history_round_trips_through_text() {
+    let history = history();
+    assert_eq!(BrowsingHistory::parse(&history.serialize()), history);
+}
+
+#[test]
+fn history_page_lists_matching_visits() {
+    let history = history();
+    let settings = Settings::default();
+    let ctx = InternalPageContext {
+        history: &history,
+        settings: &settings,
+        inspection: None,
+        console: None,
+    };
+
+    let page = internal_pages::load(&"orinium://history?q=rust".parse().unwrap(), &ctx)
+        .expect("history page");
+    assert!(page.contains("https://docs.rs/rustls"));
+    assert!(!page.contains("https://example.com/"));
+}
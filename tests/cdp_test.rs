@@ -0,0 +1,266 @@
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+use orinium_browser::browser::core::cdp::json::Json;
+use orinium_browser::browser::core::cdp::websocket::{self, Frame, HttpRequest};
+use orinium_browser::browser::core::cdp::{CdpServer, RemoteTarget, Session, TargetInfo};
+use orinium_browser::engine::html::HtmlNodeType;
+use orinium_browser::engine::html::parser::Parser;
+use orinium_browser::engine::script::ScriptValue;
+use orinium_browser::engine::tree::NodeRef;
+use url::Url;
+
+/// 1 つのタブだけを持ち、移動するとすぐ決まった文書を読み込むブラウザ
+#[derive(Default)]
+struct FakeBrowser {
+    url: Option<Url>,
+    loaded: bool,
+}
+
+impl RemoteTarget for FakeBrowser {
+    fn targets(&self) -> Vec<TargetInfo> {
+        vec![TargetInfo {
+            id: 7,
+            url: self.url.as_ref().map(Url::to_string).unwrap_or_default(),
+            title: "Fake".to_string(),
+        }]
+    }
+
+    fn navigate(&mut self, _target: Option<u64>, url: Url) -> Result<u64> {
+        self.url = Some(url);
+        self.loaded = false;
+        Ok(7)
+    }
+
+    fn document(&self, _target: Option<u64>) -> Result<Option<NodeRef<HtmlNodeType>>> {
+        if self.url.is_none() {
+            return Ok(None);
+        }
+        let dom = Parser::new(r#"<html><body><p id="a">hi</p></body></html>"#).parse();
+        Ok(Some(dom.root))
+    }
+
+    fn is_loaded(&self, _target: Option<u64>) -> bool {
+        self.loaded
+    }
+
+    fn capture_screenshot(&mut self, _target: Option<u64>) -> Result<Vec<u8>> {
+        Ok(b"PNG".to_vec())
+    }
+
+    fn evaluate(&mut self, _target: Option<u64>, expression: &str) -> Result<ScriptValue> {
+        match expression {
+            "1 + 1" => Ok(ScriptValue::Number(2.0)),
+            _ => bail!("ReferenceError: {expression} is not defined"),
+        }
+    }
+}
+
+fn call(session: &mut Session, browser: &mut FakeBrowser, message: &str) -> Vec<Json> {
+    session
+        .handle_message(browser, message)
+        .iter()
+        .map(|text| Json::parse(text).unwrap())
+        .collect()
+}
+
+#[test]
+fn json_round_trips_through_text() {
+    let text = r#"{"id":1,"params":{"url":"https://a.test/\"q\"","ok":true,"n":-2.5,"list":[null,3]},"s":"😀"}"#;
+    let json = Json::parse(text).unwrap();
+    assert_eq!(json.get("s").and_then(Json::as_str), Some("😀"));
+    assert_eq!(json.get("id").and_then(Json::as_i64), Some(1));
+    assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+
+    assert!(Json::parse("{\"a\":1,}").is_err());
+    assert!(Json::parse(&"[".repeat(1000)).is_err());
+}
+
+#[test]
+fn websocket_handshake_matches_the_rfc_example() {
+    assert_eq!(
+        websocket::accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+
+    let raw = b"GET /devtools/page/7 HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nSec-WebSocket-Key: abc\r\n\r\nrest";
+    let (request, len) = HttpRequest::parse(raw).unwrap().unwrap();
+    assert_eq!(request.path, "/devtools/page/7");
+    assert_eq!(request.websocket_key(), Some("abc"));
+    assert_eq!(&raw[len..], b"rest");
+    assert!(HttpRequest::parse(b"GET / HTTP/1.1\r\n").unwrap().is_none());
+}
+
+#[test]
+fn masked_frames_are_unmasked() {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    let mut raw = vec![0x81, 0x80 | 5];
+    raw.extend_from_slice(&mask);
+    raw.extend(b"Hello".iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+
+    let (frame, len) = Frame::decode(&raw).unwrap().unwrap();
+    assert_eq!(len, raw.len());
+    assert!(frame.fin);
+    assert_eq!(frame.opcode, websocket::OPCODE_TEXT);
+    assert_eq!(frame.payload, b"Hello");
+    assert!(Frame::decode(&raw[..4]).unwrap().is_none());
+
+    let long = vec![b'x'; 300];
+    let encoded = Frame::encode(websocket::OPCODE_TEXT, &long);
+    assert_eq!(&encoded[..4], &[0x81, 126, 0x01, 0x2c]);
+    assert_eq!(Frame::decode(&encoded).unwrap().unwrap().0.payload, long);
+}
+
+#[test]
+fn navigate_then_get_document() {
+    let mut browser = FakeBrowser::default();
+    let mut session = Session::new(None);
+
+    let replies = call(
+        &mut session,
+        &mut browser,
+        r#"{"id":1,"method":"Page.navigate","params":{"url":"https://example.com/"}}"#,
+    );
+    assert_eq!(replies[0].get("id").and_then(Json::as_i64), Some(1));
+    assert_eq!(
+        browser.url.as_ref().map(Url::as_str),
+        Some("https://example.com/")
+    );
+
+    let replies = call(
+        &mut session,
+        &mut browser,
+        r#"{"id":2,"method":"DOM.getDocument","params":{"depth":-1}}"#,
+    );
+    let root = replies[0]
+        .get("result")
+        .and_then(|r| r.get("root"))
+        .unwrap();
+    assert_eq!(root.get("nodeType").and_then(Json::as_i64), Some(9));
+    assert_eq!(root.get("nodeId").and_then(Json::as_i64), Some(1));
+    let text = replies[0].to_string();
+    assert!(text.contains(r#""nodeName":"P""#), "{text}");
+    assert!(text.contains(r#""attributes":["id","a"]"#), "{text}");
+    assert!(text.contains(r#""nodeValue":"hi""#), "{text}");
+}
+
+#[test]
+fn errors_are_reported_per_message() {
+    let mut browser = FakeBrowser::default();
+    let mut session = Session::new(None);
+
+    let replies = call(&mut session, &mut browser, r#"{"id":3,"method":"Foo.bar"}"#);
+    let error = replies[0].get("error").unwrap();
+    assert_eq!(error.get("code").and_then(Json::as_i64), Some(-32601));
+    assert_eq!(
+        error.get("message").and_then(Json::as_str),
+        Some("'Foo.bar' wasn't found")
+    );
+
+    let replies = call(
+        &mut session,
+        &mut browser,
+        r#"{"id":4,"method":"Runtime.evaluate","params":{"expression":"nope"}}"#,
+    );
+    let result = replies[0].get("result").unwrap();
+    assert!(result.get("exceptionDetails").is_some());
+
+    let replies = call(
+        &mut session,
+        &mut browser,
+        r#"{"id":5,"method":"Runtime.evaluate","params":{"expression":"1 + 1"}}"#,
+    );
+    let value = replies[0]
+        .get("result")
+        .and_then(|r| r.get("result"))
+        .unwrap();
+    assert_eq!(value.get("type").and_then(Json::as_str), Some("number"));
+    assert_eq!(value.get("value").and_then(Json::as_f64), Some(2.0));
+}
+
+#[test]
+fn load_event_fires_once_after_page_enable() {
+    let mut browser = FakeBrowser::default();
+    let mut session = Session::new(None);
+
+    call(
+        &mut session,
+        &mut browser,
+        r#"{"id":1,"method":"Page.enable"}"#,
+    );
+    call(
+        &mut session,
+        &mut browser,
+        r#"{"id":2,"method":"Page.navigate","params":{"url":"https://example.com/"}}"#,
+    );
+    assert!(session.poll_events(&browser).is_empty());
+
+    browser.loaded = true;
+    let events = session.poll_events(&browser);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].contains("Page.loadEventFired"));
+    assert!(session.poll_events(&browser).is_empty());
+}
+
+/// server に WebSocket の接続を頼み、返ってきたステータス行を返す
+fn upgrade(server: &mut CdpServer, browser: &mut FakeBrowser, headers: &str) -> String {
+    let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+    stream
+        .write_all(
+            format!(
+                "GET /devtools/browser HTTP/1.1\r\n{headers}Upgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_millis(10)))
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut response = Vec::new();
+    while !response.windows(2).any(|w| w == b"\r\n") && Instant::now() < deadline {
+        server.poll(browser);
+        let mut buf = [0u8; 1024];
+        if let Ok(n) = stream.read(&mut buf) {
+            response.extend_from_slice(&buf[..n]);
+        }
+    }
+    let response = String::from_utf8_lossy(&response);
+    response.lines().next().unwrap_or_default().to_string()
+}
+
+#[test]
+fn websocket_upgrades_from_pages_need_an_allowed_origin() {
+    let mut browser = FakeBrowser::default();
+    let mut server = CdpServer::bind("127.0.0.1:0").unwrap();
+
+    // 自動化ツールは Origin を送らない
+    let status = upgrade(&mut server, &mut browser, "Host: 127.0.0.1:9222\r\n");
+    assert!(status.contains("101"), "{status}");
+
+    let from_page = "Host: 127.0.0.1:9222\r\nOrigin: https://evil.example\r\n";
+    let status = upgrade(&mut server, &mut browser, from_page);
+    assert!(status.contains("403"), "{status}");
+
+    server.set_allowed_origins(vec!["https://evil.example".to_string()]);
+    let status = upgrade(&mut server, &mut browser, from_page);
+    assert!(status.contains("101"), "{status}");
+}
+
+#[test]
+fn requests_for_other_host_names_are_refused() {
+    let mut browser = FakeBrowser::default();
+    let mut server = CdpServer::bind("127.0.0.1:0").unwrap();
+
+    // DNS リバインディングで 127.0.0.1 を指すようにした名前
+    let status = upgrade(&mut server, &mut browser, "Host: rebind.example:9222\r\n");
+    assert!(status.contains("403"), "{status}");
+
+    for host in ["localhost:9222", "[::1]:9222", "127.0.0.1"] {
+        let status = upgrade(&mut server, &mut browser, &format!("Host: {host}\r\n"));
+        assert!(status.contains("101"), "{host}: {status}");
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::mpsc;
+
+use orinium_browser::browser::{BrowserApp, BrowserCommand, BrowserEvent};
+
+fn browser() -> (BrowserApp, mpsc::Receiver<BrowserEvent>) {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    let (tx, rx) = mpsc::channel();
+    browser.set_event_sink(Box::new(tx));
+    (browser, rx)
+}
+
+#[test]
+fn commands_run_when_processed_and_report_events() {
+    let (mut browser, events) = browser();
+    let commands = browser.command_sender();
+    commands.send(BrowserCommand::Navigate(
+        "https://example.com/".parse().unwrap(),
+    ));
+    // 送っただけでは実行しない
+    assert_eq!(browser.window_title(), "Orinium Browser");
+    assert!(events.try_recv().is_err());
+
+    browser.process_commands();
+    let events: Vec<_> = events.try_iter().collect();
+    assert_eq!(events[0], BrowserEvent::NeedsRedraw);
+    assert_eq!(
+        events[1],
+        BrowserEvent::TitleChanged("https://example.com/ (loading…) - Orinium Browser".to_string())
+    );
+    assert!(matches!(events[2], BrowserEvent::LoadProgress(Some(_))));
+    assert_eq!(events.len(), 3);
+}
+
+#[test]
+fn unchanged_state_is_not_reported_again() {
+    let (mut browser, events) = browser();
+    browser.command_sender().send(BrowserCommand::None);
+    browser.process_commands();
+    browser.process_commands();
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn resize_and_exit_go_through_the_bus() {
+    let (mut browser, events) = browser();
+    let commands = browser.command_sender();
+    // 最小化したときの大きさ 0 は無視する
+    commands.send(BrowserCommand::Resize {
+        width: 0,
+        height: 0,
+    });
+    commands.send(BrowserCommand::Resize {
+        width: 1024,
+        height: 768,
+    });
+    commands.send(BrowserCommand::Exit);
+    browser.process_commands();
+
+    assert_eq!(browser.window_size(), (1024.0, 768.0));
+    assert_eq!(
+        events.try_iter().collect::<Vec<_>>(),
+        vec![BrowserEvent::NeedsRedraw, BrowserEvent::Exit]
+    );
+}
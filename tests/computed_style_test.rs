@@ -0,0 +1,123 @@
+use orinium_browser::browser::core::devtools::StyleInspection;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::css::values::{CssValue, Unit};
+use orinium_browser::engine::html::HtmlNodeType;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter::cascade;
+use orinium_browser::engine::layouter::css_resolver::{CssResolver, StyleOrigin};
+use orinium_browser::engine::tree::NodeRef;
+
+const HTML: &str =
+    r#"<html><body><div class="box"><p id="intro" class="note">Hi</p></div></body></html>"#;
+
+/// tag の最初の要素へのパス（ルートからの子インデックス）
+fn path_to(node: &NodeRef<HtmlNodeType>, tag: &str) -> Option<Vec<usize>> {
+    for (i, child) in node.borrow().children().iter().enumerate() {
+        if matches!(&child.borrow().value, HtmlNodeType::Element { tag_name, .. } if tag_name == tag)
+        {
+            return Some(vec![i]);
+        }
+        if let Some(mut path) = path_to(child, tag) {
+            path.insert(0, i);
+            return Some(path);
+        }
+    }
+    None
+}
+
+fn inspect(ua_css: &str, author_css: &str) -> StyleInspection {
+    let media = MediaContext::default();
+    let ua = CssParser::new(ua_css).parse().unwrap();
+    let author = CssParser::new(author_css).parse().unwrap();
+    let mut styles = CssResolver::resolve_with_origin(&ua, &media, StyleOrigin::UserAgent);
+    styles.extend(CssResolver::resolve_with_media(&author, &media));
+
+    let dom = HtmlParser::new(HTML).parse();
+    let path = path_to(&dom.root, "p").expect("p element");
+    let chain = cascade::element_chain(&dom.root, &path).expect("element chain");
+    StyleInspection::new(&chain, &styles, None).expect("inspection")
+}
+
+#[test]
+fn matched_rules_are_ordered_and_flag_the_losers() {
+    let inspection = inspect(
+        "p { margin: 1em; color: black; }",
+        ".box p { color: red; } #intro { color: blue; } h1 { color: green; }",
+    );
+    assert_eq!(inspection.element, "p#intro.note");
+
+    let colors: Vec<_> = inspection
+        .matched
+        .iter()
+        .filter(|d| d.name == "color")
+        .map(|d| (d.selector.as_str(), d.origin, d.overridden))
+        .collect();
+    assert_eq!(
+        colors,
+        vec![
+            ("#intro", StyleOrigin::Author, false),
+            (".box p", StyleOrigin::Author, true),
+            ("p", StyleOrigin::UserAgent, true),
+        ]
+    );
+
+    assert_eq!(
+        inspection.computed,
+        vec![
+            ("color".to_string(), CssValue::Keyword("blue".to_string())),
+            ("margin".to_string(), CssValue::Length(1.0, Unit::Em)),
+        ]
+    );
+}
+
+#[test]
+fn later_declaration_wins_on_equal_specificity() {
+    let inspection = inspect(
+        "",
+        ".note { --gap: 2px; padding: 1px; } .note { padding: 3px; }",
+    );
+
+    let padding: Vec<_> = inspection
+        .matched
+        .iter()
+        .filter(|d| d.name == "padding")
+        .map(|d| (d.value.to_string(), d.overridden))
+        .collect();
+    assert_eq!(
+        padding,
+        vec![("3px".to_string(), false), ("1px".to_string(), true)]
+    );
+    assert!(inspection.computed.iter().all(|(name, _)| name != "--gap"));
+}
+
+#[test]
+fn values_and_selectors_print_as_css() {
+    let value = CssValue::Function(
+        "rgb".to_string(),
+        vec![
+            CssValue::Number(255.0),
+            CssValue::Number(0.0),
+            CssValue::Number(0.0),
+        ],
+    );
+    assert_eq!(value.to_string(), "rgb(255, 0, 0)");
+    assert_eq!(CssValue::Color("1f1f11".to_string()).to_string(), "#1f1f11");
+    assert_eq!(
+        CssValue::List(vec![
+            CssValue::Length(50.0, Unit::Percent),
+            CssValue::Keyword("auto".to_string()),
+        ])
+        .to_string(),
+        "50% auto"
+    );
+
+    let sheet = CssParser::new("div.a p#b:hover, [lang|=en] { color: red; }")
+        .parse()
+        .unwrap();
+    let selectors: Vec<_> = CssResolver::resolve_with_media(&sheet, &MediaContext::default())
+        .iter()
+        .map(|d| d.selector.to_string())
+        .collect();
+    assert_eq!(selectors, vec!["div.a p#b:hover", "[lang|=\"en\"]"]);
+}
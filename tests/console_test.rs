@@ -0,0 +1,101 @@
+use orinium_browser::browser::Settings;
+use orinium_browser::browser::core::browsing_history::BrowsingHistory;
+use orinium_browser::browser::core::devtools::{Console, MAX_CONSOLE_ENTRIES};
+use orinium_browser::browser::core::internal_pages::{self, InternalPageContext};
+use orinium_browser::engine::diagnostics::{self, Level, Message, Source};
+use orinium_browser::engine::html::parser::Parser;
+
+fn message(level: Level, text: &str) -> Message {
+    Message {
+        level,
+        source: Source::Css,
+        text: text.to_string(),
+        location: None,
+    }
+}
+
+#[test]
+fn capture_collects_reports_and_locations() {
+    let ((), messages) = diagnostics::capture(|| {
+        diagnostics::report(Level::Warning, Source::Css, "outer");
+        let ((), inner) = diagnostics::capture(|| {
+            diagnostics::with_location("app.js", || {
+                diagnostics::report(Level::Info, Source::Script, "inner");
+            });
+        });
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner[0].location.as_deref(), Some("app.js"));
+    });
+    assert_eq!(messages, vec![message(Level::Warning, "outer")]);
+
+    // capture の外で出したものは集めない
+    diagnostics::report(Level::Error, Source::Css, "lost");
+    let ((), messages) = diagnostics::capture(|| {});
+    assert!(messages.is_empty());
+}
+
+#[test]
+fn html_parser_recoveries_are_reported() {
+    let (_, messages) = diagnostics::capture(|| Parser::new("<div></span></div>").parse());
+    assert!(
+        messages
+            .iter()
+            .any(|m| m.source == Source::Html && m.text.contains("</span>"))
+    );
+}
+
+#[test]
+fn console_groups_repeats_and_filters_by_level() {
+    let mut console = Console::new();
+    console.push(message(
+        Level::Warning,
+        "Unknown CSS color keyword: reddish",
+    ));
+    console.push(message(Level::Info, "hello"));
+    console.push(message(
+        Level::Warning,
+        "Unknown CSS color keyword: reddish",
+    ));
+    console.push(message(Level::Error, "Failed to parse CSS"));
+
+    assert_eq!(console.len(), 3);
+    let warnings: Vec<_> = console
+        .entries(Level::Warning)
+        .map(|e| (e.message.text.as_str(), e.count))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            ("Unknown CSS color keyword: reddish", 2),
+            ("Failed to parse CSS", 1),
+        ]
+    );
+
+    for i in 0..MAX_CONSOLE_ENTRIES {
+        console.push(message(Level::Info, &i.to_string()));
+    }
+    assert_eq!(console.len(), MAX_CONSOLE_ENTRIES);
+    assert!(console.entries(Level::Error).next().is_none());
+}
+
+#[test]
+fn console_page_shows_the_filtered_messages() {
+    let mut console = Console::new();
+    console.push(message(Level::Info, "hello"));
+    console.push(message(Level::Error, "<broken>"));
+
+    let history = BrowsingHistory::new();
+    let settings = Settings::default();
+    let ctx = InternalPageContext {
+        history: &history,
+        settings: &settings,
+        inspection: None,
+        console: Some(&console),
+    };
+    let load = |url: &str| internal_pages::load(&url.parse().unwrap(), &ctx).unwrap();
+
+    let all = load("orinium://console");
+    assert!(all.contains("hello") && all.contains("&lt;broken&gt;"));
+    let errors = load("orinium://console?level=error");
+    assert!(!errors.contains("hello") && errors.contains("&lt;broken&gt;"));
+}
@@ -0,0 +1,290 @@
+use std::path::PathBuf;
+
+use orinium_browser::browser::core::content_blocking::{
+    CONTENT_BLOCKING_EXCEPTIONS_FILE_NAME, ContentBlocker,
+};
+use orinium_browser::browser::core::extensions::{
+    Extension, InterceptedRequest, RequestAction, ResourceType,
+};
+use url::Url;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orinium-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+fn blocker(list: &str) -> ContentBlocker {
+    let mut blocker = ContentBlocker::new();
+    blocker.add_filter_list(list);
+    blocker
+}
+
+/// document のページから url を resource として読むとき止めるか
+fn blocks(blocker: &ContentBlocker, document: &str, s: &str, resource: ResourceType) -> bool {
+    let document = url(document);
+    let url = url(s);
+    blocker.intercept_request(&InterceptedRequest {
+        url: &url,
+        document: Some(&document),
+        resource,
+    }) == RequestAction::Block
+}
+
+fn blocks_script(blocker: &ContentBlocker, s: &str) -> bool {
+    blocks(blocker, "https://news.example/", s, ResourceType::Script)
+}
+
+const LIST: &str = "\
+[Adblock Plus 2.0]
+! Title: test list
+||ads.example.net^
+/banner/*/ad.js
+|https://track.example.org/pixel|
+-analytics.
+@@||ads.example.net/allowed/
+example.com##.ad
+/^https?://regex/
+||images.example.net^$image
+||cdn.example.io/tracker.js$unknown-option
+";
+
+#[test]
+fn filter_lists_skip_comments_and_cosmetic_rules() {
+    // ヘッダー、コメント、要素を隠す規則、正規表現、知らないオプションは読まない
+    assert_eq!(blocker(LIST).len(), 6);
+    assert!(ContentBlocker::new().is_empty());
+}
+
+#[test]
+fn image_rules_apply_only_to_images() {
+    let blocker = blocker(LIST);
+    assert!(blocks(
+        &blocker,
+        "https://news.example/",
+        "https://images.example.net/banner.png",
+        ResourceType::Image
+    ));
+    assert!(!blocks_script(&blocker, "https://images.example.net/a.js"));
+}
+
+#[test]
+fn host_anchors_match_the_domain_and_its_subdomains() {
+    let blocker = blocker(LIST);
+    assert!(blocks_script(&blocker, "https://ads.example.net/a.js"));
+    assert!(blocks_script(
+        &blocker,
+        "http://cdn.ads.example.net:8080/a.js"
+    ));
+    assert!(!blocks_script(&blocker, "https://badads.example.net/a.js"));
+    assert!(!blocks_script(&blocker, "https://ads.example.network/a.js"));
+    // 例外の規則
+    assert!(!blocks_script(
+        &blocker,
+        "https://ads.example.net/allowed/a.js"
+    ));
+}
+
+#[test]
+fn wildcards_anchors_and_separators() {
+    let blocker = blocker(LIST);
+    assert!(blocks_script(
+        &blocker,
+        "https://cdn.example/banner/728x90/ad.js"
+    ));
+    assert!(!blocks_script(
+        &blocker,
+        "https://cdn.example/banner/ad.json"
+    ));
+
+    assert!(blocks_script(&blocker, "https://track.example.org/pixel"));
+    assert!(!blocks_script(
+        &blocker,
+        "https://track.example.org/pixel?id=1"
+    ));
+    assert!(!blocks_script(&blocker, "http://track.example.org/pixel"));
+
+    assert!(blocks_script(
+        &blocker,
+        "https://site.example/js/google-analytics.js"
+    ));
+    assert!(!blocks_script(
+        &blocker,
+        "https://site.example/analytics.js"
+    ));
+
+    // ^ は区切り文字か URL の終わり
+    let separators = self::blocker("||example.com/ads^");
+    assert!(blocks_script(&separators, "https://example.com/ads"));
+    assert!(blocks_script(&separators, "https://example.com/ads/1.js"));
+    assert!(blocks_script(&separators, "https://example.com/ads?x=1"));
+    assert!(!blocks_script(&separators, "https://example.com/ads-1.js"));
+}
+
+#[test]
+fn many_wildcards_on_a_long_url_finish_quickly() {
+    // 戻り方が悪いと `*` の数だけ URL の長さの累乗の時間がかかる
+    let blocker = self::blocker("*a*a*a*a*a*a*a^b\n|https://*/*a*a*a*a*a*a^end|");
+    let query = "a".repeat(4000);
+    assert!(!blocks_script(
+        &blocker,
+        &format!("https://cdn.example/?q={query}")
+    ));
+    assert!(blocks_script(
+        &blocker,
+        &format!("https://cdn.example/?q={query}/b")
+    ));
+    assert!(blocks_script(
+        &blocker,
+        &format!("https://cdn.example/{query}&end")
+    ));
+}
+
+#[test]
+fn options_limit_resource_types_parties_and_domains() {
+    let blocker = blocker(
+        "||cdn.example.io/widget.js$script,third-party\n\
+         ||fonts.example.io^$stylesheet\n\
+         ||social.example.io^$domain=news.example|~sports.news.example\n\
+         ||api.example.io^$~xmlhttprequest\n\
+         /Tracker.js$match-case\n",
+    );
+    let script = ResourceType::Script;
+
+    assert!(blocks(
+        &blocker,
+        "https://news.example/",
+        "https://cdn.example.io/widget.js",
+        script
+    ));
+    // 同じサイトからは読んでよい
+    assert!(!blocks(
+        &blocker,
+        "https://www.example.io/",
+        "https://cdn.example.io/widget.js",
+        script
+    ));
+
+    assert!(blocks(
+        &blocker,
+        "https://news.example/",
+        "https://fonts.example.io/a.css",
+        ResourceType::Stylesheet
+    ));
+    assert!(!blocks(
+        &blocker,
+        "https://news.example/",
+        "https://fonts.example.io/a.js",
+        script
+    ));
+
+    assert!(blocks(
+        &blocker,
+        "https://www.news.example/",
+        "https://social.example.io/like.js",
+        script
+    ));
+    assert!(!blocks(
+        &blocker,
+        "https://sports.news.example/",
+        "https://social.example.io/like.js",
+        script
+    ));
+    assert!(!blocks(
+        &blocker,
+        "https://blog.example/",
+        "https://social.example.io/like.js",
+        script
+    ));
+
+    assert!(blocks(
+        &blocker,
+        "https://news.example/",
+        "https://api.example.io/v1.js",
+        script
+    ));
+    assert!(!blocks(
+        &blocker,
+        "https://news.example/",
+        "https://api.example.io/v1",
+        ResourceType::XmlHttpRequest
+    ));
+
+    assert!(blocks(
+        &blocker,
+        "https://news.example/",
+        "https://a.example/Tracker.js",
+        script
+    ));
+    assert!(!blocks(
+        &blocker,
+        "https://news.example/",
+        "https://a.example/tracker.js",
+        script
+    ));
+}
+
+#[test]
+fn documents_opened_in_tabs_are_never_blocked() {
+    let blocker = blocker(LIST);
+    let url = url("https://ads.example.net/");
+    assert!(!blocker.should_block(&InterceptedRequest {
+        url: &url,
+        document: None,
+        resource: ResourceType::Document,
+    }));
+    assert!(blocks(
+        &blocker,
+        "https://news.example/",
+        "https://ads.example.net/frame.html",
+        ResourceType::Subdocument
+    ));
+}
+
+#[test]
+fn blocking_can_be_disabled_per_site_and_saved() {
+    let blocker = blocker(LIST);
+    assert!(blocker.set_enabled_for("news.example", false));
+    assert!(!blocker.set_enabled_for("news.example", false));
+    assert!(!blocker.is_enabled_for("News.Example"));
+    assert!(!blocks_script(&blocker, "https://ads.example.net/a.js"));
+    assert!(blocks(
+        &blocker,
+        "https://blog.example/",
+        "https://ads.example.net/a.js",
+        ResourceType::Script
+    ));
+
+    let dir = temp_dir("content-blocking");
+    let path = dir.join(CONTENT_BLOCKING_EXCEPTIONS_FILE_NAME);
+    blocker.save_exceptions(&path).unwrap();
+    let loaded = ContentBlocker::new();
+    loaded.load_exceptions(&path).unwrap();
+    assert_eq!(loaded.allowed_sites(), vec!["news.example".to_string()]);
+
+    assert!(blocker.set_enabled_for("news.example", true));
+    assert!(blocks_script(&blocker, "https://ads.example.net/a.js"));
+
+    // 設定で切ればどのサイトでも止めない
+    blocker.set_enabled(false);
+    assert!(!blocks_script(&blocker, "https://ads.example.net/a.js"));
+}
+
+#[test]
+fn filter_lists_are_loaded_from_a_directory() {
+    let dir = temp_dir("filter-lists");
+    std::fs::write(dir.join("easylist.txt"), "||ads.example.net^\n").unwrap();
+    std::fs::write(dir.join("easyprivacy.txt"), "-analytics.\n").unwrap();
+    std::fs::write(dir.join("README.md"), "||readme.example^\n").unwrap();
+
+    let mut blocker = ContentBlocker::new();
+    assert_eq!(blocker.load_dir(&dir).unwrap(), 2);
+    assert!(blocks_script(&blocker, "https://ads.example.net/a.js"));
+    assert!(!blocks_script(&blocker, "https://readme.example/a.js"));
+    assert_eq!(blocker.load_dir(&dir.join("missing")).unwrap(), 0);
+}
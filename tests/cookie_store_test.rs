@@ -0,0 +1,186 @@
+use orinium_browser::platform::network::{CookieStore, SameSite};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+fn set(store: &CookieStore, to: &str, headers: &[&str], now: SystemTime) {
+    let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+    store.set_cookies(&url(to), &headers, None, now);
+}
+
+#[test]
+fn attributes_are_parsed() {
+    let store = CookieStore::new();
+    set(
+        &store,
+        "https://www.example.com/account/login",
+        &["id=42; Domain=.Example.com; Path=/; Secure; HttpOnly; SameSite=Strict; Max-Age=60"],
+        at(1000),
+    );
+
+    let cookies = store.cookies(at(1000));
+    assert_eq!(cookies.len(), 1);
+    let cookie = &cookies[0];
+    assert_eq!(cookie.domain, "example.com");
+    assert!(!cookie.host_only && cookie.secure && cookie.http_only);
+    assert_eq!(cookie.same_site, SameSite::Strict);
+    assert_eq!(cookie.expires, Some(at(1060)));
+}
+
+#[test]
+fn domain_and_path_decide_where_cookies_go() {
+    let store = CookieStore::new();
+    let now = at(0);
+    set(
+        &store,
+        "https://www.example.com/docs/intro",
+        &["host=1", "shared=2; Domain=example.com", "root=3; Path=/"],
+        now,
+    );
+
+    let header = |to: &str| store.get_cookie_header(&url(to), None, now);
+    // Path がなければ /docs に置かれ、パスの長いものが先に来る
+    assert_eq!(
+        header("https://www.example.com/docs/a").as_deref(),
+        Some("host=1; shared=2; root=3")
+    );
+    assert_eq!(
+        header("https://www.example.com/").as_deref(),
+        Some("root=3")
+    );
+    assert_eq!(
+        header("https://api.example.com/docs/a").as_deref(),
+        Some("shared=2")
+    );
+    assert_eq!(header("https://example.org/"), None);
+}
+
+#[test]
+fn invalid_cookies_are_rejected() {
+    let store = CookieStore::new();
+    set(
+        &store,
+        "http://www.example.com/",
+        &[
+            "a=1; Domain=other.com",
+            "b=2; Domain=com",
+            "c=3; Secure",
+            "d=4; SameSite=None",
+        ],
+        at(0),
+    );
+    assert!(store.cookies(at(0)).is_empty());
+}
+
+#[test]
+fn cookies_expire() {
+    let store = CookieStore::new();
+    set(
+        &store,
+        "https://example.com/",
+        &[
+            "short=1; Max-Age=10",
+            "dated=2; Expires=Thu, 01 Jan 1970 00:01:40 GMT",
+            "session=3",
+        ],
+        at(0),
+    );
+    let to = url("https://example.com/");
+    assert_eq!(
+        store.get_cookie_header(&to, None, at(5)).as_deref(),
+        Some("short=1; dated=2; session=3")
+    );
+    assert_eq!(
+        store.get_cookie_header(&to, None, at(50)).as_deref(),
+        Some("dated=2; session=3")
+    );
+
+    // Max-Age=0 で消す
+    set(
+        &store,
+        "https://example.com/",
+        &["session=; Max-Age=0"],
+        at(60),
+    );
+    assert_eq!(
+        store.get_cookie_header(&to, None, at(60)).as_deref(),
+        Some("dated=2")
+    );
+}
+
+#[test]
+fn cross_site_subresources_only_get_same_site_none() {
+    let store = CookieStore::new();
+    set(
+        &store,
+        "https://tracker.example/",
+        &["lax=1", "none=2; SameSite=None; Secure"],
+        at(0),
+    );
+    let to = url("https://tracker.example/pixel.png");
+    let page = url("https://news.example.org/article");
+
+    assert_eq!(
+        store.get_cookie_header(&to, Some(&page), at(0)).as_deref(),
+        Some("none=2")
+    );
+    // トップレベルのページとして開けばすべて送る
+    assert_eq!(
+        store.get_cookie_header(&to, None, at(0)).as_deref(),
+        Some("lax=1; none=2")
+    );
+    // 同じサイトの文書からなら送る
+    let same_site = url("https://www.tracker.example/");
+    assert_eq!(
+        store
+            .get_cookie_header(&to, Some(&same_site), at(0))
+            .as_deref(),
+        Some("lax=1; none=2")
+    );
+
+    // 別のサイトの文書から読んだ応答は SameSite=None しか置けない
+    store.set_cookies(
+        &to,
+        &[
+            "blocked=3".to_string(),
+            "ok=4; SameSite=None; Secure".to_string(),
+        ],
+        Some(&page),
+        at(1),
+    );
+    let names: Vec<String> = store.cookies(at(1)).into_iter().map(|c| c.name).collect();
+    assert!(names.contains(&"ok".to_string()));
+    assert!(!names.contains(&"blocked".to_string()));
+}
+
+#[test]
+fn persistent_cookies_round_trip() {
+    let store = CookieStore::new();
+    set(
+        &store,
+        "https://example.com/",
+        &["keep=1; Max-Age=3600; HttpOnly", "session=2"],
+        at(0),
+    );
+    assert!(store.take_modified());
+
+    let restored = CookieStore::new();
+    restored.load(&store.serialize(at(0)), at(10));
+    assert!(!restored.take_modified());
+    let cookies = restored.cookies(at(10));
+    assert_eq!(cookies.len(), 1);
+    assert_eq!(cookies[0].name, "keep");
+    assert!(cookies[0].http_only);
+
+    // 期限が切れたものは読み込まない
+    let expired = CookieStore::new();
+    expired.load(&store.serialize(at(0)), at(3600));
+    assert!(expired.cookies(at(3600)).is_empty());
+}
@@ -0,0 +1,186 @@
+use orinium_browser::browser::core::csp::{ContentSecurityPolicy, Directive};
+use orinium_browser::browser::core::webview::{FetchKind, WebView, WebViewTask};
+use orinium_browser::engine::html::parser::Parser;
+use orinium_browser::engine::script::{self, ScriptSource};
+use url::Url;
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+fn policy(value: &str) -> ContentSecurityPolicy {
+    ContentSecurityPolicy::from_headers(&[(
+        "Content-Security-Policy".to_string(),
+        value.to_string(),
+    )])
+}
+
+#[test]
+fn no_policy_allows_everything() {
+    let csp = ContentSecurityPolicy::new();
+    let document = url("https://example.com/");
+    assert!(csp.is_empty());
+    assert!(csp.allows_url(Directive::Script, &url("https://evil.test/x.js"), &document));
+    assert!(csp.allows_inline(Directive::Style, None));
+}
+
+#[test]
+fn sources_match_self_hosts_and_schemes() {
+    let document = url("https://example.com/page");
+    let csp = policy(
+        "default-src 'self'; script-src 'self' https://cdn.test *.static.test:8443 \
+         https://assets.test/js/; img-src data: https:",
+    );
+
+    let script = |u: &str| csp.allows_url(Directive::Script, &url(u), &document);
+    assert!(script("https://example.com/app.js"));
+    assert!(script("https://cdn.test/lib.js"));
+    assert!(!script("http://cdn.test/lib.js"));
+    assert!(script("https://a.static.test:8443/x.js"));
+    assert!(!script("https://static.test:8443/x.js"));
+    assert!(!script("https://a.static.test/x.js"));
+    assert!(script("https://assets.test/js/app.js"));
+    assert!(!script("https://assets.test/css/app.js"));
+    assert!(!script("https://evil.test/x.js"));
+
+    let image = |u: &str| csp.allows_url(Directive::Image, &url(u), &document);
+    assert!(image("data:image/png;base64,AAAA"));
+    assert!(image("https://any.test/a.png"));
+    assert!(!image("http://any.test/a.png"));
+
+    // style-src がなければ default-src
+    assert!(csp.allows_url(
+        Directive::Style,
+        &url("https://example.com/a.css"),
+        &document
+    ));
+    assert!(!csp.allows_url(Directive::Style, &url("https://cdn.test/a.css"), &document));
+    // frame-src、child-src もなければ default-src
+    assert!(!csp.allows_url(Directive::Frame, &url("https://cdn.test/"), &document));
+}
+
+#[test]
+fn self_matches_local_documents() {
+    let csp = policy("img-src 'self'");
+    let image = |u: &str, document: &str| csp.allows_url(Directive::Image, &url(u), &url(document));
+
+    assert!(image("file:///tmp/a.png", "file:///tmp/page.html"));
+    assert!(!image("https://example.com/a.png", "file:///tmp/page.html"));
+    assert!(image(
+        "HTTPS://Example.com:443/a.png",
+        "https://example.com/"
+    ));
+    assert!(!image(
+        "https://example.com:8443/a.png",
+        "https://example.com/"
+    ));
+    assert!(image(
+        "blob:https://example.com/uuid",
+        "https://example.com/page"
+    ));
+}
+
+#[test]
+fn images_are_requested_only_if_img_src_allows_them() {
+    let html = r#"<html><head>
+<meta http-equiv="Content-Security-Policy" content="img-src 'self' https://cdn.test">
+<style>body { background-image: url(/bg.png) } div { background: url("https://evil.test/x.png") }</style>
+</head><body>
+<img src="a.png"><img src="https://cdn.test/b.png"><img src="https://evil.test/c.png">
+<div>text</div>
+</body></html>"#;
+    let mut webview = WebView::new();
+    webview.tick();
+    webview.on_html_fetched(html.to_string(), url("https://example.com/dir/page.html"));
+
+    let images: Vec<String> = webview
+        .tick()
+        .into_iter()
+        .filter_map(|task| match task {
+            WebViewTask::Fetch {
+                url,
+                kind: FetchKind::Image,
+            } => Some(url.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        images,
+        [
+            "https://example.com/bg.png",
+            "https://example.com/dir/a.png",
+            "https://cdn.test/b.png",
+        ]
+    );
+    // 同じ画像は要求し直さない
+    assert!(
+        !webview
+            .tick()
+            .iter()
+            .any(|task| matches!(task, WebViewTask::Fetch { .. }))
+    );
+}
+
+#[test]
+fn inline_needs_unsafe_inline_or_a_nonce() {
+    let csp = policy("script-src 'self' 'unsafe-inline'; style-src 'nonce-Abc123' 'unsafe-inline'");
+    assert!(csp.allows_inline(Directive::Script, None));
+    // nonce があれば 'unsafe-inline' は効かない
+    assert!(!csp.allows_inline(Directive::Style, None));
+    assert!(csp.allows_inline(Directive::Style, Some("Abc123")));
+    assert!(!csp.allows_inline(Directive::Style, Some("abc123")));
+
+    let document = url("https://example.com/");
+    let strict = policy("script-src 'nonce-r4nd0m'");
+    let elsewhere = url("https://cdn.test/lib.js");
+    assert!(!strict.allows_element(Directive::Script, &elsewhere, None, &document));
+    assert!(strict.allows_element(Directive::Script, &elsewhere, Some("r4nd0m"), &document));
+}
+
+#[test]
+fn every_policy_must_allow() {
+    let document = url("https://example.com/");
+    let mut csp = policy("script-src *");
+    assert!(csp.allows_url(Directive::Script, &url("https://cdn.test/a.js"), &document));
+    // meta 要素の分を足すと厳しくなる
+    csp.add("script-src 'self', img-src 'none'");
+    assert!(!csp.allows_url(Directive::Script, &url("https://cdn.test/a.js"), &document));
+    assert!(csp.allows_url(
+        Directive::Script,
+        &url("https://example.com/a.js"),
+        &document
+    ));
+    assert!(!csp.allows_url(
+        Directive::Image,
+        &url("https://example.com/a.png"),
+        &document
+    ));
+}
+
+#[test]
+fn blocked_scripts_are_not_collected() {
+    let html = r#"<html><head>
+<script>var inline = 1;</script>
+<script nonce="n1">var trusted = 1;</script>
+<script src="https://cdn.test/lib.js"></script>
+<script src="/app.js"></script>
+</head></html>"#;
+    let dom = Parser::new(html).parse();
+    let document = url("https://example.com/");
+    let csp = policy("script-src 'self' 'nonce-n1'");
+
+    let scripts = script::collect_scripts_where(&dom, &document, |node, source| {
+        let nonce = node.get_attr("nonce");
+        match source {
+            ScriptSource::Inline(_) => csp.allows_inline(Directive::Script, nonce),
+            ScriptSource::External(u) => csp.allows_element(Directive::Script, u, nonce, &document),
+        }
+    });
+    assert_eq!(
+        scripts,
+        vec![
+            ScriptSource::Inline("var trusted = 1;".into()),
+            ScriptSource::External(url("https://example.com/app.js")),
+        ]
+    );
+}
@@ -0,0 +1,89 @@
+use orinium_browser::engine::css::matcher::ElementInfo;
+use orinium_browser::engine::css::parser::{
+    AttributeOperator, AttributeSelector, ComplexSelector, CssNodeType, Parser,
+};
+
+/// css の最初のルールのセレクタ
+fn selectors(css: &str) -> Vec<ComplexSelector> {
+    let stylesheet = Parser::new(css).parse().expect("parse");
+    match stylesheet.children()[0].node() {
+        CssNodeType::Rule { selectors } => selectors.clone(),
+        other => panic!("not a rule: {:?}", other),
+    }
+}
+
+fn input(attributes: &[(&str, &str)]) -> ElementInfo {
+    ElementInfo {
+        tag_name: "input".to_string(),
+        id: None,
+        classes: Vec::new(),
+        attributes: attributes
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect(),
+        hovered: false,
+        active: false,
+        focused: false,
+        focus_within: false,
+        visited: false,
+    }
+}
+
+#[test]
+fn parses_attribute_selectors() {
+    let parsed = selectors(r#"input[type="text"][ Disabled ], a[href^=https] {}"#);
+    assert_eq!(parsed.len(), 2);
+
+    let input = &parsed[0].parts[0].selector;
+    assert_eq!(input.tag.as_deref(), Some("input"));
+    assert_eq!(
+        input.attributes,
+        [
+            AttributeSelector {
+                name: "type".to_string(),
+                operator: AttributeOperator::Equals,
+                value: "text".to_string(),
+            },
+            AttributeSelector {
+                name: "disabled".to_string(),
+                operator: AttributeOperator::Exists,
+                value: String::new(),
+            },
+        ]
+    );
+
+    let link = &parsed[1].parts[0].selector;
+    assert_eq!(link.attributes[0].operator, AttributeOperator::Prefix);
+    assert_eq!(link.attributes[0].value, "https");
+}
+
+#[test]
+fn attribute_only_selector_is_not_a_type_selector() {
+    let parsed = selectors("[hidden] {}");
+    let sel = &parsed[0].parts[0].selector;
+    assert_eq!(sel.tag, None);
+    assert_eq!(sel.attributes[0].name, "hidden");
+}
+
+#[test]
+fn matches_attribute_values() {
+    let matches = |css: &str, element: &ElementInfo| selectors(css)[0].matches(&[element.clone()]);
+
+    let text = input(&[("type", "text"), ("lang", "en-US"), ("class", "a b")]);
+    assert!(matches(r#"input[type="text"] {}"#, &text));
+    assert!(!matches(r#"input[type="hidden"] {}"#, &text));
+    assert!(matches("[lang|=en] {}", &text));
+    assert!(!matches("[lang|=e] {}", &text));
+    assert!(matches(r#"[class~="b"] {}"#, &text));
+    assert!(matches("[lang$=US] {}", &text));
+    assert!(matches("[lang*=n-U] {}", &text));
+    assert!(!matches(r#"[lang^=""] {}"#, &text));
+    assert!(!matches("[placeholder] {}", &text));
+    assert!(matches("[placeholder] {}", &input(&[("placeholder", "")])));
+}
+
+#[test]
+fn attribute_selectors_count_like_classes() {
+    let parsed = selectors(r#"input[type="text"] {}"#);
+    assert_eq!(parsed[0].specificity(), (0, 1, 1));
+}
@@ -107,3 +107,24 @@ fn string_line_continuation() {
 
     assert_eq!(tokens, vec![Token::String("ab".into()), Token::EOF,]);
 }
+
+#[test]
+fn unquoted_url_is_one_string() {
+    let tokens = tokenize("url( img/a.png?x=1 ) url(\"b.png\")");
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Function("url".into()),
+            Token::Delim('('),
+            Token::String("img/a.png?x=1".into()),
+            Token::Delim(')'),
+            Token::Whitespace,
+            Token::Function("url".into()),
+            Token::Delim('('),
+            Token::String("b.png".into()),
+            Token::Delim(')'),
+            Token::EOF,
+        ]
+    );
+}
@@ -0,0 +1,37 @@
+use orinium_browser::browser::core::resource_loader::{BrowserResourceLoader, DataURI};
+use url::Url;
+
+#[test]
+fn base64_and_percent_encoded_data_are_decoded() {
+    let (mime, body) = DataURI::load("data:text/html;base64,PGgxPkhpPC9oMT4=").unwrap();
+    assert_eq!(mime, "text/html");
+    assert_eq!(body, b"<h1>Hi</h1>");
+
+    let (mime, body) = DataURI::load("data:,Hello%2C%20World!#top").unwrap();
+    assert_eq!(mime, "text/plain;charset=US-ASCII");
+    assert_eq!(body, b"Hello, World!");
+
+    // パディングと空白は省略できる
+    let (mime, body) = DataURI::load("data:;charset=utf-8;base64,SG k").unwrap();
+    assert_eq!(mime, "text/plain;charset=utf-8");
+    assert_eq!(body, b"Hi");
+}
+
+#[test]
+fn malformed_data_urls_are_rejected() {
+    assert!(DataURI::load("data:text/plain").is_err());
+    assert!(DataURI::load("data:text/plain;base64,S").is_err());
+    assert!(DataURI::load("data:image/png;base64,****").is_err());
+}
+
+#[test]
+fn loader_answers_data_urls_without_the_network() {
+    let loader = BrowserResourceLoader::new(None);
+    let url = Url::parse("data:text/css,p%20%7B%20color:%20red%20%7D").unwrap();
+    let response = loader.fetch_blocking(url).unwrap();
+    assert_eq!(response.body, b"p { color: red }");
+    assert_eq!(
+        response.headers,
+        vec![("content-type".to_string(), "text/css".to_string())]
+    );
+}
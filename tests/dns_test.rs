@@ -0,0 +1,98 @@
+use orinium_browser::platform::network::dns::{self, DnsCache, MAX_TTL};
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::time::{Duration, Instant};
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn address_families_are_interleaved() {
+    let sorted = dns::interleave(vec![
+        addr("[2001:db8::1]:443"),
+        addr("[2001:db8::2]:443"),
+        addr("[2001:db8::3]:443"),
+        addr("192.0.2.1:443"),
+    ]);
+    assert_eq!(
+        sorted,
+        vec![
+            addr("[2001:db8::1]:443"),
+            addr("192.0.2.1:443"),
+            addr("[2001:db8::2]:443"),
+            addr("[2001:db8::3]:443"),
+        ]
+    );
+
+    // 最初のアドレスの種類から始める
+    let sorted = dns::interleave(vec![addr("192.0.2.1:80"), addr("[2001:db8::1]:80")]);
+    assert_eq!(sorted[0], addr("192.0.2.1:80"));
+}
+
+#[test]
+fn cache_respects_ttl_and_cap() {
+    let cache = DnsCache::new();
+    let ip: IpAddr = "192.0.2.1".parse().unwrap();
+    let now = Instant::now();
+
+    cache.insert("Example.com", vec![ip], now + Duration::from_secs(30), now);
+    assert_eq!(cache.get("example.com", now), Some(vec![ip]));
+    assert_eq!(
+        cache.get("example.com", now + Duration::from_secs(30)),
+        None
+    );
+
+    // TTL が長すぎても MAX_TTL で切れる
+    cache.insert("example.org", vec![ip], now + MAX_TTL * 10, now);
+    assert!(cache.get("example.org", now + MAX_TTL).is_none());
+
+    cache.remove("EXAMPLE.ORG");
+    assert!(cache.get("example.org", now).is_none());
+}
+
+#[test]
+fn refused_address_falls_through_to_the_next() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let open = listener.local_addr().unwrap();
+    // 使っていないポート
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let stream = runtime()
+        .block_on(dns::happy_eyeballs(
+            vec![closed, open],
+            Duration::from_secs(10),
+        ))
+        .unwrap();
+    assert_eq!(stream.peer_addr().unwrap(), open);
+}
+
+#[test]
+fn all_failures_are_reported() {
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let result = runtime().block_on(dns::happy_eyeballs(
+        vec![closed],
+        dns::CONNECTION_ATTEMPT_DELAY,
+    ));
+    assert!(result.is_err());
+    assert!(
+        runtime()
+            .block_on(dns::happy_eyeballs(
+                Vec::new(),
+                dns::CONNECTION_ATTEMPT_DELAY
+            ))
+            .is_err()
+    );
+}
@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use orinium_browser::engine::events::{self, Event, EventListeners, EventPhase, EventType};
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+fn record(log: &Log, name: &'static str) -> impl FnMut(&mut Event) + 'static {
+    let log = log.clone();
+    move |event: &mut Event| {
+        log.borrow_mut().push(format!("{name}:{:?}", event.phase));
+    }
+}
+
+#[test]
+fn events_are_captured_then_bubbled() {
+    let log: Log = Rc::default();
+    let mut listeners = EventListeners::new();
+    listeners.add(vec![], EventType::Click, true, record(&log, "root"));
+    listeners.add(vec![], EventType::Click, false, record(&log, "root"));
+    listeners.add(vec![0], EventType::Click, false, record(&log, "parent"));
+    listeners.add(vec![0, 1], EventType::Click, false, record(&log, "target"));
+    listeners.add(vec![0, 2], EventType::Click, false, record(&log, "sibling"));
+
+    let mut event = Event::new(EventType::Click, vec![0, 1]);
+    assert!(events::dispatch(&mut listeners, &mut event));
+    assert_eq!(
+        *log.borrow(),
+        vec![
+            "root:Capturing",
+            "target:AtTarget",
+            "parent:Bubbling",
+            "root:Bubbling",
+        ]
+    );
+    assert_eq!(event.target, vec![0, 1]);
+}
+
+#[test]
+fn stop_propagation_and_prevent_default() {
+    let log: Log = Rc::default();
+    let mut listeners = EventListeners::new();
+    listeners.add(vec![], EventType::Click, false, record(&log, "root"));
+    listeners.add(vec![0], EventType::Click, false, |event: &mut Event| {
+        assert_eq!(event.phase, EventPhase::Bubbling);
+        event.prevent_default();
+        event.stop_propagation();
+    });
+
+    let mut event = Event::new(EventType::Click, vec![0, 0]);
+    assert!(!events::dispatch(&mut listeners, &mut event));
+    assert!(event.default_prevented());
+    assert!(log.borrow().is_empty());
+}
+
+#[test]
+fn scroll_does_not_bubble_and_cannot_be_cancelled() {
+    let log: Log = Rc::default();
+    let mut listeners = EventListeners::new();
+    listeners.add(vec![], EventType::Scroll, false, record(&log, "root"));
+    listeners.add(vec![3], EventType::Scroll, false, |event: &mut Event| {
+        event.prevent_default();
+    });
+
+    let mut event = Event::new(EventType::Scroll, vec![3]);
+    assert!(events::dispatch(&mut listeners, &mut event));
+    assert!(log.borrow().is_empty());
+}
+
+#[test]
+fn key_down_carries_the_key() {
+    let mut listeners = EventListeners::new();
+    listeners.add(vec![], EventType::KeyDown, true, |event: &mut Event| {
+        if event.key.as_deref() == Some("Enter") {
+            event.prevent_default();
+        }
+    });
+
+    assert!(!events::dispatch(
+        &mut listeners,
+        &mut Event::key_down(vec![1], "Enter")
+    ));
+    assert!(events::dispatch(
+        &mut listeners,
+        &mut Event::key_down(vec![1], "a")
+    ));
+}
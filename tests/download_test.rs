@@ -0,0 +1,251 @@
+use orinium_browser::browser::core::downloads::{DownloadManager, DownloadState};
+use orinium_browser::platform::network::download::{self, PartialDownload, Resume};
+use orinium_browser::platform::network::{NetworkCore, StatusCode, StoragePartition};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orinium-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 途中まで受信したことにする
+fn write_partial(path: &Path, url: &str, body: &[u8], etag: &str) {
+    let mut part = download::open_part(path, url, Resume::Restart, &headers(&[("ETag", etag)]))
+        .unwrap()
+        .unwrap();
+    part.write_all(body).unwrap();
+}
+
+#[test]
+fn partial_download_asks_for_the_rest() {
+    let dir = temp_dir("download-partial");
+    let path = dir.join("file.bin");
+    let url = "http://example.com/file.bin";
+
+    // 何もなければ最初から
+    let fresh = PartialDownload::load(&path, url);
+    assert_eq!(fresh, PartialDownload::default());
+    assert_eq!(
+        fresh.request_headers(),
+        vec![("Accept-Encoding", "identity".to_string())]
+    );
+
+    write_partial(&path, url, b"hello", "\"v1\"");
+    let partial = PartialDownload::load(&path, url);
+    assert_eq!(partial.received, 5);
+    assert_eq!(partial.validator.as_deref(), Some("\"v1\""));
+    assert!(
+        partial
+            .request_headers()
+            .contains(&("Range", "bytes=5-".to_string()))
+    );
+    assert!(
+        partial
+            .request_headers()
+            .contains(&("If-Range", "\"v1\"".to_string()))
+    );
+
+    // 別の URL の続きは使わない
+    assert_eq!(
+        PartialDownload::load(&path, "http://example.com/other"),
+        PartialDownload::default()
+    );
+
+    download::discard(&path);
+    assert!(!download::part_path(&path).exists());
+}
+
+#[test]
+fn resume_mode_checks_the_range_and_etag() {
+    let partial = PartialDownload {
+        received: 100,
+        validator: Some("\"v1\"".to_string()),
+    };
+
+    assert_eq!(
+        partial.resume_mode(StatusCode::OK, &[]),
+        Some(Resume::Restart)
+    );
+    assert_eq!(
+        partial.resume_mode(
+            StatusCode::PARTIAL_CONTENT,
+            &headers(&[("Content-Range", "bytes 100-199/200"), ("ETag", "\"v1\"")]),
+        ),
+        Some(Resume::Append)
+    );
+    // 頼んだのと違う位置から
+    assert_eq!(
+        partial.resume_mode(
+            StatusCode::PARTIAL_CONTENT,
+            &headers(&[("Content-Range", "bytes 0-199/200")]),
+        ),
+        None
+    );
+    // ファイルが変わった
+    assert_eq!(
+        partial.resume_mode(
+            StatusCode::PARTIAL_CONTENT,
+            &headers(&[("Content-Range", "bytes 100-199/200"), ("ETag", "\"v2\"")]),
+        ),
+        None
+    );
+    assert_eq!(
+        partial.resume_mode(
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            &headers(&[("Content-Range", "bytes */100")]),
+        ),
+        Some(Resume::Complete)
+    );
+    assert_eq!(partial.resume_mode(StatusCode::NOT_FOUND, &[]), None);
+}
+
+#[test]
+fn validator_prefers_a_strong_etag() {
+    assert_eq!(
+        download::validator(&headers(&[
+            ("ETag", "\"abc\""),
+            ("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+        ])),
+        Some("\"abc\"".to_string())
+    );
+    assert_eq!(
+        download::validator(&headers(&[
+            ("ETag", "W/\"abc\""),
+            ("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+        ])),
+        Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string())
+    );
+    assert_eq!(
+        download::validator(&headers(&[
+            ("ETag", "\"abc\""),
+            ("Content-Encoding", "gzip")
+        ])),
+        None
+    );
+}
+
+/// 1 回目は Content-Length の途中で切り、2 回目は Range の続きを 206 で返すサーバー
+fn flaky_server(body: &'static [u8]) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    std::thread::spawn(move || {
+        for attempt in 0..2 {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim_end().is_empty() {
+                    break;
+                }
+                head.push_str(&line.to_ascii_lowercase());
+            }
+            let start = head
+                .lines()
+                .find_map(|l| l.strip_prefix("range: bytes="))
+                .and_then(|r| r.trim().trim_end_matches('-').parse::<usize>().ok());
+            seen.lock().unwrap().push(head);
+
+            let mut stream = stream;
+            match (attempt, start) {
+                (0, _) => {
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Length: {}\r\n\r\n",
+                        body.len()
+                    )
+                    .unwrap();
+                    stream.write_all(&body[..4]).unwrap();
+                }
+                (_, Some(start)) => {
+                    write!(
+                        stream,
+                        "HTTP/1.1 206 Partial Content\r\nETag: \"v1\"\r\n\
+                         Content-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\n\r\n",
+                        start,
+                        body.len() - 1,
+                        body.len(),
+                        body.len() - start
+                    )
+                    .unwrap();
+                    stream.write_all(&body[start..]).unwrap();
+                }
+                (_, None) => {
+                    write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                        body.len()
+                    )
+                    .unwrap();
+                    stream.write_all(body).unwrap();
+                }
+            }
+            // 1 回目は接続を切って途切れさせる
+        }
+    });
+    (format!("http://127.0.0.1:{port}/file.bin"), requests)
+}
+
+#[test]
+fn interrupted_download_resumes_with_range() {
+    let dir = temp_dir("download-resume");
+    let path = dir.join("file.bin");
+    let (url, requests) = flaky_server(b"0123456789");
+
+    let network = NetworkCore::new();
+    network.download(url, path.clone(), 1, StoragePartition::Default);
+    let response = loop {
+        if let Some(msg) = network.try_receive().into_iter().next() {
+            break msg.response.unwrap();
+        }
+        std::thread::yield_now();
+    };
+
+    assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(std::fs::read(&path).unwrap(), b"0123456789");
+    assert!(!download::part_path(&path).exists());
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[1].contains("range: bytes=4-"));
+    assert!(requests[1].contains("if-range: \"v1\""));
+}
+
+#[test]
+fn unfinished_downloads_are_saved() {
+    let mut downloads = DownloadManager::new();
+    let url = Url::parse("https://example.com/big.iso").unwrap();
+    downloads.start(1, url.clone(), PathBuf::from("/tmp/big.iso"));
+    downloads.start(
+        2,
+        Url::parse("https://example.com/small.txt").unwrap(),
+        PathBuf::from("/tmp/small.txt"),
+    );
+    downloads.on_progress(1, 50, Some(100));
+    downloads.on_finished(2, Ok(()));
+    assert!(downloads.take_modified());
+
+    assert_eq!(downloads.get(1).unwrap().received, 50);
+    assert_eq!(downloads.get(2).unwrap().state, DownloadState::Completed);
+
+    // 終わったものは残さない
+    let unfinished = DownloadManager::parse_unfinished(&downloads.serialize());
+    assert_eq!(unfinished, vec![(url, PathBuf::from("/tmp/big.iso"))]);
+    assert!(DownloadManager::parse_unfinished("download\tnot a url\t/tmp/x\n").is_empty());
+}
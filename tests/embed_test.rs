@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use orinium_browser::embed::{OriniumView, ViewEvent};
+use orinium_browser::engine::renderer_model::DrawCommand;
+use orinium_browser::platform::renderer::compositor::Compositor;
+use winit::dpi::PhysicalSize;
+
+/// 出したフレームの描画命令を取っておく
+#[derive(Default)]
+struct Recorder {
+    frames: Rc<RefCell<Vec<Vec<DrawCommand>>>>,
+    commands: Vec<DrawCommand>,
+}
+
+impl Compositor for Recorder {
+    fn resize(&mut self, _size: PhysicalSize<u32>) {}
+    fn set_scale_factor(&mut self, _scale_factor: f64) {}
+    fn set_debug_overlay(&mut self, _enabled: bool) {}
+
+    fn parse_draw_commands(&mut self, commands: &[DrawCommand]) {
+        self.commands = commands.to_vec();
+    }
+
+    fn render(&mut self) -> Result<()> {
+        self.frames.borrow_mut().push(self.commands.clone());
+        Ok(())
+    }
+
+    fn capture_frame(&mut self) -> Result<image::RgbaImage> {
+        anyhow::bail!("nothing to capture")
+    }
+}
+
+#[test]
+fn view_loads_and_draws_the_page_without_browser_ui() {
+    let recorder = Recorder::default();
+    let frames = recorder.frames.clone();
+    let mut view = OriniumView::with_compositor((640, 480), 1.0, Box::new(recorder));
+    view.navigate(
+        "data:text/html,<title>Embedded</title><p>hello</p>"
+            .parse()
+            .unwrap(),
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut events = Vec::new();
+    while view.title() != "Embedded" && Instant::now() < deadline {
+        events.extend(view.update());
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(view.title(), "Embedded");
+    assert_eq!(view.url().unwrap().scheme(), "data");
+    assert!(events.contains(&ViewEvent::NeedsRedraw));
+    assert!(events.contains(&ViewEvent::TitleChanged("Embedded".to_string())));
+
+    view.render();
+    let frames = frames.borrow();
+    let frame = frames.last().unwrap();
+    // タブバーも URL バーもなく、ページのキャンバスが左上から全体を覆う
+    assert!(matches!(
+        frame.first(),
+        Some(DrawCommand::DrawRect {
+            x: 0.0,
+            y: 0.0,
+            width: 640.0,
+            height: 480.0,
+            ..
+        })
+    ));
+}
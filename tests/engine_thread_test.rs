@@ -0,0 +1,50 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use orinium_browser::browser::core::engine_thread::{EngineMessage, EngineThread};
+use orinium_browser::browser::{BrowserApp, BrowserEvent};
+use winit::event::WindowEvent;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Exit とフレームが両方届くまでイベントを待つ（届く順は決まっていない）
+fn wait_for_exit_and_frame(events: &mpsc::Receiver<BrowserEvent>) {
+    let (mut exit, mut frame) = (false, false);
+    while !(exit && frame) {
+        match events
+            .recv_timeout(TIMEOUT)
+            .expect("engine stopped sending events")
+        {
+            BrowserEvent::Exit => exit = true,
+            BrowserEvent::Frame(_) => frame = true,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn engine_thread_sends_frames_and_exits_on_close() {
+    let (tx, events) = mpsc::channel();
+    let (engine, ready) = EngineThread::spawn(
+        || Ok(BrowserApp::new((640, 480), "Orinium Browser".to_string())),
+        Box::new(tx),
+    )
+    .unwrap();
+    assert_eq!(ready.window_size, (640, 480));
+    assert_eq!(ready.window_title, "Orinium Browser");
+
+    engine.send(EngineMessage::Window(WindowEvent::CloseRequested));
+    // 最初のフレームはウィンドウを開く前から描いておく
+    wait_for_exit_and_frame(&events);
+    drop(engine);
+}
+
+#[test]
+fn failing_build_is_reported_by_spawn() {
+    let (tx, _events) = mpsc::channel();
+    let result = EngineThread::spawn(|| anyhow::bail!("no profile"), Box::new(tx));
+    let Err(e) = result else {
+        panic!("spawn should fail");
+    };
+    assert_eq!(e.to_string(), "no profile");
+}
@@ -0,0 +1,29 @@
+use orinium_browser::browser::core::internal_pages::error_page;
+use orinium_browser::browser::core::resource_loader::BrowserNetworkError;
+use orinium_browser::platform::network::{NetworkError, StatusCode};
+
+#[test]
+fn error_page_describes_the_failure_and_links_to_retry() {
+    let url = "https://example.com/?a=1&b=<2>".parse().unwrap();
+    let page = error_page(
+        &url,
+        &BrowserNetworkError::NetworkError(NetworkError::TlsFailed),
+    );
+
+    assert!(page.contains("<title>Secure connection failed</title>"));
+    assert!(page.contains("TLS handshake failed"));
+    // URL はエスケープしてから埋め込む
+    assert!(page.contains("href=\"https://example.com/?a=1&amp;b=%3C2%3E\""));
+    assert!(page.contains(">Retry</a>"));
+}
+
+#[test]
+fn http_errors_show_the_status() {
+    let url = "https://example.com/missing".parse().unwrap();
+    let page = error_page(
+        &url,
+        &BrowserNetworkError::HttpStatus(StatusCode::NOT_FOUND),
+    );
+
+    assert!(page.contains("HTTP 404 Not Found"));
+}
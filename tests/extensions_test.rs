@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+use orinium_browser::browser::core::extensions::{
+    ContentScript, Extension, ExtensionRegistry, InterceptedRequest, MANIFEST_FILE_NAME,
+    ManifestExtension, MatchPattern, RequestAction, ResourceType, ToolbarAction,
+};
+use url::Url;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orinium-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+/// url へのスクリプトの読み込みを拡張機能に見せる
+fn intercept(registry: &ExtensionRegistry, s: &str) -> RequestAction {
+    let url = url(s);
+    registry.intercept_request(&InterceptedRequest {
+        url: &url,
+        document: None,
+        resource: ResourceType::Script,
+    })
+}
+
+fn matches(pattern: &str, s: &str) -> bool {
+    MatchPattern::parse(pattern).unwrap().matches(&url(s))
+}
+
+#[test]
+fn match_patterns_follow_web_extensions() {
+    assert!(matches("https://example.com/*", "https://example.com/"));
+    assert!(matches(
+        "https://example.com/*",
+        "https://example.com/a?b=c"
+    ));
+    assert!(!matches("https://example.com/*", "http://example.com/"));
+    assert!(!matches(
+        "https://example.com/*",
+        "https://www.example.com/"
+    ));
+
+    // * のスキームは http と https だけ
+    assert!(matches("*://example.com/*", "http://example.com/"));
+    assert!(!matches("*://example.com/*", "file:///example.com/"));
+
+    assert!(matches("https://*.example.com/*", "https://example.com/"));
+    assert!(matches(
+        "https://*.example.com/*",
+        "https://a.b.example.com/"
+    ));
+    assert!(!matches(
+        "https://*.example.com/*",
+        "https://badexample.com/"
+    ));
+
+    assert!(matches("*://*/*.js", "https://cdn.example.net/lib/app.js"));
+    assert!(!matches("*://*/*.js", "https://cdn.example.net/app.json"));
+    assert!(matches(
+        "https://example.com/a/*/c",
+        "https://example.com/a/b/c"
+    ));
+    assert!(!matches("https://example.com/a", "https://example.com/a/b"));
+
+    assert!(matches("<all_urls>", "file:///home/user/a.html"));
+    assert!(!matches("<all_urls>", "orinium://settings"));
+
+    for invalid in [
+        "example.com/*",
+        "ftp://example.com/*",
+        "https://example.com",
+        "https://a*.example.com/*",
+    ] {
+        assert!(MatchPattern::parse(invalid).is_err(), "{}", invalid);
+    }
+}
+
+fn read_fixture(file: &str) -> Result<String> {
+    match file {
+        "content.css" => Ok("body { color: red }".to_string()),
+        "content.js" => Ok("document.title = 'x';".to_string()),
+        "action.js" => Ok("console.log('clicked');".to_string()),
+        _ => bail!("no such file: {}", file),
+    }
+}
+
+const MANIFEST: &str = r#"
+name = "Quiet reading"
+version = "1.0"
+
+[[content]]
+matches = "https://*.example.com/* https://example.org/*"
+css = "content.css"
+js = "content.js"
+
+[[block]]
+matches = "*://ads.example.net/*"
+
+[action]
+title = "Toggle quiet reading"
+js = "action.js"
+"#;
+
+#[test]
+fn manifests_register_content_blocking_and_a_button() {
+    let extension = ManifestExtension::parse(MANIFEST, read_fixture).unwrap();
+    assert_eq!(extension.name(), "Quiet reading");
+
+    let mut registry = ExtensionRegistry::new();
+    registry.register(Box::new(extension));
+
+    let content = registry.content_for(&url("https://news.example.com/today"));
+    assert_eq!(content.css, vec!["body { color: red }".to_string()]);
+    assert_eq!(
+        content.scripts,
+        vec![(
+            "Quiet reading".to_string(),
+            "document.title = 'x';".to_string()
+        )]
+    );
+    assert!(
+        registry
+            .content_for(&url("https://example.net/"))
+            .css
+            .is_empty()
+    );
+
+    assert_eq!(
+        intercept(&registry, "https://ads.example.net/banner.png"),
+        RequestAction::Block
+    );
+    assert_eq!(
+        intercept(&registry, "https://example.net/"),
+        RequestAction::Continue
+    );
+
+    // label がなければ名前の最初の文字
+    assert_eq!(
+        registry.toolbar_actions(),
+        vec![&ToolbarAction {
+            label: "Q".to_string(),
+            title: "Toggle quiet reading".to_string(),
+            script: Some("console.log('clicked');".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn invalid_manifests_are_rejected() {
+    assert!(ManifestExtension::parse("version = \"1.0\"", read_fixture).is_err());
+    assert!(
+        ManifestExtension::parse(
+            "name = \"A\"\n[[content]]\njs = \"missing.js\"",
+            read_fixture
+        )
+        .is_err()
+    );
+    assert!(
+        ManifestExtension::parse(
+            "name = \"A\"\n[[content]]\nmatches = \"example.com\"",
+            read_fixture
+        )
+        .is_err()
+    );
+    assert!(ManifestExtension::parse("name = \"A\"\n[unknown]", read_fixture).is_err());
+}
+
+struct Redirector;
+
+impl Extension for Redirector {
+    fn name(&self) -> &str {
+        "redirector"
+    }
+
+    fn intercept_request(&self, request: &InterceptedRequest) -> RequestAction {
+        if request.url.scheme() == "http" {
+            let mut https = request.url.clone();
+            https.set_scheme("https").unwrap();
+            RequestAction::Redirect(https)
+        } else {
+            RequestAction::Continue
+        }
+    }
+}
+
+#[test]
+fn rust_extensions_can_redirect_requests() {
+    let mut registry = ExtensionRegistry::new();
+    registry.register(Box::new(Redirector));
+    assert_eq!(
+        intercept(&registry, "http://example.com/a"),
+        RequestAction::Redirect(url("https://example.com/a"))
+    );
+    assert_eq!(
+        intercept(&registry, "https://example.com/a"),
+        RequestAction::Continue
+    );
+    assert!(registry.toolbar_actions().is_empty());
+    assert_eq!(
+        registry.content_for(&url("https://example.com/")),
+        Default::default()
+    );
+}
+
+#[test]
+fn extensions_are_loaded_from_a_directory() {
+    let dir = temp_dir("extensions");
+    let quiet = dir.join("quiet");
+    std::fs::create_dir_all(&quiet).unwrap();
+    std::fs::write(quiet.join(MANIFEST_FILE_NAME), MANIFEST).unwrap();
+    for file in ["content.css", "content.js", "action.js"] {
+        std::fs::write(quiet.join(file), read_fixture(file).unwrap()).unwrap();
+    }
+    // 読めないものは飛ばす
+    let broken = dir.join("broken");
+    std::fs::create_dir_all(&broken).unwrap();
+    std::fs::write(
+        broken.join(MANIFEST_FILE_NAME),
+        "name = \"Broken\"\n[[content]]\njs = \"../quiet/content.js\"\n",
+    )
+    .unwrap();
+
+    let mut registry = ExtensionRegistry::new();
+    assert_eq!(registry.load_dir(&dir).unwrap(), 1);
+    assert_eq!(registry.names(), vec!["Quiet reading"]);
+    assert_eq!(
+        registry.load_dir(&dir.join("missing")).unwrap(),
+        0,
+        "a missing directory has no extensions"
+    );
+}
+
+#[test]
+fn content_scripts_need_a_matching_pattern() {
+    let script = ContentScript {
+        matches: vec![MatchPattern::parse("https://example.com/docs/*").unwrap()],
+        css: None,
+        js: None,
+    };
+    assert!(script.matches(&url("https://example.com/docs/intro")));
+    assert!(!script.matches(&url("https://example.com/blog/")));
+}
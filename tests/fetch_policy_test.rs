@@ -0,0 +1,254 @@
+use orinium_browser::browser::core::fetch_policy::{
+    self, Initiator, PolicyError, RequestMode, check_request, check_response,
+};
+use orinium_browser::browser::core::origin::Origin;
+use orinium_browser::platform::network::NetworkCore;
+use orinium_browser::platform::network::StoragePartition;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use url::Url;
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+#[test]
+fn referer_is_trimmed_across_origins_and_dropped_on_downgrade() {
+    let page = url("https://user:pw@example.com/dir/page.html?q=1#top");
+    let referer = |to: &str| fetch_policy::referrer_header(&page, &url(to));
+
+    assert_eq!(
+        referer("https://example.com/next").as_deref(),
+        Some("https://example.com/dir/page.html?q=1")
+    );
+    assert_eq!(
+        referer("https://other.test/").as_deref(),
+        Some("https://example.com/")
+    );
+    assert_eq!(referer("http://example.com/"), None);
+    assert_eq!(referer("file:///tmp/a.html"), None);
+    assert_eq!(
+        fetch_policy::referrer_header(&url("about:blank"), &url("https://example.com/")),
+        None
+    );
+}
+
+fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[test]
+fn origins_compare_scheme_host_and_port() {
+    let origin = Origin::of(&url("https://Example.com/a?b"));
+    assert_eq!(origin, Origin::of(&url("https://example.com:443/c")));
+    assert_ne!(origin, Origin::of(&url("http://example.com/")));
+    assert_ne!(origin, Origin::of(&url("https://example.com:8443/")));
+    assert_ne!(origin, Origin::of(&url("https://sub.example.com/")));
+    assert_eq!(origin.ascii_serialization(), "https://example.com");
+    assert_eq!(
+        Origin::of(&url("http://localhost:8080/")).to_string(),
+        "http://localhost:8080"
+    );
+    assert_eq!(
+        Origin::of(&url("blob:https://example.com/uuid")),
+        Origin::of(&url("https://example.com/"))
+    );
+
+    // opaque なオリジンは自分とだけ一致する
+    let file = Origin::of(&url("file:///tmp/a.html"));
+    assert!(file.is_opaque());
+    assert_eq!(file, file.clone());
+    assert_ne!(file, Origin::of(&url("file:///tmp/a.html")));
+    assert_eq!(file.ascii_serialization(), "null");
+}
+
+#[test]
+fn cross_origin_reads_are_blocked_by_default() {
+    let document = Initiator::new(url("https://example.com/page"));
+
+    // 移動とサブリソースはどこへでも
+    for mode in [RequestMode::Navigate, RequestMode::NoCors] {
+        assert!(
+            check_request(Some(&document), &url("https://cdn.test/a.css"), "GET", mode).is_ok()
+        );
+    }
+    // スクリプトの要求
+    let cors = RequestMode::Cors;
+    assert!(
+        check_request(
+            Some(&document),
+            &url("https://example.com/api"),
+            "POST",
+            cors
+        )
+        .is_ok()
+    );
+    assert!(check_request(Some(&document), &url("https://api.test/data"), "GET", cors).is_ok());
+    assert!(matches!(
+        check_request(Some(&document), &url("https://api.test/data"), "PUT", cors),
+        Err(PolicyError::CrossOrigin { .. })
+    ));
+    assert!(check_request(Some(&document), &url("data:text/plain,hi"), "GET", cors).is_ok());
+
+    // ウェブのページからローカルのファイルは読めない
+    let file = url("file:///etc/passwd");
+    assert_eq!(
+        check_request(Some(&document), &file, "GET", RequestMode::NoCors),
+        Err(PolicyError::LocalResource(file.clone()))
+    );
+    assert!(
+        check_request(
+            Some(&Initiator::new(url("file:///tmp/a.html"))),
+            &file,
+            "GET",
+            RequestMode::NoCors
+        )
+        .is_ok()
+    );
+    // URL バーからは何でも開ける
+    assert!(check_request(None, &file, "GET", RequestMode::Navigate).is_ok());
+}
+
+#[test]
+fn cors_headers_allow_cross_origin_responses() {
+    let document = Initiator::new(url("https://example.com/page"));
+    let api = url("https://api.test/data");
+
+    assert!(check_response(&document, &url("https://example.com/x"), &[], false).is_ok());
+    assert!(matches!(
+        check_response(&document, &api, &[], false),
+        Err(PolicyError::CorsRejected { .. })
+    ));
+    assert!(
+        check_response(
+            &document,
+            &api,
+            &headers(&[("Access-Control-Allow-Origin", "*")]),
+            false
+        )
+        .is_ok()
+    );
+    assert!(
+        check_response(
+            &document,
+            &api,
+            &headers(&[("access-control-allow-origin", "https://example.com")]),
+            false
+        )
+        .is_ok()
+    );
+    assert!(
+        check_response(
+            &document,
+            &api,
+            &headers(&[("Access-Control-Allow-Origin", "https://other.test")]),
+            false
+        )
+        .is_err()
+    );
+    assert!(
+        check_response(
+            &document,
+            &api,
+            &headers(&[
+                ("Access-Control-Allow-Origin", "*"),
+                ("Access-Control-Allow-Origin", "https://example.com"),
+            ]),
+            false
+        )
+        .is_err()
+    );
+
+    assert_eq!(
+        fetch_policy::request_headers(&document, &api),
+        vec![("Origin", "https://example.com".to_string())]
+    );
+    assert!(fetch_policy::request_headers(&document, &url("https://example.com/api")).is_empty());
+}
+
+#[test]
+fn cors_with_cookies_needs_the_exact_origin_and_allow_credentials() {
+    let document = Initiator::new(url("https://example.com/page"));
+    let api = url("https://api.test/data");
+    let check = |pairs: &[(&str, &str)]| check_response(&document, &api, &headers(pairs), true);
+
+    // Cookie を付けた要求には * では足りない
+    assert!(matches!(
+        check(&[
+            ("Access-Control-Allow-Origin", "*"),
+            ("Access-Control-Allow-Credentials", "true"),
+        ]),
+        Err(PolicyError::CorsRejected { .. })
+    ));
+    assert!(check(&[("Access-Control-Allow-Origin", "https://example.com")]).is_err());
+    assert!(
+        check(&[
+            ("Access-Control-Allow-Origin", "https://example.com"),
+            ("Access-Control-Allow-Credentials", "false"),
+        ])
+        .is_err()
+    );
+    assert!(
+        check(&[
+            ("Access-Control-Allow-Origin", "https://example.com"),
+            ("access-control-allow-credentials", "true"),
+        ])
+        .is_ok()
+    );
+    // 同じオリジンなら CORS は要らない
+    assert!(check_response(&document, &url("https://example.com/x"), &[], true).is_ok());
+}
+
+#[test]
+fn origin_header_is_sent() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut origin = None;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim_end().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("origin")
+            {
+                origin = Some(value.trim().to_string());
+            }
+        }
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nAccess-Control-Allow-Origin: *\r\nContent-Length: 2\r\n\r\nok"
+        )
+        .unwrap();
+        origin
+    });
+
+    let network = NetworkCore::new();
+    network.fetch_with_headers(
+        format!("http://127.0.0.1:{port}/data"),
+        1,
+        false,
+        StoragePartition::Default,
+        None,
+        vec![("Origin", "https://example.com".to_string())],
+    );
+    let response = loop {
+        if let Some(msg) = network.try_receive().into_iter().next() {
+            break msg.response.unwrap();
+        }
+        std::thread::yield_now();
+    };
+
+    assert_eq!(response.body, b"ok");
+    assert_eq!(
+        server.join().unwrap().as_deref(),
+        Some("https://example.com")
+    );
+}
@@ -0,0 +1,67 @@
+use orinium_browser::browser::core::resource_loader::FileURI;
+use orinium_browser::browser::core::ui::url_bar::resolve_command_line;
+use std::fs;
+use std::path::PathBuf;
+use url::Url;
+
+fn fixture(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orinium-file-url-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("images")).unwrap();
+    fs::write(dir.join("index.html"), "<h1>Hello</h1>").unwrap();
+    fs::write(dir.join("style.css"), "h1 { color: red }").unwrap();
+    dir
+}
+
+#[test]
+fn files_are_read_with_a_mime_type() {
+    let dir = fixture("files");
+    let url = Url::from_file_path(dir.join("index.html")).unwrap();
+
+    let (mime, body) = FileURI::load(&url).unwrap();
+    assert_eq!(mime, "text/html");
+    assert_eq!(body, b"<h1>Hello</h1>");
+
+    // 相対パスはファイルの場所から解決する
+    let (mime, _) = FileURI::load(&url.join("style.css").unwrap()).unwrap();
+    assert_eq!(mime, "text/css");
+    assert!(FileURI::load(&url.join("missing.png").unwrap()).is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn directories_are_listed() {
+    let dir = fixture("listing");
+    // 末尾の / がなくても中を指すリンクにする
+    let url = Url::from_file_path(&dir).unwrap();
+
+    let (mime, body) = FileURI::load(&url).unwrap();
+    let html = String::from_utf8(body).unwrap();
+    assert_eq!(mime, "text/html");
+    assert!(html.contains(&format!("href=\"{url}/images/\">images/</a>")));
+    assert!(html.contains(&format!("href=\"{url}/index.html\">index.html</a>")));
+    assert!(html.find("images/").unwrap() < html.find("index.html").unwrap());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn command_line_paths_become_file_urls() {
+    let dir = fixture("cli");
+    let url = resolve_command_line("./index.html", &dir).unwrap();
+    assert_eq!(url.scheme(), "file");
+    assert_eq!(
+        url.to_file_path().unwrap(),
+        dir.canonicalize().unwrap().join("index.html")
+    );
+
+    assert_eq!(
+        resolve_command_line("https://example.com/", &dir)
+            .unwrap()
+            .as_str(),
+        "https://example.com/"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,39 @@
+use orinium_browser::engine::html::parser::Parser;
+use orinium_browser::engine::input::focus::{is_focusable, next_focus, tab_index};
+
+#[test]
+fn links_and_enabled_controls_are_focusable() {
+    let dom = Parser::new(
+        r#"<a href="/x">x</a><a>no href</a><input><input type="hidden"><input disabled>
+        <button>b</button><div tabindex="-1">d</div><span>s</span>"#,
+    )
+    .parse();
+
+    let focusable = |tag: &str| -> Vec<bool> {
+        dom.get_elements_by_tag_name(tag)
+            .iter()
+            .map(|n| is_focusable(&n.borrow().value))
+            .collect()
+    };
+    assert_eq!(focusable("a"), vec![true, false]);
+    assert_eq!(focusable("input"), vec![true, false, false]);
+    assert_eq!(focusable("button"), vec![true]);
+    assert_eq!(focusable("div"), vec![true]);
+    assert_eq!(focusable("span"), vec![false]);
+
+    let div = dom.get_elements_by_tag_name("div")[0].clone();
+    assert_eq!(tab_index(&div.borrow().value), Some(-1));
+}
+
+#[test]
+fn tab_moves_through_the_order_and_leaves_the_page_at_the_ends() {
+    let order = vec![vec![0, 1], vec![0, 3], vec![1]];
+
+    assert_eq!(next_focus(&order, None, false), Some(vec![0, 1]));
+    assert_eq!(next_focus(&order, None, true), Some(vec![1]));
+    assert_eq!(next_focus(&order, Some(&[0, 1]), false), Some(vec![0, 3]));
+    assert_eq!(next_focus(&order, Some(&[0, 3]), true), Some(vec![0, 1]));
+    assert_eq!(next_focus(&order, Some(&[1]), false), None);
+    assert_eq!(next_focus(&order, Some(&[0, 1]), true), None);
+    assert_eq!(next_focus(&[], None, false), None);
+}
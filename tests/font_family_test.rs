@@ -0,0 +1,27 @@
+use orinium_browser::engine::layouter::types::{FontFamilyList, MAX_FONT_FAMILY_LISTS};
+
+fn list(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn font_family_lists_are_interned_up_to_the_limit() {
+    let serif = FontFamilyList::intern(&list(&["Georgia", "serif"]));
+    assert_eq!(serif, FontFamilyList::intern(&list(&["Georgia", "serif"])));
+    assert_ne!(serif, FontFamilyList::intern(&list(&["serif", "Georgia"])));
+    assert_eq!(
+        serif.names().as_ref(),
+        list(&["Georgia", "serif"]).as_slice()
+    );
+    assert_eq!(FontFamilyList::intern(&[]), FontFamilyList::default());
+
+    // 表が埋まったら、新しいリストは既定のフォントになる
+    for i in 0..MAX_FONT_FAMILY_LISTS {
+        FontFamilyList::intern(&[format!("Family {i}")]);
+    }
+    let overflow = FontFamilyList::intern(&list(&["One Too Many"]));
+    assert_eq!(overflow, FontFamilyList::default());
+    assert!(overflow.names().is_empty());
+    // 前からあるリストはそのまま
+    assert_eq!(serif, FontFamilyList::intern(&list(&["Georgia", "serif"])));
+}
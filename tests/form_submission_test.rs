@@ -0,0 +1,108 @@
+use orinium_browser::browser::core::webview::form::{self, FieldValues};
+use orinium_browser::engine::html::parser::Parser;
+use url::Url;
+
+const FORM: &str = r#"<html><body>
+<form action="/search#results">
+    <input name="q" value="rust lang">
+    <input type="hidden" name="lang" value="ja">
+    <input type="checkbox" name="safe" checked>
+    <input type="checkbox" name="images" value="1">
+    <input name="disabled" value="x" disabled>
+    <select name="sort"><option value="new">New</option><option selected>Old</option></select>
+    <textarea name="note">hello</textarea>
+    <button name="go" value="1">Search</button>
+    <input type="submit" name="other" value="Other">
+    <button type="button">Cancel</button>
+</form>
+</body></html>"#;
+
+fn base() -> Url {
+    "https://example.com/dir/page.html?x=1".parse().unwrap()
+}
+
+#[test]
+fn button_types_decide_whether_the_form_is_submitted() {
+    let dom = Parser::new(FORM).parse();
+    let buttons = dom.get_elements_by_tag_name("button");
+    assert!(form::submits_form(&buttons[0].borrow().value));
+    assert!(!form::submits_form(&buttons[1].borrow().value));
+
+    let inputs = dom.get_elements_by_tag_name("input");
+    assert!(!form::submits_form(&inputs[0].borrow().value));
+    assert!(form::submits_form(&inputs[5].borrow().value));
+}
+
+#[test]
+fn form_data_collects_successful_controls_in_order() {
+    let dom = Parser::new(FORM).parse();
+    let button = dom.get_elements_by_tag_name("button")[0].clone();
+    let owner = form::form_owner(&button).expect("form owner");
+
+    let data = form::form_data(&owner, Some(&button), &FieldValues::new());
+    let pairs: Vec<(&str, &str)> = data.iter().map(|(n, v)| (n.as_str(), v.as_str())).collect();
+    assert_eq!(
+        pairs,
+        vec![
+            ("q", "rust lang"),
+            ("lang", "ja"),
+            ("safe", "on"),
+            ("sort", "Old"),
+            ("note", "hello"),
+            ("go", "1"),
+        ]
+    );
+}
+
+#[test]
+fn submission_url_resolves_action_and_replaces_query() {
+    let dom = Parser::new(FORM).parse();
+    let button = dom.get_elements_by_tag_name("button")[0].clone();
+    let owner = form::form_owner(&button).unwrap();
+
+    let url =
+        form::submission_url(&owner, Some(&button), &FieldValues::new(), &base(), &base()).unwrap();
+    assert_eq!(
+        url.as_str(),
+        "https://example.com/search?q=rust+lang&lang=ja&safe=on&sort=Old&note=hello&go=1"
+    );
+}
+
+#[test]
+fn empty_action_submits_to_the_document_and_post_is_not_supported() {
+    let dom = Parser::new(r#"<form><input name="a" value="b"></form>"#).parse();
+    let owner = dom.get_elements_by_tag_name("form")[0].clone();
+    let url = form::submission_url(&owner, None, &FieldValues::new(), &base(), &base()).unwrap();
+    assert_eq!(url.as_str(), "https://example.com/dir/page.html?a=b");
+
+    let dom = Parser::new(r#"<form method="POST"><input name="a"></form>"#).parse();
+    let owner = dom.get_elements_by_tag_name("form")[0].clone();
+    assert!(form::submission_url(&owner, None, &FieldValues::new(), &base(), &base()).is_none());
+}
+
+#[test]
+fn typed_values_are_restored_into_a_fresh_document() {
+    let html = r#"<form><input name="q" value=""><input type="password" value="secret"><input type="checkbox" value="on"><textarea>x</textarea></form>"#;
+    let dom = Parser::new(html).parse();
+    let inputs = dom.get_elements_by_tag_name("input");
+    inputs[0]
+        .borrow_mut()
+        .value
+        .set_attr("value", "typed".to_string());
+
+    let state = form::save_form_state(&dom.root);
+    // パスワード欄とチェックボックスは残さない
+    assert_eq!(state.fields.len(), 1);
+
+    let fresh = Parser::new(html).parse();
+    form::restore_form_state(&fresh.root, &state);
+    let inputs = fresh.get_elements_by_tag_name("input");
+    assert_eq!(inputs[0].borrow().value.get_attr("value"), Some("typed"));
+    assert_eq!(inputs[1].borrow().value.get_attr("value"), Some("secret"));
+
+    // 文書が変わって入力欄でなくなっていれば入れない
+    let changed = Parser::new(r#"<form><p>text</p></form>"#).parse();
+    form::restore_form_state(&changed.root, &state);
+    let p = changed.get_elements_by_tag_name("p");
+    assert_eq!(p[0].borrow().value.get_attr("value"), None);
+}
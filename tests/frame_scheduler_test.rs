@@ -0,0 +1,83 @@
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use orinium_browser::browser::core::frame_scheduler::{FrameScheduler, Invalidation};
+use orinium_browser::browser::{BrowserApp, BrowserCommand, BrowserEvent};
+use orinium_browser::platform::renderer::compositor::FrameRecorder;
+use winit::event::WindowEvent;
+
+#[test]
+fn invalidations_are_coalesced_into_one_frame() {
+    let mut frames = FrameScheduler::new();
+    let now = Instant::now();
+    assert!(!frames.poll(now));
+
+    frames.invalidate(Invalidation::Scroll);
+    frames.invalidate(Invalidation::Style);
+    frames.invalidate(Invalidation::Scroll);
+    assert!(frames.poll(now));
+    // 頼んだフレームが始まるまでは頼み直さない
+    frames.invalidate(Invalidation::Content);
+    assert!(!frames.poll(now));
+
+    let reasons = frames.begin_frame(now);
+    assert!(reasons.contains(Invalidation::Scroll));
+    assert!(reasons.contains(Invalidation::Style));
+    assert!(reasons.contains(Invalidation::Content));
+    assert!(!reasons.contains(Invalidation::Animation));
+    assert!(frames.pending().is_empty());
+}
+
+#[test]
+fn frames_are_paced_to_the_refresh_rate() {
+    let mut frames = FrameScheduler::new();
+    frames.set_refresh_rate(120_000);
+    let interval = frames.frame_interval();
+    assert!(interval > Duration::from_millis(8) && interval < Duration::from_millis(9));
+    // 0 は不明として無視する
+    frames.set_refresh_rate(0);
+    assert_eq!(frames.frame_interval(), interval);
+
+    let start = Instant::now();
+    frames.begin_frame(start);
+    frames.invalidate(Invalidation::Animation);
+    assert!(!frames.poll(start + interval / 2));
+    assert!(frames.poll(start + interval));
+}
+
+#[test]
+fn browser_requests_one_redraw_per_refresh() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    let (tx, events) = mpsc::channel();
+    browser.set_event_sink(Box::new(tx));
+    // テストの間に次のリフレッシュが来ないよう、ゆっくりにする
+    browser.set_refresh_rate(1);
+    let commands = browser.command_sender();
+    let redraws = |events: &mpsc::Receiver<BrowserEvent>| {
+        events
+            .try_iter()
+            .filter(|event| *event == BrowserEvent::NeedsRedraw)
+            .count()
+    };
+
+    for dy in [10.0, 20.0, 30.0] {
+        commands.send(BrowserCommand::Scroll { dx: 0.0, dy });
+    }
+    commands.send(BrowserCommand::Resize {
+        width: 1024,
+        height: 768,
+    });
+    browser.process_commands();
+    assert_eq!(redraws(&events), 1);
+
+    // フレームを描いたあと、次のリフレッシュまでは頼まない
+    let mut compositor = FrameRecorder::new((1024, 768));
+    commands.send(browser.handle_window_event(WindowEvent::RedrawRequested, &mut compositor));
+    assert!(compositor.take_rendered().is_some());
+    commands.send(BrowserCommand::Resize {
+        width: 800,
+        height: 600,
+    });
+    browser.process_commands();
+    assert_eq!(redraws(&events), 0);
+}
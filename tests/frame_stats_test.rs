@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::engine::renderer_model::DrawCommand;
+use orinium_browser::platform::renderer::frame_stats::{
+    self, FRAME_HISTORY, FrameCounts, FrameStats,
+};
+
+#[test]
+fn fps_counts_frames_in_the_last_second() {
+    let start = Instant::now();
+    let mut stats = FrameStats::new();
+    for i in 0..30 {
+        stats.record(
+            start + Duration::from_millis(i * 50),
+            Duration::from_millis(2),
+        );
+    }
+
+    let now = start + Duration::from_millis(29 * 50);
+    // 450ms〜1450ms の 21 フレーム
+    assert_eq!(stats.fps(now), 21);
+    assert_eq!(stats.fps(now + Duration::from_secs(5)), 0);
+}
+
+#[test]
+fn percentiles_use_the_recent_frames() {
+    let now = Instant::now();
+    let mut stats = FrameStats::new();
+    assert_eq!(stats.percentile(50.0), None);
+
+    for ms in 1..=100 {
+        stats.record(now, Duration::from_millis(ms));
+    }
+    assert_eq!(stats.percentile(50.0), Some(Duration::from_millis(50)));
+    assert_eq!(stats.percentile(95.0), Some(Duration::from_millis(95)));
+    assert_eq!(stats.percentile(100.0), Some(Duration::from_millis(100)));
+
+    for _ in 0..FRAME_HISTORY {
+        stats.record(now, Duration::from_millis(1));
+    }
+    assert_eq!(stats.len(), FRAME_HISTORY);
+    assert_eq!(stats.percentile(99.0), Some(Duration::from_millis(1)));
+}
+
+#[test]
+fn overlay_shows_the_counts() {
+    let now = Instant::now();
+    let mut stats = FrameStats::new();
+    stats.record(now, Duration::from_micros(4200));
+    let counts = FrameCounts {
+        draw_commands: 12,
+        vertices: 30,
+        rect_instances: 5,
+        text_sections: 7,
+    };
+
+    let commands = frame_stats::overlay_commands(&stats, &counts, now, 800.0);
+    let text: Vec<&str> = commands
+        .iter()
+        .filter_map(|c| match c {
+            DrawCommand::DrawText { text, .. } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        text,
+        vec![
+            "1 fps",
+            "frame ms  p50 4.2  p95 4.2  p99 4.2",
+            "draw commands  12",
+            "vertices  30  rects  5",
+            "text sections  7",
+        ]
+    );
+    assert!(matches!(commands[0], DrawCommand::DrawRect { x, .. } if x + 300.0 <= 800.0));
+}
@@ -0,0 +1,99 @@
+use orinium_browser::engine::css::parser::{Parser as CssParser, ParserErrorKind};
+use orinium_browser::engine::fuzz::{fuzz_parse_css, fuzz_parse_html};
+use orinium_browser::engine::html::HtmlNodeType;
+use orinium_browser::engine::html::parser::{MAX_TREE_DEPTH, Parser as HtmlParser};
+use orinium_browser::engine::tree::NodeRef;
+
+/// 深い木を作る入力は、テストのスレッドの既定のスタックでは足りないので広げて走らせる
+fn with_large_stack(f: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(64 * 1024 * 1024)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap();
+}
+
+fn depth(node: &NodeRef<HtmlNodeType>) -> usize {
+    1 + node
+        .borrow()
+        .children()
+        .iter()
+        .map(depth)
+        .max()
+        .unwrap_or(0)
+}
+
+#[test]
+fn malformed_html_does_not_panic() {
+    for input in [
+        &b""[..],
+        b"<",
+        b"</",
+        b"<!",
+        b"<!DOCTYPE",
+        b"<!doctype html PUBLIC \"x",
+        b"</div></body></html>",
+        b"<p><p><li><li><a><a><dt><dd>",
+        b"<body><body><html>",
+        b"<script><div></script",
+        b"<style>p{</style>",
+        b"&#x110000;&#xFFFFFFFFFF;&#;&amp",
+        b"<a href='\xff\xfe'>\xc3</a>",
+        "<!İdoctype>".as_bytes(),
+    ] {
+        fuzz_parse_html(input);
+    }
+}
+
+#[test]
+fn malformed_css_does_not_panic() {
+    for input in [
+        "",
+        "{",
+        "}",
+        "@media",
+        "@media (",
+        "@media ) {}",
+        "@media screen and (min-width: {",
+        "a{b:calc(}",
+        "a{b:calc(1px +}",
+        "a[=]{}",
+        ":not({}",
+        "a{color:red!important!important}",
+        "@import url(",
+        "/* unterminated",
+        "\"unterminated",
+        "a{b:\\",
+    ] {
+        fuzz_parse_css(input.as_bytes());
+    }
+}
+
+#[test]
+fn deeply_nested_elements_are_flattened() {
+    with_large_stack(|| {
+        let html = "<div>".repeat(10_000);
+        let dom = HtmlParser::new(&html).parse();
+        // 要素は MAX_TREE_DEPTH 段まで（+1 は Document）
+        assert!(depth(&dom.root) <= MAX_TREE_DEPTH + 1);
+
+        fuzz_parse_html(html.as_bytes());
+    });
+}
+
+#[test]
+fn deeply_nested_css_is_rejected() {
+    for css in [
+        "@media{".repeat(10_000),
+        format!("@media {} {{}}", "(".repeat(10_000)),
+        format!("a{{b:{}}}", "calc(".repeat(10_000)),
+    ] {
+        let error = CssParser::new(&css).parse().unwrap_err();
+        assert!(
+            matches!(error.kind, ParserErrorKind::NestingTooDeep),
+            "{error}"
+        );
+        fuzz_parse_css(css.as_bytes());
+    }
+}
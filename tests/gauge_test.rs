@@ -0,0 +1,102 @@
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter;
+use orinium_browser::engine::layouter::css_resolver::CssResolver;
+use orinium_browser::engine::layouter::types::{ContainerRole, InfoNode, NodeKind, TextStyle};
+use orinium_browser::engine::renderer_model::{DrawCommand, generate_draw_commands};
+
+const CSS: &str = "progress, meter { width: 100px; height: 10px }";
+
+fn gauges(body: &str) -> (Vec<ContainerRole>, Vec<DrawCommand>) {
+    let sheet = CssParser::new(CSS).parse().unwrap();
+    let styles = CssResolver::resolve_with_media(&sheet, &MediaContext::default());
+    let dom = HtmlParser::new(&format!("<html><body>{body}</body></html>")).parse();
+    let (mut layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+
+    fn collect(info: &InfoNode, roles: &mut Vec<ContainerRole>) {
+        if let NodeKind::Container {
+            role: role @ ContainerRole::Gauge { .. },
+            ..
+        } = &info.kind
+        {
+            roles.push(role.clone());
+        }
+        for child in &info.children {
+            collect(child, roles);
+        }
+    }
+    let mut roles = Vec::new();
+    collect(&info, &mut roles);
+    (roles, generate_draw_commands(&layout, &info))
+}
+
+fn fraction(role: &ContainerRole) -> f32 {
+    match role {
+        ContainerRole::Gauge { fraction, .. } => *fraction,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn progress_is_filled_by_value_over_max() {
+    let (roles, commands) = gauges("<progress value=\"30\" max=\"120\">25%</progress>");
+    assert_eq!(roles.len(), 1);
+    assert_eq!(fraction(&roles[0]), 0.25);
+
+    let ContainerRole::Gauge { color, .. } = roles[0] else {
+        unreachable!()
+    };
+    assert!(commands.iter().any(|c| matches!(
+        c,
+        DrawCommand::DrawRect { width, height, color: c, .. }
+            if *width == 25.0 && *height == 10.0 && *c == color
+    )));
+}
+
+#[test]
+fn progress_values_are_clamped() {
+    let (roles, _) = gauges(
+        "<progress value=\"5\" max=\"2\"></progress>\
+         <progress value=\"-1\"></progress>\
+         <progress></progress>",
+    );
+    let fractions: Vec<f32> = roles.iter().map(fraction).collect();
+    assert_eq!(fractions, [1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn meter_color_depends_on_the_optimum_region() {
+    let (roles, _) = gauges(
+        "<meter value=\"0.5\"></meter>\
+         <meter min=\"0\" max=\"100\" low=\"20\" high=\"80\" optimum=\"90\" value=\"50\"></meter>\
+         <meter min=\"0\" max=\"100\" low=\"20\" high=\"80\" optimum=\"90\" value=\"10\"></meter>",
+    );
+    let colors: Vec<_> = roles
+        .iter()
+        .map(|role| match role {
+            ContainerRole::Gauge { color, .. } => *color,
+            _ => unreachable!(),
+        })
+        .collect();
+    assert_eq!(fraction(&roles[1]), 0.5);
+    // 最適、1 つ隣、最も遠い範囲でそれぞれ違う色になる
+    assert_ne!(colors[0], colors[1]);
+    assert_ne!(colors[1], colors[2]);
+    assert_ne!(colors[0], colors[2]);
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::platform::geolocation::{
+    self, FixedLocationProvider, Geolocation, Position, PositionError, PositionResult,
+};
+
+const TOKYO: Position = Position {
+    latitude: 35.681236,
+    longitude: 139.767125,
+    accuracy: 25.0,
+};
+
+/// 答えが count 個届くまで待つ
+fn wait_for(service: &Geolocation, count: usize) -> Vec<PositionResult> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut results = Vec::new();
+    while results.len() < count && Instant::now() < deadline {
+        results.extend(service.poll());
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    results
+}
+
+#[test]
+fn command_output_is_parsed() {
+    assert_eq!(
+        geolocation::parse_position("35.681236 139.767125 25"),
+        Some(TOKYO)
+    );
+    assert_eq!(
+        geolocation::parse_position("-33.8688 151.2093 1200.5\n"),
+        Some(Position {
+            latitude: -33.8688,
+            longitude: 151.2093,
+            accuracy: 1200.5,
+        })
+    );
+    assert_eq!(geolocation::parse_position("35.6 139.7"), None);
+    assert_eq!(geolocation::parse_position("91 0 10"), None);
+    assert_eq!(geolocation::parse_position("kCLErrorDomain error 1"), None);
+}
+
+#[test]
+fn error_codes_match_the_web_api() {
+    assert_eq!(PositionError::PermissionDenied.code(), 1);
+    assert_eq!(
+        PositionError::PositionUnavailable("no fix".to_string()).code(),
+        2
+    );
+    assert_eq!(PositionError::Timeout.code(), 3);
+}
+
+#[test]
+fn requests_are_answered_in_the_background() {
+    let service = Geolocation::new(Box::new(FixedLocationProvider(TOKYO)));
+    service.request(1, geolocation::DEFAULT_TIMEOUT);
+    service.request(2, geolocation::DEFAULT_TIMEOUT);
+
+    let mut results = wait_for(&service, 2);
+    results.sort_by_key(|(id, _)| *id);
+    assert_eq!(results, vec![(1, Ok(TOKYO)), (2, Ok(TOKYO))]);
+    assert!(service.poll().is_empty());
+}
@@ -0,0 +1,48 @@
+use orinium_browser::browser::core::history::History;
+use url::Url;
+
+fn url(s: &str) -> Url {
+    s.parse().unwrap()
+}
+
+#[test]
+fn back_and_forward_move_through_entries() {
+    let mut history = History::new();
+    history.push(url("https://a.example/"));
+    history.push(url("https://b.example/"));
+    history.push(url("https://c.example/"));
+
+    assert!(history.can_go_back());
+    assert!(!history.can_go_forward());
+
+    assert_eq!(history.back().unwrap().url, url("https://b.example/"));
+    assert_eq!(history.back().unwrap().url, url("https://a.example/"));
+    assert!(history.back().is_none());
+
+    assert_eq!(history.forward().unwrap().url, url("https://b.example/"));
+}
+
+#[test]
+fn push_discards_forward_entries() {
+    let mut history = History::new();
+    history.push(url("https://a.example/"));
+    history.push(url("https://b.example/"));
+    history.back();
+
+    history.push(url("https://c.example/"));
+
+    assert_eq!(history.len(), 2);
+    assert!(!history.can_go_forward());
+    assert_eq!(history.current().unwrap().url, url("https://c.example/"));
+}
+
+#[test]
+fn scroll_position_is_kept_per_entry() {
+    let mut history = History::new();
+    history.push(url("https://a.example/"));
+    history.current_mut().unwrap().scroll = (0.0, 320.0);
+    history.push(url("https://b.example/"));
+
+    assert_eq!(history.current().unwrap().scroll, (0.0, 0.0));
+    assert_eq!(history.back().unwrap().scroll, (0.0, 320.0));
+}
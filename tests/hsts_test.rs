@@ -0,0 +1,60 @@
+use orinium_browser::platform::network::hsts::{self, HstsStore};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+#[test]
+fn header_is_parsed() {
+    assert_eq!(
+        hsts::parse("max-age=31536000; includeSubDomains; preload"),
+        Some((Duration::from_secs(31536000), true))
+    );
+    assert_eq!(
+        hsts::parse(r#"max-age="60""#),
+        Some((Duration::from_secs(60), false))
+    );
+    assert_eq!(hsts::parse("includeSubDomains"), None);
+    assert_eq!(hsts::parse("max-age=1; max-age=2"), None);
+}
+
+#[test]
+fn remembered_hosts_are_upgraded_until_they_expire() {
+    let store = HstsStore::new();
+    store.record("Example.com", "max-age=100", at(0));
+
+    assert!(store.should_upgrade("example.com", at(99)));
+    assert!(!store.should_upgrade("www.example.com", at(99)));
+    assert!(!store.should_upgrade("example.com", at(100)));
+
+    store.record("example.org", "max-age=100; includeSubDomains", at(0));
+    assert!(store.should_upgrade("a.b.example.org", at(50)));
+
+    // max-age=0 で忘れる
+    store.record("example.org", "max-age=0", at(60));
+    assert!(!store.should_upgrade("example.org", at(60)));
+}
+
+#[test]
+fn ip_addresses_are_not_remembered_and_preloaded_tlds_are_upgraded() {
+    let store = HstsStore::new();
+    store.record("127.0.0.1", "max-age=100", at(0));
+    assert!(!store.should_upgrade("127.0.0.1", at(1)));
+    assert!(store.should_upgrade("web.dev", at(0)));
+}
+
+#[test]
+fn store_round_trips_through_text() {
+    let store = HstsStore::new();
+    store.record("example.com", "max-age=3600; includeSubDomains", at(0));
+    assert!(store.take_modified());
+    // 期限が少し延びただけでは書き直さない
+    store.record("example.com", "max-age=3600; includeSubDomains", at(10));
+    assert!(!store.take_modified());
+
+    let restored = HstsStore::new();
+    restored.load(&store.serialize(at(0)), at(20));
+    assert!(restored.should_upgrade("www.example.com", at(20)));
+    assert!(!restored.take_modified());
+}
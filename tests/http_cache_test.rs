@@ -0,0 +1,242 @@
+use orinium_browser::platform::network::Cache;
+use orinium_browser::platform::network::cache::{CacheKey, CacheLookup, INDEX_FILE_NAME};
+use orinium_browser::platform::network::http_date;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+fn headers(list: &[(&str, &str)]) -> Vec<(String, String)> {
+    list.iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect()
+}
+
+fn key(s: &str) -> CacheKey {
+    CacheKey::new(&Url::parse(s).unwrap(), None)
+}
+
+fn at(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+#[test]
+fn http_dates_round_trip() {
+    let time = http_date::parse("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+    assert_eq!(time, at(784111777));
+    assert_eq!(http_date::format(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+    assert_eq!(
+        http_date::parse("Sunday, 06-Nov-94 08:49:37 GMT"),
+        Some(time)
+    );
+    assert_eq!(http_date::parse("Sun Nov  6 08:49:37 1994"), Some(time));
+    assert_eq!(http_date::parse("yesterday"), None);
+}
+
+#[test]
+fn max_age_decides_freshness() {
+    let cache = Cache::new();
+    let key = key("https://example.com/a.css");
+    assert!(cache.store(
+        &key,
+        b"body".to_vec(),
+        headers(&[("Cache-Control", "public, max-age=60")]),
+        at(1000),
+    ));
+
+    assert!(matches!(
+        cache.lookup(&key, at(1059)),
+        CacheLookup::Fresh(_)
+    ));
+    assert!(matches!(
+        cache.lookup(&key, at(1060)),
+        CacheLookup::Stale(_)
+    ));
+}
+
+#[test]
+fn expires_is_relative_to_date() {
+    let cache = Cache::new();
+    let key = key("https://example.com/b.js");
+    cache.store(
+        &key,
+        Vec::new(),
+        headers(&[
+            ("Date", &http_date::format(at(0))),
+            ("Expires", &http_date::format(at(100))),
+        ]),
+        at(5000),
+    );
+
+    assert!(matches!(
+        cache.lookup(&key, at(5099)),
+        CacheLookup::Fresh(_)
+    ));
+    assert!(matches!(
+        cache.lookup(&key, at(5100)),
+        CacheLookup::Stale(_)
+    ));
+}
+
+#[test]
+fn uncacheable_responses_are_not_stored() {
+    let cache = Cache::new();
+    let key = key("https://example.com/");
+    assert!(!cache.store(
+        &key,
+        Vec::new(),
+        headers(&[("Cache-Control", "no-store, max-age=60")]),
+        at(0),
+    ));
+    // 古くなっていて確かめる手段もない
+    assert!(!cache.store(&key, Vec::new(), Vec::new(), at(0)));
+    assert_eq!(cache.lookup(&key, at(0)), CacheLookup::Miss);
+}
+
+#[test]
+fn not_modified_freshens_the_stored_body() {
+    let cache = Cache::new();
+    let key = key("https://example.com/logo.png");
+    cache.store(
+        &key,
+        b"png".to_vec(),
+        headers(&[
+            ("Cache-Control", "no-cache"),
+            ("ETag", "\"v1\""),
+            ("Last-Modified", "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ]),
+        at(0),
+    );
+
+    let CacheLookup::Stale(entry) = cache.lookup(&key, at(10)) else {
+        panic!("no-cache responses must be revalidated");
+    };
+    assert_eq!(
+        Cache::conditional_headers(&entry),
+        vec![
+            ("If-None-Match", "\"v1\"".to_string()),
+            (
+                "If-Modified-Since",
+                "Sun, 06 Nov 1994 08:49:37 GMT".to_string()
+            ),
+        ]
+    );
+
+    let entry = cache
+        .freshen(
+            &key,
+            &headers(&[("Cache-Control", "max-age=30"), ("ETag", "\"v1\"")]),
+            at(20),
+        )
+        .unwrap();
+    assert_eq!(entry.body, b"png");
+    assert!(matches!(cache.lookup(&key, at(49)), CacheLookup::Fresh(_)));
+}
+
+#[test]
+fn disk_cache_survives_restart_and_evicts_old_entries() {
+    let dir = std::env::temp_dir().join(format!("orinium-http-cache-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let fresh = headers(&[("Cache-Control", "max-age=3600")]);
+    let now = SystemTime::now();
+    let later = |secs| now + Duration::from_secs(secs);
+    let first = key("https://example.com/1");
+    let second = key("https://example.com/2");
+
+    let cache = Cache::new();
+    cache.enable_disk(dir.clone(), 1024);
+    cache.store(&first, vec![b'a'; 400], fresh.clone(), later(0));
+    cache.store(&second, vec![b'b'; 400], fresh.clone(), later(1));
+    assert!(cache.disk_usage() <= 1024);
+
+    // 再起動したつもりで別のキャッシュから読む
+    let restarted = Cache::new();
+    restarted.enable_disk(dir.clone(), 1024);
+    let CacheLookup::Fresh(entry) = restarted.lookup(&second, later(2)) else {
+        panic!("the entry must be read back from disk");
+    };
+    assert_eq!(entry.body, vec![b'b'; 400]);
+
+    // 予算を超えたら最近使っていないものから捨てる
+    let third = key("https://example.com/3");
+    restarted.store(&third, vec![b'c'; 400], fresh, later(3));
+    assert!(restarted.disk_usage() <= 1024);
+    let reopened = Cache::new();
+    reopened.enable_disk(dir.clone(), 1024);
+    assert!(matches!(
+        reopened.lookup(&third, later(4)),
+        CacheLookup::Fresh(_)
+    ));
+
+    restarted.clear();
+    assert_eq!(restarted.disk_usage(), 0);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn entries_are_partitioned_by_top_level_site() {
+    let cache = Cache::new();
+    let font = Url::parse("https://cdn.example.net/font.woff2").unwrap();
+    let news = Url::parse("https://news.example.com/").unwrap();
+    let shop = Url::parse("https://shop.example.org/").unwrap();
+    let fresh = headers(&[("Cache-Control", "max-age=60")]);
+
+    let on_news = CacheKey::new(&font, Some(&news));
+    assert_eq!(on_news.site(), "https://example.com");
+    cache.store(&on_news, b"font".to_vec(), fresh, at(0));
+
+    // 同じサイトの別のページからは使えるが、別のサイトからは使えない
+    let www = Url::parse("https://www.example.com/a").unwrap();
+    assert!(matches!(
+        cache.lookup(&CacheKey::new(&font, Some(&www)), at(1)),
+        CacheLookup::Fresh(_)
+    ));
+    assert_eq!(
+        cache.lookup(&CacheKey::new(&font, Some(&shop)), at(1)),
+        CacheLookup::Miss
+    );
+    assert_eq!(
+        cache.lookup(&CacheKey::new(&font, None), at(1)),
+        CacheLookup::Miss
+    );
+}
+
+#[test]
+fn disk_cache_recovers_from_a_corrupted_index_and_entries() {
+    let dir = std::env::temp_dir().join(format!("orinium-http-cache-index-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let fresh = headers(&[("Cache-Control", "max-age=3600")]);
+    let now = SystemTime::now();
+    let first = key("https://example.com/1");
+    let second = key("https://example.com/2");
+
+    let cache = Cache::new();
+    cache.enable_disk(dir.clone(), 1 << 20);
+    cache.store(&first, vec![b'a'; 100], fresh.clone(), now);
+    cache.store(&second, vec![b'b'; 100], fresh, now);
+    cache.save_index_if_modified();
+    let index = std::fs::read_to_string(dir.join(INDEX_FILE_NAME)).unwrap();
+    assert_eq!(index.lines().count(), 3);
+
+    // 索引が壊れていてもディレクトリの中身から使用量を数え直す
+    std::fs::write(dir.join(INDEX_FILE_NAME), "garbage").unwrap();
+    // 書いている途中で止まったファイル
+    std::fs::write(dir.join("0123456789abcdef.tmp"), "partial").unwrap();
+    let restarted = Cache::new();
+    restarted.enable_disk(dir.clone(), 1 << 20);
+    assert_eq!(restarted.disk_usage(), cache.disk_usage());
+    assert!(!dir.join("0123456789abcdef.tmp").exists());
+
+    // 途中で切れたファイルは読まずに消す
+    for file in std::fs::read_dir(&dir).unwrap().flatten() {
+        let path = file.path();
+        if path.extension().is_some_and(|e| e == "entry") {
+            let data = std::fs::read(&path).unwrap();
+            std::fs::write(&path, &data[..data.len() - 10]).unwrap();
+        }
+    }
+    let truncated = Cache::new();
+    truncated.enable_disk(dir.clone(), 1 << 20);
+    assert_eq!(truncated.lookup(&first, now), CacheLookup::Miss);
+    assert_eq!(truncated.lookup(&second, now), CacheLookup::Miss);
+    assert_eq!(truncated.disk_usage(), 0);
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,95 @@
+use orinium_browser::browser::core::csp::ContentSecurityPolicy;
+use orinium_browser::browser::core::fetch_policy::{
+    self, Initiator, PolicyError, RequestMode, check_request, check_response,
+};
+use orinium_browser::browser::core::origin::Origin;
+use orinium_browser::browser::core::webview::sandbox::SandboxFlags;
+use orinium_browser::browser::core::webview::{FetchKind, WebView, WebViewTask};
+use url::Url;
+
+#[test]
+fn empty_sandbox_forbids_everything() {
+    let flags = SandboxFlags::parse("");
+    assert!(flags.is_sandboxed());
+    assert!(flags.scripts && flags.forms && flags.top_navigation && flags.popups && flags.origin);
+    assert!(!SandboxFlags::none().is_sandboxed());
+}
+
+#[test]
+fn allow_tokens_lift_restrictions() {
+    let flags = SandboxFlags::parse("  Allow-Scripts allow-top-navigation\tallow-unknown ");
+    assert!(!flags.scripts);
+    assert!(!flags.top_navigation);
+    assert!(flags.forms);
+    assert!(flags.origin);
+
+    let flags = SandboxFlags::parse("allow-forms allow-same-origin allow-popups");
+    assert!(flags.scripts);
+    assert!(!flags.popups);
+    assert!(!flags.forms);
+    assert!(!flags.origin);
+}
+
+#[test]
+fn nested_frames_keep_the_parents_restrictions() {
+    let parent = SandboxFlags::parse("allow-scripts allow-forms");
+    let child = SandboxFlags::parse("allow-scripts allow-same-origin allow-top-navigation");
+    let nested = parent.union(child);
+    assert!(!nested.scripts);
+    assert!(nested.forms);
+    assert!(nested.top_navigation);
+    assert!(nested.origin);
+
+    assert_eq!(SandboxFlags::none().union(child), child);
+}
+
+/// https://example.com/ の文書の `<iframe sandbox="{sandbox}">` に同じオリジンの文書を
+/// 読み込み、その文書を返す
+fn framed_document(sandbox: &str) -> Initiator {
+    let html = format!(r#"<iframe sandbox="{sandbox}" src="/frame.html"></iframe>"#);
+    let mut webview = WebView::new();
+    webview.tick();
+    webview.on_html_fetched(html, Url::parse("https://example.com/").unwrap());
+
+    let frame = webview
+        .tick()
+        .into_iter()
+        .find_map(|task| match task {
+            WebViewTask::Fetch {
+                kind: FetchKind::Frame { frame, kind },
+                ..
+            } if matches!(*kind, FetchKind::Html) => Some(frame),
+            _ => None,
+        })
+        .expect("the frame asks for its document");
+    webview.on_frame_html_fetched(frame, String::new(), ContentSecurityPolicy::new());
+    webview.frame(frame).unwrap().initiator().unwrap()
+}
+
+#[test]
+fn sandboxed_frames_have_an_opaque_origin_for_requests() {
+    let api = Url::parse("https://example.com/api").unwrap();
+
+    let frame = framed_document("allow-scripts");
+    assert_eq!(frame.url.as_str(), "https://example.com/frame.html");
+    assert!(frame.origin.is_opaque());
+    // 同じサーバーへの要求も別のオリジンへのものになる
+    assert_eq!(
+        fetch_policy::request_headers(&frame, &api),
+        vec![("Origin", "null".to_string())]
+    );
+    assert!(matches!(
+        check_response(&frame, &api, &[], false),
+        Err(PolicyError::CorsRejected { .. })
+    ));
+    assert!(matches!(
+        check_request(Some(&frame), &api, "POST", RequestMode::Cors),
+        Err(PolicyError::CrossOrigin { origin, .. }) if origin == "null"
+    ));
+
+    // allow-same-origin なら URL のオリジンのまま
+    let frame = framed_document("allow-scripts allow-same-origin");
+    assert_eq!(frame.origin, Origin::of(&api));
+    assert!(fetch_policy::request_headers(&frame, &api).is_empty());
+    assert!(check_response(&frame, &api, &[], false).is_ok());
+}
@@ -0,0 +1,120 @@
+use orinium_browser::browser::core::fetch_policy::RequestMode;
+use orinium_browser::browser::core::webview::FetchKind;
+use orinium_browser::engine::input;
+use orinium_browser::engine::layouter::types::{
+    Color, ContainerRole, ContainerStyle, InfoNode, NodeKind,
+};
+use orinium_browser::engine::renderer_model::{
+    DrawCommand, generate_draw_commands_with_frames, generate_draw_commands_with_selection,
+};
+use ui_layout::{LayoutEngine, LayoutNode, Length, Style};
+
+const MARKER: Color = Color(1, 2, 3, 255);
+
+fn container(role: ContainerRole) -> InfoNode {
+    InfoNode {
+        kind: NodeKind::Container {
+            scroll_x: false,
+            scroll_y: false,
+            scroll_offset_x: 0.0,
+            scroll_offset_y: 0.0,
+            style: ContainerStyle::default(),
+            role,
+        },
+        children: Vec::new(),
+    }
+}
+
+/// 上に高さ 50px のブロック、その下に 300x150 の iframe があるページ
+fn page() -> (LayoutNode, InfoNode) {
+    let mut spacer = Style::default();
+    spacer.size.height = Length::Px(50.0);
+    let mut frame = Style::default();
+    frame.size.width = Length::Px(300.0);
+    frame.size.height = Length::Px(150.0);
+    let mut layout = LayoutNode::with_children(
+        Style::default(),
+        vec![
+            LayoutNode::with_children(spacer, Vec::new()),
+            LayoutNode::with_children(frame, Vec::new()),
+        ],
+    );
+    LayoutEngine::layout(&mut layout, 800.0, 600.0);
+
+    let mut info = container(ContainerRole::Normal);
+    info.children.push(container(ContainerRole::Normal));
+    info.children.push(container(ContainerRole::Frame));
+    (layout, info)
+}
+
+fn marker() -> DrawCommand {
+    DrawCommand::DrawRect {
+        x: 0.0,
+        y: 0.0,
+        width: 10.0,
+        height: 10.0,
+        color: MARKER,
+    }
+}
+
+fn is_marker(command: &DrawCommand) -> bool {
+    matches!(command, DrawCommand::DrawRect { color, .. } if *color == MARKER)
+}
+
+#[test]
+fn frame_document_is_drawn_clipped_to_the_iframe() {
+    let (layout, info) = page();
+    let commands = generate_draw_commands_with_frames(&layout, &info, None, None, &|path| {
+        (path == [1]).then(|| vec![marker()])
+    });
+
+    let i = commands.iter().position(is_marker).expect("frame drawn");
+    assert!(matches!(
+        commands[i - 1],
+        DrawCommand::PushClip { x, y, width, height }
+            if (x, y, width, height) == (0.0, 0.0, 300.0, 150.0)
+    ));
+    assert!(matches!(commands[i + 1], DrawCommand::PopClip));
+    assert_eq!(commands.iter().filter(|c| is_marker(c)).count(), 1);
+
+    // iframe を知らなければ何も描かない
+    let commands = generate_draw_commands_with_selection(&layout, &info, None, None);
+    assert!(!commands.iter().any(is_marker));
+}
+
+#[test]
+fn hit_test_finds_the_iframe() {
+    let (layout, info) = page();
+
+    let hits = input::hit_test(&layout, &info, 20.0, 80.0);
+    let i = input::find_frame(&hits).expect("iframe hit");
+    assert_eq!(input::node_path(&hits, i), vec![1]);
+
+    let hits = input::hit_test(&layout, &info, 20.0, 20.0);
+    assert_eq!(input::find_frame(&hits), None);
+}
+
+#[test]
+fn frame_fetches_are_unwrapped_by_kind() {
+    let kind = FetchKind::Frame {
+        frame: 7,
+        kind: Box::new(FetchKind::Html),
+    };
+    assert_eq!(RequestMode::for_fetch(&kind), RequestMode::Navigate);
+    let (frame, kind) = kind.split_frame();
+    assert_eq!(frame, Some(7));
+    assert!(matches!(kind, FetchKind::Html));
+
+    let kind = FetchKind::Frame {
+        frame: 7,
+        kind: Box::new(FetchKind::ScriptRequest {
+            document: 7,
+            request: 1,
+        }),
+    };
+    assert_eq!(RequestMode::for_fetch(&kind), RequestMode::Cors);
+
+    let (frame, kind) = FetchKind::Css.split_frame();
+    assert_eq!(frame, None);
+    assert!(matches!(kind, FetchKind::Css));
+}
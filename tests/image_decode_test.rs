@@ -0,0 +1,110 @@
+use std::io::Cursor;
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+
+use image::{ImageFormat, Rgba, RgbaImage};
+use orinium_browser::browser::{BrowserApp, BrowserCommand, BrowserEvent, Tab};
+use orinium_browser::platform::decode::{DecodePool, Decoded};
+use orinium_browser::platform::network::StoragePartition;
+use url::Url;
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbaImage::from_pixel(width, height, Rgba([255, 0, 0, 255]));
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+#[test]
+fn images_are_decoded_on_worker_threads() {
+    let (tx, results) = mpsc::channel();
+    let caller = std::thread::current().id();
+    let pool = DecodePool::with_workers(2, move |decoded| {
+        let _ = tx.send((std::thread::current().id(), decoded));
+    });
+    let good: Url = "https://example.com/a.png".parse().unwrap();
+    let bad: Url = "https://example.com/b.png".parse().unwrap();
+    pool.decode_image(good.clone(), StoragePartition::Default, png(3, 2));
+    pool.decode_image(
+        bad.clone(),
+        StoragePartition::Private,
+        b"not an image".to_vec(),
+    );
+
+    for _ in 0..2 {
+        let (thread, decoded) = results.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_ne!(thread, caller);
+        let Decoded::Image {
+            url,
+            partition,
+            image,
+        } = decoded;
+        if url == good {
+            assert_eq!(partition, StoragePartition::Default);
+            assert_eq!(image.unwrap().dimensions(), (3, 2));
+        } else {
+            assert_eq!(url, bad);
+            assert_eq!(partition, StoragePartition::Private);
+            assert!(image.is_err());
+        }
+    }
+}
+
+#[test]
+fn decoded_images_are_kept_and_trigger_a_redraw() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    let (tx, events) = mpsc::channel();
+    browser.set_event_sink(Box::new(tx));
+    browser.add_tab(Tab::new());
+    let url: Url = "https://example.com/photo.jpg".parse().unwrap();
+
+    browser
+        .command_sender()
+        .send(BrowserCommand::Decoded(Decoded::Image {
+            url: url.clone(),
+            partition: StoragePartition::Default,
+            image: Ok(Arc::new(RgbaImage::new(4, 4))),
+        }));
+    browser.process_commands();
+
+    let image = browser.decoded_image(StoragePartition::Default, &url);
+    assert_eq!(image.unwrap().dimensions(), (4, 4));
+    assert!(events.try_iter().any(|e| e == BrowserEvent::NeedsRedraw));
+}
+
+#[test]
+fn private_images_are_kept_apart_and_dropped_with_the_last_private_tab() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    browser.add_tab(Tab::new());
+    browser.add_tab(Tab::new_private());
+    let url: Url = "https://example.com/photo.jpg".parse().unwrap();
+
+    browser
+        .command_sender()
+        .send(BrowserCommand::Decoded(Decoded::Image {
+            url: url.clone(),
+            partition: StoragePartition::Private,
+            image: Ok(Arc::new(RgbaImage::new(4, 4))),
+        }));
+    browser.process_commands();
+
+    assert!(
+        browser
+            .decoded_image(StoragePartition::Private, &url)
+            .is_some()
+    );
+    assert!(
+        browser
+            .decoded_image(StoragePartition::Default, &url)
+            .is_none()
+    );
+
+    browser.close_tab(1);
+    assert!(
+        browser
+            .decoded_image(StoragePartition::Private, &url)
+            .is_none()
+    );
+}
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use image::RgbaImage;
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter;
+use orinium_browser::engine::layouter::css_resolver::CssResolver;
+use orinium_browser::engine::layouter::types::TextStyle;
+use orinium_browser::engine::renderer_model::{DrawCommand, generate_draw_commands};
+
+/// `body` をレイアウトし、src が "photo.png" の画像に 10×5 の画像を付けて描画コマンドにする
+fn draw(body: &str) -> Vec<DrawCommand> {
+    let sheet = CssParser::new("").parse().unwrap();
+    let styles = CssResolver::resolve_with_media(&sheet, &MediaContext::default());
+    let dom = HtmlParser::new(&format!("<html><body>{body}</body></html>")).parse();
+    let (mut layout, mut info) = layouter::build_layout_and_info(
+        &dom.root,
+        &styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    let image = Arc::new(RgbaImage::new(10, 5));
+    layouter::attach_images(&mut layout, &mut info, &|src| {
+        (src == "photo.png").then(|| Arc::clone(&image))
+    });
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+    generate_draw_commands(&layout, &info)
+}
+
+fn image_sizes(commands: &[DrawCommand]) -> Vec<(f32, f32)> {
+    commands
+        .iter()
+        .filter_map(|c| match c {
+            DrawCommand::DrawImage { width, height, .. } => Some((*width, *height)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn img_takes_the_size_of_its_image() {
+    let commands = draw("<img src=\"photo.png\">");
+    assert_eq!(image_sizes(&commands), vec![(10.0, 5.0)]);
+}
+
+#[test]
+fn img_keeps_the_aspect_ratio_when_one_side_is_given() {
+    let commands = draw("<img src=\"photo.png\" width=\"40\">");
+    assert_eq!(image_sizes(&commands), vec![(40.0, 20.0)]);
+
+    let commands = draw("<img src=\"photo.png\" height=\"10\">");
+    assert_eq!(image_sizes(&commands), vec![(20.0, 10.0)]);
+}
+
+#[test]
+fn img_without_a_decoded_image_is_not_drawn() {
+    let commands = draw("<img src=\"missing.png\" width=\"40\" height=\"20\">");
+    assert!(image_sizes(&commands).is_empty());
+}
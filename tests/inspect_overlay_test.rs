@@ -0,0 +1,71 @@
+use orinium_browser::browser::core::devtools::BoxModel;
+use orinium_browser::browser::core::ui::inspect_overlay;
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::renderer_model::DrawCommand;
+
+fn model() -> BoxModel {
+    // border 2px、padding 8px、margin 上下 10px 左右 0
+    BoxModel::new(
+        "p#intro.note".to_string(),
+        (20.0, 30.0, 200.0, 60.0),
+        (22.0, 32.0, 196.0, 56.0),
+        (30.0, 40.0, 180.0, 40.0),
+        [10.0, 0.0, 10.0, 0.0],
+    )
+}
+
+fn rects(commands: &[DrawCommand]) -> Vec<(f32, f32, f32, f32)> {
+    commands
+        .iter()
+        .filter_map(|c| match c {
+            DrawCommand::DrawRect {
+                x,
+                y,
+                width,
+                height,
+                ..
+            } => Some((*x, *y, *width, *height)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn tooltip_y(commands: &[DrawCommand]) -> f32 {
+    commands
+        .iter()
+        .find_map(|c| match c {
+            DrawCommand::DrawText { y, .. } => Some(*y),
+            _ => None,
+        })
+        .expect("tooltip text")
+}
+
+#[test]
+fn margin_box_surrounds_the_border_box() {
+    assert_eq!(model().margin, (20.0, 20.0, 200.0, 80.0));
+    assert_eq!(
+        inspect_overlay::tooltip_text(&model()),
+        "p#intro.note  200 × 60  at (20, 30)"
+    );
+}
+
+#[test]
+fn each_box_is_painted_only_outside_the_inner_one() {
+    let commands = inspect_overlay::draw_commands(&model(), (800.0, 600.0), &FallbackTextMeasurer);
+    let rects = rects(&commands);
+
+    // margin は上下だけ、border と padding は 4 辺、content は 1 つ、最後に札の背景
+    assert_eq!(rects.len(), 2 + 4 + 4 + 1 + 1);
+    assert_eq!(rects[0], (20.0, 20.0, 200.0, 10.0));
+    assert_eq!(rects[1], (20.0, 90.0, 200.0, 10.0));
+    assert_eq!(rects[10], (30.0, 40.0, 180.0, 40.0));
+}
+
+#[test]
+fn tooltip_moves_above_the_element_near_the_bottom() {
+    let below = inspect_overlay::draw_commands(&model(), (800.0, 600.0), &FallbackTextMeasurer);
+    assert!(tooltip_y(&below) > 100.0);
+
+    let above = inspect_overlay::draw_commands(&model(), (800.0, 110.0), &FallbackTextMeasurer);
+    assert!(tooltip_y(&above) < 20.0);
+}
@@ -0,0 +1,57 @@
+use orinium_browser::browser::Settings;
+use orinium_browser::browser::core::browsing_history::BrowsingHistory;
+use orinium_browser::browser::core::internal_pages::{self, InternalPageContext};
+
+fn load(url: &str) -> anyhow::Result<String> {
+    let history = BrowsingHistory::new();
+    let settings = Settings::default();
+    let ctx = InternalPageContext {
+        history: &history,
+        settings: &settings,
+        inspection: None,
+        console: None,
+    };
+    internal_pages::load(&url.parse().unwrap(), &ctx)
+}
+
+#[test]
+fn about_and_orinium_urls_are_internal() {
+    assert!(internal_pages::is_internal(&"about:blank".parse().unwrap()));
+    assert!(internal_pages::is_internal(
+        &"orinium://version".parse().unwrap()
+    ));
+    assert!(!internal_pages::is_internal(
+        &"https://example.com/".parse().unwrap()
+    ));
+}
+
+#[test]
+fn built_in_pages_are_generated() {
+    assert!(load("about:blank").unwrap().contains("<body></body>"));
+    assert!(
+        load("orinium://version")
+            .unwrap()
+            .contains(env!("CARGO_PKG_VERSION"))
+    );
+    assert!(
+        load("orinium://flags")
+            .unwrap()
+            .contains("ORINIUM_PROFILE_DIR")
+    );
+    assert!(
+        load("orinium://inspect")
+            .unwrap()
+            .contains("No element selected")
+    );
+    // about: の後に名前を書いても同じページになる
+    assert_eq!(
+        load("about:flags").unwrap(),
+        load("orinium://flags").unwrap()
+    );
+}
+
+#[test]
+fn unknown_pages_are_errors() {
+    assert!(load("orinium://nowhere").is_err());
+    assert!(load("about:nowhere").is_err());
+}
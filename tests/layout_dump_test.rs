@@ -0,0 +1,168 @@
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter::css_resolver::{CssResolver, ResolvedStyles};
+use orinium_browser::engine::layouter::dump::{dump_layout, dump_styles};
+use orinium_browser::engine::layouter::{self, types::TextStyle};
+
+const HTML: &str =
+    r#"<html><body><p class="note">Hi <a href="/next">next</a></p><!-- c --></body></html>"#;
+
+const CSS: &str = "p.note { color: #ff0000; font-size: 12px } body { padding-left: 8px }";
+
+fn styles() -> ResolvedStyles {
+    let sheet = CssParser::new(CSS).parse().unwrap();
+    CssResolver::resolve_with_media(&sheet, &MediaContext::default())
+}
+
+#[test]
+fn styles_are_dumped_per_element() {
+    let dom = HtmlParser::new(HTML).parse();
+    assert_eq!(
+        dump_styles(&dom.root, &styles()),
+        "#document\n\
+         └── html\n\
+         \x20   └── body { padding-left: 8px }\n\
+         \x20       └── p.note { color: #ff0000; font-size: 12px }\n\
+         \x20           └── a\n"
+    );
+}
+
+#[test]
+fn layout_dump_shows_boxes_and_text_styles() {
+    let dom = HtmlParser::new(HTML).parse();
+    let (mut layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &styles(),
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+
+    let dump = dump_layout(&layout, &info);
+    let lines: Vec<&str> = dump.lines().collect();
+    assert!(lines[0].starts_with("box [0,0 800x"), "{dump}");
+
+    let link = lines
+        .iter()
+        .find(|line| line.contains(r#"link href="/next""#))
+        .expect(&dump);
+    assert!(link.contains("── link"), "{dump}");
+
+    // 文字の行には、初期値と違うスタイルだけが並ぶ
+    let text = lines
+        .iter()
+        .find(|line| line.contains(r#"text "next""#))
+        .expect(&dump);
+    assert!(text.contains(" 12px #ff0000"), "{dump}");
+    assert!(!text.contains("bold"), "{dump}");
+}
+
+#[test]
+fn long_text_is_cut_off() {
+    let html = format!("<p>{}</p>", "あ".repeat(100));
+    let dom = HtmlParser::new(&html).parse();
+    let (layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &ResolvedStyles::default(),
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+
+    let dump = dump_layout(&layout, &info);
+    let expected = format!("text \"{}…\"", "あ".repeat(40));
+    assert!(dump.contains(&expected), "{dump}");
+}
+
+fn text_lines(html: &str, css: &str) -> Vec<String> {
+    let sheet = CssParser::new(css).parse().unwrap();
+    let styles = CssResolver::resolve_with_media(&sheet, &MediaContext::default());
+    let dom = HtmlParser::new(html).parse();
+    let (mut layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+    dump_layout(&layout, &info)
+        .lines()
+        .filter(|line| line.contains("text \""))
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn whitespace_follows_the_white_space_property() {
+    let html = "<html><body><pre>a  b\n\tc</pre><p>a  b\n\tc</p></body></html>";
+    let lines = text_lines(html, "pre { white-space: pre }");
+
+    // pre では空白も改行も残り、タブは次の止まりまで広がる
+    assert!(lines[0].contains(r#"text "a  b\n        c""#), "{lines:?}");
+    assert!(lines[0].contains("white-space=pre lines=2"), "{lines:?}");
+    // ほかはまとめて 1 行になる
+    assert!(lines[1].contains(r#"text "a b c""#), "{lines:?}");
+}
+
+#[test]
+fn pre_line_keeps_only_line_breaks() {
+    let html = "<html><body><p>a   b \n  c</p></body></html>";
+    let lines = text_lines(html, "p { white-space: pre-line }");
+
+    assert!(lines[0].contains(r#"text "a b\nc""#), "{lines:?}");
+}
+
+#[test]
+fn closed_details_show_only_their_summary() {
+    let closed =
+        "<html><body><details><summary>More</summary><p>Hidden</p></details></body></html>";
+    let lines = text_lines(closed, "");
+    assert_eq!(lines.len(), 1, "{lines:?}");
+    assert!(lines[0].contains(r#"text "More""#), "{lines:?}");
+
+    let open =
+        "<html><body><details open><summary>More</summary><p>Shown</p></details></body></html>";
+    let lines = text_lines(open, "");
+    assert_eq!(lines.len(), 2, "{lines:?}");
+    assert!(lines[1].contains(r#"text "Shown""#), "{lines:?}");
+}
+
+#[test]
+fn subscripts_are_shifted_below_the_line() {
+    let html = "<html><body><p>H<sub>2</sub>O</p></body></html>";
+    let lines = text_lines(html, "sub { vertical-align: sub; font-size: 10px }");
+
+    let top = |line: &str| -> f32 {
+        let rect = line.split(" [").nth(1).expect(line);
+        let y = rect.split([',', ' ']).nth(1).expect(line);
+        y.parse().unwrap()
+    };
+    assert!(lines[1].contains(r#"text "2""#), "{lines:?}");
+    assert!(lines[1].contains(" sub"), "{lines:?}");
+    assert!(top(&lines[1]) > top(&lines[0]), "{lines:?}");
+}
@@ -0,0 +1,34 @@
+use orinium_browser::browser::core::progress::LoadProgress;
+
+#[test]
+fn progress_counts_bytes_and_finished_resources() {
+    let mut progress = LoadProgress::new();
+    progress.request_started();
+    assert_eq!(progress.fraction(), 0.0);
+
+    // HTML の半分を受信
+    progress.bytes_received(1, 500, Some(1000));
+    assert_eq!(progress.fraction(), 0.25);
+
+    progress.request_finished(1);
+    assert_eq!(progress.resource_counts(), (1, 1));
+    // まだ CSS が来るかもしれないので 1.0 にはしない
+    assert_eq!(progress.fraction(), 0.5);
+}
+
+#[test]
+fn progress_never_goes_backwards() {
+    let mut progress = LoadProgress::new();
+    progress.request_started();
+    progress.request_finished(1);
+    let before = progress.fraction();
+
+    // HTML を読んでから CSS が 2 つ見つかった
+    progress.request_started();
+    progress.request_started();
+    assert_eq!(progress.fraction(), before);
+    assert_eq!(progress.resource_counts(), (1, 3));
+
+    progress.reset();
+    assert_eq!(progress.fraction(), 0.0);
+}
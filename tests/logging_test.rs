@@ -0,0 +1,114 @@
+use log::{Level, LevelFilter, Log, Record};
+use orinium_browser::browser::Settings;
+use orinium_browser::browser::core::internal_pages;
+use orinium_browser::platform::logging::{LevelFilters, Logger};
+
+fn log(logger: &Logger, level: Level, target: &str, message: &str) {
+    logger.log(
+        &Record::builder()
+            .level(level)
+            .target(target)
+            .args(format_args!("{message}"))
+            .build(),
+    );
+}
+
+#[test]
+fn longest_matching_subsystem_decides_the_level() {
+    let filters = LevelFilters::parse(
+        "warn, orinium_browser::platform::network=debug,platform=error,console",
+    )
+    .unwrap();
+
+    assert_eq!(filters.level_for("browser::core::app"), LevelFilter::Warn);
+    assert_eq!(filters.level_for("platform::renderer"), LevelFilter::Error);
+    assert_eq!(
+        filters.level_for("platform::network::cache"),
+        LevelFilter::Debug
+    );
+    // 名前の途中では一致させない
+    assert_eq!(
+        filters.level_for("platform::networking"),
+        LevelFilter::Error
+    );
+    assert_eq!(filters.level_for("console"), LevelFilter::Trace);
+    assert_eq!(filters.max_level(), LevelFilter::Trace);
+
+    assert_eq!(LevelFilters::parse(&filters.to_string()).unwrap(), filters);
+    assert!(LevelFilters::parse("platform=loud").is_err());
+}
+
+#[test]
+fn logger_keeps_the_latest_messages_that_pass_the_filter() {
+    let logger = Logger::new(2, false);
+    logger.set_filters(LevelFilters::parse("warn,platform::network=debug").unwrap());
+
+    log(
+        &logger,
+        Level::Info,
+        "orinium_browser::browser::core::app",
+        "hidden",
+    );
+    log(
+        &logger,
+        Level::Debug,
+        "orinium_browser::platform::network",
+        "first",
+    );
+    log(&logger, Level::Warn, "console", "second");
+    log(
+        &logger,
+        Level::Error,
+        "orinium_browser::browser::core::app",
+        "third",
+    );
+
+    let records = logger.records();
+    let messages: Vec<&str> = records.iter().map(|r| r.message.as_str()).collect();
+    assert_eq!(messages, ["second", "third"]);
+    assert_eq!(records[1].subsystem, "browser::core::app");
+
+    logger.clear();
+    assert!(logger.records().is_empty());
+}
+
+#[test]
+fn logger_appends_to_the_log_file() {
+    let path = std::env::temp_dir().join(format!("orinium-log-test-{}.log", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let logger = Logger::new(10, false);
+    logger.set_file(Some(&path)).unwrap();
+    assert_eq!(logger.file().as_deref(), Some(path.as_path()));
+
+    log(
+        &logger,
+        Level::Error,
+        "orinium_browser::platform::io",
+        "disk full",
+    );
+    logger.flush();
+    logger.set_file(None).unwrap();
+    log(
+        &logger,
+        Level::Error,
+        "orinium_browser::platform::io",
+        "not written",
+    );
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(text.contains("ERROR platform::io: disk full"));
+    assert!(!text.contains("not written"));
+}
+
+#[test]
+fn logs_page_changes_only_log_settings() {
+    let mut settings = Settings::default();
+    let url = "orinium://logs?level=debug&log.levels=info%2Cconsole%3Ddebug&homepage=https%3A%2F%2Fexample.com%2F"
+        .parse()
+        .unwrap();
+
+    assert!(internal_pages::update_settings(&url, &mut settings));
+    assert_eq!(settings.log_levels, "info,console=debug");
+    assert_eq!(settings.homepage, Settings::default().homepage);
+}
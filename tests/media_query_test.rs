@@ -0,0 +1,72 @@
+use orinium_browser::engine::css::media::{ColorScheme, MediaContext, MediaType};
+use orinium_browser::engine::css::parser::Parser;
+use orinium_browser::engine::css::values::CssValue;
+use orinium_browser::engine::layouter::css_resolver::CssResolver;
+
+/// scheme で解決したとき p に当たる color の値を順に返す
+fn colors(css: &str, scheme: ColorScheme) -> Vec<String> {
+    let media = MediaContext {
+        color_scheme: scheme,
+        ..Default::default()
+    };
+    colors_in(css, &media)
+}
+
+/// media で解決したとき p に当たる color の値を順に返す
+fn colors_in(css: &str, media: &MediaContext) -> Vec<String> {
+    let stylesheet = Parser::new(css).parse().expect("parse");
+    CssResolver::resolve_with_media(&stylesheet, media)
+        .into_iter()
+        .filter(|d| d.name == "color")
+        .filter_map(|d| match d.value {
+            CssValue::Keyword(k) => Some(k),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn prefers_color_scheme_selects_rules() {
+    let css = r#"
+        p { color: black; }
+        @media (prefers-color-scheme: dark) { p { color: white; } }
+        @media screen and (prefers-color-scheme: light) { p { color: navy; } }
+    "#;
+
+    assert_eq!(colors(css, ColorScheme::Light), ["black", "navy"]);
+    assert_eq!(colors(css, ColorScheme::Dark), ["black", "white"]);
+}
+
+#[test]
+fn media_query_lists_and_negation() {
+    let css = r#"
+        @media print, (prefers-color-scheme: dark) { p { color: white; } }
+        @media not print { p { color: gray; } }
+        @media print { p { color: red; } }
+    "#;
+
+    assert_eq!(colors(css, ColorScheme::Light), ["gray"]);
+    assert_eq!(colors(css, ColorScheme::Dark), ["white", "gray"]);
+}
+
+#[test]
+fn print_rules_apply_when_printing() {
+    let css = r#"
+        @media screen { p { color: navy; } }
+        @media print { p { color: black; } }
+        @media all and (prefers-color-scheme: light) { p { color: gray; } }
+    "#;
+    let print = MediaContext {
+        media_type: MediaType::Print,
+        ..Default::default()
+    };
+
+    assert_eq!(colors_in(css, &MediaContext::default()), ["navy", "gray"]);
+    assert_eq!(colors_in(css, &print), ["black", "gray"]);
+}
+
+#[test]
+fn unknown_media_features_still_apply() {
+    let css = "@media (max-width: 600px) { p { color: green; } }";
+    assert_eq!(colors(css, ColorScheme::Light), ["green"]);
+}
@@ -0,0 +1,109 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use orinium_browser::browser::{BrowserApp, Tab};
+use orinium_browser::platform::memory::{
+    self, MemoryPressure, MemoryRegistry, Subsystem, TrackedMemory,
+};
+use orinium_browser::platform::network::Cache;
+use orinium_browser::platform::network::cache::{CacheKey, CacheLookup};
+use url::Url;
+
+#[test]
+fn budget_is_enforced_from_the_largest_consumer() {
+    let registry = MemoryRegistry::new(1000);
+    let glyphs = TrackedMemory::evictable(Subsystem::Glyphs);
+    let images = TrackedMemory::evictable(Subsystem::DecodedImages);
+    let dom = TrackedMemory::new(Subsystem::Dom);
+    registry.register(&glyphs);
+    registry.register(&images);
+    registry.register(&dom);
+    glyphs.set(600);
+    images.set(200);
+    dom.set(300);
+
+    let usage = registry.usage();
+    assert_eq!(usage.of(Subsystem::Glyphs), 600);
+    assert_eq!(usage.total(), 1100);
+
+    // 大きいグリフの半分を捨てれば収まる。DOM は数えるだけで捨てない
+    assert_eq!(registry.enforce_budget(), 300);
+    assert_eq!(
+        glyphs.take_eviction_request(),
+        Some(MemoryPressure::Moderate)
+    );
+    assert_eq!(images.take_eviction_request(), None);
+    assert_eq!(glyphs.take_eviction_request(), None);
+
+    // 捨てたものは集計から外れる
+    drop(glyphs);
+    assert_eq!(registry.usage().total(), 500);
+    assert_eq!(registry.enforce_budget(), 0);
+}
+
+#[test]
+fn pressure_follows_available_memory() {
+    let meminfo = |available: u64| {
+        format!(
+            "MemTotal:       1000000 kB\nMemFree:          10000 kB\nMemAvailable:   {available} kB\n"
+        )
+    };
+    assert_eq!(memory::pressure_from_meminfo(&meminfo(500_000)), None);
+    assert_eq!(
+        memory::pressure_from_meminfo(&meminfo(80_000)),
+        Some(MemoryPressure::Moderate)
+    );
+    assert_eq!(
+        memory::pressure_from_meminfo(&meminfo(30_000)),
+        Some(MemoryPressure::Critical)
+    );
+    assert_eq!(memory::pressure_from_meminfo("MemTotal: 1000 kB\n"), None);
+}
+
+#[test]
+fn http_cache_drops_stale_entries_under_pressure() {
+    let cache = Cache::new();
+    let key = |s: &str| CacheKey::new(&Url::parse(s).unwrap(), None);
+    let headers = vec![("Cache-Control".to_string(), "max-age=3600".to_string())];
+    let now = SystemTime::now();
+    let old = UNIX_EPOCH + Duration::from_secs(1000);
+    cache.store(
+        &key("https://example.com/new.css"),
+        vec![0; 100],
+        headers.clone(),
+        now,
+    );
+    cache.store(
+        &key("https://example.com/old.css"),
+        vec![0; 100],
+        headers,
+        old,
+    );
+    let usage = cache.memory_usage();
+    assert!(usage > 200);
+
+    memory::registry().relieve(MemoryPressure::Moderate);
+    assert!(cache.memory_usage() < usage);
+    assert!(matches!(
+        cache.lookup(&key("https://example.com/new.css"), now),
+        CacheLookup::Fresh(_)
+    ));
+    assert_eq!(
+        cache.lookup(&key("https://example.com/old.css"), now),
+        CacheLookup::Miss
+    );
+
+    memory::registry().relieve(MemoryPressure::Critical);
+    assert_eq!(cache.memory_usage(), 0);
+}
+
+#[test]
+fn open_pages_are_counted_as_dom() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    browser.open_in_new_tab("data:text/html,<p>hello</p><p>world</p>".parse().unwrap());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !browser.active_tab().is_some_and(Tab::is_loaded) && Instant::now() < deadline {
+        browser.tick();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert!(browser.memory_usage().of(Subsystem::Dom) > 0);
+}
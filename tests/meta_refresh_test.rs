@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use orinium_browser::browser::core::webview::refresh::{self, MetaRefresh};
+use orinium_browser::engine::html::parser::Parser;
+
+fn refresh(seconds: u64, url: Option<&str>) -> Option<MetaRefresh> {
+    Some(MetaRefresh {
+        delay: Duration::from_secs(seconds),
+        url: url.map(str::to_string),
+    })
+}
+
+#[test]
+fn content_is_a_delay_and_an_optional_url() {
+    assert_eq!(refresh::parse("5"), refresh(5, None));
+    assert_eq!(refresh::parse(" 0; url=/next"), refresh(0, Some("/next")));
+    assert_eq!(
+        refresh::parse("3, URL = 'page.html?a=1' ignored"),
+        refresh(3, Some("page.html?a=1"))
+    );
+    assert_eq!(
+        refresh::parse("1.5 https://example.com/"),
+        refresh(1, Some("https://example.com/"))
+    );
+    assert_eq!(refresh::parse("2;"), refresh(2, None));
+    assert_eq!(refresh::parse("0;url=\"\""), refresh(0, None));
+
+    assert_eq!(refresh::parse(""), None);
+    assert_eq!(refresh::parse("soon"), None);
+    assert_eq!(refresh::parse("5abc"), None);
+}
+
+#[test]
+fn first_refresh_meta_in_the_document_is_used() {
+    let dom = Parser::new(
+        r#"<html><head>
+<meta charset="utf-8">
+<meta http-equiv="Refresh" content="invalid">
+<meta http-equiv="refresh" content="10; url=later.html">
+<meta http-equiv="refresh" content="0">
+</head><body></body></html>"#,
+    )
+    .parse();
+    assert_eq!(refresh::find(&dom), refresh(10, Some("later.html")));
+
+    let dom = Parser::new("<html><head><title>x</title></head></html>").parse();
+    assert_eq!(refresh::find(&dom), None);
+}
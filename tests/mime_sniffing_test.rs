@@ -0,0 +1,114 @@
+use orinium_browser::browser::core::mime::{self, MimeType, Presentation};
+use url::Url;
+
+fn headers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|(n, v)| (n.to_string(), v.to_string()))
+        .collect()
+}
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+#[test]
+fn content_type_header_is_parsed() {
+    let mime = MimeType::parse("Text/HTML; charset=\"Shift_JIS\"").unwrap();
+    assert_eq!(mime.essence, "text/html");
+    assert_eq!(mime.charset.as_deref(), Some("Shift_JIS"));
+    assert!(MimeType::parse("nonsense").is_none());
+}
+
+#[test]
+fn missing_types_come_from_the_extension_then_the_bytes() {
+    let none = headers(&[]);
+    assert_eq!(
+        mime::content_type(&none, &url("https://a.example/notes.txt"), b"<p>hi").essence,
+        "text/plain"
+    );
+    assert_eq!(
+        mime::content_type(&none, &url("https://a.example/page"), b"  <!doctype html>").essence,
+        "text/html"
+    );
+    assert_eq!(
+        mime::content_type(&none, &url("https://a.example/x"), b"\x89PNG\r\n\x1a\n....").essence,
+        "image/png"
+    );
+    assert_eq!(
+        mime::content_type(&none, &url("https://a.example/x"), b"\x00\x01binary").essence,
+        "application/octet-stream"
+    );
+}
+
+#[test]
+fn declared_types_are_corrected_unless_nosniff() {
+    let gif = b"GIF89a......";
+    let declared = headers(&[("Content-Type", "image/png")]);
+    assert_eq!(
+        mime::content_type(&declared, &url("https://a.example/i"), gif).essence,
+        "image/gif"
+    );
+
+    let binary = b"\x00\x00\x00 not text";
+    let plain = headers(&[("content-type", "text/plain")]);
+    assert_eq!(
+        mime::content_type(&plain, &url("https://a.example/f"), binary).essence,
+        "application/octet-stream"
+    );
+    let nosniff = headers(&[
+        ("content-type", "text/plain"),
+        ("x-content-type-options", "nosniff"),
+    ]);
+    assert_eq!(
+        mime::content_type(&nosniff, &url("https://a.example/f"), binary).essence,
+        "text/plain"
+    );
+}
+
+#[test]
+fn presentation_follows_the_type() {
+    let none = headers(&[]);
+    let present = |essence: &str| mime::presentation(&none, &MimeType::new(essence));
+    assert_eq!(present("text/html"), Presentation::Html);
+    assert_eq!(present("application/json"), Presentation::PlainText);
+    assert_eq!(present("image/jpeg"), Presentation::Image);
+    assert_eq!(present("application/zip"), Presentation::Download);
+
+    let attachment = headers(&[("Content-Disposition", "attachment; filename=\"a.txt\"")]);
+    assert_eq!(
+        mime::presentation(&attachment, &MimeType::new("text/plain")),
+        Presentation::Download
+    );
+    assert_eq!(
+        mime::download_file_name(&attachment, &url("https://a.example/dl")),
+        "a.txt"
+    );
+    assert_eq!(
+        mime::download_file_name(&none, &url("https://a.example/files/my%20file.zip")),
+        "my file.zip"
+    );
+}
+
+#[test]
+fn text_is_decoded_with_its_charset() {
+    // 「日本」を Shift_JIS で
+    let sjis = b"\x93\xfa\x96\x7b";
+    let mime = MimeType::parse("text/plain; charset=shift_jis").unwrap();
+    assert_eq!(mime::decode_text(sjis, &mime), "日本");
+
+    let mut html = b"<html><head><meta charset=\"euc-jp\"></head><body>".to_vec();
+    html.extend_from_slice(b"\xc6\xfc\xcb\xdc");
+    let decoded = mime::decode_text(&html, &MimeType::new("text/html"));
+    assert!(decoded.ends_with("<body>日本"));
+
+    // BOM は charset より強い
+    let bom = b"\xef\xbb\xbfok";
+    assert_eq!(
+        mime::decode_text(
+            bom,
+            &MimeType::parse("text/plain; charset=iso-8859-1").unwrap()
+        ),
+        "ok"
+    );
+}
@@ -0,0 +1,34 @@
+use orinium_browser::platform::network::CancellationToken;
+use std::time::Duration;
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn finished_future_is_not_cancelled() {
+    let token = CancellationToken::new();
+
+    let result = runtime().block_on(token.run_until_cancelled(async { 42 }));
+
+    assert_eq!(result, Some(42));
+}
+
+#[test]
+fn cancel_stops_pending_future() {
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        canceller.cancel();
+    });
+
+    let result =
+        runtime().block_on(token.run_until_cancelled(tokio::time::sleep(Duration::from_secs(10))));
+
+    assert!(result.is_none());
+    assert!(token.is_cancelled());
+}
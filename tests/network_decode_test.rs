@@ -0,0 +1,88 @@
+use flate2::Compression;
+use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+use orinium_browser::platform::network::NetworkError;
+use orinium_browser::platform::network::decode::decode_body;
+use std::io::Write;
+
+const HTML: &[u8] = b"<p>hello, brotli</p>";
+
+fn headers(encoding: &str) -> Vec<(String, String)> {
+    vec![
+        ("Content-Type".into(), "text/html".into()),
+        ("Content-Encoding".into(), encoding.into()),
+        ("Content-Length".into(), "24".into()),
+    ]
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn gzip_body_is_decoded_and_headers_are_removed() {
+    let mut headers = headers("gzip");
+    let body = decode_body(&mut headers, gzip(HTML)).unwrap();
+
+    assert_eq!(body, HTML);
+    assert_eq!(
+        headers,
+        vec![("Content-Type".to_string(), "text/html".to_string())]
+    );
+}
+
+#[test]
+fn deflate_accepts_zlib_and_raw_streams() {
+    let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+    zlib.write_all(HTML).unwrap();
+    let body = decode_body(&mut headers("deflate"), zlib.finish().unwrap()).unwrap();
+    assert_eq!(body, HTML);
+
+    let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+    raw.write_all(HTML).unwrap();
+    let body = decode_body(&mut headers("Deflate"), raw.finish().unwrap()).unwrap();
+    assert_eq!(body, HTML);
+}
+
+#[test]
+fn brotli_body_is_decoded() {
+    let compressed = vec![
+        139, 9, 128, 60, 112, 62, 104, 101, 108, 108, 111, 44, 32, 98, 114, 111, 116, 108, 105, 60,
+        47, 112, 62, 3,
+    ];
+    let body = decode_body(&mut headers("br"), compressed).unwrap();
+    assert_eq!(body, HTML);
+}
+
+#[test]
+fn multiple_encodings_are_undone_in_reverse_order() {
+    let twice = gzip(&gzip(HTML));
+    let body = decode_body(&mut headers("gzip, identity, gzip"), twice).unwrap();
+    assert_eq!(body, HTML);
+}
+
+#[test]
+fn unencoded_and_empty_bodies_are_left_alone() {
+    let mut plain = vec![("Content-Length".to_string(), "20".to_string())];
+    assert_eq!(decode_body(&mut plain, HTML.to_vec()).unwrap(), HTML);
+    assert_eq!(plain.len(), 1);
+
+    assert!(
+        decode_body(&mut headers("gzip"), Vec::new())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn unknown_or_corrupt_encodings_fail() {
+    assert!(matches!(
+        decode_body(&mut headers("zstd"), HTML.to_vec()),
+        Err(NetworkError::ContentDecodingFailed)
+    ));
+    assert!(matches!(
+        decode_body(&mut headers("gzip"), HTML.to_vec()),
+        Err(NetworkError::ContentDecodingFailed)
+    ));
+}
@@ -0,0 +1,189 @@
+use orinium_browser::platform::network::hints::{HintBudget, MAX_HINTS_IN_FLIGHT};
+use orinium_browser::platform::network::sender_pool::{ConnectionLimiter, HostKey};
+use orinium_browser::platform::network::{
+    ConnectionHint, NetworkConfig, NetworkCore, NetworkError, StoragePartition,
+};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+fn key(host: &str) -> HostKey {
+    HostKey {
+        scheme: hyper::http::uri::Scheme::HTTP,
+        host: host.to_string(),
+        port: 80,
+    }
+}
+
+/// リクエストのヘッダーを読み飛ばす
+fn read_head(stream: &TcpStream) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+}
+
+fn network(config: NetworkConfig) -> NetworkCore {
+    let network = NetworkCore::new();
+    network.set_network_config(config);
+    network
+}
+
+#[test]
+fn limiter_caps_connections_per_host_and_in_total() {
+    let limiter = ConnectionLimiter::new();
+    let a = key("a.example");
+    let b = key("b.example");
+
+    let first = limiter.try_acquire(&a, 2, 3).unwrap();
+    let _second = limiter.try_acquire(&a, 2, 3).unwrap();
+    assert!(limiter.try_acquire(&a, 2, 3).is_none());
+    assert_eq!(limiter.open_connections(&a), 2);
+
+    let _third = limiter.try_acquire(&b, 2, 3).unwrap();
+    // 全体の上限
+    assert!(limiter.try_acquire(&b, 2, 3).is_none());
+
+    // 閉じると枠が空く
+    drop(first);
+    assert_eq!(limiter.open_connections(&a), 1);
+    assert!(limiter.try_acquire(&b, 2, 3).is_some());
+}
+
+#[test]
+fn hints_are_bounded_and_one_per_origin() {
+    let budget = HintBudget::new();
+    let first = budget.try_start(&key("host0.example")).unwrap();
+    // 同じオリジンは進めている途中なら始めない
+    assert!(budget.try_start(&key("host0.example")).is_none());
+
+    let _rest: Vec<_> = (1..MAX_HINTS_IN_FLIGHT)
+        .map(|i| budget.try_start(&key(&format!("host{i}.example"))).unwrap())
+        .collect();
+    assert_eq!(budget.in_flight(), MAX_HINTS_IN_FLIGHT);
+    assert!(budget.try_start(&key("other.example")).is_none());
+
+    drop(first);
+    assert!(budget.try_start(&key("other.example")).is_some());
+}
+
+#[test]
+fn preconnected_connection_is_used_by_the_next_fetch() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let seen = connections.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            seen.fetch_add(1, Ordering::SeqCst);
+            std::thread::spawn(move || {
+                read_head(&stream);
+                write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+                std::thread::sleep(Duration::from_secs(1));
+            });
+        }
+    });
+
+    let network = network(NetworkConfig::default());
+    let origin = format!("http://127.0.0.1:{port}/");
+    network.hint(
+        origin.clone(),
+        ConnectionHint::Preconnect,
+        StoragePartition::Default,
+    );
+    // リクエストを出す前に繋がる
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while connections.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+    let response = network.fetch_blocking(&format!("{origin}a")).unwrap();
+    assert_eq!(response.body, b"ok");
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn silent_server_times_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        // 受け付けるだけで何も返さない
+        let (stream, _) = listener.accept().unwrap();
+        read_head(&stream);
+        std::thread::sleep(Duration::from_secs(10));
+    });
+
+    let network = network(NetworkConfig {
+        read_timeout: Duration::from_millis(200),
+        max_retries: 0,
+        ..NetworkConfig::default()
+    });
+    let started = Instant::now();
+    let result = network.fetch_blocking(&format!("http://127.0.0.1:{port}/"));
+    assert!(matches!(result, Err(NetworkError::Timeout)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn dropped_connection_is_retried() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let seen = connections.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            read_head(&stream);
+            // 1 回目は応答せずに切る
+            if seen.fetch_add(1, Ordering::SeqCst) == 0 {
+                continue;
+            }
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").unwrap();
+        }
+    });
+
+    let network = network(NetworkConfig {
+        max_retries: 1,
+        retry_backoff: Duration::from_millis(10),
+        ..NetworkConfig::default()
+    });
+    let response = network
+        .fetch_blocking(&format!("http://127.0.0.1:{port}/"))
+        .unwrap();
+    assert_eq!(response.body, b"ok");
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn slow_body_hits_the_total_timeout() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        read_head(&stream);
+        write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n").unwrap();
+        // read_timeout には掛からない間隔で少しずつ送る
+        for _ in 0..100 {
+            if stream.write_all(b"x").is_err() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    });
+
+    let network = network(NetworkConfig {
+        read_timeout: Duration::from_secs(1),
+        total_timeout: Some(Duration::from_millis(300)),
+        max_retries: 0,
+        ..NetworkConfig::default()
+    });
+    let result = network.fetch_blocking(&format!("http://127.0.0.1:{port}/"));
+    assert!(matches!(result, Err(NetworkError::Timeout)));
+}
@@ -0,0 +1,148 @@
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use orinium_browser::platform::network::{NetworkCore, Response, StoragePartition};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+/// ヘッダーと、chunked で送るボディの断片を返すサーバー
+///
+/// gate を受け取るまで最後の断片と終わりのチャンクを送らない。
+fn chunked_server(headers: &'static str, parts: Vec<Vec<u8>>, gate: Receiver<()>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+            line.clear();
+        }
+
+        let head = format!("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n{headers}\r\n");
+        stream.write_all(head.as_bytes()).unwrap();
+        let last = parts.len() - 1;
+        for (i, part) in parts.into_iter().enumerate() {
+            if i == last {
+                let _ = gate.recv();
+            }
+            stream
+                .write_all(format!("{:x}\r\n", part.len()).as_bytes())
+                .unwrap();
+            stream.write_all(&part).unwrap();
+            stream.write_all(b"\r\n").unwrap();
+            stream.flush().unwrap();
+        }
+        stream.write_all(b"0\r\n\r\n").unwrap();
+    });
+
+    format!("http://{addr}/")
+}
+
+fn receive(network: &NetworkCore) -> Response {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if let Some(msg) = network.try_receive().into_iter().next() {
+            return msg.response.expect("fetch failed");
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("timed out waiting for the response");
+}
+
+/// 次のチャンクを待つ（終わりなら None）
+fn next_chunk(stream: &mut orinium_browser::platform::network::BodyStream) -> Option<Vec<u8>> {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if let Poll::Ready(chunk) = stream.try_next_chunk() {
+            return chunk.map(|c| c.to_vec());
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("timed out waiting for a chunk");
+}
+
+#[test]
+fn chunked_body_is_collected_by_a_normal_fetch() {
+    let (open, gate) = mpsc::channel();
+    open.send(()).unwrap();
+    let url = chunked_server("", vec![b"hello".to_vec(), b", world".to_vec()], gate);
+
+    let mut response = NetworkCore::new().fetch_blocking(&url).unwrap();
+    assert_eq!(response.body, b"hello, world");
+
+    // 受信し終えたボディも BodyStream として読める
+    let mut stream = response.body_stream();
+    assert_eq!(
+        next_chunk(&mut stream).as_deref(),
+        Some(&b"hello, world"[..])
+    );
+    assert_eq!(next_chunk(&mut stream), None);
+    assert!(stream.is_finished());
+}
+
+#[test]
+fn streaming_fetch_delivers_chunks_before_the_body_ends() {
+    let (open, gate) = mpsc::channel();
+    let url = chunked_server(
+        "Content-Type: text/plain\r\n",
+        vec![b"first ".to_vec(), b"second".to_vec()],
+        gate,
+    );
+    let network = NetworkCore::new();
+    network.fetch_streaming(url, 1, false, StoragePartition::Default, None);
+
+    let mut response = receive(&network);
+    assert!(response.status.is_success());
+    assert!(response.body.is_empty());
+    let mut stream = response.body_stream();
+
+    // 最後の断片はまだ送られていない
+    assert_eq!(next_chunk(&mut stream).as_deref(), Some(&b"first "[..]));
+    assert!(stream.try_next_chunk().is_pending());
+
+    open.send(()).unwrap();
+    assert_eq!(next_chunk(&mut stream).as_deref(), Some(&b"second"[..]));
+    assert_eq!(next_chunk(&mut stream), None);
+    assert!(stream.is_finished());
+    assert!(stream.error().is_none());
+}
+
+#[test]
+fn streamed_gzip_body_is_decoded_incrementally() {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(b"<p>streamed and compressed</p>")
+        .unwrap();
+    let compressed = encoder.finish().unwrap();
+    let (first, rest) = compressed.split_at(compressed.len() / 2);
+
+    let (open, gate) = mpsc::channel();
+    open.send(()).unwrap();
+    let url = chunked_server(
+        "Content-Encoding: gzip\r\n",
+        vec![first.to_vec(), rest.to_vec()],
+        gate,
+    );
+    let network = NetworkCore::new();
+    network.fetch_streaming(url, 1, false, StoragePartition::Default, None);
+
+    let mut response = receive(&network);
+    assert!(
+        !response
+            .headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case("content-encoding"))
+    );
+    let mut stream = response.body_stream();
+    let mut body = Vec::new();
+    while let Some(chunk) = next_chunk(&mut stream) {
+        body.extend(chunk);
+    }
+    assert_eq!(body, b"<p>streamed and compressed</p>");
+    assert!(stream.is_finished());
+}
@@ -0,0 +1,76 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::platform::notifications::{
+    MemoryNotificationBackend, Notification, NotificationCenter, NotificationCommand,
+    NotificationEvent, NotificationResult,
+};
+
+fn notification(title: &str, tag: &str, origin: &str) -> Notification {
+    Notification {
+        title: title.to_string(),
+        body: String::new(),
+        tag: tag.to_string(),
+        origin: origin.to_string(),
+    }
+}
+
+/// 出来事が count 個届くまで待つ
+fn wait_for(center: &NotificationCenter, count: usize) -> Vec<NotificationResult> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut events = Vec::new();
+    while events.len() < count && Instant::now() < deadline {
+        events.extend(center.poll());
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    events
+}
+
+#[test]
+fn notifications_are_shown_and_closed() {
+    let center = NotificationCenter::new(Box::new(MemoryNotificationBackend::new()));
+    center.send(NotificationCommand::Show(
+        1,
+        notification("New message", "", "https://example.com"),
+    ));
+    assert_eq!(wait_for(&center, 1), vec![(1, NotificationEvent::Shown)]);
+
+    center.send(NotificationCommand::Close(1));
+    assert_eq!(wait_for(&center, 1), vec![(1, NotificationEvent::Closed)]);
+
+    // 閉じたものをもう一度閉じても何も起きない
+    center.send(NotificationCommand::Close(1));
+    center.send(NotificationCommand::Show(
+        2,
+        notification("Another", "", "https://example.com"),
+    ));
+    assert_eq!(wait_for(&center, 1), vec![(2, NotificationEvent::Shown)]);
+}
+
+#[test]
+fn same_tag_replaces_the_previous_notification() {
+    let center = NotificationCenter::new(Box::new(MemoryNotificationBackend::new()));
+    center.send(NotificationCommand::Show(
+        1,
+        notification("1 new message", "inbox", "https://example.com"),
+    ));
+    center.send(NotificationCommand::Show(
+        2,
+        notification("2 new messages", "inbox", "https://example.com"),
+    ));
+    // 別のオリジンの同じタグは置き換えない
+    center.send(NotificationCommand::Show(
+        3,
+        notification("Reminder", "inbox", "https://example.org"),
+    ));
+
+    assert_eq!(
+        wait_for(&center, 4),
+        vec![
+            (1, NotificationEvent::Shown),
+            (1, NotificationEvent::Closed),
+            (2, NotificationEvent::Shown),
+            (3, NotificationEvent::Shown),
+        ]
+    );
+    assert!(center.poll().is_empty());
+}
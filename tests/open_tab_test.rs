@@ -0,0 +1,64 @@
+use orinium_browser::browser::{BrowserApp, BrowserCommand, Tab};
+
+#[test]
+fn links_open_in_new_tabs() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    let mut tab = Tab::new();
+    tab.navigate("https://example.com/".parse().unwrap());
+    browser.add_tab(tab);
+
+    // 裏で開いたタブには移らない
+    browser.execute(BrowserCommand::OpenTab {
+        url: "https://example.org/".parse().unwrap(),
+        background: true,
+        referrer: Some("https://example.com/".parse().unwrap()),
+        opener: None,
+    });
+    assert_eq!(browser.current_session().tabs.len(), 2);
+    assert!(browser.window_title().starts_with("https://example.com/"));
+
+    browser.execute(BrowserCommand::OpenTab {
+        url: "https://example.net/".parse().unwrap(),
+        background: false,
+        referrer: None,
+        opener: None,
+    });
+    assert_eq!(browser.current_session().tabs.len(), 3);
+    assert!(browser.window_title().starts_with("https://example.net/"));
+}
+
+#[test]
+fn tabs_opened_from_a_private_tab_are_private() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    browser.add_tab(Tab::new_private());
+    browser.switch_tab(0);
+
+    browser.execute(BrowserCommand::OpenTab {
+        url: "https://example.org/".parse().unwrap(),
+        background: false,
+        referrer: None,
+        opener: None,
+    });
+    assert!(browser.current_session().tabs.is_empty());
+    assert!(
+        browser
+            .window_title()
+            .ends_with("Orinium Browser (Private)")
+    );
+}
+
+#[test]
+fn referrer_belongs_to_the_navigation() {
+    let page: url::Url = "https://example.com/".parse().unwrap();
+    let mut tab = Tab::new();
+    tab.navigate_from("https://example.org/".parse().unwrap(), Some(page.clone()));
+    assert_eq!(tab.referrer(), Some(&page));
+
+    // URL バーからの移動と、戻る / 進むでは送らない
+    tab.navigate("https://example.net/".parse().unwrap());
+    assert_eq!(tab.referrer(), None);
+    tab.navigate_from("https://example.net/a".parse().unwrap(), Some(page));
+    tab.go_back();
+    assert_eq!(tab.referrer(), None);
+    assert_eq!(tab.opener(), None);
+}
@@ -0,0 +1,52 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::browser::core::webview::metrics::PageLoadMetrics;
+
+#[test]
+fn stages_are_measured_from_the_navigation_start() {
+    let start = Instant::now();
+    let mut metrics = PageLoadMetrics::new(start);
+
+    metrics.record_fetch(start + Duration::from_millis(120));
+    // 2 回目の HTML（<iframe> の文書など）は数えない
+    metrics.record_fetch(start + Duration::from_millis(500));
+    metrics.add_parse(Duration::from_millis(4));
+    metrics.add_style(Duration::from_millis(2));
+    metrics.add_style(Duration::from_millis(3));
+    metrics.add_layout(Duration::from_millis(7));
+
+    assert_eq!(metrics.fetch, Some(Duration::from_millis(120)));
+    assert_eq!(metrics.parse, Duration::from_millis(4));
+    assert_eq!(metrics.style, Duration::from_millis(5));
+    assert!(!metrics.is_complete());
+}
+
+#[test]
+fn nothing_is_added_after_load_and_first_paint() {
+    let start = Instant::now();
+    let mut metrics = PageLoadMetrics::new(start);
+
+    metrics.add_layout(Duration::from_millis(7));
+    metrics.record_first_paint(start + Duration::from_millis(150));
+    metrics.record_load(start + Duration::from_millis(200));
+    assert!(metrics.is_complete());
+
+    // :hover などでの再計算
+    metrics.add_layout(Duration::from_millis(3));
+    metrics.record_first_paint(start + Duration::from_millis(900));
+    assert_eq!(metrics.layout, Duration::from_millis(7));
+    assert_eq!(metrics.first_paint, Some(Duration::from_millis(150)));
+}
+
+#[test]
+fn report_has_one_line_per_stage() {
+    let start = Instant::now();
+    let mut metrics = PageLoadMetrics::new(start);
+    metrics.record_fetch(start + Duration::from_micros(12_345));
+
+    let report = metrics.to_string();
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 6);
+    assert!(lines[0].starts_with("fetch") && lines[0].ends_with("12.3 ms"));
+    assert!(lines[4].starts_with("first paint") && lines[4].ends_with("- ms"));
+}
@@ -0,0 +1,183 @@
+use std::path::PathBuf;
+
+use orinium_browser::browser::core::passwords::{
+    self, Credential, PasswordManager, SubmittedLogin,
+};
+use orinium_browser::browser::core::webview::{WebView, form};
+use orinium_browser::engine::html::HtmlNodeType;
+use orinium_browser::engine::html::parser::Parser;
+use orinium_browser::engine::tree::NodeRef;
+use orinium_browser::platform::keychain::{EncryptedFileStore, MemorySecretStore, SecretStore};
+use url::Url;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("orinium-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn credential(username: &str, password: &str) -> Credential {
+    Credential {
+        username: username.to_string(),
+        password: password.to_string(),
+    }
+}
+
+fn login(origin: &str, username: &str, password: &str) -> SubmittedLogin {
+    SubmittedLogin {
+        origin: origin.to_string(),
+        credential: credential(username, password),
+    }
+}
+
+fn name(node: &NodeRef<HtmlNodeType>) -> Option<String> {
+    node.borrow().value.get_attr("name").map(str::to_string)
+}
+
+#[test]
+fn login_form_fields_are_found() {
+    let dom = Parser::new(
+        r#"<html><body><form action="/login">
+        <input name="q">
+        <input type="email" name="user">
+        <input type="checkbox" name="remember">
+        <input type="password" name="pass">
+        <input type="password" name="confirm">
+        </form><form><input name="search"></form></body></html>"#,
+    )
+    .parse();
+    let forms = dom.get_elements_by_tag_name("form");
+
+    let fields = form::login_fields(&forms[0]).expect("login form");
+    assert_eq!(
+        fields.username.as_ref().and_then(name).as_deref(),
+        Some("user")
+    );
+    assert_eq!(name(&fields.password).as_deref(), Some("pass"));
+    assert!(form::login_fields(&forms[1]).is_none());
+}
+
+/// node の下に value 属性のある要素があるか
+fn has_value_attr(node: &NodeRef<HtmlNodeType>) -> bool {
+    let n = node.borrow();
+    n.value.has_attr("value") || n.children().iter().any(has_value_attr)
+}
+
+#[test]
+fn filled_logins_are_not_written_to_the_dom() {
+    let mut webview = WebView::new();
+    webview.tick();
+    webview.on_html_fetched(
+        r#"<html><body><form action="/login">
+        <input name="user"><input type="password" name="pass">
+        </form></body></html>"#
+            .to_string(),
+        Url::parse("https://example.com/").unwrap(),
+    );
+    webview.tick();
+
+    // ユーザー名の欄にフォーカスして入れる
+    webview.focus_next(false);
+    assert!(webview.fill_login(&credential("taro", "secret")));
+    assert_eq!(
+        webview.login_field(),
+        Some(("https://example.com".to_string(), "taro".to_string()))
+    );
+    // outerHTML や getAttribute、要素の複製からは読めない
+    assert!(!has_value_attr(&webview.dom_root().unwrap()));
+
+    // 送信すれば値は送られる
+    let url = webview.submit_focused_input().unwrap();
+    assert_eq!(url.query(), Some("user=taro&pass=secret"));
+    assert_eq!(
+        webview.take_submitted_login(),
+        Some(login("https://example.com", "taro", "secret"))
+    );
+}
+
+#[test]
+fn credentials_round_trip_and_origin() {
+    let credentials = [credential("taro", "p@ss&word="), credential("", "only")];
+    let secret = passwords::encode_credentials(&credentials);
+    assert_eq!(passwords::decode_credentials(&secret), credentials);
+
+    let url = Url::parse("https://example.com:8443/login?next=/").unwrap();
+    assert_eq!(
+        passwords::login_origin(&url).as_deref(),
+        Some("https://example.com:8443")
+    );
+    assert!(passwords::login_origin(&Url::parse("file:///tmp/login.html").unwrap()).is_none());
+}
+
+#[test]
+fn saving_replaces_the_same_username() {
+    let origin = "https://example.com";
+    let mut manager = PasswordManager::new(Box::new(MemorySecretStore::new()));
+    assert!(manager.should_offer_to_save(&login(origin, "taro", "old")));
+
+    manager.save(origin, credential("taro", "old")).unwrap();
+    manager
+        .save(origin, credential("hanako", "secret"))
+        .unwrap();
+    // 保存済みのものは尋ねず、パスワードが変わったら尋ねる
+    assert!(!manager.should_offer_to_save(&login(origin, "taro", "old")));
+    assert!(manager.should_offer_to_save(&login(origin, "taro", "new")));
+    assert!(!manager.should_offer_to_save(&login(origin, "taro", "")));
+
+    manager.save(origin, credential("taro", "new")).unwrap();
+    assert_eq!(
+        manager.credentials(origin),
+        [credential("hanako", "secret"), credential("taro", "new")]
+    );
+    manager.remove(origin, "hanako").unwrap();
+    assert_eq!(manager.credentials(origin), [credential("taro", "new")]);
+    assert!(manager.credentials("https://other.example").is_empty());
+}
+
+#[test]
+fn never_saved_sites_are_not_offered_and_persist() {
+    let dir = temp_dir("password-exceptions");
+    let path = dir.join(passwords::PASSWORD_EXCEPTIONS_FILE_NAME);
+
+    let mut manager = PasswordManager::new(Box::new(MemorySecretStore::new()));
+    manager.never_save("https://example.com");
+    manager.never_save("https://example.com");
+    assert!(!manager.should_offer_to_save(&login("https://example.com", "taro", "x")));
+    manager.save_exceptions(&path).unwrap();
+
+    let mut reloaded = PasswordManager::new(Box::new(MemorySecretStore::new()));
+    reloaded.load_exceptions(&path).unwrap();
+    assert_eq!(reloaded.never_saved_origins(), ["https://example.com"]);
+    // ファイルがなければ何もしない
+    reloaded.load_exceptions(&dir.join("missing")).unwrap();
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn encrypted_file_store_keeps_secrets_across_opens() {
+    let dir = temp_dir("password-store");
+    let path = dir.join(passwords::PASSWORD_FILE_NAME);
+    let key_path = dir.join("passwords.key");
+
+    let mut store = EncryptedFileStore::open(&path, &key_path).unwrap();
+    store.set("https://example.com", "taro=secret").unwrap();
+    store.set("https://other.example", "x=y").unwrap();
+    store.delete("https://other.example").unwrap();
+    let data = std::fs::read(&path).unwrap();
+    assert!(!data.windows(6).any(|w| w == b"secret"));
+
+    let mut reopened = EncryptedFileStore::open(&path, &key_path).unwrap();
+    assert_eq!(
+        reopened.get("https://example.com").unwrap().as_deref(),
+        Some("taro=secret")
+    );
+    assert_eq!(reopened.get("https://other.example").unwrap(), None);
+
+    // 別の鍵では開けない
+    std::fs::remove_file(&key_path).unwrap();
+    assert!(EncryptedFileStore::open(&path, &key_path).is_err());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
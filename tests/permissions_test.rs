@@ -0,0 +1,75 @@
+use orinium_browser::browser::core::permissions::{
+    self, Permission, PermissionState, PermissionStore,
+};
+use url::Url;
+
+#[test]
+fn undecided_permissions_ask() {
+    let mut store = PermissionStore::new();
+    let origin = "https://example.com";
+    assert_eq!(
+        store.state(origin, Permission::Notifications),
+        PermissionState::Ask
+    );
+
+    assert!(store.set(origin, Permission::Notifications, PermissionState::Allow));
+    assert!(store.set(origin, Permission::Geolocation, PermissionState::Deny));
+    assert!(!store.set(origin, Permission::Geolocation, PermissionState::Deny));
+    assert_eq!(
+        store.state(origin, Permission::Notifications),
+        PermissionState::Allow
+    );
+    assert_eq!(
+        store.state("https://other.example", Permission::Notifications),
+        PermissionState::Ask
+    );
+
+    // Ask に戻すと決定を消す
+    assert!(store.set(origin, Permission::Notifications, PermissionState::Ask));
+    assert_eq!(store.grants().len(), 1);
+    assert!(store.reset(origin));
+    assert!(store.grants().is_empty());
+}
+
+#[test]
+fn decisions_are_saved_one_per_line() {
+    let mut store = PermissionStore::new();
+    store.set(
+        "https://example.com",
+        Permission::Notifications,
+        PermissionState::Allow,
+    );
+    store.set(
+        "https://example.org",
+        Permission::Autoplay,
+        PermissionState::Deny,
+    );
+
+    let text = store.serialize();
+    assert_eq!(
+        text,
+        "allow\tnotifications\thttps://example.com\ndeny\tautoplay\thttps://example.org\n"
+    );
+    assert_eq!(PermissionStore::parse(&text), store);
+    assert!(
+        PermissionStore::parse("allow\tcamera\thttps://example.com\nmaybe\tautoplay\thttps://a\n")
+            .grants()
+            .is_empty()
+    );
+}
+
+#[test]
+fn only_http_documents_have_a_permission_origin() {
+    let url = Url::parse("https://example.com/app?x=1").unwrap();
+    assert_eq!(
+        permissions::permission_origin(&url).as_deref(),
+        Some("https://example.com")
+    );
+    for url in ["file:///tmp/a.html", "about:blank", "data:text/html,hi"] {
+        assert!(permissions::permission_origin(&Url::parse(url).unwrap()).is_none());
+    }
+    assert_eq!(
+        Permission::from_name("geolocation"),
+        Some(Permission::Geolocation)
+    );
+}
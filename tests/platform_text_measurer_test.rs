@@ -1,5 +1,7 @@
+use glyphon::{Attrs, Buffer, Metrics, Shaping, fontdb};
 use orinium_browser::engine::bridge::text::{TextMeasureRequest, TextMeasurer};
 use orinium_browser::engine::layouter::types::TextStyle;
+use orinium_browser::platform::font;
 use orinium_browser::platform::renderer::text_measurer::PlatformTextMeasurer;
 
 #[test]
@@ -43,3 +45,52 @@ fn platform_text_measurer_from_bytes_smoke() {
     assert!(res.width > 0.0);
     assert!(res.height > 0.0);
 }
+
+/// フェイスのファミリー名が絵文字のフォントのものか
+fn is_emoji_face(face: &fontdb::FaceInfo) -> bool {
+    face.families.iter().any(|(name, _)| name.contains("Emoji"))
+}
+
+#[test]
+fn platform_text_measurer_measures_color_emoji_via_fallback() {
+    let Ok((_, bytes)) = font::default_font() else {
+        eprintln!("skipping emoji measurement test: no system font found");
+        return;
+    };
+    let mut font_sys = font::font_system_with_fallbacks(bytes.clone());
+    if !font_sys.db().faces().any(is_emoji_face) {
+        eprintln!("skipping emoji measurement test: no emoji font found");
+        return;
+    }
+
+    // 主フォントに無い絵文字は、絵文字のフォントでシェーピングされる
+    let mut buffer = Buffer::new(&mut font_sys, Metrics::new(32.0, 40.0));
+    buffer.set_text(&mut font_sys, "😀", &Attrs::new(), Shaping::Advanced, None);
+    let fonts: Vec<_> = buffer
+        .layout_runs()
+        .flat_map(|run| run.glyphs.iter().map(|glyph| glyph.font_id))
+        .collect();
+    assert!(!fonts.is_empty());
+    for id in fonts {
+        let face = font_sys.db().face(id).expect("shaped with a loaded face");
+        assert!(is_emoji_face(face), "shaped with {:?}", face.families);
+    }
+
+    // 計測も豆腐（.notdef）ではなく絵文字の幅になる
+    let pm = PlatformTextMeasurer::from_bytes("t", bytes).expect("create measurer");
+    let width = |text: &str| {
+        let req = TextMeasureRequest {
+            text: text.to_string(),
+            style: TextStyle {
+                font_size: 32.0,
+                ..Default::default()
+            },
+            max_width: None,
+            wrap: false,
+        };
+        pm.measure(&req).expect("measure").width
+    };
+    let emoji = width("😀");
+    let tofu = width("\u{FFFF}");
+    assert!(emoji > tofu, "emoji w={emoji} tofu w={tofu}");
+}
@@ -0,0 +1,169 @@
+use orinium_browser::engine::html::preload::{
+    self, PreloadDestination, PreloadRequest, ResourceHint,
+};
+use url::Url;
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+/// html の中で見つかる URL と種類
+fn scan(html: &str) -> Vec<(String, PreloadDestination)> {
+    preload::scan(html, &url("https://example.com/articles/today.html"))
+        .requests
+        .into_iter()
+        .map(|request| (request.url.to_string(), request.destination))
+        .collect()
+}
+
+#[test]
+fn stylesheets_scripts_and_images_are_found_in_document_order() {
+    let found = scan(
+        r#"<!DOCTYPE html>
+        <html><head>
+          <link rel="stylesheet" href="/css/site.css">
+          <LINK REL="Stylesheet" HREF="print.css" media="print">
+          <link rel="alternate stylesheet" href="/css/contrast.css">
+          <link rel="icon" href="/favicon.ico">
+          <script src="https://cdn.example.net/app.js"></script>
+          <script>var html = '<img src="/not-an-image.png">';</script>
+        </head><body>
+          <img src="../images/hero.png" alt="">
+          <img src="data:image/png;base64,AAAA">
+          <img src="/css/site.css">
+        </body></html>"#,
+    );
+    assert_eq!(
+        found,
+        vec![
+            (
+                "https://example.com/css/site.css".to_string(),
+                PreloadDestination::Style
+            ),
+            (
+                "https://example.com/articles/print.css".to_string(),
+                PreloadDestination::Style
+            ),
+            (
+                "https://cdn.example.net/app.js".to_string(),
+                PreloadDestination::Script
+            ),
+            (
+                "https://example.com/images/hero.png".to_string(),
+                PreloadDestination::Image
+            ),
+        ]
+    );
+}
+
+#[test]
+fn link_preload_needs_a_known_destination() {
+    let found = scan(
+        r#"<link rel="preload" href="/fonts/body.woff2" as="font" crossorigin>
+        <link rel="preload" href="/data.json" as="fetch">
+        <link rel="preload" href="/no-as.css">
+        <link rel="preload" href="/late.js" as="SCRIPT">"#,
+    );
+    assert_eq!(
+        found,
+        vec![
+            (
+                "https://example.com/fonts/body.woff2".to_string(),
+                PreloadDestination::Font
+            ),
+            (
+                "https://example.com/late.js".to_string(),
+                PreloadDestination::Script
+            ),
+        ]
+    );
+}
+
+#[test]
+fn only_classic_scripts_are_preloaded() {
+    let found = scan(
+        r#"<script type="module" src="/module.js"></script>
+        <script type="text/template" src="/template.html"></script>
+        <script type="text/javascript" src="/classic.js" nonce="abc"></script>"#,
+    );
+    assert_eq!(
+        found,
+        vec![(
+            "https://example.com/classic.js".to_string(),
+            PreloadDestination::Script
+        )]
+    );
+    assert_eq!(
+        preload::scan(
+            r#"<script src="/classic.js" nonce="abc"></script>"#,
+            &url("https://example.com/")
+        )
+        .requests,
+        vec![PreloadRequest {
+            url: url("https://example.com/classic.js"),
+            destination: PreloadDestination::Script,
+            nonce: Some("abc".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn the_first_base_href_resolves_later_urls() {
+    let found = scan(
+        r#"<link rel="stylesheet" href="before.css">
+        <base href="https://static.example.com/v2/">
+        <base href="https://ignored.example.com/">
+        <link rel="stylesheet" href="after.css">"#,
+    );
+    assert_eq!(
+        found,
+        vec![
+            (
+                "https://example.com/articles/before.css".to_string(),
+                PreloadDestination::Style
+            ),
+            (
+                "https://static.example.com/v2/after.css".to_string(),
+                PreloadDestination::Style
+            ),
+        ]
+    );
+}
+
+#[test]
+fn scanning_stops_at_a_meta_content_security_policy() {
+    // meta のポリシーで許されるかはパーサーが読むまで分からない
+    let found = scan(
+        r#"<link rel="stylesheet" href="/a.css">
+        <meta http-equiv="Content-Security-Policy" content="style-src 'self'">
+        <link rel="stylesheet" href="https://other.example/b.css">"#,
+    );
+    assert_eq!(
+        found,
+        vec![(
+            "https://example.com/a.css".to_string(),
+            PreloadDestination::Style
+        )]
+    );
+}
+
+#[test]
+fn connection_hints_are_collected_as_origins() {
+    let found = preload::scan(
+        r#"<link rel="preconnect" href="https://fonts.example.net/css?family=Serif">
+        <link rel="dns-prefetch" href="//cdn.example.org/assets/">
+        <link rel="dns-prefetch preconnect" href="https://api.example.com:8443/v1">
+        <link rel="preconnect" href="https://fonts.example.net/other">
+        <link rel="dns-prefetch" href="ftp://files.example.com/">"#,
+        &url("https://example.com/"),
+    );
+    assert!(found.requests.is_empty());
+    assert_eq!(
+        found.hints,
+        vec![
+            ResourceHint::Preconnect(url("https://fonts.example.net/")),
+            ResourceHint::DnsPrefetch(url("https://cdn.example.org/")),
+            ResourceHint::Preconnect(url("https://api.example.com:8443/")),
+        ]
+    );
+}
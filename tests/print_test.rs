@@ -0,0 +1,95 @@
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter;
+use orinium_browser::engine::layouter::css_resolver::CssResolver;
+use orinium_browser::engine::layouter::types::{InfoNode, TextStyle};
+use orinium_browser::engine::renderer_model::{
+    self, DrawCommand,
+    paginate::{self, PaperSize},
+};
+use orinium_browser::platform::renderer::pdf;
+use ui_layout::LayoutNode;
+
+/// 高さ 150px の箱、高さ 100px のボタン、高さ 300px の箱を縦に並べた文書
+const HTML: &str =
+    r#"<html><body><div class="a"></div><button>Go</button><div class="b"></div></body></html>"#;
+const CSS: &str = "
+html, body, div, button { display: block; margin: 0; padding: 0; border: 0 }
+.a { height: 150px }
+button { height: 100px }
+.b { height: 300px }
+";
+
+fn layout(page: (f32, f32)) -> (LayoutNode, InfoNode) {
+    let dom = HtmlParser::new(HTML).parse();
+    let sheet = CssParser::new(CSS).parse().unwrap();
+    let styles = CssResolver::resolve(&sheet);
+    let (mut layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, page.0, page.1);
+    (layout, info)
+}
+
+#[test]
+fn pages_break_above_a_button_instead_of_through_it() {
+    let (layout, info) = layout((400.0, 200.0));
+    assert_eq!(paginate::content_height(&layout, &info), 550.0);
+    // 200px で区切るとボタン（150〜250px）の途中になるので、その上で区切る
+    assert_eq!(
+        paginate::page_breaks(&layout, &info, 200.0),
+        [0.0, 150.0, 350.0]
+    );
+}
+
+#[test]
+fn each_page_is_clipped_and_shifted_to_its_top() {
+    let (layout, info) = layout((400.0, 200.0));
+    let commands = renderer_model::generate_draw_commands(&layout, &info);
+    let pages = paginate::paginate(&layout, &info, &commands, (400.0, 200.0));
+    assert_eq!(pages.len(), 3);
+
+    let second = &pages[1];
+    assert!(matches!(
+        second[0],
+        DrawCommand::PushClip { height, .. } if height == 200.0
+    ));
+    assert!(matches!(second[1], DrawCommand::PushTransform { dy, .. } if dy == -150.0));
+    assert!(matches!(second.last(), Some(DrawCommand::PopClip)));
+    // ボタンの文字は 2 ページ目にだけある
+    let has_text = |page: &[DrawCommand]| {
+        page.iter()
+            .any(|c| matches!(c, DrawCommand::DrawText { text, .. } if text == "Go"))
+    };
+    assert!(!has_text(&pages[0]));
+    assert!(has_text(second));
+    assert!(!has_text(&pages[2]));
+}
+
+#[test]
+fn pdf_has_one_page_per_printed_page() {
+    let page = PaperSize::A4.content_size();
+    let (layout, info) = layout(page);
+    let commands = renderer_model::generate_draw_commands(&layout, &info);
+    let pages = paginate::paginate(&layout, &info, &commands, page);
+    assert_eq!(pages.len(), 1);
+
+    let bytes = pdf::write_pdf(&pages, PaperSize::A4, "Print test");
+    assert!(bytes.starts_with(b"%PDF-"));
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(text.contains("/Count 1"));
+    assert!(text.contains("/Helvetica"));
+    assert!(text.contains("(Print test)"));
+}
@@ -0,0 +1,105 @@
+use orinium_browser::browser::{BrowserApp, Tab};
+use orinium_browser::platform::network::{NetworkCore, StoragePartition};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::time::{Duration, Instant};
+
+#[test]
+fn private_tabs_are_left_out_of_the_session() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+
+    let mut tab = Tab::new();
+    tab.navigate("https://example.com/".parse().unwrap());
+    browser.add_tab(tab);
+    let mut private = Tab::new_private();
+    private.navigate("https://example.org/".parse().unwrap());
+    browser.add_tab(private);
+
+    let session = browser.current_session();
+    assert_eq!(session.tabs.len(), 1);
+    assert_eq!(session.tabs[0].url.as_str(), "https://example.com/");
+
+    browser.switch_tab(1);
+    assert!(
+        browser
+            .window_title()
+            .ends_with("Orinium Browser (Private)")
+    );
+}
+
+/// 届いた Cookie ヘッダー（なければ "none"）を返し、毎回 Cookie を設定するサーバー
+fn cookie_echo_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            std::thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut stream = stream;
+                loop {
+                    let mut cookie = "none".to_string();
+                    let mut line = String::new();
+                    loop {
+                        line.clear();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        let header = line.trim_end();
+                        if header.is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':')
+                            && name.eq_ignore_ascii_case("cookie")
+                        {
+                            cookie = value.trim().to_string();
+                        }
+                    }
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nSet-Cookie: visited=yes\r\n\r\n{}",
+                        cookie.len(),
+                        cookie
+                    );
+                    if stream.write_all(response.as_bytes()).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    format!("http://{addr}/")
+}
+
+fn fetch(network: &NetworkCore, url: &str, id: usize, partition: StoragePartition) -> String {
+    network.fetch_async(url.to_string(), id, false, partition, None);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline {
+        if let Some(msg) = network.try_receive().into_iter().next() {
+            let response = msg.response.expect("fetch failed");
+            return String::from_utf8(response.body).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    panic!("timed out fetching {url}");
+}
+
+#[test]
+fn private_partition_keeps_its_own_cookies() {
+    let url = cookie_echo_server();
+    let network = NetworkCore::new();
+    let (default, private) = (StoragePartition::Default, StoragePartition::Private);
+
+    assert_eq!(fetch(&network, &url, 1, default), "none");
+    assert_eq!(fetch(&network, &url, 2, default), "visited=yes");
+
+    // 通常のタブの Cookie はプライベートタブに送られない
+    assert_eq!(fetch(&network, &url, 3, private), "none");
+    assert_eq!(fetch(&network, &url, 4, private), "visited=yes");
+
+    // 捨てたらプライベートの Cookie だけが消える
+    network.clear_partition(private);
+    assert_eq!(fetch(&network, &url, 5, private), "none");
+    assert_eq!(fetch(&network, &url, 6, default), "visited=yes");
+}
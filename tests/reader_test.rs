@@ -0,0 +1,79 @@
+use orinium_browser::browser::core::reader::{self, ReaderOptions, ReaderTheme};
+use orinium_browser::engine::html::parser::Parser;
+
+const ARTICLE: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Rust 1.0</title><script>track();</script></head>
+<body>
+    <nav class="site-nav"><a href="/">Home</a> <a href="/blog">Blog</a> <a href="/about">About</a></nav>
+    <div id="sidebar"><a href="/a">Another post</a><a href="/b">Yet another post</a></div>
+    <article class="post">
+        <p>Today we are very proud to announce the 1.0 release of Rust, a new programming language aimed at safe, concurrent, practical systems programming.</p>
+        <p>Rust is a language for writing fast and reliable software, and the 1.0 release marks the end of a long period of instability, breaking changes, and rapid iteration.</p>
+        <p>Read the <a href="/learn">documentation</a> to get started, or <img src="images/logo.png" alt="Rust logo"> install the toolchain today.</p>
+        <script>alert("ad");</script>
+    </article>
+    <footer>Copyright, all rights reserved, and some more footer text that is long enough.</footer>
+</body>
+</html>"#;
+
+#[test]
+fn extract_keeps_the_article_and_drops_the_chrome() {
+    let dom = Parser::new(ARTICLE).parse();
+    let base_url = "https://blog.rust-lang.org/2015/05/15/".parse().unwrap();
+
+    let article = reader::extract(&dom, " Rust 1.0 ", &base_url).expect("article");
+    assert_eq!(article.title, "Rust 1.0");
+    assert!(article.content.contains("we are very proud to announce"));
+    assert!(article.content.contains("long period of instability"));
+    assert!(!article.content.contains("Another post"));
+    assert!(!article.content.contains("Home"));
+    assert!(!article.content.contains("alert"));
+    assert!(!article.content.contains("Copyright"));
+}
+
+#[test]
+fn extract_makes_links_and_images_absolute() {
+    let dom = Parser::new(ARTICLE).parse();
+    let base_url = "https://blog.rust-lang.org/2015/05/15/".parse().unwrap();
+
+    let article = reader::extract(&dom, "Rust 1.0", &base_url).expect("article");
+    assert!(
+        article
+            .content
+            .contains("<a href=\"https://blog.rust-lang.org/learn\">documentation</a>")
+    );
+    assert!(
+        article
+            .content
+            .contains("src=\"https://blog.rust-lang.org/2015/05/15/images/logo.png\"")
+    );
+}
+
+#[test]
+fn extract_gives_up_on_short_pages() {
+    let dom =
+        Parser::new("<html><body><p>Nothing much to read here, just a line.</p></body></html>")
+            .parse();
+    let base_url = "https://example.com/".parse().unwrap();
+
+    assert!(reader::extract(&dom, "Short", &base_url).is_none());
+}
+
+#[test]
+fn render_applies_the_reader_options() {
+    let dom = Parser::new(ARTICLE).parse();
+    let base_url = "https://blog.rust-lang.org/".parse().unwrap();
+    let article = reader::extract(&dom, "Rust <1.0>", &base_url).expect("article");
+
+    let page = reader::render(
+        &article,
+        &ReaderOptions {
+            font_size: 22.0,
+            theme: ReaderTheme::Dark,
+        },
+    );
+    assert!(page.contains("<title>Rust &lt;1.0&gt;</title>"));
+    assert!(page.contains("font-size: 22px"));
+    assert!(page.contains("background: #1c1b22"));
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use image::{Rgba, RgbaImage};
+use orinium_browser::browser::core::reftest::{self, Outcome, Tolerance};
+use orinium_browser::platform::renderer::headless::HeadlessRenderer;
+
+fn solid(width: u32, height: u32, color: [u8; 4]) -> RgbaImage {
+    RgbaImage::from_pixel(width, height, Rgba(color))
+}
+
+#[test]
+fn small_differences_are_tolerated() {
+    let expected = solid(10, 10, [100, 100, 100, 255]);
+    let mut actual = solid(10, 10, [104, 100, 97, 255]);
+    let tolerance = Tolerance {
+        channel: 8,
+        max_differing_pixels: 2,
+    };
+    assert!(reftest::compare(&actual, &expected, &tolerance).passes(&tolerance));
+
+    // 2 画素までは違っていてもよい
+    actual.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+    actual.put_pixel(9, 9, Rgba([0, 0, 0, 255]));
+    let diff = reftest::compare(&actual, &expected, &tolerance);
+    assert_eq!(diff.differing_pixels, 2);
+    assert_eq!(diff.max_channel_delta, 155);
+    assert!(diff.passes(&tolerance));
+    assert_eq!(diff.image.get_pixel(0, 0), &Rgba([255, 0, 0, 255]));
+
+    actual.put_pixel(5, 5, Rgba([0, 0, 0, 255]));
+    assert!(!reftest::compare(&actual, &expected, &tolerance).passes(&tolerance));
+}
+
+#[test]
+fn images_of_different_sizes_never_match() {
+    let diff = reftest::compare(
+        &solid(10, 10, [0, 0, 0, 255]),
+        &solid(10, 12, [0, 0, 0, 255]),
+        &Tolerance::default(),
+    );
+    assert_eq!(diff.size_mismatch, Some(((10, 10), (10, 12))));
+    assert!(!diff.passes(&Tolerance::default()));
+}
+
+#[test]
+fn fixtures_are_paired_with_reference_images() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/reftests");
+    let fixtures = reftest::fixtures(&dir).unwrap();
+    let names: Vec<&str> = fixtures.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["block_boxes", "flex_row", "linked_stylesheet"]);
+    assert_eq!(fixtures[0].reference, dir.join("block_boxes.png"));
+}
+
+#[test]
+fn rendered_fixtures_match_reference_images() {
+    // GPU のアダプターがない環境（多くの CI）では描けない
+    if let Err(e) = pollster::block_on(HeadlessRenderer::new((1, 1), 1.0)) {
+        eprintln!("Skipping reftests: {e:#}");
+        return;
+    }
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/reftests");
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reftests");
+    let tolerance = Tolerance::default();
+
+    let mut failures = Vec::new();
+    for fixture in reftest::fixtures(&dir).unwrap() {
+        match reftest::run(&fixture, &tolerance, &out_dir).unwrap() {
+            Outcome::Passed => {}
+            Outcome::Written => eprintln!("Wrote reference image {:?}", fixture.reference),
+            Outcome::Failed {
+                diff,
+                actual_path,
+                diff_path,
+            } => failures.push(format!(
+                "{}: {} pixels differ (max delta {}, size {:?}); see {:?} and {:?}",
+                fixture.name,
+                diff.differing_pixels,
+                diff.max_channel_delta,
+                diff.size_mismatch,
+                actual_path,
+                diff_path
+            )),
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
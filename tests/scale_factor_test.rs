@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::browser::{BrowserApp, Tab};
+use orinium_browser::engine::renderer_model::DrawCommand;
+use orinium_browser::platform::renderer::compositor::{Compositor, FrameRecorder};
+
+/// 最初に描く、ページの下に敷くキャンバスの矩形の大きさ（ビューポートの CSS ピクセル）
+fn canvas_size(commands: &[DrawCommand]) -> Option<(f32, f32)> {
+    commands.iter().find_map(|command| match command {
+        DrawCommand::DrawRect {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+            ..
+        } => Some((*width, *height)),
+        _ => None,
+    })
+}
+
+#[test]
+fn moving_to_a_hidpi_monitor_keeps_the_css_viewport() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    browser.set_chrome_visible(false);
+    browser.open_in_new_tab("data:text/html,<p>hello</p>".parse().unwrap());
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !browser.active_tab().is_some_and(Tab::is_loaded) && Instant::now() < deadline {
+        browser.tick();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let mut compositor = FrameRecorder::new((800, 600));
+    browser.redraw(&mut compositor);
+    let before = compositor.take_rendered().unwrap();
+    assert_eq!(before.scale_factor, 1.0);
+
+    // 倍率 2 のモニターに移ると、同じ論理サイズを保つよう物理ピクセルが倍になる
+    browser.set_scale_factor(2.0);
+    browser.set_window_size((1600, 1200));
+    compositor.resize(winit::dpi::PhysicalSize::new(1600, 1200));
+    browser.redraw(&mut compositor);
+    let after = compositor.take_rendered().unwrap();
+
+    assert_eq!(after.scale_factor, 2.0);
+    // ブラウザの UI は描かないので、ページがウィンドウ全体になる
+    let viewport = (800.0, 600.0);
+    assert_eq!(canvas_size(&before.draw_commands), Some(viewport));
+    assert_eq!(canvas_size(&after.draw_commands), Some(viewport));
+}
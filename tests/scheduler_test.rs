@@ -0,0 +1,87 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::browser::core::scheduler::Scheduler;
+
+fn drain(scheduler: &mut Scheduler<&'static str>, now: Instant) -> Vec<&'static str> {
+    std::iter::from_fn(|| scheduler.next_task(now)).collect()
+}
+
+#[test]
+fn timers_fire_in_due_order() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new();
+    scheduler.set_timer(start, Duration::from_millis(20), false, "late");
+    scheduler.set_timer(start, Duration::from_millis(10), false, "early");
+    scheduler.set_timer(start, Duration::from_millis(10), false, "early too");
+
+    assert!(drain(&mut scheduler, start).is_empty());
+    assert_eq!(
+        scheduler.next_wakeup(start),
+        Some(start + Duration::from_millis(10))
+    );
+
+    let later = start + Duration::from_millis(30);
+    assert_eq!(drain(&mut scheduler, later), ["early", "early too", "late"]);
+    assert!(scheduler.is_empty());
+}
+
+#[test]
+fn microtasks_run_before_the_next_task() {
+    let now = Instant::now();
+    let mut scheduler = Scheduler::new();
+    scheduler.post_task("task 1");
+    scheduler.post_task("task 2");
+
+    assert_eq!(scheduler.next_task(now), Some("task 1"));
+    // task 1 の実行中に積まれた microtask
+    scheduler.post_microtask("microtask");
+    assert_eq!(drain(&mut scheduler, now), ["microtask", "task 2"]);
+}
+
+#[test]
+fn intervals_repeat_until_cleared() {
+    let start = Instant::now();
+    let mut scheduler = Scheduler::new();
+    let id = scheduler.set_timer(start, Duration::from_millis(10), true, "tick");
+
+    let first = start + Duration::from_millis(10);
+    assert_eq!(drain(&mut scheduler, first), ["tick"]);
+    // 同じ時刻にはもう一度実行しない
+    assert!(drain(&mut scheduler, first).is_empty());
+    assert_eq!(
+        drain(&mut scheduler, first + Duration::from_millis(10)),
+        ["tick"]
+    );
+
+    assert!(scheduler.clear_timer(id));
+    assert!(!scheduler.clear_timer(id));
+    assert!(drain(&mut scheduler, first + Duration::from_secs(1)).is_empty());
+}
+
+#[test]
+fn zero_delay_timers_wait_for_the_next_turn() {
+    let now = Instant::now();
+    let mut scheduler = Scheduler::new();
+    scheduler.set_timer(now, Duration::ZERO, true, "spin");
+
+    assert!(drain(&mut scheduler, now).is_empty());
+    assert_eq!(
+        drain(&mut scheduler, now + Duration::from_millis(1)),
+        ["spin"]
+    );
+}
+
+#[test]
+fn cancelled_tasks_are_dropped() {
+    let now = Instant::now();
+    let mut scheduler = Scheduler::new();
+    scheduler.post_task("keep");
+    scheduler.post_task("drop");
+    scheduler.set_timer(now, Duration::from_millis(1), false, "drop");
+
+    scheduler.cancel_where(|t| *t == "drop");
+    assert_eq!(
+        drain(&mut scheduler, now + Duration::from_secs(1)),
+        ["keep"]
+    );
+}
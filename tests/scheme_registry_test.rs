@@ -0,0 +1,111 @@
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+use orinium_browser::browser::core::extensions::{Extension, ExtensionRegistry};
+use orinium_browser::browser::core::resource_loader::BrowserResourceLoader;
+use orinium_browser::browser::core::schemes::{SchemeHandler, SchemeRegistry, SchemeResponse};
+use orinium_browser::platform::network::StoragePartition;
+use url::Url;
+
+fn url(s: &str) -> Url {
+    Url::parse(s).unwrap()
+}
+
+/// notes:<name> を `<h1>name</h1>` にする
+fn notes(url: &Url) -> Result<SchemeResponse> {
+    if url.path().is_empty() {
+        bail!("No note name in {}", url);
+    }
+    Ok(SchemeResponse {
+        mime: Some("text/html".to_string()),
+        body: format!("<h1>{}</h1>", url.path()).into_bytes(),
+    })
+}
+
+struct Notes;
+
+impl Extension for Notes {
+    fn name(&self) -> &str {
+        "Notes"
+    }
+
+    fn scheme_handlers(&self) -> Vec<(String, Arc<dyn SchemeHandler>)> {
+        let handler: Arc<dyn SchemeHandler> = Arc::new(notes);
+        vec![("notes".to_string(), handler)]
+    }
+}
+
+#[test]
+fn built_in_schemes_are_read_locally() {
+    let registry = SchemeRegistry::new();
+    assert!(registry.handles(&url("data:,hi")));
+    assert!(registry.handles(&url("resource:///audio/birds.mp3")));
+    assert!(registry.handles(&url("file:///tmp/page.html")));
+    // ネットワークと内部ページはここでは読まない
+    assert!(!registry.handles(&url("https://example.com/")));
+    assert!(!registry.handles(&url("about:blank")));
+    assert!(registry.load(&url("orinium://history")).is_none());
+
+    let response = registry.load(&url("data:text/plain,hi")).unwrap().unwrap();
+    assert_eq!(response.mime.as_deref(), Some("text/plain"));
+    assert_eq!(response.body, b"hi");
+}
+
+#[test]
+fn uris_and_paths_resolve_the_same_file() {
+    let dir = std::env::temp_dir().join(format!("orinium-schemes-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("chime.wav");
+    std::fs::write(&path, b"RIFF").unwrap();
+
+    let registry = SchemeRegistry::new();
+    let file_url = Url::from_file_path(&path).unwrap();
+    assert_eq!(registry.load_uri(file_url.as_str()).unwrap(), b"RIFF");
+    assert_eq!(registry.load_uri(path.to_str().unwrap()).unwrap(), b"RIFF");
+    assert_eq!(registry.load_uri("data:,RIFF").unwrap(), b"RIFF");
+    assert!(registry.load_uri("https://example.com/chime.wav").is_err());
+}
+
+#[test]
+fn registered_schemes_cannot_replace_built_in_ones() {
+    let mut registry = SchemeRegistry::new();
+    for scheme in [
+        "http", "HTTPS", "file", "data", "resource", "about", "orinium",
+    ] {
+        assert!(
+            registry.register(scheme, Arc::new(notes)).is_err(),
+            "{scheme}"
+        );
+    }
+    assert!(registry.register("not a scheme", Arc::new(notes)).is_err());
+    assert!(registry.register("1notes", Arc::new(notes)).is_err());
+
+    registry.register("Notes", Arc::new(notes)).unwrap();
+    assert!(registry.handles(&url("notes:today")));
+    assert!(registry.load(&url("notes:")).unwrap().is_err());
+}
+
+#[test]
+fn extension_schemes_are_served_by_the_loader() {
+    let mut extensions = ExtensionRegistry::new();
+    extensions.register(Box::new(Notes));
+    assert!(extensions.handles_scheme("notes"));
+    assert!(!extensions.handles_scheme("https"));
+
+    let mut loader = BrowserResourceLoader::new(None);
+    for (scheme, handler) in extensions.scheme_handlers() {
+        loader.register_scheme(&scheme, handler).unwrap();
+    }
+    let response = loader.fetch_blocking(url("notes:today")).unwrap();
+    assert_eq!(response.body, b"<h1>today</h1>");
+    assert_eq!(
+        response.headers,
+        vec![("content-type".to_string(), "text/html".to_string())]
+    );
+
+    loader.fetch_async(url("notes:"), 7, false, StoragePartition::Default, None);
+    let messages = loader.try_receive();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].id, 7);
+    assert!(messages[0].response.is_err());
+}
@@ -0,0 +1,86 @@
+use orinium_browser::engine::html::parser::{DomTree, Parser};
+use orinium_browser::engine::script::dom;
+
+const PAGE: &str = r#"<html><body>
+<div id="app" class="main wide"><p class="note">one</p><p>two</p></div>
+<ul><li class="note">three</li></ul>
+</body></html>"#;
+
+#[test]
+fn query_selector_finds_elements_in_document_order() {
+    let tree = Parser::new(PAGE).parse();
+
+    let selectors = dom::parse_selectors(".note").unwrap();
+    let found = dom::query_selector_all(&tree.root, &selectors);
+    let texts: Vec<String> = found.iter().map(DomTree::inner_text).collect();
+    assert_eq!(texts, ["one", "three"]);
+
+    let selectors = dom::parse_selectors("#app p, ul li").unwrap();
+    let found = dom::query_selector_all(&tree.root, &selectors);
+    assert_eq!(found.len(), 3);
+
+    let first = dom::query_selector(&tree.root, &dom::parse_selectors("div.wide p").unwrap());
+    assert_eq!(DomTree::inner_text(&first.unwrap()), "one");
+}
+
+#[test]
+fn invalid_selectors_are_rejected() {
+    assert!(dom::parse_selectors("").is_none());
+    assert!(dom::parse_selectors("p { color: red }").is_none());
+}
+
+#[test]
+fn inner_html_round_trips_through_the_parser() {
+    let tree = Parser::new(PAGE).parse();
+    let app = dom::get_element_by_id(&tree.root, "app").unwrap();
+
+    dom::set_inner_html(&app, r#"<span title="a b">x &amp; y</span>"#);
+    assert_eq!(
+        dom::inner_html(&app),
+        r#"<span title="a b">x &amp; y</span>"#
+    );
+    assert_eq!(dom::text_content(&app), "x & y");
+}
+
+#[test]
+fn text_content_and_attributes_are_written_to_the_dom() {
+    let tree = Parser::new(PAGE).parse();
+    let app = dom::get_element_by_id(&tree.root, "app").unwrap();
+
+    dom::set_text_content(&app, "<b>plain</b>");
+    assert_eq!(dom::inner_html(&app), "&lt;b&gt;plain&lt;/b&gt;");
+
+    dom::set_text_content(&app, "");
+    assert!(app.borrow().children().is_empty());
+
+    dom::set_attribute(&app, "Data-State", "ready");
+    assert_eq!(
+        dom::get_attribute(&app, "data-state").as_deref(),
+        Some("ready")
+    );
+}
+
+#[cfg(feature = "js")]
+#[test]
+fn scripts_build_content_through_the_dom() {
+    use orinium_browser::engine::script::ScriptRuntime;
+
+    let tree = Parser::new(PAGE).parse();
+    let mut runtime = ScriptRuntime::new();
+    runtime.attach_document(&tree);
+
+    runtime
+        .execute(
+            r#"document.getElementById("app").innerHTML = "<h1>Hello</h1>";
+               document.querySelector("ul li").textContent = "changed";
+               console.log("done", 1);"#,
+            "inline",
+        )
+        .unwrap();
+
+    assert!(runtime.take_dom_mutated());
+    let app = dom::get_element_by_id(&tree.root, "app").unwrap();
+    assert_eq!(dom::inner_html(&app), "<h1>Hello</h1>");
+    let li = dom::query_selector(&tree.root, &dom::parse_selectors("li").unwrap()).unwrap();
+    assert_eq!(DomTree::inner_text(&li), "changed");
+}
@@ -0,0 +1,203 @@
+use orinium_browser::engine::html::parser::Parser;
+use orinium_browser::engine::script::{self, ScriptRuntime, ScriptSource};
+use url::Url;
+
+fn base() -> Url {
+    "https://example.com/app/index.html".parse().unwrap()
+}
+
+#[test]
+fn scripts_are_collected_in_document_order() {
+    let html = r#"<html><head>
+<script>var a = 1;</script>
+<script src="lib.js"></script>
+</head><body>
+<script type="text/javascript" src="/main.js">ignored</script>
+<script type="Application/JavaScript">var b = 2;</script>
+</body></html>"#;
+    let dom = Parser::new(html).parse();
+
+    let scripts = script::collect_scripts(&dom, &base());
+    assert_eq!(
+        scripts,
+        vec![
+            ScriptSource::Inline("var a = 1;".into()),
+            ScriptSource::External("https://example.com/app/lib.js".parse().unwrap()),
+            ScriptSource::External("https://example.com/main.js".parse().unwrap()),
+            ScriptSource::Inline("var b = 2;".into()),
+        ]
+    );
+}
+
+#[test]
+fn non_classic_scripts_are_skipped() {
+    let html = r#"<html><body>
+<script type="module">import x from "./x.js";</script>
+<script type="application/json">{"a": 1}</script>
+<script type="text/template"><p>hi</p></script>
+<script type="">var ok = true;</script>
+</body></html>"#;
+    let dom = Parser::new(html).parse();
+
+    let scripts = script::collect_scripts(&dom, &base());
+    assert_eq!(scripts, vec![ScriptSource::Inline("var ok = true;".into())]);
+}
+
+#[test]
+fn runtime_refuses_to_run_after_shutdown() {
+    let mut runtime = ScriptRuntime::new();
+    assert!(runtime.execute("1 + 1", "test").is_ok());
+
+    runtime.shutdown();
+    assert!(runtime.is_shut_down());
+    if script::ENABLED {
+        assert!(runtime.execute("1 + 1", "test").is_err());
+    }
+}
+
+#[cfg(feature = "js")]
+#[test]
+fn script_errors_are_reported() {
+    let mut runtime = ScriptRuntime::new();
+    let err = runtime
+        .execute("throw new Error('boom')", "page.js")
+        .unwrap_err();
+    assert!(err.to_string().contains("page.js"));
+    // 例外の後も同じ realm で続けて実行できる
+    assert!(runtime.execute("var x = 1; x + 1", "next.js").is_ok());
+}
+
+#[cfg(feature = "js")]
+#[test]
+fn timers_are_requested_from_the_browser_and_fired_by_id() {
+    use orinium_browser::engine::script::TimerRequest;
+    use std::time::Duration;
+
+    let mut runtime = ScriptRuntime::new();
+    runtime
+        .execute(
+            r#"var log = [];
+               var t = setTimeout((x) => log.push(x), 25, "fired");
+               var i = setInterval(() => log.push("tick"), 10);
+               clearInterval(i);"#,
+            "timers.js",
+        )
+        .unwrap();
+
+    assert_eq!(
+        runtime.take_timer_requests(),
+        vec![
+            TimerRequest::Set {
+                id: 1,
+                delay: Duration::from_millis(25),
+                repeat: false
+            },
+            TimerRequest::Set {
+                id: 2,
+                delay: Duration::from_millis(10),
+                repeat: true
+            },
+            TimerRequest::Clear(2),
+        ]
+    );
+
+    runtime.fire_timer(1).unwrap();
+    // 止めたタイマーや実行済みのタイマーは何もしない
+    runtime.fire_timer(2).unwrap();
+    runtime.fire_timer(1).unwrap();
+    runtime
+        .execute(
+            r#"if (log.join() !== "fired") throw new Error(log.join());"#,
+            "check",
+        )
+        .unwrap();
+}
+
+#[cfg(feature = "js")]
+#[test]
+fn fetch_resolves_with_the_response_from_the_browser() {
+    use orinium_browser::engine::script::{FetchRequest, FetchResponse};
+
+    let mut runtime = ScriptRuntime::new();
+    runtime
+        .execute(
+            r#"var result = null;
+               fetch("/api/items").then((r) => r.json()).then((items) => { result = items.length; });
+               var xhr = new XMLHttpRequest();
+               xhr.open("get", "data.txt");
+               xhr.onerror = () => { result = "xhr failed"; };
+               xhr.send();"#,
+            "fetch.js",
+        )
+        .unwrap();
+
+    assert_eq!(
+        runtime.take_fetch_requests(),
+        vec![
+            FetchRequest {
+                id: 1,
+                method: "GET".into(),
+                url: "/api/items".into()
+            },
+            FetchRequest {
+                id: 2,
+                method: "GET".into(),
+                url: "data.txt".into()
+            },
+        ]
+    );
+
+    let response = FetchResponse {
+        status: 200,
+        status_text: "OK".into(),
+        url: "https://example.com/api/items".into(),
+        body: "[1, 2, 3]".into(),
+    };
+    runtime.complete_fetch(1, Ok(response)).unwrap();
+    runtime
+        .execute(
+            r#"if (result !== 3) throw new Error(String(result));"#,
+            "check",
+        )
+        .unwrap();
+
+    runtime.complete_fetch(2, Err("blocked".into())).unwrap();
+    runtime
+        .execute(
+            r#"if (result !== "xhr failed") throw new Error(String(result));"#,
+            "check",
+        )
+        .unwrap();
+}
+
+#[cfg(feature = "js")]
+#[test]
+fn local_storage_is_shared_by_documents_of_the_same_origin() {
+    use orinium_browser::engine::script::storage::WebStorage;
+
+    let local = WebStorage::new().shared();
+    let mut first = ScriptRuntime::new();
+    first.set_storage("https://example.com", Some(local.clone()), None);
+    first
+        .execute(r#"localStorage.setItem("count", 1 + 1);"#, "first.js")
+        .unwrap();
+    assert_eq!(
+        local.borrow().get_item("https://example.com", "count"),
+        Some("2")
+    );
+
+    let mut second = ScriptRuntime::new();
+    second.set_storage(
+        "https://example.com",
+        Some(local.clone()),
+        Some(WebStorage::new().shared()),
+    );
+    second
+        .execute(
+            r#"if (localStorage.getItem("count") !== "2") throw new Error("not shared");
+               if (localStorage.length !== 1) throw new Error("length");
+               if (sessionStorage.getItem("count") !== null) throw new Error("session");"#,
+            "second.js",
+        )
+        .unwrap();
+}
@@ -0,0 +1,41 @@
+use orinium_browser::browser::core::session::{Session, SessionTab};
+
+#[test]
+fn session_round_trips_through_text() {
+    let session = Session {
+        tabs: vec![
+            SessionTab {
+                url: "https://example.com/".parse().unwrap(),
+                title: Some("Example\tDomain".to_string()),
+                scroll: (0.0, 320.5),
+            },
+            SessionTab {
+                url: "resource:///test/compatibility_test.html".parse().unwrap(),
+                title: None,
+                scroll: (0.0, 0.0),
+            },
+        ],
+        active: 1,
+    };
+
+    let restored = Session::parse(&session.serialize());
+
+    assert_eq!(restored.active, 1);
+    assert_eq!(restored.tabs.len(), 2);
+    // タブ文字は区切りと紛れないよう空白になる
+    assert_eq!(restored.tabs[0].title.as_deref(), Some("Example Domain"));
+    assert_eq!(restored.tabs[0].scroll, (0.0, 320.5));
+    assert_eq!(restored.tabs[1], session.tabs[1]);
+}
+
+#[test]
+fn broken_lines_are_skipped() {
+    let session = Session::parse(
+        "active\t5\ntab\t0\t0\tnot a url\t\ngarbage\ntab\t0\t10\thttps://example.com/\tExample\n",
+    );
+
+    assert_eq!(session.tabs.len(), 1);
+    assert_eq!(session.tabs[0].scroll, (0.0, 10.0));
+    // 範囲外のアクティブタブは最後のタブに寄せる
+    assert_eq!(session.active, 0);
+}
@@ -0,0 +1,123 @@
+use orinium_browser::browser::Settings;
+use orinium_browser::browser::core::browsing_history::BrowsingHistory;
+use orinium_browser::browser::core::internal_pages::{self, InternalPageContext};
+use orinium_browser::browser::core::reader::ReaderTheme;
+use orinium_browser::browser::settings::{ColorSchemePreference, CookiePolicy};
+use orinium_browser::engine::css::media::ColorScheme;
+
+#[test]
+fn settings_round_trip_through_toml() {
+    let mut settings = Settings::default();
+    settings.set("homepage", "https://example.com/").unwrap();
+    settings
+        .set(
+            "search_engine",
+            "https://search.example/?q={query}&lang=\"en\"",
+        )
+        .unwrap();
+    settings.set("font.family", "Noto Serif").unwrap();
+    settings.set("font.size", "18").unwrap();
+    settings.set("default_zoom", "1.25").unwrap();
+    settings.set("scroll_speed", "2").unwrap();
+    settings.set("color_scheme", "dark").unwrap();
+    settings.set("cookie_policy", "block-all").unwrap();
+    settings.set("meta_refresh", "off").unwrap();
+    settings.set("reader.theme", "sepia").unwrap();
+    settings.set("debug.frame_stats", "on").unwrap();
+    settings.set("memory.budget_mb", "1024").unwrap();
+    settings
+        .set("log.levels", "warn,platform::network=debug")
+        .unwrap();
+    settings.set("log.file", "/tmp/orinium.log").unwrap();
+    assert!(settings.set("log.levels", "network=loud").is_err());
+    assert_eq!(settings.memory_budget(), 1024 * 1024 * 1024);
+    assert!(settings.set("memory.budget_mb", "1").is_err());
+
+    assert_eq!(Settings::parse(&settings.serialize()), settings);
+}
+
+#[test]
+fn parse_reads_tables_and_skips_invalid_values() {
+    let settings = Settings::parse(
+        r#"
+# comment
+homepage = "https://example.com/"  # trailing comment
+default_zoom = 100          # out of range, keeps the default
+scroll_speed = "fast"
+restore_session = false
+unknown = 1
+
+[font]
+family = 'Fira Sans'
+size = 20
+
+[reader]
+theme = "dark"
+"#,
+    );
+
+    assert_eq!(settings.homepage.as_str(), "https://example.com/");
+    assert_eq!(settings.default_zoom, 1.0);
+    assert_eq!(settings.scroll_speed, 1.0);
+    assert!(!settings.restore_session);
+    assert_eq!(settings.font_family.as_deref(), Some("Fira Sans"));
+    assert_eq!(settings.font_size, 20.0);
+    assert_eq!(settings.reader.theme, ReaderTheme::Dark);
+}
+
+#[test]
+fn set_rejects_invalid_values() {
+    let mut settings = Settings::default();
+    assert!(settings.set("homepage", "not a url").is_err());
+    assert!(
+        settings
+            .set("search_engine", "https://example.com/")
+            .is_err()
+    );
+    assert!(settings.set("font.size", "1000").is_err());
+    assert!(settings.set("cookie_policy", "sometimes").is_err());
+    assert!(settings.set("no_such_setting", "1").is_err());
+    assert_eq!(settings, Settings::default());
+}
+
+#[test]
+fn settings_page_changes_settings_from_the_query() {
+    let mut settings = Settings::default();
+    let url = "orinium://settings?cookie_policy=allow-all&font.size=14"
+        .parse()
+        .unwrap();
+    assert!(internal_pages::update_settings(&url, &mut settings));
+    assert_eq!(settings.cookie_policy, CookiePolicy::AllowAll);
+    assert_eq!(settings.font_size, 14.0);
+    // 同じ値をもう一度書いても変化はない
+    assert!(!internal_pages::update_settings(&url, &mut settings));
+
+    let history = BrowsingHistory::new();
+    let ctx = InternalPageContext {
+        history: &history,
+        settings: &settings,
+        inspection: None,
+        console: None,
+    };
+    let page = internal_pages::load(&url, &ctx).expect("settings page");
+    assert!(page.contains("<strong class=\"choice\">Allow all</strong>"));
+    assert!(page.contains("href=\"orinium://settings?cookie_policy=block-all\""));
+    assert!(page.contains("<strong class=\"choice\">14px</strong>"));
+}
+
+#[test]
+fn color_scheme_preference_overrides_the_system() {
+    let mut settings = Settings::default();
+    assert_eq!(
+        settings.page_defaults(ColorScheme::Dark).color_scheme,
+        ColorScheme::Dark
+    );
+
+    settings.set("color_scheme", "light").unwrap();
+    assert_eq!(settings.color_scheme, ColorSchemePreference::Light);
+    assert_eq!(
+        settings.page_defaults(ColorScheme::Dark).color_scheme,
+        ColorScheme::Light
+    );
+    assert!(settings.set("color_scheme", "blue").is_err());
+}
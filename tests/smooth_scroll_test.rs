@@ -0,0 +1,141 @@
+use std::time::{Duration, Instant};
+
+use orinium_browser::engine::input::scroll::SmoothScroller;
+use orinium_browser::engine::layouter::types::{ContainerRole, ContainerStyle, InfoNode, NodeKind};
+
+fn container() -> InfoNode {
+    InfoNode {
+        kind: NodeKind::Container {
+            scroll_x: false,
+            scroll_y: true,
+            scroll_offset_x: 0.0,
+            scroll_offset_y: 0.0,
+            style: ContainerStyle::default(),
+            role: ContainerRole::Normal,
+        },
+        children: Vec::new(),
+    }
+}
+
+fn offset_y(info: &InfoNode) -> f32 {
+    match info.kind {
+        NodeKind::Container {
+            scroll_offset_y, ..
+        } => scroll_offset_y,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn scroll_eases_toward_target_and_stops() {
+    let mut info = container();
+    let mut scroller = SmoothScroller::new();
+    scroller.scroll_to(&[], (0.0, 300.0));
+
+    let mut now = Instant::now();
+    assert!(scroller.step(&mut info, now));
+
+    // 1 フレーム目は途中まで
+    let first = offset_y(&info);
+    assert!(first > 0.0 && first < 300.0);
+
+    let mut prev = first;
+    for _ in 0..120 {
+        now += Duration::from_millis(16);
+        if !scroller.step(&mut info, now) {
+            break;
+        }
+        // 単調に近づく
+        assert!(offset_y(&info) >= prev);
+        prev = offset_y(&info);
+    }
+
+    assert_eq!(offset_y(&info), 300.0);
+    assert!(!scroller.is_animating());
+}
+
+#[test]
+fn scroll_target_for_missing_container_is_dropped() {
+    let mut info = container();
+    let mut scroller = SmoothScroller::new();
+    scroller.scroll_to(&[3], (0.0, 100.0));
+
+    assert!(!scroller.step(&mut info, Instant::now()));
+    assert_eq!(offset_y(&info), 0.0);
+}
+
+#[test]
+fn scroll_offsets_are_clamped_to_content() {
+    use orinium_browser::engine::input::scroll::clamp_scroll_offsets;
+    use ui_layout::{LayoutEngine, LayoutNode, Length, Style};
+
+    // 高さ 1000px のコンテンツを 400px のビューポートで表示する
+    let mut child_style = Style::default();
+    child_style.size.height = Length::Px(1000.0);
+    let mut layout = LayoutNode::with_children(
+        Style::default(),
+        vec![LayoutNode::with_children(child_style, Vec::new())],
+    );
+    LayoutEngine::layout(&mut layout, 800.0, 400.0);
+
+    let mut info = container();
+    info.children.push(container());
+    if let NodeKind::Container {
+        scroll_offset_y, ..
+    } = &mut info.kind
+    {
+        *scroll_offset_y = 5000.0;
+    }
+
+    clamp_scroll_offsets(&layout, &mut info, (800.0, 400.0));
+    assert_eq!(offset_y(&info), 600.0);
+}
+
+#[test]
+fn touch_drag_scrolls_and_flings() {
+    use orinium_browser::engine::input::gesture::{Gesture, TouchTracker};
+
+    let mut touch = TouchTracker::new();
+    let mut now = Instant::now();
+    touch.touch_started(0, (100.0, 300.0), now);
+
+    // 指を上に動かすと下にスクロールする
+    now += Duration::from_millis(16);
+    assert_eq!(
+        touch.touch_moved(0, (100.0, 280.0), now),
+        Some(Gesture::Scroll { dx: 0.0, dy: 20.0 })
+    );
+
+    now += Duration::from_millis(16);
+    touch.touch_moved(0, (100.0, 260.0), now);
+    touch.touch_ended(0, now);
+    assert!(touch.is_flinging());
+
+    // 減速して止まる
+    let mut total = 0.0;
+    for _ in 0..600 {
+        now += Duration::from_millis(16);
+        match touch.step_fling(now) {
+            Some((_, dy)) => total += dy,
+            None => break,
+        }
+    }
+    assert!(total > 0.0);
+    assert!(!touch.is_flinging());
+}
+
+#[test]
+fn two_finger_pinch_zooms() {
+    use orinium_browser::engine::input::gesture::{Gesture, TouchTracker};
+
+    let mut touch = TouchTracker::new();
+    let now = Instant::now();
+    touch.touch_started(0, (100.0, 100.0), now);
+    touch.touch_started(1, (200.0, 100.0), now);
+
+    // 指の間隔が 100px → 200px
+    match touch.touch_moved(1, (300.0, 100.0), now) {
+        Some(Gesture::Zoom { scale }) => assert!((scale - 2.0).abs() < 1e-4),
+        other => panic!("expected zoom, got {other:?}"),
+    }
+}
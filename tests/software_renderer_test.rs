@@ -0,0 +1,91 @@
+use orinium_browser::engine::layouter::types::Color;
+use orinium_browser::engine::renderer_model::DrawCommand;
+use orinium_browser::platform::renderer::compositor::Compositor;
+use orinium_browser::platform::renderer::software::SoftwareRenderer;
+use winit::dpi::PhysicalSize;
+
+#[test]
+fn rects_are_scaled_translated_and_clipped() {
+    let mut renderer = SoftwareRenderer::new_headless(PhysicalSize::new(20, 20), 2.0);
+    let red = Color(255, 0, 0, 255);
+    renderer.parse_draw_commands(&[
+        DrawCommand::PushTransform { dx: 1.0, dy: 1.0 },
+        DrawCommand::PushClip {
+            x: 0.0,
+            y: 0.0,
+            width: 3.0,
+            height: 10.0,
+        },
+        DrawCommand::DrawRect {
+            x: 0.0,
+            y: 0.0,
+            width: 5.0,
+            height: 5.0,
+            color: red,
+        },
+        DrawCommand::PopClip,
+        DrawCommand::PopTransform,
+    ]);
+
+    let image = renderer.capture_frame().unwrap();
+    assert_eq!(image.dimensions(), (20, 20));
+    // CSS の (1, 1)〜(4, 6) が物理ピクセルの (2, 2)〜(8, 12) になる
+    assert_eq!(image.get_pixel(2, 2).0, [255, 0, 0, 255]);
+    assert_eq!(image.get_pixel(7, 11).0, [255, 0, 0, 255]);
+    assert_eq!(image.get_pixel(1, 2).0, [255, 255, 255, 255]);
+    // クリップの外
+    assert_eq!(image.get_pixel(8, 2).0, [255, 255, 255, 255]);
+    assert_eq!(image.get_pixel(2, 12).0, [255, 255, 255, 255]);
+}
+
+#[test]
+fn ellipses_stay_inside_the_clip() {
+    let mut renderer = SoftwareRenderer::new_headless(PhysicalSize::new(20, 20), 1.0);
+    renderer.parse_draw_commands(&[
+        DrawCommand::PushClip {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 20.0,
+        },
+        DrawCommand::DrawEllipse {
+            center: (10.0, 10.0),
+            radius_x: 8.0,
+            radius_y: 8.0,
+            color: Color(0, 0, 255, 255),
+        },
+        DrawCommand::PopClip,
+    ]);
+
+    let image = renderer.capture_frame().unwrap();
+    assert_eq!(image.get_pixel(6, 10).0, [0, 0, 255, 255]);
+    assert_eq!(image.get_pixel(13, 10).0, [255, 255, 255, 255]);
+    assert_eq!(image.get_pixel(1, 1).0, [255, 255, 255, 255]);
+}
+
+#[test]
+fn headless_renderer_cannot_present() {
+    let mut renderer = SoftwareRenderer::new_headless(PhysicalSize::new(4, 4), 1.0);
+    assert!(renderer.render().is_err());
+}
+
+#[test]
+fn scale_factor_changes_apply_to_the_next_frame() {
+    let mut renderer = SoftwareRenderer::new_headless(PhysicalSize::new(20, 20), 1.0);
+    let commands = [DrawCommand::DrawRect {
+        x: 0.0,
+        y: 0.0,
+        width: 4.0,
+        height: 4.0,
+        color: Color(0, 128, 0, 255),
+    }];
+    renderer.parse_draw_commands(&commands);
+    let image = renderer.capture_frame().unwrap();
+    assert_eq!(image.get_pixel(6, 6).0, [255, 255, 255, 255]);
+
+    renderer.set_scale_factor(2.0);
+    renderer.parse_draw_commands(&commands);
+    let image = renderer.capture_frame().unwrap();
+    assert_eq!(image.get_pixel(6, 6).0, [0, 128, 0, 255]);
+    assert_eq!(image.get_pixel(8, 8).0, [255, 255, 255, 255]);
+}
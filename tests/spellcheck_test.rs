@@ -0,0 +1,79 @@
+use orinium_browser::engine::input::spellcheck::{SpellChecker, words};
+
+const AFF: &str = "SET UTF-8
+TRY esianrtolcdugmphbyfvkwzxjq
+REP 1
+REP f ph
+
+PFX U Y 1
+PFX U 0 un .
+
+SFX S Y 2
+SFX S y ies [^aeiou]y
+SFX S 0 s [^y]
+
+SFX D Y 1
+SFX D 0 ed .
+";
+
+const DIC: &str = "8
+hello
+world
+city/S
+lock/USD
+phone/S
+the
+cat
+Paris
+";
+
+fn checker() -> SpellChecker {
+    SpellChecker::parse("en_US", AFF, DIC)
+}
+
+#[test]
+fn words_in_the_dictionary_are_correct() {
+    let checker = checker();
+    assert!(checker.check("hello"));
+    assert!(checker.check("Hello"));
+    assert!(checker.check("HELLO"));
+    assert!(checker.check("Paris"));
+    assert!(!checker.check("paris"));
+    assert!(!checker.check("helo"));
+}
+
+#[test]
+fn affix_rules_derive_words() {
+    let checker = checker();
+    assert!(checker.check("cities"));
+    assert!(!checker.check("citys"));
+    assert!(checker.check("locks"));
+    assert!(checker.check("unlock"));
+    // 接頭辞と接尾辞の組み合わせ
+    assert!(checker.check("unlocked"));
+    // hello には S の規則が付いていない
+    assert!(!checker.check("hellos"));
+}
+
+#[test]
+fn misspelled_words_are_found_by_byte_range() {
+    let checker = checker();
+    let text = "Helo the wrold, 2cats a cat";
+    let ranges = checker.misspelled_words(text);
+    let misspelled: Vec<&str> = ranges.iter().map(|r| &text[r.clone()]).collect();
+    assert_eq!(misspelled, ["Helo", "wrold"]);
+    assert_eq!(words("it's fine."), [0..4, 5..9]);
+}
+
+#[test]
+fn suggestions_fix_common_typos() {
+    let checker = checker();
+    assert_eq!(
+        checker.suggest("helo").first().map(String::as_str),
+        Some("hello")
+    );
+    assert!(checker.suggest("wrold").contains(&"world".to_string()));
+    assert!(checker.suggest("fone").contains(&"phone".to_string()));
+    assert!(checker.suggest("thecat").contains(&"the cat".to_string()));
+    assert!(checker.suggest("HELO").contains(&"HELLO".to_string()));
+}
@@ -0,0 +1,42 @@
+use orinium_browser::browser::Tab;
+use orinium_browser::browser::core::internal_pages::crash_page;
+use orinium_browser::browser::core::tab::TabError;
+use url::Url;
+
+fn crash_message(tab: &Tab) -> Option<(&str, Option<&Url>)> {
+    match tab.load_error()? {
+        (TabError::Crashed(message), url) => Some((message.as_str(), url)),
+        _ => None,
+    }
+}
+
+#[test]
+fn crash_page_offers_to_reload() {
+    let url = "https://example.com/?a=1&b=2".parse().unwrap();
+    let page = crash_page(&url, "index out of bounds: <3>");
+
+    assert!(page.contains("<title>This tab crashed</title>"));
+    assert!(page.contains("index out of bounds: &lt;3&gt;"));
+    assert!(page.contains("href=\"https://example.com/?a=1&amp;b=2\">Reload</a>"));
+}
+
+#[test]
+fn panic_in_a_tab_shows_the_crash_page() {
+    let url: Url = "https://example.com/page".parse().unwrap();
+    let mut tab = Tab::new();
+    tab.navigate(url.clone());
+
+    assert_eq!(tab.isolate(|_| 42), 42);
+    assert!(!tab.is_error_page());
+
+    let tasks: Vec<u32> = tab.isolate(|_| panic!("layout exploded"));
+    assert!(tasks.is_empty());
+    assert!(tab.is_error_page());
+    assert_eq!(crash_message(&tab), Some(("layout exploded", Some(&url))));
+    // 履歴には積まないので、再読み込みで元のページを読み直す
+    assert_eq!(tab.history().current().map(|entry| &entry.url), Some(&url));
+
+    // クラッシュを伝えるページでもパニックしたら何も表示しない
+    tab.isolate::<()>(|_| panic!("again"));
+    assert_eq!(crash_message(&tab), Some(("again", None)));
+}
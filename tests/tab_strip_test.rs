@@ -0,0 +1,24 @@
+use orinium_browser::browser::core::ui::tab_strip::{TAB_STRIP_HEIGHT, TabStripHit, hit_test};
+
+#[test]
+fn clicks_map_to_tabs_close_buttons_and_new_tab() {
+    let y = TAB_STRIP_HEIGHT - 10.0;
+
+    // 幅 800 に 2 タブ → 1 タブ 220px（上限）、左端 10px から並ぶ
+    assert_eq!(hit_test(800.0, 2, 20.0, y), Some(TabStripHit::Tab(0)));
+    assert_eq!(hit_test(800.0, 2, 250.0, y), Some(TabStripHit::Tab(1)));
+    assert_eq!(hit_test(800.0, 2, 215.0, y), Some(TabStripHit::Close(0)));
+    assert_eq!(hit_test(800.0, 2, 460.0, y), Some(TabStripHit::NewTab));
+    assert_eq!(hit_test(800.0, 2, 700.0, y), None);
+}
+
+#[test]
+fn tabs_shrink_to_fit_the_window() {
+    let y = TAB_STRIP_HEIGHT - 10.0;
+
+    // 幅 400 に 6 タブ → (400 - 24 - 20) / 6 ≈ 59 → 最小幅 64px
+    assert_eq!(
+        hit_test(400.0, 6, 10.0 + 65.0 * 3.0 + 5.0, y),
+        Some(TabStripHit::Tab(3))
+    );
+}
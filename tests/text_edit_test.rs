@@ -0,0 +1,111 @@
+use orinium_browser::engine::input::text_edit::{self, TextEdit};
+
+#[test]
+fn typing_inserts_at_caret() {
+    let mut edit = TextEdit::new("helo");
+    assert_eq!(edit.caret(), 4);
+
+    edit.move_left(false);
+    edit.insert("l");
+    assert_eq!(edit.value(), "hello");
+    assert_eq!(edit.caret(), 4);
+
+    // 1 行の入力欄に改行は入らない
+    edit.move_end(false);
+    edit.insert(" wor\nld");
+    assert_eq!(edit.value(), "hello world");
+}
+
+#[test]
+fn backspace_and_delete_remove_whole_characters() {
+    let mut edit = TextEdit::new("aあb");
+    edit.move_left(false);
+    edit.backspace();
+    assert_eq!(edit.value(), "ab");
+    assert_eq!(edit.caret(), 1);
+
+    edit.delete();
+    assert_eq!(edit.value(), "a");
+    edit.delete();
+    assert_eq!(edit.value(), "a");
+}
+
+#[test]
+fn shift_arrows_select_and_typing_replaces_selection() {
+    let mut edit = TextEdit::new("hello world");
+    edit.move_home(false);
+    edit.move_word_right(true);
+    assert_eq!(edit.selection(), Some(0..5));
+    assert_eq!(edit.selected_text(), Some("hello"));
+
+    edit.insert("goodbye");
+    assert_eq!(edit.value(), "goodbye world");
+    assert_eq!(edit.selection(), None);
+
+    // 選択中に Shift なしで動かすと選択の端に寄る
+    edit.select_all();
+    edit.move_left(false);
+    assert_eq!(edit.caret(), 0);
+    assert_eq!(edit.selection(), None);
+}
+
+#[test]
+fn word_moves_skip_spaces() {
+    let mut edit = TextEdit::new("one  two three");
+    edit.move_word_left(false);
+    assert_eq!(edit.caret(), 9);
+    edit.move_word_left(false);
+    assert_eq!(edit.caret(), 5);
+    edit.move_word_right(false);
+    assert_eq!(edit.caret(), 8);
+}
+
+#[test]
+fn set_caret_snaps_to_character_boundary() {
+    let mut edit = TextEdit::new("あい");
+    edit.set_caret(4, false);
+    assert_eq!(edit.caret(), 3);
+    edit.set_caret(100, true);
+    assert_eq!(edit.selection(), Some(3..6));
+}
+
+#[test]
+fn password_offsets_map_between_value_and_mask() {
+    let value = "aあb";
+    assert_eq!(text_edit::masked_offset(value, 4), 2 * '•'.len_utf8());
+    assert_eq!(text_edit::unmasked_offset(value, 2 * '•'.len_utf8()), 4);
+    assert_eq!(text_edit::unmasked_offset(value, 100), value.len());
+}
+
+#[test]
+fn preedit_is_shown_at_caret_until_committed() {
+    let mut edit = TextEdit::new("ab");
+    edit.move_left(false);
+
+    edit.set_preedit("かな", Some(3));
+    assert_eq!(edit.value(), "ab");
+    let composition = edit.composition().expect("composition");
+    assert_eq!(composition.text, "aかなb");
+    assert_eq!(composition.preedit, 1..7);
+    assert_eq!(composition.caret, 4);
+
+    edit.commit("仮名");
+    assert_eq!(edit.value(), "a仮名b");
+    assert_eq!(edit.caret(), 7);
+    assert!(edit.composition().is_none());
+}
+
+#[test]
+fn preedit_replaces_selection_and_empty_text_cancels() {
+    let mut edit = TextEdit::new("hello");
+    edit.select_all();
+
+    edit.set_preedit("x", None);
+    let composition = edit.composition().expect("composition");
+    assert_eq!(composition.text, "x");
+    assert_eq!(composition.caret, 1);
+
+    edit.set_preedit("", None);
+    assert!(edit.composition().is_none());
+    assert_eq!(edit.value(), "hello");
+}
@@ -0,0 +1,82 @@
+use orinium_browser::engine::bridge::text::{
+    FallbackTextMeasurer, TextMeasureRequest, TextMeasurer,
+};
+use orinium_browser::engine::layouter::types::TextStyle;
+
+fn request(text: &str, max_width: Option<f32>) -> TextMeasureRequest<TextStyle> {
+    TextMeasureRequest {
+        text: text.to_string(),
+        style: TextStyle {
+            font_size: 10.0,
+            ..Default::default()
+        },
+        max_width,
+        wrap: max_width.is_some(),
+    }
+}
+
+fn line_texts<'a>(
+    text: &'a str,
+    lines: &[orinium_browser::engine::bridge::text::LineFragment],
+) -> Vec<&'a str> {
+    lines.iter().map(|l| &text[l.start..l.end]).collect()
+}
+
+#[test]
+fn fallback_measurer_wraps_at_spaces() {
+    // 1 文字 6px なので 60px には 10 文字まで入る
+    let text = "hello world again";
+    let res = FallbackTextMeasurer
+        .measure(&request(text, Some(60.0)))
+        .expect("measure");
+
+    assert_eq!(
+        line_texts(text, &res.lines),
+        vec!["hello ", "world ", "again"]
+    );
+    assert_eq!(res.line_count, 3);
+    assert!((res.height - 36.0).abs() < 0.01);
+}
+
+#[test]
+fn fallback_measurer_wraps_japanese_without_spaces() {
+    let text = "日本語の文章を折り返す";
+    let res = FallbackTextMeasurer
+        .measure(&request(text, Some(30.0)))
+        .expect("measure");
+
+    assert!(res.line_count > 1);
+    assert!(res.lines.iter().all(|l| l.width <= 30.0));
+    // 行を連結すると元の文字列に戻る
+    assert_eq!(line_texts(text, &res.lines).concat(), text);
+}
+
+#[test]
+fn fallback_measurer_keeps_single_line_without_wrap() {
+    let text = "hello world again";
+    let res = FallbackTextMeasurer
+        .measure(&request(text, None))
+        .expect("measure");
+
+    assert_eq!(res.line_count, 1);
+    assert_eq!(line_texts(text, &res.lines), vec![text]);
+}
+
+#[test]
+fn fallback_measurer_maps_points_and_offsets() {
+    let text = "hello world again";
+    let res = FallbackTextMeasurer
+        .measure(&request(text, Some(60.0)))
+        .expect("measure");
+
+    // 2 行目 "world " の 'r' (offset 8) の左半分
+    assert_eq!(res.offset_at(6.0 * 2.0 + 1.0, 12.0 + 1.0), 8);
+    // 行末より右は行の最後のクラスタの後ろ
+    assert_eq!(res.offset_at(500.0, 1.0), 6);
+
+    // offset -> 位置 -> offset で往復できる
+    for offset in [0, 3, 8, 14] {
+        let (x, y) = res.caret_position(offset);
+        assert_eq!(res.offset_at(x, y), offset, "offset {offset}");
+    }
+}
@@ -0,0 +1,66 @@
+use orinium_browser::engine::bridge::text::{
+    FallbackTextMeasurer, TextMeasureRequest, TextMeasurer,
+};
+use orinium_browser::engine::input::selection::{Selection, TextPosition, highlight_rects};
+use orinium_browser::engine::layouter::types::TextStyle;
+
+fn pos(path: &[usize], offset: usize) -> TextPosition {
+    TextPosition {
+        path: path.to_vec(),
+        offset,
+    }
+}
+
+#[test]
+fn selection_range_is_ordered_by_document_position() {
+    // 後ろから前に向かってドラッグしても同じ範囲になる
+    let forward = Selection {
+        anchor: pos(&[0, 0], 2),
+        focus: pos(&[1, 0], 3),
+    };
+    let backward = Selection {
+        anchor: pos(&[1, 0], 3),
+        focus: pos(&[0, 0], 2),
+    };
+
+    for sel in [&forward, &backward] {
+        assert_eq!(sel.range_in(&[0, 0], 10), Some(2..10));
+        assert_eq!(sel.range_in(&[0, 1], 4), Some(0..4));
+        assert_eq!(sel.range_in(&[1, 0], 10), Some(0..3));
+        assert_eq!(sel.range_in(&[1, 1], 10), None);
+    }
+}
+
+#[test]
+fn collapsed_selection_selects_nothing() {
+    let sel = Selection::collapsed(pos(&[0], 3));
+
+    assert!(sel.is_collapsed());
+    assert_eq!(sel.range_in(&[0], 10), None);
+}
+
+#[test]
+fn highlight_spans_wrapped_lines() {
+    // 1 文字 6px, 行の高さ 12px
+    let text = "hello world again";
+    let res = FallbackTextMeasurer
+        .measure(&TextMeasureRequest {
+            text: text.to_string(),
+            style: TextStyle {
+                font_size: 10.0,
+                ..Default::default()
+            },
+            max_width: Some(60.0),
+            wrap: true,
+        })
+        .expect("measure");
+
+    // "llo " + "wor"
+    let rects = highlight_rects(&res.lines, res.line_height(), &(2..9));
+
+    assert_eq!(rects.len(), 2);
+    let (x, y, w, h) = rects[0];
+    assert!((x - 12.0).abs() < 0.01 && y == 0.0 && (w - 24.0).abs() < 0.01 && h == 12.0);
+    let (x, y, w, _) = rects[1];
+    assert!(x == 0.0 && (y - 12.0).abs() < 0.01 && (w - 18.0).abs() < 0.01);
+}
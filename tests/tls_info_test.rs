@@ -0,0 +1,111 @@
+use orinium_browser::browser::core::security::Security;
+use orinium_browser::browser::core::ui::url_bar::UrlBar;
+use orinium_browser::platform::network::TlsInfo;
+use orinium_browser::platform::network::tls_info::CertificateInfo;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+use url::Url;
+
+/// `openssl req -x509` で作った自己署名証明書
+/// （CN=test.example、2025-01-01 から 2051-01-01 まで、DNS 名 2 つ）
+const CERTIFICATE: &[u8] = include_bytes!("fixtures/self_signed.der");
+
+fn tls_info() -> TlsInfo {
+    TlsInfo {
+        version: "TLS 1.3".to_string(),
+        cipher_suite: Some("TLS13_AES_128_GCM_SHA256".to_string()),
+        alpn: Some("h2".to_string()),
+        certificates: vec![CertificateInfo::parse(CERTIFICATE).unwrap()],
+    }
+}
+
+#[test]
+fn certificate_fields_are_read_from_der() {
+    let certificate = CertificateInfo::parse(CERTIFICATE).unwrap();
+    assert_eq!(certificate.subject, "CN=test.example, O=Orinium Test, C=JP");
+    assert_eq!(certificate.issuer, certificate.subject);
+    assert_eq!(certificate.common_name(), "test.example");
+    assert_eq!(certificate.issuer_name(), "Orinium Test");
+    assert_eq!(certificate.serial_number, "1234");
+    assert_eq!(
+        certificate.dns_names,
+        vec!["test.example".to_string(), "www.test.example".to_string()]
+    );
+    // UTCTime
+    assert_eq!(
+        certificate.not_before,
+        Some(UNIX_EPOCH + Duration::from_secs(1_735_689_600))
+    );
+    // 2050 年以降は GeneralizedTime
+    assert_eq!(
+        certificate.not_after,
+        Some(UNIX_EPOCH + Duration::from_secs(2_556_144_000))
+    );
+}
+
+#[test]
+fn broken_certificates_are_rejected() {
+    assert!(CertificateInfo::parse(&[]).is_none());
+    assert!(CertificateInfo::parse(&CERTIFICATE[..100]).is_none());
+    assert!(CertificateInfo::parse(b"not a certificate").is_none());
+}
+
+#[test]
+fn security_follows_the_scheme() {
+    let tls = Arc::new(tls_info());
+    let https = Url::parse("https://test.example/").unwrap();
+    assert_eq!(
+        Security::for_document(&https, Some(tls.clone())),
+        Security::Secure(Some(tls))
+    );
+    assert_eq!(
+        Security::for_document(&Url::parse("http://test.example/").unwrap(), None),
+        Security::NotSecure
+    );
+    assert_eq!(
+        Security::for_document(&Url::parse("file:///tmp/a.html").unwrap(), None),
+        Security::Local
+    );
+}
+
+#[test]
+fn details_describe_the_connection() {
+    let security = Security::Secure(Some(Arc::new(tls_info())));
+    let now = UNIX_EPOCH + Duration::from_secs(1_760_000_000);
+    let details = security.details(now);
+    assert_eq!(details[0], "Connection is secure");
+    assert_eq!(details[1], "TLS 1.3, TLS13_AES_128_GCM_SHA256 (h2)");
+    assert!(details.contains(&"Issued to: test.example".to_string()));
+    assert!(details.contains(&"Issued by: Orinium Test".to_string()));
+    assert!(details.iter().any(|l| l.starts_with("Valid from")));
+    assert!(!details.iter().any(|l| l.contains("expired")));
+
+    let later = UNIX_EPOCH + Duration::from_secs(2_600_000_000);
+    assert!(
+        security
+            .details(later)
+            .iter()
+            .any(|l| l.ends_with("(expired)"))
+    );
+}
+
+#[test]
+fn badge_toggles_the_details_popup() {
+    let mut url_bar = UrlBar::new();
+    // 印がなければ押せない
+    assert!(!url_bar.security_badge_hit_test(800.0, 12.0, 20.0));
+
+    url_bar.set_security(Security::NotSecure);
+    assert!(url_bar.security_badge_hit_test(800.0, 12.0, 20.0));
+    url_bar.focus();
+    url_bar.toggle_security_popup();
+    assert!(url_bar.is_security_popup_open());
+    assert!(!url_bar.is_focused());
+    url_bar.toggle_security_popup();
+    assert!(!url_bar.is_security_popup_open());
+
+    // 別のページに移ったら閉じる
+    url_bar.toggle_security_popup();
+    url_bar.set_security(Security::Secure(None));
+    assert!(!url_bar.is_security_popup_open());
+}
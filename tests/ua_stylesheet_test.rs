@@ -0,0 +1,50 @@
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter::css_resolver::StyleOrigin;
+use orinium_browser::engine::layouter::dump::dump_layout;
+use orinium_browser::engine::layouter::{self, types::TextStyle, ua};
+
+#[test]
+fn user_agent_stylesheet_parses() {
+    assert!(ua::user_agent_sheet().is_some());
+
+    let styles = ua::user_agent_styles(&MediaContext::default());
+    assert!(!styles.is_empty());
+    assert!(styles.iter().all(|d| d.origin == StyleOrigin::UserAgent));
+}
+
+#[test]
+fn default_styles_come_from_the_stylesheet() {
+    let html = "<html><body><h1>Title</h1><p>Plain <strong>loud</strong> \
+                <code>mono</code></p></body></html>";
+    let dom = HtmlParser::new(html).parse();
+    let (mut layout, info) = layouter::build_layout_and_info(
+        &dom.root,
+        &ua::user_agent_styles(&MediaContext::default()),
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+
+    let dump = dump_layout(&layout, &info);
+    let line = |text: &str| {
+        dump.lines()
+            .find(|line| line.contains(&format!("text {text:?}")))
+            .unwrap_or_else(|| panic!("{dump}"))
+            .to_string()
+    };
+    assert!(line("Title").contains(" 32px "), "{dump}");
+    assert!(line("Title").contains(" bold"), "{dump}");
+    assert!(line("loud").contains(" bold"), "{dump}");
+    assert!(line("mono").contains("monospace"), "{dump}");
+    assert!(!line("Plain ").contains(" bold"), "{dump}");
+}
@@ -0,0 +1,126 @@
+use orinium_browser::browser::core::idn::{decode_punycode, display_host};
+use orinium_browser::browser::core::ui::url_bar::{
+    SearchEngine, UrlBar, fixup_input, resolve_input,
+};
+
+fn resolve(input: &str) -> String {
+    resolve_input(
+        input,
+        &SearchEngine::new("https://search.example/?q={query}"),
+    )
+    .expect("resolve")
+    .to_string()
+}
+
+#[test]
+fn urls_with_scheme_are_used_as_is() {
+    assert_eq!(resolve("http://example.com/a"), "http://example.com/a");
+    assert_eq!(resolve("  https://example.com  "), "https://example.com/");
+}
+
+#[test]
+fn host_names_get_https() {
+    assert_eq!(resolve("example.com"), "https://example.com/");
+    assert_eq!(
+        resolve("example.com/path?x=1"),
+        "https://example.com/path?x=1"
+    );
+    assert_eq!(resolve("localhost:8080"), "https://localhost:8080/");
+}
+
+#[test]
+fn other_text_is_searched() {
+    assert_eq!(
+        resolve("rust layout"),
+        "https://search.example/?q=rust+layout"
+    );
+    assert_eq!(resolve("orinium"), "https://search.example/?q=orinium");
+    assert!(resolve_input("   ", &SearchEngine::default()).is_none());
+}
+
+#[test]
+fn typing_replaces_selected_url() {
+    let mut bar = UrlBar::new();
+    bar.show_url(Some(&"https://example.com/".parse().unwrap()));
+    bar.focus();
+
+    bar.insert_str("ab");
+    bar.move_left();
+    bar.insert_str("é");
+    bar.backspace();
+    bar.delete();
+
+    assert_eq!(bar.text(), "a");
+}
+
+#[test]
+fn ime_commit_replaces_selected_url() {
+    let mut bar = UrlBar::new();
+    bar.show_url(Some(&"https://example.com/".parse().unwrap()));
+    bar.focus();
+
+    // 変換中はまだ値に入らない
+    bar.set_preedit("にほんご", Some(0));
+    assert_eq!(bar.text(), "https://example.com/");
+    assert!(bar.preedit().is_some());
+
+    bar.commit_preedit("日本語");
+    assert_eq!(bar.text(), "日本語");
+    assert!(bar.preedit().is_none());
+}
+
+#[test]
+fn sloppy_input_is_fixed_up() {
+    // 貼り付けで混じった改行
+    assert_eq!(
+        resolve("https://example.com/a\nb"),
+        "https://example.com/ab"
+    );
+    assert_eq!(resolve("example.com/a b"), "https://example.com/a%20b");
+    assert_eq!(resolve("example.com/100%"), "https://example.com/100%25");
+    assert_eq!(resolve("example.com/%41"), "https://example.com/%41");
+    assert_eq!(resolve("192.168.0.1:8080"), "https://192.168.0.1:8080/");
+    // 数字だけなら検索する
+    assert_eq!(resolve("3.14"), "https://search.example/?q=3.14");
+    assert!(fixup_input("is example.com down").is_none());
+}
+
+#[test]
+fn idn_hosts_are_sent_as_punycode() {
+    assert_eq!(resolve("例え.jp"), "https://xn--r8jz45g.jp/");
+    assert_eq!(
+        resolve("例え。jp/パス"),
+        "https://xn--r8jz45g.jp/%E3%83%91%E3%82%B9"
+    );
+}
+
+#[test]
+fn idn_hosts_are_shown_in_unicode_unless_confusable() {
+    let show = |url: &str| {
+        let mut bar = UrlBar::new();
+        bar.show_url(Some(&url.parse().unwrap()));
+        bar.text().to_string()
+    };
+    assert_eq!(
+        show("https://xn--r8jz45g.jp/%E3%83%91%E3%82%B9?q=%20"),
+        "https://例え.jp/パス?q=%20"
+    );
+    assert_eq!(show("https://xn--e1afmkfd.xn--p1ai/"), "https://пример.рф/");
+    // ラテン文字とキリル文字が混ざっている
+    assert_eq!(
+        show("https://xn--pple-43d.com/"),
+        "https://xn--pple-43d.com/"
+    );
+    // キリル文字だけで apple に見える
+    assert_eq!(
+        show("https://xn--80ak6aa92e.com/"),
+        "https://xn--80ak6aa92e.com/"
+    );
+
+    assert_eq!(decode_punycode("bcher-kva").as_deref(), Some("bücher"));
+    assert_eq!(
+        display_host("www.xn--bcher-kva.example"),
+        "www.bücher.example"
+    );
+    assert!(decode_punycode("9999999999").is_none());
+}
@@ -0,0 +1,79 @@
+use orinium_browser::browser::core::webview::viewport::{self, ViewportMeta, ViewportWidth};
+use orinium_browser::engine::html::parser::Parser;
+
+#[test]
+fn content_is_a_list_of_properties() {
+    assert_eq!(
+        viewport::parse("width=device-width, initial-scale=1"),
+        ViewportMeta {
+            width: Some(ViewportWidth::DeviceWidth),
+            initial_scale: Some(1.0),
+            ..Default::default()
+        }
+    );
+    assert_eq!(
+        viewport::parse(" WIDTH = 480px ; minimum-scale=0.5;maximum-scale=no; user-scalable=no"),
+        ViewportMeta {
+            width: Some(ViewportWidth::Px(480.0)),
+            minimum_scale: Some(0.5),
+            maximum_scale: Some(0.1),
+            ..Default::default()
+        }
+    );
+    // 読めない値と知らない名前は飛ばす
+    assert_eq!(
+        viewport::parse("width=wide, initial-scale=-2, shrink-to-fit=no, height"),
+        ViewportMeta::default()
+    );
+    assert_eq!(
+        viewport::parse("initial-scale=50").initial_scale,
+        Some(10.0)
+    );
+}
+
+#[test]
+fn fixed_width_pages_are_scaled_to_the_window() {
+    let fixed = viewport::parse("width=400");
+    assert_eq!(fixed.initial_scale(800.0), 2.0);
+    assert_eq!(fixed.initial_scale(200.0), 0.5);
+
+    let device = viewport::parse("width=device-width");
+    assert_eq!(device.initial_scale(800.0), 1.0);
+
+    // initial-scale が width より優先され、minimum-scale と maximum-scale に収める
+    assert_eq!(
+        viewport::parse("width=400, initial-scale=1.5").initial_scale(800.0),
+        1.5
+    );
+    assert_eq!(
+        viewport::parse("width=400, maximum-scale=1.25").initial_scale(800.0),
+        1.25
+    );
+    assert_eq!(
+        viewport::parse("initial-scale=0.5, minimum-scale=0.75").initial_scale(800.0),
+        0.75
+    );
+}
+
+#[test]
+fn the_last_viewport_meta_element_wins() {
+    let dom = Parser::new(
+        r#"<html><head>
+<meta name="description" content="width=100">
+<meta name="viewport" content="width=320">
+<meta name="Viewport" content="width=device-width, initial-scale=2">
+</head><body></body></html>"#,
+    )
+    .parse();
+    assert_eq!(
+        viewport::find(&dom),
+        Some(ViewportMeta {
+            width: Some(ViewportWidth::DeviceWidth),
+            initial_scale: Some(2.0),
+            ..Default::default()
+        })
+    );
+
+    let dom = Parser::new("<html><head></head><body></body></html>").parse();
+    assert_eq!(viewport::find(&dom), None);
+}
@@ -0,0 +1,112 @@
+use orinium_browser::browser::core::visited_links::VisitedLinks;
+use orinium_browser::engine::bridge::text::FallbackTextMeasurer;
+use orinium_browser::engine::css::media::MediaContext;
+use orinium_browser::engine::css::parser::Parser as CssParser;
+use orinium_browser::engine::html::parser::Parser as HtmlParser;
+use orinium_browser::engine::layouter;
+use orinium_browser::engine::layouter::css_resolver::CssResolver;
+use orinium_browser::engine::layouter::dump::dump_layout;
+use orinium_browser::engine::layouter::types::{Color, InfoNode, NodeKind, TextStyle};
+use ui_layout::LayoutNode;
+use url::Url;
+
+const CSS: &str = "
+a:link { color: #0000ff }
+a:visited { color: #ff0000; font-size: 40px }
+";
+
+/// css で body をレイアウトする（visited にある href のリンクは訪れたもの）
+fn layout(body: &str, css: &str, visited: &[&str]) -> (LayoutNode, InfoNode) {
+    let sheet = CssParser::new(css).parse().unwrap();
+    let styles = CssResolver::resolve_with_media(&sheet, &MediaContext::default());
+    let dom = HtmlParser::new(&format!("<html><body>{body}</body></html>")).parse();
+    let (mut layout, info) = layouter::build_layout_and_info_with_visited(
+        &dom.root,
+        &styles,
+        &FallbackTextMeasurer,
+        TextStyle {
+            font_size: 16.0,
+            ..Default::default()
+        },
+        Vec::new(),
+        None,
+        None,
+        None,
+        None,
+        &|href| visited.contains(&href),
+        &|_| None,
+    );
+    ui_layout::LayoutEngine::layout(&mut layout, 800.0, 600.0);
+    (layout, info)
+}
+
+/// テキストとその TextStyle を文書順に集める
+fn text_styles(body: &str, visited: &[&str]) -> Vec<(String, TextStyle)> {
+    let (_, info) = layout(body, CSS, visited);
+
+    fn collect(info: &InfoNode, out: &mut Vec<(String, TextStyle)>) {
+        if let NodeKind::Text { text, style, .. } = &info.kind {
+            out.push((text.clone(), *style));
+        }
+        for child in &info.children {
+            collect(child, out);
+        }
+    }
+    let mut out = Vec::new();
+    collect(&info, &mut out);
+    out
+}
+
+fn style_of(styles: &[(String, TextStyle)], text: &str) -> TextStyle {
+    styles
+        .iter()
+        .find(|(t, _)| t == text)
+        .map(|(_, style)| *style)
+        .unwrap_or_else(|| panic!("no text {text:?}"))
+}
+
+#[test]
+fn visited_links_only_change_colors() {
+    let styles = text_styles(
+        r#"<a href="/seen">seen</a><a href="/new">new</a><a>anchor</a>"#,
+        &["/seen"],
+    );
+
+    let seen = style_of(&styles, "seen");
+    assert_eq!(seen.color, Color(255, 0, 0, 255));
+    // 色以外は :visited で変えられない
+    assert_eq!(seen.font_size, 16.0);
+
+    assert_eq!(style_of(&styles, "new").color, Color(0, 0, 255, 255));
+    // href のない a はリンクではない
+    assert_ne!(style_of(&styles, "anchor").color, Color(0, 0, 255, 255));
+}
+
+#[test]
+fn link_rules_lay_out_visited_links_like_unvisited_ones() {
+    // :link で大きさや表示を変えても、訪れたリンクかどうかは読み取れない
+    let css = "a:link { display: block; width: 120px; font-size: 30px }
+               a:visited { display: none }";
+    let body = r#"<a href="/page">link</a>"#;
+    let (unvisited_layout, unvisited_info) = layout(body, css, &[]);
+    let (visited_layout, visited_info) = layout(body, css, &["/page"]);
+
+    let unvisited = dump_layout(&unvisited_layout, &unvisited_info);
+    assert!(unvisited.contains("30px"), "{unvisited}");
+    assert_eq!(unvisited, dump_layout(&visited_layout, &visited_info));
+}
+
+#[test]
+fn visited_links_ignore_fragments() {
+    let mut links = VisitedLinks::new();
+    assert!(links.is_empty());
+
+    links.add(&Url::parse("https://example.com/page#top").unwrap());
+    assert!(links.contains(&Url::parse("https://example.com/page").unwrap()));
+    assert!(links.contains(&Url::parse("https://example.com/page#end").unwrap()));
+    assert!(!links.contains(&Url::parse("https://example.com/other").unwrap()));
+    assert_eq!(links.len(), 1);
+
+    links.clear();
+    assert!(!links.contains(&Url::parse("https://example.com/page").unwrap()));
+}
@@ -0,0 +1,77 @@
+use orinium_browser::engine::script::storage::{self, QUOTA, QuotaExceeded, WebStorage};
+use url::Url;
+
+const ORIGIN: &str = "https://example.com";
+
+#[test]
+fn items_are_kept_per_origin() {
+    let mut storage = WebStorage::new();
+    storage.set_item(ORIGIN, "theme", "dark").unwrap();
+    storage.set_item(ORIGIN, "lang", "ja").unwrap();
+    storage
+        .set_item("https://other.example", "theme", "light")
+        .unwrap();
+
+    assert_eq!(storage.get_item(ORIGIN, "theme"), Some("dark"));
+    assert_eq!(storage.len(ORIGIN), 2);
+    assert_eq!(storage.key(ORIGIN, 0), Some("lang"));
+    assert_eq!(storage.key(ORIGIN, 2), None);
+
+    storage.remove_item(ORIGIN, "theme");
+    assert_eq!(storage.get_item(ORIGIN, "theme"), None);
+    storage.clear(ORIGIN);
+    assert!(storage.is_empty(ORIGIN));
+    assert_eq!(
+        storage.get_item("https://other.example", "theme"),
+        Some("light")
+    );
+}
+
+#[test]
+fn writes_over_the_quota_are_rejected() {
+    let mut storage = WebStorage::new();
+    let big = "a".repeat(QUOTA - 3);
+    storage.set_item(ORIGIN, "big", &big).unwrap();
+    assert_eq!(storage.usage(ORIGIN), QUOTA);
+
+    assert_eq!(storage.set_item(ORIGIN, "x", "y"), Err(QuotaExceeded));
+    assert_eq!(storage.get_item(ORIGIN, "x"), None);
+    // 置き換えるなら古い値の分は数えない
+    storage.set_item(ORIGIN, "big", "small").unwrap();
+    // 別のオリジンには影響しない
+    storage.set_item("https://other.example", "x", "y").unwrap();
+}
+
+#[test]
+fn serialized_storage_round_trips_with_escapes() {
+    let mut storage = WebStorage::new();
+    storage
+        .set_item(ORIGIN, "tab\tkey", "line1\nline2\\end\r")
+        .unwrap();
+    storage.set_item("http://localhost:8080", "k", "").unwrap();
+    assert!(storage.take_modified());
+    assert!(!storage.take_modified());
+
+    let parsed = WebStorage::parse(&storage.serialize());
+    assert_eq!(parsed, storage);
+    assert_eq!(
+        parsed.get_item(ORIGIN, "tab\tkey"),
+        Some("line1\nline2\\end\r")
+    );
+}
+
+#[test]
+fn invalid_lines_are_skipped_when_parsing() {
+    let parsed = WebStorage::parse("garbage\nitem\thttps://example.com\tk\tv\n");
+    assert_eq!(parsed.get_item(ORIGIN, "k"), Some("v"));
+    assert_eq!(parsed.len(ORIGIN), 1);
+}
+
+#[test]
+fn opaque_origins_have_no_storage() {
+    let url: Url = "https://example.com:443/page?q=1".parse().unwrap();
+    assert_eq!(storage::storage_origin(&url).as_deref(), Some(ORIGIN));
+
+    let data: Url = "data:text/html,<p>hi</p>".parse().unwrap();
+    assert_eq!(storage::storage_origin(&data), None);
+}
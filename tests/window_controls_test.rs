@@ -0,0 +1,59 @@
+use orinium_browser::browser::Settings;
+use orinium_browser::browser::core::ui::window_controls::{
+    RESIZE_BORDER, WINDOW_CONTROLS_WIDTH, hit_test, resize_edge,
+};
+use orinium_browser::browser::core::ui::{TAB_STRIP_HEIGHT, WindowControl};
+use winit::window::ResizeDirection;
+
+#[test]
+fn buttons_sit_at_the_right_end_of_the_tab_strip() {
+    let y = TAB_STRIP_HEIGHT / 2.0;
+    let left = 800.0 - WINDOW_CONTROLS_WIDTH;
+
+    assert_eq!(
+        hit_test(800.0, left + 1.0, y),
+        Some(WindowControl::Minimize)
+    );
+    assert_eq!(
+        hit_test(800.0, left + WINDOW_CONTROLS_WIDTH / 2.0, y),
+        Some(WindowControl::Maximize)
+    );
+    assert_eq!(hit_test(800.0, 799.0, y), Some(WindowControl::Close));
+    assert_eq!(hit_test(800.0, left - 1.0, y), None);
+    // タブバーの下はページ
+    assert_eq!(hit_test(800.0, 799.0, TAB_STRIP_HEIGHT + 1.0), None);
+}
+
+#[test]
+fn window_edges_map_to_resize_directions() {
+    let size = (800.0, 600.0);
+    let inside = RESIZE_BORDER + 1.0;
+
+    assert_eq!(
+        resize_edge(size, 1.0, 1.0),
+        Some(ResizeDirection::NorthWest)
+    );
+    assert_eq!(
+        resize_edge(size, 799.0, 599.0),
+        Some(ResizeDirection::SouthEast)
+    );
+    assert_eq!(resize_edge(size, 400.0, 1.0), Some(ResizeDirection::North));
+    assert_eq!(resize_edge(size, 1.0, 300.0), Some(ResizeDirection::West));
+    assert_eq!(resize_edge(size, 799.0, 300.0), Some(ResizeDirection::East));
+    assert_eq!(
+        resize_edge(size, 400.0, 599.0),
+        Some(ResizeDirection::South)
+    );
+    assert_eq!(resize_edge(size, inside, inside), None);
+}
+
+#[test]
+fn custom_titlebar_is_a_setting() {
+    let mut settings = Settings::default();
+    assert!(!settings.custom_titlebar);
+
+    settings.set("window.custom_titlebar", "on").unwrap();
+    assert!(settings.custom_titlebar);
+    let parsed = Settings::parse(&settings.serialize());
+    assert!(parsed.custom_titlebar);
+}
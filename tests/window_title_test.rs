@@ -0,0 +1,19 @@
+use orinium_browser::browser::{BrowserApp, Tab};
+
+#[test]
+fn window_title_follows_the_active_tab() {
+    let mut browser = BrowserApp::new((800, 600), "Orinium Browser".to_string());
+    assert_eq!(browser.window_title(), "Orinium Browser");
+
+    browser.add_tab(Tab::new());
+    assert_eq!(browser.window_title(), "New Tab - Orinium Browser");
+
+    let mut tab = Tab::new();
+    tab.navigate("https://example.com/".parse().unwrap());
+    browser.add_tab(tab);
+    browser.switch_tab(1);
+    assert_eq!(
+        browser.window_title(),
+        "https://example.com/ (loading…) - Orinium Browser"
+    );
+}